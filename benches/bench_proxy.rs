@@ -106,6 +106,20 @@ impl BenchFixture {
                 address: backend_address.clone(),
                 http_version: None,
                 health_check: None,
+                circuit_breaker: None,
+                tls: None,
+                pool: None,
+                max_in_flight: None,
+                queue: None,
+                discovery: None,
+                drain: false,
+                drain_timeout_secs: None,
+                slow_start_secs: None,
+                echo: false,
+                tcp: None,
+                proxy: None,
+                bandwidth: None,
+                tenant: None,
             }],
             domains: vec![Domain {
                 host: None,
@@ -114,63 +128,85 @@ impl BenchFixture {
                 headers: None,
                 security: None,
                 fingerprinting: None,
+                ja4_variants: None,
+                fingerprint_format: None,
+                redirect: None,
                 routes: vec![
                     Route {
                         prefix: "/bench/fp".to_string(),
                         backend: backend_address.clone(),
                         fingerprinting: Some(true),
-                        force_new_connection: false,
                         replace_path: Some("/".to_string()),
-                        security: None,
-                        headers: None,
+                        ..Default::default()
                     },
                     Route {
                         prefix: "/bench/nofp".to_string(),
                         backend: backend_address.clone(),
                         fingerprinting: Some(false),
-                        force_new_connection: false,
                         replace_path: Some("/".to_string()),
-                        security: None,
-                        headers: None,
+                        ..Default::default()
                     },
                     Route {
                         prefix: "/".to_string(),
                         backend: backend_address,
                         fingerprinting: Some(true),
-                        force_new_connection: false,
-                        replace_path: None,
-                        security: None,
-                        headers: None,
+                        ..Default::default()
                     },
                 ],
+                tenant: None,
             }],
             tls: Some(TlsConfig {
                 alpn: vec!["h2".to_string(), "http/1.1".to_string()],
                 options: Default::default(),
                 client_auth: Default::default(),
+                client_cert: Default::default(),
                 session_resumption: Default::default(),
+                ocsp: Default::default(),
             }),
             fingerprint: FingerprintConfig {
                 tls_enabled: true,
                 http_enabled: true,
                 tcp_enabled: false,
+                tcp_capture: Default::default(),
                 max_capture: 64 * 1024,
+                tls_info_headers: false,
+                headers: Default::default(),
+                whoami: Default::default(),
+                signing: Default::default(),
             },
             logging: LoggingConfig { level: "warn".to_string(), show_target: false },
             timeout: TimeoutConfig {
                 upstream_connect_ms: Some(5000),
                 proxy_idle_ms: 600_000, // 10 min - bench groups share a connection pool
                 shutdown_secs: 5,
+                drain_delay_secs: 0,
                 tls_handshake_secs: 10,
                 connection_handling_secs: 600, // 10 min - each group runs ~15s warmup + 15s measure
                 keep_alive: KeepAliveConfig::default(),
             },
             security: SecurityConfig::default(),
-            telemetry: TelemetryConfig { metrics_port: None, otel_log_level: "warn".to_string() },
+            telemetry: TelemetryConfig {
+                metrics_port: None,
+                otel_log_level: "warn".to_string(),
+                ..Default::default()
+            },
             reload: huginn_proxy_lib::config::ReloadConfig::default(),
             headers: None,
             preserve_host: false,
+            default_host: None,
             backend_pool: Default::default(),
+            compression: None,
+            redirect: None,
+            cache: Default::default(),
+            access_log: Default::default(),
+            load_shedding: Default::default(),
+            http2: Default::default(),
+            request_id: Default::default(),
+            event_stream: Default::default(),
+            client_tracking: Default::default(),
+            handshake_capture: Default::default(),
+            tenants: vec![],
+            include: vec![],
         };
 
         // 5. Start proxy in a background task
@@ -185,9 +221,13 @@ impl BenchFixture {
                 dynamic_cfg,
                 huginn_proxy_lib::Metrics::new_noop(),
                 None,
+                None,
+                None,
                 huginn_proxy_lib::WatchOptions::default(),
                 shutdown_tx,
                 huginn_proxy_lib::Readiness::new(),
+                Arc::new(huginn_proxy_lib::HealthRegistry::new()),
+                huginn_proxy_lib::RuntimeHandles::default(),
             )
             .await;
         });
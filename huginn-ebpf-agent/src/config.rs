@@ -4,6 +4,8 @@ use std::net::{Ipv4Addr, Ipv6Addr};
 pub const DEFAULT_PIN_PATH: &str = pin::DEFAULT_PIN_BASE;
 pub use huginn_ebpf::{CaptureBackend, EbpfLogLevel, XdpAttachMode};
 
+pub const DEFAULT_GC_INTERVAL_SECS: u64 = 30;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub interface: String,
@@ -12,6 +14,10 @@ pub struct Config {
     pub dst_port: u16,
     pub pin_path: String,
     pub syn_map_max_entries: u32,
+    /// Seconds between stale-entry sweeps of the SYN maps; 0 disables sweeping.
+    pub gc_interval_secs: u64,
+    /// Entries captured more than this many SYNs ago are swept.
+    pub gc_max_tick_delta: u64,
     pub capture: CaptureBackend,
     pub metrics_listen_addr: String,
     pub metrics_port: u16,
@@ -73,6 +79,14 @@ pub fn from_env(get_var: impl Fn(&str) -> Option<String>) -> Result<Config, Conf
         .transpose()
         .map(|opt| opt.unwrap_or(huginn_ebpf::DEFAULT_SYN_MAP_MAX_ENTRIES))?;
 
+    let gc_interval_secs =
+        parse_u64(&get_var, "HUGINN_EBPF_GC_INTERVAL_SECS")?.unwrap_or(DEFAULT_GC_INTERVAL_SECS);
+
+    // Default to the lookup staleness threshold, so the sweep removes exactly what the proxy
+    // would refuse anyway.
+    let gc_max_tick_delta = parse_u64(&get_var, "HUGINN_EBPF_GC_MAX_TICK_DELTA")?
+        .unwrap_or_else(|| u64::from(syn_map_max_entries).saturating_mul(2));
+
     let metrics_listen_addr = get_var("HUGINN_EBPF_METRICS_ADDR")
         .ok_or(ConfigError::Missing { name: "HUGINN_EBPF_METRICS_ADDR".to_string() })?;
 
@@ -95,6 +109,8 @@ pub fn from_env(get_var: impl Fn(&str) -> Option<String>) -> Result<Config, Conf
         dst_port,
         pin_path,
         syn_map_max_entries,
+        gc_interval_secs,
+        gc_max_tick_delta,
         capture,
         metrics_listen_addr,
         metrics_port,
@@ -102,6 +118,21 @@ pub fn from_env(get_var: impl Fn(&str) -> Option<String>) -> Result<Config, Conf
     })
 }
 
+fn parse_u64(
+    get_var: &impl Fn(&str) -> Option<String>,
+    name: &str,
+) -> Result<Option<u64>, ConfigError> {
+    get_var(name)
+        .map(|s| {
+            s.trim().parse().map_err(|_| ConfigError::Invalid {
+                name: name.to_string(),
+                value: s.clone(),
+                reason: "must be a non-negative integer".to_string(),
+            })
+        })
+        .transpose()
+}
+
 fn resolve_log_level(
     get_var: &impl Fn(&str) -> Option<String>,
 ) -> Result<EbpfLogLevel, ConfigError> {
@@ -3,11 +3,14 @@
 use huginn_ebpf::{EbpfLogLevel, EbpfLogPoller, EbpfProbe};
 use huginn_ebpf_agent::config::from_env;
 use huginn_ebpf_agent::error::Result;
+use huginn_ebpf_agent::telemetry::{labels, Metrics};
 use std::env;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::unix::AsyncFd;
 use tokio::io::Interest;
 use tokio::signal;
+use tokio::time::MissedTickBehavior;
 
 /// Drain the eBPF log ring buffer when its fd is readable.
 fn spawn_ebpf_log_drain(poller: EbpfLogPoller) {
@@ -33,6 +36,30 @@ fn spawn_ebpf_log_drain(poller: EbpfLogPoller) {
     });
 }
 
+/// Periodically remove SYN map entries older than `max_tick_delta` and report map occupancy.
+/// Never returns; with `interval_secs == 0` it only waits.
+async fn sweep_syn_maps(
+    probe: &mut EbpfProbe,
+    metrics: &Metrics,
+    interval_secs: u64,
+    max_tick_delta: u64,
+) {
+    if interval_secs == 0 {
+        return std::future::pending().await;
+    }
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        if let Some(stats) = probe.sweep_stale(max_tick_delta) {
+            metrics.record_syn_map_sweep(labels::FAMILY_V4, stats);
+        }
+        if let Some(stats) = probe.sweep_stale_v6(max_tick_delta) {
+            metrics.record_syn_map_sweep(labels::FAMILY_V6, stats);
+        }
+    }
+}
+
 async fn wait_for_shutdown_signal() -> Result<()> {
     let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate())
         .map_err(|e| std::io::Error::other(format!("Failed to setup SIGTERM handler: {e}")))?;
@@ -115,10 +142,16 @@ async fn main() -> Result<()> {
         dst_port = %cfg.dst_port,
         capture = capture_str,
         log_level = cfg.log_level.as_str(),
+        gc_interval_secs = cfg.gc_interval_secs,
+        gc_max_tick_delta = cfg.gc_max_tick_delta,
         "eBPF agent ready, waiting for SIGTERM"
     );
 
-    wait_for_shutdown_signal().await?;
+    // The sweep borrows the probe until the shutdown signal arrives.
+    tokio::select! {
+        res = wait_for_shutdown_signal() => res?,
+        () = sweep_syn_maps(&mut probe, &metrics, cfg.gc_interval_secs, cfg.gc_max_tick_delta) => {}
+    }
 
     // Pins are intentionally left in place: the next agent instance reuses the
     // same maps, so a proxy holding them never sees a reconnection gap. Dropping
@@ -1,10 +1,10 @@
 use huginn_ebpf::{
     syn_captured_count_from_path, syn_captured_v6_count_from_path,
     syn_insert_failures_count_from_path, syn_insert_failures_v6_count_from_path,
-    syn_malformed_count_from_path, syn_malformed_v6_count_from_path,
+    syn_malformed_count_from_path, syn_malformed_v6_count_from_path, SweepStats,
 };
 use opentelemetry::global;
-use opentelemetry::metrics::{Gauge, Meter};
+use opentelemetry::metrics::{Counter, Gauge, Meter};
 use opentelemetry::KeyValue;
 use opentelemetry_sdk::metrics::SdkMeterProvider;
 use prometheus::Registry;
@@ -23,6 +23,10 @@ pub mod labels {
 pub struct Metrics {
     pub agent_up: Gauge<u64>,
     pub build_info: Gauge<u64>,
+    /// Labels: family (ipv4, ipv6)
+    pub tcp_syn_map_entries: Gauge<u64>,
+    /// Labels: family (ipv4, ipv6)
+    pub tcp_syn_map_evicted_total: Counter<u64>,
 }
 
 impl Metrics {
@@ -36,6 +40,14 @@ impl Metrics {
                 .u64_gauge("huginn_ebpf_agent_build_info")
                 .with_description("Build information (version, rust version)")
                 .build(),
+            tcp_syn_map_entries: meter
+                .u64_gauge("tcp_syn_map_entries")
+                .with_description("TCP SYN map entries left after the last stale-entry sweep")
+                .build(),
+            tcp_syn_map_evicted_total: meter
+                .u64_counter("tcp_syn_map_evicted_total")
+                .with_description("Number of stale TCP SYN map entries removed by the sweep")
+                .build(),
         }
     }
 
@@ -47,6 +59,13 @@ impl Metrics {
         self.agent_up.record(0, &[]);
     }
 
+    /// Record one sweep of the `family` SYN map (`labels::FAMILY_V4` or `labels::FAMILY_V6`).
+    pub fn record_syn_map_sweep(&self, family: &'static str, stats: SweepStats) {
+        let attrs = [KeyValue::new(labels::FAMILY, family)];
+        self.tcp_syn_map_entries.record(stats.entries, &attrs);
+        self.tcp_syn_map_evicted_total.add(stats.evicted, &attrs);
+    }
+
     pub fn set_build_info(&self) {
         let version = env!("CARGO_PKG_VERSION");
         let rust_version = env!("CARGO_PKG_RUST_VERSION");
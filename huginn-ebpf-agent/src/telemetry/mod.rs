@@ -5,5 +5,5 @@ pub mod metrics_handler;
 pub mod router;
 pub mod server;
 pub mod status;
-pub use metrics::init_metrics;
+pub use metrics::{init_metrics, labels, Metrics};
 pub use server::start_observability_server;
@@ -3,7 +3,7 @@ use std::net::{Ipv4Addr, Ipv6Addr};
 
 use huginn_ebpf_agent::config::{
     from_env, resolve_capture_backend, CaptureBackend, ConfigError, EbpfLogLevel, XdpAttachMode,
-    DEFAULT_PIN_PATH,
+    DEFAULT_GC_INTERVAL_SECS, DEFAULT_PIN_PATH,
 };
 
 /// Build a `get_var` closure from a list of (name, value) pairs.
@@ -107,6 +107,25 @@ fn from_env_minimal_applies_defaults() {
     assert_eq!(cfg.syn_map_max_entries, huginn_ebpf::DEFAULT_SYN_MAP_MAX_ENTRIES);
     assert!(matches!(cfg.capture, CaptureBackend::Xdp(XdpAttachMode::Native)));
     assert_eq!(cfg.log_level, EbpfLogLevel::Off, "log level must default to off");
+    assert_eq!(cfg.gc_interval_secs, DEFAULT_GC_INTERVAL_SECS);
+    assert_eq!(
+        cfg.gc_max_tick_delta,
+        u64::from(huginn_ebpf::DEFAULT_SYN_MAP_MAX_ENTRIES).saturating_mul(2),
+        "sweep threshold must default to the lookup staleness threshold"
+    );
+}
+
+#[test]
+fn gc_settings_follow_env() {
+    let cfg = parse_ok(required_with(&[("HUGINN_EBPF_SYN_MAP_MAX_ENTRIES", "100")]));
+    assert_eq!(cfg.gc_max_tick_delta, 200, "default delta tracks the map capacity");
+
+    let cfg = parse_ok(required_with(&[
+        ("HUGINN_EBPF_GC_INTERVAL_SECS", "0"),
+        ("HUGINN_EBPF_GC_MAX_TICK_DELTA", " 4096 "),
+    ]));
+    assert_eq!(cfg.gc_interval_secs, 0, "0 disables the sweep");
+    assert_eq!(cfg.gc_max_tick_delta, 4096);
 }
 
 #[test]
@@ -175,6 +194,8 @@ fn from_env_invalid_values_are_reported() {
         ("HUGINN_EBPF_METRICS_PORT", "-1"),
         ("HUGINN_EBPF_SYN_MAP_MAX_ENTRIES", "lots"),
         ("HUGINN_EBPF_LOG_LEVEL", "verbose"),
+        ("HUGINN_EBPF_GC_INTERVAL_SECS", "often"),
+        ("HUGINN_EBPF_GC_MAX_TICK_DELTA", "-5"),
     ] {
         let result = from_env(required_with(&[(name, bad)]));
         assert!(
@@ -28,8 +28,14 @@ fn read_percpu_counter_from_path(path: impl AsRef<std::path::Path>) -> Option<u6
 /// counter now. An entry is stale when more than `2 x syn_map_max_entries` SYNs have
 /// arrived since capture, enough to have evicted and reused the LRU slot once.
 pub fn is_stale(stored_tick: u64, current_tick: u64, syn_map_max_entries: u32) -> bool {
-    let threshold = u64::from(syn_map_max_entries).saturating_mul(2);
-    current_tick.saturating_sub(stored_tick) > threshold
+    exceeds_tick_delta(stored_tick, current_tick, u64::from(syn_map_max_entries).saturating_mul(2))
+}
+
+/// Returns `true` when more than `max_tick_delta` SYNs have arrived since `stored_tick`.
+///
+/// A `current_tick` behind `stored_tick` (entry written after the tick was read) counts as age 0.
+pub fn exceeds_tick_delta(stored_tick: u64, current_tick: u64, max_tick_delta: u64) -> bool {
+    current_tick.saturating_sub(stored_tick) > max_tick_delta
 }
 
 pub fn syn_insert_failures_count_from_path(base_path: &str) -> Option<u64> {
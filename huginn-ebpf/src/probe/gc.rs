@@ -0,0 +1,76 @@
+use aya::maps::{HashMap, Map, MapData};
+use aya::Pod;
+use tracing::debug;
+
+use crate::types::{SynRawDataV4, SynRawDataV6};
+
+use super::counters::exceeds_tick_delta;
+use super::EbpfProbe;
+
+/// Outcome of one stale-entry sweep over a TCP SYN map.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SweepStats {
+    /// Entries left in the map after the sweep.
+    pub entries: u64,
+    /// Entries removed because they were older than the tick delta.
+    pub evicted: u64,
+}
+
+impl EbpfProbe {
+    /// Remove IPv4 SYN map entries captured more than `max_tick_delta` SYNs ago.
+    ///
+    /// The LRU only evicts under insert pressure, so on a quiet interface entries for connections
+    /// that were never looked up (scans, clients that gave up) linger indefinitely. Lookups
+    /// already refuse entries older than 2x `syn_map_max_entries`; sweeping keeps the map itself
+    /// from filling with them. Returns `None` when the map or the global tick cannot be read.
+    pub fn sweep_stale(&mut self, max_tick_delta: u64) -> Option<SweepStats> {
+        let current_tick = self.read_current_tick()?;
+        let map = self.syn_map_v4_mut()?;
+        let stats = sweep::<u64, SynRawDataV4>(map, |v| v.tick, current_tick, max_tick_delta)?;
+        debug!(current_tick, entries = stats.entries, evicted = stats.evicted, "SYN map swept");
+        Some(stats)
+    }
+
+    /// Remove IPv6 SYN map entries captured more than `max_tick_delta` SYNs ago.
+    ///
+    /// See [`sweep_stale`](Self::sweep_stale).
+    pub fn sweep_stale_v6(&mut self, max_tick_delta: u64) -> Option<SweepStats> {
+        let current_tick = self.read_current_tick()?;
+        let map = self.syn_map_v6_mut()?;
+        let stats = sweep::<[u8; 18], SynRawDataV6>(map, |v| v.tick, current_tick, max_tick_delta)?;
+        debug!(
+            current_tick,
+            entries = stats.entries,
+            evicted = stats.evicted,
+            "SYN v6 map swept"
+        );
+        Some(stats)
+    }
+}
+
+/// Collect the stale keys first, then delete them: removing while iterating makes the kernel
+/// restart the key walk from the beginning.
+fn sweep<K: Pod, V: Pod>(
+    map: &mut Map,
+    tick_of: impl Fn(&V) -> u64,
+    current_tick: u64,
+    max_tick_delta: u64,
+) -> Option<SweepStats> {
+    let mut map = HashMap::<&mut MapData, K, V>::try_from(map).ok()?;
+    let mut seen = 0u64;
+    let mut stale = Vec::new();
+    // Entries can vanish mid-walk (LRU eviction, concurrent sweep); skip them.
+    for (key, value) in map.iter().flatten() {
+        seen = seen.saturating_add(1);
+        if exceeds_tick_delta(tick_of(&value), current_tick, max_tick_delta) {
+            stale.push(key);
+        }
+    }
+    let mut evicted = 0u64;
+    for key in &stale {
+        if map.remove(key).is_ok() {
+            evicted = evicted.saturating_add(1);
+        }
+    }
+    Some(SweepStats { entries: seen.saturating_sub(evicted), evicted })
+}
@@ -15,15 +15,17 @@ use crate::EbpfLogLevel;
 
 mod attach;
 mod counters;
+mod gc;
 mod keys;
 mod lookup;
 mod maps;
 
 pub use counters::{
-    is_stale, syn_captured_count_from_path, syn_captured_v6_count_from_path,
+    exceeds_tick_delta, is_stale, syn_captured_count_from_path, syn_captured_v6_count_from_path,
     syn_insert_failures_count_from_path, syn_insert_failures_v6_count_from_path,
     syn_malformed_count_from_path, syn_malformed_v6_count_from_path,
 };
+pub use gc::SweepStats;
 pub use keys::{make_bpf_key_v4, make_bpf_key_v6};
 
 /// Raw bytes of the compiled BPF object (XDP + TC programs), embedded at compile time.
@@ -321,6 +323,20 @@ impl EbpfProbe {
         }
     }
 
+    fn syn_map_v4_mut(&mut self) -> Option<&mut Map> {
+        match &mut self.inner {
+            ProbeInner::Embedded { ebpf } => ebpf.map_mut(pin::SYN_MAP_V4_NAME),
+            ProbeInner::Pinned(p) => Some(&mut p.ipv4.syn),
+        }
+    }
+
+    fn syn_map_v6_mut(&mut self) -> Option<&mut Map> {
+        match &mut self.inner {
+            ProbeInner::Embedded { ebpf } => ebpf.map_mut(pin::SYN_MAP_V6_NAME),
+            ProbeInner::Pinned(p) => Some(&mut p.ipv6.syn),
+        }
+    }
+
     fn counter_map(&self) -> Option<&Map> {
         match &self.inner {
             ProbeInner::Embedded { ebpf } => ebpf.map(pin::COUNTER_NAME),
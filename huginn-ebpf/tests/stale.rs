@@ -1,4 +1,4 @@
-use huginn_ebpf::is_stale;
+use huginn_ebpf::{exceeds_tick_delta, is_stale};
 
 #[test]
 fn fresh_entry_is_not_stale() {
@@ -57,3 +57,18 @@ fn u32_max_entries_does_not_overflow() {
     // u64::MAX is well above threshold → stale.
     assert!(is_stale(0, u64::MAX, max));
 }
+
+#[test]
+fn tick_delta_boundary_is_exclusive() {
+    assert!(!exceeds_tick_delta(1000, 1500, 500));
+    assert!(exceeds_tick_delta(1000, 1501, 500));
+    // Entry written after the tick was read.
+    assert!(!exceeds_tick_delta(2000, 1000, 0));
+}
+
+#[test]
+fn is_stale_matches_tick_delta_of_twice_capacity() {
+    for age in [0u64, 16_383, 16_384, 16_385] {
+        assert_eq!(is_stale(0, age, 8192), exceeds_tick_delta(0, age, 16_384), "age {age}");
+    }
+}
@@ -0,0 +1,235 @@
+//! Passive per-backend circuit breaker.
+//!
+//! Complements active health checks: instead of probing, it watches the outcome of real
+//! forwarded requests. A connection error or a 5xx response counts as a failure.
+//!
+//! ## States
+//!
+//! | State | Selection | Leaves when |
+//! |---|---|---|
+//! | [`CircuitState::Closed`] | eligible | `failure_threshold` consecutive failures → `Open` |
+//! | [`CircuitState::Open`] | skipped | `open_duration_secs` elapsed → `HalfOpen` |
+//! | [`CircuitState::HalfOpen`] | up to `half_open_requests` trial requests | all trials succeed → `Closed`; any failure → `Open` |
+//!
+//! Breakers are created lazily on first use (like the per-prefix round-robin counters in
+//! [`crate::backend::BackendSelector`]) and rebuilt when a backend's `circuit_breaker` config
+//! changes on hot reload, so no reload plumbing is required.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::config::CircuitBreakerConfig;
+
+/// Circuit state of a single backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// Outcome of [`CircuitBreaker::try_acquire`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Acquire {
+    /// The backend must not be used right now.
+    Denied,
+    /// The request may be sent.
+    Granted,
+    /// The request may be sent as the first trial of a circuit that just went half-open.
+    GrantedHalfOpen,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    /// When the circuit last entered `Open` or `HalfOpen`.
+    changed_at: Instant,
+    half_open_in_flight: u32,
+    half_open_successes: u32,
+}
+
+/// Circuit breaker for one backend.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                changed_at: Instant::now(),
+                half_open_in_flight: 0,
+                half_open_successes: 0,
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &CircuitBreakerConfig {
+        &self.config
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).state
+    }
+
+    fn open_duration(&self) -> Duration {
+        Duration::from_secs(self.config.open_duration_secs)
+    }
+
+    /// Whether the backend may be selected at `now` (read-only; does not claim a trial slot).
+    pub fn is_available_at(&self, now: Instant) -> bool {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match inner.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => {
+                now.saturating_duration_since(inner.changed_at) >= self.open_duration()
+            }
+            CircuitState::HalfOpen => {
+                inner.half_open_in_flight < self.config.half_open_requests
+                    || self.trial_slots_expired(&inner, now)
+            }
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.is_available_at(Instant::now())
+    }
+
+    /// Claim the right to send one request to the backend at `now`. Moves an open circuit whose
+    /// open period has elapsed to `HalfOpen` ([`Acquire::GrantedHalfOpen`]).
+    pub fn try_acquire_at(&self, now: Instant) -> Acquire {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match inner.state {
+            CircuitState::Closed => Acquire::Granted,
+            CircuitState::Open => {
+                if now.saturating_duration_since(inner.changed_at) < self.open_duration() {
+                    return Acquire::Denied;
+                }
+                inner.state = CircuitState::HalfOpen;
+                inner.changed_at = now;
+                inner.half_open_in_flight = 1;
+                inner.half_open_successes = 0;
+                Acquire::GrantedHalfOpen
+            }
+            CircuitState::HalfOpen => {
+                // Trial requests that never reported back (e.g. rejected by a later gate) would
+                // otherwise pin the breaker half-open forever; recycle their slots.
+                if self.trial_slots_expired(&inner, now) {
+                    inner.changed_at = now;
+                    inner.half_open_in_flight = 0;
+                }
+                if inner.half_open_in_flight >= self.config.half_open_requests {
+                    return Acquire::Denied;
+                }
+                inner.half_open_in_flight = inner.half_open_in_flight.saturating_add(1);
+                Acquire::Granted
+            }
+        }
+    }
+
+    pub fn try_acquire(&self) -> Acquire {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// Record the outcome of a forwarded request. Returns `Some(new_state)` on a transition.
+    pub fn record_at(&self, success: bool, now: Instant) -> Option<CircuitState> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match (inner.state, success) {
+            (CircuitState::Closed, true) => {
+                inner.consecutive_failures = 0;
+                None
+            }
+            (CircuitState::Closed, false) => {
+                inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+                if inner.consecutive_failures < self.config.failure_threshold {
+                    return None;
+                }
+                Some(Self::open(&mut inner, now))
+            }
+            (CircuitState::HalfOpen, true) => {
+                inner.half_open_in_flight = inner.half_open_in_flight.saturating_sub(1);
+                inner.half_open_successes = inner.half_open_successes.saturating_add(1);
+                if inner.half_open_successes < self.config.half_open_requests {
+                    return None;
+                }
+                inner.state = CircuitState::Closed;
+                inner.consecutive_failures = 0;
+                inner.half_open_in_flight = 0;
+                inner.half_open_successes = 0;
+                Some(CircuitState::Closed)
+            }
+            (CircuitState::HalfOpen, false) => Some(Self::open(&mut inner, now)),
+            // Late results from requests sent before the circuit opened carry no new signal.
+            (CircuitState::Open, _) => None,
+        }
+    }
+
+    pub fn record(&self, success: bool) -> Option<CircuitState> {
+        self.record_at(success, Instant::now())
+    }
+
+    fn open(inner: &mut Inner, now: Instant) -> CircuitState {
+        inner.state = CircuitState::Open;
+        inner.changed_at = now;
+        inner.consecutive_failures = 0;
+        inner.half_open_in_flight = 0;
+        inner.half_open_successes = 0;
+        CircuitState::Open
+    }
+
+    fn trial_slots_expired(&self, inner: &Inner, now: Instant) -> bool {
+        now.saturating_duration_since(inner.changed_at) >= self.open_duration()
+    }
+}
+
+/// Address → [`CircuitBreaker`] map, shared by every connection.
+#[derive(Debug, Default)]
+pub struct CircuitBreakerRegistry {
+    inner: RwLock<HashMap<String, Arc<CircuitBreaker>>>,
+}
+
+impl CircuitBreakerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Breaker for `address` under `config`, created on first use. A breaker whose config no
+    /// longer matches (hot reload) is replaced with a fresh, closed one.
+    pub fn get(&self, address: &str, config: &CircuitBreakerConfig) -> Arc<CircuitBreaker> {
+        if let Some(cb) = self
+            .inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(address)
+        {
+            if cb.config() == config {
+                return Arc::clone(cb);
+            }
+        }
+        let mut map = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let entry = map
+            .entry(address.to_string())
+            .or_insert_with(|| Arc::new(CircuitBreaker::new(config.clone())));
+        if entry.config() != config {
+            *entry = Arc::new(CircuitBreaker::new(config.clone()));
+        }
+        Arc::clone(entry)
+    }
+}
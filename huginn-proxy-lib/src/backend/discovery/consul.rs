@@ -0,0 +1,107 @@
+//! Consul catalog provider: `GET /v1/health/service/<service>?passing` on the configured agent.
+
+use bytes::Bytes;
+use http::{Request, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde::Deserialize;
+
+use super::provider::DiscoveryError;
+
+/// Client for one service's health endpoint.
+pub(super) struct ConsulProvider {
+    client: Client<HttpConnector, Full<Bytes>>,
+    uri: String,
+    token: Option<String>,
+}
+
+/// One entry of the health endpoint; only the fields needed to reach the instance.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ServiceEntry {
+    node: Node,
+    service: Service,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Node {
+    address: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Service {
+    /// Empty when the instance registered without its own address: the node's applies.
+    #[serde(default)]
+    address: String,
+    port: u16,
+}
+
+impl ConsulProvider {
+    /// `service`, `tag` and `datacenter` are validated to need no URL encoding.
+    pub(super) fn new(
+        url: &str,
+        service: &str,
+        tag: Option<&str>,
+        datacenter: Option<&str>,
+        token: Option<String>,
+    ) -> Self {
+        let mut uri =
+            format!("{}/v1/health/service/{service}?passing=true", url.trim_end_matches('/'));
+        if let Some(tag) = tag {
+            uri.push_str(&format!("&tag={tag}"));
+        }
+        if let Some(datacenter) = datacenter {
+            uri.push_str(&format!("&dc={datacenter}"));
+        }
+        let client = Client::builder(TokioExecutor::new()).build(HttpConnector::new());
+        Self { client, uri, token }
+    }
+
+    /// `host:port` of every instance whose checks pass.
+    pub(super) async fn endpoints(&self) -> Result<Vec<String>, DiscoveryError> {
+        let mut request = Request::get(self.uri.as_str());
+        if let Some(token) = &self.token {
+            request = request.header("x-consul-token", token.as_str());
+        }
+        let request = request
+            .body(Full::new(Bytes::new()))
+            .map_err(|e| DiscoveryError::Consul(e.to_string()))?;
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| DiscoveryError::Consul(e.to_string()))?;
+        let status = response.status();
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| DiscoveryError::Consul(e.to_string()))?
+            .to_bytes();
+        if status != StatusCode::OK {
+            return Err(DiscoveryError::Consul(format!("{} answered {status}", self.uri)));
+        }
+        let entries: Vec<ServiceEntry> = serde_json::from_slice(&body)
+            .map_err(|e| DiscoveryError::Consul(format!("unexpected response: {e}")))?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| {
+                let host = if entry.service.address.is_empty() {
+                    entry.node.address
+                } else {
+                    entry.service.address
+                };
+                // IPv6 instance addresses need brackets in `host:port`.
+                if host.contains(':') {
+                    format!("[{host}]:{}", entry.service.port)
+                } else {
+                    format!("{host}:{}", entry.service.port)
+                }
+            })
+            .collect())
+    }
+}
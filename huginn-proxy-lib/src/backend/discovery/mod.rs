@@ -0,0 +1,356 @@
+//! Backend address discovery (`discovery` on a backend).
+//!
+//! One resolver task per backend with a `discovery` table asks the backend's [`Provider`] for its
+//! members and keeps the full address set in [`DiscoveredAddrs`]. The backend connector reads it
+//! to spread new connections over every address (round-robin, falling through to the next address
+//! when one refuses), and when a refresh drops an address the backend's pooled clients are
+//! replaced, so connections to the removed address finish their in-flight requests and are closed
+//! instead of being reused.
+//!
+//! A failed or empty refresh keeps the last known set: a resolver outage must not take a healthy
+//! backend down. Until the first refresh succeeds the connector resolves a `dns` backend's host
+//! itself; a discovered service has no host to resolve, so it has no address until then. For a
+//! service an empty answer is the provider saying no member is healthy: the backend is marked
+//! unhealthy in the [`HealthRegistry`] (routes fail over like for a failed health check) until
+//! members come back.
+
+mod consul;
+mod provider;
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, timeout, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::backend::health_check::{HealthRegistry, UpstreamHealth};
+use crate::config::{Backend, DiscoveryConfig};
+use crate::proxy::reload::SharedClientPool;
+use crate::telemetry::metrics::values;
+use crate::telemetry::Metrics;
+pub use provider::DiscoveryError;
+use provider::Provider;
+
+/// Upper bound on one refresh; the refresh interval bounds it further.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Addresses of one backend and the round-robin cursor over them.
+struct AddrSet {
+    addrs: Vec<SocketAddr>,
+    next: AtomicUsize,
+}
+
+/// Current address set of every backend with `discovery`, keyed by backend address.
+#[derive(Default)]
+pub struct DiscoveredAddrs {
+    backends: ArcSwap<HashMap<String, Arc<AddrSet>>>,
+}
+
+/// Outcome of [`DiscoveredAddrs::update`] when the set changed.
+struct AddrChange {
+    added: Vec<SocketAddr>,
+    removed: Vec<SocketAddr>,
+}
+
+impl DiscoveredAddrs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Addresses of `backend` in the order the next connection should try them: each call starts
+    /// one address further. `None` when the backend is not discovered (or a `dns` backend has not
+    /// been resolved yet).
+    pub fn connect_order(&self, backend: &str) -> Option<Vec<SocketAddr>> {
+        let set = self.backends.load().get(backend).cloned()?;
+        let len = set.addrs.len();
+        let start = set
+            .next
+            .fetch_add(1, Ordering::Relaxed)
+            .checked_rem(len)
+            .unwrap_or_default();
+        Some(
+            set.addrs
+                .iter()
+                .cycle()
+                .skip(start)
+                .take(len)
+                .copied()
+                .collect(),
+        )
+    }
+
+    /// Current addresses of `backend`, sorted.
+    pub fn addrs(&self, backend: &str) -> Vec<SocketAddr> {
+        self.backends
+            .load()
+            .get(backend)
+            .map(|set| set.addrs.clone())
+            .unwrap_or_default()
+    }
+
+    /// Start `backend` with no address unless it already has a set, so the connector never
+    /// resolves the logical name of a discovered service.
+    fn register(&self, backend: &str) {
+        self.backends.rcu(|backends| {
+            let mut backends = HashMap::clone(backends);
+            backends.entry(backend.to_string()).or_insert_with(|| {
+                Arc::new(AddrSet { addrs: Vec::new(), next: AtomicUsize::new(0) })
+            });
+            backends
+        });
+    }
+
+    /// Replace the set of `backend`; `None` when `addrs` is the current set.
+    fn update(&self, backend: &str, mut addrs: Vec<SocketAddr>) -> Option<AddrChange> {
+        addrs.sort_unstable();
+        addrs.dedup();
+        let current = self.addrs(backend);
+        if current == addrs {
+            return None;
+        }
+        let old: HashSet<&SocketAddr> = current.iter().collect();
+        let new: HashSet<&SocketAddr> = addrs.iter().collect();
+        let change = AddrChange {
+            added: addrs.iter().filter(|a| !old.contains(a)).copied().collect(),
+            removed: current
+                .iter()
+                .filter(|a| !new.contains(a))
+                .copied()
+                .collect(),
+        };
+        let set = Arc::new(AddrSet { addrs, next: AtomicUsize::new(0) });
+        self.backends.rcu(|backends| {
+            let mut backends = HashMap::clone(backends);
+            backends.insert(backend.to_string(), Arc::clone(&set));
+            backends
+        });
+        Some(change)
+    }
+
+    fn remove(&self, backend: &str) {
+        self.backends.rcu(|backends| {
+            let mut backends = HashMap::clone(backends);
+            backends.remove(backend);
+            backends
+        });
+    }
+}
+
+struct ActiveResolver {
+    config: DiscoveryConfig,
+    cancel: CancellationToken,
+    _join: JoinHandle<()>,
+}
+
+/// Owns the resolver tasks; reconciles them on hot reload and stops them on shutdown.
+#[derive(Default)]
+pub struct BackendDiscovery {
+    addrs: Arc<DiscoveredAddrs>,
+    health: Option<Arc<HealthRegistry>>,
+    active: Mutex<HashMap<String, ActiveResolver>>,
+}
+
+impl BackendDiscovery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report the membership of discovered services (`static`, `srv`, `consul`) as backend
+    /// health: a service with no member is unhealthy.
+    pub fn with_health(mut self, registry: Arc<HealthRegistry>) -> Self {
+        self.health = Some(registry);
+        self
+    }
+
+    /// Address sets maintained by the resolver tasks, for the backend connector.
+    pub fn addrs(&self) -> &Arc<DiscoveredAddrs> {
+        &self.addrs
+    }
+
+    /// Stops all resolver tasks (used on graceful process shutdown).
+    pub fn shutdown(&self) {
+        let mut guard = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        for (addr, active) in guard.drain() {
+            active.cancel.cancel();
+            info!(backend = %addr, "discovery task cancelled (shutdown)");
+        }
+    }
+
+    /// Diff `backends` against the running set: cancels removed/changed resolvers (forgetting
+    /// the addresses and health of removed ones) and spawns new ones.
+    pub fn reconcile(
+        &self,
+        backends: &[Backend],
+        client_pool: &SharedClientPool,
+        metrics: &Arc<Metrics>,
+        handle: &Handle,
+    ) {
+        let wanted: HashMap<&str, &DiscoveryConfig> = backends
+            .iter()
+            .filter_map(|b| b.discovery.as_ref().map(|d| (b.address.as_str(), d)))
+            .collect();
+
+        let mut guard = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        guard.retain(|addr, active| {
+            if wanted.contains_key(addr.as_str()) {
+                return true;
+            }
+            active.cancel.cancel();
+            self.addrs.remove(addr);
+            metrics.record_backend_discovered_addresses(addr, 0);
+            if active.config.is_service() {
+                self.forget_health(addr);
+            }
+            info!(backend = %addr, "discovery removed (config)");
+            false
+        });
+
+        for (addr, config) in wanted {
+            if guard
+                .get(addr)
+                .is_some_and(|active| active.config == *config)
+            {
+                continue;
+            }
+            if let Some(old) = guard.remove(addr) {
+                old.cancel.cancel();
+                if old.config.is_service() && !config.is_service() {
+                    self.forget_health(addr);
+                }
+            }
+            let health = if config.is_service() {
+                self.addrs.register(addr);
+                self.health.as_ref().map(|registry| {
+                    let health = registry.get_or_create(addr);
+                    metrics.record_backend_health(addr, health.is_healthy());
+                    health
+                })
+            } else {
+                None
+            };
+            let cancel = CancellationToken::new();
+            let resolver = Resolver {
+                address: addr.to_string(),
+                provider: Provider::new(addr, config),
+                health,
+                addrs: Arc::clone(&self.addrs),
+                client_pool: Arc::clone(client_pool),
+                metrics: Arc::clone(metrics),
+            };
+            let join = handle.spawn(resolver.run(config.refresh_secs(), cancel.clone()));
+            guard.insert(
+                addr.to_string(),
+                ActiveResolver { config: config.clone(), cancel, _join: join },
+            );
+        }
+    }
+
+    fn forget_health(&self, addr: &str) {
+        if let Some(registry) = &self.health {
+            registry.remove(addr);
+        }
+    }
+}
+
+/// State of one resolver task.
+struct Resolver {
+    address: String,
+    provider: Provider,
+    /// Set for discovered services when a [`HealthRegistry`] is attached.
+    health: Option<Arc<UpstreamHealth>>,
+    addrs: Arc<DiscoveredAddrs>,
+    client_pool: SharedClientPool,
+    metrics: Arc<Metrics>,
+}
+
+impl Resolver {
+    async fn run(self, refresh_secs: u64, cancel: CancellationToken) {
+        let refresh = Duration::from_secs(refresh_secs);
+        let mut ticker = interval(refresh);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => {
+                    info!(backend = %self.address, "discovery loop stopped");
+                    return;
+                }
+                _ = ticker.tick() => self.refresh(refresh.min(RESOLVE_TIMEOUT)).await,
+            }
+        }
+    }
+
+    async fn refresh(&self, resolve_timeout: Duration) {
+        let address = self.address.as_str();
+        let members = match timeout(resolve_timeout, self.provider.members()).await {
+            Ok(Ok(members)) => members,
+            Ok(Err(e)) => {
+                warn!(backend = %address, error = %e, "discovery: refresh failed, keeping last known addresses");
+                self.metrics
+                    .record_backend_discovery_refresh(address, values::DISCOVERY_ERROR);
+                return;
+            }
+            Err(_) => {
+                warn!(backend = %address, "discovery: refresh timed out, keeping last known addresses");
+                self.metrics
+                    .record_backend_discovery_refresh(address, values::DISCOVERY_ERROR);
+                return;
+            }
+        };
+        if members.is_empty() {
+            warn!(backend = %address, "discovery: no addresses returned, keeping last known addresses");
+            self.metrics
+                .record_backend_discovery_refresh(address, values::DISCOVERY_EMPTY);
+            self.set_healthy(false);
+            return;
+        }
+        self.set_healthy(true);
+        let Some(change) = self.addrs.update(address, members) else {
+            self.metrics
+                .record_backend_discovery_refresh(address, values::DISCOVERY_UNCHANGED);
+            return;
+        };
+        self.metrics
+            .record_backend_discovery_refresh(address, values::DISCOVERY_CHANGED);
+        self.metrics
+            .record_backend_discovered_addresses(address, self.addrs.addrs(address).len());
+        self.metrics.record_backend_member_changes(
+            address,
+            change.added.len(),
+            change.removed.len(),
+        );
+        info!(
+            backend = %address,
+            added = ?change.added,
+            removed = ?change.removed,
+            "discovery: backend addresses changed"
+        );
+        if !change.removed.is_empty() {
+            // New clients hold no connection; the old ones close theirs once in-flight requests end.
+            self.client_pool
+                .rcu(|pool| pool.with_fresh_backend_clients(address));
+        }
+    }
+
+    /// Mirror the provider's answer into the backend's health, logging transitions.
+    fn set_healthy(&self, healthy: bool) {
+        let Some(health) = &self.health else {
+            return;
+        };
+        if health.is_healthy() != healthy {
+            health.set(healthy);
+            self.metrics.record_backend_health(&self.address, healthy);
+            if healthy {
+                info!(backend = %self.address, "discovery: service has members again, marked healthy");
+            } else {
+                warn!(backend = %self.address, "discovery: service has no healthy member, marked unhealthy");
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Where a backend's members come from: one [`Provider`] per `discovery` type.
+
+use std::net::SocketAddr;
+
+use thiserror::Error;
+use tokio::net::lookup_host;
+use tracing::debug;
+
+use super::consul::ConsulProvider;
+use crate::config::DiscoveryConfig;
+use crate::security::bot_verification::dns::{query, DnsError, TYPE_SRV};
+use crate::security::bot_verification::SYSTEM_RESOLVER;
+
+#[derive(Debug, Error)]
+pub enum DiscoveryError {
+    #[error("lookup of '{0}' failed: {1}")]
+    Lookup(String, std::io::Error),
+    #[error("SRV query failed: {0}")]
+    Srv(#[from] DnsError),
+    #[error("no nameserver in /etc/resolv.conf to send SRV queries to")]
+    NoResolver,
+    #[error("Consul request failed: {0}")]
+    Consul(String),
+}
+
+/// Source of the members of one backend, built from its `discovery` table.
+pub(super) enum Provider {
+    /// A and AAAA records of the backend's own `host:port`.
+    Dns { address: String },
+    /// Fixed `host:port` members.
+    Static { members: Vec<String> },
+    /// Targets of the SRV records of `name`.
+    Srv { name: String },
+    /// Passing instances of a Consul service.
+    Consul(Box<ConsulProvider>),
+}
+
+impl Provider {
+    pub(super) fn new(address: &str, config: &DiscoveryConfig) -> Self {
+        match config {
+            DiscoveryConfig::Dns { .. } => Self::Dns { address: address.to_string() },
+            DiscoveryConfig::Static { members, .. } => Self::Static { members: members.clone() },
+            DiscoveryConfig::Srv { name, .. } => Self::Srv { name: name.clone() },
+            DiscoveryConfig::Consul { url, service, tag, datacenter, token, .. } => {
+                Self::Consul(Box::new(ConsulProvider::new(
+                    url,
+                    service,
+                    tag.as_deref(),
+                    datacenter.as_deref(),
+                    token.as_ref().map(|token| token.expose().clone()),
+                )))
+            }
+        }
+    }
+
+    /// Current member addresses. An empty list is an answer (the service has no healthy member),
+    /// an error is not: callers keep the last known members on errors.
+    pub(super) async fn members(&self) -> Result<Vec<SocketAddr>, DiscoveryError> {
+        match self {
+            Self::Dns { address } => lookup_host(address.as_str())
+                .await
+                .map(|found| found.collect())
+                .map_err(|e| DiscoveryError::Lookup(address.clone(), e)),
+            Self::Static { members } => resolve_all(members.iter().cloned()).await,
+            Self::Srv { name } => {
+                let resolver = (*SYSTEM_RESOLVER).ok_or(DiscoveryError::NoResolver)?;
+                let records = query(resolver, name, TYPE_SRV).await?;
+                let records: Vec<_> = records
+                    .iter()
+                    .filter_map(|record| record.srv())
+                    // A target of "." means the service is not offered.
+                    .filter(|srv| !srv.target.is_empty())
+                    .collect();
+                let Some(priority) = records.iter().map(|srv| srv.priority).min() else {
+                    return Ok(Vec::new());
+                };
+                let endpoints: Vec<String> = records
+                    .into_iter()
+                    .filter(|srv| srv.priority == priority)
+                    .map(|srv| format!("{}:{}", srv.target, srv.port))
+                    .collect();
+                resolve_all(endpoints).await
+            }
+            Self::Consul(consul) => resolve_all(consul.endpoints().await?).await,
+        }
+    }
+}
+
+/// Resolve every `host:port` endpoint. Endpoints that do not resolve are skipped as long as one
+/// does, so one stale name does not freeze the member list.
+async fn resolve_all(
+    endpoints: impl IntoIterator<Item = String>,
+) -> Result<Vec<SocketAddr>, DiscoveryError> {
+    let mut addrs = Vec::new();
+    let mut first_error = None;
+    for endpoint in endpoints {
+        let resolved = lookup_host(endpoint.as_str())
+            .await
+            .map(Iterator::collect::<Vec<_>>);
+        match resolved {
+            Ok(found) => addrs.extend(found),
+            Err(e) => {
+                debug!(endpoint = %endpoint, error = %e, "discovery: member did not resolve");
+                first_error.get_or_insert(DiscoveryError::Lookup(endpoint, e));
+            }
+        }
+    }
+    match first_error {
+        Some(error) if addrs.is_empty() => Err(error),
+        _ => Ok(addrs),
+    }
+}
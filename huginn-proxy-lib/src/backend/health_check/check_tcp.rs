@@ -5,18 +5,26 @@
 //! does **not** perform a TLS handshake, and does **not** validate any
 //! response.
 //!
-//! Suitable as a default check for any backend that listens for TCP.
+//! Suitable as a default check for any backend that listens for TCP. For a `unix:` backend it
+//! connects to the socket instead.
 
 use std::time::Duration;
-use tokio::net::TcpStream;
+use tokio::net::{TcpStream, UnixStream};
+
+use crate::config::unix_socket_path;
 use tracing::trace;
 
-/// Perform a TCP health check against `addr` (`host:port`).
-/// Returns `true` if the 3-way handshake completed inside `timeout`.
+/// Perform a TCP health check against `addr` (`host:port` or `unix:<path>`).
+/// Returns `true` if the 3-way handshake (or unix socket connect) completed inside `timeout`.
 pub async fn check_tcp(addr: &str, timeout: Duration) -> bool {
-    let res = tokio::time::timeout(timeout, TcpStream::connect(addr))
-        .await
-        .is_ok_and(|r| r.is_ok());
+    let res = match unix_socket_path(addr) {
+        Some(path) => tokio::time::timeout(timeout, UnixStream::connect(path))
+            .await
+            .is_ok_and(|r| r.is_ok()),
+        None => tokio::time::timeout(timeout, TcpStream::connect(addr))
+            .await
+            .is_ok_and(|r| r.is_ok()),
+    };
 
     trace!(
         backend = %addr,
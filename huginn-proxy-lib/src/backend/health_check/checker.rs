@@ -42,7 +42,11 @@ impl HealthCheckSupervisor {
     }
 
     /// Diff `backends` against the running set: cancels removed/changed, spawns new tasks.
+    /// Config drains (`drain` on a backend) and slow starts (`slow_start_secs`) are applied to
+    /// the registry on the same pass.
     pub fn reconcile(&self, backends: &[Backend], metrics: &Arc<Metrics>, handle: &Handle) {
+        self.registry.configure_drains(backends);
+        self.registry.configure_slow_start(backends);
         let wanted = collect_wanted_checks(backends);
 
         {
@@ -84,6 +88,7 @@ impl HealthCheckSupervisor {
             }
 
             let health = self.registry.get_or_create(&addr);
+            metrics.record_backend_health(&addr, health.is_healthy());
             let cancel = CancellationToken::new();
             let m = metrics.clone();
             let addr_for_task = addr.clone();
@@ -91,6 +96,7 @@ impl HealthCheckSupervisor {
             let join = handle.spawn(run_health_checker(
                 addr_for_task,
                 health,
+                Arc::clone(&self.registry),
                 config.clone(),
                 task_token,
                 m,
@@ -116,15 +122,17 @@ fn collect_wanted_checks(backends: &[Backend]) -> HashMap<String, HealthCheckCon
     out
 }
 
+/// Probe `address` every `config.interval_secs` until `cancel`, updating `health` on each
+/// transition. A recovery begins the backend's slow start in `registry`.
 pub async fn run_health_checker(
     address: String,
     health: Arc<UpstreamHealth>,
+    registry: Arc<HealthRegistry>,
     config: HealthCheckConfig,
     cancel: CancellationToken,
     metrics: Arc<Metrics>,
 ) {
     let mut counter = ConsecutiveCounter::new(config.unhealthy_threshold, config.healthy_threshold);
-    let timeout = Duration::from_secs(config.timeout_secs);
     let http_client = if matches!(&config.check_type, HealthCheckType::Http { .. }) {
         Some(HealthCheckHttpClient::new(config.timeout_secs))
     } else {
@@ -132,53 +140,62 @@ pub async fn run_health_checker(
     };
     let mut ticker = interval(Duration::from_secs(config.interval_secs));
     ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let probe = Probe {
+        timeout: Duration::from_secs(config.timeout_secs),
+        address,
+        health,
+        registry,
+        config,
+        http_client,
+        metrics,
+    };
     loop {
         tokio::select! {
             _ = cancel.cancelled() => {
-                info!(backend = %address, "health check loop stopped");
+                info!(backend = %probe.address, "health check loop stopped");
                 return;
             }
-            _ = ticker.tick() => {
-                run_one_probe(
-                    &address,
-                    &health,
-                    &config,
-                    http_client.as_ref(),
-                    &mut counter,
-                    timeout,
-                    &metrics,
-                )
-                .await;
-            }
+            _ = ticker.tick() => probe.run(&mut counter).await,
         }
     }
 }
 
-async fn run_one_probe(
-    address: &str,
-    health: &Arc<UpstreamHealth>,
-    config: &HealthCheckConfig,
-    http_client: Option<&HealthCheckHttpClient>,
-    counter: &mut ConsecutiveCounter,
+/// What every probe of one backend's checker uses.
+struct Probe {
+    address: String,
+    health: Arc<UpstreamHealth>,
+    registry: Arc<HealthRegistry>,
+    config: HealthCheckConfig,
+    /// `Some` for HTTP checks.
+    http_client: Option<HealthCheckHttpClient>,
     timeout: Duration,
-    metrics: &Metrics,
-) {
-    let ok = match &config.check_type {
-        HealthCheckType::Tcp => check_tcp(address, timeout).await,
-        HealthCheckType::Http { path, expected_status } => {
-            let Some(client) = http_client else {
-                return;
-            };
-            check_http(client, address, path, *expected_status, timeout).await
-        }
-    };
-    metrics.record_health_check_probe(address, ok);
-    if let Some(new_state) = counter.record(ok) {
-        health.set(new_state);
-        if new_state {
-            info!(backend = %address, "upstream is now healthy (enough consecutive successes)");
-        } else {
-            info!(backend = %address, "upstream is now unhealthy (enough consecutive failures)");
+    metrics: Arc<Metrics>,
+}
+
+impl Probe {
+    /// Probe the backend once and record the outcome in `counter`, applying a health transition
+    /// when it completes one.
+    async fn run(&self, counter: &mut ConsecutiveCounter) {
+        let address = self.address.as_str();
+        let ok = match &self.config.check_type {
+            HealthCheckType::Tcp => check_tcp(address, self.timeout).await,
+            HealthCheckType::Http { path, expected_status } => {
+                let Some(client) = &self.http_client else {
+                    return;
+                };
+                check_http(client, address, path, *expected_status, self.timeout).await
+            }
+        };
+        self.metrics.record_health_check_probe(address, ok);
+        if let Some(new_state) = counter.record(ok) {
+            self.health.set(new_state);
+            self.metrics.record_backend_health(address, new_state);
+            if new_state {
+                info!(backend = %address, "upstream is now healthy (enough consecutive successes)");
+                self.registry.begin_slow_start(address);
+            } else {
+                info!(backend = %address, "upstream is now unhealthy (enough consecutive failures)");
+            }
         }
     }
 }
@@ -17,16 +17,91 @@
 //! inserted into the registry. [`HealthRegistry::is_healthy`] returns `true`
 //! for any unknown address, health checks are per-backend opt-in; traffic is
 //! not gated until a backend registers a probe.
+//!
+//! ## Drain
+//!
+//! A backend is **drained** by `drain = true` in its config ([`HealthRegistry::configure_drains`],
+//! on every load and hot reload) or by the admin API ([`HealthRegistry::drain`]): it is then
+//! reported unhealthy regardless of its probe, so no new request is routed to it while in-flight
+//! ones complete. Drain state is keyed by address and works for backends without a health check.
+//!
+//! Every forwarded request holds the [`drain_cutoff`](HealthRegistry::drain_cutoff) token of its
+//! backend. When the backend has `drain_timeout_secs`, draining it starts a timer that cancels the
+//! token once the timeout passes, cutting the requests still in flight; undraining first stops
+//! the timer.
+//!
+//! ## Slow start
+//!
+//! A backend with `slow_start_secs` that recovers (its probe turns healthy again) or is added by a
+//! hot reload **warms up**: its [`slow_start_weight`](HealthRegistry::slow_start_weight) ramps
+//! from 10% to 100% over the window, and the selector sends it that fraction of its share of
+//! traffic. Backends of the first configuration start at full weight.
 
 use super::health::UpstreamHealth;
-use std::collections::HashMap;
+use crate::config::Backend;
+use crate::telemetry::Metrics;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
 
 /// Address → health state map shared between the future `HealthCheckSupervisor`
 /// (writer, on hot reload) and the forwarding gate (reader, per request).
-#[derive(Debug, Default, Clone)]
+#[derive(Default, Clone)]
 pub struct HealthRegistry {
     inner: Arc<RwLock<HashMap<String, Arc<UpstreamHealth>>>>,
+    drains: Arc<RwLock<Drains>>,
+    slow_starts: Arc<RwLock<SlowStarts>>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+/// Weight of a backend when its slow start begins.
+const SLOW_START_INITIAL_WEIGHT: f64 = 0.1;
+
+/// Slow-start state of every backend, keyed by address.
+#[derive(Debug, Default)]
+struct SlowStarts {
+    /// `slow_start_secs` of the backends that set it.
+    windows: HashMap<String, Duration>,
+    /// When each warming backend (re)entered rotation.
+    since: HashMap<String, Instant>,
+    /// Addresses of the last configuration; `None` until the first one is applied.
+    known: Option<HashSet<String>>,
+}
+
+/// Drain state of every backend, keyed by address.
+#[derive(Debug, Default)]
+struct Drains {
+    /// Drained through the admin API.
+    admin: HashSet<String>,
+    /// Drained by `drain = true` in the config.
+    config: HashSet<String>,
+    /// `drain_timeout_secs` of the backends that set it.
+    timeouts: HashMap<String, Duration>,
+    /// Token handed to the requests forwarded to each backend; cancelled (and forgotten, so the
+    /// next request gets a fresh one) when the backend's drain timeout passes.
+    cutoffs: HashMap<String, CancellationToken>,
+    /// Pending drain timer of each drained backend with a timeout.
+    timers: HashMap<String, CancellationToken>,
+}
+
+impl Drains {
+    fn is_drained(&self, address: &str) -> bool {
+        self.admin.contains(address) || self.config.contains(address)
+    }
+}
+
+impl fmt::Debug for HealthRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HealthRegistry")
+            .field("inner", &self.inner)
+            .field("drains", &self.drains)
+            .field("slow_starts", &self.slow_starts)
+            .finish_non_exhaustive()
+    }
 }
 
 impl HealthRegistry {
@@ -34,12 +109,22 @@ impl HealthRegistry {
         Self::default()
     }
 
+    /// Record `huginn_backend_drained` whenever a backend is drained or undrained.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Returns `true` if the backend is healthy **or** has no health check
     /// configured (address absent from the registry).
     ///
     /// Opt-in: only backends with an active health-check configuration are
-    /// registered; unknown addresses are treated as healthy (no gate).
+    /// registered; unknown addresses are treated as healthy (no gate). A drained
+    /// backend is never healthy.
     pub fn is_healthy(&self, address: &str) -> bool {
+        if self.is_drained(address) {
+            return false;
+        }
         match self.inner.read() {
             Ok(map) => map.get(address).is_none_or(|h| h.is_healthy()),
             // Lock poisoning means a checker task panicked. Fail-open so the
@@ -74,6 +159,214 @@ impl HealthRegistry {
         map.keys().cloned().collect()
     }
 
+    /// Stop routing new requests to `address` until [`undrain`](Self::undrain). Returns `false`
+    /// if the admin API had already drained it.
+    pub fn drain(&self, address: &str) -> bool {
+        let mut drains = self.drains.write().unwrap_or_else(|e| e.into_inner());
+        let was_drained = drains.is_drained(address);
+        let inserted = drains.admin.insert(address.to_string());
+        if !was_drained {
+            self.drain_started(&mut drains, address);
+        }
+        inserted
+    }
+
+    /// Lift the admin API's drain of `address`. Returns `false` if the admin API had not drained
+    /// it. A backend drained by its config stays drained.
+    pub fn undrain(&self, address: &str) -> bool {
+        let mut drains = self.drains.write().unwrap_or_else(|e| e.into_inner());
+        let removed = drains.admin.remove(address);
+        if removed && !drains.is_drained(address) {
+            self.drain_ended(&mut drains, address);
+        }
+        removed
+    }
+
+    /// Whether `address` is drained, by its config or the admin API.
+    pub fn is_drained(&self, address: &str) -> bool {
+        let drains = self.drains.read().unwrap_or_else(|e| e.into_inner());
+        drains.is_drained(address)
+    }
+
+    /// Whether `address` is drained by `drain = true` in its config.
+    pub fn is_drained_by_config(&self, address: &str) -> bool {
+        let drains = self.drains.read().unwrap_or_else(|e| e.into_inner());
+        drains.config.contains(address)
+    }
+
+    /// Apply `drain` and `drain_timeout_secs` of `backends`. Backends leaving the config lose
+    /// their config drain; a changed timeout applies from the backend's next drain.
+    pub fn configure_drains(&self, backends: &[Backend]) {
+        let mut drains = self.drains.write().unwrap_or_else(|e| e.into_inner());
+        drains.timeouts = backends
+            .iter()
+            .filter_map(|b| {
+                let secs = b.drain_timeout_secs?;
+                Some((b.address.clone(), Duration::from_secs(secs)))
+            })
+            .collect();
+        let wanted: HashSet<String> = backends
+            .iter()
+            .filter(|b| b.drain)
+            .map(|b| b.address.clone())
+            .collect();
+        let lifted: Vec<String> = drains.config.difference(&wanted).cloned().collect();
+        for address in lifted {
+            drains.config.remove(&address);
+            if !drains.is_drained(&address) {
+                info!(backend = %address, "backend undrained (config)");
+                self.drain_ended(&mut drains, &address);
+            }
+        }
+        for address in wanted {
+            let was_drained = drains.is_drained(&address);
+            drains.config.insert(address.clone());
+            if !was_drained {
+                info!(backend = %address, "backend drained (config)");
+                self.drain_started(&mut drains, &address);
+            }
+        }
+    }
+
+    /// Token cancelled when the drain timeout of `address` passes; held by each request
+    /// forwarded to it.
+    pub fn drain_cutoff(&self, address: &str) -> CancellationToken {
+        {
+            let drains = self.drains.read().unwrap_or_else(|e| e.into_inner());
+            if let Some(cutoff) = drains.cutoffs.get(address) {
+                return cutoff.clone();
+            }
+        }
+        let mut drains = self.drains.write().unwrap_or_else(|e| e.into_inner());
+        drains
+            .cutoffs
+            .entry(address.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// `address` just left rotation: report it and arm its drain timer, if it has a timeout.
+    /// Without a runtime (plain unit tests) there is no timer.
+    fn drain_started(&self, drains: &mut Drains, address: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_backend_drained(address, true);
+        }
+        let Some(timeout) = drains.timeouts.get(address).copied() else {
+            return;
+        };
+        let Ok(handle) = Handle::try_current() else {
+            return;
+        };
+        let timer = CancellationToken::new();
+        if let Some(old) = drains.timers.insert(address.to_string(), timer.clone()) {
+            old.cancel();
+        }
+        let registry = self.clone();
+        let address = address.to_string();
+        handle.spawn(async move {
+            tokio::select! {
+                _ = timer.cancelled() => {}
+                _ = tokio::time::sleep(timeout) => registry.drain_timed_out(&address, &timer),
+            }
+        });
+    }
+
+    /// `address` is back in rotation: report it and stop its drain timer.
+    fn drain_ended(&self, drains: &mut Drains, address: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_backend_drained(address, false);
+        }
+        if let Some(timer) = drains.timers.remove(address) {
+            timer.cancel();
+        }
+    }
+
+    fn drain_timed_out(&self, address: &str, timer: &CancellationToken) {
+        let mut drains = self.drains.write().unwrap_or_else(|e| e.into_inner());
+        // Undrained (or drained anew) while the timer fired.
+        if timer.is_cancelled() {
+            return;
+        }
+        drains.timers.remove(address);
+        if let Some(cutoff) = drains.cutoffs.remove(address) {
+            warn!(backend = %address, "drain timeout passed, cutting requests still in flight");
+            cutoff.cancel();
+        }
+    }
+
+    /// Apply `slow_start_secs` of `backends`. Backends that were not in the previous
+    /// configuration start warming up; on the first call none does.
+    pub fn configure_slow_start(&self, backends: &[Backend]) {
+        let mut slow_starts = self.slow_starts.write().unwrap_or_else(|e| e.into_inner());
+        slow_starts.windows = backends
+            .iter()
+            .filter_map(|b| {
+                let secs = b.slow_start_secs?;
+                Some((b.address.clone(), Duration::from_secs(secs)))
+            })
+            .collect();
+        let addresses: HashSet<String> = backends.iter().map(|b| b.address.clone()).collect();
+        let added: Vec<String> = match &slow_starts.known {
+            Some(known) => addresses.difference(known).cloned().collect(),
+            None => Vec::new(),
+        };
+        slow_starts.known = Some(addresses);
+        let SlowStarts { windows, since, .. } = &mut *slow_starts;
+        since.retain(|address, _| windows.contains_key(address));
+        for address in added {
+            if windows.contains_key(&address) {
+                info!(backend = %address, "backend added, slow start begins");
+                since.insert(address, Instant::now());
+            }
+        }
+    }
+
+    /// Start the slow start of `address` over, e.g. when its probe turns healthy again. No-op
+    /// for a backend without `slow_start_secs`.
+    pub fn begin_slow_start(&self, address: &str) {
+        let mut slow_starts = self.slow_starts.write().unwrap_or_else(|e| e.into_inner());
+        if slow_starts.windows.contains_key(address) {
+            info!(backend = %address, "backend recovered, slow start begins");
+            slow_starts
+                .since
+                .insert(address.to_string(), Instant::now());
+        }
+    }
+
+    /// Share of its normal traffic `address` gets: from `0.1` when its slow start begins up to
+    /// `1.0` at the end of its `slow_start_secs`, and `1.0` when it is not warming up.
+    pub fn slow_start_weight(&self, address: &str) -> f64 {
+        let slow_starts = self.slow_starts.read().unwrap_or_else(|e| e.into_inner());
+        let (Some(window), Some(since)) =
+            (slow_starts.windows.get(address), slow_starts.since.get(address))
+        else {
+            return 1.0;
+        };
+        let progress = since.elapsed().as_secs_f64() / window.as_secs_f64().max(f64::EPSILON);
+        if progress >= 1.0 {
+            return 1.0;
+        }
+        SLOW_START_INITIAL_WEIGHT + (1.0 - SLOW_START_INITIAL_WEIGHT) * progress
+    }
+
+    /// Probe result for `address`, ignoring drain; `None` when it has no health check.
+    pub fn probe_status(&self, address: &str) -> Option<bool> {
+        let map = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        map.get(address).map(|h| h.is_healthy())
+    }
+
+    /// Point-in-time `(address, healthy)` pairs for every registered backend, sorted by
+    /// address. Backs the observability server's `/health/backends` endpoint.
+    pub fn snapshot(&self) -> Vec<(String, bool)> {
+        let map = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        let mut out: Vec<(String, bool)> = map
+            .iter()
+            .map(|(addr, h)| (addr.clone(), h.is_healthy()))
+            .collect();
+        out.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
+
     /// Number of backends currently registered. Mostly useful for tests.
     pub fn len(&self) -> usize {
         self.inner.read().unwrap_or_else(|e| e.into_inner()).len()
@@ -0,0 +1,56 @@
+/// What pins a request to a backend, see [`super::BackendSelector::select_affine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity<'a> {
+    /// Token of the backend the client was pinned to (the sticky cookie value), see
+    /// [`backend_token`].
+    Token(&'a str),
+    /// Key hashed over the eligible backends (client IP or JA4).
+    Key(&'a str),
+}
+
+/// Opaque, stable token naming `address` in a sticky cookie: the backend address itself is not
+/// exposed to clients, and every proxy instance derives the same token for the same backend.
+pub fn backend_token(address: &str) -> String {
+    format!("{:016x}", fnv1a(&[address.as_bytes()]))
+}
+
+/// Rendezvous (highest random weight) choice of `key` among `candidates`. Removing a candidate
+/// only moves the keys that were on it; they come back once it returns.
+pub fn rendezvous<'a>(key: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .copied()
+        .max_by_key(|addr| fnv1a(&[key.as_bytes(), b"\0", addr.as_bytes()]))
+}
+
+/// [`rendezvous`] with backends below full `weight` (warming up, see
+/// [`HealthRegistry::slow_start_weight`](crate::HealthRegistry::slow_start_weight)) keeping only
+/// that fraction of their keys; the others go to the next candidate in the key's order. A key
+/// kept at some weight stays kept as the weight grows.
+pub fn weighted_rendezvous<'a>(
+    key: &str,
+    candidates: &[&'a str],
+    weight: impl Fn(&str) -> f64,
+) -> Option<&'a str> {
+    let mut ranked = candidates.to_vec();
+    ranked.sort_by_key(|addr| std::cmp::Reverse(fnv1a(&[key.as_bytes(), b"\0", addr.as_bytes()])));
+    ranked
+        .iter()
+        .copied()
+        .find(|addr| {
+            let share = weight(addr);
+            let draw = fnv1a(&[b"slow-start\0", key.as_bytes(), b"\0", addr.as_bytes()]);
+            share >= 1.0 || (draw as f64) < share * u64::MAX as f64
+        })
+        .or_else(|| ranked.first().copied())
+}
+
+/// 64-bit FNV-1a: stable across processes and builds, unlike `DefaultHasher`.
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
@@ -1,4 +1,6 @@
+pub mod affinity;
 pub mod round_robin;
 pub mod selector;
+pub use affinity::{backend_token, Affinity};
 pub use round_robin::RoundRobin;
 pub use selector::BackendSelector;
@@ -1,8 +1,11 @@
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
 use std::sync::RwLock;
 
 use crate::backend::health_check::HealthRegistry;
 
+use super::affinity::{backend_token, weighted_rendezvous, Affinity};
 use super::round_robin::RoundRobin;
 
 /// Selects one healthy backend among route candidates using the currently configured strategy.
@@ -30,19 +33,80 @@ impl BackendSelector {
         route_prefix: &str,
         candidates: &[&str],
         health_registry: &HealthRegistry,
+    ) -> Option<String> {
+        self.select_where(route_prefix, candidates, |addr| health_registry.is_healthy(addr))
+    }
+
+    /// Same as [`BackendSelector::select`], with an arbitrary eligibility predicate (e.g. active
+    /// health combined with circuit-breaker state, see [`crate::backend::UpstreamGateway`]).
+    pub fn select_where(
+        &self,
+        route_prefix: &str,
+        candidates: &[&str],
+        eligible: impl Fn(&str) -> bool,
+    ) -> Option<String> {
+        self.select_affine(route_prefix, candidates, None, eligible)
+    }
+
+    /// Same as [`BackendSelector::select_where`], keeping a sticky client on its backend.
+    ///
+    /// - [`Affinity::Token`] picks the eligible candidate the token names; when it is not
+    ///   eligible (or unknown) the request fails over to round-robin.
+    /// - [`Affinity::Key`] picks by rendezvous hashing over the eligible candidates.
+    pub fn select_affine(
+        &self,
+        route_prefix: &str,
+        candidates: &[&str],
+        affinity: Option<Affinity<'_>>,
+        eligible: impl Fn(&str) -> bool,
+    ) -> Option<String> {
+        self.select_weighted(route_prefix, candidates, affinity, eligible, |_| 1.0)
+    }
+
+    /// Same as [`BackendSelector::select_affine`], with candidates below full `weight` (`0.0` to
+    /// `1.0`, e.g. a backend warming up after recovery) getting only that fraction of their share.
+    ///
+    /// - Round-robin skips a weighted candidate with probability `1 - weight`, in favour of the
+    ///   next candidate that is not skipped.
+    /// - [`Affinity::Key`] keeps only a `weight` fraction of the keys that hash to a weighted
+    ///   candidate (see [`weighted_rendezvous`]).
+    /// - [`Affinity::Token`] ignores weights: pinned clients stay on their backend.
+    pub fn select_weighted(
+        &self,
+        route_prefix: &str,
+        candidates: &[&str],
+        affinity: Option<Affinity<'_>>,
+        eligible: impl Fn(&str) -> bool,
+        weight: impl Fn(&str) -> f64,
     ) -> Option<String> {
         let healthy: Vec<&str> = candidates
             .iter()
             .copied()
-            .filter(|addr| health_registry.is_healthy(addr))
+            .filter(|addr| eligible(addr))
             .collect();
 
         match healthy.len() {
             0 => None,
             1 => Some(healthy[0].to_string()),
             len => {
+                let pinned = match affinity {
+                    Some(Affinity::Token(token)) => healthy
+                        .iter()
+                        .copied()
+                        .find(|addr| backend_token(addr) == token),
+                    Some(Affinity::Key(key)) => weighted_rendezvous(key, &healthy, &weight),
+                    None => None,
+                };
+                if let Some(addr) = pinned {
+                    return Some(addr.to_string());
+                }
                 let idx = self.get_or_create_rr(route_prefix).next(len);
-                Some(healthy[idx].to_string())
+                let picked = (0..len)
+                    .filter_map(|offset| healthy.get(idx.saturating_add(offset).checked_rem(len)?))
+                    .copied()
+                    .find(|addr| kept(weight(addr)))
+                    .unwrap_or(healthy[idx]);
+                Some(picked.to_string())
             }
         }
     }
@@ -64,3 +128,17 @@ impl BackendSelector {
             .clone()
     }
 }
+
+/// Whether round-robin keeps a candidate of `weight`: always at `1.0` and above, never at `0.0`
+/// and below, otherwise with probability `weight`. The draw comes from a randomly keyed hasher,
+/// which avoids a dependency on a random number generator.
+fn kept(weight: f64) -> bool {
+    if weight >= 1.0 {
+        return true;
+    }
+    if weight <= 0.0 {
+        return false;
+    }
+    let draw = RandomState::new().build_hasher().finish();
+    (draw as f64) < weight * u64::MAX as f64
+}
@@ -1,9 +1,13 @@
+pub mod circuit_breaker;
+pub mod discovery;
 pub mod health_check;
 pub mod load_balance;
 mod upstream_gateway;
 
+pub use circuit_breaker::{Acquire, CircuitBreaker, CircuitBreakerRegistry, CircuitState};
+pub use discovery::{BackendDiscovery, DiscoveredAddrs, DiscoveryError};
 pub use health_check::{
     check_http, HealthCheckHttpClient, HealthCheckSupervisor, HealthRegistry, UpstreamHealth,
 };
-pub use load_balance::{BackendSelector, RoundRobin};
+pub use load_balance::{backend_token, Affinity, BackendSelector, RoundRobin};
 pub use upstream_gateway::UpstreamGateway;
@@ -1,19 +1,83 @@
 use std::sync::Arc;
 
-use super::{BackendSelector, HealthRegistry};
+use super::{
+    Acquire, Affinity, BackendSelector, CircuitBreaker, CircuitBreakerRegistry, CircuitState,
+    HealthRegistry,
+};
+use crate::config::Backend;
+use crate::proxy::forwarding::find_backend_config;
+use crate::telemetry::Metrics;
 
-/// Combines selection and health-gate into a single forwarding context.
+/// Combines selection, health-gate and circuit breakers into a single forwarding context.
 ///
-/// [`BackendSelector`] (round-robin algorithm) and the [`HealthRegistry`]
-/// (per-backend health state). Cheap to clone both fields are `Arc`.
+/// [`BackendSelector`] (round-robin algorithm), the [`HealthRegistry`] (per-backend health
+/// state) and the [`CircuitBreakerRegistry`] (passive ejection). Cheap to clone all fields are
+/// `Arc`.
 #[derive(Clone)]
 pub struct UpstreamGateway {
     pub health: Arc<HealthRegistry>,
     pub selector: Arc<BackendSelector>,
+    pub circuit_breakers: Arc<CircuitBreakerRegistry>,
 }
 
 impl UpstreamGateway {
-    pub fn new(health: Arc<HealthRegistry>, selector: Arc<BackendSelector>) -> Self {
-        Self { health, selector }
+    pub fn new(
+        health: Arc<HealthRegistry>,
+        selector: Arc<BackendSelector>,
+        circuit_breakers: Arc<CircuitBreakerRegistry>,
+    ) -> Self {
+        Self { health, selector, circuit_breakers }
+    }
+
+    /// Breaker of `address`, or `None` when the backend has no `circuit_breaker` config.
+    pub fn circuit_breaker(
+        &self,
+        address: &str,
+        backends: &[Backend],
+    ) -> Option<Arc<CircuitBreaker>> {
+        let config = find_backend_config(address, backends)?
+            .circuit_breaker
+            .as_ref()?;
+        Some(self.circuit_breakers.get(address, config))
+    }
+
+    /// Pick a backend among `candidates` that is both healthy and not ejected by its circuit
+    /// breaker, then claim a request slot on its breaker. `None` when no candidate is eligible.
+    /// `affinity` keeps a sticky client on its backend while that backend stays eligible. A
+    /// backend in its slow start gets its [`HealthRegistry::slow_start_weight`] of its share.
+    pub fn select(
+        &self,
+        route_prefix: &str,
+        candidates: &[&str],
+        affinity: Option<Affinity<'_>>,
+        backends: &[Backend],
+        metrics: &Metrics,
+    ) -> Option<String> {
+        let selected = self.selector.select_weighted(
+            route_prefix,
+            candidates,
+            affinity,
+            |addr| {
+                self.health.is_healthy(addr)
+                    && self
+                        .circuit_breaker(addr, backends)
+                        .is_none_or(|cb| cb.is_available())
+            },
+            |addr| self.health.slow_start_weight(addr),
+        )?;
+        if let Some(cb) = self.circuit_breaker(&selected, backends) {
+            match cb.try_acquire() {
+                Acquire::Granted => {}
+                Acquire::GrantedHalfOpen => {
+                    metrics.record_circuit_breaker_transition(
+                        &selected,
+                        CircuitState::HalfOpen.as_str(),
+                    );
+                }
+                // Lost the last half-open trial slot to a concurrent request.
+                Acquire::Denied => return None,
+            }
+        }
+        Some(selected)
     }
 }
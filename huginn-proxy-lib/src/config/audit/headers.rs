@@ -20,8 +20,9 @@ fn duplicate_header_names(headers: &[CustomHeader]) -> Vec<String> {
     dupes
 }
 
-/// Findings for one request/response header-manipulation block: names added more than once
-/// (silently last-wins at runtime) and names both added and removed (contradictory).
+/// Findings for one request/response header-manipulation block: names added or set more than
+/// once (silently last-wins at runtime), names both added and removed (contradictory) and names
+/// both set and removed (`remove` runs first, so the `set` never applies).
 fn collect_header_manipulation_warnings(
     out: &mut Vec<ConfigWarning>,
     scope: &str,
@@ -36,6 +37,14 @@ fn collect_header_manipulation_warnings(
                 ),
             });
         }
+        for name in duplicate_header_names(&group.set) {
+            out.push(ConfigWarning {
+                scope: scope.to_string(),
+                message: format!(
+                    "{direction} header '{name}' is set more than once; only the last value applies"
+                ),
+            });
+        }
         let removed: HashSet<String> = group
             .remove
             .iter()
@@ -54,6 +63,19 @@ fn collect_header_manipulation_warnings(
                 });
             }
         }
+        let mut shadowed: HashSet<String> = HashSet::new();
+        for header in &group.set {
+            let key = header.name.to_ascii_lowercase();
+            if removed.contains(&key) && shadowed.insert(key) {
+                out.push(ConfigWarning {
+                    scope: scope.to_string(),
+                    message: format!(
+                        "{direction} header '{}' is both set and removed; removal runs first, so the set never applies",
+                        header.name
+                    ),
+                });
+            }
+        }
     }
 }
 
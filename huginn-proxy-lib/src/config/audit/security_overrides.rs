@@ -1,7 +1,9 @@
 //! Audit for whole-block security overrides that silently drop parent protection.
 
 use super::ConfigWarning;
-use crate::config::{Config, IpFilterConfig, IpFilterMode, RateLimitConfig, SecurityHeaders};
+use crate::config::{
+    Config, IpFilterConfig, IpFilterMode, RateLimitConfig, SecurityHeaders, SecurityHeadersPreset,
+};
 
 /// Non-fatal audit for the whole-block override footgun.
 ///
@@ -83,15 +85,23 @@ fn collect_dropped(
     }
     if let Some(over) = over_hdr {
         let mut dropped: Vec<&str> = Vec::new();
-        if parent_hdr.hsts.enabled && !over.hsts.enabled {
+        if parent_hdr.preset != SecurityHeadersPreset::Off
+            && over.preset == SecurityHeadersPreset::Off
+        {
+            dropped.push("preset");
+        }
+        if parent_hdr.sends_hsts() && !over.sends_hsts() {
             dropped.push("HSTS");
         }
-        if parent_hdr.csp.enabled && !over.csp.enabled {
+        if parent_hdr.sends_csp() && !over.sends_csp() {
             dropped.push("CSP");
         }
         if !parent_hdr.custom.is_empty() && over.custom.is_empty() {
             dropped.push("custom headers");
         }
+        if !parent_hdr.remove.is_empty() && over.remove.is_empty() {
+            dropped.push("header removal");
+        }
         if !dropped.is_empty() {
             push(format!(
                 "headers override drops parent-enabled protections [{}] (whole block replaced, not merged)",
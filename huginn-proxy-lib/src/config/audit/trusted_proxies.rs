@@ -34,6 +34,9 @@ const IPV6_BROAD_PREFIX: u8 = 7;
 ///   `192.168/16` (RFC 1918), `fc00::/7` (RFC 4193 ULA), `fe80::/10` (link-local) — so ordinary
 ///   private ranges never warn. See the `IPV4_BROAD_PREFIX` / `IPV6_BROAD_PREFIX` consts for the
 ///   rationale. These are heuristic thresholds, not spec-mandated values.
+///
+/// It also reports an `ip_filter` with `use_forwarded_for = true` while no peer is trusted: the
+/// filter then keeps evaluating the TCP peer, which is probably not what was meant.
 pub fn trusted_proxies_warnings(cfg: &Config) -> Vec<ConfigWarning> {
     let trusted_proxies = &cfg.security.trusted_proxies;
     let mut out = Vec::new();
@@ -62,5 +65,26 @@ pub fn trusted_proxies_warnings(cfg: &Config) -> Vec<ConfigWarning> {
             });
         }
     }
+    if !trusted_proxies.has_trust() && uses_forwarded_for(cfg) {
+        out.push(ConfigWarning {
+            scope: "trusted_proxies".to_string(),
+            message: "an ip_filter sets use_forwarded_for = true but no peer is trusted; X-Forwarded-For is ignored and the TCP peer is filtered".to_string(),
+        });
+    }
     out
 }
+
+/// Whether the global, a domain or a route `ip_filter` sets `use_forwarded_for`.
+fn uses_forwarded_for(cfg: &Config) -> bool {
+    let domain_filters = cfg.domains.iter().flat_map(|domain| {
+        let domain_filter = domain.security.as_ref().and_then(|s| s.ip_filter.as_ref());
+        let route_filters = domain
+            .routes
+            .iter()
+            .filter_map(|route| route.security.as_ref().and_then(|s| s.ip_filter.as_ref()));
+        domain_filter.into_iter().chain(route_filters)
+    });
+    std::iter::once(&cfg.security.ip_filter)
+        .chain(domain_filters)
+        .any(|filter| filter.use_forwarded_for)
+}
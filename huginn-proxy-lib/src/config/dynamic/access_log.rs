@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Access log sampling for a route (`access_log` on a route).
+///
+/// Applies only when the static `[access_log]` is enabled. Each request is kept with the rate of
+/// its response class; routes without this block log every request.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RouteAccessLogConfig {
+    /// Fraction (`0.0`-`1.0`) of responses with a status below 400 that are logged (default: 1.0).
+    #[serde(default = "default_rate")]
+    pub sample_rate: f64,
+    /// Fraction (`0.0`-`1.0`) of `4xx` and `5xx` responses that are logged (default: 1.0).
+    #[serde(default = "default_rate")]
+    pub error_sample_rate: f64,
+}
+
+impl Default for RouteAccessLogConfig {
+    fn default() -> Self {
+        Self { sample_rate: default_rate(), error_sample_rate: default_rate() }
+    }
+}
+
+impl RouteAccessLogConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (name, rate) in
+            [("sample_rate", self.sample_rate), ("error_sample_rate", self.error_sample_rate)]
+        {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(ProxyError::Config(format!(
+                    "access_log.{name} must be between 0.0 and 1.0, got {rate}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sampling rate for a response with `status`.
+    pub fn rate_for(&self, status: u16) -> f64 {
+        if status >= 400 {
+            self.error_sample_rate
+        } else {
+            self.sample_rate
+        }
+    }
+
+    pub(crate) fn effective_view(&self) -> RouteAccessLogView {
+        RouteAccessLogView {
+            sample_rate: self.sample_rate,
+            error_sample_rate: self.error_sample_rate,
+        }
+    }
+}
+
+fn default_rate() -> f64 {
+    1.0
+}
+
+/// Allowlisted effective-config view of [`RouteAccessLogConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct RouteAccessLogView {
+    sample_rate: f64,
+    error_sample_rate: f64,
+}
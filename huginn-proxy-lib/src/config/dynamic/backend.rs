@@ -1,18 +1,124 @@
 use std::convert::TryFrom;
+use std::path::Path;
 
+use super::access_log::{RouteAccessLogConfig, RouteAccessLogView};
+use super::bandwidth::{BandwidthConfig, BandwidthView};
+use super::body_rewrite::{BodyRewriteConfig, BodyRewriteView};
+use super::cache::{RouteCacheConfig, RouteCacheView};
+use super::compression::{CompressionConfig, CompressionView};
+use super::connect::{ConnectConfig, ConnectView};
+use super::discovery::{DiscoveryConfig, DiscoveryView};
+use super::failover::{FailoverConfig, FailoverView};
+use super::header_match::{HeaderMatch, HeaderMatchView};
 use super::headers::{HeaderManipulation, HeaderManipulationView};
+use super::pattern::RegexPattern;
+use super::privacy::{PrivacyConfig, PrivacyView};
+use super::queue::{BackendQueueConfig, BackendQueueView};
+use super::redirect::{RedirectConfig, RedirectView};
+use super::rewrite::{PathRewriteConfig, PathRewriteView};
+use super::route_timeout::{RouteTimeoutConfig, RouteTimeoutView};
 use super::security::{DomainSecurityConfig, RouteSecurityConfig, ScopedSecurityView};
+use super::serve_static::{ServeStaticConfig, ServeStaticView};
+use super::sticky::{StickyConfig, StickyView};
+use super::synthetic::{SyntheticResponseConfig, SyntheticResponseView};
+use super::tenant::NO_TENANT_LABEL;
+use super::upstream_proxy::{BackendProxyConfig, BackendProxyView};
+use super::waf::{RouteWafConfig, RouteWafView};
+use crate::config::startup::tcp::{TcpConfig, TcpView};
 use crate::error::{ProxyError, Result};
+use http::HeaderMap;
 use serde::{Deserialize, Deserializer, Serialize};
 
 /// HTTP version preference for backend connections
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum BackendHttpVersion {
+    /// HTTP/1.1 only: one request at a time per pooled keep-alive connection, never pipelined
     Http11,
+    /// HTTP/2 only (h2c prior knowledge on plain backends): every request multiplexed on pooled
+    /// connections
     Http2,
     /// Preserve client's HTTP version (default for HTTPS)
     Preserve,
+    /// Let ALPN pick `h2` or `http/1.1` on each TLS connection; HTTP/1.1 on a plain backend
+    Auto,
+}
+
+/// JA4 fingerprint variant selectable for header injection via `ja4_variants`.
+///
+/// Each variant maps to one `x-tls-ja4*` upstream header (see
+/// [`crate::fingerprinting::names`]).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Ja4Variant {
+    /// `x-tls-ja4`: sorted, hashed (standard FoxIO JA4).
+    #[serde(rename = "ja4")]
+    Ja4,
+    /// `x-tls-ja4-r`: sorted, raw.
+    #[serde(rename = "ja4_r")]
+    Ja4R,
+    /// `x-tls-ja4-o`: original ClientHello order, hashed.
+    #[serde(rename = "ja4_o")]
+    Ja4O,
+    /// `x-tls-ja4-or`: original ClientHello order, raw.
+    #[serde(rename = "ja4_or")]
+    Ja4Or,
+    /// `x-tls-ja4-s1`: sorted, ephemeral extensions excluded, hashed.
+    #[serde(rename = "ja4_s1")]
+    Ja4S1,
+    /// `x-tls-ja4-s1r`: sorted, ephemeral extensions excluded, raw.
+    #[serde(rename = "ja4_s1r")]
+    Ja4S1r,
+}
+
+impl Ja4Variant {
+    /// Every variant, in header-injection order. Used when neither the route nor its domain
+    /// sets `ja4_variants`.
+    pub const ALL: &'static [Ja4Variant] = &[
+        Ja4Variant::Ja4,
+        Ja4Variant::Ja4R,
+        Ja4Variant::Ja4O,
+        Ja4Variant::Ja4Or,
+        Ja4Variant::Ja4S1,
+        Ja4Variant::Ja4S1r,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Ja4Variant::Ja4 => "ja4",
+            Ja4Variant::Ja4R => "ja4_r",
+            Ja4Variant::Ja4O => "ja4_o",
+            Ja4Variant::Ja4Or => "ja4_or",
+            Ja4Variant::Ja4S1 => "ja4_s1",
+            Ja4Variant::Ja4S1r => "ja4_s1r",
+        }
+    }
+}
+
+/// How fingerprints reach the backend, selectable via `fingerprint_format`.
+///
+/// `structured` and `jwt` pack every signal the proxy would otherwise send as separate headers
+/// (JA4 variants, Akamai, TCP SYN, spoofing detection, classification, `x-tls-*`) plus the client
+/// IP into the single [`names::CONTEXT`](crate::fingerprinting::names::CONTEXT) header.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FingerprintFormat {
+    /// One header per signal (default)
+    #[default]
+    Headers,
+    /// One RFC 8941 Structured Field dictionary of strings
+    Structured,
+    /// One compact HS256 JWT, signed with the active `[fingerprint.signing]` key
+    Jwt,
+}
+
+impl FingerprintFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FingerprintFormat::Headers => "headers",
+            FingerprintFormat::Structured => "structured",
+            FingerprintFormat::Jwt => "jwt",
+        }
+    }
 }
 
 /// Probing strategy for an upstream health check: TCP connect only, or HTTP `GET` over
@@ -164,15 +270,179 @@ impl HealthCheckConfig {
     }
 }
 
+/// Per-backend passive circuit breaker (opt-in: omit the table to disable).
+///
+/// Driven by live traffic rather than probes: `failure_threshold` consecutive failed requests
+/// (connection error or 5xx) open the circuit and eject the backend from selection for
+/// `open_duration_secs`. It then lets `half_open_requests` trial requests through; all must
+/// succeed to close it again, any failure re-opens it.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed requests before the circuit opens.
+    #[serde(default = "default_cb_failure_threshold")]
+    pub failure_threshold: u32,
+    /// How long an open circuit ejects the backend before trial requests are allowed, in seconds.
+    #[serde(default = "default_cb_open_duration_secs")]
+    pub open_duration_secs: u64,
+    /// Trial requests allowed while half-open; all must succeed to close the circuit.
+    #[serde(default = "default_cb_half_open_requests")]
+    pub half_open_requests: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_cb_failure_threshold(),
+            open_duration_secs: default_cb_open_duration_secs(),
+            half_open_requests: default_cb_half_open_requests(),
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.failure_threshold < 1 {
+            return Err(ProxyError::Config(
+                "circuit_breaker.failure_threshold must be at least 1".to_string(),
+            ));
+        }
+        if self.open_duration_secs == 0 {
+            return Err(ProxyError::Config(
+                "circuit_breaker.open_duration_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.half_open_requests < 1 {
+            return Err(ProxyError::Config(
+                "circuit_breaker.half_open_requests must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Per-backend upstream TLS (re-encryption). Presence of the table switches the backend to
+/// `https://`; omit it for plaintext backends.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BackendTlsConfig {
+    /// PEM bundle of CAs trusted for the backend certificate. When unset, the system trust store
+    /// is used.
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+    /// SNI sent to the backend and name its certificate is verified against. Defaults to the host
+    /// part of `address`.
+    #[serde(default)]
+    pub server_name: Option<String>,
+    /// Client certificate (PEM) presented to the backend for mutual TLS. Requires
+    /// `client_key_path`.
+    #[serde(default)]
+    pub client_cert_path: Option<String>,
+    /// Private key (PEM, PKCS#8/PKCS#1/SEC1) for `client_cert_path`.
+    #[serde(default)]
+    pub client_key_path: Option<String>,
+}
+
+impl BackendTlsConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.client_cert_path.is_some() != self.client_key_path.is_some() {
+            return Err(ProxyError::Config(
+                "tls.client_cert_path and tls.client_key_path must both be set or both omitted"
+                    .to_string(),
+            ));
+        }
+        if let Some(name) = &self.server_name {
+            rustls_pki_types::ServerName::try_from(name.as_str()).map_err(|e| {
+                ProxyError::Config(format!("tls.server_name '{name}' is not a valid name: {e}"))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Per-backend connection pool (`[backends.pool]`). Presence of the table gives the backend its
+/// own pooled clients; unset idle settings inherit `[backend_pool]`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct BackendPoolLimits {
+    /// Maximum idle connections kept open to this backend. Unset inherits
+    /// `backend_pool.pool_max_idle_per_host`.
+    #[serde(default)]
+    pub max_idle: Option<usize>,
+    /// Seconds an idle connection is kept before it is closed. Unset inherits
+    /// `backend_pool.idle_timeout`.
+    #[serde(default)]
+    pub idle_timeout: Option<u64>,
+    /// Maximum HTTP/1.1 requests in flight to this backend, i.e. open connections in use.
+    /// Unset means unlimited.
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+    /// Maximum HTTP/2 streams in flight to this backend. Enforced by the proxy on top of the
+    /// backend's own `SETTINGS_MAX_CONCURRENT_STREAMS`. Unset means unlimited.
+    #[serde(default)]
+    pub max_concurrent_streams: Option<usize>,
+    /// How long a request waits for a free connection or stream once a limit is reached, in
+    /// milliseconds. Past it the request fails with `503`. Default: 1000.
+    #[serde(default = "default_pool_wait_timeout_ms")]
+    pub wait_timeout_ms: u64,
+}
+
+impl Default for BackendPoolLimits {
+    fn default() -> Self {
+        Self {
+            max_idle: None,
+            idle_timeout: None,
+            max_connections: None,
+            max_concurrent_streams: None,
+            wait_timeout_ms: default_pool_wait_timeout_ms(),
+        }
+    }
+}
+
+fn default_pool_wait_timeout_ms() -> u64 {
+    1000
+}
+
+impl BackendPoolLimits {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_connections == Some(0) {
+            return Err(ProxyError::Config(
+                "pool.max_connections must be greater than 0 (omit it for no limit)".to_string(),
+            ));
+        }
+        if self.max_concurrent_streams == Some(0) {
+            return Err(ProxyError::Config(
+                "pool.max_concurrent_streams must be greater than 0 (omit it for no limit)"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Effective idle settings: this backend's own, falling back to `[backend_pool]`.
+    pub fn resolve(&self, shared: &BackendPoolConfig) -> BackendPoolConfig {
+        BackendPoolConfig {
+            enabled: shared.enabled,
+            idle_timeout: self.idle_timeout.unwrap_or(shared.idle_timeout),
+            pool_max_idle_per_host: self.max_idle.unwrap_or(shared.pool_max_idle_per_host),
+            http2: shared.http2,
+            tcp: shared.tcp,
+        }
+    }
+}
+
 /// Backend server configuration
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Backend {
-    /// Backend server address (host:port format)
-    /// Example: "backend-1:9000" or "192.168.1.10:8080"
+    /// Backend server address (host:port format), a unix socket as `unix:<path>`, or the name of
+    /// a service whose members come from `discovery`
+    /// Example: "backend-1:9000", "192.168.1.10:8080" or "unix:///var/run/app.sock"
     pub address: String,
     /// HTTP version to use when connecting to this backend
-    /// Options: "http11", "http2", "preserve" (default: "preserve" for HTTPS, "http11" for HTTP)
+    /// Options: "http11", "http2", "preserve", "auto" (ALPN decides on TLS backends, HTTP/1.1 on
+    /// plain ones)
+    /// (default: "http11")
     #[serde(default)]
     pub http_version: Option<BackendHttpVersion>,
     /// Optional per-backend active probe (opt-in).
@@ -180,17 +450,221 @@ pub struct Backend {
     /// When `None`, this backend is not health-gated at proxy level and is treated as healthy by default.
     #[serde(default)]
     pub health_check: Option<HealthCheckConfig>,
+    /// Passive circuit breaker (optional). When `None`, traffic results never eject this backend.
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Upstream TLS (optional). When `None`, the backend is reached over plain `http://`.
+    #[serde(default)]
+    pub tls: Option<BackendTlsConfig>,
+    /// Connection pool limits for this backend (optional). When `None`, the backend shares the
+    /// pool configured by `[backend_pool]` and has no concurrency limit.
+    #[serde(default)]
+    pub pool: Option<BackendPoolLimits>,
+    /// Maximum requests in flight to this backend (optional). Past it, requests routed to the
+    /// backend wait in `queue`, or without one are answered `503` with `Retry-After` right away.
+    /// `None` means unlimited.
+    #[serde(default)]
+    pub max_in_flight: Option<usize>,
+    /// Priority wait queue for requests over `max_in_flight` (optional). Requires
+    /// `max_in_flight`.
+    #[serde(default)]
+    pub queue: Option<BackendQueueConfig>,
+    /// Address discovery (optional). When `None`, the hostname is resolved per new connection
+    /// and only the first reachable address is used. With a service provider (`static`, `srv`,
+    /// `consul`), `address` is the logical service name and the provider supplies the members.
+    #[serde(default)]
+    pub discovery: Option<DiscoveryConfig>,
+    /// Take the backend out of rotation: routes stop selecting it, requests already in flight
+    /// finish. Applied on every load and hot reload; the admin API can drain on top of it but
+    /// cannot undrain it.
+    #[serde(default)]
+    pub drain: bool,
+    /// Seconds requests in flight to the backend may still run once it is drained (by `drain`
+    /// or the admin API); past it their responses and WebSocket tunnels are cut. `None` lets them
+    /// finish however long they take.
+    #[serde(default)]
+    pub drain_timeout_secs: Option<u64>,
+    /// Seconds over which the backend's share of traffic ramps from 10% to 100% when it recovers
+    /// from unhealthy or is added by a hot reload. `None` gives it a full share right away.
+    #[serde(default)]
+    pub slow_start_secs: Option<u64>,
+    /// Built-in echo backend: the proxy answers requests routed here itself, with the request
+    /// (method, path, headers as a backend would receive them, protocols) as JSON. `address` is
+    /// only the name routes reference; nothing is connected to.
+    #[serde(default)]
+    pub echo: bool,
+    /// TCP socket options of connections to this backend (optional). Unset options inherit
+    /// `[backend_pool.tcp]`; a table gives the backend its own pooled clients.
+    #[serde(default)]
+    pub tcp: Option<TcpConfig>,
+    /// SOCKS5 proxy to reach this backend through (optional). `None` connects directly; a table
+    /// gives the backend its own pooled clients.
+    #[serde(default)]
+    pub proxy: Option<BackendProxyConfig>,
+    /// Body bandwidth shaping of requests to this backend (optional). `None` means unshaped;
+    /// a route's own `bandwidth` applies on top.
+    #[serde(default)]
+    pub bandwidth: Option<BandwidthConfig>,
+    /// Name of the `[[tenants]]` entry that declares this backend; `None` for a top-level
+    /// backend. Set at load time: only routes of the same tenant's domains may use it.
+    #[serde(skip)]
+    pub tenant: Option<String>,
+}
+
+/// Path of the unix socket named by an address in `unix:` form (`unix:/var/run/app.sock` or
+/// `unix:///var/run/app.sock`); `None` for a `host:port` address.
+pub fn unix_socket_path(address: &str) -> Option<&Path> {
+    let rest = address.strip_prefix("unix:")?;
+    Some(Path::new(rest.strip_prefix("//").unwrap_or(rest)))
+}
+
+impl Backend {
+    /// Backend at `address` with every other setting at its default.
+    pub fn new(address: impl Into<String>) -> Self {
+        Self { address: address.into(), ..Self::default() }
+    }
+
+    /// Unix socket this backend is reached through, when `address` uses the `unix:` form.
+    pub fn unix_socket_path(&self) -> Option<&Path> {
+        unix_socket_path(&self.address)
+    }
+
+    /// Reject a zero `max_in_flight`, `drain_timeout_secs` or `slow_start_secs`, a `queue`
+    /// without `max_in_flight`, invalid `discovery` or `proxy` (and the two together),
+    /// connection settings on an `echo` backend and
+    /// settings a unix socket backend cannot honor: upstream TLS, `tcp`, `proxy` and HTTP health
+    /// checks.
+    pub fn validate(&self) -> Result<()> {
+        if self.max_in_flight == Some(0) {
+            return Err(ProxyError::Config(format!(
+                "Backend '{}': max_in_flight must be greater than 0",
+                self.address
+            )));
+        }
+        if self.drain_timeout_secs == Some(0) {
+            return Err(ProxyError::Config(format!(
+                "Backend '{}': drain_timeout_secs must be greater than 0",
+                self.address
+            )));
+        }
+        if self.slow_start_secs == Some(0) {
+            return Err(ProxyError::Config(format!(
+                "Backend '{}': slow_start_secs must be greater than 0",
+                self.address
+            )));
+        }
+        if self.echo {
+            let connection_setting = [
+                ("health_check", self.health_check.is_some()),
+                ("tls", self.tls.is_some()),
+                ("pool", self.pool.is_some()),
+                ("discovery", self.discovery.is_some()),
+                ("tcp", self.tcp.is_some()),
+                ("proxy", self.proxy.is_some()),
+            ]
+            .into_iter()
+            .find_map(|(key, set)| set.then_some(key));
+            if let Some(key) = connection_setting {
+                return Err(ProxyError::Config(format!(
+                    "Backend '{}': {key} cannot be set on an echo backend",
+                    self.address
+                )));
+            }
+            if self.unix_socket_path().is_some() {
+                return Err(ProxyError::Config(format!(
+                    "Backend '{}': an echo backend needs a name, not a unix socket",
+                    self.address
+                )));
+            }
+        }
+        if let Some(tcp) = &self.tcp {
+            tcp.validate(&format!("Backend '{}': tcp", self.address))?;
+        }
+        if let Some(bandwidth) = &self.bandwidth {
+            bandwidth.validate(&format!("Backend '{}': bandwidth", self.address))?;
+        }
+        if let Some(queue) = &self.queue {
+            queue.validate(&self.address)?;
+            if self.max_in_flight.is_none() {
+                return Err(ProxyError::Config(format!(
+                    "Backend '{}': queue requires max_in_flight",
+                    self.address
+                )));
+            }
+        }
+        if let Some(proxy) = &self.proxy {
+            proxy.validate(&self.address)?;
+            if self.discovery.is_some() {
+                return Err(ProxyError::Config(format!(
+                    "Backend '{}': proxy cannot be combined with discovery; the proxy dials the \
+                     backend address itself",
+                    self.address
+                )));
+            }
+        }
+        if let Some(discovery) = &self.discovery {
+            discovery.validate(&self.address)?;
+            if discovery.is_service() && self.health_check.is_some() {
+                return Err(ProxyError::Config(format!(
+                    "Backend '{}': health_check cannot be combined with a discovered service; \
+                     its health follows the discovery provider",
+                    self.address
+                )));
+            }
+        }
+        let Some(path) = self.unix_socket_path() else {
+            return Ok(());
+        };
+        if path.as_os_str().is_empty() {
+            return Err(ProxyError::Config(format!(
+                "Backend '{}': unix socket path is empty",
+                self.address
+            )));
+        }
+        if self.tls.is_some() {
+            return Err(ProxyError::Config(format!(
+                "Backend '{}': tls is not supported for unix socket backends",
+                self.address
+            )));
+        }
+        if self.tcp.is_some() {
+            return Err(ProxyError::Config(format!(
+                "Backend '{}': tcp is not supported for unix socket backends",
+                self.address
+            )));
+        }
+        if self.proxy.is_some() {
+            return Err(ProxyError::Config(format!(
+                "Backend '{}': proxy is not supported for unix socket backends",
+                self.address
+            )));
+        }
+        if self
+            .health_check
+            .as_ref()
+            .is_some_and(|hc| matches!(hc.check_type, HealthCheckType::Http { .. }))
+        {
+            return Err(ProxyError::Config(format!(
+                "Backend '{}': unix socket backends support only tcp (connect) health checks",
+                self.address
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// Route configuration for path-based routing
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Route {
     /// URL path prefix to match (e.g., "/api", "/static")
-    /// The most specific (longest) matching prefix wins; declaration order does not matter
+    /// The most specific (longest) matching prefix wins; declaration order does not matter.
+    /// Also names the route in metrics, logs and the admin API, including with `exact`/`regex`.
     pub prefix: String,
     /// Backend address to route matching requests to
-    /// Must match one of the backend addresses defined in `backends`
+    /// Must match one of the backend addresses defined in `backends`. Optional (empty) on
+    /// `serve_static` routes only.
+    #[serde(default)]
     pub backend: String,
     /// Enable fingerprint header **injection** for this route (whole-block override).
     /// `None` (unset) inherits the domain's `fingerprinting`, then the built-in default `true`.
@@ -198,6 +672,21 @@ pub struct Route {
     /// global `[fingerprint]` config.
     #[serde(default)]
     pub fingerprinting: Option<bool>,
+    /// JA4 variants injected as `x-tls-ja4*` headers when fingerprinting is on (whole-block
+    /// override). `None` (unset) inherits the domain's `ja4_variants`, then every variant.
+    /// An empty list injects no JA4 header while keeping HTTP/2 and TCP SYN headers.
+    #[serde(default)]
+    pub ja4_variants: Option<Vec<Ja4Variant>>,
+    /// How fingerprints are sent upstream (whole-block override). `None` (unset) inherits the
+    /// domain's `fingerprint_format`, then `headers`.
+    #[serde(default)]
+    pub fingerprint_format: Option<FingerprintFormat>,
+    /// Inject `x-huginn-net-os`: the operating system guessed by matching the client's TCP SYN
+    /// signature against the bundled p0f-style database, with the match confidence. Needs TCP
+    /// SYN capture (`fingerprint.tcp_enabled`) and fingerprinting on for the route.
+    /// Default: false
+    #[serde(default)]
+    pub tcp_os: bool,
     /// Force a new TCP/TLS connection from the proxy to the backend for each request,
     /// bypassing the backend connection pool.
     /// Note: this does not affect the client→proxy TLS session or JA4 fingerprints,
@@ -212,6 +701,23 @@ pub struct Route {
     /// Example: prefix = "/api", replace_path = "" (or "/")
     ///   Request: /api/users → Backend: /users (path stripping)
     pub replace_path: Option<String>,
+    /// Regex rewrite of the path and query sent to the backend (optional), e.g.
+    /// `^/v(\d+)/(.*)$` → `/api/$2?version=$1`. Exclusive with `replace_path`.
+    #[serde(default)]
+    pub rewrite: Option<PathRewriteConfig>,
+    /// Forward the client's `Host` to the backend on this route (whole-value override of the
+    /// global `preserve_host`). `None` (unset) inherits the global setting.
+    #[serde(default)]
+    pub preserve_host: Option<bool>,
+    /// `Host` sent to the backend on this route, e.g. the hostname a vendor backend requires
+    /// (optional). Replaces both the client's host and the backend address; exclusive with
+    /// `preserve_host = true`.
+    #[serde(default)]
+    pub host_rewrite: Option<String>,
+    /// Retry idempotent requests on the route's next backend when the selected one answers with
+    /// one of the configured statuses or headers (optional).
+    #[serde(default)]
+    pub failover: Option<FailoverConfig>,
     /// Per-route security policy override (optional). Currently carries `rate_limit`.
     /// Overlays onto the domain-effective (or global) policy.
     #[serde(default)]
@@ -220,15 +726,371 @@ pub struct Route {
     /// Allows adding or removing headers for specific routes
     #[serde(default)]
     pub headers: Option<HeaderManipulation>,
+    /// WebSocket (HTTP/1.1 `Upgrade`) proxying for this route.
+    /// Default: disabled (upgrade requests are forwarded as plain requests)
+    #[serde(default)]
+    pub websocket: WebSocketConfig,
+    /// Application protocol spoken on this route.
+    /// `grpc` forces HTTP/2 to the backend (h2c for plain backends) and records `grpc-status`.
+    /// Default: `http`
+    #[serde(default)]
+    pub protocol: RouteProtocol,
+    /// External authorization service consulted before forwarding (optional).
+    /// When `None`, requests on this route are not sent to an authorization service.
+    #[serde(default)]
+    pub ext_authz: Option<ExtAuthzConfig>,
+    /// Maximum request body size in bytes (optional).
+    /// A larger declared `Content-Length` is answered `413` before forwarding; a streamed body
+    /// is cut off once it crosses the limit. `None` means unlimited.
+    #[serde(default)]
+    pub max_request_body_bytes: Option<u64>,
+    /// Maximum backend response body size in bytes (optional).
+    /// A larger declared `Content-Length` is answered `502`; a streamed body is cut off once it
+    /// crosses the limit. `None` means unlimited.
+    #[serde(default)]
+    pub max_response_body_bytes: Option<u64>,
+    /// Response compression for this route (whole-block override of the global
+    /// `[compression]`). `None` inherits the global block.
+    #[serde(default)]
+    pub compression: Option<CompressionConfig>,
+    /// Response caching for this route (optional). `None` means responses are not cached.
+    /// Storage is shared and sized by the static `[cache]` block.
+    #[serde(default)]
+    pub cache: Option<RouteCacheConfig>,
+    /// Access log sampling for this route (optional). `None` logs every request when the static
+    /// `[access_log]` is enabled.
+    #[serde(default)]
+    pub access_log: Option<RouteAccessLogConfig>,
+    /// Maximum requests in flight on this route (optional). Past it, requests are answered `503`
+    /// with `Retry-After` right away instead of queueing. `None` means unlimited.
+    #[serde(default)]
+    pub max_in_flight: Option<usize>,
+    /// Priority of this route's requests under overload. `[load_shedding]` rejects requests of
+    /// routes at or below its `shed_priority` first.
+    /// Default: `normal`
+    #[serde(default)]
+    pub priority: RoutePriority,
+    /// Priorities by request header, replacing `priority` for the requests that match; the first
+    /// matching entry wins (e.g. paid customers by an `x-plan` header).
+    #[serde(default)]
+    pub priority_headers: Vec<PriorityHeader>,
+    /// Backend connect, first-byte and total timeouts for this route (optional). `None` keeps
+    /// the global `[timeout]` behavior.
+    #[serde(default)]
+    pub timeout: Option<RouteTimeoutConfig>,
+    /// Session affinity across the backends of this route's prefix (optional). `None` keeps
+    /// round-robin.
+    #[serde(default)]
+    pub sticky: Option<StickyConfig>,
+    /// Response served by the proxy instead of a backend, e.g. a maintenance page (optional).
+    #[serde(default)]
+    pub synthetic: Option<SyntheticResponseConfig>,
+    /// Forward-proxy tunnels for `CONNECT` requests (optional). `None` leaves CONNECT requests
+    /// unrouted.
+    #[serde(default)]
+    pub connect: Option<ConnectConfig>,
+    /// Header allowlist, removal and hashing of the traffic exchanged with the backend, e.g. for
+    /// a third-party backend (optional). `None` forwards headers as they are.
+    #[serde(default)]
+    pub privacy: Option<PrivacyConfig>,
+    /// Find/replace in HTML and JSON response bodies, e.g. backend URLs to the public hostname
+    /// (optional). `None` forwards bodies as they are.
+    #[serde(default)]
+    pub body_rewrite: Option<BodyRewriteConfig>,
+    /// Serve files from a directory instead of a backend (optional). The route needs no
+    /// `backend`; one that is set is not contacted for the route's requests.
+    #[serde(default)]
+    pub serve_static: Option<ServeStaticConfig>,
+    /// Match only the path equal to `prefix`, not its sub-paths.
+    /// Default: false
+    #[serde(default)]
+    pub exact: bool,
+    /// Regex the whole request path must also match (optional), e.g. `^/users/[0-9]+$`.
+    /// Compiled when the config loads; combine with `prefix = "/"` to match on the regex alone.
+    #[serde(default)]
+    pub regex: Option<RegexPattern>,
+    /// Routes with a higher `match_priority` are tried first, before the longest-prefix order.
+    /// Default: 0
+    #[serde(default)]
+    pub match_priority: i32,
+    /// HTTP methods this route serves, e.g. `["POST", "PUT"]`. Empty (default) serves any method.
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Request header conditions that must all hold (presence, exact value or regex).
+    #[serde(default)]
+    pub match_headers: Vec<HeaderMatch>,
+    /// Turns `[security.waf]` inspection on or off for this route (optional). `None` follows
+    /// `security.waf.enabled`.
+    #[serde(default)]
+    pub waf: Option<RouteWafConfig>,
+    /// Body bandwidth shaping of this route (optional). `None` means unshaped.
+    #[serde(default)]
+    pub bandwidth: Option<BandwidthConfig>,
+}
+
+impl Route {
+    /// Route forwarding requests under `prefix` to the backend at `backend`, with every other
+    /// setting at its default.
+    pub fn new(prefix: impl Into<String>, backend: impl Into<String>) -> Self {
+        Self { prefix: prefix.into(), backend: backend.into(), ..Self::default() }
+    }
+
+    /// Whether `other` matches exactly the same requests, i.e. belongs to the same
+    /// load-balance group.
+    pub fn same_matcher(&self, other: &Route) -> bool {
+        self.prefix == other.prefix
+            && self.exact == other.exact
+            && self.regex == other.regex
+            && self.match_priority == other.match_priority
+            && self.methods == other.methods
+            && self.match_headers == other.match_headers
+    }
+
+    /// Whether `self` matches every request `other` matches: it has no `regex`, `methods` or
+    /// `match_headers`, and its prefix covers `other`'s.
+    pub fn covers(&self, other: &Route) -> bool {
+        if self.regex.is_some() || !self.methods.is_empty() || !self.match_headers.is_empty() {
+            return false;
+        }
+        if self.exact {
+            other.exact && other.prefix == self.prefix
+        } else {
+            crate::proxy::router::prefix_matches(&other.prefix, &self.prefix)
+        }
+    }
+
+    /// Priority of a request on this route: the first matching `priority_headers` entry's, or
+    /// `priority`.
+    pub fn request_priority(&self, headers: &HeaderMap) -> RoutePriority {
+        self.priority_headers
+            .iter()
+            .find(|p| p.header.matches(headers))
+            .map_or(self.priority, |p| p.priority)
+    }
+
+    /// Number of method and header conditions; routes with more of them are tried first.
+    fn condition_count(&self) -> usize {
+        usize::from(!self.methods.is_empty()).saturating_add(self.match_headers.len())
+    }
+
+    /// Specificity of the matcher among routes with the same prefix: exact, then regex, then
+    /// plain prefix.
+    fn matcher_rank(&self) -> u8 {
+        match (self.exact, &self.regex) {
+            (true, _) => 0,
+            (false, Some(_)) => 1,
+            (false, None) => 2,
+        }
+    }
+}
+
+/// Application protocol of a route.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteProtocol {
+    /// Plain HTTP: the backend version follows `http_version` (default)
+    #[default]
+    Http,
+    /// gRPC: always HTTP/2 upstream, trailers forwarded, `grpc-status` surfaced in metrics
+    Grpc,
+}
+
+impl RouteProtocol {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RouteProtocol::Http => "http",
+            RouteProtocol::Grpc => "grpc",
+        }
+    }
+}
+
+/// A request header that sets the priority of a route's requests (`priority_headers` on a
+/// route).
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PriorityHeader {
+    /// Condition the request must meet, as in `match_headers`.
+    pub header: HeaderMatch,
+    /// Priority of the requests that meet it.
+    pub priority: RoutePriority,
+}
+
+impl PriorityHeader {
+    fn effective_view(&self) -> PriorityHeaderView<'_> {
+        PriorityHeaderView {
+            header: self.header.effective_view(),
+            priority: self.priority.as_str(),
+        }
+    }
+}
+
+/// Priority of a route's requests under overload, lowest first.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RoutePriority {
+    /// Shed first (e.g. batch jobs, prefetches)
+    Low,
+    /// Default priority
+    #[default]
+    Normal,
+    /// Shed last (e.g. login, checkout)
+    High,
+}
+
+impl RoutePriority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RoutePriority::Low => "low",
+            RoutePriority::Normal => "normal",
+            RoutePriority::High => "high",
+        }
+    }
+}
+
+/// Per-route WebSocket proxying.
+///
+/// When enabled, an HTTP/1.1 request carrying `Connection: upgrade` and `Upgrade: websocket` is
+/// forwarded to the backend over HTTP/1.1; if the backend answers `101 Switching Protocols`
+/// both connections are spliced and bytes are copied in both directions until either side
+/// closes or the tunnel stays idle for `idle_timeout_secs`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WebSocketConfig {
+    /// Accept upgrade requests on this route (default: false).
+    #[serde(default)]
+    pub enabled: bool,
+    /// Close an upgraded connection after this many seconds without traffic in either
+    /// direction (default: 300).
+    #[serde(default = "default_websocket_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self { enabled: false, idle_timeout_secs: default_websocket_idle_timeout_secs() }
+    }
+}
+
+impl WebSocketConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.idle_timeout_secs == 0 {
+            return Err(ProxyError::Config(
+                "websocket.idle_timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_websocket_idle_timeout_secs() -> u64 {
+    300
+}
+
+/// Per-route external authorization (`[domains.routes.ext_authz]`).
+///
+/// Before forwarding, the request's method, URI and headers (including the injected
+/// fingerprint and `X-Forwarded-*` headers) are sent to `url` as a body-less `GET`. A `2xx`
+/// answer lets the request through; any other answer is relayed to the client. A transport
+/// error or a `timeout_ms` expiry follows `failure_mode`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ExtAuthzConfig {
+    /// Authorization endpoint, `http://host:port/path`.
+    pub url: String,
+    /// Budget for the whole check: connect, response and denial body (default: 1000).
+    #[serde(default = "default_ext_authz_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Outcome when the service cannot be reached or times out (default: `deny`).
+    #[serde(default)]
+    pub failure_mode: ExtAuthzFailureMode,
+    /// Status returned to the client when `failure_mode = "deny"` and the check failed
+    /// (default: 403).
+    #[serde(default = "default_ext_authz_status_on_error")]
+    pub status_on_error: u16,
+    /// Headers copied from a `2xx` authorization response onto the forwarded request
+    /// (e.g. `x-user-id`). Client-supplied copies are replaced.
+    #[serde(default)]
+    pub upstream_headers: Vec<String>,
+}
+
+/// What to do when the authorization service fails.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtAuthzFailureMode {
+    /// Fail closed: answer with `status_on_error` (default)
+    #[default]
+    Deny,
+    /// Fail open: forward the request as if it was allowed
+    Allow,
+}
+
+impl ExtAuthzFailureMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExtAuthzFailureMode::Deny => "deny",
+            ExtAuthzFailureMode::Allow => "allow",
+        }
+    }
+}
+
+impl ExtAuthzConfig {
+    pub fn validate(&self) -> Result<()> {
+        let uri: http::Uri = self.url.parse().map_err(|e| {
+            ProxyError::Config(format!("ext_authz.url '{}' is not a valid URI: {e}", self.url))
+        })?;
+        if uri.scheme_str() != Some("http") || uri.authority().is_none() {
+            return Err(ProxyError::Config(format!(
+                "ext_authz.url '{}' must be an absolute http:// URL",
+                self.url
+            )));
+        }
+        if self.timeout_ms == 0 {
+            return Err(ProxyError::Config(
+                "ext_authz.timeout_ms must be greater than 0".to_string(),
+            ));
+        }
+        if !(400..=599).contains(&self.status_on_error) {
+            return Err(ProxyError::Config(format!(
+                "ext_authz.status_on_error must be a 4xx or 5xx status, got {}",
+                self.status_on_error
+            )));
+        }
+        for name in &self.upstream_headers {
+            http::HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+                ProxyError::Config(format!(
+                    "ext_authz.upstream_headers: invalid name '{name}': {e}"
+                ))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn default_ext_authz_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_ext_authz_status_on_error() -> u16 {
+    403
 }
 
-/// Sort routes longest-prefix first so `pick_route` can use an early-terminating `find`.
+/// Sort routes in match order so `pick_route` can use an early-terminating `find`: highest
+/// `match_priority` first, then longest prefix, then exact before regex before plain prefix,
+/// then more `methods`/`match_headers` conditions before fewer.
 ///
-/// Stable sort preserves declaration order within same-length prefixes, which matters
-/// for load-balance groups that share the same prefix (round-robin candidates).
+/// Stable sort preserves declaration order within equal keys, which matters for load-balance
+/// groups that share the same prefix (round-robin candidates) and for regex routes, which are
+/// tried in declaration order.
 /// Call this once at config load time via `Config::into_parts`; do not call per-request.
 pub fn sort_routes(routes: &mut [Route]) {
-    routes.sort_by_key(|r| std::cmp::Reverse(r.prefix.len()));
+    routes.sort_by_key(|r| {
+        (
+            std::cmp::Reverse(r.match_priority),
+            std::cmp::Reverse(r.prefix.len()),
+            r.matcher_rank(),
+            std::cmp::Reverse(r.condition_count()),
+        )
+    });
 }
 
 /// Identifier used for the catch-all (host-less) domain, the entry with `host: None`
@@ -243,7 +1105,7 @@ pub const DEFAULT_DOMAIN_LABEL: &str = "_default_";
 ///
 /// `cert_path` / `key_path` are optional, omit both for plain-HTTP domains.
 /// Both must be present together; specifying only one is a validation error.
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Domain {
     /// Domain pattern used for SNI matching and routing.
@@ -272,9 +1134,44 @@ pub struct Domain {
     /// `None` (unset) means the built-in default `true`; a route's own `fingerprinting` overrides it.
     #[serde(default)]
     pub fingerprinting: Option<bool>,
+    /// Default JA4 variants injected for routes in this domain (whole-block override).
+    /// `None` (unset) means every variant; a route's own `ja4_variants` overrides it.
+    #[serde(default)]
+    pub ja4_variants: Option<Vec<Ja4Variant>>,
+    /// Default fingerprint format for routes in this domain (whole-block override).
+    /// `None` (unset) means `headers`; a route's own `fingerprint_format` overrides it.
+    #[serde(default)]
+    pub fingerprint_format: Option<FingerprintFormat>,
+    /// Redirects for this domain's hosts, evaluated before its routes (whole-block override of
+    /// the global `[redirect]`).
+    #[serde(default)]
+    pub redirect: Option<RedirectConfig>,
     /// Path-based routing rules scoped to this domain.
     #[serde(default)]
     pub routes: Vec<Route>,
+    /// Name of the `[[tenants]]` entry that declares this domain; `None` for a top-level domain.
+    /// Set at load time, it cannot be configured on the domain itself.
+    #[serde(skip)]
+    pub tenant: Option<String>,
+}
+
+/// Contents of an `include` file: routes for domains declared in the main config.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteFragment {
+    #[serde(default)]
+    pub domains: Vec<DomainRoutes>,
+}
+
+/// Routes an `include` file adds to the main-config domain with the same `host`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainRoutes {
+    /// Host of the target domain; omitted for the catch-all domain.
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub routes: Vec<Route>,
 }
 
 impl Domain {
@@ -283,6 +1180,12 @@ impl Domain {
     pub fn label(&self) -> &str {
         self.host.as_deref().unwrap_or(DEFAULT_DOMAIN_LABEL)
     }
+
+    /// Identifier of this domain's tenant in metrics labels: its name, or [`NO_TENANT_LABEL`]
+    /// (`"_none_"`) for a top-level domain.
+    pub fn tenant_label(&self) -> &str {
+        self.tenant.as_deref().unwrap_or(NO_TENANT_LABEL)
+    }
 }
 
 /// Sort routes within every domain longest-prefix first.
@@ -317,6 +1220,14 @@ pub struct BackendPoolConfig {
     /// Default: 0 (unlimited)
     #[serde(default)]
     pub pool_max_idle_per_host: usize,
+
+    /// HTTP/2 settings of backend connections
+    #[serde(default)]
+    pub http2: BackendHttp2Config,
+
+    /// TCP socket options of backend connections; `[backends.tcp]` overrides them per backend
+    #[serde(default)]
+    pub tcp: TcpConfig,
 }
 
 impl Default for BackendPoolConfig {
@@ -325,10 +1236,72 @@ impl Default for BackendPoolConfig {
             enabled: true,
             idle_timeout: default_backend_pool_idle_timeout(),
             pool_max_idle_per_host: 0,
+            http2: BackendHttp2Config::default(),
+            tcp: TcpConfig::default(),
         }
     }
 }
 
+impl BackendPoolConfig {
+    pub fn validate(&self) -> Result<()> {
+        self.http2.validate()?;
+        self.tcp.validate("backend_pool.tcp")
+    }
+}
+
+/// HTTP/2 settings of backend connections (`[backend_pool.http2]`). Unset values keep hyper's
+/// defaults; keep-alive pings are off unless `keep_alive_interval_ms` is set.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BackendHttp2Config {
+    /// `SETTINGS_INITIAL_WINDOW_SIZE`: bytes a backend may send on one stream before the proxy
+    /// reads them
+    #[serde(default)]
+    pub initial_stream_window_size: Option<u32>,
+    /// Flow-control window of the whole connection, in bytes
+    #[serde(default)]
+    pub initial_connection_window_size: Option<u32>,
+    /// Size both windows from the measured bandwidth-delay product instead
+    /// Default: false
+    #[serde(default)]
+    pub adaptive_window: bool,
+    /// `SETTINGS_MAX_FRAME_SIZE`: largest frame payload the proxy accepts, in bytes
+    #[serde(default)]
+    pub max_frame_size: Option<u32>,
+    /// Milliseconds between keep-alive pings. Unset sends none
+    #[serde(default)]
+    pub keep_alive_interval_ms: Option<u64>,
+    /// Milliseconds to wait for a ping acknowledgement before closing the connection
+    /// Default: 20000 (hyper's)
+    #[serde(default)]
+    pub keep_alive_timeout_ms: Option<u64>,
+    /// Also ping connections with no request in flight, so idle pooled connections to a dead
+    /// backend are noticed
+    /// Default: false
+    #[serde(default)]
+    pub keep_alive_while_idle: bool,
+}
+
+impl BackendHttp2Config {
+    pub fn validate(&self) -> Result<()> {
+        crate::config::startup::http2::validate_flow_control(
+            "backend_pool.http2",
+            self.initial_stream_window_size,
+            self.initial_connection_window_size,
+            self.adaptive_window,
+            self.max_frame_size,
+        )?;
+        if self.keep_alive_interval_ms == Some(0) || self.keep_alive_timeout_ms == Some(0) {
+            return Err(ProxyError::Config(
+                "backend_pool.http2.keep_alive_interval_ms and keep_alive_timeout_ms must be \
+                 greater than 0"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
 /// Built-in default for fingerprint header **injection** when neither the route nor its domain sets `fingerprinting`.
 pub const DEFAULT_FINGERPRINTING: bool = true;
 
@@ -340,12 +1313,61 @@ fn default_backend_pool_idle_timeout() -> u64 {
     90
 }
 
+fn default_cb_failure_threshold() -> u32 {
+    5
+}
+
+fn default_cb_open_duration_secs() -> u64 {
+    30
+}
+
+fn default_cb_half_open_requests() -> u32 {
+    1
+}
+
 /// Allowlisted effective-config view of [`Backend`]. Field names are the JSON keys.
 #[derive(Serialize)]
 pub(crate) struct BackendView<'a> {
     address: &'a str,
     http_version: Option<&'static str>,
     health_check: Option<HealthCheckView<'a>>,
+    circuit_breaker: Option<CircuitBreakerView>,
+    tls: Option<BackendTlsView<'a>>,
+    pool: Option<BackendPoolLimitsView>,
+    max_in_flight: Option<usize>,
+    queue: Option<BackendQueueView>,
+    discovery: Option<DiscoveryView<'a>>,
+    drain: bool,
+    drain_timeout_secs: Option<u64>,
+    slow_start_secs: Option<u64>,
+    echo: bool,
+    tcp: Option<TcpView>,
+    proxy: Option<BackendProxyView<'a>>,
+    bandwidth: Option<BandwidthView>,
+    tenant: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct BackendTlsView<'a> {
+    server_name: Option<&'a str>,
+    ca_certificate_configured: bool,
+    client_certificate_configured: bool,
+}
+
+#[derive(Serialize)]
+struct BackendPoolLimitsView {
+    max_idle: Option<usize>,
+    idle_timeout: Option<u64>,
+    max_connections: Option<usize>,
+    max_concurrent_streams: Option<usize>,
+    wait_timeout_ms: u64,
+}
+
+#[derive(Serialize)]
+struct CircuitBreakerView {
+    failure_threshold: u32,
+    open_duration_secs: u64,
+    half_open_requests: u32,
 }
 
 #[derive(Serialize)]
@@ -374,7 +1396,11 @@ pub(crate) struct DomainView<'a> {
     headers: Option<HeaderManipulationView<'a>>,
     security: Option<ScopedSecurityView<'a>>,
     fingerprinting: Option<bool>,
+    ja4_variants: Option<Vec<&'static str>>,
+    fingerprint_format: Option<&'static str>,
+    redirect: Option<RedirectView<'a>>,
     routes: Vec<RouteView<'a>>,
+    tenant: Option<&'a str>,
 }
 
 #[derive(Serialize)]
@@ -382,10 +1408,63 @@ struct RouteView<'a> {
     prefix: &'a str,
     backend: &'a str,
     fingerprinting: Option<bool>,
+    ja4_variants: Option<Vec<&'static str>>,
+    fingerprint_format: Option<&'static str>,
+    tcp_os: bool,
     force_new_connection: bool,
     replace_path: Option<&'a str>,
+    rewrite: Option<PathRewriteView<'a>>,
+    preserve_host: Option<bool>,
+    host_rewrite: Option<&'a str>,
+    failover: Option<FailoverView<'a>>,
     security: Option<ScopedSecurityView<'a>>,
     headers: Option<HeaderManipulationView<'a>>,
+    websocket: WebSocketView,
+    protocol: &'static str,
+    ext_authz: Option<ExtAuthzView<'a>>,
+    max_request_body_bytes: Option<u64>,
+    max_response_body_bytes: Option<u64>,
+    compression: Option<CompressionView<'a>>,
+    cache: Option<RouteCacheView>,
+    access_log: Option<RouteAccessLogView>,
+    max_in_flight: Option<usize>,
+    priority: &'static str,
+    priority_headers: Vec<PriorityHeaderView<'a>>,
+    timeout: Option<RouteTimeoutView>,
+    sticky: Option<StickyView<'a>>,
+    synthetic: Option<SyntheticResponseView<'a>>,
+    connect: Option<ConnectView<'a>>,
+    privacy: Option<PrivacyView<'a>>,
+    body_rewrite: Option<BodyRewriteView<'a>>,
+    serve_static: Option<ServeStaticView<'a>>,
+    exact: bool,
+    regex: Option<&'a str>,
+    match_priority: i32,
+    methods: &'a [String],
+    match_headers: Vec<HeaderMatchView<'a>>,
+    waf: Option<RouteWafView>,
+    bandwidth: Option<BandwidthView>,
+}
+
+#[derive(Serialize)]
+struct PriorityHeaderView<'a> {
+    header: HeaderMatchView<'a>,
+    priority: &'static str,
+}
+
+#[derive(Serialize)]
+struct ExtAuthzView<'a> {
+    url: &'a str,
+    timeout_ms: u64,
+    failure_mode: &'static str,
+    status_on_error: u16,
+    upstream_headers: &'a [String],
+}
+
+#[derive(Serialize)]
+struct WebSocketView {
+    enabled: bool,
+    idle_timeout_secs: u64,
 }
 
 /// Allowlisted effective-config view of [`BackendPoolConfig`]. Field names are the JSON keys.
@@ -394,6 +1473,19 @@ pub(crate) struct BackendPoolView {
     enabled: bool,
     idle_timeout: u64,
     pool_max_idle_per_host: usize,
+    http2: BackendHttp2View,
+    tcp: TcpView,
+}
+
+#[derive(Serialize)]
+struct BackendHttp2View {
+    initial_stream_window_size: Option<u32>,
+    initial_connection_window_size: Option<u32>,
+    adaptive_window: bool,
+    max_frame_size: Option<u32>,
+    keep_alive_interval_ms: Option<u64>,
+    keep_alive_timeout_ms: Option<u64>,
+    keep_alive_while_idle: bool,
 }
 
 impl Backend {
@@ -405,6 +1497,55 @@ impl Backend {
                 .health_check
                 .as_ref()
                 .map(HealthCheckConfig::effective_view),
+            circuit_breaker: self
+                .circuit_breaker
+                .as_ref()
+                .map(CircuitBreakerConfig::effective_view),
+            tls: self.tls.as_ref().map(BackendTlsConfig::effective_view),
+            pool: self.pool.as_ref().map(BackendPoolLimits::effective_view),
+            max_in_flight: self.max_in_flight,
+            queue: self.queue.as_ref().map(BackendQueueConfig::effective_view),
+            discovery: self.discovery.as_ref().map(DiscoveryConfig::effective_view),
+            drain: self.drain,
+            drain_timeout_secs: self.drain_timeout_secs,
+            slow_start_secs: self.slow_start_secs,
+            echo: self.echo,
+            tcp: self.tcp.as_ref().map(TcpConfig::effective_view),
+            proxy: self.proxy.as_ref().map(BackendProxyConfig::effective_view),
+            bandwidth: self.bandwidth.as_ref().map(BandwidthConfig::effective_view),
+            tenant: self.tenant.as_deref(),
+        }
+    }
+}
+
+impl BackendPoolLimits {
+    fn effective_view(&self) -> BackendPoolLimitsView {
+        BackendPoolLimitsView {
+            max_idle: self.max_idle,
+            idle_timeout: self.idle_timeout,
+            max_connections: self.max_connections,
+            max_concurrent_streams: self.max_concurrent_streams,
+            wait_timeout_ms: self.wait_timeout_ms,
+        }
+    }
+}
+
+impl BackendTlsConfig {
+    fn effective_view(&self) -> BackendTlsView<'_> {
+        BackendTlsView {
+            server_name: self.server_name.as_deref(),
+            ca_certificate_configured: self.ca_cert_path.is_some(),
+            client_certificate_configured: self.client_cert_path.is_some(),
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    fn effective_view(&self) -> CircuitBreakerView {
+        CircuitBreakerView {
+            failure_threshold: self.failure_threshold,
+            open_duration_secs: self.open_duration_secs,
+            half_open_requests: self.half_open_requests,
         }
     }
 }
@@ -442,7 +1583,11 @@ impl Domain {
                 .as_ref()
                 .map(DomainSecurityConfig::effective_view),
             fingerprinting: self.fingerprinting,
+            ja4_variants: ja4_variants_view(self.ja4_variants.as_deref()),
+            fingerprint_format: self.fingerprint_format.map(FingerprintFormat::as_str),
+            redirect: self.redirect.as_ref().map(RedirectConfig::effective_view),
             routes: self.routes.iter().map(Route::effective_view).collect(),
+            tenant: self.tenant.as_deref(),
         }
     }
 }
@@ -453,8 +1598,15 @@ impl Route {
             prefix: self.prefix.as_str(),
             backend: self.backend.as_str(),
             fingerprinting: self.fingerprinting,
+            ja4_variants: ja4_variants_view(self.ja4_variants.as_deref()),
+            fingerprint_format: self.fingerprint_format.map(FingerprintFormat::as_str),
+            tcp_os: self.tcp_os,
             force_new_connection: self.force_new_connection,
             replace_path: self.replace_path.as_deref(),
+            rewrite: self.rewrite.as_ref().map(PathRewriteConfig::effective_view),
+            preserve_host: self.preserve_host,
+            host_rewrite: self.host_rewrite.as_deref(),
+            failover: self.failover.as_ref().map(FailoverConfig::effective_view),
             security: self
                 .security
                 .as_ref()
@@ -463,26 +1615,101 @@ impl Route {
                 .headers
                 .as_ref()
                 .map(HeaderManipulation::effective_view),
+            websocket: WebSocketView {
+                enabled: self.websocket.enabled,
+                idle_timeout_secs: self.websocket.idle_timeout_secs,
+            },
+            protocol: self.protocol.as_str(),
+            ext_authz: self.ext_authz.as_ref().map(|a| ExtAuthzView {
+                url: a.url.as_str(),
+                timeout_ms: a.timeout_ms,
+                failure_mode: a.failure_mode.as_str(),
+                status_on_error: a.status_on_error,
+                upstream_headers: a.upstream_headers.as_slice(),
+            }),
+            max_request_body_bytes: self.max_request_body_bytes,
+            max_response_body_bytes: self.max_response_body_bytes,
+            compression: self
+                .compression
+                .as_ref()
+                .map(CompressionConfig::effective_view),
+            cache: self.cache.as_ref().map(RouteCacheConfig::effective_view),
+            access_log: self
+                .access_log
+                .as_ref()
+                .map(RouteAccessLogConfig::effective_view),
+            max_in_flight: self.max_in_flight,
+            priority: self.priority.as_str(),
+            priority_headers: self
+                .priority_headers
+                .iter()
+                .map(PriorityHeader::effective_view)
+                .collect(),
+            timeout: self
+                .timeout
+                .as_ref()
+                .map(RouteTimeoutConfig::effective_view),
+            sticky: self.sticky.as_ref().map(StickyConfig::effective_view),
+            synthetic: self
+                .synthetic
+                .as_ref()
+                .map(SyntheticResponseConfig::effective_view),
+            connect: self.connect.as_ref().map(ConnectConfig::effective_view),
+            privacy: self.privacy.as_ref().map(PrivacyConfig::effective_view),
+            body_rewrite: self
+                .body_rewrite
+                .as_ref()
+                .map(BodyRewriteConfig::effective_view),
+            serve_static: self
+                .serve_static
+                .as_ref()
+                .map(ServeStaticConfig::effective_view),
+            exact: self.exact,
+            regex: self.regex.as_ref().map(RegexPattern::as_str),
+            match_priority: self.match_priority,
+            methods: &self.methods,
+            match_headers: self
+                .match_headers
+                .iter()
+                .map(HeaderMatch::effective_view)
+                .collect(),
+            waf: self.waf.as_ref().map(RouteWafConfig::effective_view),
+            bandwidth: self.bandwidth.as_ref().map(BandwidthConfig::effective_view),
         }
     }
 }
 
+fn ja4_variants_view(variants: Option<&[Ja4Variant]>) -> Option<Vec<&'static str>> {
+    variants.map(|v| v.iter().copied().map(Ja4Variant::as_str).collect())
+}
+
 impl BackendPoolConfig {
     pub(crate) fn effective_view(&self) -> BackendPoolView {
         BackendPoolView {
             enabled: self.enabled,
             idle_timeout: self.idle_timeout,
             pool_max_idle_per_host: self.pool_max_idle_per_host,
+            http2: BackendHttp2View {
+                initial_stream_window_size: self.http2.initial_stream_window_size,
+                initial_connection_window_size: self.http2.initial_connection_window_size,
+                adaptive_window: self.http2.adaptive_window,
+                max_frame_size: self.http2.max_frame_size,
+                keep_alive_interval_ms: self.http2.keep_alive_interval_ms,
+                keep_alive_timeout_ms: self.http2.keep_alive_timeout_ms,
+                keep_alive_while_idle: self.http2.keep_alive_while_idle,
+            },
+            tcp: self.tcp.effective_view(),
         }
     }
 }
 
 impl BackendHttpVersion {
-    fn as_str(self) -> &'static str {
+    pub fn as_str(self) -> &'static str {
         match self {
             BackendHttpVersion::Http11 => "http11",
             BackendHttpVersion::Http2 => "http2",
             BackendHttpVersion::Preserve => "preserve",
+            BackendHttpVersion::Auto => "auto",
         }
     }
 }
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Bandwidth shaping of a route or backend (`bandwidth` on either).
+///
+/// Body bytes are metered through a token bucket that refills at `bytes_per_sec` and holds up to
+/// `burst_bytes`; once it is empty, frames are held back instead of rejected. Request bodies
+/// (uploads) and response bodies (downloads) have separate buckets of the same size.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BandwidthConfig {
+    /// Sustained rate in bytes per second, per direction.
+    pub bytes_per_sec: u64,
+    /// Bytes that may pass at once after an idle period. Default: `bytes_per_sec`
+    #[serde(default)]
+    pub burst_bytes: Option<u64>,
+    /// Who shares a bucket. Default: `shared`
+    #[serde(default)]
+    pub limit_by: BandwidthKey,
+}
+
+/// Bucket key of a [`BandwidthConfig`].
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BandwidthKey {
+    /// One bucket for every client of the route or backend (default)
+    #[default]
+    Shared,
+    /// One bucket per client IP, resolved through `trusted_proxies`
+    Ip,
+    /// One bucket per JA4 fingerprint; falls back to the client IP without one (plain HTTP)
+    Ja4,
+}
+
+impl BandwidthKey {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BandwidthKey::Shared => "shared",
+            BandwidthKey::Ip => "ip",
+            BandwidthKey::Ja4 => "ja4",
+        }
+    }
+}
+
+impl BandwidthConfig {
+    /// `scope` names the block in error messages (`Backend 'api:80': bandwidth`, ...).
+    pub fn validate(&self, scope: &str) -> Result<()> {
+        if self.bytes_per_sec == 0 {
+            return Err(ProxyError::Config(format!(
+                "{scope}: bytes_per_sec must be greater than 0"
+            )));
+        }
+        if self.burst_bytes == Some(0) {
+            return Err(ProxyError::Config(format!(
+                "{scope}: burst_bytes must be greater than 0 (omit it to use bytes_per_sec)"
+            )));
+        }
+        Ok(())
+    }
+
+    /// `burst_bytes`, or `bytes_per_sec` when unset.
+    pub fn burst(&self) -> u64 {
+        self.burst_bytes.unwrap_or(self.bytes_per_sec)
+    }
+
+    pub(crate) fn effective_view(&self) -> BandwidthView {
+        BandwidthView {
+            bytes_per_sec: self.bytes_per_sec,
+            burst_bytes: self.burst(),
+            limit_by: self.limit_by.as_str(),
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`BandwidthConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct BandwidthView {
+    bytes_per_sec: u64,
+    burst_bytes: u64,
+    limit_by: &'static str,
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use super::compression::{media_type_allowed, validate_media_types};
+use crate::error::{ProxyError, Result};
+
+/// Find/replace in the bodies of backend responses (`body_rewrite` on a route), e.g. to turn
+/// absolute backend URLs in HTML or JSON into the public hostname.
+///
+/// Eligible responses (an allowlisted `Content-Type`, no `Content-Encoding`, a full body) are
+/// rewritten as they stream through: only the last `longest find - 1` bytes are held back
+/// between data frames, so a match split across frames is still replaced. `rules` are plain
+/// strings, matched case-sensitively left to right; where several match at the same place, the
+/// first listed wins. Replaced text is not searched again.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BodyRewriteConfig {
+    /// Replacements, in order of precedence.
+    pub rules: Vec<BodyRewriteRule>,
+    /// Media types rewritten, matched against the `Content-Type` without parameters. `type/*`
+    /// matches a whole top-level type (default: `["text/html", "application/json"]`).
+    #[serde(default = "default_content_types")]
+    pub content_types: Vec<String>,
+    /// Largest piece of a body rewritten at once, in bytes (default: 65536). Bigger data frames
+    /// are split, so memory per response stays bounded however the backend frames its body.
+    /// Every `find` must fit in it.
+    #[serde(default = "default_max_buffer_bytes")]
+    pub max_buffer_bytes: usize,
+}
+
+/// One replacement of [`BodyRewriteConfig`].
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BodyRewriteRule {
+    /// Text searched for, e.g. `http://app.internal:8080`.
+    pub find: String,
+    /// Text written in its place; may be empty to delete it.
+    pub replace: String,
+}
+
+impl BodyRewriteConfig {
+    /// Reject an empty rule list, empty or duplicate `find` strings, a `find` longer than
+    /// `max_buffer_bytes` and invalid media types. `context` prefixes error messages.
+    pub fn validate(&self, context: &str) -> Result<()> {
+        if self.rules.is_empty() {
+            return Err(ProxyError::Config(format!("{context}.rules must not be empty")));
+        }
+        if self.max_buffer_bytes == 0 {
+            return Err(ProxyError::Config(format!("{context}.max_buffer_bytes must be > 0")));
+        }
+        if self.content_types.is_empty() {
+            return Err(ProxyError::Config(format!(
+                "{context}.content_types must list at least one media type"
+            )));
+        }
+        validate_media_types(&format!("{context}.content_types"), &self.content_types)?;
+        for (i, rule) in self.rules.iter().enumerate() {
+            if rule.find.is_empty() {
+                return Err(ProxyError::Config(format!("{context}.rules[{i}].find is empty")));
+            }
+            if rule.find.len() > self.max_buffer_bytes {
+                return Err(ProxyError::Config(format!(
+                    "{context}.rules[{i}].find is longer than max_buffer_bytes ({})",
+                    self.max_buffer_bytes
+                )));
+            }
+            if self
+                .rules
+                .iter()
+                .take(i)
+                .any(|earlier| earlier.find == rule.find)
+            {
+                return Err(ProxyError::Config(format!(
+                    "{context}.rules[{i}]: '{}' is already replaced by an earlier rule",
+                    rule.find
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// `true` when `content_type` (a `Content-Type` header value) is in the allowlist.
+    pub fn allows_content_type(&self, content_type: &str) -> bool {
+        media_type_allowed(&self.content_types, content_type)
+    }
+
+    /// Length of the longest `find`, in bytes.
+    pub fn longest_find(&self) -> usize {
+        self.rules
+            .iter()
+            .map(|rule| rule.find.len())
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn effective_view(&self) -> BodyRewriteView<'_> {
+        BodyRewriteView {
+            rules: self
+                .rules
+                .iter()
+                .map(|rule| BodyRewriteRuleView { find: &rule.find, replace: &rule.replace })
+                .collect(),
+            content_types: &self.content_types,
+            max_buffer_bytes: self.max_buffer_bytes,
+        }
+    }
+}
+
+fn default_content_types() -> Vec<String> {
+    vec!["text/html".to_string(), "application/json".to_string()]
+}
+
+fn default_max_buffer_bytes() -> usize {
+    64 * 1024
+}
+
+/// Allowlisted effective-config view of [`BodyRewriteConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct BodyRewriteView<'a> {
+    rules: Vec<BodyRewriteRuleView<'a>>,
+    content_types: &'a [String],
+    max_buffer_bytes: usize,
+}
+
+#[derive(Serialize)]
+struct BodyRewriteRuleView<'a> {
+    find: &'a str,
+    replace: &'a str,
+}
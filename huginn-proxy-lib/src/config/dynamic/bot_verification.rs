@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+
+use http::HeaderName;
+use serde::{Deserialize, Serialize};
+
+use super::pattern::RegexPattern;
+use crate::error::{ProxyError, Result};
+
+/// Verification of clients claiming to be crawlers (`[security.bot_verification]`).
+///
+/// A request whose `User-Agent` matches a crawler's `user_agent` is verified by reverse DNS of
+/// the client IP, which must name a host under one of the crawler's `domains`, and a forward
+/// lookup of that host, which must resolve back to the client IP. The outcome is cached per IP
+/// and sent to the backend in `header` (`true` / `false`); with `on_spoofed = "block"` a failed
+/// verification is answered with `status` instead.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BotVerificationConfig {
+    /// Verify requests matching `crawlers` (default: false).
+    #[serde(default)]
+    pub enabled: bool,
+    /// Crawlers to verify, tried in order; the first whose `user_agent` matches is used.
+    #[serde(default)]
+    pub crawlers: Vec<CrawlerConfig>,
+    /// What a failed verification does (default: tag).
+    #[serde(default)]
+    pub on_spoofed: SpoofedAction,
+    /// Status of a blocked request, 4xx (default: 403).
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// Request header carrying the outcome (default: `x-huginn-bot-verified`). A value sent by
+    /// the client is always removed.
+    #[serde(default = "default_header")]
+    pub header: String,
+    /// DNS server, `ip` or `ip:port` (optional). Default: the first `nameserver` of
+    /// `/etc/resolv.conf`.
+    #[serde(default)]
+    pub resolver: Option<String>,
+    /// Time allowed for both lookups of one verification, in milliseconds (default: 2000).
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// How long an outcome is reused for the same client IP, in seconds (default: 3600).
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+    /// Most client IPs whose outcome is cached (default: 10000).
+    #[serde(default = "default_cache_max_entries")]
+    pub cache_max_entries: usize,
+}
+
+impl Default for BotVerificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            crawlers: Vec::new(),
+            on_spoofed: SpoofedAction::default(),
+            status: default_status(),
+            header: default_header(),
+            resolver: None,
+            timeout_ms: default_timeout_ms(),
+            cache_ttl_secs: default_cache_ttl_secs(),
+            cache_max_entries: default_cache_max_entries(),
+        }
+    }
+}
+
+/// One crawler of `bot_verification.crawlers`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CrawlerConfig {
+    /// Crawler name, reported in logs and as the `crawler` label of
+    /// `huginn_bot_verifications_total`, e.g. `googlebot`.
+    pub name: String,
+    /// Regex matched against the `User-Agent` of a request, e.g. `(?i)googlebot`.
+    pub user_agent: RegexPattern,
+    /// Domains the crawler's reverse DNS names end with, e.g. `["googlebot.com", "google.com"]`.
+    pub domains: Vec<String>,
+}
+
+/// What a failed bot verification does.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SpoofedAction {
+    /// Forward the request with the header set to `false`
+    #[default]
+    Tag,
+    /// Reject the request with the configured `status`
+    Block,
+}
+
+impl SpoofedAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpoofedAction::Tag => "tag",
+            SpoofedAction::Block => "block",
+        }
+    }
+}
+
+impl BotVerificationConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(400..=499).contains(&self.status) {
+            return Err(ProxyError::Config(format!(
+                "security.bot_verification.status must be a 4xx status, got {}",
+                self.status
+            )));
+        }
+        HeaderName::from_bytes(self.header.as_bytes()).map_err(|e| {
+            ProxyError::Config(format!(
+                "security.bot_verification.header '{}' is not a valid header name: {e}",
+                self.header
+            ))
+        })?;
+        if let Some(resolver) = &self.resolver {
+            if parse_resolver(resolver).is_none() {
+                return Err(ProxyError::Config(format!(
+                    "security.bot_verification.resolver '{resolver}' must be an IP address, \
+                     optionally with a port"
+                )));
+            }
+        }
+        if self.timeout_ms == 0 || self.cache_ttl_secs == 0 || self.cache_max_entries == 0 {
+            return Err(ProxyError::Config(
+                "security.bot_verification.timeout_ms, cache_ttl_secs and cache_max_entries \
+                 must be greater than 0"
+                    .to_string(),
+            ));
+        }
+        if self.enabled && self.crawlers.is_empty() {
+            return Err(ProxyError::Config(
+                "security.bot_verification is enabled but lists no crawlers".to_string(),
+            ));
+        }
+        let mut names = HashSet::new();
+        for crawler in &self.crawlers {
+            crawler.validate()?;
+            if !names.insert(crawler.name.as_str()) {
+                return Err(ProxyError::Config(format!(
+                    "security.bot_verification: crawler '{}' is listed more than once",
+                    crawler.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The configured `resolver` as a socket address (port 53 when none is given).
+    pub fn resolver_addr(&self) -> Option<SocketAddr> {
+        self.resolver.as_deref().and_then(parse_resolver)
+    }
+
+    /// The crawler a request with this `User-Agent` claims to be, if any.
+    pub fn crawler_for(&self, user_agent: &str) -> Option<&CrawlerConfig> {
+        self.crawlers
+            .iter()
+            .find(|c| c.user_agent.regex().is_match(user_agent))
+    }
+
+    pub(crate) fn effective_view(&self) -> BotVerificationView<'_> {
+        BotVerificationView {
+            enabled: self.enabled,
+            crawlers: self
+                .crawlers
+                .iter()
+                .map(|c| CrawlerView {
+                    name: &c.name,
+                    user_agent: c.user_agent.as_str(),
+                    domains: &c.domains,
+                })
+                .collect(),
+            on_spoofed: self.on_spoofed.as_str(),
+            status: self.status,
+            header: &self.header,
+            resolver: self.resolver.as_deref(),
+            timeout_ms: self.timeout_ms,
+            cache_ttl_secs: self.cache_ttl_secs,
+            cache_max_entries: self.cache_max_entries,
+        }
+    }
+}
+
+impl CrawlerConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(ProxyError::Config(
+                "security.bot_verification: crawler name must not be empty".to_string(),
+            ));
+        }
+        if self.domains.is_empty() {
+            return Err(ProxyError::Config(format!(
+                "security.bot_verification: crawler '{}' lists no domains",
+                self.name
+            )));
+        }
+        for domain in &self.domains {
+            let valid = !domain.is_empty()
+                && !domain.starts_with('.')
+                && domain
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.'));
+            if !valid {
+                return Err(ProxyError::Config(format!(
+                    "security.bot_verification: crawler '{}' has an invalid domain '{domain}'",
+                    self.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `host` (a reverse DNS name, without the trailing dot) is one of `domains` or a
+    /// subdomain of one.
+    pub fn owns_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.domains.iter().any(|domain| {
+            let domain = domain.to_ascii_lowercase();
+            host == domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+    }
+}
+
+fn parse_resolver(value: &str) -> Option<SocketAddr> {
+    value.parse::<SocketAddr>().ok().or_else(|| {
+        value
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, 53))
+    })
+}
+
+fn default_status() -> u16 {
+    403
+}
+
+fn default_header() -> String {
+    "x-huginn-bot-verified".to_string()
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_cache_max_entries() -> usize {
+    10_000
+}
+
+/// Allowlisted effective-config view of [`BotVerificationConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct BotVerificationView<'a> {
+    enabled: bool,
+    crawlers: Vec<CrawlerView<'a>>,
+    on_spoofed: &'static str,
+    status: u16,
+    header: &'a str,
+    resolver: Option<&'a str>,
+    timeout_ms: u64,
+    cache_ttl_secs: u64,
+    cache_max_entries: usize,
+}
+
+#[derive(Serialize)]
+struct CrawlerView<'a> {
+    name: &'a str,
+    user_agent: &'a str,
+    domains: &'a [String],
+}
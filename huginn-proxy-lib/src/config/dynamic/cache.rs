@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Response caching for a route (`cache` on a route).
+///
+/// `GET` responses are stored in the shared `[cache]` store, keyed by host, path and query plus
+/// the request headers the response names in `Vary`. Freshness comes from the response's
+/// `Cache-Control` (`s-maxage`, then `max-age`) or `Expires`; responses marked `no-store`,
+/// `no-cache` or `private`, or carrying `Set-Cookie`, are never stored.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RouteCacheConfig {
+    /// Cache responses on this route (default: true).
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Larger responses are forwarded without being stored (default: 1048576).
+    #[serde(default = "default_max_object_bytes")]
+    pub max_object_bytes: u64,
+    /// Freshness, in seconds, for responses that carry no `Cache-Control` lifetime or `Expires`
+    /// (default: 0, such responses are not stored).
+    #[serde(default)]
+    pub default_ttl_secs: u64,
+    /// How long, in seconds, an expired entry may still be served when the backend fails
+    /// (error or `5xx`) while refreshing it (default: 0, never serve stale).
+    #[serde(default)]
+    pub stale_if_error_secs: u64,
+}
+
+impl Default for RouteCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            max_object_bytes: default_max_object_bytes(),
+            default_ttl_secs: 0,
+            stale_if_error_secs: 0,
+        }
+    }
+}
+
+impl RouteCacheConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_object_bytes == 0 {
+            return Err(ProxyError::Config(
+                "cache.max_object_bytes must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> RouteCacheView {
+        RouteCacheView {
+            enabled: self.enabled,
+            max_object_bytes: self.max_object_bytes,
+            default_ttl_secs: self.default_ttl_secs,
+            stale_if_error_secs: self.stale_if_error_secs,
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_max_object_bytes() -> u64 {
+    1024 * 1024
+}
+
+/// Allowlisted effective-config view of [`RouteCacheConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct RouteCacheView {
+    enabled: bool,
+    max_object_bytes: u64,
+    default_ttl_secs: u64,
+    stale_if_error_secs: u64,
+}
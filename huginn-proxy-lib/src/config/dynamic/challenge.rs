@@ -0,0 +1,215 @@
+use std::sync::Arc;
+
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::security::FingerprintList;
+use super::sticky::is_cookie_name;
+use super::synthetic::MAX_SYNTHETIC_BODY_BYTES;
+use crate::config::Secret;
+use crate::error::{ProxyError, Result};
+use crate::security::PreparedChallenge;
+
+/// Shortest accepted `secret`, in bytes.
+const MIN_SECRET_BYTES: usize = 16;
+
+/// Largest accepted `difficulty`: about 16 million hashes for the page on average.
+const MAX_DIFFICULTY: u8 = 24;
+
+/// Challenge page for suspect fingerprints (`[security.challenge]`).
+///
+/// A request whose connection fingerprints match a `suspect` entry is answered before routing
+/// with a page generated by the proxy instead of being forwarded. The page is given a challenge
+/// signed with `secret` and bound to the client's JA4 and IP, solves a proof of work of
+/// `difficulty` bits from it, stores the resulting token in a cookie and reloads itself; requests
+/// that carry a valid, unexpired token are forwarded as usual. Only clients that run the page's
+/// script get through, which stops most scripted clients reusing a browser's fingerprint. Entries
+/// match like `[security.fingerprint_filter]` entries. An empty list (default) disables the
+/// challenge.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ChallengeConfig {
+    /// Fingerprints whose requests must pass the challenge.
+    #[serde(default)]
+    pub suspect: FingerprintList,
+    /// HMAC-SHA256 key of the cookie tokens, at least 16 bytes. Required with `suspect` entries;
+    /// changing it invalidates every issued cookie.
+    #[serde(default)]
+    pub secret: Secret<String>,
+    /// Cookie carrying the token (default: `huginn_challenge`).
+    #[serde(default = "default_cookie_name")]
+    pub cookie_name: String,
+    /// How long a passed challenge is valid, in seconds (default: 3600).
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Status of the challenge page (default: 403).
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// Leading zero bits the SHA-256 of a token must have (default: 16, at most 24). Each bit
+    /// doubles the page's work; `0` only asks for the script to run.
+    #[serde(default = "default_difficulty")]
+    pub difficulty: u8,
+    /// Inline HTML of the challenge page. Exclusive with `body_file`. Default: a built-in page.
+    ///
+    /// `{{cookie_name}}`, `{{challenge}}`, `{{difficulty}}` and `{{ttl_secs}}` are replaced
+    /// before it is served; the page must solve the challenge and store the token in the cookie.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// File read for the challenge page when the config is loaded or reloaded, with the same
+    /// placeholders. Exclusive with `body`.
+    #[serde(default)]
+    pub body_file: Option<String>,
+    /// Token key and page template. Filled in by the config loader.
+    #[serde(skip)]
+    pub prepared: Option<Arc<PreparedChallenge>>,
+}
+
+impl Default for ChallengeConfig {
+    fn default() -> Self {
+        Self {
+            suspect: FingerprintList::default(),
+            secret: Secret::default(),
+            cookie_name: default_cookie_name(),
+            ttl_secs: default_ttl_secs(),
+            status: default_status(),
+            difficulty: default_difficulty(),
+            body: None,
+            body_file: None,
+            prepared: None,
+        }
+    }
+}
+
+fn default_cookie_name() -> String {
+    "huginn_challenge".to_string()
+}
+
+fn default_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_status() -> u16 {
+    403
+}
+
+fn default_difficulty() -> u8 {
+    16
+}
+
+impl ChallengeConfig {
+    /// Whether any request can be challenged (at least one `suspect` entry).
+    pub fn is_active(&self) -> bool {
+        !self.suspect.is_empty()
+    }
+
+    /// Build the token key and read `body_file` into [`prepared`](Self::prepared).
+    pub fn prepare(&mut self) -> Result<()> {
+        let file_page = match &self.body_file {
+            Some(path) => Some(std::fs::read_to_string(path).map_err(|e| {
+                ProxyError::Config(format!("security.challenge.body_file '{path}': {e}"))
+            })?),
+            None => None,
+        };
+        self.prepared = Some(Arc::new(PreparedChallenge::new(self, file_page)));
+        Ok(())
+    }
+
+    /// The token key and page template built by [`prepare`](Self::prepare). A config that did
+    /// not go through the loader has them built on each call, with the built-in page in place of
+    /// `body_file`.
+    pub fn prepared(&self) -> Arc<PreparedChallenge> {
+        self.prepared
+            .clone()
+            .unwrap_or_else(|| Arc::new(PreparedChallenge::new(self, None)))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if StatusCode::from_u16(self.status).is_err() || self.status < 200 {
+            return Err(ProxyError::Config(format!(
+                "security.challenge.status must be a final HTTP status (200-599), got {}",
+                self.status
+            )));
+        }
+        if !is_cookie_name(&self.cookie_name) {
+            return Err(ProxyError::Config(format!(
+                "security.challenge.cookie_name '{}' is not a valid cookie name",
+                self.cookie_name
+            )));
+        }
+        if self.ttl_secs == 0 {
+            return Err(ProxyError::Config(
+                "security.challenge.ttl_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.difficulty > MAX_DIFFICULTY {
+            return Err(ProxyError::Config(format!(
+                "security.challenge.difficulty must be at most {MAX_DIFFICULTY}, got {}",
+                self.difficulty
+            )));
+        }
+        for (kind, entry) in self.suspect.entries() {
+            if entry.is_empty() || entry == "*" {
+                return Err(ProxyError::Config(format!(
+                    "security.challenge.suspect.{kind} entries must not be empty or a bare '*'"
+                )));
+            }
+        }
+        if self.is_active() && self.secret.expose().len() < MIN_SECRET_BYTES {
+            return Err(ProxyError::Config(format!(
+                "security.challenge.secret must be at least {MIN_SECRET_BYTES} bytes when \
+                 suspect fingerprints are listed"
+            )));
+        }
+        match (&self.body, &self.body_file) {
+            (Some(_), Some(_)) => Err(ProxyError::Config(
+                "security.challenge.body and security.challenge.body_file are mutually exclusive"
+                    .to_string(),
+            )),
+            (Some(body), None) if body.len() as u64 > MAX_SYNTHETIC_BODY_BYTES => {
+                Err(ProxyError::Config(format!(
+                    "security.challenge.body exceeds {MAX_SYNTHETIC_BODY_BYTES} bytes"
+                )))
+            }
+            (None, Some(path)) => {
+                let metadata = std::fs::metadata(path).map_err(|e| {
+                    ProxyError::Config(format!("security.challenge.body_file '{path}': {e}"))
+                })?;
+                if !metadata.is_file() || metadata.len() > MAX_SYNTHETIC_BODY_BYTES {
+                    return Err(ProxyError::Config(format!(
+                        "security.challenge.body_file '{path}' must be a file of at most \
+                         {MAX_SYNTHETIC_BODY_BYTES} bytes"
+                    )));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn effective_view(&self) -> ChallengeView<'_> {
+        ChallengeView {
+            suspect: &self.suspect,
+            secret: &self.secret,
+            cookie_name: &self.cookie_name,
+            ttl_secs: self.ttl_secs,
+            status: self.status,
+            difficulty: self.difficulty,
+            body_bytes: self.body.as_ref().map(String::len),
+            body_file: self.body_file.as_deref(),
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`ChallengeConfig`]. Field names are the JSON keys; the
+/// inline page is summarized by its length.
+#[derive(Serialize)]
+pub(crate) struct ChallengeView<'a> {
+    suspect: &'a FingerprintList,
+    secret: &'a Secret<String>,
+    cookie_name: &'a str,
+    ttl_secs: u64,
+    status: u16,
+    difficulty: u8,
+    body_bytes: Option<usize>,
+    body_file: Option<&'a str>,
+}
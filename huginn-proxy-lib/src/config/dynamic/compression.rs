@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Response compression (`[compression]` globally, `compression` on a route).
+///
+/// Backend responses are compressed when the client's `Accept-Encoding` allows one of
+/// `algorithms`, the `Content-Type` is in `content_types` and the body is not known to be
+/// smaller than `min_size_bytes`. Responses the backend already encoded are left alone.
+/// A route-level block fully replaces the global one.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CompressionConfig {
+    /// Compress responses (default: true). `false` on a route turns off a global `[compression]`.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Encodings offered, in order of preference when the client weighs them equally
+    /// (default: `["br", "zstd", "gzip"]`).
+    #[serde(default = "default_algorithms")]
+    pub algorithms: Vec<CompressionAlgorithm>,
+    /// Responses with a smaller `Content-Length` are sent as-is (default: 1024).
+    /// Bodies without a declared length are always compressed.
+    #[serde(default = "default_min_size_bytes")]
+    pub min_size_bytes: u64,
+    /// Media types eligible for compression, matched against the `Content-Type` without
+    /// parameters. `type/*` matches a whole top-level type.
+    #[serde(default = "default_content_types")]
+    pub content_types: Vec<String>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            algorithms: default_algorithms(),
+            min_size_bytes: default_min_size_bytes(),
+            content_types: default_content_types(),
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.algorithms.is_empty() {
+            return Err(ProxyError::Config(
+                "compression.algorithms must list at least one encoding".to_string(),
+            ));
+        }
+        validate_media_types("compression.content_types", &self.content_types)
+    }
+
+    /// `true` when `content_type` (a `Content-Type` header value) is in the allowlist.
+    pub fn allows_content_type(&self, content_type: &str) -> bool {
+        media_type_allowed(&self.content_types, content_type)
+    }
+
+    pub(crate) fn effective_view(&self) -> CompressionView<'_> {
+        CompressionView {
+            enabled: self.enabled,
+            algorithms: self
+                .algorithms
+                .iter()
+                .copied()
+                .map(CompressionAlgorithm::as_str)
+                .collect(),
+            min_size_bytes: self.min_size_bytes,
+            content_types: self.content_types.as_slice(),
+        }
+    }
+}
+
+/// Content coding produced by the proxy.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    /// `br` (Brotli)
+    Br,
+    /// `zstd` (Zstandard)
+    Zstd,
+    /// `gzip`
+    Gzip,
+}
+
+impl CompressionAlgorithm {
+    /// The `Content-Encoding` / `Accept-Encoding` token.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Br => "br",
+            CompressionAlgorithm::Zstd => "zstd",
+            CompressionAlgorithm::Gzip => "gzip",
+        }
+    }
+}
+
+/// Reject entries of the media type allowlist `field` that are neither `type/subtype` nor
+/// `type/*`.
+pub(crate) fn validate_media_types(field: &str, content_types: &[String]) -> Result<()> {
+    for content_type in content_types {
+        let valid = content_type
+            .split_once('/')
+            .is_some_and(|(kind, sub)| !kind.is_empty() && !sub.is_empty() && kind != "*");
+        if !valid {
+            return Err(ProxyError::Config(format!(
+                "{field}: invalid media type '{content_type}' \
+                 (expected 'type/subtype' or 'type/*')"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// `true` when `content_type` (a `Content-Type` header value) is in `allowed`, matched without
+/// parameters; `type/*` entries match a whole top-level type.
+pub(crate) fn media_type_allowed(allowed: &[String], content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    let Some((kind, _)) = essence.split_once('/') else {
+        return false;
+    };
+    allowed
+        .iter()
+        .any(|allowed| match allowed.strip_suffix("/*") {
+            Some(allowed_kind) => allowed_kind.eq_ignore_ascii_case(kind),
+            None => allowed.eq_ignore_ascii_case(essence),
+        })
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_algorithms() -> Vec<CompressionAlgorithm> {
+    vec![CompressionAlgorithm::Br, CompressionAlgorithm::Zstd, CompressionAlgorithm::Gzip]
+}
+
+fn default_min_size_bytes() -> u64 {
+    1024
+}
+
+fn default_content_types() -> Vec<String> {
+    [
+        "text/html",
+        "text/css",
+        "text/plain",
+        "text/javascript",
+        "text/xml",
+        "application/javascript",
+        "application/json",
+        "application/xml",
+        "application/wasm",
+        "image/svg+xml",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect()
+}
+
+/// Allowlisted effective-config view of [`CompressionConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct CompressionView<'a> {
+    enabled: bool,
+    algorithms: Vec<&'static str>,
+    min_size_bytes: u64,
+    content_types: &'a [String],
+}
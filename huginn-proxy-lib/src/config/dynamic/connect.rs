@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Forward-proxy tunnels of a route (`connect` on a route).
+///
+/// The route answers `CONNECT host:port` requests by opening a TCP connection to the target and
+/// splicing it with the client's connection; it keeps serving other requests by its prefix as
+/// usual. CONNECT requests are matched on the target host like any request, so these routes
+/// usually live on the catch-all domain; their `prefix` is not checked, `methods` and
+/// `match_headers` are. Allowed targets are the route's `backend` and the `targets` entries;
+/// any other target is answered `403`. When the target is a configured backend with a `tls`
+/// block and `fingerprint` is on, the first bytes the client sends through the tunnel are read
+/// as its TLS ClientHello and fingerprinted (JA4) before they are passed on.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectConfig {
+    /// Further allowed targets as `host:port`: `*` as the port allows any port, a `*.` host
+    /// prefix any subdomain, e.g. `["mirror.internal:443", "*.corp.example:*"]` (default: none).
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Close a tunnel after this many seconds without traffic in either direction
+    /// (default: 300).
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    /// Fingerprint the inner ClientHello of tunnels to TLS backends (default: true).
+    #[serde(default = "default_fingerprint")]
+    pub fingerprint: bool,
+}
+
+impl Default for ConnectConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            idle_timeout_secs: default_idle_timeout_secs(),
+            fingerprint: default_fingerprint(),
+        }
+    }
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    300
+}
+
+fn default_fingerprint() -> bool {
+    true
+}
+
+impl ConnectConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.idle_timeout_secs == 0 {
+            return Err(ProxyError::Config(
+                "connect.idle_timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(entry) = self.targets.iter().find(|entry| !valid_target(entry)) {
+            return Err(ProxyError::Config(format!(
+                "connect.targets: '{entry}' is not a host:port target (use '*' as the port for \
+                 any port and '*.example.com' for subdomains; '*' alone is not a host)"
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_secs)
+    }
+
+    /// Whether one of the `targets` entries allows `host` (without IPv6 brackets) and `port`.
+    pub fn allows(&self, host: &str, port: u16) -> bool {
+        self.targets
+            .iter()
+            .any(|entry| target_matches(entry, host, port))
+    }
+
+    pub(crate) fn effective_view(&self) -> ConnectView<'_> {
+        ConnectView {
+            targets: &self.targets,
+            idle_timeout_secs: self.idle_timeout_secs,
+            fingerprint: self.fingerprint,
+        }
+    }
+}
+
+/// Host and port of a `host:port` target, with the brackets of an IPv6 host removed.
+pub(crate) fn split_target(target: &str) -> Option<(&str, &str)> {
+    let (host, port) = target.rsplit_once(':')?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    Some((host, port))
+}
+
+fn valid_target(entry: &str) -> bool {
+    let Some((host, port)) = split_target(entry) else {
+        return false;
+    };
+    let host = host.strip_prefix("*.").unwrap_or(host);
+    let port_ok = port == "*" || port.parse::<u16>().is_ok_and(|p| p != 0);
+    port_ok && !host.is_empty() && !host.contains('*')
+}
+
+fn target_matches(entry: &str, host: &str, port: u16) -> bool {
+    let Some((pattern, ports)) = split_target(entry) else {
+        return false;
+    };
+    let port_ok = ports == "*" || ports.parse::<u16>() == Ok(port);
+    let host_ok = match pattern.strip_prefix("*.") {
+        Some(base) => host
+            .to_ascii_lowercase()
+            .ends_with(&format!(".{}", base.to_ascii_lowercase())),
+        None => pattern.eq_ignore_ascii_case(host),
+    };
+    port_ok && host_ok
+}
+
+/// Allowlisted effective-config view of [`ConnectConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct ConnectView<'a> {
+    targets: &'a [String],
+    idle_timeout_secs: u64,
+    fingerprint: bool,
+}
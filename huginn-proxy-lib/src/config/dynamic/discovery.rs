@@ -0,0 +1,220 @@
+use std::net::IpAddr;
+
+use http::uri::Authority;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Secret;
+use crate::error::{ProxyError, Result};
+
+/// How a backend's addresses are discovered (`discovery` on a backend).
+///
+/// Without it a backend hostname is resolved by the connector whenever a new connection is
+/// opened, and the first address that accepts wins. With it the proxy keeps the full address set
+/// of the backend, refreshes it on a schedule, spreads new connections over every address and
+/// drains pooled connections when an address disappears.
+///
+/// `dns` refreshes the addresses of the backend's own hostname. The other providers turn the
+/// backend into a logical service: `address` is only the name routes reference (and the `Host`
+/// sent upstream), and the provider maintains its members.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum DiscoveryConfig {
+    /// Re-resolve the host of the backend's `address` (A and AAAA records, through the system
+    /// resolver) every `refresh_secs`.
+    Dns {
+        /// Seconds between two resolutions. The system resolver does not expose record TTLs, so
+        /// set it to the TTL of the records (or lower).
+        #[serde(default = "default_refresh_secs")]
+        refresh_secs: u64,
+    },
+    /// A fixed list of `host:port` members, each re-resolved every `refresh_secs`.
+    Static {
+        members: Vec<String>,
+        #[serde(default = "default_refresh_secs")]
+        refresh_secs: u64,
+    },
+    /// Members from the SRV records of `name` (e.g. `_http._tcp.billing.service.consul`), asked
+    /// to the first `nameserver` of `/etc/resolv.conf`. Only the lowest priority is used.
+    Srv {
+        name: String,
+        #[serde(default = "default_refresh_secs")]
+        refresh_secs: u64,
+    },
+    /// Members from the Consul health API: the instances of `service` whose checks pass.
+    Consul {
+        /// Consul HTTP API, `http://host:port`.
+        #[serde(default = "default_consul_url")]
+        url: String,
+        service: String,
+        /// Only instances carrying this tag.
+        #[serde(default)]
+        tag: Option<String>,
+        /// Datacenter to query instead of the agent's own.
+        #[serde(default)]
+        datacenter: Option<String>,
+        /// ACL token, sent as `X-Consul-Token`.
+        #[serde(default)]
+        token: Option<Secret<String>>,
+        #[serde(default = "default_refresh_secs")]
+        refresh_secs: u64,
+    },
+}
+
+fn default_refresh_secs() -> u64 {
+    30
+}
+
+fn default_consul_url() -> String {
+    "http://127.0.0.1:8500".to_string()
+}
+
+impl DiscoveryConfig {
+    /// Seconds between two refreshes of the address set.
+    pub fn refresh_secs(&self) -> u64 {
+        match self {
+            Self::Dns { refresh_secs }
+            | Self::Static { refresh_secs, .. }
+            | Self::Srv { refresh_secs, .. }
+            | Self::Consul { refresh_secs, .. } => *refresh_secs,
+        }
+    }
+
+    /// Whether the backend is a logical service whose members come from the provider (every
+    /// provider but `dns`). Its health follows the provider: no member means unhealthy.
+    pub fn is_service(&self) -> bool {
+        !matches!(self, Self::Dns { .. })
+    }
+
+    /// Reject a zero refresh interval, incomplete provider settings and addresses the provider
+    /// cannot serve: unix sockets, IP literals for `dns`, names that are no URI authority for
+    /// services.
+    pub fn validate(&self, address: &str) -> Result<()> {
+        let invalid =
+            |message: String| ProxyError::Config(format!("Backend '{address}': {message}"));
+        if self.refresh_secs() == 0 {
+            return Err(invalid("discovery.refresh_secs must be greater than 0".to_string()));
+        }
+        if address.starts_with("unix:") {
+            return Err(invalid("discovery is not supported for unix socket backends".to_string()));
+        }
+        match self {
+            Self::Dns { .. } => {
+                let host = address
+                    .rsplit_once(':')
+                    .map_or(address, |(host, _)| host)
+                    .trim_start_matches('[')
+                    .trim_end_matches(']');
+                if host.parse::<IpAddr>().is_ok() {
+                    return Err(invalid(
+                        "discovery type dns needs a hostname address, not an IP".to_string(),
+                    ));
+                }
+                return Ok(());
+            }
+            Self::Static { members, .. } => {
+                if members.is_empty() {
+                    return Err(invalid("discovery.members must not be empty".to_string()));
+                }
+                for member in members {
+                    let port = member.rsplit_once(':').map(|(_, port)| port.parse::<u16>());
+                    if !matches!(port, Some(Ok(_))) {
+                        return Err(invalid(format!(
+                            "discovery member '{member}' must be host:port"
+                        )));
+                    }
+                }
+            }
+            Self::Srv { name, .. } => {
+                if name.is_empty() {
+                    return Err(invalid("discovery.name must not be empty".to_string()));
+                }
+            }
+            Self::Consul { url, service, tag, datacenter, .. } => {
+                let valid_url = url
+                    .strip_prefix("http://")
+                    .is_some_and(|rest| rest.trim_end_matches('/').parse::<Authority>().is_ok());
+                if !valid_url {
+                    return Err(invalid(format!("discovery.url '{url}' must be http://host:port")));
+                }
+                for (key, value) in [
+                    ("service", Some(service)),
+                    ("tag", tag.as_ref()),
+                    ("datacenter", datacenter.as_ref()),
+                ] {
+                    if value.is_some_and(|value| !is_query_safe(value)) {
+                        return Err(invalid(format!(
+                            "discovery.{key} must be non-empty and use only letters, digits, \
+                             '-', '_' and '.'"
+                        )));
+                    }
+                }
+            }
+        }
+        if address.parse::<Authority>().is_err() {
+            return Err(invalid(
+                "a discovered service needs a name usable as a URI host (e.g. 'billing')"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> DiscoveryView<'_> {
+        let mut view = DiscoveryView {
+            kind: "dns",
+            refresh_secs: self.refresh_secs(),
+            members: None,
+            name: None,
+            url: None,
+            service: None,
+            tag: None,
+            datacenter: None,
+            token_configured: false,
+        };
+        match self {
+            Self::Dns { .. } => {}
+            Self::Static { members, .. } => {
+                view.kind = "static";
+                view.members = Some(members.as_slice());
+            }
+            Self::Srv { name, .. } => {
+                view.kind = "srv";
+                view.name = Some(name.as_str());
+            }
+            Self::Consul { url, service, tag, datacenter, token, .. } => {
+                view.kind = "consul";
+                view.url = Some(url.as_str());
+                view.service = Some(service.as_str());
+                view.tag = tag.as_deref();
+                view.datacenter = datacenter.as_deref();
+                view.token_configured = token.is_some();
+            }
+        }
+        view
+    }
+}
+
+/// Consul names are sent in the query string unencoded, so they are limited to unreserved
+/// characters.
+fn is_query_safe(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Allowlisted effective-config view of [`DiscoveryConfig`]. Field names are the JSON keys;
+/// fields of other providers are `null`.
+#[derive(Serialize)]
+pub(crate) struct DiscoveryView<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    refresh_secs: u64,
+    members: Option<&'a [String]>,
+    name: Option<&'a str>,
+    url: Option<&'a str>,
+    service: Option<&'a str>,
+    tag: Option<&'a str>,
+    datacenter: Option<&'a str>,
+    token_configured: bool,
+}
@@ -0,0 +1,117 @@
+use http::HeaderName;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Most attempts accepted for `max_attempts`, the first one included.
+pub const MAX_FAILOVER_ATTEMPTS: usize = 10;
+
+/// Largest accepted `replay_body_bytes` (1 MiB): each buffered body is held in memory until the
+/// request completes.
+pub const MAX_REPLAY_BODY_BYTES: usize = 1024 * 1024;
+
+/// Failover to the route's next backend (`failover` on a route).
+///
+/// When the selected backend answers with one of `statuses`, or with the `header` set, the
+/// request is sent again to another healthy backend of the route (one sharing its matcher), up to
+/// `max_attempts` backends in all; the client gets the last answer. A request that fails without
+/// an answer counts with the status it would be answered with (`502` when the backend cannot be
+/// reached, `504` on a timeout). Only requests that can be sent again unchanged fail over:
+/// idempotent methods (`GET`, `HEAD`, `OPTIONS`, `TRACE`, `PUT`, `DELETE`) without a request body
+/// and that are not WebSocket upgrades. With `replay_body_bytes`, request bodies up to that size
+/// are buffered before forwarding, and requests of any method whose body fits fail over too;
+/// larger bodies stream as usual and their requests get the first answer.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FailoverConfig {
+    /// Response statuses that fail over (default: 502, 503, 504).
+    #[serde(default = "default_statuses")]
+    pub statuses: Vec<u16>,
+    /// Response header whose presence fails over, whatever the status (optional), e.g. a header
+    /// a backend sets while it is warming up.
+    #[serde(default)]
+    pub header: Option<String>,
+    /// Backends tried for one request, the first one included (default: 2).
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: usize,
+    /// Buffer request bodies up to this many bytes so they can be sent again, and fail over
+    /// non-idempotent methods (e.g. `POST`) whose body fits (default: 0, no buffering).
+    #[serde(default)]
+    pub replay_body_bytes: usize,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            statuses: default_statuses(),
+            header: None,
+            max_attempts: default_max_attempts(),
+            replay_body_bytes: 0,
+        }
+    }
+}
+
+impl FailoverConfig {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(status) = self
+            .statuses
+            .iter()
+            .find(|status| !(100..=599).contains(*status))
+        {
+            return Err(ProxyError::Config(format!(
+                "failover.statuses: {status} is not an HTTP status"
+            )));
+        }
+        if let Some(header) = &self.header {
+            HeaderName::from_bytes(header.as_bytes()).map_err(|_| {
+                ProxyError::Config(format!(
+                    "failover.header '{header}' is not a valid HTTP header name"
+                ))
+            })?;
+        }
+        if self.statuses.is_empty() && self.header.is_none() {
+            return Err(ProxyError::Config(
+                "failover needs statuses or a header to fail over on".to_string(),
+            ));
+        }
+        if !(2..=MAX_FAILOVER_ATTEMPTS).contains(&self.max_attempts) {
+            return Err(ProxyError::Config(format!(
+                "failover.max_attempts must be between 2 and {MAX_FAILOVER_ATTEMPTS}, got {}",
+                self.max_attempts
+            )));
+        }
+        if self.replay_body_bytes > MAX_REPLAY_BODY_BYTES {
+            return Err(ProxyError::Config(format!(
+                "failover.replay_body_bytes must be at most {MAX_REPLAY_BODY_BYTES}, got {}",
+                self.replay_body_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> FailoverView<'_> {
+        FailoverView {
+            statuses: &self.statuses,
+            header: self.header.as_deref(),
+            max_attempts: self.max_attempts,
+            replay_body_bytes: self.replay_body_bytes,
+        }
+    }
+}
+
+fn default_statuses() -> Vec<u16> {
+    vec![502, 503, 504]
+}
+
+fn default_max_attempts() -> usize {
+    2
+}
+
+/// Allowlisted effective-config view of [`FailoverConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct FailoverView<'a> {
+    statuses: &'a [u16],
+    header: Option<&'a str>,
+    max_attempts: usize,
+    replay_body_bytes: usize,
+}
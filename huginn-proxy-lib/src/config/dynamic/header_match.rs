@@ -0,0 +1,65 @@
+use http::{HeaderMap, HeaderName};
+use serde::{Deserialize, Serialize};
+
+use super::pattern::RegexPattern;
+use crate::error::{ProxyError, Result};
+
+/// A request header condition of a route (`match_headers` on a route).
+///
+/// With neither `equals` nor `regex` the header only has to be present. A header sent several
+/// times matches when any of its values does.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct HeaderMatch {
+    /// Header name (case-insensitive).
+    pub name: String,
+    /// Exact value the header must have (optional). Exclusive with `regex`.
+    #[serde(default)]
+    pub equals: Option<String>,
+    /// Regex the header value must match (optional), compiled when the config loads.
+    #[serde(default)]
+    pub regex: Option<RegexPattern>,
+}
+
+impl HeaderMatch {
+    pub fn validate(&self) -> Result<()> {
+        HeaderName::from_bytes(self.name.as_bytes()).map_err(|e| {
+            ProxyError::Config(format!("Invalid match_headers name '{}': {e}", self.name))
+        })?;
+        if self.equals.is_some() && self.regex.is_some() {
+            return Err(ProxyError::Config(format!(
+                "match_headers '{}': equals and regex are mutually exclusive",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether `headers` satisfy this condition.
+    pub fn matches(&self, headers: &HeaderMap) -> bool {
+        let mut values = headers.get_all(self.name.as_str()).iter();
+        match (&self.equals, &self.regex) {
+            (Some(expected), _) => values.any(|v| v.as_bytes() == expected.as_bytes()),
+            (None, Some(regex)) => {
+                values.any(|v| v.to_str().is_ok_and(|value| regex.regex().is_match(value)))
+            }
+            (None, None) => headers.contains_key(self.name.as_str()),
+        }
+    }
+
+    pub(crate) fn effective_view(&self) -> HeaderMatchView<'_> {
+        HeaderMatchView {
+            name: &self.name,
+            equals: self.equals.as_deref(),
+            regex: self.regex.as_ref().map(RegexPattern::as_str),
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`HeaderMatch`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct HeaderMatchView<'a> {
+    name: &'a str,
+    equals: Option<&'a str>,
+    regex: Option<&'a str>,
+}
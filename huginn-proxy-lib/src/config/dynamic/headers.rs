@@ -1,6 +1,8 @@
+use http::{HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
 
 use crate::config::Secret;
+use crate::error::{ProxyError, Result};
 
 /// Custom header configuration
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -14,18 +16,94 @@ pub struct CustomHeader {
     pub value: Secret<String>,
 }
 
-/// Header manipulation for requests or responses
+/// Header manipulation for requests or responses.
+///
+/// Applied in the order `remove` → `set` → `add`. Values of `set` and `add` may reference
+/// [`TemplateVar`]s as `${name}`.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
 #[serde(deny_unknown_fields)]
 pub struct HeaderManipulationGroup {
     /// Headers to add (overwrite if exist)
     #[serde(default)]
     pub add: Vec<CustomHeader>,
+    /// Headers to rewrite; only applied when the header is already present
+    #[serde(default)]
+    pub set: Vec<CustomHeader>,
     /// Headers to remove
     #[serde(default)]
     pub remove: Vec<String>,
 }
 
+/// Per-request variable usable in `set` / `add` header values as `${name}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateVar {
+    /// `${client_ip}`: effective client IP (after PROXY protocol / trusted-proxy resolution)
+    ClientIp,
+    /// `${ja4}`: JA4 fingerprint of the TLS connection; empty on plain HTTP or when
+    /// fingerprinting is disabled for the route
+    Ja4,
+    /// `${route_prefix}`: `prefix` of the matched route
+    RoutePrefix,
+    /// `${host}`: request host the proxy routed on
+    Host,
+}
+
+impl TemplateVar {
+    pub const ALL: &'static [TemplateVar] = &[
+        TemplateVar::ClientIp,
+        TemplateVar::Ja4,
+        TemplateVar::RoutePrefix,
+        TemplateVar::Host,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TemplateVar::ClientIp => "client_ip",
+            TemplateVar::Ja4 => "ja4",
+            TemplateVar::RoutePrefix => "route_prefix",
+            TemplateVar::Host => "host",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|v| v.as_str() == name)
+    }
+}
+
+/// Expand the `${name}` references in `template`, writing each variable's value with `resolve`.
+///
+/// A `$` not followed by `{` is kept literally. Fails with a description of the problem on an
+/// unterminated `${` or an unknown variable name; both are rejected at config load, so expansion
+/// at request time cannot fail for a validated config. The template itself is never echoed in
+/// the error since header values may be secrets.
+pub fn render_header_template<F>(
+    template: &str,
+    mut resolve: F,
+) -> std::result::Result<String, String>
+where
+    F: FnMut(TemplateVar, &mut String),
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        let (literal, tail) = rest.split_at(start);
+        out.push_str(literal);
+        let tail = tail.get(2..).unwrap_or_default();
+        let Some(end) = tail.find('}') else {
+            return Err("unterminated '${'".to_string());
+        };
+        let (name, after) = tail.split_at(end);
+        let var = TemplateVar::from_name(name).ok_or_else(|| {
+            let known: Vec<_> = TemplateVar::ALL.iter().map(|v| v.as_str()).collect();
+            format!("unknown variable '${{{name}}}' (known: {})", known.join(", "))
+        })?;
+        resolve(var, &mut out);
+        rest = after.get(1..).unwrap_or_default();
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
 /// Header manipulation configuration
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
 #[serde(deny_unknown_fields)]
@@ -49,10 +127,18 @@ pub(crate) struct HeaderManipulationView<'a> {
 #[derive(Serialize)]
 struct HeaderGroupView<'a> {
     add: &'a [CustomHeader],
+    set: &'a [CustomHeader],
     remove: &'a [String],
 }
 
 impl HeaderManipulation {
+    /// Reject header names that are not valid HTTP tokens and value templates that do not
+    /// parse or that would not form a valid header value.
+    pub fn validate(&self) -> Result<()> {
+        self.request.validate("request")?;
+        self.response.validate("response")
+    }
+
     pub(crate) fn effective_view(&self) -> HeaderManipulationView<'_> {
         HeaderManipulationView {
             request: self.request.effective_view(),
@@ -62,7 +148,40 @@ impl HeaderManipulation {
 }
 
 impl HeaderManipulationGroup {
+    fn validate(&self, context: &str) -> Result<()> {
+        for name in &self.remove {
+            validate_header_name(name, context)?;
+        }
+        for header in self.set.iter().chain(&self.add) {
+            validate_header_name(&header.name, context)?;
+            // Variables expand to header-safe text; check the literal parts.
+            let sample = render_header_template(header.value.expose(), |_, _| {}).map_err(|e| {
+                ProxyError::Config(format!(
+                    "Invalid {context} header value template for '{}': {e}",
+                    header.name
+                ))
+            })?;
+            if HeaderValue::from_str(&sample).is_err() {
+                return Err(ProxyError::Config(format!(
+                    "Invalid {context} header value for '{}'",
+                    header.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
     fn effective_view(&self) -> HeaderGroupView<'_> {
-        HeaderGroupView { add: self.add.as_slice(), remove: self.remove.as_slice() }
+        HeaderGroupView {
+            add: self.add.as_slice(),
+            set: self.set.as_slice(),
+            remove: self.remove.as_slice(),
+        }
     }
 }
+
+fn validate_header_name(name: &str, context: &str) -> Result<()> {
+    HeaderName::from_bytes(name.as_bytes())
+        .map(|_| ())
+        .map_err(|e| ProxyError::Config(format!("Invalid {context} header name '{name}': {e}")))
+}
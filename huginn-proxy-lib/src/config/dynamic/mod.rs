@@ -1,18 +1,82 @@
+pub mod access_log;
 pub mod backend;
+pub mod bandwidth;
+pub mod body_rewrite;
+pub mod bot_verification;
+pub mod cache;
+pub mod challenge;
+pub mod compression;
+pub mod connect;
+pub mod discovery;
+pub mod failover;
+pub mod header_match;
 pub mod headers;
+pub mod pattern;
+pub mod privacy;
+pub mod queue;
+pub mod redirect;
+pub mod rewrite;
+pub mod route_timeout;
 pub mod security;
+pub mod serve_static;
+pub mod sticky;
+pub mod strict_http;
+pub mod synthetic;
+pub mod tarpit;
+pub mod tenant;
+pub mod tls_match;
+pub mod upstream_proxy;
+pub mod waf;
+pub use access_log::RouteAccessLogConfig;
 pub use backend::{
-    sort_domain_routes, sort_routes, Backend, BackendHttpVersion, BackendPoolConfig, Domain,
-    HealthCheckConfig, HealthCheckType, Route, DEFAULT_DOMAIN_LABEL, DEFAULT_FINGERPRINTING,
+    sort_domain_routes, sort_routes, unix_socket_path, Backend, BackendHttp2Config,
+    BackendHttpVersion, BackendPoolConfig, BackendPoolLimits, BackendTlsConfig,
+    CircuitBreakerConfig, Domain, DomainRoutes, ExtAuthzConfig, ExtAuthzFailureMode,
+    FingerprintFormat, HealthCheckConfig, HealthCheckType, Ja4Variant, PriorityHeader, Route,
+    RouteFragment, RoutePriority, RouteProtocol, WebSocketConfig, DEFAULT_DOMAIN_LABEL,
+    DEFAULT_FINGERPRINTING,
 };
-pub use headers::{CustomHeader, HeaderManipulation, HeaderManipulationGroup};
+pub use bandwidth::{BandwidthConfig, BandwidthKey};
+pub use body_rewrite::{BodyRewriteConfig, BodyRewriteRule};
+pub use bot_verification::{BotVerificationConfig, CrawlerConfig, SpoofedAction};
+pub use cache::RouteCacheConfig;
+pub use challenge::ChallengeConfig;
+pub use compression::{CompressionAlgorithm, CompressionConfig};
+pub use connect::ConnectConfig;
+pub use discovery::DiscoveryConfig;
+pub use failover::{FailoverConfig, MAX_FAILOVER_ATTEMPTS, MAX_REPLAY_BODY_BYTES};
+pub use header_match::HeaderMatch;
+pub use headers::{
+    render_header_template, CustomHeader, HeaderManipulation, HeaderManipulationGroup, TemplateVar,
+};
+pub use pattern::RegexPattern;
+pub use privacy::{
+    PrivacyConfig, PrivacyHeaderGroup, MIN_PRIVACY_HASH_KEY_BYTES, PRIVACY_KEPT_HEADERS,
+};
+pub use queue::BackendQueueConfig;
+pub use redirect::{RedirectConfig, RedirectRule};
+pub use rewrite::PathRewriteConfig;
+pub use route_timeout::RouteTimeoutConfig;
 pub use security::{
-    CspConfig, DomainSecurityConfig, HstsConfig, IpFilterConfig, IpFilterMode, LimitBy,
-    RateLimitConfig, RouteSecurityConfig, SecurityConfig, SecurityDynamicConfig, SecurityHeaders,
+    CspConfig, DomainSecurityConfig, FingerprintFilterConfig, FingerprintList, HstsConfig,
+    IpFilterConfig, IpFilterMode, IpFilterPrecedence, LimitBy, RateLimitConfig, RateLimitStore,
+    RedisStoreConfig, RouteSecurityConfig, SecurityConfig, SecurityDynamicConfig, SecurityHeaders,
+    SecurityHeadersPreset,
 };
+pub use serve_static::ServeStaticConfig;
+pub use sticky::{StickyConfig, StickyHashKey, StickyMode};
+pub use strict_http::{StrictHttpConfig, StrictHttpMode};
+pub use synthetic::{SyntheticResponseConfig, MAX_SYNTHETIC_BODY_BYTES};
+pub use tarpit::{TarpitConfig, MAX_TARPIT_DELAY_MS};
+pub use tenant::{Tenant, NO_TENANT_LABEL};
+pub use tls_match::TlsMatch;
+pub use upstream_proxy::BackendProxyConfig;
+pub use waf::{RouteWafConfig, WafConfig, WafMode, WafRule, WafRuleFile, WafRuleSet, WafTarget};
 
 use backend::{BackendPoolView, BackendView, DomainView};
+use compression::CompressionView;
 use headers::HeaderManipulationView;
+use redirect::RedirectView;
 use security::SecurityView;
 use serde::Serialize;
 use std::sync::Arc;
@@ -31,12 +95,18 @@ pub struct DynamicConfig {
     pub domains: Arc<Vec<Domain>>,
     /// Preserve the original Host header from clients when forwarding
     pub preserve_host: bool,
+    /// Host assumed for HTTP/1.0 requests without a `Host` header
+    pub default_host: Option<String>,
     /// Global header manipulation applied to all requests/responses
     pub headers: Option<HeaderManipulation>,
     /// Dynamic security policy (headers, IP filter, rate limits)
     pub security: SecurityDynamicConfig,
     /// Backend connection pool settings (idle timeout, max idle connections per host)
     pub backend_pool: BackendPoolConfig,
+    /// Global response compression; routes may replace it with their own block
+    pub compression: Option<CompressionConfig>,
+    /// Global redirects (HTTPS and path rules); a domain's own `redirect` block replaces it
+    pub redirect: Option<RedirectConfig>,
 }
 
 /// Allowlisted effective-config view of [`DynamicConfig`]. Each section mirrors one config type;
@@ -46,9 +116,12 @@ pub(crate) struct DynamicView<'a> {
     backends: Vec<BackendView<'a>>,
     domains: Vec<DomainView<'a>>,
     preserve_host: bool,
+    default_host: Option<&'a str>,
     headers: Option<HeaderManipulationView<'a>>,
     security: SecurityView<'a>,
     backend_pool: BackendPoolView,
+    compression: Option<CompressionView<'a>>,
+    redirect: Option<RedirectView<'a>>,
 }
 
 impl DynamicConfig {
@@ -57,12 +130,18 @@ impl DynamicConfig {
             backends: self.backends.iter().map(Backend::effective_view).collect(),
             domains: self.domains.iter().map(Domain::effective_view).collect(),
             preserve_host: self.preserve_host,
+            default_host: self.default_host.as_deref(),
             headers: self
                 .headers
                 .as_ref()
                 .map(HeaderManipulation::effective_view),
             security: self.security.effective_view(),
             backend_pool: self.backend_pool.effective_view(),
+            compression: self
+                .compression
+                .as_ref()
+                .map(CompressionConfig::effective_view),
+            redirect: self.redirect.as_ref().map(RedirectConfig::effective_view),
         }
     }
 }
@@ -0,0 +1,101 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{ProxyError, Result};
+
+/// A regular expression from the config, compiled when the config is deserialized.
+///
+/// An invalid pattern fails the load (or the reload, which keeps the previous config) instead
+/// of the first request that would use it. Compares and serializes as its source text.
+#[derive(Debug, Clone)]
+pub struct RegexPattern(Regex);
+
+impl RegexPattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        Regex::new(pattern)
+            .map(Self)
+            .map_err(|e| ProxyError::Config(format!("Invalid regex '{pattern}': {e}")))
+    }
+
+    /// The compiled expression.
+    pub fn regex(&self) -> &Regex {
+        &self.0
+    }
+
+    /// The pattern as written in the config.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Check that every capture group `template` (the config key `field`) references exists in
+    /// this pattern, following the `regex` crate's expansion syntax.
+    pub(crate) fn validate_template(&self, field: &str, template: &str) -> Result<()> {
+        for group in template_groups(field, template)? {
+            let defined = match group.parse::<usize>() {
+                Ok(index) => index < self.0.captures_len(),
+                Err(_) => self.0.capture_names().flatten().any(|name| name == group),
+            };
+            if !defined {
+                return Err(ProxyError::Config(format!(
+                    "{field} '{template}' references group '{group}', which '{}' does not \
+                     capture (use ${{1}} when a group number is followed by a letter or digit)",
+                    self.as_str()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Group names referenced by `template`, following the `regex` crate's expansion syntax.
+fn template_groups<'t>(field: &str, template: &'t str) -> Result<Vec<&'t str>> {
+    let mut groups = Vec::new();
+    let mut rest = template;
+    while let Some((_, after)) = rest.split_once('$') {
+        if let Some(escaped) = after.strip_prefix('$') {
+            rest = escaped;
+            continue;
+        }
+        let (group, tail) = match after.strip_prefix('{') {
+            Some(braced) => braced.split_once('}').ok_or_else(|| {
+                ProxyError::Config(format!("{field} '{template}': unclosed '${{'"))
+            })?,
+            None => after.split_at(
+                after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len()),
+            ),
+        };
+        if group.is_empty() {
+            return Err(ProxyError::Config(format!(
+                "{field} '{template}': '$' must be followed by a group (use $$ for '$')"
+            )));
+        }
+        groups.push(group);
+        rest = tail;
+    }
+    Ok(groups)
+}
+
+impl PartialEq for RegexPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for RegexPattern {}
+
+impl<'de> Deserialize<'de> for RegexPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern)
+            .map(Self)
+            .map_err(|e| serde::de::Error::custom(format!("invalid regex '{pattern}': {e}")))
+    }
+}
+
+impl Serialize for RegexPattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
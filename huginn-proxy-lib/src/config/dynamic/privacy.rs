@@ -0,0 +1,169 @@
+use http::HeaderName;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Secret;
+use crate::error::{ProxyError, Result};
+
+/// Shortest accepted `hash_key`, in bytes.
+pub const MIN_PRIVACY_HASH_KEY_BYTES: usize = 32;
+
+/// Headers a privacy policy never touches: message framing and protocol upgrades, without which
+/// the request or response could not be forwarded. `sec-websocket-*` headers are kept too.
+pub const PRIVACY_KEPT_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "transfer-encoding",
+    "content-encoding",
+    "connection",
+    "upgrade",
+    "te",
+    "trailer",
+];
+
+/// Header privacy of a route (`privacy` on a route), for routes whose backend is a third party.
+///
+/// Request headers are scrubbed before forwarding, after header manipulation, so they leave the
+/// proxy exactly as configured here (fingerprint signing runs last and covers the result);
+/// response headers are scrubbed as soon as the backend answers, before caching, compression and
+/// header manipulation. In each direction `allow` (when set) drops every header it does not
+/// list, then `strip` headers are removed and `hash` headers have each value replaced by its
+/// keyed hash, so the backend can still correlate requests without learning the value.
+/// Framing and upgrade headers ([`PRIVACY_KEPT_HEADERS`]) are never scrubbed.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PrivacyConfig {
+    /// Headers of requests sent to the backend.
+    #[serde(default)]
+    pub request: PrivacyHeaderGroup,
+    /// Headers of responses from the backend.
+    #[serde(default)]
+    pub response: PrivacyHeaderGroup,
+    /// HMAC-SHA256 key of `hash` values, at least 32 bytes. Required when a `hash` list is set.
+    #[serde(default)]
+    pub hash_key: Option<Secret<String>>,
+}
+
+/// Header scrubbing of one direction of a route's traffic.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PrivacyHeaderGroup {
+    /// Headers allowed through; any other is removed. `None` (default) allows every header.
+    #[serde(default)]
+    pub allow: Option<Vec<String>>,
+    /// Headers removed.
+    #[serde(default)]
+    pub strip: Vec<String>,
+    /// Headers whose values are replaced by the lowercase hex HMAC-SHA256 of the value.
+    #[serde(default)]
+    pub hash: Vec<String>,
+}
+
+impl PrivacyConfig {
+    /// Reject invalid or kept header names, a header both stripped and hashed, `hash` lists
+    /// without a `hash_key` and a short key. `context` prefixes error messages.
+    pub fn validate(&self, context: &str) -> Result<()> {
+        self.request.validate(&format!("{context}.request"))?;
+        self.response.validate(&format!("{context}.response"))?;
+        let hashes = !self.request.hash.is_empty() || !self.response.hash.is_empty();
+        match &self.hash_key {
+            None if hashes => {
+                Err(ProxyError::Config(format!("{context}: hash lists need a hash_key")))
+            }
+            Some(key) if key.expose().len() < MIN_PRIVACY_HASH_KEY_BYTES => {
+                Err(ProxyError::Config(format!(
+                    "{context}.hash_key must be at least {MIN_PRIVACY_HASH_KEY_BYTES} bytes"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn effective_view(&self) -> PrivacyView<'_> {
+        PrivacyView {
+            request: self.request.effective_view(),
+            response: self.response.effective_view(),
+            hash_key: self.hash_key.as_ref(),
+        }
+    }
+}
+
+impl PrivacyHeaderGroup {
+    /// Whether the group scrubs anything.
+    pub fn is_active(&self) -> bool {
+        self.allow.is_some() || !self.strip.is_empty() || !self.hash.is_empty()
+    }
+
+    /// Whether a header named `name` reaches the other side of this group unchanged.
+    pub fn lets_through(&self, name: &str) -> bool {
+        let listed = |names: &[String]| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+        is_kept_header(name)
+            || (self.allow.as_deref().is_none_or(listed)
+                && !listed(&self.strip)
+                && !listed(&self.hash))
+    }
+
+    fn validate(&self, context: &str) -> Result<()> {
+        let lists = [
+            ("allow", self.allow.as_deref().unwrap_or_default()),
+            ("strip", self.strip.as_slice()),
+            ("hash", self.hash.as_slice()),
+        ];
+        for (key, names) in lists {
+            for name in names {
+                if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                    return Err(ProxyError::Config(format!(
+                        "{context}.{key}: invalid header name '{name}'"
+                    )));
+                }
+                if key != "allow" && is_kept_header(name) {
+                    return Err(ProxyError::Config(format!(
+                        "{context}.{key}: '{name}' carries message framing and cannot be scrubbed"
+                    )));
+                }
+            }
+        }
+        if let Some(name) = self
+            .hash
+            .iter()
+            .find(|name| self.strip.iter().any(|s| s.eq_ignore_ascii_case(name)))
+        {
+            return Err(ProxyError::Config(format!(
+                "{context}: '{name}' is in both strip and hash"
+            )));
+        }
+        Ok(())
+    }
+
+    fn effective_view(&self) -> PrivacyHeaderGroupView<'_> {
+        PrivacyHeaderGroupView {
+            allow: self.allow.as_deref(),
+            strip: &self.strip,
+            hash: &self.hash,
+        }
+    }
+}
+
+/// Whether `name` is one of the headers a privacy policy never touches.
+pub fn is_kept_header(name: &str) -> bool {
+    PRIVACY_KEPT_HEADERS
+        .iter()
+        .any(|kept| kept.eq_ignore_ascii_case(name))
+        || name
+            .get(..14)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("sec-websocket-"))
+}
+
+/// Allowlisted effective-config view of [`PrivacyConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct PrivacyView<'a> {
+    request: PrivacyHeaderGroupView<'a>,
+    response: PrivacyHeaderGroupView<'a>,
+    hash_key: Option<&'a Secret<String>>,
+}
+
+#[derive(Serialize)]
+struct PrivacyHeaderGroupView<'a> {
+    allow: Option<&'a [String]>,
+    strip: &'a [String],
+    hash: &'a [String],
+}
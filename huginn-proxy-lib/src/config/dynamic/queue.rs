@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Wait queue of a backend at its `max_in_flight` limit (`queue` on a backend).
+///
+/// Requests over the limit wait for a slot instead of being answered `503` right away. A freed
+/// slot goes to the highest-priority waiter (route `priority`, or `priority_headers`), oldest
+/// first. When the queue is full, a request displaces the newest waiter of a lower priority, or
+/// is answered `429` itself.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BackendQueueConfig {
+    /// Requests that may wait for a slot at once.
+    pub max_queued: usize,
+    /// Milliseconds a request may wait before it is answered `503`.
+    /// Default: 5000
+    #[serde(default = "default_queue_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_queue_timeout_ms() -> u64 {
+    5000
+}
+
+impl BackendQueueConfig {
+    pub fn validate(&self, address: &str) -> Result<()> {
+        if self.max_queued == 0 || self.timeout_ms == 0 {
+            return Err(ProxyError::Config(format!(
+                "Backend '{address}': queue.max_queued and queue.timeout_ms must be greater than 0"
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    pub(crate) fn effective_view(&self) -> BackendQueueView {
+        BackendQueueView { max_queued: self.max_queued, timeout_ms: self.timeout_ms }
+    }
+}
+
+/// Allowlisted effective-config view of [`BackendQueueConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct BackendQueueView {
+    max_queued: usize,
+    timeout_ms: u64,
+}
@@ -0,0 +1,147 @@
+use http::HeaderValue;
+use serde::{Deserialize, Serialize};
+
+use super::pattern::RegexPattern;
+use crate::error::{ProxyError, Result};
+
+/// Status codes accepted for `https_status` and `rules[].status`.
+const REDIRECT_STATUSES: [u16; 5] = [301, 302, 303, 307, 308];
+
+/// Redirects answered by the proxy before routing (`[redirect]` globally, `redirect` on a
+/// domain).
+///
+/// `https` sends requests received on a plaintext listener to the same URL over HTTPS. `rules`
+/// are tried in order and the first whose `path` matches answers with a redirect to `to`, built
+/// from the path's capture groups. A domain-level block fully replaces the global one.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RedirectConfig {
+    /// Redirect plaintext requests to HTTPS (default: false). Requests forwarded by a trusted
+    /// proxy with `X-Forwarded-Proto: https` already count as HTTPS.
+    #[serde(default)]
+    pub https: bool,
+    /// Port written into the HTTPS `Location`; 443 is left out of the URL (default: 443).
+    #[serde(default = "default_https_port")]
+    pub https_port: u16,
+    /// Status of the HTTPS redirect (default: 308, which keeps the method and body).
+    #[serde(default = "default_https_status")]
+    pub https_status: u16,
+    /// Path redirects, tried in order before `https`.
+    #[serde(default)]
+    pub rules: Vec<RedirectRule>,
+}
+
+/// One entry of `redirect.rules`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RedirectRule {
+    /// Only redirect requests for this host (case-insensitive). Any host when unset.
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Regex matched against the request path, e.g. `^/old/(.*)$`.
+    pub path: RegexPattern,
+    /// `Location` template: an absolute URL or a path. `$1` / `${1}` and `${name}` insert capture
+    /// groups of `path`, `$$` a literal `$`.
+    pub to: String,
+    /// Redirect status: 301, 302, 303, 307 or 308 (default: 301).
+    #[serde(default = "default_rule_status")]
+    pub status: u16,
+    /// Append the request's query string to the target (default: true).
+    #[serde(default = "default_keep_query")]
+    pub keep_query: bool,
+}
+
+impl RedirectConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.https_port == 0 {
+            return Err(ProxyError::Config("redirect.https_port must be > 0".to_string()));
+        }
+        validate_status("redirect.https_status", self.https_status)?;
+        for rule in &self.rules {
+            rule.validate()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> RedirectView<'_> {
+        RedirectView {
+            https: self.https,
+            https_port: self.https_port,
+            https_status: self.https_status,
+            rules: self
+                .rules
+                .iter()
+                .map(|rule| RedirectRuleView {
+                    host: rule.host.as_deref(),
+                    path: rule.path.as_str(),
+                    to: &rule.to,
+                    status: rule.status,
+                    keep_query: rule.keep_query,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl RedirectRule {
+    pub fn validate(&self) -> Result<()> {
+        validate_status("redirect.rules.status", self.status)?;
+        if !(self.to.starts_with('/') || self.to.contains("://")) {
+            return Err(ProxyError::Config(format!(
+                "redirect.rules.to '{}' must be an absolute URL or start with '/'",
+                self.to
+            )));
+        }
+        if HeaderValue::from_str(&self.to).is_err() {
+            return Err(ProxyError::Config(format!(
+                "redirect.rules.to '{}' is not a valid Location value",
+                self.to
+            )));
+        }
+        self.path.validate_template("redirect.rules.to", &self.to)
+    }
+}
+
+fn validate_status(field: &str, status: u16) -> Result<()> {
+    if REDIRECT_STATUSES.contains(&status) {
+        Ok(())
+    } else {
+        Err(ProxyError::Config(format!(
+            "{field} must be one of 301, 302, 303, 307, 308, got {status}"
+        )))
+    }
+}
+
+fn default_https_port() -> u16 {
+    443
+}
+
+fn default_https_status() -> u16 {
+    308
+}
+
+fn default_rule_status() -> u16 {
+    301
+}
+
+fn default_keep_query() -> bool {
+    true
+}
+
+/// Allowlisted effective-config view of [`RedirectConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct RedirectView<'a> {
+    https: bool,
+    https_port: u16,
+    https_status: u16,
+    rules: Vec<RedirectRuleView<'a>>,
+}
+
+#[derive(Serialize)]
+struct RedirectRuleView<'a> {
+    host: Option<&'a str>,
+    path: &'a str,
+    to: &'a str,
+    status: u16,
+    keep_query: bool,
+}
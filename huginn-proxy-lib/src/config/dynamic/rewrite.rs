@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use super::pattern::RegexPattern;
+use crate::error::{ProxyError, Result};
+
+/// Regex rewrite of the path and query sent to the backend (`rewrite` on a route).
+///
+/// When `path` matches the request path, the backend receives `to` instead, built from the
+/// path's capture groups; `to` may carry a query string of its own, e.g. `^/v(\d+)/(.*)$` →
+/// `/api/$2?version=$1`. A path that does not match is forwarded unchanged. Exclusive with
+/// `replace_path`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PathRewriteConfig {
+    /// Regex matched against the request path, e.g. `^/v(\d+)/(.*)$`.
+    pub path: RegexPattern,
+    /// Path and optional query sent to the backend. `$1` / `${1}` and `${name}` insert capture
+    /// groups of `path`, `$$` a literal `$`.
+    pub to: String,
+    /// Append the request's query string to the rewritten one (default: true).
+    #[serde(default = "default_keep_query")]
+    pub keep_query: bool,
+}
+
+impl PathRewriteConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.to.starts_with('/') {
+            return Err(ProxyError::Config(format!(
+                "rewrite.to '{}' must start with '/'",
+                self.to
+            )));
+        }
+        if self.to.contains('#') {
+            return Err(ProxyError::Config(format!(
+                "rewrite.to '{}' cannot contain a fragment",
+                self.to
+            )));
+        }
+        self.path.validate_template("rewrite.to", &self.to)
+    }
+
+    /// Path and query for the backend when `path` matches the request's `path`; `None` keeps
+    /// the request's own.
+    pub fn apply(&self, path: &str, query: Option<&str>) -> Option<String> {
+        let captures = self.path.regex().captures(path)?;
+        let mut target = String::new();
+        captures.expand(&self.to, &mut target);
+        if let Some(query) = query.filter(|q| self.keep_query && !q.is_empty()) {
+            target.push(if target.contains('?') { '&' } else { '?' });
+            target.push_str(query);
+        }
+        Some(target)
+    }
+
+    pub(crate) fn effective_view(&self) -> PathRewriteView<'_> {
+        PathRewriteView { path: self.path.as_str(), to: &self.to, keep_query: self.keep_query }
+    }
+}
+
+fn default_keep_query() -> bool {
+    true
+}
+
+/// Allowlisted effective-config view of [`PathRewriteConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct PathRewriteView<'a> {
+    path: &'a str,
+    to: &'a str,
+    keep_query: bool,
+}
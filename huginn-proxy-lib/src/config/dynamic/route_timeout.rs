@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Backend timeouts of a route (`timeout` on a route).
+///
+/// Each field overrides one limit for requests of this route; unset fields keep the global
+/// behavior (`[timeout] upstream_connect_ms` for connects, no limit for the rest). A request that
+/// runs out of time is answered `504` if the response has not started yet, and aborted otherwise.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RouteTimeoutConfig {
+    /// TCP connect timeout to the backend in milliseconds. Replaces `[timeout]
+    /// upstream_connect_ms`, higher or lower. Only applies when a new connection is opened.
+    #[serde(default)]
+    pub connect_ms: Option<u64>,
+    /// Time allowed from dispatching the request to the backend's response head, in
+    /// milliseconds (connect and request upload included).
+    #[serde(default)]
+    pub first_byte_ms: Option<u64>,
+    /// Time allowed for the whole exchange, response body included, in milliseconds.
+    #[serde(default)]
+    pub total_ms: Option<u64>,
+}
+
+impl RouteTimeoutConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("connect_ms", self.connect_ms),
+            ("first_byte_ms", self.first_byte_ms),
+            ("total_ms", self.total_ms),
+        ] {
+            if value == Some(0) {
+                return Err(ProxyError::Config(format!("timeout.{name} must be greater than 0")));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> RouteTimeoutView {
+        RouteTimeoutView {
+            connect_ms: self.connect_ms,
+            first_byte_ms: self.first_byte_ms,
+            total_ms: self.total_ms,
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`RouteTimeoutConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct RouteTimeoutView {
+    connect_ms: Option<u64>,
+    first_byte_ms: Option<u64>,
+    total_ms: Option<u64>,
+}
@@ -1,9 +1,17 @@
+use std::collections::BTreeMap;
 use std::net::IpAddr;
 
+use http::{HeaderName, HeaderValue};
 use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 
+use super::bot_verification::{BotVerificationConfig, BotVerificationView};
+use super::challenge::{ChallengeConfig, ChallengeView};
 use super::headers::CustomHeader;
+use super::strict_http::{StrictHttpConfig, StrictHttpView};
+use super::tarpit::{TarpitConfig, TarpitView};
+use super::tls_match::{TlsMatch, TlsMatchView};
+use super::waf::{WafConfig, WafView};
 use crate::config::Secret;
 
 /// Security configuration (used for TOML deserialization via Config)
@@ -13,6 +21,10 @@ pub struct SecurityConfig {
     /// Maximum number of concurrent connections allowed (static requires restart to change)
     #[serde(default = "default_max_connections")]
     pub max_connections: usize,
+    /// Maximum concurrent connections from one client IP (static requires restart to change).
+    /// `None` means no per-IP limit.
+    #[serde(default)]
+    pub max_connections_per_ip: Option<usize>,
     /// Security headers configuration
     #[serde(default)]
     pub headers: SecurityHeaders,
@@ -28,16 +40,48 @@ pub struct SecurityConfig {
     /// route, so it is configured once globally and is **not** overridable per domain/route.
     #[serde(default)]
     pub trusted_proxies: TrustedProxiesConfig,
+    /// JA4 / Akamai / TCP fingerprint allow and deny lists (`[security.fingerprint_filter]`).
+    ///
+    /// Global only: fingerprints describe the client connection, not the route it asks for.
+    #[serde(default)]
+    pub fingerprint_filter: FingerprintFilterConfig,
+    /// Web application firewall rules and limits (`[security.waf]`). Routes switch it on or
+    /// off with their own `waf` block.
+    #[serde(default)]
+    pub waf: WafConfig,
+    /// Reverse-DNS verification of clients claiming to be crawlers
+    /// (`[security.bot_verification]`). Global only.
+    #[serde(default)]
+    pub bot_verification: BotVerificationConfig,
+    /// Strict protocol conformance checks against request smuggling (`[security.strict_http]`).
+    /// Global only.
+    #[serde(default)]
+    pub strict_http: StrictHttpConfig,
+    /// Challenge page served to suspect fingerprints instead of forwarding
+    /// (`[security.challenge]`). Global only.
+    #[serde(default)]
+    pub challenge: ChallengeConfig,
+    /// Delayed TLS handshakes for graylisted IPs and JA4 fingerprints (`[security.tarpit]`).
+    /// Global only.
+    #[serde(default)]
+    pub tarpit: TarpitConfig,
 }
 
 impl Default for SecurityConfig {
     fn default() -> Self {
         Self {
             max_connections: default_max_connections(),
+            max_connections_per_ip: None,
             headers: SecurityHeaders::default(),
             ip_filter: IpFilterConfig::default(),
             rate_limit: RateLimitConfig::default(),
             trusted_proxies: TrustedProxiesConfig::default(),
+            fingerprint_filter: FingerprintFilterConfig::default(),
+            waf: WafConfig::default(),
+            bot_verification: BotVerificationConfig::default(),
+            strict_http: StrictHttpConfig::default(),
+            challenge: ChallengeConfig::default(),
+            tarpit: TarpitConfig::default(),
         }
     }
 }
@@ -79,6 +123,24 @@ impl TrustedProxiesConfig {
     pub fn has_trust(&self) -> bool {
         self.insecure || !self.cidrs.is_empty()
     }
+
+    /// The client behind the trusted proxies: when `peer_ip` is trusted, the right-most
+    /// `X-Forwarded-For` entry that is not, otherwise `peer_ip` itself. Falls back to `peer_ip`
+    /// if every entry is trusted or the header is absent.
+    pub fn client_ip(&self, peer_ip: IpAddr, headers: &http::HeaderMap) -> IpAddr {
+        if !self.trusts(&peer_ip) {
+            return peer_ip;
+        }
+        headers
+            .get("x-forwarded-for")
+            .and_then(|xff| xff.to_str().ok())
+            .and_then(|xff| {
+                xff.rsplit(',')
+                    .filter_map(|raw| raw.trim().parse::<IpAddr>().ok())
+                    .find(|ip| !self.trusts(ip))
+            })
+            .unwrap_or(peer_ip)
+    }
 }
 
 pub(crate) fn default_max_connections() -> usize {
@@ -105,12 +167,17 @@ pub struct DomainSecurityConfig {
     /// Per-route rate-limit overrides then overlay onto this domain-effective config.
     #[serde(default)]
     pub rate_limit: Option<RateLimitConfig>,
+    /// Set at load time when `rate_limit` is the tenant's: the tenant's domains that inherit it
+    /// count against one limiter.
+    #[serde(skip)]
+    pub tenant_rate_limit: bool,
 }
 
 /// Dynamic security configuration (hot-reloadable at runtime via ArcSwap)
 ///
-/// Contains only the fields that can change without restart. `max_connections`
-/// is excluded because it controls a process-level resource (ConnectionManager).
+/// Contains only the fields that can change without restart. `max_connections` and
+/// `max_connections_per_ip` are excluded because they control a process-level resource
+/// (ConnectionManager).
 #[derive(Debug, Clone, PartialEq)]
 pub struct SecurityDynamicConfig {
     /// Security headers injected into responses
@@ -121,12 +188,31 @@ pub struct SecurityDynamicConfig {
     pub rate_limit: RateLimitConfig,
     /// Trusted reverse-proxy configuration (global, not overridable per scope).
     pub trusted_proxies: TrustedProxiesConfig,
+    /// Fingerprint allow/deny lists (global, not overridable per scope).
+    pub fingerprint_filter: FingerprintFilterConfig,
+    /// Web application firewall (per-route on/off and mode).
+    pub waf: WafConfig,
+    /// Crawler verification (global, not overridable per scope).
+    pub bot_verification: BotVerificationConfig,
+    /// Strict protocol conformance checks (global, not overridable per scope).
+    pub strict_http: StrictHttpConfig,
+    /// Challenge page for suspect fingerprints (global, not overridable per scope).
+    pub challenge: ChallengeConfig,
+    /// ClientHello tarpit for graylisted clients (global, not overridable per scope).
+    pub tarpit: TarpitConfig,
 }
 
 /// Security headers configuration
+///
+/// Applied to backend responses in the order `remove` → `preset` → `custom` → HSTS → CSP, so
+/// `custom` overrides a preset header of the same name and an enabled `hsts` / `csp` block
+/// replaces the preset's value.
 #[derive(Debug, Deserialize, Clone, PartialEq, Default)]
 #[serde(deny_unknown_fields)]
 pub struct SecurityHeaders {
+    /// Baseline header set (default: off)
+    #[serde(default)]
+    pub preset: SecurityHeadersPreset,
     /// Custom headers to add to all responses
     #[serde(default)]
     pub custom: Vec<CustomHeader>,
@@ -136,6 +222,61 @@ pub struct SecurityHeaders {
     /// CSP (Content Security Policy) configuration
     #[serde(default)]
     pub csp: CspConfig,
+    /// Headers stripped from backend responses (e.g. `server`, `x-powered-by`)
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+impl SecurityHeaders {
+    /// `scope` names the block in error messages (`security.headers`, ...).
+    pub fn validate(&self, scope: &str) -> crate::error::Result<()> {
+        for name in &self.remove {
+            HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+                crate::error::ProxyError::Config(format!(
+                    "{scope}: invalid remove header name '{name}': {e}"
+                ))
+            })?;
+        }
+        self.csp.validate(scope)
+    }
+
+    /// Whether HTTPS responses get `Strict-Transport-Security`, from `hsts` or the preset.
+    pub fn sends_hsts(&self) -> bool {
+        self.hsts.enabled || self.preset != SecurityHeadersPreset::Off
+    }
+
+    /// Whether responses get a Content Security Policy, from `csp` or the preset.
+    pub fn sends_csp(&self) -> bool {
+        self.csp.enabled || self.preset == SecurityHeadersPreset::Strict
+    }
+}
+
+/// Baseline set of security headers (`preset` in a `headers` block)
+///
+/// Both `balanced` and `strict` strip the `Server` and `X-Powered-By` headers of backend
+/// responses and send HSTS on HTTPS connections.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SecurityHeadersPreset {
+    /// No baseline; only the configured `custom`, `hsts` and `csp` headers are sent
+    #[default]
+    Off,
+    /// Headers safe for most sites: `nosniff`, same-origin framing,
+    /// `strict-origin-when-cross-origin` referrers and a one-year HSTS
+    Balanced,
+    /// Locked-down headers: no framing, no referrer, same-origin COOP / CORP, a restrictive
+    /// `Permissions-Policy` and CSP, and a two-year HSTS including subdomains
+    Strict,
+}
+
+impl SecurityHeadersPreset {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SecurityHeadersPreset::Off => "off",
+            SecurityHeadersPreset::Balanced => "balanced",
+            SecurityHeadersPreset::Strict => "strict",
+        }
+    }
 }
 
 /// HSTS (HTTP Strict Transport Security) configuration
@@ -195,11 +336,74 @@ pub struct CspConfig {
     /// (e.g. `connect-src` hosts), so they are never exposed in the effective-config view or logs.
     #[serde(default = "default_csp_policy")]
     pub policy: Secret<String>,
+    /// Policy built from directives, e.g. `script-src = ["'self'", "cdn.example.com"]`.
+    /// Replaces `policy` when non-empty; a directive with no sources (e.g.
+    /// `upgrade-insecure-requests = []`) is sent bare. Only the directive names are exposed in
+    /// the effective-config view.
+    #[serde(default)]
+    pub directives: BTreeMap<String, Vec<String>>,
+    /// Send the policy as `Content-Security-Policy-Report-Only`: violations are reported but
+    /// not blocked (default: false)
+    #[serde(default)]
+    pub report_only: bool,
 }
 
 impl Default for CspConfig {
     fn default() -> Self {
-        Self { enabled: false, policy: default_csp_policy() }
+        Self {
+            enabled: false,
+            policy: default_csp_policy(),
+            directives: BTreeMap::new(),
+            report_only: false,
+        }
+    }
+}
+
+impl CspConfig {
+    /// The policy sent: `directives` joined as `name source ...; ...`, or `policy` when there
+    /// are none.
+    pub fn header_value(&self) -> String {
+        if self.directives.is_empty() {
+            return self.policy.expose().clone();
+        }
+        self.directives
+            .iter()
+            .map(|(name, sources)| {
+                std::iter::once(name.as_str())
+                    .chain(sources.iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    fn validate(&self, scope: &str) -> crate::error::Result<()> {
+        for (name, sources) in &self.directives {
+            if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "{scope}: invalid csp directive name '{name}'"
+                )));
+            }
+            // Sources are not echoed: they are as sensitive as the policy string.
+            if sources.iter().any(|source| {
+                source.is_empty()
+                    || !source
+                        .bytes()
+                        .all(|b| b.is_ascii_graphic() && b != b';' && b != b',')
+            }) {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "{scope}: csp directive '{name}' has an empty source or one containing \
+                     whitespace, ';' or ','"
+                )));
+            }
+        }
+        if self.enabled && HeaderValue::from_str(&self.header_value()).is_err() {
+            return Err(crate::error::ProxyError::Config(format!(
+                "{scope}: csp policy is not a valid header value"
+            )));
+        }
+        Ok(())
     }
 }
 
@@ -218,6 +422,19 @@ pub enum IpFilterMode {
     Allowlist,
     /// Block IPs in the denylist
     Denylist,
+    /// Evaluate both lists; `precedence` decides IPs that match both
+    Both,
+}
+
+/// Which list wins when an IP matches both the allowlist and the denylist (`mode = "both"`)
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFilterPrecedence {
+    /// A denylist match blocks, even inside an allowlisted range
+    #[default]
+    Deny,
+    /// An allowlist match allows, even inside a denylisted range
+    Allow,
 }
 
 /// IP filtering (ACL) configuration
@@ -237,16 +454,144 @@ pub struct IpFilterConfig {
     #[serde(default)]
     #[serde(deserialize_with = "deserialize_ip_networks")]
     pub denylist: Vec<IpNet>,
+    /// Winner for IPs matching both lists (when mode = "both"). IPs matching neither are
+    /// blocked if the allowlist is non-empty, allowed otherwise.
+    /// Default: `deny`
+    #[serde(default)]
+    pub precedence: IpFilterPrecedence,
+    /// Filter the client behind trusted proxies instead of the TCP peer: when the peer is in
+    /// `[security.trusted_proxies]`, the right-most `X-Forwarded-For` hop outside them is checked.
+    /// Default: false
+    #[serde(default)]
+    pub use_forwarded_for: bool,
+    /// Filter only requests whose TLS connection matches (SNI, ALPN, JA4); others are allowed.
+    /// Default: every request
+    #[serde(default)]
+    pub match_tls: Option<TlsMatch>,
 }
 
 impl Default for IpFilterConfig {
     fn default() -> Self {
-        Self { mode: IpFilterMode::Disabled, allowlist: vec![], denylist: vec![] }
+        Self {
+            mode: IpFilterMode::Disabled,
+            allowlist: vec![],
+            denylist: vec![],
+            precedence: IpFilterPrecedence::Deny,
+            use_forwarded_for: false,
+            match_tls: None,
+        }
+    }
+}
+
+impl IpFilterConfig {
+    /// `scope` names the block in error messages (`security.ip_filter`, ...).
+    pub fn validate(&self, scope: &str) -> crate::error::Result<()> {
+        match &self.match_tls {
+            Some(condition) => condition.validate(&format!("{scope}.match_tls")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Fingerprint allow/deny lists (`[security.fingerprint_filter]`).
+///
+/// A request whose connection fingerprints match a `deny` entry, and none of the `allow`
+/// entries, is answered with `status` before routing. Entries match a fingerprint exactly, or
+/// by prefix when they end with `*` (e.g. `t13d1516h2_*`). Empty lists (default) disable the
+/// filter.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FingerprintFilterConfig {
+    /// Fingerprints that are never blocked, even when they also match a `deny` entry.
+    #[serde(default)]
+    pub allow: FingerprintList,
+    /// Fingerprints whose requests are rejected.
+    #[serde(default)]
+    pub deny: FingerprintList,
+    /// Status of the synthetic response for blocked requests (4xx). Default: 403.
+    #[serde(default = "default_fingerprint_filter_status")]
+    pub status: u16,
+}
+
+impl Default for FingerprintFilterConfig {
+    fn default() -> Self {
+        Self {
+            allow: FingerprintList::default(),
+            deny: FingerprintList::default(),
+            status: default_fingerprint_filter_status(),
+        }
     }
 }
 
+impl FingerprintFilterConfig {
+    /// Whether any request can be blocked (at least one `deny` entry).
+    pub fn is_active(&self) -> bool {
+        !self.deny.is_empty()
+    }
+
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if !(400..=499).contains(&self.status) {
+            return Err(crate::error::ProxyError::Config(format!(
+                "security.fingerprint_filter.status must be a 4xx status, got {}",
+                self.status
+            )));
+        }
+        for (list, entries) in [("allow", &self.allow), ("deny", &self.deny)] {
+            for (kind, entry) in entries.entries() {
+                if entry.is_empty() || entry == "*" {
+                    return Err(crate::error::ProxyError::Config(format!(
+                        "security.fingerprint_filter.{list}.{kind} entries must not be empty \
+                         or a bare '*'"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fingerprint entries per signal. JA4 entries are compared with every JA4 variant of the
+/// connection (`ja4`, `ja4_r`, `ja4_o`, `ja4_or`, `ja4_s1`, `ja4_s1r`).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FingerprintList {
+    /// TLS ClientHello fingerprints (any JA4 variant).
+    #[serde(default)]
+    pub ja4: Vec<String>,
+    /// HTTP/2 Akamai fingerprints (only observed on HTTP/2 requests).
+    #[serde(default)]
+    pub akamai: Vec<String>,
+    /// TCP SYN (p0f-style) signatures.
+    #[serde(default)]
+    pub tcp: Vec<String>,
+}
+
+impl FingerprintList {
+    pub fn is_empty(&self) -> bool {
+        self.ja4.is_empty() && self.akamai.is_empty() && self.tcp.is_empty()
+    }
+
+    /// Every entry with the key of the list it belongs to (`ja4`, `akamai`, `tcp`).
+    pub fn entries(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        tagged("ja4", &self.ja4)
+            .chain(tagged("akamai", &self.akamai))
+            .chain(tagged("tcp", &self.tcp))
+    }
+}
+
+fn tagged<'a>(
+    kind: &'static str,
+    list: &'a [String],
+) -> impl Iterator<Item = (&'static str, &'a str)> + 'a {
+    list.iter().map(move |entry| (kind, entry.as_str()))
+}
+
+fn default_fingerprint_filter_status() -> u16 {
+    403
+}
+
 /// Custom deserializer for IP networks that handles parsing errors gracefully
-fn deserialize_ip_networks<'de, D>(deserializer: D) -> Result<Vec<IpNet>, D::Error>
+pub(super) fn deserialize_ip_networks<'de, D>(deserializer: D) -> Result<Vec<IpNet>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
@@ -292,6 +637,19 @@ pub struct RateLimitConfig {
     /// Custom header name for "header" limit_by mode
     /// Required when limit_by = "header"
     pub limit_by_header: Option<String>,
+    /// Where the counters live: "memory" (per process) or "redis" (shared by every replica)
+    /// Default: "memory"
+    #[serde(default)]
+    pub store: RateLimitStore,
+    /// Redis connection for store = "redis"
+    /// Required when store = "redis"
+    #[serde(default)]
+    pub redis: Option<RedisStoreConfig>,
+    /// Limit only requests whose TLS connection matches (SNI, ALPN, JA4); others are not limited
+    /// by this block, e.g. `{ sni = [""] }` for clients that sent no SNI.
+    /// Default: every request
+    #[serde(default)]
+    pub match_tls: Option<TlsMatch>,
 }
 
 impl Default for RateLimitConfig {
@@ -303,10 +661,113 @@ impl Default for RateLimitConfig {
             window_seconds: default_window_seconds(),
             limit_by: default_limit_by(),
             limit_by_header: None,
+            store: RateLimitStore::default(),
+            redis: None,
+            match_tls: None,
         }
     }
 }
 
+impl RateLimitConfig {
+    /// `scope` names the block in error messages (`security.rate_limit`, ...).
+    pub fn validate(&self, scope: &str) -> crate::error::Result<()> {
+        if let Some(condition) = &self.match_tls {
+            condition.validate(&format!("{scope}.match_tls"))?;
+        }
+        match (self.store, &self.redis) {
+            (RateLimitStore::Redis, None) => Err(crate::error::ProxyError::Config(format!(
+                "{scope}: store = \"redis\" requires a redis block"
+            ))),
+            (RateLimitStore::Redis, Some(redis)) => redis.validate(scope),
+            (RateLimitStore::Memory, _) => Ok(()),
+        }
+    }
+}
+
+/// Counter store of a rate limiter
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitStore {
+    /// Counters in process memory; each replica enforces the limit on its own
+    #[default]
+    Memory,
+    /// Counters in Redis, shared by every replica pointing at the same server.
+    /// Falls back to the in-memory counters while Redis is unreachable
+    Redis,
+}
+
+impl RateLimitStore {
+    fn as_str(self) -> &'static str {
+        match self {
+            RateLimitStore::Memory => "memory",
+            RateLimitStore::Redis => "redis",
+        }
+    }
+}
+
+/// Redis connection of a `store = "redis"` rate limiter
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RedisStoreConfig {
+    /// Server URL, `redis://[user:password@]host[:port][/db]`
+    pub url: Secret<String>,
+    /// Multiplexed connections shared by all requests
+    /// Default: 4
+    #[serde(default = "default_redis_pool_size")]
+    pub pool_size: usize,
+    /// Time budget of one counter update; past it the in-memory counters decide
+    /// Default: 50
+    #[serde(default = "default_redis_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Prefix of every counter key
+    /// Default: "huginn:rl:"
+    #[serde(default = "default_redis_key_prefix")]
+    pub key_prefix: String,
+}
+
+impl RedisStoreConfig {
+    pub(crate) fn validate(&self, scope: &str) -> crate::error::Result<()> {
+        let url = self.url.expose();
+        if !url.starts_with("redis://") {
+            return Err(crate::error::ProxyError::Config(format!(
+                "{scope}: redis.url must be a redis:// URL"
+            )));
+        }
+        if self.pool_size == 0 {
+            return Err(crate::error::ProxyError::Config(format!(
+                "{scope}: redis.pool_size must be > 0"
+            )));
+        }
+        if self.timeout_ms == 0 {
+            return Err(crate::error::ProxyError::Config(format!(
+                "{scope}: redis.timeout_ms must be > 0"
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> RedisStoreView<'_> {
+        RedisStoreView {
+            url: &self.url,
+            pool_size: self.pool_size,
+            timeout_ms: self.timeout_ms,
+            key_prefix: &self.key_prefix,
+        }
+    }
+}
+
+fn default_redis_pool_size() -> usize {
+    4
+}
+
+fn default_redis_timeout_ms() -> u64 {
+    50
+}
+
+fn default_redis_key_prefix() -> String {
+    "huginn:rl:".to_string()
+}
+
 /// Per-route security policy override (`[domains.routes.security]`).
 ///
 /// Mirrors the per-domain `security` block (`global → domain → route`). Each field, when
@@ -343,6 +804,23 @@ pub enum LimitBy {
     /// Rate limit by combination of IP and route
     /// Provides per-IP limits that are also route-specific
     Combined,
+    /// Rate limit by the connection's JA4 fingerprint
+    /// Clients sharing a TLS stack share the limit, whatever their IP. Falls back to the client
+    /// IP when the connection has no JA4 (plain HTTP)
+    Ja4,
+    /// Rate limit by the HTTP/2 Akamai fingerprint
+    /// Falls back to the client IP for HTTP/1.x requests
+    Akamai,
+    /// Rate limit by combination of client IP and JA4 fingerprint
+    /// Falls back to the client IP alone when the connection has no JA4
+    #[serde(rename = "ip+ja4")]
+    IpJa4,
+    /// Rate limit by the SNI of the TLS connection
+    /// Falls back to the client IP when the client sent no SNI (or on plain HTTP)
+    Sni,
+    /// Rate limit by the ALPN protocol of the TLS connection
+    /// Falls back to the client IP when none was agreed on (or on plain HTTP)
+    Alpn,
 }
 
 fn default_requests_per_second() -> u32 {
@@ -365,9 +843,22 @@ fn default_limit_by() -> LimitBy {
 #[derive(Serialize)]
 pub(crate) struct SecurityView<'a> {
     headers: SecurityHeadersView<'a>,
-    ip_filter: IpFilterView,
+    ip_filter: IpFilterView<'a>,
     rate_limit: RateLimitView<'a>,
     trusted_proxies: TrustedProxiesView,
+    fingerprint_filter: FingerprintFilterView<'a>,
+    waf: WafView<'a>,
+    bot_verification: BotVerificationView<'a>,
+    strict_http: StrictHttpView,
+    challenge: ChallengeView<'a>,
+    tarpit: TarpitView<'a>,
+}
+
+#[derive(Serialize)]
+struct FingerprintFilterView<'a> {
+    allow: &'a FingerprintList,
+    deny: &'a FingerprintList,
+    status: u16,
 }
 
 #[derive(Serialize)]
@@ -381,15 +872,17 @@ struct TrustedProxiesView {
 #[derive(Serialize)]
 pub(crate) struct ScopedSecurityView<'a> {
     headers: Option<SecurityHeadersView<'a>>,
-    ip_filter: Option<IpFilterView>,
+    ip_filter: Option<IpFilterView<'a>>,
     rate_limit: Option<RateLimitView<'a>>,
 }
 
 #[derive(Serialize)]
 struct SecurityHeadersView<'a> {
+    preset: &'static str,
     custom: &'a [CustomHeader],
     hsts: HstsView,
     csp: CspView<'a>,
+    remove: &'a [String],
 }
 
 #[derive(Serialize)]
@@ -404,13 +897,19 @@ struct HstsView {
 struct CspView<'a> {
     enabled: bool,
     policy: &'a Secret<String>,
+    /// Directive names only; their sources are as sensitive as `policy`.
+    directives: Vec<&'a str>,
+    report_only: bool,
 }
 
 #[derive(Serialize)]
-struct IpFilterView {
+struct IpFilterView<'a> {
     mode: &'static str,
     allowlist: Vec<String>,
     denylist: Vec<String>,
+    precedence: &'static str,
+    use_forwarded_for: bool,
+    match_tls: Option<TlsMatchView<'a>>,
 }
 
 #[derive(Serialize)]
@@ -421,6 +920,17 @@ struct RateLimitView<'a> {
     window_seconds: u64,
     limit_by: &'static str,
     limit_by_header: Option<&'a str>,
+    store: &'static str,
+    redis: Option<RedisStoreView<'a>>,
+    match_tls: Option<TlsMatchView<'a>>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RedisStoreView<'a> {
+    url: &'a Secret<String>,
+    pool_size: usize,
+    timeout_ms: u64,
+    key_prefix: &'a str,
 }
 
 impl SecurityDynamicConfig {
@@ -438,6 +948,16 @@ impl SecurityDynamicConfig {
                     .collect(),
                 insecure: self.trusted_proxies.insecure,
             },
+            fingerprint_filter: FingerprintFilterView {
+                allow: &self.fingerprint_filter.allow,
+                deny: &self.fingerprint_filter.deny,
+                status: self.fingerprint_filter.status,
+            },
+            waf: self.waf.effective_view(),
+            bot_verification: self.bot_verification.effective_view(),
+            strict_http: self.strict_http.effective_view(),
+            challenge: self.challenge.effective_view(),
+            tarpit: self.tarpit.effective_view(),
         }
     }
 }
@@ -471,6 +991,7 @@ impl RouteSecurityConfig {
 impl SecurityHeaders {
     fn effective_view(&self) -> SecurityHeadersView<'_> {
         SecurityHeadersView {
+            preset: self.preset.as_str(),
             custom: self.custom.as_slice(),
             hsts: HstsView {
                 enabled: self.hsts.enabled,
@@ -478,17 +999,26 @@ impl SecurityHeaders {
                 include_subdomains: self.hsts.include_subdomains,
                 preload: self.hsts.preload,
             },
-            csp: CspView { enabled: self.csp.enabled, policy: &self.csp.policy },
+            csp: CspView {
+                enabled: self.csp.enabled,
+                policy: &self.csp.policy,
+                directives: self.csp.directives.keys().map(String::as_str).collect(),
+                report_only: self.csp.report_only,
+            },
+            remove: self.remove.as_slice(),
         }
     }
 }
 
 impl IpFilterConfig {
-    fn effective_view(&self) -> IpFilterView {
+    fn effective_view(&self) -> IpFilterView<'_> {
         IpFilterView {
             mode: self.mode.as_str(),
             allowlist: self.allowlist.iter().map(ToString::to_string).collect(),
             denylist: self.denylist.iter().map(ToString::to_string).collect(),
+            precedence: self.precedence.as_str(),
+            use_forwarded_for: self.use_forwarded_for,
+            match_tls: self.match_tls.as_ref().map(TlsMatch::effective_view),
         }
     }
 }
@@ -502,6 +1032,9 @@ impl RateLimitConfig {
             window_seconds: self.window_seconds,
             limit_by: self.limit_by.as_str(),
             limit_by_header: self.limit_by_header.as_deref(),
+            store: self.store.as_str(),
+            redis: self.redis.as_ref().map(RedisStoreConfig::effective_view),
+            match_tls: self.match_tls.as_ref().map(TlsMatch::effective_view),
         }
     }
 }
@@ -512,17 +1045,33 @@ impl IpFilterMode {
             IpFilterMode::Disabled => "disabled",
             IpFilterMode::Allowlist => "allowlist",
             IpFilterMode::Denylist => "denylist",
+            IpFilterMode::Both => "both",
         }
     }
 }
 
-impl LimitBy {
+impl IpFilterPrecedence {
     fn as_str(self) -> &'static str {
+        match self {
+            IpFilterPrecedence::Deny => "deny",
+            IpFilterPrecedence::Allow => "allow",
+        }
+    }
+}
+
+impl LimitBy {
+    /// Config spelling of the strategy, also used as the `strategy` metrics label.
+    pub fn as_str(self) -> &'static str {
         match self {
             LimitBy::Ip => "ip",
             LimitBy::Header => "header",
             LimitBy::Route => "route",
             LimitBy::Combined => "combined",
+            LimitBy::Ja4 => "ja4",
+            LimitBy::Akamai => "akamai",
+            LimitBy::IpJa4 => "ip+ja4",
+            LimitBy::Sni => "sni",
+            LimitBy::Alpn => "alpn",
         }
     }
 }
@@ -0,0 +1,80 @@
+use http::HeaderValue;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Files served by the proxy itself from a directory (`serve_static` on a route).
+///
+/// The request path after the route's `prefix` is resolved under `root`; requests never leave
+/// it: `..` segments, backslashes and NUL bytes are refused, and a file reached through a
+/// symlink pointing outside `root` is not served. Only `GET` and `HEAD` are answered. Responses
+/// carry a `Content-Type` guessed from the file extension, an `ETag` and `Last-Modified`, honour
+/// `If-None-Match` / `If-Modified-Since` with `304` and serve single `Range` requests with `206`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ServeStaticConfig {
+    /// Directory the files are served from. Must exist when the config loads.
+    pub root: String,
+    /// File served for a request naming a directory (default: `index.html`). Empty answers such
+    /// requests with `404`.
+    #[serde(default = "default_index")]
+    pub index: String,
+    /// `Cache-Control` of served files (optional), e.g. `public, max-age=3600`.
+    #[serde(default)]
+    pub cache_control: Option<String>,
+    /// Serve files and directories whose name starts with `.`, such as `.well-known`
+    /// (default: false).
+    #[serde(default)]
+    pub dotfiles: bool,
+}
+
+impl ServeStaticConfig {
+    /// Reject a `root` that is not a directory, an `index` that is not a plain file name and an
+    /// invalid `cache_control`. `context` prefixes error messages.
+    pub fn validate(&self, context: &str) -> Result<()> {
+        let metadata = std::fs::metadata(&self.root)
+            .map_err(|e| ProxyError::Config(format!("{context}.root '{}': {e}", self.root)))?;
+        if !metadata.is_dir() {
+            return Err(ProxyError::Config(format!(
+                "{context}.root '{}' is not a directory",
+                self.root
+            )));
+        }
+        if self.index.contains(['/', '\\', '\0']) || self.index == "." || self.index == ".." {
+            return Err(ProxyError::Config(format!(
+                "{context}.index '{}' must be a file name",
+                self.index
+            )));
+        }
+        if let Some(cache_control) = &self.cache_control {
+            if HeaderValue::from_str(cache_control).is_err() {
+                return Err(ProxyError::Config(format!(
+                    "{context}.cache_control '{cache_control}' is not a valid header value"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> ServeStaticView<'_> {
+        ServeStaticView {
+            root: &self.root,
+            index: &self.index,
+            cache_control: self.cache_control.as_deref(),
+            dotfiles: self.dotfiles,
+        }
+    }
+}
+
+fn default_index() -> String {
+    "index.html".to_string()
+}
+
+/// Allowlisted effective-config view of [`ServeStaticConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct ServeStaticView<'a> {
+    root: &'a str,
+    index: &'a str,
+    cache_control: Option<&'a str>,
+    dotfiles: bool,
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Session affinity of a route (`sticky` on a route).
+///
+/// Keeps sending a client to the same backend among the route's candidates (the routes sharing
+/// its prefix) instead of round-robin. When the pinned backend is unhealthy, drained by its
+/// circuit breaker or no longer a candidate, the request fails over to the next eligible one.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct StickyConfig {
+    /// How the client is recognized.
+    pub mode: StickyMode,
+    /// Request attribute hashed to pick the backend, with `mode = "hash"` (default: `client_ip`).
+    #[serde(default)]
+    pub hash_by: StickyHashKey,
+    /// Name of the proxy-issued cookie, with `mode = "cookie"` (default: `huginn_affinity`).
+    #[serde(default = "default_sticky_cookie")]
+    pub cookie: String,
+    /// Lifetime of the cookie in seconds (`Max-Age`), with `mode = "cookie"` (default: 3600).
+    #[serde(default = "default_sticky_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+/// How a sticky route recognizes a returning client.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StickyMode {
+    /// Hash `hash_by` over the eligible backends (stateless, no cookie)
+    Hash,
+    /// Pin the client with a cookie naming its backend, set on the first response
+    Cookie,
+}
+
+impl StickyMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StickyMode::Hash => "hash",
+            StickyMode::Cookie => "cookie",
+        }
+    }
+}
+
+/// Request attribute hashed by `mode = "hash"`.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StickyHashKey {
+    /// Client IP, resolved through `trusted_proxies` like the rate limiter's
+    #[default]
+    ClientIp,
+    /// JA4 fingerprint of the TLS connection. Falls back to the client IP on plain HTTP
+    Ja4,
+}
+
+impl StickyHashKey {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StickyHashKey::ClientIp => "client_ip",
+            StickyHashKey::Ja4 => "ja4",
+        }
+    }
+}
+
+impl StickyConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.mode != StickyMode::Cookie {
+            return Ok(());
+        }
+        if !is_cookie_name(&self.cookie) {
+            return Err(ProxyError::Config(format!(
+                "sticky.cookie '{}' is not a valid cookie name",
+                self.cookie
+            )));
+        }
+        if self.ttl_secs == 0 {
+            return Err(ProxyError::Config("sticky.ttl_secs must be greater than 0".to_string()));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> StickyView<'_> {
+        StickyView {
+            mode: self.mode.as_str(),
+            hash_by: self.hash_by.as_str(),
+            cookie: &self.cookie,
+            ttl_secs: self.ttl_secs,
+        }
+    }
+}
+
+/// RFC 6265 cookie name: a non-empty token (no separators, spaces or controls).
+pub(crate) fn is_cookie_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+}
+
+fn default_sticky_cookie() -> String {
+    "huginn_affinity".to_string()
+}
+
+fn default_sticky_ttl_secs() -> u64 {
+    3600
+}
+
+/// Allowlisted effective-config view of [`StickyConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct StickyView<'a> {
+    mode: &'static str,
+    hash_by: &'static str,
+    cookie: &'a str,
+    ttl_secs: u64,
+}
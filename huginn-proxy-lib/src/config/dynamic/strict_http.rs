@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+/// Strict protocol conformance checks (`[security.strict_http]`).
+///
+/// Requests are checked before routing for messages that a backend could read differently from
+/// the proxy: repeated `Content-Length`, header values outside visible ASCII and absolute-form
+/// request targets (`GET http://host/path`). With `mode = "enforce"` a repeated identical
+/// `Content-Length` and an absolute-form target naming the request's own `Host` are normalized,
+/// anything else is answered `400`; `mode = "detect"` forwards every request unchanged and only
+/// counts the violations. `Transfer-Encoding` together with `Content-Length` is rejected whether
+/// strict mode is on or not.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StrictHttpConfig {
+    /// Check requests (default: false).
+    #[serde(default)]
+    pub enabled: bool,
+    /// What a violation does (default: enforce).
+    #[serde(default)]
+    pub mode: StrictHttpMode,
+}
+
+/// What a strict-mode violation does.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StrictHttpMode {
+    /// Normalize the request when that is unambiguous, reject it with `400` otherwise
+    #[default]
+    Enforce,
+    /// Forward the request unchanged; only count and log the violation
+    Detect,
+}
+
+impl StrictHttpMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StrictHttpMode::Enforce => "enforce",
+            StrictHttpMode::Detect => "detect",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct StrictHttpView {
+    enabled: bool,
+    mode: &'static str,
+}
+
+impl StrictHttpConfig {
+    pub(crate) fn effective_view(&self) -> StrictHttpView {
+        StrictHttpView { enabled: self.enabled, mode: self.mode.as_str() }
+    }
+}
@@ -0,0 +1,130 @@
+use http::{HeaderName, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use super::headers::CustomHeader;
+use crate::error::{ProxyError, Result};
+
+/// Largest accepted `body` or `body_file`; the proxy keeps synthetic pages small on purpose.
+pub const MAX_SYNTHETIC_BODY_BYTES: u64 = 1024 * 1024;
+
+/// Response served by the proxy itself on a route (`synthetic` on a route).
+///
+/// While active, requests matching the route are answered with `status`, `headers` and the body
+/// without selecting or contacting a backend: maintenance pages, health stubs, placeholders. The
+/// admin API can switch it on and off at runtime (`PUT /admin/synthetic`) without a reload.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SyntheticResponseConfig {
+    /// Serve the response; `false` keeps it ready to be switched on (default: true).
+    #[serde(default = "default_synthetic_enabled")]
+    pub enabled: bool,
+    /// Response status (default: 503).
+    #[serde(default = "default_synthetic_status")]
+    pub status: u16,
+    /// Response headers, e.g. `Retry-After` or `Cache-Control`.
+    #[serde(default)]
+    pub headers: Vec<CustomHeader>,
+    /// Inline response body. Exclusive with `body_file`.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// File read for the response body, e.g. a maintenance page. Exclusive with `body`.
+    #[serde(default)]
+    pub body_file: Option<String>,
+    /// `Content-Type` of the body (default: `text/plain; charset=utf-8` for `body`, `text/html;
+    /// charset=utf-8` for `body_file`).
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
+impl SyntheticResponseConfig {
+    pub fn validate(&self) -> Result<()> {
+        if StatusCode::from_u16(self.status).is_err() || self.status < 200 {
+            return Err(ProxyError::Config(format!(
+                "synthetic.status must be a final HTTP status (200-599), got {}",
+                self.status
+            )));
+        }
+        for header in &self.headers {
+            HeaderName::from_bytes(header.name.as_bytes()).map_err(|e| {
+                ProxyError::Config(format!("Invalid synthetic header name '{}': {e}", header.name))
+            })?;
+            if HeaderValue::from_str(header.value.expose()).is_err() {
+                return Err(ProxyError::Config(format!(
+                    "Invalid synthetic header value for '{}'",
+                    header.name
+                )));
+            }
+        }
+        if let Some(content_type) = &self.content_type {
+            if HeaderValue::from_str(content_type).is_err() {
+                return Err(ProxyError::Config(format!(
+                    "Invalid synthetic.content_type '{content_type}'"
+                )));
+            }
+        }
+        match (&self.body, &self.body_file) {
+            (Some(_), Some(_)) => Err(ProxyError::Config(
+                "synthetic.body and synthetic.body_file are mutually exclusive".to_string(),
+            )),
+            (Some(body), None) if body.len() as u64 > MAX_SYNTHETIC_BODY_BYTES => {
+                Err(ProxyError::Config(format!(
+                    "synthetic.body exceeds {MAX_SYNTHETIC_BODY_BYTES} bytes"
+                )))
+            }
+            (None, Some(path)) => {
+                let metadata = std::fs::metadata(path).map_err(|e| {
+                    ProxyError::Config(format!("synthetic.body_file '{path}': {e}"))
+                })?;
+                if !metadata.is_file() || metadata.len() > MAX_SYNTHETIC_BODY_BYTES {
+                    return Err(ProxyError::Config(format!(
+                        "synthetic.body_file '{path}' must be a file of at most \
+                         {MAX_SYNTHETIC_BODY_BYTES} bytes"
+                    )));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `content_type`, or the default for the configured body; `None` without a body.
+    pub fn effective_content_type(&self) -> Option<&str> {
+        match (&self.content_type, &self.body, &self.body_file) {
+            (Some(content_type), _, _) => Some(content_type),
+            (None, Some(_), _) => Some("text/plain; charset=utf-8"),
+            (None, None, Some(_)) => Some("text/html; charset=utf-8"),
+            (None, None, None) => None,
+        }
+    }
+
+    pub(crate) fn effective_view(&self) -> SyntheticResponseView<'_> {
+        SyntheticResponseView {
+            enabled: self.enabled,
+            status: self.status,
+            headers: &self.headers,
+            body_bytes: self.body.as_ref().map(String::len),
+            body_file: self.body_file.as_deref(),
+            content_type: self.effective_content_type(),
+        }
+    }
+}
+
+fn default_synthetic_enabled() -> bool {
+    true
+}
+
+fn default_synthetic_status() -> u16 {
+    503
+}
+
+/// Allowlisted effective-config view of [`SyntheticResponseConfig`]. Field names are the JSON
+/// keys; the inline body is summarized by its length.
+#[derive(Serialize)]
+pub(crate) struct SyntheticResponseView<'a> {
+    enabled: bool,
+    status: u16,
+    headers: &'a [CustomHeader],
+    body_bytes: Option<usize>,
+    body_file: Option<&'a str>,
+    content_type: Option<&'a str>,
+}
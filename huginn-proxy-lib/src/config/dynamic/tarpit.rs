@@ -0,0 +1,115 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+use super::security::deserialize_ip_networks;
+use crate::error::{ProxyError, Result};
+
+/// Longest accepted `delay_ms`.
+pub const MAX_TARPIT_DELAY_MS: u64 = 60_000;
+
+/// ClientHello tarpit for graylisted clients (`[security.tarpit]`).
+///
+/// On TLS listeners, a connection whose client IP is in `ips`, or whose ClientHello matches a
+/// `ja4` entry, waits `delay_ms` before the proxy answers its ClientHello. Scanners then pay the
+/// delay on every connection before any HTTP processing happens, while the proxy holds nothing
+/// but the idle socket. JA4 entries match like `[security.fingerprint_filter]` entries. At most
+/// `max_concurrent` connections wait at a time; graylisted connections beyond it are handshaken
+/// without delay. Empty lists (default) disable the tarpit; TLS passthrough listeners are never
+/// tarpitted.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TarpitConfig {
+    /// JA4 fingerprints to delay (any JA4 variant, `*` suffix for a prefix).
+    #[serde(default)]
+    pub ja4: Vec<String>,
+    /// Client IPs to delay, in CIDR notation, e.g. `["203.0.113.0/24"]`.
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_ip_networks")]
+    pub ips: Vec<IpNet>,
+    /// How long a graylisted ClientHello waits for its answer, in milliseconds (default: 10000).
+    #[serde(default = "default_delay_ms")]
+    pub delay_ms: u64,
+    /// Most connections waiting at a time (default: 256).
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+}
+
+impl Default for TarpitConfig {
+    fn default() -> Self {
+        Self {
+            ja4: Vec::new(),
+            ips: Vec::new(),
+            delay_ms: default_delay_ms(),
+            max_concurrent: default_max_concurrent(),
+        }
+    }
+}
+
+fn default_delay_ms() -> u64 {
+    10_000
+}
+
+fn default_max_concurrent() -> usize {
+    256
+}
+
+impl TarpitConfig {
+    /// Whether any connection can be tarpitted (at least one `ja4` or `ips` entry).
+    pub fn is_active(&self) -> bool {
+        !self.ja4.is_empty() || !self.ips.is_empty()
+    }
+
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+
+    /// Whether `ip` is in one of the `ips` ranges.
+    pub fn lists_ip(&self, ip: &IpAddr) -> bool {
+        self.ips.iter().any(|net| net.contains(ip))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.delay_ms == 0 || self.delay_ms > MAX_TARPIT_DELAY_MS {
+            return Err(ProxyError::Config(format!(
+                "security.tarpit.delay_ms must be between 1 and {MAX_TARPIT_DELAY_MS}, got {}",
+                self.delay_ms
+            )));
+        }
+        if self.max_concurrent == 0 {
+            return Err(ProxyError::Config(
+                "security.tarpit.max_concurrent must be greater than 0".to_string(),
+            ));
+        }
+        if self
+            .ja4
+            .iter()
+            .any(|entry| entry.is_empty() || entry == "*")
+        {
+            return Err(ProxyError::Config(
+                "security.tarpit.ja4 entries must not be empty or a bare '*'".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> TarpitView<'_> {
+        TarpitView {
+            ja4: &self.ja4,
+            ips: self.ips.iter().map(ToString::to_string).collect(),
+            delay_ms: self.delay_ms,
+            max_concurrent: self.max_concurrent,
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`TarpitConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct TarpitView<'a> {
+    ja4: &'a [String],
+    ips: Vec<String>,
+    delay_ms: u64,
+    max_concurrent: usize,
+}
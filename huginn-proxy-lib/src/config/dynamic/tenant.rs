@@ -0,0 +1,85 @@
+use serde::Deserialize;
+
+use super::backend::{Backend, Domain};
+use super::headers::HeaderManipulation;
+use super::security::DomainSecurityConfig;
+use crate::error::{ProxyError, Result};
+
+/// Label of requests to domains that belong to no tenant, in metrics labels and logs.
+pub const NO_TENANT_LABEL: &str = "_none_";
+
+/// Longest accepted tenant `name`.
+const MAX_NAME_LEN: usize = 64;
+
+/// A tenant (`[[tenants]]`): the domains, routes and backends one team owns, with the policies
+/// its domains default to.
+///
+/// At load time the tenant's `domains` and `backends` are moved into the top-level lists, tagged
+/// with the tenant (see [`Domain::tenant`] and [`Backend::tenant`]).
+/// Routes of a tenant's domains can only use that tenant's backends, and its backends can only be
+/// used by its own routes, so one team's config cannot send traffic to another team's services.
+/// `headers` and every `security` sub-block are the default of each of the tenant's domains:
+/// a domain's own block replaces it, as a domain block replaces the global one. The domains that
+/// inherit `security.rate_limit` share one limiter.
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Tenant {
+    /// Unique tenant name, the `tenant` label of request metrics and the `tenant` log field.
+    /// ASCII letters, digits, `-` and `_`.
+    pub name: String,
+    /// Header manipulation of the tenant's domains that set none of their own (optional).
+    #[serde(default)]
+    pub headers: Option<HeaderManipulation>,
+    /// Security headers, IP filter and rate limit of the tenant's domains (optional). Each
+    /// present sub-block applies to the domains that do not set it themselves.
+    #[serde(default)]
+    pub security: Option<DomainSecurityConfig>,
+    /// Backends owned by the tenant; emptied when they are moved into the top-level `backends`.
+    #[serde(default)]
+    pub backends: Vec<Backend>,
+    /// Domains owned by the tenant; emptied when they are moved into the top-level `domains`.
+    #[serde(default)]
+    pub domains: Vec<Domain>,
+}
+
+impl Tenant {
+    pub fn validate(&self) -> Result<()> {
+        let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if self.name.is_empty()
+            || self.name.len() > MAX_NAME_LEN
+            || !self.name.chars().all(valid_char)
+            || self.name == NO_TENANT_LABEL
+        {
+            return Err(ProxyError::Config(format!(
+                "tenant name '{}' is invalid: use 1-{MAX_NAME_LEN} ASCII letters, digits, '-' or \
+                 '_' (and not '{NO_TENANT_LABEL}')",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Tag `domain` with this tenant and fill in the blocks it leaves unset with the tenant's.
+    pub(crate) fn adopt(&self, domain: &mut Domain) {
+        domain.tenant = Some(self.name.clone());
+        if domain.headers.is_none() {
+            domain.headers.clone_from(&self.headers);
+        }
+        let Some(defaults) = &self.security else {
+            return;
+        };
+        let security = domain
+            .security
+            .get_or_insert_with(DomainSecurityConfig::default);
+        if security.headers.is_none() {
+            security.headers.clone_from(&defaults.headers);
+        }
+        if security.ip_filter.is_none() {
+            security.ip_filter.clone_from(&defaults.ip_filter);
+        }
+        if security.rate_limit.is_none() && defaults.rate_limit.is_some() {
+            security.rate_limit.clone_from(&defaults.rate_limit);
+            security.tenant_rate_limit = true;
+        }
+    }
+}
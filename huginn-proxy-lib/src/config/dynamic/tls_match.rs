@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+use crate::security::TlsMetadata;
+
+/// TLS condition of a rate limit or IP filter block (`match_tls`).
+///
+/// The block applies only to requests whose connection matches every list given: its SNI is one
+/// of `sni` (compared ignoring case), its ALPN protocol one of `alpn` and its JA4 one of `ja4`.
+/// `""` stands for a missing value, so `sni = [""]` matches connections that sent no SNI;
+/// plaintext connections have none of the three.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TlsMatch {
+    /// Server names, `""` for none (optional).
+    #[serde(default)]
+    pub sni: Vec<String>,
+    /// ALPN protocols, e.g. `h2` or `http/1.1`, `""` for none (optional).
+    #[serde(default)]
+    pub alpn: Vec<String>,
+    /// JA4 fingerprints, `""` for none (optional).
+    #[serde(default)]
+    pub ja4: Vec<String>,
+}
+
+impl TlsMatch {
+    /// `scope` names the block in error messages (`security.rate_limit.match_tls`, ...).
+    pub fn validate(&self, scope: &str) -> Result<()> {
+        if self.sni.is_empty() && self.alpn.is_empty() && self.ja4.is_empty() {
+            return Err(ProxyError::Config(format!("{scope}: needs sni, alpn or ja4 values")));
+        }
+        Ok(())
+    }
+
+    /// Whether the connection described by `tls` satisfies this condition.
+    pub fn matches(&self, tls: &TlsMetadata<'_>) -> bool {
+        listed(&self.sni, tls.sni, str::eq_ignore_ascii_case)
+            && listed(&self.alpn, tls.alpn, |a, b| a == b)
+            && listed(&self.ja4, tls.ja4, |a, b| a == b)
+    }
+
+    pub(crate) fn effective_view(&self) -> TlsMatchView<'_> {
+        TlsMatchView { sni: &self.sni, alpn: &self.alpn, ja4: &self.ja4 }
+    }
+}
+
+/// Whether `values` is empty or holds `value` (`""` when missing), compared with `same`.
+fn listed(values: &[String], value: Option<&str>, same: impl Fn(&str, &str) -> bool) -> bool {
+    values.is_empty() || values.iter().any(|v| same(v, value.unwrap_or("")))
+}
+
+/// Allowlisted effective-config view of [`TlsMatch`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct TlsMatchView<'a> {
+    sni: &'a [String],
+    alpn: &'a [String],
+    ja4: &'a [String],
+}
@@ -0,0 +1,99 @@
+use http::uri::Authority;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Secret;
+use crate::error::{ProxyError, Result};
+
+/// SOCKS5 proxy a backend is reached through (`proxy` on a backend).
+///
+/// Every connection the proxy opens to the backend for requests goes through a SOCKS5
+/// (RFC 1928) `CONNECT` on the proxy, e.g. a bastion in front of a private network; TLS to the
+/// backend, when it has a `tls` table, runs end to end inside the tunnel. With `socks5://` the
+/// backend hostname is resolved locally and the proxy is given an IP; with `socks5h://` the
+/// hostname is sent to the proxy, which resolves it. Active health checks still connect to the
+/// backend directly.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BackendProxyConfig {
+    /// Proxy address, `socks5://host:port` or `socks5h://host:port`.
+    pub url: String,
+    /// Username for username/password authentication (RFC 1929). Requires `password`.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Password for username/password authentication. Requires `username`.
+    #[serde(default)]
+    pub password: Option<Secret<String>>,
+}
+
+impl BackendProxyConfig {
+    /// `host:port` of the proxy, and whether backend hostnames are resolved by the proxy
+    /// (`socks5h`). `None` when `url` is not a SOCKS5 URL.
+    pub fn server(&self) -> Option<(&str, bool)> {
+        let (rest, remote_dns) = match self.url.strip_prefix("socks5h://") {
+            Some(rest) => (rest, true),
+            None => (self.url.strip_prefix("socks5://")?, false),
+        };
+        Some((rest.trim_end_matches('/'), remote_dns))
+    }
+
+    /// Username and password, when authentication is configured.
+    pub fn credentials(&self) -> Option<(&str, &str)> {
+        match (&self.username, &self.password) {
+            (Some(username), Some(password)) => {
+                Some((username.as_str(), password.expose().as_str()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Reject a URL that is not `socks5://host:port` or `socks5h://host:port`, credentials in
+    /// the URL, and a username without password (or the reverse); RFC 1929 limits both to 1-255
+    /// bytes.
+    pub fn validate(&self, address: &str) -> Result<()> {
+        let invalid =
+            |message: String| ProxyError::Config(format!("Backend '{address}': {message}"));
+        let valid_server = self.server().is_some_and(|(server, _)| {
+            !server.contains('@')
+                && server
+                    .parse::<Authority>()
+                    .is_ok_and(|authority| authority.port_u16().is_some_and(|port| port != 0))
+        });
+        if !valid_server {
+            return Err(invalid(format!(
+                "proxy.url '{}' must be socks5://host:port or socks5h://host:port (credentials \
+                 go in proxy.username and proxy.password)",
+                self.url
+            )));
+        }
+        match (&self.username, &self.password) {
+            (None, None) => Ok(()),
+            (Some(username), Some(password)) => {
+                for (key, value) in
+                    [("username", username.as_str()), ("password", password.expose().as_str())]
+                {
+                    if value.is_empty() || value.len() > 255 {
+                        return Err(invalid(format!("proxy.{key} must be 1 to 255 bytes long")));
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(invalid("proxy.username and proxy.password must be set together".to_string())),
+        }
+    }
+
+    pub(crate) fn effective_view(&self) -> BackendProxyView<'_> {
+        BackendProxyView {
+            url: self.url.as_str(),
+            username: self.username.as_deref(),
+            password: self.password.as_ref(),
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`BackendProxyConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct BackendProxyView<'a> {
+    url: &'a str,
+    username: Option<&'a str>,
+    password: Option<&'a Secret<String>>,
+}
@@ -0,0 +1,326 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::pattern::RegexPattern;
+use crate::error::{ProxyError, Result};
+
+/// Web application firewall (`[security.waf]`).
+///
+/// Requests are matched against the enabled built-in `rule_sets`, the inline `rules` and the
+/// rules of `rules_files`, and checked against the method and header limits. The first hit
+/// blocks the request with `status`, or is only counted and logged in `detect` mode. A route's
+/// `waf` block turns inspection on or off for that route and can change its mode.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WafConfig {
+    /// Inspect the requests of every route (default: false). Routes opt in or out with their
+    /// own `waf` block either way.
+    #[serde(default)]
+    pub enabled: bool,
+    /// What a hit does (default: block).
+    #[serde(default)]
+    pub mode: WafMode,
+    /// Status of a blocked request, 4xx (default: 403).
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// Built-in rule sets: `sqli`, `xss`, `path_traversal`.
+    #[serde(default)]
+    pub rule_sets: Vec<WafRuleSet>,
+    /// Rule files (`.toml`, `.yaml` or `.yml`, holding a `rules` list), read when the config is
+    /// loaded or reloaded.
+    #[serde(default)]
+    pub rules_files: Vec<String>,
+    /// Inline rules, tried after the built-in sets.
+    #[serde(default)]
+    pub rules: Vec<WafRule>,
+    /// Rules read from `rules_files`, tried last. Filled in by the config loader.
+    #[serde(skip)]
+    pub file_rules: Vec<WafRule>,
+    /// Methods a request may use, e.g. `["GET", "POST"]`. Empty (default) allows any.
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    /// Most request headers a request may carry (optional).
+    #[serde(default)]
+    pub max_headers: Option<usize>,
+    /// Largest single request header, name plus value, in bytes (optional).
+    #[serde(default)]
+    pub max_header_bytes: Option<usize>,
+    /// Request body bytes read ahead for `body` rules (default: 65536). The rest of a longer
+    /// body is forwarded without inspection.
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+}
+
+impl Default for WafConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: WafMode::default(),
+            status: default_status(),
+            rule_sets: Vec::new(),
+            rules_files: Vec::new(),
+            rules: Vec::new(),
+            file_rules: Vec::new(),
+            allowed_methods: Vec::new(),
+            max_headers: None,
+            max_header_bytes: None,
+            max_body_bytes: default_max_body_bytes(),
+        }
+    }
+}
+
+/// What a WAF hit does.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WafMode {
+    /// Reject the request with the configured `status`
+    #[default]
+    Block,
+    /// Forward the request; only count and log the hit
+    Detect,
+}
+
+impl WafMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WafMode::Block => "block",
+            WafMode::Detect => "detect",
+        }
+    }
+}
+
+/// Built-in rule set. `sqli` and `xss` inspect the path, query and body; `path_traversal` the
+/// path and query.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WafRuleSet {
+    /// SQL injection: `UNION SELECT`, quoted tautologies, stacked statements, time-based probes
+    Sqli,
+    /// Cross-site scripting: script tags, inline event handlers, `javascript:` URIs
+    Xss,
+    /// `..` path segments and well-known sensitive files
+    PathTraversal,
+}
+
+impl WafRuleSet {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WafRuleSet::Sqli => "sqli",
+            WafRuleSet::Xss => "xss",
+            WafRuleSet::PathTraversal => "path_traversal",
+        }
+    }
+}
+
+/// Part of a request a WAF rule is matched against.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WafTarget {
+    /// Request path, percent-decoded
+    Path,
+    /// Query string, percent-decoded
+    Query,
+    /// Each request header value
+    Headers,
+    /// Request body, decompressed and, for form posts, percent-decoded
+    Body,
+    /// SNI of the TLS connection, empty when none was sent (or on plain HTTP)
+    Sni,
+    /// ALPN protocol of the TLS connection, empty when none was agreed on
+    Alpn,
+    /// JA4 of the TLS connection, empty when it was not computed
+    Ja4,
+}
+
+/// One pattern rule (`[[security.waf.rules]]` or an entry of a rules file).
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WafRule {
+    /// Rule name, reported in logs and as the `rule` label of `huginn_waf_hits_total`.
+    /// Letters, digits, `_`, `-` and `.`.
+    pub id: String,
+    /// Request parts the pattern is matched against.
+    pub targets: Vec<WafTarget>,
+    /// Regex that must match somewhere in a target, compiled when the config loads.
+    pub pattern: RegexPattern,
+}
+
+/// Contents of a `rules_files` entry.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WafRuleFile {
+    #[serde(default)]
+    pub rules: Vec<WafRule>,
+}
+
+/// WAF switch of a route (`waf` on a route).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RouteWafConfig {
+    /// Inspect this route's requests with `[security.waf]` (default: true).
+    #[serde(default = "default_route_enabled")]
+    pub enabled: bool,
+    /// Mode for this route, e.g. `detect` while rolling out rules (optional). Default: the
+    /// global `mode`.
+    #[serde(default)]
+    pub mode: Option<WafMode>,
+}
+
+impl WafConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(400..=499).contains(&self.status) {
+            return Err(ProxyError::Config(format!(
+                "security.waf.status must be a 4xx status, got {}",
+                self.status
+            )));
+        }
+        for method in &self.allowed_methods {
+            if http::Method::from_bytes(method.as_bytes()).is_err()
+                || method.bytes().any(|b| b.is_ascii_lowercase())
+            {
+                return Err(ProxyError::Config(format!(
+                    "security.waf.allowed_methods: invalid method '{method}' \
+                     (methods are case-sensitive, e.g. \"POST\")"
+                )));
+            }
+        }
+        if self.max_headers == Some(0)
+            || self.max_header_bytes == Some(0)
+            || self.max_body_bytes == 0
+        {
+            return Err(ProxyError::Config(
+                "security.waf.max_headers, max_header_bytes and max_body_bytes must be greater \
+                 than 0"
+                    .to_string(),
+            ));
+        }
+        let mut ids = HashSet::new();
+        for rule in self.rules.iter().chain(&self.file_rules) {
+            rule.validate()?;
+            if !ids.insert(rule.id.as_str()) {
+                return Err(ProxyError::Config(format!(
+                    "security.waf: rule id '{}' is used more than once",
+                    rule.id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether some rule reads the request body, so it has to be read ahead of forwarding.
+    pub fn inspects_body(&self) -> bool {
+        self.rule_sets
+            .iter()
+            .any(|set| matches!(set, WafRuleSet::Sqli | WafRuleSet::Xss))
+            || self
+                .rules
+                .iter()
+                .chain(&self.file_rules)
+                .any(|rule| rule.targets.contains(&WafTarget::Body))
+    }
+
+    /// Whether requests of a route with `route` as its `waf` block are inspected, and in which
+    /// mode.
+    pub fn mode_for(&self, route: Option<&RouteWafConfig>) -> Option<WafMode> {
+        match route {
+            Some(route) => route.enabled.then(|| route.mode.unwrap_or(self.mode)),
+            None => self.enabled.then_some(self.mode),
+        }
+    }
+
+    pub(crate) fn effective_view(&self) -> WafView<'_> {
+        WafView {
+            enabled: self.enabled,
+            mode: self.mode.as_str(),
+            status: self.status,
+            rule_sets: self.rule_sets.iter().map(|s| s.as_str()).collect(),
+            rules_files: &self.rules_files,
+            rules: self
+                .rules
+                .iter()
+                .chain(&self.file_rules)
+                .map(|rule| WafRuleView {
+                    id: &rule.id,
+                    targets: &rule.targets,
+                    pattern: rule.pattern.as_str(),
+                })
+                .collect(),
+            allowed_methods: &self.allowed_methods,
+            max_headers: self.max_headers,
+            max_header_bytes: self.max_header_bytes,
+            max_body_bytes: self.max_body_bytes,
+        }
+    }
+}
+
+impl WafRule {
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty()
+            || !self
+                .id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'))
+        {
+            return Err(ProxyError::Config(format!(
+                "security.waf: invalid rule id '{}' (letters, digits, '_', '-' and '.')",
+                self.id
+            )));
+        }
+        if self.targets.is_empty() {
+            return Err(ProxyError::Config(format!(
+                "security.waf: rule '{}' has no targets",
+                self.id
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl RouteWafConfig {
+    pub(crate) fn effective_view(&self) -> RouteWafView {
+        RouteWafView { enabled: self.enabled, mode: self.mode.map(WafMode::as_str) }
+    }
+}
+
+fn default_status() -> u16 {
+    403
+}
+
+fn default_max_body_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_route_enabled() -> bool {
+    true
+}
+
+/// Allowlisted effective-config view of [`WafConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct WafView<'a> {
+    enabled: bool,
+    mode: &'static str,
+    status: u16,
+    rule_sets: Vec<&'static str>,
+    rules_files: &'a [String],
+    /// Inline rules followed by the rules read from `rules_files`.
+    rules: Vec<WafRuleView<'a>>,
+    allowed_methods: &'a [String],
+    max_headers: Option<usize>,
+    max_header_bytes: Option<usize>,
+    max_body_bytes: usize,
+}
+
+#[derive(Serialize)]
+struct WafRuleView<'a> {
+    id: &'a str,
+    targets: &'a [WafTarget],
+    pattern: &'a str,
+}
+
+/// Allowlisted effective-config view of [`RouteWafConfig`].
+#[derive(Serialize)]
+pub(crate) struct RouteWafView {
+    enabled: bool,
+    mode: Option<&'static str>,
+}
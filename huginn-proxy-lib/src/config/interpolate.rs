@@ -0,0 +1,463 @@
+//! `${NAME}` substitution in config files.
+//!
+//! Runs on the raw file text before it is parsed, so parse errors still point at the right line.
+//! Values come from the process environment and, when [`SECRETS_FILE_ENV`] names one, from a
+//! `NAME=value` secrets file that is re-read on every load (so rotated secrets are picked up by a
+//! reload).
+//!
+//! | Syntax              | Result                                                 |
+//! |---------------------|--------------------------------------------------------|
+//! | `${NAME}`           | Value of `NAME`; an error if it is not defined         |
+//! | `${NAME:-fallback}` | Value of `NAME`, or `fallback` if it is unset or empty |
+//! | `$${`               | A literal `${`                                         |
+//!
+//! Only upper-case names (`[A-Z_][A-Z0-9_]*`) are variables, so `${client_ip}` and the other
+//! header template variables reach the header templates untouched. A `$` not followed by `{` is
+//! left alone (regex anchors), and comments are not interpolated.
+//!
+//! A value is written for the place it lands in, so it cannot change the structure of the file:
+//! escaped in a double-quoted string, with `'` doubled in a YAML single-quoted string, and
+//! rejected where it cannot be written as is (a `'` in a TOML literal string, or anything but a
+//! plain word or number outside quotes). A fallback is file text and is written as is.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::config::parser::ConfigFormat;
+use crate::error::{ProxyError, Result};
+
+/// Environment variable naming the optional secrets file.
+pub(crate) const SECRETS_FILE_ENV: &str = "HUGINN_SECRETS_FILE";
+
+/// Where `${NAME}` values come from: the process environment first, then the secrets file.
+pub(crate) struct Variables {
+    secrets: HashMap<String, String>,
+}
+
+impl Variables {
+    /// The process environment plus the secrets file named by [`SECRETS_FILE_ENV`], if set.
+    pub(crate) fn load() -> Result<Self> {
+        let Some(path) = env::var_os(SECRETS_FILE_ENV).filter(|path| !path.is_empty()) else {
+            return Ok(Self { secrets: HashMap::new() });
+        };
+        let path = Path::new(&path);
+        let content = fs::read_to_string(path).map_err(|e| {
+            ProxyError::Config(format!(
+                "Failed to read secrets file '{}' ({SECRETS_FILE_ENV}): {e}",
+                path.display()
+            ))
+        })?;
+        let secrets = parse_secrets(&content)
+            .map_err(|e| ProxyError::Config(format!("Secrets file '{}': {e}", path.display())))?;
+        Ok(Self { secrets })
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        env::var(name)
+            .ok()
+            .or_else(|| self.secrets.get(name).cloned())
+    }
+}
+
+/// Replace every `${NAME}` / `${NAME:-fallback}` in `content`, a file in `format`. Every
+/// undefined name is reported in one error, with its line; values are never echoed.
+pub(crate) fn interpolate(
+    content: &str,
+    format: ConfigFormat,
+    variables: &Variables,
+) -> Result<String> {
+    let mut scanner = Scanner::new(format);
+    let mut out = String::with_capacity(content.len());
+    let mut undefined = Vec::new();
+    let mut rest = content;
+    while let Some(c) = rest.chars().next() {
+        scanner.enter(c);
+        if scanner.state != State::Comment {
+            if let Some(after) = rest.strip_prefix("$${") {
+                out.push_str("${");
+                scanner.wrote_value();
+                rest = after;
+                continue;
+            }
+            if let Some((reference, after)) = rest
+                .strip_prefix("${")
+                .map(|after| parse_reference(after, scanner.line))
+                .transpose()?
+                .flatten()
+            {
+                let value = variables
+                    .get(reference.name)
+                    .filter(|value| reference.fallback.is_none() || !value.is_empty());
+                match (value, reference.fallback) {
+                    (Some(value), _) => out.push_str(&scanner.write(reference.name, &value)?),
+                    (None, Some(fallback)) => out.push_str(fallback),
+                    (None, None) => {
+                        undefined.push(format!("`{}` (line {})", reference.name, scanner.line));
+                    }
+                }
+                scanner.wrote_value();
+                rest = after;
+                continue;
+            }
+        }
+        let consumed = scanner.step(rest, c);
+        let (syntax, after) = rest.split_at(consumed);
+        out.push_str(syntax);
+        rest = after;
+    }
+
+    if !undefined.is_empty() {
+        return Err(ProxyError::Config(format!(
+            "undefined variable(s): {}; set them in the environment or the {SECRETS_FILE_ENV} \
+             file, or give a fallback with `${{NAME:-fallback}}`",
+            undefined.join(", ")
+        )));
+    }
+    Ok(out)
+}
+
+/// A `${NAME}` or `${NAME:-fallback}` reference.
+struct Reference<'a> {
+    name: &'a str,
+    fallback: Option<&'a str>,
+}
+
+/// The reference at the start of `after` (the text past `${`) and the text that follows it, or
+/// `None` when `after` does not start with a variable name (e.g. `${client_ip}`).
+fn parse_reference(after: &str, line: usize) -> Result<Option<(Reference<'_>, &str)>> {
+    let end = after
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(after.len());
+    let (name, rest) = after.split_at(end);
+    if !is_variable_name(name) {
+        return Ok(None);
+    }
+    if let Some(rest) = rest.strip_prefix('}') {
+        return Ok(Some((Reference { name, fallback: None }, rest)));
+    }
+    let line_end = |text: &str| text.find('\n').unwrap_or(text.len());
+    if let Some(rest) = rest.strip_prefix(":-") {
+        if let Some(close) = rest[..line_end(rest)].find('}') {
+            let (fallback, rest) = rest.split_at(close);
+            return Ok(Some((Reference { name, fallback: Some(fallback) }, &rest[1..])));
+        }
+    }
+    Err(ProxyError::Config(format!(
+        "line {line}: unterminated `${{` (write `$${{` for a literal one)"
+    )))
+}
+
+/// Where the scanner is in the file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Outside strings and comments.
+    Plain,
+    Comment,
+    /// Inside a quoted string.
+    Quoted(Quote),
+    /// Inside a YAML block scalar (`|` or `>`) whose key sits at `indent`.
+    Block {
+        indent: usize,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Quote {
+    /// `"..."`: TOML basic strings, JSON strings and YAML double-quoted scalars.
+    Double,
+    /// TOML `"""..."""`.
+    MultiDouble,
+    /// TOML `'...'`: no escapes.
+    Literal,
+    /// TOML `'''...'''`: no escapes.
+    MultiLiteral,
+    /// YAML `'...'`: `'` is written `''`.
+    Single,
+}
+
+impl Quote {
+    fn close(self) -> &'static str {
+        match self {
+            Self::Double => "\"",
+            Self::MultiDouble => "\"\"\"",
+            Self::Literal | Self::Single => "'",
+            Self::MultiLiteral => "'''",
+        }
+    }
+}
+
+/// Tracks strings, comments and (for YAML) block scalars, just enough to know how a value must
+/// be written where a reference sits. It does not validate the file; the parser does.
+struct Scanner {
+    format: ConfigFormat,
+    state: State,
+    line: usize,
+    /// Indentation of the current line, while only spaces have been seen on it.
+    indent: usize,
+    at_indent: bool,
+    /// Previous character, and the last one outside strings that is not whitespace, on this line.
+    prev: Option<char>,
+    last: Option<char>,
+    /// YAML: open `[` / `{` collections, and whether this line ends in a block scalar indicator.
+    flow_depth: usize,
+    block_header: bool,
+}
+
+impl Scanner {
+    fn new(format: ConfigFormat) -> Self {
+        Self {
+            format,
+            state: State::Plain,
+            line: 1,
+            indent: 0,
+            at_indent: true,
+            prev: None,
+            last: None,
+            flow_depth: 0,
+            block_header: false,
+        }
+    }
+
+    /// Leave a YAML block scalar at the first line indented no deeper than its key.
+    fn enter(&mut self, c: char) {
+        if let State::Block { indent } = self.state {
+            if self.at_indent && !c.is_whitespace() && self.indent <= indent {
+                self.state = State::Plain;
+            }
+        }
+    }
+
+    /// Consume the syntax at the start of `rest` (first character `c`), returning its length.
+    fn step(&mut self, rest: &str, c: char) -> usize {
+        let len = match self.state {
+            State::Comment | State::Block { .. } => c.len_utf8(),
+            State::Quoted(quote) => self.quoted(rest, c, quote),
+            State::Plain => self.plain(rest, c),
+        };
+        for c in rest[..len].chars() {
+            if c == '\n' {
+                self.new_line();
+            } else if self.at_indent && c == ' ' {
+                self.indent = self.indent.saturating_add(1);
+            } else {
+                self.at_indent = false;
+                self.prev = Some(c);
+            }
+        }
+        len
+    }
+
+    fn quoted(&mut self, rest: &str, c: char, quote: Quote) -> usize {
+        if c == '\\' && matches!(quote, Quote::Double | Quote::MultiDouble) {
+            return rest.chars().take(2).map(char::len_utf8).sum();
+        }
+        if quote == Quote::Single && rest.starts_with("''") {
+            return 2;
+        }
+        if rest.starts_with(quote.close()) {
+            self.state = State::Plain;
+            self.last = Some(c);
+            return quote.close().len();
+        }
+        c.len_utf8()
+    }
+
+    fn plain(&mut self, rest: &str, c: char) -> usize {
+        let value_start = self.value_start();
+        let (state, len) = match (self.format, c) {
+            (ConfigFormat::Toml, '#') => (State::Comment, 1),
+            (ConfigFormat::Yaml, '#') if self.prev.is_none_or(char::is_whitespace) => {
+                (State::Comment, 1)
+            }
+            (ConfigFormat::Toml, '"') if rest.starts_with("\"\"\"") => {
+                (State::Quoted(Quote::MultiDouble), 3)
+            }
+            (ConfigFormat::Toml, '\'') if rest.starts_with("'''") => {
+                (State::Quoted(Quote::MultiLiteral), 3)
+            }
+            (ConfigFormat::Toml, '\'') => (State::Quoted(Quote::Literal), 1),
+            (ConfigFormat::Toml | ConfigFormat::Json, '"') => (State::Quoted(Quote::Double), 1),
+            (ConfigFormat::Yaml, '"') if value_start => (State::Quoted(Quote::Double), 1),
+            (ConfigFormat::Yaml, '\'') if value_start => (State::Quoted(Quote::Single), 1),
+            _ => (State::Plain, c.len_utf8()),
+        };
+        self.state = state;
+        if self.format == ConfigFormat::Yaml && state == State::Plain {
+            match c {
+                '[' | '{' => self.flow_depth = self.flow_depth.saturating_add(1),
+                ']' | '}' => self.flow_depth = self.flow_depth.saturating_sub(1),
+                '|' | '>' if value_start && self.flow_depth == 0 => self.block_header = true,
+                // Indentation and chomping indicators, e.g. `|2-`.
+                '0'..='9' | '+' | '-' if self.block_header => {}
+                c if !c.is_whitespace() => self.block_header = false,
+                _ => {}
+            }
+        }
+        if !c.is_whitespace() && state != State::Comment {
+            self.last = Some(c);
+        }
+        len
+    }
+
+    fn new_line(&mut self) {
+        self.line = self.line.saturating_add(1);
+        if self.block_header && matches!(self.state, State::Plain | State::Comment) {
+            self.state = State::Block { indent: self.indent };
+        } else if self.state == State::Comment {
+            self.state = State::Plain;
+        }
+        self.block_header = false;
+        self.indent = 0;
+        self.at_indent = true;
+        self.prev = None;
+        self.last = None;
+    }
+
+    /// YAML: whether a quote here would start a quoted scalar rather than sit inside a plain one.
+    fn value_start(&self) -> bool {
+        self.last
+            .is_none_or(|last| matches!(last, ':' | '-' | '?' | ',' | '[' | '{'))
+    }
+
+    /// Record text written in place of a reference: it belongs to the current value.
+    fn wrote_value(&mut self) {
+        self.at_indent = false;
+        self.prev = Some('}');
+        if self.state == State::Plain {
+            self.last = Some('}');
+            self.block_header = false;
+        }
+    }
+
+    /// `value` of variable `name`, written for the place the scanner is at.
+    fn write(&self, name: &str, value: &str) -> Result<String> {
+        let line = self.line;
+        if value.contains('\n') {
+            return Err(ProxyError::Config(format!(
+                "line {line}: the value of `{name}` spans several lines"
+            )));
+        }
+        let control = value.chars().any(|c| c.is_control() && c != '\t');
+        match self.state {
+            State::Quoted(Quote::Double | Quote::MultiDouble) => Ok(escape(value)),
+            State::Quoted(quote @ (Quote::Literal | Quote::MultiLiteral)) => {
+                let closes = match quote {
+                    Quote::MultiLiteral => value.contains("'''"),
+                    _ => value.contains('\''),
+                };
+                if closes || control {
+                    return Err(ProxyError::Config(format!(
+                        "line {line}: the value of `{name}` cannot be written in a \
+                         single-quoted string; use a double-quoted one"
+                    )));
+                }
+                Ok(value.to_string())
+            }
+            State::Quoted(Quote::Single) if control => Err(ProxyError::Config(format!(
+                "line {line}: the value of `{name}` has control characters; use a double-quoted \
+                 string"
+            ))),
+            State::Quoted(Quote::Single) => Ok(value.replace('\'', "''")),
+            State::Block { .. } if control => Err(ProxyError::Config(format!(
+                "line {line}: the value of `{name}` has control characters; use a double-quoted \
+                 string"
+            ))),
+            State::Block { .. } => Ok(value.to_string()),
+            State::Plain | State::Comment => {
+                let plain = match self.format {
+                    ConfigFormat::Yaml => self.is_yaml_plain(value),
+                    ConfigFormat::Toml | ConfigFormat::Json => value.chars().all(|c| {
+                        c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.' | ':')
+                    }),
+                };
+                if control || !plain {
+                    return Err(ProxyError::Config(format!(
+                        "line {line}: the value of `{name}` is not a plain word or number; put \
+                         `${{{name}}}` in a double-quoted string"
+                    )));
+                }
+                Ok(value.to_string())
+            }
+        }
+    }
+
+    /// Whether `value` stays one YAML plain scalar where the scanner is.
+    fn is_yaml_plain(&self, value: &str) -> bool {
+        let starts_scalar = self.value_start();
+        let indicator =
+            value.starts_with(|c: char| c.is_whitespace() || "-?:,[]{}#&*!|>'\"%@`".contains(c));
+        let breaks_scalar = (starts_scalar && indicator)
+            || value.ends_with(char::is_whitespace)
+            || value.ends_with(':')
+            || value.contains(": ")
+            || value.contains(" #")
+            || (self.flow_depth > 0 && value.contains([',', '[', ']', '{', '}']));
+        !breaks_scalar
+    }
+}
+
+/// `value` escaped for a double-quoted string; the escapes are the same in TOML, YAML and JSON.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04X}", u32::from(c));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `NAME=value` lines; blank lines and `#` comments are skipped, an `export ` prefix and one pair
+/// of matching quotes around the value are stripped.
+fn parse_secrets(content: &str) -> std::result::Result<HashMap<String, String>, String> {
+    let mut secrets = HashMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(format!("line {}: expected NAME=value", index.saturating_add(1)));
+        };
+        let name = name.trim();
+        if !is_valid_name(name) {
+            return Err(format!(
+                "line {}: invalid variable name `{name}`",
+                index.saturating_add(1)
+            ));
+        }
+        secrets.insert(name.to_string(), unquote(value.trim()).to_string());
+    }
+    Ok(secrets)
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(value)
+}
+
+/// `[A-Za-z_][A-Za-z0-9_]*`, like a shell variable.
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `[A-Z_][A-Z0-9_]*`: the names `${...}` interpolates. Lower-case names are header template
+/// variables.
+fn is_variable_name(name: &str) -> bool {
+    is_valid_name(name) && !name.contains(|c: char| c.is_ascii_lowercase())
+}
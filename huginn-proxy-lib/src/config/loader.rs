@@ -1,10 +1,11 @@
 use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::config::audit;
+use crate::config::interpolate::{interpolate, Variables};
 use crate::config::parser::ConfigFormat;
-use crate::config::Config;
+use crate::config::{ClientAuth, Config, RouteFragment, WafRuleFile, DEFAULT_DOMAIN_LABEL};
 use crate::error::{ProxyError, Result};
 
 pub fn load_from_path<P: AsRef<Path>>(p: P) -> Result<Config> {
@@ -14,15 +15,180 @@ pub fn load_from_path<P: AsRef<Path>>(p: P) -> Result<Config> {
     let content = fs::read_to_string(path)
         .map_err(|e| ProxyError::Config(format!("Failed to read config file: {e}")))?;
 
-    let mut cfg = format.parser().parse(&content)?;
+    let variables = Variables::load()?;
+    let content = interpolate(&content, format, &variables).map_err(|e| in_file(path, e))?;
+    let cfg = format
+        .parser()
+        .parse(&content)
+        .map_err(|e| in_file(path, e))?;
 
+    finish(cfg, &variables)
+}
+
+/// Finish a config built in code (see [`ProxyBuilder`](crate::ProxyBuilder)) the way
+/// [`load_from_path`] finishes a parsed file: includes, WAF rule files, the challenge page,
+/// validation and audit.
+pub(crate) fn prepare(cfg: Config) -> Result<Config> {
+    finish(cfg, &Variables::load()?)
+}
+
+fn finish(mut cfg: Config, variables: &Variables) -> Result<Config> {
+    merge_tenants(&mut cfg)?;
     normalize_domain_hosts(&mut cfg);
+    merge_included_routes(&mut cfg, variables)?;
+    load_waf_rule_files(&mut cfg)?;
+    cfg.security.challenge.prepare()?;
     validate_config(&cfg)?;
     audit::run(&cfg);
 
     Ok(cfg)
 }
 
+/// Prefix a config error with the file it came from.
+fn in_file(path: &Path, e: ProxyError) -> ProxyError {
+    match e {
+        ProxyError::Config(message) => ProxyError::Config(format!("{}: {message}", path.display())),
+        other => other,
+    }
+}
+
+/// Move the domains and backends of every `[[tenants]]` entry into `domains` and `backends`,
+/// tagged with the tenant, and give its domains the tenant's `headers` and `security` blocks they
+/// do not set themselves. A backend address can be declared only once across the config.
+fn merge_tenants(cfg: &mut Config) -> Result<()> {
+    let mut names = HashSet::new();
+    for tenant in &mut cfg.tenants {
+        tenant.validate()?;
+        if !names.insert(tenant.name.clone()) {
+            return Err(ProxyError::Config(format!("Duplicate tenant name '{}'", tenant.name)));
+        }
+        if tenant.domains.is_empty() {
+            return Err(ProxyError::Config(format!(
+                "tenant '{}' declares no domains; a tenant only receives traffic for its own hosts",
+                tenant.name
+            )));
+        }
+        for mut backend in tenant.backends.drain(..) {
+            if cfg.backends.iter().any(|b| b.address == backend.address) {
+                return Err(ProxyError::Config(format!(
+                    "tenant '{}': backend '{}' is already declared; a backend belongs to one \
+                     tenant or to none",
+                    tenant.name, backend.address
+                )));
+            }
+            backend.tenant = Some(tenant.name.clone());
+            cfg.backends.push(backend);
+        }
+        for mut domain in std::mem::take(&mut tenant.domains) {
+            tenant.adopt(&mut domain);
+            cfg.domains.push(domain);
+        }
+    }
+    Ok(())
+}
+
+/// Append the routes of every `include` file to the domain with the same `host`, in `include`
+/// order and file-name order within a pattern; a file matched twice is read once. Included files
+/// go through the same `${NAME}` interpolation as the main config but cannot include others.
+fn merge_included_routes(cfg: &mut Config, variables: &Variables) -> Result<()> {
+    let mut seen = HashSet::new();
+    for pattern in cfg.include.clone() {
+        for path in expand_include(&pattern)? {
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            let format = ConfigFormat::from_path(&path)?;
+            let content = fs::read_to_string(&path).map_err(|e| {
+                ProxyError::Config(format!(
+                    "Failed to read included file '{}': {e}",
+                    path.display()
+                ))
+            })?;
+            let content =
+                interpolate(&content, format, variables).map_err(|e| in_file(&path, e))?;
+            let fragment: RouteFragment = format
+                .deserialize(&content)
+                .map_err(|e| in_file(&path, e))?;
+            for entry in fragment.domains {
+                let host = entry.host.map(|host| host.to_ascii_lowercase());
+                let Some(domain) = cfg.domains.iter_mut().find(|d| d.host == host) else {
+                    return Err(ProxyError::Config(format!(
+                        "{}: domain '{}' is not declared in the main config; included files \
+                         can only add routes to existing domains",
+                        path.display(),
+                        host.as_deref().unwrap_or(DEFAULT_DOMAIN_LABEL)
+                    )));
+                };
+                domain.routes.extend(entry.routes);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Files named by an `include` entry, sorted. `*` wildcards are allowed in the file name only
+/// and, like a shell, do not match a leading `.`; a pattern without one must name an existing
+/// file, while a wildcard may match nothing.
+fn expand_include(pattern: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let wildcard = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| name.contains('*'));
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    if dir.is_some_and(|dir| dir.to_string_lossy().contains('*')) {
+        return Err(ProxyError::Config(format!(
+            "include '{pattern}': wildcards are only supported in the file name"
+        )));
+    }
+    let Some(wildcard) = wildcard else {
+        if !path.is_file() {
+            return Err(ProxyError::Config(format!("include '{pattern}': file not found")));
+        }
+        return Ok(vec![path.to_path_buf()]);
+    };
+
+    let dir = dir.unwrap_or(Path::new("."));
+    let entries =
+        fs::read_dir(dir).map_err(|e| ProxyError::Config(format!("include '{pattern}': {e}")))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| ProxyError::Config(format!("include '{pattern}': {e}")))?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else { continue };
+        let hidden = name.starts_with('.') && !wildcard.starts_with('.');
+        if !hidden && wildcard_matches(wildcard, name) && entry.path().is_file() {
+            files.push(dir.join(name));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of characters.
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let mut parts: Vec<&str> = pattern.split('*').collect();
+    let Some(last) = parts.pop() else {
+        return false;
+    };
+    let mut parts = parts.into_iter();
+    let Some(first) = parts.next() else {
+        return name == last;
+    };
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    for part in parts {
+        let Some(index) = rest.find(part) else {
+            return false;
+        };
+        rest = rest
+            .get(index.saturating_add(part.len())..)
+            .unwrap_or_default();
+    }
+    rest.ends_with(last)
+}
+
 /// Lowercase every domain `host`. DNS names and the HTTP `Host` header are
 /// case-insensitive (RFC 4343 / RFC 7230); the request side is lowercased in
 /// `extract_request_host`, so config and request hosts compare consistently.
@@ -34,6 +200,29 @@ fn normalize_domain_hosts(cfg: &mut Config) {
     }
 }
 
+/// Read the rules of every `security.waf.rules_files` entry into `file_rules`, in file order.
+/// Relative paths resolve against the working directory, like the other paths of the config.
+fn load_waf_rule_files(cfg: &mut Config) -> Result<()> {
+    let mut rules = Vec::new();
+    for file in &cfg.security.waf.rules_files {
+        let path = Path::new(file);
+        let content = fs::read_to_string(path).map_err(|e| {
+            ProxyError::Config(format!("Failed to read WAF rules file '{file}': {e}"))
+        })?;
+        let parsed: WafRuleFile = ConfigFormat::from_path(path)?
+            .deserialize(&content)
+            .map_err(|e| match e {
+                ProxyError::Config(message) => {
+                    ProxyError::Config(format!("WAF rules file '{file}': {message}"))
+                }
+                other => other,
+            })?;
+        rules.extend(parsed.rules);
+    }
+    cfg.security.waf.file_rules = rules;
+    Ok(())
+}
+
 /// Reject duplicate hosts and more than one catch-all (host-less) domain, which
 /// would make domain selection and cert resolution disagree (routing keeps the
 /// first match; the cert resolver keeps the last).
@@ -89,6 +278,43 @@ fn validate_config(cfg: &Config) -> Result<()> {
         }
     }
 
+    if let Some(tls) = &cfg.tls {
+        if let ClientAuth::Required { ca_cert_path } | ClientAuth::Optional { ca_cert_path } =
+            &tls.client_auth
+        {
+            if !Path::new(ca_cert_path).exists() {
+                return Err(ProxyError::Config(format!(
+                    "tls.client_auth: client CA file not found: {ca_cert_path}"
+                )));
+            }
+        }
+        let secret_file = tls
+            .session_resumption
+            .ticket_keys
+            .as_ref()
+            .and_then(|keys| keys.secret_file.as_deref());
+        if let Some(path) = secret_file {
+            if !Path::new(path).exists() {
+                return Err(ProxyError::Config(format!(
+                    "tls.session_resumption.ticket_keys: secret file not found: {path}"
+                )));
+            }
+        }
+    }
+
+    for backend in &cfg.backends {
+        let Some(tls) = &backend.tls else { continue };
+        let paths = [&tls.ca_cert_path, &tls.client_cert_path, &tls.client_key_path];
+        for path in paths.into_iter().flatten() {
+            if !Path::new(path).exists() {
+                return Err(ProxyError::Config(format!(
+                    "Backend '{}': TLS file not found: {path}",
+                    backend.address
+                )));
+            }
+        }
+    }
+
     cfg.validate_cross_refs()?;
 
     Ok(())
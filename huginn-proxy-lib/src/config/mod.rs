@@ -6,6 +6,7 @@ pub mod startup;
 pub mod watcher;
 
 pub(crate) mod audit;
+mod interpolate;
 mod loader;
 mod root;
 
@@ -14,22 +15,45 @@ pub use audit::{
     security_override_warnings, trusted_proxies_warnings, ConfigWarning,
 };
 pub use dynamic::security::{
-    CspConfig, DomainSecurityConfig, HstsConfig, IpFilterConfig, IpFilterMode, LimitBy,
-    RateLimitConfig, RouteSecurityConfig, SecurityConfig, SecurityDynamicConfig, SecurityHeaders,
-    TrustedProxiesConfig,
+    CspConfig, DomainSecurityConfig, FingerprintFilterConfig, FingerprintList, HstsConfig,
+    IpFilterConfig, IpFilterMode, IpFilterPrecedence, LimitBy, RateLimitConfig, RateLimitStore,
+    RedisStoreConfig, RouteSecurityConfig, SecurityConfig, SecurityDynamicConfig, SecurityHeaders,
+    SecurityHeadersPreset, TrustedProxiesConfig,
 };
 pub use dynamic::{
-    sort_domain_routes, sort_routes, Backend, BackendHttpVersion, BackendPoolConfig, CustomHeader,
-    Domain, DynamicConfig, HeaderManipulation, HeaderManipulationGroup, HealthCheckConfig,
-    HealthCheckType, Route, DEFAULT_DOMAIN_LABEL, DEFAULT_FINGERPRINTING,
+    render_header_template, sort_domain_routes, sort_routes, unix_socket_path, Backend,
+    BackendHttp2Config, BackendHttpVersion, BackendPoolConfig, BackendPoolLimits,
+    BackendProxyConfig, BackendQueueConfig, BackendTlsConfig, BandwidthConfig, BandwidthKey,
+    BodyRewriteConfig, BodyRewriteRule, BotVerificationConfig, ChallengeConfig,
+    CircuitBreakerConfig, CompressionAlgorithm, CompressionConfig, ConnectConfig, CrawlerConfig,
+    CustomHeader, DiscoveryConfig, Domain, DomainRoutes, DynamicConfig, ExtAuthzConfig,
+    ExtAuthzFailureMode, FailoverConfig, FingerprintFormat, HeaderManipulation,
+    HeaderManipulationGroup, HeaderMatch, HealthCheckConfig, HealthCheckType, Ja4Variant,
+    PathRewriteConfig, PriorityHeader, PrivacyConfig, PrivacyHeaderGroup, RedirectConfig,
+    RedirectRule, RegexPattern, Route, RouteAccessLogConfig, RouteCacheConfig, RouteFragment,
+    RoutePriority, RouteProtocol, RouteTimeoutConfig, RouteWafConfig, ServeStaticConfig,
+    SpoofedAction, StickyConfig, StickyHashKey, StickyMode, StrictHttpConfig, StrictHttpMode,
+    SyntheticResponseConfig, TarpitConfig, TemplateVar, Tenant, TlsMatch, WafConfig, WafMode,
+    WafRule, WafRuleFile, WafRuleSet, WafTarget, WebSocketConfig, DEFAULT_DOMAIN_LABEL,
+    DEFAULT_FINGERPRINTING, MAX_FAILOVER_ATTEMPTS, MAX_REPLAY_BODY_BYTES, MAX_SYNTHETIC_BODY_BYTES,
+    MAX_TARPIT_DELAY_MS, MIN_PRIVACY_HASH_KEY_BYTES, NO_TENANT_LABEL, PRIVACY_KEPT_HEADERS,
 };
 pub use effective::{EffectiveConfigSummary, EffectiveConfigView};
 pub use loader::load_from_path;
-pub use parser::{ConfigFormat, ConfigParser, TomlParser, YamlParser};
+pub(crate) use loader::prepare;
+pub use parser::{ConfigFormat, ConfigParser, JsonParser, TomlParser, YamlParser};
+pub(crate) use root::{validate_route, validate_route_shadowing};
 pub use root::{Config, ConfigParts};
 pub use secret::Secret;
 pub use startup::{
-    ClientAuth, FingerprintConfig, KeepAliveConfig, ListenConfig, LoggingConfig,
-    ProxyProtocolConfig, ProxyProtocolMode, ReloadConfig, SessionResumptionConfig, StaticConfig,
-    TelemetryConfig, TimeoutConfig, TlsConfig, TlsOptions, TlsVersion,
+    AccessLogConfig, AccessLogField, AccessLogOutput, AdminConfig, AnomalyDetectionConfig,
+    CacheConfig, ClientAuth, ClientCertConfig, ClientTrackingConfig, EventSinkKind,
+    EventStreamConfig, FingerprintConfig, FingerprintHeadersConfig, FingerprintStatsConfig,
+    HandshakeCaptureConfig, HandshakeCaptureFormat, Http2Config, KeepAliveConfig, ListenAddr,
+    ListenConfig, ListenerConfig, ListenerFingerprintConfig, LoadSheddingConfig, LoggingConfig,
+    MetricsConfig, MissingClientCert, MustStapleFailure, OcspConfig, PassthroughConfig,
+    PassthroughRoute, ProxyProtocolConfig, ProxyProtocolMode, ReloadConfig, RequestIdConfig,
+    RequestIdFormat, SessionResumptionConfig, SigningConfig, SigningKeyConfig, StaticConfig,
+    TcpCapture, TcpConfig, TelemetryConfig, TicketKeysConfig, TimeoutConfig, TlsConfig, TlsOptions,
+    TlsVersion, TracingConfig, UdpListenerConfig, WhoamiConfig,
 };
@@ -0,0 +1,16 @@
+use super::{with_suggestion, ConfigParser};
+use crate::config::Config;
+use crate::error::{ProxyError, Result};
+
+pub struct JsonParser;
+
+impl ConfigParser for JsonParser {
+    fn parse(&self, content: &str) -> Result<Config> {
+        serde_json::from_str(content)
+            .map_err(|e| ProxyError::Config(with_suggestion(format!("JSON parse error: {e}"))))
+    }
+
+    fn format_name(&self) -> &'static str {
+        "JSON"
+    }
+}
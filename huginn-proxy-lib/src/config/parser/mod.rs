@@ -10,18 +10,23 @@
 //! |-----------------|--------|
 //! | `.toml`         | TOML   |
 //! | `.yaml`, `.yml` | YAML   |
+//! | `.json`         | JSON   |
 
+mod json;
 mod toml;
 mod yaml;
 
+pub use json::JsonParser;
 pub use toml::TomlParser;
 pub use yaml::YamlParser;
 
 use std::fmt;
 use std::path::Path;
 
+use serde::de::DeserializeOwned;
+
 use crate::config::Config;
-use crate::error::Result;
+use crate::error::{ProxyError, Result};
 
 /// Trait implemented by every config file parser
 pub trait ConfigParser: Send + Sync {
@@ -46,6 +51,8 @@ pub enum ConfigFormat {
     Toml,
     /// YAML used for `.yaml` and `.yml` files.
     Yaml,
+    /// JSON used for `.json` files.
+    Json,
 }
 
 impl ConfigFormat {
@@ -54,16 +61,17 @@ impl ConfigFormat {
     /// # Errors
     ///
     /// Returns [`crate::error::ProxyError::Config`] if the extension is missing or not one of
-    /// `.toml`, `.yaml`, `.yml`.
+    /// `.toml`, `.yaml`, `.yml`, `.json`.
     pub fn from_path(path: &Path) -> Result<Self> {
         match path.extension().and_then(|e| e.to_str()) {
             Some("toml") => Ok(Self::Toml),
             Some("yaml") | Some("yml") => Ok(Self::Yaml),
+            Some("json") => Ok(Self::Json),
             Some(ext) => Err(crate::error::ProxyError::Config(format!(
-                "Unsupported config file extension '.{ext}'. Use .toml, .yaml, or .yml"
+                "Unsupported config file extension '.{ext}'. Use .toml, .yaml, .yml, or .json"
             ))),
             None => Err(crate::error::ProxyError::Config(format!(
-                "Config file '{}' has no extension. Use .toml, .yaml, or .yml",
+                "Config file '{}' has no extension. Use .toml, .yaml, .yml, or .json",
                 path.display()
             ))),
         }
@@ -74,8 +82,22 @@ impl ConfigFormat {
         match self {
             Self::Toml => &TomlParser,
             Self::Yaml => &YamlParser,
+            Self::Json => &JsonParser,
         }
     }
+
+    /// Deserialize `content` in this format into `T`, for files the main config references
+    /// (route fragments, WAF rules). Errors carry the same `did you mean` hints as [`Config`]'s.
+    pub(crate) fn deserialize<T: DeserializeOwned>(self, content: &str) -> Result<T> {
+        match self {
+            Self::Toml => {
+                ::toml::from_str(content).map_err(|e| e.to_string().trim_end().to_string())
+            }
+            Self::Yaml => serde_norway::from_str(content).map_err(|e| e.to_string()),
+            Self::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| ProxyError::Config(with_suggestion(format!("{self} parse error: {e}"))))
+    }
 }
 
 impl fmt::Display for ConfigFormat {
@@ -83,6 +105,65 @@ impl fmt::Display for ConfigFormat {
         match self {
             Self::Toml => write!(f, "TOML"),
             Self::Yaml => write!(f, "YAML"),
+            Self::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// `message` with a `did you mean` hint appended when it reports an unknown field or variant
+/// that is a likely typo of one of the expected names.
+pub(crate) fn with_suggestion(message: String) -> String {
+    match suggestion(&message) {
+        Some(name) => format!("{message}\nhelp: did you mean `{name}`?"),
+        None => message,
+    }
+}
+
+/// The expected name closest to the unknown one in a serde `unknown field` / `unknown variant`
+/// message, if it is within a third of the name's length in edits.
+fn suggestion(message: &str) -> Option<&str> {
+    let rest = message
+        .split_once("unknown field ")
+        .or_else(|| message.split_once("unknown variant "))?
+        .1;
+    let (unknown, expected) = rest.split_once(", expected")?;
+    let unknown = unknown.trim_matches('`');
+    let max_distance = (unknown.chars().count() / 3).max(1);
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|candidate| (edit_distance(unknown, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i.saturating_add(1)];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous
+                .get(j)
+                .copied()
+                .unwrap_or_default()
+                .saturating_add(usize::from(ca != *cb));
+            let delete = previous
+                .get(j.saturating_add(1))
+                .copied()
+                .unwrap_or_default()
+                .saturating_add(1);
+            let insert = current
+                .get(j)
+                .copied()
+                .unwrap_or_default()
+                .saturating_add(1);
+            current.push(substitute.min(delete).min(insert));
         }
+        previous = current;
     }
+    previous.last().copied().unwrap_or_default()
 }
@@ -1,4 +1,4 @@
-use super::ConfigParser;
+use super::{with_suggestion, ConfigParser};
 use crate::config::Config;
 use crate::error::{ProxyError, Result};
 
@@ -6,7 +6,12 @@ pub struct TomlParser;
 
 impl ConfigParser for TomlParser {
     fn parse(&self, content: &str) -> Result<Config> {
-        toml::from_str(content).map_err(|e| ProxyError::Config(format!("TOML parse error: {e}")))
+        toml::from_str(content).map_err(|e| {
+            ProxyError::Config(with_suggestion(format!(
+                "TOML parse error: {}",
+                e.to_string().trim_end()
+            )))
+        })
     }
 
     fn format_name(&self) -> &'static str {
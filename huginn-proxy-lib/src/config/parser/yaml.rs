@@ -1,4 +1,4 @@
-use super::ConfigParser;
+use super::{with_suggestion, ConfigParser};
 use crate::config::Config;
 use crate::error::{ProxyError, Result};
 
@@ -7,7 +7,7 @@ pub struct YamlParser;
 impl ConfigParser for YamlParser {
     fn parse(&self, content: &str) -> Result<Config> {
         serde_norway::from_str(content)
-            .map_err(|e| ProxyError::Config(format!("YAML parse error: {e}")))
+            .map_err(|e| ProxyError::Config(with_suggestion(format!("YAML parse error: {e}"))))
     }
 
     fn format_name(&self) -> &'static str {
@@ -1,22 +1,35 @@
-use std::collections::HashSet;
 use std::sync::Arc;
 
 use serde::Deserialize;
 
-use super::dynamic::backend::{Backend, BackendPoolConfig, Domain};
+use super::dynamic::backend::{
+    Backend, BackendHttpVersion, BackendPoolConfig, Domain, FingerprintFormat, Route, RouteProtocol,
+};
+use super::dynamic::compression::CompressionConfig;
 use super::dynamic::headers::HeaderManipulation;
+use super::dynamic::redirect::RedirectConfig;
 use super::dynamic::security::{SecurityConfig, SecurityDynamicConfig};
+use super::dynamic::tenant::Tenant;
 use super::dynamic::DynamicConfig;
+use super::startup::access_log::AccessLogConfig;
+use super::startup::cache::CacheConfig;
+use super::startup::client_tracking::ClientTrackingConfig;
+use super::startup::event_stream::EventStreamConfig;
 use super::startup::fingerprinting::FingerprintConfig;
+use super::startup::handshake_capture::HandshakeCaptureConfig;
+use super::startup::http2::Http2Config;
 use super::startup::listen::ListenConfig;
+use super::startup::load_shedding::LoadSheddingConfig;
 use super::startup::reload::ReloadConfig;
+use super::startup::request_id::RequestIdConfig;
 use super::startup::telemetry::{LoggingConfig, TelemetryConfig};
 use super::startup::timeout::TimeoutConfig;
 use super::startup::tls::TlsConfig;
 use super::startup::StaticConfig;
+use crate::utils::time::unix_now;
 
 /// Main configuration structure, the TOML deserialization target.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     /// Listener configuration (addresses and socket options)
@@ -27,12 +40,27 @@ pub struct Config {
     /// Domain entries, each groups a TLS cert with its path-based routes (optional)
     #[serde(default)]
     pub domains: Vec<Domain>,
+    /// Tenants, each owning domains and backends that are moved into `domains` and `backends`
+    /// at load time (see [`super::Tenant`])
+    /// Default: empty
+    #[serde(default)]
+    pub tenants: Vec<Tenant>,
+    /// Files whose routes are merged into `domains` at load time (see [`super::RouteFragment`])
+    /// The file name may contain `*` wildcards, e.g. "routes.d/*.toml"
+    /// Default: empty
+    #[serde(default)]
+    pub include: Vec<String>,
     /// Preserve the original Host header from clients when forwarding to backends
     /// When true: Backend receives the original Host header (useful for virtual hosting)
     /// When false: Backend receives the backend address as Host header (default)
     /// Default: false
     #[serde(default)]
     pub preserve_host: bool,
+    /// Host assumed for HTTP/1.0 requests without a `Host` header (optional), e.g.
+    /// `"www.example.com"`: they are routed, and forwarded, as if they carried it.
+    /// Default: None (such requests go to the catch-all domain)
+    #[serde(default)]
+    pub default_host: Option<String>,
     /// TLS termination configuration (optional)
     /// If not provided, proxy operates in plain HTTP mode
     /// Default: None
@@ -48,6 +76,9 @@ pub struct Config {
     /// Timeout configuration
     #[serde(default)]
     pub timeout: TimeoutConfig,
+    /// HTTP/2 settings of client connections (window sizes, streams, keep-alive pings)
+    #[serde(default)]
+    pub http2: Http2Config,
     /// Security configuration
     #[serde(default)]
     pub security: SecurityConfig,
@@ -66,6 +97,35 @@ pub struct Config {
     /// Controls idle timeout and max idle connections per host
     #[serde(default)]
     pub backend_pool: BackendPoolConfig,
+    /// Response compression for all routes (optional)
+    /// Routes can replace it with their own `compression` block
+    #[serde(default)]
+    pub compression: Option<CompressionConfig>,
+    /// HTTPS and path redirects answered before routing (optional)
+    /// Domains can replace it with their own `redirect` block
+    #[serde(default)]
+    pub redirect: Option<RedirectConfig>,
+    /// Response cache storage, used by routes with a `cache` block
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Per-request structured access log
+    #[serde(default)]
+    pub access_log: AccessLogConfig,
+    /// Raw ClientHello capture for offline analysis
+    #[serde(default)]
+    pub handshake_capture: HandshakeCaptureConfig,
+    /// Adaptive load shedding under overload
+    #[serde(default)]
+    pub load_shedding: LoadSheddingConfig,
+    /// Request ID generation and correlation header
+    #[serde(default)]
+    pub request_id: RequestIdConfig,
+    /// Fingerprint events published to a webhook, NATS or Kafka
+    #[serde(default)]
+    pub event_stream: EventStreamConfig,
+    /// Cookie-based client first-seen / request-count headers for backends
+    #[serde(default)]
+    pub client_tracking: ClientTrackingConfig,
 }
 
 /// Config split into its static and dynamic halves.
@@ -80,26 +140,132 @@ pub struct ConfigParts {
 impl Config {
     /// Validate cross-references within the config.
     pub fn validate_cross_refs(&self) -> crate::error::Result<()> {
-        let backend_addrs: HashSet<&str> =
-            self.backends.iter().map(|b| b.address.as_str()).collect();
-
+        self.listen.validate()?;
+        self.telemetry.admin.validate(self.telemetry.metrics_port)?;
+        self.telemetry.tracing.validate()?;
+        self.telemetry.metrics.validate()?;
+        self.telemetry
+            .fingerprint_stats
+            .validate(self.telemetry.metrics_port)?;
+        self.telemetry.anomaly_detection.validate()?;
+        for listener in &self.listen.listeners {
+            if listener.tls == Some(true) && self.tls.is_none() {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "listener {}: tls = true requires a [tls] section",
+                    listener.addr
+                )));
+            }
+            if let Some(tenant) = listener
+                .tenant
+                .as_ref()
+                .filter(|name| !self.tenants.iter().any(|t| &t.name == *name))
+            {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "listener {}: tenant '{tenant}' is not declared in [[tenants]]",
+                    listener.addr
+                )));
+            }
+            if listener.fingerprint.tcp_enabled == Some(true) && !self.fingerprint.tcp_enabled {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "listener {}: fingerprint.tcp_enabled = true requires the global \
+                     fingerprint.tcp_enabled",
+                    listener.addr
+                )));
+            }
+        }
+        if let Some(headers) = &self.headers {
+            headers.validate()?;
+        }
+        if let Some(host) = &self.default_host {
+            // An authority without userinfo: a name or address with an optional port.
+            if host.contains('@') || host.parse::<http::uri::Authority>().is_err() {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "default_host '{host}' is not a valid host (name or address, optional port)"
+                )));
+            }
+        }
+        if let Some(compression) = &self.compression {
+            compression.validate()?;
+        }
+        if let Some(redirect) = &self.redirect {
+            redirect.validate()?;
+        }
+        self.cache.validate()?;
+        self.access_log.validate()?;
+        self.handshake_capture.validate()?;
+        self.http2.validate()?;
+        self.backend_pool.validate()?;
+        self.load_shedding.validate()?;
+        self.request_id.validate()?;
+        self.event_stream.validate()?;
+        self.client_tracking.validate()?;
+        if self.security.max_connections_per_ip == Some(0) {
+            return Err(crate::error::ProxyError::Config(
+                "security.max_connections_per_ip must be greater than 0 (omit it for no limit)"
+                    .to_string(),
+            ));
+        }
+        self.security.headers.validate("security.headers")?;
+        self.security.ip_filter.validate("security.ip_filter")?;
+        self.security.rate_limit.validate("security.rate_limit")?;
         for domain in &self.domains {
+            if let Some(headers) = &domain.headers {
+                headers.validate()?;
+            }
+            if let Some(headers) = domain.security.as_ref().and_then(|s| s.headers.as_ref()) {
+                headers.validate(&format!("Domain '{}' security.headers", domain.label()))?;
+            }
+            if let Some(redirect) = &domain.redirect {
+                redirect.validate()?;
+            }
+            if let Some(ip_filter) = domain.security.as_ref().and_then(|s| s.ip_filter.as_ref()) {
+                ip_filter.validate(&format!("Domain '{}' security.ip_filter", domain.label()))?;
+            }
+            if let Some(rate_limit) = domain.security.as_ref().and_then(|s| s.rate_limit.as_ref()) {
+                rate_limit.validate(&format!("Domain '{}' security.rate_limit", domain.label()))?;
+            }
             for route in &domain.routes {
-                if !backend_addrs.contains(route.backend.as_str()) {
-                    return Err(crate::error::ProxyError::Config(format!(
-                        "Domain '{}' route '{}' references unknown backend '{}' (known: [{}])",
-                        domain.label(),
-                        route.prefix,
-                        route.backend,
-                        backend_addrs.iter().copied().collect::<Vec<_>>().join(", ")
-                    )));
-                }
+                validate_route(domain, route, &self.backends, self.cache.max_size_bytes)?;
             }
+            let jwt = |format: Option<FingerprintFormat>| format == Some(FingerprintFormat::Jwt);
+            if !self.fingerprint.signing.enabled
+                && (jwt(domain.fingerprint_format)
+                    || domain.routes.iter().any(|r| jwt(r.fingerprint_format)))
+            {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "Domain '{}': fingerprint_format = \"jwt\" requires fingerprint.signing",
+                    domain.label()
+                )));
+            }
+            validate_route_shadowing(domain)?;
+        }
+        if let Some(tls) = &self.tls {
+            tls.client_cert.validate()?;
+            tls.session_resumption.validate()?;
+            tls.ocsp.validate()?;
         }
+        self.fingerprint.headers.validate()?;
+        self.fingerprint.whoami.validate()?;
+        self.fingerprint.signing.validate(unix_now())?;
+        self.security.fingerprint_filter.validate()?;
+        self.security.waf.validate()?;
+        self.security.bot_verification.validate()?;
+        self.security.challenge.validate()?;
+        self.security.tarpit.validate()?;
         for backend in &self.backends {
+            backend.validate()?;
             if let Some(hc) = &backend.health_check {
                 hc.validate()?;
             }
+            if let Some(cb) = &backend.circuit_breaker {
+                cb.validate()?;
+            }
+            if let Some(tls) = &backend.tls {
+                tls.validate()?;
+            }
+            if let Some(pool) = &backend.pool {
+                pool.validate()?;
+            }
         }
         Ok(())
     }
@@ -119,9 +285,18 @@ impl Config {
                 fingerprint: self.fingerprint,
                 logging: self.logging,
                 timeout: self.timeout,
+                http2: self.http2,
                 telemetry: self.telemetry,
                 reload: self.reload,
                 max_connections: self.security.max_connections,
+                max_connections_per_ip: self.security.max_connections_per_ip,
+                cache: self.cache,
+                access_log: self.access_log,
+                handshake_capture: self.handshake_capture,
+                load_shedding: self.load_shedding,
+                request_id: self.request_id,
+                event_stream: self.event_stream,
+                client_tracking: self.client_tracking,
             },
             dynamic_cfg: DynamicConfig {
                 backends: Arc::new(self.backends),
@@ -131,15 +306,278 @@ impl Config {
                     Arc::new(domains)
                 },
                 preserve_host: self.preserve_host,
+                default_host: self.default_host,
                 headers: self.headers,
                 security: SecurityDynamicConfig {
                     headers: self.security.headers,
                     ip_filter: self.security.ip_filter,
                     rate_limit: self.security.rate_limit,
                     trusted_proxies: self.security.trusted_proxies,
+                    fingerprint_filter: self.security.fingerprint_filter,
+                    waf: self.security.waf,
+                    bot_verification: self.security.bot_verification,
+                    strict_http: self.security.strict_http,
+                    challenge: self.security.challenge,
+                    tarpit: self.security.tarpit,
                 },
                 backend_pool: self.backend_pool,
+                compression: self.compression,
+                redirect: self.redirect,
             },
         }
     }
 }
+
+/// Rejects a route of `domain` that can never match because a route tried before it (higher
+/// `match_priority`, or the same prefix without conditions) takes all of its requests. Routes
+/// with the same matcher are a load-balance group, not shadowing.
+pub(crate) fn validate_route_shadowing(domain: &Domain) -> crate::error::Result<()> {
+    let mut routes = domain.routes.clone();
+    super::sort_routes(&mut routes);
+    for (index, route) in routes.iter().enumerate() {
+        let shadowing = routes
+            .iter()
+            .take(index)
+            .find(|earlier| !earlier.same_matcher(route) && earlier.covers(route));
+        if let Some(earlier) = shadowing {
+            return Err(crate::error::ProxyError::Config(format!(
+                "Domain '{}' route '{}' is never matched: route '{}' (match_priority {}) \
+                 is tried first and matches all of its requests; raise its match_priority \
+                 or narrow the other route",
+                domain.label(),
+                route.prefix,
+                earlier.prefix,
+                earlier.match_priority
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks one route of `domain` against the configured `backends` and the `[cache]` size cap.
+/// Shared by [`Config::validate_cross_refs`] and routes added at runtime through the admin API.
+pub(crate) fn validate_route(
+    domain: &Domain,
+    route: &Route,
+    backends: &[Backend],
+    cache_max_size_bytes: u64,
+) -> crate::error::Result<()> {
+    // `serve_static` routes answer from their directory and need no backend.
+    if route.backend.is_empty() && route.serve_static.is_none() {
+        return Err(crate::error::ProxyError::Config(format!(
+            "Domain '{}' route '{}' has no backend; only serve_static routes can leave it out",
+            domain.label(),
+            route.prefix
+        )));
+    }
+    let backend = backends.iter().find(|b| b.address == route.backend);
+    if backend.is_none() && !route.backend.is_empty() {
+        return Err(crate::error::ProxyError::Config(format!(
+            "Domain '{}' route '{}' references unknown backend '{}' (known: [{}])",
+            domain.label(),
+            route.prefix,
+            route.backend,
+            backends
+                .iter()
+                .map(|b| b.address.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+    if let Some(backend) = backend.filter(|b| b.tenant != domain.tenant) {
+        let owner = |tenant: &Option<String>| {
+            tenant
+                .as_ref()
+                .map_or_else(|| "no tenant".to_string(), |name| format!("tenant '{name}'"))
+        };
+        return Err(crate::error::ProxyError::Config(format!(
+            "Domain '{}' route '{}' ({}) references backend '{}' of {}; routes can only use \
+             backends of their own tenant",
+            domain.label(),
+            route.prefix,
+            owner(&domain.tenant),
+            route.backend,
+            owner(&backend.tenant)
+        )));
+    }
+    if let Some(rewrite) = &route.rewrite {
+        if route.replace_path.is_some() {
+            return Err(crate::error::ProxyError::Config(format!(
+                "Domain '{}' route '{}': replace_path and rewrite are mutually exclusive",
+                domain.label(),
+                route.prefix
+            )));
+        }
+        rewrite.validate()?;
+    }
+    if let Some(host) = &route.host_rewrite {
+        if route.preserve_host == Some(true) {
+            return Err(crate::error::ProxyError::Config(format!(
+                "Domain '{}' route '{}': preserve_host = true and host_rewrite are mutually \
+                 exclusive",
+                domain.label(),
+                route.prefix
+            )));
+        }
+        if host.contains('@') || host.parse::<http::uri::Authority>().is_err() {
+            return Err(crate::error::ProxyError::Config(format!(
+                "Domain '{}' route '{}': host_rewrite '{host}' is not a valid host (name or \
+                 address, optional port)",
+                domain.label(),
+                route.prefix
+            )));
+        }
+    }
+    if let Some(failover) = &route.failover {
+        failover.validate()?;
+    }
+    if let Some(connect) = &route.connect {
+        connect.validate()?;
+    }
+    if route.exact && route.regex.is_some() {
+        return Err(crate::error::ProxyError::Config(format!(
+            "Domain '{}' route '{}': exact and regex are mutually exclusive",
+            domain.label(),
+            route.prefix
+        )));
+    }
+    for method in &route.methods {
+        if http::Method::from_bytes(method.as_bytes()).is_err()
+            || method.bytes().any(|b| b.is_ascii_lowercase())
+        {
+            return Err(crate::error::ProxyError::Config(format!(
+                "Domain '{}' route '{}': invalid method '{method}' in methods \
+                 (methods are case-sensitive, e.g. \"POST\")",
+                domain.label(),
+                route.prefix
+            )));
+        }
+    }
+    for header in &route.match_headers {
+        header.validate()?;
+    }
+    for priority_header in &route.priority_headers {
+        priority_header.header.validate()?;
+    }
+    route.websocket.validate()?;
+    if let Some(ext_authz) = &route.ext_authz {
+        ext_authz.validate()?;
+    }
+    if let Some(headers) = &route.headers {
+        headers.validate()?;
+    }
+    if let Some(compression) = &route.compression {
+        compression.validate()?;
+    }
+    if let Some(cache) = &route.cache {
+        cache.validate()?;
+        if cache.max_object_bytes > cache_max_size_bytes {
+            return Err(crate::error::ProxyError::Config(format!(
+                "Domain '{}' route '{}': cache.max_object_bytes ({}) exceeds \
+                 [cache] max_size_bytes ({})",
+                domain.label(),
+                route.prefix,
+                cache.max_object_bytes,
+                cache_max_size_bytes
+            )));
+        }
+    }
+    if let Some(access_log) = &route.access_log {
+        access_log.validate()?;
+    }
+    if let Some(timeout) = &route.timeout {
+        timeout.validate()?;
+    }
+    if let Some(sticky) = &route.sticky {
+        sticky.validate()?;
+    }
+    if let Some(synthetic) = &route.synthetic {
+        synthetic.validate()?;
+    }
+    if let Some(privacy) = &route.privacy {
+        privacy.validate(&format!(
+            "Domain '{}' route '{}': privacy",
+            domain.label(),
+            route.prefix
+        ))?;
+    }
+    if let Some(body_rewrite) = &route.body_rewrite {
+        body_rewrite.validate(&format!(
+            "Domain '{}' route '{}': body_rewrite",
+            domain.label(),
+            route.prefix
+        ))?;
+    }
+    if let Some(serve_static) = &route.serve_static {
+        if route.regex.is_some() {
+            return Err(crate::error::ProxyError::Config(format!(
+                "Domain '{}' route '{}': serve_static cannot be combined with regex; files are \
+                 looked up by the path after prefix",
+                domain.label(),
+                route.prefix
+            )));
+        }
+        serve_static.validate(&format!(
+            "Domain '{}' route '{}': serve_static",
+            domain.label(),
+            route.prefix
+        ))?;
+    }
+    if let Some(bandwidth) = &route.bandwidth {
+        bandwidth.validate(&format!(
+            "Domain '{}' route '{}': bandwidth",
+            domain.label(),
+            route.prefix
+        ))?;
+    }
+    if let Some(headers) = route.security.as_ref().and_then(|s| s.headers.as_ref()) {
+        headers.validate(&format!(
+            "Domain '{}' route '{}' security.headers",
+            domain.label(),
+            route.prefix
+        ))?;
+    }
+    if let Some(ip_filter) = route.security.as_ref().and_then(|s| s.ip_filter.as_ref()) {
+        ip_filter.validate(&format!(
+            "Domain '{}' route '{}' security.ip_filter",
+            domain.label(),
+            route.prefix
+        ))?;
+    }
+    if let Some(rate_limit) = route.security.as_ref().and_then(|s| s.rate_limit.as_ref()) {
+        rate_limit.validate(&format!(
+            "Domain '{}' route '{}' security.rate_limit",
+            domain.label(),
+            route.prefix
+        ))?;
+    }
+    if route.max_request_body_bytes == Some(0) || route.max_response_body_bytes == Some(0) {
+        return Err(crate::error::ProxyError::Config(format!(
+            "Domain '{}' route '{}': max_request_body_bytes and \
+             max_response_body_bytes must be greater than 0",
+            domain.label(),
+            route.prefix
+        )));
+    }
+    if route.max_in_flight == Some(0) {
+        return Err(crate::error::ProxyError::Config(format!(
+            "Domain '{}' route '{}': max_in_flight must be greater than 0",
+            domain.label(),
+            route.prefix
+        )));
+    }
+    if route.protocol == RouteProtocol::Grpc {
+        let http11_backend = backends.iter().any(|b| {
+            b.address == route.backend && b.http_version == Some(BackendHttpVersion::Http11)
+        });
+        if route.websocket.enabled || http11_backend {
+            return Err(crate::error::ProxyError::Config(format!(
+                "Domain '{}' route '{}': protocol = \"grpc\" requires HTTP/2 and \
+                 cannot be combined with websocket.enabled or an http11 backend",
+                domain.label(),
+                route.prefix
+            )));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Per-request access log (`[access_log]`).
+///
+/// Static: the output is opened once at startup (changing it requires a restart). Each request
+/// produces one JSON object per line, independent of the `[logging]` level.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AccessLogConfig {
+    /// Write access log records. Default `false`.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where records go: `"stdout"` (default) or `"file"` (requires `path`).
+    #[serde(default)]
+    pub output: AccessLogOutput,
+    /// Log file for `output = "file"`.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Rotate the file once it reaches this size in bytes; `0` never rotates. Default `104857600`
+    /// (100 MiB).
+    #[serde(default = "default_max_size_bytes")]
+    pub max_size_bytes: u64,
+    /// Rotated files kept next to `path` (`<path>.1` is the newest). Default `5`.
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Fields written in each record, in this order. Default: every field.
+    #[serde(default = "default_fields")]
+    pub fields: Vec<AccessLogField>,
+}
+
+/// Destination of `[access_log]` records.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogOutput {
+    #[default]
+    Stdout,
+    File,
+}
+
+/// One selectable access log field. The serialized name is the JSON key of the record.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLogField {
+    /// Request start, RFC 3339 UTC with milliseconds.
+    Timestamp,
+    /// ID from `[request_id]`; `null` when request IDs are disabled.
+    RequestId,
+    /// Id of the client connection, as listed by the admin API's `/admin/connections`.
+    ConnectionId,
+    /// Effective client IP (after PROXY protocol resolution).
+    ClientIp,
+    /// SNI of the TLS connection; `null` on plain HTTP.
+    Sni,
+    /// Request host (`:authority` or `Host`).
+    Host,
+    Method,
+    Path,
+    /// HTTP version, e.g. `HTTP/1.1`.
+    Protocol,
+    Status,
+    /// Time to the response head, in milliseconds.
+    DurationMs,
+    /// Request `Content-Length`; `null` when absent.
+    BytesIn,
+    /// Response `Content-Length`; `null` when absent.
+    BytesOut,
+    /// Backend selected for the request; `null` when none was.
+    Backend,
+    /// Tenant of the matched domain; `null` when it belongs to no tenant.
+    Tenant,
+    Ja4,
+    Akamai,
+    TcpSyn,
+}
+
+impl AccessLogField {
+    pub const ALL: [AccessLogField; 18] = [
+        Self::Timestamp,
+        Self::RequestId,
+        Self::ConnectionId,
+        Self::ClientIp,
+        Self::Sni,
+        Self::Host,
+        Self::Method,
+        Self::Path,
+        Self::Protocol,
+        Self::Status,
+        Self::DurationMs,
+        Self::BytesIn,
+        Self::BytesOut,
+        Self::Backend,
+        Self::Tenant,
+        Self::Ja4,
+        Self::Akamai,
+        Self::TcpSyn,
+    ];
+}
+
+fn default_max_size_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+fn default_fields() -> Vec<AccessLogField> {
+    AccessLogField::ALL.to_vec()
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            output: AccessLogOutput::default(),
+            path: None,
+            max_size_bytes: default_max_size_bytes(),
+            max_files: default_max_files(),
+            fields: default_fields(),
+        }
+    }
+}
+
+impl AccessLogConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.output == AccessLogOutput::File && self.path.is_none() {
+            return Err(ProxyError::Config(
+                "access_log.output = \"file\" requires access_log.path".to_string(),
+            ));
+        }
+        if self.output == AccessLogOutput::File && self.max_size_bytes > 0 && self.max_files == 0 {
+            return Err(ProxyError::Config(
+                "access_log.max_files must be greater than 0 when rotation is enabled".to_string(),
+            ));
+        }
+        if self.fields.is_empty() {
+            return Err(ProxyError::Config("access_log.fields must not be empty".to_string()));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> AccessLogView<'_> {
+        AccessLogView {
+            enabled: self.enabled,
+            output: self.output,
+            path: self.path.as_ref().map(|p| p.display().to_string()),
+            max_size_bytes: self.max_size_bytes,
+            max_files: self.max_files,
+            fields: &self.fields,
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`AccessLogConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct AccessLogView<'a> {
+    enabled: bool,
+    output: AccessLogOutput,
+    path: Option<String>,
+    max_size_bytes: u64,
+    max_files: usize,
+    fields: &'a [AccessLogField],
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Response cache storage (`[cache]`).
+///
+/// Static: the store is created once at startup (changing it requires a restart). Which responses
+/// are cached is decided per route by its `cache` block, which is hot-reloadable. Entries live in
+/// memory only and are lost on restart.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Total size of the cached responses (body plus headers) in bytes. When full, the least
+    /// recently used entries are evicted. Default `67108864` (64 MiB).
+    #[serde(default = "default_max_size_bytes")]
+    pub max_size_bytes: u64,
+}
+
+fn default_max_size_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { max_size_bytes: default_max_size_bytes() }
+    }
+}
+
+impl CacheConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_size_bytes == 0 {
+            return Err(ProxyError::Config(
+                "cache.max_size_bytes must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> CacheView {
+        CacheView { max_size_bytes: self.max_size_bytes }
+    }
+}
+
+/// Allowlisted effective-config view of [`CacheConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct CacheView {
+    max_size_bytes: u64,
+}
@@ -0,0 +1,167 @@
+use http::HeaderName;
+use serde::{Deserialize, Serialize};
+
+use crate::config::dynamic::security::{RedisStoreConfig, RedisStoreView};
+use crate::config::dynamic::sticky::is_cookie_name;
+use crate::config::Secret;
+use crate::error::{ProxyError, Result};
+
+/// Shortest accepted `secret`, in bytes.
+const MIN_SECRET_BYTES: usize = 16;
+
+/// Cookie-based client tracking (`[client_tracking]`).
+///
+/// Static: the store is created once at startup. A client without a valid tracking cookie is
+/// given a random opaque ID in `cookie_name`, signed with `secret`. Every forwarded request is
+/// counted per cookie and JA4, and the backend receives when that client was first and last seen
+/// and how many requests it made, as basic reputation data. Values of these headers sent by the
+/// client are always removed. Records live in memory, or in Redis when `redis` is set so every
+/// replica shares them.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClientTrackingConfig {
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// HMAC-SHA256 key of the cookie, at least 16 bytes. Required when enabled; changing it makes
+    /// every client new again.
+    #[serde(default)]
+    pub secret: Secret<String>,
+    /// Cookie carrying the client ID.
+    /// Default: `huginn_client`
+    #[serde(default = "default_cookie_name")]
+    pub cookie_name: String,
+    /// Lifetime of the cookie, and how long a record is kept after the client was last seen, in
+    /// seconds.
+    /// Default: 2592000 (30 days)
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Header carrying when the client was first seen (Unix seconds).
+    /// Default: `x-huginn-client-first-seen`
+    #[serde(default = "default_first_seen_header")]
+    pub first_seen_header: String,
+    /// Header carrying when the client was last seen before this request (Unix seconds); not
+    /// sent on a client's first request.
+    /// Default: `x-huginn-client-last-seen`
+    #[serde(default = "default_last_seen_header")]
+    pub last_seen_header: String,
+    /// Header carrying the client's request count, this request included.
+    /// Default: `x-huginn-client-request-count`
+    #[serde(default = "default_request_count_header")]
+    pub request_count_header: String,
+    /// Records kept in memory; past it the least recently seen client is dropped. With `redis`
+    /// the in-memory records are only used while Redis is unreachable.
+    /// Default: 100000
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    /// Redis server sharing the records between replicas. Default: records stay in memory.
+    #[serde(default)]
+    pub redis: Option<RedisStoreConfig>,
+}
+
+fn default_cookie_name() -> String {
+    "huginn_client".to_string()
+}
+
+fn default_ttl_secs() -> u64 {
+    2_592_000
+}
+
+fn default_first_seen_header() -> String {
+    "x-huginn-client-first-seen".to_string()
+}
+
+fn default_last_seen_header() -> String {
+    "x-huginn-client-last-seen".to_string()
+}
+
+fn default_request_count_header() -> String {
+    "x-huginn-client-request-count".to_string()
+}
+
+fn default_max_entries() -> usize {
+    100_000
+}
+
+impl Default for ClientTrackingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            secret: Secret::default(),
+            cookie_name: default_cookie_name(),
+            ttl_secs: default_ttl_secs(),
+            first_seen_header: default_first_seen_header(),
+            last_seen_header: default_last_seen_header(),
+            request_count_header: default_request_count_header(),
+            max_entries: default_max_entries(),
+            redis: None,
+        }
+    }
+}
+
+impl ClientTrackingConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.secret.expose().len() < MIN_SECRET_BYTES {
+            return Err(ProxyError::Config(format!(
+                "client_tracking.secret must be at least {MIN_SECRET_BYTES} bytes"
+            )));
+        }
+        if !is_cookie_name(&self.cookie_name) {
+            return Err(ProxyError::Config(format!(
+                "client_tracking.cookie_name '{}' is not a valid cookie name",
+                self.cookie_name
+            )));
+        }
+        for (key, header) in [
+            ("first_seen_header", &self.first_seen_header),
+            ("last_seen_header", &self.last_seen_header),
+            ("request_count_header", &self.request_count_header),
+        ] {
+            HeaderName::from_bytes(header.as_bytes()).map_err(|_| {
+                ProxyError::Config(format!(
+                    "client_tracking.{key} '{header}' is not a valid HTTP header name"
+                ))
+            })?;
+        }
+        if self.ttl_secs == 0 || self.max_entries == 0 {
+            return Err(ProxyError::Config(
+                "client_tracking.ttl_secs and max_entries must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(redis) = &self.redis {
+            redis.validate("client_tracking")?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> ClientTrackingView<'_> {
+        ClientTrackingView {
+            enabled: self.enabled,
+            secret: &self.secret,
+            cookie_name: &self.cookie_name,
+            ttl_secs: self.ttl_secs,
+            first_seen_header: &self.first_seen_header,
+            last_seen_header: &self.last_seen_header,
+            request_count_header: &self.request_count_header,
+            max_entries: self.max_entries,
+            redis: self.redis.as_ref().map(RedisStoreConfig::effective_view),
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`ClientTrackingConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct ClientTrackingView<'a> {
+    enabled: bool,
+    secret: &'a Secret<String>,
+    cookie_name: &'a str,
+    ttl_secs: u64,
+    first_seen_header: &'a str,
+    last_seen_header: &'a str,
+    request_count_header: &'a str,
+    max_entries: usize,
+    redis: Option<RedisStoreView<'a>>,
+}
@@ -0,0 +1,304 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Secret;
+use crate::error::{ProxyError, Result};
+
+/// Fingerprint event stream (`[event_stream]`).
+///
+/// Static: the sink is set up once at startup. Every request produces one compact JSON event
+/// (client IP, SNI, JA4 variants, Akamai, TCP SYN, backend, status) that is queued and published
+/// in batches to a webhook, NATS or Kafka, or stored in a ClickHouse or SQLite table for direct
+/// queries. A full queue drops new events instead of slowing requests down.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EventStreamConfig {
+    /// Publish fingerprint events. Default `false`.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where events go: `"webhook"` (default), `"nats"`, `"kafka"`, `"clickhouse"` or `"sqlite"`.
+    #[serde(default)]
+    pub sink: EventSinkKind,
+    /// `webhook`: `http(s)://` URL each batch is POSTed to as a JSON array. `nats`: server URL,
+    /// e.g. `nats://127.0.0.1:4222`. `clickhouse`: `http(s)://` URL of the HTTP interface, e.g.
+    /// `http://127.0.0.1:8123`.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// `kafka`: bootstrap brokers, `host:port`.
+    #[serde(default)]
+    pub brokers: Vec<String>,
+    /// `nats`: subject, `kafka`: topic events are published to.
+    #[serde(default)]
+    pub topic: Option<String>,
+    /// `webhook`: sent as `Authorization: Bearer <token>`. `nats`: authentication token.
+    #[serde(default)]
+    pub token: Option<Secret<String>>,
+    /// `webhook`, `clickhouse`: PEM bundle of CAs trusted for an `https://` URL. Default: the
+    /// system store.
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+    /// `clickhouse`, `sqlite`: table events are inserted into, created when missing. ClickHouse
+    /// accepts `database.table`. Default `huginn_fingerprints`.
+    #[serde(default = "default_table")]
+    pub table: String,
+    /// `clickhouse`: user name. Default: the server's default user.
+    #[serde(default)]
+    pub user: Option<String>,
+    /// `clickhouse`: password of `user`.
+    #[serde(default)]
+    pub password: Option<Secret<String>>,
+    /// `sqlite`: database file.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// `sqlite`: start a new database once the file reaches this size in bytes; `0` never
+    /// rotates. Default `1073741824` (1 GiB).
+    #[serde(default = "default_max_size_bytes")]
+    pub max_size_bytes: u64,
+    /// `sqlite`: rotated databases kept next to `path` (`<path>.1` is the newest). Default `5`.
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Events published together. Default `100`.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Milliseconds a partial batch waits for more events before it is published. Default
+    /// `1000`.
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+    /// Events waiting to be published; past this new events are dropped. Default `10000`.
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+    /// Milliseconds one batch may take to publish before it is dropped. Default `5000`.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+/// Destination of `[event_stream]` events.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EventSinkKind {
+    #[default]
+    Webhook,
+    /// Requires the `nats` feature.
+    Nats,
+    /// Requires the `kafka` feature.
+    Kafka,
+    Clickhouse,
+    /// Requires the `sqlite` feature.
+    Sqlite,
+}
+
+fn default_table() -> String {
+    "huginn_fingerprints".to_string()
+}
+
+fn default_max_size_bytes() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+fn default_flush_interval_ms() -> u64 {
+    1000
+}
+
+fn default_queue_capacity() -> usize {
+    10_000
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+impl Default for EventStreamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sink: EventSinkKind::default(),
+            url: None,
+            brokers: Vec::new(),
+            topic: None,
+            token: None,
+            ca_cert_path: None,
+            table: default_table(),
+            user: None,
+            password: None,
+            path: None,
+            max_size_bytes: default_max_size_bytes(),
+            max_files: default_max_files(),
+            batch_size: default_batch_size(),
+            flush_interval_ms: default_flush_interval_ms(),
+            queue_capacity: default_queue_capacity(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+impl EventStreamConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let url_scheme = self
+            .url
+            .as_deref()
+            .and_then(|url| url.parse::<http::Uri>().ok())
+            .and_then(|uri| uri.scheme_str().map(str::to_ascii_lowercase));
+        let has_topic = self.topic.as_deref().is_some_and(|t| !t.trim().is_empty());
+        let http_url = matches!(url_scheme.as_deref(), Some("http" | "https"));
+        match self.sink {
+            EventSinkKind::Webhook => {
+                if !http_url {
+                    return Err(ProxyError::Config(
+                        "event_stream.sink = \"webhook\" requires an http(s) event_stream.url"
+                            .to_string(),
+                    ));
+                }
+            }
+            EventSinkKind::Nats => {
+                if !cfg!(feature = "nats") {
+                    return Err(ProxyError::Config(
+                        "event_stream.sink = \"nats\" requires building with the `nats` feature"
+                            .to_string(),
+                    ));
+                }
+                if !matches!(url_scheme.as_deref(), Some("nats" | "tls")) || !has_topic {
+                    return Err(ProxyError::Config(
+                        "event_stream.sink = \"nats\" requires a nats:// event_stream.url and \
+                         event_stream.topic"
+                            .to_string(),
+                    ));
+                }
+            }
+            EventSinkKind::Kafka => {
+                if !cfg!(feature = "kafka") {
+                    return Err(ProxyError::Config(
+                        "event_stream.sink = \"kafka\" requires building with the `kafka` feature"
+                            .to_string(),
+                    ));
+                }
+                if self.brokers.is_empty() || !has_topic {
+                    return Err(ProxyError::Config(
+                        "event_stream.sink = \"kafka\" requires event_stream.brokers and \
+                         event_stream.topic"
+                            .to_string(),
+                    ));
+                }
+            }
+            EventSinkKind::Clickhouse => {
+                if !http_url || self.url.as_deref().is_some_and(|url| url.contains('?')) {
+                    return Err(ProxyError::Config(
+                        "event_stream.sink = \"clickhouse\" requires an http(s) event_stream.url \
+                         without a query string"
+                            .to_string(),
+                    ));
+                }
+                validate_table(&self.table, true)?;
+            }
+            EventSinkKind::Sqlite => {
+                if !cfg!(feature = "sqlite") {
+                    return Err(ProxyError::Config(
+                        "event_stream.sink = \"sqlite\" requires building with the `sqlite` feature"
+                            .to_string(),
+                    ));
+                }
+                if self.path.is_none() {
+                    return Err(ProxyError::Config(
+                        "event_stream.sink = \"sqlite\" requires event_stream.path".to_string(),
+                    ));
+                }
+                if self.max_size_bytes > 0 && self.max_files == 0 {
+                    return Err(ProxyError::Config(
+                        "event_stream.max_files must be greater than 0 when rotation is enabled"
+                            .to_string(),
+                    ));
+                }
+                validate_table(&self.table, false)?;
+            }
+        }
+        if self.batch_size == 0
+            || self.flush_interval_ms == 0
+            || self.queue_capacity == 0
+            || self.timeout_ms == 0
+        {
+            return Err(ProxyError::Config(
+                "event_stream.batch_size, flush_interval_ms, queue_capacity and timeout_ms must \
+                 be greater than 0"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> EventStreamView<'_> {
+        EventStreamView {
+            enabled: self.enabled,
+            sink: self.sink,
+            url: self.url.as_deref(),
+            brokers: &self.brokers,
+            topic: self.topic.as_deref(),
+            token: self.token.as_ref(),
+            ca_cert_path: self.ca_cert_path.as_deref(),
+            table: &self.table,
+            user: self.user.as_deref(),
+            password: self.password.as_ref(),
+            path: self.path.as_ref().map(|p| p.display().to_string()),
+            max_size_bytes: self.max_size_bytes,
+            max_files: self.max_files,
+            batch_size: self.batch_size,
+            flush_interval_ms: self.flush_interval_ms,
+            queue_capacity: self.queue_capacity,
+            timeout_ms: self.timeout_ms,
+        }
+    }
+}
+
+/// `table` is spliced into SQL, so it must be a plain identifier (`database.table` when
+/// `qualified`).
+fn validate_table(table: &str, qualified: bool) -> Result<()> {
+    let parts: Vec<&str> = table.split('.').collect();
+    let identifier = |part: &&str| {
+        part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let max_parts = if qualified { 2 } else { 1 };
+    if parts.len() > max_parts || !parts.iter().all(identifier) {
+        return Err(ProxyError::Config(format!(
+            "event_stream.table '{table}' must be a table name of letters, digits and '_'{}",
+            if qualified {
+                ", optionally prefixed with 'database.'"
+            } else {
+                ""
+            }
+        )));
+    }
+    Ok(())
+}
+
+/// Allowlisted effective-config view of [`EventStreamConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct EventStreamView<'a> {
+    enabled: bool,
+    sink: EventSinkKind,
+    url: Option<&'a str>,
+    brokers: &'a [String],
+    topic: Option<&'a str>,
+    token: Option<&'a Secret<String>>,
+    ca_cert_path: Option<&'a str>,
+    table: &'a str,
+    user: Option<&'a str>,
+    password: Option<&'a Secret<String>>,
+    path: Option<String>,
+    max_size_bytes: u64,
+    max_files: usize,
+    batch_size: usize,
+    flush_interval_ms: u64,
+    queue_capacity: usize,
+    timeout_ms: u64,
+}
@@ -1,5 +1,12 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
 use serde::{Deserialize, Serialize};
 
+use crate::config::Secret;
+use crate::error::{ProxyError, Result};
+use crate::fingerprinting::names;
+
 /// Fingerprinting configuration
 #[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
@@ -13,18 +20,342 @@ pub struct FingerprintConfig {
     /// Default: true
     #[serde(default = "default_true")]
     pub http_enabled: bool,
-    /// Enable TCP SYN fingerprinting via eBPF/XDP (p0f-style raw signature).
-    /// Requires the `ebpf-tcp` Cargo feature and the `huginn-ebpf-agent`
-    /// running on the same node with pinned maps at `HUGINN_EBPF_PIN_PATH`.
-    /// When false the proxy does not open BPF maps.
+    /// Enable TCP SYN fingerprinting (p0f-style raw signature) from `tcp_capture`.
+    /// With the default eBPF source, requires the `ebpf-tcp` Cargo feature and the
+    /// `huginn-ebpf-agent` running on the same node with pinned maps at `HUGINN_EBPF_PIN_PATH`.
+    /// When false the proxy neither opens BPF maps nor a raw socket.
     /// Default: false
     #[serde(default)]
     pub tcp_enabled: bool,
+    /// Where TCP SYN fingerprints come from when `tcp_enabled` is set: the eBPF agent's pinned
+    /// maps, or a raw socket opened by the proxy itself (no agent, no `ebpf-tcp` feature; needs
+    /// `CAP_NET_RAW` on Linux).
+    /// Default: ebpf
+    #[serde(default)]
+    pub tcp_capture: TcpCapture,
     /// Maximum bytes to capture for HTTP/2 fingerprinting
     /// This limits the amount of data buffered for fingerprint extraction
     /// Default: 65536 (64 KB)
     #[serde(default = "default_max_capture")]
     pub max_capture: usize,
+    /// Forward the negotiated TLS parameters (ALPN, SNI, version, cipher suite, 0-RTT attempt)
+    /// as `x-tls-*` headers on TLS listeners. Independent of `tls_enabled`.
+    /// Default: false
+    #[serde(default)]
+    pub tls_info_headers: bool,
+    /// Names of the injected fingerprint headers (`[fingerprint.headers]`).
+    /// Default: the built-in `x-tls-ja4*` / `x-http2-akamai` / `x-tcp-p0f` names
+    #[serde(default)]
+    pub headers: FingerprintHeadersConfig,
+    /// Built-in diagnostics endpoint (`[fingerprint.whoami]`).
+    /// Default: disabled
+    #[serde(default)]
+    pub whoami: WhoamiConfig,
+    /// HMAC signature over the fingerprint headers (`[fingerprint.signing]`).
+    /// Default: disabled
+    #[serde(default)]
+    pub signing: SigningConfig,
+}
+
+/// Shortest accepted signing secret, in bytes.
+pub const MIN_SIGNING_SECRET_LEN: usize = 32;
+
+/// Signature of the fingerprint headers sent upstream (`[fingerprint.signing]`).
+///
+/// Every forwarded request gets an `x-huginn-net-signature` header: an HMAC-SHA256, under the
+/// active key, of a timestamp and the fingerprint headers the request carries, so a backend
+/// holding the key can check they come from the proxy. The active key is the one with the latest
+/// `not_before` already passed (a key without it counts as active since ever): a new key can be
+/// deployed to backends first and take over at its `not_before` without a restart.
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SigningConfig {
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// Signing keys; at least one must be active when the proxy starts.
+    #[serde(default)]
+    pub keys: Vec<SigningKeyConfig>,
+}
+
+/// One `[[fingerprint.signing.keys]]` entry.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SigningKeyConfig {
+    /// Key identifier sent as `kid` in the signature header (letters, digits, `.`, `_`, `-`).
+    pub id: String,
+    /// Inline secret (at least 32 bytes). Exclusive with `secret_file`
+    #[serde(default)]
+    pub secret: Option<Secret<String>>,
+    /// File holding the secret (at least 32 bytes; surrounding whitespace is ignored). Read once
+    /// at startup. Exclusive with `secret`
+    #[serde(default)]
+    pub secret_file: Option<String>,
+    /// Unix time (seconds) from which the key signs.
+    /// Default: active since ever
+    #[serde(default)]
+    pub not_before: Option<u64>,
+}
+
+impl SigningConfig {
+    /// Check the keys; `now` (unix seconds) decides whether one of them is active.
+    pub fn validate(&self, now: u64) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.keys.is_empty() {
+            return Err(ProxyError::Config(
+                "fingerprint.signing requires at least one key".to_string(),
+            ));
+        }
+        let mut ids = HashSet::new();
+        for key in &self.keys {
+            key.validate()?;
+            if !ids.insert(key.id.as_str()) {
+                return Err(ProxyError::Config(format!(
+                    "fingerprint.signing key '{}' is defined more than once",
+                    key.id
+                )));
+            }
+        }
+        if !self
+            .keys
+            .iter()
+            .any(|key| key.not_before.unwrap_or(0) <= now)
+        {
+            return Err(ProxyError::Config(
+                "fingerprint.signing: no key is active yet (every not_before is in the future)"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl SigningKeyConfig {
+    pub fn validate(&self) -> Result<()> {
+        let valid_id = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+        if !valid_id {
+            return Err(ProxyError::Config(format!(
+                "fingerprint.signing key id '{}' must be non-empty and use only letters, digits, \
+                 '.', '_' and '-'",
+                self.id
+            )));
+        }
+        match (&self.secret, &self.secret_file) {
+            (Some(_), Some(_)) => Err(ProxyError::Config(format!(
+                "fingerprint.signing key '{}': secret and secret_file are mutually exclusive",
+                self.id
+            ))),
+            (None, None) => Err(ProxyError::Config(format!(
+                "fingerprint.signing key '{}' requires secret or secret_file",
+                self.id
+            ))),
+            (Some(secret), None) => self.check_secret(secret.expose().trim().as_bytes()),
+            (None, Some(_)) => Ok(()),
+        }
+    }
+
+    /// The secret, read from `secret_file` when configured.
+    pub fn load_secret(&self) -> Result<Vec<u8>> {
+        let secret = match (&self.secret, &self.secret_file) {
+            (Some(secret), _) => secret.expose().trim().as_bytes().to_vec(),
+            (None, Some(path)) => {
+                let bytes = std::fs::read(path).map_err(|e| {
+                    ProxyError::Config(format!(
+                        "fingerprint.signing key '{}' secret_file '{path}': {e}",
+                        self.id
+                    ))
+                })?;
+                bytes.trim_ascii().to_vec()
+            }
+            (None, None) => Vec::new(),
+        };
+        self.check_secret(&secret)?;
+        Ok(secret)
+    }
+
+    fn check_secret(&self, secret: &[u8]) -> Result<()> {
+        if secret.len() < MIN_SIGNING_SECRET_LEN {
+            return Err(ProxyError::Config(format!(
+                "fingerprint.signing key '{}': secret must be at least {MIN_SIGNING_SECRET_LEN} \
+                 bytes, got {}",
+                self.id,
+                secret.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Fingerprint diagnostics endpoint (`[fingerprint.whoami]`).
+///
+/// A request to `path`, on any host, is answered by the proxy itself with the fingerprints it
+/// computed for the caller (JA4 variants, Akamai, TCP SYN), the negotiated TLS parameters and the
+/// `X-Forwarded-*` headers a backend would receive, as JSON. Nothing is forwarded upstream.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WhoamiConfig {
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// Exact request path answered by the endpoint.
+    /// Default: `/._huginn/whoami`
+    #[serde(default = "default_whoami_path")]
+    pub path: String,
+}
+
+fn default_whoami_path() -> String {
+    "/._huginn/whoami".to_string()
+}
+
+impl Default for WhoamiConfig {
+    fn default() -> Self {
+        Self { enabled: false, path: default_whoami_path() }
+    }
+}
+
+impl WhoamiConfig {
+    /// The path must be an absolute path without a query.
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if !self.path.starts_with('/') || self.path.contains(['?', '#']) {
+            return Err(ProxyError::Config(format!(
+                "fingerprint.whoami.path '{}' must start with '/' and have no query",
+                self.path
+            )));
+        }
+        Ok(())
+    }
+
+    /// Path answered by the endpoint, `None` when it is disabled.
+    pub fn enabled_path(&self) -> Option<&str> {
+        self.enabled.then_some(self.path.as_str())
+    }
+}
+
+/// Source of TCP SYN fingerprints (`fingerprint.tcp_capture`).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TcpCapture {
+    /// Read the SYNs the `huginn-ebpf-agent` captured in pinned BPF maps
+    #[default]
+    Ebpf,
+    /// Capture IPv4 SYNs on a raw socket in the proxy process
+    RawSocket,
+}
+
+impl TcpCapture {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TcpCapture::Ebpf => "ebpf",
+            TcpCapture::RawSocket => "raw_socket",
+        }
+    }
+}
+
+/// Overrides for the names of the proxy-authoritative fingerprint headers.
+///
+/// Each key renames one header; `prefix` replaces the leading `x-` of every header without an
+/// explicit name (e.g. `prefix = "x-acme-"` turns `x-tls-ja4` into `x-acme-tls-ja4`). Renamed
+/// headers are stripped from client input exactly like the built-in ones.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FingerprintHeadersConfig {
+    /// Replacement for the leading `x-` of the built-in names
+    pub prefix: Option<String>,
+    /// Name for `x-tls-ja4`
+    pub ja4: Option<String>,
+    /// Name for `x-tls-ja4-r`
+    pub ja4_r: Option<String>,
+    /// Name for `x-tls-ja4-o`
+    pub ja4_o: Option<String>,
+    /// Name for `x-tls-ja4-or`
+    pub ja4_or: Option<String>,
+    /// Name for `x-tls-ja4-s1`
+    pub ja4_s1: Option<String>,
+    /// Name for `x-tls-ja4-s1r`
+    pub ja4_s1r: Option<String>,
+    /// Name for `x-http2-akamai`
+    pub http2_akamai: Option<String>,
+    /// Name for `x-tcp-p0f`
+    pub tcp_p0f: Option<String>,
+    /// Name for `x-huginn-net-os`
+    pub tcp_os: Option<String>,
+    /// Name for `x-fingerprint-spoofing-detected`
+    pub spoofing_detected: Option<String>,
+}
+
+impl FingerprintHeadersConfig {
+    /// Configured name of the header whose built-in name is `default` (one of
+    /// [`names::FINGERPRINTS`] or [`names::SPOOFING_DETECTED`]).
+    pub fn name_for(&self, default: &'static str) -> Cow<'_, str> {
+        let explicit = match default {
+            names::TLS_JA4 => self.ja4.as_deref(),
+            names::TLS_JA4_R => self.ja4_r.as_deref(),
+            names::TLS_JA4_O => self.ja4_o.as_deref(),
+            names::TLS_JA4_OR => self.ja4_or.as_deref(),
+            names::TLS_JA4_S1 => self.ja4_s1.as_deref(),
+            names::TLS_JA4_S1R => self.ja4_s1r.as_deref(),
+            names::HTTP2_AKAMAI => self.http2_akamai.as_deref(),
+            names::TCP_SYN => self.tcp_p0f.as_deref(),
+            names::TCP_OS => self.tcp_os.as_deref(),
+            names::SPOOFING_DETECTED => self.spoofing_detected.as_deref(),
+            _ => None,
+        };
+        if let Some(name) = explicit {
+            return Cow::Borrowed(name);
+        }
+        match (self.prefix.as_deref(), default.strip_prefix("x-")) {
+            (Some(prefix), Some(rest)) => Cow::Owned(format!("{prefix}{rest}")),
+            _ => Cow::Borrowed(default),
+        }
+    }
+
+    /// Reject names that are not valid header names or that collide with each other.
+    pub fn validate(&self) -> crate::error::Result<()> {
+        crate::fingerprinting::FingerprintHeaderNames::from_config(self).map(|_| ())
+    }
+}
+
+/// Per-listener overrides of [`FingerprintConfig`] (`[listen.listeners.fingerprint]`).
+///
+/// Unset fields inherit the global `[fingerprint]` value. Header names stay global.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerFingerprintConfig {
+    /// Override `fingerprint.tls_enabled` on this listener
+    pub tls_enabled: Option<bool>,
+    /// Override `fingerprint.http_enabled` on this listener
+    pub http_enabled: Option<bool>,
+    /// Override `fingerprint.tcp_enabled` on this listener. Can only turn the SYN lookup off:
+    /// the SYN capture is set up for the whole process by the global flag
+    pub tcp_enabled: Option<bool>,
+    /// Override `fingerprint.max_capture` on this listener
+    pub max_capture: Option<usize>,
+}
+
+impl ListenerFingerprintConfig {
+    /// Effective fingerprint config for the listener, falling back to `global` per field.
+    pub fn resolve(&self, global: &FingerprintConfig) -> FingerprintConfig {
+        FingerprintConfig {
+            tls_enabled: self.tls_enabled.unwrap_or(global.tls_enabled),
+            http_enabled: self.http_enabled.unwrap_or(global.http_enabled),
+            tcp_enabled: self.tcp_enabled.unwrap_or(global.tcp_enabled),
+            tcp_capture: global.tcp_capture,
+            max_capture: self.max_capture.unwrap_or(global.max_capture),
+            tls_info_headers: global.tls_info_headers,
+            headers: global.headers.clone(),
+            whoami: global.whoami.clone(),
+            signing: global.signing.clone(),
+        }
+    }
 }
 
 impl Default for FingerprintConfig {
@@ -33,7 +364,12 @@ impl Default for FingerprintConfig {
             tls_enabled: default_true(),
             http_enabled: default_true(),
             tcp_enabled: false,
+            tcp_capture: TcpCapture::default(),
             max_capture: default_max_capture(),
+            tls_info_headers: false,
+            headers: FingerprintHeadersConfig::default(),
+            whoami: WhoamiConfig::default(),
+            signing: SigningConfig::default(),
         }
     }
 }
@@ -48,16 +384,86 @@ fn default_max_capture() -> usize {
 
 /// Allowlisted effective-config view of [`FingerprintConfig`]. Field names are the JSON keys.
 #[derive(Serialize)]
-pub(crate) struct FingerprintView {
+pub(crate) struct FingerprintView<'a> {
     tls_enabled: bool,
     http_enabled: bool,
     tcp_enabled: bool,
+    tcp_capture: &'static str,
     max_capture: usize,
+    tls_info_headers: bool,
+    /// Effective header names, keyed by built-in name.
+    headers: BTreeMap<&'static str, String>,
+    whoami: WhoamiView<'a>,
+    signing: SigningView<'a>,
+}
+
+#[derive(Serialize)]
+struct SigningView<'a> {
+    enabled: bool,
+    keys: Vec<SigningKeyView<'a>>,
+}
+
+/// The inline secret serializes as `<redacted>`; the file path is shown.
+#[derive(Serialize)]
+struct SigningKeyView<'a> {
+    id: &'a str,
+    secret: Option<&'a Secret<String>>,
+    secret_file: Option<&'a str>,
+    not_before: Option<u64>,
+}
+
+/// Allowlisted effective-config view of [`WhoamiConfig`].
+#[derive(Serialize)]
+pub(crate) struct WhoamiView<'a> {
+    enabled: bool,
+    path: &'a str,
 }
 
 impl FingerprintConfig {
-    pub(crate) fn effective_view(&self) -> FingerprintView {
+    pub(crate) fn effective_view(&self) -> FingerprintView<'_> {
         FingerprintView {
+            tls_enabled: self.tls_enabled,
+            http_enabled: self.http_enabled,
+            tcp_enabled: self.tcp_enabled,
+            tcp_capture: self.tcp_capture.as_str(),
+            max_capture: self.max_capture,
+            tls_info_headers: self.tls_info_headers,
+            headers: names::FINGERPRINTS
+                .iter()
+                .chain([&names::SPOOFING_DETECTED])
+                .map(|&default| (default, self.headers.name_for(default).into_owned()))
+                .collect(),
+            whoami: WhoamiView { enabled: self.whoami.enabled, path: &self.whoami.path },
+            signing: SigningView {
+                enabled: self.signing.enabled,
+                keys: self
+                    .signing
+                    .keys
+                    .iter()
+                    .map(|key| SigningKeyView {
+                        id: &key.id,
+                        secret: key.secret.as_ref(),
+                        secret_file: key.secret_file.as_deref(),
+                        not_before: key.not_before,
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`ListenerFingerprintConfig`]. Unset overrides are `null`.
+#[derive(Serialize)]
+pub(crate) struct ListenerFingerprintView {
+    tls_enabled: Option<bool>,
+    http_enabled: Option<bool>,
+    tcp_enabled: Option<bool>,
+    max_capture: Option<usize>,
+}
+
+impl ListenerFingerprintConfig {
+    pub(crate) fn effective_view(&self) -> ListenerFingerprintView {
+        ListenerFingerprintView {
             tls_enabled: self.tls_enabled,
             http_enabled: self.http_enabled,
             tcp_enabled: self.tcp_enabled,
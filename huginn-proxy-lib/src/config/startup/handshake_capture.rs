@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Raw ClientHello capture for offline analysis (`[handshake_capture]`).
+///
+/// Static: the file is opened once at startup (changing it requires a restart). A sampled
+/// fraction of TLS connections has its ClientHello record written, together with the
+/// fingerprints computed from it, as JSON lines or as a PCAPNG capture.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HandshakeCaptureConfig {
+    /// Capture ClientHellos. Default `false`.
+    #[serde(default)]
+    pub enabled: bool,
+    /// File format: `"jsonl"` (default) or `"pcapng"`.
+    #[serde(default)]
+    pub format: HandshakeCaptureFormat,
+    /// Capture file; required when `enabled`.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Fraction of TLS connections captured, from `0.0` to `1.0`. Default `1.0`.
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+    /// Rotate the file once it reaches this size in bytes; `0` never rotates. Default `104857600`
+    /// (100 MiB).
+    #[serde(default = "default_max_size_bytes")]
+    pub max_size_bytes: u64,
+    /// Rotated files kept next to `path` (`<path>.1` is the newest). Default `5`.
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+/// File format of `[handshake_capture]`.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HandshakeCaptureFormat {
+    /// One JSON object per ClientHello, the record bytes hex-encoded.
+    #[default]
+    Jsonl,
+    /// One synthesized TCP segment per ClientHello (link type `RAW`), the fingerprints in the
+    /// packet comment.
+    Pcapng,
+}
+
+fn default_sample_rate() -> f64 {
+    1.0
+}
+
+fn default_max_size_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+impl Default for HandshakeCaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: HandshakeCaptureFormat::default(),
+            path: None,
+            sample_rate: default_sample_rate(),
+            max_size_bytes: default_max_size_bytes(),
+            max_files: default_max_files(),
+        }
+    }
+}
+
+impl HandshakeCaptureConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.path.is_none() {
+            return Err(ProxyError::Config(
+                "handshake_capture.enabled requires handshake_capture.path".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.sample_rate) {
+            return Err(ProxyError::Config(format!(
+                "handshake_capture.sample_rate must be between 0.0 and 1.0, got {}",
+                self.sample_rate
+            )));
+        }
+        if self.max_size_bytes > 0 && self.max_files == 0 {
+            return Err(ProxyError::Config(
+                "handshake_capture.max_files must be greater than 0 when rotation is enabled"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> HandshakeCaptureView {
+        HandshakeCaptureView {
+            enabled: self.enabled,
+            format: self.format,
+            path: self.path.as_ref().map(|p| p.display().to_string()),
+            sample_rate: self.sample_rate,
+            max_size_bytes: self.max_size_bytes,
+            max_files: self.max_files,
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`HandshakeCaptureConfig`]. Field names are the JSON
+/// keys.
+#[derive(Serialize)]
+pub(crate) struct HandshakeCaptureView {
+    enabled: bool,
+    format: HandshakeCaptureFormat,
+    path: Option<String>,
+    sample_rate: f64,
+    max_size_bytes: u64,
+    max_files: usize,
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Largest flow-control window HTTP/2 allows (RFC 9113 §6.9.1).
+const MAX_WINDOW_SIZE: u32 = 2_147_483_647;
+/// Allowed `SETTINGS_MAX_FRAME_SIZE` values (RFC 9113 §6.5.2).
+const FRAME_SIZE_RANGE: std::ops::RangeInclusive<u32> = 16_384..=16_777_215;
+
+/// HTTP/2 settings of client connections (`[http2]`).
+///
+/// Static: the connection builder is created once at startup. Unset values keep hyper's
+/// defaults, except the keep-alive ping, which follows `timeout.proxy_idle_ms`.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Http2Config {
+    /// `SETTINGS_INITIAL_WINDOW_SIZE`: bytes a client may send on one stream before the proxy
+    /// reads them.
+    #[serde(default)]
+    pub initial_stream_window_size: Option<u32>,
+    /// Flow-control window of the whole connection, in bytes.
+    #[serde(default)]
+    pub initial_connection_window_size: Option<u32>,
+    /// Size both windows from the measured bandwidth-delay product instead.
+    /// Default: false
+    #[serde(default)]
+    pub adaptive_window: bool,
+    /// `SETTINGS_MAX_FRAME_SIZE`: largest frame payload the proxy accepts, in bytes.
+    #[serde(default)]
+    pub max_frame_size: Option<u32>,
+    /// `SETTINGS_MAX_CONCURRENT_STREAMS`: streams a client may have open at once.
+    #[serde(default)]
+    pub max_concurrent_streams: Option<u32>,
+    /// Milliseconds between keep-alive pings. `0` disables them.
+    /// Default: `timeout.proxy_idle_ms`
+    #[serde(default)]
+    pub keep_alive_interval_ms: Option<u64>,
+    /// Milliseconds to wait for a ping acknowledgement before closing the connection.
+    /// Default: the interval plus 1000
+    #[serde(default)]
+    pub keep_alive_timeout_ms: Option<u64>,
+}
+
+impl Http2Config {
+    pub fn validate(&self) -> Result<()> {
+        validate_flow_control(
+            "http2",
+            self.initial_stream_window_size,
+            self.initial_connection_window_size,
+            self.adaptive_window,
+            self.max_frame_size,
+        )?;
+        if self.max_concurrent_streams == Some(0) {
+            return Err(ProxyError::Config(
+                "http2.max_concurrent_streams must be greater than 0 (omit it for hyper's default)"
+                    .to_string(),
+            ));
+        }
+        if self.keep_alive_timeout_ms == Some(0) {
+            return Err(ProxyError::Config(
+                "http2.keep_alive_timeout_ms must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> Http2View {
+        Http2View {
+            initial_stream_window_size: self.initial_stream_window_size,
+            initial_connection_window_size: self.initial_connection_window_size,
+            adaptive_window: self.adaptive_window,
+            max_frame_size: self.max_frame_size,
+            max_concurrent_streams: self.max_concurrent_streams,
+            keep_alive_interval_ms: self.keep_alive_interval_ms,
+            keep_alive_timeout_ms: self.keep_alive_timeout_ms,
+        }
+    }
+}
+
+/// Checks the flow-control settings shared by `[http2]` and `[backend_pool.http2]`.
+pub(crate) fn validate_flow_control(
+    section: &str,
+    stream_window: Option<u32>,
+    connection_window: Option<u32>,
+    adaptive_window: bool,
+    max_frame_size: Option<u32>,
+) -> Result<()> {
+    for (name, window) in [
+        ("initial_stream_window_size", stream_window),
+        ("initial_connection_window_size", connection_window),
+    ] {
+        if let Some(window) = window.filter(|w| *w > MAX_WINDOW_SIZE) {
+            return Err(ProxyError::Config(format!(
+                "{section}.{name} must be at most {MAX_WINDOW_SIZE}, got {window}"
+            )));
+        }
+        if adaptive_window && window.is_some() {
+            return Err(ProxyError::Config(format!(
+                "{section}.{name} cannot be combined with {section}.adaptive_window"
+            )));
+        }
+    }
+    if let Some(size) = max_frame_size.filter(|s| !FRAME_SIZE_RANGE.contains(s)) {
+        return Err(ProxyError::Config(format!(
+            "{section}.max_frame_size must be between {} and {}, got {size}",
+            FRAME_SIZE_RANGE.start(),
+            FRAME_SIZE_RANGE.end()
+        )));
+    }
+    Ok(())
+}
+
+/// Allowlisted effective-config view of [`Http2Config`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct Http2View {
+    initial_stream_window_size: Option<u32>,
+    initial_connection_window_size: Option<u32>,
+    adaptive_window: bool,
+    max_frame_size: Option<u32>,
+    max_concurrent_streams: Option<u32>,
+    keep_alive_interval_ms: Option<u64>,
+    keep_alive_timeout_ms: Option<u64>,
+}
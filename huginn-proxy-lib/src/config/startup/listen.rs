@@ -1,7 +1,15 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use tracing::warn;
 
+use super::fingerprinting::{ListenerFingerprintConfig, ListenerFingerprintView};
+use super::passthrough::{PassthroughConfig, PassthroughView};
+use super::tcp::{TcpConfig, TcpView};
+use super::udp::{UdpListenerConfig, UdpListenerView};
+
 /// PROXY protocol (v1 and v2) handling for a listener.
 ///
 /// Honored **only** for peers in `security.trusted_proxies` (anti-spoofing). v1 and v2 are
@@ -68,13 +76,40 @@ pub struct ListenConfig {
     /// ```text
     /// ["0.0.0.0:7000", "[::]:7000"]
     /// ```
+    /// These addresses use the global `[tls]` and `[fingerprint]` settings; see `listeners` for
+    /// addresses with their own. Default: empty
+    #[serde(default)]
     pub addrs: Vec<SocketAddr>,
+    /// Additional listeners (`[[listen.listeners]]`), each with optional TLS and fingerprint
+    /// overrides, e.g. an internal plaintext port next to the public TLS one. Default: empty
+    #[serde(default)]
+    pub listeners: Vec<ListenerConfig>,
+    /// UDP forwarders (`[[listen.udp]]`), each relaying the datagrams of one address to one
+    /// backend. Default: empty
+    #[serde(default)]
+    pub udp: Vec<UdpListenerConfig>,
     /// `listen(2)` backlog, length of the pending-connection queue per listener socket.
     /// Raise this under high connection rates to avoid the kernel silently dropping SYNs before
     /// `accept(2)` is called. The kernel clamps the value to `net.core.somaxconn`.
     /// Passed directly to `listen(2)`. Default: 4096 (matches modern Linux SOMAXCONN)
     #[serde(default = "default_tcp_backlog")]
     pub tcp_backlog: i32,
+    /// Socket options of TCP listeners (`[listen.tcp]`). Set on the listening socket, so
+    /// every accepted client connection inherits them. Unix socket listeners ignore them.
+    #[serde(default)]
+    pub tcp: TcpConfig,
+    /// Accept loops per address. Above 1, each loop owns its own socket bound with
+    /// `SO_REUSEPORT` and the kernel spreads incoming connections across them, so a single
+    /// accept loop stops being the bottleneck at high connection rates. Unix socket listeners
+    /// always have one. Default: 1
+    #[serde(default = "default_acceptors")]
+    pub acceptors: usize,
+    /// Bind every TCP listener with `SO_REUSEPORT` even with one acceptor, so a new proxy
+    /// process (same user) can bind the same addresses while this one still serves: start the
+    /// new binary, wait for its `/ready`, then drain the old one (SIGTERM or
+    /// `POST /admin/drain`). Default: false
+    #[serde(default)]
+    pub reuse_port: bool,
     /// PROXY protocol (v1 and v2) handling: mode and header read timeout. See
     /// [`ProxyProtocolConfig`].
     #[serde(default)]
@@ -85,7 +120,12 @@ impl Default for ListenConfig {
     fn default() -> Self {
         Self {
             addrs: vec![],
+            listeners: vec![],
+            udp: vec![],
             tcp_backlog: default_tcp_backlog(),
+            tcp: TcpConfig::default(),
+            acceptors: default_acceptors(),
+            reuse_port: false,
             proxy_protocol: ProxyProtocolConfig::default(),
         }
     }
@@ -95,6 +135,144 @@ fn default_tcp_backlog() -> i32 {
     4096
 }
 
+fn default_acceptors() -> usize {
+    1
+}
+
+/// Address of a `[[listen.listeners]]` entry: `host:port`, or a unix socket as `unix:<path>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{addr}"),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ListenAddr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        if let Some(path) = crate::config::unix_socket_path(&raw) {
+            if path.as_os_str().is_empty() {
+                return Err(serde::de::Error::custom("unix socket path is empty"));
+            }
+            return Ok(ListenAddr::Unix(path.to_path_buf()));
+        }
+        raw.parse()
+            .map(ListenAddr::Tcp)
+            .map_err(|e| serde::de::Error::custom(format!("invalid listen address '{raw}': {e}")))
+    }
+}
+
+/// One entry of `[[listen.listeners]]`: an address with its own TLS and fingerprint settings,
+/// optionally serving a single tenant.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerConfig {
+    /// Address and port to listen on, or `unix:<path>` for a unix domain socket. Connections on
+    /// a unix socket have no client IP: they are seen as `127.0.0.1`, never read a PROXY header
+    /// and get no TCP SYN fingerprint.
+    pub addr: ListenAddr,
+    /// Permissions of the socket file of a `unix:` listener (e.g. `0o660`). Default: left to
+    /// the process umask
+    #[serde(default)]
+    pub mode: Option<u32>,
+    /// Terminate TLS on this listener. Unset follows `[tls]`; `false` serves plain HTTP even
+    /// when `[tls]` is configured; `true` requires `[tls]`.
+    /// Default: unset
+    #[serde(default)]
+    pub tls: Option<bool>,
+    /// Overrides of the global `[fingerprint]` flags for connections on this listener
+    #[serde(default)]
+    pub fingerprint: ListenerFingerprintConfig,
+    /// Relay TLS connections by SNI without terminating them (`[listen.listeners.passthrough]`).
+    /// Only the ClientHello is read, for routing and the JA4 fingerprint; no HTTP handling
+    /// applies. Cannot be combined with `tls = true`. Default: unset
+    #[serde(default)]
+    pub passthrough: Option<PassthroughConfig>,
+    /// Name of the `[[tenants]]` entry this listener serves: requests are routed among that
+    /// tenant's domains only, so the hosts of other tenants are not found here. Cannot be
+    /// combined with `passthrough`. Default: unset (every domain)
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+impl ListenConfig {
+    /// Reject `acceptors = 0`, which would leave the addresses bound but never accepted on,
+    /// addresses listed more than once across `addrs` and `listeners`, `mode` on a TCP listener,
+    /// zero `tcp` options, invalid `passthrough` tables, `passthrough` with a `tenant` and invalid
+    /// or repeated `udp` entries.
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if self.acceptors == 0 {
+            return Err(crate::error::ProxyError::Config(
+                "listen.acceptors must be at least 1".to_string(),
+            ));
+        }
+        self.tcp.validate("listen.tcp")?;
+        let mut seen = HashSet::new();
+        let all = self
+            .addrs
+            .iter()
+            .map(|&addr| ListenAddr::Tcp(addr))
+            .chain(self.listeners.iter().map(|l| l.addr.clone()));
+        for addr in all {
+            if !seen.insert(addr.clone()) {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "listen address {addr} is configured more than once"
+                )));
+            }
+        }
+        for listener in &self.listeners {
+            if listener.mode.is_some() && !matches!(listener.addr, ListenAddr::Unix(_)) {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "listener {}: mode is only valid for unix: addresses",
+                    listener.addr
+                )));
+            }
+            if let Some(passthrough) = &listener.passthrough {
+                if listener.tls == Some(true) {
+                    return Err(crate::error::ProxyError::Config(format!(
+                        "listener {}: passthrough cannot be combined with tls = true",
+                        listener.addr
+                    )));
+                }
+                if listener.tenant.is_some() {
+                    return Err(crate::error::ProxyError::Config(format!(
+                        "listener {}: passthrough cannot be combined with tenant",
+                        listener.addr
+                    )));
+                }
+                passthrough.validate(&format!("listener {}: passthrough", listener.addr))?;
+            }
+        }
+        let mut udp_addrs = HashSet::new();
+        for udp in &self.udp {
+            udp.validate()?;
+            if !udp_addrs.insert(udp.addr) {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "listen.udp address {} is configured more than once",
+                    udp.addr
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of configured addresses, `addrs` and `listeners` together.
+    pub fn address_count(&self) -> usize {
+        self.addrs.len().saturating_add(self.listeners.len())
+    }
+}
+
 fn default_proxy_protocol_header_timeout_ms() -> u64 {
     100
 }
@@ -130,10 +308,25 @@ where
 #[derive(Serialize)]
 pub(crate) struct ListenView {
     addrs: Vec<String>,
+    listeners: Vec<ListenerView>,
+    udp: Vec<UdpListenerView>,
     tcp_backlog: i32,
+    tcp: TcpView,
+    acceptors: usize,
+    reuse_port: bool,
     proxy_protocol: ProxyProtocolView,
 }
 
+#[derive(Serialize)]
+struct ListenerView {
+    addr: String,
+    mode: Option<u32>,
+    tls: Option<bool>,
+    fingerprint: ListenerFingerprintView,
+    passthrough: Option<PassthroughView>,
+    tenant: Option<String>,
+}
+
 #[derive(Serialize)]
 struct ProxyProtocolView {
     mode: &'static str,
@@ -144,7 +337,30 @@ impl ListenConfig {
     pub(crate) fn effective_view(&self) -> ListenView {
         ListenView {
             addrs: self.addrs.iter().map(ToString::to_string).collect(),
+            listeners: self
+                .listeners
+                .iter()
+                .map(|l| ListenerView {
+                    addr: l.addr.to_string(),
+                    mode: l.mode,
+                    tls: l.tls,
+                    fingerprint: l.fingerprint.effective_view(),
+                    passthrough: l
+                        .passthrough
+                        .as_ref()
+                        .map(PassthroughConfig::effective_view),
+                    tenant: l.tenant.clone(),
+                })
+                .collect(),
+            udp: self
+                .udp
+                .iter()
+                .map(UdpListenerConfig::effective_view)
+                .collect(),
             tcp_backlog: self.tcp_backlog,
+            tcp: self.tcp.effective_view(),
+            acceptors: self.acceptors,
+            reuse_port: self.reuse_port,
             proxy_protocol: ProxyProtocolView {
                 mode: self.proxy_protocol.mode.as_str(),
                 header_timeout_ms: self.proxy_protocol.header_timeout_ms,
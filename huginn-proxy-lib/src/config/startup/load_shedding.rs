@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::dynamic::backend::RoutePriority;
+use crate::error::{ProxyError, Result};
+
+/// Adaptive load shedding (`[load_shedding]`).
+///
+/// Static: the shedder is created once at startup. Every `window_ms` the proxy compares the p99
+/// handling latency of its requests (and, when set, the tokio global queue depth) with the
+/// thresholds. While overloaded, the share of requests rejected with `503` grows by `shed_step`
+/// per window up to `max_shed_ratio`; once back under the thresholds it shrinks the same way.
+/// Only routes whose `priority` is at or below `shed_priority` are shed.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LoadSheddingConfig {
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// p99 handling latency, in milliseconds, above which the proxy counts as overloaded.
+    /// Measured from request arrival to the response head, backend time included.
+    /// Default: 1000
+    #[serde(default = "default_latency_threshold_ms")]
+    pub latency_threshold_ms: u64,
+    /// Tasks waiting in the tokio global run queue above which the proxy counts as overloaded.
+    /// `0` disables this signal.
+    /// Default: 0
+    #[serde(default)]
+    pub max_queue_depth: usize,
+    /// Length of the evaluation window in milliseconds.
+    /// Default: 1000
+    #[serde(default = "default_window_ms")]
+    pub window_ms: u64,
+    /// Largest fraction (`0.0`-`1.0`] of sheddable requests rejected.
+    /// Default: 0.5
+    #[serde(default = "default_max_shed_ratio")]
+    pub max_shed_ratio: f64,
+    /// Change of the shed fraction per window (`0.0`-`1.0`].
+    /// Default: 0.1
+    #[serde(default = "default_shed_step")]
+    pub shed_step: f64,
+    /// Highest route priority that may be shed.
+    /// Default: `low`
+    #[serde(default = "default_shed_priority")]
+    pub shed_priority: RoutePriority,
+}
+
+fn default_latency_threshold_ms() -> u64 {
+    1000
+}
+
+fn default_window_ms() -> u64 {
+    1000
+}
+
+fn default_max_shed_ratio() -> f64 {
+    0.5
+}
+
+fn default_shed_step() -> f64 {
+    0.1
+}
+
+fn default_shed_priority() -> RoutePriority {
+    RoutePriority::Low
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            latency_threshold_ms: default_latency_threshold_ms(),
+            max_queue_depth: 0,
+            window_ms: default_window_ms(),
+            max_shed_ratio: default_max_shed_ratio(),
+            shed_step: default_shed_step(),
+            shed_priority: default_shed_priority(),
+        }
+    }
+}
+
+impl LoadSheddingConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.latency_threshold_ms == 0 || self.window_ms == 0 {
+            return Err(ProxyError::Config(
+                "load_shedding.latency_threshold_ms and load_shedding.window_ms must be greater \
+                 than 0"
+                    .to_string(),
+            ));
+        }
+        for (name, ratio) in
+            [("max_shed_ratio", self.max_shed_ratio), ("shed_step", self.shed_step)]
+        {
+            if !(ratio > 0.0 && ratio <= 1.0) {
+                return Err(ProxyError::Config(format!(
+                    "load_shedding.{name} must be greater than 0.0 and at most 1.0, got {ratio}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> LoadSheddingView {
+        LoadSheddingView {
+            enabled: self.enabled,
+            latency_threshold_ms: self.latency_threshold_ms,
+            max_queue_depth: self.max_queue_depth,
+            window_ms: self.window_ms,
+            max_shed_ratio: self.max_shed_ratio,
+            shed_step: self.shed_step,
+            shed_priority: self.shed_priority.as_str(),
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`LoadSheddingConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct LoadSheddingView {
+    enabled: bool,
+    latency_threshold_ms: u64,
+    max_queue_depth: usize,
+    window_ms: u64,
+    max_shed_ratio: f64,
+    shed_step: f64,
+    shed_priority: &'static str,
+}
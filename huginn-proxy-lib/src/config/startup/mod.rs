@@ -1,22 +1,63 @@
+pub mod access_log;
+pub mod cache;
+pub mod client_tracking;
+pub mod event_stream;
 pub mod fingerprinting;
+pub mod handshake_capture;
+pub mod http2;
 pub mod listen;
+pub mod load_shedding;
+pub mod passthrough;
 pub mod reload;
+pub mod request_id;
+pub mod tcp;
 pub mod telemetry;
 pub mod timeout;
 pub mod tls;
+pub mod udp;
 
 use serde::Serialize;
 
-pub use fingerprinting::FingerprintConfig;
-pub use listen::{ListenConfig, ProxyProtocolConfig, ProxyProtocolMode};
+pub use access_log::{AccessLogConfig, AccessLogField, AccessLogOutput};
+pub use cache::CacheConfig;
+pub use client_tracking::ClientTrackingConfig;
+pub use event_stream::{EventSinkKind, EventStreamConfig};
+pub use fingerprinting::{
+    FingerprintConfig, FingerprintHeadersConfig, ListenerFingerprintConfig, SigningConfig,
+    SigningKeyConfig, TcpCapture, WhoamiConfig,
+};
+pub use handshake_capture::{HandshakeCaptureConfig, HandshakeCaptureFormat};
+pub use http2::Http2Config;
+pub use listen::{
+    ListenAddr, ListenConfig, ListenerConfig, ProxyProtocolConfig, ProxyProtocolMode,
+};
+pub use load_shedding::LoadSheddingConfig;
+pub use passthrough::{PassthroughConfig, PassthroughRoute};
 pub use reload::ReloadConfig;
-pub use telemetry::{LoggingConfig, TelemetryConfig};
+pub use request_id::{RequestIdConfig, RequestIdFormat};
+pub use tcp::TcpConfig;
+pub use telemetry::{
+    AdminConfig, AnomalyDetectionConfig, FingerprintStatsConfig, LoggingConfig, MetricsConfig,
+    TelemetryConfig, TracingConfig,
+};
 pub use timeout::{KeepAliveConfig, TimeoutConfig};
-pub use tls::{ClientAuth, SessionResumptionConfig, TlsConfig, TlsOptions, TlsVersion};
+pub use tls::{
+    ClientAuth, ClientCertConfig, MissingClientCert, MustStapleFailure, OcspConfig,
+    SessionResumptionConfig, TicketKeysConfig, TlsConfig, TlsOptions, TlsVersion,
+};
+pub use udp::UdpListenerConfig;
 
+use access_log::AccessLogView;
+use cache::CacheView;
+use client_tracking::ClientTrackingView;
+use event_stream::EventStreamView;
 use fingerprinting::FingerprintView;
+use handshake_capture::HandshakeCaptureView;
+use http2::Http2View;
 use listen::ListenView;
+use load_shedding::LoadSheddingView;
 use reload::ReloadView;
+use request_id::RequestIdView;
 use telemetry::{LoggingView, TelemetryView};
 use timeout::TimeoutView;
 use tls::{effective_tls_view, TlsView};
@@ -38,12 +79,30 @@ pub struct StaticConfig {
     pub logging: LoggingConfig,
     /// Connection and request timeouts
     pub timeout: TimeoutConfig,
+    /// HTTP/2 settings of client connections
+    pub http2: Http2Config,
     /// Telemetry / metrics configuration
     pub telemetry: TelemetryConfig,
     /// Filesystem-watch / hot-reload configuration
     pub reload: ReloadConfig,
     /// Maximum concurrent connections (from \[security\] in TOML)
     pub max_connections: usize,
+    /// Maximum concurrent connections per client IP (from \[security\] in TOML)
+    pub max_connections_per_ip: Option<usize>,
+    /// Response cache storage
+    pub cache: CacheConfig,
+    /// Per-request access log
+    pub access_log: AccessLogConfig,
+    /// Raw ClientHello capture for offline analysis
+    pub handshake_capture: HandshakeCaptureConfig,
+    /// Adaptive load shedding
+    pub load_shedding: LoadSheddingConfig,
+    /// Request ID generation and propagation
+    pub request_id: RequestIdConfig,
+    /// Fingerprint events published to a webhook, NATS or Kafka
+    pub event_stream: EventStreamConfig,
+    /// Cookie-based client first-seen / request-count tracking
+    pub client_tracking: ClientTrackingConfig,
 }
 
 /// Allowlisted effective-config view of [`StaticConfig`]. Each section mirrors one config type;
@@ -52,12 +111,21 @@ pub struct StaticConfig {
 pub(crate) struct StaticView<'a> {
     listen: ListenView,
     tls: TlsView<'a>,
-    fingerprint: FingerprintView,
+    fingerprint: FingerprintView<'a>,
     logging: LoggingView<'a>,
     timeout: TimeoutView,
+    http2: Http2View,
     telemetry: TelemetryView<'a>,
     reload: ReloadView,
     max_connections: usize,
+    max_connections_per_ip: Option<usize>,
+    cache: CacheView,
+    access_log: AccessLogView<'a>,
+    handshake_capture: HandshakeCaptureView,
+    load_shedding: LoadSheddingView,
+    request_id: RequestIdView<'a>,
+    event_stream: EventStreamView<'a>,
+    client_tracking: ClientTrackingView<'a>,
 }
 
 impl StaticConfig {
@@ -68,9 +136,18 @@ impl StaticConfig {
             fingerprint: self.fingerprint.effective_view(),
             logging: self.logging.effective_view(),
             timeout: self.timeout.effective_view(),
+            http2: self.http2.effective_view(),
             telemetry: self.telemetry.effective_view(),
             reload: self.reload.effective_view(),
             max_connections: self.max_connections,
+            max_connections_per_ip: self.max_connections_per_ip,
+            cache: self.cache.effective_view(),
+            access_log: self.access_log.effective_view(),
+            handshake_capture: self.handshake_capture.effective_view(),
+            load_shedding: self.load_shedding.effective_view(),
+            request_id: self.request_id.effective_view(),
+            event_stream: self.event_stream.effective_view(),
+            client_tracking: self.client_tracking.effective_view(),
         }
     }
 }
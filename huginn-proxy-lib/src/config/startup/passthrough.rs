@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// TLS passthrough of a listener (`[listen.listeners.passthrough]`): the ClientHello is read
+/// for its SNI and JA4 but TLS is not terminated; the connection is relayed byte for byte to
+/// the backend its SNI routes to, so encryption stays end to end.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PassthroughConfig {
+    /// SNI routes. An exact name wins over a `*.` wildcard, and a longer wildcard over a
+    /// shorter one. Default: empty
+    #[serde(default)]
+    pub routes: Vec<PassthroughRoute>,
+    /// Backend (`host:port`) of ClientHellos without SNI or matching no route. Unset drops
+    /// those connections. Default: unset
+    #[serde(default)]
+    pub default_backend: Option<String>,
+}
+
+/// One SNI route of a passthrough listener.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PassthroughRoute {
+    /// Server name, exact (`api.example.com`) or a wildcard of its subdomains
+    /// (`*.example.com`). Matched case-insensitively.
+    pub sni: String,
+    /// Backend address (`host:port`) the connection is relayed to.
+    pub backend: String,
+}
+
+impl PassthroughConfig {
+    /// `section` prefixes error messages, e.g. `listener 0.0.0.0:443: passthrough`.
+    pub fn validate(&self, section: &str) -> Result<()> {
+        if self.routes.is_empty() && self.default_backend.is_none() {
+            return Err(ProxyError::Config(format!(
+                "{section} needs at least one route or a default_backend"
+            )));
+        }
+        for route in &self.routes {
+            let name = route.sni.strip_prefix("*.").unwrap_or(&route.sni);
+            if name.is_empty() || name.contains('*') {
+                return Err(ProxyError::Config(format!(
+                    "{section}: invalid sni '{}' (use a name or '*.' followed by a name)",
+                    route.sni
+                )));
+            }
+            validate_backend(section, &route.backend)?;
+        }
+        if let Some(backend) = &self.default_backend {
+            validate_backend(section, backend)?;
+        }
+        Ok(())
+    }
+
+    /// Backend of a ClientHello with server name `sni`: the exact route, else the longest
+    /// matching wildcard, else `default_backend`.
+    pub fn backend_for(&self, sni: Option<&str>) -> Option<&str> {
+        let routed = sni.and_then(|sni| {
+            let sni = sni.trim_end_matches('.');
+            let exact = self
+                .routes
+                .iter()
+                .find(|r| !r.sni.starts_with("*.") && r.sni.eq_ignore_ascii_case(sni));
+            exact.or_else(|| {
+                self.routes
+                    .iter()
+                    .filter(|r| {
+                        r.sni.strip_prefix('*').is_some_and(|suffix| {
+                            sni.len() > suffix.len()
+                                && sni
+                                    .get(sni.len().saturating_sub(suffix.len())..)
+                                    .is_some_and(|tail| tail.eq_ignore_ascii_case(suffix))
+                        })
+                    })
+                    .max_by_key(|r| r.sni.len())
+            })
+        });
+        routed
+            .map(|r| r.backend.as_str())
+            .or(self.default_backend.as_deref())
+    }
+
+    pub(crate) fn effective_view(&self) -> PassthroughView {
+        PassthroughView {
+            routes: self
+                .routes
+                .iter()
+                .map(|r| PassthroughRouteView { sni: r.sni.clone(), backend: r.backend.clone() })
+                .collect(),
+            default_backend: self.default_backend.clone(),
+        }
+    }
+}
+
+/// Rejects a backend that is not `host:port` with a non-zero port.
+pub(super) fn validate_backend(section: &str, backend: &str) -> Result<()> {
+    let valid = backend
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok_and(|p| p > 0));
+    if !valid {
+        return Err(ProxyError::Config(format!(
+            "{section}: backend '{backend}' must be host:port"
+        )));
+    }
+    Ok(())
+}
+
+/// Allowlisted effective-config view of [`PassthroughConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct PassthroughView {
+    routes: Vec<PassthroughRouteView>,
+    default_backend: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PassthroughRouteView {
+    sni: String,
+    backend: String,
+}
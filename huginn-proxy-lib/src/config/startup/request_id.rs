@@ -0,0 +1,89 @@
+use http::HeaderName;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// Request IDs (`[request_id]`).
+///
+/// Static: read once at startup. Every request gets an ID in `header`, sent to the backend,
+/// echoed in the response when the backend did not set one, and written to the access log and
+/// the request span. A client-sent ID is replaced unless `trust_incoming` is set and the peer is
+/// in `[security.trusted_proxies]`.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RequestIdConfig {
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// Header carrying the ID.
+    /// Default: `x-request-id`
+    #[serde(default = "default_header")]
+    pub header: String,
+    /// Format of generated IDs.
+    /// Default: `uuid`
+    #[serde(default)]
+    pub format: RequestIdFormat,
+    /// Keep the ID a trusted proxy already set instead of generating one.
+    /// Default: false
+    #[serde(default)]
+    pub trust_incoming: bool,
+}
+
+/// Format of generated request IDs.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestIdFormat {
+    /// Random UUID (version 4), e.g. `9b2f6c1e-4a7d-4f3b-8c2e-1d5a6b7c8d9e`
+    #[default]
+    Uuid,
+    /// ULID: 26 characters, sortable by creation time
+    Ulid,
+}
+
+fn default_header() -> String {
+    "x-request-id".to_string()
+}
+
+impl Default for RequestIdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            header: default_header(),
+            format: RequestIdFormat::default(),
+            trust_incoming: false,
+        }
+    }
+}
+
+impl RequestIdConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        HeaderName::from_bytes(self.header.as_bytes()).map_err(|_| {
+            ProxyError::Config(format!(
+                "request_id.header '{}' is not a valid HTTP header name",
+                self.header
+            ))
+        })?;
+        Ok(())
+    }
+
+    pub(crate) fn effective_view(&self) -> RequestIdView<'_> {
+        RequestIdView {
+            enabled: self.enabled,
+            header: &self.header,
+            format: self.format,
+            trust_incoming: self.trust_incoming,
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`RequestIdConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct RequestIdView<'a> {
+    enabled: bool,
+    header: &'a str,
+    format: RequestIdFormat,
+    trust_incoming: bool,
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProxyError, Result};
+
+/// TCP socket options, for client connections (`[listen.tcp]`) and backend connections
+/// (`[backend_pool.tcp]`, `[backends.tcp]`).
+///
+/// Unset options keep the current default: the kernel's on listeners, hyper's (plus
+/// `timeout.keep_alive`) on backend connections. Setting any `keepalive_*` option turns
+/// `SO_KEEPALIVE` on.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TcpConfig {
+    /// `TCP_NODELAY`: send small writes right away instead of coalescing them (Nagle).
+    #[serde(default)]
+    pub nodelay: Option<bool>,
+    /// `TCP_KEEPIDLE`: seconds a connection is idle before the first keep-alive probe.
+    #[serde(default)]
+    pub keepalive_time_secs: Option<u64>,
+    /// `TCP_KEEPINTVL`: seconds between unanswered keep-alive probes.
+    #[serde(default)]
+    pub keepalive_interval_secs: Option<u64>,
+    /// `TCP_KEEPCNT`: unanswered probes before the connection is dropped.
+    #[serde(default)]
+    pub keepalive_retries: Option<u32>,
+    /// `SO_SNDBUF`, in bytes. The kernel may round or double it.
+    #[serde(default)]
+    pub send_buffer_size: Option<usize>,
+    /// `SO_RCVBUF`, in bytes. The kernel may round or double it.
+    #[serde(default)]
+    pub recv_buffer_size: Option<usize>,
+}
+
+impl TcpConfig {
+    /// `section` prefixes the option in error messages, e.g. `listen.tcp`.
+    pub fn validate(&self, section: &str) -> Result<()> {
+        let zero = [
+            ("keepalive_time_secs", self.keepalive_time_secs == Some(0)),
+            ("keepalive_interval_secs", self.keepalive_interval_secs == Some(0)),
+            ("keepalive_retries", self.keepalive_retries == Some(0)),
+            ("send_buffer_size", self.send_buffer_size == Some(0)),
+            ("recv_buffer_size", self.recv_buffer_size == Some(0)),
+        ];
+        if let Some((name, _)) = zero.iter().find(|(_, is_zero)| *is_zero) {
+            return Err(ProxyError::Config(format!(
+                "{section}.{name} must be greater than 0 (omit it for the default)"
+            )));
+        }
+        Ok(())
+    }
+
+    /// These options, each unset one taken from `shared`.
+    pub fn or(&self, shared: &TcpConfig) -> TcpConfig {
+        TcpConfig {
+            nodelay: self.nodelay.or(shared.nodelay),
+            keepalive_time_secs: self.keepalive_time_secs.or(shared.keepalive_time_secs),
+            keepalive_interval_secs: self
+                .keepalive_interval_secs
+                .or(shared.keepalive_interval_secs),
+            keepalive_retries: self.keepalive_retries.or(shared.keepalive_retries),
+            send_buffer_size: self.send_buffer_size.or(shared.send_buffer_size),
+            recv_buffer_size: self.recv_buffer_size.or(shared.recv_buffer_size),
+        }
+    }
+
+    /// `true` when a `keepalive_*` option is set.
+    pub fn keepalive_configured(&self) -> bool {
+        self.keepalive_time_secs.is_some()
+            || self.keepalive_interval_secs.is_some()
+            || self.keepalive_retries.is_some()
+    }
+
+    pub(crate) fn effective_view(&self) -> TcpView {
+        TcpView {
+            nodelay: self.nodelay,
+            keepalive_time_secs: self.keepalive_time_secs,
+            keepalive_interval_secs: self.keepalive_interval_secs,
+            keepalive_retries: self.keepalive_retries,
+            send_buffer_size: self.send_buffer_size,
+            recv_buffer_size: self.recv_buffer_size,
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`TcpConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct TcpView {
+    nodelay: Option<bool>,
+    keepalive_time_secs: Option<u64>,
+    keepalive_interval_secs: Option<u64>,
+    keepalive_retries: Option<u32>,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::config::Secret;
+
 /// Telemetry configuration
 /// Controls observability features: metrics, tracing, and OpenTelemetry integration
 #[derive(Debug, Deserialize, Clone, Default, PartialEq)]
@@ -18,6 +20,404 @@ pub struct TelemetryConfig {
     /// Default: "warn" (suppress informational logs from OpenTelemetry SDK)
     #[serde(default = "default_otel_log_level")]
     pub otel_log_level: String,
+    /// Authenticated admin API served on the metrics port under `/admin/`
+    #[serde(default)]
+    pub admin: AdminConfig,
+    /// OpenTelemetry request spans exported over OTLP
+    #[serde(default)]
+    pub tracing: TracingConfig,
+    /// Histogram buckets and label cardinality of the Prometheus metrics
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// Most frequent JA4 and Akamai fingerprints, served on the metrics port
+    #[serde(default)]
+    pub fingerprint_stats: FingerprintStatsConfig,
+    /// Warnings when a JA4 fingerprint's share of traffic suddenly grows
+    #[serde(default)]
+    pub anomaly_detection: AnomalyDetectionConfig,
+}
+
+/// Admin API configuration (`[telemetry.admin]`)
+/// Runtime inspection and mutation endpoints served by the observability server under `/admin/`.
+/// Every request must carry `Authorization: Bearer <token>`.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AdminConfig {
+    /// Serve the admin API. Requires `metrics_port` and a non-empty `token`.
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// Bearer token required on every admin request
+    #[serde(default)]
+    pub token: Secret<String>,
+}
+
+impl AdminConfig {
+    pub fn validate(&self, metrics_port: Option<u16>) -> crate::error::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if metrics_port.is_none() {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.admin.enabled requires telemetry.metrics_port".to_string(),
+            ));
+        }
+        if self.token.expose().trim().is_empty() {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.admin.enabled requires a non-empty telemetry.admin.token".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Request tracing configuration (`[telemetry.tracing]`)
+/// One OpenTelemetry span per proxied request, exported over OTLP/HTTP (protobuf).
+/// An incoming W3C `traceparent` is continued and the span's context is sent to the backend.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TracingConfig {
+    /// Create and export request spans.
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// OTLP/HTTP traces endpoint
+    /// Default: "http://localhost:4318/v1/traces"
+    #[serde(default = "default_otlp_endpoint")]
+    pub otlp_endpoint: String,
+    /// `service.name` resource attribute of exported spans
+    /// Default: "huginn-proxy"
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    /// Fraction (0.0-1.0) of new traces that are sampled. Requests carrying a `traceparent`
+    /// follow the caller's sampling decision.
+    /// Default: 1.0
+    #[serde(default = "default_sample_ratio")]
+    pub sample_ratio: f64,
+    /// Timeout of one export request in milliseconds
+    /// Default: 10000
+    #[serde(default = "default_export_timeout_ms")]
+    pub export_timeout_ms: u64,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            otlp_endpoint: default_otlp_endpoint(),
+            service_name: default_service_name(),
+            sample_ratio: default_sample_ratio(),
+            export_timeout_ms: default_export_timeout_ms(),
+        }
+    }
+}
+
+impl TracingConfig {
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let scheme_ok = self
+            .otlp_endpoint
+            .parse::<http::Uri>()
+            .ok()
+            .and_then(|uri| uri.scheme_str().map(|s| s == "http" || s == "https"))
+            .unwrap_or(false);
+        if !scheme_ok {
+            return Err(crate::error::ProxyError::Config(format!(
+                "telemetry.tracing.otlp_endpoint must be an http(s) URL, got '{}'",
+                self.otlp_endpoint
+            )));
+        }
+        if self.service_name.trim().is_empty() {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.tracing.service_name must not be empty".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.sample_ratio) {
+            return Err(crate::error::ProxyError::Config(format!(
+                "telemetry.tracing.sample_ratio must be between 0.0 and 1.0, got {}",
+                self.sample_ratio
+            )));
+        }
+        if self.export_timeout_ms == 0 {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.tracing.export_timeout_ms must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Prometheus metrics configuration (`[telemetry.metrics]`)
+/// Shapes the exported series: histogram buckets and a cap on per-route / per-backend labels.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Upper bounds, in seconds, of the buckets of every `*_seconds` histogram. Strictly
+    /// increasing; `+Inf` is implicit.
+    /// Default: [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]
+    #[serde(default = "default_duration_buckets")]
+    pub duration_buckets: Vec<f64>,
+    /// Distinct values recorded per `route` and per backend label. Values first seen once the
+    /// cap is reached are reported as `other`, so a route table that keeps growing through
+    /// reloads cannot grow the number of series without bound.
+    /// Default: 1000
+    #[serde(default = "default_max_label_values")]
+    pub max_label_values: usize,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            duration_buckets: default_duration_buckets(),
+            max_label_values: default_max_label_values(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if self.duration_buckets.is_empty() {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.metrics.duration_buckets must not be empty".to_string(),
+            ));
+        }
+        if self
+            .duration_buckets
+            .iter()
+            .any(|bound| !bound.is_finite() || *bound <= 0.0)
+        {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.metrics.duration_buckets must be finite and greater than 0".to_string(),
+            ));
+        }
+        if self
+            .duration_buckets
+            .windows(2)
+            .any(|pair| pair[0] >= pair[1])
+        {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.metrics.duration_buckets must be strictly increasing".to_string(),
+            ));
+        }
+        if self.max_label_values == 0 {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.metrics.max_label_values must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Fingerprint analytics configuration (`[telemetry.fingerprint_stats]`)
+/// Counts requests and distinct client IPs per JA4 and Akamai fingerprint over a rolling window
+/// and keeps the `top_n` of each. Memory is fixed: counts live in count-min sketches, and only
+/// the current top entries keep their fingerprint string.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FingerprintStatsConfig {
+    /// Track fingerprints. Requires `metrics_port`.
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// Fingerprints kept per kind (JA4, Akamai)
+    /// Default: 20
+    #[serde(default = "default_top_n")]
+    pub top_n: usize,
+    /// Counting window in seconds; counts restart from zero when it ends
+    /// Default: 3600
+    #[serde(default = "default_stats_window_secs")]
+    pub window_secs: u64,
+}
+
+impl Default for FingerprintStatsConfig {
+    fn default() -> Self {
+        Self { enabled: false, top_n: default_top_n(), window_secs: default_stats_window_secs() }
+    }
+}
+
+impl FingerprintStatsConfig {
+    pub fn validate(&self, metrics_port: Option<u16>) -> crate::error::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if metrics_port.is_none() {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.fingerprint_stats.enabled requires telemetry.metrics_port".to_string(),
+            ));
+        }
+        if !(1..=1000).contains(&self.top_n) {
+            return Err(crate::error::ProxyError::Config(format!(
+                "telemetry.fingerprint_stats.top_n must be between 1 and 1000, got {}",
+                self.top_n
+            )));
+        }
+        if self.window_secs == 0 {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.fingerprint_stats.window_secs must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Fingerprint anomaly detection (`[telemetry.anomaly_detection]`)
+/// Compares each JA4 fingerprint's share of requests in a short window with its share over a
+/// long window, and reports a fingerprint whose share is both large and growing fast (an
+/// emerging bot campaign) as a `warn!` log, a metric and optionally a webhook call.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AnomalyDetectionConfig {
+    /// Detect anomalies.
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// Window of current traffic, in seconds
+    /// Default: 60
+    #[serde(default = "default_short_window_secs")]
+    pub short_window_secs: u64,
+    /// Window of baseline traffic, in seconds; nothing is reported until one has passed
+    /// Default: 3600
+    #[serde(default = "default_long_window_secs")]
+    pub long_window_secs: u64,
+    /// Smallest share of short-window requests, `0.0`–`1.0`, a fingerprint must reach
+    /// Default: 0.2
+    #[serde(default = "default_min_share")]
+    pub min_share: f64,
+    /// How many times its long-window share the short-window share must be; `1.0` reports any
+    /// fingerprint above `min_share` that is not shrinking
+    /// Default: 3.0
+    #[serde(default = "default_min_growth")]
+    pub min_growth: f64,
+    /// Fewest short-window requests of a fingerprint before it can be reported
+    /// Default: 100
+    #[serde(default = "default_min_requests")]
+    pub min_requests: u64,
+    /// Seconds before the same fingerprint is reported again
+    /// Default: 600
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// `http(s)://` URL each anomaly is POSTed to as JSON (optional)
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Sent as `Authorization: Bearer <token>` to `webhook_url`
+    #[serde(default)]
+    pub webhook_token: Option<Secret<String>>,
+}
+
+impl Default for AnomalyDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            short_window_secs: default_short_window_secs(),
+            long_window_secs: default_long_window_secs(),
+            min_share: default_min_share(),
+            min_growth: default_min_growth(),
+            min_requests: default_min_requests(),
+            cooldown_secs: default_cooldown_secs(),
+            webhook_url: None,
+            webhook_token: None,
+        }
+    }
+}
+
+impl AnomalyDetectionConfig {
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.short_window_secs == 0 || self.long_window_secs <= self.short_window_secs {
+            return Err(crate::error::ProxyError::Config(format!(
+                "telemetry.anomaly_detection.short_window_secs must be greater than 0 and less \
+                 than long_window_secs, got {} and {}",
+                self.short_window_secs, self.long_window_secs
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.min_share) || self.min_share == 0.0 {
+            return Err(crate::error::ProxyError::Config(format!(
+                "telemetry.anomaly_detection.min_share must be greater than 0.0 and at most 1.0, \
+                 got {}",
+                self.min_share
+            )));
+        }
+        if !(1.0..=f64::MAX).contains(&self.min_growth) {
+            return Err(crate::error::ProxyError::Config(format!(
+                "telemetry.anomaly_detection.min_growth must be at least 1.0, got {}",
+                self.min_growth
+            )));
+        }
+        let webhook_ok = self.webhook_url.as_deref().is_none_or(|url| {
+            url.parse::<http::Uri>().is_ok_and(|uri| {
+                matches!(
+                    uri.scheme_str().map(str::to_ascii_lowercase).as_deref(),
+                    Some("http" | "https")
+                )
+            })
+        });
+        if !webhook_ok {
+            return Err(crate::error::ProxyError::Config(
+                "telemetry.anomaly_detection.webhook_url must be an http(s) URL".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_short_window_secs() -> u64 {
+    60
+}
+
+fn default_long_window_secs() -> u64 {
+    3600
+}
+
+fn default_min_share() -> f64 {
+    0.2
+}
+
+fn default_min_growth() -> f64 {
+    3.0
+}
+
+fn default_min_requests() -> u64 {
+    100
+}
+
+fn default_cooldown_secs() -> u64 {
+    600
+}
+
+fn default_top_n() -> usize {
+    20
+}
+
+fn default_stats_window_secs() -> u64 {
+    3600
+}
+
+fn default_duration_buckets() -> Vec<f64> {
+    vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]
+}
+
+fn default_max_label_values() -> usize {
+    1000
+}
+
+fn default_otlp_endpoint() -> String {
+    "http://localhost:4318/v1/traces".to_string()
+}
+
+fn default_service_name() -> String {
+    "huginn-proxy".to_string()
+}
+
+fn default_sample_ratio() -> f64 {
+    1.0
+}
+
+fn default_export_timeout_ms() -> u64 {
+    10_000
 }
 
 fn default_otel_log_level() -> String {
@@ -53,6 +453,58 @@ fn default_false() -> bool {
 pub(crate) struct TelemetryView<'a> {
     metrics_port: Option<u16>,
     otel_log_level: &'a str,
+    admin: AdminView<'a>,
+    tracing: TracingView<'a>,
+    metrics: MetricsView<'a>,
+    fingerprint_stats: FingerprintStatsView,
+    anomaly_detection: AnomalyDetectionView<'a>,
+}
+
+/// Allowlisted effective-config view of [`AdminConfig`]. The token serializes as `<redacted>`.
+#[derive(Serialize)]
+pub(crate) struct AdminView<'a> {
+    enabled: bool,
+    token: &'a Secret<String>,
+}
+
+/// Allowlisted effective-config view of [`TracingConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct TracingView<'a> {
+    enabled: bool,
+    otlp_endpoint: &'a str,
+    service_name: &'a str,
+    sample_ratio: f64,
+    export_timeout_ms: u64,
+}
+
+/// Allowlisted effective-config view of [`MetricsConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct MetricsView<'a> {
+    duration_buckets: &'a [f64],
+    max_label_values: usize,
+}
+
+/// Allowlisted effective-config view of [`FingerprintStatsConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct FingerprintStatsView {
+    enabled: bool,
+    top_n: usize,
+    window_secs: u64,
+}
+
+/// Allowlisted effective-config view of [`AnomalyDetectionConfig`]. The webhook token serializes
+/// as `<redacted>`.
+#[derive(Serialize)]
+pub(crate) struct AnomalyDetectionView<'a> {
+    enabled: bool,
+    short_window_secs: u64,
+    long_window_secs: u64,
+    min_share: f64,
+    min_growth: f64,
+    min_requests: u64,
+    cooldown_secs: u64,
+    webhook_url: Option<&'a str>,
+    webhook_token: Option<&'a Secret<String>>,
 }
 
 /// Allowlisted effective-config view of [`LoggingConfig`]. Field names are the JSON keys.
@@ -67,6 +519,34 @@ impl TelemetryConfig {
         TelemetryView {
             metrics_port: self.metrics_port,
             otel_log_level: self.otel_log_level.as_str(),
+            admin: AdminView { enabled: self.admin.enabled, token: &self.admin.token },
+            tracing: TracingView {
+                enabled: self.tracing.enabled,
+                otlp_endpoint: self.tracing.otlp_endpoint.as_str(),
+                service_name: self.tracing.service_name.as_str(),
+                sample_ratio: self.tracing.sample_ratio,
+                export_timeout_ms: self.tracing.export_timeout_ms,
+            },
+            metrics: MetricsView {
+                duration_buckets: &self.metrics.duration_buckets,
+                max_label_values: self.metrics.max_label_values,
+            },
+            fingerprint_stats: FingerprintStatsView {
+                enabled: self.fingerprint_stats.enabled,
+                top_n: self.fingerprint_stats.top_n,
+                window_secs: self.fingerprint_stats.window_secs,
+            },
+            anomaly_detection: AnomalyDetectionView {
+                enabled: self.anomaly_detection.enabled,
+                short_window_secs: self.anomaly_detection.short_window_secs,
+                long_window_secs: self.anomaly_detection.long_window_secs,
+                min_share: self.anomaly_detection.min_share,
+                min_growth: self.anomaly_detection.min_growth,
+                min_requests: self.anomaly_detection.min_requests,
+                cooldown_secs: self.anomaly_detection.cooldown_secs,
+                webhook_url: self.anomaly_detection.webhook_url.as_deref(),
+                webhook_token: self.anomaly_detection.webhook_token.as_ref(),
+            },
         }
     }
 }
@@ -9,7 +9,8 @@ pub struct TimeoutConfig {
     #[serde(default)]
     pub upstream_connect_ms: Option<u64>,
     /// Idle connection timeout for inbound client connections in milliseconds.
-    /// Applied as HTTP/1.1 `header_read_timeout` and HTTP/2 keep-alive interval.
+    /// Applied as HTTP/1.1 `header_read_timeout` and, unless `http2.keep_alive_interval_ms` is
+    /// set, HTTP/2 keep-alive interval.
     /// Default: 60000 (60 seconds)
     #[serde(default = "default_proxy_idle_ms")]
     pub proxy_idle_ms: u64,
@@ -17,6 +18,12 @@ pub struct TimeoutConfig {
     /// Default: 30
     #[serde(default = "default_shutdown_timeout")]
     pub shutdown_secs: u64,
+    /// Seconds between flipping `/ready` to not ready and closing the listeners on shutdown or
+    /// drain. Connections are still accepted meanwhile, so load balancers polling `/ready` (or a
+    /// new process sharing the ports through `listen.reuse_port`) take over the traffic first.
+    /// Default: 0
+    #[serde(default)]
+    pub drain_delay_secs: u64,
     /// TLS handshake timeout in seconds
     /// Maximum time allowed for completing the TLS handshake
     /// Prevents slow clients from holding connections during handshake
@@ -45,6 +52,7 @@ impl Default for TimeoutConfig {
             upstream_connect_ms: None,
             proxy_idle_ms: default_proxy_idle_ms(),
             shutdown_secs: default_shutdown_timeout(),
+            drain_delay_secs: 0,
             tls_handshake_secs: default_tls_handshake_timeout(),
             connection_handling_secs: default_connection_handling_timeout(),
             keep_alive: KeepAliveConfig::default(),
@@ -117,6 +125,7 @@ pub(crate) struct TimeoutView {
     upstream_connect_ms: Option<u64>,
     proxy_idle_ms: u64,
     shutdown_secs: u64,
+    drain_delay_secs: u64,
     tls_handshake_secs: u64,
     connection_handling_secs: u64,
     keep_alive: KeepAliveView,
@@ -134,6 +143,7 @@ impl TimeoutConfig {
             upstream_connect_ms: self.upstream_connect_ms,
             proxy_idle_ms: self.proxy_idle_ms,
             shutdown_secs: self.shutdown_secs,
+            drain_delay_secs: self.drain_delay_secs,
             tls_handshake_secs: self.tls_handshake_secs,
             connection_handling_secs: self.connection_handling_secs,
             keep_alive: KeepAliveView {
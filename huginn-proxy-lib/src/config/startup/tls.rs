@@ -1,7 +1,9 @@
+use crate::config::Secret;
+use crate::error::{ProxyError, Result};
 use serde::{Deserialize, Serialize};
 
 /// TLS version configuration
-#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum TlsVersion {
     /// TLS 1.2
@@ -56,6 +58,15 @@ pub struct TlsOptions {
     /// Default: false (lenient, serve the default cert for unmatched SNI).
     #[serde(default)]
     pub sni_strict: bool,
+    /// Close connections whose ClientHello carries the `encrypted_client_hello` extension but
+    /// no outer SNI.
+    ///
+    /// ECH clients always send the public name as the outer SNI, so an ECH extension without
+    /// one points at a hand-crafted ClientHello rather than a browser.
+    ///
+    /// Default: false (allow).
+    #[serde(default)]
+    pub reject_ech_without_sni: bool,
 }
 
 impl Default for TlsOptions {
@@ -67,10 +78,33 @@ impl Default for TlsOptions {
             cipher_suites: default_cipher_suites(),
             curve_preferences: default_curve_preferences(),
             sni_strict: false,
+            reject_ech_without_sni: false,
         }
     }
 }
 
+impl TlsOptions {
+    /// Versions the acceptor enables, ascending: `versions` (all versions when empty) narrowed to
+    /// the `min_version` ..= `max_version` range.
+    pub fn effective_versions(&self) -> Vec<TlsVersion> {
+        let min = self.min_version.unwrap_or(TlsVersion::V1_2);
+        let max = self.max_version.unwrap_or(TlsVersion::V1_3);
+        [TlsVersion::V1_2, TlsVersion::V1_3]
+            .into_iter()
+            .filter(|v| self.versions.is_empty() || self.versions.contains(v))
+            .filter(|v| (min..=max).contains(v))
+            .collect()
+    }
+
+    /// Whether `versions` leaves out a version, i.e. is more than the default "all versions".
+    pub fn restricts_versions(&self) -> bool {
+        !self.versions.is_empty()
+            && !default_tls_versions()
+                .iter()
+                .all(|v| self.versions.contains(v))
+    }
+}
+
 fn default_tls_versions() -> Vec<TlsVersion> {
     vec![TlsVersion::V1_2, TlsVersion::V1_3]
 }
@@ -112,6 +146,68 @@ pub enum ClientAuth {
         /// Can contain one or more CA certificates
         ca_cert_path: String,
     },
+    /// Client certificates are requested but not required
+    /// A presented certificate must still be signed by the specified CA; clients without one
+    /// complete the handshake and are handled per [`ClientCertConfig::on_missing`]
+    Optional {
+        /// Path to client CA certificate file (PEM format)
+        ca_cert_path: String,
+    },
+}
+
+/// What to do with a request whose TLS connection presented no client certificate
+/// (`client_auth = optional` only; `required` already rejects at the handshake).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingClientCert {
+    /// Forward the request without client certificate headers (default)
+    #[default]
+    Allow,
+    /// Answer with [`ClientCertConfig::reject_status`] instead of forwarding
+    Reject,
+}
+
+/// Handling of the verified client certificate after the handshake
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ClientCertConfig {
+    /// Forward the client certificate subject and SHA-256 fingerprint to the backend as
+    /// `x-huginn-client-cert-subject` / `x-huginn-client-cert-sha256` (default: false).
+    /// Client-supplied values of these headers are always stripped.
+    #[serde(default)]
+    pub forward_headers: bool,
+    /// Behaviour for connections without a client certificate (default: allow)
+    #[serde(default)]
+    pub on_missing: MissingClientCert,
+    /// HTTP status returned when `on_missing = "reject"` (default: 403, must be 4xx)
+    #[serde(default = "default_reject_status")]
+    pub reject_status: u16,
+}
+
+impl Default for ClientCertConfig {
+    fn default() -> Self {
+        Self {
+            forward_headers: false,
+            on_missing: MissingClientCert::default(),
+            reject_status: default_reject_status(),
+        }
+    }
+}
+
+impl ClientCertConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(400..=499).contains(&self.reject_status) {
+            return Err(ProxyError::Config(format!(
+                "tls.client_cert.reject_status must be a 4xx status, got {}",
+                self.reject_status
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn default_reject_status() -> u16 {
+    403
 }
 
 /// Session resumption configuration for TLS
@@ -128,14 +224,121 @@ pub struct SessionResumptionConfig {
     /// TLS 1.3 uses stateless session tickets and doesn't use this cache
     #[serde(default = "default_session_cache_size")]
     pub max_sessions: usize,
+    /// TLS 1.3 session ticket keys derived from a shared secret (`[tls.session_resumption.ticket_keys]`)
+    /// Default: unset (per-process keys, tickets only resume on the instance that issued them)
+    #[serde(default)]
+    pub ticket_keys: Option<TicketKeysConfig>,
 }
 
 impl Default for SessionResumptionConfig {
     fn default() -> Self {
-        Self { enabled: default_true(), max_sessions: default_session_cache_size() }
+        Self {
+            enabled: default_true(),
+            max_sessions: default_session_cache_size(),
+            ticket_keys: None,
+        }
     }
 }
 
+impl SessionResumptionConfig {
+    pub fn validate(&self) -> Result<()> {
+        match &self.ticket_keys {
+            Some(keys) => keys.validate(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Shortest accepted shared ticket secret, in bytes.
+pub const MIN_TICKET_SECRET_LEN: usize = 32;
+
+/// Longest accepted ticket key rotation interval: the TLS 1.3 ticket lifetime limit (7 days).
+pub const MAX_TICKET_ROTATION_SECS: u64 = 7 * 24 * 3600;
+
+/// Shared TLS 1.3 session ticket keys.
+///
+/// The encryption key of each `rotation_secs` period is derived from the secret and the period
+/// number, so every replica configured with the same secret issues and accepts the same tickets
+/// without coordinating, and keys rotate on schedule without a reload. Tickets of the previous
+/// period stay accepted, so a ticket is valid for at most two periods.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[cfg_attr(test, derive(serde::Serialize))]
+#[serde(deny_unknown_fields)]
+pub struct TicketKeysConfig {
+    /// Inline secret (at least 32 bytes). Exclusive with `secret_file`
+    #[serde(default)]
+    pub secret: Option<Secret<String>>,
+    /// File holding the secret (at least 32 bytes; surrounding whitespace is ignored), e.g. a
+    /// mounted Kubernetes secret. Read once at startup. Exclusive with `secret`
+    #[serde(default)]
+    pub secret_file: Option<String>,
+    /// Seconds between key rotations; also the advertised ticket lifetime (default: 3600)
+    #[serde(default = "default_ticket_rotation_secs")]
+    pub rotation_secs: u64,
+}
+
+impl TicketKeysConfig {
+    pub fn validate(&self) -> Result<()> {
+        match (&self.secret, &self.secret_file) {
+            (Some(_), Some(_)) => {
+                return Err(ProxyError::Config(
+                    "tls.session_resumption.ticket_keys: secret and secret_file are mutually \
+                     exclusive"
+                        .to_string(),
+                ))
+            }
+            (None, None) => {
+                return Err(ProxyError::Config(
+                    "tls.session_resumption.ticket_keys requires secret or secret_file".to_string(),
+                ))
+            }
+            (Some(secret), None) => check_ticket_secret(secret.expose().trim().as_bytes())?,
+            (None, Some(_)) => {}
+        }
+        if !(60..=MAX_TICKET_ROTATION_SECS).contains(&self.rotation_secs) {
+            return Err(ProxyError::Config(format!(
+                "tls.session_resumption.ticket_keys.rotation_secs must be between 60 and \
+                 {MAX_TICKET_ROTATION_SECS}, got {}",
+                self.rotation_secs
+            )));
+        }
+        Ok(())
+    }
+
+    /// The shared secret, read from `secret_file` when configured.
+    pub fn load_secret(&self) -> Result<Vec<u8>> {
+        let secret = match (&self.secret, &self.secret_file) {
+            (Some(secret), _) => secret.expose().trim().as_bytes().to_vec(),
+            (None, Some(path)) => {
+                let bytes = std::fs::read(path).map_err(|e| {
+                    ProxyError::Config(format!(
+                        "tls.session_resumption.ticket_keys.secret_file '{path}': {e}"
+                    ))
+                })?;
+                bytes.trim_ascii().to_vec()
+            }
+            (None, None) => Vec::new(),
+        };
+        check_ticket_secret(&secret)?;
+        Ok(secret)
+    }
+}
+
+fn check_ticket_secret(secret: &[u8]) -> Result<()> {
+    if secret.len() < MIN_TICKET_SECRET_LEN {
+        return Err(ProxyError::Config(format!(
+            "tls.session_resumption.ticket_keys secret must be at least {MIN_TICKET_SECRET_LEN} \
+             bytes, got {}",
+            secret.len()
+        )));
+    }
+    Ok(())
+}
+
+fn default_ticket_rotation_secs() -> u64 {
+    3600
+}
+
 fn default_true() -> bool {
     true
 }
@@ -144,6 +347,79 @@ fn default_session_cache_size() -> usize {
     256
 }
 
+/// What to do with a certificate carrying the OCSP Must-Staple extension (RFC 7633) while no
+/// valid OCSP response is available for it.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MustStapleFailure {
+    /// Serve the certificate without a staple (default); clients enforcing Must-Staple fail
+    /// the handshake, others connect normally
+    #[default]
+    Serve,
+    /// Stop serving the certificate until a response is stapled; its handshakes fail as if
+    /// the domain had no certificate
+    Reject,
+}
+
+/// OCSP stapling for the configured certificates
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct OcspConfig {
+    /// Fetch OCSP responses from the responder named in each certificate and staple them in
+    /// the handshake (default: false). A chain needs its issuer certificate after the leaf.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Timeout of one responder request, in milliseconds (default: 5000)
+    #[serde(default = "default_ocsp_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Delay before retrying a failed fetch, in seconds (default: 300). A stapled response is
+    /// kept until its `nextUpdate` while retries fail.
+    #[serde(default = "default_ocsp_retry_secs")]
+    pub retry_secs: u64,
+    /// Behaviour for Must-Staple certificates without a valid response (default: serve)
+    #[serde(default)]
+    pub must_staple_failure: MustStapleFailure,
+}
+
+impl Default for OcspConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: default_ocsp_timeout_ms(),
+            retry_secs: default_ocsp_retry_secs(),
+            must_staple_failure: MustStapleFailure::default(),
+        }
+    }
+}
+
+/// Longest accepted OCSP retry delay (1 day).
+pub const MAX_OCSP_RETRY_SECS: u64 = 24 * 3600;
+
+impl OcspConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.timeout_ms == 0 {
+            return Err(ProxyError::Config(
+                "tls.ocsp.timeout_ms must be greater than 0".to_string(),
+            ));
+        }
+        if !(1..=MAX_OCSP_RETRY_SECS).contains(&self.retry_secs) {
+            return Err(ProxyError::Config(format!(
+                "tls.ocsp.retry_secs must be between 1 and {MAX_OCSP_RETRY_SECS}, got {}",
+                self.retry_secs
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn default_ocsp_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_ocsp_retry_secs() -> u64 {
+    300
+}
+
 #[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct TlsConfig {
@@ -159,9 +435,15 @@ pub struct TlsConfig {
     /// Default: disabled (no client authentication required)
     #[serde(default)]
     pub client_auth: ClientAuth,
+    /// Client certificate forwarding and rejection policy (mTLS only)
+    #[serde(default)]
+    pub client_cert: ClientCertConfig,
     /// Session resumption configuration
     #[serde(default)]
     pub session_resumption: SessionResumptionConfig,
+    /// OCSP stapling configuration
+    #[serde(default)]
+    pub ocsp: OcspConfig,
 }
 
 /// Allowlisted effective-config view of TLS: `{"enabled": false}` when TLS is off, otherwise the
@@ -170,7 +452,7 @@ pub struct TlsConfig {
 #[serde(untagged)]
 pub(crate) enum TlsView<'a> {
     Disabled { enabled: bool },
-    Enabled(TlsEnabledView<'a>),
+    Enabled(Box<TlsEnabledView<'a>>),
 }
 
 #[derive(Serialize)]
@@ -179,7 +461,9 @@ pub(crate) struct TlsEnabledView<'a> {
     alpn: &'a [String],
     options: TlsOptionsView<'a>,
     client_auth: ClientAuthView,
-    session_resumption: SessionResumptionView,
+    client_cert: ClientCertView,
+    session_resumption: SessionResumptionView<'a>,
+    ocsp: OcspView,
 }
 
 #[derive(Serialize)]
@@ -190,6 +474,7 @@ struct TlsOptionsView<'a> {
     cipher_suites: &'a [String],
     curve_preferences: &'a [String],
     sni_strict: bool,
+    reject_ech_without_sni: bool,
 }
 
 #[derive(Serialize)]
@@ -200,9 +485,33 @@ struct ClientAuthView {
 }
 
 #[derive(Serialize)]
-struct SessionResumptionView {
+struct ClientCertView {
+    forward_headers: bool,
+    on_missing: &'static str,
+    reject_status: u16,
+}
+
+#[derive(Serialize)]
+struct SessionResumptionView<'a> {
     enabled: bool,
     max_sessions: usize,
+    ticket_keys: Option<TicketKeysView<'a>>,
+}
+
+/// The inline secret serializes as `<redacted>`; the file path is shown.
+#[derive(Serialize)]
+struct TicketKeysView<'a> {
+    secret: Option<&'a Secret<String>>,
+    secret_file: Option<&'a str>,
+    rotation_secs: u64,
+}
+
+#[derive(Serialize)]
+struct OcspView {
+    enabled: bool,
+    timeout_ms: u64,
+    retry_secs: u64,
+    must_staple_failure: &'static str,
 }
 
 /// Build the effective-config view for the optional TLS section.
@@ -218,9 +527,12 @@ pub(crate) fn effective_tls_view(config: Option<&TlsConfig>) -> TlsView<'_> {
         ClientAuth::Required { .. } => {
             ClientAuthView { mode: "required", ca_certificate_configured: Some(true) }
         }
+        ClientAuth::Optional { .. } => {
+            ClientAuthView { mode: "optional", ca_certificate_configured: Some(true) }
+        }
     };
 
-    TlsView::Enabled(TlsEnabledView {
+    TlsView::Enabled(Box::new(TlsEnabledView {
         enabled: true,
         alpn: config.alpn.as_slice(),
         options: TlsOptionsView {
@@ -235,17 +547,54 @@ pub(crate) fn effective_tls_view(config: Option<&TlsConfig>) -> TlsView<'_> {
             cipher_suites: config.options.cipher_suites.as_slice(),
             curve_preferences: config.options.curve_preferences.as_slice(),
             sni_strict: config.options.sni_strict,
+            reject_ech_without_sni: config.options.reject_ech_without_sni,
         },
         client_auth,
+        client_cert: ClientCertView {
+            forward_headers: config.client_cert.forward_headers,
+            on_missing: config.client_cert.on_missing.as_str(),
+            reject_status: config.client_cert.reject_status,
+        },
         session_resumption: SessionResumptionView {
             enabled: config.session_resumption.enabled,
             max_sessions: config.session_resumption.max_sessions,
+            ticket_keys: config.session_resumption.ticket_keys.as_ref().map(|keys| {
+                TicketKeysView {
+                    secret: keys.secret.as_ref(),
+                    secret_file: keys.secret_file.as_deref(),
+                    rotation_secs: keys.rotation_secs,
+                }
+            }),
         },
-    })
+        ocsp: OcspView {
+            enabled: config.ocsp.enabled,
+            timeout_ms: config.ocsp.timeout_ms,
+            retry_secs: config.ocsp.retry_secs,
+            must_staple_failure: config.ocsp.must_staple_failure.as_str(),
+        },
+    }))
 }
 
-impl TlsVersion {
+impl MissingClientCert {
+    fn as_str(self) -> &'static str {
+        match self {
+            MissingClientCert::Allow => "allow",
+            MissingClientCert::Reject => "reject",
+        }
+    }
+}
+
+impl MustStapleFailure {
     fn as_str(self) -> &'static str {
+        match self {
+            MustStapleFailure::Serve => "serve",
+            MustStapleFailure::Reject => "reject",
+        }
+    }
+}
+
+impl TlsVersion {
+    pub fn as_str(self) -> &'static str {
         match self {
             TlsVersion::V1_2 => "1.2",
             TlsVersion::V1_3 => "1.3",
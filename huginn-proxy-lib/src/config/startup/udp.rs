@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+use super::passthrough::validate_backend;
+use crate::error::{ProxyError, Result};
+
+/// One `[[listen.udp]]` entry: datagrams received on `addr` are forwarded to `backend`.
+///
+/// Each client address gets its own session, with a dedicated upstream socket, so replies
+/// find their way back. Sessions end after `idle_timeout_secs` without traffic either way.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct UdpListenerConfig {
+    /// Address and port to receive datagrams on.
+    pub addr: SocketAddr,
+    /// Backend address (`host:port`), resolved once at startup.
+    pub backend: String,
+    /// Seconds a session may go without a datagram in either direction before it is closed.
+    /// Default: 60
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    /// Concurrent sessions. Datagrams from new clients are dropped while it is reached.
+    /// Default: 4096
+    #[serde(default = "default_max_sessions")]
+    pub max_sessions: usize,
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    60
+}
+
+fn default_max_sessions() -> usize {
+    4096
+}
+
+impl UdpListenerConfig {
+    pub fn validate(&self) -> Result<()> {
+        let section = format!("listen.udp {}", self.addr);
+        if self.idle_timeout_secs == 0 {
+            return Err(ProxyError::Config(format!(
+                "{section}: idle_timeout_secs must be greater than 0"
+            )));
+        }
+        if self.max_sessions == 0 {
+            return Err(ProxyError::Config(format!(
+                "{section}: max_sessions must be greater than 0"
+            )));
+        }
+        validate_backend(&section, &self.backend)
+    }
+
+    pub(crate) fn effective_view(&self) -> UdpListenerView {
+        UdpListenerView {
+            addr: self.addr.to_string(),
+            backend: self.backend.clone(),
+            idle_timeout_secs: self.idle_timeout_secs,
+            max_sessions: self.max_sessions,
+        }
+    }
+}
+
+/// Allowlisted effective-config view of [`UdpListenerConfig`]. Field names are the JSON keys.
+#[derive(Serialize)]
+pub(crate) struct UdpListenerView {
+    addr: String,
+    backend: String,
+    idle_timeout_secs: u64,
+    max_sessions: usize,
+}
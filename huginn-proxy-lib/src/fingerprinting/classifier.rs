@@ -0,0 +1,79 @@
+//! Pluggable request classification for library users.
+//!
+//! A [`FingerprintClassifier`] registered through [`run`](crate::proxy::run) sees every routed
+//! request together with the fingerprints observed on its connection and decides whether it
+//! is forwarded, rejected, or forwarded with a tag. It runs after the built-in IP filter,
+//! fingerprint filter and rate limit, so it only pays for requests those let through.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use http::{HeaderMap, Method, StatusCode};
+use huginn_net_http::AkamaiFingerprint;
+use huginn_net_tcp::TcpObservation;
+
+use super::Ja4Fingerprints;
+use crate::proxy::connection::ConnectionContext;
+
+/// Request metadata and connection fingerprints handed to a [`FingerprintClassifier`].
+///
+/// Fingerprints are the ones observed on the connection, whatever the route's
+/// `fingerprinting` setting (which only governs header injection). Client-supplied copies of
+/// the fingerprint headers are already stripped from `headers`.
+#[derive(Debug, Clone, Copy)]
+pub struct FingerprintSet<'a> {
+    /// Effective client address (after PROXY protocol resolution).
+    pub peer: SocketAddr,
+    pub method: &'a Method,
+    /// Routing host (`:authority` / `Host` without port).
+    pub host: &'a str,
+    pub path: &'a str,
+    /// Matched route prefix.
+    pub route: &'a str,
+    pub headers: &'a HeaderMap,
+    /// JA4 fingerprints of the TLS ClientHello (`None` on plain HTTP).
+    pub ja4: Option<&'a Ja4Fingerprints>,
+    /// HTTP/2 Akamai fingerprint (`None` on HTTP/1.x or when not extracted).
+    pub akamai: Option<&'a AkamaiFingerprint>,
+    /// TCP SYN signature (`None` when not captured).
+    pub tcp: Option<&'a TcpObservation>,
+    /// The connection the request came in on: its id, SNI, ALPN and age.
+    pub connection: Option<&'a ConnectionContext>,
+}
+
+/// Decision returned by [`FingerprintClassifier::classify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Forward the request unchanged.
+    Allow,
+    /// Answer with a synthetic response carrying this status (normally a 4xx) instead of
+    /// forwarding.
+    Deny(StatusCode),
+    /// Forward the request with the tag in the
+    /// [`x-huginn-classification`](crate::fingerprinting::names::CLASSIFICATION) header. A tag
+    /// that is not a valid header value is dropped and the request is forwarded untagged.
+    Tag(String),
+}
+
+impl Verdict {
+    /// Value of the `verdict` label of `huginn_classifier_verdicts_total`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny(_) => "deny",
+            Self::Tag(_) => "tag",
+        }
+    }
+}
+
+/// Custom bot-detection logic plugged into the request path.
+///
+/// `classify` runs inline on the request task for every routed request, so it must not block:
+/// keep it to in-memory lookups and hand slow work (e.g. model scoring) to a background task
+/// that updates the state consulted here.
+pub trait FingerprintClassifier: Send + Sync + 'static {
+    fn classify(&self, set: &FingerprintSet<'_>) -> Verdict;
+}
+
+/// Shared handle to a registered classifier, as accepted by [`run`](crate::proxy::run).
+pub type SharedClassifier = Arc<dyn FingerprintClassifier>;
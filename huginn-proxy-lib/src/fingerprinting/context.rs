@@ -0,0 +1,154 @@
+//! Single-header fingerprint propagation (`fingerprint_format` on a route or domain).
+//!
+//! With `structured` or `jwt`, [`pack_context`] runs once the proxy has injected its fingerprint
+//! headers: it removes them and sends their values, plus the client IP, in the one
+//! [`names::CONTEXT`] header, under stable keys independent of the configured header names:
+//! `client_ip` (resolved through `[security.trusted_proxies]`), `ja4`, `ja4_r`, `ja4_o`,
+//! `ja4_or`, `ja4_s1`, `ja4_s1r`, `akamai`, `tcp`, `os` (OS guess), `spoofed` (spoofing
+//! detection), `class` (classifier tag) and `tls_alpn`, `tls_sni`, `tls_version`, `tls_cipher`,
+//! `tls_early_data`, `tls_ech`. Only the signals the proxy would have sent as headers are present.
+//!
+//! `structured` writes an RFC 8941 dictionary of strings, e.g.
+//! `client_ip="203.0.113.7", ja4="t13d1516h2_8daaf6152771_02713d6af862"`. `jwt` writes a compact
+//! HS256 JWT whose header carries the signing key id as `kid` and whose claims are the same keys
+//! plus `iat` and `exp`.
+
+use std::net::IpAddr;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{Map, Value};
+
+use crate::config::FingerprintFormat;
+use crate::fingerprinting::headers::{names, tls_info, FingerprintHeaderNames};
+use crate::fingerprinting::signing::HeaderSigner;
+use crate::utils::time::unix_now;
+
+/// Validity of a context JWT, in seconds after `iat`.
+pub const JWT_LIFETIME_SECS: u64 = 60;
+
+/// Context key of each fingerprint header, by built-in name.
+const FINGERPRINT_KEYS: &[(&str, &str)] = &[
+    (names::TLS_JA4, "ja4"),
+    (names::TLS_JA4_R, "ja4_r"),
+    (names::TLS_JA4_O, "ja4_o"),
+    (names::TLS_JA4_OR, "ja4_or"),
+    (names::TLS_JA4_S1, "ja4_s1"),
+    (names::TLS_JA4_S1R, "ja4_s1r"),
+    (names::HTTP2_AKAMAI, "akamai"),
+    (names::TCP_SYN, "tcp"),
+    (names::TCP_OS, "os"),
+];
+
+/// Context key of each TLS parameter header.
+const TLS_INFO_KEYS: &[(&str, &str)] = &[
+    (tls_info::ALPN, "tls_alpn"),
+    (tls_info::SNI, "tls_sni"),
+    (tls_info::VERSION, "tls_version"),
+    (tls_info::CIPHER, "tls_cipher"),
+    (tls_info::EARLY_DATA, "tls_early_data"),
+    (tls_info::ECH, "tls_ech"),
+];
+
+/// Move the fingerprint headers of `headers` into [`names::CONTEXT`] as `format` says.
+///
+/// No-op for [`FingerprintFormat::Headers`]. With [`FingerprintFormat::Jwt`] and no `signer`
+/// (or no key active yet) the headers are removed and no context is sent.
+pub fn pack_context(
+    headers: &mut HeaderMap,
+    format: FingerprintFormat,
+    header_names: &FingerprintHeaderNames,
+    client_ip: IpAddr,
+    signer: Option<&HeaderSigner>,
+) {
+    let packed = match format {
+        FingerprintFormat::Headers => return,
+        FingerprintFormat::Structured => {
+            let fields = take_fields(headers, header_names, client_ip);
+            Some(structured(&fields))
+        }
+        FingerprintFormat::Jwt => {
+            let fields = take_fields(headers, header_names, client_ip);
+            signer.and_then(|signer| jwt(&fields, signer, unix_now()))
+        }
+    };
+    if let Some(value) = packed.and_then(|v| HeaderValue::from_str(&v).ok()) {
+        headers.insert(names::CONTEXT, value);
+    }
+}
+
+/// Remove the fingerprint headers and return `(key, value)` for each one present.
+fn take_fields(
+    headers: &mut HeaderMap,
+    header_names: &FingerprintHeaderNames,
+    client_ip: IpAddr,
+) -> Vec<(&'static str, String)> {
+    let mut fields = vec![("client_ip", client_ip.to_string())];
+    let sources = FINGERPRINT_KEYS
+        .iter()
+        .filter_map(|&(default, key)| Some((header_names.get(default)?.clone(), key)))
+        .chain([
+            (header_names.spoofing_detected().clone(), "spoofed"),
+            (HeaderName::from_static(names::CLASSIFICATION), "class"),
+        ])
+        .chain(
+            TLS_INFO_KEYS
+                .iter()
+                .map(|&(name, key)| (HeaderName::from_static(name), key)),
+        );
+    for (name, key) in sources {
+        let Some(value) = headers.remove(&name) else {
+            continue;
+        };
+        if let Ok(value) = value.to_str() {
+            fields.push((key, value.to_string()));
+        }
+    }
+    fields
+}
+
+/// RFC 8941 dictionary with one string item per field. Values an sf-string cannot hold (tabs)
+/// are left out.
+fn structured(fields: &[(&str, String)]) -> String {
+    let mut out = String::new();
+    for (key, value) in fields {
+        if value.contains('\t') {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(", ");
+        }
+        out.push_str(key);
+        out.push_str("=\"");
+        for c in value.chars() {
+            if matches!(c, '"' | '\\') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push('"');
+    }
+    out
+}
+
+/// Compact HS256 JWT of `fields`, issued at `iat`, signed with the key active then.
+fn jwt(fields: &[(&str, String)], signer: &HeaderSigner, iat: u64) -> Option<String> {
+    let kid = signer.active_key_id(iat)?;
+    // Key ids are restricted to `[A-Za-z0-9._-]`: no JSON escaping needed.
+    let header = format!(r#"{{"alg":"HS256","typ":"JWT","kid":"{kid}"}}"#);
+    let mut claims: Map<String, Value> = fields
+        .iter()
+        .map(|(key, value)| ((*key).to_string(), Value::from(value.as_str())))
+        .collect();
+    claims.insert("iat".to_string(), Value::from(iat));
+    claims.insert("exp".to_string(), Value::from(iat.saturating_add(JWT_LIFETIME_SECS)));
+
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header),
+        URL_SAFE_NO_PAD.encode(Value::Object(claims).to_string())
+    );
+    let (_, tag) = signer.mac(iat, signing_input.as_bytes())?;
+    Some(format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(tag)))
+}
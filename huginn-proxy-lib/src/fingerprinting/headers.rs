@@ -1,3 +1,10 @@
+use std::collections::HashSet;
+
+use http::HeaderName;
+
+use crate::config::FingerprintHeadersConfig;
+use crate::error::{ProxyError, Result};
+
 /// HTTP header names for fingerprint injection
 ///
 /// These constants define the header names used to inject fingerprints
@@ -58,6 +65,14 @@ pub mod names {
     /// Only injected when the `ebpf-tcp` feature is enabled and fingerprinting is configured.
     pub const TCP_SYN: &str = "x-tcp-p0f";
 
+    /// Header name for the operating system guessed from the TCP SYN signature
+    ///
+    /// This header contains the best match of the SYN in the bundled p0f-style database,
+    /// with the match confidence.
+    /// Example: `"Linux 2.2.x-3.x; confidence=1.00"`
+    /// Only injected on routes with `tcp_os = true` when a SYN was captured and matched.
+    pub const TCP_OS: &str = "x-huginn-net-os";
+
     /// All proxy-authoritative fingerprint headers.
     ///
     /// Written exclusively by the proxy from data observed on the connection
@@ -72,6 +87,7 @@ pub mod names {
         TLS_JA4_S1R,
         HTTP2_AKAMAI,
         TCP_SYN,
+        TCP_OS,
     ];
 
     /// Header injected toward the backend listing which fingerprint signatures the
@@ -82,6 +98,107 @@ pub mod names {
     /// This header is itself proxy-authoritative: it is stripped from client input so
     /// the client cannot forge or suppress the detection signal.
     pub const SPOOFING_DETECTED: &str = "x-fingerprint-spoofing-detected";
+
+    /// Header injected toward the backend carrying the tag returned by a registered
+    /// [`FingerprintClassifier`](crate::fingerprinting::FingerprintClassifier)
+    /// ([`Verdict::Tag`](crate::fingerprinting::Verdict::Tag)).
+    ///
+    /// Proxy-authoritative like [`SPOOFING_DETECTED`]: always stripped from client input.
+    pub const CLASSIFICATION: &str = "x-huginn-classification";
+
+    /// Header injected toward the backend carrying the HMAC signature of the fingerprint
+    /// headers, when `[fingerprint.signing]` is enabled.
+    ///
+    /// Proxy-authoritative like [`SPOOFING_DETECTED`]: always stripped from client input.
+    pub const SIGNATURE: &str = "x-huginn-net-signature";
+
+    /// Header injected toward the backend carrying every fingerprint signal at once, as a
+    /// Structured Field dictionary or a JWT, on routes with `fingerprint_format` set to
+    /// `structured` or `jwt`.
+    ///
+    /// Proxy-authoritative like [`SPOOFING_DETECTED`]: always stripped from client input.
+    pub const CONTEXT: &str = "x-huginn-context";
+}
+
+/// Effective names of the proxy-authoritative fingerprint headers
+///
+/// Built once at startup from `[fingerprint.headers]`; [`Default`] yields the built-in
+/// [`names`]. Each configured name is paired with its built-in name, which stays the stable
+/// identifier used for metric labels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FingerprintHeaderNames {
+    fingerprints: Vec<(&'static str, HeaderName)>,
+    spoofing_detected: HeaderName,
+}
+
+impl Default for FingerprintHeaderNames {
+    fn default() -> Self {
+        Self {
+            fingerprints: names::FINGERPRINTS
+                .iter()
+                .map(|&name| (name, HeaderName::from_static(name)))
+                .collect(),
+            spoofing_detected: HeaderName::from_static(names::SPOOFING_DETECTED),
+        }
+    }
+}
+
+impl FingerprintHeaderNames {
+    /// Resolve and validate the configured names: each must be a valid header name, unique,
+    /// and distinct from the `x-forwarded-*` and client certificate headers.
+    pub fn from_config(config: &FingerprintHeadersConfig) -> Result<Self> {
+        let parse = |default: &'static str| {
+            let name = config.name_for(default);
+            HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+                ProxyError::Config(format!("Invalid fingerprint header name '{name}': {e}"))
+            })
+        };
+        let fingerprints = names::FINGERPRINTS
+            .iter()
+            .map(|&default| Ok((default, parse(default)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let spoofing_detected = parse(names::SPOOFING_DETECTED)?;
+
+        let reserved = forwarded::ALL
+            .iter()
+            .chain(client_cert::ALL)
+            .chain(tls_info::ALL)
+            .chain([&names::CLASSIFICATION, &names::SIGNATURE, &names::CONTEXT]);
+        let mut seen: HashSet<&str> = reserved.copied().collect();
+        for name in fingerprints
+            .iter()
+            .map(|(_, n)| n)
+            .chain([&spoofing_detected])
+        {
+            if !seen.insert(name.as_str()) {
+                return Err(ProxyError::Config(format!(
+                    "Fingerprint header name '{name}' is used more than once or collides with a \
+                     header the proxy already injects"
+                )));
+            }
+        }
+        Ok(Self { fingerprints, spoofing_detected })
+    }
+
+    /// Every fingerprint header as `(built-in name, effective name)`.
+    pub fn fingerprints(&self) -> impl Iterator<Item = (&'static str, &HeaderName)> {
+        self.fingerprints
+            .iter()
+            .map(|(default, name)| (*default, name))
+    }
+
+    /// Effective name of the fingerprint header whose built-in name is `default`.
+    pub fn get(&self, default: &str) -> Option<&HeaderName> {
+        self.fingerprints
+            .iter()
+            .find(|(d, _)| *d == default)
+            .map(|(_, name)| name)
+    }
+
+    /// Effective name of [`names::SPOOFING_DETECTED`].
+    pub fn spoofing_detected(&self) -> &HeaderName {
+        &self.spoofing_detected
+    }
 }
 
 /// HTTP header names for X-Forwarded-* headers
@@ -109,4 +226,59 @@ pub mod forwarded {
     ///
     /// Contains the protocol used by the client ("http" or "https").
     pub const PROTO: &str = "x-forwarded-proto";
+
+    /// All X-Forwarded-* headers written by the proxy.
+    pub const ALL: &[&str] = &[FOR, HOST, PORT, PROTO];
+}
+
+/// HTTP header names for the verified mTLS client certificate
+///
+/// Injected when `tls.client_cert.forward_headers` is enabled and the client presented a
+/// certificate accepted by `tls.client_auth`. Client-supplied copies are always stripped.
+pub mod client_cert {
+    /// Header name for the client certificate subject
+    ///
+    /// RFC 4514 distinguished name, e.g. `CN=client,O=Acme`.
+    pub const SUBJECT: &str = "x-huginn-client-cert-subject";
+
+    /// Header name for the client certificate fingerprint
+    ///
+    /// SHA-256 of the DER-encoded certificate, lowercase hex.
+    pub const SHA256: &str = "x-huginn-client-cert-sha256";
+
+    /// All proxy-authoritative client certificate headers.
+    pub const ALL: &[&str] = &[SUBJECT, SHA256];
+}
+
+/// HTTP header names for the negotiated TLS parameters
+///
+/// Injected when `fingerprint.tls_info_headers` is enabled on a TLS listener. Client-supplied
+/// copies are always stripped.
+pub mod tls_info {
+    /// Header name for the negotiated ALPN protocol, e.g. `h2`
+    pub const ALPN: &str = "x-tls-alpn";
+
+    /// Header name for the SNI the client sent
+    pub const SNI: &str = "x-tls-sni";
+
+    /// Header name for the negotiated protocol version, e.g. `TLSv1_3`
+    pub const VERSION: &str = "x-tls-version";
+
+    /// Header name for the negotiated cipher suite, e.g. `TLS13_AES_128_GCM_SHA256`
+    pub const CIPHER: &str = "x-tls-cipher";
+
+    /// Header name for TLS 1.3 0-RTT early data
+    ///
+    /// `true` when the ClientHello carried the `early_data` extension, `false` otherwise. The
+    /// proxy never accepts early data; the header only reports that the client attempted it.
+    pub const EARLY_DATA: &str = "x-tls-early-data";
+
+    /// Header name for Encrypted Client Hello
+    ///
+    /// `true` when the ClientHello carried the `encrypted_client_hello` extension (real or
+    /// GREASE), `false` otherwise.
+    pub const ECH: &str = "x-huginn-net-ech";
+
+    /// All proxy-authoritative TLS parameter headers.
+    pub const ALL: &[&str] = &[ALPN, SNI, VERSION, CIPHER, EARLY_DATA, ECH];
 }
@@ -1,5 +1,6 @@
+use bytes::BytesMut;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
@@ -11,15 +12,28 @@ use tokio::sync::watch;
 use tokio::time::Instant;
 use tracing::{debug, warn};
 
+/// Client connection preface every HTTP/2 connection starts with (RFC 9113 §3.4).
+const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
 /// CapturingStream captures all data read from the inner stream
 /// while passing it through. Processes fingerprint inline for optimal performance
+///
+/// The capture buffer grows with the bytes actually read (the connection preface, SETTINGS and
+/// first HEADERS frames are usually well under 1 KiB) rather than being reserved up front at
+/// `max_capture`, and it is released as soon as the fingerprint is extracted, the cap is hit or
+/// the connection turns out not to start with the HTTP/2 preface (HTTP/1.x on a plaintext
+/// listener). From then on reads pass straight through.
+///
+/// Extraction runs entirely inside `poll_read`: no task or channel is created per connection
+/// besides the `watch` sender the result is published on.
 pub struct CapturingStream<S> {
     inner: S,
     fingerprint_tx: watch::Sender<Option<AkamaiFingerprint>>,
     fingerprint_extracted: Arc<AtomicBool>,
     max_capture: usize,
-    captured_len: Arc<AtomicUsize>,
-    buffer: Vec<u8>, // Inline buffer for fast processing
+    /// Set once capturing stops (fingerprint extracted or `max_capture` reached).
+    done: bool,
+    buffer: BytesMut, // Inline buffer for fast processing
     parser: Http2Parser<'static>,
     parsed_offset: usize,
     // Track whether each required frame type has been seen across multiple reads.
@@ -45,8 +59,8 @@ impl<S> CapturingStream<S> {
                 fingerprint_tx,
                 fingerprint_extracted: fingerprint_extracted.clone(),
                 max_capture,
-                captured_len: Arc::new(AtomicUsize::new(0)),
-                buffer: Vec::with_capacity(max_capture),
+                done: max_capture == 0,
+                buffer: BytesMut::new(),
                 parser: Http2Parser::new(),
                 parsed_offset: 0,
                 seen_settings_frame: false,
@@ -57,6 +71,112 @@ impl<S> CapturingStream<S> {
             fingerprint_extracted,
         )
     }
+
+    /// Stop capturing and free the buffer.
+    fn finish(&mut self) {
+        self.done = true;
+        self.buffer = BytesMut::new();
+    }
+
+    /// Append newly read bytes (up to `max_capture`) and try to extract the fingerprint.
+    fn capture(&mut self, read_data: &[u8]) {
+        let remaining = self.max_capture.saturating_sub(self.buffer.len());
+        let to_capture = read_data.len().min(remaining);
+        self.buffer.extend_from_slice(&read_data[..to_capture]);
+
+        // HTTP/1.x on a plaintext listener (or a TLS one without `h2` in ALPN): nothing to extract.
+        let checked = self.buffer.len().min(HTTP2_PREFACE.len());
+        if self.buffer[..checked] != HTTP2_PREFACE[..checked] {
+            debug!("CapturingStream: not an HTTP/2 connection");
+            self.finish();
+            return;
+        }
+
+        self.process_frames();
+
+        if !self.done && self.buffer.len() >= self.max_capture {
+            debug!("CapturingStream: max_capture reached without a fingerprint");
+            self.finish();
+        }
+    }
+
+    fn process_frames(&mut self) {
+        // Use parse_frames_skip_preface to handle preface automatically
+        let frame_data = &self.buffer[self.parsed_offset..];
+        const MIN_FRAME_LEN: usize = 9; // HTTP/2 frame header: 3 length + 1 type + 1 flags + 4 stream id
+
+        if frame_data.len() < MIN_FRAME_LEN {
+            return;
+        }
+        // Use parse_frames_skip_preface to get both frames and bytes consumed (handles preface automatically)
+        let Ok((frames, bytes_consumed)) = self.parser.parse_frames_skip_preface(frame_data) else {
+            // Parsing error, continue (might need more data)
+            // Note: HTTP/1.1 detection happens in handle_proxy_request
+            // when req.version() != HTTP_2
+            return;
+        };
+        if frames.is_empty() {
+            return;
+        }
+        // Update parsed_offset based on actual bytes consumed (includes preface if present)
+        self.parsed_offset = self.parsed_offset.saturating_add(bytes_consumed);
+
+        // Track frame types across reads: SETTINGS and HEADERS
+        // may arrive in different TCP segments. Update flags from
+        // the frames seen in this read, then when both are present
+        // re-parse the full buffer to get all frames together.
+        self.seen_settings_frame |= frames
+            .iter()
+            .any(|f| f.frame_type == Http2FrameType::Settings && f.stream_id == 0);
+        self.seen_headers_frame |= frames
+            .iter()
+            .any(|f| f.frame_type == Http2FrameType::Headers && f.stream_id > 0);
+
+        if !(self.seen_settings_frame && self.seen_headers_frame) {
+            return;
+        }
+        let Some(all_frames) = self
+            .parser
+            .parse_frames_skip_preface(&self.buffer)
+            .ok()
+            .map(|(f, _)| f)
+        else {
+            return;
+        };
+
+        match extract_akamai_fingerprint(&all_frames) {
+            Ok(fingerprint) => {
+                debug!(
+                    "CapturingStream: extracted fingerprint inline: {}",
+                    fingerprint.fingerprint
+                );
+                let _ = self.fingerprint_tx.send(Some(fingerprint));
+                self.fingerprint_extracted.store(true, Ordering::Relaxed);
+
+                if let Some(start) = self.extraction_start.take() {
+                    let duration = start.elapsed().as_secs_f64();
+                    self.metrics.http2_fingerprints_extracted_total.add(1, &[]);
+                    self.metrics
+                        .http2_fingerprint_extraction_duration_seconds
+                        .record(duration, &[]);
+                }
+                self.finish();
+            }
+            Err(HuginnNetHttpError::MalformedPseudoHeaders(reason)) => {
+                warn!(
+                    "CapturingStream: malformed HEADERS frame, possible spoofed traffic: {}",
+                    reason
+                );
+                self.metrics.record_http2_fingerprint_failure();
+            }
+            Err(HuginnNetHttpError::NoSettingsFrame) => {
+                debug!("CapturingStream: SETTINGS frame not yet received, will retry on next read");
+            }
+            Err(e) => {
+                debug!("CapturingStream: fingerprint extraction error: {e}");
+            }
+        }
+    }
 }
 
 impl<S: AsyncRead + Unpin> AsyncRead for CapturingStream<S> {
@@ -67,102 +187,9 @@ impl<S: AsyncRead + Unpin> AsyncRead for CapturingStream<S> {
     ) -> Poll<std::io::Result<()>> {
         let before = buf.filled().len();
         let result = Pin::new(&mut self.inner).poll_read(cx, buf);
-        let after = buf.filled().len();
-
-        if after > before && !self.fingerprint_extracted.load(Ordering::Relaxed) {
-            let read_data = &buf.filled()[before..after];
-            let current_len = self.captured_len.load(Ordering::Relaxed);
-
-            if current_len < self.max_capture {
-                let remaining = self.max_capture.saturating_sub(current_len);
-                let to_capture = read_data.len().min(remaining);
-                let data_to_process = &read_data[..to_capture];
-
-                self.captured_len
-                    .store(current_len.saturating_add(to_capture), Ordering::Relaxed);
-
-                self.buffer.extend_from_slice(data_to_process);
-
-                // Use parse_frames_skip_preface to handle preface automatically
-                let frame_data = &self.buffer[self.parsed_offset..];
-                const MIN_FRAME_LEN: usize = 9; // HTTP/2 frame header: 3 length + 1 type + 1 flags + 4 stream id
-
-                if frame_data.len() >= MIN_FRAME_LEN {
-                    // Use parse_frames_skip_preface to get both frames and bytes consumed (handles preface automatically)
-                    match self.parser.parse_frames_skip_preface(frame_data) {
-                        Ok((frames, bytes_consumed)) => {
-                            if !frames.is_empty() {
-                                // Update parsed_offset based on actual bytes consumed (includes preface if present)
-                                self.parsed_offset =
-                                    self.parsed_offset.saturating_add(bytes_consumed);
-
-                                // Track frame types across reads: SETTINGS and HEADERS
-                                // may arrive in different TCP segments. Update flags from
-                                // the frames seen in this read, then when both are present
-                                // re-parse the full buffer to get all frames together.
-                                self.seen_settings_frame |= frames.iter().any(|f| {
-                                    f.frame_type == Http2FrameType::Settings && f.stream_id == 0
-                                });
-                                self.seen_headers_frame |= frames.iter().any(|f| {
-                                    f.frame_type == Http2FrameType::Headers && f.stream_id > 0
-                                });
-
-                                let all_frames_opt = (self.seen_settings_frame
-                                    && self.seen_headers_frame)
-                                    .then(|| {
-                                        self.parser
-                                            .parse_frames_skip_preface(&self.buffer)
-                                            .ok()
-                                            .map(|(f, _)| f)
-                                    })
-                                    .flatten();
-
-                                if let Some(frames) = all_frames_opt.as_deref() {
-                                    match extract_akamai_fingerprint(frames) {
-                                        Ok(fingerprint) => {
-                                            debug!(
-                                                "CapturingStream: extracted fingerprint inline: {}",
-                                                fingerprint.fingerprint
-                                            );
-                                            let _ = self.fingerprint_tx.send(Some(fingerprint));
-                                            self.fingerprint_extracted
-                                                .store(true, Ordering::Relaxed);
-
-                                            if let Some(start) = self.extraction_start.take() {
-                                                let duration = start.elapsed().as_secs_f64();
-                                                self.metrics
-                                                    .http2_fingerprints_extracted_total
-                                                    .add(1, &[]);
-                                                self.metrics
-                                                    .http2_fingerprint_extraction_duration_seconds
-                                                    .record(duration, &[]);
-                                            }
-                                        }
-                                        Err(HuginnNetHttpError::MalformedPseudoHeaders(reason)) => {
-                                            warn!(
-                                                "CapturingStream: malformed HEADERS frame, possible spoofed traffic: {}",
-                                                reason
-                                            );
-                                            self.metrics.record_http2_fingerprint_failure();
-                                        }
-                                        Err(HuginnNetHttpError::NoSettingsFrame) => {
-                                            debug!("CapturingStream: SETTINGS frame not yet received, will retry on next read");
-                                        }
-                                        Err(e) => {
-                                            debug!("CapturingStream: fingerprint extraction error: {e}");
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        Err(_) => {
-                            // Parsing error, continue (might need more data)
-                            // Note: HTTP/1.1 detection happens in handle_proxy_request
-                            // when req.version() != HTTP_2
-                        }
-                    }
-                }
-            }
+
+        if !self.done && buf.filled().len() > before {
+            self.capture(&buf.filled()[before..]);
         }
 
         result
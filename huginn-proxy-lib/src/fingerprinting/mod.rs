@@ -1,12 +1,24 @@
+pub mod classifier;
+pub mod context;
 pub mod headers;
 pub mod http2_extractor;
 pub mod ja4;
+pub mod os_match;
+pub mod signing;
+pub mod syn_capture;
 pub mod tls_extractor;
 pub mod types;
 
-pub use headers::{forwarded, names};
+pub use classifier::{FingerprintClassifier, FingerprintSet, SharedClassifier, Verdict};
+pub use context::pack_context;
+pub use headers::{client_cert, forwarded, names, tls_info, FingerprintHeaderNames};
 pub use http2_extractor::CapturingStream;
 pub use huginn_net_tcp::TcpObservation;
 pub use ja4::Ja4Fingerprints;
-pub use tls_extractor::read_client_hello;
+pub use os_match::{match_os, OsGuess};
+pub use signing::{HeaderSigner, SignedRequest};
+pub use tls_extractor::{
+    client_hello_has_sni, client_hello_offers_early_data, client_hello_offers_ech,
+    read_client_hello,
+};
 pub use types::SynResult;
@@ -0,0 +1,64 @@
+//! Operating system guess from the TCP SYN signature (`tcp_os` on a route).
+//!
+//! The observed SYN is matched against the p0f-style signatures bundled with `huginn-net-db`.
+//! The database is parsed on first use and shared by every connection afterwards; if it fails
+//! to load, the failure is logged once and no guess is ever made.
+
+use std::fmt;
+use std::sync::{Arc, LazyLock};
+
+use huginn_net_db::{SharedTcpSignatureMatcher, TcpDatabase};
+use huginn_net_tcp::matcher_api::TcpMatcher;
+use huginn_net_tcp::{OsKind, TcpObservation};
+use tracing::warn;
+
+static MATCHER: LazyLock<Option<SharedTcpSignatureMatcher>> =
+    LazyLock::new(|| match TcpDatabase::load_default() {
+        Ok(database) => Some(SharedTcpSignatureMatcher::new(Arc::new(database))),
+        Err(e) => {
+            warn!("TCP signature database failed to load, no OS guesses will be made: {e}");
+            None
+        }
+    });
+
+/// Best database match for a client SYN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OsGuess {
+    /// Operating system name, e.g. `Linux`
+    pub name: String,
+    /// Version range of the matched signature, e.g. `2.2.x-3.x`
+    pub variant: Option<String>,
+    /// Whether the matched signature is a generic (catch-all) one
+    pub generic: bool,
+    /// Similarity between the SYN and the signature, in `[0.0, 1.0]`
+    pub confidence: f32,
+}
+
+/// Value of the `x-huginn-net-os` header: the OS label, then `; confidence=` with two decimals,
+/// then `; generic` for catch-all signatures, e.g. `Linux 2.2.x-3.x; confidence=1.00`.
+impl fmt::Display for OsGuess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(variant) = &self.variant {
+            write!(f, " {variant}")?;
+        }
+        write!(f, "; confidence={:.2}", self.confidence)?;
+        if self.generic {
+            f.write_str("; generic")?;
+        }
+        Ok(())
+    }
+}
+
+/// Match `syn` against the bundled signature database.
+///
+/// `None` when no signature is close enough or the database could not be loaded.
+pub fn match_os(syn: &TcpObservation) -> Option<OsGuess> {
+    let found = MATCHER.as_ref()?.match_tcp_request(syn)?;
+    Some(OsGuess {
+        name: found.os.name,
+        variant: found.os.variant,
+        generic: matches!(found.os.kind, OsKind::Generic),
+        confidence: found.quality.clamp(0.0, 1.0),
+    })
+}
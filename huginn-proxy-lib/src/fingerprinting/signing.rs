@@ -0,0 +1,175 @@
+//! HMAC signature of the fingerprint headers sent upstream (`[fingerprint.signing]`).
+//!
+//! The signature header reads `v1;kid=<key id>;ts=<unix seconds>;h=<name>,<name>;sig=<hex>`,
+//! where `sig` is the lowercase hex HMAC-SHA256, under the key `kid`, of
+//!
+//! ```text
+//! v1\n<kid>\n<ts>\n<method>\n<authority>\n<path>\n<name>:<value>\n<name>:<value>\n...
+//! ```
+//!
+//! where `<authority>` is the `x-forwarded-host` the backend receives (empty without one) and
+//! `<path>` the path and query it receives, followed by one line per header listed in `h`, in that
+//! order. `h` lists the covered headers the request carries: the fingerprint headers (under their
+//! configured names), the spoofing-detection, classification and packed context headers and the
+//! `x-tls-*` parameter headers. Binding the request line keeps a signed header set from being
+//! replayed on another request, and listing the names inside the signed message means a header
+//! cannot be dropped or added unnoticed.
+//!
+//! A backend verifies by recomputing the HMAC over the listed headers, comparing in constant
+//! time, and rejecting a `ts` too far from its own clock.
+
+use std::fmt;
+
+use aws_lc_rs::hmac;
+use http::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::config::SigningConfig;
+use crate::error::Result;
+use crate::fingerprinting::headers::{names, tls_info, FingerprintHeaderNames};
+use crate::utils::hex;
+use crate::utils::time::unix_now;
+
+/// Version prefix of the signature header and the signed message.
+const VERSION: &str = "v1";
+
+/// The request a signature is bound to, as the backend receives it.
+#[derive(Debug, Clone, Copy)]
+pub struct SignedRequest<'a> {
+    pub method: &'a str,
+    /// Value of `x-forwarded-host`: the host the proxy routed on, empty without one.
+    pub authority: &'a str,
+    /// Path and query, after the route's `replace_path` or `rewrite`.
+    pub path: &'a str,
+}
+
+struct SigningKey {
+    id: String,
+    key: hmac::Key,
+    not_before: u64,
+}
+
+/// Signs the fingerprint headers of forwarded requests with the active key.
+pub struct HeaderSigner {
+    /// Sorted by `not_before`, oldest first.
+    keys: Vec<SigningKey>,
+}
+
+impl fmt::Debug for HeaderSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids: Vec<&str> = self.keys.iter().map(|k| k.id.as_str()).collect();
+        f.debug_struct("HeaderSigner")
+            .field("keys", &ids)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HeaderSigner {
+    /// `keys` are `(id, secret, not_before)`.
+    pub fn new(keys: impl IntoIterator<Item = (String, Vec<u8>, u64)>) -> Self {
+        let mut keys: Vec<SigningKey> = keys
+            .into_iter()
+            .map(|(id, secret, not_before)| SigningKey {
+                id,
+                key: hmac::Key::new(hmac::HMAC_SHA256, &secret),
+                not_before,
+            })
+            .collect();
+        keys.sort_by_key(|k| k.not_before);
+        Self { keys }
+    }
+
+    /// Build from config, reading `secret_file`s; `None` when signing is disabled.
+    pub fn from_config(config: &SigningConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let keys = config
+            .keys
+            .iter()
+            .map(|key| Ok((key.id.clone(), key.load_secret()?, key.not_before.unwrap_or(0))))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(Self::new(keys)))
+    }
+
+    /// Id of the key signing at `unix_secs`: the latest whose `not_before` has passed.
+    pub fn active_key_id(&self, unix_secs: u64) -> Option<&str> {
+        self.active_key(unix_secs).map(|k| k.id.as_str())
+    }
+
+    /// Replace the signature header of `headers` with one over `request` and the covered headers
+    /// they carry.
+    pub fn sign(
+        &self,
+        request: SignedRequest<'_>,
+        headers: &mut HeaderMap,
+        header_names: &FingerprintHeaderNames,
+    ) {
+        self.sign_at(request, headers, header_names, unix_now());
+    }
+
+    /// [`sign`](Self::sign) with the timestamp `unix_secs`.
+    pub fn sign_at(
+        &self,
+        request: SignedRequest<'_>,
+        headers: &mut HeaderMap,
+        header_names: &FingerprintHeaderNames,
+        unix_secs: u64,
+    ) {
+        headers.remove(names::SIGNATURE);
+        let Some(key) = self.active_key(unix_secs) else {
+            return;
+        };
+
+        let covered: Vec<HeaderName> = header_names
+            .fingerprints()
+            .map(|(_, name)| name.clone())
+            .chain([
+                header_names.spoofing_detected().clone(),
+                HeaderName::from_static(names::CLASSIFICATION),
+                HeaderName::from_static(names::CONTEXT),
+            ])
+            .chain(
+                tls_info::ALL
+                    .iter()
+                    .map(|&name| HeaderName::from_static(name)),
+            )
+            .collect();
+
+        let SignedRequest { method, authority, path } = request;
+        let mut message =
+            format!("{VERSION}\n{}\n{unix_secs}\n{method}\n{authority}\n{path}\n", key.id)
+                .into_bytes();
+        let mut listed: Vec<&str> = Vec::new();
+        for name in &covered {
+            let Some(value) = headers.get(name) else {
+                continue;
+            };
+            message.extend_from_slice(name.as_str().as_bytes());
+            message.push(b':');
+            message.extend_from_slice(value.as_bytes());
+            message.push(b'\n');
+            listed.push(name.as_str());
+        }
+        let tag = hmac::sign(&key.key, &message);
+
+        let signature = format!(
+            "{VERSION};kid={};ts={unix_secs};h={};sig={}",
+            key.id,
+            listed.join(","),
+            hex::encode(tag.as_ref())
+        );
+        if let Ok(value) = HeaderValue::from_str(&signature) {
+            headers.insert(names::SIGNATURE, value);
+        }
+    }
+
+    /// HMAC-SHA256 of `message` under the key active at `unix_secs`, with that key's id.
+    pub fn mac(&self, unix_secs: u64, message: &[u8]) -> Option<(&str, hmac::Tag)> {
+        let key = self.active_key(unix_secs)?;
+        Some((key.id.as_str(), hmac::sign(&key.key, message)))
+    }
+
+    fn active_key(&self, unix_secs: u64) -> Option<&SigningKey> {
+        self.keys.iter().rev().find(|k| k.not_before <= unix_secs)
+    }
+}
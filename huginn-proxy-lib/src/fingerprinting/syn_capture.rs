@@ -0,0 +1,373 @@
+//! TCP SYN capture without eBPF (`fingerprint.tcp_capture = "raw_socket"`).
+//!
+//! A raw IPv4 socket receives a copy of every inbound TCP segment of the host. A capture thread
+//! keeps the SYNs addressed to the proxy's listeners, keyed by client address, until the
+//! accepted connection looks them up. This needs `CAP_NET_RAW` instead of the eBPF agent, at the
+//! cost of copying every inbound TCP packet to userspace. The signature is built the same way as
+//! from a BPF map entry. IPv6 raw sockets do not deliver the IP header (no hop limit), so IPv6
+//! clients always miss.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use huginn_net_tcp::syn_options::{parse_options_raw, ParsedTcpOptions};
+use huginn_net_tcp::tcp::{IpVersion, PayloadSize, Quirk, TcpOption};
+use huginn_net_tcp::{ttl, window_size, TcpObservation};
+use socket2::{Domain, Protocol, Socket, Type};
+use tracing::{info, warn};
+
+use crate::config::{ListenAddr, StaticConfig, TcpCapture};
+use crate::fingerprinting::SynResult;
+use crate::proxy::server::SynProbe;
+
+/// Most SYNs waiting for their connection to be accepted; matches the eBPF map default.
+pub const MAX_PENDING_SYNS: usize = 8192;
+/// How long a captured SYN waits for its connection. Accepts follow the handshake within
+/// milliseconds; older entries belong to scans or connections that were never completed.
+pub const PENDING_SYN_TTL: Duration = Duration::from_secs(30);
+/// Enough for the largest IPv4 header (60 bytes) plus the largest TCP header (60 bytes); the
+/// rest of a segment is discarded by the kernel.
+const CAPTURE_LEN: usize = 120;
+/// How often the capture thread checks whether the proxy still holds the capture.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+const IPPROTO_TCP: u8 = 6;
+const IP_RF: u16 = 0x8000;
+const IP_DF: u16 = 0x4000;
+const IP_OFFSET: u16 = 0x1fff;
+const IP_TOS_ECN: u8 = 0x03;
+const TCP_URG: u8 = 0x20;
+const TCP_ACK: u8 = 0x10;
+const TCP_PSH: u8 = 0x08;
+const TCP_SYN: u8 = 0x02;
+const TCP_ECE: u8 = 0x40;
+const TCP_CWR: u8 = 0x80;
+
+/// A SYN read off the wire.
+#[derive(Debug, Clone)]
+pub struct CapturedSyn {
+    pub source: SocketAddrV4,
+    pub destination: SocketAddrV4,
+    /// [`SynResult::Hit`], or [`SynResult::Malformed`] when the TCP options do not parse.
+    pub result: SynResult,
+}
+
+/// Parse an IPv4 packet as received on a raw socket (IP header first). `None` unless it is a
+/// TCP SYN without ACK.
+pub fn parse_ipv4_syn(packet: &[u8]) -> Option<CapturedSyn> {
+    let version_ihl = byte(packet, 0)?;
+    if version_ihl & 0xf0 != 0x40 || byte(packet, 9)? != IPPROTO_TCP {
+        return None;
+    }
+    let ip_len = usize::from(version_ihl & 0x0f).saturating_mul(4);
+    let tos = byte(packet, 1)?;
+    let ip_id = u16_at(packet, 4)?;
+    let frag_off = u16_at(packet, 6)?;
+    // Only the first fragment carries the TCP header.
+    if ip_len < 20 || frag_off & IP_OFFSET != 0 {
+        return None;
+    }
+    let ip_ttl = byte(packet, 8)?;
+    let src_ip = Ipv4Addr::from(u32_at(packet, 12)?);
+    let dst_ip = Ipv4Addr::from(u32_at(packet, 16)?);
+
+    let tcp = packet.get(ip_len..)?;
+    let src_port = u16_at(tcp, 0)?;
+    let dst_port = u16_at(tcp, 2)?;
+    let seq = u32_at(tcp, 4)?;
+    let ack_seq = u32_at(tcp, 8)?;
+    let data_offset = byte(tcp, 12)?;
+    let flags = byte(tcp, 13)?;
+    let window = u16_at(tcp, 14)?;
+    let urg_ptr = u16_at(tcp, 18)?;
+    if flags & TCP_SYN == 0 || flags & TCP_ACK != 0 {
+        return None;
+    }
+
+    let tcp_len = usize::from(data_offset.checked_shr(4).unwrap_or(0)).saturating_mul(4);
+    let options = tcp
+        .get(20..tcp_len.max(20).min(tcp.len()))
+        .unwrap_or_default();
+
+    let mut quirks = Vec::new();
+    let df = frag_off & IP_DF != 0;
+    if df {
+        quirks.push(Quirk::Df);
+    }
+    if df && ip_id != 0 {
+        quirks.push(Quirk::NonZeroID);
+    }
+    if !df && ip_id == 0 {
+        quirks.push(Quirk::ZeroID);
+    }
+    if frag_off & IP_RF != 0 {
+        quirks.push(Quirk::MustBeZero);
+    }
+    if flags & (TCP_ECE | TCP_CWR) != 0 || tos & IP_TOS_ECN != 0 {
+        quirks.push(Quirk::Ecn);
+    }
+    if seq == 0 {
+        quirks.push(Quirk::SeqNumZero);
+    }
+    if ack_seq != 0 {
+        quirks.push(Quirk::AckNumNonZero);
+    }
+    if urg_ptr != 0 {
+        quirks.push(Quirk::NonZeroURG);
+    }
+    if flags & TCP_URG != 0 {
+        quirks.push(Quirk::Urg);
+    }
+    if flags & TCP_PSH != 0 {
+        quirks.push(Quirk::Push);
+    }
+
+    let ip_olen = u8::try_from(ip_len.saturating_sub(20)).ok()?;
+    let result = match observation(options, ip_ttl, ip_olen, window, quirks) {
+        Some(observation) => SynResult::Hit(observation),
+        None => SynResult::Malformed,
+    };
+    Some(CapturedSyn {
+        source: SocketAddrV4::new(src_ip, src_port),
+        destination: SocketAddrV4::new(dst_ip, dst_port),
+        result,
+    })
+}
+
+/// Build the signature from the SYN fields, as `huginn_ebpf::parse_syn_v4` does for a map entry.
+fn observation(
+    options: &[u8],
+    ip_ttl: u8,
+    ip_olen: u8,
+    window: u16,
+    mut quirks: Vec<Quirk>,
+) -> Option<TcpObservation> {
+    let parsed: ParsedTcpOptions = parse_options_raw(options);
+    if parsed.malformed {
+        return None;
+    }
+    let ip_plus_tcp = 20_u16.saturating_add(u16::from(ip_olen)).saturating_add(20);
+    let wsize = window_size::detect_win_multiplicator(
+        window,
+        parsed.mss.unwrap_or(0),
+        ip_plus_tcp,
+        parsed.olayout.contains(&TcpOption::TS),
+        &IpVersion::V4,
+    );
+    if parsed.wscale.is_some_and(|ws| ws > 14) {
+        quirks.push(Quirk::ExcessiveWindowScaling);
+    }
+    let (ts_val, ts_ecr, trailing_nonzero) = scan_option_quirks(options);
+    if ts_val == Some(0) {
+        quirks.push(Quirk::OwnTimestampZero);
+    }
+    if ts_ecr.is_some_and(|v| v != 0) {
+        quirks.push(Quirk::PeerTimestampNonZero);
+    }
+    if trailing_nonzero {
+        quirks.push(Quirk::TrailinigNonZero);
+    }
+    Some(TcpObservation {
+        version: IpVersion::V4,
+        ittl: ttl::calculate_ttl(ip_ttl),
+        olen: ip_olen,
+        mss: parsed.mss,
+        wsize,
+        wscale: parsed.wscale,
+        olayout: parsed.olayout,
+        quirks,
+        pclass: PayloadSize::Zero,
+    })
+}
+
+/// Timestamp values and whether non-zero bytes follow the end-of-options marker.
+fn scan_option_quirks(opts: &[u8]) -> (Option<u32>, Option<u32>, bool) {
+    let mut rest = opts;
+    let mut ts_val = None;
+    let mut ts_ecr = None;
+    while let Some((&kind, tail)) = rest.split_first() {
+        match kind {
+            0 => return (ts_val, ts_ecr, tail.iter().any(|&b| b != 0)),
+            1 => rest = tail,
+            _ => {
+                let Some((&len, data)) = tail.split_first() else {
+                    break;
+                };
+                let data_len = usize::from(len).saturating_sub(2);
+                let Some(option_data) = data.get(..data_len) else {
+                    break;
+                };
+                if kind == 8 && len == 10 {
+                    ts_val = u32_at(option_data, 0);
+                    ts_ecr = u32_at(option_data, 4);
+                }
+                rest = data.get(data_len..).unwrap_or_default();
+            }
+        }
+    }
+    (ts_val, ts_ecr, false)
+}
+
+fn byte(buf: &[u8], pos: usize) -> Option<u8> {
+    buf.get(pos).copied()
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> Option<u16> {
+    let bytes = buf.get(pos..pos.checked_add(2)?)?;
+    Some(u16::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> Option<u32> {
+    let bytes = buf.get(pos..pos.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+struct PendingSyn {
+    result: SynResult,
+    captured: Instant,
+}
+
+/// Captured SYNs waiting for their connection, keyed by client address.
+pub struct SynCapture {
+    pending: Mutex<HashMap<SocketAddrV4, PendingSyn>>,
+    max_entries: usize,
+}
+
+impl SynCapture {
+    pub fn new(max_entries: usize) -> Self {
+        Self { pending: Mutex::new(HashMap::new()), max_entries }
+    }
+
+    /// Keep `syn` until its connection is accepted. A retransmitted SYN replaces the earlier
+    /// one. A full capture drops its expired entries first; if it is still full `syn` is
+    /// dropped.
+    pub fn record(&self, syn: CapturedSyn) {
+        let now = Instant::now();
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.len() >= self.max_entries && !pending.contains_key(&syn.source) {
+            pending.retain(|_, p| now.duration_since(p.captured) <= PENDING_SYN_TTL);
+            if pending.len() >= self.max_entries {
+                return;
+            }
+        }
+        pending.insert(syn.source, PendingSyn { result: syn.result, captured: now });
+    }
+
+    /// Take the SYN of the connection from `peer`. IPv6 and expired entries are a miss.
+    pub fn take(&self, peer: SocketAddr) -> SynResult {
+        // Dual-stack listeners report IPv4 clients as IPv4-mapped IPv6 addresses.
+        let IpAddr::V4(ip) = peer.ip().to_canonical() else {
+            return SynResult::Miss;
+        };
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        match pending.remove(&SocketAddrV4::new(ip, peer.port())) {
+            Some(syn) if syn.captured.elapsed() <= PENDING_SYN_TTL => syn.result,
+            _ => SynResult::Miss,
+        }
+    }
+
+    /// Number of SYNs waiting, expired ones included until they are pruned.
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Open the raw socket and start capturing the SYNs sent to `listeners`. An unspecified
+/// listener IP accepts any destination IP on its port. The capture thread exits once the
+/// returned capture is dropped.
+pub fn start_raw_socket_capture(listeners: Vec<SocketAddr>) -> std::io::Result<Arc<SynCapture>> {
+    let socket = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::TCP))?;
+    socket.set_read_timeout(Some(READ_TIMEOUT))?;
+    let capture = Arc::new(SynCapture::new(MAX_PENDING_SYNS));
+    let weak = Arc::downgrade(&capture);
+    std::thread::Builder::new()
+        .name("huginn-syn-capture".to_string())
+        .spawn(move || capture_loop(&socket, &listeners, &weak))?;
+    Ok(capture)
+}
+
+fn capture_loop(socket: &Socket, listeners: &[SocketAddr], capture: &Weak<SynCapture>) {
+    let mut reader = socket;
+    let mut buf = [0u8; CAPTURE_LEN];
+    loop {
+        let result = reader.read(&mut buf);
+        let Some(capture) = capture.upgrade() else {
+            return;
+        };
+        match result {
+            Ok(len) => {
+                let Some(syn) = buf.get(..len).and_then(parse_ipv4_syn) else {
+                    continue;
+                };
+                let destination = IpAddr::V4(*syn.destination.ip());
+                let wanted = listeners.iter().any(|l| {
+                    l.port() == syn.destination.port()
+                        && (l.ip().is_unspecified() || l.ip() == destination)
+                });
+                if wanted {
+                    capture.record(syn);
+                }
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock
+                        | std::io::ErrorKind::TimedOut
+                        | std::io::ErrorKind::Interrupted
+                ) => {}
+            Err(e) => {
+                warn!(error = %e, "raw socket TCP SYN capture stopped");
+                return;
+            }
+        }
+    }
+}
+
+/// The SYN probe for `fingerprint.tcp_capture = "raw_socket"`, or `None` when TCP fingerprinting
+/// is off, another source is configured, or the raw socket cannot be opened (e.g. without
+/// `CAP_NET_RAW`), in which case every connection misses.
+pub fn raw_socket_probe(static_cfg: &StaticConfig) -> Option<SynProbe> {
+    let fingerprint = &static_cfg.fingerprint;
+    if !fingerprint.tcp_enabled || fingerprint.tcp_capture != TcpCapture::RawSocket {
+        return None;
+    }
+    let listeners: Vec<SocketAddr> = static_cfg
+        .listen
+        .addrs
+        .iter()
+        .copied()
+        .chain(
+            static_cfg
+                .listen
+                .listeners
+                .iter()
+                .filter_map(|l| match l.addr {
+                    ListenAddr::Tcp(addr) if l.fingerprint.resolve(fingerprint).tcp_enabled => {
+                        Some(addr)
+                    }
+                    _ => None,
+                }),
+        )
+        .collect();
+    match start_raw_socket_capture(listeners) {
+        Ok(capture) => {
+            info!("TCP SYN fingerprinting capturing on a raw socket (IPv4 only)");
+            Some(Arc::new(move |peer| capture.take(peer)))
+        }
+        Err(e) => {
+            warn!(
+                error = %e,
+                "cannot open raw socket for TCP SYN capture (needs CAP_NET_RAW); \
+                 TCP fingerprints disabled"
+            );
+            None
+        }
+    }
+}
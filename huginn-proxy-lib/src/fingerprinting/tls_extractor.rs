@@ -1,32 +1,59 @@
+use bytes::{Bytes, BytesMut};
 use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::time::Instant;
 
 use super::ja4::Ja4Fingerprints;
 use crate::telemetry::Metrics;
 
+/// TLS record header: content type (1), legacy version (2), length (2).
+const TLS_RECORD_HEADER_LEN: usize = 5;
+
+/// Initial buffer size; covers most ClientHellos, including ones with post-quantum key shares.
+const INITIAL_CAPACITY: usize = 2048;
+
+/// Largest ClientHello buffered before giving up on a complete record.
+const MAX_CLIENT_HELLO: usize = 64 * 1024;
+
+/// Handshake message header: type (1), length (3).
+const HANDSHAKE_HEADER_LEN: usize = 4;
+
+/// `server_name` extension (RFC 6066 section 3).
+const EXTENSION_SERVER_NAME: u16 = 0x0000;
+
+/// `early_data` extension (RFC 8446 section 4.2.10).
+const EXTENSION_EARLY_DATA: u16 = 0x002a;
+
+/// `encrypted_client_hello` extension (draft-ietf-tls-esni), real or GREASE.
+const EXTENSION_ECH: u16 = 0xfe0d;
+
 /// Reads TLS ClientHello from the stream and extracts JA4 fingerprint
-pub async fn read_client_hello(
-    stream: &mut tokio::net::TcpStream,
+///
+/// The bytes read are returned as [`Bytes`] so the TLS acceptor can replay them (see
+/// `PrefixedStream`) without another copy. The buffer only grows past its initial size when the
+/// record header announces a larger ClientHello.
+pub async fn read_client_hello<S: AsyncRead + Unpin>(
+    stream: &mut S,
     metrics: Arc<Metrics>,
-) -> std::io::Result<(Vec<u8>, Option<Ja4Fingerprints>)> {
+) -> std::io::Result<(Bytes, Option<Ja4Fingerprints>)> {
     use huginn_net_tls::tls_process::parse_tls_client_hello;
-    use tokio::io::AsyncReadExt;
 
     let start = Instant::now();
-    let mut buf = Vec::with_capacity(8192);
+    let mut buf = BytesMut::with_capacity(INITIAL_CAPACITY);
     loop {
-        if buf.len() >= 5 {
+        if buf.len() >= TLS_RECORD_HEADER_LEN {
             let len = u16::from_be_bytes([buf[3], buf[4]]) as usize;
-            let needed = len.saturating_add(5);
+            let needed = len.saturating_add(TLS_RECORD_HEADER_LEN);
             if buf.len() >= needed {
                 break;
             }
+            buf.reserve(needed.saturating_sub(buf.len()));
         }
         let read = stream.read_buf(&mut buf).await?;
         if read == 0 {
             break;
         }
-        if buf.len() > 64 * 1024 {
+        if buf.len() > MAX_CLIENT_HELLO {
             break;
         }
     }
@@ -51,5 +78,77 @@ pub async fn read_client_hello(
         }
     };
 
-    Ok((buf, fingerprints))
+    Ok((buf.freeze(), fingerprints))
+}
+
+/// Whether the ClientHello in `buf` (as returned by [`read_client_hello`]) carries the TLS 1.3
+/// `early_data` extension, i.e. the client attempted to send 0-RTT data.
+///
+/// Returns `false` for anything that is not a well-formed ClientHello.
+pub fn client_hello_offers_early_data(buf: &[u8]) -> bool {
+    client_hello_has_extension(buf, EXTENSION_EARLY_DATA)
+}
+
+/// Whether the ClientHello in `buf` carries the `encrypted_client_hello` extension.
+///
+/// Clients that support ECH send the extension on every connection, with a GREASE payload when
+/// they have no ECH config for the server, so this tells ECH-capable clients apart rather than
+/// connections whose inner ClientHello is actually hidden. The proxy has no ECH keys and always
+/// completes the handshake with the outer ClientHello.
+pub fn client_hello_offers_ech(buf: &[u8]) -> bool {
+    client_hello_has_extension(buf, EXTENSION_ECH)
+}
+
+/// Whether the ClientHello in `buf` carries a `server_name` extension (the outer SNI under ECH).
+pub fn client_hello_has_sni(buf: &[u8]) -> bool {
+    client_hello_has_extension(buf, EXTENSION_SERVER_NAME)
+}
+
+fn client_hello_has_extension(buf: &[u8], wanted: u16) -> bool {
+    client_hello_extensions(buf).is_some_and(|mut extensions| {
+        while let Some((ext_type, rest)) = split_u16(extensions) {
+            let Some((_, rest)) = split_vec_u16(rest) else {
+                return false;
+            };
+            if ext_type == wanted {
+                return true;
+            }
+            extensions = rest;
+        }
+        false
+    })
+}
+
+/// Extensions block of the ClientHello in `buf`, without its length prefix.
+fn client_hello_extensions(buf: &[u8]) -> Option<&[u8]> {
+    // Handshake record (22) whose first message is a ClientHello (1).
+    let record = buf.get(TLS_RECORD_HEADER_LEN..)?;
+    if buf.first() != Some(&22) || record.first() != Some(&1) {
+        return None;
+    }
+    let hello = record.get(HANDSHAKE_HEADER_LEN..)?;
+    // legacy_version (2), random (32), session id, cipher suites, compression methods.
+    let rest = hello.get(34..)?;
+    let (_, rest) = split_vec_u8(rest)?;
+    let (_, rest) = split_vec_u16(rest)?;
+    let (_, rest) = split_vec_u8(rest)?;
+    let (extensions, _) = split_vec_u16(rest)?;
+    Some(extensions)
+}
+
+fn split_u16(buf: &[u8]) -> Option<(u16, &[u8])> {
+    let (value, rest) = buf.split_first_chunk::<2>()?;
+    Some((u16::from_be_bytes(*value), rest))
+}
+
+/// Split a vector with a one-byte length prefix off the front of `buf`.
+fn split_vec_u8(buf: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (&len, rest) = buf.split_first()?;
+    rest.split_at_checked(usize::from(len))
+}
+
+/// Split a vector with a two-byte length prefix off the front of `buf`.
+fn split_vec_u16(buf: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (len, rest) = split_u16(buf)?;
+    rest.split_at_checked(usize::from(len))
 }
@@ -11,7 +11,8 @@ pub mod tls;
 pub(crate) mod utils;
 
 pub use backend::{
-    BackendSelector, HealthCheckSupervisor, HealthRegistry, RoundRobin, UpstreamHealth,
+    BackendDiscovery, BackendSelector, HealthCheckSupervisor, HealthRegistry, RoundRobin,
+    UpstreamHealth,
 };
 pub use config::{
     load_from_path, Backend, BackendHttpVersion, Config, DynamicConfig, Route, StaticConfig,
@@ -19,11 +20,17 @@ pub use config::{
 };
 pub use error::{ProxyError, Result};
 pub use fingerprinting::SynResult;
-pub use fingerprinting::{forwarded, names, read_client_hello, CapturingStream, Ja4Fingerprints};
+pub use fingerprinting::{
+    client_cert, forwarded, names, read_client_hello, CapturingStream, FingerprintClassifier,
+    FingerprintSet, Ja4Fingerprints, SharedClassifier, Verdict,
+};
+pub use proxy::builder::{ProxyBuilder, ProxyHandle};
 pub use proxy::reload::{
-    initial_client_pool, initial_rate_limiter, try_reload, SharedClientPool, SharedRateLimiter,
+    initial_client_pool, initial_rate_limiter, try_reload, ConfigGeneration, SharedClientPool,
+    SharedRateLimiter,
 };
+pub use proxy::runtime::RuntimeHandles;
 pub use proxy::server::{SynProbe, WatchOptions};
-pub use proxy::shutdown::{shutdown_channel, ShutdownSender, ShutdownWatch};
+pub use proxy::shutdown::{shutdown_channel, DrainTrigger, ShutdownSender, ShutdownWatch};
 pub use proxy::{forwarding, run};
 pub use telemetry::{Metrics, Readiness};
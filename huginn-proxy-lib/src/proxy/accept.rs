@@ -1,23 +1,49 @@
 use crate::backend::health_check::HealthRegistry;
-use crate::backend::{BackendSelector, UpstreamGateway};
-use crate::config::{FingerprintConfig, KeepAliveConfig};
-use crate::fingerprinting::{SynResult, TcpObservation};
-use crate::proxy::connection::{ConnectionError, ConnectionManager};
+use crate::backend::{BackendSelector, CircuitBreakerRegistry, UpstreamGateway};
+use crate::config::{
+    ClientCertConfig, DynamicConfig, FingerprintConfig, KeepAliveConfig, ListenAddr,
+    PassthroughConfig,
+};
+use crate::fingerprinting::{
+    FingerprintHeaderNames, HeaderSigner, SharedClassifier, SynResult, TcpObservation,
+};
+use crate::proxy::bandwidth::BandwidthLimits;
+use crate::proxy::cache::ResponseCache;
+use crate::proxy::client_tracking::ClientTracker;
+use crate::proxy::concurrency::ConcurrencyLimits;
+use crate::proxy::connect::TunnelContext;
+use crate::proxy::connection::{
+    ConnectionContext, ConnectionError, ConnectionManager, ConnectionRegistry, CountedStream,
+    TrackedConnection,
+};
+use crate::proxy::listener::{AcceptedStream, BoundListener};
+use crate::proxy::load_shedding::LoadShedder;
+use crate::proxy::middleware::Middleware;
 use crate::proxy::peer_resolution::{resolve_peer, ResolvedProxyProtocol};
-use crate::proxy::reload::{SharedClientPool, SharedDynamicConfig, SharedRateLimiter};
+use crate::proxy::reload::{
+    ConfigGeneration, SharedClientPool, SharedDynamicConfig, SharedRateLimiter,
+};
+use crate::proxy::request_id::RequestIdGenerator;
 use crate::proxy::security_context::SecurityContext;
 use crate::proxy::shutdown::ShutdownWatch;
+use crate::proxy::synthetic_response::SyntheticSwitches;
 use crate::proxy::transport::{
-    handle_plain_connection, handle_tls_connection, PlainConnectionConfig, TlsConnectionConfig,
+    handle_passthrough_connection, handle_plain_connection, handle_tls_connection,
+    PassthroughConnectionConfig, PlainConnectionConfig, RawTcpStream, TlsConnectionConfig,
+};
+use crate::security::{BotVerifier, Tarpit};
+use crate::telemetry::{
+    AccessLogContext, AccessLogger, AnomalyDetector, EventStream, FingerprintStats,
+    HandshakeCapture, LogLevels, Metrics, RequestTracer,
 };
-use crate::telemetry::Metrics;
 use crate::tls::setup::SharedTlsAcceptor;
 use hyper_util::rt::TokioExecutor;
 use hyper_util::server::conn::auto::Builder as ConnBuilder;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::net::TcpListener;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::watch;
 use tokio::time::{Duration, Instant};
 use tracing::warn;
 
@@ -31,8 +57,6 @@ pub type SynProbe = Arc<dyn Fn(SocketAddr) -> SynResult + Send + Sync>;
 pub struct AcceptContext {
     pub dynamic_cfg: SharedDynamicConfig,
     pub rate_limiter: SharedRateLimiter,
-    pub tls_acceptor: Option<SharedTlsAcceptor>,
-    pub fingerprint_config: FingerprintConfig,
     pub keep_alive_config: KeepAliveConfig,
     pub metrics: Arc<Metrics>,
     pub client_pool: SharedClientPool,
@@ -40,14 +64,78 @@ pub struct AcceptContext {
     pub syn_probe: Option<SynProbe>,
     pub health_registry: Arc<HealthRegistry>,
     pub backend_selector: Arc<BackendSelector>,
+    pub circuit_breakers: Arc<CircuitBreakerRegistry>,
     pub tls_handshake_timeout: Duration,
     pub connection_handling_timeout: Duration,
+    /// `timeout.upstream_connect_ms`, for backends of TLS passthrough listeners.
+    pub upstream_connect_timeout: Option<Duration>,
     pub proxy_protocol: ResolvedProxyProtocol,
+    /// `tls.client_cert` policy, set only when `tls.client_auth` is not `disabled`.
+    pub client_cert_policy: Option<ClientCertConfig>,
+    /// `tls.options.reject_ech_without_sni`.
+    pub reject_ech_without_sni: bool,
+    /// Fingerprint header names resolved from `[fingerprint.headers]`.
+    pub fingerprint_headers: Arc<FingerprintHeaderNames>,
+    /// `[fingerprint.signing]` signer, when enabled.
+    pub header_signer: Option<Arc<HeaderSigner>>,
+    /// Classifier registered through `run()`.
+    pub classifier: Option<SharedClassifier>,
+    /// Middleware registered through `run()`.
+    pub middleware: Option<Middleware>,
+    /// Response cache shared by every connection, sized by `[cache]`.
+    pub response_cache: Arc<ResponseCache>,
+    /// `max_in_flight` semaphores shared by every connection.
+    pub concurrency_limits: Arc<ConcurrencyLimits>,
+    /// `bandwidth` token buckets shared by every connection.
+    pub bandwidth_limits: Arc<BandwidthLimits>,
+    /// `[load_shedding]` overload detector shared by every connection, when enabled.
+    pub load_shedder: Option<Arc<LoadShedder>>,
+    /// `[request_id]` generator shared by every connection, when enabled.
+    pub request_ids: Option<Arc<RequestIdGenerator>>,
+    /// `[client_tracking]` cookie issuer and records shared by every connection, when enabled.
+    pub client_tracker: Option<Arc<ClientTracker>>,
+    /// Bumped by config reloads; open connections drain when it moves.
+    pub config_generation: ConfigGeneration,
+    /// Open connections listed by the admin API; disabled unless the admin API is served.
+    pub connections: ConnectionRegistry,
+    /// `[access_log]` writer; disabled unless the access log is enabled.
+    pub access_log: AccessLogger,
+    /// `[handshake_capture]` writer; disabled unless the capture is enabled.
+    pub handshake_capture: HandshakeCapture,
+    /// Runtime log levels, changed through the admin API.
+    pub log_levels: LogLevels,
+    /// `[telemetry.tracing]` request spans; disabled unless tracing is enabled.
+    pub tracer: RequestTracer,
+    /// `[telemetry.fingerprint_stats]` counters; disabled unless enabled.
+    pub fingerprint_stats: FingerprintStats,
+    /// `[telemetry.anomaly_detection]` detector; disabled unless enabled.
+    pub anomaly_detector: AnomalyDetector,
+    /// `[event_stream]` exporter; disabled unless the event stream is enabled.
+    pub event_stream: EventStream,
+    /// Runtime switches of the routes' `synthetic` responses, shared with the admin API.
+    pub synthetic: SyntheticSwitches,
+    /// `[security.bot_verification]` outcome cache shared by every connection.
+    pub bot_verifier: Arc<BotVerifier>,
+    /// `[security.tarpit]` connections waiting, shared by every listener.
+    pub tarpit: Arc<Tarpit>,
+}
+
+/// Settings of one listen address: `listen.addrs` entries share the global `[tls]` and
+/// `[fingerprint]`, while each `[[listen.listeners]]` entry resolves its own.
+pub struct ListenerContext {
+    pub addr: ListenAddr,
+    /// `None` serves plain HTTP.
+    pub tls_acceptor: Option<SharedTlsAcceptor>,
+    pub fingerprint_config: FingerprintConfig,
+    /// Relays TLS by SNI instead of serving HTTP; `tls_acceptor` is `None` then.
+    pub passthrough: Option<Arc<PassthroughConfig>>,
+    /// Tenant whose domains are the only ones served.
+    pub tenant: Option<Arc<str>>,
 }
 
 pub async fn accept_loop(
-    addr: SocketAddr,
-    listener: TcpListener,
+    endpoint: Arc<ListenerContext>,
+    listener: BoundListener,
     shutdown_signal: Arc<AtomicUsize>,
     mut shutdown_rx: ShutdownWatch,
     connection_manager: Arc<ConnectionManager>,
@@ -63,7 +151,7 @@ pub async fn accept_loop(
             accepted = listener.accept() => match accepted {
                 Ok(pair) => pair,
                 Err(e) => {
-                    warn!(error = %e, ?addr, "accept error");
+                    warn!(error = %e, addr = %endpoint.addr, "accept error");
                     continue;
                 }
             },
@@ -77,21 +165,44 @@ pub async fn accept_loop(
                 drop(stream);
                 break;
             }
-            Err(ConnectionError::LimitExceeded { .. }) => {
+            Err(
+                ConnectionError::LimitExceeded { .. } | ConnectionError::PerIpLimitExceeded { .. },
+            ) => {
                 drop(stream);
                 continue;
             }
         };
 
         let ctx_task = Arc::clone(&ctx);
+        let endpoint_task = Arc::clone(&endpoint);
+        let connection_manager = Arc::clone(&connection_manager);
         tokio::spawn(async move {
             let _guard = guard;
-            let mut stream = stream;
 
             // Load the latest config snapshot inside the task: the accept loop never blocks on
             // config access, and each connection sees the config current at the time it runs.
+            // Subscribing first means a reload racing with the load drains this connection.
+            let config_changed = ctx_task.config_generation.subscribe();
             let dynamic = ctx_task.dynamic_cfg.load();
 
+            let mut stream = match stream {
+                AcceptedStream::Tcp(stream) => stream,
+                // Unix sockets carry no client IP: no PROXY header to read, no SYN to look up.
+                AcceptedStream::Unix(stream) => {
+                    serve_connection(
+                        &ctx_task,
+                        &endpoint_task,
+                        &dynamic,
+                        stream,
+                        socket_peer,
+                        None,
+                        config_changed,
+                    )
+                    .await;
+                    return;
+                }
+            };
+
             // Resolve the effective client peer. Runs inside the spawned task so that slow or
             // malicious peers cannot delay acceptance of subsequent connections: a peer
             // deliberately withholding the PROXY header up to the timeout would
@@ -113,9 +224,19 @@ pub async fn accept_loop(
                 Some(p) => p,
                 None => return, // dropped (require + untrusted, bad header, or timeout)
             };
+            // Counted against the resolved client, so clients behind an L4 proxy are not pooled
+            // under its address. Over the limit, the connection is dropped unanswered.
+            let Ok(_ip_guard) = connection_manager.try_accept_ip(peer.ip(), &ctx_task.metrics)
+            else {
+                return;
+            };
 
             let syn_start = Instant::now();
-            let syn_result = ctx_task.syn_probe.as_ref().map(|probe| probe(peer));
+            let syn_result = ctx_task
+                .syn_probe
+                .as_ref()
+                .filter(|_| endpoint_task.fingerprint_config.tcp_enabled)
+                .map(|probe| probe(peer));
             let syn_duration = syn_start.elapsed().as_secs_f64();
             let syn_fingerprint: Option<TcpObservation> = syn_result.as_ref().and_then(|r| {
                 ctx_task
@@ -124,65 +245,186 @@ pub async fn accept_loop(
                 r.observation().cloned()
             });
 
-            let rate_mgr = (**ctx_task.rate_limiter.load()).clone();
-            let security = SecurityContext::new(
-                dynamic.security.headers.clone(),
-                dynamic.security.ip_filter.clone(),
-                dynamic.security.rate_limit.clone(),
-                rate_mgr,
-                dynamic.headers.clone(),
-                dynamic.security.trusted_proxies.clone(),
-            );
-            let backends = Arc::clone(&dynamic.backends);
-            let domains = Arc::clone(&dynamic.domains);
-            let preserve_host = dynamic.preserve_host;
-            let upstream = UpstreamGateway::new(
-                ctx_task.health_registry.clone(),
-                ctx_task.backend_selector.clone(),
-            );
-
-            if let Some(ref tls_acceptor) = ctx_task.tls_acceptor {
-                handle_tls_connection(
-                    stream,
-                    peer,
-                    TlsConnectionConfig {
-                        tls_acceptor: tls_acceptor.clone(),
-                        fingerprint_config: ctx_task.fingerprint_config.clone(),
-                        domains: domains.clone(),
-                        backends,
-                        keep_alive: ctx_task.keep_alive_config.clone(),
-                        security: security.clone(),
-                        metrics: ctx_task.metrics.clone(),
-                        builder: ctx_task.builder.clone(),
-                        preserve_host,
-                        tls_handshake_timeout: ctx_task.tls_handshake_timeout,
-                        connection_handling_timeout: ctx_task.connection_handling_timeout,
-                        client_pool: ctx_task.client_pool.load_full(),
-                        syn_fingerprint: syn_fingerprint.clone(),
-                        upstream: upstream.clone(),
-                    },
-                )
-                .await;
-            } else {
-                handle_plain_connection(
-                    stream,
-                    peer,
-                    PlainConnectionConfig {
-                        domains,
-                        backends,
-                        keep_alive: ctx_task.keep_alive_config.clone(),
-                        security,
-                        metrics: ctx_task.metrics.clone(),
-                        builder: ctx_task.builder.clone(),
-                        preserve_host,
-                        connection_handling_timeout: ctx_task.connection_handling_timeout,
-                        client_pool: ctx_task.client_pool.load_full(),
-                        syn_fingerprint,
-                        upstream,
-                    },
-                )
-                .await;
-            }
+            serve_connection(
+                &ctx_task,
+                &endpoint_task,
+                &dynamic,
+                stream,
+                peer,
+                syn_fingerprint,
+                config_changed,
+            )
+            .await;
+        });
+    }
+}
+
+/// Serve HTTP (or HTTPS, per the listener) on an accepted connection whose client is `peer`.
+/// The connection drains once `config_changed` sees a new reload generation.
+async fn serve_connection<S>(
+    ctx: &AcceptContext,
+    endpoint: &ListenerContext,
+    dynamic: &DynamicConfig,
+    stream: S,
+    peer: SocketAddr,
+    syn_fingerprint: Option<TcpObservation>,
+    config_changed: watch::Receiver<u64>,
+) where
+    S: AsyncRead + AsyncWrite + RawTcpStream + Unpin + Send + 'static,
+{
+    // Shared by every request of the connection; the transports add TLS details and fingerprints.
+    let mut connection = ConnectionContext::new(peer);
+    if let Some(syn) = &syn_fingerprint {
+        connection = connection.with_tcp_syn(syn.to_string());
+    }
+    let connection = Arc::new(connection);
+    // Listed by `/admin/connections` until the connection ends; `None` unless the admin API is on.
+    let tls = endpoint.tls_acceptor.is_some() || endpoint.passthrough.is_some();
+    let tracked = ctx.connections.register(&connection, &endpoint.addr, tls);
+    let stream = CountedStream::new(stream, tracked.as_ref().map(TrackedConnection::traffic));
+    let handshake_capture = ctx
+        .handshake_capture
+        .clone()
+        .with_server(match &endpoint.addr {
+            ListenAddr::Tcp(addr) => Some(*addr),
+            ListenAddr::Unix(_) => None,
         });
+
+    if let Some(passthrough) = &endpoint.passthrough {
+        handle_passthrough_connection(
+            stream,
+            peer,
+            PassthroughConnectionConfig {
+                passthrough: Arc::clone(passthrough),
+                tls_fingerprint: endpoint.fingerprint_config.tls_enabled,
+                metrics: ctx.metrics.clone(),
+                tls_handshake_timeout: ctx.tls_handshake_timeout,
+                connect_timeout: ctx.upstream_connect_timeout,
+                connection_handling_timeout: ctx.connection_handling_timeout,
+                syn_fingerprint,
+                connection,
+                tracked,
+                handshake_capture,
+            },
+        )
+        .await;
+        return;
+    }
+
+    let rate_mgr = (**ctx.rate_limiter.load()).clone();
+    let security = SecurityContext::new(
+        dynamic.security.headers.clone(),
+        dynamic.security.ip_filter.clone(),
+        dynamic.security.rate_limit.clone(),
+        rate_mgr,
+        dynamic.headers.clone(),
+        dynamic.security.trusted_proxies.clone(),
+        dynamic.security.fingerprint_filter.clone(),
+    )
+    .with_classifier(ctx.classifier.clone())
+    .with_middleware(ctx.middleware.clone())
+    .with_compression(dynamic.compression.clone())
+    .with_redirect(dynamic.redirect.clone())
+    .with_response_cache(Some(Arc::clone(&ctx.response_cache)))
+    .with_concurrency_limits(Arc::clone(&ctx.concurrency_limits))
+    .with_bandwidth_limits(Arc::clone(&ctx.bandwidth_limits))
+    .with_load_shedder(ctx.load_shedder.clone())
+    .with_request_ids(ctx.request_ids.clone())
+    .with_client_tracker(ctx.client_tracker.clone())
+    .with_synthetic_switches(ctx.synthetic.clone())
+    .with_waf(dynamic.security.waf.clone())
+    .with_bot_verification(dynamic.security.bot_verification.clone(), Arc::clone(&ctx.bot_verifier))
+    .with_strict_http(dynamic.security.strict_http.clone())
+    .with_challenge(dynamic.security.challenge.clone())
+    .with_tarpit(dynamic.security.tarpit.clone(), Arc::clone(&ctx.tarpit))
+    .with_tunnels(TunnelContext {
+        connect_timeout: ctx.upstream_connect_timeout,
+        client_hello_timeout: ctx.tls_handshake_timeout,
+        handshake_capture: handshake_capture.clone(),
+        fingerprint_stats: ctx.fingerprint_stats.clone(),
+    })
+    .with_fingerprint_diagnostics(
+        endpoint.fingerprint_config.tls_info_headers,
+        endpoint
+            .fingerprint_config
+            .whoami
+            .enabled_path()
+            .map(Arc::from),
+    )
+    .with_header_signer(ctx.header_signer.clone())
+    .with_default_host(dynamic.default_host.as_deref())
+    .with_listener_tenant(endpoint.tenant.clone());
+    let backends = Arc::clone(&dynamic.backends);
+    let domains = Arc::clone(&dynamic.domains);
+    let preserve_host = dynamic.preserve_host;
+    let upstream = UpstreamGateway::new(
+        ctx.health_registry.clone(),
+        ctx.backend_selector.clone(),
+        ctx.circuit_breakers.clone(),
+    );
+
+    let access_log = AccessLogContext::new(ctx.access_log.clone(), Arc::clone(&connection))
+        .with_tracer(ctx.tracer.clone())
+        .with_fingerprint_stats(ctx.fingerprint_stats.clone())
+        .with_anomaly_detector(ctx.anomaly_detector.clone())
+        .with_event_stream(ctx.event_stream.clone());
+
+    if let Some(ref tls_acceptor) = endpoint.tls_acceptor {
+        handle_tls_connection(
+            stream,
+            peer,
+            TlsConnectionConfig {
+                tls_acceptor: tls_acceptor.clone(),
+                fingerprint_config: endpoint.fingerprint_config.clone(),
+                domains: domains.clone(),
+                backends,
+                keep_alive: ctx.keep_alive_config.clone(),
+                security: security.clone(),
+                metrics: ctx.metrics.clone(),
+                builder: ctx.builder.clone(),
+                preserve_host,
+                tls_handshake_timeout: ctx.tls_handshake_timeout,
+                connection_handling_timeout: ctx.connection_handling_timeout,
+                client_pool: ctx.client_pool.load_full(),
+                syn_fingerprint: syn_fingerprint.clone(),
+                upstream: upstream.clone(),
+                client_cert_policy: ctx.client_cert_policy.clone(),
+                reject_ech_without_sni: ctx.reject_ech_without_sni,
+                fingerprint_headers: Arc::clone(&ctx.fingerprint_headers),
+                config_changed,
+                connection,
+                tracked,
+                access_log,
+                handshake_capture,
+                log_levels: ctx.log_levels.clone(),
+            },
+        )
+        .await;
+    } else {
+        handle_plain_connection(
+            stream,
+            peer,
+            PlainConnectionConfig {
+                fingerprint_config: endpoint.fingerprint_config.clone(),
+                domains,
+                backends,
+                keep_alive: ctx.keep_alive_config.clone(),
+                security,
+                metrics: ctx.metrics.clone(),
+                builder: ctx.builder.clone(),
+                preserve_host,
+                connection_handling_timeout: ctx.connection_handling_timeout,
+                client_pool: ctx.client_pool.load_full(),
+                syn_fingerprint,
+                upstream,
+                fingerprint_headers: Arc::clone(&ctx.fingerprint_headers),
+                config_changed,
+                connection,
+                tracked,
+                access_log,
+                log_levels: ctx.log_levels.clone(),
+            },
+        )
+        .await;
     }
 }
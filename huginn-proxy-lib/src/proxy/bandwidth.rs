@@ -0,0 +1,264 @@
+//! `bandwidth` shaping of routes and backends.
+//!
+//! Each shaped route and backend owns token buckets, created on first use: one per direction,
+//! and with `limit_by = "ip"` or `"ja4"` one per client as well. A body wrapped in
+//! [`ShapedBody`] takes each data frame's bytes out of its buckets and holds the frame back until
+//! they have refilled enough to pay for it, so a slow bucket slows the transfer down instead of
+//! failing it. Nothing is buffered beyond the one frame being held.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use hyper::body::{Body, Frame, SizeHint};
+use tokio::time::{Instant, Sleep};
+
+use crate::config::BandwidthConfig;
+
+/// Client buckets kept before idle ones are dropped.
+const PRUNE_AT: usize = 1024;
+
+/// Direction of the bodies a bucket meters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Request bodies, client to backend
+    Upload,
+    /// Response bodies, backend to client
+    Download,
+}
+
+/// Buckets of the routes and backends that set `bandwidth`, shared by every connection.
+#[derive(Debug, Default)]
+pub struct BandwidthLimits {
+    buckets: RwLock<Buckets>,
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    map: HashMap<BucketKey, Arc<TokenBucket>>,
+    prune_at: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BucketKey {
+    /// `route <domain><prefix>` or `backend <address>`
+    scope: String,
+    /// Client key under `limit_by = "ip"` / `"ja4"`; `None` for a shared bucket.
+    client: Option<String>,
+    direction: Direction,
+}
+
+impl BandwidthLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bucket of route `prefix` on `domain` for `client` (`None` when shared).
+    pub fn route_bucket(
+        &self,
+        domain: &str,
+        prefix: &str,
+        client: Option<&str>,
+        direction: Direction,
+        config: &BandwidthConfig,
+    ) -> Arc<TokenBucket> {
+        self.bucket(format!("route {domain}{prefix}"), client, direction, config)
+    }
+
+    /// Bucket of the backend at `address` for `client` (`None` when shared).
+    pub fn backend_bucket(
+        &self,
+        address: &str,
+        client: Option<&str>,
+        direction: Direction,
+        config: &BandwidthConfig,
+    ) -> Arc<TokenBucket> {
+        self.bucket(format!("backend {address}"), client, direction, config)
+    }
+
+    /// A bucket whose rate or burst no longer matches (hot reload) is replaced with a full one;
+    /// bodies still holding the old one keep draining it.
+    fn bucket(
+        &self,
+        scope: String,
+        client: Option<&str>,
+        direction: Direction,
+        config: &BandwidthConfig,
+    ) -> Arc<TokenBucket> {
+        let key = BucketKey { scope, client: client.map(str::to_string), direction };
+        let current = self
+            .buckets
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .map
+            .get(&key)
+            .filter(|bucket| bucket.matches(config))
+            .map(Arc::clone);
+        if let Some(bucket) = current {
+            return bucket;
+        }
+        let mut buckets = self.buckets.write().unwrap_or_else(|e| e.into_inner());
+        if buckets.map.len() >= buckets.prune_at.max(PRUNE_AT) {
+            // Idle buckets are full again and unused, so dropping them changes nothing.
+            buckets
+                .map
+                .retain(|_, bucket| Arc::strong_count(bucket) > 1 || !bucket.is_idle());
+            buckets.prune_at = buckets.map.len().saturating_mul(2);
+        }
+        let bucket = buckets
+            .map
+            .entry(key)
+            .or_insert_with(|| Arc::new(TokenBucket::new(config)));
+        if !bucket.matches(config) {
+            *bucket = Arc::new(TokenBucket::new(config));
+        }
+        Arc::clone(bucket)
+    }
+}
+
+/// Token bucket kept as the time it will be full again (GCRA): taking `n` bytes pushes that time
+/// `n / bytes_per_sec` further, and a caller waits for whatever lies beyond one burst from now.
+#[derive(Debug)]
+pub struct TokenBucket {
+    bytes_per_sec: u64,
+    burst: u64,
+    full_at: Mutex<Instant>,
+}
+
+impl TokenBucket {
+    pub fn new(config: &BandwidthConfig) -> Self {
+        Self {
+            bytes_per_sec: config.bytes_per_sec,
+            burst: config.burst(),
+            full_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Take `bytes` out of the bucket, going into debt if needed. Returns how long the caller
+    /// must wait before sending them: zero while the bucket covers them.
+    pub fn reserve(&self, bytes: u64) -> Duration {
+        let now = Instant::now();
+        let mut full_at = self.full_at.lock().unwrap_or_else(|e| e.into_inner());
+        let start = (*full_at).max(now);
+        *full_at = start.checked_add(self.refill_time(bytes)).unwrap_or(start);
+        full_at
+            .saturating_duration_since(now)
+            .saturating_sub(self.refill_time(self.burst))
+    }
+
+    fn refill_time(&self, bytes: u64) -> Duration {
+        let nanos = u128::from(bytes)
+            .saturating_mul(1_000_000_000)
+            .checked_div(u128::from(self.bytes_per_sec))
+            .unwrap_or(0);
+        Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+    }
+
+    fn is_idle(&self) -> bool {
+        *self.full_at.lock().unwrap_or_else(|e| e.into_inner()) <= Instant::now()
+    }
+
+    fn matches(&self, config: &BandwidthConfig) -> bool {
+        self.bytes_per_sec == config.bytes_per_sec && self.burst == config.burst()
+    }
+}
+
+/// Buckets a request's bodies are shaped by, one list per direction.
+#[derive(Debug, Clone, Default)]
+pub struct Shaping {
+    upload: Vec<Arc<TokenBucket>>,
+    download: Vec<Arc<TokenBucket>>,
+}
+
+impl Shaping {
+    /// Add the upload and download buckets that `bucket` returns for each direction.
+    pub fn with(mut self, bucket: impl Fn(Direction) -> Arc<TokenBucket>) -> Self {
+        self.upload.push(bucket(Direction::Upload));
+        self.download.push(bucket(Direction::Download));
+        self
+    }
+
+    pub fn buckets(&self, direction: Direction) -> Vec<Arc<TokenBucket>> {
+        match direction {
+            Direction::Upload => self.upload.clone(),
+            Direction::Download => self.download.clone(),
+        }
+    }
+}
+
+/// Body wrapper that delays each data frame until all of its buckets can pay for it.
+pub struct ShapedBody<B> {
+    inner: B,
+    buckets: Vec<Arc<TokenBucket>>,
+    held: Option<(Frame<Bytes>, Pin<Box<Sleep>>)>,
+    on_throttled: Option<Box<dyn Fn() + Send + Sync>>,
+}
+
+impl<B> ShapedBody<B> {
+    /// With no `buckets` frames pass through unchanged.
+    pub fn new(inner: B, buckets: Vec<Arc<TokenBucket>>) -> Self {
+        Self { inner, buckets, held: None, on_throttled: None }
+    }
+
+    /// Run `f` each time a frame is held back (typically to record a metric).
+    pub fn on_throttled(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_throttled = Some(Box::new(f));
+        self
+    }
+}
+
+impl<B> Body for ShapedBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if this.held.is_none() {
+            let frame = match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => frame,
+                other => return Poll::Ready(other),
+            };
+            let len = frame
+                .data_ref()
+                .map_or(0, |data| u64::try_from(data.len()).unwrap_or(u64::MAX));
+            if len == 0 {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            let wait = this
+                .buckets
+                .iter()
+                .map(|bucket| bucket.reserve(len))
+                .max()
+                .unwrap_or_default();
+            if wait.is_zero() {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            if let Some(on_throttled) = &this.on_throttled {
+                on_throttled();
+            }
+            this.held = Some((frame, Box::pin(tokio::time::sleep(wait))));
+        }
+        if let Some((_, delay)) = this.held.as_mut() {
+            ready!(delay.as_mut().poll(cx));
+        }
+        Poll::Ready(this.held.take().map(|(frame, _)| Ok(frame)))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.held.is_none() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,68 @@
+//! Body byte accounting for `huginn_request_bytes_total` / `huginn_response_bytes_total`.
+//!
+//! [`CountedBody`] counts the data bytes of the frames it passes on, as the receiving side pulls
+//! them: nothing is buffered, and a slow reader slows the count down instead of growing memory.
+//! The total is reported once, when the body is dropped (after its last frame, or early when the
+//! client or backend goes away), so an aborted transfer counts the bytes that actually moved.
+//! Unlike the `Content-Length` based counters, chunked and streamed bodies are included.
+
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::Buf;
+use hyper::body::{Body, Frame, SizeHint};
+
+/// Body wrapper that reports the number of data bytes it passed on when dropped.
+pub struct CountedBody<B> {
+    inner: B,
+    bytes: u64,
+    on_done: Option<Box<dyn FnOnce(u64) + Send + Sync>>,
+}
+
+impl<B> CountedBody<B> {
+    /// Wrap `inner`; `on_done` receives the byte count once the body is dropped.
+    pub fn new(inner: B, on_done: impl FnOnce(u64) + Send + Sync + 'static) -> Self {
+        Self { inner, bytes: 0, on_done: Some(Box::new(on_done)) }
+    }
+}
+
+impl<B> Body for CountedBody<B>
+where
+    B: Body + Unpin,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        if let Some(data) = frame
+            .as_ref()
+            .and_then(|f| f.as_ref().ok())
+            .and_then(Frame::data_ref)
+        {
+            let len = u64::try_from(data.remaining()).unwrap_or(u64::MAX);
+            this.bytes = this.bytes.saturating_add(len);
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B> Drop for CountedBody<B> {
+    fn drop(&mut self) {
+        if let Some(on_done) = self.on_done.take() {
+            on_done(self.bytes);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Body size caps for routes with `max_request_body_bytes` / `max_response_body_bytes`.
+//!
+//! A declared `Content-Length` above the cap is rejected before the body is touched (`413` for
+//! requests, `502` for responses). Bodies without one are counted as they stream: the frame that
+//! crosses the cap is replaced by a [`BodyTooLarge`] error, which makes hyper abort that side of
+//! the exchange instead of delivering a silently truncated body.
+
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::Bytes;
+use http::header::CONTENT_LENGTH;
+use http::HeaderMap;
+use hyper::body::{Body, Frame, SizeHint};
+use thiserror::Error;
+
+use crate::utils::http::BoxError;
+
+/// A streamed body went past its configured cap.
+#[derive(Debug, Error)]
+#[error("body exceeds the limit of {limit} bytes")]
+pub struct BodyTooLarge {
+    pub limit: u64,
+}
+
+/// `true` when `headers` declare a `Content-Length` greater than `limit`.
+pub fn declared_length_exceeds(headers: &HeaderMap, limit: Option<u64>) -> bool {
+    let Some(limit) = limit else {
+        return false;
+    };
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .is_some_and(|length| length > limit)
+}
+
+/// Body wrapper that fails once more than `limit` data bytes have been read.
+///
+/// With `limit = None` frames pass through unchanged; only the error type is widened, so the
+/// proxy can use a single body type whether or not the route sets a cap.
+pub struct LimitedBody<B> {
+    inner: B,
+    limit: Option<u64>,
+    read: u64,
+    on_exceeded: Option<Box<dyn FnOnce() + Send + Sync>>,
+}
+
+impl<B> LimitedBody<B> {
+    pub fn new(inner: B, limit: Option<u64>) -> Self {
+        Self { inner, limit, read: 0, on_exceeded: None }
+    }
+
+    /// Run `f` once, when the limit is crossed (typically to record a metric).
+    pub fn on_exceeded(mut self, f: impl FnOnce() + Send + Sync + 'static) -> Self {
+        self.on_exceeded = Some(Box::new(f));
+        self
+    }
+}
+
+impl<B> Body for LimitedBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let frame = match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+            None => return Poll::Ready(None),
+        };
+        if let (Some(limit), Some(data)) = (this.limit, frame.data_ref()) {
+            let len = u64::try_from(data.len()).unwrap_or(u64::MAX);
+            this.read = this.read.saturating_add(len);
+            if this.read > limit {
+                if let Some(on_exceeded) = this.on_exceeded.take() {
+                    on_exceeded();
+                }
+                return Poll::Ready(Some(Err(Box::new(BodyTooLarge { limit }))));
+            }
+        }
+        Poll::Ready(Some(Ok(frame)))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
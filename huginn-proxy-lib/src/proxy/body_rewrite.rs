@@ -0,0 +1,188 @@
+//! Response body find/replace for routes with a `body_rewrite` block.
+//!
+//! Eligible backend responses get their body rewritten frame by frame as it streams to the
+//! client. Between frames the rewriter holds back only the bytes that could still be the start
+//! of a match, and data frames larger than `max_buffer_bytes` are cut into pieces first, so a
+//! response never costs more than about `max_buffer_bytes` plus the longest `find`.
+
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::Bytes;
+use http::header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_TYPE, ETAG};
+use http::{HeaderMap, Response};
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame};
+
+use crate::config::BodyRewriteConfig;
+use crate::proxy::compression::{is_transformable, weak_etag};
+use crate::utils::http::{BoxError, RespBody};
+
+/// `true` when `config` applies to `resp`: a status that carries a full body, no
+/// `Content-Encoding` or `Content-Range`, no `Cache-Control: no-transform` and an allowlisted
+/// `Content-Type`.
+pub fn is_rewritable<B>(config: &BodyRewriteConfig, resp: &Response<B>) -> bool {
+    is_transformable(resp)
+        && resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| config.allows_content_type(ct))
+}
+
+/// Rewrite the body of `resp` with `config` as it streams. `Content-Length` and `Accept-Ranges`
+/// are dropped, since the length changes, and a strong `ETag` becomes weak.
+pub fn rewrite_response(
+    resp: Response<RespBody>,
+    config: &BodyRewriteConfig,
+) -> Response<RespBody> {
+    let (mut parts, body) = resp.into_parts();
+    let headers = &mut parts.headers;
+    headers.remove(CONTENT_LENGTH);
+    headers.remove(ACCEPT_RANGES);
+    if let Some(weak) = headers.get(ETAG).and_then(weak_etag) {
+        headers.insert(ETAG, weak);
+    }
+    let body = RewrittenBody {
+        inner: body,
+        rewriter: Rewriter::new(config),
+        input: Bytes::new(),
+        max_buffer_bytes: config.max_buffer_bytes.max(1),
+        trailers: None,
+        done: false,
+    };
+    Response::from_parts(parts, body.boxed())
+}
+
+/// Streaming find/replace over a body fed in pieces.
+pub struct Rewriter {
+    /// `find` and `replace` of each rule, in order of precedence.
+    rules: Vec<(Box<[u8]>, Bytes)>,
+    /// Bytes kept back after each piece: the longest `find` minus one.
+    hold: usize,
+    /// Input not yet written out.
+    pending: Vec<u8>,
+}
+
+impl Rewriter {
+    pub fn new(config: &BodyRewriteConfig) -> Self {
+        let rules = config
+            .rules
+            .iter()
+            .map(|rule| {
+                (Box::from(rule.find.as_bytes()), Bytes::copy_from_slice(rule.replace.as_bytes()))
+            })
+            .collect();
+        Self { rules, hold: config.longest_find().saturating_sub(1), pending: Vec::new() }
+    }
+
+    /// Feed the next piece of the body and return the rewritten output that is ready (possibly
+    /// nothing). The tail that could begin a match is kept until more input arrives.
+    pub fn feed(&mut self, data: &[u8]) -> Bytes {
+        self.pending.extend_from_slice(data);
+        let safe = self.pending.len().saturating_sub(self.hold);
+        self.rewrite(safe)
+    }
+
+    /// End of body: rewrite and return whatever is still pending.
+    pub fn finish(&mut self) -> Bytes {
+        let end = self.pending.len();
+        self.rewrite(end)
+    }
+
+    /// Replace the matches starting before `end` in the pending input and return the output up
+    /// to `end` (or the end of the last match past it); the rest stays pending.
+    fn rewrite(&mut self, end: usize) -> Bytes {
+        let mut input = std::mem::take(&mut self.pending);
+        let mut out = Vec::with_capacity(input.len());
+        let mut copied = 0;
+        let mut at = 0;
+        while at < end {
+            let rest = input.get(at..).unwrap_or_default();
+            match self.rules.iter().find(|(find, _)| rest.starts_with(find)) {
+                Some((find, replace)) => {
+                    out.extend_from_slice(input.get(copied..at).unwrap_or_default());
+                    out.extend_from_slice(replace);
+                    at = at.saturating_add(find.len());
+                    copied = at;
+                }
+                None => at = at.saturating_add(1),
+            }
+        }
+        out.extend_from_slice(input.get(copied..at).unwrap_or_default());
+        input.drain(..at.min(input.len()));
+        self.pending = input;
+        Bytes::from(out)
+    }
+}
+
+/// Body wrapper that rewrites data frames; trailers are forwarded after the last output.
+struct RewrittenBody {
+    inner: RespBody,
+    rewriter: Rewriter,
+    /// Data of the current frame not yet fed to the rewriter.
+    input: Bytes,
+    max_buffer_bytes: usize,
+    trailers: Option<HeaderMap>,
+    done: bool,
+}
+
+impl Body for RewrittenBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        loop {
+            if !this.input.is_empty() {
+                let piece = this
+                    .input
+                    .split_to(this.input.len().min(this.max_buffer_bytes));
+                let out = this.rewriter.feed(&piece);
+                if !out.is_empty() {
+                    return Poll::Ready(Some(Ok(Frame::data(out))));
+                }
+                continue;
+            }
+            if let Some(trailers) = this.trailers.take() {
+                return Poll::Ready(Some(Ok(Frame::trailers(trailers))));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => this.input = data,
+                    Err(frame) => {
+                        // The held-back tail must precede the trailers.
+                        this.trailers = frame.into_trailers().ok();
+                        if let Some(rest) = this.finish() {
+                            return Poll::Ready(Some(Ok(Frame::data(rest))));
+                        }
+                    }
+                },
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => {
+                    if let Some(rest) = this.finish() {
+                        return Poll::Ready(Some(Ok(Frame::data(rest))));
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done && self.input.is_empty() && self.trailers.is_none()
+    }
+}
+
+impl RewrittenBody {
+    /// Mark the body done and return the rewritten held-back tail, if any.
+    fn finish(&mut self) -> Option<Bytes> {
+        self.done = true;
+        Some(self.rewriter.finish()).filter(|rest| !rest.is_empty())
+    }
+}
@@ -0,0 +1,323 @@
+//! In-process embedding: build a proxy in code, start it on the caller's runtime and change its
+//! routes while it runs.
+//!
+//! [`ProxyBuilder`] assembles a [`Config`] the way a config file would (routes, backends, any
+//! other setting through [`configure`](ProxyBuilder::configure)) and validates it like
+//! [`load_from_path`](crate::config::load_from_path) does. [`start`](ProxyBuilder::start) binds
+//! the listeners and returns a [`ProxyHandle`] once they accept connections.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use tokio::task::JoinHandle;
+use tower::{Layer, Service};
+
+use crate::backend::HealthRegistry;
+use crate::config::{
+    self, Backend, Config, ConfigParts, Domain, DynamicConfig, Route, StaticConfig,
+};
+use crate::error::{ProxyError, Result};
+use crate::fingerprinting::{FingerprintClassifier, FingerprintSet, SharedClassifier, Verdict};
+use crate::proxy::middleware::{BoxError, Middleware, Next, ProxyRequest, ProxyResponse};
+use crate::proxy::reload::SharedDynamicConfig;
+use crate::proxy::runtime::RuntimeHandles;
+use crate::proxy::server::{run, SynProbe, WatchOptions};
+use crate::proxy::shutdown::shutdown_channel;
+use crate::telemetry::admin::{apply_route_change, RouteChange, RouteChangeError};
+use crate::telemetry::{Metrics, Readiness};
+
+/// How often [`ProxyBuilder::start`] checks whether the listeners are up.
+const READY_POLL: Duration = Duration::from_millis(10);
+
+/// Builder of an embedded proxy.
+///
+/// ```no_run
+/// # async fn example() -> huginn_proxy_lib::Result<()> {
+/// use http::{header, StatusCode};
+/// use huginn_proxy_lib::{Backend, ProxyBuilder, Route, Verdict};
+///
+/// let proxy = ProxyBuilder::new()
+///     .listen(([127, 0, 0, 1], 8080).into())
+///     .backend(Backend::new("127.0.0.1:9000"))
+///     .route(Route::new("/", "127.0.0.1:9000"))
+///     .on_fingerprint(|set| {
+///         if set.headers.contains_key(header::USER_AGENT) {
+///             Verdict::Allow
+///         } else {
+///             Verdict::Deny(StatusCode::FORBIDDEN)
+///         }
+///     })
+///     .start()
+///     .await?;
+/// proxy.stop().await
+/// # }
+/// ```
+pub struct ProxyBuilder {
+    config: Config,
+    classifier: Option<SharedClassifier>,
+    middleware: Option<Middleware>,
+    syn_probe: Option<SynProbe>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl Default for ProxyBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProxyBuilder {
+    /// Builder over an empty config: add at least one listen address and a route.
+    pub fn new() -> Self {
+        Self::from_config(Config::default())
+    }
+
+    /// Builder starting from `config`, e.g. one read with
+    /// [`load_from_path`](crate::config::load_from_path) and extended in code.
+    pub fn from_config(config: Config) -> Self {
+        Self { config, classifier: None, middleware: None, syn_probe: None, metrics: None }
+    }
+
+    /// Accept client connections on `addr`.
+    pub fn listen(mut self, addr: SocketAddr) -> Self {
+        self.config.listen.addrs.push(addr);
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.config.backends.push(backend);
+        self
+    }
+
+    /// Add `route` to the catch-all domain (any `Host`), created on first use.
+    pub fn route(self, route: Route) -> Self {
+        self.push_route(None, route)
+    }
+
+    /// Add `route` to the domain of `host`, created on first use.
+    pub fn host_route(self, host: impl Into<String>, route: Route) -> Self {
+        self.push_route(Some(host.into().to_ascii_lowercase()), route)
+    }
+
+    /// Add a whole domain, with its own certificate, security and redirect settings.
+    pub fn domain(mut self, domain: Domain) -> Self {
+        self.config.domains.push(domain);
+        self
+    }
+
+    /// Change any other setting of the config, e.g. `tls` or `security`.
+    pub fn configure(mut self, f: impl FnOnce(&mut Config)) -> Self {
+        f(&mut self.config);
+        self
+    }
+
+    /// Classify every routed request with `f` (see [`FingerprintClassifier`]). Replaces any
+    /// classifier set before.
+    pub fn on_fingerprint<F>(self, f: F) -> Self
+    where
+        F: Fn(&FingerprintSet<'_>) -> Verdict + Send + Sync + 'static,
+    {
+        self.classifier(Arc::new(FnClassifier(f)))
+    }
+
+    /// Classify every routed request with `classifier`. Replaces any classifier set before.
+    pub fn classifier(mut self, classifier: SharedClassifier) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    /// Run `layer` around forwarding (see [`Middleware`]). Replaces any layer set before; stack
+    /// several with `tower::ServiceBuilder`.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Next> + Send + Sync + 'static,
+        L::Service: Service<ProxyRequest, Response = ProxyResponse> + Clone + Send + Sync + 'static,
+        <L::Service as Service<ProxyRequest>>::Error: Into<BoxError>,
+        <L::Service as Service<ProxyRequest>>::Future: Send + 'static,
+    {
+        self.middleware = Some(Middleware::new(layer));
+        self
+    }
+
+    /// Record metrics into `metrics` instead of discarding them.
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Look up TCP SYN fingerprints with `probe` (see [`SynProbe`]).
+    pub fn syn_probe(mut self, probe: SynProbe) -> Self {
+        self.syn_probe = Some(probe);
+        self
+    }
+
+    /// Validate the config, bind the listeners and start serving on the current Tokio runtime.
+    ///
+    /// Returns once the listeners accept connections, or the error that stopped the proxy
+    /// before (invalid config, address in use, ...). Like the binary, the proxy drains and stops
+    /// on SIGTERM/SIGINT as well as through [`ProxyHandle::shutdown`].
+    pub async fn start(self) -> Result<ProxyHandle> {
+        let config = config::prepare(self.config)?;
+        let ConfigParts { static_cfg, dynamic_cfg } = config.into_parts();
+        let static_cfg = Arc::new(static_cfg);
+        let dynamic_cfg: SharedDynamicConfig = Arc::new(ArcSwap::from_pointee(dynamic_cfg));
+        let metrics = self.metrics.unwrap_or_else(Metrics::new_noop);
+        let health = Arc::new(HealthRegistry::new().with_metrics(Arc::clone(&metrics)));
+        let readiness = Readiness::new();
+        let runtime = RuntimeHandles::default();
+        let (shutdown_tx, _) = shutdown_channel();
+
+        let mut task = tokio::spawn(run(
+            Arc::clone(&static_cfg),
+            Arc::clone(&dynamic_cfg),
+            metrics,
+            self.syn_probe,
+            self.classifier,
+            self.middleware,
+            WatchOptions::default(),
+            shutdown_tx,
+            readiness.clone(),
+            Arc::clone(&health),
+            runtime.clone(),
+        ));
+        while !readiness.is_ready() {
+            if task.is_finished() {
+                joined((&mut task).await)?;
+                return Err(ProxyError::Config(
+                    "proxy stopped before its listeners were ready".to_string(),
+                ));
+            }
+            tokio::time::sleep(READY_POLL).await;
+        }
+        Ok(ProxyHandle { static_cfg, dynamic_cfg, health, runtime, readiness, task })
+    }
+
+    fn push_route(mut self, host: Option<String>, route: Route) -> Self {
+        match self.config.domains.iter_mut().find(|d| d.host == host) {
+            Some(domain) => domain.routes.push(route),
+            None => {
+                self.config
+                    .domains
+                    .push(Domain { host, routes: vec![route], ..Domain::default() })
+            }
+        }
+        self
+    }
+}
+
+/// Closure registered through [`ProxyBuilder::on_fingerprint`].
+struct FnClassifier<F>(F);
+
+impl<F> FingerprintClassifier for FnClassifier<F>
+where
+    F: Fn(&FingerprintSet<'_>) -> Verdict + Send + Sync + 'static,
+{
+    fn classify(&self, set: &FingerprintSet<'_>) -> Verdict {
+        (self.0)(set)
+    }
+}
+
+/// Running embedded proxy, returned by [`ProxyBuilder::start`].
+///
+/// Dropping the handle leaves the proxy running; call [`stop`](Self::stop) to drain it.
+pub struct ProxyHandle {
+    static_cfg: Arc<StaticConfig>,
+    dynamic_cfg: SharedDynamicConfig,
+    health: Arc<HealthRegistry>,
+    runtime: RuntimeHandles,
+    readiness: Readiness,
+    task: JoinHandle<Result<()>>,
+}
+
+impl ProxyHandle {
+    /// Routing config in effect now.
+    pub fn config(&self) -> Arc<DynamicConfig> {
+        self.dynamic_cfg.load_full()
+    }
+
+    /// Whether the listeners accept connections (false once draining).
+    pub fn is_ready(&self) -> bool {
+        self.readiness.is_ready()
+    }
+
+    /// Add `route` to the domain of `host` (`None` for the catch-all domain), like
+    /// `POST /admin/routes`. Open connections are drained onto the new config.
+    pub async fn add_route(
+        &self,
+        host: Option<&str>,
+        route: Route,
+    ) -> std::result::Result<(), RouteChangeError> {
+        let host = host.map(str::to_ascii_lowercase);
+        self.change_routes(RouteChange::Add { host, route: Box::new(route) })
+            .await
+    }
+
+    /// Remove the route with `prefix` from the domain of `host` (`None` for the catch-all
+    /// domain).
+    pub async fn remove_route(
+        &self,
+        host: Option<&str>,
+        prefix: &str,
+    ) -> std::result::Result<(), RouteChangeError> {
+        let host = host.map(str::to_ascii_lowercase);
+        self.change_routes(RouteChange::Remove { host, prefix: prefix.to_string() })
+            .await
+    }
+
+    /// Stop routing new requests to the backend at `address`, like `POST /admin/drain`.
+    /// Returns `false` if it was already drained this way.
+    pub fn drain_backend(&self, address: &str) -> bool {
+        self.health.drain(address)
+    }
+
+    /// Lift a [`drain_backend`](Self::drain_backend). A backend drained by its config stays
+    /// drained.
+    pub fn undrain_backend(&self, address: &str) -> bool {
+        self.health.undrain(address)
+    }
+
+    /// Backend health and drain state.
+    pub fn health(&self) -> &Arc<HealthRegistry> {
+        &self.health
+    }
+
+    /// Live state shared with the request path (synthetic response switches, connections, ...).
+    pub fn runtime(&self) -> &RuntimeHandles {
+        &self.runtime
+    }
+
+    /// Start draining, as SIGTERM does; [`wait`](Self::wait) returns once the proxy stopped.
+    pub fn shutdown(&self) {
+        self.runtime.drain.request();
+    }
+
+    /// Wait until the proxy stops, on [`shutdown`](Self::shutdown) or a signal.
+    pub async fn wait(self) -> Result<()> {
+        joined(self.task.await)
+    }
+
+    /// [`shutdown`](Self::shutdown), then [`wait`](Self::wait).
+    pub async fn stop(self) -> Result<()> {
+        self.shutdown();
+        self.wait().await
+    }
+
+    async fn change_routes(
+        &self,
+        change: RouteChange,
+    ) -> std::result::Result<(), RouteChangeError> {
+        // Same lock as file reloads and the admin API.
+        let _guard = self.runtime.reload_mutex.lock().await;
+        let current = self.dynamic_cfg.load_full();
+        let updated = apply_route_change(&self.static_cfg, &current, change)?;
+        self.dynamic_cfg.store(Arc::new(updated));
+        self.runtime.config_generation.bump();
+        Ok(())
+    }
+}
+
+fn joined(result: std::result::Result<Result<()>, tokio::task::JoinError>) -> Result<()> {
+    result.map_err(|e| ProxyError::Io(std::io::Error::other(e)))?
+}
@@ -0,0 +1,596 @@
+//! Response caching for routes with a `cache` block.
+//!
+//! A shared-cache subset of RFC 9111: only `GET` responses with an explicit (or configured
+//! default) freshness lifetime are stored, and nothing is revalidated. `no-cache` responses are
+//! not stored, and a request's own `no-cache` skips the lookup but still refreshes the entry. A
+//! successful unsafe request (`POST`, `PUT`, `PATCH`, `DELETE`) evicts the entries for its URL.
+//! Bodies are captured while they stream to the client and stored once complete, so a miss is
+//! never delayed by the cache.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, SystemTime};
+
+use bytes::{Bytes, BytesMut};
+use http::header::{
+    AGE, AUTHORIZATION, CACHE_CONTROL, CONNECTION, CONTENT_LENGTH, DATE, EXPIRES, PRAGMA, RANGE,
+    SET_COOKIE, TRANSFER_ENCODING, UPGRADE, VARY,
+};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Response, StatusCode, Uri};
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame, SizeHint};
+use tokio::time::Instant;
+
+use crate::config::{CacheConfig, RouteCacheConfig};
+use crate::utils::http::{full_body, BoxError, RespBody};
+
+/// Statuses cacheable by default (RFC 9110 §15.1), minus `206`: range requests bypass the cache.
+const CACHEABLE_STATUSES: &[u16] = &[200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// Rough per-header bookkeeping cost counted against `max_size_bytes`.
+const HEADER_OVERHEAD: u64 = 32;
+
+/// Shared store behind every route's `cache` block, sized by the static `[cache]` block.
+pub struct ResponseCache {
+    max_size_bytes: u64,
+    store: Mutex<Store>,
+}
+
+#[derive(Default)]
+struct Store {
+    urls: HashMap<CacheUrl, Variants>,
+    entries: HashMap<String, Entry>,
+    /// Entry keys by last use, least recent first.
+    lru: BTreeMap<u64, String>,
+    clock: u64,
+    size: u64,
+}
+
+/// The stored variants of one URL.
+struct Variants {
+    /// Request headers named by the latest response's `Vary`.
+    vary: Vec<HeaderName>,
+    keys: HashSet<String>,
+}
+
+struct Entry {
+    response: Arc<CachedResponse>,
+    url: CacheUrl,
+    last_used: u64,
+    size: u64,
+}
+
+/// Outcome of a cache lookup.
+enum Lookup {
+    /// A fresh entry: serve it without contacting the backend.
+    Fresh(Arc<CachedResponse>),
+    /// An expired entry still inside its `stale_if_error_secs` window: forward the request, and
+    /// serve this entry if the backend fails.
+    Stale(Arc<CachedResponse>),
+    Miss,
+}
+
+/// A stored response.
+pub struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: Instant,
+    /// `Age` the response already had when the backend sent it.
+    initial_age: Duration,
+    fresh_for: Duration,
+    stale_if_error: Duration,
+}
+
+impl CachedResponse {
+    fn age(&self) -> Duration {
+        self.initial_age.saturating_add(self.stored_at.elapsed())
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.age() < self.fresh_for
+    }
+
+    fn usable_on_error(&self) -> bool {
+        self.age() < self.fresh_for.saturating_add(self.stale_if_error)
+    }
+
+    /// Build the response served to the client, with an up-to-date `Age`.
+    pub fn to_response(&self) -> Response<RespBody> {
+        let mut resp = Response::new(full_body(self.body.clone()));
+        *resp.status_mut() = self.status;
+        *resp.headers_mut() = self.headers.clone();
+        resp.headers_mut()
+            .insert(AGE, HeaderValue::from(self.age().as_secs()));
+        resp
+    }
+}
+
+/// What the cache does with one request on a caching route.
+pub enum CacheStep {
+    /// Serve this fresh entry without contacting the backend.
+    Hit(Arc<CachedResponse>),
+    /// Forward, then store the response. `stale` is served instead if the backend fails.
+    Fetch {
+        request: CacheableRequest,
+        stale: Option<Arc<CachedResponse>>,
+    },
+    /// An unsafe method: evict the URL once the backend answers with a `2xx` or `3xx`.
+    Invalidate(CacheUrl),
+    /// Neither served from nor stored in the cache (`HEAD`, `Range`, `no-store`, ...).
+    Bypass,
+}
+
+/// Cache identity of a URL: `GET` plus the lowercased host and the path and query.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheUrl(String);
+
+impl CacheUrl {
+    pub fn new(host: &str, uri: &Uri) -> Self {
+        let path_and_query = uri.path_and_query().map_or("/", |pq| pq.as_str());
+        Self(format!("GET {}{}", host.to_ascii_lowercase(), path_and_query))
+    }
+}
+
+/// A `GET` request the cache may answer, with what is needed to store its response.
+pub struct CacheableRequest {
+    url: CacheUrl,
+    headers: HeaderMap,
+    /// `false` when the client asked for an end-to-end reload (`no-cache`, `max-age=0`).
+    lookup: bool,
+}
+
+impl CacheableRequest {
+    /// `None` when the request must bypass the cache: not a `GET`, a `Range` or `Upgrade`
+    /// request, or `Cache-Control: no-store`.
+    fn new(method: &Method, host: &str, uri: &Uri, headers: &HeaderMap) -> Option<Self> {
+        if method != Method::GET || headers.contains_key(RANGE) || headers.contains_key(UPGRADE) {
+            return None;
+        }
+        let directives = directives(headers);
+        if has_directive(&directives, "no-store") {
+            return None;
+        }
+        let pragma_no_cache = !headers.contains_key(CACHE_CONTROL)
+            && headers
+                .get_all(PRAGMA)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .any(|v| v.trim().eq_ignore_ascii_case("no-cache"));
+        let reload = has_directive(&directives, "no-cache")
+            || directive_secs(&directives, "max-age") == Some(0)
+            || pragma_no_cache;
+        Some(Self { url: CacheUrl::new(host, uri), headers: headers.clone(), lookup: !reload })
+    }
+
+    fn key(&self, vary: &[HeaderName]) -> String {
+        let mut key = self.url.0.clone();
+        for name in vary {
+            key.push('\n');
+            key.push_str(name.as_str());
+            key.push(':');
+            let values: Vec<&str> = self
+                .headers
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .collect();
+            key.push_str(&values.join(","));
+        }
+        key
+    }
+}
+
+impl ResponseCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        Self { max_size_bytes: config.max_size_bytes, store: Mutex::new(Store::default()) }
+    }
+
+    /// Decide how a request on a caching route is handled, looking up its entry when it has one.
+    pub fn prepare(
+        &self,
+        method: &Method,
+        host: &str,
+        uri: &Uri,
+        headers: &HeaderMap,
+    ) -> CacheStep {
+        if !method.is_safe() {
+            return CacheStep::Invalidate(CacheUrl::new(host, uri));
+        }
+        let Some(request) = CacheableRequest::new(method, host, uri, headers) else {
+            return CacheStep::Bypass;
+        };
+        match self.lookup(&request) {
+            Lookup::Fresh(entry) => CacheStep::Hit(entry),
+            Lookup::Stale(entry) => CacheStep::Fetch { request, stale: Some(entry) },
+            Lookup::Miss => CacheStep::Fetch { request, stale: None },
+        }
+    }
+
+    /// Look up the entry matching `request`, dropping it if it is past any use.
+    fn lookup(&self, request: &CacheableRequest) -> Lookup {
+        let mut store = self.store.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(variants) = store.urls.get(&request.url) else {
+            return Lookup::Miss;
+        };
+        let key = request.key(&variants.vary);
+        let Some(response) = store.entries.get(&key).map(|e| Arc::clone(&e.response)) else {
+            return Lookup::Miss;
+        };
+        if !response.usable_on_error() {
+            store.remove(&key);
+            return Lookup::Miss;
+        }
+        store.touch(&key);
+        if !request.lookup {
+            return Lookup::Miss;
+        }
+        if response.is_fresh() {
+            Lookup::Fresh(response)
+        } else {
+            Lookup::Stale(response)
+        }
+    }
+
+    /// Evict every stored variant of `url`.
+    pub fn invalidate(&self, url: &CacheUrl) {
+        self.store
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove_url(url);
+    }
+
+    /// Bytes currently accounted to stored responses.
+    pub fn size_bytes(&self) -> u64 {
+        self.store.lock().unwrap_or_else(|e| e.into_inner()).size
+    }
+
+    /// Number of stored responses.
+    pub fn len(&self) -> usize {
+        self.store
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert(&self, request: &CacheableRequest, vary: Vec<HeaderName>, response: CachedResponse) {
+        let size = entry_size(&response);
+        if size > self.max_size_bytes {
+            return;
+        }
+        let key = request.key(&vary);
+        let mut store = self.store.lock().unwrap_or_else(|e| e.into_inner());
+        // A changed `Vary` makes the URL's other variants unreachable: drop them.
+        if store
+            .urls
+            .get(&request.url)
+            .is_some_and(|variants| variants.vary != vary)
+        {
+            store.remove_url(&request.url);
+        }
+        store.remove(&key);
+        while store.size.saturating_add(size) > self.max_size_bytes {
+            let Some((_, oldest)) = store.lru.pop_first() else {
+                break;
+            };
+            store.remove(&oldest);
+        }
+        store.clock = store.clock.wrapping_add(1);
+        let last_used = store.clock;
+        store.lru.insert(last_used, key.clone());
+        store.size = store.size.saturating_add(size);
+        store
+            .urls
+            .entry(request.url.clone())
+            .or_insert_with(|| Variants { vary, keys: HashSet::new() })
+            .keys
+            .insert(key.clone());
+        store.entries.insert(
+            key,
+            Entry { response: Arc::new(response), url: request.url.clone(), last_used, size },
+        );
+    }
+}
+
+impl Store {
+    fn touch(&mut self, key: &str) {
+        self.clock = self.clock.wrapping_add(1);
+        let clock = self.clock;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.lru.remove(&entry.last_used);
+            entry.last_used = clock;
+            self.lru.insert(clock, key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        let Some(entry) = self.remove_entry(key) else {
+            return;
+        };
+        if let Some(variants) = self.urls.get_mut(&entry.url) {
+            variants.keys.remove(key);
+            if variants.keys.is_empty() {
+                self.urls.remove(&entry.url);
+            }
+        }
+    }
+
+    fn remove_url(&mut self, url: &CacheUrl) {
+        let Some(variants) = self.urls.remove(url) else {
+            return;
+        };
+        for key in &variants.keys {
+            self.remove_entry(key);
+        }
+    }
+
+    fn remove_entry(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.lru.remove(&entry.last_used);
+        self.size = self.size.saturating_sub(entry.size);
+        Some(entry)
+    }
+}
+
+fn entry_size(response: &CachedResponse) -> u64 {
+    let headers: usize = response
+        .headers
+        .iter()
+        .map(|(name, value)| name.as_str().len().saturating_add(value.len()))
+        .sum();
+    let header_count = u64::try_from(response.headers.len()).unwrap_or(u64::MAX);
+    u64::try_from(response.body.len().saturating_add(headers))
+        .unwrap_or(u64::MAX)
+        .saturating_add(header_count.saturating_mul(HEADER_OVERHEAD))
+}
+
+/// Wrap `resp` so that it is stored once its body has streamed to the client completely.
+/// Responses that may not be stored are returned untouched.
+pub fn store_response(
+    cache: &Arc<ResponseCache>,
+    config: &RouteCacheConfig,
+    request: CacheableRequest,
+    resp: Response<RespBody>,
+) -> Response<RespBody> {
+    let Some(pending) = storable(config, &request, &resp) else {
+        return resp;
+    };
+    let (parts, body) = resp.into_parts();
+    let mut headers = parts.headers.clone();
+    for name in [AGE, CONNECTION, TRANSFER_ENCODING] {
+        headers.remove(name);
+    }
+    let body = CachingBody {
+        inner: body,
+        buffer: BytesMut::new(),
+        limit: config.max_object_bytes,
+        pending: Some(Pending {
+            cache: Arc::clone(cache),
+            request,
+            vary: pending.vary,
+            response: CachedResponse {
+                status: parts.status,
+                headers,
+                body: Bytes::new(),
+                stored_at: Instant::now(),
+                initial_age: pending.initial_age,
+                fresh_for: pending.fresh_for,
+                stale_if_error: Duration::from_secs(config.stale_if_error_secs),
+            },
+        }),
+    };
+    Response::from_parts(parts, body.boxed())
+}
+
+struct Storable {
+    vary: Vec<HeaderName>,
+    initial_age: Duration,
+    fresh_for: Duration,
+}
+
+/// Storage rules for a shared cache (RFC 9111 §3), `None` when `resp` may not be stored.
+fn storable<B>(
+    config: &RouteCacheConfig,
+    request: &CacheableRequest,
+    resp: &Response<B>,
+) -> Option<Storable> {
+    if !CACHEABLE_STATUSES.contains(&resp.status().as_u16()) {
+        return None;
+    }
+    let headers = resp.headers();
+    let directives = directives(headers);
+    if ["no-store", "no-cache", "private"]
+        .iter()
+        .any(|d| has_directive(&directives, d))
+        || headers.contains_key(SET_COOKIE)
+    {
+        return None;
+    }
+    let shareable = ["public", "s-maxage", "must-revalidate"]
+        .iter()
+        .any(|d| has_directive(&directives, d));
+    if request.headers.contains_key(AUTHORIZATION) && !shareable {
+        return None;
+    }
+    let too_large = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .is_some_and(|len| len > config.max_object_bytes);
+    if too_large {
+        return None;
+    }
+    let mut vary = Vec::new();
+    for name in headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    {
+        if name == "*" {
+            return None;
+        }
+        vary.push(HeaderName::from_bytes(name.as_bytes()).ok()?);
+    }
+    vary.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    vary.dedup();
+
+    let fresh_for = freshness_lifetime(headers, &directives, config.default_ttl_secs)?;
+    let initial_age = headers
+        .get(AGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_default();
+    // Worth keeping while it can still be served, fresh or as a `stale_if_error_secs` fallback.
+    let usable_for = fresh_for.saturating_add(Duration::from_secs(config.stale_if_error_secs));
+    (!fresh_for.is_zero() && initial_age < usable_for).then_some(Storable {
+        vary,
+        initial_age,
+        fresh_for,
+    })
+}
+
+/// `s-maxage`, then `max-age`, then `Expires - Date`, then `default_ttl_secs` (0 = none).
+fn freshness_lifetime(
+    headers: &HeaderMap,
+    directives: &[(String, Option<String>)],
+    default_ttl_secs: u64,
+) -> Option<Duration> {
+    if let Some(secs) =
+        directive_secs(directives, "s-maxage").or_else(|| directive_secs(directives, "max-age"))
+    {
+        return Some(Duration::from_secs(secs));
+    }
+    if let Some(expires) = headers.get(EXPIRES) {
+        // An unparsable `Expires` (e.g. "0") means "already expired".
+        let expires = expires
+            .to_str()
+            .ok()
+            .and_then(|v| httpdate::parse_http_date(v).ok());
+        let date = headers
+            .get(DATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| httpdate::parse_http_date(v).ok())
+            .unwrap_or_else(SystemTime::now);
+        return Some(
+            expires
+                .and_then(|e| e.duration_since(date).ok())
+                .unwrap_or_default(),
+        );
+    }
+    (default_ttl_secs > 0).then(|| Duration::from_secs(default_ttl_secs))
+}
+
+/// `Cache-Control` directives as lowercase `(name, value)` pairs.
+fn directives(headers: &HeaderMap) -> Vec<(String, Option<String>)> {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|directive| {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name, Some(value.trim().trim_matches('"').to_string())),
+                None => (directive, None),
+            };
+            let name = name.trim().to_ascii_lowercase();
+            (!name.is_empty()).then_some((name, value))
+        })
+        .collect()
+}
+
+fn has_directive(directives: &[(String, Option<String>)], name: &str) -> bool {
+    directives.iter().any(|(n, _)| n == name)
+}
+
+fn directive_secs(directives: &[(String, Option<String>)], name: &str) -> Option<u64> {
+    directives
+        .iter()
+        .find(|(n, _)| n == name)
+        .and_then(|(_, value)| value.as_deref()?.parse::<u64>().ok())
+}
+
+struct Pending {
+    cache: Arc<ResponseCache>,
+    request: CacheableRequest,
+    vary: Vec<HeaderName>,
+    response: CachedResponse,
+}
+
+/// Body wrapper that copies data frames aside and stores the response at end of stream. A body
+/// that fails, carries trailers or outgrows `max_object_bytes` is passed through but not stored.
+struct CachingBody {
+    inner: RespBody,
+    buffer: BytesMut,
+    limit: u64,
+    pending: Option<Pending>,
+}
+
+impl Body for CachingBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let frame = match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => {
+                this.abandon();
+                return Poll::Ready(Some(Err(e)));
+            }
+            None => {
+                if let Some(mut pending) = this.pending.take() {
+                    pending.response.body = std::mem::take(&mut this.buffer).freeze();
+                    pending
+                        .cache
+                        .insert(&pending.request, pending.vary, pending.response);
+                }
+                return Poll::Ready(None);
+            }
+        };
+        if this.pending.is_some() {
+            match frame.data_ref() {
+                Some(data) => {
+                    let len = u64::try_from(this.buffer.len().saturating_add(data.len()))
+                        .unwrap_or(u64::MAX);
+                    if len > this.limit {
+                        this.abandon();
+                    } else {
+                        this.buffer.extend_from_slice(data);
+                    }
+                }
+                None => this.abandon(),
+            }
+        }
+        Poll::Ready(Some(Ok(frame)))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        // Report the end only once the pending entry has been stored on the final poll.
+        self.pending.is_none() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl CachingBody {
+    fn abandon(&mut self) {
+        self.pending = None;
+        self.buffer = BytesMut::new();
+    }
+}
@@ -1,18 +1,95 @@
-use crate::config::{BackendPoolConfig, KeepAliveConfig};
+use crate::backend::DiscoveredAddrs;
+use crate::config::{Backend, BackendPoolConfig, BackendPoolLimits, KeepAliveConfig, TcpConfig};
+use crate::error::{ProxyError, Result};
+use crate::proxy::bandwidth::ShapedBody;
+use crate::proxy::body_bytes::CountedBody;
+use crate::proxy::body_limit::LimitedBody;
+use crate::proxy::pool_connector::{set_tcp_options, TrackedConnector};
+use crate::proxy::prefetch::PrefetchedBody;
+use crate::proxy::socks5::Socks5Proxy;
+use crate::telemetry::Metrics;
+use crate::tls::build_upstream_client_config;
+use bytes::Bytes;
 use http::Version;
-use hyper::body::Incoming;
+use http_body_util::Full;
+use hyper::body::{Body, Frame, Incoming, SizeHint};
+use hyper_rustls::{FixedServerNameResolver, HttpsConnector, HttpsConnectorBuilder};
 use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::client::legacy::Client;
-use hyper_util::rt::TokioExecutor;
+use hyper_util::rt::{TokioExecutor, TokioTimer};
+use rustls_pki_types::ServerName;
+use std::collections::HashMap;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_rustls::rustls::ClientConfig;
 
-pub type HttpClient = Client<HttpConnector, Incoming>;
+/// Request body sent to backends: the client's body (replaying what `[security.waf]` read
+/// ahead), counted for `huginn_request_bytes_total`, behind the route's `max_request_body_bytes`
+/// cap and paced by the route's and backend's `bandwidth`.
+pub type UpstreamBody = ShapedBody<LimitedBody<CountedBody<PrefetchedBody<Incoming>>>>;
+
+pub type HttpClient = Client<TrackedConnector, UpstreamBody>;
+
+/// Client for external authorization checks (see `[domains.routes.ext_authz]`). Check
+/// requests are built by the proxy and carry no body.
+pub type AuthzClient = Client<HttpConnector, Full<Bytes>>;
+
+/// Client for backends reached over `https://` (see `[backends.tls]`).
+pub type HttpsClient = Client<HttpsConnector<TrackedConnector>, UpstreamBody>;
+
+/// Pooled clients of one backend with a `pool`, `discovery`, `tcp` or `proxy` table or a `unix:`
+/// address: its own idle, socket and proxy settings, plus the concurrency limits from
+/// [`BackendPoolLimits`].
+#[derive(Clone)]
+struct PlainBackendClients {
+    /// Connector of the backend's clients (bound to the socket for `unix:` backends), kept for
+    /// one-off clients.
+    connector: TrackedConnector,
+    pool_config: BackendPoolConfig,
+    http11: Arc<HttpClient>,
+    http2: Arc<HttpClient>,
+}
+
+/// In-flight limits of one backend with a `pool` table.
+struct BackendLimiter {
+    /// HTTP/1.1 requests in flight (`max_connections`).
+    connections: Option<Arc<Semaphore>>,
+    /// HTTP/2 streams in flight (`max_concurrent_streams`).
+    streams: Option<Arc<Semaphore>>,
+    wait: Duration,
+}
+
+/// The backend's connection or stream limit stayed exhausted for `pool.wait_timeout_ms`.
+#[derive(Debug)]
+pub struct PoolWaitTimeout;
+
+/// Pooled clients of one TLS backend. Each backend gets its own clients because trust anchors,
+/// SNI and client certificate are per-backend.
+#[derive(Clone)]
+struct TlsBackendClients {
+    /// Connector wrapped in TLS (socket options and SOCKS5 proxy of the backend), kept for
+    /// one-off clients.
+    connector: TrackedConnector,
+    tls: Arc<ClientConfig>,
+    server_name: Option<ServerName<'static>>,
+    pool_config: BackendPoolConfig,
+    http11: Arc<HttpsClient>,
+    http2: Arc<HttpsClient>,
+    /// Offers both `h2` and `http/1.1` (`http_version = "auto"`): each connection speaks what
+    /// ALPN picked, `h2` ones shared by concurrent requests, `http/1.1` ones one request at a
+    /// time.
+    negotiated: Arc<HttpsClient>,
+}
 
 /// Shared HTTP client pool for backend connections
 ///
 /// This pool maintains reusable HTTP/1.1 and HTTP/2 clients to avoid
-/// creating new TCP and TLS connections for every request.
+/// creating new TCP and TLS connections for every request. Backends with a
+/// `tls`, `pool`, `discovery`, `tcp` or `proxy` table or a `unix:` address get dedicated
+/// clients; the rest share one pair.
 ///
 /// # Force New Connection
 ///
@@ -28,11 +105,25 @@ pub struct ClientPool {
     /// Client for HTTP/2 requests (http2_only with pooling)
     http2: Arc<HttpClient>,
 
-    /// Configuration (stored for creating one-off clients)
-    keep_alive: KeepAliveConfig,
+    /// Connector (`[backend_pool.tcp]` socket options, TCP keep-alive, connect timeout, metrics)
+    /// cloned into every backend client, including one-off clients.
+    connector: TrackedConnector,
 
-    /// TCP connect timeout (None = no timeout).
-    upstream_connect_ms: Option<u64>,
+    /// Pool settings (stored for creating per-backend clients)
+    config: BackendPoolConfig,
+
+    /// TLS clients keyed by backend address; backends without `tls` are absent.
+    tls_backends: Arc<HashMap<String, TlsBackendClients>>,
+
+    /// Plain clients of backends with a `pool`, `discovery`, `tcp` or `proxy` table or a `unix:`
+    /// address, keyed by address.
+    pooled_backends: Arc<HashMap<String, PlainBackendClients>>,
+
+    /// Concurrency limits of backends whose `pool` table sets one, keyed by address.
+    limiters: Arc<HashMap<String, BackendLimiter>>,
+
+    /// Client for external authorization services (pooled like HTTP/1.1 backends)
+    authz: Arc<AuthzClient>,
 }
 
 impl ClientPool {
@@ -41,22 +132,170 @@ impl ClientPool {
         config: BackendPoolConfig,
         upstream_connect_ms: Option<u64>,
     ) -> Self {
-        let http11_client = Self::create_http11_client(keep_alive, &config, upstream_connect_ms);
-        let http2_client = Self::create_http2_client(keep_alive, &config, upstream_connect_ms);
+        // Backend connectors apply the connect timeout themselves, so routes can replace it.
+        let connector =
+            TrackedConnector::new(Self::create_connector(keep_alive, &config.tcp, None))
+                .with_connect_timeout(upstream_connect_ms.map(Duration::from_millis));
+        let http11_client = Self::create_http_client(&connector, &config, false);
+        let http2_client = Self::create_http_client(&connector, &config, true);
+        let authz_client = Self::create_authz_client(keep_alive, &config, upstream_connect_ms);
 
         Self {
             http11: Arc::new(http11_client),
             http2: Arc::new(http2_client),
-            keep_alive: keep_alive.clone(),
-            upstream_connect_ms,
+            authz: Arc::new(authz_client),
+            connector,
+            config,
+            tls_backends: Arc::new(HashMap::new()),
+            pooled_backends: Arc::new(HashMap::new()),
+            limiters: Arc::new(HashMap::new()),
         }
     }
 
-    fn create_http11_client(
+    /// Report opened and open backend connections (`huginn_backend_connections_opened_total`,
+    /// `huginn_backend_pool_connections`). Call before [`ClientPool::with_backends`].
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.connector = self.connector.with_metrics(metrics);
+        self.http11 = Arc::new(Self::create_http_client(&self.connector, &self.config, false));
+        self.http2 = Arc::new(Self::create_http_client(&self.connector, &self.config, true));
+        self
+    }
+
+    /// Dial backends with `discovery` at their discovered addresses (see
+    /// [`BackendDiscovery`](crate::backend::BackendDiscovery)). Call before
+    /// [`ClientPool::with_backends`].
+    pub fn with_discovery(mut self, discovered: Arc<DiscoveredAddrs>) -> Self {
+        self.connector = self.connector.with_discovery(discovered);
+        self.http11 = Arc::new(Self::create_http_client(&self.connector, &self.config, false));
+        self.http2 = Arc::new(Self::create_http_client(&self.connector, &self.config, true));
+        self
+    }
+
+    /// Build the dedicated clients of every backend with a `tls`, `pool`, `discovery`, `tcp` or
+    /// `proxy` table or a `unix:` address.
+    ///
+    /// Fails when a backend's CA bundle, client certificate or key cannot be loaded, so callers
+    /// (startup, hot reload) can refuse the config instead of failing each request.
+    pub fn with_backends(mut self, backends: &[Backend]) -> Result<Self> {
+        let mut tls_backends = HashMap::new();
+        let mut pooled_backends = HashMap::new();
+        let mut limiters = HashMap::new();
+        for backend in backends {
+            let mut pool_config = backend
+                .pool
+                .as_ref()
+                .map_or_else(|| self.config.clone(), |limits| limits.resolve(&self.config));
+            if let Some(tcp) = &backend.tcp {
+                pool_config.tcp = tcp.or(&self.config.tcp);
+            }
+            if let Some(limiter) = backend.pool.as_ref().and_then(BackendLimiter::new) {
+                limiters.insert(backend.address.clone(), limiter);
+            }
+            let mut tcp_connector = self.connector.clone().with_tcp(&pool_config.tcp);
+            if let Some(proxy) = &backend.proxy {
+                let socks5 = Socks5Proxy::from_config(proxy).ok_or_else(|| {
+                    ProxyError::Config(format!(
+                        "Backend '{}': proxy.url '{}' is not a SOCKS5 URL",
+                        backend.address, proxy.url
+                    ))
+                })?;
+                tcp_connector = tcp_connector.with_socks5(Arc::new(socks5));
+            }
+            let Some(tls_cfg) = &backend.tls else {
+                let connector = match backend.unix_socket_path() {
+                    Some(path) => self.connector.unix(&backend.address, path),
+                    None if backend.pool.is_some()
+                        || backend.discovery.is_some()
+                        || backend.tcp.is_some()
+                        || backend.proxy.is_some() =>
+                    {
+                        tcp_connector
+                    }
+                    None => continue,
+                };
+                pooled_backends.insert(
+                    backend.address.clone(),
+                    PlainBackendClients::new(connector, pool_config),
+                );
+                continue;
+            };
+            let tls = build_upstream_client_config(tls_cfg)
+                .map_err(|e| ProxyError::Tls(format!("Backend '{}': {e}", backend.address)))?;
+            let server_name = tls_cfg
+                .server_name
+                .as_deref()
+                .map(|name| {
+                    ServerName::try_from(name)
+                        .map(|n| n.to_owned())
+                        .map_err(|e| {
+                            ProxyError::Config(format!(
+                                "Backend '{}': tls.server_name: {e}",
+                                backend.address
+                            ))
+                        })
+                })
+                .transpose()?;
+            tls_backends.insert(
+                backend.address.clone(),
+                Self::tls_backend_clients(tcp_connector, tls, server_name, pool_config),
+            );
+        }
+        self.tls_backends = Arc::new(tls_backends);
+        self.pooled_backends = Arc::new(pooled_backends);
+        self.limiters = Arc::new(limiters);
+        Ok(self)
+    }
+
+    /// Copy of this pool where `backend` gets new, empty clients; every other backend keeps its
+    /// clients and connections. The old clients close their connections once the requests in
+    /// flight on them finish, which drains connections to addresses discovery dropped.
+    pub fn with_fresh_backend_clients(&self, backend: &str) -> Self {
+        let mut pool = self.clone();
+        if let Some(clients) = self.pooled_backends.get(backend) {
+            let mut pooled_backends = HashMap::clone(&self.pooled_backends);
+            pooled_backends.insert(
+                backend.to_string(),
+                PlainBackendClients::new(clients.connector.clone(), clients.pool_config.clone()),
+            );
+            pool.pooled_backends = Arc::new(pooled_backends);
+        }
+        if let Some(clients) = self.tls_backends.get(backend) {
+            let mut tls_backends = HashMap::clone(&self.tls_backends);
+            tls_backends.insert(
+                backend.to_string(),
+                Self::tls_backend_clients(
+                    clients.connector.clone(),
+                    Arc::clone(&clients.tls),
+                    clients.server_name.clone(),
+                    clients.pool_config.clone(),
+                ),
+            );
+            pool.tls_backends = Arc::new(tls_backends);
+        }
+        pool
+    }
+
+    fn tls_backend_clients(
+        connector: TrackedConnector,
+        tls: Arc<ClientConfig>,
+        server_name: Option<ServerName<'static>>,
+        pool_config: BackendPoolConfig,
+    ) -> TlsBackendClients {
+        let name = server_name.as_ref();
+        let client = |version| {
+            Arc::new(Self::create_https_client(&connector, &tls, name, &pool_config, version))
+        };
+        let http11 = client(Some(Version::HTTP_11));
+        let http2 = client(Some(Version::HTTP_2));
+        let negotiated = client(None);
+        TlsBackendClients { connector, tls, server_name, pool_config, http11, http2, negotiated }
+    }
+
+    fn create_connector(
         keep_alive: &KeepAliveConfig,
-        config: &BackendPoolConfig,
+        tcp: &TcpConfig,
         upstream_connect_ms: Option<u64>,
-    ) -> HttpClient {
+    ) -> HttpConnector {
         let mut connector = HttpConnector::new();
         // TCP keep-alive: sends periodic packets to keep TCP connection alive
         if keep_alive.enabled {
@@ -64,44 +303,143 @@ impl ClientPool {
         } else {
             connector.set_keepalive(None);
         }
+        // `[backend_pool.tcp]` options, including a keep-alive time replacing the one above.
+        set_tcp_options(&mut connector, tcp);
         connector.set_connect_timeout(upstream_connect_ms.map(Duration::from_millis));
+        connector
+    }
 
+    /// Client builder with the pool and HTTP/2 settings of `config`. `enabled = false` keeps no
+    /// idle connection, so every request opens a new one.
+    fn client_builder(config: &BackendPoolConfig) -> hyper_util::client::legacy::Builder {
         let mut builder = Client::builder(TokioExecutor::new());
+        // The timer lets the pool close idle connections in the background, not only on checkout.
+        builder.pool_timer(TokioTimer::new());
         builder.pool_idle_timeout(Duration::from_secs(config.idle_timeout));
 
         // Configure connection pool settings
-        if config.pool_max_idle_per_host > 0 {
+        if !config.enabled {
+            builder.pool_max_idle_per_host(0);
+        } else if config.pool_max_idle_per_host > 0 {
             builder.pool_max_idle_per_host(config.pool_max_idle_per_host);
         }
 
-        builder.build(connector)
+        // HTTP/2 settings only take effect on connections that negotiate HTTP/2.
+        let http2 = &config.http2;
+        builder
+            .timer(TokioTimer::new())
+            .http2_initial_stream_window_size(http2.initial_stream_window_size)
+            .http2_initial_connection_window_size(http2.initial_connection_window_size)
+            .http2_max_frame_size(http2.max_frame_size)
+            .http2_keep_alive_interval(http2.keep_alive_interval_ms.map(Duration::from_millis))
+            .http2_keep_alive_while_idle(http2.keep_alive_while_idle);
+        if http2.adaptive_window {
+            builder.http2_adaptive_window(true);
+        }
+        if let Some(timeout_ms) = http2.keep_alive_timeout_ms {
+            builder.http2_keep_alive_timeout(Duration::from_millis(timeout_ms));
+        }
+        builder
+    }
+
+    /// Plain `http://` client. HTTP/2 uses persistent connections with native multiplexing
+    /// (h2c, prior knowledge).
+    fn create_http_client(
+        connector: &TrackedConnector,
+        config: &BackendPoolConfig,
+        http2: bool,
+    ) -> HttpClient {
+        let mut builder = Self::client_builder(config);
+        builder.http2_only(http2);
+        builder.build(connector.clone())
     }
 
-    fn create_http2_client(
+    fn create_authz_client(
         keep_alive: &KeepAliveConfig,
         config: &BackendPoolConfig,
         upstream_connect_ms: Option<u64>,
-    ) -> HttpClient {
-        let mut connector = HttpConnector::new();
-        // TCP keep-alive: sends periodic packets to keep TCP connection alive
-        // HTTP/2 uses persistent connections by default with native multiplexing
-        if keep_alive.enabled {
-            connector.set_keepalive(Some(Duration::from_secs(keep_alive.upstream_idle_timeout)));
-        } else {
-            connector.set_keepalive(None);
-        }
-        connector.set_connect_timeout(upstream_connect_ms.map(Duration::from_millis));
+    ) -> AuthzClient {
+        let connector = Self::create_connector(keep_alive, &config.tcp, upstream_connect_ms);
+        Self::client_builder(config).build(connector)
+    }
 
-        let mut builder = Client::builder(TokioExecutor::new());
-        builder.http2_only(true);
-        builder.pool_idle_timeout(Duration::from_secs(config.idle_timeout));
+    /// HTTPS client for one TLS backend. With a `version`, ALPN advertises only that protocol
+    /// (`h2` or `http/1.1`), so the backend cannot negotiate a different one; without, it offers
+    /// both and each connection speaks the one the backend picks. Connections are opened by
+    /// `connector`, which carries the backend's socket options and proxy.
+    fn create_https_client(
+        connector: &TrackedConnector,
+        tls: &Arc<ClientConfig>,
+        server_name: Option<&ServerName<'static>>,
+        config: &BackendPoolConfig,
+        version: Option<Version>,
+    ) -> HttpsClient {
+        let mut connector = connector.clone();
+        connector.enforce_http(false);
 
-        // Configure connection pool settings
-        if config.pool_max_idle_per_host > 0 {
-            builder.pool_max_idle_per_host(config.pool_max_idle_per_host);
-        }
+        let builder = HttpsConnectorBuilder::new()
+            .with_tls_config(ClientConfig::clone(tls))
+            .https_only();
+        let builder = match server_name {
+            Some(name) => {
+                builder.with_server_name_resolver(FixedServerNameResolver::new(name.clone()))
+            }
+            None => builder,
+        };
+        let http2 = version == Some(Version::HTTP_2);
+        let https = match version {
+            None => builder.enable_all_versions().wrap_connector(connector),
+            Some(_) if http2 => builder.enable_http2().wrap_connector(connector),
+            Some(_) => builder.enable_http1().wrap_connector(connector),
+        };
 
-        builder.build(connector)
+        let mut builder = Self::client_builder(config);
+        builder.http2_only(http2);
+        builder.build(https)
+    }
+
+    /// Client used for `ext_authz` checks.
+    pub fn authz_client(&self) -> &AuthzClient {
+        &self.authz
+    }
+
+    /// Whether `backend` is reached over `https://`.
+    pub fn is_tls_backend(&self, backend: &str) -> bool {
+        self.tls_backends.contains_key(backend)
+    }
+
+    /// Whether `backend` has its own pooled clients (a `tls`, `pool`, `discovery`, `tcp` or
+    /// `proxy` table, or a `unix:` address).
+    pub fn has_dedicated_pool(&self, backend: &str) -> bool {
+        self.tls_backends.contains_key(backend) || self.pooled_backends.contains_key(backend)
+    }
+
+    /// HTTPS client for a TLS backend, or `None` when `backend` has no `tls` table. `version`
+    /// `None` lets ALPN pick the protocol of each connection (`http_version = "auto"`).
+    ///
+    /// With `force_new`, a one-off client (no pooling) is built, mirroring
+    /// [`ClientPool::create_oneoff_client`].
+    pub fn get_tls_client(
+        &self,
+        backend: &str,
+        version: Option<Version>,
+        force_new: bool,
+    ) -> Option<Arc<HttpsClient>> {
+        let clients = self.tls_backends.get(backend)?;
+        if force_new {
+            return Some(Arc::new(Self::create_https_client(
+                &clients.connector,
+                &clients.tls,
+                clients.server_name.as_ref(),
+                &Self::oneoff_config(&clients.pool_config),
+                version,
+            )));
+        }
+        Some(Arc::clone(match version {
+            None => &clients.negotiated,
+            Some(Version::HTTP_2) => &clients.http2,
+            Some(_) => &clients.http11,
+        }))
     }
 
     /// Get the appropriate client for the given HTTP version
@@ -129,6 +467,66 @@ impl ClientPool {
         }
     }
 
+    /// [`ClientPool::get_client`] for a plain backend: its own clients when it has a `pool`,
+    /// `discovery` or `tcp` table or a `unix:` address, the shared ones otherwise.
+    pub fn get_backend_client(
+        &self,
+        backend: &str,
+        version: Version,
+        force_new: bool,
+    ) -> Option<&Arc<HttpClient>> {
+        if force_new {
+            return None;
+        }
+        match self.pooled_backends.get(backend) {
+            Some(clients) => Some(match version {
+                Version::HTTP_2 => &clients.http2,
+                _ => &clients.http11,
+            }),
+            None => self.get_client(version, false),
+        }
+    }
+
+    /// Wait for a free connection (HTTP/1.1) or stream (HTTP/2) of `backend`.
+    ///
+    /// Returns `Ok(None)` when the backend sets no limit for `version`. The permit must be held
+    /// until the response body is done (see [`PooledBody`]); past `pool.wait_timeout_ms` the
+    /// request is refused with [`PoolWaitTimeout`].
+    pub async fn acquire(
+        &self,
+        backend: &str,
+        version: Version,
+    ) -> std::result::Result<Option<OwnedSemaphorePermit>, PoolWaitTimeout> {
+        let Some(limiter) = self.limiters.get(backend) else {
+            return Ok(None);
+        };
+        let semaphore = match version {
+            Version::HTTP_2 => limiter.streams.as_ref(),
+            _ => limiter.connections.as_ref(),
+        };
+        let Some(semaphore) = semaphore else {
+            return Ok(None);
+        };
+        if let Ok(permit) = Arc::clone(semaphore).try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+        match tokio::time::timeout(limiter.wait, Arc::clone(semaphore).acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            // The semaphores are never closed; a closed one is treated like a full one.
+            Ok(Err(_)) | Err(_) => Err(PoolWaitTimeout),
+        }
+    }
+
+    /// `config` without pooling, for one-off clients.
+    fn oneoff_config(config: &BackendPoolConfig) -> BackendPoolConfig {
+        BackendPoolConfig {
+            enabled: false,
+            idle_timeout: 0,
+            pool_max_idle_per_host: 0,
+            ..config.clone()
+        }
+    }
+
     /// Create a one-off client for `force_new_connection` scenarios
     ///
     /// This client will NOT pool connections. Each request will establish
@@ -145,21 +543,89 @@ impl ClientPool {
     /// Creating a new client per request adds latency
     /// (TCP handshake + TLS handshake). Only use when necessary.
     pub fn create_oneoff_client(&self, version: Version) -> HttpClient {
-        // For one-off clients, disable pooling by setting max idle to 0
-        let oneoff_config =
-            BackendPoolConfig { enabled: false, idle_timeout: 0, pool_max_idle_per_host: 0 };
-
-        match version {
-            Version::HTTP_2 => Self::create_http2_client(
-                &self.keep_alive,
-                &oneoff_config,
-                self.upstream_connect_ms,
-            ),
-            _ => Self::create_http11_client(
-                &self.keep_alive,
-                &oneoff_config,
-                self.upstream_connect_ms,
-            ),
+        // For one-off clients, disable pooling (`enabled = false` keeps no idle connection)
+        let config = Self::oneoff_config(&self.config);
+        Self::create_http_client(&self.connector, &config, version == Version::HTTP_2)
+    }
+
+    /// [`ClientPool::create_oneoff_client`] for `backend`: connects through its unix socket when
+    /// it has a `unix:` address, with its socket options when it has a `tcp` table.
+    pub fn create_oneoff_backend_client(&self, backend: &str, version: Version) -> HttpClient {
+        let (connector, config) = self
+            .pooled_backends
+            .get(backend)
+            .map_or((&self.connector, &self.config), |clients| {
+                (&clients.connector, &clients.pool_config)
+            });
+        let config = Self::oneoff_config(config);
+        Self::create_http_client(connector, &config, version == Version::HTTP_2)
+    }
+}
+
+impl PlainBackendClients {
+    fn new(connector: TrackedConnector, pool_config: BackendPoolConfig) -> Self {
+        Self {
+            http11: Arc::new(ClientPool::create_http_client(&connector, &pool_config, false)),
+            http2: Arc::new(ClientPool::create_http_client(&connector, &pool_config, true)),
+            connector,
+            pool_config,
+        }
+    }
+}
+
+impl BackendLimiter {
+    /// `None` when `limits` sets neither `max_connections` nor `max_concurrent_streams`.
+    fn new(limits: &BackendPoolLimits) -> Option<Self> {
+        if limits.max_connections.is_none() && limits.max_concurrent_streams.is_none() {
+            return None;
+        }
+        Some(Self {
+            connections: limits.max_connections.map(|n| Arc::new(Semaphore::new(n))),
+            streams: limits
+                .max_concurrent_streams
+                .map(|n| Arc::new(Semaphore::new(n))),
+            wait: Duration::from_millis(limits.wait_timeout_ms),
+        })
+    }
+}
+
+/// Backend response body holding the connection or stream permit from [`ClientPool::acquire`]
+/// until it is fully read, fails or is dropped.
+pub struct PooledBody<B> {
+    inner: B,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl<B> PooledBody<B> {
+    pub fn new(inner: B, permit: Option<OwnedSemaphorePermit>) -> Self {
+        Self { inner, permit }
+    }
+}
+
+impl<B> Body for PooledBody<B>
+where
+    B: Body + Unpin,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_frame(cx);
+        if matches!(poll, Poll::Ready(None) | Poll::Ready(Some(Err(_)))) {
+            this.permit = None;
         }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
     }
 }
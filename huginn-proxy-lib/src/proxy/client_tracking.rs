@@ -0,0 +1,351 @@
+//! Cookie-based client tracking (`[client_tracking]`).
+//!
+//! A client is identified by an opaque cookie `<id>.<issued>.<mac>`: 16 random bytes in hex, the
+//! Unix time the cookie was issued and the hex HMAC-SHA256 of `v1\n<id>\n<issued>\n<ja4>` under
+//! the configured `secret`, so a client cannot pick its own ID and a cookie replayed from another
+//! TLS stack is not accepted. A record holds when the client was first and last seen and its
+//! request count, and is dropped `ttl_secs` after the client was last seen.
+//!
+//! No record is created for the request that is given a cookie: it is only written once the
+//! client sends the cookie back, seeded with the issuing request, so clients that drop cookies
+//! cannot fill the store.
+//!
+//! Records live in an in-memory LRU, or in Redis when a `redis` block is set. While Redis is
+//! unreachable or slower than its `timeout_ms`, the in-memory records are used and Redis is
+//! retried a second later, like the rate limiter does.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use aws_lc_rs::{hmac, rand};
+use http::header::SET_COOKIE;
+use http::{HeaderMap, HeaderName, HeaderValue, Response};
+use redis::{RedisResult, Script};
+
+use crate::config::{ClientTrackingConfig, RedisStoreConfig};
+use crate::error::{ProxyError, Result};
+use crate::proxy::handler::sticky::cookie_value;
+use crate::telemetry::metrics::values;
+use crate::telemetry::Metrics;
+use crate::utils::hex;
+use crate::utils::redis::RedisConnections;
+use crate::utils::time::unix_now;
+
+/// Version prefix of the signed cookie message.
+const VERSION: &str = "v1";
+
+/// Random bytes of a client ID.
+const ID_BYTES: usize = 16;
+
+/// `KEYS[1]`: the client's record. `ARGV`: now (Unix seconds), TTL (seconds), time of the request
+/// that issued the cookie to seed a missing record with (`0` for none).
+/// Returns `{first seen, previous last seen (0 for a new record), request count}`.
+const OBSERVE_SCRIPT: &str = r"
+if ARGV[3] ~= '0' and redis.call('EXISTS', KEYS[1]) == 0 then
+  redis.call('HSET', KEYS[1], 'first', ARGV[3], 'last', ARGV[3], 'count', 1)
+end
+redis.call('HSETNX', KEYS[1], 'first', ARGV[1])
+local last = redis.call('HGET', KEYS[1], 'last')
+redis.call('HSET', KEYS[1], 'last', ARGV[1])
+local count = redis.call('HINCRBY', KEYS[1], 'count', 1)
+redis.call('EXPIRE', KEYS[1], ARGV[2])
+return {tonumber(redis.call('HGET', KEYS[1], 'first')), tonumber(last or '0'), count}
+";
+
+/// What is known about a client once the current request is counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientRecord {
+    /// Unix time of the client's first request.
+    pub first_seen: u64,
+    /// Unix time of the client's previous request; `None` on its first.
+    pub last_seen: Option<u64>,
+    /// Requests of the client, this one included.
+    pub request_count: u64,
+}
+
+/// One tracked request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackedClient {
+    pub record: ClientRecord,
+    /// Cookie to set on the response, for a client that came without a valid one.
+    pub new_cookie: Option<String>,
+}
+
+/// A correctly signed tracking cookie.
+struct ClientCookie {
+    id: String,
+    issued: u64,
+}
+
+/// Issues tracking cookies and keeps the client records; shared by every connection.
+pub struct ClientTracker {
+    key: hmac::Key,
+    cookie_name: String,
+    ttl_secs: u64,
+    max_entries: usize,
+    first_seen_header: HeaderName,
+    last_seen_header: HeaderName,
+    request_count_header: HeaderName,
+    memory: Mutex<MemoryRecords>,
+    redis: Option<RedisRecords>,
+    metrics: Arc<Metrics>,
+}
+
+impl ClientTracker {
+    /// `None` when `config.enabled` is false. Fails on a header name or Redis URL that config
+    /// validation would have rejected.
+    pub fn from_config(
+        config: &ClientTrackingConfig,
+        metrics: Arc<Metrics>,
+    ) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let header = |name: &str| {
+            HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+                ProxyError::Config(format!("client_tracking: invalid header name '{name}': {e}"))
+            })
+        };
+        let redis = config
+            .redis
+            .as_ref()
+            .map(RedisRecords::new)
+            .transpose()
+            .map_err(|e| ProxyError::Config(format!("client_tracking.redis: {e}")))?;
+        Ok(Some(Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, config.secret.expose().as_bytes()),
+            cookie_name: config.cookie_name.clone(),
+            ttl_secs: config.ttl_secs,
+            max_entries: config.max_entries,
+            first_seen_header: header(&config.first_seen_header)?,
+            last_seen_header: header(&config.last_seen_header)?,
+            request_count_header: header(&config.request_count_header)?,
+            memory: Mutex::new(MemoryRecords::default()),
+            redis,
+            metrics,
+        }))
+    }
+
+    /// Count a request with `headers` on a connection with JA4 `ja4` (`None` without TLS), at the
+    /// current time. See [`track_at`](Self::track_at).
+    pub async fn track(&self, headers: &mut HeaderMap, ja4: Option<&str>) -> Option<TrackedClient> {
+        self.track_at(headers, ja4, unix_now()).await
+    }
+
+    /// Count a request at Unix time `now`: replace the tracking headers sent by the client with
+    /// the client's record. A client without a valid cookie is given a new ID, returned in
+    /// [`TrackedClient::new_cookie`]. `None` only when no random ID could be generated; the
+    /// request then goes on untracked.
+    pub async fn track_at(
+        &self,
+        headers: &mut HeaderMap,
+        ja4: Option<&str>,
+        now: u64,
+    ) -> Option<TrackedClient> {
+        headers.remove(&self.first_seen_header);
+        headers.remove(&self.last_seen_header);
+        headers.remove(&self.request_count_header);
+
+        let ja4 = ja4.unwrap_or_default();
+        let (record, new_cookie) = match self.valid_cookie(headers, ja4) {
+            Some(cookie) => {
+                // The request that issued the cookie seeds the record, unless it has expired.
+                let issued = Some(cookie.issued).filter(|&t| now < t.saturating_add(self.ttl_secs));
+                let key = format!("{}|{ja4}", cookie.id);
+                (self.observe(&key, now, issued).await, None)
+            }
+            None => {
+                let record = ClientRecord { first_seen: now, last_seen: None, request_count: 1 };
+                (record, Some(self.issue_cookie(ja4, now)?))
+            }
+        };
+        self.metrics
+            .record_client_tracking(if record.last_seen.is_some() {
+                values::CLIENT_RETURNING
+            } else {
+                values::CLIENT_NEW
+            });
+
+        for (name, value) in [
+            (&self.first_seen_header, Some(record.first_seen)),
+            (&self.last_seen_header, record.last_seen),
+            (&self.request_count_header, Some(record.request_count)),
+        ] {
+            if let Some(value) = value {
+                headers.insert(name.clone(), HeaderValue::from(value));
+            }
+        }
+        Some(TrackedClient { record, new_cookie })
+    }
+
+    /// Set the cookie of a client that came without a valid one on `response`.
+    pub fn set_cookie<B>(&self, response: &mut Response<B>, client: &TrackedClient, secure: bool) {
+        let Some(cookie) = &client.new_cookie else {
+            return;
+        };
+        let mut cookie = format!(
+            "{}={cookie}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+            self.cookie_name, self.ttl_secs
+        );
+        if secure {
+            cookie.push_str("; Secure");
+        }
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            response.headers_mut().append(SET_COOKIE, value);
+        }
+    }
+
+    /// Records held in memory.
+    pub fn memory_len(&self) -> usize {
+        self.memory
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .records
+            .len()
+    }
+
+    /// The tracking cookie of the request, if it has one signed for a connection with `ja4`.
+    fn valid_cookie(&self, headers: &HeaderMap, ja4: &str) -> Option<ClientCookie> {
+        let mut parts = cookie_value(headers, &self.cookie_name)?.splitn(3, '.');
+        let (id, issued, mac) = (parts.next()?, parts.next()?, parts.next()?);
+        if id.len() != ID_BYTES.saturating_mul(2) {
+            return None;
+        }
+        let issued = issued.parse().ok()?;
+        let mac = hex::decode(mac)?;
+        hmac::verify(&self.key, &message(id, issued, ja4), &mac).ok()?;
+        Some(ClientCookie { id: id.to_string(), issued })
+    }
+
+    fn issue_cookie(&self, ja4: &str, now: u64) -> Option<String> {
+        let mut bytes = [0u8; ID_BYTES];
+        rand::fill(&mut bytes).ok()?;
+        let id = hex::encode(&bytes);
+        let tag = hmac::sign(&self.key, &message(&id, now, ja4));
+        Some(format!("{id}.{now}.{}", hex::encode(tag.as_ref())))
+    }
+
+    /// Count a request of the client at `key`. A missing record is seeded with the request that
+    /// issued the cookie at `issued`, if given.
+    async fn observe(&self, key: &str, now: u64, issued: Option<u64>) -> ClientRecord {
+        if let Some(redis) = &self.redis {
+            let call = redis.observe(key, now, self.ttl_secs, issued);
+            if let Some(record) = redis.connections.run(call).await {
+                return record;
+            }
+        }
+        self.memory
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .observe(key, now, issued, self.ttl_secs, self.max_entries)
+    }
+}
+
+fn message(id: &str, issued: u64, ja4: &str) -> Vec<u8> {
+    format!("{VERSION}\n{id}\n{issued}\n{ja4}").into_bytes()
+}
+
+/// In-memory records, least recently seen dropped first.
+#[derive(Default)]
+struct MemoryRecords {
+    records: HashMap<String, MemoryRecord>,
+    /// Record keys by last use, least recent first.
+    lru: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+struct MemoryRecord {
+    first_seen: u64,
+    last_seen: u64,
+    request_count: u64,
+    last_used: u64,
+}
+
+impl MemoryRecords {
+    fn observe(
+        &mut self,
+        key: &str,
+        now: u64,
+        issued: Option<u64>,
+        ttl_secs: u64,
+        max_entries: usize,
+    ) -> ClientRecord {
+        let previous = self.records.remove(key);
+        if let Some(record) = &previous {
+            self.lru.remove(&record.last_used);
+        }
+        // A record not seen for `ttl_secs` has expired: the client starts over.
+        let previous = previous
+            .filter(|r| now < r.last_seen.saturating_add(ttl_secs))
+            .or_else(|| {
+                issued.map(|issued| MemoryRecord {
+                    first_seen: issued,
+                    last_seen: issued,
+                    request_count: 1,
+                    last_used: 0,
+                })
+            });
+        while self.records.len() >= max_entries {
+            let Some((_, oldest)) = self.lru.pop_first() else {
+                break;
+            };
+            self.records.remove(&oldest);
+        }
+        self.clock = self.clock.wrapping_add(1);
+        let record = MemoryRecord {
+            first_seen: previous.as_ref().map_or(now, |r| r.first_seen),
+            last_seen: now,
+            request_count: previous
+                .as_ref()
+                .map_or(1, |r| r.request_count.saturating_add(1)),
+            last_used: self.clock,
+        };
+        let observed = ClientRecord {
+            first_seen: record.first_seen,
+            last_seen: previous.map(|r| r.last_seen),
+            request_count: record.request_count,
+        };
+        self.lru.insert(self.clock, key.to_string());
+        self.records.insert(key.to_string(), record);
+        observed
+    }
+}
+
+/// Records in Redis, one hash per client.
+struct RedisRecords {
+    connections: RedisConnections,
+    script: Script,
+}
+
+impl RedisRecords {
+    /// Parse the URL; no connection is opened until the first request.
+    fn new(config: &RedisStoreConfig) -> RedisResult<Self> {
+        Ok(Self {
+            connections: RedisConnections::new(config, "Client tracking")?,
+            script: Script::new(OBSERVE_SCRIPT),
+        })
+    }
+
+    async fn observe(
+        &self,
+        key: &str,
+        now: u64,
+        ttl_secs: u64,
+        issued: Option<u64>,
+    ) -> RedisResult<ClientRecord> {
+        let mut connection = self.connections.connection().await?;
+        let mut invocation = self.script.prepare_invoke();
+        invocation
+            .key(format!("{}client:{key}", self.connections.key_prefix()))
+            .arg(now)
+            .arg(ttl_secs)
+            .arg(issued.unwrap_or_default());
+        let (first_seen, last_seen, request_count): (u64, u64, u64) =
+            invocation.invoke_async(&mut connection).await?;
+        Ok(ClientRecord {
+            first_seen,
+            last_seen: Some(last_seen).filter(|&last| last > 0),
+            request_count,
+        })
+    }
+}
@@ -0,0 +1,295 @@
+//! Response compression for `[compression]` and route-level `compression` blocks.
+//!
+//! The encoding is negotiated from the client's `Accept-Encoding` (RFC 9110 §12.5.3) before the
+//! request is forwarded; once the backend answers, eligible responses get their body re-encoded
+//! frame by frame, so large or streamed bodies are never buffered whole. Compression is
+//! synchronous and uses fast levels (Brotli 4, zstd 3, gzip 6) suited to on-the-fly use.
+
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::Bytes;
+use http::header::{
+    ACCEPT_ENCODING, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE,
+    CONTENT_TYPE, ETAG, VARY,
+};
+use http::{HeaderMap, HeaderValue, Method, Response, StatusCode};
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame};
+
+use crate::config::{CompressionAlgorithm, CompressionConfig};
+use crate::utils::http::{BoxError, RespBody};
+
+const BROTLI_QUALITY: u32 = 4;
+const BROTLI_WINDOW: u32 = 22;
+const BROTLI_BUFFER: usize = 4096;
+const ZSTD_LEVEL: i32 = 3;
+
+/// Pick the encoding for a request, or `None` when the response must stay uncompressed.
+///
+/// The highest `q` among `algorithms` wins; equal weights go to the earlier entry of
+/// `algorithms`. A `*` entry stands for every coding not listed explicitly and `q=0` excludes.
+/// `HEAD` requests are never compressed: their responses describe the uncompressed body.
+pub fn negotiate(
+    method: &Method,
+    headers: &HeaderMap,
+    algorithms: &[CompressionAlgorithm],
+) -> Option<CompressionAlgorithm> {
+    if method == Method::HEAD {
+        return None;
+    }
+    let offered: Vec<(&str, f32)> = headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(parse_coding)
+        .collect();
+
+    let quality = |coding: &str| {
+        offered
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(coding))
+            .or_else(|| offered.iter().find(|(name, _)| *name == "*"))
+            .map_or(0.0, |(_, q)| *q)
+    };
+
+    let mut best: Option<(CompressionAlgorithm, f32)> = None;
+    for &algorithm in algorithms {
+        let q = quality(algorithm.as_str());
+        let better = match best {
+            Some((_, best_q)) => q > best_q,
+            None => q > 0.0,
+        };
+        if better {
+            best = Some((algorithm, q));
+        }
+    }
+    best.map(|(algorithm, _)| algorithm)
+}
+
+/// One `coding;q=x` element of `Accept-Encoding`. Elements with an unparsable weight are dropped.
+fn parse_coding(element: &str) -> Option<(&str, f32)> {
+    let mut params = element.split(';');
+    let coding = params.next()?.trim();
+    if coding.is_empty() {
+        return None;
+    }
+    let mut q = 1.0;
+    for param in params {
+        if let Some((name, value)) = param.split_once('=') {
+            if name.trim().eq_ignore_ascii_case("q") {
+                q = value.trim().parse::<f32>().ok()?;
+            }
+        }
+    }
+    Some((coding, q))
+}
+
+/// `true` when `config` allows compressing `resp`: a status that carries a full body, no
+/// existing `Content-Encoding` or `Content-Range`, no `Cache-Control: no-transform`, an
+/// allowlisted `Content-Type` and a `Content-Length` (if declared) of at least `min_size_bytes`.
+pub fn is_compressible<B>(config: &CompressionConfig, resp: &Response<B>) -> bool {
+    if !is_transformable(resp) {
+        return false;
+    }
+    let headers = resp.headers();
+    let content_type_allowed = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| config.allows_content_type(ct));
+    if !content_type_allowed {
+        return false;
+    }
+    let too_small = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .is_some_and(|len| len < config.min_size_bytes);
+    !too_small
+}
+
+/// `true` when the proxy may change the body of `resp`: a status that carries a full body, no
+/// existing `Content-Encoding` or `Content-Range` and no `Cache-Control: no-transform`.
+pub(crate) fn is_transformable<B>(resp: &Response<B>) -> bool {
+    let status = resp.status();
+    if status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+        || status == StatusCode::PARTIAL_CONTENT
+    {
+        return false;
+    }
+    let headers = resp.headers();
+    let already_encoded = headers
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| !v.trim().eq_ignore_ascii_case("identity"));
+    if already_encoded || headers.contains_key(CONTENT_RANGE) {
+        return false;
+    }
+    let no_transform = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|d| d.trim().eq_ignore_ascii_case("no-transform"));
+    !no_transform
+}
+
+/// Re-encode `resp` with `algorithm` and fix up its representation headers: `Content-Encoding`
+/// is set, `Content-Length` and `Accept-Ranges` are dropped, `Vary: Accept-Encoding` is added and
+/// a strong `ETag` becomes weak. If the encoder cannot be created the response is returned as-is.
+pub fn compress_response(
+    resp: Response<RespBody>,
+    algorithm: CompressionAlgorithm,
+) -> Response<RespBody> {
+    let encoder = match Encoder::new(algorithm) {
+        Ok(encoder) => encoder,
+        Err(e) => {
+            tracing::warn!(error = %e, encoding = algorithm.as_str(), "compression disabled");
+            return resp;
+        }
+    };
+    let (mut parts, body) = resp.into_parts();
+    let headers = &mut parts.headers;
+    headers.remove(CONTENT_LENGTH);
+    headers.remove(ACCEPT_RANGES);
+    headers.insert(CONTENT_ENCODING, HeaderValue::from_static(algorithm.as_str()));
+    let varies = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|v| v.trim() == "*" || v.trim().eq_ignore_ascii_case("accept-encoding"));
+    if !varies {
+        headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    if let Some(weak) = headers.get(ETAG).and_then(weak_etag) {
+        headers.insert(ETAG, weak);
+    }
+    let body = CompressedBody { inner: body, encoder: Some(encoder), trailers: None };
+    Response::from_parts(parts, body.boxed())
+}
+
+/// `W/"x"` for a strong `"x"`; `None` when the tag is already weak or unreadable.
+pub(crate) fn weak_etag(etag: &HeaderValue) -> Option<HeaderValue> {
+    let tag = etag.to_str().ok()?;
+    if tag.starts_with("W/") {
+        return None;
+    }
+    HeaderValue::from_str(&format!("W/{tag}")).ok()
+}
+
+enum Encoder {
+    Br(Box<brotli::CompressorWriter<Vec<u8>>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    fn new(algorithm: CompressionAlgorithm) -> io::Result<Self> {
+        Ok(match algorithm {
+            CompressionAlgorithm::Br => Self::Br(Box::new(brotli::CompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER,
+                BROTLI_QUALITY,
+                BROTLI_WINDOW,
+            ))),
+            CompressionAlgorithm::Zstd => {
+                Self::Zstd(zstd::stream::write::Encoder::new(Vec::new(), ZSTD_LEVEL)?)
+            }
+            CompressionAlgorithm::Gzip => Self::Gzip(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            )),
+        })
+    }
+
+    /// Feed `data` and return whatever compressed output is ready (possibly nothing).
+    fn encode(&mut self, data: &[u8]) -> io::Result<Bytes> {
+        let out = match self {
+            Self::Br(w) => {
+                w.write_all(data)?;
+                w.get_mut()
+            }
+            Self::Zstd(w) => {
+                w.write_all(data)?;
+                w.get_mut()
+            }
+            Self::Gzip(w) => {
+                w.write_all(data)?;
+                w.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(out)))
+    }
+
+    /// Terminate the stream and return the remaining output.
+    fn finish(self) -> io::Result<Bytes> {
+        let out = match self {
+            Self::Br(w) => w.into_inner(),
+            Self::Zstd(w) => w.finish()?,
+            Self::Gzip(w) => w.finish()?,
+        };
+        Ok(Bytes::from(out))
+    }
+}
+
+/// Body wrapper that compresses data frames; trailers are forwarded after the final block.
+struct CompressedBody {
+    inner: RespBody,
+    encoder: Option<Encoder>,
+    trailers: Option<HeaderMap>,
+}
+
+impl Body for CompressedBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(trailers) = this.trailers.take() {
+                return Poll::Ready(Some(Ok(Frame::trailers(trailers))));
+            }
+            let Some(encoder) = this.encoder.as_mut() else {
+                return Poll::Ready(None);
+            };
+            match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => {
+                        let out = encoder.encode(&data)?;
+                        if !out.is_empty() {
+                            return Poll::Ready(Some(Ok(Frame::data(out))));
+                        }
+                    }
+                    Err(frame) => {
+                        // The final compressed block must precede the trailers.
+                        this.trailers = frame.into_trailers().ok();
+                        return Poll::Ready(Some(Ok(Frame::data(this.finish()?))));
+                    }
+                },
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(Some(Ok(Frame::data(this.finish()?)))),
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.encoder.is_none() && self.trailers.is_none()
+    }
+}
+
+impl CompressedBody {
+    fn finish(&mut self) -> io::Result<Bytes> {
+        match self.encoder.take() {
+            Some(encoder) => encoder.finish(),
+            None => Ok(Bytes::new()),
+        }
+    }
+}
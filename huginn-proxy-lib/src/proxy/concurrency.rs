@@ -0,0 +1,420 @@
+//! `max_in_flight` concurrency limits of routes and backends.
+//!
+//! Each limited route and backend owns a semaphore with `max_in_flight` permits, created on first
+//! use. A request takes its permits without waiting and keeps them, together with the
+//! in-flight gauges, until its response body is done (see [`InFlightBody`]).
+//!
+//! A backend with a `queue` owns a [`BackendQueue`] instead: requests over the limit wait for a
+//! slot there, and a freed slot goes to the highest-priority waiter.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+
+use hyper::body::{Body, Frame, SizeHint};
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+
+use crate::config::{BackendQueueConfig, RoutePriority};
+use crate::telemetry::Metrics;
+
+/// Semaphores of the routes and backends that set `max_in_flight`, shared by every connection.
+#[derive(Debug, Default)]
+pub struct ConcurrencyLimits {
+    routes: RwLock<HashMap<String, Limit>>,
+    backends: RwLock<HashMap<String, Limit>>,
+    queues: RwLock<HashMap<String, Arc<BackendQueue>>>,
+}
+
+#[derive(Debug)]
+struct Limit {
+    max: usize,
+    semaphore: Arc<Semaphore>,
+}
+
+impl ConcurrencyLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a slot of route `prefix` on `domain`. `None` when `max` requests are already in
+    /// flight on it.
+    pub fn try_acquire_route(
+        &self,
+        domain: &str,
+        prefix: &str,
+        max: usize,
+    ) -> Option<OwnedSemaphorePermit> {
+        try_acquire(&self.routes, &format!("{domain}{prefix}"), max)
+    }
+
+    /// Take a slot of the backend at `address`. `None` when `max` requests are already in flight
+    /// to it.
+    pub fn try_acquire_backend(&self, address: &str, max: usize) -> Option<OwnedSemaphorePermit> {
+        try_acquire(&self.backends, address, max)
+    }
+
+    /// Queue of the backend at `address`. One whose `max` or `queue` no longer matches (hot
+    /// reload) is replaced; requests waiting in the old one are still served by it.
+    pub fn backend_queue(
+        &self,
+        address: &str,
+        max: usize,
+        queue: &BackendQueueConfig,
+    ) -> Arc<BackendQueue> {
+        let current = self
+            .queues
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(address)
+            .filter(|q| q.max == max && q.config == *queue)
+            .map(Arc::clone);
+        if let Some(current) = current {
+            return current;
+        }
+        let mut map = self.queues.write().unwrap_or_else(|e| e.into_inner());
+        let entry = map
+            .entry(address.to_string())
+            .or_insert_with(|| Arc::new(BackendQueue::new(max, *queue)));
+        if entry.max != max || entry.config != *queue {
+            *entry = Arc::new(BackendQueue::new(max, *queue));
+        }
+        Arc::clone(entry)
+    }
+}
+
+/// A limit whose `max` no longer matches (hot reload) is replaced with a fresh semaphore; requests
+/// still holding permits of the old one release them there.
+fn try_acquire(
+    limits: &RwLock<HashMap<String, Limit>>,
+    key: &str,
+    max: usize,
+) -> Option<OwnedSemaphorePermit> {
+    let current = limits
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(key)
+        .filter(|limit| limit.max == max)
+        .map(|limit| Arc::clone(&limit.semaphore));
+    let semaphore = match current {
+        Some(semaphore) => semaphore,
+        None => {
+            let mut map = limits.write().unwrap_or_else(|e| e.into_inner());
+            let limit = map
+                .entry(key.to_string())
+                .or_insert_with(|| Limit { max, semaphore: Arc::new(Semaphore::new(max)) });
+            if limit.max != max {
+                *limit = Limit { max, semaphore: Arc::new(Semaphore::new(max)) };
+            }
+            Arc::clone(&limit.semaphore)
+        }
+    };
+    semaphore.try_acquire_owned().ok()
+}
+
+/// Why a request left a [`BackendQueue`] without a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueRejection {
+    /// The queue was full of requests of the same or a higher priority, or a higher-priority
+    /// request took this one's place.
+    Full,
+    /// No slot was freed within `timeout_ms`.
+    Timeout,
+}
+
+/// `max_in_flight` slots of a backend with a `queue`, and the requests waiting for one.
+#[derive(Debug)]
+pub struct BackendQueue {
+    max: usize,
+    config: BackendQueueConfig,
+    state: Mutex<QueueState>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    in_flight: usize,
+    /// Waiting requests by priority, each oldest first.
+    waiting: BTreeMap<RoutePriority, VecDeque<Waiter>>,
+    queued: usize,
+    next_id: u64,
+}
+
+/// A waiting request. `true` hands it a slot; `false` tells it it was displaced.
+#[derive(Debug)]
+struct Waiter {
+    id: u64,
+    grant: oneshot::Sender<bool>,
+}
+
+impl QueueState {
+    /// Remove the oldest waiter of the highest priority.
+    fn pop_first(&mut self) -> Option<Waiter> {
+        let waiter = self
+            .waiting
+            .values_mut()
+            .rev()
+            .find_map(VecDeque::pop_front)?;
+        self.queued = self.queued.saturating_sub(1);
+        Some(waiter)
+    }
+
+    /// Remove the newest waiter of the lowest priority, if it is below `priority`.
+    fn pop_below(&mut self, priority: RoutePriority) -> Option<Waiter> {
+        let waiter = self
+            .waiting
+            .range_mut(..priority)
+            .find_map(|(_, waiters)| waiters.pop_back())?;
+        self.queued = self.queued.saturating_sub(1);
+        Some(waiter)
+    }
+
+    /// Remove waiter `id`; `false` when it has already left the queue.
+    fn remove(&mut self, priority: RoutePriority, id: u64) -> bool {
+        let Some(waiters) = self.waiting.get_mut(&priority) else {
+            return false;
+        };
+        let Some(pos) = waiters.iter().position(|w| w.id == id) else {
+            return false;
+        };
+        waiters.remove(pos);
+        self.queued = self.queued.saturating_sub(1);
+        true
+    }
+}
+
+impl BackendQueue {
+    pub fn new(max: usize, config: BackendQueueConfig) -> Self {
+        Self { max, config, state: Mutex::new(QueueState::default()) }
+    }
+
+    /// Take a slot, waiting up to `timeout_ms` behind the requests of the same or a higher
+    /// `priority` when all `max` are in use. Time spent waiting is counted in the backend's
+    /// `huginn_backend_queued_requests`.
+    pub async fn acquire(
+        self: &Arc<Self>,
+        priority: RoutePriority,
+        address: &str,
+        metrics: &Arc<Metrics>,
+    ) -> Result<QueuePermit, QueueRejection> {
+        let (id, grant) = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.in_flight < self.max {
+                state.in_flight = state.in_flight.saturating_add(1);
+                return Ok(QueuePermit { queue: Arc::clone(self) });
+            }
+            if state.queued >= self.config.max_queued {
+                // Make room by displacing a lower-priority request, or give up.
+                let displaced = state.pop_below(priority).ok_or(QueueRejection::Full)?;
+                let _ = displaced.grant.send(false);
+            }
+            let (tx, rx) = oneshot::channel();
+            let id = state.next_id;
+            state.next_id = id.wrapping_add(1);
+            state
+                .waiting
+                .entry(priority)
+                .or_default()
+                .push_back(Waiter { id, grant: tx });
+            state.queued = state.queued.saturating_add(1);
+            (id, rx)
+        };
+        let mut waiting = Waiting {
+            queue: Arc::clone(self),
+            priority,
+            id,
+            grant,
+            done: false,
+            metrics: Arc::clone(metrics),
+            address: address.to_string(),
+        };
+        metrics.record_backend_queued(1, address);
+        let granted = match tokio::time::timeout(self.config.timeout(), &mut waiting.grant).await {
+            Ok(result) => result.ok(),
+            // A slot may have been handed over right as the wait ran out.
+            Err(_) => waiting.leave(),
+        };
+        waiting.done = true;
+        match granted {
+            Some(true) => Ok(QueuePermit { queue: Arc::clone(self) }),
+            Some(false) => Err(QueueRejection::Full),
+            None => Err(QueueRejection::Timeout),
+        }
+    }
+
+    /// Hand a freed slot to the next waiter, or return it.
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some(waiter) = state.pop_first() {
+            if waiter.grant.send(true).is_ok() {
+                return;
+            }
+        }
+        state.in_flight = state.in_flight.saturating_sub(1);
+    }
+}
+
+/// A request in a [`BackendQueue`]; leaves it when dropped (e.g. the client went away), passing
+/// on a slot it was handed in the meantime.
+struct Waiting {
+    queue: Arc<BackendQueue>,
+    priority: RoutePriority,
+    id: u64,
+    grant: oneshot::Receiver<bool>,
+    done: bool,
+    metrics: Arc<Metrics>,
+    address: String,
+}
+
+impl Waiting {
+    /// Leave the queue. Returns what the request was sent if it had already been taken out
+    /// (a slot or a displacement), `None` if it was still waiting.
+    fn leave(&mut self) -> Option<bool> {
+        let removed = self
+            .queue
+            .state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(self.priority, self.id);
+        // Waiters are only ever taken out under the lock, together with their message.
+        if removed {
+            None
+        } else {
+            self.grant.try_recv().ok()
+        }
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        self.metrics.record_backend_queued(-1, &self.address);
+        if !self.done && self.leave() == Some(true) {
+            self.queue.release();
+        }
+    }
+}
+
+/// A slot of a [`BackendQueue`], handed to the next waiter when dropped.
+#[derive(Debug)]
+pub struct QueuePermit {
+    queue: Arc<BackendQueue>,
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
+
+/// Backend slot held by an [`InFlight`].
+#[derive(Debug)]
+pub enum BackendPermit {
+    Immediate(OwnedSemaphorePermit),
+    Queued(QueuePermit),
+}
+
+impl From<OwnedSemaphorePermit> for BackendPermit {
+    fn from(permit: OwnedSemaphorePermit) -> Self {
+        BackendPermit::Immediate(permit)
+    }
+}
+
+impl From<QueuePermit> for BackendPermit {
+    fn from(permit: QueuePermit) -> Self {
+        BackendPermit::Queued(permit)
+    }
+}
+
+/// Permits of one request, counted in the in-flight gauges until dropped.
+pub struct InFlight {
+    metrics: Arc<Metrics>,
+    route: Option<RouteSlot>,
+    backend: Option<BackendSlot>,
+}
+
+struct RouteSlot {
+    _permit: OwnedSemaphorePermit,
+    prefix: String,
+    domain: String,
+}
+
+struct BackendSlot {
+    _permit: BackendPermit,
+    address: String,
+}
+
+impl InFlight {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self { metrics, route: None, backend: None }
+    }
+
+    /// Count the request in route `prefix`'s gauge while `permit` is held.
+    pub fn with_route(mut self, permit: OwnedSemaphorePermit, prefix: &str, domain: &str) -> Self {
+        self.metrics.record_route_in_flight(1, prefix, domain);
+        self.route = Some(RouteSlot {
+            _permit: permit,
+            prefix: prefix.to_string(),
+            domain: domain.to_string(),
+        });
+        self
+    }
+
+    /// Count the request in the gauge of the backend at `address` while `permit` is held.
+    pub fn with_backend(mut self, permit: impl Into<BackendPermit>, address: &str) -> Self {
+        self.metrics.record_backend_in_flight(1, address);
+        self.backend = Some(BackendSlot { _permit: permit.into(), address: address.to_string() });
+        self
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Some(route) = &self.route {
+            self.metrics
+                .record_route_in_flight(-1, &route.prefix, &route.domain);
+        }
+        if let Some(backend) = &self.backend {
+            self.metrics.record_backend_in_flight(-1, &backend.address);
+        }
+    }
+}
+
+/// Response body holding the request's [`InFlight`] permits until it is fully read, fails or is
+/// dropped.
+pub struct InFlightBody<B> {
+    inner: B,
+    in_flight: Option<InFlight>,
+}
+
+impl<B> InFlightBody<B> {
+    pub fn new(inner: B, in_flight: InFlight) -> Self {
+        Self { inner, in_flight: Some(in_flight) }
+    }
+}
+
+impl<B> Body for InFlightBody<B>
+where
+    B: Body + Unpin,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_frame(cx);
+        if matches!(poll, Poll::Ready(None) | Poll::Ready(Some(Err(_)))) {
+            this.in_flight = None;
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,213 @@
+//! `CONNECT` tunnels (forward proxy mode) for routes with a `connect` block.
+//!
+//! The target is dialled before the client is answered, so an unreachable target gets a `502`
+//! instead of a tunnel that closes at once. Once the `200` is sent, the client connection is
+//! taken over from hyper and spliced with the target by a spawned task, bounded only by
+//! `connect.idle_timeout_secs`. Tunnels to a configured backend with a `tls` block carry TLS the
+//! proxy does not terminate: the client's first record there is its ClientHello, which is read,
+//! fingerprinted and then passed on unchanged, so internal egress traffic is labelled with the
+//! same JA4 fingerprints as traffic the proxy terminates.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::upgrade::OnUpgrade;
+use hyper::Request;
+use hyper_util::rt::TokioIo;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tracing::debug;
+
+use crate::config::dynamic::connect::split_target;
+use crate::config::{Backend, ConnectConfig};
+use crate::fingerprinting::read_client_hello;
+use crate::proxy::http_result::{HttpError, HttpResult};
+use crate::proxy::route_timeout::TimeoutKind;
+use crate::proxy::router::RouteMatch;
+use crate::proxy::websocket::{splice, TunnelEnd};
+use crate::telemetry::{FingerprintStats, HandshakeCapture, HandshakeRecord, Metrics};
+use crate::utils::http::{empty_body, RespBody};
+
+/// Listener settings and telemetry sinks used by the tunnels of a connection.
+#[derive(Clone)]
+pub struct TunnelContext {
+    /// `[timeout] upstream_connect_ms`; a route's `timeout.connect_ms` replaces it. `None` waits
+    /// for the OS connect timeout.
+    pub connect_timeout: Option<Duration>,
+    /// Time the client gets to send its inner ClientHello, `[timeout] tls_handshake_secs`.
+    pub client_hello_timeout: Duration,
+    /// `[handshake_capture]` writer for inner ClientHellos.
+    pub handshake_capture: HandshakeCapture,
+    /// `[telemetry.fingerprint_stats]` counters, fed with the JA4 of inner ClientHellos.
+    pub fingerprint_stats: FingerprintStats,
+}
+
+impl Default for TunnelContext {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            client_hello_timeout: Duration::from_secs(15),
+            handshake_capture: HandshakeCapture::disabled(),
+            fingerprint_stats: FingerprintStats::disabled(),
+        }
+    }
+}
+
+/// Target of a `CONNECT` request: the host (lowercased, without IPv6 brackets) and port of its
+/// authority-form request target. `None` when the target has no port.
+pub fn connect_target<B>(req: &Request<B>) -> Option<(String, u16)> {
+    let authority = req.uri().authority()?;
+    let port = authority.port_u16()?;
+    let host = authority.host();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    Some((host.to_ascii_lowercase(), port))
+}
+
+/// The configured backend whose address is `host:port`, if any.
+pub fn target_backend<'a>(backends: &'a [Backend], host: &str, port: u16) -> Option<&'a Backend> {
+    backends
+        .iter()
+        .find(|backend| same_target(&backend.address, host, port))
+}
+
+/// Whether a tunnel of `route_match` may reach `host:port`: it is the route's backend or one of
+/// its `connect.targets`.
+pub fn target_allowed(route_match: &RouteMatch<'_>, host: &str, port: u16) -> bool {
+    same_target(route_match.backend, host, port)
+        || route_match
+            .connect
+            .is_some_and(|connect| connect.allows(host, port))
+}
+
+fn same_target(address: &str, host: &str, port: u16) -> bool {
+    split_target(address)
+        .is_some_and(|(h, p)| h.eq_ignore_ascii_case(host) && p.parse::<u16>() == Ok(port))
+}
+
+/// Answer a `CONNECT` request of `route_match`: check and dial its target, then reply `200` and
+/// splice the two connections in a spawned task. Returns the response with the `host:port` the
+/// tunnel goes to.
+pub async fn open_tunnel<B>(
+    req: &mut Request<B>,
+    connect: &ConnectConfig,
+    route_match: &RouteMatch<'_>,
+    backends: &[Backend],
+    tunnels: &TunnelContext,
+    metrics: &Arc<Metrics>,
+    peer: SocketAddr,
+) -> HttpResult<(hyper::Response<RespBody>, String)> {
+    let Some((host, port)) = connect_target(req) else {
+        return Err(HttpError::InvalidUri(format!(
+            "CONNECT needs a host:port target, got '{}'",
+            req.uri()
+        )));
+    };
+    let target = if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    };
+    if !target_allowed(route_match, &host, port) {
+        return Err(HttpError::ConnectTargetRefused(target));
+    }
+
+    let limit = route_match
+        .timeout
+        .and_then(|timeout| timeout.connect_ms)
+        .map(Duration::from_millis)
+        .or(tunnels.connect_timeout);
+    let dial = TcpStream::connect((host.as_str(), port));
+    let upstream = match limit {
+        Some(limit) => tokio::time::timeout(limit, dial)
+            .await
+            .map_err(|_| HttpError::BackendTimeout(TimeoutKind::Connect))?,
+        None => dial.await,
+    }
+    .map_err(|e| HttpError::FailedToGetResponseFromBackend(format!("{target}: {e}")))?;
+
+    let inspect = connect.fingerprint
+        && target_backend(backends, &host, port).is_some_and(|backend| backend.tls.is_some());
+    let client = hyper::upgrade::on(req);
+    let tunnel = Tunnel {
+        peer,
+        target: target.clone(),
+        inspect,
+        idle_timeout: connect.idle_timeout(),
+        tunnels: tunnels.clone(),
+        metrics: Arc::clone(metrics),
+    };
+    tokio::spawn(tunnel.run(client, upstream));
+    debug!(?peer, target = %target, inspect, "CONNECT tunnel opened");
+
+    let mut response = hyper::Response::new(empty_body());
+    *response.status_mut() = http::StatusCode::OK;
+    Ok((response, target))
+}
+
+/// One tunnel, from the client's upgrade to the end of the splice.
+struct Tunnel {
+    peer: SocketAddr,
+    target: String,
+    /// Read and fingerprint the client's ClientHello before splicing.
+    inspect: bool,
+    idle_timeout: Duration,
+    tunnels: TunnelContext,
+    metrics: Arc<Metrics>,
+}
+
+impl Tunnel {
+    async fn run(self, client: OnUpgrade, mut upstream: TcpStream) {
+        let mut client = match client.await {
+            Ok(upgraded) => TokioIo::new(upgraded),
+            Err(e) => {
+                debug!(peer = ?self.peer, error = %e, "CONNECT upgrade failed");
+                return;
+            }
+        };
+
+        if self.inspect {
+            let read = read_client_hello(&mut client, Arc::clone(&self.metrics));
+            let limit = self.tunnels.client_hello_timeout;
+            let (prefix, ja4) = match tokio::time::timeout(limit, read).await {
+                Ok(Ok(hello)) => hello,
+                Ok(Err(e)) => {
+                    debug!(peer = ?self.peer, error = %e, "no ClientHello in tunnel");
+                    return;
+                }
+                Err(_) => {
+                    debug!(peer = ?self.peer, target = %self.target, "no ClientHello in tunnel");
+                    return;
+                }
+            };
+            let ja4_full = ja4.as_ref().map(|f| f.ja4.full.to_string());
+            debug!(
+                peer = ?self.peer,
+                target = %self.target,
+                ja4 = ja4_full.as_deref().unwrap_or("-"),
+                "fingerprinted ClientHello in CONNECT tunnel"
+            );
+            self.tunnels
+                .fingerprint_stats
+                .observe(self.peer.ip(), ja4_full.as_deref(), None);
+            self.tunnels
+                .handshake_capture
+                .capture(|| HandshakeRecord::new(self.peer, &prefix, ja4.as_ref(), None));
+            if let Err(e) = upstream.write_all(&prefix).await {
+                debug!(target = %self.target, error = %e, "CONNECT target closed");
+                return;
+            }
+        }
+
+        match splice(client, upstream, self.idle_timeout).await {
+            Ok(TunnelEnd::Closed) => {}
+            Ok(TunnelEnd::IdleTimeout) => {
+                debug!(target = %self.target, "CONNECT tunnel closed after idle timeout");
+            }
+            Err(e) => debug!(target = %self.target, error = %e, "CONNECT tunnel closed with error"),
+        }
+    }
+}
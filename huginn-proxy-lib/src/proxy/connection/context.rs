@@ -0,0 +1,152 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use huginn_net_http::AkamaiFingerprint;
+use tokio::sync::watch;
+
+use crate::fingerprinting::Ja4Fingerprints;
+use crate::security::TlsMetadata;
+
+/// Source of connection ids; unique for the life of the process, across listeners and reloads.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// What the proxy knows about one client connection, gathered once as the connection is accepted
+/// and handshaken, and shared by every request served on it.
+///
+/// The context is created when the connection is accepted (with its TCP SYN signature, if any),
+/// receives its TLS details after the handshake and its Akamai receiver when HTTP/2 fingerprinting
+/// starts. TLS details are set once; an h2c upgrade replaces the Akamai receiver. Every request
+/// of the connection carries an `Arc<ConnectionContext>` extension, so middleware layers and the
+/// request handler read the same values; the access log and the admin API's
+/// `/admin/connections` use it too.
+#[derive(Debug)]
+pub struct ConnectionContext {
+    id: u64,
+    peer: SocketAddr,
+    started: SystemTime,
+    tcp_syn: Option<Arc<str>>,
+    tls: OnceLock<ConnectionTls>,
+    akamai: Mutex<Option<watch::Receiver<Option<AkamaiFingerprint>>>>,
+}
+
+/// TLS details of a connection, set once its handshake completed (or, for passthrough, once its
+/// ClientHello was read).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionTls {
+    /// SNI sent by the client
+    pub sni: Option<Arc<str>>,
+    /// ALPN protocol agreed on; `None` on passthrough, where the proxy does not terminate TLS
+    pub alpn: Option<Arc<str>>,
+    /// JA4 fingerprints of the ClientHello, when TLS fingerprinting is enabled
+    pub ja4: Option<Ja4Set>,
+}
+
+/// The JA4 variants of a ClientHello, as strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ja4Set {
+    /// Normalized JA4 (`ja4`)
+    pub ja4: Arc<str>,
+    /// Not normalized JA4 (`ja4_o`)
+    pub ja4_o: Arc<str>,
+    /// Normalized and stable JA4 (`ja4_s1`)
+    pub ja4_s1: Arc<str>,
+}
+
+impl From<&Ja4Fingerprints> for Ja4Set {
+    fn from(fingerprints: &Ja4Fingerprints) -> Self {
+        Self {
+            ja4: Arc::from(fingerprints.ja4.full.to_string()),
+            ja4_o: Arc::from(fingerprints.ja4_original.full.to_string()),
+            ja4_s1: Arc::from(fingerprints.ja4_stable_v1.full.to_string()),
+        }
+    }
+}
+
+impl ConnectionContext {
+    /// A context for a connection from `peer`, the effective client address (after PROXY
+    /// protocol resolution), with the next connection id.
+    pub fn new(peer: SocketAddr) -> Self {
+        Self {
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            peer,
+            started: SystemTime::now(),
+            tcp_syn: None,
+            tls: OnceLock::new(),
+            akamai: Mutex::new(None),
+        }
+    }
+
+    /// TCP SYN signature observed when the connection was accepted.
+    pub fn with_tcp_syn(mut self, signature: String) -> Self {
+        self.tcp_syn = Some(Arc::from(signature));
+        self
+    }
+
+    /// Record the TLS details of the connection; ignored once set.
+    pub fn set_tls(&self, tls: ConnectionTls) {
+        self.tls.set(tls).ok();
+    }
+
+    /// The Akamai fingerprint is read from `rx` on each access, so it shows up as soon as the
+    /// HTTP/2 extractor publishes it. Replaces the receiver of an earlier call.
+    pub fn set_akamai(&self, rx: watch::Receiver<Option<AkamaiFingerprint>>) {
+        *self.akamai.lock().unwrap_or_else(|e| e.into_inner()) = Some(rx);
+    }
+
+    /// Process-wide unique id of the connection, as listed by `/admin/connections` and written as
+    /// `connection_id` in the access log.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// When the connection was accepted.
+    pub fn started(&self) -> SystemTime {
+        self.started
+    }
+
+    /// How long the connection has been open.
+    pub fn age(&self) -> Duration {
+        self.started.elapsed().unwrap_or(Duration::ZERO)
+    }
+
+    /// TLS details; `None` on a plaintext connection or before the handshake completed.
+    pub fn tls(&self) -> Option<&ConnectionTls> {
+        self.tls.get()
+    }
+
+    pub fn sni(&self) -> Option<&str> {
+        self.tls().and_then(|tls| tls.sni.as_deref())
+    }
+
+    pub fn alpn(&self) -> Option<&str> {
+        self.tls().and_then(|tls| tls.alpn.as_deref())
+    }
+
+    pub fn ja4(&self) -> Option<&Ja4Set> {
+        self.tls().and_then(|tls| tls.ja4.as_ref())
+    }
+
+    /// Akamai fingerprint, once the HTTP/2 extractor published it.
+    pub fn akamai(&self) -> Option<String> {
+        self.akamai
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .and_then(|rx| rx.borrow().as_ref().map(|f| f.fingerprint.clone()))
+    }
+
+    pub fn tcp_syn(&self) -> Option<&str> {
+        self.tcp_syn.as_deref()
+    }
+
+    /// SNI, ALPN and JA4 in the form the security checks take.
+    pub fn tls_metadata(&self) -> TlsMetadata<'_> {
+        TlsMetadata { sni: self.sni(), alpn: self.alpn(), ja4: self.ja4().map(|set| &*set.ja4) }
+    }
+}
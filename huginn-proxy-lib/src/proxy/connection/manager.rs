@@ -1,3 +1,4 @@
+use std::net::IpAddr;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use thiserror::Error;
@@ -8,6 +9,7 @@ use crate::telemetry::metrics::values;
 use crate::telemetry::Metrics;
 
 use super::guards::ConnectionGuard;
+use super::per_ip::{PerIpConnections, PerIpGuard};
 
 /// Errors that can occur when trying to accept a connection
 #[derive(Debug, Error)]
@@ -16,6 +18,8 @@ pub enum ConnectionError {
     Shutdown,
     #[error("Connection limit exceeded (current: {current}, limit: {limit})")]
     LimitExceeded { current: usize, limit: usize },
+    #[error("Per-IP connection limit exceeded (ip: {ip}, limit: {limit})")]
+    PerIpLimitExceeded { ip: IpAddr, limit: usize },
 }
 
 /// Manages connection limits and lifecycle
@@ -24,6 +28,7 @@ pub struct ConnectionManager {
     max_connections: usize,
     shutdown_signal: Arc<AtomicUsize>,
     connections_closed_tx: watch::Sender<()>,
+    per_ip: Option<Arc<PerIpConnections>>,
 }
 
 impl ConnectionManager {
@@ -37,9 +42,19 @@ impl ConnectionManager {
             max_connections,
             shutdown_signal,
             connections_closed_tx,
+            per_ip: None,
         }
     }
 
+    /// Cap open connections per client IP (`security.max_connections_per_ip`); `None` leaves
+    /// them uncapped.
+    pub fn with_max_connections_per_ip(mut self, limit: Option<usize>, metrics: &Metrics) -> Self {
+        self.per_ip = limit.map(|limit| {
+            Arc::new(PerIpConnections::new(limit, metrics.connection_client_ips.clone()))
+        });
+        self
+    }
+
     /// Get the active connections counter (for metrics)
     pub fn active_connections(&self) -> Arc<AtomicUsize> {
         self.active_connections.clone()
@@ -62,26 +77,28 @@ impl ConnectionManager {
             return Err(ConnectionError::Shutdown);
         }
 
-        // TODO: race condition, two threads can both read the same count, pass the limit check,
-        // and both increment, exceeding max_connections. Fix: fetch_add first, then revert if over limit.
-        // Check connection limit (DoS protection)
-        let current_connections = self.active_connections.load(Ordering::Relaxed);
-        if current_connections >= self.max_connections {
+        // Check connection limit (DoS protection). Checked and incremented in one step, so
+        // concurrent accepts cannot both take the last slot.
+        let max = self.max_connections;
+        let reserved =
+            self.active_connections
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                    (current < max).then(|| current.saturating_add(1))
+                });
+        if let Err(current_connections) = reserved {
             metrics.record_connection_rejected(values::REASON_LIMIT_EXCEEDED);
             warn!(
                 current = current_connections,
-                limit = self.max_connections,
+                limit = max,
                 peer = %peer,
                 "Connection limit exceeded, rejecting connection"
             );
             return Err(ConnectionError::LimitExceeded {
                 current: current_connections,
-                limit: self.max_connections,
+                limit: max,
             });
         }
 
-        self.active_connections.fetch_add(1, Ordering::Relaxed);
-
         metrics.connections_total.add(1, &[]);
         metrics.connections_active.add(1, &[]);
 
@@ -91,4 +108,28 @@ impl ConnectionManager {
             metrics.connections_active.clone(),
         ))
     }
+
+    /// Count a connection against its client IP, once the real client is known (after the
+    /// PROXY protocol header). `Ok(None)` when no per-IP limit is configured.
+    pub fn try_accept_ip(
+        &self,
+        ip: IpAddr,
+        metrics: &Metrics,
+    ) -> Result<Option<PerIpGuard>, ConnectionError> {
+        let Some(per_ip) = &self.per_ip else {
+            return Ok(None);
+        };
+        match per_ip.try_acquire(ip) {
+            Some(guard) => Ok(Some(guard)),
+            None => {
+                metrics.record_connection_rejected(values::REASON_PER_IP_LIMIT_EXCEEDED);
+                warn!(
+                    ip = %ip,
+                    limit = per_ip.limit(),
+                    "Per-IP connection limit exceeded, rejecting connection"
+                );
+                Err(ConnectionError::PerIpLimitExceeded { ip, limit: per_ip.limit() })
+            }
+        }
+    }
 }
@@ -1,7 +1,13 @@
+pub mod context;
 pub mod guards;
 pub mod manager;
+pub mod per_ip;
+pub mod registry;
 pub mod stream;
 
+pub use context::{ConnectionContext, ConnectionTls, Ja4Set};
 pub use guards::{ConnectionGuard, TlsConnectionGuard};
 pub use manager::{ConnectionError, ConnectionManager};
-pub use stream::PrefixedStream;
+pub use per_ip::{PerIpConnections, PerIpGuard};
+pub use registry::{ConnectionInfo, ConnectionRegistry, ConnectionTraffic, TrackedConnection};
+pub use stream::{CountedStream, PrefixedStream};
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use opentelemetry::metrics::UpDownCounter;
+
+/// Open connections per client IP (`security.max_connections_per_ip`).
+///
+/// Only IPs with at least one open connection have an entry, so the table is bounded by
+/// `max_connections`.
+pub struct PerIpConnections {
+    limit: usize,
+    counts: Mutex<HashMap<IpAddr, usize>>,
+    client_ips: UpDownCounter<i64>,
+}
+
+impl PerIpConnections {
+    /// `client_ips` is `huginn_connection_client_ips`: moved by one per IP entering or leaving
+    /// the table.
+    pub fn new(limit: usize, client_ips: UpDownCounter<i64>) -> Self {
+        Self { limit, counts: Mutex::new(HashMap::new()), client_ips }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Open connections from `ip`.
+    pub fn current(&self, ip: IpAddr) -> usize {
+        self.counts
+            .lock()
+            .map(|counts| counts.get(&ip).copied().unwrap_or(0))
+            .unwrap_or(0)
+    }
+
+    /// Count a new connection from `ip`, or `None` when it already has `limit` open ones.
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Option<PerIpGuard> {
+        let mut counts = self.counts.lock().ok()?;
+        let count = counts.get(&ip).copied().unwrap_or(0);
+        if count >= self.limit {
+            return None;
+        }
+        if count == 0 {
+            self.client_ips.add(1, &[]);
+        }
+        counts.insert(ip, count.saturating_add(1));
+        Some(PerIpGuard { connections: Arc::clone(self), ip })
+    }
+
+    fn release(&self, ip: IpAddr) {
+        let Ok(mut counts) = self.counts.lock() else {
+            return;
+        };
+        let Some(count) = counts.get_mut(&ip) else {
+            return;
+        };
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            counts.remove(&ip);
+            self.client_ips.add(-1, &[]);
+        }
+    }
+}
+
+/// One connection counted against its client IP; released when dropped.
+pub struct PerIpGuard {
+    connections: Arc<PerIpConnections>,
+    ip: IpAddr,
+}
+
+impl Drop for PerIpGuard {
+    fn drop(&mut self) {
+        self.connections.release(self.ip);
+    }
+}
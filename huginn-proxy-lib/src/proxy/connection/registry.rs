@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use super::ConnectionContext;
+
+/// Open client connections, the fingerprints observed on them and their traffic, listed by the
+/// admin API's `/admin/connections`. Entries are keyed by the id of their [`ConnectionContext`]. A disabled registry (the default) tracks nothing, so the
+/// proxy pays for it only when `[telemetry.admin]` is enabled.
+#[derive(Clone, Default)]
+pub struct ConnectionRegistry {
+    inner: Option<Arc<RegistryInner>>,
+}
+
+#[derive(Default)]
+struct RegistryInner {
+    entries: Mutex<HashMap<u64, Arc<ConnectionEntry>>>,
+}
+
+struct ConnectionEntry {
+    context: Arc<ConnectionContext>,
+    listener: String,
+    tls: bool,
+    traffic: Arc<ConnectionTraffic>,
+}
+
+/// Bytes read from and written to a client connection, TLS records and protocol framing
+/// included. Updated by [`CountedStream`](super::CountedStream) as the connection is served.
+#[derive(Debug, Default)]
+pub struct ConnectionTraffic {
+    received: AtomicU64,
+    sent: AtomicU64,
+}
+
+impl ConnectionTraffic {
+    pub fn add_received(&self, bytes: u64) {
+        self.received.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_sent(&self, bytes: u64) {
+        self.sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+}
+
+/// Point-in-time view of one open connection. SNI and ALPN are `None` on plaintext connections;
+/// fingerprints are `None` until observed (or when the corresponding fingerprinting is disabled
+/// on the listener). Throughput is the average since
+/// the connection opened.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionInfo {
+    pub id: u64,
+    pub peer: String,
+    pub listener: String,
+    pub tls: bool,
+    pub age_secs: u64,
+    pub sni: Option<String>,
+    pub alpn: Option<String>,
+    pub ja4: Option<String>,
+    pub akamai: Option<String>,
+    pub tcp_syn: Option<String>,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub received_bytes_per_sec: u64,
+    pub sent_bytes_per_sec: u64,
+}
+
+impl ConnectionRegistry {
+    /// A registry that tracks connections.
+    pub fn new() -> Self {
+        Self { inner: Some(Arc::default()) }
+    }
+
+    /// A registry that tracks nothing; `register` always returns `None`.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Record a new connection, described by `context`. The entry is removed when the returned
+    /// handle is dropped.
+    pub fn register(
+        &self,
+        context: &Arc<ConnectionContext>,
+        listener: impl ToString,
+        tls: bool,
+    ) -> Option<TrackedConnection> {
+        let inner = self.inner.as_ref()?;
+        let entry = Arc::new(ConnectionEntry {
+            context: Arc::clone(context),
+            listener: listener.to_string(),
+            tls,
+            traffic: Arc::default(),
+        });
+        inner
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(context.id(), Arc::clone(&entry));
+        Some(TrackedConnection { entry, registry: Arc::clone(inner) })
+    }
+
+    /// Open connections sorted by id (oldest first).
+    pub fn snapshot(&self) -> Vec<ConnectionInfo> {
+        let Some(inner) = &self.inner else {
+            return Vec::new();
+        };
+        let entries: Vec<Arc<ConnectionEntry>> = inner
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect();
+        let mut out: Vec<ConnectionInfo> = entries.iter().map(|e| e.info()).collect();
+        out.sort_unstable_by_key(|c| c.id);
+        out
+    }
+
+    /// Number of tracked connections.
+    pub fn len(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| {
+            inner
+                .entries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .len()
+        })
+    }
+
+    /// Returns `true` if no connection is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ConnectionEntry {
+    fn info(&self) -> ConnectionInfo {
+        let context = &self.context;
+        let age_secs = context.age().as_secs();
+        let (received, sent) = (self.traffic.received(), self.traffic.sent());
+        ConnectionInfo {
+            id: context.id(),
+            peer: context.peer().to_string(),
+            listener: self.listener.clone(),
+            tls: self.tls,
+            age_secs,
+            sni: context.sni().map(str::to_string),
+            alpn: context.alpn().map(str::to_string),
+            ja4: context.ja4().map(|set| set.ja4.to_string()),
+            akamai: context.akamai(),
+            tcp_syn: context.tcp_syn().map(str::to_string),
+            bytes_received: received,
+            bytes_sent: sent,
+            received_bytes_per_sec: per_sec(received, age_secs),
+            sent_bytes_per_sec: per_sec(sent, age_secs),
+        }
+    }
+}
+
+/// Average rate of `bytes` over `age_secs`; connections younger than a second count as one.
+fn per_sec(bytes: u64, age_secs: u64) -> u64 {
+    bytes.checked_div(age_secs.max(1)).unwrap_or(bytes)
+}
+
+/// Registry handle held by a connection task for its lifetime.
+pub struct TrackedConnection {
+    entry: Arc<ConnectionEntry>,
+    registry: Arc<RegistryInner>,
+}
+
+impl TrackedConnection {
+    /// Traffic counters of this connection, for [`CountedStream`](super::CountedStream).
+    pub fn traffic(&self) -> Arc<ConnectionTraffic> {
+        Arc::clone(&self.entry.traffic)
+    }
+}
+
+impl Drop for TrackedConnection {
+    fn drop(&mut self) {
+        self.registry
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.entry.context.id());
+    }
+}
@@ -1,18 +1,24 @@
+use bytes::{Buf, Bytes};
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
+use super::ConnectionTraffic;
+
 /// Stream wrapper that prepends a prefix buffer before reading from the inner stream
 /// Used to preserve ClientHello data read during TLS handshake
+///
+/// The prefix is the buffer [`read_client_hello`](crate::fingerprinting::read_client_hello)
+/// filled, replayed in place and released as soon as it has been consumed.
 pub struct PrefixedStream<S> {
-    prefix: Vec<u8>,
-    offset: usize,
+    prefix: Bytes,
     inner: S,
 }
 
 impl<S> PrefixedStream<S> {
-    pub fn new(prefix: Vec<u8>, inner: S) -> Self {
-        Self { prefix, offset: 0, inner }
+    pub fn new(prefix: Bytes, inner: S) -> Self {
+        Self { prefix, inner }
     }
 }
 
@@ -22,11 +28,14 @@ impl<S: AsyncRead + Unpin> AsyncRead for PrefixedStream<S> {
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<std::io::Result<()>> {
-        if self.offset < self.prefix.len() {
-            let remaining = &self.prefix[self.offset..];
-            let to_copy = remaining.len().min(buf.remaining());
-            buf.put_slice(&remaining[..to_copy]);
-            self.offset = self.offset.saturating_add(to_copy);
+        if !self.prefix.is_empty() {
+            let to_copy = self.prefix.len().min(buf.remaining());
+            buf.put_slice(&self.prefix[..to_copy]);
+            self.prefix.advance(to_copy);
+            if self.prefix.is_empty() {
+                // Drop the reference now rather than with the connection.
+                self.prefix = Bytes::new();
+            }
             return Poll::Ready(Ok(()));
         }
         Pin::new(&mut self.inner).poll_read(cx, buf)
@@ -50,3 +59,75 @@ impl<S: AsyncWrite + Unpin> AsyncWrite for PrefixedStream<S> {
         Pin::new(&mut self.inner).poll_shutdown(cx)
     }
 }
+
+/// Stream wrapper that adds the bytes read and written to a connection's [`ConnectionTraffic`].
+/// Without traffic counters (connections not tracked by the registry) I/O passes through.
+pub struct CountedStream<S> {
+    inner: S,
+    traffic: Option<Arc<ConnectionTraffic>>,
+}
+
+impl<S> CountedStream<S> {
+    pub fn new(inner: S, traffic: Option<Arc<ConnectionTraffic>>) -> Self {
+        Self { inner, traffic }
+    }
+
+    /// The wrapped stream. I/O done on it directly is not counted.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for CountedStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        if let Some(traffic) = &self.traffic {
+            let read = buf.filled().len().saturating_sub(before);
+            traffic.add_received(u64::try_from(read).unwrap_or(u64::MAX));
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for CountedStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let written = ready!(Pin::new(&mut self.inner).poll_write(cx, data))?;
+        if let Some(traffic) = &self.traffic {
+            traffic.add_sent(u64::try_from(written).unwrap_or(u64::MAX));
+        }
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let written = ready!(Pin::new(&mut self.inner).poll_write_vectored(cx, bufs))?;
+        if let Some(traffic) = &self.traffic {
+            traffic.add_sent(u64::try_from(written).unwrap_or(u64::MAX));
+        }
+        Poll::Ready(Ok(written))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
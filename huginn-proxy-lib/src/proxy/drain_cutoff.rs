@@ -0,0 +1,79 @@
+//! Cutting requests to a drained backend (`drain_timeout_secs` on a backend).
+//!
+//! Each forwarded request holds its backend's
+//! [`drain_cutoff`](crate::backend::HealthRegistry::drain_cutoff) token. The wait for the response
+//! head races it, and [`CutoffBody`] fails a response body that is still streaming when it is
+//! cancelled; WebSocket tunnels are closed by [`spawn_tunnel`](crate::proxy::websocket::spawn_tunnel).
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use hyper::body::{Body, Frame, SizeHint};
+use thiserror::Error;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFutureOwned};
+
+use crate::utils::http::BoxError;
+
+/// The response body was still streaming when the backend's drain timeout passed.
+#[derive(Debug, Error)]
+#[error("backend drain timeout passed")]
+pub struct DrainCutoff;
+
+/// Body wrapper that fails with [`DrainCutoff`] once `cutoff` is cancelled.
+pub struct CutoffBody<B> {
+    inner: B,
+    cutoff: Option<Pin<Box<WaitForCancellationFutureOwned>>>,
+    on_cutoff: Option<Box<dyn FnOnce() + Send + Sync>>,
+}
+
+impl<B> CutoffBody<B> {
+    pub fn new(inner: B, cutoff: CancellationToken) -> Self {
+        Self { inner, cutoff: Some(Box::pin(cutoff.cancelled_owned())), on_cutoff: None }
+    }
+
+    /// Run `f` once, when the body is cut (typically to record a metric).
+    pub fn on_cutoff(mut self, f: impl FnOnce() + Send + Sync + 'static) -> Self {
+        self.on_cutoff = Some(Box::new(f));
+        self
+    }
+}
+
+impl<B> Body for CutoffBody<B>
+where
+    B: Body + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = B::Data;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if let Poll::Ready(frame) = Pin::new(&mut this.inner).poll_frame(cx) {
+            return Poll::Ready(frame.map(|frame| frame.map_err(Into::into)));
+        }
+        let expired = this
+            .cutoff
+            .as_mut()
+            .is_some_and(|cutoff| cutoff.as_mut().poll(cx).is_ready());
+        if !expired {
+            return Poll::Pending;
+        }
+        this.cutoff = None;
+        if let Some(on_cutoff) = this.on_cutoff.take() {
+            on_cutoff();
+        }
+        Poll::Ready(Some(Err(Box::new(DrainCutoff))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,70 @@
+//! Built-in echo backend (`echo = true` on a backend).
+//!
+//! Requests routed to an echo backend are not sent anywhere:
+//! [`forward`](crate::proxy::forwarding::forward) hands the fully prepared upstream request
+//! (rewritten path, injected fingerprint and `X-Forwarded-*` headers, header manipulation applied)
+//! to [`echo_response`], which answers with it as JSON. E2E tests and demos get a backend without
+//! running one.
+
+use std::collections::BTreeMap;
+
+use http::{Request, Response, StatusCode, Version};
+use http_body_util::BodyExt;
+use hyper::body::Body;
+use serde::Serialize;
+
+use crate::utils::http::{json_response, BoxError, RespBody};
+
+#[derive(Serialize)]
+struct EchoView {
+    method: String,
+    /// Path and query as a backend would receive them.
+    uri: String,
+    /// Protocol of the upstream request (after `http_version` and gRPC/WebSocket rules).
+    protocol: String,
+    /// Protocol negotiated with the client.
+    client_protocol: String,
+    /// Request headers as a backend would receive them; repeated headers are joined with `, `.
+    headers: BTreeMap<String, String>,
+    body_bytes: usize,
+}
+
+/// Read the body of `req` and answer `200` with the request described as JSON.
+pub async fn echo_response<B>(
+    req: Request<B>,
+    client_version: Version,
+) -> Result<Response<RespBody>, BoxError>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    let (parts, body) = req.into_parts();
+    let body = body.collect().await.map_err(Into::into)?.to_bytes();
+
+    let mut headers: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in &parts.headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        headers
+            .entry(name.as_str().to_string())
+            .and_modify(|joined| {
+                joined.push_str(", ");
+                joined.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+
+    Ok(json_response(
+        StatusCode::OK,
+        EchoView {
+            method: parts.method.to_string(),
+            uri: parts
+                .uri
+                .path_and_query()
+                .map_or_else(|| "/".to_string(), ToString::to_string),
+            protocol: format!("{:?}", parts.version),
+            client_protocol: format!("{client_version:?}"),
+            headers,
+            body_bytes: body.len(),
+        },
+    ))
+}
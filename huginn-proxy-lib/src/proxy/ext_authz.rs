@@ -0,0 +1,168 @@
+//! External authorization for routes with `[domains.routes.ext_authz]`.
+//!
+//! The check is a body-less `GET` to the configured URL carrying the client's headers (already
+//! enriched with the fingerprint and `X-Forwarded-*` headers) plus `X-Forwarded-Method` and
+//! `X-Forwarded-Uri`. A `2xx` answer allows the request and may hand identity headers to the
+//! backend; any other answer is relayed to the client with the service's headers and body, so
+//! login redirects and `WWW-Authenticate` challenges keep working.
+
+use bytes::Bytes;
+use http::header::{CONTENT_LENGTH, HOST};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response, Uri};
+use http_body_util::{BodyExt, Full, Limited};
+use tokio::time::Duration;
+
+use crate::config::ExtAuthzConfig;
+use crate::proxy::client_pool::AuthzClient;
+use crate::utils::http::{full_body, RespBody};
+
+/// Method of the original request, sent to the authorization service.
+pub const FORWARDED_METHOD: &str = "x-forwarded-method";
+/// Path and query of the original request, sent to the authorization service.
+pub const FORWARDED_URI: &str = "x-forwarded-uri";
+
+/// Upper bound on a denial body relayed to the client.
+const MAX_DENIAL_BODY_BYTES: usize = 64 * 1024;
+
+/// Connection-scoped headers (RFC 9110 §7.6.1) that are not copied between exchanges.
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Outcome of one authorization check.
+#[derive(Debug)]
+pub enum AuthzDecision {
+    /// `2xx`: forward the request after applying these `upstream_headers` values.
+    Allow(Vec<(HeaderName, HeaderValue)>),
+    /// Any other status: answer the client with this response instead of forwarding.
+    Deny(Response<RespBody>),
+    /// Transport error, timeout or unreadable denial; `failure_mode` decides.
+    Failed(String),
+}
+
+impl AuthzDecision {
+    /// Value of the `result` label of `huginn_ext_authz_checks_total`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Allow(_) => "allow",
+            Self::Deny(_) => "deny",
+            Self::Failed(_) => "error",
+        }
+    }
+}
+
+fn is_hop_by_hop(name: &HeaderName) -> bool {
+    HOP_BY_HOP.contains(&name.as_str())
+}
+
+/// Build the check request sent to `config.url` for a client request.
+///
+/// The client's `Host` is not copied (the check targets the authorization service; the
+/// routing host travels in `X-Forwarded-Host`), nor are hop-by-hop and body framing headers.
+pub fn check_request(
+    config: &ExtAuthzConfig,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+) -> Result<Request<Full<Bytes>>, http::Error> {
+    let mut builder = Request::builder()
+        .method(Method::GET)
+        .uri(config.url.as_str());
+    if let Some(check_headers) = builder.headers_mut() {
+        for (name, value) in headers {
+            if name != HOST && name != CONTENT_LENGTH && !is_hop_by_hop(name) {
+                check_headers.append(name, value.clone());
+            }
+        }
+        if let Ok(value) = HeaderValue::from_str(method.as_str()) {
+            check_headers.insert(FORWARDED_METHOD, value);
+        }
+        let path_and_query = uri.path_and_query().map_or("/", |pq| pq.as_str());
+        if let Ok(value) = HeaderValue::from_str(path_and_query) {
+            check_headers.insert(FORWARDED_URI, value);
+        }
+    }
+    builder.body(Full::new(Bytes::new()))
+}
+
+/// Send a check built by [`check_request`], within `config.timeout_ms`.
+pub async fn check(
+    client: &AuthzClient,
+    config: &ExtAuthzConfig,
+    request: Request<Full<Bytes>>,
+) -> AuthzDecision {
+    let budget = Duration::from_millis(config.timeout_ms);
+    match tokio::time::timeout(budget, exchange(client, config, request)).await {
+        Ok(decision) => decision,
+        Err(_) => AuthzDecision::Failed(format!("timed out after {}ms", config.timeout_ms)),
+    }
+}
+
+async fn exchange(
+    client: &AuthzClient,
+    config: &ExtAuthzConfig,
+    request: Request<Full<Bytes>>,
+) -> AuthzDecision {
+    let response = match client.request(request).await {
+        Ok(response) => response,
+        Err(e) => return AuthzDecision::Failed(e.to_string()),
+    };
+    let (parts, body) = response.into_parts();
+    let body = match Limited::new(body, MAX_DENIAL_BODY_BYTES).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => return AuthzDecision::Failed(format!("response body: {e}")),
+    };
+
+    if parts.status.is_success() {
+        return AuthzDecision::Allow(upstream_headers(config, &parts.headers));
+    }
+
+    let mut denial = Response::new(full_body(body));
+    *denial.status_mut() = parts.status;
+    for (name, value) in &parts.headers {
+        if name != CONTENT_LENGTH && !is_hop_by_hop(name) {
+            denial.headers_mut().append(name, value.clone());
+        }
+    }
+    AuthzDecision::Deny(denial)
+}
+
+/// Every value of the configured `upstream_headers` in an allowing response.
+fn upstream_headers(
+    config: &ExtAuthzConfig,
+    response_headers: &HeaderMap,
+) -> Vec<(HeaderName, HeaderValue)> {
+    config
+        .upstream_headers
+        .iter()
+        .filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok())
+        .flat_map(|name| {
+            response_headers
+                .get_all(&name)
+                .iter()
+                .map(|value| (name.clone(), value.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Replace the client's copies of `upstream_headers` with the values granted by the
+/// authorization service, so a client can never assert an identity the service did not.
+pub fn apply_upstream_headers(
+    headers: &mut HeaderMap,
+    config: &ExtAuthzConfig,
+    granted: Vec<(HeaderName, HeaderValue)>,
+) {
+    for name in &config.upstream_headers {
+        headers.remove(name.as_str());
+    }
+    for (name, value) in granted {
+        headers.append(name, value);
+    }
+}
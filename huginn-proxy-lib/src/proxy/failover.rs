@@ -0,0 +1,178 @@
+//! Failover to the route's next backend (`failover` on a route).
+//!
+//! A request that can be sent again unchanged is copied before each attempt; when the answer
+//! calls for failover, the copy goes to another eligible backend among the route's candidates,
+//! picked by the same selector as the first one. With `replay_body_bytes`, small request bodies
+//! are read in full first so they can be copied too; larger ones stream and are not replayed.
+//! Non-idempotent requests are only sent again when the failed attempt never reached the
+//! backend (connection refused, connect timeout, no pooled connection). Later attempts reuse the
+//! first backend's in-flight slot and bandwidth buckets; the circuit breaker and drain cutoff
+//! follow the backend actually tried.
+
+use bytes::Bytes;
+use http::{Request, Response, StatusCode};
+use hyper::body::{Body, Incoming};
+use tracing::debug;
+
+use crate::backend::UpstreamGateway;
+use crate::config::{FailoverConfig, RouteProtocol};
+use crate::proxy::forwarding::{forward, ForwardConfig};
+use crate::proxy::http_result::{HttpError, HttpResult};
+use crate::proxy::prefetch::PrefetchedBody;
+use crate::proxy::websocket::is_websocket_upgrade;
+use crate::telemetry::metrics::values;
+use crate::utils::http::RespBody;
+
+/// Forward `req` to `backend`, then to the next eligible backend of `candidates` each time the
+/// answer calls for `failover`, up to its `max_attempts`. `served_by` is updated to the backend
+/// whose answer is returned. Without `failover`, or for a request that cannot be replayed, this is
+/// a single [`forward`]; a non-idempotent request only goes to the next backend when the previous
+/// one never received it.
+pub(crate) async fn forward_with_failover(
+    req: Request<PrefetchedBody<Incoming>>,
+    backend: String,
+    mut config: ForwardConfig<'_>,
+    failover: Option<&FailoverConfig>,
+    candidates: &[&str],
+    upstream: &UpstreamGateway,
+    served_by: &mut Option<String>,
+) -> HttpResult<Response<RespBody>> {
+    let Some(failover) = failover else {
+        return forward(req, backend, config).await;
+    };
+    let mut req = buffer_body(req, failover, config.protocol).await?;
+    let mut current = backend;
+    let mut tried: Vec<String> = Vec::new();
+    loop {
+        let attempts = tried.len().saturating_add(1);
+        let replayed = if attempts < failover.max_attempts {
+            replay(&req, failover)
+        } else {
+            None
+        };
+        let idempotent = req.method().is_idempotent();
+        let result = forward(req, current.clone(), config.clone()).await;
+        let (Some(reason), Some(replayed)) = (failover_reason(failover, &result), replayed) else {
+            return result;
+        };
+        if !idempotent && !never_sent(&result) {
+            return result;
+        }
+        let remaining: Vec<&str> = candidates
+            .iter()
+            .copied()
+            .filter(|c| *c != current && !tried.iter().any(|t| t == c))
+            .collect();
+        let Some(next) = upstream.select(
+            config.matched_prefix,
+            &remaining,
+            None,
+            config.backends,
+            &config.metrics,
+        ) else {
+            return result;
+        };
+        config
+            .metrics
+            .record_backend_failover(&current, reason, config.route, config.domain);
+        config.metrics.record_backend_selection(&next);
+        config.circuit_breaker = upstream.circuit_breaker(&next, config.backends);
+        config.drain_cutoff = upstream.health.drain_cutoff(&next);
+        *served_by = Some(next.clone());
+        tried.push(std::mem::replace(&mut current, next));
+        req = replayed;
+    }
+}
+
+/// Read the body of `req` ahead, up to `config.replay_body_bytes` (possibly past it by part of
+/// one frame), so [`replay`] can copy it. Bodies declared larger are left streaming; gRPC
+/// streams and WebSocket upgrades are never read ahead.
+pub async fn buffer_body<B>(
+    req: Request<PrefetchedBody<B>>,
+    config: &FailoverConfig,
+    protocol: RouteProtocol,
+) -> HttpResult<Request<PrefetchedBody<B>>>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: std::fmt::Display,
+{
+    let limit = config.replay_body_bytes;
+    let declared = usize::try_from(req.body().size_hint().lower()).unwrap_or(usize::MAX);
+    if limit == 0
+        || protocol == RouteProtocol::Grpc
+        || is_websocket_upgrade(req.headers())
+        || req.body().is_end_stream()
+        || declared > limit
+    {
+        return Ok(req);
+    }
+    let (parts, body) = req.into_parts();
+    let body = body
+        .fill(limit)
+        .await
+        .map_err(|e| HttpError::RequestBodyReadFailed(e.to_string()))?;
+    if body.try_clone().is_none() {
+        debug!(limit, "request body exceeds failover.replay_body_bytes, not replayable");
+    }
+    Ok(Request::from_parts(parts, body))
+}
+
+/// Copy of `req` to send to another backend, since the original body is consumed by the first
+/// attempt. Without `config.replay_body_bytes`, only idempotent requests without a body are
+/// copied; with it, requests of any method whose body was read in full, though non-idempotent
+/// copies are only sent when [`never_sent`] holds. WebSocket upgrades never are.
+pub fn replay<B>(
+    req: &Request<PrefetchedBody<B>>,
+    config: &FailoverConfig,
+) -> Option<Request<PrefetchedBody<B>>>
+where
+    B: Body<Data = Bytes> + Unpin,
+{
+    let buffering = config.replay_body_bytes > 0;
+    if !(buffering || (req.method().is_idempotent() && req.body().is_end_stream()))
+        || is_websocket_upgrade(req.headers())
+    {
+        return None;
+    }
+    let mut copy = Request::new(req.body().try_clone()?);
+    *copy.method_mut() = req.method().clone();
+    *copy.uri_mut() = req.uri().clone();
+    *copy.version_mut() = req.version();
+    *copy.headers_mut() = req.headers().clone();
+    *copy.extensions_mut() = req.extensions().clone();
+    Some(copy)
+}
+
+/// Why `result` fails over under `config` (`reason` label of `huginn_backend_failovers_total`),
+/// or `None` when it is returned to the client. Errors count only when the backend failed.
+pub fn failover_reason<B>(
+    config: &FailoverConfig,
+    result: &HttpResult<Response<B>>,
+) -> Option<&'static str> {
+    let status = match result {
+        Ok(response) => response.status(),
+        Err(
+            e @ (HttpError::FailedToGetResponseFromBackend(_)
+            | HttpError::BackendConnectFailed(_)
+            | HttpError::BackendTimeout(_)
+            | HttpError::BackendPoolExhausted
+            | HttpError::BackendDrainCutoff),
+        ) => StatusCode::from(e.clone()),
+        Err(_) => return None,
+    };
+    if config.statuses.contains(&status.as_u16()) {
+        return Some(values::FAILOVER_STATUS);
+    }
+    let flagged = result
+        .as_ref()
+        .ok()
+        .zip(config.header.as_deref())
+        .is_some_and(|(response, header)| response.headers().contains_key(header));
+    flagged.then_some(values::FAILOVER_HEADER)
+}
+
+/// Whether `result` is an error that guarantees the backend received none of the request, so
+/// even a non-idempotent one can be sent to another backend.
+pub fn never_sent<B>(result: &HttpResult<Response<B>>) -> bool {
+    result.as_ref().err().is_some_and(HttpError::nothing_sent)
+}
@@ -1,27 +1,68 @@
-use crate::config::{BackendHttpVersion, KeepAliveConfig};
+use crate::backend::CircuitBreaker;
+use crate::config::{
+    unix_socket_path, BackendHttpVersion, KeepAliveConfig, PathRewriteConfig, RouteProtocol,
+    RouteTimeoutConfig, WebSocketConfig,
+};
+use crate::proxy::bandwidth::{Direction, ShapedBody, Shaping};
+use crate::proxy::body_bytes::CountedBody;
+use crate::proxy::body_limit::{declared_length_exceeds, LimitedBody};
+use crate::proxy::client_pool::PooledBody;
+use crate::proxy::drain_cutoff::CutoffBody;
+use crate::proxy::echo::echo_response;
+use crate::proxy::grpc::{grpc_status, is_grpc_content_type, observe_grpc_status};
 use crate::proxy::http_result::{HttpError, HttpResult};
+use crate::proxy::prefetch::PrefetchedBody;
+use crate::proxy::route_timeout::{
+    is_connect_timeout, with_connect_timeout, DeadlineBody, TimeoutKind,
+};
+use crate::proxy::websocket::{is_websocket_upgrade, spawn_tunnel};
 use crate::proxy::ClientPool;
+use crate::telemetry::metrics::values;
 use crate::telemetry::Metrics;
 use crate::utils::http::RespBody;
-use http::{Request, Response, Version};
+use http::header::HOST;
+use http::{HeaderValue, Request, Response, StatusCode, Version};
+use http_body_util::combinators::MapFrame;
 use http_body_util::BodyExt;
-use hyper::body::Incoming;
+use hyper::body::{Body, Frame, Incoming};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::time::Instant;
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
+#[derive(Clone)]
 pub struct ForwardConfig<'a> {
     pub backends: &'a [crate::config::Backend],
     pub keep_alive: &'a KeepAliveConfig,
     pub metrics: Arc<Metrics>,
     pub matched_prefix: &'a str,
     pub replace_path: Option<&'a str>,
+    /// Route's regex rewrite; exclusive with `replace_path`.
+    pub rewrite: Option<&'a PathRewriteConfig>,
     pub security_headers: Option<&'a crate::config::SecurityHeaders>,
     pub is_https: bool,
+    /// Forward the client's host (route override, else global `preserve_host`).
     pub preserve_host: bool,
+    /// Route's `host_rewrite`: the `Host` sent whatever `preserve_host` says.
+    pub host_rewrite: Option<&'a str>,
     pub route: &'a str,
     pub domain: &'a str,
     pub client_pool: &'a Arc<ClientPool>,
     pub force_new_connection: bool,
+    /// Breaker of the selected backend; fed with the outcome of this request.
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>,
+    pub websocket: &'a WebSocketConfig,
+    pub protocol: RouteProtocol,
+    pub max_request_body_bytes: Option<u64>,
+    pub max_response_body_bytes: Option<u64>,
+    /// Route's backend timeouts; `None` keeps the global `[timeout]` behavior.
+    pub timeout: Option<RouteTimeoutConfig>,
+    /// Drain cutoff of the selected backend: cancelled when the backend is drained and its
+    /// `drain_timeout_secs` passes.
+    pub drain_cutoff: CancellationToken,
+    /// Token buckets of the route's and backend's `bandwidth`; the request body is paced by the
+    /// upload ones. `None` when neither is shaped.
+    pub bandwidth: Option<&'a Shaping>,
 }
 
 pub fn find_backend_config<'a>(
@@ -45,7 +86,8 @@ pub fn determine_http_version(
         });
 
     match http_version {
-        BackendHttpVersion::Http11 => Version::HTTP_11,
+        // Written as HTTP/1.1; sent as HTTP/2 when the connection's ALPN picks `h2`.
+        BackendHttpVersion::Http11 | BackendHttpVersion::Auto => Version::HTTP_11,
         BackendHttpVersion::Http2 => Version::HTTP_2,
         BackendHttpVersion::Preserve => {
             // Preserve client version, but HTTP/3 is not supported, convert to HTTP/2
@@ -58,25 +100,47 @@ pub fn determine_http_version(
     }
 }
 
-pub async fn forward(
-    mut req: Request<Incoming>,
-    backend: String,
-    config: ForwardConfig<'_>,
-) -> HttpResult<Response<RespBody>> {
-    let start = Instant::now();
-    let protocol = format!("{:?}", req.version());
+/// `Host` the backend receives: the route's `host_rewrite`, the client's host (its `Host` header,
+/// or the request authority of HTTP/2 clients) when preserved, else the backend's own authority,
+/// so a rewritten request lands on the backend's virtual host rather than the client's.
+pub fn upstream_host(
+    parts: &http::request::Parts,
+    host_rewrite: Option<&str>,
+    preserve_host: bool,
+    backend_authority: &str,
+) -> Option<HeaderValue> {
+    match host_rewrite {
+        Some(host) => HeaderValue::from_str(host).ok(),
+        None if preserve_host => parts.headers.get(HOST).cloned().or_else(|| {
+            parts
+                .uri
+                .authority()
+                .and_then(|authority| HeaderValue::from_str(authority.as_str()).ok())
+        }),
+        None => HeaderValue::from_str(backend_authority).ok(),
+    }
+}
 
-    let org_pq = req
-        .uri()
+/// Path and query the backend receives: the route's matching regex `rewrite`, else the
+/// `replace_path` substitution of `matched_prefix`, else the client's own.
+pub fn upstream_path(
+    uri: &http::Uri,
+    rewrite: Option<&PathRewriteConfig>,
+    replace_path: Option<&str>,
+    matched_prefix: &str,
+) -> HttpResult<String> {
+    let org_pq = uri
         .path_and_query()
         .map(|pq| pq.as_str())
         .unwrap_or("/")
         .as_bytes();
 
-    // Replace some parts of path if replace_path is enabled for chosen upstream
-    let new_pq = match config.replace_path {
-        Some(new_path) => {
-            let matched_path: &[u8] = config.matched_prefix.as_bytes();
+    let rewritten = rewrite.and_then(|rewrite| rewrite.apply(uri.path(), uri.query()));
+
+    let new_pq = match (rewritten, replace_path) {
+        (Some(rewritten), _) => rewritten.into_bytes(),
+        (None, Some(new_path)) => {
+            let matched_path: &[u8] = matched_prefix.as_bytes();
             if matched_path.is_empty() || org_pq.len() < matched_path.len() {
                 return Err(HttpError::InvalidUri("Path and query is broken".to_string()));
             }
@@ -87,19 +151,70 @@ pub async fn forward(
             new_pq.extend_from_slice(&org_pq[matched_path.len()..]);
             new_pq
         }
-        None => org_pq.to_vec(),
+        (None, None) => org_pq.to_vec(),
     };
 
-    let new_path_str = String::from_utf8(new_pq)
-        .map_err(|e| HttpError::InvalidUri(format!("Invalid UTF-8 in path: {}", e)))?;
+    String::from_utf8(new_pq)
+        .map_err(|e| HttpError::InvalidUri(format!("Invalid UTF-8 in path: {}", e)))
+}
 
-    let uri = format!("http://{}{}", backend, new_path_str)
-        .parse::<http::Uri>()
-        .map_err(|e| HttpError::InvalidUri(e.to_string()))?;
+pub async fn forward(
+    mut req: Request<PrefetchedBody<Incoming>>,
+    backend: String,
+    config: ForwardConfig<'_>,
+) -> HttpResult<Response<RespBody>> {
+    let start = Instant::now();
+    let protocol = format!("{:?}", req.version());
+
+    let new_path_str =
+        upstream_path(req.uri(), config.rewrite, config.replace_path, config.matched_prefix)?;
 
     let client_version = req.version();
     let backend_config = find_backend_config(&backend, config.backends);
-    let target_version = determine_http_version(backend_config, client_version, false);
+
+    // WebSocket handshakes are only possible over HTTP/1.1 on both legs; claim the client's
+    // connection now so it can be spliced to the backend's once it answers 101.
+    let client_upgrade = (config.websocket.enabled
+        && client_version == Version::HTTP_11
+        && is_websocket_upgrade(req.headers()))
+    .then(|| hyper::upgrade::on(&mut req));
+    // gRPC needs HTTP/2 end to end; plain backends are reached over h2c (prior knowledge).
+    let target_version = if client_upgrade.is_some() {
+        Version::HTTP_11
+    } else if config.protocol == RouteProtocol::Grpc {
+        Version::HTTP_2
+    } else {
+        determine_http_version(backend_config, client_version, false)
+    };
+
+    // `http_version = "auto"` leaves the protocol to ALPN, unless the route needs one.
+    let policy = backend_config
+        .and_then(|b| b.http_version)
+        .unwrap_or(BackendHttpVersion::Http11);
+    let negotiate = policy == BackendHttpVersion::Auto
+        && client_upgrade.is_none()
+        && config.protocol != RouteProtocol::Grpc;
+    // Backends with a `tls` table are re-encrypted over `https://` with their own client.
+    let tls_client = config.client_pool.get_tls_client(
+        &backend,
+        (!negotiate).then_some(target_version),
+        config.force_new_connection,
+    );
+    let scheme = if tls_client.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+
+    // A unix socket backend is reached through its own connector; the URI only needs a host.
+    let authority = if unix_socket_path(&backend).is_some() {
+        "localhost"
+    } else {
+        backend.as_str()
+    };
+    let uri = format!("{}://{}{}", scheme, authority, new_path_str)
+        .parse::<http::Uri>()
+        .map_err(|e| HttpError::InvalidUri(e.to_string()))?;
 
     if req.version() != target_version {
         *req.version_mut() = target_version;
@@ -107,6 +222,14 @@ pub async fn forward(
 
     let (mut parts, body) = req.into_parts();
 
+    // A declared length over the cap is refused before the backend sees anything.
+    if declared_length_exceeds(&parts.headers, config.max_request_body_bytes) {
+        config
+            .metrics
+            .record_request_body_too_large(config.route, config.domain);
+        return Err(HttpError::RequestBodyTooLarge);
+    }
+
     if let Some(content_length) = parts.headers.get(hyper::header::CONTENT_LENGTH) {
         if let Ok(length_str) = content_length.to_str() {
             if let Ok(length) = length_str.parse::<u64>() {
@@ -120,32 +243,194 @@ pub async fn forward(
         }
     }
 
-    let original_host = config
-        .preserve_host
-        .then(|| parts.headers.get("host").cloned())
-        .flatten();
+    let upstream_host = upstream_host(&parts, config.host_rewrite, config.preserve_host, authority);
     parts.uri = uri;
-    if let Some(host) = original_host {
-        parts.headers.insert("host", host);
+    if let Some(host) = upstream_host {
+        parts.headers.insert(HOST, host);
     }
 
+    let body = {
+        let metrics = Arc::clone(&config.metrics);
+        let (backend, route, domain) =
+            (backend.clone(), config.route.to_string(), config.domain.to_string());
+        CountedBody::new(body, move |bytes| {
+            metrics.record_request_bytes(bytes, &backend, &route, &domain);
+        })
+    };
+
+    // Bodies without a declared length are capped as they stream.
+    let request_limit_hit = config
+        .max_request_body_bytes
+        .map(|_| Arc::new(AtomicBool::new(false)));
+    let body = LimitedBody::new(body, config.max_request_body_bytes);
+    let body = match &request_limit_hit {
+        Some(hit) => {
+            let hit = Arc::clone(hit);
+            let metrics = Arc::clone(&config.metrics);
+            let (route, domain) = (config.route.to_string(), config.domain.to_string());
+            body.on_exceeded(move || {
+                hit.store(true, Ordering::Relaxed);
+                metrics.record_request_body_too_large(&route, &domain);
+            })
+        }
+        None => body,
+    };
+    let body = match config.bandwidth {
+        Some(shaping) => {
+            let metrics = Arc::clone(&config.metrics);
+            let (route, domain) = (config.route.to_string(), config.domain.to_string());
+            ShapedBody::new(body, shaping.buckets(Direction::Upload)).on_throttled(move || {
+                metrics.record_bandwidth_throttled(
+                    values::DIRECTION_CLIENT_TO_BACKEND,
+                    &route,
+                    &domain,
+                );
+            })
+        }
+        None => ShapedBody::new(body, Vec::new()),
+    };
+
     let out_req = Request::from_parts(parts, body);
 
-    let result = if let Some(pooled_client) = config
-        .client_pool
-        .get_client(target_version, config.force_new_connection)
-    {
-        pooled_client.request(out_req).await
-    } else {
-        let oneoff_client = config.client_pool.create_oneoff_client(target_version);
-        oneoff_client.request(out_req).await
+    // An `echo` backend is answered here, with the request exactly as it would have been sent.
+    if backend_config.is_some_and(|b| b.echo) {
+        let mut resp = match echo_response(out_req, client_version).await {
+            Ok(resp) => resp,
+            Err(_)
+                if request_limit_hit
+                    .as_ref()
+                    .is_some_and(|hit| hit.load(Ordering::Relaxed)) =>
+            {
+                return Err(HttpError::RequestBodyTooLarge);
+            }
+            Err(e) => return Err(HttpError::RequestBodyReadFailed(e.to_string())),
+        };
+        crate::security::apply_security_headers(
+            &mut resp,
+            config.security_headers,
+            config.is_https,
+        );
+        let status_code = resp.status().as_u16();
+        config.metrics.record_backend_request(
+            &backend,
+            status_code,
+            &protocol,
+            config.route,
+            config.domain,
+        );
+        config.metrics.record_backend_duration(
+            start.elapsed().as_secs_f64(),
+            &backend,
+            status_code,
+            &protocol,
+            config.route,
+            config.domain,
+        );
+        return Ok(resp);
+    }
+
+    // Backends with `pool.max_connections` / `pool.max_concurrent_streams`: wait for a slot.
+    let permit = match config.client_pool.acquire(&backend, target_version).await {
+        Ok(permit) => permit,
+        Err(_) => {
+            let error = HttpError::BackendPoolExhausted;
+            config.metrics.record_backend_error(
+                &backend,
+                error.error_type(),
+                config.route,
+                config.domain,
+            );
+            return Err(error);
+        }
+    };
+
+    let timeout = config.timeout.unwrap_or_default();
+    let deadline = timeout
+        .total_ms
+        .and_then(|ms| start.checked_add(Duration::from_millis(ms)));
+    let dispatched = Instant::now();
+    // The response head must arrive before the first-byte or the total deadline, whichever
+    // comes first.
+    let head_limit = [
+        timeout
+            .first_byte_ms
+            .and_then(|ms| dispatched.checked_add(Duration::from_millis(ms)))
+            .map(|at| (at, TimeoutKind::FirstByte)),
+        deadline.map(|at| (at, TimeoutKind::Total)),
+    ]
+    .into_iter()
+    .flatten()
+    .min_by_key(|(at, _)| *at);
+
+    let sending = with_connect_timeout(timeout.connect_ms.map(Duration::from_millis), async {
+        if let Some(tls_client) = tls_client {
+            tls_client.request(out_req).await
+        } else if let Some(pooled_client) = config.client_pool.get_backend_client(
+            &backend,
+            target_version,
+            config.force_new_connection,
+        ) {
+            pooled_client.request(out_req).await
+        } else {
+            let oneoff_client = config
+                .client_pool
+                .create_oneoff_backend_client(&backend, target_version);
+            oneoff_client.request(out_req).await
+        }
+    });
+    let head = async {
+        match head_limit {
+            Some((at, kind)) => tokio::time::timeout_at(at, sending).await.map_err(|_| kind),
+            None => Ok(sending.await),
+        }
+    };
+    let result = tokio::select! {
+        head = head => match head {
+            Ok(result) => result,
+            Err(kind) => {
+                record_circuit_breaker(&config, &backend, false);
+                return Err(backend_timeout(kind, &backend, &config));
+            }
+        },
+        _ = config.drain_cutoff.cancelled() => return Err(backend_drain_cutoff(&backend, &config)),
     };
 
     let duration = start.elapsed().as_secs_f64();
 
+    // The exchange failed because the client overran the cap, not because of the backend.
+    let request_too_large = request_limit_hit
+        .as_ref()
+        .is_some_and(|hit| hit.load(Ordering::Relaxed));
+    if request_too_large && result.is_err() {
+        return Err(HttpError::RequestBodyTooLarge);
+    }
+
+    let success = result
+        .as_ref()
+        .is_ok_and(|resp| !resp.status().is_server_error());
+    record_circuit_breaker(&config, &backend, success);
+
     match result {
         Ok(mut resp) => {
             let status_code = resp.status().as_u16();
+            config.metrics.record_backend_negotiated_protocol(
+                &backend,
+                policy.as_str(),
+                resp.version(),
+            );
+
+            if let Some(client_upgrade) = client_upgrade {
+                if resp.status() == StatusCode::SWITCHING_PROTOCOLS {
+                    spawn_tunnel(
+                        client_upgrade,
+                        hyper::upgrade::on(&mut resp),
+                        Duration::from_secs(config.websocket.idle_timeout_secs),
+                        backend.clone(),
+                        config.drain_cutoff.clone(),
+                        Arc::clone(&config.metrics),
+                    );
+                }
+            }
 
             if let Some(content_length) = resp.headers().get(hyper::header::CONTENT_LENGTH) {
                 if let Ok(length_str) = content_length.to_str() {
@@ -181,10 +466,50 @@ pub async fn forward(
                 config.route,
                 config.domain,
             );
+            if declared_length_exceeds(resp.headers(), config.max_response_body_bytes) {
+                config.metrics.record_response_body_too_large(
+                    &backend,
+                    config.route,
+                    config.domain,
+                );
+                return Err(HttpError::ResponseBodyTooLarge);
+            }
+            let resp = limit_response_body(resp, &backend, &config)
+                .map(|body| PooledBody::new(body, permit));
+            let resp = limit_response_time(resp, deadline, &backend, &config);
+            let resp = cut_on_drain_timeout(resp, &backend, &config);
+            let resp = count_response_bytes(resp, &backend, &config);
+            let resp = observe_first_frame(resp, dispatched, &backend, &config);
+            if config.protocol == RouteProtocol::Grpc && is_grpc_content_type(resp.headers()) {
+                // Trailers-only responses (typically errors) carry the status in the headers.
+                if let Some(status) = grpc_status(resp.headers()) {
+                    config.metrics.record_grpc_response(
+                        &backend,
+                        status,
+                        config.route,
+                        config.domain,
+                    );
+                } else {
+                    let metrics = Arc::clone(&config.metrics);
+                    let (route, domain) = (config.route.to_string(), config.domain.to_string());
+                    return Ok(resp.map(|body| {
+                        observe_grpc_status(body, move |status| {
+                            metrics.record_grpc_response(&backend, status, &route, &domain);
+                        })
+                    }));
+                }
+            }
             Ok(resp.map(|b| b.boxed()))
         }
+        Err(e) if is_connect_timeout(&e) => {
+            Err(backend_timeout(TimeoutKind::Connect, &backend, &config))
+        }
         Err(e) => {
-            let error = HttpError::FailedToGetResponseFromBackend(e.to_string());
+            let error = if e.is_connect() {
+                HttpError::BackendConnectFailed(e.to_string())
+            } else {
+                HttpError::FailedToGetResponseFromBackend(e.to_string())
+            };
             config.metrics.record_backend_error(
                 &backend,
                 error.error_type(),
@@ -195,3 +520,139 @@ pub async fn forward(
         }
     }
 }
+
+/// Record `huginn_backend_ttfb_seconds` when the first frame of the response body arrives,
+/// measured from `dispatched`. Bodies that end without a frame are not recorded.
+#[allow(clippy::type_complexity)]
+fn observe_first_frame<B>(
+    resp: Response<B>,
+    dispatched: Instant,
+    backend: &str,
+    config: &ForwardConfig<'_>,
+) -> Response<MapFrame<B, impl FnMut(Frame<B::Data>) -> Frame<B::Data>>>
+where
+    B: Body,
+{
+    let metrics = Arc::clone(&config.metrics);
+    let mut labels =
+        Some((backend.to_string(), config.route.to_string(), config.domain.to_string()));
+    resp.map(|body| {
+        body.map_frame(move |frame| {
+            if let Some((backend, route, domain)) = labels.take() {
+                metrics.record_backend_ttfb(
+                    dispatched.elapsed().as_secs_f64(),
+                    &backend,
+                    &route,
+                    &domain,
+                );
+            }
+            frame
+        })
+    })
+}
+
+/// Cap a backend response body at the route's `max_response_body_bytes`. Past the cap the body
+/// fails, so the client sees an aborted response rather than a truncated one.
+fn limit_response_body(
+    resp: Response<Incoming>,
+    backend: &str,
+    config: &ForwardConfig<'_>,
+) -> Response<LimitedBody<Incoming>> {
+    let limit = config.max_response_body_bytes;
+    if limit.is_none() {
+        return resp.map(|body| LimitedBody::new(body, None));
+    }
+    let metrics = Arc::clone(&config.metrics);
+    let (backend, route, domain) =
+        (backend.to_string(), config.route.to_string(), config.domain.to_string());
+    resp.map(|body| {
+        LimitedBody::new(body, limit).on_exceeded(move || {
+            metrics.record_response_body_too_large(&backend, &route, &domain);
+        })
+    })
+}
+
+/// Feed the outcome of the request to the backend's circuit breaker, if it has one.
+fn record_circuit_breaker(config: &ForwardConfig<'_>, backend: &str, success: bool) {
+    if let Some(cb) = config.circuit_breaker.as_ref() {
+        if let Some(state) = cb.record(success) {
+            config
+                .metrics
+                .record_circuit_breaker_transition(backend, state.as_str());
+        }
+    }
+}
+
+/// Record a backend request that ran out of time and build the `504` error for it.
+fn backend_timeout(kind: TimeoutKind, backend: &str, config: &ForwardConfig<'_>) -> HttpError {
+    let error = HttpError::BackendTimeout(kind);
+    config
+        .metrics
+        .record_backend_timeout(backend, kind.as_str(), config.route, config.domain);
+    config
+        .metrics
+        .record_backend_error(backend, error.error_type(), config.route, config.domain);
+    error
+}
+
+/// Record a backend request cut by its backend's drain timeout and build the `503` error for it.
+fn backend_drain_cutoff(backend: &str, config: &ForwardConfig<'_>) -> HttpError {
+    let error = HttpError::BackendDrainCutoff;
+    config.metrics.record_backend_drain_cutoff(backend);
+    config
+        .metrics
+        .record_backend_error(backend, error.error_type(), config.route, config.domain);
+    error
+}
+
+/// Fail a response body still streaming when the backend's drain timeout passes.
+fn cut_on_drain_timeout<B>(
+    resp: Response<B>,
+    backend: &str,
+    config: &ForwardConfig<'_>,
+) -> Response<CutoffBody<B>> {
+    let metrics = Arc::clone(&config.metrics);
+    let backend = backend.to_string();
+    let cutoff = config.drain_cutoff.clone();
+    resp.map(|body| {
+        CutoffBody::new(body, cutoff)
+            .on_cutoff(move || metrics.record_backend_drain_cutoff(&backend))
+    })
+}
+
+/// Fail a response body still streaming at the route's `total_ms` deadline, so the client sees
+/// an aborted response rather than one that hangs past the limit.
+fn limit_response_time<B>(
+    resp: Response<B>,
+    deadline: Option<Instant>,
+    backend: &str,
+    config: &ForwardConfig<'_>,
+) -> Response<DeadlineBody<B>> {
+    if deadline.is_none() {
+        return resp.map(|body| DeadlineBody::new(body, None));
+    }
+    let metrics = Arc::clone(&config.metrics);
+    let (backend, route, domain) =
+        (backend.to_string(), config.route.to_string(), config.domain.to_string());
+    resp.map(|body| {
+        DeadlineBody::new(body, deadline).on_expired(move || {
+            metrics.record_backend_timeout(&backend, TimeoutKind::Total.as_str(), &route, &domain);
+        })
+    })
+}
+
+/// Count the response body bytes delivered to the client in `huginn_response_bytes_total`.
+fn count_response_bytes<B>(
+    resp: Response<B>,
+    backend: &str,
+    config: &ForwardConfig<'_>,
+) -> Response<CountedBody<B>> {
+    let metrics = Arc::clone(&config.metrics);
+    let (backend, route, domain) =
+        (backend.to_string(), config.route.to_string(), config.domain.to_string());
+    resp.map(|body| {
+        CountedBody::new(body, move |bytes| {
+            metrics.record_response_bytes(bytes, &backend, &route, &domain);
+        })
+    })
+}
@@ -0,0 +1,54 @@
+//! gRPC support for routes with `protocol = "grpc"`.
+//!
+//! gRPC runs over HTTP/2 and reports its outcome in the `grpc-status` trailer rather than the
+//! HTTP status, which is almost always `200`. Trailers already pass through the proxy untouched
+//! (response bodies are forwarded frame by frame); this module only peeks at them so the status
+//! can be surfaced in `huginn_grpc_responses_total`.
+
+use http::header::CONTENT_TYPE;
+use http::HeaderMap;
+use http_body_util::combinators::BoxBody;
+use http_body_util::BodyExt;
+use hyper::body::Body;
+
+/// Trailer (or trailers-only header) carrying the gRPC status code.
+pub const GRPC_STATUS: &str = "grpc-status";
+
+const GRPC_CONTENT_TYPE: &str = "application/grpc";
+
+/// `true` when `Content-Type` is `application/grpc` or one of its `+proto` / `+json` /
+/// parameterised forms. gRPC-Web (`application/grpc-web*`) is not native gRPC and is excluded.
+pub fn is_grpc_content_type(headers: &HeaderMap) -> bool {
+    let Some(value) = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let Some((head, rest)) = value.split_at_checked(GRPC_CONTENT_TYPE.len()) else {
+        return false;
+    };
+    if !head.eq_ignore_ascii_case(GRPC_CONTENT_TYPE) {
+        return false;
+    }
+    rest.is_empty() || rest.starts_with('+') || rest.starts_with(';')
+}
+
+/// The `grpc-status` value in `headers`, if present and valid UTF-8.
+pub fn grpc_status(headers: &HeaderMap) -> Option<&str> {
+    headers.get(GRPC_STATUS).and_then(|v| v.to_str().ok())
+}
+
+/// Wrap a gRPC response body so `on_status` is called with `grpc-status` when the trailers
+/// frame goes by. Frames are forwarded unchanged.
+pub fn observe_grpc_status<B, F>(body: B, mut on_status: F) -> BoxBody<B::Data, B::Error>
+where
+    B: Body + Send + Sync + 'static,
+    B::Data: Send,
+    F: FnMut(&str) + Send + Sync + 'static,
+{
+    body.map_frame(move |frame| {
+        if let Some(status) = frame.trailers_ref().and_then(grpc_status) {
+            on_status(status);
+        }
+        frame
+    })
+    .boxed()
+}
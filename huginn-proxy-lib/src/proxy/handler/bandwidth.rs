@@ -0,0 +1,43 @@
+use std::net::IpAddr;
+
+use crate::config::{BandwidthConfig, BandwidthKey};
+use crate::proxy::bandwidth::{BandwidthLimits, Shaping};
+use crate::proxy::router::RouteMatch;
+
+/// Collect the token buckets shaping the request's bodies: its route's and its backend's
+/// `bandwidth`, each keyed per its `limit_by`.
+///
+/// `client_ip` is the client as resolved through `trusted_proxies`; `ja4` the connection's JA4,
+/// if any. Returns `None` if neither the route nor the backend is shaped.
+pub fn shape_bandwidth(
+    limits: &BandwidthLimits,
+    route_match: &RouteMatch,
+    domain: &str,
+    backend: &str,
+    backend_bandwidth: Option<&BandwidthConfig>,
+    client_ip: IpAddr,
+    ja4: Option<&str>,
+) -> Option<Shaping> {
+    if route_match.bandwidth.is_none() && backend_bandwidth.is_none() {
+        return None;
+    }
+    let client_key = |config: &BandwidthConfig| match config.limit_by {
+        BandwidthKey::Shared => None,
+        BandwidthKey::Ip => Some(client_ip.to_string()),
+        BandwidthKey::Ja4 => Some(ja4.map_or_else(|| client_ip.to_string(), str::to_string)),
+    };
+    let mut shaping = Shaping::default();
+    if let Some(config) = route_match.bandwidth {
+        let client = client_key(config);
+        let prefix = route_match.matched_prefix;
+        shaping = shaping.with(|direction| {
+            limits.route_bucket(domain, prefix, client.as_deref(), direction, config)
+        });
+    }
+    if let Some(config) = backend_bandwidth {
+        let client = client_key(config);
+        shaping = shaping
+            .with(|direction| limits.backend_bucket(backend, client.as_deref(), direction, config));
+    }
+    Some(shaping)
+}
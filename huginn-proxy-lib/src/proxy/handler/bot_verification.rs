@@ -0,0 +1,61 @@
+use http::header::USER_AGENT;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::config::SpoofedAction;
+use crate::proxy::http_result::{HttpError, HttpResult};
+use crate::proxy::SecurityContext;
+use crate::security::BotVerdict;
+use crate::telemetry::Metrics;
+
+/// Run `[security.bot_verification]` on a request from `peer_ip` (the client IP is resolved
+/// through `trusted_proxies`).
+///
+/// Removes the client's own verification header, then, when the `User-Agent` claims one of the
+/// configured crawlers, sets it to `true` (verified) or `false` (spoofed, or the lookups
+/// failed). Fails with `HttpError::BotSpoofed` when the claim was disproved and `on_spoofed`
+/// is `block`.
+pub async fn verify_bot(
+    headers: &mut HeaderMap,
+    security: &SecurityContext,
+    peer_ip: IpAddr,
+    metrics: &Arc<Metrics>,
+) -> HttpResult<()> {
+    let config = &security.bot_verification;
+    if !config.enabled {
+        return Ok(());
+    }
+    let Ok(header) = HeaderName::from_bytes(config.header.as_bytes()) else {
+        return Ok(());
+    };
+    headers.remove(&header);
+
+    let Some(crawler) = headers
+        .get(USER_AGENT)
+        .and_then(|ua| ua.to_str().ok())
+        .and_then(|ua| config.crawler_for(ua))
+    else {
+        return Ok(());
+    };
+    let client_ip = security.trusted_proxies.client_ip(peer_ip, headers);
+    let verdict = security
+        .bot_verifier
+        .verify(config, crawler, client_ip)
+        .await;
+    metrics.record_bot_verification(&crawler.name, verdict.as_str());
+
+    if verdict == BotVerdict::Spoofed && config.on_spoofed == SpoofedAction::Block {
+        debug!(%client_ip, crawler = %crawler.name, "request blocked: crawler claim not verified");
+        let status = StatusCode::from_u16(config.status).unwrap_or(StatusCode::FORBIDDEN);
+        return Err(HttpError::BotSpoofed(status, crawler.name.clone()));
+    }
+    let value = if verdict == BotVerdict::Verified {
+        "true"
+    } else {
+        "false"
+    };
+    headers.insert(header, HeaderValue::from_static(value));
+    Ok(())
+}
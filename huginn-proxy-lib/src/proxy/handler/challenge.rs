@@ -0,0 +1,70 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use http::HeaderMap;
+use hyper::Response;
+use tracing::debug;
+
+use super::sticky::cookie_value;
+use crate::config::{ChallengeConfig, CustomHeader, Secret, SyntheticResponseConfig};
+use crate::fingerprinting::Ja4Fingerprints;
+use crate::proxy::synthetic_response::synthetic_route_response;
+use crate::security::{find_suspect, ObservedFingerprints};
+use crate::telemetry::metrics::values;
+use crate::telemetry::Metrics;
+use crate::utils::http::RespBody;
+use crate::utils::time::unix_now;
+
+/// Run `[security.challenge]` on a request whose connection has the `observed` fingerprints.
+///
+/// Returns the challenge page to answer with when the fingerprints match a `suspect` entry and
+/// the request carries no valid token for the connection's JA4 and `client_ip` (resolved through
+/// the trusted proxies); `None` lets the request go on.
+pub async fn check_challenge(
+    headers: &HeaderMap,
+    config: &ChallengeConfig,
+    observed: &ObservedFingerprints,
+    ja4: Option<&Ja4Fingerprints>,
+    metrics: &Arc<Metrics>,
+    client_ip: IpAddr,
+) -> Option<Response<RespBody>> {
+    if !config.is_active() {
+        return None;
+    }
+    let suspect = find_suspect(config, observed)?;
+    let prepared = config.prepared();
+    let ja4 = ja4.map(|f| f.ja4.full.to_string()).unwrap_or_default();
+    let now = unix_now();
+    if cookie_value(headers, &config.cookie_name)
+        .is_some_and(|token| prepared.tokens.verify(token, &ja4, client_ip, now))
+    {
+        metrics.record_challenge(values::CHALLENGE_PASSED);
+        return None;
+    }
+
+    debug!(
+        %client_ip,
+        kind = suspect.kind,
+        entry = suspect.entry,
+        "serving challenge page to suspect fingerprint"
+    );
+    metrics.record_challenge(values::CHALLENGE_SERVED);
+    let page = prepared
+        .page
+        .replace("{{cookie_name}}", &config.cookie_name)
+        .replace("{{challenge}}", &prepared.tokens.issue(&ja4, client_ip, now))
+        .replace("{{difficulty}}", &config.difficulty.to_string())
+        .replace("{{ttl_secs}}", &config.ttl_secs.to_string());
+    let response = SyntheticResponseConfig {
+        enabled: true,
+        status: config.status,
+        headers: vec![CustomHeader {
+            name: "cache-control".to_string(),
+            value: Secret::new("no-store".to_string()),
+        }],
+        body: Some(page),
+        body_file: None,
+        content_type: Some("text/html; charset=utf-8".to_string()),
+    };
+    Some(synthetic_route_response(&response).await)
+}
@@ -0,0 +1,52 @@
+use http::{HeaderMap, StatusCode};
+use hyper::header::{HeaderName, HeaderValue};
+
+use crate::config::{ClientCertConfig, MissingClientCert};
+use crate::fingerprinting::client_cert;
+use crate::proxy::http_result::{HttpError, HttpResult};
+use crate::tls::ClientCertInfo;
+
+/// Client certificate state of one mTLS connection, shared by all of its requests.
+#[derive(Debug, Clone)]
+pub struct ClientCertContext {
+    /// Certificate presented by the client, `None` under `client_auth = optional` when the
+    /// client sent none.
+    pub cert: Option<ClientCertInfo>,
+    pub policy: ClientCertConfig,
+}
+
+impl ClientCertContext {
+    /// Enforce `on_missing`: a connection without a certificate is rejected with the configured
+    /// status when the policy says so.
+    pub fn check(&self) -> HttpResult<()> {
+        if self.cert.is_none() && self.policy.on_missing == MissingClientCert::Reject {
+            let status =
+                StatusCode::from_u16(self.policy.reject_status).unwrap_or(StatusCode::FORBIDDEN);
+            return Err(HttpError::ClientCertificateRequired(status));
+        }
+        Ok(())
+    }
+}
+
+/// Strip client-supplied `x-huginn-client-cert-*` headers, then inject the connection's
+/// certificate identity when `forward_headers` is enabled.
+///
+/// Stripping is unconditional (plain HTTP and mTLS-less listeners included) so a client can
+/// never impersonate a certificate holder toward the backend.
+pub fn apply_client_cert_headers(headers: &mut HeaderMap, context: Option<&ClientCertContext>) {
+    for &name in client_cert::ALL {
+        headers.remove(name);
+    }
+    let Some(ClientCertContext { cert: Some(cert), policy }) = context else {
+        return;
+    };
+    if !policy.forward_headers {
+        return;
+    }
+    if let Ok(hv) = HeaderValue::from_bytes(cert.subject.as_bytes()) {
+        headers.insert(HeaderName::from_static(client_cert::SUBJECT), hv);
+    }
+    if let Ok(hv) = HeaderValue::from_str(&cert.sha256) {
+        headers.insert(HeaderName::from_static(client_cert::SHA256), hv);
+    }
+}
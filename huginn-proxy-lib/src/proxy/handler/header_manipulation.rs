@@ -1,15 +1,17 @@
-use crate::config::{HeaderManipulation, HeaderManipulationGroup};
+use crate::config::{CustomHeader, HeaderManipulation, HeaderManipulationGroup};
+use crate::proxy::handler::headers::HeaderTemplateContext;
 use crate::telemetry::metrics::values;
 use crate::telemetry::Metrics;
 use http::{HeaderMap, HeaderName, HeaderValue};
 use std::sync::Arc;
 
-/// Apply header manipulation group (add and remove headers)
+/// Apply header manipulation group (remove, set and add headers)
 ///
 /// # Arguments
 /// * `headers` - The header map to modify
 /// * `manipulation` - The header manipulation configuration
 /// * `context` - Context string (use `values::CONTEXT_REQUEST` or `values::CONTEXT_RESPONSE`) for metrics
+/// * `template` - Values for `${...}` variables in `set` / `add` header values
 /// * `metrics` - Metrics instance for tracking header operations
 ///
 /// # Example
@@ -17,17 +19,25 @@ use std::sync::Arc;
 /// use http::HeaderMap;
 /// use huginn_proxy_lib::config::HeaderManipulationGroup;
 /// use huginn_proxy_lib::proxy::handler::header_manipulation::apply_header_manipulation_group;
+/// use huginn_proxy_lib::proxy::handler::HeaderTemplateContext;
 /// use huginn_proxy_lib::telemetry::{metrics::values, Metrics};
 ///
 /// let mut headers = HeaderMap::new();
 /// let manipulation = HeaderManipulationGroup::default();
 ///
-/// apply_header_manipulation_group(&mut headers, &manipulation, values::CONTEXT_REQUEST, &Metrics::new_noop());
+/// apply_header_manipulation_group(
+///     &mut headers,
+///     &manipulation,
+///     values::CONTEXT_REQUEST,
+///     &HeaderTemplateContext::default(),
+///     &Metrics::new_noop(),
+/// );
 /// ```
 pub fn apply_header_manipulation_group(
     headers: &mut HeaderMap,
     manipulation: &HeaderManipulationGroup,
     context: &str,
+    template: &HeaderTemplateContext<'_>,
     metrics: &Arc<Metrics>,
 ) {
     // Remove headers first
@@ -36,18 +46,29 @@ pub fn apply_header_manipulation_group(
         metrics.record_headers_removed(removed_count, context);
     }
 
+    // Then rewrite headers that are present
+    if !manipulation.set.is_empty() {
+        let set_count = set_headers(headers, &expand_values(&manipulation.set, template));
+        metrics.record_headers_added(set_count, context);
+    }
+
     // Then add headers
     if !manipulation.add.is_empty() {
-        let to_add: Vec<(String, String)> = manipulation
-            .add
-            .iter()
-            .map(|h| (h.name.clone(), h.value.expose().clone()))
-            .collect();
-        let added_count = add_headers(headers, &to_add);
+        let added_count = add_headers(headers, &expand_values(&manipulation.add, template));
         metrics.record_headers_added(added_count, context);
     }
 }
 
+fn expand_values(
+    headers: &[CustomHeader],
+    template: &HeaderTemplateContext<'_>,
+) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|h| (h.name.clone(), template.expand(h.value.expose()).into_owned()))
+        .collect()
+}
+
 /// Apply header manipulation configuration to request headers
 ///
 /// # Arguments
@@ -55,6 +76,7 @@ pub fn apply_header_manipulation_group(
 /// * `global_manipulation` - Global header manipulation configuration (optional)
 /// * `domain_manipulation` - Per-domain header manipulation configuration (optional)
 /// * `route_manipulation` - Per-route header manipulation configuration (optional)
+/// * `template` - Values for `${...}` variables in `set` / `add` header values
 ///
 /// Applies the three scopes in order: global → domain → route. Because `add`
 /// overwrites, the most specific scope wins for a given header name.
@@ -63,18 +85,37 @@ pub fn apply_request_header_manipulation(
     global_manipulation: Option<&HeaderManipulation>,
     domain_manipulation: Option<&HeaderManipulation>,
     route_manipulation: Option<&HeaderManipulation>,
+    template: &HeaderTemplateContext<'_>,
     metrics: &Arc<Metrics>,
 ) {
     if let Some(global) = global_manipulation {
-        apply_header_manipulation_group(headers, &global.request, values::CONTEXT_REQUEST, metrics);
+        apply_header_manipulation_group(
+            headers,
+            &global.request,
+            values::CONTEXT_REQUEST,
+            template,
+            metrics,
+        );
     }
 
     if let Some(domain) = domain_manipulation {
-        apply_header_manipulation_group(headers, &domain.request, values::CONTEXT_REQUEST, metrics);
+        apply_header_manipulation_group(
+            headers,
+            &domain.request,
+            values::CONTEXT_REQUEST,
+            template,
+            metrics,
+        );
     }
 
     if let Some(route) = route_manipulation {
-        apply_header_manipulation_group(headers, &route.request, values::CONTEXT_REQUEST, metrics);
+        apply_header_manipulation_group(
+            headers,
+            &route.request,
+            values::CONTEXT_REQUEST,
+            template,
+            metrics,
+        );
     }
 }
 
@@ -85,6 +126,7 @@ pub fn apply_request_header_manipulation(
 /// * `global_manipulation` - Global header manipulation configuration (optional)
 /// * `domain_manipulation` - Per-domain header manipulation configuration (optional)
 /// * `route_manipulation` - Per-route header manipulation configuration (optional)
+/// * `template` - Values for `${...}` variables in `set` / `add` header values
 ///
 /// Applies the three scopes in order: global → domain → route. Because `add`
 /// overwrites, the most specific scope wins for a given header name.
@@ -93,6 +135,7 @@ pub fn apply_response_header_manipulation(
     global_manipulation: Option<&HeaderManipulation>,
     domain_manipulation: Option<&HeaderManipulation>,
     route_manipulation: Option<&HeaderManipulation>,
+    template: &HeaderTemplateContext<'_>,
     metrics: &Arc<Metrics>,
 ) {
     if let Some(global) = global_manipulation {
@@ -100,6 +143,7 @@ pub fn apply_response_header_manipulation(
             headers,
             &global.response,
             values::CONTEXT_RESPONSE,
+            template,
             metrics,
         );
     }
@@ -109,6 +153,7 @@ pub fn apply_response_header_manipulation(
             headers,
             &domain.response,
             values::CONTEXT_RESPONSE,
+            template,
             metrics,
         );
     }
@@ -118,6 +163,7 @@ pub fn apply_response_header_manipulation(
             headers,
             &route.response,
             values::CONTEXT_RESPONSE,
+            template,
             metrics,
         );
     }
@@ -161,6 +207,39 @@ pub fn remove_headers(headers: &mut HeaderMap, headers_to_remove: &[String]) ->
     removed_count
 }
 
+/// Rewrite headers that are already present in a header map; absent headers stay absent
+///
+/// # Arguments
+/// * `headers` - The header map to modify
+/// * `headers_to_set` - List of (name, value) tuples to rewrite
+///
+/// # Example
+/// ```
+/// use http::HeaderMap;
+/// use huginn_proxy_lib::proxy::handler::header_manipulation::set_headers;
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert("server", "nginx".parse().unwrap());
+/// let to_set = vec![
+///     ("server".to_string(), "huginn".to_string()),
+///     ("x-absent".to_string(), "value".to_string()),
+/// ];
+///
+/// assert_eq!(set_headers(&mut headers, &to_set), 1);
+/// assert_eq!(headers.get("server").unwrap(), "huginn");
+/// assert!(headers.get("x-absent").is_none());
+/// ```
+pub fn set_headers(headers: &mut HeaderMap, headers_to_set: &[(String, String)]) -> u64 {
+    let present: Vec<(String, String)> = headers_to_set
+        .iter()
+        .filter(|(name, _)| {
+            HeaderName::from_bytes(name.as_bytes()).is_ok_and(|n| headers.contains_key(&n))
+        })
+        .cloned()
+        .collect();
+    add_headers(headers, &present)
+}
+
 /// Add headers to a header map (overwrite if exists)
 ///
 /// # Arguments
@@ -1,10 +1,14 @@
+use http::HeaderMap;
 use huginn_net_http::AkamaiFingerprint;
-use hyper::body::Incoming;
-use hyper::header::HeaderValue;
+use hyper::header::{HeaderName, HeaderValue};
 use hyper::Request;
-use std::net::SocketAddr;
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
 
-use crate::fingerprinting::headers::forwarded;
+use crate::config::{render_header_template, Ja4Variant, TemplateVar};
+use crate::fingerprinting::headers::{forwarded, names};
+use crate::fingerprinting::{FingerprintHeaderNames, Ja4Fingerprints};
 
 /// Convert Akamai fingerprint to HTTP header value
 pub fn akamai_header_value(value: Option<&AkamaiFingerprint>) -> Option<HeaderValue> {
@@ -16,6 +20,44 @@ pub fn tls_header_value(value: Option<&huginn_net_tls::Ja4Payload>) -> Option<He
     value.and_then(|f| HeaderValue::from_str(&f.full.to_string()).ok())
 }
 
+/// Header name and value for one JA4 variant of the connection's fingerprints
+pub fn ja4_header(
+    variant: Ja4Variant,
+    fingerprints: &Ja4Fingerprints,
+) -> (&'static str, Option<HeaderValue>) {
+    let (name, value) = match variant {
+        Ja4Variant::Ja4 => (names::TLS_JA4, fingerprints.ja4.full.to_string()),
+        Ja4Variant::Ja4R => (names::TLS_JA4_R, fingerprints.ja4.raw.to_string()),
+        Ja4Variant::Ja4O => (names::TLS_JA4_O, fingerprints.ja4_original.full.to_string()),
+        Ja4Variant::Ja4Or => (names::TLS_JA4_OR, fingerprints.ja4_original.raw.to_string()),
+        Ja4Variant::Ja4S1 => (names::TLS_JA4_S1, fingerprints.ja4_stable_v1.full.to_string()),
+        Ja4Variant::Ja4S1r => (names::TLS_JA4_S1R, fingerprints.ja4_stable_v1.raw.to_string()),
+    };
+    (name, HeaderValue::from_str(&value).ok())
+}
+
+/// Inject the selected JA4 variants as `x-tls-ja4*` headers (or their `[fingerprint.headers]`
+/// names)
+///
+/// Variants not listed in `variants` are left absent (client-supplied copies are already
+/// stripped by `strip_client_fingerprints`).
+pub fn add_ja4_headers(
+    headers: &mut HeaderMap,
+    fingerprints: &Ja4Fingerprints,
+    variants: &[Ja4Variant],
+    header_names: &FingerprintHeaderNames,
+) {
+    for &variant in variants {
+        if let (name, Some(hv)) = ja4_header(variant, fingerprints) {
+            let name = header_names
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| HeaderName::from_static(name));
+            headers.insert(name, hv);
+        }
+    }
+}
+
 /// Add X-Forwarded-* headers to the request
 ///
 /// This function:
@@ -23,8 +65,8 @@ pub fn tls_header_value(value: Option<&huginn_net_tls::Ja4Payload>) -> Option<He
 /// 2. Sets X-Forwarded-Host from the resolved routing host
 /// 3. Sets X-Forwarded-Port from the peer's port
 /// 4. Sets X-Forwarded-Proto based on is_https flag
-pub fn add_forwarded_headers(
-    req: &mut Request<Incoming>,
+pub fn add_forwarded_headers<B>(
+    req: &mut Request<B>,
     peer: SocketAddr,
     is_https: bool,
     forwarded_host: &str,
@@ -71,3 +113,47 @@ pub fn add_forwarded_headers(
         req.headers_mut().insert(forwarded::PROTO, header_value);
     }
 }
+
+/// Per-request values of the `${...}` variables allowed in header manipulation `set` / `add`
+/// values. Unknown values (e.g. `ja4` on plain HTTP) expand to an empty string.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeaderTemplateContext<'a> {
+    pub client_ip: Option<IpAddr>,
+    pub ja4: Option<&'a Ja4Fingerprints>,
+    pub route_prefix: &'a str,
+    pub host: &'a str,
+}
+
+impl HeaderTemplateContext<'_> {
+    /// Expand the variables of a header value template; values without `${` are borrowed as is.
+    pub fn expand<'t>(&self, template: &'t str) -> Cow<'t, str> {
+        if !template.contains("${") {
+            return Cow::Borrowed(template);
+        }
+        match render_header_template(template, |var, out| self.write_var(var, out)) {
+            Ok(value) => Cow::Owned(value),
+            Err(e) => {
+                // Unreachable for a validated config; keep the raw value rather than drop it.
+                tracing::warn!(error = %e, "Failed to expand header value template");
+                Cow::Borrowed(template)
+            }
+        }
+    }
+
+    fn write_var(&self, var: TemplateVar, out: &mut String) {
+        match var {
+            TemplateVar::ClientIp => {
+                if let Some(ip) = self.client_ip {
+                    let _ = write!(out, "{ip}");
+                }
+            }
+            TemplateVar::Ja4 => {
+                if let Some(fingerprints) = self.ja4 {
+                    let _ = write!(out, "{}", fingerprints.ja4.full);
+                }
+            }
+            TemplateVar::RoutePrefix => out.push_str(self.route_prefix),
+            TemplateVar::Host => out.push_str(self.host),
+        }
+    }
+}
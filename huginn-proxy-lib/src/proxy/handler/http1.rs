@@ -0,0 +1,47 @@
+//! HTTP/1.x message checks hyper leaves to the proxy (RFC 9112).
+//!
+//! hyper parses a request carrying both `Transfer-Encoding` and `Content-Length` by the
+//! former and passes both headers on; a request without `Host` reaches the handler as well.
+//! [`check_http1_request`] runs first in [`handle_proxy_request`](super::handle_proxy_request)
+//! and answers these with a plain `400` instead of forwarding them or routing them by an empty
+//! host.
+
+use http::header::{CONTENT_LENGTH, HOST, TRANSFER_ENCODING};
+use http::{HeaderValue, Request, Version};
+
+use crate::proxy::http_result::HttpError;
+
+/// Reject an HTTP/1.x request with ambiguous framing or a missing/duplicated `Host`, and give an
+/// HTTP/1.0 request without `Host` the configured `default_host`. HTTP/2 requests pass through:
+/// hyper already rejects connection-specific headers there, and `:authority` replaces `Host`.
+pub fn check_http1_request<B>(
+    req: &mut Request<B>,
+    default_host: Option<&str>,
+) -> Result<(), HttpError> {
+    let version = req.version();
+    if version != Version::HTTP_10 && version != Version::HTTP_11 {
+        return Ok(());
+    }
+
+    // RFC 9112 §6.1: a request with both may be an attempt at request smuggling; the proxy
+    // never forwards it (hyper would frame it by Transfer-Encoding, a backend might not).
+    let headers = req.headers();
+    if headers.contains_key(TRANSFER_ENCODING) && headers.contains_key(CONTENT_LENGTH) {
+        return Err(HttpError::AmbiguousFraming("both Transfer-Encoding and Content-Length"));
+    }
+
+    // RFC 9112 §3.2: exactly one Host. HTTP/1.0 predates the requirement.
+    match headers.get_all(HOST).iter().count() {
+        0 if version == Version::HTTP_11 => Err(HttpError::InvalidHostInRequestHeader),
+        0 => {
+            if let Some(host) = default_host.filter(|_| req.uri().host().is_none()) {
+                if let Ok(value) = HeaderValue::from_str(host) {
+                    req.headers_mut().insert(HOST, value);
+                }
+            }
+            Ok(())
+        }
+        1 => Ok(()),
+        _ => Err(HttpError::InvalidHostInRequestHeader),
+    }
+}
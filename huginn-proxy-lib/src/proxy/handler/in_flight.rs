@@ -0,0 +1,99 @@
+use http::StatusCode;
+use hyper::header::{HeaderValue, RETRY_AFTER};
+use hyper::Response;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::config::BackendQueueConfig;
+use crate::proxy::concurrency::{ConcurrencyLimits, InFlight, QueueRejection};
+use crate::proxy::router::RouteMatch;
+use crate::telemetry::metrics::values;
+use crate::telemetry::Metrics;
+use crate::utils::http::{json_error, RespBody};
+
+/// Take the request's slots on its route and backend `max_in_flight` limits. With a backend
+/// `queue`, the request waits for the backend slot at its route's `priority`.
+///
+/// Returns:
+/// - `Ok(None)` if neither the route nor the backend is limited
+/// - `Ok(Some(slots))` to be held until the response body is done
+/// - `Err(503 response)` with `Retry-After` if either limit is reached, or the queue wait ran out
+/// - `Err(429 response)` with `Retry-After` if the backend queue is full
+pub async fn acquire_in_flight(
+    limits: &ConcurrencyLimits,
+    route_match: &RouteMatch<'_>,
+    domain: &str,
+    backend: &str,
+    backend_max: Option<usize>,
+    backend_queue: Option<&BackendQueueConfig>,
+    metrics: &Arc<Metrics>,
+) -> Result<Option<InFlight>, Response<RespBody>> {
+    if route_match.max_in_flight.is_none() && backend_max.is_none() {
+        return Ok(None);
+    }
+    let prefix = route_match.matched_prefix;
+    let route_permit = match route_match.max_in_flight {
+        Some(max) => match limits.try_acquire_route(domain, prefix, max) {
+            Some(permit) => Some(permit),
+            None => return Err(reject(values::SCOPE_ROUTE, prefix, domain, metrics)),
+        },
+        None => None,
+    };
+
+    let mut in_flight = InFlight::new(Arc::clone(metrics));
+    if let Some(permit) = route_permit {
+        in_flight = in_flight.with_route(permit, prefix, domain);
+    }
+    match (backend_max, backend_queue) {
+        (Some(max), Some(queue)) => {
+            let priority = route_match.priority;
+            let queue = limits.backend_queue(backend, max, queue);
+            match queue.acquire(priority, backend, metrics).await {
+                Ok(permit) => in_flight = in_flight.with_backend(permit, backend),
+                Err(rejection) => {
+                    return Err(reject_queued(rejection, backend, priority.as_str(), metrics))
+                }
+            }
+        }
+        (Some(max), None) => match limits.try_acquire_backend(backend, max) {
+            Some(permit) => in_flight = in_flight.with_backend(permit, backend),
+            None => return Err(reject(values::SCOPE_BACKEND, prefix, domain, metrics)),
+        },
+        (None, _) => {}
+    }
+    Ok(Some(in_flight))
+}
+
+fn reject(
+    scope: &'static str,
+    route: &str,
+    domain: &str,
+    metrics: &Arc<Metrics>,
+) -> Response<RespBody> {
+    debug!(scope, route, domain, "max_in_flight reached");
+    metrics.record_concurrency_rejection(scope, route, domain);
+    let mut resp = json_error(StatusCode::SERVICE_UNAVAILABLE, "too_many_in_flight");
+    resp.headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+    resp
+}
+
+fn reject_queued(
+    rejection: QueueRejection,
+    backend: &str,
+    priority: &'static str,
+    metrics: &Arc<Metrics>,
+) -> Response<RespBody> {
+    let (reason, status, error) = match rejection {
+        QueueRejection::Full => (values::QUEUE_FULL, StatusCode::TOO_MANY_REQUESTS, "queue_full"),
+        QueueRejection::Timeout => {
+            (values::QUEUE_TIMEOUT, StatusCode::SERVICE_UNAVAILABLE, "queue_timeout")
+        }
+    };
+    debug!(backend, priority, reason, "backend queue rejected request");
+    metrics.record_backend_queue_rejection(backend, reason, priority);
+    let mut resp = json_error(status, error);
+    resp.headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+    resp
+}
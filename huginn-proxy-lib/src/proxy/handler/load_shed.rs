@@ -0,0 +1,34 @@
+use http::StatusCode;
+use hyper::header::{HeaderValue, RETRY_AFTER};
+use hyper::Response;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::proxy::load_shedding::LoadShedder;
+use crate::proxy::router::RouteMatch;
+use crate::telemetry::Metrics;
+use crate::utils::http::{json_error, RespBody};
+
+/// Check whether the request is shed because the proxy is overloaded.
+///
+/// Returns:
+/// - `None` if request is allowed to proceed
+/// - `Some(503 response)` with `Retry-After` if it is shed
+pub fn check_load_shedding(
+    load_shedder: Option<&Arc<LoadShedder>>,
+    route_match: &RouteMatch,
+    domain: &str,
+    metrics: &Arc<Metrics>,
+) -> Option<Response<RespBody>> {
+    let shedder = load_shedder?;
+    if !shedder.should_shed(route_match.priority) {
+        return None;
+    }
+    let priority = route_match.priority.as_str();
+    debug!(route = route_match.matched_prefix, domain, priority, "request shed");
+    metrics.record_load_shed(route_match.matched_prefix, domain, priority);
+    let mut resp = json_error(StatusCode::SERVICE_UNAVAILABLE, "overloaded");
+    resp.headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+    Some(resp)
+}
@@ -1,11 +1,40 @@
+pub mod bandwidth;
+pub mod bot_verification;
+pub mod challenge;
+pub mod client_cert;
 pub mod header_manipulation;
 pub mod headers;
 pub mod host;
+pub mod http1;
+pub mod in_flight;
+pub mod load_shed;
+pub mod privacy;
 pub mod rate_limit_validation;
 pub mod request;
 pub mod resolve;
-pub use headers::{add_forwarded_headers, akamai_header_value, tls_header_value};
+pub mod sticky;
+pub mod strict_http;
+pub mod tls_info;
+pub mod waf;
+pub mod whoami;
+pub use bandwidth::shape_bandwidth;
+pub use bot_verification::verify_bot;
+pub use challenge::check_challenge;
+pub use client_cert::{apply_client_cert_headers, ClientCertContext};
+pub use headers::{
+    add_forwarded_headers, add_ja4_headers, akamai_header_value, ja4_header, tls_header_value,
+    HeaderTemplateContext,
+};
 pub use host::{extract_request_host_inner, strip_host_port};
+pub use http1::check_http1_request;
+pub use in_flight::acquire_in_flight;
+pub use load_shed::check_load_shedding;
+pub use privacy::{scrub_request_headers, scrub_response_headers};
 pub use rate_limit_validation::check_rate_limit;
 pub use request::handle_proxy_request;
 pub use resolve::{resolve_security, EffectiveSecurity};
+pub use sticky::StickySession;
+pub use strict_http::{check_strict_http, ProtocolViolation};
+pub use tls_info::apply_tls_info_headers;
+pub use waf::check_waf;
+pub use whoami::{is_whoami_request, whoami_response};
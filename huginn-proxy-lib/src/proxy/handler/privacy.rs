@@ -0,0 +1,122 @@
+//! `privacy` on a route: drop, remove or hash configured headers of the traffic exchanged with
+//! its backend, counting each scrubbed header in `huginn_privacy_headers_scrubbed_total`.
+
+use std::sync::Arc;
+
+use aws_lc_rs::hmac;
+use http::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::config::dynamic::privacy::is_kept_header;
+use crate::config::{PrivacyConfig, PrivacyHeaderGroup};
+use crate::telemetry::metrics::values;
+use crate::telemetry::Metrics;
+use crate::utils::hex;
+
+/// Scrub the headers of a request about to be forwarded on a route with `privacy`.
+pub fn scrub_request_headers(
+    headers: &mut HeaderMap,
+    privacy: &PrivacyConfig,
+    route: &str,
+    domain: &str,
+    metrics: &Arc<Metrics>,
+) {
+    let counts = scrub_headers(headers, &privacy.request, privacy);
+    counts.record(metrics, values::CONTEXT_REQUEST, route, domain);
+}
+
+/// Scrub the headers of a backend response on a route with `privacy`.
+pub fn scrub_response_headers(
+    headers: &mut HeaderMap,
+    privacy: &PrivacyConfig,
+    route: &str,
+    domain: &str,
+    metrics: &Arc<Metrics>,
+) {
+    let counts = scrub_headers(headers, &privacy.response, privacy);
+    counts.record(metrics, values::CONTEXT_RESPONSE, route, domain);
+}
+
+/// Headers scrubbed in one pass, by action.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScrubCounts {
+    pub not_allowed: u64,
+    pub stripped: u64,
+    pub hashed: u64,
+}
+
+impl ScrubCounts {
+    fn record(self, metrics: &Metrics, context: &'static str, route: &str, domain: &str) {
+        for (action, count) in [
+            (values::PRIVACY_NOT_ALLOWED, self.not_allowed),
+            (values::PRIVACY_STRIPPED, self.stripped),
+            (values::PRIVACY_HASHED, self.hashed),
+        ] {
+            metrics.record_privacy_headers_scrubbed(count, route, domain, context, action);
+        }
+    }
+}
+
+/// Apply `group` to `headers`: drop the headers `allow` does not list, remove `strip` headers and
+/// hash the values of `hash` headers with the `hash_key` of `privacy`. Returns the number of
+/// header names affected by each step.
+pub fn scrub_headers(
+    headers: &mut HeaderMap,
+    group: &PrivacyHeaderGroup,
+    privacy: &PrivacyConfig,
+) -> ScrubCounts {
+    let mut counts = ScrubCounts::default();
+    if !group.is_active() {
+        return counts;
+    }
+    if let Some(allow) = &group.allow {
+        let dropped: Vec<HeaderName> = headers
+            .keys()
+            .filter(|name| {
+                !is_kept_header(name.as_str())
+                    && !allow
+                        .iter()
+                        .any(|allowed| allowed.eq_ignore_ascii_case(name.as_str()))
+            })
+            .cloned()
+            .collect();
+        for name in dropped {
+            headers.remove(&name);
+            counts.not_allowed = counts.not_allowed.saturating_add(1);
+        }
+    }
+    for name in &group.strip {
+        if headers.remove(name.as_str()).is_some() {
+            counts.stripped = counts.stripped.saturating_add(1);
+        }
+    }
+    let Some(key) = &privacy.hash_key else {
+        return counts;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key.expose().as_bytes());
+    for name in &group.hash {
+        let Ok(name) = HeaderName::from_bytes(name.as_bytes()) else {
+            continue;
+        };
+        let hashed: Vec<HeaderValue> = headers
+            .get_all(&name)
+            .iter()
+            .map(|value| hash_value(&key, value.as_bytes()))
+            .collect();
+        if hashed.is_empty() {
+            continue;
+        }
+        headers.remove(&name);
+        for value in hashed {
+            headers.append(name.clone(), value);
+        }
+        counts.hashed = counts.hashed.saturating_add(1);
+    }
+    counts
+}
+
+/// Lowercase hex HMAC-SHA256 of `value` under `key`.
+fn hash_value(key: &hmac::Key, value: &[u8]) -> HeaderValue {
+    let tag = hmac::sign(key, value);
+    HeaderValue::from_str(&hex::encode(tag.as_ref()))
+        .unwrap_or_else(|_| HeaderValue::from_static(""))
+}
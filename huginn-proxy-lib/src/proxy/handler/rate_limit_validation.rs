@@ -1,11 +1,14 @@
 use http::StatusCode;
 use hyper::Response;
 use std::sync::Arc;
+use tokio::sync::watch;
 use tracing::debug;
 
-use crate::config::{RateLimitConfig, TrustedProxiesConfig};
+use crate::config::{LimitBy, RateLimitConfig, TrustedProxiesConfig};
 use crate::proxy::router::RouteMatch;
-use crate::security::{extract_rate_limit_key, RateLimitManager, RateLimitResult};
+use crate::security::{
+    extract_rate_limit_key, RateLimitFingerprints, RateLimitManager, RateLimitResult, TlsMetadata,
+};
 use crate::telemetry::Metrics;
 use crate::utils::http::{json_error, RespBody};
 
@@ -14,16 +17,22 @@ use crate::utils::http::{json_error, RespBody};
 /// Returns:
 /// - `None` if request is allowed to proceed
 /// - `Some(429 response)` if request exceeds rate limit
+///
+/// `tls` describes the request's connection, for the `match_tls` condition and the `ja4`, `sni`
+/// and `alpn` strategies. `fingerprint_rx` is the connection's Akamai fingerprint, passed for
+/// HTTP/2 requests only; it is read only when the effective strategy is keyed by it.
 #[allow(clippy::too_many_arguments)]
-pub fn check_rate_limit(
+pub async fn check_rate_limit(
     rate_limit_manager: Option<&Arc<RateLimitManager>>,
     rate_limit_config: &RateLimitConfig,
-    route_match: &RouteMatch,
+    route_match: &RouteMatch<'_>,
     peer: std::net::SocketAddr,
     headers: &http::HeaderMap,
     metrics: &Arc<Metrics>,
     domain: &str,
     trusted_proxies: &TrustedProxiesConfig,
+    tls: TlsMetadata<'_>,
+    fingerprint_rx: Option<&watch::Receiver<Option<huginn_net_http::AkamaiFingerprint>>>,
 ) -> Option<Response<RespBody>> {
     let manager = rate_limit_manager?;
 
@@ -31,9 +40,20 @@ pub fn check_rate_limit(
     // domain-effective config (key strategy, header); otherwise use that config. `trusted_proxies`
     // is global (not per-scope) and resolves the real client IP from XFF for ip/combined keys.
     let effective = route_match.rate_limit.unwrap_or(rate_limit_config);
+    if effective
+        .match_tls
+        .as_ref()
+        .is_some_and(|condition| !condition.matches(&tls))
+    {
+        return None;
+    }
     let limit_by = effective.limit_by;
     let limit_by_header = effective.limit_by_header.as_deref();
 
+    let akamai = fingerprint_rx
+        .filter(|_| limit_by == LimitBy::Akamai)
+        .and_then(|rx| rx.borrow().as_ref().map(|f| f.fingerprint.clone()));
+
     let rate_limit_key = extract_rate_limit_key(
         limit_by,
         peer,
@@ -41,22 +61,24 @@ pub fn check_rate_limit(
         limit_by_header,
         headers,
         trusted_proxies,
+        RateLimitFingerprints { tls, akamai: akamai.as_deref() },
     );
 
-    let strategy = format!("{:?}", limit_by).to_lowercase();
-    metrics.record_rate_limit_request(&strategy, route_match.matched_prefix, domain);
+    let strategy = limit_by.as_str();
+    metrics.record_rate_limit_request(strategy, route_match.matched_prefix, domain);
 
-    let rate_limit_result =
-        manager.check(&rate_limit_key, domain, Some(route_match.matched_prefix));
+    let rate_limit_result = manager
+        .check(&rate_limit_key, domain, Some(route_match.matched_prefix))
+        .await;
 
     match rate_limit_result {
         RateLimitResult::Limited { limit, reset_after, .. } => {
-            metrics.record_rate_limit_rejection(&strategy, route_match.matched_prefix, domain);
+            metrics.record_rate_limit_rejection(strategy, route_match.matched_prefix, domain);
             Some(create_429_response(limit, reset_after.as_secs()))
         }
         RateLimitResult::Allowed { limit, remaining } => {
             debug!(limit = limit, remaining = remaining, "Rate limit check passed");
-            metrics.record_rate_limit_allowed(&strategy, route_match.matched_prefix, domain);
+            metrics.record_rate_limit_allowed(strategy, route_match.matched_prefix, domain);
             None
         }
     }
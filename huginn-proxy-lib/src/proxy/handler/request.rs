@@ -1,29 +1,74 @@
+use super::client_cert::{apply_client_cert_headers, ClientCertContext};
 use super::host::extract_request_host;
+use super::http1::check_http1_request;
+use super::strict_http::{check_strict_http, ProtocolViolation};
 use crate::backend::UpstreamGateway;
-use crate::config::{Backend, Domain, KeepAliveConfig, DEFAULT_DOMAIN_LABEL};
-use crate::fingerprinting::names;
+use crate::config::{
+    Backend, CompressionAlgorithm, CompressionConfig, Domain, ExtAuthzFailureMode,
+    HeaderManipulation, IpFilterConfig, KeepAliveConfig, RateLimitConfig, RouteCacheConfig,
+    RouteProtocol, ServeStaticConfig, DEFAULT_DOMAIN_LABEL,
+};
+use crate::fingerprinting::headers::forwarded;
 use crate::fingerprinting::TcpObservation;
-use crate::proxy::forwarding::forward;
+use crate::fingerprinting::{
+    match_os, names, pack_context, FingerprintHeaderNames, FingerprintSet, Ja4Fingerprints,
+    SignedRequest, Verdict,
+};
+use crate::proxy::bandwidth::{Direction, ShapedBody, Shaping};
+use crate::proxy::body_rewrite;
+use crate::proxy::cache::{self, CacheStep, ResponseCache};
+use crate::proxy::client_tracking::TrackedClient;
+use crate::proxy::compression;
+use crate::proxy::concurrency::{InFlight, InFlightBody};
+use crate::proxy::connect::open_tunnel;
+use crate::proxy::connection::ConnectionContext;
+use crate::proxy::ext_authz::{self, AuthzDecision};
+use crate::proxy::failover::forward_with_failover;
+use crate::proxy::forwarding::{upstream_path, ForwardConfig};
+use crate::proxy::handler::bandwidth::shape_bandwidth;
+use crate::proxy::handler::bot_verification::verify_bot;
+use crate::proxy::handler::challenge::check_challenge;
 use crate::proxy::handler::header_manipulation::{
     apply_request_header_manipulation, apply_response_header_manipulation,
 };
-use crate::proxy::handler::headers::{add_forwarded_headers, akamai_header_value};
+use crate::proxy::handler::headers::{
+    add_forwarded_headers, add_ja4_headers, akamai_header_value, HeaderTemplateContext,
+};
+use crate::proxy::handler::in_flight::acquire_in_flight;
+use crate::proxy::handler::load_shed::check_load_shedding;
+use crate::proxy::handler::privacy::{scrub_request_headers, scrub_response_headers};
 use crate::proxy::handler::rate_limit_validation::check_rate_limit;
-use crate::proxy::handler::resolve::{domain_defers_ip_filter, resolve_security};
+use crate::proxy::handler::resolve::{
+    domain_defers_ip_filter, resolve_security, EffectiveSecurity,
+};
+use crate::proxy::handler::sticky::StickySession;
+use crate::proxy::handler::tls_info::apply_tls_info_headers;
+use crate::proxy::handler::waf::check_waf;
+use crate::proxy::handler::whoami::{is_whoami_request, whoami_response};
 use crate::proxy::http_result::{HttpError, HttpResult};
-use crate::proxy::ClientPool;
+use crate::proxy::middleware::ProxyRequest;
+use crate::proxy::redirect::{find_redirect, is_secure_request};
+use crate::proxy::router::RouteMatch;
+use crate::proxy::static_files;
+use crate::proxy::synthetic_response::synthetic_route_response;
+use crate::proxy::{find_backend_config, ClientPool, SecurityContext};
+use crate::security::{check_fingerprint_filter, ObservedFingerprints, TlsMetadata};
 use crate::telemetry::metrics::values;
-use crate::telemetry::Metrics;
+use crate::telemetry::{Metrics, RequestLog};
+use crate::tls::TlsHandshakeInfo;
 use http::HeaderMap;
+use http::Method;
 use http::StatusCode;
 use http::Version;
+use http_body_util::BodyExt;
 use hyper::body::Incoming;
 use hyper::header::HeaderName;
-use hyper::Request;
+use hyper::{Request, Response};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::watch;
 use tokio::time::Instant;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::utils::http::RespBody;
 
@@ -32,113 +77,303 @@ use crate::utils::http::RespBody;
 /// Returns the names of the fingerprint headers the client actually supplied.
 /// A non-empty return value means the client attempted to spoof those signatures.
 ///
-/// The detection header ([`names::SPOOFING_DETECTED`]) is also stripped here,
-/// so the client cannot forge or suppress the detection signal.
+/// The detection header ([`names::SPOOFING_DETECTED`]), the classifier tag header
+/// ([`names::CLASSIFICATION`]), the signature header ([`names::SIGNATURE`]) and the packed
+/// context header ([`names::CONTEXT`]) are also stripped here, so the client cannot forge or
+/// suppress any of these signals.
 pub fn strip_client_fingerprints(headers: &mut HeaderMap) -> Vec<&'static str> {
+    strip_client_fingerprints_named(headers, &FingerprintHeaderNames::default())
+}
+
+/// [`strip_client_fingerprints`] for the header names configured in `[fingerprint.headers]`.
+///
+/// The returned entries are the built-in names of the stripped headers (stable metric labels),
+/// whatever they are called on the wire.
+pub fn strip_client_fingerprints_named(
+    headers: &mut HeaderMap,
+    header_names: &FingerprintHeaderNames,
+) -> Vec<&'static str> {
     let mut spoofed = Vec::new();
-    for &name in names::FINGERPRINTS {
+    for (default, name) in header_names.fingerprints() {
         if headers.remove(name).is_some() {
-            spoofed.push(name);
+            spoofed.push(default);
         }
     }
-    headers.remove(names::SPOOFING_DETECTED);
+    headers.remove(header_names.spoofing_detected());
+    headers.remove(names::CLASSIFICATION);
+    headers.remove(names::SIGNATURE);
+    headers.remove(names::CONTEXT);
     spoofed
 }
 
-fn check_ip_access(
-    peer: std::net::SocketAddr,
-    ip_filter: &crate::config::IpFilterConfig,
-    metrics: &Arc<Metrics>,
-) -> HttpResult<()> {
-    let client_ip = peer.ip();
+/// Effective name of a fingerprint header, falling back to its built-in name.
+fn fingerprint_name(header_names: &FingerprintHeaderNames, default: &'static str) -> HeaderName {
+    header_names
+        .get(default)
+        .cloned()
+        .unwrap_or_else(|| HeaderName::from_static(default))
+}
 
-    if !crate::security::is_ip_allowed(client_ip, ip_filter) {
-        debug!(?peer, "IP blocked by filter");
-        metrics.record_ip_filter_denied();
-        metrics.record_error(values::ERROR_IP_BLOCKED);
-        return Err(HttpError::Forbidden);
+/// Connection and request inputs shared by the stages of [`handle_proxy_request`].
+struct RequestContext<'a> {
+    security: &'a SecurityContext,
+    metrics: &'a Arc<Metrics>,
+    fingerprint_headers: &'a FingerprintHeaderNames,
+    peer: SocketAddr,
+    is_https: bool,
+    ja4_fingerprints: Option<&'a Ja4Fingerprints>,
+    fingerprint_rx: Option<&'a AkamaiReceiver>,
+    syn_fingerprint: Option<&'a TcpObservation>,
+    client_cert: Option<&'a ClientCertContext>,
+    /// TLS details of the connection, the same for the IP filter, rate limiter and WAF.
+    tls: TlsMetadata<'a>,
+    start: Instant,
+    method: String,
+    protocol: String,
+}
+
+type AkamaiReceiver = watch::Receiver<Option<huginn_net_http::AkamaiFingerprint>>;
+
+impl RequestContext<'_> {
+    /// Record a request answered with `status` before it was routed: the entrypoint counter.
+    fn record_outcome(&self, status: u16) {
+        self.metrics
+            .record_entrypoint_request(&self.method, status, &self.protocol);
     }
 
-    metrics.record_ip_filter_allowed();
-    Ok(())
+    /// [`Self::record_outcome`] for `result`, which is passed through.
+    fn finish(&self, result: HttpResult<Response<RespBody>>) -> HttpResult<Response<RespBody>> {
+        self.record_outcome(status_of(&result));
+        result
+    }
+
+    /// The Akamai fingerprint receiver, for HTTP/2 requests only.
+    fn akamai_rx(&self, version: Version) -> Option<&AkamaiReceiver> {
+        self.fingerprint_rx.filter(|_| version == Version::HTTP_2)
+    }
 }
 
-/// Enforce the IP ACL, recording the entrypoint-request metric on rejection. Shared by the
-/// pre-routing (domain-effective) and post-routing (route-effective) check sites.
-fn enforce_ip_access(
-    peer: std::net::SocketAddr,
-    ip_filter: &crate::config::IpFilterConfig,
-    metrics: &Arc<Metrics>,
-    method: &str,
-    protocol: &str,
-) -> HttpResult<()> {
-    if let Err(e) = check_ip_access(peer, ip_filter, metrics) {
-        let status_code = StatusCode::from(e.clone()).as_u16();
-        metrics.record_entrypoint_request(method, status_code, protocol);
-        return Err(e);
+/// A request matched to a route: its effective policy and the labels of its metrics.
+struct RoutedRequest<'a> {
+    route_match: RouteMatch<'a>,
+    effective: EffectiveSecurity<'a>,
+    /// Rate-limit base, `domain.or(global)`; the route override is applied in
+    /// `check_rate_limit`.
+    rate_limit: &'a RateLimitConfig,
+    domain_headers: Option<&'a HeaderManipulation>,
+    domain_label: &'a str,
+    tenant_label: &'a str,
+}
+
+impl<'a> RoutedRequest<'a> {
+    fn new(security: &'a SecurityContext, domain: &'a Domain, route_match: RouteMatch<'a>) -> Self {
+        let effective = resolve_security(security, Some(domain), &route_match);
+        Self {
+            effective,
+            rate_limit: domain
+                .security
+                .as_ref()
+                .and_then(|s| s.rate_limit.as_ref())
+                .unwrap_or(&security.rate_limit_config),
+            domain_headers: domain.headers.as_ref(),
+            domain_label: domain.label(),
+            tenant_label: domain.tenant_label(),
+            route_match,
+        }
+    }
+
+    fn prefix(&self) -> &'a str {
+        self.route_match.matched_prefix
+    }
+
+    /// Record a routed request answered with `status`: the entrypoint counter, the route's
+    /// request counter and its duration, labelled with the `backend` that served it.
+    fn record_outcome(&self, ctx: &RequestContext<'_>, status: u16, backend: Option<&str>) {
+        ctx.record_outcome(status);
+        ctx.metrics.record_request(
+            &ctx.method,
+            status,
+            &ctx.protocol,
+            self.prefix(),
+            self.domain_label,
+            self.tenant_label,
+        );
+        ctx.metrics.record_request_duration(
+            ctx.start.elapsed().as_secs_f64(),
+            &ctx.method,
+            status,
+            &ctx.protocol,
+            self.prefix(),
+            self.domain_label,
+            self.tenant_label,
+            backend,
+        );
+    }
+
+    /// [`Self::record_outcome`] for `result`, which is passed through.
+    fn finish(
+        &self,
+        ctx: &RequestContext<'_>,
+        result: HttpResult<Response<RespBody>>,
+        backend: Option<&str>,
+    ) -> HttpResult<Response<RespBody>> {
+        self.record_outcome(ctx, status_of(&result), backend);
+        result
     }
-    Ok(())
 }
 
-/// Handle request routing and forwarding.
-///
-/// `peer` is the effective client address as resolved by `resolve_peer`, and is expected to be
-/// already canonicalized: IPv4-mapped IPv6 clients (`::ffff:a.b.c.d`, e.g. declared in a PROXY
-/// protocol header) are normalized to plain IPv4 at that single point. This handler therefore does
-/// **not** re-normalize; it relies on that contract so `ip_filter`, the rate-limit key and
-/// `X-Forwarded-For` all observe one consistent form.
-#[allow(clippy::too_many_arguments)]
-pub async fn handle_proxy_request(
-    mut req: Request<Incoming>,
-    domains: Arc<Vec<Domain>>,
-    backends: Arc<Vec<Backend>>,
-    ja4_fingerprints: Option<crate::fingerprinting::Ja4Fingerprints>,
-    fingerprint_rx: Option<watch::Receiver<Option<huginn_net_http::AkamaiFingerprint>>>,
-    syn_fingerprint: Option<TcpObservation>,
-    keep_alive: &KeepAliveConfig,
-    security: &crate::proxy::SecurityContext,
-    metrics: Arc<Metrics>,
-    peer: std::net::SocketAddr,
-    is_https: bool,
-    preserve_host: bool,
-    client_pool: &Arc<ClientPool>,
-    upstream: &UpstreamGateway,
-    connection_sni: Option<&str>,
-) -> HttpResult<hyper::Response<RespBody>> {
-    let start = Instant::now();
-    let method = req.method().to_string();
-    let protocol = format!("{:?}", req.version());
+/// Where an admitted request is answered from.
+enum Destination<'a> {
+    /// Forwarded to the selected backend.
+    Backend(String),
+    /// Answered from the route's `serve_static` directory.
+    Files(&'a ServeStaticConfig),
+}
 
-    if let Some(content_length) = req.headers().get(hyper::header::CONTENT_LENGTH) {
-        if let Ok(length_str) = content_length.to_str() {
-            if let Ok(length) = length_str.parse::<u64>() {
-                metrics.record_bytes_received(length, &protocol);
-            }
+impl Destination<'_> {
+    fn backend(&self) -> Option<&str> {
+        match self {
+            Self::Backend(backend) => Some(backend),
+            Self::Files(_) => None,
         }
     }
+}
 
-    let path = req.uri().path();
-    let host = extract_request_host(&req);
+/// Status code the client receives for `result`.
+fn status_of(result: &HttpResult<Response<RespBody>>) -> u16 {
+    match result {
+        Ok(response) => response.status().as_u16(),
+        Err(error) => StatusCode::from(error.clone()).as_u16(),
+    }
+}
 
-    let domain = crate::proxy::router::pick_domain(&domains, &host);
-    let domain_headers = domain.and_then(|d| d.headers.as_ref());
-    let domain_label: &str = domain.map_or(DEFAULT_DOMAIN_LABEL, Domain::label);
+/// Route cache config, store and lookup outcome of a request on a route with `cache`.
+type Caching<'a> = (&'a RouteCacheConfig, &'a Arc<ResponseCache>, CacheStep);
 
-    // Rate-limit base is `domain.or(global)`; the route override is applied in `check_rate_limit`.
-    let domain_security = domain.and_then(|d| d.security.as_ref());
-    let effective_rate_limit = domain_security
-        .and_then(|s| s.rate_limit.as_ref())
-        .unwrap_or(&security.rate_limit_config);
+/// Compression config and algorithm negotiated for a response.
+type Negotiated<'a> = (&'a CompressionConfig, CompressionAlgorithm);
 
-    // If no route overrides the IP filter, the domain/global filter applies to every route, so
-    // enforce it pre-routing (a blocked client never learns whether a host/route exists). If a
-    // route does override it, defer to post-routing (route-level ACL; see `resolve_security`).
-    let defer_ip_check = domain.is_some_and(domain_defers_ip_filter);
+fn check_ip_access(
+    headers: &HeaderMap,
+    ip_filter: &IpFilterConfig,
+    ctx: &RequestContext<'_>,
+) -> HttpResult<()> {
+    let peer = ctx.peer;
+    let client_ip =
+        crate::security::filtered_ip(peer.ip(), headers, ip_filter, &ctx.security.trusted_proxies);
+    // A filter with `match_tls` leaves the requests of other connections alone.
+    let applies = ip_filter
+        .match_tls
+        .as_ref()
+        .is_none_or(|condition| condition.matches(&ctx.tls));
+
+    if applies && !crate::security::is_ip_allowed(client_ip, ip_filter) {
+        debug!(?peer, %client_ip, "IP blocked by filter");
+        ctx.metrics.record_ip_filter_denied();
+        ctx.metrics.record_error(values::ERROR_IP_BLOCKED);
+        return Err(HttpError::Forbidden);
+    }
+
+    ctx.metrics.record_ip_filter_allowed();
+    Ok(())
+}
+
+/// Fingerprints of the connection carrying a request of `request_version`, as matched by the
+/// global fingerprint lists. The Akamai fingerprint only counts on HTTP/2 requests.
+fn observed_fingerprints(
+    ctx: &RequestContext<'_>,
+    request_version: Version,
+) -> ObservedFingerprints {
+    let akamai = ctx
+        .akamai_rx(request_version)
+        .and_then(|rx| rx.borrow().as_ref().map(|f| f.fingerprint.clone()));
+    ObservedFingerprints::new(ctx.ja4_fingerprints, akamai.as_deref(), ctx.syn_fingerprint)
+}
+
+/// Enforce `[security.fingerprint_filter]` against the connection's fingerprints. Runs
+/// pre-routing (the filter is global), so a blocked client never learns whether a host exists.
+fn enforce_fingerprint_filter(
+    observed: &ObservedFingerprints,
+    ctx: &RequestContext<'_>,
+) -> HttpResult<()> {
+    let filter = &ctx.security.fingerprint_filter;
+    if !filter.is_active() {
+        return Ok(());
+    }
+    let Some(block) = check_fingerprint_filter(filter, observed) else {
+        return Ok(());
+    };
+    debug!(
+        peer = ?ctx.peer,
+        kind = block.kind,
+        entry = block.entry,
+        "request blocked by fingerprint filter"
+    );
+    ctx.metrics
+        .record_fingerprint_filter_blocked(block.kind, block.entry);
+    let status = StatusCode::from_u16(filter.status).unwrap_or(StatusCode::FORBIDDEN);
+    let error = HttpError::FingerprintBlocked(status);
+    ctx.metrics.record_error(error.error_type());
+    Err(error)
+}
+
+/// HTTP/1.x `Host` handling and `[security.strict_http]` conformance checks.
+fn check_framing(req: &mut Request<Incoming>, ctx: &RequestContext<'_>) -> HttpResult<()> {
+    let security = ctx.security;
+    check_http1_request(req, security.default_host.as_deref())
+        .and_then(|()| check_strict_http(req, &security.strict_http, ctx.metrics, ctx.peer))
+        .inspect_err(|error| {
+            if let HttpError::AmbiguousFraming(_) = error {
+                let violation = ProtocolViolation::TransferEncodingWithContentLength;
+                ctx.metrics
+                    .record_protocol_violation(violation.as_str(), "rejected");
+            }
+        })
+}
+
+/// Checks every request goes through before routing: the domain's IP filter (unless a route
+/// overrides it, see `defer_ip_check`), the fingerprint filter and challenge, misdirected
+/// requests and a missing client certificate. `Some` is the answer of a request stopped here.
+async fn screen_request(
+    req: &Request<Incoming>,
+    ctx: &RequestContext<'_>,
+    domains: &[Domain],
+    domain: Option<&Domain>,
+    host: &str,
+    defer_ip_check: bool,
+) -> Option<HttpResult<Response<RespBody>>> {
+    let security = ctx.security;
+    let peer = ctx.peer;
     if !defer_ip_check {
-        let domain_ip_filter = domain_security
+        let domain_ip_filter = domain
+            .and_then(|d| d.security.as_ref())
             .and_then(|s| s.ip_filter.as_ref())
             .unwrap_or(&security.ip_filter);
-        enforce_ip_access(peer, domain_ip_filter, &metrics, &method, &protocol)?;
+        if let Err(error) = check_ip_access(req.headers(), domain_ip_filter, ctx) {
+            return Some(Err(error));
+        }
+    }
+
+    if security.fingerprint_filter.is_active() || security.challenge.is_active() {
+        let observed = observed_fingerprints(ctx, req.version());
+        if let Err(error) = enforce_fingerprint_filter(&observed, ctx) {
+            return Some(Err(error));
+        }
+
+        // `[security.challenge]`: a suspect without a valid cookie gets the challenge page
+        // instead of being forwarded.
+        if let Some(response) = check_challenge(
+            req.headers(),
+            &security.challenge,
+            &observed,
+            ctx.ja4_fingerprints,
+            ctx.metrics,
+            security.trusted_proxies.client_ip(peer.ip(), req.headers()),
+        )
+        .await
+        {
+            return Some(Ok(response));
+        }
     }
 
     // Misdirected-request enforcement (RFC 9110 §15.5.20 / RFC 7540 §9.1.2), always on,
@@ -147,9 +382,9 @@ pub async fn handle_proxy_request(
     // that selected the connection's certificate. Reject with 421 when that host is served
     // by a *different* certificate (same-cert / wildcard / SAN coalescing is allowed). Only
     // fires on TLS connections that presented an SNI; runs after the IP filter so a blocked
-    // client never learns whether a host exists.
-    if let Some(sni) = connection_sni {
-        if !crate::proxy::router::authority_matches_sni(&domains, sni, &host) {
+    // client never learns whether a host exists. CONNECT targets are not checked.
+    if let Some(sni) = ctx.tls.sni.filter(|_| req.method() != Method::CONNECT) {
+        if !crate::proxy::router::authority_matches_sni(domains, sni, host) {
             debug!(
                 ?peer,
                 sni,
@@ -157,158 +392,353 @@ pub async fn handle_proxy_request(
                 "421 Misdirected Request: host not covered by the connection's certificate (SNI)"
             );
             let error = HttpError::MisdirectedRequest;
-            metrics.record_error(error.error_type());
-            let status_code = StatusCode::from(error.clone()).as_u16();
-            metrics.record_entrypoint_request(&method, status_code, &protocol);
-            return Err(error);
+            ctx.metrics.record_error(error.error_type());
+            return Some(Err(error));
         }
     }
 
-    let route_match = match domain {
-        None => {
-            let error = HttpError::MisdirectedRequest;
+    // `tls.client_cert.on_missing = "reject"`: a client that completed an optional-mTLS
+    // handshake without a certificate is refused before routing.
+    if let Some(context) = ctx.client_cert {
+        if let Err(error) = context.check() {
+            debug!(?peer, "request rejected: no client certificate presented");
+            ctx.metrics.record_error(error.error_type());
+            return Some(Err(error));
+        }
+    }
+    None
+}
+
+/// Requests the proxy answers itself before routing: the whoami endpoint and redirects.
+fn answer_before_routing(
+    req: &Request<Incoming>,
+    ctx: &RequestContext<'_>,
+    domain: Option<&Domain>,
+    host: &str,
+) -> Option<Response<RespBody>> {
+    let security = ctx.security;
+    let peer = ctx.peer;
+    // `[fingerprint.whoami]`: the proxy answers with what it computed for the caller, on any host
+    // and without a route. Filters above still apply, so a blocked client learns nothing.
+    if is_whoami_request(req, security.whoami_path.as_deref()) {
+        let akamai = ctx
+            .akamai_rx(req.version())
+            .and_then(|rx| rx.borrow().as_ref().map(|f| f.fingerprint.clone()));
+        debug!(?peer, host = %host, "answering fingerprint diagnostics (whoami) request");
+        return Some(whoami_response(
+            req,
+            peer,
+            ctx.is_https,
+            host,
+            ctx.ja4_fingerprints,
+            akamai,
+            ctx.syn_fingerprint,
+        ));
+    }
+
+    // Redirects come before routing, so a host or path that only redirects needs no route (and
+    // a host no domain serves can still be sent elsewhere by a global rule). CONNECT targets
+    // are never redirected.
+    let redirect = domain
+        .and_then(|d| d.redirect.as_ref())
+        .or(security.redirect.as_ref())
+        .filter(|_| req.method() != Method::CONNECT)
+        .and_then(|config| {
+            let secure = is_secure_request(
+                ctx.is_https,
+                peer.ip(),
+                req.headers(),
+                &security.trusted_proxies,
+            );
+            find_redirect(config, host, req.uri(), secure)
+        })?;
+    debug!(?peer, host = %host, location = ?redirect.location, "redirecting before routing");
+    let domain_label = domain.map_or(DEFAULT_DOMAIN_LABEL, Domain::label);
+    ctx.metrics
+        .record_redirect(domain_label, redirect.kind, redirect.status.as_u16());
+    Some(redirect.into_response())
+}
+
+/// The domain serving the request and the route of that domain it matches.
+fn match_route<'a>(
+    req: &Request<Incoming>,
+    ctx: &RequestContext<'_>,
+    domain: Option<&'a Domain>,
+) -> HttpResult<(&'a Domain, RouteMatch<'a>)> {
+    let matched = domain.ok_or(HttpError::MisdirectedRequest).and_then(|d| {
+        crate::proxy::router::pick_request_route(
+            req.method(),
+            req.uri().path(),
+            req.headers(),
+            &d.routes,
+        )
+        .map(|route_match| (d, route_match))
+        .ok_or(HttpError::NoMatchingRoute)
+    });
+    matched.inspect_err(|error| ctx.metrics.record_error(error.error_type()))
+}
+
+/// The route's own rate limit, `Some` with the `429` answer when the client is over it.
+async fn rate_limit(
+    req: &Request<Incoming>,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+) -> Option<Response<RespBody>> {
+    check_rate_limit(
+        ctx.security.rate_limit_manager.as_ref(),
+        routed.rate_limit,
+        &routed.route_match,
+        ctx.peer,
+        req.headers(),
+        ctx.metrics,
+        routed.domain_label,
+        &ctx.security.trusted_proxies,
+        ctx.tls,
+        ctx.akamai_rx(req.version()),
+    )
+    .await
+}
+
+/// Routes that answer without a backend: a synthetic response or a CONNECT tunnel. `Some` is the
+/// answer; a tunnel's target is recorded as the request's backend.
+async fn answer_without_backend(
+    req: &mut Request<Incoming>,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+    backends: &[Backend],
+    request_log: &mut RequestLog,
+) -> Option<HttpResult<Response<RespBody>>> {
+    let route_match = &routed.route_match;
+    let metrics = ctx.metrics;
+
+    // A route in maintenance (or stubbed) answers by itself; no backend is selected.
+    if let Some(synthetic) = route_match.synthetic.filter(|config| {
+        ctx.security
+            .synthetic
+            .is_active(routed.domain_label, routed.prefix(), config)
+    }) {
+        let response = synthetic_route_response(synthetic).await;
+        let status_code = response.status().as_u16();
+        metrics.record_synthetic_response(routed.prefix(), routed.domain_label, status_code);
+        return Some(Ok(response));
+    }
+
+    // A CONNECT request becomes a tunnel to its target; no backend is selected.
+    let connect = route_match
+        .connect
+        .filter(|_| req.method() == Method::CONNECT)?;
+    if let Some(rate_limited_response) = rate_limit(req, ctx, routed).await {
+        return Some(Ok(rate_limited_response));
+    }
+    let opened =
+        open_tunnel(req, connect, route_match, backends, &ctx.security.tunnels, metrics, ctx.peer)
+            .await;
+    let result = match &opened {
+        Ok(_) => values::CONNECT_OPENED,
+        Err(HttpError::ConnectTargetRefused(_)) => values::CONNECT_REFUSED,
+        Err(_) => values::CONNECT_FAILED,
+    };
+    metrics.record_connect_tunnel(routed.prefix(), routed.domain_label, result);
+    Some(match opened {
+        Ok((response, target)) => {
+            request_log.backend = Some(target);
+            Ok(response)
+        }
+        Err(error) => {
             metrics.record_error(error.error_type());
-            let status_code = StatusCode::from(error.clone()).as_u16();
-            metrics.record_entrypoint_request(&method, status_code, &protocol);
-            return Err(error);
+            Err(error)
         }
-        Some(d) => match crate::proxy::router::pick_route_with_fingerprinting(path, &d.routes) {
-            Some(r) => r,
-            None => {
-                let error = HttpError::NoMatchingRoute;
-                metrics.record_error(error.error_type());
-                let status_code = StatusCode::from(error.clone()).as_u16();
-                metrics.record_entrypoint_request(&method, status_code, &protocol);
-                return Err(error);
-            }
-        },
+    })
+}
+
+/// The backend the request goes to (the sticky session's when it is still eligible), and that
+/// session. `Err` when no candidate backend is healthy.
+fn select_backend<'a>(
+    req: &Request<Incoming>,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'a>,
+    upstream: &UpstreamGateway,
+    backends: &[Backend],
+) -> HttpResult<(String, Option<StickySession<'a>>)> {
+    let route_match = &routed.route_match;
+    let sticky = route_match.sticky.map(|config| {
+        StickySession::from_request(
+            config,
+            req.headers(),
+            ctx.peer,
+            &ctx.security.trusted_proxies,
+            ctx.ja4_fingerprints,
+        )
+    });
+    let Some(selected) = upstream.select(
+        routed.prefix(),
+        &route_match.backend_candidates,
+        sticky.as_ref().and_then(StickySession::affinity),
+        backends,
+        ctx.metrics,
+    ) else {
+        ctx.metrics
+            .record_health_check_gate_reject(route_match.backend);
+        return Err(HttpError::UpstreamUnhealthy);
     };
+    Ok((selected, sticky))
+}
 
-    // Route is known: resolve the whole-block effective policy (route.or(domain).or(global)).
-    let effective = resolve_security(security, domain, &route_match);
+/// Admission of a request bound for `backend` (`None` on `serve_static` routes): rate limit,
+/// bot verification, WAF and the in-flight limits. `Ok` carries the request (its body prefetched
+/// for the WAF) and the in-flight slot it holds; `Err` the answer of a request stopped here.
+async fn admit(
+    mut req: Request<Incoming>,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+    backend: Option<&str>,
+    backend_config: Option<&Backend>,
+) -> Result<(ProxyRequest, Option<InFlight>), HttpResult<Response<RespBody>>> {
+    let security = ctx.security;
+    let metrics = ctx.metrics;
+    if let Some(rate_limited_response) = rate_limit(&req, ctx, routed).await {
+        return Err(Ok(rate_limited_response));
+    }
 
-    // Deferred route-level IP check, before backend selection (blocked client never hits upstream).
-    if defer_ip_check {
-        enforce_ip_access(peer, effective.ip_filter, &metrics, &method, &protocol)?;
+    // After rate limiting, so a flood of claimed crawlers cannot flood the resolver.
+    if let Err(error) = verify_bot(req.headers_mut(), security, ctx.peer.ip(), metrics).await {
+        metrics.record_error(error.error_type());
+        return Err(Err(error));
     }
 
-    let selected_upstream = match upstream.selector.select(
-        route_match.matched_prefix,
-        &route_match.backend_candidates,
-        &upstream.health,
-    ) {
-        Some(addr) => addr,
-        None => {
-            metrics.record_health_check_gate_reject(route_match.backend);
-            let error = HttpError::UpstreamUnhealthy;
-            let status_code = StatusCode::from(error.clone()).as_u16();
-            metrics.record_entrypoint_request(&method, status_code, &protocol);
-            metrics.record_request(
-                &method,
-                status_code,
-                &protocol,
-                route_match.matched_prefix,
-                domain_label,
-            );
-            metrics.record_request_duration(
-                start.elapsed().as_secs_f64(),
-                &method,
-                status_code,
-                &protocol,
-                route_match.matched_prefix,
-                domain_label,
-            );
-            return Err(error);
+    // Before the in-flight slot: a blocked request never holds one.
+    let req = match check_waf(
+        req,
+        &security.waf,
+        &routed.route_match,
+        routed.domain_label,
+        metrics,
+        ctx.peer,
+        ctx.tls,
+    )
+    .await
+    {
+        Ok(req) => req,
+        Err(error) => {
+            metrics.record_error(error.error_type());
+            return Err(Err(error));
         }
     };
-    metrics.record_backend_selection(&selected_upstream);
 
-    if let Some(rate_limited_response) = check_rate_limit(
-        security.rate_limit_manager.as_ref(),
-        effective_rate_limit,
-        &route_match,
-        peer,
-        req.headers(),
-        &metrics,
-        domain_label,
-        &security.trusted_proxies,
-    ) {
-        let status_code = rate_limited_response.status().as_u16();
-        metrics.record_entrypoint_request(&method, status_code, &protocol);
-        metrics.record_request(
-            &method,
-            status_code,
-            &protocol,
-            route_match.matched_prefix,
-            domain_label,
-        );
-        metrics.record_request_duration(
-            start.elapsed().as_secs_f64(),
-            &method,
-            status_code,
-            &protocol,
-            route_match.matched_prefix,
-            domain_label,
-        );
-        return Ok(rate_limited_response);
+    // Held until the response body is done; every early return below releases it.
+    let in_flight = acquire_in_flight(
+        &security.concurrency_limits,
+        &routed.route_match,
+        routed.domain_label,
+        backend.unwrap_or_default(),
+        backend_config.and_then(|b| b.max_in_flight),
+        backend_config.and_then(|b| b.queue.as_ref()),
+        metrics,
+    )
+    .await
+    .map_err(Ok)?;
+    Ok((req, in_flight))
+}
+
+/// Run the library user's classifier, tagging the request or denying it.
+fn classify(
+    req: &mut ProxyRequest,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+    host: &str,
+) -> HttpResult<()> {
+    let Some(classifier) = ctx.security.classifier.as_ref() else {
+        return Ok(());
+    };
+    let akamai = ctx
+        .akamai_rx(req.version())
+        .and_then(|rx| rx.borrow().clone());
+    let connection = req.extensions().get::<Arc<ConnectionContext>>().cloned();
+    let verdict = classifier.classify(&FingerprintSet {
+        peer: ctx.peer,
+        method: req.method(),
+        host,
+        path: req.uri().path(),
+        route: routed.prefix(),
+        headers: req.headers(),
+        ja4: ctx.ja4_fingerprints,
+        akamai: akamai.as_ref(),
+        tcp: ctx.syn_fingerprint,
+        connection: connection.as_deref(),
+    });
+    ctx.metrics.record_classifier_verdict(verdict.label());
+    match verdict {
+        Verdict::Allow => {}
+        Verdict::Deny(status) => {
+            debug!(peer = ?ctx.peer, %status, "request denied by fingerprint classifier");
+            let error = HttpError::ClassifierDenied(status);
+            ctx.metrics.record_error(error.error_type());
+            return Err(error);
+        }
+        Verdict::Tag(tag) => match hyper::header::HeaderValue::from_str(&tag) {
+            Ok(hv) => {
+                req.headers_mut()
+                    .insert(HeaderName::from_static(names::CLASSIFICATION), hv);
+            }
+            Err(_) => debug!("classifier tag is not a valid header value, dropped"),
+        },
     }
+    Ok(())
+}
 
-    // Strip proxy-authoritative fingerprint headers unconditionally, must run outside the
-    // fingerprinting gate, so routes with fingerprinting=false also strip spoofed values.
-    let spoofed = strip_client_fingerprints(req.headers_mut());
-    for &name in &spoofed {
-        metrics.record_fingerprint_spoofing_attempt(name);
+/// `x-huginn-net-os` from the database match of the client's SYN, if any.
+fn inject_os_guess(
+    req: &mut ProxyRequest,
+    syn_fp: &TcpObservation,
+    fingerprint_headers: &FingerprintHeaderNames,
+) {
+    let Some(guess) = match_os(syn_fp) else {
+        debug!("Handler: no OS signature matches the TCP SYN fingerprint");
+        return;
+    };
+    let name = fingerprint_name(fingerprint_headers, names::TCP_OS);
+    debug!("Handler: injecting {} header: {}", name, guess);
+    if let Ok(hv) = hyper::header::HeaderValue::from_str(&guess.to_string()) {
+        req.headers_mut().insert(name, hv);
     }
+}
 
-    // Extract and inject fingerprints first (fingerprints are extracted from TLS handshake/HTTP2 frames,
-    // not from HTTP headers, so adding X-Forwarded-* headers won't affect fingerprint generation)
+/// Fingerprint, spoofing, client certificate and TLS headers, packed into one context header
+/// when the route asks for it.
+fn inject_fingerprints(
+    req: &mut ProxyRequest,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+    spoofed: &[&'static str],
+) {
+    let metrics = ctx.metrics;
+    let fingerprint_headers = ctx.fingerprint_headers;
+    let effective = &routed.effective;
+    // Extract and inject fingerprints first (fingerprints are extracted from TLS handshake/HTTP2
+    // frames, not from HTTP headers, so adding X-Forwarded-* headers won't affect fingerprint
+    // generation)
     if effective.fingerprinting {
-        if let Some(ref fingerprints) = ja4_fingerprints {
-            if let Ok(hv) = hyper::header::HeaderValue::from_str(&fingerprints.ja4.full.to_string())
-            {
-                req.headers_mut()
-                    .insert(HeaderName::from_static(names::TLS_JA4), hv);
-            }
-            if let Ok(hv) = hyper::header::HeaderValue::from_str(&fingerprints.ja4.raw.to_string())
-            {
-                req.headers_mut()
-                    .insert(HeaderName::from_static(names::TLS_JA4_R), hv);
-            }
-            if let Ok(hv) =
-                hyper::header::HeaderValue::from_str(&fingerprints.ja4_original.full.to_string())
-            {
-                req.headers_mut()
-                    .insert(HeaderName::from_static(names::TLS_JA4_O), hv);
-            }
-            if let Ok(hv) =
-                hyper::header::HeaderValue::from_str(&fingerprints.ja4_original.raw.to_string())
-            {
-                req.headers_mut()
-                    .insert(HeaderName::from_static(names::TLS_JA4_OR), hv);
-            }
-            if let Ok(hv) =
-                hyper::header::HeaderValue::from_str(&fingerprints.ja4_stable_v1.full.to_string())
-            {
-                req.headers_mut()
-                    .insert(HeaderName::from_static(names::TLS_JA4_S1), hv);
-            }
-            if let Ok(hv) =
-                hyper::header::HeaderValue::from_str(&fingerprints.ja4_stable_v1.raw.to_string())
-            {
-                req.headers_mut()
-                    .insert(HeaderName::from_static(names::TLS_JA4_S1R), hv);
-            }
+        if let Some(fingerprints) = ctx.ja4_fingerprints {
+            add_ja4_headers(
+                req.headers_mut(),
+                fingerprints,
+                effective.ja4_variants,
+                fingerprint_headers,
+            );
         }
-        if let Some(ref rx) = fingerprint_rx {
+        if let Some(rx) = ctx.fingerprint_rx {
             if req.version() == Version::HTTP_2 {
                 let akamai = rx.borrow().clone();
                 debug!("Handler: akamai fingerprint: {:?}", akamai);
                 if let Some(hv) = akamai_header_value(akamai.as_ref()) {
-                    debug!("Handler: injecting {} header: {:?}", names::HTTP2_AKAMAI, hv);
-                    req.headers_mut()
-                        .insert(HeaderName::from_static(names::HTTP2_AKAMAI), hv);
+                    let name = fingerprint_name(fingerprint_headers, names::HTTP2_AKAMAI);
+                    debug!("Handler: injecting {} header: {:?}", name, hv);
+                    req.headers_mut().insert(name, hv);
                 } else {
-                    debug!("Handler: no HTTP fingerprint header to inject (HTTP/2 connection but fingerprint not extracted)");
+                    debug!(
+                        "Handler: no HTTP fingerprint header to inject \
+                         (HTTP/2 connection but fingerprint not extracted)"
+                    );
                     metrics.record_http2_fingerprint_failure();
                 }
             } else {
@@ -316,17 +746,21 @@ pub async fn handle_proxy_request(
                 metrics.record_http2_fingerprint_not_applicable();
             }
         }
-        match syn_fingerprint {
-            Some(ref syn_fp) => {
-                debug!("Handler: injecting {} header: {}", names::TCP_SYN, syn_fp);
+        match ctx.syn_fingerprint {
+            Some(syn_fp) => {
+                let name = fingerprint_name(fingerprint_headers, names::TCP_SYN);
+                debug!("Handler: injecting {} header: {}", name, syn_fp);
                 if let Ok(hv) = hyper::header::HeaderValue::from_str(&syn_fp.to_string()) {
-                    req.headers_mut()
-                        .insert(HeaderName::from_static(names::TCP_SYN), hv);
+                    req.headers_mut().insert(name, hv);
+                }
+                if routed.route_match.tcp_os {
+                    inject_os_guess(req, syn_fp, fingerprint_headers);
                 }
             }
             None => {
                 debug!(
-                    "Handler: no TCP SYN fingerprint available - keep-alive request or SYN not captured"
+                    "Handler: no TCP SYN fingerprint available - SYN not captured for this \
+                     connection"
                 );
             }
         }
@@ -336,89 +770,644 @@ pub async fn handle_proxy_request(
     // Runs outside the fingerprinting gate so backends on fingerprinting=false routes
     // also receive the detection signal.
     if !spoofed.is_empty() {
-        if let Ok(hv) = hyper::header::HeaderValue::from_str(&spoofed.join(",")) {
+        let spoofed_names: Vec<HeaderName> = spoofed
+            .iter()
+            .map(|&default| fingerprint_name(fingerprint_headers, default))
+            .collect();
+        let listed = spoofed_names
+            .iter()
+            .map(HeaderName::as_str)
+            .collect::<Vec<_>>()
+            .join(",");
+        if let Ok(hv) = hyper::header::HeaderValue::from_str(&listed) {
             req.headers_mut()
-                .insert(HeaderName::from_static(names::SPOOFING_DETECTED), hv);
+                .insert(fingerprint_headers.spoofing_detected().clone(), hv);
         }
     }
 
-    // Add X-Forwarded-* headers after fingerprinting. X-Forwarded-Host mirrors the resolved
-    // routing host (`host`) so it agrees with the backend the request is sent to, even for
-    // coalesced HTTP/2 connections where `:authority` differs from the connection SNI.
-    add_forwarded_headers(&mut req, peer, is_https, &host);
+    apply_client_cert_headers(req.headers_mut(), ctx.client_cert);
+    let tls_info = req.extensions_mut().remove::<Arc<TlsHandshakeInfo>>();
+    apply_tls_info_headers(
+        req.headers_mut(),
+        tls_info
+            .as_deref()
+            .filter(|_| ctx.security.tls_info_headers),
+    );
+    // Routes with `fingerprint_format` other than `headers` get the headers above packed into
+    // one context header, with the client IP resolved through the trusted proxies.
+    let client_ip = ctx
+        .security
+        .trusted_proxies
+        .client_ip(ctx.peer.ip(), req.headers());
+    pack_context(
+        req.headers_mut(),
+        effective.fingerprint_format,
+        fingerprint_headers,
+        client_ip,
+        ctx.security.header_signer.as_deref(),
+    );
+}
+
+/// The route's `ext_authz` check. Adds the headers an allowing service grants; `Some` is the
+/// answer of a denied request (or of a failed check with `failure_mode = "deny"`).
+async fn authorize(
+    req: &mut ProxyRequest,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+    client_pool: &ClientPool,
+) -> Option<HttpResult<Response<RespBody>>> {
+    let authz = routed.route_match.ext_authz?;
+    let check_start = Instant::now();
+    // Built before awaiting so the client request is not borrowed across the check.
+    let check = ext_authz::check_request(authz, req.method(), req.uri(), req.headers());
+    let decision = match check {
+        Ok(check) => ext_authz::check(client_pool.authz_client(), authz, check).await,
+        Err(e) => AuthzDecision::Failed(format!("invalid check request: {e}")),
+    };
+    ctx.metrics.record_ext_authz_check(
+        decision.label(),
+        check_start.elapsed().as_secs_f64(),
+        routed.prefix(),
+        routed.domain_label,
+    );
+    match decision {
+        AuthzDecision::Allow(granted) => {
+            ext_authz::apply_upstream_headers(req.headers_mut(), authz, granted);
+            None
+        }
+        AuthzDecision::Deny(response) => Some(Ok(response)),
+        AuthzDecision::Failed(reason) => match authz.failure_mode {
+            ExtAuthzFailureMode::Allow => {
+                warn!(peer = ?ctx.peer, %reason, "ext_authz check failed, failing open");
+                None
+            }
+            ExtAuthzFailureMode::Deny => {
+                let status =
+                    StatusCode::from_u16(authz.status_on_error).unwrap_or(StatusCode::FORBIDDEN);
+                let error = HttpError::ExternalAuthFailed(status, reason);
+                ctx.metrics.record_error(error.error_type());
+                Some(Err(error))
+            }
+        },
+    }
+}
 
+/// Response compression negotiated on the client's own Accept-Encoding, before header
+/// manipulation can touch it. gRPC routes are skipped: gRPC compresses messages itself.
+fn negotiate_compression<'a, B>(
+    req: &Request<B>,
+    ctx: &RequestContext<'a>,
+    routed: &RoutedRequest<'a>,
+) -> Option<Negotiated<'a>> {
+    let route_match = &routed.route_match;
+    route_match
+        .compression
+        .or(ctx.security.compression.as_ref())
+        .filter(|c| c.enabled && route_match.protocol != RouteProtocol::Grpc)
+        .and_then(|c| {
+            compression::negotiate(req.method(), req.headers(), &c.algorithms).map(|a| (c, a))
+        })
+}
+
+/// `response` compressed with the negotiated algorithm, when its type and size allow it.
+fn compress(
+    response: Response<RespBody>,
+    negotiated: Option<Negotiated<'_>>,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+) -> Response<RespBody> {
+    match negotiated {
+        Some((config, algorithm)) if compression::is_compressible(config, &response) => {
+            ctx.metrics.record_compressed_response(
+                algorithm.as_str(),
+                routed.prefix(),
+                routed.domain_label,
+            );
+            compression::compress_response(response, algorithm)
+        }
+        _ => response,
+    }
+}
+
+/// Header manipulation, privacy scrubbing and signing of the request as the backend gets it.
+/// `body_rewrite` routes also drop `Accept-Encoding`.
+fn finalize_request_headers(
+    req: &mut ProxyRequest,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+    template: &HeaderTemplateContext<'_>,
+) {
+    let security = ctx.security;
     apply_request_header_manipulation(
         req.headers_mut(),
         security.global_header_manipulation.as_ref(),
-        domain_headers,
-        route_match.headers,
-        &metrics,
+        routed.domain_headers,
+        routed.route_match.headers,
+        template,
+        ctx.metrics,
     );
 
-    let result = forward(
-        req,
-        selected_upstream,
-        crate::proxy::forwarding::ForwardConfig {
-            backends: &backends,
-            keep_alive,
-            metrics: Arc::clone(&metrics),
-            matched_prefix: route_match.matched_prefix,
-            replace_path: route_match.replace_path,
-            security_headers: Some(effective.security_headers),
-            is_https,
-            preserve_host,
-            route: route_match.matched_prefix,
-            domain: domain_label,
-            client_pool,
-            force_new_connection: route_match.force_new_connection,
-        },
+    // Scrubbed after manipulation, so the backend gets exactly what `privacy` lets through.
+    let privacy = routed.route_match.privacy;
+    if let Some(privacy) = privacy {
+        scrub_request_headers(
+            req.headers_mut(),
+            privacy,
+            routed.prefix(),
+            routed.domain_label,
+            ctx.metrics,
+        );
+    }
+    // Encoded responses are never rewritten, so the backend is not offered any encoding;
+    // compression was already negotiated with the client's own header.
+    if routed.route_match.body_rewrite.is_some() {
+        req.headers_mut().remove(http::header::ACCEPT_ENCODING);
+    }
+    // Signed last, so the signature covers the request line and fingerprint headers exactly as
+    // the backend receives them; not at all when `privacy` keeps the signature from it.
+    let Some(signer) = &security.header_signer else {
+        return;
+    };
+    if privacy.is_some_and(|p| !p.request.lets_through(names::SIGNATURE)) {
+        return;
+    }
+    let route_match = &routed.route_match;
+    let path =
+        upstream_path(req.uri(), route_match.rewrite, route_match.replace_path, routed.prefix())
+            .unwrap_or_else(|_| req.uri().path().to_string());
+    let authority = req
+        .headers()
+        .get(forwarded::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let method = req.method().clone();
+    let request = SignedRequest { method: method.as_str(), authority: &authority, path: &path };
+    signer.sign(request, req.headers_mut(), ctx.fingerprint_headers);
+}
+
+/// Answer the request from the `serve_static` directory `files`, with the route's security
+/// headers.
+async fn serve_files(
+    req: &ProxyRequest,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+    files: &ServeStaticConfig,
+) -> Response<RespBody> {
+    let mut response = static_files::serve_static(
+        files,
+        req.method(),
+        req.uri().path(),
+        routed.prefix(),
+        req.headers(),
     )
     .await;
+    ctx.metrics.record_static_response(
+        routed.prefix(),
+        routed.domain_label,
+        response.status().as_u16(),
+    );
+    crate::security::apply_security_headers(
+        &mut response,
+        Some(routed.effective.security_headers),
+        ctx.is_https,
+    );
+    response
+}
 
-    let mut result = result;
-    if let Ok(ref mut response) = result {
-        if let Some(content_length) = response.headers().get(hyper::header::CONTENT_LENGTH) {
-            if let Ok(length_str) = content_length.to_str() {
-                if let Ok(length) = length_str.parse::<u64>() {
-                    metrics.record_bytes_sent(length, &protocol);
+/// Forward to `backend` (failing over along the route's candidates) through the library
+/// user's middleware, then scrub and rewrite the response.
+async fn forward(
+    req: ProxyRequest,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+    backend: String,
+    config: ForwardConfig<'_>,
+    upstream: &UpstreamGateway,
+    served_by: &mut Option<String>,
+) -> HttpResult<Response<RespBody>> {
+    let route_match = &routed.route_match;
+    let send = |req| {
+        forward_with_failover(
+            req,
+            backend,
+            config,
+            route_match.failover,
+            &route_match.backend_candidates,
+            upstream,
+            served_by,
+        )
+    };
+    let result = match &ctx.security.middleware {
+        Some(middleware) => {
+            let result = middleware.call(req, send).await;
+            if let Err(e @ HttpError::MiddlewareFailed(_)) = &result {
+                ctx.metrics.record_error(e.error_type());
+            }
+            result
+        }
+        None => send(req).await,
+    };
+    // Before caching, so stored responses are scrubbed and rewritten once and served as they
+    // are.
+    result.map(|mut response| {
+        if let Some(privacy) = route_match.privacy {
+            scrub_response_headers(
+                response.headers_mut(),
+                privacy,
+                routed.prefix(),
+                routed.domain_label,
+                ctx.metrics,
+            );
+        }
+        match route_match.body_rewrite {
+            Some(config) if body_rewrite::is_rewritable(config, &response) => {
+                body_rewrite::rewrite_response(response, config)
+            }
+            _ => response,
+        }
+    })
+}
+
+/// Complete a cache lookup with the backend's answer: serve the stale entry when the backend
+/// failed, store a fresh response, or apply a successful invalidation.
+fn settle_cache(
+    result: HttpResult<Response<RespBody>>,
+    caching: Option<Caching<'_>>,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+) -> HttpResult<Response<RespBody>> {
+    match caching {
+        Some((config, store, CacheStep::Fetch { request, stale })) => {
+            let failed = result
+                .as_ref()
+                .map_or(true, |resp| resp.status().is_server_error());
+            match stale.filter(|_| failed) {
+                Some(entry) => {
+                    debug!(peer = ?ctx.peer, "backend failed, serving stale cached response");
+                    ctx.metrics.record_cache_lookup(
+                        values::CACHE_STALE,
+                        routed.prefix(),
+                        routed.domain_label,
+                    );
+                    Ok(entry.to_response())
+                }
+                None => {
+                    ctx.metrics.record_cache_lookup(
+                        values::CACHE_MISS,
+                        routed.prefix(),
+                        routed.domain_label,
+                    );
+                    result.map(|resp| cache::store_response(store, config, request, resp))
                 }
             }
         }
+        Some((_, store, CacheStep::Invalidate(url))) => {
+            let succeeded = result
+                .as_ref()
+                .is_ok_and(|resp| resp.status().is_success() || resp.status().is_redirection());
+            if succeeded {
+                store.invalidate(&url);
+            }
+            result
+        }
+        _ => result,
+    }
+}
+
+/// Response header manipulation, the sticky session cookie and the client tracking cookie.
+fn decorate_response(
+    response: &mut Response<RespBody>,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+    template: &HeaderTemplateContext<'_>,
+    sticky: Option<&StickySession>,
+    tracked: Option<&TrackedClient>,
+    backend: Option<&str>,
+) {
+    if let Some(content_length) = response.headers().get(hyper::header::CONTENT_LENGTH) {
+        if let Ok(length_str) = content_length.to_str() {
+            if let Ok(length) = length_str.parse::<u64>() {
+                ctx.metrics.record_bytes_sent(length, &ctx.protocol);
+            }
+        }
+    }
 
-        apply_response_header_manipulation(
-            response.headers_mut(),
-            security.global_header_manipulation.as_ref(),
-            domain_headers,
-            route_match.headers,
-            &metrics,
-        );
+    apply_response_header_manipulation(
+        response.headers_mut(),
+        ctx.security.global_header_manipulation.as_ref(),
+        routed.domain_headers,
+        routed.route_match.headers,
+        template,
+        ctx.metrics,
+    );
+    if let (Some(sticky), Some(backend)) = (sticky, backend) {
+        sticky.pin(response, backend, routed.prefix(), ctx.is_https);
     }
+    if let (Some(tracker), Some(tracked)) = (&ctx.security.client_tracker, tracked) {
+        tracker.set_cookie(response, tracked, ctx.is_https);
+    }
+}
 
-    let duration = start.elapsed().as_secs_f64();
-    let status_code = match &result {
-        Ok(resp) => resp.status().as_u16(),
-        Err(e) => {
-            let code: StatusCode = (*e).clone().into();
-            code.as_u16()
+/// Wrap the response body in the download bandwidth shaping and the in-flight slot, both held
+/// until the client has received it.
+fn wrap_body(
+    result: HttpResult<Response<RespBody>>,
+    shaping: Option<Shaping>,
+    in_flight: Option<InFlight>,
+    ctx: &RequestContext<'_>,
+    routed: &RoutedRequest<'_>,
+) -> HttpResult<Response<RespBody>> {
+    // Shaped last, so the limit applies to the bytes the client receives (compressed or cached).
+    let result = match shaping {
+        Some(shaping) => result.map(|resp| {
+            let metrics = Arc::clone(ctx.metrics);
+            let (route, domain) = (routed.prefix().to_string(), routed.domain_label.to_string());
+            resp.map(|body| {
+                ShapedBody::new(body, shaping.buckets(Direction::Download))
+                    .on_throttled(move || {
+                        metrics.record_bandwidth_throttled(
+                            values::DIRECTION_BACKEND_TO_CLIENT,
+                            &route,
+                            &domain,
+                        );
+                    })
+                    .boxed()
+            })
+        }),
+        None => result,
+    };
+    match in_flight {
+        Some(in_flight) => {
+            result.map(|resp| resp.map(|body| InFlightBody::new(body, in_flight).boxed()))
         }
+        None => result,
+    }
+}
+
+/// Handle request routing and forwarding.
+///
+/// `peer` is the effective client address as resolved by `resolve_peer`, and is expected to be
+/// already canonicalized: IPv4-mapped IPv6 clients (`::ffff:a.b.c.d`, e.g. declared in a PROXY
+/// protocol header) are normalized to plain IPv4 at that single point. This handler therefore does
+/// **not** re-normalize; it relies on that contract so `ip_filter`, the rate-limit key and
+/// `X-Forwarded-For` all observe one consistent form.
+///
+/// `request_log` receives the matched tenant and route, its access log sampling and the backend
+/// chosen for the request, including when the request then fails.
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_proxy_request(
+    mut req: Request<Incoming>,
+    domains: Arc<Vec<Domain>>,
+    backends: Arc<Vec<Backend>>,
+    ja4_fingerprints: Option<Ja4Fingerprints>,
+    fingerprint_rx: Option<AkamaiReceiver>,
+    syn_fingerprint: Option<TcpObservation>,
+    keep_alive: &KeepAliveConfig,
+    security: &SecurityContext,
+    metrics: Arc<Metrics>,
+    peer: SocketAddr,
+    is_https: bool,
+    preserve_host: bool,
+    client_pool: &Arc<ClientPool>,
+    upstream: &UpstreamGateway,
+    connection_sni: Option<&str>,
+    client_cert: Option<&ClientCertContext>,
+    fingerprint_headers: &FingerprintHeaderNames,
+    request_log: &mut RequestLog,
+) -> HttpResult<hyper::Response<RespBody>> {
+    let start = Instant::now();
+    let handshake = req.extensions().get::<Arc<TlsHandshakeInfo>>().cloned();
+    let ja4 = ja4_fingerprints.as_ref().map(|f| f.ja4.full.to_string());
+    let ctx = RequestContext {
+        security,
+        metrics: &metrics,
+        fingerprint_headers,
+        peer,
+        is_https,
+        ja4_fingerprints: ja4_fingerprints.as_ref(),
+        fingerprint_rx: fingerprint_rx.as_ref(),
+        syn_fingerprint: syn_fingerprint.as_ref(),
+        client_cert,
+        tls: TlsMetadata {
+            sni: connection_sni,
+            alpn: handshake.as_ref().and_then(|info| info.alpn.as_deref()),
+            ja4: ja4.as_deref(),
+        },
+        start,
+        method: req.method().to_string(),
+        protocol: format!("{:?}", req.version()),
     };
 
-    metrics.record_entrypoint_request(&method, status_code, &protocol);
-    metrics.record_request(
-        &method,
-        status_code,
-        &protocol,
-        route_match.matched_prefix,
-        domain_label,
+    if let Some(content_length) = req.headers().get(hyper::header::CONTENT_LENGTH) {
+        if let Ok(length_str) = content_length.to_str() {
+            if let Ok(length) = length_str.parse::<u64>() {
+                metrics.record_bytes_received(length, &ctx.protocol);
+            }
+        }
+    }
+
+    if let Err(error) = check_framing(&mut req, &ctx) {
+        return ctx.finish(Err(error));
+    }
+
+    let host = extract_request_host(&req);
+    let domain = crate::proxy::router::pick_tenant_domain(
+        &domains,
+        &host,
+        security.listener_tenant.as_deref(),
     );
-    metrics.record_request_duration(
-        duration,
-        &method,
-        status_code,
-        &protocol,
-        route_match.matched_prefix,
-        domain_label,
+    request_log.tenant = domain.and_then(|d| d.tenant.clone());
+
+    // If no route overrides the IP filter, the domain/global filter applies to every route, so
+    // enforce it pre-routing (a blocked client never learns whether a host/route exists). If a
+    // route does override it, defer to post-routing (route-level ACL; see `resolve_security`).
+    let defer_ip_check = domain.is_some_and(domain_defers_ip_filter);
+    if let Some(result) = screen_request(&req, &ctx, &domains, domain, &host, defer_ip_check).await
+    {
+        return ctx.finish(result);
+    }
+    if let Some(response) = answer_before_routing(&req, &ctx, domain, &host) {
+        return ctx.finish(Ok(response));
+    }
+
+    let routed = match match_route(&req, &ctx, domain) {
+        Ok((domain, route_match)) => RoutedRequest::new(security, domain, route_match),
+        Err(error) => return ctx.finish(Err(error)),
+    };
+    request_log.route = Some(routed.prefix().to_string());
+    request_log.sampling = routed.route_match.access_log.copied();
+
+    // Shed before any further work on the request, backend selection included.
+    if let Some(shed_response) = check_load_shedding(
+        security.load_shedder.as_ref(),
+        &routed.route_match,
+        routed.domain_label,
+        &metrics,
+    ) {
+        return routed.finish(&ctx, Ok(shed_response), None);
+    }
+
+    // Deferred route-level IP check, before backend selection (blocked client never hits upstream).
+    if defer_ip_check {
+        if let Err(error) = check_ip_access(req.headers(), routed.effective.ip_filter, &ctx) {
+            return routed.finish(&ctx, Err(error), None);
+        }
+    }
+
+    if let Some(result) =
+        answer_without_backend(&mut req, &ctx, &routed, &backends, request_log).await
+    {
+        return routed.finish(&ctx, result, None);
+    }
+
+    // A `serve_static` route selects no backend, but its requests go through the same admission,
+    // classification and authorization as proxied ones; only the forwarding step differs.
+    let (destination, sticky) = match routed.route_match.serve_static {
+        Some(files) => (Destination::Files(files), None),
+        None => match select_backend(&req, &ctx, &routed, upstream, &backends) {
+            Ok((selected, sticky)) => {
+                metrics.record_backend_selection(&selected);
+                request_log.backend = Some(selected.clone());
+                (Destination::Backend(selected), sticky)
+            }
+            Err(error) => return routed.finish(&ctx, Err(error), None),
+        },
+    };
+    let selected_backend = destination
+        .backend()
+        .and_then(|selected| find_backend_config(selected, &backends));
+
+    let (mut req, in_flight) =
+        match admit(req, &ctx, &routed, destination.backend(), selected_backend).await {
+            Ok(admitted) => admitted,
+            Err(result) => return routed.finish(&ctx, result, destination.backend()),
+        };
+
+    // Keyed on the client as seen before header manipulation, like the rate limiter.
+    let shaping = shape_bandwidth(
+        &security.bandwidth_limits,
+        &routed.route_match,
+        routed.domain_label,
+        destination.backend().unwrap_or_default(),
+        selected_backend.and_then(|b| b.bandwidth.as_ref()),
+        security.trusted_proxies.client_ip(peer.ip(), req.headers()),
+        ctx.tls.ja4,
     );
 
-    result
+    // Strip proxy-authoritative fingerprint headers unconditionally, must run outside the
+    // fingerprinting gate, so routes with fingerprinting=false also strip spoofed values.
+    let spoofed = strip_client_fingerprints_named(req.headers_mut(), fingerprint_headers);
+    for &name in &spoofed {
+        metrics.record_fingerprint_spoofing_attempt(name);
+    }
+    if let Err(error) = classify(&mut req, &ctx, &routed, &host) {
+        return routed.finish(&ctx, Err(error), destination.backend());
+    }
+    inject_fingerprints(&mut req, &ctx, &routed, &spoofed);
+
+    // Add X-Forwarded-* headers after fingerprinting. X-Forwarded-Host mirrors the resolved
+    // routing host (`host`) so it agrees with the backend the request is sent to, even for
+    // coalesced HTTP/2 connections where `:authority` differs from the connection SNI.
+    add_forwarded_headers(&mut req, peer, is_https, &host);
+
+    // `[client_tracking]`: count the request and tell the backend how well it knows the client.
+    let tracked = match &security.client_tracker {
+        Some(tracker) => tracker.track(req.headers_mut(), ctx.tls.ja4).await,
+        None => None,
+    };
+
+    // External authorization sees the request as the backend would (fingerprint and
+    // X-Forwarded-* headers included), before header manipulation.
+    if let Some(result) = authorize(&mut req, &ctx, &routed, client_pool).await {
+        return routed.finish(&ctx, result, destination.backend());
+    }
+
+    let template = HeaderTemplateContext {
+        client_ip: Some(peer.ip()),
+        ja4: ctx
+            .ja4_fingerprints
+            .filter(|_| routed.effective.fingerprinting),
+        route_prefix: routed.prefix(),
+        host: &host,
+    };
+    let negotiated = negotiate_compression(&req, &ctx, &routed);
+
+    // The cache sees the client's request too, and only once authorization has passed. A hit
+    // skips the backend but still goes through compression and response header manipulation.
+    let caching = routed
+        .route_match
+        .cache
+        .filter(|c| c.enabled)
+        .zip(security.response_cache.as_ref())
+        .map(|(config, store)| {
+            let step = store.prepare(req.method(), &host, req.uri(), req.headers());
+            (config, store, step)
+        });
+
+    finalize_request_headers(&mut req, &ctx, &routed, &template);
+
+    let result = match (&caching, destination) {
+        (Some((_, _, CacheStep::Hit(entry))), _) => {
+            metrics.record_cache_lookup(values::CACHE_HIT, routed.prefix(), routed.domain_label);
+            Ok(entry.to_response())
+        }
+        (_, Destination::Files(files)) => Ok(serve_files(&req, &ctx, &routed, files).await),
+        (_, Destination::Backend(selected_upstream)) => {
+            let route_match = &routed.route_match;
+            let config = ForwardConfig {
+                backends: &backends,
+                keep_alive,
+                metrics: Arc::clone(&metrics),
+                matched_prefix: routed.prefix(),
+                replace_path: route_match.replace_path,
+                rewrite: route_match.rewrite,
+                host_rewrite: route_match.host_rewrite,
+                security_headers: Some(routed.effective.security_headers),
+                is_https,
+                preserve_host: route_match.preserve_host.unwrap_or(preserve_host),
+                route: routed.prefix(),
+                domain: routed.domain_label,
+                client_pool,
+                force_new_connection: route_match.force_new_connection,
+                circuit_breaker: upstream.circuit_breaker(&selected_upstream, &backends),
+                websocket: route_match.websocket,
+                protocol: route_match.protocol,
+                max_request_body_bytes: route_match.max_request_body_bytes,
+                max_response_body_bytes: route_match.max_response_body_bytes,
+                timeout: route_match.timeout,
+                drain_cutoff: upstream.health.drain_cutoff(&selected_upstream),
+                bandwidth: shaping.as_ref(),
+            };
+            forward(
+                req,
+                &ctx,
+                &routed,
+                selected_upstream,
+                config,
+                upstream,
+                &mut request_log.backend,
+            )
+            .await
+        }
+    };
+    let mut result = settle_cache(result, caching, &ctx, &routed)
+        .map(|response| compress(response, negotiated, &ctx, &routed));
+    if let Ok(ref mut response) = result {
+        decorate_response(
+            response,
+            &ctx,
+            &routed,
+            &template,
+            sticky.as_ref(),
+            tracked.as_ref(),
+            request_log.backend.as_deref(),
+        );
+    }
+
+    if let Some(shedder) = &security.load_shedder {
+        shedder.record(start.elapsed());
+    }
+    let result = routed.finish(&ctx, result, request_log.backend.as_deref());
+    wrap_body(result, shaping, in_flight, &ctx, &routed)
 }
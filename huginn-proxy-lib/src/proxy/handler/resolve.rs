@@ -1,4 +1,6 @@
-use crate::config::{Domain, IpFilterConfig, SecurityHeaders, DEFAULT_FINGERPRINTING};
+use crate::config::{
+    Domain, FingerprintFormat, IpFilterConfig, Ja4Variant, SecurityHeaders, DEFAULT_FINGERPRINTING,
+};
 use crate::proxy::router::RouteMatch;
 use crate::proxy::SecurityContext;
 
@@ -6,16 +8,20 @@ use crate::proxy::SecurityContext;
 ///
 /// Every field is resolved `route.or(domain).or(global)`: the most specific scope that sets a
 /// block wins **entirely** (no field-level merge). `fingerprinting` falls back to
-/// [`DEFAULT_FINGERPRINTING`] when neither route nor domain sets it.
+/// [`DEFAULT_FINGERPRINTING`] when neither route nor domain sets it, `ja4_variants` to
+/// [`Ja4Variant::ALL`] and `fingerprint_format` to [`FingerprintFormat::Headers`].
 pub struct EffectiveSecurity<'a> {
     pub ip_filter: &'a IpFilterConfig,
     pub security_headers: &'a SecurityHeaders,
     pub fingerprinting: bool,
+    pub ja4_variants: &'a [Ja4Variant],
+    pub fingerprint_format: FingerprintFormat,
 }
 
-/// Resolve the effective `ip_filter`, `security_headers`, and `fingerprinting` gate for a matched
-/// route. Rate limiting is intentionally excluded: its limiters are stateful and precomputed in
-/// [`crate::security::RateLimitManager`], keyed by domain label + route prefix.
+/// Resolve the effective `ip_filter`, `security_headers`, `fingerprinting` gate, JA4 variant
+/// selection and fingerprint format for a matched route. Rate limiting is intentionally excluded: its limiters are
+/// stateful and precomputed in [`crate::security::RateLimitManager`], keyed by domain label +
+/// route prefix.
 pub fn resolve_security<'a>(
     global: &'a SecurityContext,
     domain: Option<&'a Domain>,
@@ -38,7 +44,23 @@ pub fn resolve_security<'a>(
         .or_else(|| domain.and_then(|d| d.fingerprinting))
         .unwrap_or(DEFAULT_FINGERPRINTING);
 
-    EffectiveSecurity { ip_filter, security_headers, fingerprinting }
+    let ja4_variants = route
+        .ja4_variants
+        .or_else(|| domain.and_then(|d| d.ja4_variants.as_deref()))
+        .unwrap_or(Ja4Variant::ALL);
+
+    let fingerprint_format = route
+        .fingerprint_format
+        .or_else(|| domain.and_then(|d| d.fingerprint_format))
+        .unwrap_or_default();
+
+    EffectiveSecurity {
+        ip_filter,
+        security_headers,
+        fingerprinting,
+        ja4_variants,
+        fingerprint_format,
+    }
 }
 
 /// Whether any route in `domain` defines its own `ip_filter` override.
@@ -0,0 +1,89 @@
+//! Session affinity (`sticky` on a route).
+//!
+//! [`StickySession`] reads the client's affinity before backend selection: the proxy-issued
+//! cookie with `mode = "cookie"`, the client IP or JA4 with `mode = "hash"`. Once the backend is
+//! known, [`StickySession::pin`] (re)issues the cookie when the client did not carry the token of
+//! that backend yet, which is also how a client moves after failing over.
+
+use std::net::SocketAddr;
+
+use http::header::{COOKIE, SET_COOKIE};
+use http::{HeaderMap, HeaderValue, Response};
+
+use crate::backend::{backend_token, Affinity};
+use crate::config::{StickyConfig, StickyHashKey, StickyMode, TrustedProxiesConfig};
+use crate::fingerprinting::Ja4Fingerprints;
+
+/// Affinity of one request on a sticky route.
+#[derive(Debug)]
+pub struct StickySession<'a> {
+    config: &'a StickyConfig,
+    /// Cookie token or hash key; `None` for a client without the cookie yet.
+    key: Option<String>,
+}
+
+impl<'a> StickySession<'a> {
+    pub fn from_request(
+        config: &'a StickyConfig,
+        headers: &HeaderMap,
+        peer: SocketAddr,
+        trusted_proxies: &TrustedProxiesConfig,
+        ja4: Option<&Ja4Fingerprints>,
+    ) -> Self {
+        let key = match (config.mode, config.hash_by) {
+            (StickyMode::Cookie, _) => cookie_value(headers, &config.cookie).map(str::to_string),
+            (StickyMode::Hash, StickyHashKey::Ja4) => Some(ja4.map_or_else(
+                || trusted_proxies.client_ip(peer.ip(), headers).to_string(),
+                |f| f.ja4.full.to_string(),
+            )),
+            (StickyMode::Hash, StickyHashKey::ClientIp) => {
+                Some(trusted_proxies.client_ip(peer.ip(), headers).to_string())
+            }
+        };
+        Self { config, key }
+    }
+
+    /// Affinity to hand to backend selection.
+    pub fn affinity(&self) -> Option<Affinity<'_>> {
+        let key = self.key.as_deref()?;
+        Some(match self.config.mode {
+            StickyMode::Cookie => Affinity::Token(key),
+            StickyMode::Hash => Affinity::Key(key),
+        })
+    }
+
+    /// Pin the client to `backend` with `mode = "cookie"`: set the cookie on `response`, scoped
+    /// to `path`, unless the request already carried the token of `backend`.
+    pub fn pin<B>(&self, response: &mut Response<B>, backend: &str, path: &str, secure: bool) {
+        if self.config.mode != StickyMode::Cookie {
+            return;
+        }
+        let token = backend_token(backend);
+        if self.key.as_deref() == Some(token.as_str()) {
+            return;
+        }
+        let mut cookie = format!(
+            "{}={token}; Path={path}; Max-Age={}; HttpOnly; SameSite=Lax",
+            self.config.cookie, self.config.ttl_secs
+        );
+        if secure {
+            cookie.push_str("; Secure");
+        }
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            response.headers_mut().append(SET_COOKIE, value);
+        }
+    }
+}
+
+/// Value of the cookie `name` in the request's `Cookie` headers.
+pub(crate) fn cookie_value<'h>(headers: &'h HeaderMap, name: &str) -> Option<&'h str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim_matches('"'))
+        .filter(|value| !value.is_empty())
+}
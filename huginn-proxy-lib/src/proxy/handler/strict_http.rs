@@ -0,0 +1,146 @@
+//! Strict protocol conformance checks (`[security.strict_http]`).
+//!
+//! hyper accepts some messages that other HTTP implementations parse differently: a repeated
+//! `Content-Length` with identical values, header values carrying bytes outside visible ASCII,
+//! and absolute-form request targets whose authority disagrees with `Host`. A backend (or
+//! another proxy behind huginn) reading such a request its own way is the basis of request
+//! smuggling and routing confusion. [`check_strict_http`] runs right after
+//! [`check_http1_request`](super::check_http1_request), before routing.
+
+use http::header::{CONTENT_LENGTH, HOST};
+use http::{HeaderValue, Request, Uri, Version};
+use tracing::debug;
+
+use crate::config::{StrictHttpConfig, StrictHttpMode};
+use crate::proxy::http_result::HttpError;
+use crate::telemetry::Metrics;
+
+/// A request a backend could read differently from the proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolViolation {
+    /// `Transfer-Encoding` together with `Content-Length` (always rejected, strict mode or not)
+    TransferEncodingWithContentLength,
+    /// More than one `Content-Length` value, in repeated headers or a comma-separated list
+    DuplicateContentLength,
+    /// A header value with a byte other than visible ASCII, space or tab
+    InvalidHeaderValue,
+    /// An HTTP/1.x absolute-form request target (`GET http://host/path`)
+    AbsoluteFormTarget,
+}
+
+impl ProtocolViolation {
+    /// `violation` label of `huginn_protocol_violations_total`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProtocolViolation::TransferEncodingWithContentLength => {
+                "transfer_encoding_with_content_length"
+            }
+            ProtocolViolation::DuplicateContentLength => "duplicate_content_length",
+            ProtocolViolation::InvalidHeaderValue => "invalid_header_value",
+            ProtocolViolation::AbsoluteFormTarget => "absolute_form_target",
+        }
+    }
+
+    /// Whether the violation leaves the end of the request body uncertain, so the connection
+    /// cannot carry another request.
+    pub fn affects_framing(self) -> bool {
+        matches!(
+            self,
+            ProtocolViolation::TransferEncodingWithContentLength
+                | ProtocolViolation::DuplicateContentLength
+        )
+    }
+}
+
+/// Run `[security.strict_http]` on a request, before routing.
+///
+/// In `enforce` mode, repeated identical `Content-Length` values are collapsed into one header
+/// and an absolute-form target is rewritten to origin-form when its authority is the request's
+/// `Host` (or the request has none, which then gets it). Every other violation fails with
+/// `HttpError::ProtocolViolation`. In `detect` mode the request is left unchanged. Each violation
+/// found is counted in `huginn_protocol_violations_total`.
+pub fn check_strict_http<B>(
+    req: &mut Request<B>,
+    config: &StrictHttpConfig,
+    metrics: &Metrics,
+    peer: std::net::SocketAddr,
+) -> Result<(), HttpError> {
+    if !config.enabled {
+        return Ok(());
+    }
+    let enforce = config.mode == StrictHttpMode::Enforce;
+    let record = |violation: ProtocolViolation, action: &'static str| {
+        debug!(?peer, violation = violation.as_str(), action, "strict HTTP violation");
+        metrics.record_protocol_violation(violation.as_str(), action);
+    };
+
+    if req.headers().values().any(|v| v.to_str().is_err()) {
+        let violation = ProtocolViolation::InvalidHeaderValue;
+        if enforce {
+            record(violation, "rejected");
+            return Err(HttpError::ProtocolViolation(violation));
+        }
+        record(violation, "detected");
+    }
+
+    let lengths: Vec<&[u8]> = req
+        .headers()
+        .get_all(CONTENT_LENGTH)
+        .iter()
+        .flat_map(|v| v.as_bytes().split(|&b| b == b','))
+        .map(<[u8]>::trim_ascii)
+        .collect();
+    if lengths.len() > 1 {
+        let violation = ProtocolViolation::DuplicateContentLength;
+        let single = lengths
+            .first()
+            .filter(|first| lengths.iter().all(|l| l == *first))
+            .and_then(|first| HeaderValue::from_bytes(first).ok());
+        match single {
+            Some(value) if enforce => {
+                record(violation, "normalized");
+                req.headers_mut().insert(CONTENT_LENGTH, value);
+            }
+            _ if enforce => {
+                record(violation, "rejected");
+                return Err(HttpError::ProtocolViolation(violation));
+            }
+            _ => record(violation, "detected"),
+        }
+    }
+
+    let absolute_form = matches!(req.version(), Version::HTTP_10 | Version::HTTP_11)
+        && req.uri().scheme().is_some();
+    if let Some(authority) = req.uri().authority().filter(|_| absolute_form) {
+        let violation = ProtocolViolation::AbsoluteFormTarget;
+        if !enforce {
+            record(violation, "detected");
+            return Ok(());
+        }
+        let host = match req.headers().get(HOST) {
+            None => HeaderValue::from_str(authority.as_str()).ok(),
+            Some(host)
+                if host
+                    .as_bytes()
+                    .eq_ignore_ascii_case(authority.as_str().as_bytes()) =>
+            {
+                Some(host.clone())
+            }
+            Some(_) => None,
+        };
+        let origin_form = req
+            .uri()
+            .path_and_query()
+            .map_or("/", |pq| pq.as_str())
+            .parse::<Uri>()
+            .ok();
+        let (Some(host), Some(origin_form)) = (host, origin_form) else {
+            record(violation, "rejected");
+            return Err(HttpError::ProtocolViolation(violation));
+        };
+        record(violation, "normalized");
+        req.headers_mut().insert(HOST, host);
+        *req.uri_mut() = origin_form;
+    }
+    Ok(())
+}
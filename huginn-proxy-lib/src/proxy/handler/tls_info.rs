@@ -0,0 +1,32 @@
+use http::HeaderMap;
+use hyper::header::{HeaderName, HeaderValue};
+
+use crate::fingerprinting::tls_info;
+use crate::tls::TlsHandshakeInfo;
+
+/// Strip client-supplied `x-tls-*` parameter headers, then inject the connection's negotiated
+/// TLS parameters when `fingerprint.tls_info_headers` is enabled (`info` is `Some`).
+///
+/// Stripping is unconditional (plain HTTP listeners included) so the backend can trust the
+/// values to come from the proxy's own handshake.
+pub fn apply_tls_info_headers(headers: &mut HeaderMap, info: Option<&TlsHandshakeInfo>) {
+    for &name in tls_info::ALL {
+        headers.remove(name);
+    }
+    let Some(info) = info else {
+        return;
+    };
+    let values = [
+        (tls_info::ALPN, info.alpn.as_deref()),
+        (tls_info::SNI, info.sni.as_deref()),
+        (tls_info::VERSION, Some(info.version.as_str())),
+        (tls_info::CIPHER, Some(info.cipher.as_str())),
+        (tls_info::EARLY_DATA, Some(if info.early_data { "true" } else { "false" })),
+        (tls_info::ECH, Some(if info.ech { "true" } else { "false" })),
+    ];
+    for (name, value) in values {
+        if let Some(hv) = value.and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(HeaderName::from_static(name), hv);
+        }
+    }
+}
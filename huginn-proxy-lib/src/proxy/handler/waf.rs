@@ -0,0 +1,62 @@
+use http::StatusCode;
+use hyper::body::Incoming;
+use hyper::Request;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::config::{RouteProtocol, WafConfig, WafMode};
+use crate::proxy::http_result::{HttpError, HttpResult};
+use crate::proxy::prefetch::PrefetchedBody;
+use crate::proxy::router::RouteMatch;
+use crate::security::{waf, TlsMetadata};
+use crate::telemetry::Metrics;
+
+/// Run `[security.waf]` on a routed request.
+///
+/// Returns the request to forward, its body replaying whatever was read ahead for `body` rules.
+/// Fails with:
+/// - `HttpError::WafBlocked` when a rule matched and the route's mode is `block`
+/// - `HttpError::RequestBodyReadFailed` when the body could not be read ahead
+///
+/// Bodies of gRPC routes are never read ahead (their messages are framed and usually binary).
+/// `tls` describes the request's connection for the `sni`, `alpn` and `ja4` targets.
+pub async fn check_waf(
+    req: Request<Incoming>,
+    waf: &WafConfig,
+    route_match: &RouteMatch<'_>,
+    domain: &str,
+    metrics: &Arc<Metrics>,
+    peer: std::net::SocketAddr,
+    tls: TlsMetadata<'_>,
+) -> HttpResult<Request<PrefetchedBody<Incoming>>> {
+    let Some(mode) = waf.mode_for(route_match.waf) else {
+        return Ok(req.map(PrefetchedBody::new));
+    };
+
+    let (parts, body) = req.into_parts();
+    let (prefix, body) = if waf.inspects_body() && route_match.protocol != RouteProtocol::Grpc {
+        let (prefix, body) = PrefetchedBody::prefetch(body, waf.max_body_bytes)
+            .await
+            .map_err(|e| HttpError::RequestBodyReadFailed(e.to_string()))?;
+        (Some(prefix), body)
+    } else {
+        (None, PrefetchedBody::new(body))
+    };
+    let inspected = prefix
+        .as_ref()
+        .map(|p| p.get(..waf.max_body_bytes).unwrap_or(p));
+
+    if let Some(hit) = waf::inspect(waf, &parts.method, &parts.uri, &parts.headers, inspected, tls)
+    {
+        metrics.record_waf_hit(hit.rule, mode.as_str(), route_match.matched_prefix, domain);
+        match mode {
+            WafMode::Block => {
+                debug!(?peer, rule = hit.rule, "request blocked by WAF");
+                let status = StatusCode::from_u16(waf.status).unwrap_or(StatusCode::FORBIDDEN);
+                return Err(HttpError::WafBlocked(status, hit.rule.to_string()));
+            }
+            WafMode::Detect => debug!(?peer, rule = hit.rule, "WAF rule matched (detect mode)"),
+        }
+    }
+    Ok(Request::from_parts(parts, body))
+}
@@ -0,0 +1,123 @@
+//! Fingerprint diagnostics endpoint (`[fingerprint.whoami]`).
+//!
+//! The proxy answers a request to the configured path itself, before routing, with what it
+//! computed for the caller. Useful to check fingerprints end to end without a backend echoing
+//! the injected headers.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use http::{Request, Response, StatusCode};
+use serde::Serialize;
+
+use crate::fingerprinting::{forwarded, Ja4Fingerprints, TcpObservation};
+use crate::proxy::handler::add_forwarded_headers;
+use crate::tls::TlsHandshakeInfo;
+use crate::utils::http::{json_response, RespBody};
+
+/// JSON body of the endpoint. Signals the listener does not compute (or the connection did not
+/// provide) are `null`.
+#[derive(Serialize)]
+struct WhoamiView<'a> {
+    client_ip: String,
+    client_port: u16,
+    http_version: String,
+    host: &'a str,
+    ja4: Option<Ja4View>,
+    /// HTTP/2 only.
+    akamai: Option<String>,
+    tcp_syn: Option<String>,
+    tls: Option<TlsView<'a>>,
+    /// `X-Forwarded-*` headers as a backend would receive them.
+    forwarded: BTreeMap<&'static str, String>,
+}
+
+#[derive(Serialize)]
+struct Ja4View {
+    ja4: String,
+    ja4_r: String,
+    ja4_o: String,
+    ja4_or: String,
+    ja4_s1: String,
+    ja4_s1r: String,
+    sni: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TlsView<'a> {
+    version: &'a str,
+    cipher: &'a str,
+    alpn: Option<&'a str>,
+    sni: Option<&'a str>,
+    early_data: bool,
+    ech: bool,
+}
+
+/// Whether `req` targets the whoami endpoint at `path`.
+pub fn is_whoami_request<B>(req: &Request<B>, path: Option<&str>) -> bool {
+    path.is_some_and(|path| req.uri().path() == path)
+}
+
+/// Build the endpoint's `200` answer for `req`. `akamai` is the connection's HTTP/2 fingerprint,
+/// already filtered to HTTP/2 requests.
+pub fn whoami_response<B>(
+    req: &Request<B>,
+    peer: SocketAddr,
+    is_https: bool,
+    host: &str,
+    ja4: Option<&Ja4Fingerprints>,
+    akamai: Option<String>,
+    tcp_syn: Option<&TcpObservation>,
+) -> Response<RespBody> {
+    // Apply the forwarding rules to a copy holding only the client's X-Forwarded-For.
+    let mut forwarded_req = Request::new(());
+    if let Some(value) = req.headers().get(forwarded::FOR) {
+        forwarded_req
+            .headers_mut()
+            .insert(forwarded::FOR, value.clone());
+    }
+    add_forwarded_headers(&mut forwarded_req, peer, is_https, host);
+    let forwarded = [forwarded::FOR, forwarded::HOST, forwarded::PORT, forwarded::PROTO]
+        .into_iter()
+        .filter_map(|name| {
+            let value = forwarded_req.headers().get(name)?.to_str().ok()?;
+            Some((name, value.to_string()))
+        })
+        .collect();
+
+    let tls = req
+        .extensions()
+        .get::<Arc<TlsHandshakeInfo>>()
+        .map(|info| TlsView {
+            version: &info.version,
+            cipher: &info.cipher,
+            alpn: info.alpn.as_deref(),
+            sni: info.sni.as_deref(),
+            early_data: info.early_data,
+            ech: info.ech,
+        });
+
+    json_response(
+        StatusCode::OK,
+        WhoamiView {
+            client_ip: peer.ip().to_string(),
+            client_port: peer.port(),
+            http_version: format!("{:?}", req.version()),
+            host,
+            ja4: ja4.map(|fps| Ja4View {
+                ja4: fps.ja4.full.to_string(),
+                ja4_r: fps.ja4.raw.to_string(),
+                ja4_o: fps.ja4_original.full.to_string(),
+                ja4_or: fps.ja4_original.raw.to_string(),
+                ja4_s1: fps.ja4_stable_v1.full.to_string(),
+                ja4_s1r: fps.ja4_stable_v1.raw.to_string(),
+                sni: fps.sni.clone(),
+            }),
+            akamai,
+            tcp_syn: tcp_syn.map(ToString::to_string),
+            tls,
+            forwarded,
+        },
+    )
+}
@@ -1,6 +1,9 @@
 use http::StatusCode;
 use thiserror::Error;
 
+use crate::proxy::handler::strict_http::ProtocolViolation;
+use crate::proxy::route_timeout::TimeoutKind;
+
 /// HTTP result type, T is typically a hyper::Response
 /// HttpError is used to generate a synthetic error response
 pub(crate) type HttpResult<T> = Result<T, HttpError>;
@@ -11,6 +14,15 @@ pub enum HttpError {
     #[error("Invalid host in request header")]
     InvalidHostInRequestHeader,
 
+    /// An HTTP/1.x request whose body length is ambiguous (RFC 9112 §6.1, §6.3), e.g. both
+    /// `Transfer-Encoding` and `Content-Length`.
+    #[error("Ambiguous request framing: {0}")]
+    AmbiguousFraming(&'static str),
+
+    /// A request rejected by `[security.strict_http]`.
+    #[error("Protocol violation: {}", .0.as_str())]
+    ProtocolViolation(ProtocolViolation),
+
     #[error("No matching backend")]
     NoMatchingBackend,
 
@@ -32,6 +44,11 @@ pub enum HttpError {
     #[error("Failed to get response from backend: {0}")]
     FailedToGetResponseFromBackend(String),
 
+    /// No connection to the backend could be opened (refused, unreachable, TLS handshake), so
+    /// none of the request was sent.
+    #[error("Failed to connect to backend: {0}")]
+    BackendConnectFailed(String),
+
     #[error("Failed to generate downstream response: {0}")]
     FailedToGenerateDownstreamResponse(String),
 
@@ -40,12 +57,67 @@ pub enum HttpError {
 
     #[error("Upstream unhealthy (active health check)")]
     UpstreamUnhealthy,
+
+    /// A `CONNECT` target that is neither the route's backend nor in its `connect.targets`.
+    #[error("CONNECT target not allowed: {0}")]
+    ConnectTargetRefused(String),
+
+    /// Carries the configured `tls.client_cert.reject_status`.
+    #[error("Client certificate required")]
+    ClientCertificateRequired(StatusCode),
+
+    /// Carries the configured `security.fingerprint_filter.status`.
+    #[error("Fingerprint blocked by filter")]
+    FingerprintBlocked(StatusCode),
+
+    /// Carries the status of the registered classifier's `Verdict::Deny`.
+    #[error("Request denied by fingerprint classifier")]
+    ClassifierDenied(StatusCode),
+
+    /// Carries the route's `ext_authz.status_on_error` (fail-closed check failure).
+    #[error("External authorization failed: {1}")]
+    ExternalAuthFailed(StatusCode, String),
+
+    /// Carries the configured `security.waf.status`.
+    #[error("Request blocked by WAF rule {1}")]
+    WafBlocked(StatusCode, String),
+
+    /// Carries the configured `security.bot_verification.status`.
+    #[error("Request blocked: claimed crawler {1} failed verification")]
+    BotSpoofed(StatusCode, String),
+
+    #[error("Request body exceeds max_request_body_bytes")]
+    RequestBodyTooLarge,
+
+    /// The client body could not be read ahead for inspection.
+    #[error("Failed to read request body: {0}")]
+    RequestBodyReadFailed(String),
+
+    #[error("Backend response body exceeds max_response_body_bytes")]
+    ResponseBodyTooLarge,
+
+    #[error("Backend connection pool limit reached (pool.wait_timeout_ms elapsed)")]
+    BackendPoolExhausted,
+
+    /// Carries the expired limit: a route `timeout` or `[timeout] upstream_connect_ms`.
+    #[error("Backend timed out ({})", .0.as_str())]
+    BackendTimeout(TimeoutKind),
+
+    /// The backend was drained and its `drain_timeout_secs` passed before it answered.
+    #[error("Backend drain timeout passed before the response")]
+    BackendDrainCutoff,
+
+    /// A layer of the registered [`Middleware`](crate::proxy::middleware::Middleware) failed.
+    #[error("Middleware failed: {0}")]
+    MiddlewareFailed(String),
 }
 
 impl From<HttpError> for StatusCode {
     fn from(e: HttpError) -> StatusCode {
         match e {
             HttpError::InvalidHostInRequestHeader => StatusCode::BAD_REQUEST,
+            HttpError::AmbiguousFraming(_) => StatusCode::BAD_REQUEST,
+            HttpError::ProtocolViolation(_) => StatusCode::BAD_REQUEST,
             HttpError::NoMatchingBackend => StatusCode::SERVICE_UNAVAILABLE,
             HttpError::NoMatchingRoute => StatusCode::NOT_FOUND,
             HttpError::MisdirectedRequest => StatusCode::MISDIRECTED_REQUEST,
@@ -53,9 +125,24 @@ impl From<HttpError> for StatusCode {
             HttpError::NoUpstreamCandidates => StatusCode::NOT_FOUND,
             HttpError::FailedToGenerateUpstreamRequest(_) => StatusCode::INTERNAL_SERVER_ERROR,
             HttpError::FailedToGetResponseFromBackend(_) => StatusCode::BAD_GATEWAY,
+            HttpError::BackendConnectFailed(_) => StatusCode::BAD_GATEWAY,
             HttpError::FailedToGenerateDownstreamResponse(_) => StatusCode::INTERNAL_SERVER_ERROR,
             HttpError::InvalidUri(_) => StatusCode::BAD_REQUEST,
             HttpError::UpstreamUnhealthy => StatusCode::BAD_GATEWAY,
+            HttpError::ConnectTargetRefused(_) => StatusCode::FORBIDDEN,
+            HttpError::ClientCertificateRequired(status) => status,
+            HttpError::FingerprintBlocked(status) => status,
+            HttpError::ClassifierDenied(status) => status,
+            HttpError::ExternalAuthFailed(status, _) => status,
+            HttpError::WafBlocked(status, _) => status,
+            HttpError::BotSpoofed(status, _) => status,
+            HttpError::RequestBodyTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            HttpError::RequestBodyReadFailed(_) => StatusCode::BAD_REQUEST,
+            HttpError::ResponseBodyTooLarge => StatusCode::BAD_GATEWAY,
+            HttpError::BackendPoolExhausted => StatusCode::SERVICE_UNAVAILABLE,
+            HttpError::BackendTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            HttpError::BackendDrainCutoff => StatusCode::SERVICE_UNAVAILABLE,
+            HttpError::MiddlewareFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -64,6 +151,8 @@ impl HttpError {
     pub fn error_type(&self) -> &'static str {
         match self {
             HttpError::InvalidHostInRequestHeader => "invalid_host",
+            HttpError::AmbiguousFraming(_) => "ambiguous_framing",
+            HttpError::ProtocolViolation(_) => "protocol_violation",
             HttpError::NoMatchingBackend => "no_matching_backend",
             HttpError::NoMatchingRoute => "no_matching_route",
             HttpError::MisdirectedRequest => "misdirected_request",
@@ -71,25 +160,78 @@ impl HttpError {
             HttpError::NoUpstreamCandidates => "no_upstream_candidates",
             HttpError::FailedToGenerateUpstreamRequest(_) => "upstream_request_failed",
             HttpError::FailedToGetResponseFromBackend(_) => "backend_error",
+            HttpError::BackendConnectFailed(_) => "connect_failed",
             HttpError::FailedToGenerateDownstreamResponse(_) => "downstream_response_failed",
             HttpError::InvalidUri(_) => "invalid_uri",
             HttpError::UpstreamUnhealthy => "upstream_unhealthy",
+            HttpError::ConnectTargetRefused(_) => "connect_target_refused",
+            HttpError::ClientCertificateRequired(_) => "client_cert_required",
+            HttpError::FingerprintBlocked(_) => "fingerprint_blocked",
+            HttpError::ClassifierDenied(_) => "classifier_denied",
+            HttpError::ExternalAuthFailed(..) => "ext_authz_failed",
+            HttpError::WafBlocked(..) => "waf_blocked",
+            HttpError::BotSpoofed(..) => "bot_spoofed",
+            HttpError::RequestBodyTooLarge => "request_body_too_large",
+            HttpError::RequestBodyReadFailed(_) => "request_body_read_failed",
+            HttpError::ResponseBodyTooLarge => "response_body_too_large",
+            HttpError::BackendPoolExhausted => "pool_wait_timeout",
+            HttpError::BackendTimeout(_) => "backend_timeout",
+            HttpError::BackendDrainCutoff => "backend_drain_cutoff",
+            HttpError::MiddlewareFailed(_) => "middleware_failed",
         }
     }
 
+    /// Whether the connection must be closed after the error response: the rest of the bytes
+    /// the client sent cannot be trusted to start a new request (RFC 9112 §6.1).
+    pub fn closes_connection(&self) -> bool {
+        match self {
+            HttpError::AmbiguousFraming(_) => true,
+            HttpError::ProtocolViolation(violation) => violation.affects_framing(),
+            _ => false,
+        }
+    }
+
+    /// Whether the error guarantees the backend received none of the request: the connection
+    /// was never opened or no pooled connection was handed out.
+    pub fn nothing_sent(&self) -> bool {
+        matches!(
+            self,
+            HttpError::BackendConnectFailed(_)
+                | HttpError::BackendPoolExhausted
+                | HttpError::BackendTimeout(TimeoutKind::Connect)
+        )
+    }
+
     fn log_level(&self) -> tracing::Level {
         match self {
             HttpError::NoMatchingRoute
             | HttpError::MisdirectedRequest
             | HttpError::Forbidden
             | HttpError::UpstreamUnhealthy
+            | HttpError::ConnectTargetRefused(_)
+            | HttpError::ClientCertificateRequired(_)
+            | HttpError::FingerprintBlocked(_)
+            | HttpError::ClassifierDenied(_)
+            | HttpError::WafBlocked(..)
+            | HttpError::BotSpoofed(..)
+            | HttpError::RequestBodyTooLarge
+            | HttpError::RequestBodyReadFailed(_)
             | HttpError::InvalidHostInRequestHeader
+            | HttpError::AmbiguousFraming(_)
+            | HttpError::ProtocolViolation(_)
             | HttpError::InvalidUri(_) => tracing::Level::DEBUG,
             HttpError::NoMatchingBackend
             | HttpError::NoUpstreamCandidates
+            | HttpError::ExternalAuthFailed(..)
+            | HttpError::ResponseBodyTooLarge
+            | HttpError::BackendPoolExhausted
+            | HttpError::BackendTimeout(_)
+            | HttpError::BackendDrainCutoff
+            | HttpError::BackendConnectFailed(_)
             | HttpError::FailedToGetResponseFromBackend(_) => tracing::Level::WARN,
             HttpError::FailedToGenerateUpstreamRequest(_)
-            | HttpError::FailedToGenerateDownstreamResponse(_) => tracing::Level::ERROR,
+            | HttpError::FailedToGenerateDownstreamResponse(_)
+            | HttpError::MiddlewareFailed(_) => tracing::Level::ERROR,
         }
     }
 
@@ -1,8 +1,10 @@
 use std::net::SocketAddr;
+use std::path::Path;
 
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
 use tokio::signal;
 
+use crate::config::TcpConfig;
 use crate::error::Result;
 
 /// Bind a TCP listener to `addr` with the given `listen(2)` backlog.
@@ -11,7 +13,19 @@ use crate::error::Result;
 /// IPv6 connections. This prevents the dual-stack ambiguity where an IPv4 client
 /// arrives as `::ffff:x.y.z.w` (`SocketAddr::V6`), which would cause the SYN
 /// fingerprint lookup to hit the wrong eBPF map.
-pub fn bind_listener(addr: SocketAddr, backlog: i32) -> std::io::Result<TcpListener> {
+///
+/// With `reuse_port`, the socket is also created with `SO_REUSEPORT` so several listeners
+/// (one per accept loop, see `listen.acceptors`) can share `addr` and have the kernel balance
+/// connections between them.
+///
+/// The `tcp` options are set before `listen(2)`, so accepted connections inherit them (buffer
+/// sizes must be set then for the kernel to pick a matching window scale).
+pub fn bind_listener(
+    addr: SocketAddr,
+    backlog: i32,
+    reuse_port: bool,
+    tcp: &TcpConfig,
+) -> std::io::Result<TcpListener> {
     use socket2::{Domain, Protocol, Socket, Type};
 
     let domain = if addr.is_ipv6() {
@@ -21,15 +35,105 @@ pub fn bind_listener(addr: SocketAddr, backlog: i32) -> std::io::Result<TcpListe
     };
     let socket = Socket::new(domain, Type::STREAM, Some(Protocol::TCP))?;
     socket.set_reuse_address(true)?;
+    if reuse_port {
+        socket.set_reuse_port(true)?;
+    }
     if addr.is_ipv6() {
         socket.set_only_v6(true)?;
     }
+    set_tcp_options(&socket, tcp)?;
     socket.bind(&addr.into())?;
     socket.listen(backlog)?;
     socket.set_nonblocking(true)?;
     TcpListener::from_std(socket.into())
 }
 
+fn set_tcp_options(socket: &socket2::Socket, tcp: &TcpConfig) -> std::io::Result<()> {
+    use std::time::Duration;
+
+    if let Some(nodelay) = tcp.nodelay {
+        socket.set_tcp_nodelay(nodelay)?;
+    }
+    if tcp.keepalive_configured() {
+        let mut keepalive = socket2::TcpKeepalive::new();
+        if let Some(secs) = tcp.keepalive_time_secs {
+            keepalive = keepalive.with_time(Duration::from_secs(secs));
+        }
+        if let Some(secs) = tcp.keepalive_interval_secs {
+            keepalive = keepalive.with_interval(Duration::from_secs(secs));
+        }
+        if let Some(retries) = tcp.keepalive_retries {
+            keepalive = keepalive.with_retries(retries);
+        }
+        socket.set_tcp_keepalive(&keepalive)?;
+    }
+    if let Some(size) = tcp.send_buffer_size {
+        socket.set_send_buffer_size(size)?;
+    }
+    if let Some(size) = tcp.recv_buffer_size {
+        socket.set_recv_buffer_size(size)?;
+    }
+    Ok(())
+}
+
+/// Bind a unix domain socket listener at `path`.
+///
+/// A socket file left behind by a previous run is removed first; any other file at `path` is
+/// an error. With `mode`, the socket file gets those permissions (e.g. `0o660`) so only the
+/// intended clients can connect.
+pub fn bind_unix_listener(path: &Path, mode: Option<u32>) -> std::io::Result<UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = UnixListener::bind(path)?;
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(listener)
+}
+
+/// Listening socket of one listen address.
+pub enum BoundListener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// Connection accepted by a [`BoundListener`].
+pub enum AcceptedStream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+/// Peer reported for connections on a unix socket, which carry no client IP.
+pub const UNIX_PEER: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST), 0);
+
+impl BoundListener {
+    /// Accept the next connection and its socket peer ([`UNIX_PEER`] for unix sockets).
+    pub async fn accept(&self) -> std::io::Result<(AcceptedStream, SocketAddr)> {
+        match self {
+            BoundListener::Tcp(listener) => {
+                let (stream, peer) = listener.accept().await?;
+                Ok((AcceptedStream::Tcp(stream), peer))
+            }
+            BoundListener::Unix(listener) => {
+                let (stream, _) = listener.accept().await?;
+                Ok((AcceptedStream::Unix(stream), UNIX_PEER))
+            }
+        }
+    }
+}
+
 pub fn register_signal(kind: signal::unix::SignalKind, name: &str) -> Result<signal::unix::Signal> {
     signal::unix::signal(kind).map_err(|e| {
         crate::error::ProxyError::Io(std::io::Error::other(format!(
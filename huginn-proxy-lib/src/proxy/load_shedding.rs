@@ -0,0 +1,136 @@
+//! Adaptive load shedding (`[load_shedding]`).
+//!
+//! Handling latencies are collected per window. When a window ends, its p99 (and the tokio global
+//! queue depth, when `max_queue_depth` is set) decides whether the proxy is overloaded, and the
+//! shed ratio moves one `shed_step` up or down accordingly. Requests of sheddable routes are then
+//! rejected with that probability, so the proxy keeps answering its other routes in time instead
+//! of letting every request time out.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::config::{LoadSheddingConfig, RoutePriority};
+use crate::telemetry::access_log::sampled;
+use crate::telemetry::Metrics;
+
+/// Latency samples kept per window; later requests of a busy window are not sampled.
+const MAX_SAMPLES: usize = 8192;
+
+/// Overload detector shared by every connection.
+pub struct LoadShedder {
+    threshold: Duration,
+    window: Duration,
+    max_queue_depth: usize,
+    max_shed_ratio: f64,
+    shed_step: f64,
+    shed_priority: RoutePriority,
+    metrics: Arc<Metrics>,
+    state: Mutex<State>,
+}
+
+struct State {
+    started: Instant,
+    samples: Vec<Duration>,
+    ratio: f64,
+}
+
+impl LoadShedder {
+    /// `None` when `config.enabled` is false.
+    pub fn from_config(config: &LoadSheddingConfig, metrics: Arc<Metrics>) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        metrics.set_load_shed_ratio(0.0);
+        Some(Self {
+            threshold: Duration::from_millis(config.latency_threshold_ms),
+            window: Duration::from_millis(config.window_ms),
+            max_queue_depth: config.max_queue_depth,
+            max_shed_ratio: config.max_shed_ratio,
+            shed_step: config.shed_step,
+            shed_priority: config.shed_priority,
+            metrics,
+            state: Mutex::new(State { started: Instant::now(), samples: Vec::new(), ratio: 0.0 }),
+        })
+    }
+
+    /// Whether to reject a request of a route with `priority`.
+    pub fn should_shed(&self, priority: RoutePriority) -> bool {
+        if priority > self.shed_priority {
+            return false;
+        }
+        sampled(self.ratio())
+    }
+
+    /// Fraction of sheddable requests currently rejected.
+    pub fn ratio(&self) -> f64 {
+        match self.state.lock() {
+            Ok(mut state) => {
+                self.roll_window(&mut state);
+                state.ratio
+            }
+            Err(_) => 0.0,
+        }
+    }
+
+    /// Record the handling latency of one request.
+    pub fn record(&self, latency: Duration) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        self.roll_window(&mut state);
+        if state.samples.len() < MAX_SAMPLES {
+            state.samples.push(latency);
+        }
+    }
+
+    /// Close the window once it is over and move the ratio one step towards the new verdict.
+    /// A window without samples counts as not overloaded by latency.
+    fn roll_window(&self, state: &mut State) {
+        if state.started.elapsed() < self.window {
+            return;
+        }
+        let p99 = p99(&mut state.samples);
+        let queue_depth = queue_depth();
+        let overloaded = p99.is_some_and(|p99| p99 > self.threshold)
+            || (self.max_queue_depth > 0 && queue_depth > self.max_queue_depth);
+
+        let previous = state.ratio;
+        state.ratio = if overloaded {
+            (previous + self.shed_step).min(self.max_shed_ratio)
+        } else {
+            (previous - self.shed_step).max(0.0)
+        };
+        if previous == 0.0 && state.ratio > 0.0 {
+            warn!(
+                p99_ms = p99.map(|d| d.as_millis()),
+                queue_depth, "Proxy overloaded, shedding requests"
+            );
+        } else if previous > 0.0 && state.ratio == 0.0 {
+            info!("Proxy no longer overloaded, load shedding stopped");
+        }
+        self.metrics.set_load_shed_ratio(state.ratio);
+        state.samples.clear();
+        state.started = Instant::now();
+    }
+}
+
+/// 99th percentile of `samples`, reordering them. `None` when empty.
+fn p99(samples: &mut [Duration]) -> Option<Duration> {
+    let index = samples
+        .len()
+        .saturating_mul(99)
+        .div_ceil(100)
+        .checked_sub(1)?;
+    let (_, p99, _) = samples.select_nth_unstable(index);
+    Some(*p99)
+}
+
+/// Tasks waiting in the runtime's global queue; `0` outside a tokio runtime.
+fn queue_depth() -> usize {
+    tokio::runtime::Handle::try_current()
+        .map(|handle| handle.metrics().global_queue_depth())
+        .unwrap_or(0)
+}
@@ -0,0 +1,136 @@
+//! Tower middleware between fingerprint injection and forwarding.
+//!
+//! A [`Middleware`] wraps a [`tower::Layer`] stack registered by the library user. For each
+//! forwarded request the stack is applied to a fresh [`Next`] service: the layers see the request
+//! as the backend will (fingerprint, `X-Forwarded-*` and manipulated headers included), may answer
+//! it themselves, and otherwise pass it on through `Next`, which hands it back to the proxy for
+//! forwarding. Compression, caching, response header manipulation and bandwidth shaping still
+//! apply to the response the stack returns.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::{Request, Response};
+use http_body_util::combinators::BoxBody;
+use hyper::body::Incoming;
+use tokio::sync::oneshot;
+use tower::util::BoxCloneSyncService;
+use tower::{Layer, Service, ServiceExt};
+
+use crate::proxy::http_result::{HttpError, HttpResult};
+use crate::proxy::prefetch::PrefetchedBody;
+
+/// Error type of the middleware stack.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Request passed through the middleware stack, its body already partly read ahead for
+/// inspection when the route needed it.
+pub type ProxyRequest = Request<PrefetchedBody<Incoming>>;
+
+/// Response returned by the middleware stack.
+pub type ProxyResponse = Response<BoxBody<Bytes, BoxError>>;
+
+type Stack = BoxCloneSyncService<ProxyRequest, ProxyResponse, BoxError>;
+
+type Handoff = (ProxyRequest, oneshot::Sender<HttpResult<ProxyResponse>>);
+
+/// Layer stack run around forwarding, registered through [`run`](crate::proxy::run) or
+/// [`ProxyBuilder::layer`](crate::ProxyBuilder::layer).
+#[derive(Clone)]
+pub struct Middleware(Arc<dyn Fn(Next) -> Stack + Send + Sync>);
+
+impl Middleware {
+    /// Wrap `layer`, e.g. a `tower::ServiceBuilder` stack. Errors of the resulting service that
+    /// are not an [`HttpError`] are answered `500`.
+    pub fn new<L>(layer: L) -> Self
+    where
+        L: Layer<Next> + Send + Sync + 'static,
+        L::Service: Service<ProxyRequest, Response = ProxyResponse> + Clone + Send + Sync + 'static,
+        <L::Service as Service<ProxyRequest>>::Error: Into<BoxError>,
+        <L::Service as Service<ProxyRequest>>::Future: Send + 'static,
+    {
+        Self(Arc::new(move |next| {
+            BoxCloneSyncService::new(layer.layer(next).map_err(Into::into))
+        }))
+    }
+
+    /// Run `req` through the stack; `forward` sends the request it passes on to the backend.
+    ///
+    /// The stack and the forwarding are polled together, so a layer's own timers keep running
+    /// while the backend answers, and a stack that returns early cancels the forwarding.
+    pub(crate) async fn call<F, Fut>(
+        &self,
+        req: ProxyRequest,
+        forward: F,
+    ) -> HttpResult<ProxyResponse>
+    where
+        F: FnOnce(ProxyRequest) -> Fut,
+        Fut: Future<Output = HttpResult<ProxyResponse>>,
+    {
+        let (next, handoff) = Next::channel();
+        let stack = (self.0)(next).oneshot(req);
+        let forwarding = async move {
+            if let Ok((req, reply)) = handoff.await {
+                reply.send(forward(req).await).ok();
+            }
+        };
+        tokio::pin!(stack, forwarding);
+        let result = tokio::select! {
+            biased;
+            result = &mut stack => result,
+            () = &mut forwarding => stack.await,
+        };
+        result.map_err(|e| match e.downcast::<HttpError>() {
+            Ok(e) => *e,
+            Err(e) => HttpError::MiddlewareFailed(e.to_string()),
+        })
+    }
+}
+
+/// Innermost service of the stack: forwards the request to the backend the proxy selected.
+///
+/// A request body can only be sent once, so every clone of a `Next` shares a single call; later
+/// calls fail with [`HttpError::MiddlewareFailed`].
+#[derive(Clone)]
+pub struct Next {
+    handoff: Arc<Mutex<Option<oneshot::Sender<Handoff>>>>,
+}
+
+impl Next {
+    fn channel() -> (Self, oneshot::Receiver<Handoff>) {
+        let (tx, rx) = oneshot::channel();
+        (Self { handoff: Arc::new(Mutex::new(Some(tx))) }, rx)
+    }
+}
+
+impl Service<ProxyRequest> for Next {
+    type Response = ProxyResponse;
+    type Error = HttpError;
+    type Future = Pin<Box<dyn Future<Output = Result<ProxyResponse, HttpError>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: ProxyRequest) -> Self::Future {
+        let handoff = self
+            .handoff
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        Box::pin(async move {
+            let failed = |reason: &str| HttpError::MiddlewareFailed(reason.to_string());
+            let handoff = handoff.ok_or_else(|| failed("request already forwarded"))?;
+            let (reply, response) = oneshot::channel();
+            handoff
+                .send((req, reply))
+                .map_err(|_| failed("proxy stopped waiting for the request"))?;
+            response
+                .await
+                .map_err(|_| failed("forwarding was cancelled"))?
+        })
+    }
+}
@@ -1,20 +1,47 @@
 pub mod accept;
+pub mod bandwidth;
+pub mod body_bytes;
+pub mod body_limit;
+pub mod body_rewrite;
+pub mod builder;
+pub mod cache;
 pub mod client_pool;
+pub mod client_tracking;
+pub mod compression;
+pub mod concurrency;
+pub mod connect;
 pub mod connection;
+pub mod drain_cutoff;
+pub mod echo;
+pub mod ext_authz;
+pub mod failover;
 pub mod forwarding;
+pub mod grpc;
 pub mod handler;
 pub mod http_result;
 pub mod listener;
+pub mod load_shedding;
+pub mod middleware;
 pub mod peer_resolution;
+pub mod pool_connector;
+pub mod prefetch;
 pub mod protocol;
+pub mod redirect;
 pub mod reload;
+pub mod request_id;
+pub mod route_timeout;
 pub mod router;
+pub mod runtime;
 pub mod security_context;
 pub mod server;
 pub mod shutdown;
+pub mod socks5;
+pub mod static_files;
 pub mod synthetic_response;
 pub mod transport;
+pub mod udp;
 pub mod watch;
+pub mod websocket;
 pub use client_pool::ClientPool;
 pub use forwarding::{determine_http_version, find_backend_config};
 pub use http_result::HttpError;
@@ -0,0 +1,398 @@
+//! Connector used by the backend clients: hyper's [`HttpConnector`] plus connection accounting.
+//! Backends addressed as `unix:<path>` get a connector bound to that socket instead.
+//!
+//! Every connection it opens is counted in `huginn_backend_connections_opened_total`, timed in
+//! `huginn_backend_connect_duration_seconds` and tracked in `huginn_backend_pool_connections`
+//! until the pool drops it. Requests that reuse a pooled connection never reach the connector,
+//! so the opened count against the request count gives the reuse rate.
+//!
+//! The connect timeout is applied here rather than by [`HttpConnector`], so a route's
+//! `timeout.connect_ms` can replace it (see [`route_timeout`](crate::proxy::route_timeout)).
+//!
+//! Backends with `discovery` are dialed at their discovered addresses in turn (see
+//! [`discovery`](crate::backend::discovery)); the metrics label and the TLS server name stay the
+//! configured `host:port`.
+//!
+//! Backends with a `proxy` table are dialed through their SOCKS5 proxy (see
+//! [`socks5`](crate::proxy::socks5)): the connect timeout covers both the connection to the proxy
+//! and the proxy's `CONNECT` to the backend.
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use http::uri::PathAndQuery;
+use http::Uri;
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper_util::client::legacy::connect::{Connected, Connection, HttpConnector};
+use hyper_util::rt::TokioIo;
+use tokio::net::{TcpStream, UnixStream};
+use tower_service::Service;
+
+use crate::backend::DiscoveredAddrs;
+use crate::config::TcpConfig;
+use crate::proxy::route_timeout::{connect_timeout_override, ConnectTimeout};
+use crate::proxy::socks5::Socks5Proxy;
+use crate::telemetry::Metrics;
+use crate::utils::http::BoxError;
+
+/// [`HttpConnector`] that reports opened and closed connections per backend address.
+#[derive(Clone)]
+pub struct TrackedConnector {
+    inner: HttpConnector,
+    unix: Option<UnixTarget>,
+    metrics: Option<Arc<Metrics>>,
+    connect_timeout: Option<Duration>,
+    discovered: Option<Arc<DiscoveredAddrs>>,
+    socks5: Option<Arc<Socks5Proxy>>,
+}
+
+/// Unix socket every connection of the connector goes to, whatever the request URI.
+#[derive(Clone)]
+struct UnixTarget {
+    path: Arc<Path>,
+    /// Backend address (`unix:...`), used as the metrics label.
+    address: Arc<str>,
+}
+
+impl TrackedConnector {
+    pub fn new(inner: HttpConnector) -> Self {
+        Self {
+            inner,
+            unix: None,
+            metrics: None,
+            connect_timeout: None,
+            discovered: None,
+            socks5: None,
+        }
+    }
+
+    /// Connector for a `unix:` backend: connections go to `path` and are labelled `address`.
+    pub fn unix(&self, address: &str, path: &Path) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            unix: Some(UnixTarget { path: Arc::from(path), address: Arc::from(address) }),
+            metrics: self.metrics.clone(),
+            connect_timeout: self.connect_timeout,
+            discovered: None,
+            socks5: None,
+        }
+    }
+
+    /// Fail connections that take longer than `timeout` to open (`[timeout] upstream_connect_ms`).
+    /// A route's `timeout.connect_ms` replaces it for that route's connections.
+    pub fn with_connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Record connections in `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Dial backends that have discovered addresses at those addresses, in round-robin order.
+    pub fn with_discovery(mut self, discovered: Arc<DiscoveredAddrs>) -> Self {
+        self.discovered = Some(discovered);
+        self
+    }
+
+    /// Reach every destination through the SOCKS5 `proxy` (a backend's `proxy` table).
+    pub fn with_socks5(mut self, proxy: Arc<Socks5Proxy>) -> Self {
+        self.socks5 = Some(proxy);
+        self
+    }
+
+    /// Open TCP connections with the socket options of `tcp`; unset ones keep their setting.
+    pub fn with_tcp(mut self, tcp: &TcpConfig) -> Self {
+        set_tcp_options(&mut self.inner, tcp);
+        self
+    }
+
+    /// Allow `https://` destinations, for connectors wrapped in TLS.
+    pub fn enforce_http(&mut self, enforce: bool) {
+        self.inner.enforce_http(enforce);
+    }
+}
+
+/// Apply the options set in `tcp` to `connector`.
+pub(crate) fn set_tcp_options(connector: &mut HttpConnector, tcp: &TcpConfig) {
+    if let Some(nodelay) = tcp.nodelay {
+        connector.set_nodelay(nodelay);
+    }
+    if let Some(secs) = tcp.keepalive_time_secs {
+        connector.set_keepalive(Some(Duration::from_secs(secs)));
+    }
+    if let Some(secs) = tcp.keepalive_interval_secs {
+        connector.set_keepalive_interval(Some(Duration::from_secs(secs)));
+    }
+    if let Some(retries) = tcp.keepalive_retries {
+        connector.set_keepalive_retries(Some(retries));
+    }
+    if tcp.send_buffer_size.is_some() {
+        connector.set_send_buffer_size(tcp.send_buffer_size);
+    }
+    if tcp.recv_buffer_size.is_some() {
+        connector.set_recv_buffer_size(tcp.recv_buffer_size);
+    }
+}
+
+type Connecting = Pin<Box<dyn Future<Output = Result<TrackedIo<BackendIo>, BoxError>> + Send>>;
+type TcpConnecting = Pin<Box<dyn Future<Output = Result<TokioIo<TcpStream>, BoxError>> + Send>>;
+
+impl Service<Uri> for TrackedConnector {
+    type Response = TrackedIo<BackendIo>;
+    type Error = BoxError;
+    type Future = Connecting;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let metrics = self.metrics.clone();
+        let connect_timeout = connect_timeout_override().or(self.connect_timeout);
+        if let Some(target) = self.unix.clone() {
+            return Box::pin(async move {
+                let started = Instant::now();
+                let stream = within(connect_timeout, async {
+                    UnixStream::connect(&*target.path)
+                        .await
+                        .map_err(BoxError::from)
+                })
+                .await?;
+                let backend = target.address.to_string();
+                let guard = metrics.map(|metrics| {
+                    metrics
+                        .record_backend_connect_duration(started.elapsed().as_secs_f64(), &backend);
+                    metrics.record_backend_connection_opened(&backend);
+                    OpenConnection { metrics, backend }
+                });
+                Ok(TrackedIo { inner: BackendIo::Unix(TokioIo::new(stream)), _guard: guard })
+            });
+        }
+        let backend = dst
+            .authority()
+            .map(|a| a.as_str().to_string())
+            .unwrap_or_default();
+        let started = Instant::now();
+        let discovered = self
+            .discovered
+            .as_ref()
+            .and_then(|discovered| discovered.connect_order(&backend));
+        let connecting: TcpConnecting = match (discovered, self.socks5.clone()) {
+            (_, Some(proxy)) => Box::pin(connect_socks5(self.inner.clone(), proxy, dst)),
+            (Some(addrs), None) => Box::pin(connect_any(self.inner.clone(), dst, addrs)),
+            (None, None) => {
+                let connecting = self.inner.call(dst);
+                Box::pin(async move { connecting.await.map_err(BoxError::from) })
+            }
+        };
+        Box::pin(async move {
+            let io = BackendIo::Tcp(within(connect_timeout, connecting).await?);
+            let guard = metrics.map(|metrics| {
+                metrics.record_backend_connect_duration(started.elapsed().as_secs_f64(), &backend);
+                metrics.record_backend_connection_opened(&backend);
+                OpenConnection { metrics, backend }
+            });
+            Ok(TrackedIo { inner: io, _guard: guard })
+        })
+    }
+}
+
+/// Connect to the first of `addrs` that accepts, keeping the scheme of `dst`.
+async fn connect_any(
+    mut inner: HttpConnector,
+    dst: Uri,
+    addrs: Vec<SocketAddr>,
+) -> Result<TokioIo<TcpStream>, BoxError> {
+    let mut last_error = None;
+    for addr in addrs {
+        let mut parts = dst.clone().into_parts();
+        parts.authority = Some(addr.to_string().parse()?);
+        if parts.path_and_query.is_none() {
+            parts.path_and_query = Some(PathAndQuery::from_static("/"));
+        }
+        match inner.call(Uri::from_parts(parts)?).await {
+            Ok(io) => return Ok(io),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.map_or_else(|| "no discovered address to connect to".into(), BoxError::from))
+}
+
+/// Connect to `dst` through a SOCKS5 `proxy`; without a port, `dst` gets the default port of
+/// its scheme.
+async fn connect_socks5(
+    inner: HttpConnector,
+    proxy: Arc<Socks5Proxy>,
+    dst: Uri,
+) -> Result<TokioIo<TcpStream>, BoxError> {
+    let host = dst.host().ok_or("backend URI has no host")?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    let port = dst.port_u16().unwrap_or(match dst.scheme_str() {
+        Some("https") => 443,
+        _ => 80,
+    });
+    let stream = proxy.connect(inner, host, port).await?;
+    Ok(TokioIo::new(stream))
+}
+
+/// Await `connecting`, failing with [`ConnectTimeout`] once `timeout` elapses.
+async fn within<T>(
+    timeout: Option<Duration>,
+    connecting: impl Future<Output = Result<T, BoxError>>,
+) -> Result<T, BoxError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, connecting)
+            .await
+            .map_err(|_| BoxError::from(ConnectTimeout(timeout)))?,
+        None => connecting.await,
+    }
+}
+
+/// Decrements `huginn_backend_pool_connections` when the connection is dropped.
+struct OpenConnection {
+    metrics: Arc<Metrics>,
+    backend: String,
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        self.metrics.record_backend_connection_closed(&self.backend);
+    }
+}
+
+/// Connection returned by [`TrackedConnector`]; I/O is passed through unchanged.
+pub struct TrackedIo<T> {
+    inner: T,
+    _guard: Option<OpenConnection>,
+}
+
+impl<T: Connection> Connection for TrackedIo<T> {
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
+}
+
+impl<T: Read + Unpin> Read for TrackedIo<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: Write + Unpin> Write for TrackedIo<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write_vectored(cx, bufs)
+    }
+}
+
+/// Transport of a backend connection: TCP, or a unix socket for `unix:` backends.
+pub enum BackendIo {
+    Tcp(TokioIo<TcpStream>),
+    Unix(TokioIo<UnixStream>),
+}
+
+impl Connection for BackendIo {
+    fn connected(&self) -> Connected {
+        match self {
+            BackendIo::Tcp(io) => io.connected(),
+            BackendIo::Unix(_) => Connected::new(),
+        }
+    }
+}
+
+impl Read for BackendIo {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            BackendIo::Tcp(io) => Pin::new(io).poll_read(cx, buf),
+            BackendIo::Unix(io) => Pin::new(io).poll_read(cx, buf),
+        }
+    }
+}
+
+impl Write for BackendIo {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            BackendIo::Tcp(io) => Pin::new(io).poll_write(cx, buf),
+            BackendIo::Unix(io) => Pin::new(io).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            BackendIo::Tcp(io) => Pin::new(io).poll_flush(cx),
+            BackendIo::Unix(io) => Pin::new(io).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            BackendIo::Tcp(io) => Pin::new(io).poll_shutdown(cx),
+            BackendIo::Unix(io) => Pin::new(io).poll_shutdown(cx),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            BackendIo::Tcp(io) => io.is_write_vectored(),
+            BackendIo::Unix(io) => io.is_write_vectored(),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            BackendIo::Tcp(io) => Pin::new(io).poll_write_vectored(cx, bufs),
+            BackendIo::Unix(io) => Pin::new(io).poll_write_vectored(cx, bufs),
+        }
+    }
+}
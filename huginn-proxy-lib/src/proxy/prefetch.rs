@@ -0,0 +1,133 @@
+//! Request bodies read ahead of forwarding, for `[security.waf]` body rules and route failover.
+//!
+//! [`PrefetchedBody::prefetch`] reads the first frames of a body, up to a byte limit, and hands
+//! back both the bytes read and a body that replays them before streaming the rest. Bodies that
+//! are not inspected are wrapped with [`PrefetchedBody::new`], which passes frames through.
+//! A body read in full by [`PrefetchedBody::fill`] can be copied with
+//! [`PrefetchedBody::try_clone`] to send the request again.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Bytes, BytesMut};
+use http::HeaderMap;
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame, SizeHint};
+
+/// Body that yields a buffered prefix (and trailers read with it), then the rest of `B`.
+pub struct PrefetchedBody<B> {
+    prefix: Option<Bytes>,
+    trailers: Option<HeaderMap>,
+    rest: Option<B>,
+}
+
+impl<B> PrefetchedBody<B> {
+    /// Pass `body` through unchanged.
+    pub fn new(body: B) -> Self {
+        Self { prefix: None, trailers: None, rest: Some(body) }
+    }
+}
+
+impl<B> PrefetchedBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+{
+    /// Read `body` until it ends or at least `limit` bytes were read. Returns the bytes read
+    /// (which may go past `limit` by part of one frame) and the body to forward.
+    pub async fn prefetch(body: B, limit: usize) -> Result<(Bytes, Self), B::Error> {
+        let body = Self::new(body).fill(limit).await?;
+        Ok((body.prefix.clone().unwrap_or_default(), body))
+    }
+
+    /// Keep reading the rest of the body into the prefix until it ends or the prefix holds at
+    /// least `limit` bytes (possibly past it by part of one frame).
+    pub async fn fill(mut self, limit: usize) -> Result<Self, B::Error> {
+        let Some(mut rest) = self.rest.take() else {
+            return Ok(self);
+        };
+        let mut buf = BytesMut::new();
+        if let Some(prefix) = self.prefix.take() {
+            buf.extend_from_slice(&prefix);
+        }
+        let mut ended = false;
+        while buf.len() < limit {
+            let Some(frame) = rest.frame().await else {
+                ended = true;
+                break;
+            };
+            match frame?.into_data() {
+                Ok(data) => buf.extend_from_slice(&data),
+                Err(frame) => {
+                    self.trailers = frame.into_trailers().ok();
+                    ended = true;
+                    break;
+                }
+            }
+        }
+        let prefix = buf.freeze();
+        self.prefix = (!prefix.is_empty()).then_some(prefix);
+        self.rest = (!ended).then_some(rest);
+        Ok(self)
+    }
+
+    /// Copy of a body that is buffered whole (or has no bytes at all), e.g. to send a request
+    /// again. `None` while part of it is still to be streamed.
+    pub fn try_clone(&self) -> Option<Self> {
+        self.rest
+            .as_ref()
+            .is_none_or(Body::is_end_stream)
+            .then(|| Self {
+                prefix: self.prefix.clone(),
+                trailers: self.trailers.clone(),
+                rest: None,
+            })
+    }
+}
+
+impl<B> Body for PrefetchedBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if let Some(prefix) = this.prefix.take() {
+            return Poll::Ready(Some(Ok(Frame::data(prefix))));
+        }
+        if let Some(trailers) = this.trailers.take() {
+            return Poll::Ready(Some(Ok(Frame::trailers(trailers))));
+        }
+        match this.rest.as_mut() {
+            Some(rest) => Pin::new(rest).poll_frame(cx),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.prefix.is_none()
+            && self.trailers.is_none()
+            && self.rest.as_ref().is_none_or(Body::is_end_stream)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let rest = self
+            .rest
+            .as_ref()
+            .map_or_else(|| SizeHint::with_exact(0), Body::size_hint);
+        let prefix = self
+            .prefix
+            .as_ref()
+            .map_or(0, |p| u64::try_from(p.len()).unwrap_or(u64::MAX));
+        let mut hint = SizeHint::new();
+        hint.set_lower(rest.lower().saturating_add(prefix));
+        if let Some(upper) = rest.upper() {
+            hint.set_upper(upper.saturating_add(prefix));
+        }
+        hint
+    }
+}
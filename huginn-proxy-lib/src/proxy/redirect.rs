@@ -0,0 +1,111 @@
+//! Redirects answered before routing: the path `rules` of a `redirect` block, then its HTTPS
+//! redirect for requests that arrived in plaintext.
+
+use std::net::IpAddr;
+
+use http::header::{HeaderValue, LOCATION};
+use http::{HeaderMap, StatusCode, Uri};
+use hyper::Response;
+
+use crate::config::{RedirectConfig, RedirectRule, TrustedProxiesConfig};
+use crate::fingerprinting::forwarded;
+use crate::utils::http::{empty_body, RespBody};
+
+/// `kind` label of a redirect from `redirect.rules`.
+pub const KIND_RULE: &str = "rule";
+/// `kind` label of a redirect from `redirect.https`.
+pub const KIND_HTTPS: &str = "https";
+
+/// A redirect chosen by [`find_redirect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub status: StatusCode,
+    pub location: HeaderValue,
+    /// [`KIND_RULE`] or [`KIND_HTTPS`].
+    pub kind: &'static str,
+}
+
+impl Redirect {
+    /// Empty-bodied response carrying `Location`.
+    pub fn into_response(self) -> Response<RespBody> {
+        let mut response = Response::new(empty_body());
+        *response.status_mut() = self.status;
+        response.headers_mut().insert(LOCATION, self.location);
+        response
+    }
+}
+
+/// The redirect `config` answers the request with, if any: the first rule whose `host` and
+/// `path` match, otherwise the HTTPS redirect when it is on and the request is not `secure`.
+/// `host` is the routing host (lowercase, without port).
+pub fn find_redirect(
+    config: &RedirectConfig,
+    host: &str,
+    uri: &Uri,
+    secure: bool,
+) -> Option<Redirect> {
+    if let Some(redirect) = config
+        .rules
+        .iter()
+        .find_map(|rule| rule_redirect(rule, host, uri))
+    {
+        return Some(redirect);
+    }
+    if !config.https || secure || host.is_empty() {
+        return None;
+    }
+    let host = if host.contains(':') {
+        format!("[{host}]")
+    } else {
+        host.to_string()
+    };
+    let port = match config.https_port {
+        443 => String::new(),
+        port => format!(":{port}"),
+    };
+    let path = uri.path_and_query().map_or("/", |pq| pq.as_str());
+    Some(Redirect {
+        status: StatusCode::from_u16(config.https_status).unwrap_or(StatusCode::PERMANENT_REDIRECT),
+        location: HeaderValue::from_str(&format!("https://{host}{port}{path}")).ok()?,
+        kind: KIND_HTTPS,
+    })
+}
+
+fn rule_redirect(rule: &RedirectRule, host: &str, uri: &Uri) -> Option<Redirect> {
+    if rule
+        .host
+        .as_deref()
+        .is_some_and(|expected| !expected.eq_ignore_ascii_case(host))
+    {
+        return None;
+    }
+    let captures = rule.path.regex().captures(uri.path())?;
+    let mut location = String::new();
+    captures.expand(&rule.to, &mut location);
+    if let Some(query) = uri.query().filter(|q| rule.keep_query && !q.is_empty()) {
+        location.push(if location.contains('?') { '&' } else { '?' });
+        location.push_str(query);
+    }
+    Some(Redirect {
+        status: StatusCode::from_u16(rule.status).unwrap_or(StatusCode::MOVED_PERMANENTLY),
+        location: HeaderValue::from_str(&location).ok()?,
+        kind: KIND_RULE,
+    })
+}
+
+/// Whether the client used HTTPS: the request arrived on a TLS listener, or a trusted proxy in
+/// front of the plaintext listener says so with `X-Forwarded-Proto: https`.
+pub fn is_secure_request(
+    is_https: bool,
+    peer: IpAddr,
+    headers: &HeaderMap,
+    trusted_proxies: &TrustedProxiesConfig,
+) -> bool {
+    is_https
+        || (trusted_proxies.trusts(&peer)
+            && headers
+                .get(forwarded::PROTO)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https")))
+}
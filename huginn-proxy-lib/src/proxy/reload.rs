@@ -1,7 +1,8 @@
 use crate::backend::health_check::HealthCheckSupervisor;
+use crate::backend::BackendDiscovery;
 use crate::config::{
-    load_from_path, Backend, BackendPoolConfig, Domain, DynamicConfig, RateLimitConfig,
-    StaticConfig,
+    load_from_path, Backend, BackendPoolConfig, BackendPoolLimits, BackendTlsConfig,
+    DiscoveryConfig, Domain, DynamicConfig, RateLimitConfig, StaticConfig,
 };
 use crate::proxy::client_pool::ClientPool;
 use crate::proxy::protocol::warn_proxy_protocol_trust_gap;
@@ -14,6 +15,7 @@ use std::hash::{Hash, Hasher};
 use std::path::Path;
 use std::sync::Arc;
 use tokio::runtime::Handle;
+use tokio::sync::watch;
 use tracing::{debug, error, info};
 
 /// Hot-swappable rate-limit manager; reused across reloads unless its config changes (see `try_reload`).
@@ -25,6 +27,38 @@ pub type SharedClientPool = Arc<ArcSwap<ClientPool>>;
 /// Hot-swappable dynamic configuration.
 pub type SharedDynamicConfig = Arc<ArcSwap<DynamicConfig>>;
 
+/// Reload generation counter, bumped by every reload that changes the dynamic config. Open
+/// connections subscribe at accept time and drain gracefully once it moves (see `try_reload`).
+#[derive(Clone)]
+pub struct ConfigGeneration {
+    tx: Arc<watch::Sender<u64>>,
+}
+
+impl ConfigGeneration {
+    pub fn new() -> Self {
+        Self { tx: Arc::new(watch::Sender::new(0)) }
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.tx.subscribe()
+    }
+
+    pub fn current(&self) -> u64 {
+        *self.tx.borrow()
+    }
+
+    pub(crate) fn bump(&self) {
+        self.tx
+            .send_modify(|generation| *generation = generation.wrapping_add(1));
+    }
+}
+
+impl Default for ConfigGeneration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Apply a config change at runtime without dropping connections.
 ///
 /// Does:
@@ -32,12 +66,15 @@ pub type SharedDynamicConfig = Arc<ArcSwap<DynamicConfig>>;
 /// - Reload per-domain certs (best-effort) FIRST, then swap rate-limiter, pool, and the
 ///   routing config LAST. Cert IO is the slow step; doing it before the synchronous stores
 ///   keeps the cert-vs-routes inconsistency window down to microseconds.
-/// - Rebuild only what changed: rate-limiter (counters reset) and client pool (idle conns drained).
-/// - Reconcile health checks for added/removed backends.
+/// - Rebuild only what changed: rate-limiter (counters reset) and client pool (idle conns drained;
+///   upstream TLS material is loaded up front and a failure rejects the reload).
+/// - Reconcile health checks and address discovery for added/removed backends.
+/// - Drain live connections when the dynamic config changed: `generation` is bumped, every open
+///   connection is shut down gracefully (HTTP/2 GOAWAY, HTTP/1 close after the in-flight
+///   response) and clients reconnect onto the new config. In-flight requests on removed routes
+///   complete with the snapshot they started with.
 ///
 /// Does NOT:
-/// - Touch live connections: each one keeps the config snapshot it took at accept time, so changes
-///   apply to new connections only (no drain / GOAWAY).
 /// - Apply static changes (listen, tls, fingerprint, timeout): logged, effective on restart only.
 /// - Run concurrently: serialised by `reload_mutex`.
 #[allow(clippy::too_many_arguments)]
@@ -50,7 +87,9 @@ pub async fn try_reload(
     reload_mutex: &tokio::sync::Mutex<()>,
     metrics: &Arc<Metrics>,
     health_supervisor: &HealthCheckSupervisor,
+    discovery: &BackendDiscovery,
     cert_resolver: Option<&Arc<DynamicCertResolver>>,
+    generation: &ConfigGeneration,
 ) {
     let _guard = reload_mutex.lock().await;
 
@@ -91,6 +130,25 @@ pub async fn try_reload(
         &new_dynamic.security.trusted_proxies,
     );
 
+    // Built before anything is swapped: unreadable upstream TLS material rejects the whole reload.
+    let refreshed_pool = match refreshed_client_pool(
+        &old_dynamic.backends,
+        &new_dynamic.backends,
+        &old_dynamic.backend_pool,
+        &new_dynamic.backend_pool,
+        &static_cfg.timeout.keep_alive,
+        static_cfg.timeout.upstream_connect_ms,
+        metrics,
+        discovery,
+    ) {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!(error = %e, "Config reload failed: upstream TLS error, keeping current config");
+            metrics.record_reload_error();
+            return;
+        }
+    };
+
     let hash = fnv1a_hash(&new_dynamic);
     let old_hash = fnv1a_hash(&old_dynamic);
 
@@ -133,24 +191,20 @@ pub async fn try_reload(
         info!("Rate-limit config changed counters reset");
     }
 
-    // Refresh the connection pool when backends are removed or pool config changes; in-flight
-    // requests keep their old pool clone and only its idle connections are dropped afterwards.
-    drain_removed_backends(
-        &old_dynamic.backends,
-        &new_dynamic.backends,
-        &old_dynamic.backend_pool,
-        &new_dynamic.backend_pool,
-        client_pool,
-        &static_cfg.timeout.keep_alive,
-        static_cfg.timeout.upstream_connect_ms,
-    );
+    // Refresh the connection pool when backends are removed or pool/upstream TLS config changes;
+    // in-flight requests keep their old pool clone and only its idle connections are dropped
+    // afterwards.
+    if let Some(new_pool) = refreshed_pool {
+        client_pool.store(Arc::new(new_pool));
+    }
 
     // Routing config swapped LAST so a connection that observes the new routes already
     // sees the matching certs, rate limiter, and pool from the same reload generation.
     let new_dynamic = Arc::new(new_dynamic);
     dynamic_cfg.store(Arc::clone(&new_dynamic));
-    // Reconcile health-check tasks for added/removed backends.
+    // Reconcile health-check and discovery tasks for added/removed backends.
     health_supervisor.reconcile(&new_dynamic.backends, metrics, &Handle::current());
+    discovery.reconcile(&new_dynamic.backends, client_pool, metrics, &Handle::current());
 
     metrics.record_reload_success(hash);
     if hash == old_hash {
@@ -159,10 +213,12 @@ pub async fn try_reload(
             "Config reloaded successfully (no effective dynamic changes)"
         );
     } else {
+        generation.bump();
         debug!(
             config_hash = hash,
             old_config_hash = old_hash,
-            "Config reloaded successfully, dynamic config changed"
+            generation = generation.current(),
+            "Config reloaded successfully, dynamic config changed; draining open connections"
         );
     }
 }
@@ -211,13 +267,26 @@ fn audit_config_changes(old: &DynamicConfig, new: &DynamicConfig) {
             "Config diff: preserve_host changed"
         );
     }
+    if old.default_host != new.default_host {
+        info!(
+            old = ?old.default_host,
+            new = ?new.default_host,
+            "Config diff: default_host changed"
+        );
+    }
 
     if old.headers != new.headers {
         info!("Config diff: global header manipulation changed");
     }
+    if old.compression != new.compression {
+        info!("Config diff: global response compression changed");
+    }
+    if old.redirect != new.redirect {
+        info!("Config diff: global redirects changed");
+    }
 
     if old.security.headers != new.security.headers {
-        info!("Config diff: security headers changed (HSTS / CSP / custom)");
+        info!("Config diff: security headers changed (preset / HSTS / CSP / custom / remove)");
     }
     if old.security.ip_filter != new.security.ip_filter {
         info!("Config diff: IP filter changed");
@@ -228,6 +297,24 @@ fn audit_config_changes(old: &DynamicConfig, new: &DynamicConfig) {
     if old.security.trusted_proxies != new.security.trusted_proxies {
         info!("Config diff: trusted proxies changed (client-IP resolution from XFF)");
     }
+    if old.security.fingerprint_filter != new.security.fingerprint_filter {
+        info!("Config diff: fingerprint filter changed");
+    }
+    if old.security.waf != new.security.waf {
+        info!("Config diff: WAF rules or limits changed");
+    }
+    if old.security.bot_verification != new.security.bot_verification {
+        info!("Config diff: bot verification changed");
+    }
+    if old.security.strict_http != new.security.strict_http {
+        info!("Config diff: strict HTTP mode changed");
+    }
+    if old.security.challenge != new.security.challenge {
+        info!("Config diff: challenge config changed");
+    }
+    if old.security.tarpit != new.security.tarpit {
+        info!("Config diff: tarpit config changed");
+    }
     if old.backend_pool != new.backend_pool {
         info!("Config diff: backend pool config changed, pool will be refreshed");
     }
@@ -259,25 +346,36 @@ fn rate_limit_signature(
         .collect()
 }
 
-/// Replace the shared client pool when backends are removed or pool config changes; otherwise
-/// keep it as-is to avoid resetting healthy connections.
-fn drain_removed_backends(
+/// Build a replacement client pool when backends are removed, pool config changes or a backend's
+/// upstream TLS, `pool` or `discovery` settings change; `Ok(None)` keeps the current pool to avoid resetting
+/// healthy connections. Errors (unreadable upstream TLS material) must abort the reload.
+#[allow(clippy::too_many_arguments)]
+fn refreshed_client_pool(
     old_backends: &[Backend],
     new_backends: &[Backend],
     old_pool_cfg: &BackendPoolConfig,
     new_pool_cfg: &BackendPoolConfig,
-    client_pool: &SharedClientPool,
     keep_alive: &crate::config::startup::timeout::KeepAliveConfig,
     upstream_connect_ms: Option<u64>,
-) {
+    metrics: &Arc<Metrics>,
+    discovery: &BackendDiscovery,
+) -> crate::error::Result<Option<ClientPool>> {
     let old_addrs: HashSet<&str> = old_backends.iter().map(|b| b.address.as_str()).collect();
     let new_addrs: HashSet<&str> = new_backends.iter().map(|b| b.address.as_str()).collect();
 
     let removed: Vec<&&str> = old_addrs.difference(&new_addrs).collect();
     let pool_cfg_changed = old_pool_cfg != new_pool_cfg;
-
-    if removed.is_empty() && !pool_cfg_changed {
-        return;
+    let tls_changed = upstream_tls_signature(old_backends) != upstream_tls_signature(new_backends);
+    let limits_changed = pool_limits_signature(old_backends) != pool_limits_signature(new_backends);
+    let discovery_changed = discovery_signature(old_backends) != discovery_signature(new_backends);
+
+    if removed.is_empty()
+        && !pool_cfg_changed
+        && !tls_changed
+        && !limits_changed
+        && !discovery_changed
+    {
+        return Ok(None);
     }
 
     if !removed.is_empty() {
@@ -289,9 +387,45 @@ fn drain_removed_backends(
     if pool_cfg_changed {
         info!("Backend pool config changed, refreshing connection pool");
     }
+    if tls_changed {
+        info!("Backend upstream TLS config changed, refreshing connection pool");
+    }
+    if limits_changed {
+        info!("Backend pool limits changed, refreshing connection pool");
+    }
+    if discovery_changed {
+        info!("Backend discovery config changed, refreshing connection pool");
+    }
 
-    let new_pool = ClientPool::new(keep_alive, new_pool_cfg.clone(), upstream_connect_ms);
-    client_pool.store(Arc::new(new_pool));
+    ClientPool::new(keep_alive, new_pool_cfg.clone(), upstream_connect_ms)
+        .with_metrics(Arc::clone(metrics))
+        .with_discovery(Arc::clone(discovery.addrs()))
+        .with_backends(new_backends)
+        .map(Some)
+}
+
+/// Per-backend upstream TLS settings, keyed by address (order-insensitive).
+fn upstream_tls_signature(backends: &[Backend]) -> BTreeMap<&str, &BackendTlsConfig> {
+    backends
+        .iter()
+        .filter_map(|b| b.tls.as_ref().map(|tls| (b.address.as_str(), tls)))
+        .collect()
+}
+
+/// Per-backend `pool` tables, keyed by address (order-insensitive).
+fn pool_limits_signature(backends: &[Backend]) -> BTreeMap<&str, &BackendPoolLimits> {
+    backends
+        .iter()
+        .filter_map(|b| b.pool.as_ref().map(|pool| (b.address.as_str(), pool)))
+        .collect()
+}
+
+/// Per-backend `discovery` tables, keyed by address (order-insensitive).
+fn discovery_signature(backends: &[Backend]) -> BTreeMap<&str, &DiscoveryConfig> {
+    backends
+        .iter()
+        .filter_map(|b| b.discovery.as_ref().map(|d| (b.address.as_str(), d)))
+        .collect()
 }
 
 /// Fast hash of a `DynamicConfig` for the `huginn_config_hash` Prometheus gauge: only needs to be
@@ -316,11 +450,17 @@ pub fn initial_rate_limiter(dynamic: &DynamicConfig) -> SharedRateLimiter {
 pub fn initial_client_pool(
     static_cfg: &StaticConfig,
     pool_cfg: &BackendPoolConfig,
-) -> SharedClientPool {
+    backends: &[Backend],
+    metrics: &Arc<Metrics>,
+    discovery: &BackendDiscovery,
+) -> crate::error::Result<SharedClientPool> {
     let pool = ClientPool::new(
         &static_cfg.timeout.keep_alive,
         pool_cfg.clone(),
         static_cfg.timeout.upstream_connect_ms,
-    );
-    Arc::new(ArcSwap::from_pointee(pool))
+    )
+    .with_metrics(Arc::clone(metrics))
+    .with_discovery(Arc::clone(discovery.addrs()))
+    .with_backends(backends)?;
+    Ok(Arc::new(ArcSwap::from_pointee(pool)))
 }
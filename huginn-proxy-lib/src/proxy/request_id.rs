@@ -0,0 +1,132 @@
+//! Request IDs (`[request_id]`).
+//!
+//! Each request gets an ID before it is handled: the one a trusted proxy already set when
+//! `trust_incoming` allows it, a new one otherwise. The ID is written to the request header (so
+//! the backend receives it) and stored in the request extensions, where the access log and the
+//! request span read it. Once the response is known it is echoed to the client, unless the
+//! backend already answered with its own.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::IpAddr;
+
+use http::header::{HeaderName, HeaderValue};
+use http::{Request, Response};
+
+use crate::config::{RequestIdConfig, RequestIdFormat, TrustedProxiesConfig};
+use crate::utils::time::unix_millis;
+
+/// Incoming IDs longer than this are replaced rather than forwarded.
+const MAX_INCOMING_LEN: usize = 128;
+
+/// Crockford base32, the ULID alphabet.
+const ULID_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Assigns request IDs, shared by every connection.
+#[derive(Debug)]
+pub struct RequestIdGenerator {
+    header: HeaderName,
+    format: RequestIdFormat,
+    trust_incoming: bool,
+}
+
+/// The ID of one request, available in its extensions.
+#[derive(Debug, Clone)]
+pub struct RequestId {
+    header: HeaderName,
+    value: HeaderValue,
+}
+
+impl RequestIdGenerator {
+    /// `None` when `config.enabled` is false or `config.header` is not a valid header name
+    /// (rejected by config validation).
+    pub fn from_config(config: &RequestIdConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let header = HeaderName::from_bytes(config.header.as_bytes()).ok()?;
+        Some(Self { header, format: config.format, trust_incoming: config.trust_incoming })
+    }
+
+    /// Give `req` its ID: keep the incoming one when `trust_incoming` is set and `peer_ip` is a
+    /// trusted proxy, generate one otherwise. The ID replaces the request header and is also
+    /// stored in the request extensions.
+    pub fn assign<B>(
+        &self,
+        req: &mut Request<B>,
+        peer_ip: IpAddr,
+        trusted_proxies: &TrustedProxiesConfig,
+    ) -> RequestId {
+        let incoming = req
+            .headers()
+            .get(&self.header)
+            .filter(|_| self.trust_incoming && trusted_proxies.trusts(&peer_ip))
+            .filter(|value| is_acceptable(value))
+            .cloned();
+        let value = incoming.unwrap_or_else(|| self.generate());
+        req.headers_mut().insert(self.header.clone(), value.clone());
+        let id = RequestId { header: self.header.clone(), value };
+        req.extensions_mut().insert(id.clone());
+        id
+    }
+
+    fn generate(&self) -> HeaderValue {
+        let id = match self.format {
+            RequestIdFormat::Uuid => uuid_v4(random_u128()),
+            RequestIdFormat::Ulid => ulid(u128::from(unix_millis()), random_u128()),
+        };
+        HeaderValue::from_str(&id).unwrap_or_else(|_| HeaderValue::from_static("-"))
+    }
+}
+
+impl RequestId {
+    pub fn as_str(&self) -> &str {
+        self.value.to_str().unwrap_or_default()
+    }
+
+    /// Add the ID to `response` unless it already carries the header.
+    pub fn echo<B>(&self, response: &mut Response<B>) {
+        if !response.headers().contains_key(&self.header) {
+            response
+                .headers_mut()
+                .insert(self.header.clone(), self.value.clone());
+        }
+    }
+}
+
+/// Non-empty, printable and not absurdly long.
+fn is_acceptable(value: &HeaderValue) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_INCOMING_LEN
+        && value
+            .to_str()
+            .is_ok_and(|v| v.bytes().all(|b| b.is_ascii_graphic()))
+}
+
+/// 128 random bits from two randomly keyed hashers, which is plenty for IDs and avoids a
+/// dependency on a random number generator.
+fn random_u128() -> u128 {
+    let high = RandomState::new().build_hasher().finish();
+    let low = RandomState::new().build_hasher().finish();
+    u128::from(high).wrapping_shl(64) | u128::from(low)
+}
+
+/// RFC 9562 version 4 UUID from `random`, e.g. `9b2f6c1e-4a7d-4f3b-8c2e-1d5a6b7c8d9e`.
+pub fn uuid_v4(random: u128) -> String {
+    let version_cleared = random & 0xffff_ffff_ffff_0fff_3fff_ffff_ffff_ffff;
+    let bits = version_cleared | 0x0000_0000_0000_4000_8000_0000_0000_0000;
+    let hex = format!("{bits:032x}");
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// ULID of `millis` (lower 48 bits) and `random` (lower 80 bits): 26 Crockford base32 characters.
+pub fn ulid(millis: u128, random: u128) -> String {
+    let timestamp = millis & 0xffff_ffff_ffff;
+    let mut bits = timestamp.wrapping_shl(80) | (random & 0xffff_ffff_ffff_ffff_ffff);
+    let mut out = [b'0'; 26];
+    for slot in out.iter_mut().rev() {
+        *slot = ULID_ALPHABET[(bits & 0x1f) as usize];
+        bits = bits.wrapping_shr(5);
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
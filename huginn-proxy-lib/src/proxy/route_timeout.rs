@@ -0,0 +1,137 @@
+//! Per-route backend timeouts (`timeout` on a route).
+//!
+//! Connectors are shared by every route of a backend, so the route's connect timeout reaches
+//! [`TrackedConnector`](crate::proxy::pool_connector::TrackedConnector) through a task-local set
+//! around the backend request. The first-byte timeout bounds the wait for the response head; the
+//! total timeout bounds the whole exchange, and [`DeadlineBody`] fails a response body that is
+//! still streaming when it expires.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use hyper::body::{Body, Frame, SizeHint};
+use thiserror::Error;
+use tokio::time::{Instant, Sleep};
+
+use crate::utils::http::BoxError;
+
+tokio::task_local! {
+    static CONNECT_TIMEOUT: Option<Duration>;
+}
+
+/// Which limit a backend request ran into; the `timeout_type` label of
+/// `huginn_backend_timeouts_total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    Connect,
+    FirstByte,
+    Total,
+}
+
+impl TimeoutKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimeoutKind::Connect => "connect",
+            TimeoutKind::FirstByte => "first_byte",
+            TimeoutKind::Total => "total",
+        }
+    }
+}
+
+/// Opening the backend connection took longer than the connect timeout.
+#[derive(Debug, Error)]
+#[error("backend connect timed out after {0:?}")]
+pub struct ConnectTimeout(pub Duration);
+
+/// The response body was still streaming when the route's `total_ms` expired.
+#[derive(Debug, Error)]
+#[error("backend response exceeded the total timeout")]
+pub struct TotalTimeout;
+
+/// Run `fut` with `timeout` replacing the connector's connect timeout for every connection it
+/// opens. `None` keeps the connector's own.
+pub async fn with_connect_timeout<F: Future>(timeout: Option<Duration>, fut: F) -> F::Output {
+    CONNECT_TIMEOUT.scope(timeout, fut).await
+}
+
+/// Connect timeout set by [`with_connect_timeout`] for the current task, if any.
+pub fn connect_timeout_override() -> Option<Duration> {
+    CONNECT_TIMEOUT.try_with(|timeout| *timeout).ok().flatten()
+}
+
+/// `true` when `error` or one of its sources is a [`ConnectTimeout`].
+pub fn is_connect_timeout(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(error);
+    while let Some(e) = current {
+        if e.is::<ConnectTimeout>() {
+            return true;
+        }
+        current = e.source();
+    }
+    false
+}
+
+/// Body wrapper that fails with [`TotalTimeout`] once `deadline` has passed. Without a deadline
+/// frames pass through unchanged.
+pub struct DeadlineBody<B> {
+    inner: B,
+    sleep: Option<Pin<Box<Sleep>>>,
+    on_expired: Option<Box<dyn FnOnce() + Send + Sync>>,
+}
+
+impl<B> DeadlineBody<B> {
+    pub fn new(inner: B, deadline: Option<Instant>) -> Self {
+        Self {
+            inner,
+            sleep: deadline.map(|deadline| Box::pin(tokio::time::sleep_until(deadline))),
+            on_expired: None,
+        }
+    }
+
+    /// Run `f` once, when the deadline expires mid-body (typically to record a metric).
+    pub fn on_expired(mut self, f: impl FnOnce() + Send + Sync + 'static) -> Self {
+        self.on_expired = Some(Box::new(f));
+        self
+    }
+}
+
+impl<B> Body for DeadlineBody<B>
+where
+    B: Body + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = B::Data;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if let Poll::Ready(frame) = Pin::new(&mut this.inner).poll_frame(cx) {
+            return Poll::Ready(frame.map(|frame| frame.map_err(Into::into)));
+        }
+        let expired = this
+            .sleep
+            .as_mut()
+            .is_some_and(|sleep| sleep.as_mut().poll(cx).is_ready());
+        if !expired {
+            return Poll::Pending;
+        }
+        this.sleep = None;
+        if let Some(on_expired) = this.on_expired.take() {
+            on_expired();
+        }
+        Poll::Ready(Some(Err(Box::new(TotalTimeout))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -1,3 +1,5 @@
+use http::{HeaderMap, Method};
+
 use crate::config::{Domain, Route};
 
 #[derive(Debug, Clone)]
@@ -5,13 +7,39 @@ pub struct RouteMatch<'a> {
     pub backend: &'a str,
     pub backend_candidates: Vec<&'a str>,
     pub fingerprinting: Option<bool>,
+    pub ja4_variants: Option<&'a [crate::config::Ja4Variant]>,
+    pub fingerprint_format: Option<crate::config::FingerprintFormat>,
+    pub tcp_os: bool,
     pub matched_prefix: &'a str,
     pub replace_path: Option<&'a str>,
+    pub rewrite: Option<&'a crate::config::PathRewriteConfig>,
+    pub preserve_host: Option<bool>,
+    pub host_rewrite: Option<&'a str>,
+    pub failover: Option<&'a crate::config::FailoverConfig>,
     pub rate_limit: Option<&'a crate::config::RateLimitConfig>,
     pub ip_filter: Option<&'a crate::config::IpFilterConfig>,
     pub security_headers: Option<&'a crate::config::SecurityHeaders>,
     pub headers: Option<&'a crate::config::HeaderManipulation>,
     pub force_new_connection: bool,
+    pub websocket: &'a crate::config::WebSocketConfig,
+    pub protocol: crate::config::RouteProtocol,
+    pub ext_authz: Option<&'a crate::config::ExtAuthzConfig>,
+    pub max_request_body_bytes: Option<u64>,
+    pub max_response_body_bytes: Option<u64>,
+    pub compression: Option<&'a crate::config::CompressionConfig>,
+    pub cache: Option<&'a crate::config::RouteCacheConfig>,
+    pub access_log: Option<&'a crate::config::RouteAccessLogConfig>,
+    pub max_in_flight: Option<usize>,
+    pub priority: crate::config::RoutePriority,
+    pub timeout: Option<crate::config::RouteTimeoutConfig>,
+    pub sticky: Option<&'a crate::config::StickyConfig>,
+    pub synthetic: Option<&'a crate::config::SyntheticResponseConfig>,
+    pub waf: Option<&'a crate::config::RouteWafConfig>,
+    pub bandwidth: Option<&'a crate::config::BandwidthConfig>,
+    pub connect: Option<&'a crate::config::ConnectConfig>,
+    pub privacy: Option<&'a crate::config::PrivacyConfig>,
+    pub body_rewrite: Option<&'a crate::config::BodyRewriteConfig>,
+    pub serve_static: Option<&'a crate::config::ServeStaticConfig>,
 }
 
 /// Returns true when `prefix` is a valid match for `path`.
@@ -26,18 +54,63 @@ pub fn prefix_matches(path: &str, prefix: &str) -> bool {
     prefix == "/" || path.len() == prefix.len() || path.as_bytes()[prefix.len()] == b'/'
 }
 
-/// Returns the first route with the longest matching prefix.
+/// Whether `route` serves `path`: its prefix matches (the whole path with `exact`) and so does
+/// its `regex`, if any.
+pub fn route_matches(path: &str, route: &Route) -> bool {
+    let prefix_ok = if route.exact {
+        path == route.prefix
+    } else {
+        prefix_matches(path, &route.prefix)
+    };
+    prefix_ok
+        && route
+            .regex
+            .as_ref()
+            .is_none_or(|r| r.regex().is_match(path))
+}
+
+/// Whether the request `method` and `headers` satisfy `route`'s `methods` and `match_headers`.
+pub fn route_accepts(route: &Route, method: &Method, headers: &HeaderMap) -> bool {
+    (route.methods.is_empty() || route.methods.iter().any(|m| m == method.as_str()))
+        && route.match_headers.iter().all(|h| h.matches(headers))
+}
+
+/// Whether `route` serves a request with `method` on `path`: `CONNECT` requests are served by
+/// the routes with a `connect` block, whatever their path matcher; other requests by path.
+fn route_serves(route: &Route, method: &Method, path: &str) -> bool {
+    if *method == Method::CONNECT {
+        route.connect.is_some()
+    } else {
+        route_matches(path, route)
+    }
+}
+
+/// Returns the first route that matches `path` in match order.
 ///
-/// Relies on routes being pre-sorted by prefix length descending (done in `Config::into_parts`),
-/// so the first match is by definition the most specific one.
+/// Relies on routes being pre-sorted by [`crate::config::sort_routes`] (done in
+/// `Config::into_parts`): `match_priority` descending, then prefix length descending, then exact
+/// before regex before plain prefix. The first match is by definition the most specific one.
 fn longest_match<'a>(path: &str, routes: &'a [Route]) -> Option<&'a Route> {
-    routes.iter().find(|r| prefix_matches(path, &r.prefix))
+    routes.iter().find(|r| route_matches(path, r))
 }
 
 pub fn pick_route<'a>(path: &str, routes: &'a [Route]) -> Option<&'a str> {
     longest_match(path, routes).map(|r| r.backend.as_str())
 }
 
+/// Prefix of the route that serves a request, `methods` and `match_headers` included.
+pub fn matched_prefix<'a>(
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    routes: &'a [Route],
+) -> Option<&'a str> {
+    routes
+        .iter()
+        .find(|r| route_serves(r, method, path) && route_accepts(r, method, headers))
+        .map(|r| r.prefix.as_str())
+}
+
 /// Finds the domain entry that matches `host`.
 ///
 /// Matching order (most specific first):
@@ -48,8 +121,22 @@ pub fn pick_route<'a>(path: &str, routes: &'a [Route]) -> Option<&'a str> {
 /// 4. `None`, no exact/wildcard match and no catch-all configured.
 ///    (The request handler maps this to HTTP 421 Misdirected Request.)
 pub fn pick_domain<'a>(domains: &'a [Domain], host: &str) -> Option<&'a Domain> {
+    pick_tenant_domain(domains, host, None)
+}
+
+/// [`pick_domain`] among the domains of `tenant` only, or among all of them for `None`.
+pub fn pick_tenant_domain<'a>(
+    domains: &'a [Domain],
+    host: &str,
+    tenant: Option<&str>,
+) -> Option<&'a Domain> {
+    let owned = || {
+        domains
+            .iter()
+            .filter(move |d| tenant.is_none_or(|t| d.tenant.as_deref() == Some(t)))
+    };
     // 1. Exact match
-    if let Some(d) = domains.iter().find(|d| d.host.as_deref() == Some(host)) {
+    if let Some(d) = owned().find(|d| d.host.as_deref() == Some(host)) {
         return Some(d);
     }
     // 2. Wildcard: strip leftmost label and compare base domain.
@@ -57,7 +144,7 @@ pub fn pick_domain<'a>(domains: &'a [Domain], host: &str) -> Option<&'a Domain>
     //    "localhost" still fall through to the catch-all below.
     if let Some(dot) = host.find('.') {
         let base = &host[dot.saturating_add(1)..];
-        if let Some(d) = domains.iter().find(|d| {
+        if let Some(d) = owned().find(|d| {
             d.host
                 .as_deref()
                 .is_some_and(|h| h.starts_with("*.") && h.get(2..) == Some(base))
@@ -66,7 +153,7 @@ pub fn pick_domain<'a>(domains: &'a [Domain], host: &str) -> Option<&'a Domain>
         }
     }
     // 3. Catch-all: first host-less domain.
-    domains.iter().find(|d| d.host.is_none())
+    owned().find(|d| d.host.is_none())
 }
 
 /// The certificate a domain is effectively served with: its own `cert_path`, or the
@@ -115,33 +202,84 @@ pub fn authority_matches_sni(domains: &[Domain], sni: &str, host: &str) -> bool
     }
 }
 
+/// Route serving `path`, on the path alone: `methods` and `match_headers` are not checked. The
+/// request handler uses [`pick_request_route`].
 pub fn pick_route_with_fingerprinting<'a>(
     path: &str,
     routes: &'a [Route],
+) -> Option<RouteMatch<'a>> {
+    let pos = routes.iter().position(|r| route_matches(path, r))?;
+    Some(route_match_at(routes, pos))
+}
+
+/// Route serving a request: the first route in match order whose path matcher, `methods` and
+/// `match_headers` all match; for a `CONNECT` request, the first route with a `connect` block
+/// whose `methods` and `match_headers` match. Its `priority` is the request's, after
+/// `priority_headers`.
+pub fn pick_request_route<'a>(
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    routes: &'a [Route],
 ) -> Option<RouteMatch<'a>> {
     let pos = routes
         .iter()
-        .position(|r| prefix_matches(path, &r.prefix))?;
+        .position(|r| route_serves(r, method, path) && route_accepts(r, method, headers))?;
+    let mut route_match = route_match_at(routes, pos);
+    route_match.priority = routes.get(pos)?.request_priority(headers);
+    Some(route_match)
+}
+
+/// [`RouteMatch`] of `routes[pos]`, with the load-balance candidates that share its matcher.
+fn route_match_at(routes: &[Route], pos: usize) -> RouteMatch<'_> {
     let first = &routes[pos];
 
     let backend_candidates = routes[pos..]
         .iter()
-        .take_while(|r| r.prefix.len() == first.prefix.len())
-        .filter(|r| r.prefix == first.prefix)
+        .take_while(|r| {
+            r.match_priority == first.match_priority && r.prefix.len() == first.prefix.len()
+        })
+        .filter(|r| r.same_matcher(first))
         .map(|r| r.backend.as_str())
         .collect::<Vec<_>>();
 
     let security = first.security.as_ref();
-    Some(RouteMatch {
+    RouteMatch {
         backend: first.backend.as_str(),
         backend_candidates,
         fingerprinting: first.fingerprinting,
+        ja4_variants: first.ja4_variants.as_deref(),
+        fingerprint_format: first.fingerprint_format,
+        tcp_os: first.tcp_os,
         matched_prefix: first.prefix.as_str(),
         replace_path: first.replace_path.as_deref(),
+        rewrite: first.rewrite.as_ref(),
+        preserve_host: first.preserve_host,
+        host_rewrite: first.host_rewrite.as_deref(),
+        failover: first.failover.as_ref(),
         rate_limit: security.and_then(|s| s.rate_limit.as_ref()),
         ip_filter: security.and_then(|s| s.ip_filter.as_ref()),
         security_headers: security.and_then(|s| s.headers.as_ref()),
         headers: first.headers.as_ref(),
         force_new_connection: first.force_new_connection,
-    })
+        websocket: &first.websocket,
+        protocol: first.protocol,
+        ext_authz: first.ext_authz.as_ref(),
+        max_request_body_bytes: first.max_request_body_bytes,
+        max_response_body_bytes: first.max_response_body_bytes,
+        compression: first.compression.as_ref(),
+        cache: first.cache.as_ref(),
+        access_log: first.access_log.as_ref(),
+        max_in_flight: first.max_in_flight,
+        priority: first.priority,
+        timeout: first.timeout,
+        sticky: first.sticky.as_ref(),
+        synthetic: first.synthetic.as_ref(),
+        waf: first.waf.as_ref(),
+        bandwidth: first.bandwidth.as_ref(),
+        connect: first.connect.as_ref(),
+        privacy: first.privacy.as_ref(),
+        body_rewrite: first.body_rewrite.as_ref(),
+        serve_static: first.serve_static.as_ref(),
+    }
 }
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use crate::proxy::connection::ConnectionRegistry;
+use crate::proxy::reload::ConfigGeneration;
+use crate::proxy::shutdown::DrainTrigger;
+use crate::proxy::synthetic_response::SyntheticSwitches;
+use crate::telemetry::{FingerprintStats, LogLevels};
+
+/// Live proxy state shared with the admin API (see [`crate::telemetry::admin`]).
+///
+/// Built by the caller of [`run`](crate::proxy::run) so the observability server can hold the
+/// same handles. `Default` disables connection tracking, runtime log levels and fingerprint
+/// stats, which is what embedders without the admin API want.
+#[derive(Clone, Default)]
+pub struct RuntimeHandles {
+    /// Open client connections; tracks nothing unless built with [`ConnectionRegistry::new`].
+    pub connections: ConnectionRegistry,
+    /// Bumped by every change to the dynamic config (file reload or admin mutation).
+    pub config_generation: ConfigGeneration,
+    /// Serialises config reloads and admin mutations.
+    pub reload_mutex: Arc<tokio::sync::Mutex<()>>,
+    /// Tracing filter control; unavailable unless returned by
+    /// [`init_tracing_with_otel`](crate::telemetry::init_tracing_with_otel).
+    pub log_levels: LogLevels,
+    /// Top JA4 / Akamai fingerprints; records nothing unless built with
+    /// [`FingerprintStats::from_config`].
+    pub fingerprint_stats: FingerprintStats,
+    /// Runtime on/off overrides of the routes' `synthetic` responses.
+    pub synthetic: SyntheticSwitches,
+    /// Drains and stops the proxy when requested, like SIGTERM.
+    pub drain: DrainTrigger,
+}
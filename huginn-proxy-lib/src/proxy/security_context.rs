@@ -1,9 +1,21 @@
 use std::sync::Arc;
 
 use crate::config::{
-    HeaderManipulation, IpFilterConfig, RateLimitConfig, SecurityHeaders, TrustedProxiesConfig,
+    BotVerificationConfig, ChallengeConfig, CompressionConfig, FingerprintFilterConfig,
+    HeaderManipulation, IpFilterConfig, RateLimitConfig, RedirectConfig, SecurityHeaders,
+    StrictHttpConfig, TarpitConfig, TrustedProxiesConfig, WafConfig,
 };
-use crate::security::RateLimitManager;
+use crate::fingerprinting::{HeaderSigner, SharedClassifier};
+use crate::proxy::bandwidth::BandwidthLimits;
+use crate::proxy::cache::ResponseCache;
+use crate::proxy::client_tracking::ClientTracker;
+use crate::proxy::concurrency::ConcurrencyLimits;
+use crate::proxy::connect::TunnelContext;
+use crate::proxy::load_shedding::LoadShedder;
+use crate::proxy::middleware::Middleware;
+use crate::proxy::request_id::RequestIdGenerator;
+use crate::proxy::synthetic_response::SyntheticSwitches;
+use crate::security::{BotVerifier, RateLimitManager, Tarpit};
 
 /// Security-related context for request handling
 #[derive(Clone)]
@@ -15,6 +27,57 @@ pub struct SecurityContext {
     pub global_header_manipulation: Option<HeaderManipulation>,
     /// Global trusted reverse-proxy config used to resolve the real client IP from XFF.
     pub trusted_proxies: TrustedProxiesConfig,
+    /// Global fingerprint allow/deny lists, enforced before routing.
+    pub fingerprint_filter: FingerprintFilterConfig,
+    /// Classifier registered by the library user through `run()`, if any.
+    pub classifier: Option<SharedClassifier>,
+    /// Layer stack registered by the library user through `run()`, run around forwarding.
+    pub middleware: Option<Middleware>,
+    /// Global response compression; a route's own `compression` block replaces it.
+    pub compression: Option<CompressionConfig>,
+    /// Global redirects; a domain's own `redirect` block replaces it.
+    pub redirect: Option<RedirectConfig>,
+    /// Store used by routes with a `cache` block.
+    pub response_cache: Option<Arc<ResponseCache>>,
+    /// Semaphores of the routes and backends that set `max_in_flight`.
+    pub concurrency_limits: Arc<ConcurrencyLimits>,
+    /// Token buckets of the routes and backends that set `bandwidth`.
+    pub bandwidth_limits: Arc<BandwidthLimits>,
+    /// Overload detector created from `[load_shedding]`, when enabled.
+    pub load_shedder: Option<Arc<LoadShedder>>,
+    /// Request ID generator created from `[request_id]`, when enabled.
+    pub request_ids: Option<Arc<RequestIdGenerator>>,
+    /// Client tracker created from `[client_tracking]`, when enabled.
+    pub client_tracker: Option<Arc<ClientTracker>>,
+    /// Runtime on/off overrides of the routes' `synthetic` responses.
+    pub synthetic: SyntheticSwitches,
+    /// `[security.waf]` rules and limits, applied to the routes it covers.
+    pub waf: WafConfig,
+    /// `[security.bot_verification]` crawlers and policy.
+    pub bot_verification: BotVerificationConfig,
+    /// Verifier (and outcome cache) shared by every connection.
+    pub bot_verifier: Arc<BotVerifier>,
+    /// `[security.strict_http]` conformance checks, run before routing.
+    pub strict_http: StrictHttpConfig,
+    /// `[security.challenge]` suspect fingerprints and page, run before routing.
+    pub challenge: ChallengeConfig,
+    /// `[security.tarpit]` graylist and delay, applied to ClientHellos on TLS listeners.
+    pub tarpit: TarpitConfig,
+    /// Connections waiting in the tarpit, shared by every connection.
+    pub tarpit_slots: Arc<Tarpit>,
+    /// Connect timeout and fingerprint telemetry of `CONNECT` tunnels.
+    pub tunnels: TunnelContext,
+    /// `fingerprint.tls_info_headers` of the listener: whether the negotiated TLS parameters are
+    /// forwarded as `x-tls-*` headers.
+    pub tls_info_headers: bool,
+    /// Path of the `[fingerprint.whoami]` diagnostics endpoint, when enabled on the listener.
+    pub whoami_path: Option<Arc<str>>,
+    /// `[fingerprint.signing]` signer of the fingerprint headers, when enabled.
+    pub header_signer: Option<Arc<HeaderSigner>>,
+    /// Top-level `default_host`: `Host` given to HTTP/1.0 requests that carry none.
+    pub default_host: Option<Arc<str>>,
+    /// `tenant` of the listener: only that tenant's domains are routed to.
+    pub listener_tenant: Option<Arc<str>>,
 }
 
 impl SecurityContext {
@@ -25,6 +88,7 @@ impl SecurityContext {
         rate_limit_manager: Option<Arc<RateLimitManager>>,
         global_header_manipulation: Option<HeaderManipulation>,
         trusted_proxies: TrustedProxiesConfig,
+        fingerprint_filter: FingerprintFilterConfig,
     ) -> Self {
         Self {
             headers,
@@ -33,6 +97,168 @@ impl SecurityContext {
             rate_limit_manager,
             global_header_manipulation,
             trusted_proxies,
+            fingerprint_filter,
+            classifier: None,
+            middleware: None,
+            compression: None,
+            redirect: None,
+            response_cache: None,
+            concurrency_limits: Arc::new(ConcurrencyLimits::new()),
+            bandwidth_limits: Arc::new(BandwidthLimits::new()),
+            load_shedder: None,
+            request_ids: None,
+            client_tracker: None,
+            synthetic: SyntheticSwitches::default(),
+            waf: WafConfig::default(),
+            bot_verification: BotVerificationConfig::default(),
+            bot_verifier: Arc::new(BotVerifier::new()),
+            strict_http: StrictHttpConfig::default(),
+            challenge: ChallengeConfig::default(),
+            tarpit: TarpitConfig::default(),
+            tarpit_slots: Arc::new(Tarpit::new()),
+            tunnels: TunnelContext::default(),
+            tls_info_headers: false,
+            whoami_path: None,
+            header_signer: None,
+            default_host: None,
+            listener_tenant: None,
         }
     }
+
+    /// Attach the classifier registered through `run()`.
+    pub fn with_classifier(mut self, classifier: Option<SharedClassifier>) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    /// Attach the middleware registered through `run()`.
+    pub fn with_middleware(mut self, middleware: Option<Middleware>) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Attach the global `[compression]` block.
+    pub fn with_compression(mut self, compression: Option<CompressionConfig>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Attach the global `[redirect]` block.
+    pub fn with_redirect(mut self, redirect: Option<RedirectConfig>) -> Self {
+        self.redirect = redirect;
+        self
+    }
+
+    /// Attach the response cache created from `[cache]`.
+    pub fn with_response_cache(mut self, response_cache: Option<Arc<ResponseCache>>) -> Self {
+        self.response_cache = response_cache;
+        self
+    }
+
+    /// Share `max_in_flight` semaphores across connections.
+    pub fn with_concurrency_limits(mut self, concurrency_limits: Arc<ConcurrencyLimits>) -> Self {
+        self.concurrency_limits = concurrency_limits;
+        self
+    }
+
+    /// Share `bandwidth` token buckets across connections.
+    pub fn with_bandwidth_limits(mut self, bandwidth_limits: Arc<BandwidthLimits>) -> Self {
+        self.bandwidth_limits = bandwidth_limits;
+        self
+    }
+
+    /// Attach the load shedder created from `[load_shedding]`.
+    pub fn with_load_shedder(mut self, load_shedder: Option<Arc<LoadShedder>>) -> Self {
+        self.load_shedder = load_shedder;
+        self
+    }
+
+    /// Attach the request ID generator created from `[request_id]`.
+    pub fn with_request_ids(mut self, request_ids: Option<Arc<RequestIdGenerator>>) -> Self {
+        self.request_ids = request_ids;
+        self
+    }
+
+    /// Attach the client tracker created from `[client_tracking]`.
+    pub fn with_client_tracker(mut self, client_tracker: Option<Arc<ClientTracker>>) -> Self {
+        self.client_tracker = client_tracker;
+        self
+    }
+
+    /// Attach the runtime switches of the routes' `synthetic` responses.
+    pub fn with_synthetic_switches(mut self, synthetic: SyntheticSwitches) -> Self {
+        self.synthetic = synthetic;
+        self
+    }
+
+    /// Attach the `[security.waf]` block.
+    pub fn with_waf(mut self, waf: WafConfig) -> Self {
+        self.waf = waf;
+        self
+    }
+
+    /// Attach the `[security.bot_verification]` block and the shared verifier.
+    pub fn with_bot_verification(
+        mut self,
+        bot_verification: BotVerificationConfig,
+        bot_verifier: Arc<BotVerifier>,
+    ) -> Self {
+        self.bot_verification = bot_verification;
+        self.bot_verifier = bot_verifier;
+        self
+    }
+
+    /// Attach the `[security.strict_http]` block.
+    pub fn with_strict_http(mut self, strict_http: StrictHttpConfig) -> Self {
+        self.strict_http = strict_http;
+        self
+    }
+
+    /// Attach the `[security.challenge]` block.
+    pub fn with_challenge(mut self, challenge: ChallengeConfig) -> Self {
+        self.challenge = challenge;
+        self
+    }
+
+    /// Attach the `[security.tarpit]` block and the shared count of waiting connections.
+    pub fn with_tarpit(mut self, tarpit: TarpitConfig, tarpit_slots: Arc<Tarpit>) -> Self {
+        self.tarpit = tarpit;
+        self.tarpit_slots = tarpit_slots;
+        self
+    }
+
+    /// Attach what `CONNECT` tunnels need from the listener.
+    pub fn with_tunnels(mut self, tunnels: TunnelContext) -> Self {
+        self.tunnels = tunnels;
+        self
+    }
+
+    /// Apply the listener's `fingerprint.tls_info_headers` and `[fingerprint.whoami]` settings.
+    pub fn with_fingerprint_diagnostics(
+        mut self,
+        tls_info_headers: bool,
+        whoami_path: Option<Arc<str>>,
+    ) -> Self {
+        self.tls_info_headers = tls_info_headers;
+        self.whoami_path = whoami_path;
+        self
+    }
+
+    /// Attach the signer created from `[fingerprint.signing]`.
+    pub fn with_header_signer(mut self, header_signer: Option<Arc<HeaderSigner>>) -> Self {
+        self.header_signer = header_signer;
+        self
+    }
+
+    /// Apply the top-level `default_host`.
+    pub fn with_default_host(mut self, default_host: Option<&str>) -> Self {
+        self.default_host = default_host.map(Arc::from);
+        self
+    }
+
+    /// Restrict routing to the domains of the listener's `tenant`.
+    pub fn with_listener_tenant(mut self, tenant: Option<Arc<str>>) -> Self {
+        self.listener_tenant = tenant;
+        self
+    }
 }
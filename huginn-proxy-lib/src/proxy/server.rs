@@ -1,52 +1,88 @@
 use crate::backend::health_check::{HealthCheckSupervisor, HealthRegistry};
-use crate::backend::BackendSelector;
+use crate::backend::{BackendDiscovery, BackendSelector, CircuitBreakerRegistry};
 use crate::config::watcher::spawn_config_watcher;
-use crate::config::{EffectiveConfigSummary, EffectiveConfigView, StaticConfig};
+use crate::config::{
+    ClientAuth, EffectiveConfigSummary, EffectiveConfigView, Http2Config, ListenAddr, StaticConfig,
+};
 use crate::error::Result;
+use crate::fingerprinting::{FingerprintHeaderNames, HeaderSigner, SharedClassifier};
 pub use crate::proxy::accept::SynProbe;
-use crate::proxy::accept::{accept_loop, AcceptContext};
+use crate::proxy::accept::{accept_loop, AcceptContext, ListenerContext};
+use crate::proxy::bandwidth::BandwidthLimits;
+use crate::proxy::cache::ResponseCache;
+use crate::proxy::client_tracking::ClientTracker;
+use crate::proxy::concurrency::ConcurrencyLimits;
 use crate::proxy::connection::ConnectionManager;
-use crate::proxy::listener::{bind_listener, register_signal};
+use crate::proxy::listener::{bind_listener, bind_unix_listener, register_signal, BoundListener};
+use crate::proxy::load_shedding::LoadShedder;
+use crate::proxy::middleware::Middleware;
 use crate::proxy::peer_resolution::ResolvedProxyProtocol;
 use crate::proxy::protocol::warn_proxy_protocol_trust_gap;
 use crate::proxy::reload::{
     initial_client_pool, initial_rate_limiter, try_reload, SharedDynamicConfig,
 };
+use crate::proxy::request_id::RequestIdGenerator;
+use crate::proxy::runtime::RuntimeHandles;
 use crate::proxy::shutdown::{wait_for_drain, ServiceHandle, ShutdownSender};
+use crate::proxy::udp::UdpForwarder;
 pub use crate::proxy::watch::WatchOptions;
-use crate::telemetry::{Metrics, Readiness};
-use crate::tls::{build_tls_acceptor, DynamicCertResolver};
+use crate::security::{BotVerifier, Tarpit};
+use crate::telemetry::{
+    AccessLogger, AnomalyDetector, EventStream, HandshakeCapture, Metrics, Readiness, RequestTracer,
+};
+use crate::tls::{build_tls_acceptor, spawn_ocsp_stapler, DynamicCertResolver, OcspStapler};
 use hyper_util::rt::{TokioExecutor, TokioTimer};
-use hyper_util::server::conn::auto::Builder as ConnBuilder;
-use std::net::SocketAddr;
+use hyper_util::server::conn::auto::{Builder as ConnBuilder, Http2Builder};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::net::TcpListener;
 use tokio::runtime::Handle;
 use tokio::signal;
 use tokio::sync::watch;
 use tokio::time::Duration;
 use tracing::{debug, info, warn};
 
+/// Run the proxy until SIGTERM/SIGINT or a drain request (`runtime.drain`).
+///
+/// `classifier` plugs custom request classification into every routed request (see
+/// [`FingerprintClassifier`](crate::fingerprinting::FingerprintClassifier)); pass `None` to
+/// run without one. `middleware` runs a tower layer stack around forwarding (see
+/// [`Middleware`]); pass `None` to forward directly. `runtime` carries the state shared with the admin API; pass
+/// `RuntimeHandles::default()` when it is not served.
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     static_cfg: Arc<StaticConfig>,
     dynamic_cfg: SharedDynamicConfig,
     metrics: Arc<Metrics>,
     syn_probe: Option<SynProbe>,
+    classifier: Option<SharedClassifier>,
+    middleware: Option<Middleware>,
     watch_opts: WatchOptions,
     shutdown_tx: ShutdownSender,
     readiness: Readiness,
+    health_registry: Arc<HealthRegistry>,
+    runtime: RuntimeHandles,
 ) -> Result<()> {
     // Derive receiver from the sender so all clones share the same channel
     let shutdown_rx = shutdown_tx.subscribe();
 
     let rate_limiter = Arc::new(initial_rate_limiter(&dynamic_cfg.load()));
-    let client_pool = initial_client_pool(&static_cfg, &dynamic_cfg.load().backend_pool);
+    let discovery = BackendDiscovery::new().with_health(health_registry.clone());
+    let client_pool = {
+        let dynamic = dynamic_cfg.load();
+        initial_client_pool(
+            &static_cfg,
+            &dynamic.backend_pool,
+            &dynamic.backends,
+            &metrics,
+            &discovery,
+        )?
+    };
+    discovery.reconcile(&dynamic_cfg.load().backends, &client_pool, &metrics, &Handle::current());
 
-    let health_registry = Arc::new(HealthRegistry::new());
     let health_supervisor = Arc::new(HealthCheckSupervisor::new(health_registry.clone()));
     health_supervisor.reconcile(&dynamic_cfg.load().backends, &metrics, &Handle::current());
     let backend_selector = Arc::new(BackendSelector::new());
+    let circuit_breakers = Arc::new(CircuitBreakerRegistry::new());
 
     let idle_timeout = Duration::from_millis(static_cfg.timeout.proxy_idle_ms);
 
@@ -56,11 +92,7 @@ pub async fn run(
         .timer(TokioTimer::new())
         .keep_alive(static_cfg.timeout.keep_alive.enabled)
         .header_read_timeout(idle_timeout);
-    builder
-        .http2()
-        .timer(TokioTimer::new())
-        .keep_alive_interval(idle_timeout)
-        .keep_alive_timeout(idle_timeout.saturating_add(Duration::from_secs(1)));
+    apply_http2_settings(builder.http2().timer(TokioTimer::new()), &static_cfg.http2, idle_timeout);
 
     // Collect background service handles for ordered cooperative shutdown.
     let mut services: Vec<ServiceHandle> = Vec::new();
@@ -68,7 +100,10 @@ pub async fn run(
     // Build the cert resolver and load initial certs from the current dynamic config.
     // `None` when TLS is not configured (plain HTTP mode).
     let cert_resolver: Option<Arc<DynamicCertResolver>> = if let Some(tls) = &static_cfg.tls {
-        let resolver = Arc::new(DynamicCertResolver::new(tls.options.sni_strict));
+        let resolver = Arc::new(
+            DynamicCertResolver::new(tls.options.sni_strict)
+                .with_must_staple_failure(tls.ocsp.must_staple_failure),
+        );
         let report = resolver.update(&dynamic_cfg.load().domains, &metrics).await;
         if report.is_partial() {
             info!(
@@ -83,6 +118,11 @@ pub async fn run(
                  rejected until a cert is provided"
             );
         }
+        if tls.ocsp.enabled {
+            let stapler =
+                OcspStapler::new(Arc::clone(&resolver), tls.ocsp.clone(), Arc::clone(&metrics));
+            services.push(spawn_ocsp_stapler(stapler, shutdown_rx.clone()));
+        }
         Some(resolver)
     } else {
         None
@@ -97,11 +137,14 @@ pub async fn run(
 
     let shutdown_signal = Arc::new(AtomicUsize::new(0));
     let (connections_closed_tx, connections_closed_rx) = watch::channel(());
-    let connection_manager = Arc::new(ConnectionManager::new(
-        static_cfg.max_connections,
-        shutdown_signal.clone(),
-        connections_closed_tx.clone(),
-    ));
+    let connection_manager = Arc::new(
+        ConnectionManager::new(
+            static_cfg.max_connections,
+            shutdown_signal.clone(),
+            connections_closed_tx.clone(),
+        )
+        .with_max_connections_per_ip(static_cfg.max_connections_per_ip, &metrics),
+    );
 
     let mut sigterm = register_signal(signal::unix::SignalKind::terminate(), "SIGTERM")?;
     let mut sigint = register_signal(signal::unix::SignalKind::interrupt(), "SIGINT")?;
@@ -128,29 +171,90 @@ pub async fn run(
         info!("Config hot-reload disabled (set [reload].watch = true to enable)");
     }
 
-    let reload_mutex = Arc::new(tokio::sync::Mutex::new(()));
+    let RuntimeHandles {
+        connections,
+        config_generation,
+        reload_mutex,
+        log_levels,
+        fingerprint_stats,
+        synthetic,
+        drain,
+    } = runtime;
 
-    let backlog = static_cfg.listen.tcp_backlog;
-    let listeners: Vec<(SocketAddr, TcpListener)> = static_cfg
+    // `listen.addrs` use the global TLS and fingerprint settings; `[[listen.listeners]]` entries
+    // resolve their own overrides on top of them. The second field is the unix socket `mode`.
+    let endpoints: Vec<(Arc<ListenerContext>, Option<u32>)> = static_cfg
         .listen
         .addrs
         .iter()
         .map(|&addr| {
-            bind_listener(addr, backlog)
-                .map(|l| (addr, l))
-                .map_err(crate::error::ProxyError::Io)
+            let endpoint = ListenerContext {
+                addr: ListenAddr::Tcp(addr),
+                tls_acceptor: tls_acceptor.clone(),
+                fingerprint_config: static_cfg.fingerprint.clone(),
+                passthrough: None,
+                tenant: None,
+            };
+            (Arc::new(endpoint), None)
         })
-        .collect::<Result<_>>()?;
+        .chain(static_cfg.listen.listeners.iter().map(|listener| {
+            let endpoint = ListenerContext {
+                addr: listener.addr.clone(),
+                tls_acceptor: tls_acceptor
+                    .clone()
+                    .filter(|_| listener.tls != Some(false) && listener.passthrough.is_none()),
+                fingerprint_config: listener.fingerprint.resolve(&static_cfg.fingerprint),
+                passthrough: listener.passthrough.clone().map(Arc::new),
+                tenant: listener.tenant.as_deref().map(Arc::from),
+            };
+            (Arc::new(endpoint), listener.mode)
+        }))
+        .collect();
 
-    for (addr, _) in &listeners {
-        info!(?addr, "starting proxy");
+    let backlog = static_cfg.listen.tcp_backlog;
+    let acceptors = static_cfg.listen.acceptors.max(1);
+    let reuse_port = acceptors > 1 || static_cfg.listen.reuse_port;
+    let mut listeners: Vec<(Arc<ListenerContext>, BoundListener)> = Vec::new();
+    for (endpoint, mode) in &endpoints {
+        let loops = match &endpoint.addr {
+            ListenAddr::Tcp(addr) => {
+                for _ in 0..acceptors {
+                    let listener =
+                        bind_listener(*addr, backlog, reuse_port, &static_cfg.listen.tcp)
+                            .map_err(crate::error::ProxyError::Io)?;
+                    listeners.push((Arc::clone(endpoint), BoundListener::Tcp(listener)));
+                }
+                acceptors
+            }
+            ListenAddr::Unix(path) => {
+                let listener =
+                    bind_unix_listener(path, *mode).map_err(crate::error::ProxyError::Io)?;
+                listeners.push((Arc::clone(endpoint), BoundListener::Unix(listener)));
+                1
+            }
+        };
+        info!(
+            addr = %endpoint.addr,
+            tls = endpoint.tls_acceptor.is_some(),
+            passthrough = endpoint.passthrough.is_some(),
+            acceptors = loops,
+            "starting proxy"
+        );
+    }
+    let mut udp_forwarders = Vec::with_capacity(static_cfg.listen.udp.len());
+    for udp in &static_cfg.listen.udp {
+        let forwarder = UdpForwarder::bind(udp, Arc::clone(&metrics))
+            .await
+            .map_err(crate::error::ProxyError::Io)?;
+        info!(addr = %udp.addr, backend = %udp.backend, "starting UDP forwarder");
+        udp_forwarders.push(forwarder);
     }
 
+    let tracer = RequestTracer::from_config(&static_cfg.telemetry.tracing)?;
+
     let ctx = Arc::new(AcceptContext {
         dynamic_cfg: Arc::clone(&dynamic_cfg),
         rate_limiter: Arc::clone(&rate_limiter),
-        tls_acceptor,
-        fingerprint_config: static_cfg.fingerprint.clone(),
         keep_alive_config: static_cfg.timeout.keep_alive.clone(),
         metrics: Arc::clone(&metrics),
         client_pool: Arc::clone(&client_pool),
@@ -158,20 +262,66 @@ pub async fn run(
         syn_probe,
         health_registry: Arc::clone(&health_registry),
         backend_selector: Arc::clone(&backend_selector),
+        circuit_breakers,
         tls_handshake_timeout: Duration::from_secs(static_cfg.timeout.tls_handshake_secs),
         connection_handling_timeout: Duration::from_secs(
             static_cfg.timeout.connection_handling_secs,
         ),
+        upstream_connect_timeout: static_cfg
+            .timeout
+            .upstream_connect_ms
+            .map(Duration::from_millis),
         proxy_protocol: ResolvedProxyProtocol::resolve(static_cfg.listen.proxy_protocol),
+        client_cert_policy: static_cfg
+            .tls
+            .as_ref()
+            .filter(|tls| !matches!(tls.client_auth, ClientAuth::Disabled))
+            .map(|tls| tls.client_cert.clone()),
+        reject_ech_without_sni: static_cfg
+            .tls
+            .as_ref()
+            .is_some_and(|tls| tls.options.reject_ech_without_sni),
+        fingerprint_headers: Arc::new(FingerprintHeaderNames::from_config(
+            &static_cfg.fingerprint.headers,
+        )?),
+        header_signer: HeaderSigner::from_config(&static_cfg.fingerprint.signing)?.map(Arc::new),
+        classifier,
+        middleware,
+        response_cache: Arc::new(ResponseCache::new(&static_cfg.cache)),
+        concurrency_limits: Arc::new(ConcurrencyLimits::new()),
+        bandwidth_limits: Arc::new(BandwidthLimits::new()),
+        load_shedder: LoadShedder::from_config(&static_cfg.load_shedding, Arc::clone(&metrics))
+            .map(Arc::new),
+        request_ids: RequestIdGenerator::from_config(&static_cfg.request_id).map(Arc::new),
+        client_tracker: ClientTracker::from_config(
+            &static_cfg.client_tracking,
+            Arc::clone(&metrics),
+        )?
+        .map(Arc::new),
+        config_generation: config_generation.clone(),
+        connections,
+        access_log: AccessLogger::from_config(&static_cfg.access_log)?,
+        handshake_capture: HandshakeCapture::from_config(&static_cfg.handshake_capture)?,
+        log_levels,
+        tracer: tracer.clone(),
+        fingerprint_stats,
+        anomaly_detector: AnomalyDetector::from_config(
+            &static_cfg.telemetry.anomaly_detection,
+            Arc::clone(&metrics),
+        )?,
+        event_stream: EventStream::from_config(&static_cfg.event_stream, Arc::clone(&metrics))?,
+        synthetic,
+        bot_verifier: Arc::new(BotVerifier::new()),
+        tarpit: Arc::new(Tarpit::new()),
     });
 
-    // Spawn one accept task per listener.
+    // Spawn one accept task per listener socket (`listen.acceptors` per address).
     // Each new connection loads a fresh snapshot of DynamicConfig + rate-limiter so it
     // automatically picks up any hot-reloaded configuration.
     let mut accept_tasks = tokio::task::JoinSet::new();
-    for (addr, listener) in listeners {
+    for (endpoint, listener) in listeners {
         accept_tasks.spawn(accept_loop(
-            addr,
+            endpoint,
             listener,
             Arc::clone(&shutdown_signal),
             shutdown_rx.clone(),
@@ -179,6 +329,9 @@ pub async fn run(
             Arc::clone(&ctx),
         ));
     }
+    for forwarder in udp_forwarders {
+        accept_tasks.spawn(forwarder.run());
+    }
 
     warn_proxy_protocol_trust_gap(
         static_cfg.listen.proxy_protocol.mode,
@@ -209,8 +362,9 @@ pub async fn run(
     }
     info!("Proxy ready: accepting connections");
 
-    // Signal loop: SIGHUP forwards to the reload channel; SIGTERM/SIGINT trigger shutdown.
-    loop {
+    // Signal loop: SIGHUP forwards to the reload channel; SIGTERM/SIGINT and drain requests
+    // trigger shutdown.
+    let reason = loop {
         tokio::select! {
             _ = sighup.recv() => {
                 info!("Received SIGHUP, triggering config reload");
@@ -231,29 +385,36 @@ pub async fn run(
                         &reload_mutex,
                         &metrics,
                         &health_supervisor,
+                        &discovery,
                         cert_resolver.as_ref(),
+                        &config_generation,
                     )
                     .await;
                 }
             }
-            _ = sigterm.recv() => {
-                info!("Received SIGTERM, initiating graceful shutdown");
-                readiness.mark_not_ready();
-                health_supervisor.shutdown();
-                shutdown_signal.store(1, Ordering::Relaxed);
-                shutdown_tx.send(true).ok();
-                break;
-            }
-            _ = sigint.recv() => {
-                info!("Received SIGINT, initiating graceful shutdown");
-                readiness.mark_not_ready();
-                health_supervisor.shutdown();
-                shutdown_signal.store(1, Ordering::Relaxed);
-                shutdown_tx.send(true).ok();
-                break;
-            }
+            _ = sigterm.recv() => break "SIGTERM",
+            _ = sigint.recv() => break "SIGINT",
+            _ = drain.requested() => break "drain request",
+        }
+    };
+
+    info!(reason, "Initiating graceful shutdown");
+    readiness.mark_draining();
+    let drain_delay = Duration::from_secs(static_cfg.timeout.drain_delay_secs);
+    if !drain_delay.is_zero() {
+        // Keep accepting while load balancers (or a new process sharing the ports) notice the
+        // readiness flip; a second signal skips the rest of the delay.
+        info!("Not ready; closing listeners in {}s", static_cfg.timeout.drain_delay_secs);
+        tokio::select! {
+            _ = tokio::time::sleep(drain_delay) => {}
+            _ = sigterm.recv() => info!("Received SIGTERM, closing listeners now"),
+            _ = sigint.recv() => info!("Received SIGINT, closing listeners now"),
         }
     }
+    health_supervisor.shutdown();
+    discovery.shutdown();
+    shutdown_signal.store(1, Ordering::Relaxed);
+    shutdown_tx.send(true).ok();
 
     accept_tasks.abort_all();
     drop(accept_tasks);
@@ -276,6 +437,39 @@ pub async fn run(
         service.shutdown(Duration::from_secs(5)).await;
     }
 
+    // Export the request spans still queued; the exporter makes blocking HTTP calls.
+    if let Err(e) = tokio::task::spawn_blocking(move || tracer.shutdown()).await {
+        warn!(error = %e, "Request span flush task failed");
+    }
+
     info!("Proxy server stopped");
     Ok(())
 }
+
+/// `[http2]` on the client-facing connection builder. Keep-alive pings default to every
+/// `idle_timeout`, each waiting a second longer for its acknowledgement.
+fn apply_http2_settings(
+    builder: &mut Http2Builder<'_, TokioExecutor>,
+    http2: &Http2Config,
+    idle_timeout: Duration,
+) {
+    let interval = http2
+        .keep_alive_interval_ms
+        .map_or(idle_timeout, Duration::from_millis);
+    let timeout = http2
+        .keep_alive_timeout_ms
+        .map_or(interval.saturating_add(Duration::from_secs(1)), Duration::from_millis);
+    builder
+        .keep_alive_interval((!interval.is_zero()).then_some(interval))
+        .keep_alive_timeout(timeout)
+        .initial_stream_window_size(http2.initial_stream_window_size)
+        .initial_connection_window_size(http2.initial_connection_window_size)
+        .max_frame_size(http2.max_frame_size);
+    if http2.adaptive_window {
+        builder.adaptive_window(true);
+    }
+    // Unset keeps hyper's limit; `max_concurrent_streams(None)` would remove it.
+    if let Some(streams) = http2.max_concurrent_streams {
+        builder.max_concurrent_streams(streams);
+    }
+}
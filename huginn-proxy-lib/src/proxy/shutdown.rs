@@ -3,7 +3,9 @@
 //! ## Shutdown sequence
 //!
 //! ```text
-//! SIGTERM / SIGINT
+//! SIGTERM / SIGINT / DrainTrigger::request (POST /admin/drain)
+//!   │
+//!   ├─▶ readiness → draining, wait timeout.drain_delay_secs
 //!   │
 //!   └─▶ shutdown_tx.send(true)          (server.rs)
 //!         │
@@ -16,6 +18,9 @@
 //!         ├─▶ ebpf-reconnect task       (ebpf.rs)
 //!         │     shutdown_rx.wait_for(true) → break
 //!         │
+//!         ├─▶ ocsp-stapling task        (tls/ocsp/mod.rs)
+//!         │     shutdown_rx.wait_for(true) → break
+//!         │
 //!         └─▶ wait_for_drain            (server.rs)
 //!               waits for all active HTTP connections to finish,
 //!               then ServiceHandle::shutdown() awaits each background task
@@ -32,6 +37,7 @@ use std::sync::Arc;
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
 /// Canonical shutdown signal type, adapted from Pingora's `ShutdownWatch`.
@@ -52,12 +58,40 @@ pub fn shutdown_channel() -> (ShutdownSender, ShutdownWatch) {
     watch::channel(false)
 }
 
+/// Asks a running proxy to drain and stop, like SIGTERM, from outside its signal loop (the
+/// admin API's `POST /admin/drain`). Clones share the request.
+#[derive(Clone, Default)]
+pub struct DrainTrigger(CancellationToken);
+
+impl DrainTrigger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request the drain. Returns `false` when it was already requested.
+    pub fn request(&self) -> bool {
+        let first = !self.0.is_cancelled();
+        self.0.cancel();
+        first
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.0.is_cancelled()
+    }
+
+    /// Resolves once the drain is requested.
+    pub async fn requested(&self) {
+        self.0.cancelled().await;
+    }
+}
+
 /// Identifies each background service for logging during shutdown.
 pub enum ServiceName {
     CertReload,
     ConfigWatcher,
     EbpfReconnect,
     MetricsServer,
+    OcspStapling,
 }
 
 impl fmt::Display for ServiceName {
@@ -67,6 +101,7 @@ impl fmt::Display for ServiceName {
             Self::ConfigWatcher => "config-watcher",
             Self::EbpfReconnect => "ebpf-reconnect",
             Self::MetricsServer => "metrics-server",
+            Self::OcspStapling => "ocsp-stapling",
         })
     }
 }
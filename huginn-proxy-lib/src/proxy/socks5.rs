@@ -0,0 +1,223 @@
+//! SOCKS5 client (RFC 1928, username/password authentication per RFC 1929) for backends with a
+//! `proxy` table.
+//!
+//! [`TrackedConnector`](crate::proxy::pool_connector::TrackedConnector) dials the proxy with its
+//! usual TCP options and timeout, then asks it for a `CONNECT` to the backend; the stream it
+//! hands to hyper (or to the TLS layer) is the tunnel. Only the `CONNECT` command is used.
+
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+
+use http::Uri;
+use hyper_util::client::legacy::connect::HttpConnector;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tower_service::Service;
+
+use crate::config::BackendProxyConfig;
+
+const VERSION: u8 = 0x05;
+const NO_AUTH: u8 = 0x00;
+const USERNAME_PASSWORD: u8 = 0x02;
+const NO_ACCEPTABLE_METHOD: u8 = 0xFF;
+const AUTH_VERSION: u8 = 0x01;
+const CMD_CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+/// A backend's SOCKS5 proxy, built from its `proxy` table. No `Debug`: it holds the password.
+#[derive(Clone)]
+pub struct Socks5Proxy {
+    /// `http://host:port` of the proxy, the form [`HttpConnector`] dials.
+    server: Uri,
+    /// Send backend hostnames to the proxy (`socks5h://`) instead of resolving them here.
+    remote_dns: bool,
+    credentials: Option<(String, String)>,
+}
+
+/// Destination of a `CONNECT` request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetAddr {
+    Ip(SocketAddr),
+    /// Hostname and port, resolved by the proxy.
+    Domain(String, u16),
+}
+
+impl Socks5Proxy {
+    /// `None` when `config.url` is not a SOCKS5 URL (rejected by config validation).
+    pub fn from_config(config: &BackendProxyConfig) -> Option<Self> {
+        let (server, remote_dns) = config.server()?;
+        let server = format!("http://{server}").parse().ok()?;
+        let credentials = config
+            .credentials()
+            .map(|(username, password)| (username.to_string(), password.to_string()));
+        Some(Self { server, remote_dns, credentials })
+    }
+
+    /// Open a tunnel to `host:port` (`host` without IPv6 brackets): dial the proxy through
+    /// `connector`, then run the SOCKS5 handshake on the connection.
+    pub async fn connect(
+        &self,
+        mut connector: HttpConnector,
+        host: &str,
+        port: u16,
+    ) -> io::Result<TcpStream> {
+        let target = match host.parse::<IpAddr>() {
+            Ok(ip) => TargetAddr::Ip(SocketAddr::new(ip, port)),
+            Err(_) if self.remote_dns => TargetAddr::Domain(host.to_string(), port),
+            Err(_) => tokio::net::lookup_host((host, port))
+                .await?
+                .next()
+                .map(TargetAddr::Ip)
+                .ok_or_else(|| io::Error::other(format!("no address found for {host}")))?,
+        };
+        let mut stream = connector
+            .call(self.server.clone())
+            .await
+            .map_err(|e| io::Error::other(format!("SOCKS5 proxy {}: {e}", self.server)))?
+            .into_inner();
+        let auth = self
+            .credentials
+            .as_ref()
+            .map(|(username, password)| (username.as_str(), password.as_str()));
+        handshake(&mut stream, &target, auth).await?;
+        Ok(stream)
+    }
+}
+
+/// Run the client side of a SOCKS5 `CONNECT` to `target` on `stream`, authenticating with
+/// `auth` (username, password) when the proxy asks for it. On success the stream carries the
+/// tunnel.
+pub async fn handshake<S>(
+    stream: &mut S,
+    target: &TargetAddr,
+    auth: Option<(&str, &str)>,
+) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let greeting: &[u8] = match auth {
+        Some(_) => &[VERSION, 2, NO_AUTH, USERNAME_PASSWORD],
+        None => &[VERSION, 1, NO_AUTH],
+    };
+    stream.write_all(greeting).await?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[0] != VERSION {
+        return Err(io::Error::other("SOCKS5 proxy answered with another protocol version"));
+    }
+    match (choice[1], auth) {
+        (NO_AUTH, _) => {}
+        (USERNAME_PASSWORD, Some((username, password))) => {
+            authenticate(stream, username, password).await?;
+        }
+        (NO_ACCEPTABLE_METHOD, _) | (USERNAME_PASSWORD, None) => {
+            return Err(io::Error::other(
+                "SOCKS5 proxy accepts none of the offered authentication methods",
+            ));
+        }
+        (method, _) => {
+            return Err(io::Error::other(format!(
+                "SOCKS5 proxy chose authentication method {method:#04x}, which was not offered"
+            )));
+        }
+    }
+
+    let mut request = vec![VERSION, CMD_CONNECT, 0x00];
+    let port = match target {
+        TargetAddr::Ip(SocketAddr::V4(addr)) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        TargetAddr::Ip(SocketAddr::V6(addr)) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        TargetAddr::Domain(host, port) => {
+            let len = u8::try_from(host.len())
+                .ok()
+                .filter(|len| *len > 0)
+                .ok_or_else(|| {
+                    io::Error::other(format!("SOCKS5 target host '{host}' must be 1-255 bytes"))
+                })?;
+            request.push(ATYP_DOMAIN);
+            request.push(len);
+            request.extend_from_slice(host.as_bytes());
+            *port
+        }
+    };
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(io::Error::other("SOCKS5 proxy answered with another protocol version"));
+    }
+    if reply[1] != 0x00 {
+        return Err(io::Error::other(format!(
+            "SOCKS5 CONNECT failed: {}",
+            reply_message(reply[1])
+        )));
+    }
+    // The bound address is of no use to the client, but must be consumed.
+    let bound_len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            usize::from(len[0])
+        }
+        atyp => {
+            return Err(io::Error::other(format!(
+                "SOCKS5 proxy replied with unknown address type {atyp:#04x}"
+            )));
+        }
+    };
+    let mut bound = vec![0u8; bound_len.saturating_add(2)];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+/// Username/password subnegotiation (RFC 1929).
+async fn authenticate<S>(stream: &mut S, username: &str, password: &str) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let field_len = |field: &str, value: &str| {
+        u8::try_from(value.len())
+            .ok()
+            .filter(|len| *len > 0)
+            .ok_or_else(|| io::Error::other(format!("SOCKS5 {field} must be 1-255 bytes")))
+    };
+    let mut request = vec![AUTH_VERSION, field_len("username", username)?];
+    request.extend_from_slice(username.as_bytes());
+    request.push(field_len("password", password)?);
+    request.extend_from_slice(password.as_bytes());
+    stream.write_all(&request).await?;
+
+    let mut status = [0u8; 2];
+    stream.read_exact(&mut status).await?;
+    if status[1] != 0x00 {
+        return Err(io::Error::other("SOCKS5 proxy rejected the username or password"));
+    }
+    Ok(())
+}
+
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        0x01 => "general SOCKS server failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
@@ -0,0 +1,398 @@
+//! Files served from a route's `serve_static` directory.
+//!
+//! The path after the route's prefix is percent-decoded and checked segment by segment before it
+//! touches the filesystem, then the resolved file is canonicalized and must still lie under the
+//! canonical `root`, so neither `..` nor symlinks lead out of it. Bodies are streamed from the
+//! file in chunks; only one byte range per request is served.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use http::header::{
+    ACCEPT_RANGES, ALLOW, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+    IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+};
+use http::{HeaderMap, HeaderValue, Method, Response, StatusCode};
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame, SizeHint};
+use tokio::io::{AsyncRead, AsyncSeekExt, ReadBuf};
+use tracing::warn;
+
+use crate::config::ServeStaticConfig;
+use crate::utils::http::{empty_body, percent_decode_utf8, BoxError, RespBody};
+
+/// Largest chunk read from a file per body frame.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Byte range requested by a `Range` header, against a file of a known length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// No usable single range: serve the whole file.
+    Full,
+    /// First and last byte offsets, inclusive.
+    Partial(u64, u64),
+    /// The range lies past the end of the file (`416`).
+    Unsatisfiable,
+}
+
+/// Answer a request for `path` (the request path) on a route with `prefix` from the directory of
+/// `config`.
+pub async fn serve_static(
+    config: &ServeStaticConfig,
+    method: &Method,
+    path: &str,
+    prefix: &str,
+    headers: &HeaderMap,
+) -> Response<RespBody> {
+    if method != Method::GET && method != Method::HEAD {
+        let mut response = status_response(StatusCode::METHOD_NOT_ALLOWED);
+        response
+            .headers_mut()
+            .insert(ALLOW, HeaderValue::from_static("GET, HEAD"));
+        return response;
+    }
+    let relative = path.strip_prefix(prefix).unwrap_or(path);
+    let Some(relative) = percent_decode_utf8(relative) else {
+        return status_response(StatusCode::BAD_REQUEST);
+    };
+    let Some(relative) = relative_path(&relative, config.dotfiles) else {
+        return status_response(StatusCode::NOT_FOUND);
+    };
+    match open(config, &relative).await {
+        Ok(Some((file, metadata, target))) => {
+            file_response(config, method, headers, file, &metadata, content_type(&target)).await
+        }
+        Ok(None) => status_response(StatusCode::NOT_FOUND),
+        Err(e) => {
+            warn!(
+                root = %config.root,
+                path = %relative.display(),
+                error = %e,
+                "serve_static failed"
+            );
+            status_response(match e.kind() {
+                io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            })
+        }
+    }
+}
+
+/// Relative filesystem path for the decoded request path, or `None` when a segment is `..`,
+/// contains a backslash or NUL byte, or (without `dotfiles`) starts with `.`. Empty and `.`
+/// segments are skipped; a trailing `/` keeps the path naming a directory.
+pub fn relative_path(decoded: &str, dotfiles: bool) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for segment in decoded.split('/') {
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+        if segment == ".."
+            || segment.contains(['\\', '\0'])
+            || (!dotfiles && segment.starts_with('.'))
+        {
+            return None;
+        }
+        relative.push(segment);
+    }
+    Some(relative)
+}
+
+/// Open the file `relative` names under `root`, or the `index` file of the directory it names,
+/// with its metadata and path. `Ok(None)` when there is no such file or it resolves outside
+/// `root`.
+async fn open(
+    config: &ServeStaticConfig,
+    relative: &Path,
+) -> io::Result<Option<(tokio::fs::File, std::fs::Metadata, PathBuf)>> {
+    let root = tokio::fs::canonicalize(&config.root).await?;
+    let mut target = root.join(relative);
+    let not_found = |e: io::Error| match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::NotADirectory => Ok(None),
+        _ => Err(e),
+    };
+    let mut metadata = match tokio::fs::metadata(&target).await {
+        Ok(metadata) => metadata,
+        Err(e) => return not_found(e),
+    };
+    if metadata.is_dir() {
+        if config.index.is_empty() {
+            return Ok(None);
+        }
+        target.push(&config.index);
+        metadata = match tokio::fs::metadata(&target).await {
+            Ok(metadata) => metadata,
+            Err(e) => return not_found(e),
+        };
+    }
+    if !metadata.is_file() {
+        return Ok(None);
+    }
+    let canonical = tokio::fs::canonicalize(&target).await?;
+    if !canonical.starts_with(&root) {
+        return Ok(None);
+    }
+    let file = tokio::fs::File::open(&canonical).await?;
+    Ok(Some((file, metadata, target)))
+}
+
+async fn file_response(
+    config: &ServeStaticConfig,
+    method: &Method,
+    headers: &HeaderMap,
+    mut file: tokio::fs::File,
+    metadata: &std::fs::Metadata,
+    content_type: &'static str,
+) -> Response<RespBody> {
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = entity_tag(len, modified);
+    let last_modified = modified.map(httpdate::fmt_http_date);
+
+    let mut response = status_response(StatusCode::OK);
+    let response_headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response_headers.insert(ETAG, value);
+    }
+    if let Some(value) = last_modified
+        .as_deref()
+        .and_then(|v| HeaderValue::from_str(v).ok())
+    {
+        response_headers.insert(LAST_MODIFIED, value);
+    }
+    if let Some(value) = config
+        .cache_control
+        .as_deref()
+        .and_then(|v| HeaderValue::from_str(v).ok())
+    {
+        response_headers.insert(CACHE_CONTROL, value);
+    }
+    if is_not_modified(headers, &etag, modified) {
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        return response;
+    }
+
+    let range = match headers.get(RANGE).and_then(|v| v.to_str().ok()) {
+        Some(range) if if_range_matches(headers, &etag, last_modified.as_deref()) => {
+            parse_range(range, len)
+        }
+        _ => ByteRange::Full,
+    };
+    let response_headers = response.headers_mut();
+    response_headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response_headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    let (start, count) = match range {
+        ByteRange::Full => (0, len),
+        ByteRange::Partial(first, last) => {
+            *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+            if let Ok(value) = HeaderValue::from_str(&format!("bytes {first}-{last}/{len}")) {
+                response.headers_mut().insert(CONTENT_RANGE, value);
+            }
+            (first, last.saturating_sub(first).saturating_add(1))
+        }
+        ByteRange::Unsatisfiable => {
+            *response.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+            if let Ok(value) = HeaderValue::from_str(&format!("bytes */{len}")) {
+                response.headers_mut().insert(CONTENT_RANGE, value);
+            }
+            response.headers_mut().remove(CONTENT_TYPE);
+            return response;
+        }
+    };
+    response
+        .headers_mut()
+        .insert(CONTENT_LENGTH, HeaderValue::from(count));
+    if method == Method::HEAD || count == 0 {
+        return response;
+    }
+    if start > 0 {
+        if let Err(e) = file.seek(io::SeekFrom::Start(start)).await {
+            warn!(root = %config.root, error = %e, "serve_static seek failed");
+            return status_response(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+    let chunk = usize::try_from(count)
+        .unwrap_or(CHUNK_BYTES)
+        .min(CHUNK_BYTES);
+    let body = FileBody { file, remaining: count, buf: vec![0; chunk] };
+    let (parts, _) = response.into_parts();
+    Response::from_parts(parts, body.boxed())
+}
+
+/// `"<mtime>-<len>"` in hex, like common static file servers.
+fn entity_tag(len: u64, modified: Option<SystemTime>) -> String {
+    let mtime = modified
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    format!("\"{mtime:x}-{len:x}\"")
+}
+
+/// `If-None-Match` (weak comparison, `*` included) or, without it, `If-Modified-Since`.
+fn is_not_modified(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    let if_none_match: Vec<&str> = headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect();
+    if !if_none_match.is_empty() {
+        return if_none_match
+            .iter()
+            .any(|&tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag);
+    }
+    let since = headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok());
+    match (since, modified) {
+        // HTTP dates have second precision.
+        (Some(since), Some(modified)) => {
+            let secs = |t: SystemTime| t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            secs(modified) <= secs(since)
+        }
+        _ => false,
+    }
+}
+
+/// `true` without `If-Range`, or when it names the current `ETag` or `Last-Modified` exactly.
+fn if_range_matches(headers: &HeaderMap, etag: &str, last_modified: Option<&str>) -> bool {
+    match headers.get(IF_RANGE).and_then(|v| v.to_str().ok()) {
+        None => true,
+        Some(value) => value == etag || last_modified == Some(value),
+    }
+}
+
+/// Parse a `Range` header against a file of `len` bytes. Only a single `bytes` range is served;
+/// several ranges or a malformed header fall back to the whole file.
+pub fn parse_range(header: &str, len: u64) -> ByteRange {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let last_byte = len.checked_sub(1);
+    match (first.trim(), last.trim()) {
+        ("", suffix) => match (suffix.parse::<u64>(), last_byte) {
+            (Ok(0), _) | (Ok(_), None) => ByteRange::Unsatisfiable,
+            (Ok(suffix), Some(last_byte)) => {
+                ByteRange::Partial(len.saturating_sub(suffix), last_byte)
+            }
+            (Err(_), _) => ByteRange::Full,
+        },
+        (first, last) => {
+            let Ok(first) = first.parse::<u64>() else {
+                return ByteRange::Full;
+            };
+            let last = match last {
+                "" => None,
+                last => match last.parse::<u64>() {
+                    Ok(last) if last >= first => Some(last),
+                    _ => return ByteRange::Full,
+                },
+            };
+            match last_byte {
+                Some(last_byte) if first <= last_byte => {
+                    ByteRange::Partial(first, last.map_or(last_byte, |l| l.min(last_byte)))
+                }
+                _ => ByteRange::Unsatisfiable,
+            }
+        }
+    }
+}
+
+/// `Content-Type` for a file, from its extension; `application/octet-stream` when unknown.
+pub fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+fn status_response(status: StatusCode) -> Response<RespBody> {
+    let mut response = Response::new(empty_body());
+    *response.status_mut() = status;
+    response
+}
+
+/// Body streaming `remaining` bytes of a file from its current position.
+struct FileBody {
+    file: tokio::fs::File,
+    remaining: u64,
+    buf: Vec<u8>,
+}
+
+impl Body for FileBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if this.remaining == 0 {
+            return Poll::Ready(None);
+        }
+        let want = usize::try_from(this.remaining)
+            .unwrap_or(usize::MAX)
+            .min(this.buf.len());
+        let mut read_buf = ReadBuf::new(this.buf.get_mut(..want).unwrap_or_default());
+        if let Err(e) = ready!(Pin::new(&mut this.file).poll_read(cx, &mut read_buf)) {
+            return Poll::Ready(Some(Err(e.into())));
+        }
+        let filled = read_buf.filled();
+        if filled.is_empty() {
+            return Poll::Ready(Some(Err("file shrank while it was being served".into())));
+        }
+        this.remaining = this
+            .remaining
+            .saturating_sub(u64::try_from(filled.len()).unwrap_or(u64::MAX));
+        Poll::Ready(Some(Ok(Frame::data(Bytes::copy_from_slice(filled)))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
@@ -1,7 +1,17 @@
+//! Responses built by the proxy itself: bare error responses for the transport layer, and the
+//! per-route `synthetic` responses (maintenance pages, health stubs) with their runtime switches.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::config::SyntheticResponseConfig;
 use crate::error::{ProxyError, ProxyResult};
-use crate::utils::http::{empty_body, RespBody};
+use crate::proxy::http_result::HttpError;
+use crate::utils::http::{empty_body, full_body, RespBody};
+use http::header::{HeaderName, HeaderValue, CONNECTION, CONTENT_TYPE};
 use http::StatusCode;
 use hyper::Response;
+use tracing::warn;
 
 /// Build HTTP response with status code of 4xx and 5xx
 pub(crate) fn synthetic_error_response(status_code: StatusCode) -> ProxyResult<Response<RespBody>> {
@@ -11,3 +21,96 @@ pub(crate) fn synthetic_error_response(status_code: StatusCode) -> ProxyResult<R
         .map_err(|e| ProxyError::Http(format!("Failed to build error response: {e}")))?;
     Ok(res)
 }
+
+/// Response for a request that failed with `error`. Errors that leave the connection unusable
+/// (see [`HttpError::closes_connection`]) also ask the client to close it.
+pub(crate) fn http_error_response(error: &HttpError) -> Response<RespBody> {
+    let mut response = match synthetic_error_response(StatusCode::from(error.clone())) {
+        Ok(resp) => resp,
+        Err(e) => crate::utils::http::json_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Failed to create error response: {e}"),
+        ),
+    };
+    if error.closes_connection() {
+        response
+            .headers_mut()
+            .insert(CONNECTION, HeaderValue::from_static("close"));
+    }
+    response
+}
+
+/// Build the response of a route's `synthetic` block. A `body_file` that cannot be read any more
+/// (it was checked when the config loaded) is logged and served as an empty body.
+pub async fn synthetic_route_response(config: &SyntheticResponseConfig) -> Response<RespBody> {
+    let body = match (&config.body, &config.body_file) {
+        (Some(body), _) => full_body(body.clone()),
+        (None, Some(path)) => match tokio::fs::read(path).await {
+            Ok(bytes) => full_body(bytes),
+            Err(e) => {
+                warn!(path = %path, error = %e, "synthetic.body_file unreadable, serving empty body");
+                empty_body()
+            }
+        },
+        (None, None) => empty_body(),
+    };
+    let mut response = Response::new(body);
+    *response.status_mut() =
+        StatusCode::from_u16(config.status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+    let headers = response.headers_mut();
+    if let Some(content_type) = config
+        .effective_content_type()
+        .and_then(|v| HeaderValue::from_str(v).ok())
+    {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    for header in &config.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(header.name.as_bytes()),
+            HeaderValue::from_str(header.value.expose()),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    response
+}
+
+/// Runtime on/off overrides of the routes' `synthetic` responses, set through the admin API.
+///
+/// Keyed by domain label and route prefix. An override outlives config reloads as long as the
+/// route keeps its `synthetic` block; it is lost on restart, like backend drain state.
+#[derive(Clone, Default)]
+pub struct SyntheticSwitches {
+    overrides: Arc<RwLock<HashMap<(String, String), bool>>>,
+}
+
+impl SyntheticSwitches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the `synthetic` response of `prefix` on `domain` is served: the runtime override
+    /// when there is one, `config.enabled` otherwise.
+    pub fn is_active(&self, domain: &str, prefix: &str, config: &SyntheticResponseConfig) -> bool {
+        self.get(domain, prefix).unwrap_or(config.enabled)
+    }
+
+    /// Runtime override of `prefix` on `domain`, if any.
+    pub fn get(&self, domain: &str, prefix: &str) -> Option<bool> {
+        self.overrides
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(domain.to_string(), prefix.to_string()))
+            .copied()
+    }
+
+    /// Force the response on or off (`Some`), or go back to the config's `enabled` (`None`).
+    pub fn set(&self, domain: &str, prefix: &str, enabled: Option<bool>) {
+        let key = (domain.to_string(), prefix.to_string());
+        let mut overrides = self.overrides.write().unwrap_or_else(|e| e.into_inner());
+        match enabled {
+            Some(enabled) => overrides.insert(key, enabled),
+            None => overrides.remove(&key),
+        };
+    }
+}
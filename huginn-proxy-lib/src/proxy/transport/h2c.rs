@@ -0,0 +1,226 @@
+//! HTTP/1.1 `Upgrade: h2c` on plaintext listeners (RFC 7540 §3.2).
+//!
+//! hyper serves prior-knowledge HTTP/2 but has no server side for the upgrade. A request asking
+//! for it is answered `101 Switching Protocols` by the plain connection handler, which then serves
+//! HTTP/2 on the upgraded connection. The upgrade request itself is stream 1 of that connection
+//! and must be answered there: [`H2cUpgradeStream`] hands it to hyper as a `HEADERS` frame
+//! ([`stream_one_frame`]) right after the client's connection preface and first `SETTINGS`.
+//!
+//! Only requests without a body are upgraded; others are served over HTTP/1.1, as the RFC
+//! allows. The `HTTP2-Settings` header is not applied: the client repeats its settings in the
+//! `SETTINGS` frame of its preface.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::Bytes;
+use http::header::{CONNECTION, CONTENT_LENGTH, HOST, TE, TRANSFER_ENCODING, UPGRADE};
+use http::{HeaderName, HeaderValue, Request, Response, StatusCode, Version};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::proxy::websocket::has_token;
+use crate::utils::http::{empty_body, RespBody};
+
+/// Client connection preface of HTTP/2 (RFC 9113 §3.4).
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+/// HTTP/2 frame header: 3 length, 1 type, 1 flags and 4 stream id bytes.
+const FRAME_HEADER_LEN: usize = 9;
+/// Default `SETTINGS_MAX_FRAME_SIZE`; larger frames are not injected or expected here.
+const MAX_FRAME_LEN: usize = 16_384;
+const FRAME_HEADERS: u8 = 0x1;
+const FRAME_SETTINGS: u8 = 0x4;
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+
+static HTTP2_SETTINGS: HeaderName = HeaderName::from_static("http2-settings");
+
+/// `true` for an HTTP/1.1 request without a body asking to upgrade to `h2c`: `Upgrade` names
+/// `h2c`, `Connection` lists `upgrade` and `http2-settings`, and one `HTTP2-Settings` is sent.
+pub fn is_h2c_upgrade<B>(req: &Request<B>) -> bool {
+    let headers = req.headers();
+    let bodyless = !headers.contains_key(TRANSFER_ENCODING)
+        && headers
+            .get(CONTENT_LENGTH)
+            .is_none_or(|v| v.as_bytes() == b"0");
+    req.version() == Version::HTTP_11
+        && bodyless
+        && has_token(headers, UPGRADE, "h2c")
+        && has_token(headers, CONNECTION, "upgrade")
+        && has_token(headers, CONNECTION, "http2-settings")
+        && headers.get_all(&HTTP2_SETTINGS).iter().count() == 1
+}
+
+/// `101 Switching Protocols` accepting the upgrade.
+pub(crate) fn switching_protocols() -> Response<RespBody> {
+    let mut response = Response::new(empty_body());
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(CONNECTION, HeaderValue::from_static("Upgrade"));
+    headers.insert(UPGRADE, HeaderValue::from_static("h2c"));
+    response
+}
+
+/// The upgrade request as the `HEADERS` frame of stream 1 (`END_STREAM`, `END_HEADERS`), or
+/// `None` when it has no `Host` or its header block does not fit one frame.
+///
+/// Fields are HPACK literals without indexing, so the decoder's dynamic table is left as the
+/// client expects it. Connection-specific headers (RFC 9113 §8.2.2) are dropped.
+pub fn stream_one_frame<B>(req: &Request<B>) -> Option<Bytes> {
+    let authority = req.headers().get(HOST)?;
+    let path = req.uri().path_and_query().map_or("/", |pq| pq.as_str());
+
+    let mut block = Vec::new();
+    literal(&mut block, b":method", req.method().as_str().as_bytes());
+    literal(&mut block, b":scheme", b"http");
+    literal(&mut block, b":authority", authority.as_bytes());
+    literal(&mut block, b":path", path.as_bytes());
+    let connection_specific = |name: &HeaderName| {
+        *name == HOST
+            || *name == CONNECTION
+            || *name == UPGRADE
+            || *name == TRANSFER_ENCODING
+            || *name == TE
+            || *name == HTTP2_SETTINGS
+            || matches!(name.as_str(), "keep-alive" | "proxy-connection")
+    };
+    for (name, value) in req.headers() {
+        if !connection_specific(name) {
+            literal(&mut block, name.as_str().as_bytes(), value.as_bytes());
+        }
+    }
+    if block.len() > MAX_FRAME_LEN {
+        return None;
+    }
+
+    let length = u32::try_from(block.len()).ok()?.to_be_bytes();
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN.saturating_add(block.len()));
+    frame.extend_from_slice(&length[1..]);
+    frame.push(FRAME_HEADERS);
+    frame.push(FLAG_END_STREAM | FLAG_END_HEADERS);
+    frame.extend_from_slice(&1u32.to_be_bytes());
+    frame.extend_from_slice(&block);
+    Some(Bytes::from(frame))
+}
+
+/// HPACK literal header field without indexing, new name, no Huffman coding (RFC 7541 §6.2.2).
+fn literal(block: &mut Vec<u8>, name: &[u8], value: &[u8]) {
+    block.push(0x00);
+    for string in [name, value] {
+        integer(block, string.len());
+        block.extend_from_slice(string);
+    }
+}
+
+/// HPACK integer with a 7-bit prefix and the Huffman bit clear (RFC 7541 §5.1).
+fn integer(block: &mut Vec<u8>, value: usize) {
+    const PREFIX_MAX: usize = 0x7f;
+    if value < PREFIX_MAX {
+        block.push(value as u8);
+        return;
+    }
+    block.push(PREFIX_MAX as u8);
+    let mut rest = value.saturating_sub(PREFIX_MAX);
+    while rest >= 0x80 {
+        block.push((rest & 0x7f) as u8 | 0x80);
+        rest = rest.wrapping_shr(7);
+    }
+    block.push(rest as u8);
+}
+
+/// Upgraded connection that inserts the stream 1 `HEADERS` frame after the client's preface and
+/// first `SETTINGS` frame. A connection not starting that way is passed through unchanged, and
+/// hyper rejects it.
+pub struct H2cUpgradeStream<S> {
+    inner: S,
+    /// Bytes read ahead until the first `SETTINGS` frame is complete.
+    head: Vec<u8>,
+    /// Bytes to return before reading on.
+    pending: Bytes,
+    /// The frame to insert; `None` once inserted or given up.
+    frame: Option<Bytes>,
+}
+
+impl<S> H2cUpgradeStream<S> {
+    pub fn new(inner: S, frame: Bytes) -> Self {
+        Self { inner, head: Vec::new(), pending: Bytes::new(), frame: Some(frame) }
+    }
+
+    /// Length `head` must reach before the frame is inserted, or `None` when the connection does
+    /// not start with the preface and a `SETTINGS` frame.
+    fn head_target(&self) -> Option<usize> {
+        let start = PREFACE.len().saturating_add(FRAME_HEADER_LEN);
+        let Some(header) = self.head.get(PREFACE.len()..start) else {
+            return PREFACE
+                .starts_with(&self.head[..self.head.len().min(PREFACE.len())])
+                .then_some(start);
+        };
+        let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        (self.head.starts_with(PREFACE) && header[3] == FRAME_SETTINGS && length <= MAX_FRAME_LEN)
+            .then(|| start.saturating_add(length))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for H2cUpgradeStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if !this.pending.is_empty() {
+                let n = buf.remaining().min(this.pending.len());
+                buf.put_slice(&this.pending.split_to(n));
+                return Poll::Ready(Ok(()));
+            }
+            if this.frame.is_none() {
+                return Pin::new(&mut this.inner).poll_read(cx, buf);
+            }
+            let Some(target) = this.head_target() else {
+                this.frame = None;
+                this.pending = Bytes::from(std::mem::take(&mut this.head));
+                continue;
+            };
+            if this.head.len() >= target {
+                let mut head = std::mem::take(&mut this.head);
+                head.extend_from_slice(&this.frame.take().unwrap_or_default());
+                this.pending = Bytes::from(head);
+                continue;
+            }
+
+            let mut chunk = [0u8; 512];
+            let want = target.saturating_sub(this.head.len()).min(chunk.len());
+            let mut read = ReadBuf::new(&mut chunk[..want]);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read))?;
+            if read.filled().is_empty() {
+                // Closed before the first SETTINGS frame: hand over what was read, then EOF.
+                this.frame = None;
+                this.pending = Bytes::from(std::mem::take(&mut this.head));
+                if this.pending.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                continue;
+            }
+            this.head.extend_from_slice(read.filled());
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for H2cUpgradeStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
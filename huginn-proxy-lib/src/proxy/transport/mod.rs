@@ -1,6 +1,11 @@
+pub mod h2c;
+pub mod passthrough;
 pub mod plain;
+pub mod splice;
 mod timeout_helper;
 pub mod tls;
 
+pub use passthrough::{handle_passthrough_connection, PassthroughConnectionConfig};
 pub use plain::{handle_plain_connection, PlainConnectionConfig};
+pub use splice::{relay, RawTcpStream, Relayed};
 pub use tls::{handle_tls_connection, TlsConnectionConfig};
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use crate::config::PassthroughConfig;
+use crate::fingerprinting::{read_client_hello, TcpObservation};
+use crate::proxy::connection::{ConnectionContext, ConnectionTls, Ja4Set, TrackedConnection};
+use crate::proxy::transport::splice::{relay, RawTcpStream, Relayed};
+use crate::telemetry::metrics::values;
+use crate::telemetry::{HandshakeCapture, HandshakeRecord, Metrics};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Configuration for relaying connections of a TLS passthrough listener
+pub struct PassthroughConnectionConfig {
+    /// The listener's `passthrough` table: SNI routes and default backend.
+    pub passthrough: Arc<PassthroughConfig>,
+    /// `fingerprint.tls_enabled` of the listener; the JA4 is only logged and tracked when set.
+    pub tls_fingerprint: bool,
+    pub metrics: Arc<Metrics>,
+    /// Bound on reading the ClientHello, `timeout.tls_handshake_secs`.
+    pub tls_handshake_timeout: Duration,
+    /// `timeout.upstream_connect_ms`; `None` waits for the OS connect timeout.
+    pub connect_timeout: Option<Duration>,
+    /// Bound on the whole relay, `timeout.connection_handling_secs`.
+    pub connection_handling_timeout: Duration,
+    pub syn_fingerprint: Option<TcpObservation>,
+    /// Shared details of the connection; receives the SNI and JA4 once the ClientHello is read.
+    pub connection: Arc<ConnectionContext>,
+    /// Admin API registry entry, held for the life of the connection.
+    pub tracked: Option<TrackedConnection>,
+    /// `[handshake_capture]` writer, with the listener address as the server of its records.
+    pub handshake_capture: HandshakeCapture,
+}
+
+/// Relay a connection of a TLS passthrough listener
+///
+/// The ClientHello is read for its SNI and JA4 and replayed to the backend the SNI routes to;
+/// from there on bytes are copied both ways untouched, so the backend terminates TLS. With the
+/// `splice` feature on Linux, TCP clients are relayed with `splice(2)` (see [`relay`]).
+pub async fn handle_passthrough_connection<S>(
+    mut stream: S,
+    peer: std::net::SocketAddr,
+    config: PassthroughConnectionConfig,
+) where
+    S: AsyncRead + AsyncWrite + RawTcpStream + Unpin + Send + 'static,
+{
+    let metrics = config.metrics;
+    let read = tokio::time::timeout(
+        config.tls_handshake_timeout,
+        read_client_hello(&mut stream, Arc::clone(&metrics)),
+    )
+    .await;
+    let (prefix, ja4_fingerprints) = match read {
+        Ok(Ok(v)) => v,
+        Ok(Err(e)) => {
+            warn!(?peer, error = %e, "failed to read client hello");
+            metrics.record_tls_passthrough(values::PASSTHROUGH_BAD_CLIENT_HELLO);
+            return;
+        }
+        Err(_) => {
+            warn!(?peer, "client hello timeout");
+            metrics.record_timeout(values::TIMEOUT_TLS_HANDSHAKE);
+            metrics.record_tls_passthrough(values::PASSTHROUGH_BAD_CLIENT_HELLO);
+            return;
+        }
+    };
+    config.handshake_capture.capture(|| {
+        HandshakeRecord::new(
+            peer,
+            &prefix,
+            ja4_fingerprints.as_ref(),
+            config.syn_fingerprint.as_ref(),
+        )
+    });
+
+    // Routing needs the SNI even when JA4 fingerprinting is off for this listener.
+    let sni = ja4_fingerprints.as_ref().and_then(|f| f.sni.clone());
+    let ja4 = ja4_fingerprints
+        .filter(|_| config.tls_fingerprint)
+        .map(|f| Ja4Set::from(&f));
+    config.connection.set_tls(ConnectionTls {
+        sni: sni.as_deref().map(Arc::from),
+        alpn: None,
+        ja4: ja4.clone(),
+    });
+
+    let Some(backend) = config.passthrough.backend_for(sni.as_deref()) else {
+        debug!(?peer, sni = sni.as_deref(), "no passthrough route for client hello");
+        metrics.record_tls_passthrough(values::PASSTHROUGH_NO_ROUTE);
+        return;
+    };
+
+    let connect = TcpStream::connect(backend);
+    let connected = match config.connect_timeout {
+        Some(limit) => tokio::time::timeout(limit, connect)
+            .await
+            .unwrap_or_else(|_| {
+                Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "connect timed out"))
+            }),
+        None => connect.await,
+    };
+    let mut upstream = match connected {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            warn!(?peer, backend, error = %e, "passthrough backend connect failed");
+            metrics.record_tls_passthrough(values::PASSTHROUGH_CONNECT_FAILED);
+            return;
+        }
+    };
+    info!(
+        ?peer,
+        sni = sni.as_deref(),
+        ja4 = ja4.as_ref().map(|set| &*set.ja4),
+        backend,
+        "relaying TLS passthrough connection"
+    );
+    metrics.record_tls_passthrough(values::PASSTHROUGH_RELAYED);
+
+    let relayed = async {
+        upstream.write_all(&prefix).await?;
+        relay(&mut stream, &mut upstream).await
+    };
+    match tokio::time::timeout(config.connection_handling_timeout, relayed).await {
+        Ok(Ok(Relayed { from_client, from_backend, spliced })) => {
+            // Spliced bytes bypass the stream's `CountedStream`; account for them here.
+            if let Some(tracked) = config.tracked.as_ref().filter(|_| spliced) {
+                let traffic = tracked.traffic();
+                traffic.add_received(from_client);
+                traffic.add_sent(from_backend);
+            }
+            debug!(
+                ?peer,
+                backend, from_client, from_backend, spliced, "passthrough connection closed"
+            );
+        }
+        Ok(Err(e)) => {
+            debug!(?peer, backend, reason = %e, "passthrough connection ended");
+        }
+        Err(_) => {
+            warn!(?peer, backend, "connection handling timeout");
+            metrics.record_timeout(values::TIMEOUT_CONNECTION_HANDLING);
+        }
+    }
+}
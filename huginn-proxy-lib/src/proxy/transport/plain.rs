@@ -1,19 +1,28 @@
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
 
-use super::timeout_helper::serve_with_timeout;
+use super::h2c::{is_h2c_upgrade, stream_one_frame, switching_protocols, H2cUpgradeStream};
+use super::timeout_helper::{drain_on_reload, serve_with_timeout};
 use crate::backend::UpstreamGateway;
-use crate::fingerprinting::TcpObservation;
+use crate::fingerprinting::{CapturingStream, FingerprintHeaderNames, TcpObservation};
+use crate::proxy::connection::{ConnectionContext, TrackedConnection};
 use crate::proxy::handler::request::handle_proxy_request;
-use crate::proxy::synthetic_response::synthetic_error_response;
+use crate::proxy::synthetic_response::http_error_response;
 use crate::proxy::ClientPool;
-use crate::telemetry::Metrics;
-use http::StatusCode;
+use crate::telemetry::{AccessLogContext, LogLevels, Metrics, RequestLog};
+use crate::utils::http::RespBody;
+use bytes::Bytes;
+use huginn_net_http::AkamaiFingerprint;
+use hyper::upgrade::OnUpgrade;
 use hyper_util::rt::{TokioExecutor, TokioIo};
 use hyper_util::server::conn::auto::Builder as ConnBuilder;
-use tokio::net::TcpStream;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::watch;
+use tracing::{debug, Instrument};
 
 /// Configuration for handling plain HTTP connections
 pub struct PlainConnectionConfig {
+    pub fingerprint_config: crate::config::FingerprintConfig,
     pub domains: Arc<Vec<crate::config::Domain>>,
     pub backends: Arc<Vec<crate::config::Backend>>,
     pub keep_alive: crate::config::KeepAliveConfig,
@@ -25,14 +34,125 @@ pub struct PlainConnectionConfig {
     pub client_pool: Arc<ClientPool>,
     pub syn_fingerprint: Option<TcpObservation>,
     pub upstream: UpstreamGateway,
+    pub fingerprint_headers: Arc<FingerprintHeaderNames>,
+    /// Reload generation; the connection drains when it changes.
+    pub config_changed: watch::Receiver<u64>,
+    /// Shared details of the connection; receives the Akamai receiver and is attached to every
+    /// request.
+    pub connection: Arc<ConnectionContext>,
+    /// Admin API registry entry, held for the life of the connection.
+    pub tracked: Option<TrackedConnection>,
+    /// Connection fields of `[access_log]` records.
+    pub access_log: AccessLogContext,
+    /// Runtime log levels; requests on routes with an override run inside a `request` span.
+    pub log_levels: LogLevels,
 }
 
+type AkamaiReceiver = watch::Receiver<Option<AkamaiFingerprint>>;
+
+/// An accepted `Upgrade: h2c`: the client side of the connection and the stream 1 frame.
+type UpgradeSlot = Arc<Mutex<Option<(OnUpgrade, Bytes)>>>;
+
 /// Handle a plain HTTP connection
-pub async fn handle_plain_connection(
-    stream: TcpStream,
+///
+/// HTTP/1.x and prior-knowledge HTTP/2 (h2c) are both served. With `fingerprint.http_enabled`,
+/// the connection is read through [`CapturingStream`], so h2c clients get the Akamai
+/// fingerprint like TLS ones. An HTTP/1.1 request asking for `Upgrade: h2c` is answered `101`
+/// and the connection continues as HTTP/2, starting with that request on stream 1.
+pub async fn handle_plain_connection<S>(
+    stream: S,
     peer: std::net::SocketAddr,
     config: PlainConnectionConfig,
-) {
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let started = tokio::time::Instant::now();
+    let upgrade: UpgradeSlot = Arc::default();
+    let (stream, fingerprint_rx) = capture_fingerprint(stream, &config);
+    let svc = plain_service(&config, peer, fingerprint_rx, Some(Arc::clone(&upgrade)));
+    let serve_fut = drain_on_reload(
+        config
+            .builder
+            .serve_connection_with_upgrades(TokioIo::new(stream), svc),
+        config.config_changed.clone(),
+        |conn| conn.graceful_shutdown(),
+    );
+    serve_with_timeout(serve_fut, config.connection_handling_timeout, config.metrics.clone(), peer)
+        .await;
+
+    let pending = upgrade.lock().ok().and_then(|mut slot| slot.take());
+    let Some((on_upgrade, frame)) = pending else {
+        return;
+    };
+    let upgraded = match on_upgrade.await {
+        Ok(upgraded) => upgraded,
+        Err(e) => {
+            debug!(?peer, error = %e, "h2c upgrade failed");
+            return;
+        }
+    };
+    let (stream, fingerprint_rx) = capture_fingerprint(TokioIo::new(upgraded), &config);
+    let svc = plain_service(&config, peer, fingerprint_rx, None);
+    let serve_fut = drain_on_reload(
+        config
+            .builder
+            .serve_connection(TokioIo::new(H2cUpgradeStream::new(stream, frame)), svc),
+        config.config_changed.clone(),
+        |conn| conn.graceful_shutdown(),
+    );
+    // The timeout covers the whole connection, before and after the upgrade.
+    let remaining = config
+        .connection_handling_timeout
+        .saturating_sub(started.elapsed());
+    serve_with_timeout(serve_fut, remaining, config.metrics, peer).await;
+}
+
+/// Wrap `stream` in a [`CapturingStream`] when the listener extracts HTTP/2 fingerprints.
+fn capture_fingerprint<S>(
+    stream: S,
+    config: &PlainConnectionConfig,
+) -> (CapturingStream<S>, Option<AkamaiReceiver>) {
+    let (fingerprint_tx, fingerprint_rx) = watch::channel(None);
+    // A capture limit of 0 passes the stream straight through.
+    let max_capture = if config.fingerprint_config.http_enabled {
+        config.fingerprint_config.max_capture
+    } else {
+        0
+    };
+    let (stream, _fingerprint_extracted) =
+        CapturingStream::new(stream, max_capture, fingerprint_tx, Arc::clone(&config.metrics));
+    let fingerprint_rx = config.fingerprint_config.http_enabled.then(|| {
+        config.connection.set_akamai(fingerprint_rx.clone());
+        fingerprint_rx
+    });
+    (stream, fingerprint_rx)
+}
+
+/// Take `req`'s upgrade into `slot` when it asks for `h2c` and can be replayed as stream 1.
+fn accept_h2c_upgrade(req: &mut hyper::Request<hyper::body::Incoming>, slot: &UpgradeSlot) -> bool {
+    if !is_h2c_upgrade(req) {
+        return false;
+    }
+    let (Some(frame), Ok(mut slot)) = (stream_one_frame(req), slot.lock()) else {
+        return false;
+    };
+    *slot = Some((hyper::upgrade::on(req), frame));
+    true
+}
+
+/// Request service of a plain connection. `upgrade` receives an accepted `Upgrade: h2c`; without
+/// it such requests are proxied like any other.
+fn plain_service(
+    config: &PlainConnectionConfig,
+    peer: std::net::SocketAddr,
+    fingerprint_rx: Option<AkamaiReceiver>,
+    upgrade: Option<UpgradeSlot>,
+) -> impl hyper::service::Service<
+    hyper::Request<hyper::body::Incoming>,
+    Response = hyper::Response<RespBody>,
+    Error = hyper::Error,
+    Future = impl Future<Output = Result<hyper::Response<RespBody>, hyper::Error>> + Send,
+> + Clone {
     let backends = config.backends.clone();
     let metrics = config.metrics.clone();
     let domains = config.domains.clone();
@@ -41,26 +161,48 @@ pub async fn handle_plain_connection(
     let client_pool = config.client_pool.clone();
     let syn_fingerprint = config.syn_fingerprint.clone();
     let upstream = config.upstream.clone();
+    let fingerprint_headers = config.fingerprint_headers.clone();
+    let connection = Arc::clone(&config.connection);
+    let access_log = config.access_log.clone();
+    let log_levels = config.log_levels.clone();
+    let preserve_host = config.preserve_host;
 
-    let svc = hyper::service::service_fn(move |req: hyper::Request<hyper::body::Incoming>| {
+    hyper::service::service_fn(move |mut req: hyper::Request<hyper::body::Incoming>| {
+        let accepted = upgrade
+            .as_ref()
+            .is_some_and(|slot| accept_h2c_upgrade(&mut req, slot));
         let domains = domains.clone();
         let backends = backends.clone();
+        let fingerprint_rx = fingerprint_rx.clone();
         let syn_fingerprint = syn_fingerprint.clone();
         let metrics = metrics.clone();
         let keep_alive = keep_alive.clone();
         let security = security.clone();
         let client_pool = client_pool.clone();
         let upstream = upstream.clone();
+        let fingerprint_headers = fingerprint_headers.clone();
+        req.extensions_mut().insert(Arc::clone(&connection));
+        let request_id = security
+            .request_ids
+            .as_ref()
+            .map(|ids| ids.assign(&mut req, peer.ip(), &security.trusted_proxies));
+        let access = access_log.start(&mut req);
+        let span = log_levels.request_span(&domains, security.listener_tenant.as_deref(), &req);
 
         async move {
-            let preserve_host = config.preserve_host;
+            if accepted {
+                // Answered on stream 1 once the connection is HTTP/2.
+                debug!(?peer, "upgrading connection to h2c");
+                return Ok(switching_protocols());
+            }
             let metrics_for_match = metrics.clone();
+            let mut request_log = RequestLog::default();
             let http_result = handle_proxy_request(
                 req,
                 domains,
                 backends,
                 None,
-                None,
+                fingerprint_rx,
                 syn_fingerprint,
                 &keep_alive,
                 &security,
@@ -71,28 +213,28 @@ pub async fn handle_plain_connection(
                 &client_pool,
                 &upstream,
                 None,
+                None,
+                &fingerprint_headers,
+                &mut request_log,
             )
+            .instrument(span)
             .await;
 
-            match http_result {
-                Ok(v) => Ok::<_, hyper::Error>(v),
+            let mut response = match http_result {
+                Ok(v) => v,
                 Err(e) => {
                     e.log_with_peer(peer);
-                    let code = StatusCode::from(e.clone());
                     metrics_for_match.record_error(e.error_type());
-                    match synthetic_error_response(code) {
-                        Ok(resp) => Ok(resp),
-                        Err(e) => Ok(crate::utils::http::json_error(
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            &format!("Failed to create error response: {e}"),
-                        )),
-                    }
+                    http_error_response(&e)
                 }
+            };
+            if let Some(request_id) = &request_id {
+                request_id.echo(&mut response);
             }
+            if let Some(access) = access {
+                access.finish(&response, request_log);
+            }
+            Ok::<_, hyper::Error>(response)
         }
-    });
-
-    let serve_fut = config.builder.serve_connection(TokioIo::new(stream), svc);
-
-    serve_with_timeout(serve_fut, config.connection_handling_timeout, config.metrics, peer).await;
+    })
 }
@@ -0,0 +1,149 @@
+//! Byte relay of TLS passthrough connections once the ClientHello has been read.
+//!
+//! With the `splice` feature on Linux, two TCP sockets are joined with `splice(2)` through a
+//! pipe per direction, so payload bytes never enter userspace. Anything else (unix socket
+//! clients, other platforms, the feature off, or no pipe available) falls back to
+//! [`tokio::io::copy_bidirectional`].
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpStream, UnixStream};
+
+use crate::proxy::connection::CountedStream;
+
+/// Client streams a relay may splice directly: the accepted TCP socket, possibly behind
+/// wrappers that only observe its bytes.
+pub trait RawTcpStream {
+    /// The underlying TCP socket; `None` when bytes must go through `AsyncRead`/`AsyncWrite`.
+    fn raw_tcp(&self) -> Option<&TcpStream> {
+        None
+    }
+}
+
+impl RawTcpStream for TcpStream {
+    fn raw_tcp(&self) -> Option<&TcpStream> {
+        Some(self)
+    }
+}
+
+impl RawTcpStream for UnixStream {}
+
+impl RawTcpStream for tokio::io::DuplexStream {}
+
+impl<S: RawTcpStream> RawTcpStream for CountedStream<S> {
+    fn raw_tcp(&self) -> Option<&TcpStream> {
+        self.get_ref().raw_tcp()
+    }
+}
+
+/// Outcome of [`relay`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Relayed {
+    pub from_client: u64,
+    pub from_backend: u64,
+    /// The bytes were moved with `splice(2)`, bypassing the client stream's wrappers, so byte
+    /// counters such as [`CountedStream`] did not see them.
+    pub spliced: bool,
+}
+
+/// Copy bytes both ways between `client` and `backend` until both directions are closed.
+pub async fn relay<S>(client: &mut S, backend: &mut TcpStream) -> io::Result<Relayed>
+where
+    S: AsyncRead + AsyncWrite + RawTcpStream + Unpin,
+{
+    #[cfg(all(target_os = "linux", feature = "splice"))]
+    if let Some(tcp) = client.raw_tcp() {
+        if let Some(pipes) = linux::Pipes::new() {
+            let (from_client, from_backend) = pipes.relay(tcp, backend).await?;
+            return Ok(Relayed { from_client, from_backend, spliced: true });
+        }
+    }
+    let (from_client, from_backend) = tokio::io::copy_bidirectional(client, backend).await?;
+    Ok(Relayed { from_client, from_backend, spliced: false })
+}
+
+#[cfg(all(target_os = "linux", feature = "splice"))]
+mod linux {
+    use std::io;
+    use std::net::Shutdown;
+    use std::os::fd::OwnedFd;
+
+    use rustix::pipe::{pipe_with, splice, PipeFlags, SpliceFlags};
+    use socket2::SockRef;
+    use tokio::io::Interest;
+    use tokio::net::TcpStream;
+    use tracing::debug;
+
+    /// Bytes moved per `splice(2)`; the default pipe capacity, so a drained pipe always has
+    /// room for the next read.
+    const CHUNK: usize = 65_536;
+
+    /// One pipe per direction, as (read end, write end).
+    pub(super) struct Pipes {
+        upstream: (OwnedFd, OwnedFd),
+        downstream: (OwnedFd, OwnedFd),
+    }
+
+    impl Pipes {
+        /// `None` when the pipes cannot be created (e.g. out of file descriptors).
+        pub(super) fn new() -> Option<Self> {
+            let pipe = || pipe_with(PipeFlags::CLOEXEC);
+            match (pipe(), pipe()) {
+                (Ok(upstream), Ok(downstream)) => Some(Self { upstream, downstream }),
+                (Err(e), _) | (_, Err(e)) => {
+                    debug!(error = %e, "splice pipes unavailable, copying in userspace");
+                    None
+                }
+            }
+        }
+
+        /// Bytes moved (client to backend, backend to client).
+        pub(super) async fn relay(
+            self,
+            client: &TcpStream,
+            backend: &TcpStream,
+        ) -> io::Result<(u64, u64)> {
+            tokio::try_join!(
+                splice_one_way(client, backend, &self.upstream),
+                splice_one_way(backend, client, &self.downstream),
+            )
+        }
+    }
+
+    /// Move bytes from `from` to `to` through `pipe` until `from` reaches EOF, then shut down
+    /// the write side of `to`, as `copy_bidirectional` does.
+    async fn splice_one_way(
+        from: &TcpStream,
+        to: &TcpStream,
+        (pipe_read, pipe_write): &(OwnedFd, OwnedFd),
+    ) -> io::Result<u64> {
+        let flags = SpliceFlags::MOVE | SpliceFlags::NONBLOCK;
+        let mut total: u64 = 0;
+        loop {
+            from.readable().await?;
+            let read = match from.try_io(Interest::READABLE, || {
+                splice(from, None, pipe_write, None, CHUNK, flags).map_err(io::Error::from)
+            }) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(e),
+            };
+            let mut pending = read;
+            while pending > 0 {
+                to.writable().await?;
+                match to.try_io(Interest::WRITABLE, || {
+                    splice(pipe_read, None, to, None, pending, flags).map_err(io::Error::from)
+                }) {
+                    Ok(written) => pending = pending.saturating_sub(written),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            total = total.saturating_add(u64::try_from(read).unwrap_or(u64::MAX));
+        }
+        SockRef::from(to).shutdown(Shutdown::Write)?;
+        Ok(total)
+    }
+}
@@ -1,6 +1,9 @@
 use crate::telemetry::metrics::values;
 use crate::telemetry::Metrics;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
+use tokio::sync::watch;
 use tracing::{debug, warn};
 
 pub async fn serve_with_timeout<F, E>(
@@ -23,3 +26,25 @@ pub async fn serve_with_timeout<F, E>(
         }
     }
 }
+
+/// Drive `conn` to completion, calling `shutdown` once `config_changed` sees a new reload
+/// generation. With hyper's `graceful_shutdown` HTTP/2 clients get a GOAWAY and HTTP/1 closes
+/// after the in-flight response, so clients reconnect onto the reloaded config.
+pub async fn drain_on_reload<C, E>(
+    conn: C,
+    mut config_changed: watch::Receiver<u64>,
+    shutdown: impl FnOnce(Pin<&mut C>),
+) -> Result<(), E>
+where
+    C: Future<Output = Result<(), E>>,
+{
+    tokio::pin!(conn);
+    tokio::select! {
+        result = conn.as_mut() => return result,
+        Ok(()) = config_changed.changed() => {
+            debug!("config reloaded, draining connection");
+        }
+    }
+    shutdown(conn.as_mut());
+    conn.await
+}
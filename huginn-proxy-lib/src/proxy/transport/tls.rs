@@ -1,22 +1,32 @@
 use std::sync::Arc;
 
-use super::timeout_helper::serve_with_timeout;
+use super::timeout_helper::{drain_on_reload, serve_with_timeout};
 use crate::backend::UpstreamGateway;
-use crate::fingerprinting::TcpObservation;
-use crate::fingerprinting::{read_client_hello, CapturingStream};
-use crate::proxy::connection::{PrefixedStream, TlsConnectionGuard};
+use crate::fingerprinting::{
+    client_hello_has_sni, client_hello_offers_early_data, client_hello_offers_ech,
+    read_client_hello, CapturingStream,
+};
+use crate::fingerprinting::{FingerprintHeaderNames, Ja4Fingerprints, TcpObservation};
+use crate::proxy::connection::{
+    ConnectionContext, ConnectionTls, Ja4Set, PrefixedStream, TlsConnectionGuard, TrackedConnection,
+};
 use crate::proxy::handler::request::handle_proxy_request;
-use crate::proxy::synthetic_response::synthetic_error_response;
-use crate::proxy::ClientPool;
-use crate::telemetry::Metrics;
-use crate::tls::record_tls_handshake_metrics;
+use crate::proxy::handler::ClientCertContext;
+use crate::proxy::synthetic_response::http_error_response;
+use crate::proxy::{ClientPool, SecurityContext};
+use crate::security::{graylisted, ObservedFingerprints};
+use crate::telemetry::metrics::values;
+use crate::telemetry::{
+    AccessLogContext, HandshakeCapture, HandshakeRecord, LogLevels, Metrics, RequestLog,
+};
 use crate::tls::setup::SharedTlsAcceptor;
-use http::StatusCode;
+use crate::tls::{record_tls_handshake_metrics, ClientCertInfo, TlsHandshakeInfo};
 use hyper_util::rt::{TokioExecutor, TokioIo};
 use hyper_util::server::conn::auto::Builder as ConnBuilder;
-use tokio::net::TcpStream;
-use tokio::time::Instant;
-use tracing::warn;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::watch;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, warn, Instrument};
 
 /// Configuration for handling TLS connections
 pub struct TlsConnectionConfig {
@@ -34,16 +44,38 @@ pub struct TlsConnectionConfig {
     pub client_pool: Arc<ClientPool>,
     pub syn_fingerprint: Option<TcpObservation>,
     pub upstream: UpstreamGateway,
+    /// `tls.client_cert` policy; `Some` only when `tls.client_auth` verifies client certificates.
+    pub client_cert_policy: Option<crate::config::ClientCertConfig>,
+    /// `tls.options.reject_ech_without_sni`.
+    pub reject_ech_without_sni: bool,
+    pub fingerprint_headers: Arc<FingerprintHeaderNames>,
+    /// Reload generation; the connection drains when it changes.
+    pub config_changed: watch::Receiver<u64>,
+    /// Shared details of the connection; receives the SNI, ALPN, JA4 and Akamai receiver once
+    /// observed and is attached to every request.
+    pub connection: Arc<ConnectionContext>,
+    /// Admin API registry entry, held for the life of the connection.
+    pub tracked: Option<TrackedConnection>,
+    /// Connection fields of `[access_log]` records.
+    pub access_log: AccessLogContext,
+    /// `[handshake_capture]` writer, with the listener address as the server of its records.
+    pub handshake_capture: HandshakeCapture,
+    /// Runtime log levels; requests on routes with an override run inside a `request` span.
+    pub log_levels: LogLevels,
 }
 
 /// Handle a TLS connection
-pub async fn handle_tls_connection(
-    mut stream: TcpStream,
+pub async fn handle_tls_connection<S>(
+    mut stream: S,
     peer: std::net::SocketAddr,
     config: TlsConnectionConfig,
-) {
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
     let metrics = config.metrics.clone();
     let acc = config.tls_acceptor.load_full();
+    let connection = config.connection;
+    let _tracked = config.tracked;
     {
         let handshake_start = Instant::now();
         let (prefix, ja4_fingerprints) =
@@ -55,7 +87,25 @@ pub async fn handle_tls_connection(
                     return;
                 }
             };
+        config.handshake_capture.capture(|| {
+            HandshakeRecord::new(
+                peer,
+                &prefix,
+                ja4_fingerprints.as_ref(),
+                config.syn_fingerprint.as_ref(),
+            )
+        });
 
+        let ech = client_hello_offers_ech(&prefix);
+        if ech && config.reject_ech_without_sni && !client_hello_has_sni(&prefix) {
+            debug!(?peer, "ECH ClientHello without outer SNI rejected");
+            metrics.record_tls_handshake_error();
+            return;
+        }
+        // Not part of the handshake duration: the client waits on the proxy, not on TLS.
+        let tarpitted = tarpit(&config.security, peer, ja4_fingerprints.as_ref(), &metrics).await;
+        let early_data =
+            config.fingerprint_config.tls_info_headers && client_hello_offers_early_data(&prefix);
         let prefixed = PrefixedStream::new(prefix, stream);
         let tls_accept_result =
             tokio::time::timeout(config.tls_handshake_timeout, acc.accept(prefixed)).await;
@@ -75,7 +125,10 @@ pub async fn handle_tls_connection(
             }
         };
 
-        let handshake_duration = handshake_start.elapsed().as_secs_f64();
+        let handshake_duration = handshake_start
+            .elapsed()
+            .saturating_sub(tarpitted)
+            .as_secs_f64();
         record_tls_handshake_metrics(&tls, handshake_duration, &metrics);
 
         // SNI negotiated by the TLS connection (the name that selected the served cert).
@@ -83,6 +136,21 @@ pub async fn handle_tls_connection(
         // and the always-on misdirected-request (421) check compares each against this value.
         let connection_sni: Option<Arc<str>> = tls.get_ref().1.server_name().map(Arc::from);
 
+        // Client certificate verified by the handshake (mTLS), decoded once per connection.
+        let client_cert: Option<Arc<ClientCertContext>> = config.client_cert_policy.map(|policy| {
+            let cert = tls
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|der| ClientCertInfo::from_der(der.as_ref()));
+            Arc::new(ClientCertContext { cert, policy })
+        });
+
+        // Negotiated TLS parameters, attached to every request of the connection as an extension
+        // for the security checks (ALPN), the `x-tls-*` headers and the whoami endpoint.
+        let tls_info = Arc::new(TlsHandshakeInfo::from_stream(&tls, early_data, ech));
+
         // Guard decrements TLS connection metrics counter when connection closes.
         // The main active_connections counter is handled by ConnectionGuard.
         let tls_connection_guard =
@@ -93,6 +161,13 @@ pub async fn handle_tls_connection(
         } else {
             None
         };
+        connection.set_tls(ConnectionTls {
+            sni: connection_sni.clone(),
+            alpn: tls_info.alpn.as_deref().map(Arc::from),
+            ja4: ja4_fingerprints.as_ref().map(Ja4Set::from),
+        });
+        let access_log = config.access_log;
+        let log_levels = config.log_levels;
 
         let syn_fingerprint = config.syn_fingerprint.clone();
 
@@ -101,6 +176,7 @@ pub async fn handle_tls_connection(
         if config.fingerprint_config.http_enabled {
             let (fingerprint_tx, fingerprint_rx) =
                 tokio::sync::watch::channel(None::<huginn_net_http::AkamaiFingerprint>);
+            connection.set_akamai(fingerprint_rx.clone());
 
             let (capturing_stream, _fingerprint_extracted) = CapturingStream::new(
                 tls,
@@ -115,9 +191,10 @@ pub async fn handle_tls_connection(
             let security = config.security.clone();
             let client_pool = config.client_pool.clone();
             let upstream = config.upstream.clone();
+            let fingerprint_headers = config.fingerprint_headers.clone();
 
-            let svc =
-                hyper::service::service_fn(move |req: hyper::Request<hyper::body::Incoming>| {
+            let svc = hyper::service::service_fn(
+                move |mut req: hyper::Request<hyper::body::Incoming>| {
                     let domains = domains.clone();
                     let backends = backends.clone();
                     let ja4_fingerprints = ja4_fingerprints.clone();
@@ -129,8 +206,23 @@ pub async fn handle_tls_connection(
                     let client_pool_for_request = client_pool.clone();
                     let upstream = upstream.clone();
                     let connection_sni = connection_sni.clone();
+                    let client_cert = client_cert.clone();
+                    let fingerprint_headers = fingerprint_headers.clone();
+                    req.extensions_mut().insert(Arc::clone(&tls_info));
+                    req.extensions_mut().insert(Arc::clone(&connection));
+                    let request_id = security
+                        .request_ids
+                        .as_ref()
+                        .map(|ids| ids.assign(&mut req, peer.ip(), &security.trusted_proxies));
+                    let access = access_log.start(&mut req);
+                    let span = log_levels.request_span(
+                        &domains,
+                        security.listener_tenant.as_deref(),
+                        &req,
+                    );
 
                     async move {
+                        let mut request_log = RequestLog::default();
                         let metrics_for_match = metrics.clone();
                         let preserve_host = config.preserve_host;
                         let http_result = handle_proxy_request(
@@ -149,30 +241,39 @@ pub async fn handle_tls_connection(
                             &client_pool_for_request,
                             &upstream,
                             connection_sni.as_deref(),
+                            client_cert.as_deref(),
+                            &fingerprint_headers,
+                            &mut request_log,
                         )
+                        .instrument(span)
                         .await;
 
-                        match http_result {
-                            Ok(v) => Ok::<_, hyper::Error>(v),
+                        let mut response = match http_result {
+                            Ok(v) => v,
                             Err(e) => {
                                 e.log_with_peer(peer);
-                                let code = StatusCode::from(e.clone());
                                 metrics_for_match.record_error(e.error_type());
-                                match synthetic_error_response(code) {
-                                    Ok(resp) => Ok(resp),
-                                    Err(e) => Ok(crate::utils::http::json_error(
-                                        StatusCode::INTERNAL_SERVER_ERROR,
-                                        &format!("Failed to create error response: {e}"),
-                                    )),
-                                }
+                                http_error_response(&e)
                             }
+                        };
+                        if let Some(request_id) = &request_id {
+                            request_id.echo(&mut response);
+                        }
+                        if let Some(access) = access {
+                            access.finish(&response, request_log);
                         }
+                        Ok::<_, hyper::Error>(response)
                     }
-                });
+                },
+            );
 
-            let serve_fut = config
-                .builder
-                .serve_connection(TokioIo::new(capturing_stream), svc);
+            let serve_fut = drain_on_reload(
+                config
+                    .builder
+                    .serve_connection_with_upgrades(TokioIo::new(capturing_stream), svc),
+                config.config_changed,
+                |conn| conn.graceful_shutdown(),
+            );
 
             serve_with_timeout(serve_fut, config.connection_handling_timeout, config.metrics, peer)
                 .await;
@@ -183,9 +284,10 @@ pub async fn handle_tls_connection(
             let security = config.security.clone();
             let client_pool = config.client_pool.clone();
             let upstream = config.upstream.clone();
+            let fingerprint_headers = config.fingerprint_headers.clone();
 
-            let svc =
-                hyper::service::service_fn(move |req: hyper::Request<hyper::body::Incoming>| {
+            let svc = hyper::service::service_fn(
+                move |mut req: hyper::Request<hyper::body::Incoming>| {
                     let domains = domains.clone();
                     let backends = backends.clone();
                     let ja4_fingerprints = ja4_fingerprints.clone();
@@ -196,8 +298,23 @@ pub async fn handle_tls_connection(
                     let client_pool = client_pool.clone();
                     let upstream = upstream.clone();
                     let connection_sni = connection_sni.clone();
+                    let client_cert = client_cert.clone();
+                    let fingerprint_headers = fingerprint_headers.clone();
+                    req.extensions_mut().insert(Arc::clone(&tls_info));
+                    req.extensions_mut().insert(Arc::clone(&connection));
+                    let request_id = security
+                        .request_ids
+                        .as_ref()
+                        .map(|ids| ids.assign(&mut req, peer.ip(), &security.trusted_proxies));
+                    let access = access_log.start(&mut req);
+                    let span = log_levels.request_span(
+                        &domains,
+                        security.listener_tenant.as_deref(),
+                        &req,
+                    );
 
                     async move {
+                        let mut request_log = RequestLog::default();
                         let preserve_host = config.preserve_host;
                         let metrics_for_match = metrics.clone();
                         let http_result = handle_proxy_request(
@@ -216,31 +333,69 @@ pub async fn handle_tls_connection(
                             &client_pool,
                             &upstream,
                             connection_sni.as_deref(),
+                            client_cert.as_deref(),
+                            &fingerprint_headers,
+                            &mut request_log,
                         )
+                        .instrument(span)
                         .await;
 
-                        match http_result {
-                            Ok(v) => Ok::<_, hyper::Error>(v),
+                        let mut response = match http_result {
+                            Ok(v) => v,
                             Err(e) => {
                                 e.log_with_peer(peer);
-                                let code = StatusCode::from(e.clone());
                                 metrics_for_match.record_error(e.error_type());
-                                match synthetic_error_response(code) {
-                                    Ok(resp) => Ok(resp),
-                                    Err(e) => Ok(crate::utils::http::json_error(
-                                        StatusCode::INTERNAL_SERVER_ERROR,
-                                        &format!("Failed to create error response: {e}"),
-                                    )),
-                                }
+                                http_error_response(&e)
                             }
+                        };
+                        if let Some(request_id) = &request_id {
+                            request_id.echo(&mut response);
+                        }
+                        if let Some(access) = access {
+                            access.finish(&response, request_log);
                         }
+                        Ok::<_, hyper::Error>(response)
                     }
-                });
+                },
+            );
 
-            let serve_fut = config.builder.serve_connection(TokioIo::new(tls), svc);
+            let serve_fut = drain_on_reload(
+                config
+                    .builder
+                    .serve_connection_with_upgrades(TokioIo::new(tls), svc),
+                config.config_changed,
+                |conn| conn.graceful_shutdown(),
+            );
 
             serve_with_timeout(serve_fut, config.connection_handling_timeout, config.metrics, peer)
                 .await;
         }
     }
 }
+
+/// Hold a connection graylisted by `[security.tarpit]` before its ClientHello is answered, unless
+/// `max_concurrent` connections are already waiting. Returns how long it waited.
+async fn tarpit(
+    security: &SecurityContext,
+    peer: std::net::SocketAddr,
+    ja4: Option<&Ja4Fingerprints>,
+    metrics: &Metrics,
+) -> Duration {
+    let config = &security.tarpit;
+    if !config.is_active() {
+        return Duration::ZERO;
+    }
+    let observed = ObservedFingerprints::new(ja4, None, None);
+    let Some(kind) = graylisted(config, peer.ip(), &observed) else {
+        return Duration::ZERO;
+    };
+    let Some(_slot) = security.tarpit_slots.try_enter(config.max_concurrent) else {
+        debug!(?peer, kind, "tarpit full, ClientHello answered without delay");
+        metrics.record_tarpit(kind, values::TARPIT_SKIPPED);
+        return Duration::ZERO;
+    };
+    debug!(?peer, kind, delay_ms = config.delay_ms, "ClientHello tarpitted");
+    metrics.record_tarpit(kind, values::TARPIT_DELAYED);
+    tokio::time::sleep(config.delay()).await;
+    config.delay()
+}
@@ -0,0 +1,297 @@
+//! UDP forwarding for `[[listen.udp]]`.
+//!
+//! A session is keyed by the client address (the local address and protocol are fixed per
+//! forwarder, so this is the 5-tuple) and owns an upstream socket connected to the backend.
+//! Client datagrams go out through it, and a per-session task sends the backend's replies back
+//! from the listening socket until the session has been idle for `idle_timeout_secs`.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use opentelemetry::KeyValue;
+use tokio::net::UdpSocket;
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::config::UdpListenerConfig;
+use crate::telemetry::metrics::{labels, values};
+use crate::telemetry::Metrics;
+
+/// Largest UDP payload.
+const MAX_DATAGRAM: usize = 65_535;
+
+/// Forwards the datagrams of one `[[listen.udp]]` address to its backend.
+pub struct UdpForwarder {
+    socket: Arc<UdpSocket>,
+    backend: SocketAddr,
+    idle_timeout: Duration,
+    max_sessions: usize,
+    metrics: Arc<Metrics>,
+    labels: Arc<UdpLabels>,
+}
+
+/// Metric attributes of a forwarder, built once instead of per datagram.
+struct UdpLabels {
+    listener: [KeyValue; 1],
+    client_to_backend: [KeyValue; 2],
+    backend_to_client: [KeyValue; 2],
+    session_limit: [KeyValue; 2],
+    session_failed: [KeyValue; 2],
+}
+
+impl UdpLabels {
+    fn new(listener: &str) -> Self {
+        let pair = |key: &'static str, value: &'static str| {
+            [KeyValue::new(labels::LISTENER, listener.to_string()), KeyValue::new(key, value)]
+        };
+        Self {
+            listener: [KeyValue::new(labels::LISTENER, listener.to_string())],
+            client_to_backend: pair(labels::DIRECTION, values::DIRECTION_CLIENT_TO_BACKEND),
+            backend_to_client: pair(labels::DIRECTION, values::DIRECTION_BACKEND_TO_CLIENT),
+            session_limit: pair(labels::REASON, values::UDP_DROP_SESSION_LIMIT),
+            session_failed: pair(labels::REASON, values::UDP_DROP_SESSION_FAILED),
+        }
+    }
+}
+
+/// Traffic of one session, shared by the forwarder and the session's reply task.
+struct SessionStats {
+    started: Instant,
+    /// Milliseconds after `started` of the last datagram in either direction.
+    last_active_ms: AtomicU64,
+    client_bytes: AtomicU64,
+    backend_bytes: AtomicU64,
+}
+
+impl SessionStats {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_active_ms: AtomicU64::new(0),
+            client_bytes: AtomicU64::new(0),
+            backend_bytes: AtomicU64::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        let elapsed = u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX);
+        self.last_active_ms.fetch_max(elapsed, Ordering::Relaxed);
+    }
+
+    fn idle_deadline(&self, idle_timeout: Duration) -> Instant {
+        let last_active = Duration::from_millis(self.last_active_ms.load(Ordering::Relaxed));
+        self.started
+            .checked_add(last_active.saturating_add(idle_timeout))
+            .unwrap_or(self.started)
+    }
+}
+
+struct Session {
+    upstream: Arc<UdpSocket>,
+    stats: Arc<SessionStats>,
+}
+
+impl UdpForwarder {
+    /// Bind `config.addr` and resolve the backend.
+    pub async fn bind(config: &UdpListenerConfig, metrics: Arc<Metrics>) -> io::Result<Self> {
+        let backend = tokio::net::lookup_host(config.backend.as_str())
+            .await?
+            .next()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("UDP backend {} resolved to no address", config.backend),
+                )
+            })?;
+        let socket = UdpSocket::bind(config.addr).await?;
+        let listener = socket.local_addr()?.to_string();
+        Ok(Self {
+            socket: Arc::new(socket),
+            backend,
+            idle_timeout: Duration::from_secs(config.idle_timeout_secs),
+            max_sessions: config.max_sessions,
+            metrics,
+            labels: Arc::new(UdpLabels::new(&listener)),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Forward until the task is dropped; dropping it also ends every session.
+    pub async fn run(self) {
+        let mut sessions: HashMap<SocketAddr, Session> = HashMap::new();
+        let mut reply_tasks: JoinSet<SocketAddr> = JoinSet::new();
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        loop {
+            tokio::select! {
+                received = self.socket.recv_from(&mut buf) => {
+                    let (len, client) = match received {
+                        Ok(received) => received,
+                        Err(e) => {
+                            debug!(error = %e, "UDP receive failed");
+                            continue;
+                        }
+                    };
+                    let Some(datagram) = buf.get(..len) else {
+                        continue;
+                    };
+                    if !sessions.contains_key(&client) {
+                        if sessions.len() >= self.max_sessions {
+                            self.metrics
+                                .udp_datagrams_dropped_total
+                                .add(1, &self.labels.session_limit);
+                            continue;
+                        }
+                        match self.open_session(client, &mut reply_tasks).await {
+                            Ok(session) => {
+                                sessions.insert(client, session);
+                            }
+                            Err(e) => {
+                                warn!(
+                                    ?client,
+                                    backend = %self.backend,
+                                    error = %e,
+                                    "UDP session failed to open"
+                                );
+                                self.metrics
+                                    .udp_datagrams_dropped_total
+                                    .add(1, &self.labels.session_failed);
+                                continue;
+                            }
+                        }
+                    }
+                    let Some(session) = sessions.get(&client) else {
+                        continue;
+                    };
+                    session.stats.touch();
+                    match session.upstream.send(datagram).await {
+                        Ok(sent) => {
+                            let sent = u64::try_from(sent).unwrap_or(u64::MAX);
+                            session.stats.client_bytes.fetch_add(sent, Ordering::Relaxed);
+                            self.metrics
+                                .udp_bytes_total
+                                .add(sent, &self.labels.client_to_backend);
+                        }
+                        Err(e) => debug!(?client, error = %e, "UDP send to backend failed"),
+                    }
+                }
+                Some(done) = reply_tasks.join_next(), if !reply_tasks.is_empty() => {
+                    let Ok(client) = done else {
+                        continue;
+                    };
+                    // A datagram from the client may have arrived as the reply task gave up.
+                    let active = sessions.get(&client).filter(|session| {
+                        session.stats.idle_deadline(self.idle_timeout) > Instant::now()
+                    });
+                    if let Some(session) = active {
+                        self.spawn_relay(client, session, &mut reply_tasks);
+                    } else if let Some(session) = sessions.remove(&client) {
+                        self.close_session(client, &session);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn open_session(
+        &self,
+        client: SocketAddr,
+        reply_tasks: &mut JoinSet<SocketAddr>,
+    ) -> io::Result<Session> {
+        let local: SocketAddr = if self.backend.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let upstream = UdpSocket::bind(local).await?;
+        upstream.connect(self.backend).await?;
+        let session =
+            Session { upstream: Arc::new(upstream), stats: Arc::new(SessionStats::new()) };
+        self.spawn_relay(client, &session, reply_tasks);
+        self.metrics
+            .udp_sessions_total
+            .add(1, &self.labels.listener);
+        self.metrics
+            .udp_sessions_active
+            .add(1, &self.labels.listener);
+        debug!(?client, backend = %self.backend, "UDP session opened");
+        Ok(session)
+    }
+
+    fn spawn_relay(
+        &self,
+        client: SocketAddr,
+        session: &Session,
+        reply_tasks: &mut JoinSet<SocketAddr>,
+    ) {
+        reply_tasks.spawn(relay_replies(
+            Arc::clone(&self.socket),
+            Arc::clone(&session.upstream),
+            client,
+            Arc::clone(&session.stats),
+            self.idle_timeout,
+            Arc::clone(&self.metrics),
+            Arc::clone(&self.labels),
+        ));
+    }
+
+    fn close_session(&self, client: SocketAddr, session: &Session) {
+        self.metrics
+            .udp_sessions_active
+            .add(-1, &self.labels.listener);
+        info!(
+            ?client,
+            backend = %self.backend,
+            client_bytes = session.stats.client_bytes.load(Ordering::Relaxed),
+            backend_bytes = session.stats.backend_bytes.load(Ordering::Relaxed),
+            duration_ms =
+                u64::try_from(session.stats.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            "UDP session closed"
+        );
+    }
+}
+
+/// Send the backend's datagrams to `client` until the session is idle; returns `client`.
+async fn relay_replies(
+    socket: Arc<UdpSocket>,
+    upstream: Arc<UdpSocket>,
+    client: SocketAddr,
+    stats: Arc<SessionStats>,
+    idle_timeout: Duration,
+    metrics: Arc<Metrics>,
+    labels: Arc<UdpLabels>,
+) -> SocketAddr {
+    let mut buf = vec![0u8; MAX_DATAGRAM];
+    loop {
+        let deadline = stats.idle_deadline(idle_timeout);
+        match tokio::time::timeout_at(deadline, upstream.recv(&mut buf)).await {
+            Ok(Ok(len)) => {
+                stats.touch();
+                let Some(datagram) = buf.get(..len) else {
+                    continue;
+                };
+                if let Err(e) = socket.send_to(datagram, client).await {
+                    debug!(?client, error = %e, "UDP send to client failed");
+                    continue;
+                }
+                let len = u64::try_from(len).unwrap_or(u64::MAX);
+                stats.backend_bytes.fetch_add(len, Ordering::Relaxed);
+                metrics.udp_bytes_total.add(len, &labels.backend_to_client);
+            }
+            // e.g. ICMP port unreachable from the backend, reported on the next receive.
+            Ok(Err(e)) => debug!(?client, error = %e, "UDP receive from backend failed"),
+            Err(_) => {
+                // Client datagrams may have moved the deadline while this one was waiting.
+                if stats.idle_deadline(idle_timeout) <= Instant::now() {
+                    return client;
+                }
+            }
+        }
+    }
+}
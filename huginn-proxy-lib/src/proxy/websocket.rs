@@ -0,0 +1,149 @@
+//! WebSocket (HTTP/1.1 `Upgrade`) tunnelling for routes with `websocket.enabled`.
+//!
+//! The opening handshake is forwarded like any other request. When the backend answers
+//! `101 Switching Protocols`, both connections are taken over from hyper and spliced by a
+//! spawned task, so the tunnel outlives the request handler and the connection's
+//! `connection_handling_secs` budget; it is bounded only by `websocket.idle_timeout_secs` and,
+//! once its backend is drained, the backend's `drain_timeout_secs`.
+
+use std::io;
+use std::sync::Arc;
+
+use http::header::{CONNECTION, UPGRADE};
+use http::HeaderMap;
+use hyper::upgrade::OnUpgrade;
+use hyper_util::rt::TokioIo;
+use opentelemetry::metrics::UpDownCounter;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::telemetry::metrics::values;
+use crate::telemetry::Metrics;
+
+const BUFFER_SIZE: usize = 16_384;
+
+/// How a spliced tunnel ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TunnelEnd {
+    /// One side closed its connection.
+    Closed,
+    /// No bytes flowed in either direction for the idle timeout.
+    IdleTimeout,
+}
+
+/// `true` for a WebSocket opening handshake: `Connection` lists the `upgrade` token and
+/// `Upgrade` names `websocket` (both case-insensitive, RFC 6455 §4.2.1).
+pub fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    has_token(headers, CONNECTION, "upgrade") && has_token(headers, UPGRADE, "websocket")
+}
+
+pub(crate) fn has_token(headers: &HeaderMap, name: http::header::HeaderName, token: &str) -> bool {
+    headers.get_all(name).iter().any(|value| {
+        value
+            .to_str()
+            .is_ok_and(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+    })
+}
+
+/// Decrements `huginn_websocket_connections_active` when the tunnel task ends.
+struct ActiveTunnelGuard(UpDownCounter<i64>);
+
+impl Drop for ActiveTunnelGuard {
+    fn drop(&mut self) {
+        self.0.add(-1, &[]);
+    }
+}
+
+/// Wait for both sides of a `101` exchange to hand over their connections, then splice them
+/// until they close, go idle, or `drain_cutoff` (the drain cutoff of `backend`) is cancelled.
+pub fn spawn_tunnel(
+    client: OnUpgrade,
+    upstream: OnUpgrade,
+    idle_timeout: Duration,
+    backend: String,
+    drain_cutoff: CancellationToken,
+    metrics: Arc<Metrics>,
+) {
+    tokio::spawn(async move {
+        let (client, upstream) = match tokio::try_join!(client, upstream) {
+            Ok(pair) => pair,
+            Err(e) => {
+                debug!(error = %e, "websocket upgrade failed");
+                return;
+            }
+        };
+        metrics.websocket_connections_active.add(1, &[]);
+        let _guard = ActiveTunnelGuard(metrics.websocket_connections_active.clone());
+
+        let spliced = tokio::select! {
+            end = splice(TokioIo::new(client), TokioIo::new(upstream), idle_timeout) => end,
+            _ = drain_cutoff.cancelled() => {
+                debug!(backend = %backend, "websocket tunnel cut: drain timeout passed");
+                metrics.record_backend_drain_cutoff(&backend);
+                return;
+            }
+        };
+        match spliced {
+            Ok(TunnelEnd::Closed) => {}
+            Ok(TunnelEnd::IdleTimeout) => {
+                debug!("websocket tunnel closed after idle timeout");
+                metrics.record_timeout(values::TIMEOUT_WEBSOCKET_IDLE);
+            }
+            Err(e) => debug!(error = %e, "websocket tunnel closed with error"),
+        }
+    });
+}
+
+/// Copy bytes between `client` and `upstream` in both directions until either side closes
+/// or neither sends anything for `idle_timeout`. Both write halves are shut down on exit.
+pub async fn splice<C, U>(client: C, upstream: U, idle_timeout: Duration) -> io::Result<TunnelEnd>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    U: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut client_rd, mut client_wr) = tokio::io::split(client);
+    let (mut upstream_rd, mut upstream_wr) = tokio::io::split(upstream);
+    let mut client_buf = vec![0u8; BUFFER_SIZE];
+    let mut upstream_buf = vec![0u8; BUFFER_SIZE];
+
+    let end = loop {
+        let step = tokio::time::timeout(idle_timeout, async {
+            tokio::select! {
+                read = client_rd.read(&mut client_buf) => {
+                    relay(read, &client_buf, &mut upstream_wr).await
+                }
+                read = upstream_rd.read(&mut upstream_buf) => {
+                    relay(read, &upstream_buf, &mut client_wr).await
+                }
+            }
+        })
+        .await;
+        match step {
+            Ok(Ok(true)) => {}
+            Ok(Ok(false)) => break TunnelEnd::Closed,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => break TunnelEnd::IdleTimeout,
+        }
+    };
+
+    let _ = client_wr.shutdown().await;
+    let _ = upstream_wr.shutdown().await;
+    Ok(end)
+}
+
+/// Forward one read to `dst`. `Ok(false)` on EOF.
+async fn relay<W: AsyncWrite + Unpin>(
+    read: io::Result<usize>,
+    buf: &[u8],
+    dst: &mut W,
+) -> io::Result<bool> {
+    let n = read?;
+    let Some(chunk) = buf.get(..n).filter(|c| !c.is_empty()) else {
+        return Ok(false);
+    };
+    dst.write_all(chunk).await?;
+    dst.flush().await?;
+    Ok(true)
+}
@@ -0,0 +1,139 @@
+//! Request body decoding for inspection.
+//!
+//! Body rules have to match the plaintext of `Content-Encoding: gzip` / `deflate` requests. The
+//! body is decoded into a separate buffer, bounded by [`DecodeLimits`] so a small compressed
+//! payload cannot expand into gigabytes (decompression bomb). The decoded copy is only for
+//! inspection: the original bytes are what gets forwarded, so the backend receives the request
+//! exactly as the client sent it.
+
+use std::borrow::Cow;
+use std::io::Read;
+
+use http::header::CONTENT_ENCODING;
+use http::HeaderMap;
+use thiserror::Error;
+
+/// Bounds applied while decoding one request body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Largest decoded body accepted, in bytes.
+    pub max_decoded_bytes: u64,
+    /// Largest accepted `decoded / encoded` size ratio.
+    pub max_ratio: u64,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self { max_decoded_bytes: 1024 * 1024, max_ratio: 100 }
+    }
+}
+
+/// Why a body could not be decoded for inspection.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("unsupported content encoding '{0}'")]
+    Unsupported(String),
+    #[error("decoded body exceeds {0} bytes")]
+    TooLarge(u64),
+    #[error("compression ratio exceeds {0}:1")]
+    RatioExceeded(u64),
+    #[error("malformed {encoding} body: {reason}")]
+    Malformed {
+        encoding: &'static str,
+        reason: String,
+    },
+}
+
+/// A content coding the inspector can undo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCoding {
+    Gzip,
+    /// HTTP `deflate`: zlib-wrapped, with a fallback for clients that send raw deflate.
+    Deflate,
+}
+
+impl ContentCoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Codings listed in `Content-Encoding`, in the order they were applied. `identity` is skipped.
+pub fn content_codings(headers: &HeaderMap) -> Result<Vec<ContentCoding>, DecodeError> {
+    let mut codings = Vec::new();
+    for value in headers.get_all(CONTENT_ENCODING) {
+        let value = value
+            .to_str()
+            .map_err(|_| DecodeError::Unsupported("<non-ascii>".to_string()))?;
+        for token in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let coding =
+                if token.eq_ignore_ascii_case("gzip") || token.eq_ignore_ascii_case("x-gzip") {
+                    ContentCoding::Gzip
+                } else if token.eq_ignore_ascii_case("deflate") {
+                    ContentCoding::Deflate
+                } else if token.eq_ignore_ascii_case("identity") {
+                    continue;
+                } else {
+                    return Err(DecodeError::Unsupported(token.to_string()));
+                };
+            codings.push(coding);
+        }
+    }
+    Ok(codings)
+}
+
+/// Plaintext view of `body` for inspection, undoing its `Content-Encoding`.
+///
+/// Uncompressed bodies are borrowed as-is. Stacked codings are undone last-applied first; each
+/// step is bounded by `limits`, with the ratio measured against the original `body`.
+pub fn decode_for_inspection<'a>(
+    headers: &HeaderMap,
+    body: &'a [u8],
+    limits: &DecodeLimits,
+) -> Result<Cow<'a, [u8]>, DecodeError> {
+    let codings = content_codings(headers)?;
+    let mut decoded = Cow::Borrowed(body);
+    for coding in codings.into_iter().rev() {
+        decoded = Cow::Owned(decode(coding, &decoded, body.len(), limits)?);
+    }
+    Ok(decoded)
+}
+
+fn decode(
+    coding: ContentCoding,
+    input: &[u8],
+    encoded_len: usize,
+    limits: &DecodeLimits,
+) -> Result<Vec<u8>, DecodeError> {
+    let ratio_cap = u64::try_from(encoded_len)
+        .unwrap_or(u64::MAX)
+        .saturating_mul(limits.max_ratio);
+    let cap = ratio_cap.min(limits.max_decoded_bytes);
+
+    let result = match coding {
+        ContentCoding::Gzip => read_capped(flate2::read::MultiGzDecoder::new(input), cap),
+        ContentCoding::Deflate => read_capped(flate2::read::ZlibDecoder::new(input), cap)
+            .or_else(|_| read_capped(flate2::read::DeflateDecoder::new(input), cap)),
+    };
+    let out = result
+        .map_err(|e| DecodeError::Malformed { encoding: coding.as_str(), reason: e.to_string() })?;
+
+    if u64::try_from(out.len()).unwrap_or(u64::MAX) > cap {
+        return Err(if cap == limits.max_decoded_bytes {
+            DecodeError::TooLarge(limits.max_decoded_bytes)
+        } else {
+            DecodeError::RatioExceeded(limits.max_ratio)
+        });
+    }
+    Ok(out)
+}
+
+/// Read at most `cap + 1` bytes, enough to tell whether the output goes past `cap`.
+fn read_capped(reader: impl Read, cap: u64) -> std::io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    reader.take(cap.saturating_add(1)).read_to_end(&mut out)?;
+    Ok(out)
+}
@@ -0,0 +1,277 @@
+//! Minimal DNS client for crawler verification and SRV backend discovery: one PTR, A, AAAA or
+//! SRV question over UDP to one resolver (RFC 1035, RFC 2782). Truncated answers are not retried
+//! over TCP; the few names and addresses a crawler check or a service needs fit in a UDP
+//! response.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Instant;
+
+use thiserror::Error;
+use tokio::net::UdpSocket;
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_AAAA: u16 = 28;
+pub const TYPE_SRV: u16 = 33;
+
+const CLASS_IN: u16 = 1;
+const HEADER_LEN: usize = 12;
+const MAX_NAME_LEN: usize = 253;
+const MAX_LABEL_LEN: usize = 63;
+/// Compression pointers followed while reading one name, to stop pointer loops.
+const MAX_POINTERS: usize = 16;
+const RCODE_NXDOMAIN: u16 = 3;
+
+#[derive(Debug, Error)]
+pub enum DnsError {
+    #[error("DNS I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid DNS name '{0}'")]
+    InvalidName(String),
+    #[error("malformed DNS response")]
+    Malformed,
+    #[error("DNS server error (rcode {0})")]
+    Server(u16),
+}
+
+/// Answer data of interest: the name of a PTR record, the address of an A / AAAA record or an
+/// SRV record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    Name(String),
+    Addr(IpAddr),
+    Srv(SrvRecord),
+}
+
+/// One SRV record: `port` on `target`, ranked by `priority` (lowest first) then `weight`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+impl Record {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Record::Name(name) => Some(name),
+            Record::Addr(_) | Record::Srv(_) => None,
+        }
+    }
+
+    pub fn addr(&self) -> Option<IpAddr> {
+        match self {
+            Record::Addr(addr) => Some(*addr),
+            Record::Name(_) | Record::Srv(_) => None,
+        }
+    }
+
+    pub fn srv(&self) -> Option<&SrvRecord> {
+        match self {
+            Record::Srv(srv) => Some(srv),
+            Record::Name(_) | Record::Addr(_) => None,
+        }
+    }
+}
+
+/// The PTR name of `ip`: `4.3.2.1.in-addr.arpa` or the nibble form under `ip6.arpa`.
+pub fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, d] = v4.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(v6) => {
+            let mut name = String::with_capacity(72);
+            for byte in v6.octets().iter().rev() {
+                let hex = format!("{byte:02x}");
+                let (high, low) = hex.split_at(1);
+                name.push_str(&format!("{low}.{high}."));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Encode a recursive query for `name` (without the trailing dot).
+pub fn encode_query(id: u16, name: &str, qtype: u16) -> Result<Vec<u8>, DnsError> {
+    let invalid = || DnsError::InvalidName(name.to_string());
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(invalid());
+    }
+    let mut msg = Vec::with_capacity(HEADER_LEN.saturating_add(name.len()).saturating_add(6));
+    msg.extend_from_slice(&id.to_be_bytes());
+    // Flags: standard query, recursion desired.
+    msg.extend_from_slice(&0x0100u16.to_be_bytes());
+    // One question, no answer / authority / additional records.
+    msg.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.') {
+        let len = u8::try_from(label.len()).map_err(|_| invalid())?;
+        if label.is_empty() || usize::from(len) > MAX_LABEL_LEN {
+            return Err(invalid());
+        }
+        msg.push(len);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+    msg.extend_from_slice(&qtype.to_be_bytes());
+    msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(msg)
+}
+
+/// Parse the answer to query `id`, keeping the `qtype` records of class IN. Other records
+/// (e.g. the CNAMEs a resolver followed) are skipped; NXDOMAIN yields no records.
+pub fn parse_response(id: u16, qtype: u16, msg: &[u8]) -> Result<Vec<Record>, DnsError> {
+    if read_u16(msg, 0)? != id {
+        return Err(DnsError::Malformed);
+    }
+    let flags = read_u16(msg, 2)?;
+    if flags & 0x8000 == 0 {
+        return Err(DnsError::Malformed);
+    }
+    match flags & 0x000f {
+        0 => {}
+        RCODE_NXDOMAIN => return Ok(Vec::new()),
+        rcode => return Err(DnsError::Server(rcode)),
+    }
+    let questions = read_u16(msg, 4)?;
+    let answers = read_u16(msg, 6)?;
+
+    let mut pos = HEADER_LEN;
+    for _ in 0..questions {
+        pos = skip_name(msg, pos)?;
+        pos = advance(pos, 4)?;
+    }
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        pos = skip_name(msg, pos)?;
+        let rtype = read_u16(msg, pos)?;
+        let class = read_u16(msg, advance(pos, 2)?)?;
+        let rdlen = usize::from(read_u16(msg, advance(pos, 8)?)?);
+        let rdata_start = advance(pos, 10)?;
+        let rdata = slice(msg, rdata_start, rdlen)?;
+        pos = advance(rdata_start, rdlen)?;
+        if rtype != qtype || class != CLASS_IN {
+            continue;
+        }
+        let record = match rtype {
+            TYPE_PTR => Record::Name(read_name(msg, rdata_start)?),
+            TYPE_A => {
+                let octets: [u8; 4] = rdata.try_into().map_err(|_| DnsError::Malformed)?;
+                Record::Addr(IpAddr::V4(Ipv4Addr::from(octets)))
+            }
+            TYPE_AAAA => {
+                let octets: [u8; 16] = rdata.try_into().map_err(|_| DnsError::Malformed)?;
+                Record::Addr(IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            TYPE_SRV => Record::Srv(SrvRecord {
+                priority: read_u16(msg, rdata_start)?,
+                weight: read_u16(msg, advance(rdata_start, 2)?)?,
+                port: read_u16(msg, advance(rdata_start, 4)?)?,
+                target: read_name(msg, advance(rdata_start, 6)?)?,
+            }),
+            _ => continue,
+        };
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Ask `resolver` for the `qtype` records of `name`. Has no timeout of its own; callers bound
+/// it with `tokio::time::timeout`.
+pub async fn query(resolver: SocketAddr, name: &str, qtype: u16) -> Result<Vec<Record>, DnsError> {
+    let id = query_id();
+    let request = encode_query(id, name, qtype)?;
+    let bind: SocketAddr = match resolver {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(resolver).await?;
+    socket.send(&request).await?;
+
+    let mut buf = vec![0u8; 4096];
+    loop {
+        let len = socket.recv(&mut buf).await?;
+        let msg = buf.get(..len).ok_or(DnsError::Malformed)?;
+        // Stray datagrams that do not answer this query are dropped.
+        if read_u16(msg, 0).ok() != Some(id) {
+            continue;
+        }
+        return parse_response(id, qtype, msg);
+    }
+}
+
+/// Unpredictable query id: SipHash with a random key, over the current instant.
+fn query_id() -> u16 {
+    let hash = RandomState::new().hash_one(Instant::now());
+    u16::try_from(hash & 0xffff).unwrap_or_default()
+}
+
+fn advance(pos: usize, by: usize) -> Result<usize, DnsError> {
+    pos.checked_add(by).ok_or(DnsError::Malformed)
+}
+
+fn slice(msg: &[u8], pos: usize, len: usize) -> Result<&[u8], DnsError> {
+    msg.get(pos..advance(pos, len)?).ok_or(DnsError::Malformed)
+}
+
+fn read_u8(msg: &[u8], pos: usize) -> Result<u8, DnsError> {
+    msg.get(pos).copied().ok_or(DnsError::Malformed)
+}
+
+fn read_u16(msg: &[u8], pos: usize) -> Result<u16, DnsError> {
+    match *slice(msg, pos, 2)? {
+        [high, low] => Ok(u16::from_be_bytes([high, low])),
+        _ => Err(DnsError::Malformed),
+    }
+}
+
+/// Position right after the (possibly compressed) name at `pos`.
+fn skip_name(msg: &[u8], mut pos: usize) -> Result<usize, DnsError> {
+    loop {
+        let len = read_u8(msg, pos)?;
+        match len {
+            0 => return advance(pos, 1),
+            l if l & 0xc0 == 0xc0 => return advance(pos, 2),
+            l if l & 0xc0 == 0 => pos = advance(pos, usize::from(l).saturating_add(1))?,
+            _ => return Err(DnsError::Malformed),
+        }
+    }
+}
+
+/// The (possibly compressed) name at `pos`, lowercased, without the trailing dot.
+fn read_name(msg: &[u8], mut pos: usize) -> Result<String, DnsError> {
+    let mut name = String::new();
+    let mut pointers = 0usize;
+    loop {
+        let len = read_u8(msg, pos)?;
+        match len {
+            0 => return Ok(name),
+            l if l & 0xc0 == 0xc0 => {
+                pointers = pointers.saturating_add(1);
+                if pointers > MAX_POINTERS {
+                    return Err(DnsError::Malformed);
+                }
+                pos = usize::from(u16::from_be_bytes([l & 0x3f, read_u8(msg, advance(pos, 1)?)?]));
+            }
+            l if l & 0xc0 == 0 => {
+                let label = slice(msg, advance(pos, 1)?, usize::from(l))?;
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                name.push_str(&String::from_utf8_lossy(label).to_ascii_lowercase());
+                if name.len() > MAX_NAME_LEN {
+                    return Err(DnsError::Malformed);
+                }
+                pos = advance(pos, usize::from(l).saturating_add(1))?;
+            }
+            _ => return Err(DnsError::Malformed),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Verification of clients claiming to be crawlers (`[security.bot_verification]`).
+//!
+//! The check the search engines document for their crawlers: the client IP's reverse DNS name
+//! must be under one of the crawler's domains, and that name must resolve back to the client
+//! IP. Outcomes are cached per client IP and crawler; lookups that fail or time out give
+//! [`BotVerdict::Unknown`], which is not cached and never blocks a request.
+
+pub mod dns;
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::config::{BotVerificationConfig, CrawlerConfig};
+use dns::{DnsError, Record, TYPE_A, TYPE_AAAA, TYPE_PTR};
+
+/// Outcome of verifying one client against the crawler it claims to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotVerdict {
+    /// Reverse and forward DNS agree on a host under the crawler's domains.
+    Verified,
+    /// The lookups completed and did not confirm the claim.
+    Spoofed,
+    /// The lookups failed or timed out.
+    Unknown,
+}
+
+impl BotVerdict {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BotVerdict::Verified => "verified",
+            BotVerdict::Spoofed => "spoofed",
+            BotVerdict::Unknown => "unknown",
+        }
+    }
+}
+
+/// First `nameserver` of `/etc/resolv.conf`, read once.
+pub(crate) static SYSTEM_RESOLVER: LazyLock<Option<SocketAddr>> = LazyLock::new(|| {
+    std::fs::read_to_string("/etc/resolv.conf")
+        .ok()?
+        .lines()
+        .find_map(|line| {
+            let mut words = line.split_whitespace();
+            if words.next()? != "nameserver" {
+                return None;
+            }
+            let ip: IpAddr = words.next()?.parse().ok()?;
+            Some(SocketAddr::new(ip, 53))
+        })
+});
+
+struct CachedVerdict {
+    verified: bool,
+    expires: Instant,
+}
+
+/// Verifies claimed crawlers and caches the outcomes; shared by every connection.
+pub struct BotVerifier {
+    cache: Mutex<HashMap<(IpAddr, String), CachedVerdict>>,
+}
+
+impl Default for BotVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BotVerifier {
+    pub fn new() -> Self {
+        Self { cache: Mutex::new(HashMap::new()) }
+    }
+
+    /// Verify that `ip` belongs to `crawler`, using `config`'s resolver, timeout and cache
+    /// limits.
+    pub async fn verify(
+        &self,
+        config: &BotVerificationConfig,
+        crawler: &CrawlerConfig,
+        ip: IpAddr,
+    ) -> BotVerdict {
+        // Dual-stack listeners report IPv4 clients as IPv4-mapped IPv6 addresses.
+        let ip = ip.to_canonical();
+        let key = (ip, crawler.name.clone());
+        if let Some(verdict) = self.cached(&key) {
+            return verdict;
+        }
+        let Some(resolver) = config.resolver_addr().or(*SYSTEM_RESOLVER) else {
+            return BotVerdict::Unknown;
+        };
+        let lookup = confirm(resolver, crawler, ip);
+        let verified =
+            match tokio::time::timeout(Duration::from_millis(config.timeout_ms), lookup).await {
+                Ok(Ok(verified)) => verified,
+                Ok(Err(_)) | Err(_) => return BotVerdict::Unknown,
+            };
+        self.store(config, key, verified);
+        if verified {
+            BotVerdict::Verified
+        } else {
+            BotVerdict::Spoofed
+        }
+    }
+
+    /// Number of cached outcomes, expired ones included until they are pruned.
+    pub fn cached_len(&self) -> usize {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    fn cached(&self, key: &(IpAddr, String)) -> Option<BotVerdict> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = cache.get(key).filter(|e| e.expires > Instant::now())?;
+        Some(if entry.verified {
+            BotVerdict::Verified
+        } else {
+            BotVerdict::Spoofed
+        })
+    }
+
+    /// Cache an outcome. A full cache drops its expired entries first; if it is still full the
+    /// outcome is not cached.
+    fn store(&self, config: &BotVerificationConfig, key: (IpAddr, String), verified: bool) {
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= config.cache_max_entries && !cache.contains_key(&key) {
+            cache.retain(|_, e| e.expires > now);
+            if cache.len() >= config.cache_max_entries {
+                return;
+            }
+        }
+        let expires = now
+            .checked_add(Duration::from_secs(config.cache_ttl_secs))
+            .unwrap_or(now);
+        cache.insert(key, CachedVerdict { verified, expires });
+    }
+}
+
+/// Reverse-resolve `ip`, then forward-resolve each name under the crawler's domains until one
+/// resolves back to `ip`.
+async fn confirm(
+    resolver: SocketAddr,
+    crawler: &CrawlerConfig,
+    ip: IpAddr,
+) -> Result<bool, DnsError> {
+    let forward_type = if ip.is_ipv4() { TYPE_A } else { TYPE_AAAA };
+    let names = dns::query(resolver, &dns::reverse_name(ip), TYPE_PTR).await?;
+    for host in names.iter().filter_map(Record::name) {
+        if !crawler.owns_host(host) {
+            continue;
+        }
+        let addrs = dns::query(resolver, host, forward_type).await?;
+        if addrs.iter().any(|r| r.addr() == Some(ip)) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,239 @@
+//! Tokens of the `[security.challenge]` cookie.
+//!
+//! The challenge page is given `<expires>.<mac>`: the Unix time the token expires at and the hex
+//! HMAC-SHA256, under the configured `secret`, of `v1\n<expires>\n<ja4>\n<client ip>`. Its script
+//! then looks for a proof of work: the first decimal `<nonce>` for which the SHA-256 of
+//! `<expires>.<mac>.<nonce>` starts with `difficulty` zero bits. That string is the token.
+//!
+//! Binding the JA4 and the client IP keeps a cookie earned by one browser from being replayed by
+//! a client with another TLS stack or from another address, and the proof of work makes every
+//! earned cookie cost CPU time.
+
+use std::fmt;
+use std::net::IpAddr;
+
+use aws_lc_rs::{digest, hmac};
+
+use crate::config::ChallengeConfig;
+use crate::security::fingerprint_filter::{find_match, FingerprintBlock, ObservedFingerprints};
+use crate::utils::hex;
+
+/// Version prefix of the signed message.
+const VERSION: &str = "v1";
+
+/// Longest accepted nonce, in digits: a `u64` counter.
+const MAX_NONCE_DIGITS: usize = 20;
+
+/// Challenge page served when neither `body` nor `body_file` is configured: finds the nonce,
+/// stores the token and reloads, so the original request is repeated with the cookie. SHA-256 is
+/// computed in script because `crypto.subtle` is missing on plain-HTTP pages.
+pub const DEFAULT_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="robots" content="noindex">
+<title>Checking your browser</title>
+</head>
+<body>
+<noscript>JavaScript is required to continue.</noscript>
+<script>
+const K = [
+  0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+  0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+  0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+  0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+  0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+  0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+  0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+  0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+const rotr = (x, n) => (x >>> n) | (x << (32 - n));
+
+// SHA-256 of an ASCII string, as eight 32-bit words.
+function sha256(text) {
+  const blocks = ((text.length + 8) >> 6) + 1;
+  const words = new Uint32Array(blocks * 16);
+  for (let i = 0; i < text.length; i++) {
+    words[i >> 2] |= text.charCodeAt(i) << (24 - (i & 3) * 8);
+  }
+  words[text.length >> 2] |= 0x80 << (24 - (text.length & 3) * 8);
+  words[blocks * 16 - 1] = text.length * 8;
+  const h = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+  const w = new Uint32Array(64);
+  for (let block = 0; block < words.length; block += 16) {
+    for (let i = 0; i < 64; i++) {
+      if (i < 16) {
+        w[i] = words[block + i];
+      } else {
+        const s0 = rotr(w[i - 15], 7) ^ rotr(w[i - 15], 18) ^ (w[i - 15] >>> 3);
+        const s1 = rotr(w[i - 2], 17) ^ rotr(w[i - 2], 19) ^ (w[i - 2] >>> 10);
+        w[i] = w[i - 16] + s0 + w[i - 7] + s1;
+      }
+    }
+    let [a, b, c, d, e, f, g, hh] = h;
+    for (let i = 0; i < 64; i++) {
+      const t1 = (hh + (rotr(e, 6) ^ rotr(e, 11) ^ rotr(e, 25)) + ((e & f) ^ (~e & g)) + K[i] + w[i]) | 0;
+      const t2 = ((rotr(a, 2) ^ rotr(a, 13) ^ rotr(a, 22)) + ((a & b) ^ (a & c) ^ (b & c))) | 0;
+      hh = g; g = f; f = e; e = (d + t1) | 0; d = c; c = b; b = a; a = (t1 + t2) | 0;
+    }
+    [a, b, c, d, e, f, g, hh].forEach((v, i) => { h[i] = (h[i] + v) | 0; });
+  }
+  return h;
+}
+
+function zeroBits(hash) {
+  let bits = 0;
+  for (const word of hash) {
+    const zeros = Math.clz32(word);
+    bits += zeros;
+    if (zeros < 32) break;
+  }
+  return bits;
+}
+
+let token = "{{challenge}}.0";
+for (let nonce = 1; zeroBits(sha256(token)) < {{difficulty}}; nonce++) {
+  token = "{{challenge}}." + nonce;
+}
+document.cookie = "{{cookie_name}}=" + token + "; Max-Age={{ttl_secs}}; Path=/; SameSite=Lax";
+location.reload();
+</script>
+</body>
+</html>
+"#;
+
+/// The `suspect` entry the request's fingerprints match, if any.
+pub fn find_suspect<'a>(
+    config: &'a ChallengeConfig,
+    observed: &ObservedFingerprints,
+) -> Option<FingerprintBlock<'a>> {
+    find_match(&config.suspect, observed)
+}
+
+/// Issues challenges and checks the tokens solved from them, with one secret.
+#[derive(Clone)]
+pub struct ChallengeTokens {
+    key: hmac::Key,
+    ttl_secs: u64,
+    difficulty: u8,
+}
+
+impl ChallengeTokens {
+    pub fn new(secret: &[u8], ttl_secs: u64, difficulty: u8) -> Self {
+        Self { key: hmac::Key::new(hmac::HMAC_SHA256, secret), ttl_secs, difficulty }
+    }
+
+    pub fn from_config(config: &ChallengeConfig) -> Self {
+        Self::new(config.secret.expose().as_bytes(), config.ttl_secs, config.difficulty)
+    }
+
+    /// Challenge for a client at `client_ip` with JA4 `ja4` (empty without TLS), whose token is
+    /// valid for `ttl_secs` from `now`.
+    pub fn issue(&self, ja4: &str, client_ip: IpAddr, now: u64) -> String {
+        let expires = now.saturating_add(self.ttl_secs);
+        let tag = hmac::sign(&self.key, &message(expires, ja4, client_ip));
+        format!("{expires}.{}", hex::encode(tag.as_ref()))
+    }
+
+    /// Whether `token` solves a challenge issued for `ja4` and `client_ip` that has not expired
+    /// at `now`. Tokens expiring further away than `ttl_secs` (issued before the TTL was lowered)
+    /// are refused too.
+    pub fn verify(&self, token: &str, ja4: &str, client_ip: IpAddr, now: u64) -> bool {
+        let Some((challenge, nonce)) = token.rsplit_once('.') else {
+            return false;
+        };
+        if nonce.is_empty()
+            || nonce.len() > MAX_NONCE_DIGITS
+            || !nonce.bytes().all(|b| b.is_ascii_digit())
+            || zero_bits(token) < u32::from(self.difficulty)
+        {
+            return false;
+        }
+        let Some((expires, mac)) = challenge.split_once('.') else {
+            return false;
+        };
+        let Ok(expires) = expires.parse::<u64>() else {
+            return false;
+        };
+        if expires <= now || expires > now.saturating_add(self.ttl_secs) {
+            return false;
+        }
+        let Some(mac) = hex::decode(mac) else {
+            return false;
+        };
+        hmac::verify(&self.key, &message(expires, ja4, client_ip), &mac).is_ok()
+    }
+}
+
+impl fmt::Debug for ChallengeTokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChallengeTokens")
+            .field("ttl_secs", &self.ttl_secs)
+            .field("difficulty", &self.difficulty)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Token for `challenge`: what the challenge page's script computes.
+pub fn solve(challenge: &str, difficulty: u8) -> String {
+    let mut nonce = 0u64;
+    loop {
+        let token = format!("{challenge}.{nonce}");
+        if zero_bits(&token) >= u32::from(difficulty) {
+            return token;
+        }
+        nonce = nonce.saturating_add(1);
+    }
+}
+
+/// Leading zero bits of the SHA-256 of `token`.
+fn zero_bits(token: &str) -> u32 {
+    let hash = digest::digest(&digest::SHA256, token.as_bytes());
+    let mut bits = 0u32;
+    for byte in hash.as_ref() {
+        bits = bits.saturating_add(byte.leading_zeros());
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+fn message(expires: u64, ja4: &str, client_ip: IpAddr) -> Vec<u8> {
+    format!("{VERSION}\n{expires}\n{ja4}\n{client_ip}").into_bytes()
+}
+
+/// Token key and page template of a [`ChallengeConfig`], built once per loaded config.
+#[derive(Clone)]
+pub struct PreparedChallenge {
+    pub tokens: ChallengeTokens,
+    /// `body`, the contents of `body_file` or [`DEFAULT_PAGE`], placeholders not yet replaced.
+    pub page: String,
+}
+
+impl PreparedChallenge {
+    /// Key and template of `config`, the page being `file_page` (the contents of `body_file`)
+    /// when given.
+    pub fn new(config: &ChallengeConfig, file_page: Option<String>) -> Self {
+        let page = file_page
+            .or_else(|| config.body.clone())
+            .unwrap_or_else(|| DEFAULT_PAGE.to_string());
+        Self { tokens: ChallengeTokens::from_config(config), page }
+    }
+}
+
+/// Compares the page only: the key is derived from `secret`, which the config compares itself.
+impl PartialEq for PreparedChallenge {
+    fn eq(&self, other: &Self) -> bool {
+        self.page == other.page
+    }
+}
+
+impl fmt::Debug for PreparedChallenge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreparedChallenge")
+            .field("tokens", &self.tokens)
+            .field("page", &self.page)
+            .finish()
+    }
+}
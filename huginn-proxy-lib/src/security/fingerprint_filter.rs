@@ -0,0 +1,93 @@
+use crate::config::{FingerprintFilterConfig, FingerprintList};
+use crate::fingerprinting::{Ja4Fingerprints, TcpObservation};
+
+/// Fingerprints observed on the connection carrying a request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObservedFingerprints {
+    /// Every JA4 variant of the TLS ClientHello (empty on plain HTTP).
+    pub ja4: Vec<String>,
+    /// HTTP/2 Akamai fingerprint (`None` on HTTP/1.x or when not extracted).
+    pub akamai: Option<String>,
+    /// TCP SYN signature (`None` when not captured).
+    pub tcp: Option<String>,
+}
+
+impl ObservedFingerprints {
+    pub fn new(
+        ja4: Option<&Ja4Fingerprints>,
+        akamai: Option<&str>,
+        tcp: Option<&TcpObservation>,
+    ) -> Self {
+        let ja4 = ja4
+            .map(|f| {
+                vec![
+                    f.ja4.full.to_string(),
+                    f.ja4.raw.to_string(),
+                    f.ja4_original.full.to_string(),
+                    f.ja4_original.raw.to_string(),
+                    f.ja4_stable_v1.full.to_string(),
+                    f.ja4_stable_v1.raw.to_string(),
+                ]
+            })
+            .unwrap_or_default();
+        Self { ja4, akamai: akamai.map(str::to_string), tcp: tcp.map(ToString::to_string) }
+    }
+
+    fn values(&self, kind: &str) -> &[String] {
+        match kind {
+            "ja4" => &self.ja4,
+            "akamai" => self.akamai.as_slice(),
+            "tcp" => self.tcp.as_slice(),
+            _ => &[],
+        }
+    }
+}
+
+/// The list entry a request matched: the `deny` entry that blocked it, or a challenge `suspect`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FingerprintBlock<'a> {
+    /// `ja4`, `akamai` or `tcp`.
+    pub kind: &'static str,
+    /// The configured entry, as written (bounded set, safe as a metric label).
+    pub entry: &'a str,
+}
+
+/// Check the observed fingerprints against the filter
+///
+/// Returns the matching `deny` entry when the request must be blocked, `None` otherwise.
+///
+/// # Logic:
+/// - Any `allow` match: never blocked
+/// - Otherwise the first `deny` match (in `ja4`, `akamai`, `tcp` order) blocks the request
+/// - Fingerprints that were not observed never match
+pub fn check_fingerprint_filter<'a>(
+    config: &'a FingerprintFilterConfig,
+    observed: &ObservedFingerprints,
+) -> Option<FingerprintBlock<'a>> {
+    if find_match(&config.allow, observed).is_some() {
+        return None;
+    }
+    find_match(&config.deny, observed)
+}
+
+pub(crate) fn find_match<'a>(
+    list: &'a FingerprintList,
+    observed: &ObservedFingerprints,
+) -> Option<FingerprintBlock<'a>> {
+    list.entries()
+        .find(|&(kind, entry)| {
+            observed
+                .values(kind)
+                .iter()
+                .any(|value| entry_matches(entry, value))
+        })
+        .map(|(kind, entry)| FingerprintBlock { kind, entry })
+}
+
+/// Exact match, or prefix match for entries ending with `*`.
+pub(crate) fn entry_matches(entry: &str, value: &str) -> bool {
+    match entry.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => value == entry,
+    }
+}
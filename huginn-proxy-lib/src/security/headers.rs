@@ -1,13 +1,56 @@
-use crate::config::SecurityHeaders;
+use crate::config::{SecurityHeaders, SecurityHeadersPreset};
 use http::{HeaderName, HeaderValue, Response};
 
+/// Headers a preset sets; `custom`, `hsts` and `csp` take precedence over them.
+struct Preset {
+    headers: &'static [(&'static str, &'static str)],
+    hsts: &'static str,
+    csp: Option<&'static str>,
+}
+
+/// Backend response headers stripped by every preset other than `off`.
+const PRESET_REMOVED: &[&str] = &["server", "x-powered-by"];
+
+const BALANCED: Preset = Preset {
+    headers: &[
+        ("x-content-type-options", "nosniff"),
+        ("x-frame-options", "SAMEORIGIN"),
+        ("referrer-policy", "strict-origin-when-cross-origin"),
+    ],
+    hsts: "max-age=31536000",
+    csp: None,
+};
+
+const STRICT: Preset = Preset {
+    headers: &[
+        ("x-content-type-options", "nosniff"),
+        ("x-frame-options", "DENY"),
+        ("referrer-policy", "no-referrer"),
+        ("cross-origin-opener-policy", "same-origin"),
+        ("cross-origin-resource-policy", "same-origin"),
+        ("permissions-policy", "camera=(), microphone=(), geolocation=(), payment=()"),
+    ],
+    hsts: "max-age=63072000; includeSubDomains",
+    csp: Some("default-src 'self'; base-uri 'self'; frame-ancestors 'none'; object-src 'none'"),
+};
+
+fn preset_for(preset: SecurityHeadersPreset) -> Option<&'static Preset> {
+    match preset {
+        SecurityHeadersPreset::Off => None,
+        SecurityHeadersPreset::Balanced => Some(&BALANCED),
+        SecurityHeadersPreset::Strict => Some(&STRICT),
+    }
+}
+
 /// Apply security headers to an HTTP response
 ///
 /// This function adds security headers to outgoing responses based on the provided configuration.
 /// Headers are applied in the following order:
-/// 1. Custom headers (user-defined)
-/// 2. HSTS (if enabled and connection is HTTPS)
-/// 3. CSP (if enabled)
+/// 1. Removal of backend headers (`remove`, plus `Server` / `X-Powered-By` with a preset)
+/// 2. Preset headers
+/// 3. Custom headers (user-defined)
+/// 4. HSTS (if enabled and connection is HTTPS, otherwise the preset's)
+/// 5. CSP (if enabled, otherwise the preset's)
 ///
 /// # Arguments
 /// * `response` - Mutable reference to the HTTP response
@@ -21,6 +64,21 @@ pub fn apply_security_headers<T>(
     let Some(config) = config else {
         return;
     };
+    let preset = preset_for(config.preset);
+
+    if preset.is_some() {
+        for name in PRESET_REMOVED {
+            response.headers_mut().remove(*name);
+        }
+    }
+    for name in &config.remove {
+        response.headers_mut().remove(name.as_str());
+    }
+    for (name, value) in preset.map_or(&[][..], |p| p.headers) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(name), HeaderValue::from_static(value));
+    }
 
     for header in &config.custom {
         if let (Ok(name), Ok(value)) = (
@@ -31,8 +89,13 @@ pub fn apply_security_headers<T>(
         }
     }
 
-    if is_https && config.hsts.enabled {
-        if let Ok(hsts_value) = build_hsts_header(&config.hsts) {
+    if is_https {
+        let hsts_value = if config.hsts.enabled {
+            build_hsts_header(&config.hsts).ok()
+        } else {
+            preset.map(|p| HeaderValue::from_static(p.hsts))
+        };
+        if let Some(hsts_value) = hsts_value {
             response
                 .headers_mut()
                 .insert(HeaderName::from_static("strict-transport-security"), hsts_value);
@@ -40,11 +103,21 @@ pub fn apply_security_headers<T>(
     }
 
     if config.csp.enabled {
-        if let Ok(csp_value) = HeaderValue::from_str(config.csp.policy.expose()) {
+        if let Ok(csp_value) = HeaderValue::from_str(&config.csp.header_value()) {
+            let name = if config.csp.report_only {
+                "content-security-policy-report-only"
+            } else {
+                "content-security-policy"
+            };
             response
                 .headers_mut()
-                .insert(HeaderName::from_static("content-security-policy"), csp_value);
+                .insert(HeaderName::from_static(name), csp_value);
         }
+    } else if let Some(csp) = preset.and_then(|p| p.csp) {
+        response.headers_mut().insert(
+            HeaderName::from_static("content-security-policy"),
+            HeaderValue::from_static(csp),
+        );
     }
 }
 
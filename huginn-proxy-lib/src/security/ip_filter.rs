@@ -1,4 +1,4 @@
-use crate::config::{IpFilterConfig, IpFilterMode};
+use crate::config::{IpFilterConfig, IpFilterMode, IpFilterPrecedence, TrustedProxiesConfig};
 use std::net::IpAddr;
 
 /// Check if an IP address is allowed based on the filter configuration
@@ -9,6 +9,9 @@ use std::net::IpAddr;
 /// - If mode is `IpFilterMode::Disabled`: always allow
 /// - If mode is `IpFilterMode::Allowlist`: allow only if IP matches allowlist
 /// - If mode is `IpFilterMode::Denylist`: block if IP matches denylist
+/// - If mode is `IpFilterMode::Both`: an IP matching both lists follows `precedence`; one
+///   matching a single list follows that list; one matching neither is blocked if the allowlist
+///   is non-empty, allowed otherwise
 pub fn is_ip_allowed(ip: IpAddr, config: &IpFilterConfig) -> bool {
     match config.mode {
         IpFilterMode::Disabled => true,
@@ -28,5 +31,30 @@ pub fn is_ip_allowed(ip: IpAddr, config: &IpFilterConfig) -> bool {
             }
             !config.denylist.iter().any(|net| net.contains(&ip))
         }
+        IpFilterMode::Both => {
+            let allowed = config.allowlist.iter().any(|net| net.contains(&ip));
+            let denied = config.denylist.iter().any(|net| net.contains(&ip));
+            match (allowed, denied) {
+                (true, true) => config.precedence == IpFilterPrecedence::Allow,
+                (true, false) => true,
+                (false, true) => false,
+                (false, false) => config.allowlist.is_empty(),
+            }
+        }
+    }
+}
+
+/// The address `config` is evaluated against: the TCP `peer_ip`, or with `use_forwarded_for`
+/// the client behind `trusted_proxies` (see [`TrustedProxiesConfig::client_ip`]).
+pub fn filtered_ip(
+    peer_ip: IpAddr,
+    headers: &http::HeaderMap,
+    config: &IpFilterConfig,
+    trusted_proxies: &TrustedProxiesConfig,
+) -> IpAddr {
+    if config.use_forwarded_for {
+        trusted_proxies.client_ip(peer_ip, headers)
+    } else {
+        peer_ip
     }
 }
@@ -1,7 +1,23 @@
+pub mod body_decode;
+pub mod bot_verification;
+pub mod challenge;
+pub mod fingerprint_filter;
 pub mod headers;
 pub mod ip_filter;
 pub mod rate_limit;
+pub mod tarpit;
+pub mod tls_metadata;
+pub mod waf;
 
+pub use body_decode::{decode_for_inspection, DecodeError, DecodeLimits};
+pub use bot_verification::{BotVerdict, BotVerifier};
+pub use challenge::{find_suspect, ChallengeTokens, PreparedChallenge};
+pub use fingerprint_filter::{check_fingerprint_filter, FingerprintBlock, ObservedFingerprints};
 pub use headers::apply_security_headers;
-pub use ip_filter::is_ip_allowed;
-pub use rate_limit::{extract_rate_limit_key, RateLimitManager, RateLimitResult};
+pub use ip_filter::{filtered_ip, is_ip_allowed};
+pub use rate_limit::{
+    extract_rate_limit_key, RateLimitFingerprints, RateLimitManager, RateLimitResult,
+};
+pub use tarpit::{graylisted, Tarpit, TarpitSlot};
+pub use tls_metadata::TlsMetadata;
+pub use waf::WafHit;
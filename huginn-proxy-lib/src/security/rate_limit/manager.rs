@@ -1,17 +1,72 @@
-use super::{RateLimitResult, RateLimiter};
-use crate::config::{Domain, LimitBy, RateLimitConfig, TrustedProxiesConfig};
+use super::{RateLimitResult, RateLimiter, RedisRateLimiter, RedisStore};
+use crate::config::{
+    Domain, LimitBy, RateLimitConfig, RateLimitStore, RedisStoreConfig, TrustedProxiesConfig,
+};
+use crate::security::TlsMetadata;
 use ahash::AHashMap;
-use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::Duration;
+use tracing::warn;
 
-/// Build the limiter for a fully-resolved config (`None` when disabled).
-fn build_limiter(config: &RateLimitConfig) -> Option<RateLimiter> {
-    if config.enabled {
-        let window = Duration::from_secs(config.window_seconds);
-        Some(RateLimiter::new(config.requests_per_second, config.burst, window))
-    } else {
-        None
+/// A configured limiter: in-process counters, or counters shared through Redis.
+enum Limiter {
+    Memory(RateLimiter),
+    Redis(RedisRateLimiter),
+}
+
+impl Limiter {
+    async fn check(&self, key: &str) -> RateLimitResult {
+        match self {
+            Limiter::Memory(limiter) => limiter.check(key),
+            Limiter::Redis(limiter) => limiter.check(key).await,
+        }
+    }
+}
+
+/// Redis stores of one manager, one per distinct `redis` block, so limiters pointing at the same
+/// server share its connections.
+#[derive(Default)]
+struct RedisStores(Vec<(RedisStoreConfig, Arc<RedisStore>)>);
+
+impl RedisStores {
+    fn get(&mut self, config: &RedisStoreConfig) -> Option<Arc<RedisStore>> {
+        if let Some((_, store)) = self.0.iter().find(|(c, _)| c == config) {
+            return Some(Arc::clone(store));
+        }
+        match RedisStore::new(config) {
+            Ok(store) => {
+                let store = Arc::new(store);
+                self.0.push((config.clone(), Arc::clone(&store)));
+                Some(store)
+            }
+            Err(e) => {
+                warn!(error = %e, "Invalid rate limit redis url, using in-memory counters");
+                None
+            }
+        }
+    }
+}
+
+/// Build the limiter for a fully-resolved config (`None` when disabled). `scope` names the
+/// limiter's counters in a shared store.
+fn build_limiter(
+    config: &RateLimitConfig,
+    scope: String,
+    stores: &mut RedisStores,
+) -> Option<Limiter> {
+    if !config.enabled {
+        return None;
     }
+    let window = Duration::from_secs(config.window_seconds);
+    let local = RateLimiter::new(config.requests_per_second, config.burst, window);
+    let store = match (config.store, &config.redis) {
+        (RateLimitStore::Redis, Some(redis)) => stores.get(redis),
+        _ => None,
+    };
+    Some(match store {
+        Some(store) => Limiter::Redis(RedisRateLimiter::new(store, scope, local)),
+        None => Limiter::Memory(local),
+    })
 }
 
 /// Manager for rate limiters (global, per-domain, and per-route).
@@ -22,19 +77,20 @@ fn build_limiter(config: &RateLimitConfig) -> Option<RateLimiter> {
 /// - Per-route rate limiting (`[domains.routes.security.rate_limit]`, whole-block replace).
 ///
 /// Limiters are keyed by domain label so the same route prefix under two different
-/// domains stays isolated. The manager is immutable after construction. Hot reload
+/// domains stays isolated. The domains that inherit their tenant's `rate_limit` share that
+/// tenant's limiter. The manager is immutable after construction. Hot reload
 /// swaps the entire manager atomically via `proxy::reload::SharedRateLimiter`.
 pub struct RateLimitManager {
     /// Global rate limiter (optional)
-    global: Option<RateLimiter>,
+    global: Option<Limiter>,
     /// Per-domain limiters keyed by domain label. Present only when the domain overrides
     /// rate limiting: `Some` = enabled limiter, `None` = explicitly disabled (does NOT fall
     /// through to the global limiter). Domains without an override are absent from the map.
-    domain_limiters: AHashMap<String, Option<RateLimiter>>,
+    domain_limiters: AHashMap<String, Option<Arc<Limiter>>>,
     /// Per-route limiters: domain label -> route prefix -> slot. Present only when the route
     /// overrides rate limiting: `Some` = enabled limiter, `None` = explicitly disabled (does NOT
     /// fall through to the domain/global limiter). Routes without an override are absent from the map.
-    route_limiters: AHashMap<String, AHashMap<String, Option<RateLimiter>>>,
+    route_limiters: AHashMap<String, AHashMap<String, Option<Limiter>>>,
 }
 
 impl RateLimitManager {
@@ -45,28 +101,50 @@ impl RateLimitManager {
     /// Each present override is recorded as an explicit slot (enabled limiter or explicit
     /// disable) so it never silently inherits the level it replaced.
     pub fn new(global_config: &RateLimitConfig, domains: &[Domain]) -> Self {
-        let global = build_limiter(global_config);
+        let mut stores = RedisStores::default();
+        let global = build_limiter(global_config, "global".to_string(), &mut stores);
 
         let mut domain_limiters = AHashMap::new();
-        let mut route_limiters: AHashMap<String, AHashMap<String, Option<RateLimiter>>> =
+        let mut tenant_limiters: AHashMap<&str, Option<Arc<Limiter>>> = AHashMap::new();
+        let mut route_limiters: AHashMap<String, AHashMap<String, Option<Limiter>>> =
             AHashMap::new();
 
         for domain in domains {
             let label = domain.label().to_string();
             let domain_override = domain.security.as_ref().and_then(|s| s.rate_limit.as_ref());
+            let tenant = domain.tenant.as_deref().filter(|_| {
+                domain
+                    .security
+                    .as_ref()
+                    .is_some_and(|s| s.tenant_rate_limit)
+            });
 
             // Record an explicit domain slot only when the domain overrides rate limiting, so a
             // disabled override (`enabled = false`) does not fall through to the global limiter.
             if let Some(cfg) = domain_override {
-                domain_limiters.insert(label.clone(), build_limiter(cfg));
+                let limiter = match tenant {
+                    Some(tenant) => tenant_limiters
+                        .entry(tenant)
+                        .or_insert_with(|| {
+                            build_limiter(cfg, format!("tenant:{tenant}"), &mut stores)
+                                .map(Arc::new)
+                        })
+                        .clone(),
+                    None => {
+                        build_limiter(cfg, format!("domain:{label}"), &mut stores).map(Arc::new)
+                    }
+                };
+                domain_limiters.insert(label.clone(), limiter);
             }
 
             for route in &domain.routes {
                 if let Some(cfg) = route.security.as_ref().and_then(|s| s.rate_limit.as_ref()) {
+                    let scope = format!("route:{label}:{}", route.prefix);
+                    let limiter = build_limiter(cfg, scope, &mut stores);
                     route_limiters
                         .entry(label.clone())
                         .or_default()
-                        .insert(route.prefix.clone(), build_limiter(cfg));
+                        .insert(route.prefix.clone(), limiter);
                 }
             }
         }
@@ -84,10 +162,13 @@ impl RateLimitManager {
     /// * `domain_label` - Matched domain label (for per-domain / per-route limiting)
     /// * `route_prefix` - Matched route prefix (for per-route limiting)
     ///
+    /// Limiters with `store = "redis"` make one Redis round trip, bounded by the store's
+    /// `timeout_ms`.
+    ///
     /// # Returns
     /// * `RateLimitResult::Allowed` if request is permitted
     /// * `RateLimitResult::Limited` if request exceeds rate limit
-    pub fn check(
+    pub async fn check(
         &self,
         key: &str,
         domain_label: &str,
@@ -99,7 +180,7 @@ impl RateLimitManager {
             if let Some(by_prefix) = self.route_limiters.get(domain_label) {
                 if let Some(slot) = by_prefix.get(prefix) {
                     return match slot {
-                        Some(limiter) => limiter.check(key),
+                        Some(limiter) => limiter.check(key).await,
                         None => {
                             RateLimitResult::Allowed { remaining: isize::MAX, limit: isize::MAX }
                         }
@@ -112,13 +193,13 @@ impl RateLimitManager {
         // disable allows the request, and neither falls through to the global limiter.
         if let Some(slot) = self.domain_limiters.get(domain_label) {
             return match slot {
-                Some(limiter) => limiter.check(key),
+                Some(limiter) => limiter.check(key).await,
                 None => RateLimitResult::Allowed { remaining: isize::MAX, limit: isize::MAX },
             };
         }
 
         match &self.global {
-            Some(global_limiter) => global_limiter.check(key),
+            Some(global_limiter) => global_limiter.check(key).await,
             None => RateLimitResult::Allowed { remaining: isize::MAX, limit: isize::MAX },
         }
     }
@@ -146,22 +227,17 @@ fn resolve_client_ip(
     headers: &http::HeaderMap,
     trusted_proxies: &TrustedProxiesConfig,
 ) -> String {
-    let peer_ip = peer.ip();
-    if !trusted_proxies.trusts(&peer_ip) {
-        return peer_ip.to_string();
-    }
-    if let Some(xff) = headers.get("x-forwarded-for") {
-        if let Ok(xff_str) = xff.to_str() {
-            for raw in xff_str.rsplit(',') {
-                if let Ok(ip) = raw.trim().parse::<IpAddr>() {
-                    if !trusted_proxies.trusts(&ip) {
-                        return ip.to_string();
-                    }
-                }
-            }
-        }
-    }
-    peer_ip.to_string()
+    trusted_proxies.client_ip(peer.ip(), headers).to_string()
+}
+
+/// Connection details available to the connection-keyed strategies (`ja4`, `akamai`, `ip+ja4`,
+/// `sni`, `alpn`). A missing value makes those strategies fall back to the client IP.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimitFingerprints<'a> {
+    /// SNI, ALPN protocol and JA4 of the TLS connection
+    pub tls: TlsMetadata<'a>,
+    /// Akamai fingerprint of the HTTP/2 connection
+    pub akamai: Option<&'a str>,
 }
 
 /// Extract rate limiting key from request context.
@@ -173,6 +249,7 @@ fn resolve_client_ip(
 /// * `header_name` - Custom header name (for `LimitBy::Header`)
 /// * `headers` - HTTP request headers
 /// * `trusted_proxies` - CIDRs whose XFF additions are trusted (see `resolve_client_ip`)
+/// * `fingerprints` - Connection details (for `LimitBy::Ja4`, `Akamai`, `IpJa4`, `Sni` and `Alpn`)
 ///
 /// # Returns
 /// Rate limiting key as a string
@@ -183,6 +260,7 @@ pub fn extract_rate_limit_key(
     header_name: Option<&str>,
     headers: &http::HeaderMap,
     trusted_proxies: &TrustedProxiesConfig,
+    fingerprints: RateLimitFingerprints<'_>,
 ) -> String {
     match limit_by {
         LimitBy::Ip => resolve_client_ip(peer, headers, trusted_proxies),
@@ -201,5 +279,32 @@ pub fn extract_rate_limit_key(
             let ip_str = resolve_client_ip(peer, headers, trusted_proxies);
             format!("{ip_str}:{route_prefix}")
         }
+        LimitBy::Ja4 => fingerprints
+            .tls
+            .ja4
+            .map(str::to_string)
+            .unwrap_or_else(|| resolve_client_ip(peer, headers, trusted_proxies)),
+        LimitBy::Akamai => fingerprints
+            .akamai
+            .map(str::to_string)
+            .unwrap_or_else(|| resolve_client_ip(peer, headers, trusted_proxies)),
+        LimitBy::IpJa4 => {
+            let ip_str = resolve_client_ip(peer, headers, trusted_proxies);
+            match fingerprints.tls.ja4 {
+                Some(ja4) => format!("{ip_str}|{ja4}"),
+                None => ip_str,
+            }
+        }
+        LimitBy::Sni => fingerprints
+            .tls
+            .sni
+            .filter(|sni| !sni.is_empty())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_else(|| resolve_client_ip(peer, headers, trusted_proxies)),
+        LimitBy::Alpn => fingerprints
+            .tls
+            .alpn
+            .map(str::to_string)
+            .unwrap_or_else(|| resolve_client_ip(peer, headers, trusted_proxies)),
     }
 }
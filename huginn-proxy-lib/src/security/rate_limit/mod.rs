@@ -7,8 +7,10 @@
 //! - [`RateLimiter`] (`limiter.rs`): high-level limiter that combines a
 //!   [`Rate`] tracker with limit enforcement and the result type
 //!   [`RateLimitResult`].
+//! - [`RedisRateLimiter`] (`redis_store.rs`): limiter for `store = "redis"`,
+//!   whose counters are shared by every replica through a [`RedisStore`].
 //! - [`RateLimitManager`] (`manager.rs`): registry of global and per-route
-//!   limiters plus key extraction (IP, header, route, combined).
+//!   limiters plus key extraction (IP, header, route, combined, fingerprints).
 //!
 //! # Example Usage
 //!
@@ -48,9 +50,11 @@
 
 mod limiter;
 mod manager;
+mod redis_store;
 
 pub use limiter::{RateLimitResult, RateLimiter};
-pub use manager::{extract_rate_limit_key, RateLimitManager};
+pub use manager::{extract_rate_limit_key, RateLimitFingerprints, RateLimitManager};
+pub use redis_store::{RedisRateLimiter, RedisStore};
 
 pub use pingora_limits::estimator::Estimator;
 pub use pingora_limits::rate::Rate;
@@ -0,0 +1,109 @@
+//! Redis counter store (`store = "redis"`): limits shared by every proxy replica.
+//!
+//! Each limiter keeps a sliding-window counter per key in Redis: the count of the current fixed
+//! window plus the previous window's count weighted by how much of it still overlaps the sliding
+//! window. The read-check-increment runs as a single Lua script, so replicas never race on a key.
+//!
+//! While Redis is unreachable or slower than `timeout_ms`, every limiter falls back to its own
+//! in-memory counters (per-process limits) and Redis is retried a second later.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use redis::{RedisResult, Script};
+
+use super::{RateLimitResult, RateLimiter};
+use crate::config::RedisStoreConfig;
+use crate::utils::redis::RedisConnections;
+use crate::utils::time::unix_millis;
+
+/// `KEYS[1]`: current window counter, `KEYS[2]`: previous window counter.
+/// `ARGV`: limit, window length (ms), time elapsed in the current window (ms).
+/// Returns `{allowed (0|1), requests in the sliding window}`.
+const SLIDING_WINDOW_SCRIPT: &str = r"
+local current = tonumber(redis.call('GET', KEYS[1]) or '0')
+local previous = tonumber(redis.call('GET', KEYS[2]) or '0')
+local limit = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local elapsed = tonumber(ARGV[3])
+local count = math.floor(previous * (window - elapsed) / window) + current
+if count >= limit then
+  return {0, count}
+end
+redis.call('INCR', KEYS[1])
+redis.call('PEXPIRE', KEYS[1], window * 2)
+return {1, count + 1}
+";
+
+/// Connections to one Redis server, shared by every limiter configured with the same
+/// [`RedisStoreConfig`].
+pub struct RedisStore {
+    redis: RedisConnections,
+    script: Script,
+}
+
+impl RedisStore {
+    /// Parse the URL; no connection is opened until the first request.
+    pub fn new(config: &RedisStoreConfig) -> RedisResult<Self> {
+        Ok(Self {
+            redis: RedisConnections::new(config, "Rate limit")?,
+            script: Script::new(SLIDING_WINDOW_SCRIPT),
+        })
+    }
+}
+
+/// Limiter whose counters live in a [`RedisStore`], with in-memory counters as fallback.
+pub struct RedisRateLimiter {
+    store: Arc<RedisStore>,
+    /// Separates the counters of limiters sharing a store (global, per-domain, per-route).
+    scope: String,
+    local: RateLimiter,
+}
+
+impl RedisRateLimiter {
+    /// `local` sets the limit and window, and decides while Redis is unreachable.
+    pub fn new(store: Arc<RedisStore>, scope: String, local: RateLimiter) -> Self {
+        Self { store, scope, local }
+    }
+
+    /// Record a request for `key` and return whether it is allowed.
+    pub async fn check(&self, key: &str) -> RateLimitResult {
+        match self.store.redis.run(self.check_shared(key)).await {
+            Some(result) => result,
+            None => self.local.check(key),
+        }
+    }
+
+    async fn check_shared(&self, key: &str) -> RedisResult<RateLimitResult> {
+        let limit = self.local.max_requests();
+        let window_ms = u64::try_from(self.local.window().as_millis())
+            .unwrap_or(u64::MAX)
+            .max(1);
+        let now = unix_millis();
+        let index = now.checked_div(window_ms).unwrap_or_default();
+        let elapsed = now.checked_rem(window_ms).unwrap_or_default();
+        // The hash tag keeps both windows of a key in the same Redis Cluster slot.
+        let base = format!("{}{{{}|{key}}}", self.store.redis.key_prefix(), self.scope);
+
+        let mut connection = self.store.redis.connection().await?;
+        let mut invocation = self.store.script.prepare_invoke();
+        invocation
+            .key(format!("{base}:{index}"))
+            .key(format!("{base}:{}", index.saturating_sub(1)))
+            .arg(limit)
+            .arg(window_ms)
+            .arg(elapsed);
+        let (allowed, count): (i64, i64) = invocation.invoke_async(&mut connection).await?;
+
+        let count = isize::try_from(count).unwrap_or(isize::MAX);
+        Ok(if allowed == 1 {
+            RateLimitResult::Allowed { limit, remaining: limit.saturating_sub(count) }
+        } else {
+            RateLimitResult::Limited {
+                limit,
+                remaining: 0,
+                reset_after: Duration::from_millis(window_ms.saturating_sub(elapsed)),
+            }
+        })
+    }
+}
@@ -0,0 +1,68 @@
+//! ClientHello tarpit (`[security.tarpit]`).
+//!
+//! A TLS connection from a graylisted IP or JA4 has its ClientHello answered only after the
+//! configured delay. The [`Tarpit`] counts the connections waiting, so a flood of graylisted
+//! clients cannot pin more than `max_concurrent` of them; the rest are served without delay.
+
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::fingerprint_filter::{entry_matches, ObservedFingerprints};
+use crate::config::TarpitConfig;
+use crate::telemetry::metrics::values;
+
+/// Why a connection from `ip` whose ClientHello gave `observed` is graylisted: `ip` or `ja4`
+/// (`kind` label of `huginn_tarpit_connections_total`).
+pub fn graylisted(
+    config: &TarpitConfig,
+    ip: IpAddr,
+    observed: &ObservedFingerprints,
+) -> Option<&'static str> {
+    if config.lists_ip(&ip) {
+        return Some(values::TARPIT_IP);
+    }
+    config
+        .ja4
+        .iter()
+        .any(|entry| observed.ja4.iter().any(|value| entry_matches(entry, value)))
+        .then_some(values::TARPIT_JA4)
+}
+
+/// Connections currently held in the tarpit; shared by every listener.
+#[derive(Debug, Default)]
+pub struct Tarpit {
+    waiting: AtomicUsize,
+}
+
+/// A place in the tarpit, given back when dropped.
+#[derive(Debug)]
+pub struct TarpitSlot<'a> {
+    tarpit: &'a Tarpit,
+}
+
+impl Tarpit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of connections waiting.
+    pub fn waiting(&self) -> usize {
+        self.waiting.load(Ordering::Relaxed)
+    }
+
+    /// Take a place when fewer than `max_concurrent` connections are waiting.
+    pub fn try_enter(&self, max_concurrent: usize) -> Option<TarpitSlot<'_>> {
+        self.waiting
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |waiting| {
+                (waiting < max_concurrent).then(|| waiting.saturating_add(1))
+            })
+            .ok()
+            .map(|_| TarpitSlot { tarpit: self })
+    }
+}
+
+impl Drop for TarpitSlot<'_> {
+    fn drop(&mut self) {
+        self.tarpit.waiting.fetch_sub(1, Ordering::AcqRel);
+    }
+}
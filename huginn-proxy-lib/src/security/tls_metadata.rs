@@ -0,0 +1,14 @@
+//! TLS details of the connection carrying a request, as seen by the security checks.
+
+/// What the TLS handshake of a request's connection told the proxy, gathered once per request so
+/// the rate limiter, the IP filter and the WAF decide on the same values. Every field is `None` on
+/// a plaintext connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TlsMetadata<'a> {
+    /// SNI sent by the client
+    pub sni: Option<&'a str>,
+    /// ALPN protocol agreed on
+    pub alpn: Option<&'a str>,
+    /// JA4 of the ClientHello, when TLS fingerprinting is enabled
+    pub ja4: Option<&'a str>,
+}
@@ -0,0 +1,252 @@
+//! `[security.waf]` rule engine.
+//!
+//! A request is checked against the method and header limits first, then the built-in rule sets,
+//! the inline rules and the rules of `rules_files`, in that order; the first hit is reported.
+//! Paths and query strings are percent-decoded before matching, bodies are decompressed
+//! ([`decode_for_inspection`]) and, for form posts, percent-decoded too.
+
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::sync::LazyLock;
+
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, Method, Uri};
+use regex::Regex;
+
+use super::body_decode::{decode_for_inspection, DecodeLimits};
+use super::TlsMetadata;
+use crate::config::{WafConfig, WafRuleSet, WafTarget};
+use crate::utils::http::percent_decode;
+
+/// Rule id reported when the method is not in `allowed_methods`.
+pub const RULE_METHOD: &str = "limit:method";
+/// Rule id reported when a request carries more than `max_headers` headers.
+pub const RULE_HEADER_COUNT: &str = "limit:header_count";
+/// Rule id reported when a header is larger than `max_header_bytes`.
+pub const RULE_HEADER_SIZE: &str = "limit:header_size";
+
+/// The rule that matched a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WafHit<'a> {
+    /// Built-in (`sqli:union`, ...), configured or limit rule id. Bounded by the config, safe as
+    /// a metric label.
+    pub rule: &'a str,
+}
+
+const URL: &[WafTarget] = &[WafTarget::Path, WafTarget::Query];
+const URL_AND_BODY: &[WafTarget] = &[WafTarget::Path, WafTarget::Query, WafTarget::Body];
+
+/// Built-in rules: id, set, targets, pattern. Ids carry a `:`, which configured ids cannot, so
+/// the two never collide.
+const BUILTIN_PATTERNS: &[(&str, WafRuleSet, &[WafTarget], &str)] = &[
+    (
+        "sqli:union",
+        WafRuleSet::Sqli,
+        URL_AND_BODY,
+        r"(?i)\bunion(?:\s|/\*.*?\*/)+(?:all(?:\s|/\*.*?\*/)+|distinct(?:\s|/\*.*?\*/)+)?select\b",
+    ),
+    (
+        "sqli:tautology",
+        WafRuleSet::Sqli,
+        URL_AND_BODY,
+        r#"(?i)['"]\s*(?:or|and)\s+(?:['"]?\w+['"]?\s*(?:=|<>|!=|like\b)\s*['"]?\w+|true\b|\d+\s*--)"#,
+    ),
+    ("sqli:comment", WafRuleSet::Sqli, URL_AND_BODY, r"'\s*(?:--(?:\s|$)|#|/\*)"),
+    (
+        "sqli:stacked",
+        WafRuleSet::Sqli,
+        URL_AND_BODY,
+        r"(?i);\s*(?:drop\s+(?:table|database)|truncate\s+table|delete\s+from|insert\s+into|update\s+\w+\s+set|shutdown)\b",
+    ),
+    (
+        "sqli:functions",
+        WafRuleSet::Sqli,
+        URL_AND_BODY,
+        r"(?i)\b(?:sleep|benchmark|pg_sleep|load_file|extractvalue|updatexml)\s*\(|\bwaitfor\s+delay\b",
+    ),
+    ("xss:script_tag", WafRuleSet::Xss, URL_AND_BODY, r"(?i)<\s*/?\s*script\b"),
+    (
+        "xss:event_handler",
+        WafRuleSet::Xss,
+        URL_AND_BODY,
+        r"(?i)<[^>]*[\s/]on[a-z]+\s*=",
+    ),
+    (
+        "xss:js_uri",
+        WafRuleSet::Xss,
+        URL_AND_BODY,
+        r#"(?i)(?:^|[=('"])\s*(?:javascript|vbscript|livescript)\s*:"#,
+    ),
+    (
+        "xss:embed_tags",
+        WafRuleSet::Xss,
+        URL_AND_BODY,
+        r"(?i)<\s*(?:iframe|object|embed|svg|base|meta|applet)\b",
+    ),
+    (
+        "traversal:dot_dot",
+        WafRuleSet::PathTraversal,
+        URL,
+        r"(?:^|[/\\=])\.\.(?:[/\\]|$)",
+    ),
+    (
+        "traversal:sensitive_files",
+        WafRuleSet::PathTraversal,
+        URL,
+        r"(?i)/etc/(?:passwd|shadow|group|hosts)\b|(?:^|[/\\])(?:\.env|\.git|\.htaccess|\.htpasswd|boot\.ini|win\.ini)(?:[/\\?]|$)",
+    ),
+];
+
+struct BuiltinRule {
+    id: &'static str,
+    set: WafRuleSet,
+    targets: &'static [WafTarget],
+    regex: Regex,
+}
+
+static BUILTIN_RULES: LazyLock<Vec<BuiltinRule>> = LazyLock::new(|| {
+    BUILTIN_PATTERNS
+        .iter()
+        .filter_map(|&(id, set, targets, pattern)| {
+            Regex::new(pattern)
+                .ok()
+                .map(|regex| BuiltinRule { id, set, targets, regex })
+        })
+        .collect()
+});
+
+/// Ids of the built-in rules of `set`.
+pub fn builtin_rule_ids(set: WafRuleSet) -> impl Iterator<Item = &'static str> {
+    BUILTIN_RULES
+        .iter()
+        .filter(move |rule| rule.set == set)
+        .map(|rule| rule.id)
+}
+
+/// Check a request against `config`.
+///
+/// `body` is the part of the body read ahead (at most `max_body_bytes`), or `None` when it was
+/// not read; `body` rules then never match. `tls` describes the request's connection for the
+/// `sni`, `alpn` and `ja4` targets. Returns the first rule that matched.
+pub fn inspect<'a>(
+    config: &'a WafConfig,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: Option<&[u8]>,
+    tls: TlsMetadata<'_>,
+) -> Option<WafHit<'a>> {
+    if let Some(rule) = check_limits(config, method, headers) {
+        return Some(WafHit { rule });
+    }
+    let request = Inspected::new(uri, headers, body, tls);
+    BUILTIN_RULES
+        .iter()
+        .filter(|rule| config.rule_sets.contains(&rule.set))
+        .find(|rule| request.matches(&rule.regex, rule.targets))
+        .map(|rule| rule.id)
+        .or_else(|| {
+            config
+                .rules
+                .iter()
+                .chain(&config.file_rules)
+                .find(|rule| request.matches(rule.pattern.regex(), &rule.targets))
+                .map(|rule| rule.id.as_str())
+        })
+        .map(|rule| WafHit { rule })
+}
+
+fn check_limits(config: &WafConfig, method: &Method, headers: &HeaderMap) -> Option<&'static str> {
+    if !config.allowed_methods.is_empty()
+        && !config.allowed_methods.iter().any(|m| m == method.as_str())
+    {
+        return Some(RULE_METHOD);
+    }
+    if config.max_headers.is_some_and(|max| headers.len() > max) {
+        return Some(RULE_HEADER_COUNT);
+    }
+    if let Some(max) = config.max_header_bytes {
+        if headers
+            .iter()
+            .any(|(name, value)| name.as_str().len().saturating_add(value.len()) > max)
+        {
+            return Some(RULE_HEADER_SIZE);
+        }
+    }
+    None
+}
+
+/// The targets of one request, decoded once and only when a rule needs them.
+struct Inspected<'r> {
+    path: String,
+    query: Option<String>,
+    headers: &'r HeaderMap,
+    raw_body: Option<&'r [u8]>,
+    body: OnceCell<Option<String>>,
+    tls: TlsMetadata<'r>,
+}
+
+impl<'r> Inspected<'r> {
+    fn new(
+        uri: &Uri,
+        headers: &'r HeaderMap,
+        body: Option<&'r [u8]>,
+        tls: TlsMetadata<'r>,
+    ) -> Self {
+        Self {
+            path: lossy(percent_decode(uri.path().as_bytes(), false)),
+            query: uri
+                .query()
+                .map(|q| lossy(percent_decode(q.as_bytes(), true))),
+            headers,
+            raw_body: body,
+            body: OnceCell::new(),
+            tls,
+        }
+    }
+
+    fn matches(&self, regex: &Regex, targets: &[WafTarget]) -> bool {
+        targets.iter().any(|target| match target {
+            WafTarget::Path => regex.is_match(&self.path),
+            WafTarget::Query => self.query.as_deref().is_some_and(|q| regex.is_match(q)),
+            WafTarget::Headers => self
+                .headers
+                .values()
+                .any(|v| regex.is_match(&String::from_utf8_lossy(v.as_bytes()))),
+            WafTarget::Body => self.body().is_some_and(|body| regex.is_match(body)),
+            WafTarget::Sni => regex.is_match(self.tls.sni.unwrap_or("")),
+            WafTarget::Alpn => regex.is_match(self.tls.alpn.unwrap_or("")),
+            WafTarget::Ja4 => regex.is_match(self.tls.ja4.unwrap_or("")),
+        })
+    }
+
+    fn body(&self) -> Option<&str> {
+        self.body
+            .get_or_init(|| {
+                let raw = self.raw_body.filter(|b| !b.is_empty())?;
+                // A body that does not decode (truncated at `max_body_bytes`, or over the decode
+                // limits) is matched as received.
+                let decoded = decode_for_inspection(self.headers, raw, &DecodeLimits::default())
+                    .unwrap_or(Cow::Borrowed(raw));
+                let form = self
+                    .headers
+                    .get(CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|ct| {
+                        ct.trim_start()
+                            .to_ascii_lowercase()
+                            .starts_with("application/x-www-form-urlencoded")
+                    });
+                Some(if form {
+                    lossy(percent_decode(&decoded, true))
+                } else {
+                    String::from_utf8_lossy(&decoded).into_owned()
+                })
+            })
+            .as_deref()
+    }
+}
+
+fn lossy(bytes: Cow<'_, [u8]>) -> String {
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -0,0 +1,481 @@
+//! Structured access log (`[access_log]`): one JSON object per line for every request.
+//!
+//! Records are built on the request path and handed to a dedicated writer thread through a
+//! bounded queue, so a slow disk never stalls a connection. When the queue is full the record is
+//! dropped and the writer reports how many were lost with a `warn!`.
+//!
+//! Connection-level fields (connection id, client IP, SNI, JA4, Akamai, TCP SYN) are read from the
+//! connection's [`ConnectionContext`], held by an [`AccessLogContext`]; each request takes a [`PendingAccess`] from it before it is handled and
+//! completes it with the response that was sent.
+//!
+//! Routes with an `access_log` block keep only a sampled fraction of their records, with a
+//! separate rate for error responses.
+//!
+//! The same context starts and ends the request's OpenTelemetry span when `[telemetry.tracing]`
+//! is enabled (see [`crate::telemetry::spans`]), so both see identical fields, and counts the
+//! request's fingerprints for `[telemetry.fingerprint_stats]` and checks its JA4 for
+//! `[telemetry.anomaly_detection]`. With `[event_stream]` enabled it
+//! also publishes the request's [`FingerprintEvent`], unaffected by the route's sampling.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use http::{Request, Response, Version};
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::config::{AccessLogConfig, AccessLogField, AccessLogOutput, RouteAccessLogConfig};
+use crate::error::Result;
+use crate::proxy::connection::ConnectionContext;
+use crate::proxy::handler::extract_request_host_inner;
+use crate::proxy::request_id::RequestId;
+use crate::telemetry::event_stream::{EventStream, FingerprintEvent};
+use crate::telemetry::rotating_file::RotatingFile;
+use crate::telemetry::spans::{RequestSpan, RequestTracer};
+use crate::telemetry::{AnomalyDetector, FingerprintStats};
+
+/// Records waiting for the writer thread; past this the newest record is dropped.
+const QUEUE_CAPACITY: usize = 8192;
+
+/// Handle to the access log writer. A disabled logger (the default) records nothing.
+#[derive(Clone, Default)]
+pub struct AccessLogger {
+    inner: Option<Arc<LoggerInner>>,
+}
+
+struct LoggerInner {
+    tx: SyncSender<AccessRecord>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AccessLogger {
+    /// Open the configured output and start the writer thread. Returns a disabled logger when
+    /// `config.enabled` is false.
+    pub fn from_config(config: &AccessLogConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::disabled());
+        }
+        let sink = match (config.output, &config.path) {
+            (AccessLogOutput::File, Some(path)) => {
+                Sink::File(RotatingFile::open(path, config.max_size_bytes, config.max_files)?)
+            }
+            _ => Sink::Stdout(BufWriter::new(io::stdout())),
+        };
+        let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer =
+            Writer { rx, sink, fields: config.fields.clone(), dropped: Arc::clone(&dropped) };
+        std::thread::Builder::new()
+            .name("access-log".to_string())
+            .spawn(move || writer.run())?;
+        Ok(Self { inner: Some(Arc::new(LoggerInner { tx, dropped })) })
+    }
+
+    /// A logger that records nothing.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Queue `record` for writing; drops it when the queue is full.
+    pub fn log(&self, record: AccessRecord) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = inner.tx.try_send(record) {
+            inner.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// One access log record. Which fields are written is decided by `access_log.fields`.
+#[derive(Debug, Clone)]
+pub struct AccessRecord {
+    pub timestamp: SystemTime,
+    /// ID assigned by `[request_id]`, when enabled.
+    pub request_id: Option<String>,
+    /// Id of the [`ConnectionContext`] the request came in on.
+    pub connection_id: u64,
+    pub client_ip: IpAddr,
+    pub sni: Option<Arc<str>>,
+    pub host: String,
+    pub method: String,
+    /// Request path, without the query string.
+    pub path: String,
+    pub protocol: Version,
+    pub status: u16,
+    pub duration: Duration,
+    pub bytes_in: Option<u64>,
+    pub bytes_out: Option<u64>,
+    pub backend: Option<String>,
+    /// Tenant of the matched domain, when it belongs to one.
+    pub tenant: Option<String>,
+    pub ja4: Option<Arc<str>>,
+    pub akamai: Option<String>,
+    pub tcp_syn: Option<Arc<str>>,
+}
+
+impl AccessRecord {
+    /// The JSON line for this record (without the trailing newline), with `fields` in order.
+    pub fn to_json(&self, fields: &[AccessLogField]) -> String {
+        serde_json::to_string(&RecordView { record: self, fields }).unwrap_or_default()
+    }
+}
+
+struct RecordView<'a> {
+    record: &'a AccessRecord,
+    fields: &'a [AccessLogField],
+}
+
+impl Serialize for RecordView<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let r = self.record;
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for field in self.fields {
+            match field {
+                AccessLogField::Timestamp => {
+                    map.serialize_entry("timestamp", &format_rfc3339_millis(r.timestamp))?
+                }
+                AccessLogField::RequestId => map.serialize_entry("request_id", &r.request_id)?,
+                AccessLogField::ConnectionId => {
+                    map.serialize_entry("connection_id", &r.connection_id)?
+                }
+                AccessLogField::ClientIp => map.serialize_entry("client_ip", &r.client_ip)?,
+                AccessLogField::Sni => map.serialize_entry("sni", &r.sni.as_deref())?,
+                AccessLogField::Host => map.serialize_entry("host", &r.host)?,
+                AccessLogField::Method => map.serialize_entry("method", &r.method)?,
+                AccessLogField::Path => map.serialize_entry("path", &r.path)?,
+                AccessLogField::Protocol => {
+                    map.serialize_entry("protocol", &format!("{:?}", r.protocol))?
+                }
+                AccessLogField::Status => map.serialize_entry("status", &r.status)?,
+                AccessLogField::DurationMs => {
+                    map.serialize_entry("duration_ms", &(r.duration.as_micros() as f64 / 1000.0))?
+                }
+                AccessLogField::BytesIn => map.serialize_entry("bytes_in", &r.bytes_in)?,
+                AccessLogField::BytesOut => map.serialize_entry("bytes_out", &r.bytes_out)?,
+                AccessLogField::Backend => map.serialize_entry("backend", &r.backend)?,
+                AccessLogField::Tenant => map.serialize_entry("tenant", &r.tenant)?,
+                AccessLogField::Ja4 => map.serialize_entry("ja4", &r.ja4.as_deref())?,
+                AccessLogField::Akamai => map.serialize_entry("akamai", &r.akamai)?,
+                AccessLogField::TcpSyn => map.serialize_entry("tcp_syn", &r.tcp_syn.as_deref())?,
+            }
+        }
+        map.end()
+    }
+}
+
+/// Access log, tracing and fingerprint telemetry of one client connection, whose connection-level
+/// fields come from its [`ConnectionContext`].
+#[derive(Clone)]
+pub struct AccessLogContext {
+    logger: AccessLogger,
+    tracer: RequestTracer,
+    fingerprint_stats: FingerprintStats,
+    anomalies: AnomalyDetector,
+    events: EventStream,
+    connection: Arc<ConnectionContext>,
+}
+
+impl AccessLogContext {
+    /// `connection` is read when each request completes, so TLS details and fingerprints set on
+    /// it later in the connection's life are recorded too.
+    pub fn new(logger: AccessLogger, connection: Arc<ConnectionContext>) -> Self {
+        Self {
+            logger,
+            tracer: RequestTracer::disabled(),
+            fingerprint_stats: FingerprintStats::disabled(),
+            anomalies: AnomalyDetector::disabled(),
+            events: EventStream::disabled(),
+            connection,
+        }
+    }
+
+    /// Also start a span for every request.
+    pub fn with_tracer(mut self, tracer: RequestTracer) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    /// Also count every request's fingerprints.
+    pub fn with_fingerprint_stats(mut self, stats: FingerprintStats) -> Self {
+        self.fingerprint_stats = stats;
+        self
+    }
+
+    /// Also check every request's JA4 for anomalies.
+    pub fn with_anomaly_detector(mut self, anomalies: AnomalyDetector) -> Self {
+        self.anomalies = anomalies;
+        self
+    }
+
+    /// Also publish every request's fingerprint event.
+    pub fn with_event_stream(mut self, events: EventStream) -> Self {
+        self.events = events;
+        self
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.logger.is_enabled()
+            || self.tracer.is_enabled()
+            || self.fingerprint_stats.is_enabled()
+            || self.anomalies.is_enabled()
+            || self.events.is_enabled()
+    }
+
+    /// The connection whose requests this context records.
+    pub fn connection(&self) -> &Arc<ConnectionContext> {
+        &self.connection
+    }
+
+    /// Start timing `req` and start its span, which rewrites the request's trace context
+    /// headers. Call it after the request ID is assigned, so the record and span carry it. `None`
+    /// when the access log, tracing, fingerprint stats and event stream are all disabled.
+    pub fn start<B>(&self, req: &mut Request<B>) -> Option<PendingAccess> {
+        if !self.is_enabled() {
+            return None;
+        }
+        Some(PendingAccess {
+            span: self.tracer.start(req),
+            ctx: self.clone(),
+            started: Instant::now(),
+            timestamp: SystemTime::now(),
+            request_id: req
+                .extensions()
+                .get::<RequestId>()
+                .map(|id| id.as_str().to_string()),
+            host: extract_request_host_inner(req),
+            method: req.method().to_string(),
+            path: req.uri().path().to_string(),
+            protocol: req.version(),
+            bytes_in: content_length(req.headers()),
+        })
+    }
+}
+
+/// A request whose record is written once its response is known.
+pub struct PendingAccess {
+    ctx: AccessLogContext,
+    span: Option<RequestSpan>,
+    started: Instant,
+    timestamp: SystemTime,
+    request_id: Option<String>,
+    host: String,
+    method: String,
+    path: String,
+    protocol: Version,
+    bytes_in: Option<u64>,
+}
+
+/// What the request handler learned about a request that its access record and span need.
+#[derive(Debug, Default)]
+pub struct RequestLog {
+    /// Tenant of the matched domain; `None` for a domain that belongs to no tenant.
+    pub tenant: Option<String>,
+    /// Prefix of the matched route.
+    pub route: Option<String>,
+    /// Backend selected for the request, including when the request then failed.
+    pub backend: Option<String>,
+    /// Sampling of the matched route; `None` keeps every record.
+    pub sampling: Option<RouteAccessLogConfig>,
+}
+
+impl PendingAccess {
+    /// Record the request with the `response` sent to the client and what the handler reported
+    /// in `log`, end its span, count its fingerprints and publish its event. The record is skipped
+    /// when the route's sampling does not keep this response; the span and event never are.
+    pub fn finish<B>(self, response: &Response<B>, log: RequestLog) {
+        let status = response.status().as_u16();
+        let ctx = self.ctx;
+        let connection = &ctx.connection;
+        let client_ip = connection.peer().ip();
+        let ja4 = connection.ja4();
+        let akamai = (self.protocol == Version::HTTP_2)
+            .then(|| connection.akamai())
+            .flatten();
+        ctx.fingerprint_stats
+            .observe(client_ip, ja4.map(|set| &*set.ja4), akamai.as_deref());
+        if let Some(ja4) = ja4 {
+            ctx.anomalies.observe(&ja4.ja4);
+        }
+        let keep = ctx.logger.is_enabled()
+            && log
+                .sampling
+                .is_none_or(|sampling| sampled(sampling.rate_for(status)));
+        if !keep && self.span.is_none() && !ctx.events.is_enabled() {
+            return;
+        }
+        let record = AccessRecord {
+            timestamp: self.timestamp,
+            request_id: self.request_id,
+            connection_id: connection.id(),
+            client_ip,
+            sni: connection.tls().and_then(|tls| tls.sni.clone()),
+            host: self.host,
+            method: self.method,
+            path: self.path,
+            protocol: self.protocol,
+            status,
+            duration: self.started.elapsed(),
+            bytes_in: self.bytes_in,
+            bytes_out: content_length(response.headers()),
+            backend: log.backend,
+            tenant: log.tenant,
+            ja4: ja4.map(|set| Arc::clone(&set.ja4)),
+            akamai,
+            tcp_syn: connection.tcp_syn().map(Arc::from),
+        };
+        if let Some(span) = self.span {
+            span.end(&record, log.route.as_deref());
+        }
+        if ctx.events.is_enabled() {
+            ctx.events.publish(FingerprintEvent {
+                timestamp: format_rfc3339_millis(record.timestamp),
+                request_id: record.request_id.clone(),
+                client_ip: record.client_ip,
+                sni: record.sni.as_deref().map(str::to_string),
+                host: record.host.clone(),
+                ja4: record.ja4.as_deref().map(str::to_string),
+                ja4_o: ja4.map(|set| set.ja4_o.to_string()),
+                ja4_s1: ja4.map(|set| set.ja4_s1.to_string()),
+                akamai: record.akamai.clone(),
+                tcp_syn: record.tcp_syn.as_deref().map(str::to_string),
+                backend: record.backend.clone(),
+                status,
+            });
+        }
+        if keep {
+            ctx.logger.log(record);
+        }
+    }
+}
+
+/// Keep a record with probability `rate`. The draw comes from a randomly keyed hasher, which is
+/// plenty for sampling and avoids a dependency on a random number generator.
+pub(crate) fn sampled(rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+    let draw = RandomState::new().build_hasher().finish();
+    (draw as f64) < rate * u64::MAX as f64
+}
+
+fn content_length(headers: &http::HeaderMap) -> Option<u64> {
+    headers
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+enum Sink {
+    Stdout(BufWriter<io::Stdout>),
+    File(RotatingFile),
+}
+
+impl Sink {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Stdout(out) => out.write_all(line),
+            Self::File(file) => file.write_record(line),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(out) => out.flush(),
+            Self::File(file) => file.flush(),
+        }
+    }
+}
+
+struct Writer {
+    rx: Receiver<AccessRecord>,
+    sink: Sink,
+    fields: Vec<AccessLogField>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Writer {
+    /// Write records until every [`AccessLogger`] is gone, flushing whenever the queue empties.
+    fn run(mut self) {
+        while let Ok(record) = self.rx.recv() {
+            self.write(&record);
+            while let Ok(record) = self.rx.try_recv() {
+                self.write(&record);
+            }
+            if let Err(e) = self.sink.flush() {
+                warn!(error = %e, "access log: flush failed");
+            }
+            let dropped = self.dropped.swap(0, Ordering::Relaxed);
+            if dropped > 0 {
+                warn!(dropped, "access log: queue full, records dropped");
+            }
+        }
+    }
+
+    fn write(&mut self, record: &AccessRecord) {
+        let mut line = record.to_json(&self.fields).into_bytes();
+        line.push(b'\n');
+        if let Err(e) = self.sink.write_line(&line) {
+            warn!(error = %e, "access log: write failed");
+        }
+    }
+}
+
+/// `2026-01-02T03:04:05.678Z`
+pub(crate) fn format_rfc3339_millis(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Gregorian date of `days` since 1970-01-01 (H. Hinnant's `civil_from_days`, unsigned form).
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days.saturating_add(719_468);
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = doe
+        .saturating_sub(doe / 1460)
+        .saturating_add(doe / 36_524)
+        .saturating_sub(doe / 146_096)
+        / 365;
+    let doy = doe.saturating_sub(
+        yoe.saturating_mul(365)
+            .saturating_add(yoe / 4)
+            .saturating_sub(yoe / 100),
+    );
+    let mp = doy.saturating_mul(5).saturating_add(2) / 153;
+    let day = doy
+        .saturating_sub(mp.saturating_mul(153).saturating_add(2) / 5)
+        .saturating_add(1);
+    let month = if mp < 10 {
+        mp.saturating_add(3)
+    } else {
+        mp.saturating_sub(9)
+    };
+    let year = yoe
+        .saturating_add(era.saturating_mul(400))
+        .saturating_add(u64::from(month <= 2));
+    (year, month, day)
+}
@@ -0,0 +1,545 @@
+//! Authenticated admin API, served by the observability server under `/admin/` when
+//! `[telemetry.admin]` is enabled.
+//!
+//! - `GET /admin/config`: effective, secret-redacted config (same view as `--print-effective-config`)
+//! - `GET /admin/backends`: configured backends with probe and drain state
+//! - `POST /admin/backends/{address}/drain` / `undrain`: take a backend out of (or back into)
+//!   rotation; `{address}` is percent-encoded when it contains `/` (unix sockets). A backend
+//!   drained by `drain = true` in the config cannot be undrained here (`409`)
+//! - `POST /admin/routes`: add or remove a route (JSON [`RouteChange`] body)
+//! - `GET /admin/connections`: open client connections, their fingerprints and traffic
+//! - `GET /admin/log_level` / `PUT /admin/log_level`: tracing filter and per-route level
+//!   overrides (JSON [`LogLevelChange`] body)
+//! - `GET /admin/synthetic` / `PUT /admin/synthetic`: routes with a `synthetic` response and
+//!   whether it is served; switch one on or off (JSON [`SyntheticChange`] body)
+//! - `POST /admin/drain`: drain and stop the proxy like SIGTERM (`/ready` turns 503, listeners
+//!   close after `timeout.drain_delay_secs`, in-flight connections finish), e.g. once a new
+//!   process sharing the ports through `listen.reuse_port` is ready
+//!
+//! Runtime mutations live in memory only: the next config reload replaces routes with the file's
+//! content, admin drains last until `undrain` or a restart, and log levels and synthetic switches
+//! last until a restart.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Limited};
+use hyper::body::Body;
+use hyper::{Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::backend::HealthRegistry;
+use crate::config::{
+    sort_routes, validate_route, validate_route_shadowing, Domain, DynamicConfig,
+    EffectiveConfigView, FingerprintFormat, Route, StaticConfig,
+};
+use crate::proxy::connection::ConnectionInfo;
+use crate::proxy::reload::SharedDynamicConfig;
+use crate::proxy::runtime::RuntimeHandles;
+use crate::telemetry::LogLevelError;
+use crate::utils::http::{json_error, json_response, percent_decode_utf8, RespBody};
+
+/// Largest accepted `POST /admin/routes` or `PUT /admin/log_level` body.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Everything the admin endpoints read or mutate; shared with the running proxy.
+#[derive(Clone)]
+pub struct AdminState {
+    token: Arc<str>,
+    static_cfg: Arc<StaticConfig>,
+    dynamic_cfg: SharedDynamicConfig,
+    health: Arc<HealthRegistry>,
+    runtime: RuntimeHandles,
+}
+
+impl AdminState {
+    /// The bearer token is taken from `static_cfg.telemetry.admin.token`.
+    pub fn new(
+        static_cfg: Arc<StaticConfig>,
+        dynamic_cfg: SharedDynamicConfig,
+        health: Arc<HealthRegistry>,
+        runtime: RuntimeHandles,
+    ) -> Self {
+        let token = Arc::from(static_cfg.telemetry.admin.token.expose().as_str());
+        Self { token, static_cfg, dynamic_cfg, health, runtime }
+    }
+}
+
+/// Body of `POST /admin/routes`, tagged by `action`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RouteChange {
+    /// Add `route` to the domain whose `host` matches (omit `host` for the catch-all domain).
+    Add {
+        #[serde(default)]
+        host: Option<String>,
+        route: Box<Route>,
+    },
+    /// Remove the route with `prefix` from the domain whose `host` matches.
+    Remove {
+        #[serde(default)]
+        host: Option<String>,
+        prefix: String,
+    },
+}
+
+/// Body of `PUT /admin/log_level`. Without `prefix`, `level` replaces the global filter
+/// (`EnvFilter` syntax); with it, `level` overrides the route of the domain whose `host` matches,
+/// and `null` clears the override.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogLevelChange {
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub level: Option<String>,
+}
+
+/// Body of `PUT /admin/synthetic`: serve (`true`) or stop serving (`false`) the `synthetic`
+/// response of the route with `prefix` on the domain whose `host` matches; `null` goes back to the
+/// route's configured `enabled`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyntheticChange {
+    #[serde(default)]
+    pub host: Option<String>,
+    pub prefix: String,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Error)]
+pub enum RouteChangeError {
+    #[error("no domain with host {0:?}")]
+    UnknownDomain(Option<String>),
+    #[error("domain {domain} has no route with prefix '{prefix}'")]
+    UnknownRoute { domain: String, prefix: String },
+    #[error("domain {domain} already has a route with prefix '{prefix}'")]
+    DuplicateRoute { domain: String, prefix: String },
+    #[error("route '{prefix}' of domain {domain} has no synthetic response")]
+    NoSyntheticResponse { domain: String, prefix: String },
+    #[error(
+        "route '{0}': security.rate_limit cannot be set at runtime, add it through the config file"
+    )]
+    RateLimitOverride(String),
+    #[error(transparent)]
+    Invalid(#[from] crate::error::ProxyError),
+}
+
+impl RouteChangeError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::UnknownDomain(_)
+            | Self::UnknownRoute { .. }
+            | Self::NoSyntheticResponse { .. } => StatusCode::NOT_FOUND,
+            Self::DuplicateRoute { .. } => StatusCode::CONFLICT,
+            Self::RateLimitOverride(_) | Self::Invalid(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Apply `change` to a copy of `current`. The added route goes through the same validation as a
+/// route loaded from the config file. Per-route rate limits are rejected: the rate limiter is only
+/// rebuilt by config reloads.
+pub fn apply_route_change(
+    static_cfg: &StaticConfig,
+    current: &DynamicConfig,
+    change: RouteChange,
+) -> Result<DynamicConfig, RouteChangeError> {
+    let mut domains: Vec<Domain> = current.domains.as_ref().clone();
+    match change {
+        RouteChange::Add { host, route } => {
+            let route = *route;
+            let domain = find_domain(&mut domains, host)?;
+            if route
+                .security
+                .as_ref()
+                .is_some_and(|s| s.rate_limit.is_some())
+            {
+                return Err(RouteChangeError::RateLimitOverride(route.prefix));
+            }
+            if domain.routes.iter().any(|r| r.prefix == route.prefix) {
+                return Err(RouteChangeError::DuplicateRoute {
+                    domain: domain.label().to_string(),
+                    prefix: route.prefix,
+                });
+            }
+            validate_route(domain, &route, &current.backends, static_cfg.cache.max_size_bytes)?;
+            if route.fingerprint_format == Some(FingerprintFormat::Jwt)
+                && !static_cfg.fingerprint.signing.enabled
+            {
+                return Err(crate::error::ProxyError::Config(format!(
+                    "route '{}': fingerprint_format = \"jwt\" requires fingerprint.signing",
+                    route.prefix
+                ))
+                .into());
+            }
+            domain.routes.push(route);
+            validate_route_shadowing(domain)?;
+            sort_routes(&mut domain.routes);
+        }
+        RouteChange::Remove { host, prefix } => {
+            let domain = find_domain(&mut domains, host)?;
+            let before = domain.routes.len();
+            domain.routes.retain(|r| r.prefix != prefix);
+            if domain.routes.len() == before {
+                return Err(RouteChangeError::UnknownRoute {
+                    domain: domain.label().to_string(),
+                    prefix,
+                });
+            }
+        }
+    }
+    Ok(DynamicConfig { domains: Arc::new(domains), ..current.clone() })
+}
+
+fn find_domain(
+    domains: &mut [Domain],
+    host: Option<String>,
+) -> Result<&mut Domain, RouteChangeError> {
+    domains
+        .iter_mut()
+        .find(|d| d.host == host)
+        .ok_or(RouteChangeError::UnknownDomain(host))
+}
+
+#[derive(Serialize)]
+struct BackendEntry<'a> {
+    address: &'a str,
+    /// Probe result; `None` when the backend has no `health_check`.
+    healthy: Option<bool>,
+    drained: bool,
+    /// Drained by `drain = true` in the config rather than (only) by the admin API.
+    drained_by_config: bool,
+    /// Share of its traffic the backend gets, below `1.0` during its slow start.
+    weight: f64,
+}
+
+#[derive(Serialize)]
+struct BackendsBody<'a> {
+    backends: Vec<BackendEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct ConnectionsBody {
+    count: usize,
+    connections: Vec<ConnectionInfo>,
+}
+
+#[derive(Serialize)]
+struct SyntheticEntry<'a> {
+    host: Option<&'a str>,
+    prefix: &'a str,
+    status: u16,
+    /// `enabled` of the route's `synthetic` block.
+    configured: bool,
+    /// Runtime switch set through `PUT /admin/synthetic`, if any.
+    #[serde(rename = "override")]
+    override_enabled: Option<bool>,
+    /// Whether requests are currently answered with the synthetic response.
+    active: bool,
+}
+
+#[derive(Serialize)]
+struct SyntheticBody<'a> {
+    routes: Vec<SyntheticEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct ChangeBody<'a> {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<&'a str>,
+}
+
+/// Serve one `/admin/...` request.
+pub async fn handle_admin<B>(req: Request<B>, state: &AdminState) -> Response<RespBody>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    if !authorized(&req, &state.token) {
+        let mut response = json_error(StatusCode::UNAUTHORIZED, "missing or invalid bearer token");
+        response.headers_mut().insert(
+            hyper::header::WWW_AUTHENTICATE,
+            hyper::header::HeaderValue::from_static("Bearer"),
+        );
+        return response;
+    }
+
+    let path = req.uri().path().to_string();
+    let segments: Vec<&str> = path
+        .trim_start_matches("/admin")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    match (req.method(), segments.as_slice()) {
+        (&Method::GET, ["config"]) => {
+            let dynamic = state.dynamic_cfg.load();
+            json_response(StatusCode::OK, EffectiveConfigView::new(&state.static_cfg, &dynamic))
+        }
+        (&Method::GET, ["backends"]) => backends_response(state),
+        (&Method::POST, ["backends", id, action @ ("drain" | "undrain")]) => {
+            drain_response(state, id, *action == "drain")
+        }
+        (&Method::GET, ["connections"]) => {
+            let connections = state.runtime.connections.snapshot();
+            json_response(StatusCode::OK, ConnectionsBody { count: connections.len(), connections })
+        }
+        (&Method::POST, ["routes"]) => routes_response(req.into_body(), state).await,
+        (&Method::GET, ["log_level"]) => match state.runtime.log_levels.snapshot() {
+            Some(snapshot) => json_response(StatusCode::OK, snapshot),
+            None => log_level_error(&LogLevelError::Unavailable),
+        },
+        (&Method::PUT, ["log_level"]) => log_level_response(req.into_body(), state).await,
+        (&Method::GET, ["synthetic"]) => synthetic_list_response(state),
+        (&Method::PUT, ["synthetic"]) => synthetic_response(req.into_body(), state).await,
+        (&Method::POST, ["drain"]) => proxy_drain_response(state),
+        (
+            _,
+            ["config" | "backends" | "connections" | "routes" | "log_level" | "synthetic" | "drain", ..],
+        ) => json_error(StatusCode::METHOD_NOT_ALLOWED, "method not allowed"),
+        _ => json_error(StatusCode::NOT_FOUND, "not found"),
+    }
+}
+
+/// Constant-time comparison of the `Authorization: Bearer` credential against `token`.
+fn authorized<B>(req: &Request<B>, token: &str) -> bool {
+    let Some(presented) = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    let (a, b) = (presented.as_bytes(), token.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn backends_response(state: &AdminState) -> Response<RespBody> {
+    let dynamic = state.dynamic_cfg.load();
+    let backends = dynamic
+        .backends
+        .iter()
+        .map(|b| BackendEntry {
+            address: b.address.as_str(),
+            healthy: state.health.probe_status(&b.address),
+            drained: state.health.is_drained(&b.address),
+            drained_by_config: state.health.is_drained_by_config(&b.address),
+            weight: state.health.slow_start_weight(&b.address),
+        })
+        .collect();
+    json_response(StatusCode::OK, BackendsBody { backends })
+}
+
+fn drain_response(state: &AdminState, id: &str, drain: bool) -> Response<RespBody> {
+    let Some(address) = percent_decode_utf8(id) else {
+        return json_error(StatusCode::BAD_REQUEST, "invalid percent-encoding in backend id");
+    };
+    let dynamic = state.dynamic_cfg.load();
+    if !dynamic.backends.iter().any(|b| b.address == address) {
+        return json_error(StatusCode::NOT_FOUND, &format!("unknown backend '{address}'"));
+    }
+    if !drain && state.health.is_drained_by_config(&address) {
+        return json_error(
+            StatusCode::CONFLICT,
+            &format!("backend '{address}' is drained by its config; set drain = false and reload"),
+        );
+    }
+    let changed = if drain {
+        state.health.drain(&address)
+    } else {
+        state.health.undrain(&address)
+    };
+    let status = match (drain, changed) {
+        (true, true) => "drained",
+        (true, false) => "already_drained",
+        (false, true) => "undrained",
+        (false, false) => "not_drained",
+    };
+    info!(backend = %address, status, "Admin API: backend drain state changed");
+    json_response(StatusCode::OK, ChangeBody { status, address: Some(&address) })
+}
+
+fn proxy_drain_response(state: &AdminState) -> Response<RespBody> {
+    let status = if state.runtime.drain.request() {
+        "draining"
+    } else {
+        "already_draining"
+    };
+    info!(status, "Admin API: proxy drain requested");
+    json_response(StatusCode::ACCEPTED, ChangeBody { status, address: None })
+}
+
+async fn routes_response<B>(body: B, state: &AdminState) -> Response<RespBody>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let change: RouteChange = match read_json(body).await {
+        Ok(change) => change,
+        Err(response) => return response,
+    };
+
+    // Same lock as file reloads, so a concurrent reload cannot interleave with this swap.
+    let _guard = state.runtime.reload_mutex.lock().await;
+    let current = state.dynamic_cfg.load_full();
+    match apply_route_change(&state.static_cfg, &current, change) {
+        Ok(updated) => {
+            state.dynamic_cfg.store(Arc::new(updated));
+            state.runtime.config_generation.bump();
+            info!("Admin API: routes updated, draining open connections");
+            json_response(StatusCode::OK, ChangeBody { status: "applied", address: None })
+        }
+        Err(e) => {
+            warn!(error = %e, "Admin API: route change rejected");
+            json_error(e.status(), &e.to_string())
+        }
+    }
+}
+
+async fn log_level_response<B>(body: B, state: &AdminState) -> Response<RespBody>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let change: LogLevelChange = match read_json(body).await {
+        Ok(change) => change,
+        Err(response) => return response,
+    };
+    let log_levels = &state.runtime.log_levels;
+    let result = match (&change.prefix, &change.level) {
+        (None, None) => {
+            return json_error(StatusCode::BAD_REQUEST, "level is required without a prefix");
+        }
+        (None, Some(filter)) => log_levels.set_filter(filter),
+        (Some(prefix), level) => {
+            let dynamic = state.dynamic_cfg.load();
+            let Some(domain) = dynamic.domains.iter().find(|d| d.host == change.host) else {
+                let e = RouteChangeError::UnknownDomain(change.host);
+                return json_error(e.status(), &e.to_string());
+            };
+            if !domain.routes.iter().any(|r| &r.prefix == prefix) {
+                let e = RouteChangeError::UnknownRoute {
+                    domain: domain.label().to_string(),
+                    prefix: prefix.clone(),
+                };
+                return json_error(e.status(), &e.to_string());
+            }
+            log_levels.set_route_level(domain.label(), prefix, level.as_deref())
+        }
+    };
+    match (result, log_levels.snapshot()) {
+        (Ok(()), Some(snapshot)) => {
+            info!(
+                host = ?change.host,
+                prefix = ?change.prefix,
+                level = ?change.level,
+                "Admin API: log level changed"
+            );
+            json_response(StatusCode::OK, snapshot)
+        }
+        (Ok(()), None) => log_level_error(&LogLevelError::Unavailable),
+        (Err(e), _) => {
+            warn!(error = %e, "Admin API: log level change rejected");
+            log_level_error(&e)
+        }
+    }
+}
+
+fn synthetic_list_response(state: &AdminState) -> Response<RespBody> {
+    let dynamic = state.dynamic_cfg.load();
+    let switches = &state.runtime.synthetic;
+    let routes = dynamic
+        .domains
+        .iter()
+        .flat_map(|d| d.routes.iter().map(move |r| (d, r)))
+        .filter_map(|(domain, route)| {
+            let config = route.synthetic.as_ref()?;
+            let override_enabled = switches.get(domain.label(), &route.prefix);
+            Some(SyntheticEntry {
+                host: domain.host.as_deref(),
+                prefix: &route.prefix,
+                status: config.status,
+                configured: config.enabled,
+                override_enabled,
+                active: override_enabled.unwrap_or(config.enabled),
+            })
+        })
+        .collect();
+    json_response(StatusCode::OK, SyntheticBody { routes })
+}
+
+async fn synthetic_response<B>(body: B, state: &AdminState) -> Response<RespBody>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let change: SyntheticChange = match read_json(body).await {
+        Ok(change) => change,
+        Err(response) => return response,
+    };
+    let dynamic = state.dynamic_cfg.load();
+    let Some(domain) = dynamic.domains.iter().find(|d| d.host == change.host) else {
+        let e = RouteChangeError::UnknownDomain(change.host);
+        return json_error(e.status(), &e.to_string());
+    };
+    let Some(route) = domain.routes.iter().find(|r| r.prefix == change.prefix) else {
+        let e = RouteChangeError::UnknownRoute {
+            domain: domain.label().to_string(),
+            prefix: change.prefix,
+        };
+        return json_error(e.status(), &e.to_string());
+    };
+    if route.synthetic.is_none() {
+        let e = RouteChangeError::NoSyntheticResponse {
+            domain: domain.label().to_string(),
+            prefix: change.prefix,
+        };
+        return json_error(e.status(), &e.to_string());
+    }
+    state
+        .runtime
+        .synthetic
+        .set(domain.label(), &route.prefix, change.enabled);
+    info!(
+        host = ?change.host,
+        prefix = %route.prefix,
+        enabled = ?change.enabled,
+        "Admin API: synthetic response switched"
+    );
+    synthetic_list_response(state)
+}
+
+fn log_level_error(error: &LogLevelError) -> Response<RespBody> {
+    let status = match error {
+        LogLevelError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        LogLevelError::InvalidFilter { .. }
+        | LogLevelError::InvalidLevel(_)
+        | LogLevelError::InvalidRoute(_) => StatusCode::BAD_REQUEST,
+        LogLevelError::Reload(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    json_error(status, &error.to_string())
+}
+
+/// Read a JSON body of at most [`MAX_BODY_BYTES`]; the error is the `400` response to send.
+async fn read_json<T, B>(body: B) -> Result<T, Response<RespBody>>
+where
+    T: serde::de::DeserializeOwned,
+    B: Body<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let bytes = match Limited::new(body, MAX_BODY_BYTES).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => {
+            return Err(json_error(StatusCode::BAD_REQUEST, &format!("failed to read body: {e}")));
+        }
+    };
+    serde_json::from_slice(&bytes)
+        .map_err(|e| json_error(StatusCode::BAD_REQUEST, &format!("invalid body: {e}")))
+}
@@ -0,0 +1,251 @@
+//! Fingerprint anomaly detection (`[telemetry.anomaly_detection]`): flags a JA4 fingerprint
+//! whose share of traffic suddenly grows, the way an emerging bot campaign shows up at the edge.
+//!
+//! Requests are counted per JA4, and in total, over a short and a long window with the sliding
+//! window [`Rate`] estimator the rate limiter uses, so memory does not grow with the number of
+//! distinct fingerprints. A fingerprint is anomalous when, in the current short window, it has
+//! at least `min_requests` requests and `min_share` of all requests, and that share is at least
+//! `min_growth` times its share over the last complete long window. Until one long window has
+//! passed there is no baseline and nothing is reported.
+//!
+//! Each anomaly is logged with `warn!`, counted in `huginn_fingerprint_anomalies_total` and, when
+//! `webhook_url` is set, POSTed to it as JSON. The same fingerprint is reported again only after
+//! `cooldown_secs`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::Bytes;
+use http::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use http::{Request, Uri};
+use http_body_util::Full;
+use pingora_limits::rate::Rate;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::config::AnomalyDetectionConfig;
+use crate::error::Result;
+use crate::telemetry::access_log::format_rfc3339_millis;
+use crate::telemetry::event_stream::{http_client, parse_url, secret_header, HttpClient};
+use crate::telemetry::fingerprint_stats::KIND_JA4;
+use crate::telemetry::Metrics;
+
+/// Time a webhook call may take before the anomaly is only logged.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Shared anomaly detector. A disabled instance (the default) observes nothing.
+#[derive(Clone, Default)]
+pub struct AnomalyDetector {
+    inner: Option<Arc<DetectorInner>>,
+}
+
+struct DetectorInner {
+    short: Rate,
+    short_total: Rate,
+    long: Rate,
+    long_total: Rate,
+    short_window: Duration,
+    long_window: Duration,
+    min_share: f64,
+    min_growth: f64,
+    min_requests: u64,
+    cooldown: Duration,
+    /// Fingerprints reported within `cooldown`, with the time of the report.
+    reported: Mutex<HashMap<String, Instant>>,
+    webhook: Option<Webhook>,
+    metrics: Arc<Metrics>,
+}
+
+/// A fingerprint whose share of traffic grew past the thresholds.
+#[derive(Debug, Clone, Serialize)]
+pub struct FingerprintAnomaly {
+    /// Time of the report, RFC 3339 UTC with milliseconds.
+    pub timestamp: String,
+    /// Fingerprint kind, `ja4`.
+    pub kind: &'static str,
+    pub fingerprint: String,
+    /// Requests with the fingerprint in the current short window.
+    pub requests: u64,
+    /// Share of the current short window's requests, `0.0`–`1.0`.
+    pub share: f64,
+    /// Share of the last complete long window's requests; `0.0` for a fingerprint not seen then.
+    pub baseline_share: f64,
+    pub short_window_secs: u64,
+    pub long_window_secs: u64,
+}
+
+impl AnomalyDetector {
+    /// Returns a disabled instance when `config.enabled` is false.
+    pub fn from_config(config: &AnomalyDetectionConfig, metrics: Arc<Metrics>) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::disabled());
+        }
+        let short_window = Duration::from_secs(config.short_window_secs);
+        let long_window = Duration::from_secs(config.long_window_secs);
+        let webhook = config
+            .webhook_url
+            .as_deref()
+            .map(|url| Webhook::new(url, config))
+            .transpose()?;
+        let inner = DetectorInner {
+            short: Rate::new(short_window),
+            short_total: Rate::new(short_window),
+            long: Rate::new(long_window),
+            long_total: Rate::new(long_window),
+            short_window,
+            long_window,
+            min_share: config.min_share,
+            min_growth: config.min_growth,
+            min_requests: config.min_requests,
+            cooldown: Duration::from_secs(config.cooldown_secs),
+            reported: Mutex::new(HashMap::new()),
+            webhook,
+            metrics,
+        };
+        Ok(Self { inner: Some(Arc::new(inner)) })
+    }
+
+    /// An instance that observes nothing.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Count one request with JA4 fingerprint `ja4`. Returns the anomaly when this request made
+    /// `ja4` cross the thresholds and it was reported; the webhook call, if any, runs in the
+    /// background.
+    pub fn observe(&self, ja4: &str) -> Option<FingerprintAnomaly> {
+        let inner = self.inner.as_ref()?;
+        let requests = inner.short.observe(&ja4, 1);
+        let total = inner.short_total.observe(&(), 1);
+        inner.long.observe(&ja4, 1);
+        inner.long_total.observe(&(), 1);
+
+        let requests = u64::try_from(requests).unwrap_or_default();
+        if requests < inner.min_requests || total <= 0 {
+            return None;
+        }
+        let share = requests as f64 / total as f64;
+        if share < inner.min_share {
+            return None;
+        }
+        // Rates of the last complete long window; none yet during the first one.
+        let long_total = inner.long_total.rate(&());
+        if long_total <= 0.0 {
+            return None;
+        }
+        let baseline_share = inner.long.rate(&ja4) / long_total;
+        if share < baseline_share * inner.min_growth {
+            return None;
+        }
+        if !inner.claim_report(ja4) {
+            return None;
+        }
+
+        let anomaly = FingerprintAnomaly {
+            timestamp: format_rfc3339_millis(SystemTime::now()),
+            kind: KIND_JA4,
+            fingerprint: ja4.to_string(),
+            requests,
+            share,
+            baseline_share,
+            short_window_secs: inner.short_window.as_secs(),
+            long_window_secs: inner.long_window.as_secs(),
+        };
+        warn!(
+            kind = anomaly.kind,
+            fingerprint = %anomaly.fingerprint,
+            requests = anomaly.requests,
+            share = anomaly.share,
+            baseline_share = anomaly.baseline_share,
+            "fingerprint anomaly: share of traffic grew past the thresholds"
+        );
+        inner.metrics.record_fingerprint_anomaly(anomaly.kind);
+        if let Some(webhook) = &inner.webhook {
+            webhook.notify(&anomaly);
+        }
+        Some(anomaly)
+    }
+}
+
+impl DetectorInner {
+    /// Whether `fingerprint` may be reported now; if so, starts its cooldown. Expired entries are
+    /// removed, so the map only holds fingerprints reported within the cooldown.
+    fn claim_report(&self, fingerprint: &str) -> bool {
+        let Ok(mut reported) = self.reported.lock() else {
+            return false;
+        };
+        if reported
+            .get(fingerprint)
+            .is_some_and(|at| at.elapsed() < self.cooldown)
+        {
+            return false;
+        }
+        reported.retain(|_, at| at.elapsed() < self.cooldown);
+        reported.insert(fingerprint.to_string(), Instant::now());
+        true
+    }
+}
+
+/// POSTs each anomaly as a JSON object.
+struct Webhook {
+    client: HttpClient,
+    uri: Uri,
+    authorization: Option<HeaderValue>,
+}
+
+impl Webhook {
+    fn new(url: &str, config: &AnomalyDetectionConfig) -> Result<Self> {
+        let uri = parse_url("telemetry.anomaly_detection.webhook_url", url)?;
+        let authorization = config
+            .webhook_token
+            .as_ref()
+            .map(|token| {
+                secret_header(
+                    "telemetry.anomaly_detection.webhook_token",
+                    format!("Bearer {}", token.expose()),
+                )
+            })
+            .transpose()?;
+        Ok(Self { client: http_client(&uri, None)?, uri, authorization })
+    }
+
+    /// Send `anomaly` from a background task; a failure is only logged.
+    fn notify(&self, anomaly: &FingerprintAnomaly) {
+        let body = match serde_json::to_vec(anomaly) {
+            Ok(body) => body,
+            Err(error) => {
+                warn!(%error, "fingerprint anomaly: webhook body not serialized");
+                return;
+            }
+        };
+        let mut req = match Request::post(self.uri.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body)))
+        {
+            Ok(req) => req,
+            Err(error) => {
+                warn!(%error, "fingerprint anomaly: webhook request not built");
+                return;
+            }
+        };
+        if let Some(authorization) = &self.authorization {
+            req.headers_mut()
+                .insert(AUTHORIZATION, authorization.clone());
+        }
+        let response = self.client.request(req);
+        tokio::spawn(async move {
+            let error = match tokio::time::timeout(WEBHOOK_TIMEOUT, response).await {
+                Ok(Ok(response)) if response.status().is_success() => return,
+                Ok(Ok(response)) => format!("webhook answered {}", response.status()),
+                Ok(Err(error)) => error.to_string(),
+                Err(_) => "timed out".to_string(),
+            };
+            warn!(%error, "fingerprint anomaly: webhook call failed");
+        });
+    }
+}
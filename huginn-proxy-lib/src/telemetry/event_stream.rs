@@ -0,0 +1,699 @@
+//! Fingerprint event stream (`[event_stream]`): one compact JSON event per request, published to
+//! a webhook, NATS or Kafka, or stored in a ClickHouse or SQLite table.
+//!
+//! Events are built next to the access record (see [`crate::telemetry::access_log`]) and handed
+//! to an exporter task through a bounded queue. The task collects up to `batch_size` events, or
+//! whatever arrived within `flush_interval_ms`, and publishes them together. When the sink falls
+//! behind the queue fills up and new events are dropped, so a slow or unreachable sink never
+//! stalls a connection; drops and failed batches are counted in
+//! `huginn_event_stream_events_total` and reported with a `warn!`.
+//!
+//! The storage sinks create their table on first use: ClickHouse gets a `MergeTree` partitioned
+//! by month, SQLite a plain table indexed on `timestamp` and `(ja4, timestamp)` in a database
+//! file that is rotated like the access log once it reaches `max_size_bytes`.
+
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use http::uri::Scheme;
+use http::{Request, Uri};
+use http_body_util::{BodyExt, Full};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde::Serialize;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time::Instant;
+use tokio_rustls::rustls::crypto::aws_lc_rs as aws_lc_provider;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tracing::warn;
+
+use crate::config::{BackendTlsConfig, EventSinkKind, EventStreamConfig};
+use crate::error::{ProxyError, Result};
+use crate::telemetry::metrics::values;
+use crate::telemetry::Metrics;
+use crate::tls::build_upstream_client_config;
+
+type SinkError = Box<dyn std::error::Error + Send + Sync>;
+
+pub(crate) type HttpClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>;
+
+/// Handle to the event exporter. A disabled stream (the default) publishes nothing.
+#[derive(Clone, Default)]
+pub struct EventStream {
+    inner: Option<Arc<StreamInner>>,
+}
+
+struct StreamInner {
+    tx: mpsc::Sender<FingerprintEvent>,
+    dropped: Arc<AtomicU64>,
+    metrics: Arc<Metrics>,
+}
+
+impl EventStream {
+    /// Set up the configured sink and spawn the exporter on the current Tokio runtime. Returns a
+    /// disabled stream when `config.enabled` is false.
+    ///
+    /// The sink connects on the first batch and again after a failure, so an unreachable sink
+    /// does not keep the proxy from starting.
+    pub fn from_config(config: &EventStreamConfig, metrics: Arc<Metrics>) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::disabled());
+        }
+        let sink = Sink::from_config(config)?;
+        let (tx, rx) = mpsc::channel(config.queue_capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let exporter = Exporter {
+            rx,
+            sink,
+            batch_size: config.batch_size.max(1),
+            flush_interval: Duration::from_millis(config.flush_interval_ms),
+            timeout: Duration::from_millis(config.timeout_ms),
+            dropped: Arc::clone(&dropped),
+            metrics: Arc::clone(&metrics),
+        };
+        tokio::spawn(exporter.run());
+        Ok(Self { inner: Some(Arc::new(StreamInner { tx, dropped, metrics })) })
+    }
+
+    /// A stream that publishes nothing.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Queue `event` for publishing; drops it when the queue is full.
+    pub fn publish(&self, event: FingerprintEvent) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = inner.tx.try_send(event) {
+            inner.dropped.fetch_add(1, Ordering::Relaxed);
+            inner.metrics.record_stream_events(values::EVENT_DROPPED, 1);
+        }
+    }
+}
+
+/// One published event. Fields without a value are left out.
+#[derive(Debug, Clone, Serialize)]
+pub struct FingerprintEvent {
+    /// Request start, RFC 3339 UTC with milliseconds.
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub client_ip: IpAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sni: Option<String>,
+    pub host: String,
+    /// Normalized JA4.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ja4: Option<String>,
+    /// JA4 without normalization.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ja4_o: Option<String>,
+    /// Normalized and stable JA4 (v1).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ja4_s1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub akamai: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tcp_syn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    pub status: u16,
+}
+
+struct Exporter {
+    rx: mpsc::Receiver<FingerprintEvent>,
+    sink: Sink,
+    batch_size: usize,
+    flush_interval: Duration,
+    timeout: Duration,
+    dropped: Arc<AtomicU64>,
+    metrics: Arc<Metrics>,
+}
+
+impl Exporter {
+    /// Publish batches until every [`EventStream`] is gone, then publish what is left.
+    async fn run(mut self) {
+        let mut batch = Vec::with_capacity(self.batch_size);
+        while let Some(event) = self.rx.recv().await {
+            batch.push(event);
+            let deadline = Instant::now()
+                .checked_add(self.flush_interval)
+                .unwrap_or_else(Instant::now);
+            while batch.len() < self.batch_size {
+                match tokio::time::timeout_at(deadline, self.rx.recv()).await {
+                    Ok(Some(event)) => batch.push(event),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            self.flush(&mut batch).await;
+        }
+    }
+
+    async fn flush(&mut self, batch: &mut Vec<FingerprintEvent>) {
+        let count = u64::try_from(batch.len()).unwrap_or(u64::MAX);
+        match tokio::time::timeout(self.timeout, self.sink.send(batch)).await {
+            Ok(Ok(())) => self
+                .metrics
+                .record_stream_events(values::EVENT_PUBLISHED, count),
+            outcome => {
+                let error = match outcome {
+                    Ok(Err(e)) => e.to_string(),
+                    _ => "timed out".to_string(),
+                };
+                warn!(sink = self.sink.kind(), events = count, %error, "event stream: batch dropped");
+                self.metrics
+                    .record_stream_events(values::EVENT_FAILED, count);
+                self.sink.reset();
+            }
+        }
+        batch.clear();
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            warn!(dropped, "event stream: queue full, events dropped");
+        }
+    }
+}
+
+enum Sink {
+    Webhook(Webhook),
+    #[cfg(feature = "nats")]
+    Nats(nats::NatsSink),
+    #[cfg(feature = "kafka")]
+    Kafka(kafka::KafkaSink),
+    Clickhouse(Clickhouse),
+    #[cfg(feature = "sqlite")]
+    Sqlite(sqlite::SqliteSink),
+}
+
+impl Sink {
+    fn from_config(config: &EventStreamConfig) -> Result<Self> {
+        match config.sink {
+            EventSinkKind::Webhook => Webhook::new(config).map(Self::Webhook),
+            EventSinkKind::Nats => nats_sink(config),
+            EventSinkKind::Kafka => kafka_sink(config),
+            EventSinkKind::Clickhouse => Clickhouse::new(config).map(Self::Clickhouse),
+            EventSinkKind::Sqlite => sqlite_sink(config),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Webhook(_) => "webhook",
+            #[cfg(feature = "nats")]
+            Self::Nats(_) => "nats",
+            #[cfg(feature = "kafka")]
+            Self::Kafka(_) => "kafka",
+            Self::Clickhouse(_) => "clickhouse",
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(_) => "sqlite",
+        }
+    }
+
+    async fn send(&mut self, batch: &[FingerprintEvent]) -> std::result::Result<(), SinkError> {
+        match self {
+            Self::Webhook(webhook) => webhook.send(batch).await,
+            #[cfg(feature = "nats")]
+            Self::Nats(nats) => nats.send(batch).await,
+            #[cfg(feature = "kafka")]
+            Self::Kafka(kafka) => kafka.send(batch).await,
+            Self::Clickhouse(clickhouse) => clickhouse.send(batch).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(sqlite) => sqlite.send(batch).await,
+        }
+    }
+
+    /// Forget the connection after a failed batch, so the next one reconnects (and makes sure
+    /// the table still exists).
+    fn reset(&mut self) {
+        match self {
+            #[cfg(feature = "nats")]
+            Self::Nats(nats) => nats.client = None,
+            Self::Clickhouse(clickhouse) => clickhouse.schema_ready = false,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+fn nats_sink(config: &EventStreamConfig) -> Result<Sink> {
+    Ok(Sink::Nats(nats::NatsSink::new(config)))
+}
+
+#[cfg(not(feature = "nats"))]
+fn nats_sink(_config: &EventStreamConfig) -> Result<Sink> {
+    Err(ProxyError::Config(
+        "event_stream.sink = \"nats\" requires building with the `nats` feature".to_string(),
+    ))
+}
+
+#[cfg(feature = "kafka")]
+fn kafka_sink(config: &EventStreamConfig) -> Result<Sink> {
+    kafka::KafkaSink::new(config).map(Sink::Kafka)
+}
+
+#[cfg(not(feature = "kafka"))]
+fn kafka_sink(_config: &EventStreamConfig) -> Result<Sink> {
+    Err(ProxyError::Config(
+        "event_stream.sink = \"kafka\" requires building with the `kafka` feature".to_string(),
+    ))
+}
+
+/// `url` of the setting `key`, e.g. `event_stream.url`.
+pub(crate) fn parse_url(key: &str, url: &str) -> Result<Uri> {
+    url.parse()
+        .map_err(|e| ProxyError::Config(format!("{key} '{url}': {e}")))
+}
+
+/// HTTP client for `uri`, trusting `ca_cert_path` (default: the system store) for `https://`.
+pub(crate) fn http_client(uri: &Uri, ca_cert_path: Option<&str>) -> Result<HttpClient> {
+    let tls = if uri.scheme() == Some(&Scheme::HTTPS) {
+        build_upstream_client_config(&BackendTlsConfig {
+            ca_cert_path: ca_cert_path.map(str::to_string),
+            ..BackendTlsConfig::default()
+        })?
+    } else {
+        // Never used for a plain `http://` URL, so no trust store is loaded.
+        Arc::new(
+            ClientConfig::builder_with_provider(Arc::new(aws_lc_provider::default_provider()))
+                .with_safe_default_protocol_versions()
+                .map_err(|e| ProxyError::Tls(format!("Failed to set TLS versions: {e}")))?
+                .with_root_certificates(RootCertStore::empty())
+                .with_no_client_auth(),
+        )
+    };
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    let connector = HttpsConnectorBuilder::new()
+        .with_tls_config(ClientConfig::clone(&tls))
+        .https_or_http()
+        .enable_http1()
+        .wrap_connector(http);
+    Ok(Client::builder(TokioExecutor::new()).build(connector))
+}
+
+/// Sensitive header value, or a config error naming the setting `key`.
+pub(crate) fn secret_header(key: &str, value: String) -> Result<HeaderValue> {
+    let mut value = HeaderValue::try_from(value)
+        .map_err(|_| ProxyError::Config(format!("{key} is not a valid header value")))?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// POSTs each batch as a JSON array.
+struct Webhook {
+    client: HttpClient,
+    uri: Uri,
+    authorization: Option<HeaderValue>,
+}
+
+impl Webhook {
+    fn new(config: &EventStreamConfig) -> Result<Self> {
+        let uri = parse_url("event_stream.url", config.url.as_deref().unwrap_or_default())?;
+        let authorization = config
+            .token
+            .as_ref()
+            .map(|token| secret_header("event_stream.token", format!("Bearer {}", token.expose())))
+            .transpose()?;
+        Ok(Self { client: http_client(&uri, config.ca_cert_path.as_deref())?, uri, authorization })
+    }
+
+    async fn send(&self, batch: &[FingerprintEvent]) -> std::result::Result<(), SinkError> {
+        let mut req = Request::post(self.uri.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(batch)?)))?;
+        if let Some(authorization) = &self.authorization {
+            req.headers_mut()
+                .insert(AUTHORIZATION, authorization.clone());
+        }
+        let response = self.client.request(req).await?;
+        if !response.status().is_success() {
+            return Err(format!("webhook answered {}", response.status()).into());
+        }
+        Ok(())
+    }
+}
+
+/// Inserts each batch through the ClickHouse HTTP interface as `JSONEachRow`, creating the table
+/// first.
+struct Clickhouse {
+    client: HttpClient,
+    /// Receives the `CREATE TABLE` statement as its body.
+    uri: Uri,
+    /// `uri` with the `INSERT` query.
+    insert_uri: Uri,
+    create_table: String,
+    user: Option<HeaderValue>,
+    password: Option<HeaderValue>,
+    schema_ready: bool,
+}
+
+impl Clickhouse {
+    fn new(config: &EventStreamConfig) -> Result<Self> {
+        let base = config
+            .url
+            .as_deref()
+            .unwrap_or_default()
+            .trim_end_matches('/');
+        let uri = parse_url("event_stream.url", &format!("{base}/"))?;
+        let table = &config.table;
+        let insert_uri = parse_url(
+            "event_stream.url",
+            &format!(
+                "{base}/?query=INSERT%20INTO%20{table}%20FORMAT%20JSONEachRow\
+             &date_time_input_format=best_effort"
+            ),
+        )?;
+        let create_table = format!(
+            "CREATE TABLE IF NOT EXISTS {table} (\
+             timestamp DateTime64(3, 'UTC'), \
+             request_id Nullable(String), \
+             client_ip String, \
+             sni Nullable(String), \
+             host LowCardinality(String), \
+             ja4 LowCardinality(Nullable(String)), \
+             ja4_o LowCardinality(Nullable(String)), \
+             ja4_s1 LowCardinality(Nullable(String)), \
+             akamai LowCardinality(Nullable(String)), \
+             tcp_syn LowCardinality(Nullable(String)), \
+             backend LowCardinality(Nullable(String)), \
+             status UInt16\
+             ) ENGINE = MergeTree PARTITION BY toYYYYMM(timestamp) ORDER BY timestamp"
+        );
+        let user = config
+            .user
+            .clone()
+            .map(|user| secret_header("event_stream.user", user))
+            .transpose()?;
+        let password = config
+            .password
+            .as_ref()
+            .map(|password| secret_header("event_stream.password", password.expose().clone()))
+            .transpose()?;
+        Ok(Self {
+            client: http_client(&uri, config.ca_cert_path.as_deref())?,
+            uri,
+            insert_uri,
+            create_table,
+            user,
+            password,
+            schema_ready: false,
+        })
+    }
+
+    async fn send(&mut self, batch: &[FingerprintEvent]) -> std::result::Result<(), SinkError> {
+        if !self.schema_ready {
+            self.query(self.uri.clone(), Bytes::from(self.create_table.clone()))
+                .await?;
+            self.schema_ready = true;
+        }
+        let mut rows = Vec::new();
+        for event in batch {
+            serde_json::to_writer(&mut rows, event)?;
+            rows.push(b'\n');
+        }
+        self.query(self.insert_uri.clone(), Bytes::from(rows)).await
+    }
+
+    async fn query(&self, uri: Uri, body: Bytes) -> std::result::Result<(), SinkError> {
+        let mut req = Request::post(uri).body(Full::new(body))?;
+        if let Some(user) = &self.user {
+            req.headers_mut().insert("x-clickhouse-user", user.clone());
+        }
+        if let Some(password) = &self.password {
+            req.headers_mut()
+                .insert("x-clickhouse-key", password.clone());
+        }
+        let response = self.client.request(req).await?;
+        let status = response.status();
+        if !status.is_success() {
+            // ClickHouse explains the failure in the body.
+            let body = response.into_body().collect().await?.to_bytes();
+            return Err(format!(
+                "clickhouse answered {status}: {}",
+                String::from_utf8_lossy(&body).trim()
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_sink(config: &EventStreamConfig) -> Result<Sink> {
+    Ok(Sink::Sqlite(sqlite::SqliteSink::new(config)))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_sink(_config: &EventStreamConfig) -> Result<Sink> {
+    Err(ProxyError::Config(
+        "event_stream.sink = \"sqlite\" requires building with the `sqlite` feature".to_string(),
+    ))
+}
+
+/// Publishes each event as one NATS message, flushing once per batch.
+#[cfg(feature = "nats")]
+mod nats {
+    use bytes::Bytes;
+
+    use super::{FingerprintEvent, SinkError};
+    use crate::config::EventStreamConfig;
+
+    pub(super) struct NatsSink {
+        url: String,
+        subject: String,
+        token: Option<String>,
+        pub(super) client: Option<async_nats::Client>,
+    }
+
+    impl NatsSink {
+        pub(super) fn new(config: &EventStreamConfig) -> Self {
+            Self {
+                url: config.url.clone().unwrap_or_default(),
+                subject: config.topic.clone().unwrap_or_default(),
+                token: config.token.as_ref().map(|token| token.expose().clone()),
+                client: None,
+            }
+        }
+
+        pub(super) async fn send(
+            &mut self,
+            batch: &[FingerprintEvent],
+        ) -> std::result::Result<(), SinkError> {
+            let client = match &self.client {
+                Some(client) => client.clone(),
+                None => {
+                    let mut options = async_nats::ConnectOptions::new();
+                    if let Some(token) = &self.token {
+                        options = options.token(token.clone());
+                    }
+                    let client = options.connect(self.url.as_str()).await?;
+                    self.client = Some(client.clone());
+                    client
+                }
+            };
+            for event in batch {
+                let payload = Bytes::from(serde_json::to_vec(event)?);
+                client.publish(self.subject.clone(), payload).await?;
+            }
+            client.flush().await?;
+            Ok(())
+        }
+    }
+}
+
+/// Produces each event as one Kafka message keyed by client IP, waiting for every delivery of
+/// the batch.
+#[cfg(feature = "kafka")]
+mod kafka {
+    use rdkafka::producer::{FutureProducer, FutureRecord};
+
+    use super::{FingerprintEvent, SinkError};
+    use crate::config::EventStreamConfig;
+    use crate::error::{ProxyError, Result};
+
+    pub(super) struct KafkaSink {
+        producer: FutureProducer,
+        topic: String,
+    }
+
+    impl KafkaSink {
+        pub(super) fn new(config: &EventStreamConfig) -> Result<Self> {
+            let producer: FutureProducer = rdkafka::ClientConfig::new()
+                .set("bootstrap.servers", config.brokers.join(","))
+                .set("message.timeout.ms", config.timeout_ms.to_string())
+                .create()
+                .map_err(|e| ProxyError::Config(format!("event_stream: Kafka producer: {e}")))?;
+            Ok(Self { producer, topic: config.topic.clone().unwrap_or_default() })
+        }
+
+        pub(super) async fn send(
+            &mut self,
+            batch: &[FingerprintEvent],
+        ) -> std::result::Result<(), SinkError> {
+            let mut deliveries = Vec::with_capacity(batch.len());
+            for event in batch {
+                let payload = serde_json::to_vec(event)?;
+                let key = event.client_ip.to_string();
+                let record = FutureRecord::to(&self.topic).key(&key).payload(&payload);
+                deliveries.push(self.producer.send_result(record).map_err(|(e, _)| e)?);
+            }
+            for delivery in deliveries {
+                delivery.await?.map_err(|(e, _)| e)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Inserts each batch in one transaction into a local SQLite file, rotated once it reaches
+/// `max_size_bytes`. SQLite calls block, so they run on the blocking thread pool.
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use rusqlite::{params, Connection};
+
+    use super::{FingerprintEvent, SinkError};
+    use crate::config::EventStreamConfig;
+    use crate::telemetry::rotating_file::shift_rotated;
+
+    /// Stored in `PRAGMA user_version`; a database written by a newer layout is left alone.
+    const SCHEMA_VERSION: i64 = 1;
+    /// How long a write waits for another connection (e.g. a reader) to release the database.
+    const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub(super) struct SqliteSink {
+        database: Arc<Database>,
+        /// Taken while a batch is written; a batch that timed out keeps it, and the next one
+        /// opens a new connection.
+        conn: Option<Connection>,
+    }
+
+    struct Database {
+        path: PathBuf,
+        schema: String,
+        insert: String,
+        max_size: u64,
+        max_files: usize,
+    }
+
+    impl SqliteSink {
+        pub(super) fn new(config: &EventStreamConfig) -> Self {
+            let table = &config.table;
+            let schema = format!(
+                "CREATE TABLE IF NOT EXISTS {table} (
+                    timestamp TEXT NOT NULL,
+                    request_id TEXT,
+                    client_ip TEXT NOT NULL,
+                    sni TEXT,
+                    host TEXT NOT NULL,
+                    ja4 TEXT,
+                    ja4_o TEXT,
+                    ja4_s1 TEXT,
+                    akamai TEXT,
+                    tcp_syn TEXT,
+                    backend TEXT,
+                    status INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS {table}_timestamp ON {table} (timestamp);
+                CREATE INDEX IF NOT EXISTS {table}_ja4 ON {table} (ja4, timestamp);"
+            );
+            let insert = format!(
+                "INSERT INTO {table} (timestamp, request_id, client_ip, sni, host, ja4, ja4_o, \
+                 ja4_s1, akamai, tcp_syn, backend, status) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
+            );
+            let database = Database {
+                path: config.path.clone().unwrap_or_default(),
+                schema,
+                insert,
+                max_size: config.max_size_bytes,
+                max_files: config.max_files,
+            };
+            Self { database: Arc::new(database), conn: None }
+        }
+
+        pub(super) async fn send(
+            &mut self,
+            batch: &[FingerprintEvent],
+        ) -> std::result::Result<(), SinkError> {
+            let database = Arc::clone(&self.database);
+            let conn = self.conn.take();
+            let batch = batch.to_vec();
+            self.conn = tokio::task::spawn_blocking(move || database.write(conn, &batch)).await??;
+            Ok(())
+        }
+    }
+
+    impl Database {
+        /// Insert `batch`, opening the database when `conn` is `None`. Returns the connection to
+        /// keep, or `None` after a rotation.
+        fn write(
+            &self,
+            conn: Option<Connection>,
+            batch: &[FingerprintEvent],
+        ) -> std::result::Result<Option<Connection>, SinkError> {
+            let mut conn = match conn {
+                Some(conn) => conn,
+                None => self.open()?,
+            };
+            let tx = conn.transaction()?;
+            {
+                let mut insert = tx.prepare_cached(&self.insert)?;
+                for event in batch {
+                    insert.execute(params![
+                        event.timestamp,
+                        event.request_id,
+                        event.client_ip.to_string(),
+                        event.sni,
+                        event.host,
+                        event.ja4,
+                        event.ja4_o,
+                        event.ja4_s1,
+                        event.akamai,
+                        event.tcp_syn,
+                        event.backend,
+                        event.status,
+                    ])?;
+                }
+            }
+            tx.commit()?;
+
+            if self.max_size > 0 && std::fs::metadata(&self.path)?.len() >= self.max_size {
+                drop(conn);
+                shift_rotated(&self.path, self.max_files)?;
+                return Ok(None);
+            }
+            Ok(Some(conn))
+        }
+
+        fn open(&self) -> std::result::Result<Connection, SinkError> {
+            let conn = Connection::open(&self.path)?;
+            conn.busy_timeout(BUSY_TIMEOUT)?;
+            let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+            if version > SCHEMA_VERSION {
+                return Err(format!(
+                    "{} has schema version {version}, newer than {SCHEMA_VERSION}",
+                    self.path.display()
+                )
+                .into());
+            }
+            conn.execute_batch(&self.schema)?;
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+            Ok(conn)
+        }
+    }
+}
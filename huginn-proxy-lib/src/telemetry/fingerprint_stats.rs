@@ -0,0 +1,280 @@
+//! Fingerprint analytics (`[telemetry.fingerprint_stats]`): the most frequent JA4 and Akamai
+//! fingerprints seen by the proxy.
+//!
+//! Each request counts once for its connection's fingerprints. Counts come from count-min
+//! sketches (the [`Estimator`] the rate limiter uses), so memory does not grow with the number of
+//! distinct fingerprints; only the current `top_n` per kind keep their fingerprint string.
+//! Distinct clients are estimated the same way, from a sketch of `(fingerprint, client IP)`
+//! pairs. Sketch estimates can only overcount, so rare fingerprints may read slightly high.
+//!
+//! The top lists are exported as the `huginn_fingerprint_top_requests` and
+//! `huginn_fingerprint_top_clients` gauges and as JSON on `/observability/fingerprints`.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use opentelemetry::metrics::Meter;
+use opentelemetry::KeyValue;
+use pingora_limits::estimator::Estimator;
+use serde::Serialize;
+
+use crate::config::FingerprintStatsConfig;
+use crate::telemetry::metrics::labels;
+
+/// Rows of each count-min sketch.
+const SKETCH_HASHES: usize = 4;
+/// Counters per row of each count-min sketch.
+const SKETCH_SLOTS: usize = 4096;
+
+/// `kind` label value of JA4 fingerprints.
+pub const KIND_JA4: &str = "ja4";
+/// `kind` label value of HTTP/2 Akamai fingerprints.
+pub const KIND_AKAMAI: &str = "akamai";
+
+/// Shared fingerprint counters. A disabled instance (the default) records nothing.
+#[derive(Clone, Default)]
+pub struct FingerprintStats {
+    inner: Option<Arc<StatsInner>>,
+}
+
+struct StatsInner {
+    top_n: usize,
+    window: Duration,
+    state: Mutex<Window>,
+}
+
+/// Counts of the current window; replaced wholesale when the window ends.
+struct Window {
+    started: Instant,
+    started_at: SystemTime,
+    ja4: Tracker,
+    akamai: Tracker,
+}
+
+impl Window {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            ja4: Tracker::new(),
+            akamai: Tracker::new(),
+        }
+    }
+}
+
+/// Sketches and top entries of one fingerprint kind.
+struct Tracker {
+    requests: Estimator,
+    pairs: Estimator,
+    clients: Estimator,
+    top: HashMap<String, Counts>,
+}
+
+#[derive(Clone, Copy)]
+struct Counts {
+    requests: u64,
+    clients: u64,
+}
+
+impl Tracker {
+    fn new() -> Self {
+        Self {
+            requests: Estimator::new(SKETCH_HASHES, SKETCH_SLOTS),
+            pairs: Estimator::new(SKETCH_HASHES, SKETCH_SLOTS),
+            clients: Estimator::new(SKETCH_HASHES, SKETCH_SLOTS),
+            top: HashMap::new(),
+        }
+    }
+
+    fn observe(&mut self, fingerprint: &str, client: IpAddr, top_n: usize) {
+        let requests = self.requests.incr(fingerprint, 1);
+        // The first request of a (fingerprint, client) pair is a new client.
+        let clients = if self.pairs.incr((fingerprint, client), 1) == 1 {
+            self.clients.incr(fingerprint, 1)
+        } else {
+            self.clients.get(fingerprint)
+        };
+        let counts = Counts {
+            requests: u64::try_from(requests).unwrap_or_default(),
+            clients: u64::try_from(clients).unwrap_or_default(),
+        };
+
+        if let Some(entry) = self.top.get_mut(fingerprint) {
+            *entry = counts;
+            return;
+        }
+        if self.top.len() < top_n {
+            self.top.insert(fingerprint.to_string(), counts);
+            return;
+        }
+        // A fingerprint outside the top list replaces the least requested entry once it has
+        // more requests than that entry.
+        let least = self
+            .top
+            .iter()
+            .min_by_key(|(_, c)| c.requests)
+            .map(|(fp, c)| (fp.clone(), c.requests));
+        if let Some((least, least_requests)) = least {
+            if counts.requests > least_requests {
+                self.top.remove(&least);
+                self.top.insert(fingerprint.to_string(), counts);
+            }
+        }
+    }
+
+    /// Top entries, most requested first.
+    fn ranked(&self) -> Vec<FingerprintCount> {
+        let mut ranked: Vec<FingerprintCount> = self
+            .top
+            .iter()
+            .map(|(fingerprint, c)| FingerprintCount {
+                fingerprint: fingerprint.clone(),
+                requests: c.requests,
+                clients: c.clients,
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            b.requests
+                .cmp(&a.requests)
+                .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+        });
+        ranked
+    }
+}
+
+/// Body of `/observability/fingerprints`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FingerprintSnapshot {
+    /// Length of the counting window in seconds.
+    pub window_secs: u64,
+    /// Unix timestamp (seconds) at which the current window started.
+    pub window_started_at: u64,
+    pub ja4: Vec<FingerprintCount>,
+    pub akamai: Vec<FingerprintCount>,
+}
+
+/// Estimated counts of one fingerprint in the current window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FingerprintCount {
+    pub fingerprint: String,
+    pub requests: u64,
+    /// Distinct client IPs.
+    pub clients: u64,
+}
+
+impl FingerprintStats {
+    /// Returns a disabled instance when `config.enabled` is false.
+    pub fn from_config(config: &FingerprintStatsConfig) -> Self {
+        if !config.enabled {
+            return Self::disabled();
+        }
+        let inner = StatsInner {
+            top_n: config.top_n,
+            window: Duration::from_secs(config.window_secs),
+            state: Mutex::new(Window::new()),
+        };
+        Self { inner: Some(Arc::new(inner)) }
+    }
+
+    /// An instance that records nothing.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Count one request from `client` with the connection's fingerprints.
+    pub fn observe(&self, client: IpAddr, ja4: Option<&str>, akamai: Option<&str>) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        if ja4.is_none() && akamai.is_none() {
+            return;
+        }
+        let Some(mut window) = inner.current() else {
+            return;
+        };
+        if let Some(ja4) = ja4 {
+            window.ja4.observe(ja4, client, inner.top_n);
+        }
+        if let Some(akamai) = akamai {
+            window.akamai.observe(akamai, client, inner.top_n);
+        }
+    }
+
+    /// Current top fingerprints. `None` when disabled.
+    pub fn snapshot(&self) -> Option<FingerprintSnapshot> {
+        let inner = self.inner.as_ref()?;
+        let window = inner.current()?;
+        Some(FingerprintSnapshot {
+            window_secs: inner.window.as_secs(),
+            window_started_at: window
+                .started_at
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            ja4: window.ja4.ranked(),
+            akamai: window.akamai.ranked(),
+        })
+    }
+
+    /// Export the top lists as gauges on `meter`, read at every scrape. Nothing is registered
+    /// when disabled.
+    pub(crate) fn register_gauges(&self, meter: &Meter) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let requests = Arc::clone(inner);
+        let _ = meter
+            .u64_observable_gauge("huginn_fingerprint_top_requests")
+            .with_description("Requests per fingerprint in the current window, top fingerprints only. kind=ja4|akamai")
+            .with_callback(move |observer| {
+                for (kind, entry) in requests.top_entries() {
+                    observer.observe(entry.requests, &gauge_labels(kind, entry.fingerprint));
+                }
+            })
+            .build();
+        let clients = Arc::clone(inner);
+        let _ = meter
+            .u64_observable_gauge("huginn_fingerprint_top_clients")
+            .with_description("Distinct client IPs per fingerprint in the current window, top fingerprints only. kind=ja4|akamai")
+            .with_callback(move |observer| {
+                for (kind, entry) in clients.top_entries() {
+                    observer.observe(entry.clients, &gauge_labels(kind, entry.fingerprint));
+                }
+            })
+            .build();
+    }
+}
+
+impl StatsInner {
+    /// The current window, started afresh when the previous one is over. `None` if the lock is
+    /// poisoned.
+    fn current(&self) -> Option<MutexGuard<'_, Window>> {
+        let mut window = self.state.lock().ok()?;
+        if window.started.elapsed() >= self.window {
+            *window = Window::new();
+        }
+        Some(window)
+    }
+
+    fn top_entries(&self) -> Vec<(&'static str, FingerprintCount)> {
+        let Some(window) = self.current() else {
+            return Vec::new();
+        };
+        let ja4 = window.ja4.ranked().into_iter().map(|c| (KIND_JA4, c));
+        let akamai = window.akamai.ranked().into_iter().map(|c| (KIND_AKAMAI, c));
+        ja4.chain(akamai).collect()
+    }
+}
+
+fn gauge_labels(kind: &'static str, fingerprint: String) -> [KeyValue; 2] {
+    [
+        KeyValue::new(labels::KIND, kind),
+        KeyValue::new(labels::FINGERPRINT, fingerprint),
+    ]
+}
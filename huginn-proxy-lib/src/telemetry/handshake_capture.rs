@@ -0,0 +1,422 @@
+//! Handshake capture (`[handshake_capture]`): the raw ClientHello of a sampled fraction of TLS
+//! connections, with the fingerprints computed from it, written to a file for offline analysis.
+//!
+//! As with the access log, records are handed to a dedicated writer thread through a bounded
+//! queue; when it is full the record is dropped and the writer reports the loss with a `warn!`.
+//! The file rotates by size like the access log file.
+//!
+//! - `jsonl`: one JSON object per line; `client_hello` holds the TLS record, hex-encoded.
+//! - `pcapng`: every ClientHello becomes one TCP segment from the client to the listener in a
+//!   synthesized IPv4/IPv6 packet (link type `RAW`), which Wireshark, tshark or Zeek dissect
+//!   like captured traffic. The packet comment is the JSON object without `client_hello`. Each
+//!   file, rotated or not, starts with its own section header.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::config::{HandshakeCaptureConfig, HandshakeCaptureFormat};
+use crate::error::Result;
+use crate::fingerprinting::{Ja4Fingerprints, TcpObservation};
+use crate::telemetry::access_log::{format_rfc3339_millis, sampled};
+use crate::telemetry::rotating_file::RotatingFile;
+use crate::utils::hex;
+
+/// Records waiting for the writer thread; past this the newest record is dropped. Records hold
+/// up to 64 KiB of ClientHello, so this is smaller than the access log queue.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Destination port of synthesized packets when the listener has no TCP address.
+const DEFAULT_SERVER_PORT: u16 = 443;
+/// Largest segment payload that fits the 16-bit IPv4 total length next to both headers.
+const MAX_SEGMENT_PAYLOAD: usize = 65_495;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_OPT_END: u16 = 0;
+const PCAPNG_OPT_COMMENT: u16 = 1;
+const PCAPNG_SHB_USERAPPL: u16 = 4;
+/// `LINKTYPE_RAW`: packets start at the IP header.
+const LINKTYPE_RAW: u16 = 101;
+
+/// Handle to the capture writer. A disabled capture (the default) records nothing.
+#[derive(Clone, Default)]
+pub struct HandshakeCapture {
+    inner: Option<Arc<CaptureInner>>,
+    server: Option<SocketAddr>,
+}
+
+struct CaptureInner {
+    tx: SyncSender<HandshakeRecord>,
+    dropped: Arc<AtomicU64>,
+    sample_rate: f64,
+}
+
+impl HandshakeCapture {
+    /// Open the capture file and start the writer thread. Returns a disabled capture when
+    /// `config.enabled` is false.
+    pub fn from_config(config: &HandshakeCaptureConfig) -> Result<Self> {
+        let Some(path) = config.path.as_ref().filter(|_| config.enabled) else {
+            return Ok(Self::disabled());
+        };
+        let header = match config.format {
+            HandshakeCaptureFormat::Jsonl => Vec::new(),
+            HandshakeCaptureFormat::Pcapng => pcapng_header(),
+        };
+        let file =
+            RotatingFile::open_with_header(path, config.max_size_bytes, config.max_files, header)?;
+        let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer = Writer { rx, file, format: config.format, dropped: Arc::clone(&dropped) };
+        std::thread::Builder::new()
+            .name("handshake-capture".to_string())
+            .spawn(move || writer.run())?;
+        Ok(Self {
+            inner: Some(Arc::new(CaptureInner { tx, dropped, sample_rate: config.sample_rate })),
+            server: None,
+        })
+    }
+
+    /// A capture that records nothing.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// The same capture, with `server` (the listener address) as the destination of records
+    /// that do not set one.
+    pub fn with_server(mut self, server: Option<SocketAddr>) -> Self {
+        self.server = server;
+        self
+    }
+
+    /// Queue the record built by `build` when this connection is sampled; drops it when the
+    /// queue is full. `build` only runs for sampled connections.
+    pub fn capture(&self, build: impl FnOnce() -> HandshakeRecord) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        if !sampled(inner.sample_rate) {
+            return;
+        }
+        let mut record = build();
+        record.server = record.server.or(self.server);
+        if let Err(TrySendError::Full(_)) = inner.tx.try_send(record) {
+            inner.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// One captured ClientHello.
+#[derive(Debug, Clone)]
+pub struct HandshakeRecord {
+    pub timestamp: SystemTime,
+    /// Effective client address (after PROXY protocol resolution).
+    pub client: SocketAddr,
+    /// Listener address; `None` on unix sockets.
+    pub server: Option<SocketAddr>,
+    /// The TLS record holding the ClientHello, as received.
+    pub client_hello: Bytes,
+    pub ja4: Option<Ja4Fingerprints>,
+    pub tcp_syn: Option<String>,
+}
+
+impl HandshakeRecord {
+    /// Record of the bytes returned by [`crate::fingerprinting::read_client_hello`]; anything
+    /// the client sent after the first TLS record is left out.
+    pub fn new(
+        client: SocketAddr,
+        prefix: &Bytes,
+        ja4: Option<&Ja4Fingerprints>,
+        tcp_syn: Option<&TcpObservation>,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            client,
+            server: None,
+            client_hello: first_record(prefix),
+            ja4: ja4.cloned(),
+            tcp_syn: tcp_syn.map(ToString::to_string),
+        }
+    }
+
+    /// The JSON line for this record, without the trailing newline.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.view(true)).unwrap_or_default()
+    }
+
+    /// This record as a PCAPNG enhanced packet block.
+    fn to_pcapng(&self) -> Vec<u8> {
+        let packet = tcp_packet(self.client, self.server, &self.client_hello);
+        let comment = serde_json::to_string(&self.view(false)).unwrap_or_default();
+        let micros = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let [t0, t1, t2, t3, t4, t5, t6, t7] =
+            u64::try_from(micros).unwrap_or(u64::MAX).to_be_bytes();
+        let packet_len = u32::try_from(packet.len()).unwrap_or(u32::MAX);
+
+        let mut body = Vec::with_capacity(
+            packet
+                .len()
+                .saturating_add(comment.len())
+                .saturating_add(40),
+        );
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&u32::from_be_bytes([t0, t1, t2, t3]).to_le_bytes());
+        body.extend_from_slice(&u32::from_be_bytes([t4, t5, t6, t7]).to_le_bytes());
+        body.extend_from_slice(&packet_len.to_le_bytes());
+        body.extend_from_slice(&packet_len.to_le_bytes());
+        body.extend_from_slice(&packet);
+        pad_to_word(&mut body);
+        push_option(&mut body, PCAPNG_OPT_COMMENT, comment.as_bytes());
+        push_option(&mut body, PCAPNG_OPT_END, &[]);
+        block(PCAPNG_ENHANCED_PACKET, &body)
+    }
+
+    fn view(&self, with_client_hello: bool) -> RecordView<'_> {
+        let ja4 = self.ja4.as_ref();
+        RecordView {
+            timestamp: format_rfc3339_millis(self.timestamp),
+            client: self.client,
+            server: self.server,
+            sni: ja4.and_then(|f| f.sni.as_deref()),
+            ja4: ja4.map(|f| f.ja4.full.to_string()),
+            ja4_r: ja4.map(|f| f.ja4.raw.to_string()),
+            ja4_o: ja4.map(|f| f.ja4_original.full.to_string()),
+            ja4_or: ja4.map(|f| f.ja4_original.raw.to_string()),
+            ja4_s1: ja4.map(|f| f.ja4_stable_v1.full.to_string()),
+            ja4_s1r: ja4.map(|f| f.ja4_stable_v1.raw.to_string()),
+            tcp_syn: self.tcp_syn.as_deref(),
+            client_hello: with_client_hello.then(|| hex::encode(&self.client_hello)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RecordView<'a> {
+    timestamp: String,
+    client: SocketAddr,
+    server: Option<SocketAddr>,
+    sni: Option<&'a str>,
+    ja4: Option<String>,
+    ja4_r: Option<String>,
+    ja4_o: Option<String>,
+    ja4_or: Option<String>,
+    ja4_s1: Option<String>,
+    ja4_s1r: Option<String>,
+    tcp_syn: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_hello: Option<String>,
+}
+
+struct Writer {
+    rx: Receiver<HandshakeRecord>,
+    file: RotatingFile,
+    format: HandshakeCaptureFormat,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Writer {
+    /// Write records until every [`HandshakeCapture`] is gone, flushing whenever the queue
+    /// empties.
+    fn run(mut self) {
+        while let Ok(record) = self.rx.recv() {
+            self.write(&record);
+            while let Ok(record) = self.rx.try_recv() {
+                self.write(&record);
+            }
+            if let Err(e) = self.file.flush() {
+                warn!(error = %e, "handshake capture: flush failed");
+            }
+            let dropped = self.dropped.swap(0, Ordering::Relaxed);
+            if dropped > 0 {
+                warn!(dropped, "handshake capture: queue full, records dropped");
+            }
+        }
+    }
+
+    fn write(&mut self, record: &HandshakeRecord) {
+        let bytes = match self.format {
+            HandshakeCaptureFormat::Jsonl => {
+                let mut line = record.to_json().into_bytes();
+                line.push(b'\n');
+                line
+            }
+            HandshakeCaptureFormat::Pcapng => record.to_pcapng(),
+        };
+        if let Err(e) = self.file.write_record(&bytes) {
+            warn!(error = %e, "handshake capture: write failed");
+        }
+    }
+}
+
+/// The first TLS record of `prefix` (all of it when the record header is incomplete).
+fn first_record(prefix: &Bytes) -> Bytes {
+    let len = match prefix.get(3..5) {
+        Some(&[high, low]) => usize::from(u16::from_be_bytes([high, low])).saturating_add(5),
+        _ => prefix.len(),
+    };
+    prefix.slice(..len.min(prefix.len()))
+}
+
+/// Section header and the single interface description of a capture file.
+fn pcapng_header() -> Vec<u8> {
+    let mut section = Vec::new();
+    section.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+    section.extend_from_slice(&1u16.to_le_bytes());
+    section.extend_from_slice(&0u16.to_le_bytes());
+    // Section length not specified.
+    section.extend_from_slice(&(-1i64).to_le_bytes());
+    push_option(&mut section, PCAPNG_SHB_USERAPPL, b"huginn-proxy");
+    push_option(&mut section, PCAPNG_OPT_END, &[]);
+
+    let mut interface = Vec::new();
+    interface.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+    interface.extend_from_slice(&0u16.to_le_bytes());
+    // No snapshot length limit.
+    interface.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut header = block(PCAPNG_SECTION_HEADER, &section);
+    header.extend_from_slice(&block(PCAPNG_INTERFACE_DESCRIPTION, &interface));
+    header
+}
+
+/// A PCAPNG block around `body`, which must already be padded to 32 bits.
+fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+    let total = u32::try_from(body.len().saturating_add(12)).unwrap_or(u32::MAX);
+    let mut out = Vec::with_capacity(body.len().saturating_add(12));
+    out.extend_from_slice(&block_type.to_le_bytes());
+    out.extend_from_slice(&total.to_le_bytes());
+    out.extend_from_slice(body);
+    out.extend_from_slice(&total.to_le_bytes());
+    out
+}
+
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    let value = value.get(..usize::from(u16::MAX)).unwrap_or(value);
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&u16::try_from(value.len()).unwrap_or(u16::MAX).to_le_bytes());
+    body.extend_from_slice(value);
+    pad_to_word(body);
+}
+
+fn pad_to_word(buf: &mut Vec<u8>) {
+    let padded = buf.len().next_multiple_of(4);
+    buf.resize(padded, 0);
+}
+
+/// An IP packet carrying `payload` in one TCP segment (PSH|ACK) from `client` to `server`.
+///
+/// The listener address stands in for the server: an unspecified or other-family address keeps
+/// its port with the unspecified address of the client's family. Checksums are valid, so tools
+/// that verify them (Zeek does by default) keep the packet.
+fn tcp_packet(client: SocketAddr, server: Option<SocketAddr>, payload: &[u8]) -> Vec<u8> {
+    let payload = payload.get(..MAX_SEGMENT_PAYLOAD).unwrap_or(payload);
+    let src = client.ip().to_canonical();
+    let dst_port = server.map_or(DEFAULT_SERVER_PORT, |s| s.port());
+    let dst = server
+        .map(|s| s.ip().to_canonical())
+        .filter(|ip| ip.is_ipv4() == src.is_ipv4())
+        .unwrap_or(match src {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        });
+    let segment_len = payload.len().saturating_add(20);
+    let segment_len16 = u16::try_from(segment_len).unwrap_or(u16::MAX);
+
+    let mut tcp = Vec::with_capacity(segment_len);
+    tcp.extend_from_slice(&client.port().to_be_bytes());
+    tcp.extend_from_slice(&dst_port.to_be_bytes());
+    tcp.extend_from_slice(&1u32.to_be_bytes()); // sequence number
+    tcp.extend_from_slice(&1u32.to_be_bytes()); // acknowledgment number
+    tcp.extend_from_slice(&[0x50, 0x18]); // 20-byte header, PSH|ACK
+    tcp.extend_from_slice(&u16::MAX.to_be_bytes()); // window
+    tcp.extend_from_slice(&[0, 0, 0, 0]); // checksum, urgent pointer
+    tcp.extend_from_slice(payload);
+
+    let mut packet = match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            let mut ip = vec![0x45, 0];
+            ip.extend_from_slice(&segment_len16.saturating_add(20).to_be_bytes());
+            ip.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]); // id, DF, TTL, TCP, checksum
+            ip.extend_from_slice(&src.octets());
+            ip.extend_from_slice(&dst.octets());
+            let ip_checksum = checksum(&[&ip]);
+            if let Some(slot) = ip.get_mut(10..12) {
+                slot.copy_from_slice(&ip_checksum.to_be_bytes());
+            }
+            let mut pseudo = Vec::with_capacity(12);
+            pseudo.extend_from_slice(&src.octets());
+            pseudo.extend_from_slice(&dst.octets());
+            pseudo.extend_from_slice(&[0, 6]);
+            pseudo.extend_from_slice(&segment_len16.to_be_bytes());
+            set_tcp_checksum(&mut tcp, &pseudo);
+            ip
+        }
+        (src, dst) => {
+            let src = ipv6(src);
+            let dst = ipv6(dst);
+            let mut ip = vec![0x60, 0, 0, 0];
+            ip.extend_from_slice(&segment_len16.to_be_bytes());
+            ip.extend_from_slice(&[6, 64]); // TCP, hop limit
+            ip.extend_from_slice(&src.octets());
+            ip.extend_from_slice(&dst.octets());
+            let mut pseudo = Vec::with_capacity(40);
+            pseudo.extend_from_slice(&src.octets());
+            pseudo.extend_from_slice(&dst.octets());
+            pseudo.extend_from_slice(&u32::from(segment_len16).to_be_bytes());
+            pseudo.extend_from_slice(&[0, 0, 0, 6]);
+            set_tcp_checksum(&mut tcp, &pseudo);
+            ip
+        }
+    };
+    packet.extend_from_slice(&tcp);
+    packet
+}
+
+fn ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+        IpAddr::V6(v6) => v6,
+    }
+}
+
+fn set_tcp_checksum(tcp: &mut [u8], pseudo_header: &[u8]) {
+    let sum = checksum(&[pseudo_header, tcp]);
+    if let Some(slot) = tcp.get_mut(16..18) {
+        slot.copy_from_slice(&sum.to_be_bytes());
+    }
+}
+
+/// Internet checksum (RFC 1071) over `chunks`; every chunk but the last has an even length.
+fn checksum(chunks: &[&[u8]]) -> u16 {
+    let mut sum: u64 = 0;
+    for chunk in chunks {
+        for word in chunk.chunks(2) {
+            let value = match word {
+                [high, low] => u16::from_be_bytes([*high, *low]),
+                [high] => u16::from_be_bytes([*high, 0]),
+                _ => 0,
+            };
+            sum = sum.wrapping_add(u64::from(value));
+        }
+    }
+    while sum > 0xFFFF {
+        sum = (sum % 0x1_0000).wrapping_add(sum / 0x1_0000);
+    }
+    !u16::try_from(sum).unwrap_or(u16::MAX)
+}
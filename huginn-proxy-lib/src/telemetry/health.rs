@@ -1,8 +1,11 @@
 use hyper::Response;
 use hyper::StatusCode;
+use serde::Serialize;
 use tracing::warn;
 
+use crate::backend::HealthRegistry;
 use crate::telemetry::status::{Status, StatusBody};
+use crate::telemetry::Readiness;
 use crate::utils::http::{json_response, RespBody};
 
 /// Health check - always 200 while the process is running.
@@ -17,17 +20,50 @@ pub fn live_check_response() -> Response<RespBody> {
 
 /// Readiness check - reports whether the proxy has finished starting up and is
 /// accepting connections.
-/// `ready` is `false` while the listeners are still binding/initialising and during
-/// graceful shutdown; `true` once the proxy is accepting connections.
-pub fn ready_check_response(ready: bool) -> Response<RespBody> {
-    if ready {
+/// Not ready (`proxy_starting`) while the listeners are still binding/initialising, and not
+/// ready (`draining`) from the start of a graceful shutdown or drain; ready in between.
+pub fn ready_check_response(readiness: &Readiness) -> Response<RespBody> {
+    if readiness.is_ready() {
         return json_response(StatusCode::OK, StatusBody::new(Status::Ready));
     }
 
-    let reason = "proxy_starting";
-    warn!(reason, "Readiness check failed: proxy is not accepting connections yet");
+    let reason = if readiness.is_draining() {
+        "draining"
+    } else {
+        "proxy_starting"
+    };
+    warn!(reason, "Readiness check failed: proxy is not ready for new connections");
     json_response(
         StatusCode::SERVICE_UNAVAILABLE,
         StatusBody::with_reason(Status::NotReady, reason),
     )
 }
+
+#[derive(Serialize)]
+struct BackendHealthEntry {
+    address: String,
+    healthy: bool,
+}
+
+#[derive(Serialize)]
+struct BackendsHealthBody {
+    status: Status,
+    backends: Vec<BackendHealthEntry>,
+}
+
+/// Per-backend active health-check state. Always 200: `status` is `healthy` when every
+/// health-checked backend is eligible, `degraded` when at least one has been ejected.
+/// Backends without a `health_check` block are not listed (they are never gated).
+pub fn backends_health_response(health: &HealthRegistry) -> Response<RespBody> {
+    let backends: Vec<BackendHealthEntry> = health
+        .snapshot()
+        .into_iter()
+        .map(|(address, healthy)| BackendHealthEntry { address, healthy })
+        .collect();
+    let status = if backends.iter().all(|b| b.healthy) {
+        Status::Healthy
+    } else {
+        Status::Degraded
+    };
+    json_response(StatusCode::OK, BackendsHealthBody { status, backends })
+}
@@ -3,7 +3,11 @@ use opentelemetry::metrics::{Counter, Gauge, Histogram, Meter, UpDownCounter};
 use opentelemetry::KeyValue;
 use opentelemetry_sdk::metrics::SdkMeterProvider;
 use prometheus::Registry;
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use crate::config::MetricsConfig;
+use crate::telemetry::FingerprintStats;
 
 pub mod labels {
     pub const ERROR_TYPE: &str = "error_type";
@@ -25,14 +29,34 @@ pub mod labels {
     pub const BACKEND: &str = "backend";
     pub const RESULT: &str = "result";
     pub const DOMAIN: &str = "domain";
+    pub const TENANT: &str = "tenant";
     pub const FAMILY: &str = "family";
+    pub const STATE: &str = "state";
+    pub const GRPC_STATUS: &str = "grpc_status";
+    pub const KIND: &str = "kind";
+    pub const FINGERPRINT: &str = "fingerprint";
+    pub const VERDICT: &str = "verdict";
+    pub const ENCODING: &str = "encoding";
+    pub const SCOPE: &str = "scope";
+    pub const PRIORITY: &str = "priority";
+    pub const RULE: &str = "rule";
+    pub const ACTION: &str = "action";
+    pub const CRAWLER: &str = "crawler";
+    pub const VIOLATION: &str = "violation";
+    pub const CHANGE: &str = "change";
+    pub const LISTENER: &str = "listener";
+    pub const DIRECTION: &str = "direction";
+    pub const HTTP_VERSION: &str = "http_version";
 }
 
 pub mod values {
     pub const ERROR_RATE_LIMITED: &str = "rate_limited";
     pub const ERROR_IP_BLOCKED: &str = "ip_blocked";
+    pub const ERROR_CONCURRENCY_LIMITED: &str = "concurrency_limited";
+    pub const ERROR_LOAD_SHED: &str = "load_shed";
     pub const TIMEOUT_TLS_HANDSHAKE: &str = "tls_handshake";
     pub const TIMEOUT_CONNECTION_HANDLING: &str = "connection_handling";
+    pub const TIMEOUT_WEBSOCKET_IDLE: &str = "websocket_idle";
     pub const CONTEXT_REQUEST: &str = "request";
     pub const CONTEXT_RESPONSE: &str = "response";
     pub const RELOAD_SUCCESS: &str = "success";
@@ -40,19 +64,83 @@ pub mod values {
     pub const REASON_EXTRACTION_FAILED: &str = "extraction_failed";
     pub const REASON_NOT_HTTP2: &str = "not_http2";
     pub const REASON_LIMIT_EXCEEDED: &str = "limit_exceeded";
+    pub const REASON_PER_IP_LIMIT_EXCEEDED: &str = "per_ip_limit_exceeded";
     pub const REASON_SHUTDOWN: &str = "shutdown";
     pub const HEALTH_PROBE_OK: &str = "ok";
     pub const HEALTH_PROBE_FAIL: &str = "fail";
+    /// Backend discovery outcomes for `backend_discovery_refreshes_total{result=...}`.
+    pub const DISCOVERY_CHANGED: &str = "changed";
+    pub const DISCOVERY_UNCHANGED: &str = "unchanged";
+    pub const DISCOVERY_EMPTY: &str = "empty";
+    pub const DISCOVERY_ERROR: &str = "error";
+    /// Member changes for `backend_discovery_member_changes_total{change=...}`.
+    pub const MEMBER_ADDED: &str = "added";
+    pub const MEMBER_REMOVED: &str = "removed";
     /// PROXY protocol drop reasons for `proxy_protocol_dropped_total{reason=...}`.
     pub const PROXY_PROTOCOL_DROP_UNTRUSTED_REQUIRE: &str = "untrusted_require";
     pub const PROXY_PROTOCOL_DROP_BAD_HEADER: &str = "bad_header";
     pub const PROXY_PROTOCOL_DROP_TIMEOUT: &str = "timeout";
+    /// TLS passthrough outcomes for `tls_passthrough_connections_total{result=...}`.
+    pub const PASSTHROUGH_RELAYED: &str = "relayed";
+    pub const PASSTHROUGH_NO_ROUTE: &str = "no_route";
+    pub const PASSTHROUGH_BAD_CLIENT_HELLO: &str = "bad_client_hello";
+    pub const PASSTHROUGH_CONNECT_FAILED: &str = "connect_failed";
+    /// UDP forwarding directions for `udp_bytes_total{direction=...}`.
+    pub const DIRECTION_CLIENT_TO_BACKEND: &str = "client_to_backend";
+    pub const DIRECTION_BACKEND_TO_CLIENT: &str = "backend_to_client";
+    /// UDP drop reasons for `udp_datagrams_dropped_total{reason=...}`.
+    pub const UDP_DROP_SESSION_LIMIT: &str = "session_limit";
+    pub const UDP_DROP_SESSION_FAILED: &str = "session_failed";
+    /// Response cache outcomes for `cache_lookups_total{result=...}`.
+    pub const CACHE_HIT: &str = "hit";
+    pub const CACHE_MISS: &str = "miss";
+    pub const CACHE_STALE: &str = "stale";
+    /// Label value used once a label reached `[telemetry.metrics] max_label_values`.
+    pub const LABEL_OVERFLOW: &str = "other";
+    /// `backend_address` of requests answered before a backend was selected.
+    pub const BACKEND_NONE: &str = "none";
+    /// `max_in_flight` scopes for `concurrency_rejected_total{scope=...}`.
+    pub const SCOPE_ROUTE: &str = "route";
+    pub const SCOPE_BACKEND: &str = "backend";
+    /// Backend queue rejections for `backend_queue_rejected_total{reason=...}`.
+    pub const QUEUE_FULL: &str = "full";
+    pub const QUEUE_TIMEOUT: &str = "timeout";
+    /// Fingerprint event outcomes for `event_stream_events_total{result=...}`.
+    pub const EVENT_PUBLISHED: &str = "published";
+    pub const EVENT_DROPPED: &str = "dropped";
+    pub const EVENT_FAILED: &str = "failed";
+    /// Challenge outcomes for `challenges_total{result=...}`.
+    pub const CHALLENGE_SERVED: &str = "served";
+    pub const CHALLENGE_PASSED: &str = "passed";
+    /// Client tracking outcomes for `client_tracking_total{result=...}`.
+    pub const CLIENT_NEW: &str = "new";
+    pub const CLIENT_RETURNING: &str = "returning";
+    /// Failover causes for `backend_failovers_total{reason=...}`.
+    pub const FAILOVER_STATUS: &str = "status";
+    pub const FAILOVER_HEADER: &str = "header";
+    /// Graylist matches for `tarpit_connections_total{kind=...}`.
+    pub const TARPIT_IP: &str = "ip";
+    pub const TARPIT_JA4: &str = "ja4";
+    /// Tarpit outcomes for `tarpit_connections_total{result=...}`.
+    pub const TARPIT_DELAYED: &str = "delayed";
+    pub const TARPIT_SKIPPED: &str = "skipped";
+    /// CONNECT outcomes for `connect_tunnels_total{result=...}`.
+    pub const CONNECT_OPENED: &str = "opened";
+    pub const CONNECT_REFUSED: &str = "refused";
+    pub const CONNECT_FAILED: &str = "failed";
+    /// Privacy actions for `privacy_headers_scrubbed_total{action=...}`.
+    pub const PRIVACY_NOT_ALLOWED: &str = "not_allowed";
+    pub const PRIVACY_STRIPPED: &str = "stripped";
+    pub const PRIVACY_HASHED: &str = "hashed";
 }
 
 #[derive(Clone)]
 pub struct Metrics {
     pub connections_total: Counter<u64>,
     pub connections_active: UpDownCounter<i64>,
+    /// `huginn_connection_client_ips`: client IPs with open connections, tracked only with
+    /// `security.max_connections_per_ip`.
+    pub connection_client_ips: UpDownCounter<i64>,
 
     pub entrypoint_requests_total: Counter<u64>,
 
@@ -84,6 +172,22 @@ pub struct Metrics {
     // Fingerprint spoofing detection metrics
     // header label: the proxy-authoritative header name the client attempted to supply
     pub fingerprint_spoofing_attempts_total: Counter<u64>,
+    // kind label: ja4 | akamai | tcp; fingerprint label: the configured deny entry that matched
+    pub fingerprint_filter_blocked_total: Counter<u64>,
+    // rule label: built-in, configured or limit rule id; action label: block | detect
+    pub waf_hits_total: Counter<u64>,
+    // crawler label: the configured crawler name; result label: verified | spoofed | unknown
+    pub bot_verifications_total: Counter<u64>,
+    // result label: served | passed
+    pub challenges_total: Counter<u64>,
+    // kind label: ip | ja4; result label: delayed | skipped (tarpit full)
+    pub tarpit_connections_total: Counter<u64>,
+    // result label: new | returning
+    pub client_tracking_total: Counter<u64>,
+    // violation label: see ProtocolViolation::as_str; action label: normalized | rejected | detected
+    pub protocol_violations_total: Counter<u64>,
+    // verdict label: allow | deny | tag, as returned by the registered FingerprintClassifier
+    pub classifier_verdicts_total: Counter<u64>,
 
     // PROXY protocol (source address recovery for L4-forwarded connections)
     /// Real client address recovered from a PROXY header sent by a trusted peer.
@@ -95,14 +199,83 @@ pub struct Metrics {
     pub proxy_protocol_no_client_addr_total: Counter<u64>,
     /// Connection dropped at the address-recovery gate. reason=untrusted_require|bad_header|timeout
     pub proxy_protocol_dropped_total: Counter<u64>,
+    /// `huginn_tls_passthrough_connections_total{result}`: connections of passthrough listeners
+    /// (`relayed|no_route|bad_client_hello|connect_failed`).
+    pub tls_passthrough_connections_total: Counter<u64>,
+
+    /// `huginn_udp_sessions_total{listener}`: UDP sessions opened, one per client address.
+    pub udp_sessions_total: Counter<u64>,
+    /// `huginn_udp_sessions_active{listener}`: UDP sessions not yet idle.
+    pub udp_sessions_active: UpDownCounter<i64>,
+    /// `huginn_udp_bytes_total{listener, direction}`: datagram payload bytes forwarded.
+    pub udp_bytes_total: Counter<u64>,
+    /// `huginn_udp_datagrams_dropped_total{listener, reason}`: client datagrams not forwarded
+    /// (`session_limit|session_failed`).
+    pub udp_datagrams_dropped_total: Counter<u64>,
 
     pub backend_requests_total: Counter<u64>,
     pub backend_errors_total: Counter<u64>,
     pub backend_duration_seconds: Histogram<f64>,
+    /// `huginn_grpc_responses_total{backend_address, grpc_status, route, domain}`: gRPC responses
+    /// on `protocol = "grpc"` routes, by `grpc-status` (from trailers or a trailers-only response).
+    pub grpc_responses_total: Counter<u64>,
+    /// `huginn_ext_authz_checks_total{route, domain, result}`: external authorization checks,
+    /// `result` = `allow` | `deny` | `error`.
+    pub ext_authz_checks_total: Counter<u64>,
+    pub ext_authz_duration_seconds: Histogram<f64>,
+    /// `huginn_request_body_too_large_total{route, domain}`: requests rejected or cut off by
+    /// the route's `max_request_body_bytes`.
+    pub request_body_too_large_total: Counter<u64>,
+    /// `huginn_response_body_too_large_total{backend_address, route, domain}`: backend
+    /// responses rejected or cut off by the route's `max_response_body_bytes`.
+    pub response_body_too_large_total: Counter<u64>,
+    /// `huginn_bandwidth_throttled_total{direction, route, domain}`: body frames held back by a
+    /// route or backend `bandwidth` limit.
+    pub bandwidth_throttled_total: Counter<u64>,
+    /// `huginn_event_stream_events_total{result}`: `[event_stream]` events, by outcome
+    /// (`published|dropped|failed`).
+    pub event_stream_events_total: Counter<u64>,
+    /// `huginn_fingerprint_anomalies_total{kind}`: fingerprints reported by
+    /// `[telemetry.anomaly_detection]`.
+    pub fingerprint_anomalies_total: Counter<u64>,
+    /// `huginn_backend_timeouts_total{backend_address, timeout_type, route, domain}`: backend
+    /// requests that ran out of time. timeout_type=connect|first_byte|total
+    pub backend_timeouts_total: Counter<u64>,
+    /// `huginn_backend_failovers_total{backend_address, reason, route, domain}`: requests sent on
+    /// to the route's next backend after `backend_address` answered. reason=status|header
+    pub backend_failovers_total: Counter<u64>,
+    /// `huginn_backend_negotiated_protocol_total{backend_address, http_version, protocol}`:
+    /// backend responses by the protocol their connection speaks, against the backend's
+    /// `http_version` policy (`auto` shows what ALPN picked).
+    pub backend_negotiated_protocol_total: Counter<u64>,
+    /// `huginn_compressed_responses_total{encoding, route, domain}`: responses compressed by
+    /// the proxy (`[compression]` / route `compression`).
+    pub compressed_responses_total: Counter<u64>,
+    /// `huginn_cache_lookups_total{result, route, domain}`: requests on routes with a `cache`
+    /// block, by outcome (`hit`, `miss`, `stale`).
+    pub cache_lookups_total: Counter<u64>,
+    /// `huginn_backend_connections_opened_total{backend_address}`: upstream connections
+    /// established. Against `huginn_backend_requests_total` it gives the pool reuse rate.
+    pub backend_connections_opened_total: Counter<u64>,
+    /// `huginn_backend_pool_connections{backend_address}`: upstream connections currently open,
+    /// busy or idle in the pool.
+    pub backend_pool_connections: UpDownCounter<i64>,
+    /// `huginn_backend_connect_duration_seconds{backend_address}`: time to open an upstream
+    /// connection. Only new connections are measured; pooled ones skip the connector.
+    pub backend_connect_duration_seconds: Histogram<f64>,
+    /// `huginn_backend_ttfb_seconds{backend_address, route, domain}`: time from sending the
+    /// request to the first frame of the backend's response body.
+    pub backend_ttfb_seconds: Histogram<f64>,
 
     // Backend throughput metrics
     pub backend_bytes_received_total: Counter<u64>,
     pub backend_bytes_sent_total: Counter<u64>,
+    /// `huginn_request_bytes_total{backend_address, route, domain}`: request body bytes streamed
+    /// to backends, counted as they are forwarded (chunked bodies included).
+    pub request_bytes_total: Counter<u64>,
+    /// `huginn_response_bytes_total{backend_address, route, domain}`: backend response body
+    /// bytes streamed to clients, counted as the client reads them.
+    pub response_bytes_total: Counter<u64>,
 
     pub backend_selections_total: Counter<u64>,
     pub errors_total: Counter<u64>,
@@ -112,6 +285,8 @@ pub struct Metrics {
     pub tls_handshake_duration_seconds: Histogram<f64>,
     pub tls_handshake_errors_total: Counter<u64>,
     pub tls_connections_active: UpDownCounter<i64>,
+    /// `huginn_websocket_connections_active`: upgraded (WebSocket) tunnels currently open.
+    pub websocket_connections_active: UpDownCounter<i64>,
 
     // Connection limit metrics
     pub connections_rejected_total: Counter<u64>,
@@ -124,6 +299,57 @@ pub struct Metrics {
     pub rate_limit_allowed_total: Counter<u64>,
     pub rate_limit_rejected_total: Counter<u64>,
 
+    // Concurrency limit (`max_in_flight`) metrics
+    /// `huginn_route_in_flight_requests{route, domain}`: requests in flight on routes with
+    /// `max_in_flight`.
+    pub route_in_flight_requests: UpDownCounter<i64>,
+    /// `huginn_backend_in_flight_requests{backend_address}`: requests in flight to backends with
+    /// `max_in_flight`.
+    pub backend_in_flight_requests: UpDownCounter<i64>,
+    /// `huginn_concurrency_rejected_total{scope, route, domain}`: requests answered `503` because
+    /// a `max_in_flight` limit was reached.
+    pub concurrency_rejected_total: Counter<u64>,
+    /// `huginn_backend_queued_requests{backend_address}`: requests waiting in a backend's
+    /// `queue` for a `max_in_flight` slot.
+    pub backend_queued_requests: UpDownCounter<i64>,
+    /// `huginn_backend_queue_rejected_total{backend_address, reason, priority}`: requests that
+    /// left a backend's `queue` without a slot (`429` when full, `503` on timeout).
+    pub backend_queue_rejected_total: Counter<u64>,
+
+    // Load shedding metrics
+    /// `huginn_load_shed_total{route, domain, priority}`: requests answered `503` by
+    /// `[load_shedding]`.
+    pub load_shed_total: Counter<u64>,
+    /// `huginn_load_shed_ratio`: fraction of sheddable requests currently rejected.
+    pub load_shed_ratio: Gauge<f64>,
+
+    // Synthetic response metrics
+    /// `huginn_synthetic_responses_total{route, domain, status_code}`: requests answered by a
+    /// route's `synthetic` response instead of a backend.
+    pub synthetic_responses_total: Counter<u64>,
+
+    // Static file metrics
+    /// `huginn_static_responses_total{route, domain, status_code}`: requests answered from the
+    /// directory of a route's `serve_static` block.
+    pub static_responses_total: Counter<u64>,
+
+    // CONNECT tunnel metrics
+    /// `huginn_connect_tunnels_total{route, domain, result}`: CONNECT requests of routes with a
+    /// `connect` block; `result` is `opened`, `refused` (target not allowed) or `failed` (the
+    /// target could not be reached).
+    pub connect_tunnels_total: Counter<u64>,
+
+    // Header privacy metrics
+    /// `huginn_privacy_headers_scrubbed_total{route, domain, context, action}`: headers a
+    /// route's `privacy` block dropped (`not_allowed`), removed (`stripped`) or hashed
+    /// (`hashed`); `context` is `request` or `response`.
+    pub privacy_headers_scrubbed_total: Counter<u64>,
+
+    // Redirect metrics
+    /// `huginn_redirects_total{domain, kind, status_code}`: requests answered with a redirect
+    /// before routing; `kind` is `https` or `rule`.
+    pub redirects_total: Counter<u64>,
+
     // IP filtering metrics
     pub ip_filter_requests_total: Counter<u64>,
     pub ip_filter_allowed_total: Counter<u64>,
@@ -142,6 +368,26 @@ pub struct Metrics {
     // Active health checks (TCP)
     pub health_check_probes_total: Counter<u64>,
     pub health_check_gate_rejects_total: Counter<u64>,
+    /// `huginn_backend_healthy{backend}`: 1 while a health-checked backend is eligible, else 0.
+    pub backend_healthy: Gauge<u64>,
+    /// `huginn_circuit_breaker_transitions_total{backend, state}`: passive circuit-breaker
+    /// transitions, labelled with the state entered (`open|half_open|closed`).
+    pub circuit_breaker_transitions_total: Counter<u64>,
+    /// `huginn_backend_discovered_addresses{backend}`: addresses currently known for a backend
+    /// with `discovery`.
+    pub backend_discovered_addresses: Gauge<u64>,
+    /// `huginn_backend_discovery_refreshes_total{backend, result}`: address refreshes
+    /// (`changed|unchanged|empty|error`).
+    pub backend_discovery_refreshes_total: Counter<u64>,
+    /// `huginn_backend_discovery_member_changes_total{backend, change}`: addresses that joined
+    /// (`added`) or left (`removed`) a discovered backend.
+    pub backend_discovery_member_changes_total: Counter<u64>,
+    /// `huginn_backend_drained{backend}`: 1 while a backend is drained (config or admin API),
+    /// else 0.
+    pub backend_drained: Gauge<u64>,
+    /// `huginn_backend_drain_cutoffs_total{backend}`: requests still in flight to a drained
+    /// backend when its `drain_timeout_secs` passed, cut by the proxy.
+    pub backend_drain_cutoffs_total: Counter<u64>,
 
     // Config reload metrics
     /// `huginn_config_reload_total{result="success|error"}` total reload attempts.
@@ -159,17 +405,59 @@ pub struct Metrics {
     pub tls_cert_last_reload_timestamp_seconds: Gauge<f64>,
     /// FNV-1a hash of the currently active certificate chain; changes on every rotation.
     pub tls_cert_hash: Gauge<u64>,
+
+    // OCSP stapling metrics
+    /// `huginn_tls_ocsp_fetch_total{result="success|error", domain}` OCSP responder requests.
+    pub tls_ocsp_fetch_total: Counter<u64>,
+    /// `1` while a valid OCSP response is stapled for the domain's certificate, else `0`.
+    pub tls_ocsp_stapled: Gauge<u64>,
+    /// Seconds since the `thisUpdate` of the stapled OCSP response.
+    pub tls_ocsp_staple_age_seconds: Gauge<f64>,
+
+    route_labels: Arc<LabelLimit>,
+    backend_labels: Arc<LabelLimit>,
+}
+
+/// Caps the distinct values of one label (`[telemetry.metrics] max_label_values`). A value first
+/// seen after the cap is reached is reported as [`values::LABEL_OVERFLOW`]; values are never
+/// evicted, so every value keeps mapping to the same series.
+struct LabelLimit {
+    max: usize,
+    seen: RwLock<HashSet<String>>,
+}
+
+impl LabelLimit {
+    fn new(max: usize) -> Self {
+        Self { max, seen: RwLock::new(HashSet::new()) }
+    }
+
+    fn value(&self, value: &str) -> String {
+        if self.seen.read().is_ok_and(|seen| seen.contains(value)) {
+            return value.to_string();
+        }
+        let Ok(mut seen) = self.seen.write() else {
+            return values::LABEL_OVERFLOW.to_string();
+        };
+        if seen.contains(value) || seen.len() < self.max {
+            seen.insert(value.to_string());
+            value.to_string()
+        } else {
+            values::LABEL_OVERFLOW.to_string()
+        }
+    }
 }
 
 impl Metrics {
     /// Create a no-op `Metrics` instance for testing.
     pub fn new_noop() -> Arc<Self> {
         let meter = opentelemetry::global::meter("huginn-proxy-noop");
-        Arc::new(Self::new(meter))
+        Arc::new(Self::new(meter, &MetricsConfig::default()))
     }
 
-    fn new(meter: Meter) -> Self {
+    fn new(meter: Meter, config: &MetricsConfig) -> Self {
         Self {
+            route_labels: Arc::new(LabelLimit::new(config.max_label_values)),
+            backend_labels: Arc::new(LabelLimit::new(config.max_label_values)),
             connections_total: meter
                 .u64_counter("huginn_connections_total")
                 .with_description("Total number of connections established")
@@ -178,6 +466,12 @@ impl Metrics {
                 .i64_up_down_counter("huginn_connections_active")
                 .with_description("Number of active connections")
                 .build(),
+            connection_client_ips: meter
+                .i64_up_down_counter("huginn_connection_client_ips")
+                .with_description(
+                    "Client IPs with at least one open connection (with max_connections_per_ip)",
+                )
+                .build(),
 
             entrypoint_requests_total: meter
                 .u64_counter("huginn_entrypoint_requests_total")
@@ -191,6 +485,7 @@ impl Metrics {
             requests_duration_seconds: meter
                 .f64_histogram("huginn_requests_duration_seconds")
                 .with_description("Request duration in seconds")
+                .with_boundaries(config.duration_buckets.clone())
                 .build(),
 
             bytes_received_total: meter
@@ -249,6 +544,38 @@ impl Metrics {
                 .u64_counter("huginn_fingerprint_spoofing_attempts_total")
                 .with_description("Total number of proxy-authoritative fingerprint headers supplied by clients (spoofing attempts). header=the stripped header name")
                 .build(),
+            fingerprint_filter_blocked_total: meter
+                .u64_counter("huginn_fingerprint_filter_blocked_total")
+                .with_description("Total requests rejected by security.fingerprint_filter. kind=ja4|akamai|tcp, fingerprint=the matching deny entry")
+                .build(),
+            waf_hits_total: meter
+                .u64_counter("huginn_waf_hits_total")
+                .with_description("Total requests matched by a security.waf rule. rule=the rule id, action=block|detect")
+                .build(),
+            bot_verifications_total: meter
+                .u64_counter("huginn_bot_verifications_total")
+                .with_description("Total requests from claimed crawlers checked by security.bot_verification. crawler=the crawler name, result=verified|spoofed|unknown")
+                .build(),
+            challenges_total: meter
+                .u64_counter("huginn_challenges_total")
+                .with_description("Total requests from suspect fingerprints handled by security.challenge. result=served (challenge page) | passed (valid cookie, forwarded)")
+                .build(),
+            tarpit_connections_total: meter
+                .u64_counter("huginn_tarpit_connections_total")
+                .with_description("Total graylisted TLS connections seen by security.tarpit. kind=ip|ja4, result=delayed|skipped (max_concurrent reached, served without delay)")
+                .build(),
+            client_tracking_total: meter
+                .u64_counter("huginn_client_tracking_total")
+                .with_description("Total requests counted by client_tracking. result=new (first request of the client and JA4) | returning")
+                .build(),
+            protocol_violations_total: meter
+                .u64_counter("huginn_protocol_violations_total")
+                .with_description("Total requests with a message a backend could read differently from the proxy. violation=the violation, action=normalized|rejected|detected")
+                .build(),
+            classifier_verdicts_total: meter
+                .u64_counter("huginn_classifier_verdicts_total")
+                .with_description("Total verdicts returned by the registered fingerprint classifier. verdict=allow|deny|tag")
+                .build(),
 
             proxy_protocol_accepted_total: meter
                 .u64_counter("huginn_proxy_protocol_accepted_total")
@@ -266,6 +593,33 @@ impl Metrics {
                 .u64_counter("huginn_proxy_protocol_dropped_total")
                 .with_description("Total connections dropped at the PROXY address-recovery gate. reason=untrusted_require|bad_header|timeout")
                 .build(),
+            tls_passthrough_connections_total: meter
+                .u64_counter("huginn_tls_passthrough_connections_total")
+                .with_description(
+                    "Connections of TLS passthrough listeners (result=relayed|no_route|bad_client_hello|connect_failed)",
+                )
+                .build(),
+
+            udp_sessions_total: meter
+                .u64_counter("huginn_udp_sessions_total")
+                .with_description("UDP sessions opened, one per client address")
+                .build(),
+            udp_sessions_active: meter
+                .i64_up_down_counter("huginn_udp_sessions_active")
+                .with_description("Open UDP sessions")
+                .build(),
+            udp_bytes_total: meter
+                .u64_counter("huginn_udp_bytes_total")
+                .with_description(
+                    "UDP payload bytes forwarded (direction=client_to_backend|backend_to_client)",
+                )
+                .build(),
+            udp_datagrams_dropped_total: meter
+                .u64_counter("huginn_udp_datagrams_dropped_total")
+                .with_description(
+                    "Client datagrams not forwarded (reason=session_limit|session_failed)",
+                )
+                .build(),
 
             backend_requests_total: meter
                 .u64_counter("huginn_backend_requests_total")
@@ -278,6 +632,90 @@ impl Metrics {
             backend_duration_seconds: meter
                 .f64_histogram("huginn_backend_duration_seconds")
                 .with_description("Backend request duration in seconds")
+                .with_boundaries(config.duration_buckets.clone())
+                .build(),
+            backend_connect_duration_seconds: meter
+                .f64_histogram("huginn_backend_connect_duration_seconds")
+                .with_description("Time to establish an upstream connection (TCP or unix socket connect, before any TLS handshake) in seconds")
+                .with_boundaries(config.duration_buckets.clone())
+                .build(),
+            backend_ttfb_seconds: meter
+                .f64_histogram("huginn_backend_ttfb_seconds")
+                .with_description("Time from sending the request to a backend to the first frame of its response body, in seconds")
+                .with_boundaries(config.duration_buckets.clone())
+                .build(),
+            grpc_responses_total: meter
+                .u64_counter("huginn_grpc_responses_total")
+                .with_description("Total gRPC responses from backends, labelled by grpc-status")
+                .build(),
+            ext_authz_checks_total: meter
+                .u64_counter("huginn_ext_authz_checks_total")
+                .with_description("Total external authorization checks. result=allow|deny|error")
+                .build(),
+            ext_authz_duration_seconds: meter
+                .f64_histogram("huginn_ext_authz_duration_seconds")
+                .with_description("External authorization check duration in seconds")
+                .with_boundaries(config.duration_buckets.clone())
+                .build(),
+            request_body_too_large_total: meter
+                .u64_counter("huginn_request_body_too_large_total")
+                .with_description("Total requests whose body exceeded max_request_body_bytes")
+                .build(),
+            response_body_too_large_total: meter
+                .u64_counter("huginn_response_body_too_large_total")
+                .with_description(
+                    "Total backend responses whose body exceeded max_response_body_bytes",
+                )
+                .build(),
+            bandwidth_throttled_total: meter
+                .u64_counter("huginn_bandwidth_throttled_total")
+                .with_description("Total body frames delayed by a bandwidth limit")
+                .build(),
+            event_stream_events_total: meter
+                .u64_counter("huginn_event_stream_events_total")
+                .with_description(
+                    "Fingerprint events of [event_stream] (result=published|dropped|failed)",
+                )
+                .build(),
+            fingerprint_anomalies_total: meter
+                .u64_counter("huginn_fingerprint_anomalies_total")
+                .with_description(
+                    "Fingerprints whose share of traffic grew past the anomaly thresholds (kind=ja4)",
+                )
+                .build(),
+            backend_timeouts_total: meter
+                .u64_counter("huginn_backend_timeouts_total")
+                .with_description(
+                    "Total backend requests that timed out. timeout_type=connect|first_byte|total",
+                )
+                .build(),
+            backend_failovers_total: meter
+                .u64_counter("huginn_backend_failovers_total")
+                .with_description(
+                    "Total requests failed over to the route's next backend. reason=status|header",
+                )
+                .build(),
+            backend_negotiated_protocol_total: meter
+                .u64_counter("huginn_backend_negotiated_protocol_total")
+                .with_description(
+                    "Total backend responses by upstream protocol and http_version policy",
+                )
+                .build(),
+            compressed_responses_total: meter
+                .u64_counter("huginn_compressed_responses_total")
+                .with_description("Total responses compressed by the proxy. encoding=br|zstd|gzip")
+                .build(),
+            cache_lookups_total: meter
+                .u64_counter("huginn_cache_lookups_total")
+                .with_description("Total response cache lookups. result=hit|miss|stale")
+                .build(),
+            backend_connections_opened_total: meter
+                .u64_counter("huginn_backend_connections_opened_total")
+                .with_description("Total upstream connections established to backends")
+                .build(),
+            backend_pool_connections: meter
+                .i64_up_down_counter("huginn_backend_pool_connections")
+                .with_description("Upstream connections currently open per backend (busy or idle)")
                 .build(),
 
             backend_bytes_received_total: meter
@@ -288,6 +726,14 @@ impl Metrics {
                 .u64_counter("huginn_backend_bytes_sent_total")
                 .with_description("Total bytes sent to backends")
                 .build(),
+            request_bytes_total: meter
+                .u64_counter("huginn_request_bytes_total")
+                .with_description("Total request body bytes streamed to backends")
+                .build(),
+            response_bytes_total: meter
+                .u64_counter("huginn_response_bytes_total")
+                .with_description("Total backend response body bytes streamed to clients")
+                .build(),
 
             backend_selections_total: meter
                 .u64_counter("huginn_backend_selections_total")
@@ -306,6 +752,7 @@ impl Metrics {
             tls_handshake_duration_seconds: meter
                 .f64_histogram("huginn_tls_handshake_duration_seconds")
                 .with_description("TLS handshake duration in seconds")
+                .with_boundaries(config.duration_buckets.clone())
                 .build(),
             tls_handshake_errors_total: meter
                 .u64_counter("huginn_tls_handshake_errors_total")
@@ -316,6 +763,11 @@ impl Metrics {
                 .with_description("Number of active TLS connections")
                 .build(),
 
+            websocket_connections_active: meter
+                .i64_up_down_counter("huginn_websocket_connections_active")
+                .with_description("Number of active WebSocket (upgraded) tunnels")
+                .build(),
+
             connections_rejected_total: meter
                 .u64_counter("huginn_connections_rejected_total")
                 .with_description("Total number of connections rejected due to connection limit")
@@ -339,6 +791,61 @@ impl Metrics {
                 .with_description("Total number of requests rejected by rate limiter (429)")
                 .build(),
 
+            route_in_flight_requests: meter
+                .i64_up_down_counter("huginn_route_in_flight_requests")
+                .with_description("Requests currently in flight per route with max_in_flight")
+                .build(),
+            backend_in_flight_requests: meter
+                .i64_up_down_counter("huginn_backend_in_flight_requests")
+                .with_description("Requests currently in flight per backend with max_in_flight")
+                .build(),
+            concurrency_rejected_total: meter
+                .u64_counter("huginn_concurrency_rejected_total")
+                .with_description("Total number of requests rejected by a max_in_flight limit (503). scope=route|backend")
+                .build(),
+            backend_queued_requests: meter
+                .i64_up_down_counter("huginn_backend_queued_requests")
+                .with_description("Requests currently waiting in a backend queue for a max_in_flight slot")
+                .build(),
+            backend_queue_rejected_total: meter
+                .u64_counter("huginn_backend_queue_rejected_total")
+                .with_description("Total number of requests rejected by a backend queue. reason=full (429)|timeout (503)")
+                .build(),
+
+            load_shed_total: meter
+                .u64_counter("huginn_load_shed_total")
+                .with_description("Total number of requests shed (503) while the proxy is overloaded")
+                .build(),
+            load_shed_ratio: meter
+                .f64_gauge("huginn_load_shed_ratio")
+                .with_description("Fraction of sheddable requests currently rejected by load shedding")
+                .build(),
+
+            synthetic_responses_total: meter
+                .u64_counter("huginn_synthetic_responses_total")
+                .with_description("Total number of requests answered by a route's synthetic response")
+                .build(),
+
+            static_responses_total: meter
+                .u64_counter("huginn_static_responses_total")
+                .with_description("Total number of requests answered from a route's serve_static directory")
+                .build(),
+
+            connect_tunnels_total: meter
+                .u64_counter("huginn_connect_tunnels_total")
+                .with_description("Total CONNECT requests of routes with a connect block. result=opened|refused (target not allowed)|failed (target unreachable)")
+                .build(),
+
+            privacy_headers_scrubbed_total: meter
+                .u64_counter("huginn_privacy_headers_scrubbed_total")
+                .with_description("Total headers scrubbed by route privacy policies. action=not_allowed|stripped|hashed")
+                .build(),
+
+            redirects_total: meter
+                .u64_counter("huginn_redirects_total")
+                .with_description("Total number of requests redirected before routing")
+                .build(),
+
             ip_filter_requests_total: meter
                 .u64_counter("huginn_ip_filter_requests_total")
                 .with_description("Total number of requests evaluated by IP filter")
@@ -379,6 +886,34 @@ impl Metrics {
                 .u64_counter("huginn_health_check_gate_rejects_total")
                 .with_description("Requests short-circuited with 502 because upstream is unhealthy")
                 .build(),
+            backend_healthy: meter
+                .u64_gauge("huginn_backend_healthy")
+                .with_description("Health state of each health-checked backend (1=healthy, 0=unhealthy)")
+                .build(),
+            circuit_breaker_transitions_total: meter
+                .u64_counter("huginn_circuit_breaker_transitions_total")
+                .with_description("Circuit breaker state transitions per backend, labelled with the state entered")
+                .build(),
+            backend_discovered_addresses: meter
+                .u64_gauge("huginn_backend_discovered_addresses")
+                .with_description("Addresses currently known for each backend with discovery")
+                .build(),
+            backend_discovery_refreshes_total: meter
+                .u64_counter("huginn_backend_discovery_refreshes_total")
+                .with_description("Backend address refreshes (result=changed|unchanged|empty|error)")
+                .build(),
+            backend_discovery_member_changes_total: meter
+                .u64_counter("huginn_backend_discovery_member_changes_total")
+                .with_description("Addresses that joined or left a discovered backend (change=added|removed)")
+                .build(),
+            backend_drained: meter
+                .u64_gauge("huginn_backend_drained")
+                .with_description("Drain state of each backend (1=drained, 0=in rotation)")
+                .build(),
+            backend_drain_cutoffs_total: meter
+                .u64_counter("huginn_backend_drain_cutoffs_total")
+                .with_description("Requests cut because they outlived the drain_timeout_secs of their drained backend")
+                .build(),
 
             config_reload_total: meter
                 .u64_counter("huginn_config_reload_total")
@@ -405,10 +940,32 @@ impl Metrics {
                 .u64_gauge("huginn_tls_cert_hash")
                 .with_description("FNV-1a hash of the currently active certificate chain DER bytes; changes on every rotation.")
                 .build(),
+
+            tls_ocsp_fetch_total: meter
+                .u64_counter("huginn_tls_ocsp_fetch_total")
+                .with_description("Total OCSP responder requests for stapling, labelled result=success|error")
+                .build(),
+            tls_ocsp_stapled: meter
+                .u64_gauge("huginn_tls_ocsp_stapled")
+                .with_description("1 while a valid OCSP response is stapled for the domain's certificate, else 0")
+                .build(),
+            tls_ocsp_staple_age_seconds: meter
+                .f64_gauge("huginn_tls_ocsp_staple_age_seconds")
+                .with_description("Seconds since the thisUpdate time of the stapled OCSP response")
+                .build(),
         }
     }
 
     /// Set build info metric with version labels
+    fn route_label(&self, route: &str) -> KeyValue {
+        KeyValue::new(labels::ROUTE, self.route_labels.value(route))
+    }
+
+    /// `key` is [`labels::BACKEND`] or [`labels::BACKEND_ADDRESS`]; both share one cap.
+    fn backend_label(&self, key: &'static str, backend: &str) -> KeyValue {
+        KeyValue::new(key, self.backend_labels.value(backend))
+    }
+
     pub fn set_build_info(&self) {
         let version = env!("CARGO_PKG_VERSION");
         let rust_version = env!("CARGO_PKG_RUST_VERSION");
@@ -431,7 +988,7 @@ impl Metrics {
         self.health_check_probes_total.add(
             1,
             &[
-                KeyValue::new(labels::BACKEND, backend.to_string()),
+                self.backend_label(labels::BACKEND, backend),
                 KeyValue::new(labels::RESULT, result),
             ],
         );
@@ -441,7 +998,65 @@ impl Metrics {
     /// updated when the request handler path records `HttpError::UpstreamUnhealthy`).
     pub fn record_health_check_gate_reject(&self, backend: &str) {
         self.health_check_gate_rejects_total
-            .add(1, &[KeyValue::new(labels::BACKEND, backend.to_string())]);
+            .add(1, &[self.backend_label(labels::BACKEND, backend)]);
+    }
+
+    /// Set the health gauge for a health-checked backend (probe start and every state transition).
+    pub fn record_backend_health(&self, backend: &str, healthy: bool) {
+        self.backend_healthy
+            .record(u64::from(healthy), &[self.backend_label(labels::BACKEND, backend)]);
+    }
+
+    pub fn record_circuit_breaker_transition(&self, backend: &str, state: &str) {
+        self.circuit_breaker_transitions_total.add(
+            1,
+            &[
+                self.backend_label(labels::BACKEND, backend),
+                KeyValue::new(labels::STATE, state.to_string()),
+            ],
+        );
+    }
+
+    pub fn record_backend_discovery_refresh(&self, backend: &str, result: &'static str) {
+        self.backend_discovery_refreshes_total.add(
+            1,
+            &[
+                self.backend_label(labels::BACKEND, backend),
+                KeyValue::new(labels::RESULT, result),
+            ],
+        );
+    }
+
+    pub fn record_backend_discovered_addresses(&self, backend: &str, count: usize) {
+        self.backend_discovered_addresses.record(
+            u64::try_from(count).unwrap_or(u64::MAX),
+            &[self.backend_label(labels::BACKEND, backend)],
+        );
+    }
+
+    pub fn record_backend_member_changes(&self, backend: &str, added: usize, removed: usize) {
+        for (change, count) in [(values::MEMBER_ADDED, added), (values::MEMBER_REMOVED, removed)] {
+            if count > 0 {
+                self.backend_discovery_member_changes_total.add(
+                    u64::try_from(count).unwrap_or(u64::MAX),
+                    &[
+                        self.backend_label(labels::BACKEND, backend),
+                        KeyValue::new(labels::CHANGE, change),
+                    ],
+                );
+            }
+        }
+    }
+
+    /// Set the drain gauge of `backend` (drain start and end).
+    pub fn record_backend_drained(&self, backend: &str, drained: bool) {
+        self.backend_drained
+            .record(u64::from(drained), &[self.backend_label(labels::BACKEND, backend)]);
+    }
+
+    pub fn record_backend_drain_cutoff(&self, backend: &str) {
+        self.backend_drain_cutoffs_total
+            .add(1, &[self.backend_label(labels::BACKEND, backend)]);
     }
 
     pub fn record_rate_limit_rejection(&self, strategy: &str, route: &str, domain: &str) {
@@ -451,18 +1066,162 @@ impl Metrics {
             1,
             &[
                 KeyValue::new(labels::STRATEGY, strategy.to_string()),
-                KeyValue::new(labels::ROUTE, route.to_string()),
+                self.route_label(route),
                 KeyValue::new(labels::DOMAIN, domain.to_string()),
             ],
         );
     }
 
+    /// A request rejected because the route's or backend's (`scope`) `max_in_flight` is reached.
+    pub fn record_concurrency_rejection(&self, scope: &'static str, route: &str, domain: &str) {
+        self.errors_total
+            .add(1, &[KeyValue::new(labels::ERROR_TYPE, values::ERROR_CONCURRENCY_LIMITED)]);
+        self.concurrency_rejected_total.add(
+            1,
+            &[
+                KeyValue::new(labels::SCOPE, scope),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    /// A request shed while the proxy is overloaded.
+    pub fn record_load_shed(&self, route: &str, domain: &str, priority: &'static str) {
+        self.errors_total
+            .add(1, &[KeyValue::new(labels::ERROR_TYPE, values::ERROR_LOAD_SHED)]);
+        self.load_shed_total.add(
+            1,
+            &[
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+                KeyValue::new(labels::PRIORITY, priority),
+            ],
+        );
+    }
+
+    pub fn set_load_shed_ratio(&self, ratio: f64) {
+        self.load_shed_ratio.record(ratio, &[]);
+    }
+
+    /// A request answered by its route's `synthetic` response.
+    pub fn record_synthetic_response(&self, route: &str, domain: &str, status_code: u16) {
+        self.synthetic_responses_total.add(
+            1,
+            &[
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+                KeyValue::new(labels::STATUS_CODE, status_code.to_string()),
+            ],
+        );
+    }
+
+    /// A request answered by its route's `serve_static` directory.
+    pub fn record_static_response(&self, route: &str, domain: &str, status_code: u16) {
+        self.static_responses_total.add(
+            1,
+            &[
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+                KeyValue::new(labels::STATUS_CODE, status_code.to_string()),
+            ],
+        );
+    }
+
+    /// A CONNECT request of a route with a `connect` block; `result` is one of
+    /// `values::CONNECT_*`.
+    pub fn record_connect_tunnel(&self, route: &str, domain: &str, result: &'static str) {
+        self.connect_tunnels_total.add(
+            1,
+            &[
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+                KeyValue::new(labels::RESULT, result),
+            ],
+        );
+    }
+
+    /// `count` headers scrubbed by a route's `privacy` block; `context` is
+    /// `values::CONTEXT_REQUEST` or `values::CONTEXT_RESPONSE`, `action` one of
+    /// `values::PRIVACY_*`.
+    pub fn record_privacy_headers_scrubbed(
+        &self,
+        count: u64,
+        route: &str,
+        domain: &str,
+        context: &'static str,
+        action: &'static str,
+    ) {
+        if count > 0 {
+            self.privacy_headers_scrubbed_total.add(
+                count,
+                &[
+                    self.route_label(route),
+                    KeyValue::new(labels::DOMAIN, domain.to_string()),
+                    KeyValue::new(labels::CONTEXT, context),
+                    KeyValue::new(labels::ACTION, action),
+                ],
+            );
+        }
+    }
+
+    /// A request answered by a `redirect` block; `kind` is `https` or `rule`.
+    pub fn record_redirect(&self, domain: &str, kind: &'static str, status_code: u16) {
+        self.redirects_total.add(
+            1,
+            &[
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+                KeyValue::new(labels::KIND, kind),
+                KeyValue::new(labels::STATUS_CODE, status_code.to_string()),
+            ],
+        );
+    }
+
+    /// `delta` is `1` when a request on a limited route starts and `-1` when it ends.
+    pub fn record_route_in_flight(&self, delta: i64, route: &str, domain: &str) {
+        self.route_in_flight_requests.add(
+            delta,
+            &[self.route_label(route), KeyValue::new(labels::DOMAIN, domain.to_string())],
+        );
+    }
+
+    /// `delta` is `1` when a request to a limited backend starts and `-1` when it ends.
+    pub fn record_backend_in_flight(&self, delta: i64, backend: &str) {
+        self.backend_in_flight_requests
+            .add(delta, &[self.backend_label(labels::BACKEND_ADDRESS, backend)]);
+    }
+
+    pub fn record_backend_queued(&self, delta: i64, backend: &str) {
+        self.backend_queued_requests
+            .add(delta, &[self.backend_label(labels::BACKEND_ADDRESS, backend)]);
+    }
+
+    /// A request that left the queue of the backend at `backend` without a slot; `reason` is
+    /// [`values::QUEUE_FULL`] or [`values::QUEUE_TIMEOUT`].
+    pub fn record_backend_queue_rejection(
+        &self,
+        backend: &str,
+        reason: &'static str,
+        priority: &'static str,
+    ) {
+        self.errors_total
+            .add(1, &[KeyValue::new(labels::ERROR_TYPE, values::ERROR_CONCURRENCY_LIMITED)]);
+        self.backend_queue_rejected_total.add(
+            1,
+            &[
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
+                KeyValue::new(labels::REASON, reason),
+                KeyValue::new(labels::PRIORITY, priority),
+            ],
+        );
+    }
+
     pub fn record_rate_limit_allowed(&self, strategy: &str, route: &str, domain: &str) {
         self.rate_limit_allowed_total.add(
             1,
             &[
                 KeyValue::new(labels::STRATEGY, strategy.to_string()),
-                KeyValue::new(labels::ROUTE, route.to_string()),
+                self.route_label(route),
                 KeyValue::new(labels::DOMAIN, domain.to_string()),
             ],
         );
@@ -473,7 +1232,7 @@ impl Metrics {
             1,
             &[
                 KeyValue::new(labels::STRATEGY, strategy.to_string()),
-                KeyValue::new(labels::ROUTE, route.to_string()),
+                self.route_label(route),
                 KeyValue::new(labels::DOMAIN, domain.to_string()),
             ],
         );
@@ -528,8 +1287,8 @@ impl Metrics {
             self.backend_bytes_received_total.add(
                 bytes,
                 &[
-                    KeyValue::new(labels::BACKEND_ADDRESS, backend.to_string()),
-                    KeyValue::new(labels::ROUTE, route.to_string()),
+                    self.backend_label(labels::BACKEND_ADDRESS, backend),
+                    self.route_label(route),
                     KeyValue::new(labels::DOMAIN, domain.to_string()),
                 ],
             );
@@ -541,8 +1300,34 @@ impl Metrics {
             self.backend_bytes_sent_total.add(
                 bytes,
                 &[
-                    KeyValue::new(labels::BACKEND_ADDRESS, backend.to_string()),
-                    KeyValue::new(labels::ROUTE, route.to_string()),
+                    self.backend_label(labels::BACKEND_ADDRESS, backend),
+                    self.route_label(route),
+                    KeyValue::new(labels::DOMAIN, domain.to_string()),
+                ],
+            );
+        }
+    }
+
+    pub fn record_request_bytes(&self, bytes: u64, backend: &str, route: &str, domain: &str) {
+        if bytes > 0 {
+            self.request_bytes_total.add(
+                bytes,
+                &[
+                    self.backend_label(labels::BACKEND_ADDRESS, backend),
+                    self.route_label(route),
+                    KeyValue::new(labels::DOMAIN, domain.to_string()),
+                ],
+            );
+        }
+    }
+
+    pub fn record_response_bytes(&self, bytes: u64, backend: &str, route: &str, domain: &str) {
+        if bytes > 0 {
+            self.response_bytes_total.add(
+                bytes,
+                &[
+                    self.backend_label(labels::BACKEND_ADDRESS, backend),
+                    self.route_label(route),
                     KeyValue::new(labels::DOMAIN, domain.to_string()),
                 ],
             );
@@ -560,15 +1345,198 @@ impl Metrics {
         self.backend_requests_total.add(
             1,
             &[
-                KeyValue::new(labels::BACKEND_ADDRESS, backend.to_string()),
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
                 KeyValue::new(labels::STATUS_CODE, status_code.to_string()),
                 KeyValue::new(labels::PROTOCOL, protocol.to_string()),
-                KeyValue::new(labels::ROUTE, route.to_string()),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    pub fn record_grpc_response(
+        &self,
+        backend: &str,
+        grpc_status: &str,
+        route: &str,
+        domain: &str,
+    ) {
+        self.grpc_responses_total.add(
+            1,
+            &[
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
+                KeyValue::new(labels::GRPC_STATUS, grpc_status.to_string()),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    pub fn record_request_body_too_large(&self, route: &str, domain: &str) {
+        self.request_body_too_large_total
+            .add(1, &[self.route_label(route), KeyValue::new(labels::DOMAIN, domain.to_string())]);
+    }
+
+    pub fn record_response_body_too_large(&self, backend: &str, route: &str, domain: &str) {
+        self.response_body_too_large_total.add(
+            1,
+            &[
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    /// `direction` is `values::DIRECTION_CLIENT_TO_BACKEND` (request bodies) or
+    /// `values::DIRECTION_BACKEND_TO_CLIENT` (response bodies).
+    pub fn record_bandwidth_throttled(&self, direction: &'static str, route: &str, domain: &str) {
+        self.bandwidth_throttled_total.add(
+            1,
+            &[
+                KeyValue::new(labels::DIRECTION, direction),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    /// `count` events of `[event_stream]` with outcome `result` ([`values::EVENT_PUBLISHED`],
+    /// [`values::EVENT_DROPPED`] or [`values::EVENT_FAILED`]).
+    pub fn record_stream_events(&self, result: &'static str, count: u64) {
+        self.event_stream_events_total
+            .add(count, &[KeyValue::new(labels::RESULT, result)]);
+    }
+
+    /// One fingerprint of `kind` reported by `[telemetry.anomaly_detection]`.
+    pub fn record_fingerprint_anomaly(&self, kind: &'static str) {
+        self.fingerprint_anomalies_total
+            .add(1, &[KeyValue::new(labels::KIND, kind)]);
+    }
+
+    pub fn record_backend_timeout(
+        &self,
+        backend: &str,
+        timeout_type: &'static str,
+        route: &str,
+        domain: &str,
+    ) {
+        self.backend_timeouts_total.add(
+            1,
+            &[
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
+                KeyValue::new(labels::TIMEOUT_TYPE, timeout_type),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    /// `backend` is the backend whose answer triggered the failover.
+    pub fn record_backend_failover(
+        &self,
+        backend: &str,
+        reason: &'static str,
+        route: &str,
+        domain: &str,
+    ) {
+        self.backend_failovers_total.add(
+            1,
+            &[
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
+                KeyValue::new(labels::REASON, reason),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    /// `policy` is the backend's `http_version`, `version` the protocol of the response.
+    pub fn record_backend_negotiated_protocol(
+        &self,
+        backend: &str,
+        policy: &'static str,
+        version: http::Version,
+    ) {
+        self.backend_negotiated_protocol_total.add(
+            1,
+            &[
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
+                KeyValue::new(labels::HTTP_VERSION, policy),
+                KeyValue::new(labels::PROTOCOL, format!("{version:?}")),
+            ],
+        );
+    }
+
+    pub fn record_compressed_response(&self, encoding: &'static str, route: &str, domain: &str) {
+        self.compressed_responses_total.add(
+            1,
+            &[
+                KeyValue::new(labels::ENCODING, encoding),
+                self.route_label(route),
                 KeyValue::new(labels::DOMAIN, domain.to_string()),
             ],
         );
     }
 
+    pub fn record_cache_lookup(&self, result: &'static str, route: &str, domain: &str) {
+        self.cache_lookups_total.add(
+            1,
+            &[
+                KeyValue::new(labels::RESULT, result),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    /// A new upstream connection to `backend` was established.
+    pub fn record_backend_connection_opened(&self, backend: &str) {
+        let attrs = [self.backend_label(labels::BACKEND_ADDRESS, backend)];
+        self.backend_connections_opened_total.add(1, &attrs);
+        self.backend_pool_connections.add(1, &attrs);
+    }
+
+    /// Time it took to establish a new upstream connection to `backend`.
+    pub fn record_backend_connect_duration(&self, duration: f64, backend: &str) {
+        self.backend_connect_duration_seconds
+            .record(duration, &[self.backend_label(labels::BACKEND_ADDRESS, backend)]);
+    }
+
+    /// Time from sending a request to `backend` to the first frame of its response body.
+    pub fn record_backend_ttfb(&self, duration: f64, backend: &str, route: &str, domain: &str) {
+        self.backend_ttfb_seconds.record(
+            duration,
+            &[
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    /// An upstream connection to `backend` was closed.
+    pub fn record_backend_connection_closed(&self, backend: &str) {
+        self.backend_pool_connections
+            .add(-1, &[self.backend_label(labels::BACKEND_ADDRESS, backend)]);
+    }
+
+    /// Record one external authorization check and how long it took.
+    pub fn record_ext_authz_check(
+        &self,
+        result: &'static str,
+        duration: f64,
+        route: &str,
+        domain: &str,
+    ) {
+        let route = self.route_label(route);
+        let domain = KeyValue::new(labels::DOMAIN, domain.to_string());
+        self.ext_authz_checks_total
+            .add(1, &[KeyValue::new(labels::RESULT, result), route.clone(), domain.clone()]);
+        self.ext_authz_duration_seconds
+            .record(duration, &[route, domain]);
+    }
+
     pub fn record_backend_duration(
         &self,
         duration: f64,
@@ -581,10 +1549,10 @@ impl Metrics {
         self.backend_duration_seconds.record(
             duration,
             &[
-                KeyValue::new(labels::BACKEND_ADDRESS, backend.to_string()),
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
                 KeyValue::new(labels::STATUS_CODE, status_code.to_string()),
                 KeyValue::new(labels::PROTOCOL, protocol.to_string()),
-                KeyValue::new(labels::ROUTE, route.to_string()),
+                self.route_label(route),
                 KeyValue::new(labels::DOMAIN, domain.to_string()),
             ],
         );
@@ -594,9 +1562,9 @@ impl Metrics {
         self.backend_errors_total.add(
             1,
             &[
-                KeyValue::new(labels::BACKEND_ADDRESS, backend.to_string()),
+                self.backend_label(labels::BACKEND_ADDRESS, backend),
                 KeyValue::new(labels::ERROR_TYPE, error_type.to_string()),
-                KeyValue::new(labels::ROUTE, route.to_string()),
+                self.route_label(route),
                 KeyValue::new(labels::DOMAIN, domain.to_string()),
             ],
         );
@@ -620,6 +1588,7 @@ impl Metrics {
         protocol: &str,
         route: &str,
         domain: &str,
+        tenant: &str,
     ) {
         self.requests_total.add(
             1,
@@ -627,12 +1596,15 @@ impl Metrics {
                 KeyValue::new(labels::METHOD, method.to_string()),
                 KeyValue::new(labels::STATUS_CODE, status_code.to_string()),
                 KeyValue::new(labels::PROTOCOL, protocol.to_string()),
-                KeyValue::new(labels::ROUTE, route.to_string()),
+                self.route_label(route),
                 KeyValue::new(labels::DOMAIN, domain.to_string()),
+                KeyValue::new(labels::TENANT, tenant.to_string()),
             ],
         );
     }
 
+    /// `backend` is `None` when the request was answered before a backend was selected.
+    #[allow(clippy::too_many_arguments)]
     pub fn record_request_duration(
         &self,
         duration: f64,
@@ -641,6 +1613,8 @@ impl Metrics {
         protocol: &str,
         route: &str,
         domain: &str,
+        tenant: &str,
+        backend: Option<&str>,
     ) {
         self.requests_duration_seconds.record(
             duration,
@@ -648,8 +1622,13 @@ impl Metrics {
                 KeyValue::new(labels::METHOD, method.to_string()),
                 KeyValue::new(labels::STATUS_CODE, status_code.to_string()),
                 KeyValue::new(labels::PROTOCOL, protocol.to_string()),
-                KeyValue::new(labels::ROUTE, route.to_string()),
+                self.route_label(route),
                 KeyValue::new(labels::DOMAIN, domain.to_string()),
+                KeyValue::new(labels::TENANT, tenant.to_string()),
+                self.backend_label(
+                    labels::BACKEND_ADDRESS,
+                    backend.unwrap_or(values::BACKEND_NONE),
+                ),
             ],
         );
     }
@@ -696,7 +1675,7 @@ impl Metrics {
 
     pub fn record_backend_selection(&self, backend: &str) {
         self.backend_selections_total
-            .add(1, &[KeyValue::new(labels::BACKEND, backend.to_string())]);
+            .add(1, &[self.backend_label(labels::BACKEND, backend)]);
     }
 
     /// Record a successful config reload.
@@ -751,11 +1730,40 @@ impl Metrics {
         );
     }
 
+    /// Record one OCSP responder request made for stapling `domain`'s certificate.
+    pub fn record_tls_ocsp_fetch(&self, domain: &str, success: bool) {
+        let result = if success {
+            values::RELOAD_SUCCESS
+        } else {
+            values::RELOAD_ERROR
+        };
+        self.tls_ocsp_fetch_total.add(
+            1,
+            &[
+                KeyValue::new(labels::RESULT, result),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    /// Record the stapling state of `domain`'s certificate: the age of its stapled OCSP
+    /// response, or `None` when nothing is stapled.
+    pub fn record_tls_ocsp_staple(&self, domain: &str, age: Option<std::time::Duration>) {
+        let attrs = [KeyValue::new(labels::DOMAIN, domain.to_string())];
+        self.tls_ocsp_stapled
+            .record(u64::from(age.is_some()), &attrs);
+        if let Some(age) = age {
+            self.tls_ocsp_staple_age_seconds
+                .record(age.as_secs_f64(), &attrs);
+        }
+    }
+
     /// Record a rejected incoming connection.
     ///
     /// `reason` is one of:
-    /// - `"limit_exceeded"` active connection count hit `max_connections`
-    /// - `"shutdown"`       proxy is shutting down
+    /// - `"limit_exceeded"`        active connection count hit `max_connections`
+    /// - `"per_ip_limit_exceeded"` the client IP already has `max_connections_per_ip` open
+    /// - `"shutdown"`              proxy is shutting down
     pub fn record_connection_rejected(&self, reason: &'static str) {
         self.connections_rejected_total
             .add(1, &[KeyValue::new(labels::REASON, reason)]);
@@ -781,6 +1789,83 @@ impl Metrics {
             .add(1, &[KeyValue::new(labels::HEADER, header)]);
     }
 
+    /// Record a verdict of the registered fingerprint classifier (`allow`, `deny`, `tag`).
+    pub fn record_classifier_verdict(&self, verdict: &'static str) {
+        self.classifier_verdicts_total
+            .add(1, &[KeyValue::new(labels::VERDICT, verdict)]);
+    }
+
+    /// Record a request rejected by the fingerprint filter. `fingerprint` is the configured
+    /// `deny` entry that matched (bounded by the config, not by client input).
+    pub fn record_fingerprint_filter_blocked(&self, kind: &'static str, fingerprint: &str) {
+        self.fingerprint_filter_blocked_total.add(
+            1,
+            &[
+                KeyValue::new(labels::KIND, kind),
+                KeyValue::new(labels::FINGERPRINT, fingerprint.to_string()),
+            ],
+        );
+    }
+
+    /// Record a request matched by a WAF rule. `rule` is a built-in, configured or limit rule id
+    /// (bounded by the config, not by client input); `action` is `block` or `detect`.
+    pub fn record_waf_hit(&self, rule: &str, action: &'static str, route: &str, domain: &str) {
+        self.waf_hits_total.add(
+            1,
+            &[
+                KeyValue::new(labels::RULE, rule.to_string()),
+                KeyValue::new(labels::ACTION, action),
+                self.route_label(route),
+                KeyValue::new(labels::DOMAIN, domain.to_string()),
+            ],
+        );
+    }
+
+    /// Record the outcome of a crawler verification, cached or not. `result` is `verified`,
+    /// `spoofed` or `unknown` (the lookups failed or timed out).
+    pub fn record_bot_verification(&self, crawler: &str, result: &'static str) {
+        self.bot_verifications_total.add(
+            1,
+            &[
+                KeyValue::new(labels::CRAWLER, crawler.to_string()),
+                KeyValue::new(labels::RESULT, result),
+            ],
+        );
+    }
+
+    /// Record a request from a `[security.challenge]` suspect: `served` the challenge page, or
+    /// `passed` with a valid cookie.
+    pub fn record_challenge(&self, result: &'static str) {
+        self.challenges_total
+            .add(1, &[KeyValue::new(labels::RESULT, result)]);
+    }
+
+    /// Record a graylisted TLS connection: `delayed` by `[security.tarpit]`, or `skipped` because
+    /// `max_concurrent` connections were already waiting.
+    pub fn record_tarpit(&self, kind: &'static str, result: &'static str) {
+        self.tarpit_connections_total
+            .add(1, &[KeyValue::new(labels::KIND, kind), KeyValue::new(labels::RESULT, result)]);
+    }
+
+    /// Record a request counted by `[client_tracking]`: `new` on the first request of a client and
+    /// JA4, `returning` afterwards.
+    pub fn record_client_tracking(&self, result: &'static str) {
+        self.client_tracking_total
+            .add(1, &[KeyValue::new(labels::RESULT, result)]);
+    }
+
+    /// Record a request violating HTTP conformance: one with both `Transfer-Encoding` and
+    /// `Content-Length`, or one caught by `[security.strict_http]`.
+    pub fn record_protocol_violation(&self, violation: &'static str, action: &'static str) {
+        self.protocol_violations_total.add(
+            1,
+            &[
+                KeyValue::new(labels::VIOLATION, violation),
+                KeyValue::new(labels::ACTION, action),
+            ],
+        );
+    }
+
     /// Record a TCP SYN fingerprint lookup result and its duration.
     ///
     /// `result` is one of:
@@ -825,10 +1910,20 @@ impl Metrics {
         self.proxy_protocol_dropped_total
             .add(1, &[KeyValue::new(labels::REASON, reason)]);
     }
+
+    /// Outcome of a TLS passthrough connection, one of the `values::PASSTHROUGH_*` constants.
+    pub fn record_tls_passthrough(&self, result: &'static str) {
+        self.tls_passthrough_connections_total
+            .add(1, &[KeyValue::new(labels::RESULT, result)]);
+    }
 }
 
-pub fn init_metrics() -> Result<(Arc<Metrics>, Registry), Box<dyn std::error::Error + Send + Sync>>
-{
+/// Install the Prometheus exporter as the global meter provider and build the proxy's
+/// instruments. `fingerprints` adds its top-N gauges when enabled.
+pub fn init_metrics(
+    config: &MetricsConfig,
+    fingerprints: &FingerprintStats,
+) -> Result<(Arc<Metrics>, Registry), Box<dyn std::error::Error + Send + Sync>> {
     let registry = Registry::default();
 
     let exporter = opentelemetry_prometheus::exporter()
@@ -840,7 +1935,8 @@ pub fn init_metrics() -> Result<(Arc<Metrics>, Registry), Box<dyn std::error::Er
     global::set_meter_provider(meter_provider);
 
     let meter = global::meter("huginn-proxy");
-    let metrics = Arc::new(Metrics::new(meter));
+    let metrics = Arc::new(Metrics::new(meter.clone(), config));
+    fingerprints.register_gauges(&meter);
 
     metrics.set_build_info();
 
@@ -1,15 +1,35 @@
+pub mod access_log;
+pub mod admin;
+pub mod anomaly;
+pub mod event_stream;
+pub mod fingerprint_stats;
+pub mod handshake_capture;
 pub mod health;
 pub mod metrics;
 pub mod metrics_handler;
 pub mod readiness;
+pub(crate) mod rotating_file;
 pub mod router;
 pub mod server;
+pub mod spans;
 pub mod status;
 pub mod tracing;
 
-pub use health::{health_check_response, live_check_response, ready_check_response};
+pub use access_log::{AccessLogContext, AccessLogger, AccessRecord, PendingAccess, RequestLog};
+pub use admin::AdminState;
+pub use anomaly::{AnomalyDetector, FingerprintAnomaly};
+pub use event_stream::{EventStream, FingerprintEvent};
+pub use fingerprint_stats::{FingerprintCount, FingerprintSnapshot, FingerprintStats};
+pub use handshake_capture::{HandshakeCapture, HandshakeRecord};
+pub use health::{
+    backends_health_response, health_check_response, live_check_response, ready_check_response,
+};
 pub use metrics::{init_metrics, values, Metrics};
 pub use metrics_handler::handle_metrics;
 pub use readiness::Readiness;
 pub use server::start_observability_server;
-pub use tracing::{init_tracing_with_otel, init_validation_tracing, shutdown_tracing};
+pub use spans::{RequestSpan, RequestTracer};
+pub use tracing::{
+    init_tracing_with_otel, init_validation_tracing, shutdown_tracing, LogLevelError,
+    LogLevelSnapshot, LogLevels, RouteLogLevel,
+};
@@ -1,29 +1,44 @@
 //! Readiness state shared between the proxy and the observability server's `/ready` endpoint.
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
 
+const STARTING: u8 = 0;
+const READY: u8 = 1;
+const DRAINING: u8 = 2;
+
 #[derive(Clone, Default)]
-pub struct Readiness(Arc<AtomicBool>);
+pub struct Readiness(Arc<AtomicU8>);
 
 impl Readiness {
-    /// Create a new handle in the not-ready state.
+    /// Create a new handle in the not-ready (starting) state.
     pub fn new() -> Self {
         Self::default()
     }
 
     /// Mark the proxy as ready to accept traffic (`/ready` -> 200).
     pub fn mark_ready(&self) {
-        self.0.store(true, Ordering::Release);
+        self.0.store(READY, Ordering::Release);
     }
 
-    /// Mark the proxy as not ready (`/ready` -> 503), e.g. during graceful shutdown.
+    /// Mark the proxy as not ready (`/ready` -> 503) because it is still starting.
     pub fn mark_not_ready(&self) {
-        self.0.store(false, Ordering::Release);
+        self.0.store(STARTING, Ordering::Release);
+    }
+
+    /// Mark the proxy as draining (`/ready` -> 503): it is shutting down or handing its
+    /// listeners over to a new process, and will not become ready again.
+    pub fn mark_draining(&self) {
+        self.0.store(DRAINING, Ordering::Release);
     }
 
     /// Whether the proxy is currently ready to accept traffic.
     pub fn is_ready(&self) -> bool {
-        self.0.load(Ordering::Acquire)
+        self.0.load(Ordering::Acquire) == READY
+    }
+
+    /// Whether the proxy is draining.
+    pub fn is_draining(&self) -> bool {
+        self.0.load(Ordering::Acquire) == DRAINING
     }
 }
@@ -0,0 +1,110 @@
+//! Size-bounded, append-mode output file shared by the access log and the handshake capture, and
+//! the file rotation the SQLite event sink reuses.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Append-mode file, renamed to `<path>.1` (shifting older files up to `<path>.<max_files>`)
+/// once it would grow past `max_size` bytes.
+///
+/// An optional header (e.g. the PCAPNG section header) is written whenever a file is opened, so
+/// every file, rotated or not, can be read on its own.
+pub(crate) struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    header: Vec<u8>,
+    file: BufWriter<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub(crate) fn open(path: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        Self::open_with_header(path, max_size, max_files, Vec::new())
+    }
+
+    /// Like [`RotatingFile::open`], writing `header` first in the current file and after every
+    /// rotation.
+    pub(crate) fn open_with_header(
+        path: &Path,
+        max_size: u64,
+        max_files: usize,
+        header: Vec<u8>,
+    ) -> io::Result<Self> {
+        let file = open_append(path)?;
+        let size = file.metadata()?.len();
+        let mut rotating = Self {
+            path: path.to_path_buf(),
+            max_size,
+            max_files,
+            header,
+            file: BufWriter::new(file),
+            size,
+        };
+        rotating.write_header()?;
+        Ok(rotating)
+    }
+
+    /// Append one record, rotating first when it would not fit. A record is never split.
+    pub(crate) fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        let len = u64::try_from(record.len()).unwrap_or(u64::MAX);
+        let header_len = u64::try_from(self.header.len()).unwrap_or(u64::MAX);
+        if self.max_size > 0
+            && self.size > header_len
+            && self.size.saturating_add(len) > self.max_size
+        {
+            self.rotate()?;
+        }
+        self.file.write_all(record)?;
+        self.size = self.size.saturating_add(len);
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        self.file.write_all(&self.header)?;
+        self.size = self
+            .size
+            .saturating_add(u64::try_from(self.header.len()).unwrap_or(u64::MAX));
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        shift_rotated(&self.path, self.max_files)?;
+        self.file = BufWriter::new(open_append(&self.path)?);
+        self.size = 0;
+        self.write_header()
+    }
+}
+
+/// Rename `path` to `<path>.1`, shifting older files up to `<path>.<max_files>`; the oldest is
+/// overwritten.
+pub(crate) fn shift_rotated(path: &Path, max_files: usize) -> io::Result<()> {
+    for index in (1..max_files).rev() {
+        rename_if_exists(&rotated_path(path, index), &rotated_path(path, index.saturating_add(1)))?;
+    }
+    rename_if_exists(path, &rotated_path(path, 1))
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// `<path>.<index>`
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{index}"));
+    PathBuf::from(rotated)
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
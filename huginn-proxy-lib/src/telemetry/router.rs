@@ -2,17 +2,30 @@ use hyper::{Response, StatusCode};
 use prometheus::Registry;
 use tracing::{debug, warn};
 
+use crate::backend::HealthRegistry;
 use crate::telemetry::status::{Status, StatusBody};
 use crate::telemetry::{
-    handle_metrics, health_check_response, live_check_response, ready_check_response, Readiness,
+    backends_health_response, handle_metrics, health_check_response, live_check_response,
+    ready_check_response, FingerprintStats, Readiness,
 };
 use crate::utils::http::{json_response, RespBody};
 
-pub fn dispatch(path: &str, registry: &Registry, readiness: &Readiness) -> Response<RespBody> {
+pub fn dispatch(
+    path: &str,
+    registry: &Registry,
+    readiness: &Readiness,
+    health: &HealthRegistry,
+    fingerprints: &FingerprintStats,
+) -> Response<RespBody> {
     let response = match path {
         "/health" => health_check_response(),
-        "/ready" => ready_check_response(readiness.is_ready()),
+        "/health/backends" => backends_health_response(health),
+        "/ready" => ready_check_response(readiness),
         "/live" => live_check_response(),
+        "/observability/fingerprints" => match fingerprints.snapshot() {
+            Some(snapshot) => json_response(StatusCode::OK, snapshot),
+            None => json_response(StatusCode::NOT_FOUND, StatusBody::new(Status::NotFound)),
+        },
         "/metrics" => handle_metrics(registry).unwrap_or_else(|e| {
             warn!(error = %e, "Failed to encode metrics");
             json_response(StatusCode::INTERNAL_SERVER_ERROR, StatusBody::new(Status::Error))
@@ -1,5 +1,7 @@
+use crate::backend::HealthRegistry;
+use crate::telemetry::admin::{handle_admin, AdminState};
 use crate::telemetry::router::dispatch;
-use crate::telemetry::Readiness;
+use crate::telemetry::{FingerprintStats, Readiness};
 use hyper::body::Incoming;
 use hyper::Request;
 use hyper_util::rt::{TokioExecutor, TokioIo};
@@ -15,21 +17,34 @@ use tracing::{info, warn};
 /// This server runs on a dedicated port and serves:
 /// - `/metrics` - Prometheus metrics
 /// - `/health` - Health check endpoint
+/// - `/health/backends` - Per-backend active health-check state
 /// - `/ready` - Readiness check endpoint
 /// - `/live` - Liveness check endpoint
+/// - `/observability/fingerprints` - Top JA4 / Akamai fingerprints, only when `fingerprints` is
+///   enabled
+/// - `/admin/...` - Authenticated admin API, only when `admin` is `Some` (see
+///   [`crate::telemetry::admin`])
 ///
 /// `readiness` is flipped to `true` by the proxy once its listeners are accepting
 /// connections and back to `false` during graceful shutdown; `/ready` reflects it.
+/// `health` is the same registry the proxy's health-check supervisor writes to.
 pub async fn start_observability_server(
     port: u16,
     registry: Registry,
     readiness: Readiness,
+    health: Arc<HealthRegistry>,
+    fingerprints: FingerprintStats,
+    admin: Option<AdminState>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let registry = Arc::new(registry);
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     let listener = TcpListener::bind(addr).await?;
 
-    info!(?addr, "Observability server started (metrics + health checks)");
+    info!(
+        ?addr,
+        admin = admin.is_some(),
+        "Observability server started (metrics + health checks)"
+    );
 
     let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate())
         .map_err(|e| std::io::Error::other(format!("Failed to setup SIGTERM handler: {e}")))?;
@@ -57,15 +72,28 @@ pub async fn start_observability_server(
 
                 let registry = registry.clone();
                 let readiness = readiness.clone();
+                let health = health.clone();
+                let fingerprints = fingerprints.clone();
+                let admin = admin.clone();
                 tokio::spawn(async move {
                     let svc = hyper::service::service_fn(move |req: Request<Incoming>| {
                         let registry = registry.clone();
                         let readiness = readiness.clone();
+                        let health = health.clone();
+                        let fingerprints = fingerprints.clone();
+                        let admin = admin.clone();
                         async move {
+                            let is_admin = req.uri().path() == "/admin"
+                                || req.uri().path().starts_with("/admin/");
+                            if let (true, Some(admin)) = (is_admin, admin.as_ref()) {
+                                return Ok::<_, hyper::Error>(handle_admin(req, admin).await);
+                            }
                             Ok::<_, hyper::Error>(dispatch(
                                 req.uri().path(),
                                 &registry,
                                 &readiness,
+                                &health,
+                                &fingerprints,
                             ))
                         }
                     });
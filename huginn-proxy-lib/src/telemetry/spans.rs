@@ -0,0 +1,192 @@
+//! OpenTelemetry request spans (`[telemetry.tracing]`).
+//!
+//! Every proxied request gets a server span that continues the client's W3C trace context
+//! (`traceparent` / `tracestate`) when it sends one. The span's own context then replaces those
+//! headers on the request, so spans recorded by the backend become its children. Route, backend,
+//! status and the connection fingerprints are attached when the response is known, from the same
+//! [`AccessRecord`] the access log writes.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::{Request, Version};
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::{SpanKind, Status, TraceContextExt, Tracer, TracerProvider};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use tracing::warn;
+
+use crate::config::TracingConfig;
+use crate::error::{ProxyError, Result};
+use crate::telemetry::AccessRecord;
+
+/// Instrumentation scope name of the request spans.
+const TRACER_NAME: &str = "huginn-proxy";
+
+/// Starts request spans. A disabled tracer (the default) creates none and leaves trace context
+/// headers untouched.
+#[derive(Clone, Default)]
+pub struct RequestTracer {
+    inner: Option<Arc<TracerInner>>,
+}
+
+struct TracerInner {
+    provider: SdkTracerProvider,
+    tracer: SdkTracer,
+    propagator: TraceContextPropagator,
+}
+
+impl RequestTracer {
+    /// Build the OTLP exporter and its batch processor. Returns a disabled tracer when
+    /// `config.enabled` is false.
+    pub fn from_config(config: &TracingConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::disabled());
+        }
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(config.otlp_endpoint.as_str())
+            .with_timeout(Duration::from_millis(config.export_timeout_ms))
+            .build()
+            .map_err(|e| {
+                ProxyError::Config(format!("telemetry.tracing: failed to build OTLP exporter: {e}"))
+            })?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                config.sample_ratio,
+            ))))
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(config.service_name.clone())
+                    .build(),
+            )
+            .build();
+        Ok(Self::from_provider(provider))
+    }
+
+    /// Trace with a provider built by the caller (custom exporter, sampler or resource).
+    pub fn from_provider(provider: SdkTracerProvider) -> Self {
+        let tracer = provider.tracer(TRACER_NAME);
+        let inner = TracerInner { provider, tracer, propagator: TraceContextPropagator::new() };
+        Self { inner: Some(Arc::new(inner)) }
+    }
+
+    /// A tracer that creates no spans.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Start the server span of `req`, continuing its trace context, and replace the request's
+    /// `traceparent` / `tracestate` with the span's. `None` when disabled.
+    pub fn start<B>(&self, req: &mut Request<B>) -> Option<RequestSpan> {
+        let inner = self.inner.as_ref()?;
+        let parent = inner.propagator.extract(&HeaderExtractor(req.headers()));
+        let span = inner
+            .tracer
+            .span_builder(req.method().to_string())
+            .with_kind(SpanKind::Server)
+            .start_with_context(&inner.tracer, &parent);
+        let cx = parent.with_span(span);
+        inner
+            .propagator
+            .inject_context(&cx, &mut HeaderInjector(req.headers_mut()));
+        Some(RequestSpan { cx })
+    }
+
+    /// Export the spans still queued and stop the exporter. Blocks until done; call it once the
+    /// proxy stopped serving requests.
+    pub fn shutdown(&self) {
+        if let Some(inner) = &self.inner {
+            if let Err(e) = inner.provider.shutdown() {
+                warn!(error = %e, "Failed to flush request spans");
+            }
+        }
+    }
+}
+
+/// The span of one request, ended once its response is known.
+pub struct RequestSpan {
+    cx: Context,
+}
+
+impl RequestSpan {
+    /// End the span with the attributes of `record`. `route` is the matched route prefix, which
+    /// also names the span (`GET /api`). Responses with a `5xx` status mark the span as failed.
+    pub fn end(self, record: &AccessRecord, route: Option<&str>) {
+        let span = self.cx.span();
+        let mut attributes = vec![
+            KeyValue::new("http.request.method", record.method.clone()),
+            KeyValue::new("url.path", record.path.clone()),
+            KeyValue::new("server.address", record.host.clone()),
+            KeyValue::new("network.protocol.version", protocol_version(record.protocol)),
+            KeyValue::new("client.address", record.client_ip.to_string()),
+            KeyValue::new("http.response.status_code", i64::from(record.status)),
+        ];
+        if let Some(route) = route {
+            span.update_name(format!("{} {route}", record.method));
+            attributes.push(KeyValue::new("http.route", route.to_string()));
+        }
+        let optional = [
+            ("huginn.request_id", record.request_id.as_deref()),
+            ("tls.client.server_name", record.sni.as_deref()),
+            ("huginn.backend", record.backend.as_deref()),
+            ("huginn.tenant", record.tenant.as_deref()),
+            ("huginn.fingerprint.ja4", record.ja4.as_deref()),
+            ("huginn.fingerprint.akamai", record.akamai.as_deref()),
+            ("huginn.fingerprint.tcp_syn", record.tcp_syn.as_deref()),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                attributes.push(KeyValue::new(key, value.to_string()));
+            }
+        }
+        span.set_attributes(attributes);
+        if record.status >= 500 {
+            span.set_status(Status::error(format!("HTTP {}", record.status)));
+        }
+        span.end();
+    }
+}
+
+fn protocol_version(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "0.9",
+        Version::HTTP_10 => "1.0",
+        Version::HTTP_2 => "2",
+        Version::HTTP_3 => "3",
+        _ => "1.1",
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) =
+            (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value))
+        {
+            self.0.insert(name, value);
+        }
+    }
+}
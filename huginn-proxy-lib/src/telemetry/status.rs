@@ -4,6 +4,7 @@ use serde::Serialize;
 #[serde(rename_all = "snake_case")]
 pub(crate) enum Status {
     Healthy,
+    Degraded,
     Alive,
     Ready,
     NotReady,
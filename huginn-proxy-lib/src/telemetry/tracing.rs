@@ -1,5 +1,18 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use http::Request;
+use serde::Serialize;
+use thiserror::Error;
+use tracing::Span;
+use tracing_subscriber::filter::{Directive, LevelFilter};
 use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::Registry;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::config::Domain;
+use crate::proxy::handler::extract_request_host_inner;
+use crate::proxy::router::{matched_prefix, pick_tenant_domain};
 
 /// Initialize warning-level tracing for one-shot CLI validation.
 ///
@@ -18,23 +31,248 @@ pub fn init_validation_tracing() -> Result<(), Box<dyn std::error::Error + Send
     Ok(())
 }
 
-/// Initialize tracing with OpenTelemetry integration
+/// Initialize tracing with OpenTelemetry integration.
+///
+/// `RUST_LOG`, when set to a valid filter, replaces `log_level` and the OpenTelemetry directive.
+/// The returned [`LogLevels`] changes the filter at runtime (see the admin API).
 pub fn init_tracing_with_otel(
     log_level: String,
     show_target: bool,
     otel_log_level: String,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let filter_str = format!("{log_level},opentelemetry={otel_log_level}");
-    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(filter_str));
+) -> Result<LogLevels, Box<dyn std::error::Error + Send + Sync>> {
+    let (filter, suffix) = match std::env::var("RUST_LOG") {
+        Ok(env) if EnvFilter::try_new(&env).is_ok() => (env, None),
+        _ => (log_level, Some(format!("opentelemetry={otel_log_level}"))),
+    };
+    let (filter_layer, log_levels) = LogLevels::reloadable(filter, suffix);
     let fmt_layer = tracing_subscriber::fmt::layer().with_target(show_target);
 
-    let subscriber = Registry::default().with(env_filter).with(fmt_layer);
+    let subscriber = Registry::default().with(filter_layer).with(fmt_layer);
 
     tracing::subscriber::set_global_default(subscriber)
         .map_err(|e| format!("Failed to set global tracing subscriber: {e}"))?;
 
-    Ok(())
+    Ok(log_levels)
+}
+
+/// Runtime control of the tracing filter, behind the admin API's `/admin/log_level`.
+///
+/// Besides replacing the whole filter, a route can get its own level: requests matching it run
+/// inside a `request` span and a span directive raises the level of everything logged inside it.
+/// A route level therefore only adds verbosity; it cannot silence what the global filter enables.
+/// A disabled handle (the default) rejects every change.
+#[derive(Clone, Default)]
+pub struct LogLevels {
+    inner: Option<Arc<LogLevelsInner>>,
+}
+
+struct LogLevelsInner {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Directives kept after every filter change (the OpenTelemetry level).
+    suffix: Option<String>,
+    state: Mutex<LogLevelSnapshot>,
+    /// `false` while no route override is set, so [`LogLevels::request_span`] skips routing.
+    has_routes: AtomicBool,
+}
+
+/// Current filter and route overrides, as listed by `GET /admin/log_level`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct LogLevelSnapshot {
+    /// Global filter in `EnvFilter` syntax, e.g. `info` or `info,huginn_proxy_lib=debug`.
+    pub filter: String,
+    pub routes: Vec<RouteLogLevel>,
+}
+
+/// Level override of one route.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RouteLogLevel {
+    /// Domain label: its `host`, or `_default_` for the catch-all.
+    pub domain: String,
+    pub prefix: String,
+    pub level: String,
+}
+
+#[derive(Debug, Error)]
+pub enum LogLevelError {
+    #[error("runtime log level control is not available")]
+    Unavailable,
+    #[error("invalid filter '{filter}': {reason}")]
+    InvalidFilter { filter: String, reason: String },
+    #[error("invalid level '{0}', expected off, error, warn, info, debug or trace")]
+    InvalidLevel(String),
+    #[error("route '{0}' cannot be used in a log level override")]
+    InvalidRoute(String),
+    #[error("failed to reload the tracing filter: {0}")]
+    Reload(String),
+}
+
+impl LogLevels {
+    /// Build a reloadable filter layer for a [`Registry`] subscriber and its control handle.
+    /// `suffix` directives are appended to every filter, including ones set at runtime. An
+    /// invalid initial `filter` keeps its valid directives, like [`EnvFilter::new`].
+    pub fn reloadable(
+        filter: String,
+        suffix: Option<String>,
+    ) -> (reload::Layer<EnvFilter, Registry>, Self) {
+        let env_filter = EnvFilter::new(with_suffix(&filter, suffix.as_deref()));
+        let (layer, handle) = reload::Layer::new(env_filter);
+        let inner = LogLevelsInner {
+            handle,
+            suffix,
+            state: Mutex::new(LogLevelSnapshot { filter, routes: Vec::new() }),
+            has_routes: AtomicBool::new(false),
+        };
+        (layer, Self { inner: Some(Arc::new(inner)) })
+    }
+
+    /// A handle that controls nothing; every change returns [`LogLevelError::Unavailable`].
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Current filter and route overrides; `None` when disabled.
+    pub fn snapshot(&self) -> Option<LogLevelSnapshot> {
+        let inner = self.inner.as_ref()?;
+        Some(inner.state().clone())
+    }
+
+    /// Replace the global filter. Route overrides are kept.
+    pub fn set_filter(&self, filter: &str) -> Result<(), LogLevelError> {
+        let inner = self.inner.as_ref().ok_or(LogLevelError::Unavailable)?;
+        EnvFilter::try_new(filter).map_err(|e| LogLevelError::InvalidFilter {
+            filter: filter.to_string(),
+            reason: e.to_string(),
+        })?;
+        let mut state = inner.state();
+        let updated = LogLevelSnapshot { filter: filter.to_string(), routes: state.routes.clone() };
+        inner.apply(&updated)?;
+        *state = updated;
+        Ok(())
+    }
+
+    /// Set the level of requests routed to `prefix` on `domain` (a [`Domain::label`]), or clear
+    /// it with `None`. The route is not checked against the config; the admin API does that.
+    pub fn set_route_level(
+        &self,
+        domain: &str,
+        prefix: &str,
+        level: Option<&str>,
+    ) -> Result<(), LogLevelError> {
+        let inner = self.inner.as_ref().ok_or(LogLevelError::Unavailable)?;
+        let key = format!("{domain}{prefix}");
+        if !key
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !"[]{},=\"'".contains(c))
+        {
+            return Err(LogLevelError::InvalidRoute(key));
+        }
+        let level = level
+            .map(|l| {
+                LevelFilter::from_str(l)
+                    .map(|parsed| parsed.to_string().to_lowercase())
+                    .map_err(|_| LogLevelError::InvalidLevel(l.to_string()))
+            })
+            .transpose()?;
+
+        let mut state = inner.state();
+        let mut updated = state.clone();
+        updated
+            .routes
+            .retain(|r| !(r.domain == domain && r.prefix == prefix));
+        if let Some(level) = level {
+            updated.routes.push(RouteLogLevel {
+                domain: domain.to_string(),
+                prefix: prefix.to_string(),
+                level,
+            });
+        }
+        inner.apply(&updated)?;
+        inner
+            .has_routes
+            .store(!updated.routes.is_empty(), Ordering::Relaxed);
+        *state = updated;
+        Ok(())
+    }
+
+    /// Span for a request whose route has a level override, `Span::none()` otherwise. Routing
+    /// mirrors the request handler (host among the domains of the listener's `tenant`, then route
+    /// matchers), and costs nothing until an override is set.
+    pub fn request_span<B>(
+        &self,
+        domains: &[Domain],
+        tenant: Option<&str>,
+        req: &Request<B>,
+    ) -> Span {
+        let Some(inner) = self
+            .inner
+            .as_ref()
+            .filter(|inner| inner.has_routes.load(Ordering::Relaxed))
+        else {
+            return Span::none();
+        };
+        let host = extract_request_host_inner(req);
+        let Some(domain) = pick_tenant_domain(domains, &host, tenant) else {
+            return Span::none();
+        };
+        let Some(prefix) =
+            matched_prefix(req.method(), req.uri().path(), req.headers(), &domain.routes)
+        else {
+            return Span::none();
+        };
+        let overridden = inner
+            .state()
+            .routes
+            .iter()
+            .any(|r| r.domain == domain.label() && r.prefix == prefix);
+        if !overridden {
+            return Span::none();
+        }
+        // ERROR so the span is enabled by any route directive, whatever its level.
+        let route = format!("{}{prefix}", domain.label());
+        tracing::error_span!("request", route = route.as_str())
+    }
+}
+
+impl LogLevelsInner {
+    fn state(&self) -> std::sync::MutexGuard<'_, LogLevelSnapshot> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Build the filter for `state` and swap it in.
+    fn apply(&self, state: &LogLevelSnapshot) -> Result<(), LogLevelError> {
+        let mut filter = EnvFilter::new(with_suffix(&state.filter, self.suffix.as_deref()));
+        for route in &state.routes {
+            let key = format!("{}{}", route.domain, route.prefix);
+            let directive =
+                format!("[request{{route={}}}]={}", escape_field_pattern(&key), route.level);
+            let directive = Directive::from_str(&directive)
+                .map_err(|_| LogLevelError::InvalidRoute(key.clone()))?;
+            filter = filter.add_directive(directive);
+        }
+        self.handle
+            .reload(filter)
+            .map_err(|e| LogLevelError::Reload(e.to_string()))
+    }
+}
+
+fn with_suffix(filter: &str, suffix: Option<&str>) -> String {
+    match suffix {
+        Some(suffix) if !filter.is_empty() => format!("{filter},{suffix}"),
+        Some(suffix) => suffix.to_string(),
+        None => filter.to_string(),
+    }
+}
+
+/// Span field values in directives are regular expressions; match `value` literally.
+fn escape_field_pattern(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if ".*+?()|^$\\".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
 }
 
 /// Shutdown tracing and flush any pending logs
@@ -5,17 +5,18 @@ use tokio_rustls::rustls::crypto::aws_lc_rs as aws_lc_provider;
 use tokio_rustls::rustls::crypto::CryptoProvider;
 use tokio_rustls::rustls::server::ResolvesServerCert;
 use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::version as rustls_version;
 use tokio_rustls::rustls::RootCertStore;
-use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::{ServerConfig, SupportedProtocolVersion};
 use tokio_rustls::TlsAcceptor;
 
 use crate::config::{ClientAuth, TlsOptions, TlsVersion};
 use crate::error::{ProxyError, Result};
 use crate::tls::cipher_suites::{
-    is_cipher_suite_supported, resolve_cipher_suites, supported_cipher_suites,
+    cipher_suite_version, is_cipher_suite_supported, resolve_cipher_suites, supported_cipher_suites,
 };
-use crate::tls::curves::{is_curve_supported, supported_curves};
-use crate::tls::session_resumption::configure_session_resumption;
+use crate::tls::curves::{is_curve_supported, resolve_kx_groups, supported_curves};
+use crate::tls::session_resumption::{configure_session_resumption, configure_ticket_keys};
 
 /// Loads CA certificates from a PEM file for client authentication
 fn load_ca_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
@@ -29,8 +30,9 @@ fn load_ca_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
 
 /// Builds a `ServerConfig` that uses a `DynamicCertResolver` for SNI-based cert selection.
 ///
-/// Cipher suites, ALPN, client auth, and session resumption are all applied here; cert
-/// provisioning is delegated to the resolver (populated via `DynamicCertResolver::update`).
+/// Protocol versions, cipher suites, key exchange groups, ALPN, client auth, and session
+/// resumption are all applied here; cert provisioning is delegated to the resolver (populated
+/// via `DynamicCertResolver::update`).
 pub fn build_server_config_with_resolver(
     resolver: Arc<dyn ResolvesServerCert>,
     alpn: &[String],
@@ -40,21 +42,28 @@ pub fn build_server_config_with_resolver(
 ) -> Result<TlsAcceptor> {
     validate_tls_options(options)?;
 
-    let provider = if options.cipher_suites.is_empty() {
-        aws_lc_provider::default_provider()
-    } else {
-        CryptoProvider {
-            cipher_suites: resolve_cipher_suites(&options.cipher_suites),
-            ..aws_lc_provider::default_provider()
-        }
-    };
+    let mut provider: CryptoProvider = aws_lc_provider::default_provider();
+    if !options.cipher_suites.is_empty() {
+        provider.cipher_suites = resolve_cipher_suites(&options.cipher_suites);
+    }
+    if !options.curve_preferences.is_empty() {
+        provider.kx_groups = resolve_kx_groups(&options.curve_preferences);
+    }
+    let versions: Vec<&'static SupportedProtocolVersion> = options
+        .effective_versions()
+        .into_iter()
+        .map(|version| match version {
+            TlsVersion::V1_2 => &rustls_version::TLS12,
+            TlsVersion::V1_3 => &rustls_version::TLS13,
+        })
+        .collect();
 
     let builder = ServerConfig::builder_with_provider(Arc::new(provider))
-        .with_safe_default_protocol_versions()
+        .with_protocol_versions(&versions)
         .map_err(|e| ProxyError::Tls(format!("Failed to set TLS protocol versions: {e}")))?;
 
     let mut server = match client_auth {
-        ClientAuth::Required { ca_cert_path } => {
+        ClientAuth::Required { ca_cert_path } | ClientAuth::Optional { ca_cert_path } => {
             let client_ca_certs = load_ca_certs(ca_cert_path)?;
             let mut root_store = RootCertStore::empty();
             for cert in client_ca_certs {
@@ -62,7 +71,15 @@ pub fn build_server_config_with_resolver(
                     .add(cert)
                     .map_err(|e| ProxyError::Tls(format!("Failed to add CA certificate: {e}")))?;
             }
-            let client_verifier = WebPkiClientVerifier::builder(Arc::new(root_store))
+            let verifier_builder = WebPkiClientVerifier::builder(Arc::new(root_store));
+            // `optional`: a presented certificate is still verified, but clients without one
+            // complete the handshake (the request handler applies `client_cert.on_missing`).
+            let verifier_builder = if matches!(client_auth, ClientAuth::Optional { .. }) {
+                verifier_builder.allow_unauthenticated()
+            } else {
+                verifier_builder
+            };
+            let client_verifier = verifier_builder
                 .build()
                 .map_err(|e| ProxyError::Tls(format!("Failed to build client verifier: {e}")))?;
             builder
@@ -77,6 +94,7 @@ pub fn build_server_config_with_resolver(
     }
 
     configure_session_resumption(&mut server, session_resumption);
+    configure_ticket_keys(&mut server, session_resumption)?;
 
     Ok(TlsAcceptor::from(Arc::new(server)))
 }
@@ -94,7 +112,8 @@ fn validate_tls_options_impl(options: &TlsOptions) -> Result<()> {
         }
     }
 
-    if !options.versions.is_empty()
+    // `versions` defaults to every version, so only an explicit subset conflicts with a range.
+    if options.restricts_versions()
         && (options.min_version.is_some() || options.max_version.is_some())
     {
         return Err(ProxyError::Tls(
@@ -118,6 +137,23 @@ fn validate_tls_options_impl(options: &TlsOptions) -> Result<()> {
         }
     }
 
+    let versions = options.effective_versions();
+    if !options.cipher_suites.is_empty()
+        && !options
+            .cipher_suites
+            .iter()
+            .any(|suite| versions.contains(&cipher_suite_version(suite)))
+    {
+        return Err(ProxyError::Tls(format!(
+            "None of the configured cipher suites can be used with the enabled TLS versions ({})",
+            versions
+                .iter()
+                .map(|v| v.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+
     for curve_name in &options.curve_preferences {
         if curve_name.is_empty() {
             return Err(ProxyError::Tls("Curve name cannot be empty".to_string()));
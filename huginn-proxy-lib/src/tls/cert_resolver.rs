@@ -1,15 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use arc_swap::ArcSwap;
+use rustls_pki_types::CertificateDer;
+use tokio::sync::Notify;
 use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
 use tokio_rustls::rustls::sign::CertifiedKey;
 
-use crate::config::Domain;
+use crate::config::{Domain, MustStapleFailure, DEFAULT_DOMAIN_LABEL};
 use crate::error::{ProxyError, Result};
 use crate::telemetry::Metrics;
 use crate::tls::cert_source::{cert_chain_hash, read_certs_and_keys};
+use crate::tls::ocsp::is_must_staple;
 use tracing::{info, warn};
 
 /// Outcome of a [`DynamicCertResolver::update`] call.
@@ -50,6 +53,17 @@ fn classify(host: Option<&str>) -> CertSlot<'_> {
     }
 }
 
+/// One certificate chain held by the resolver, as listed by
+/// [`DynamicCertResolver::certificates`].
+#[derive(Debug, Clone)]
+pub struct LoadedCert {
+    /// Host pattern of the first domain serving the chain (`_default_` for the catch-all).
+    pub domain: String,
+    /// [`cert_chain_hash`] of the chain.
+    pub hash: u64,
+    pub chain: Vec<CertificateDer<'static>>,
+}
+
 #[derive(Default)]
 struct CertMap {
     exact: HashMap<String, Arc<CertifiedKey>>,
@@ -87,6 +101,62 @@ impl CertMap {
             CertSlot::Default => self.default.clone(),
         }
     }
+
+    /// Every entry with its domain label: exact hosts and wildcards in name order, then the
+    /// default certificate.
+    fn entries(&self) -> Vec<(String, &Arc<CertifiedKey>)> {
+        let mut exact: Vec<_> = self.exact.iter().collect();
+        exact.sort_by(|a, b| a.0.cmp(b.0));
+        let mut wildcard: Vec<_> = self.wildcard.iter().collect();
+        wildcard.sort_by(|a, b| a.0.cmp(b.0));
+        exact
+            .into_iter()
+            .map(|(host, key)| (host.clone(), key))
+            .chain(
+                wildcard
+                    .into_iter()
+                    .map(|(base, key)| (format!("*.{base}"), key)),
+            )
+            .chain(
+                self.default
+                    .iter()
+                    .map(|key| (DEFAULT_DOMAIN_LABEL.to_string(), key)),
+            )
+            .collect()
+    }
+
+    /// Copy of the map with each certificate's OCSP response taken from `staples` (by chain
+    /// hash). With `reject_unstapled`, Must-Staple certificates without a response are left out.
+    fn stapled(&self, staples: &HashMap<u64, Vec<u8>>, reject_unstapled: bool) -> CertMap {
+        let staple = |key: &Arc<CertifiedKey>| -> Option<Arc<CertifiedKey>> {
+            let ocsp = staples.get(&cert_chain_hash(&key.cert)).cloned();
+            if ocsp.is_none()
+                && reject_unstapled
+                && key.cert.first().is_some_and(|leaf| is_must_staple(leaf))
+            {
+                return None;
+            }
+            if ocsp == key.ocsp {
+                return Some(Arc::clone(key));
+            }
+            let mut stapled = CertifiedKey::clone(key);
+            stapled.ocsp = ocsp;
+            Some(Arc::new(stapled))
+        };
+        CertMap {
+            exact: self
+                .exact
+                .iter()
+                .filter_map(|(host, key)| Some((host.clone(), staple(key)?)))
+                .collect(),
+            wildcard: self
+                .wildcard
+                .iter()
+                .filter_map(|(base, key)| Some((base.clone(), staple(key)?)))
+                .collect(),
+            default: self.default.as_ref().and_then(staple),
+        }
+    }
 }
 
 /// SNI-based certificate resolver populated from `DynamicConfig.domains`.
@@ -94,8 +164,20 @@ impl CertMap {
 /// Cert maps are swapped atomically via `ArcSwap` so `resolve()` (called on
 /// every TLS handshake) never blocks. `update()` builds the new maps async,
 /// then swaps them in with a single pointer store.
+///
+/// OCSP responses set through [`set_staple`](Self::set_staple) are kept by chain hash and
+/// applied on top of the loaded certificates, so they survive reloads that keep the chain.
 pub struct DynamicCertResolver {
+    /// Served map: `loaded` with OCSP staples applied.
     inner: ArcSwap<CertMap>,
+    /// Certificates as loaded from disk, without staples.
+    loaded: ArcSwap<CertMap>,
+    /// OCSP responses by chain hash; the lock also serializes rebuilds of `inner`.
+    staples: Mutex<HashMap<u64, Vec<u8>>>,
+    /// Stop serving Must-Staple certificates that have no OCSP response.
+    must_staple_failure: MustStapleFailure,
+    /// Signalled after every [`update`](Self::update), for the OCSP stapling task.
+    reloaded: Notify,
     /// Strict SNI mode - full parity with Traefik's `sniStrict`. When `true`, the
     /// default-cert fallback is disabled for *both* cases: an SNI that matches no
     /// exact/wildcard cert is rejected, and a connection with **no** SNI (IP-literal
@@ -111,6 +193,7 @@ impl std::fmt::Debug for DynamicCertResolver {
             .field("exact_domains", &map.exact.len())
             .field("wildcard_domains", &map.wildcard.len())
             .field("sni_strict", &self.sni_strict)
+            .field("must_staple_failure", &self.must_staple_failure)
             .finish()
     }
 }
@@ -123,7 +206,20 @@ impl Default for DynamicCertResolver {
 
 impl DynamicCertResolver {
     pub fn new(sni_strict: bool) -> Self {
-        Self { inner: ArcSwap::new(Arc::new(CertMap::default())), sni_strict }
+        Self {
+            inner: ArcSwap::new(Arc::new(CertMap::default())),
+            loaded: ArcSwap::new(Arc::new(CertMap::default())),
+            staples: Mutex::new(HashMap::new()),
+            must_staple_failure: MustStapleFailure::default(),
+            reloaded: Notify::new(),
+            sni_strict,
+        }
+    }
+
+    /// Set the `[tls.ocsp] must_staple_failure` policy applied to certificates without a staple.
+    pub fn with_must_staple_failure(mut self, policy: MustStapleFailure) -> Self {
+        self.must_staple_failure = policy;
+        self
     }
 
     /// Reload cert maps from `domains`. Domains without `cert_path`/`key_path` are skipped.
@@ -140,7 +236,7 @@ impl DynamicCertResolver {
     /// certificate that actually went into service (carried-over certs keep their existing
     /// gauge value; no spurious success is emitted for them).
     pub async fn update(&self, domains: &[Domain], metrics: &Metrics) -> CertReloadReport {
-        let old = self.inner.load();
+        let old = self.loaded.load();
         let mut next = CertMap::default();
         // Buffered until after the swap; (host, cert_hash) per successfully loaded cert.
         let mut loaded: Vec<(String, u64)> = Vec::new();
@@ -184,7 +280,18 @@ impl DynamicCertResolver {
             }
         }
 
-        self.inner.store(Arc::new(next));
+        let hashes: HashSet<u64> = next
+            .entries()
+            .iter()
+            .map(|(_, key)| cert_chain_hash(&key.cert))
+            .collect();
+        self.loaded.store(Arc::new(next));
+        {
+            let mut staples = self.staples.lock().unwrap_or_else(|e| e.into_inner());
+            staples.retain(|hash, _| hashes.contains(hash));
+            self.restaple(&staples);
+        }
+        self.reloaded.notify_one();
 
         // Emit success metrics only now that the new map is live, so the gauges
         // never advertise a cert that didn't actually go into service.
@@ -195,6 +302,50 @@ impl DynamicCertResolver {
         CertReloadReport { loaded: loaded.len(), failed }
     }
 
+    /// Staple `ocsp` (a DER `OCSPResponse`) to every served certificate whose chain hashes to
+    /// `cert_hash`; `None` removes the staple.
+    pub fn set_staple(&self, cert_hash: u64, ocsp: Option<Vec<u8>>) {
+        let mut staples = self.staples.lock().unwrap_or_else(|e| e.into_inner());
+        match ocsp {
+            Some(ocsp) => staples.insert(cert_hash, ocsp),
+            None => staples.remove(&cert_hash),
+        };
+        self.restaple(&staples);
+    }
+
+    /// The loaded certificate chains, one entry per distinct chain.
+    pub fn certificates(&self) -> Vec<LoadedCert> {
+        let loaded = self.loaded.load();
+        let mut seen = HashSet::new();
+        loaded
+            .entries()
+            .into_iter()
+            .filter_map(|(domain, key)| {
+                let hash = cert_chain_hash(&key.cert);
+                seen.insert(hash)
+                    .then(|| LoadedCert { domain, hash, chain: key.cert.clone() })
+            })
+            .collect()
+    }
+
+    /// Resolves after the next [`update`](Self::update), or at once if one ran since the last
+    /// call.
+    pub async fn reloaded(&self) {
+        self.reloaded.notified().await;
+    }
+
+    /// Rebuild the served map from `loaded` and `staples` (held locked by the caller).
+    fn restaple(&self, staples: &HashMap<u64, Vec<u8>>) {
+        let loaded = self.loaded.load_full();
+        let reject_unstapled = self.must_staple_failure == MustStapleFailure::Reject;
+        if staples.is_empty() && !reject_unstapled {
+            self.inner.store(loaded);
+        } else {
+            self.inner
+                .store(Arc::new(loaded.stapled(staples, reject_unstapled)));
+        }
+    }
+
     /// Core SNI → cert resolution. Separated from [`ResolvesServerCert::resolve`]
     /// so it can be unit-tested without constructing a rustls `ClientHello`.
     fn resolve_sni(&self, sni: Option<&str>) -> Option<Arc<CertifiedKey>> {
@@ -254,6 +405,12 @@ impl DynamicCertResolver {
     pub fn resolves_for(&self, sni: Option<&str>) -> bool {
         self.resolve_sni(sni).is_some()
     }
+
+    /// Test-only: the OCSP response stapled to the certificate an SNI resolves to.
+    #[doc(hidden)]
+    pub fn staple_for(&self, sni: Option<&str>) -> Option<Vec<u8>> {
+        self.resolve_sni(sni)?.ocsp.clone()
+    }
 }
 
 impl ResolvesServerCert for DynamicCertResolver {
@@ -2,6 +2,8 @@ use tokio_rustls::rustls::crypto::aws_lc_rs::cipher_suite as cs;
 use tokio_rustls::rustls::SupportedCipherSuite;
 use tracing::warn;
 
+use crate::config::TlsVersion;
+
 /// Cipher suites supported by rustls with the aws-lc-rs crypto provider.
 pub fn supported_cipher_suites() -> Vec<&'static str> {
     vec![
@@ -25,6 +27,15 @@ pub fn is_cipher_suite_supported(name: &str) -> bool {
     supported_cipher_suites().contains(&name)
 }
 
+/// TLS version a cipher suite belongs to: `TLS13_*` suites are TLS 1.3 only, the others TLS 1.2.
+pub fn cipher_suite_version(name: &str) -> TlsVersion {
+    if name.starts_with("TLS13_") {
+        TlsVersion::V1_3
+    } else {
+        TlsVersion::V1_2
+    }
+}
+
 /// Resolve a list of cipher suite name strings into `SupportedCipherSuite` values.
 ///
 /// Unknown names are silently skipped (validation should have been done earlier
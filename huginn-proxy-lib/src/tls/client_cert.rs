@@ -0,0 +1,174 @@
+//! Identity of a verified mTLS client certificate, as forwarded to backends.
+//!
+//! rustls hands over the peer certificate as raw DER after the verifier accepted it, so only
+//! the subject `Name` is decoded here (a walk of the `tbsCertificate` fields up to `subject`);
+//! everything else about the certificate is covered by its SHA-256 fingerprint.
+
+use std::fmt::Write;
+
+use aws_lc_rs::digest::{digest, SHA256};
+
+use super::der::{read_expected, read_tlv, TAG_OID, TAG_SEQUENCE, TAG_SET};
+
+const TAG_VERSION: u8 = 0xa0;
+const TAG_UTF8_STRING: u8 = 0x0c;
+const TAG_PRINTABLE_STRING: u8 = 0x13;
+const TAG_TELETEX_STRING: u8 = 0x14;
+const TAG_IA5_STRING: u8 = 0x16;
+const TAG_BMP_STRING: u8 = 0x1e;
+
+/// Subject and fingerprint of a client certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertInfo {
+    /// Subject distinguished name in RFC 4514 form, e.g. `CN=client,O=Acme`.
+    pub subject: String,
+    /// SHA-256 of the DER-encoded certificate, lowercase hex.
+    pub sha256: String,
+}
+
+impl ClientCertInfo {
+    /// Decode the end-entity certificate presented by the client.
+    ///
+    /// Returns `None` when `der` is not a well-formed X.509 certificate.
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let subject = subject_dn(der)?;
+        let mut sha256 = String::new();
+        push_hex(digest(&SHA256, der).as_ref(), &mut sha256);
+        Some(Self { subject, sha256 })
+    }
+}
+
+/// Walk `Certificate → tbsCertificate` to the `subject` field and render it.
+fn subject_dn(der: &[u8]) -> Option<String> {
+    let (certificate, _) = read_expected(der, TAG_SEQUENCE)?;
+    let (tbs, _) = read_expected(certificate, TAG_SEQUENCE)?;
+
+    let mut fields = tbs;
+    if fields.first() == Some(&TAG_VERSION) {
+        fields = read_tlv(fields)?.2;
+    }
+    // serialNumber, signature, issuer, validity
+    for _ in 0..4 {
+        fields = read_tlv(fields)?.2;
+    }
+    let (name, _) = read_expected(fields, TAG_SEQUENCE)?;
+
+    let mut rdns = Vec::new();
+    let mut remaining = name;
+    while !remaining.is_empty() {
+        let (rdn, rest) = read_expected(remaining, TAG_SET)?;
+        rdns.push(format_rdn(rdn)?);
+        remaining = rest;
+    }
+    // RFC 4514 §2.1: RDNs are written starting from the last element of the sequence.
+    rdns.reverse();
+    Some(rdns.join(","))
+}
+
+/// Render one RDN; multi-valued RDNs join their attributes with `+`.
+fn format_rdn(mut rdn: &[u8]) -> Option<String> {
+    let mut attributes = Vec::new();
+    while !rdn.is_empty() {
+        let (attribute, rest) = read_expected(rdn, TAG_SEQUENCE)?;
+        let (oid, value) = read_expected(attribute, TAG_OID)?;
+        let (tag, contents, _) = read_tlv(value)?;
+
+        let mut out = attribute_type(oid)?;
+        out.push('=');
+        match decode_string(tag, contents) {
+            Some(text) => escape_value(&text, &mut out),
+            // RFC 4514 §2.4: values of other types use `#` + hex of the whole BER encoding.
+            None => {
+                out.push('#');
+                push_hex(value, &mut out);
+            }
+        }
+        attributes.push(out);
+        rdn = rest;
+    }
+    Some(attributes.join("+"))
+}
+
+/// Short name from RFC 4514 §3 (plus `STREET` / `UID`), or the dotted OID otherwise.
+fn attribute_type(oid: &[u8]) -> Option<String> {
+    let dotted = dotted_oid(oid)?;
+    let short = match dotted.as_str() {
+        "2.5.4.3" => "CN",
+        "2.5.4.6" => "C",
+        "2.5.4.7" => "L",
+        "2.5.4.8" => "ST",
+        "2.5.4.9" => "STREET",
+        "2.5.4.10" => "O",
+        "2.5.4.11" => "OU",
+        "0.9.2342.19200300.100.1.1" => "UID",
+        "0.9.2342.19200300.100.1.25" => "DC",
+        _ => return Some(dotted),
+    };
+    Some(short.to_string())
+}
+
+fn dotted_oid(oid: &[u8]) -> Option<String> {
+    let mut arcs = Vec::new();
+    let mut acc: u64 = 0;
+    for &b in oid {
+        acc = acc.checked_mul(128)?.checked_add(u64::from(b & 0x7f))?;
+        if b & 0x80 == 0 {
+            arcs.push(acc);
+            acc = 0;
+        }
+    }
+    let (&first, rest) = arcs.split_first()?;
+    // The first subidentifier packs the first two arcs (X.690 §8.19.4).
+    let (root, second) = match first {
+        0..=39 => (0, first),
+        40..=79 => (1, first.checked_sub(40)?),
+        _ => (2, first.checked_sub(80)?),
+    };
+    let mut dotted = format!("{root}.{second}");
+    for arc in rest {
+        let _ = write!(dotted, ".{arc}");
+    }
+    Some(dotted)
+}
+
+fn decode_string(tag: u8, contents: &[u8]) -> Option<String> {
+    match tag {
+        TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_IA5_STRING => {
+            std::str::from_utf8(contents).ok().map(str::to_string)
+        }
+        // T.61 in practice carries Latin-1.
+        TAG_TELETEX_STRING => Some(contents.iter().map(|&b| char::from(b)).collect()),
+        TAG_BMP_STRING => {
+            let units = contents
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .ok()
+        }
+        _ => None,
+    }
+}
+
+/// RFC 4514 §2.4 escaping of an attribute value.
+fn escape_value(value: &str, out: &mut String) {
+    let last = value.chars().count().saturating_sub(1);
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            '"' | '+' | ',' | ';' | '<' | '>' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '#' if i == 0 => out.push_str("\\#"),
+            ' ' if i == 0 || i == last => out.push_str("\\ "),
+            '\0' => out.push_str("\\00"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn push_hex(bytes: &[u8], out: &mut String) {
+    for b in bytes {
+        let _ = write!(out, "{b:02x}");
+    }
+}
@@ -1,27 +1,32 @@
+use tokio_rustls::rustls::crypto::aws_lc_rs::kx_group;
+use tokio_rustls::rustls::crypto::SupportedKxGroup;
+use tracing::warn;
+
 /// Elliptic curves (key exchange groups) supported by rustls
 ///
-/// This module provides the list of elliptic curves supported by rustls
-/// for ECDHE key exchange.
-/// List of elliptic curves supported by rustls
+/// This module provides the list of key exchange groups supported by rustls with the
+/// aws-lc-rs crypto provider, for (EC)DHE key exchange.
+/// List of key exchange groups supported by rustls
 ///
-/// Returns a list of all curve names supported by rustls.
+/// Returns a list of all group names supported by rustls.
 /// These are the names that can be used in the TLS configuration.
 ///
-/// rustls supports the following curves:
+/// rustls supports the following groups:
+/// - X25519MLKEM768 - post-quantum hybrid, TLS 1.3 only (rustls' first preference)
 /// - X25519 (Curve25519) - preferred for performance
 /// - secp256r1 (P-256) - widely supported
 /// - secp384r1 (P-384) - higher security
-/// - secp521r1 (P-521) - highest security
 ///
-/// **Total: 4 curves**
+/// **Total: 4 groups**
 pub fn supported_curves() -> Vec<&'static str> {
     vec![
+        // Post-quantum hybrid (TLS 1.3 only)
+        "X25519MLKEM768",
         // X25519 (Curve25519) - preferred for performance
         "X25519",
         // NIST curves
         "secp256r1", // P-256
         "secp384r1", // P-384
-        "secp521r1", // P-521
     ]
 }
 
@@ -29,3 +34,27 @@ pub fn supported_curves() -> Vec<&'static str> {
 pub fn is_curve_supported(name: &str) -> bool {
     supported_curves().contains(&name)
 }
+
+/// Resolve curve names into key exchange groups, keeping the configured preference order.
+///
+/// Unknown names are skipped (validation should have been done earlier by
+/// [`is_curve_supported`]). If the returned `Vec` is empty, callers should fall back to the
+/// provider's default groups.
+pub fn resolve_kx_groups(names: &[String]) -> Vec<&'static dyn SupportedKxGroup> {
+    names
+        .iter()
+        .filter_map(|name| match name.as_str() {
+            "X25519MLKEM768" => Some(kx_group::X25519MLKEM768),
+            "X25519" => Some(kx_group::X25519),
+            "secp256r1" => Some(kx_group::SECP256R1),
+            "secp384r1" => Some(kx_group::SECP384R1),
+            unknown => {
+                warn!(
+                    curve = unknown,
+                    "unknown curve ignored; check `supported_curves()` for valid names"
+                );
+                None
+            }
+        })
+        .collect()
+}
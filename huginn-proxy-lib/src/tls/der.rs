@@ -0,0 +1,67 @@
+//! The few DER (X.690) primitives the certificate and OCSP code needs: reading one element and
+//! encoding one from its contents. Only definite lengths are supported, as DER requires.
+
+pub(crate) const TAG_BOOLEAN: u8 = 0x01;
+pub(crate) const TAG_INTEGER: u8 = 0x02;
+pub(crate) const TAG_BIT_STRING: u8 = 0x03;
+pub(crate) const TAG_OCTET_STRING: u8 = 0x04;
+pub(crate) const TAG_NULL: u8 = 0x05;
+pub(crate) const TAG_OID: u8 = 0x06;
+pub(crate) const TAG_ENUMERATED: u8 = 0x0a;
+pub(crate) const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub(crate) const TAG_SEQUENCE: u8 = 0x30;
+pub(crate) const TAG_SET: u8 = 0x31;
+
+/// One DER element: `(tag, contents, remaining input)`.
+pub(crate) fn read_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (usize::from(first), rest)
+    } else {
+        let num_bytes = usize::from(first & 0x7f);
+        if num_bytes == 0 || num_bytes > std::mem::size_of::<usize>() {
+            return None;
+        }
+        let (len_bytes, rest) = rest.split_at_checked(num_bytes)?;
+        let len = len_bytes
+            .iter()
+            .try_fold(0usize, |acc, &b| acc.checked_mul(256)?.checked_add(usize::from(b)))?;
+        (len, rest)
+    };
+    let (contents, rest) = rest.split_at_checked(len)?;
+    Some((tag, contents, rest))
+}
+
+/// Contents of the next element if it carries `tag`, and the remaining input.
+pub(crate) fn read_expected(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    match read_tlv(input)? {
+        (t, contents, rest) if t == tag => Some((contents, rest)),
+        _ => None,
+    }
+}
+
+/// The next element with its tag and length octets, and the remaining input.
+pub(crate) fn split_element(input: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (_, _, rest) = read_tlv(input)?;
+    input.split_at_checked(input.len().checked_sub(rest.len())?)
+}
+
+/// Encode one element.
+pub(crate) fn encode(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let len = contents.len();
+    let mut out = Vec::with_capacity(len.saturating_add(10));
+    out.push(tag);
+    match u8::try_from(len) {
+        Ok(short) if short < 0x80 => out.push(short),
+        _ => {
+            let be = len.to_be_bytes();
+            let skip = be.iter().take_while(|&&b| b == 0).count();
+            let len_bytes = be.get(skip..).unwrap_or_default();
+            out.push(0x80 | u8::try_from(len_bytes.len()).unwrap_or_default());
+            out.extend_from_slice(len_bytes);
+        }
+    }
+    out.extend_from_slice(contents);
+    out
+}
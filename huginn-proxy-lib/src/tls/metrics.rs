@@ -18,6 +18,50 @@ pub fn extract_tls_info<S>(tls: &tokio_rustls::server::TlsStream<S>) -> (String,
     (tls_version, cipher_suite)
 }
 
+/// Negotiated parameters of one TLS connection, forwarded as `x-tls-*` headers when
+/// `fingerprint.tls_info_headers` is enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsHandshakeInfo {
+    /// ALPN protocol agreed on, `None` when the client offered none
+    pub alpn: Option<String>,
+    /// SNI sent by the client
+    pub sni: Option<String>,
+    /// Protocol version, formatted like the `tls_version` metric label
+    pub version: String,
+    /// Cipher suite, formatted like the `cipher_suite` metric label
+    pub cipher: String,
+    /// The ClientHello carried the TLS 1.3 `early_data` extension
+    pub early_data: bool,
+    /// The ClientHello carried the `encrypted_client_hello` extension
+    pub ech: bool,
+}
+
+impl TlsHandshakeInfo {
+    /// Collect the parameters of a completed handshake; `early_data` and `ech` come from the
+    /// ClientHello (see [`client_hello_offers_early_data`] and [`client_hello_offers_ech`]).
+    ///
+    /// [`client_hello_offers_early_data`]: crate::fingerprinting::client_hello_offers_early_data
+    /// [`client_hello_offers_ech`]: crate::fingerprinting::client_hello_offers_ech
+    pub fn from_stream<S>(
+        tls: &tokio_rustls::server::TlsStream<S>,
+        early_data: bool,
+        ech: bool,
+    ) -> Self {
+        let (version, cipher) = extract_tls_info(tls);
+        let (_, connection) = tls.get_ref();
+        Self {
+            alpn: connection
+                .alpn_protocol()
+                .map(|p| String::from_utf8_lossy(p).into_owned()),
+            sni: connection.server_name().map(str::to_string),
+            version,
+            cipher,
+            early_data,
+            ech,
+        }
+    }
+}
+
 pub fn record_tls_handshake_metrics<S>(
     tls: &tokio_rustls::server::TlsStream<S>,
     handshake_duration: f64,
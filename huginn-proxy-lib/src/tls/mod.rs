@@ -2,14 +2,23 @@ pub mod acceptor;
 pub mod cert_resolver;
 pub mod cert_source;
 pub mod cipher_suites;
+pub mod client_cert;
 pub mod curves;
+pub(crate) mod der;
 pub mod metrics;
+pub mod ocsp;
 pub mod session_resumption;
 pub mod setup;
+pub mod ticket_keys;
+pub mod upstream;
 pub use acceptor::build_server_config_with_resolver;
-pub use cert_resolver::{CertReloadReport, DynamicCertResolver};
+pub use cert_resolver::{CertReloadReport, DynamicCertResolver, LoadedCert};
 pub use cert_source::{cert_chain_hash, ServerCertsKeys};
 pub use cipher_suites::{is_cipher_suite_supported, supported_cipher_suites};
+pub use client_cert::ClientCertInfo;
 pub use curves::{is_curve_supported, supported_curves};
-pub use metrics::{extract_tls_info, record_tls_handshake_metrics};
+pub use metrics::{extract_tls_info, record_tls_handshake_metrics, TlsHandshakeInfo};
+pub use ocsp::{spawn_ocsp_stapler, OcspStapler};
 pub use setup::build_tls_acceptor;
+pub use ticket_keys::SharedTicketer;
+pub use upstream::build_upstream_client_config;
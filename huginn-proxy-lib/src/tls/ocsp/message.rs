@@ -0,0 +1,459 @@
+//! OCSP messages (RFC 6960) for stapling: one single-certificate request per chain, POSTed over
+//! HTTP to the responder named in the leaf's Authority Information Access extension.
+//!
+//! Only what a staple decision needs is decoded from the response: its status, the entry for
+//! our serial number and its validity window. The responder's signature is not checked here; the
+//! client verifies the stapled response against the issuer it already trusts.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use aws_lc_rs::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+use bytes::Bytes;
+use http::header::CONTENT_TYPE;
+use http::{Method, Request};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use rustls_pki_types::CertificateDer;
+use thiserror::Error;
+
+use crate::tls::der::{
+    encode, read_expected, read_tlv, split_element, TAG_BIT_STRING, TAG_BOOLEAN, TAG_ENUMERATED,
+    TAG_GENERALIZED_TIME, TAG_INTEGER, TAG_NULL, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE,
+};
+
+/// `[0]` constructed: certificate version, `responseBytes`, `nextUpdate`.
+const TAG_EXPLICIT_0: u8 = 0xa0;
+/// `[3]` constructed: certificate extensions.
+const TAG_EXTENSIONS: u8 = 0xa3;
+/// `[6]` primitive `uniformResourceIdentifier` of a `GeneralName`.
+const TAG_URI: u8 = 0x86;
+/// `CertStatus` choices; `good` and `unknown` are implicit NULLs, `revoked` a sequence.
+const TAG_STATUS_GOOD: u8 = 0x80;
+const TAG_STATUS_REVOKED: u8 = 0xa1;
+
+/// 1.3.6.1.5.5.7.1.1 `id-pe-authorityInfoAccess`
+const OID_AUTHORITY_INFO_ACCESS: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x01, 0x01];
+/// 1.3.6.1.5.5.7.1.24 `id-pe-tlsfeature` (RFC 7633)
+const OID_TLS_FEATURE: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x01, 0x18];
+/// 1.3.6.1.5.5.7.48.1 `id-ad-ocsp`
+const OID_AD_OCSP: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01];
+/// 1.3.6.1.5.5.7.48.1.1 `id-pkix-ocsp-basic`
+const OID_OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+/// 1.3.14.3.2.26 `id-sha1`, the `CertID` hash every responder accepts.
+const OID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+
+/// TLS feature `status_request` (RFC 6066 extension 5): the certificate is Must-Staple.
+const FEATURE_STATUS_REQUEST: &[u8] = &[0x05];
+
+/// Largest response body read from a responder.
+const MAX_RESPONSE_LEN: usize = 64 * 1024;
+/// How far `thisUpdate` may lie in the future before a response is refused.
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Error)]
+pub enum OcspError {
+    #[error("certificate chain has no issuer certificate after the leaf")]
+    NoIssuer,
+    #[error("certificate names no OCSP responder")]
+    NoResponder,
+    #[error("unsupported OCSP responder URL '{0}' (only http:// is supported)")]
+    UnsupportedResponder(String),
+    #[error("malformed certificate")]
+    MalformedCertificate,
+    #[error("OCSP request failed: {0}")]
+    Http(String),
+    #[error("OCSP responder answered HTTP {0}")]
+    HttpStatus(u16),
+    #[error("OCSP request timed out")]
+    Timeout,
+    #[error("malformed OCSP response")]
+    Malformed,
+    #[error("OCSP responder error (responseStatus {0})")]
+    Responder(u8),
+    #[error("OCSP response has no entry for the certificate")]
+    NoMatchingResponse,
+    #[error("certificate is revoked")]
+    Revoked,
+    #[error("certificate status is unknown to the responder")]
+    Unknown,
+    #[error("OCSP response has no nextUpdate")]
+    NoNextUpdate,
+    #[error("OCSP response is not valid yet")]
+    NotYetValid,
+    #[error("OCSP response has expired")]
+    Expired,
+}
+
+pub type OcspClient = Client<HttpConnector, Full<Bytes>>;
+
+/// Plain-HTTP client for responder requests; responders are not reached over TLS.
+pub fn ocsp_client() -> OcspClient {
+    Client::builder(TokioExecutor::new()).build_http()
+}
+
+/// Where and what to ask about one certificate chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OcspTarget {
+    /// Responder URL from the leaf's Authority Information Access extension.
+    pub responder: String,
+    /// DER-encoded `OCSPRequest` for the leaf.
+    pub request: Vec<u8>,
+    /// The leaf carries the TLS feature `status_request` (OCSP Must-Staple, RFC 7633).
+    pub must_staple: bool,
+    serial: Vec<u8>,
+}
+
+impl OcspTarget {
+    /// Build the request for the leaf of `chain`; the certificate after it must be its issuer.
+    pub fn from_chain(chain: &[CertificateDer<'_>]) -> Result<Self, OcspError> {
+        let leaf = chain.first().ok_or(OcspError::MalformedCertificate)?;
+        let leaf = TbsFields::parse(leaf).ok_or(OcspError::MalformedCertificate)?;
+        let responder = extension(leaf.extensions, OID_AUTHORITY_INFO_ACCESS)
+            .and_then(ocsp_responder)
+            .ok_or(OcspError::NoResponder)?;
+        if !responder.starts_with("http://") {
+            return Err(OcspError::UnsupportedResponder(responder));
+        }
+        let issuer = chain.get(1).ok_or(OcspError::NoIssuer)?;
+        let issuer = TbsFields::parse(issuer).ok_or(OcspError::MalformedCertificate)?;
+
+        Ok(Self {
+            responder,
+            request: encode_request(leaf.issuer, issuer.public_key, leaf.serial),
+            must_staple: leaf.is_must_staple(),
+            serial: leaf.serial.to_vec(),
+        })
+    }
+
+    /// Decode `der` as the answer to this target's request, checked for freshness at `now`.
+    pub fn parse_response(&self, der: &[u8], now: SystemTime) -> Result<OcspResponse, OcspError> {
+        let single = match decode_response(der).ok_or(OcspError::Malformed)? {
+            Decoded::Status(status) => return Err(OcspError::Responder(status)),
+            Decoded::Responses(responses) => responses
+                .into_iter()
+                .find(|r| r.serial == self.serial.as_slice())
+                .ok_or(OcspError::NoMatchingResponse)?,
+        };
+        match single.status {
+            TAG_STATUS_GOOD => {}
+            TAG_STATUS_REVOKED => return Err(OcspError::Revoked),
+            _ => return Err(OcspError::Unknown),
+        }
+
+        let this_update = generalized_time(single.this_update).ok_or(OcspError::Malformed)?;
+        let next_update = single.next_update.ok_or(OcspError::NoNextUpdate)?;
+        let next_update = generalized_time(next_update).ok_or(OcspError::Malformed)?;
+        if this_update > now.checked_add(MAX_CLOCK_SKEW).unwrap_or(now) {
+            return Err(OcspError::NotYetValid);
+        }
+        if next_update <= now {
+            return Err(OcspError::Expired);
+        }
+        Ok(OcspResponse { der: der.to_vec(), this_update, next_update })
+    }
+}
+
+/// A `good` response for one certificate, as stapled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OcspResponse {
+    /// The `OCSPResponse` exactly as the responder sent it.
+    pub der: Vec<u8>,
+    pub this_update: SystemTime,
+    pub next_update: SystemTime,
+}
+
+impl OcspResponse {
+    /// Halfway through the validity window, the point where a fresh response is fetched.
+    pub fn refresh_at(&self) -> SystemTime {
+        let window = self
+            .next_update
+            .duration_since(self.this_update)
+            .unwrap_or_default();
+        self.this_update
+            .checked_add(window.checked_div(2).unwrap_or_default())
+            .unwrap_or(self.this_update)
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.next_update <= now
+    }
+
+    /// Time since `thisUpdate`; zero while it lies in the future.
+    pub fn age(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.this_update).unwrap_or_default()
+    }
+}
+
+/// `true` when `cert` (DER) carries the TLS feature `status_request` (OCSP Must-Staple).
+pub fn is_must_staple(cert: &[u8]) -> bool {
+    TbsFields::parse(cert).is_some_and(|fields| fields.is_must_staple())
+}
+
+/// POST the target's request to its responder and decode the answer.
+///
+/// `timeout` bounds the whole exchange: connect, response and body.
+pub async fn fetch(
+    client: &OcspClient,
+    target: &OcspTarget,
+    timeout: Duration,
+) -> Result<OcspResponse, OcspError> {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(&target.responder)
+        .header(CONTENT_TYPE, "application/ocsp-request")
+        .body(Full::new(Bytes::from(target.request.clone())))
+        .map_err(|e| OcspError::Http(e.to_string()))?;
+
+    let exchange = async {
+        let response = client
+            .request(request)
+            .await
+            .map_err(|e| OcspError::Http(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(OcspError::HttpStatus(response.status().as_u16()));
+        }
+        let body = Limited::new(response.into_body(), MAX_RESPONSE_LEN)
+            .collect()
+            .await
+            .map_err(|e| OcspError::Http(e.to_string()))?;
+        Ok::<_, OcspError>(body.to_bytes())
+    };
+    let body = tokio::time::timeout(timeout, exchange)
+        .await
+        .map_err(|_| OcspError::Timeout)??;
+    target.parse_response(&body, SystemTime::now())
+}
+
+/// The `tbsCertificate` fields used for OCSP.
+struct TbsFields<'a> {
+    /// `serialNumber` contents.
+    serial: &'a [u8],
+    /// `issuer` Name, tag and length included (hashed as such in the `CertID`).
+    issuer: &'a [u8],
+    /// `subjectPublicKey` bits, without the unused-bits octet.
+    public_key: &'a [u8],
+    /// Contents of the `Extensions` sequence; empty without extensions.
+    extensions: &'a [u8],
+}
+
+impl<'a> TbsFields<'a> {
+    fn parse(der: &'a [u8]) -> Option<Self> {
+        let (certificate, _) = read_expected(der, TAG_SEQUENCE)?;
+        let (tbs, _) = read_expected(certificate, TAG_SEQUENCE)?;
+
+        let mut fields = tbs;
+        if fields.first() == Some(&TAG_EXPLICIT_0) {
+            fields = read_tlv(fields)?.2;
+        }
+        let (serial, fields) = read_expected(fields, TAG_INTEGER)?;
+        let (_, _, fields) = read_tlv(fields)?; // signature
+        let (issuer, fields) = split_element(fields)?;
+        let (_, _, fields) = read_tlv(fields)?; // validity
+        let (_, _, fields) = read_tlv(fields)?; // subject
+        let (spki, mut fields) = read_expected(fields, TAG_SEQUENCE)?;
+        let (_, spki) = read_expected(spki, TAG_SEQUENCE)?; // algorithm
+        let (key_bits, _) = read_expected(spki, TAG_BIT_STRING)?;
+        let public_key = key_bits.get(1..)?;
+
+        // Optional issuerUniqueID [1] and subjectUniqueID [2] precede the extensions [3].
+        let mut extensions: &[u8] = &[];
+        while !fields.is_empty() {
+            let (tag, contents, rest) = read_tlv(fields)?;
+            if tag == TAG_EXTENSIONS {
+                extensions = read_expected(contents, TAG_SEQUENCE)?.0;
+            }
+            fields = rest;
+        }
+        Some(Self { serial, issuer, public_key, extensions })
+    }
+
+    fn is_must_staple(&self) -> bool {
+        let Some((mut features, _)) = extension(self.extensions, OID_TLS_FEATURE)
+            .and_then(|value| read_expected(value, TAG_SEQUENCE))
+        else {
+            return false;
+        };
+        while let Some((feature, rest)) = read_expected(features, TAG_INTEGER) {
+            if feature == FEATURE_STATUS_REQUEST {
+                return true;
+            }
+            features = rest;
+        }
+        false
+    }
+}
+
+/// `extnValue` contents of the extension identified by `oid`.
+fn extension<'a>(mut extensions: &'a [u8], oid: &[u8]) -> Option<&'a [u8]> {
+    while !extensions.is_empty() {
+        let (ext, rest) = read_expected(extensions, TAG_SEQUENCE)?;
+        extensions = rest;
+        let (id, mut ext) = read_expected(ext, TAG_OID)?;
+        if id != oid {
+            continue;
+        }
+        if ext.first() == Some(&TAG_BOOLEAN) {
+            ext = read_tlv(ext)?.2; // critical
+        }
+        return Some(read_expected(ext, TAG_OCTET_STRING)?.0);
+    }
+    None
+}
+
+/// First `id-ad-ocsp` URI of an Authority Information Access extension value.
+fn ocsp_responder(value: &[u8]) -> Option<String> {
+    let (mut descriptions, _) = read_expected(value, TAG_SEQUENCE)?;
+    while !descriptions.is_empty() {
+        let (description, rest) = read_expected(descriptions, TAG_SEQUENCE)?;
+        descriptions = rest;
+        let (method, location) = read_expected(description, TAG_OID)?;
+        if method != OID_AD_OCSP {
+            continue;
+        }
+        if let Some((uri, _)) = read_expected(location, TAG_URI) {
+            return std::str::from_utf8(uri).ok().map(str::to_string);
+        }
+    }
+    None
+}
+
+/// `OCSPRequest` with a single SHA-1 `CertID` and no extensions.
+fn encode_request(issuer_name: &[u8], issuer_key: &[u8], serial: &[u8]) -> Vec<u8> {
+    let name_hash = digest(&SHA1_FOR_LEGACY_USE_ONLY, issuer_name);
+    let key_hash = digest(&SHA1_FOR_LEGACY_USE_ONLY, issuer_key);
+    let algorithm =
+        encode(TAG_SEQUENCE, &[encode(TAG_OID, OID_SHA1), encode(TAG_NULL, &[])].concat());
+    let cert_id = encode(
+        TAG_SEQUENCE,
+        &[
+            algorithm,
+            encode(TAG_OCTET_STRING, name_hash.as_ref()),
+            encode(TAG_OCTET_STRING, key_hash.as_ref()),
+            encode(TAG_INTEGER, serial),
+        ]
+        .concat(),
+    );
+    // OCSPRequest → TBSRequest → requestList → Request → reqCert
+    let request = encode(TAG_SEQUENCE, &cert_id);
+    let request_list = encode(TAG_SEQUENCE, &request);
+    let tbs_request = encode(TAG_SEQUENCE, &request_list);
+    encode(TAG_SEQUENCE, &tbs_request)
+}
+
+/// Fields of one `SingleResponse`.
+struct SingleResponse<'a> {
+    serial: &'a [u8],
+    /// Tag of the `CertStatus` choice.
+    status: u8,
+    this_update: &'a [u8],
+    next_update: Option<&'a [u8]>,
+}
+
+enum Decoded<'a> {
+    /// `responseStatus` other than `successful`.
+    Status(u8),
+    Responses(Vec<SingleResponse<'a>>),
+}
+
+/// Walk `OCSPResponse → BasicOCSPResponse → ResponseData` to its `SingleResponse`s.
+fn decode_response(der: &[u8]) -> Option<Decoded<'_>> {
+    let (response, _) = read_expected(der, TAG_SEQUENCE)?;
+    let (status, rest) = read_expected(response, TAG_ENUMERATED)?;
+    match status {
+        [0] => {}
+        [status] => return Some(Decoded::Status(*status)),
+        _ => return None,
+    }
+    let (bytes, _) = read_expected(rest, TAG_EXPLICIT_0)?;
+    let (bytes, _) = read_expected(bytes, TAG_SEQUENCE)?;
+    let (kind, bytes) = read_expected(bytes, TAG_OID)?;
+    if kind != OID_OCSP_BASIC {
+        return None;
+    }
+    let (basic, _) = read_expected(bytes, TAG_OCTET_STRING)?;
+    let (basic, _) = read_expected(basic, TAG_SEQUENCE)?;
+    let (data, _) = read_expected(basic, TAG_SEQUENCE)?;
+
+    let mut fields = data;
+    if fields.first() == Some(&TAG_EXPLICIT_0) {
+        fields = read_tlv(fields)?.2; // version
+    }
+    let (_, _, fields) = read_tlv(fields)?; // responderID
+    let (_, fields) = read_expected(fields, TAG_GENERALIZED_TIME)?; // producedAt
+    let (mut entries, _) = read_expected(fields, TAG_SEQUENCE)?;
+
+    let mut responses = Vec::new();
+    while !entries.is_empty() {
+        let (single, rest) = read_expected(entries, TAG_SEQUENCE)?;
+        entries = rest;
+        let (cert_id, fields) = read_expected(single, TAG_SEQUENCE)?;
+        // hashAlgorithm, issuerNameHash, issuerKeyHash, serialNumber
+        let (_, _, cert_id) = read_tlv(cert_id)?;
+        let (_, _, cert_id) = read_tlv(cert_id)?;
+        let (_, _, cert_id) = read_tlv(cert_id)?;
+        let (serial, _) = read_expected(cert_id, TAG_INTEGER)?;
+        let (status, _, fields) = read_tlv(fields)?;
+        let (this_update, fields) = read_expected(fields, TAG_GENERALIZED_TIME)?;
+        let next_update = match read_expected(fields, TAG_EXPLICIT_0) {
+            Some((explicit, _)) => Some(read_expected(explicit, TAG_GENERALIZED_TIME)?.0),
+            None => None,
+        };
+        responses.push(SingleResponse { serial, status, this_update, next_update });
+    }
+    Some(Decoded::Responses(responses))
+}
+
+/// `YYYYMMDDHHMMSS[.fff]Z` as a point in time.
+fn generalized_time(contents: &[u8]) -> Option<SystemTime> {
+    let text = std::str::from_utf8(contents).ok()?.strip_suffix('Z')?;
+    // Fractional seconds are allowed in OCSP responses but carry nothing a staple needs.
+    let digits = text.split_once('.').map_or(text, |(whole, _)| whole);
+    if digits.len() != 14 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |from: usize, to: usize| digits.get(from..to)?.parse::<u64>().ok();
+    let (year, month, day) = (field(0, 4)?, field(4, 6)?, field(6, 8)?);
+    let (hour, minute, second) = (field(8, 10)?, field(10, 12)?, field(12, 14)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    let secs = days_from_civil(year, month, day)?
+        .checked_mul(86_400)?
+        .checked_add(hour.checked_mul(3600)?)?
+        .checked_add(minute.checked_mul(60)?)?
+        .checked_add(second.min(59))?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// Days since 1970-01-01 of a Gregorian date (H. Hinnant's `days_from_civil`, unsigned form);
+/// `None` before the epoch.
+fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = year / 400;
+    let yoe = year % 400;
+    let mp = if month > 2 {
+        month.checked_sub(3)?
+    } else {
+        month.checked_add(9)?
+    };
+    let doy = mp
+        .checked_mul(153)?
+        .checked_add(2)?
+        .checked_div(5)?
+        .checked_add(day)?
+        .checked_sub(1)?;
+    let doe = yoe
+        .checked_mul(365)?
+        .checked_add(yoe / 4)?
+        .checked_sub(yoe / 100)?
+        .checked_add(doy)?;
+    era.checked_mul(146_097)?
+        .checked_add(doe)?
+        .checked_sub(719_468)
+}
@@ -0,0 +1,154 @@
+//! OCSP stapling (`[tls.ocsp]`).
+//!
+//! A background task keeps one `good` OCSP response per served certificate chain and hands it
+//! to the [`DynamicCertResolver`], which staples it in the handshake. A response is refreshed
+//! halfway through its validity window, retried every `retry_secs` while the responder fails,
+//! and dropped at its `nextUpdate` so an expired response is never stapled. Chains are keyed by
+//! their hash, so a certificate reload that keeps a chain keeps its response.
+
+pub mod message;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tracing::{debug, info, warn};
+
+use crate::config::OcspConfig;
+use crate::proxy::shutdown::{ServiceHandle, ServiceName, ShutdownWatch};
+use crate::telemetry::Metrics;
+use crate::tls::cert_resolver::{DynamicCertResolver, LoadedCert};
+use message::{fetch, ocsp_client, OcspClient, OcspResponse, OcspTarget};
+
+pub use message::{is_must_staple, OcspError};
+
+/// Longest pause between passes; staple age gauges are refreshed at least this often.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Shortest pause between passes.
+const MIN_WAIT: Duration = Duration::from_secs(1);
+
+/// Stapling state of one certificate chain.
+struct ChainState {
+    /// Metrics/log label of the first domain serving the chain.
+    domain: String,
+    /// `None` when the chain cannot be stapled (no responder URL or no issuer).
+    target: Option<OcspTarget>,
+    response: Option<OcspResponse>,
+    next_fetch: SystemTime,
+}
+
+impl ChainState {
+    fn new(cert: &LoadedCert, now: SystemTime) -> Self {
+        let target = match OcspTarget::from_chain(&cert.chain) {
+            Ok(target) => Some(target),
+            Err(e) => {
+                info!(domain = %cert.domain, reason = %e, "Certificate will not be OCSP stapled");
+                None
+            }
+        };
+        Self { domain: cert.domain.clone(), target, response: None, next_fetch: now }
+    }
+}
+
+/// Fetches, caches and refreshes the OCSP responses of the resolver's certificates.
+pub struct OcspStapler {
+    resolver: Arc<DynamicCertResolver>,
+    config: OcspConfig,
+    metrics: Arc<Metrics>,
+    client: OcspClient,
+    chains: HashMap<u64, ChainState>,
+}
+
+impl OcspStapler {
+    pub fn new(
+        resolver: Arc<DynamicCertResolver>,
+        config: OcspConfig,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self { resolver, config, metrics, client: ocsp_client(), chains: HashMap::new() }
+    }
+
+    /// One pass over the resolver's certificates: fetch the responses that are due, drop the
+    /// expired ones and record staple ages. Returns when the next fetch is due.
+    pub async fn refresh(&mut self, now: SystemTime) -> SystemTime {
+        let certs = self.resolver.certificates();
+        self.chains
+            .retain(|hash, _| certs.iter().any(|cert| cert.hash == *hash));
+
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let retry = Duration::from_secs(self.config.retry_secs);
+        let mut next_pass = now.checked_add(CHECK_INTERVAL).unwrap_or(now);
+
+        for cert in &certs {
+            let chain = self
+                .chains
+                .entry(cert.hash)
+                .or_insert_with(|| ChainState::new(cert, now));
+            let Some(target) = &chain.target else {
+                continue;
+            };
+
+            if chain.next_fetch <= now {
+                match fetch(&self.client, target, timeout).await {
+                    Ok(response) => {
+                        debug!(domain = %chain.domain, "OCSP response fetched");
+                        self.metrics.record_tls_ocsp_fetch(&chain.domain, true);
+                        self.resolver
+                            .set_staple(cert.hash, Some(response.der.clone()));
+                        chain.next_fetch = response
+                            .refresh_at()
+                            .max(now.checked_add(retry).unwrap_or(now));
+                        chain.response = Some(response);
+                    }
+                    Err(e) => {
+                        warn!(domain = %chain.domain, responder = %target.responder, error = %e, "OCSP fetch failed");
+                        self.metrics.record_tls_ocsp_fetch(&chain.domain, false);
+                        chain.next_fetch = now.checked_add(retry).unwrap_or(now);
+                    }
+                }
+            }
+
+            if chain.response.as_ref().is_some_and(|r| r.is_expired(now)) {
+                warn!(domain = %chain.domain, "OCSP response expired without a successful refresh; no longer stapled");
+                chain.response = None;
+                self.resolver.set_staple(cert.hash, None);
+            }
+            let age = chain.response.as_ref().map(|r| r.age(now));
+            self.metrics.record_tls_ocsp_staple(&chain.domain, age);
+            next_pass = next_pass.min(chain.next_fetch);
+        }
+        next_pass
+    }
+}
+
+/// Spawn the stapling task. It runs a pass at start, when a pass is due and after every
+/// certificate reload, until shutdown.
+pub fn spawn_ocsp_stapler(
+    mut stapler: OcspStapler,
+    mut shutdown_rx: ShutdownWatch,
+) -> ServiceHandle {
+    let resolver = Arc::clone(&stapler.resolver);
+    let handle = tokio::spawn(async move {
+        loop {
+            let now = SystemTime::now();
+            let next_pass = tokio::select! {
+                biased;
+                _ = shutdown_rx.wait_for(|v| *v) => break,
+                next_pass = stapler.refresh(now) => next_pass,
+            };
+            let wait = next_pass
+                .duration_since(now)
+                .unwrap_or_default()
+                .clamp(MIN_WAIT, CHECK_INTERVAL);
+            tokio::select! {
+                biased;
+                _ = shutdown_rx.wait_for(|v| *v) => break,
+                _ = resolver.reloaded() => {}
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+        info!("OCSP stapling task shutting down");
+    });
+
+    ServiceHandle { handle, name: ServiceName::OcspStapling }
+}
@@ -5,6 +5,8 @@ use tokio_rustls::rustls::server::{
 use tokio_rustls::rustls::ServerConfig;
 
 use crate::config::SessionResumptionConfig;
+use crate::error::Result;
+use crate::tls::ticket_keys::SharedTicketer;
 
 /// Configures session resumption in ServerConfig
 ///
@@ -15,9 +17,9 @@ use crate::config::SessionResumptionConfig;
 /// Rustls automatically selects the appropriate mechanism based on the negotiated TLS version.
 /// A single ServerConfig can handle both versions simultaneously.
 ///
-/// We use rustls defaults for TLS 1.3 ticketer (no explicit configuration).
+/// We use rustls defaults for TLS 1.3 ticketer (no explicit configuration); shared ticket keys
+/// are installed separately by [`configure_ticket_keys`].
 /// We only configure TLS 1.2 session storage with a configurable cache size.
-/// For TLS 1.3: we leave rustls default ticketer unchanged
 /// TLS 1.2 requires server-side storage to remember session IDs
 pub fn configure_session_resumption(server: &mut ServerConfig, config: &SessionResumptionConfig) {
     if !config.enabled {
@@ -31,6 +33,22 @@ pub fn configure_session_resumption(server: &mut ServerConfig, config: &SessionR
     server.session_storage = cache;
 }
 
+/// Installs the shared TLS 1.3 ticketer when `ticket_keys` is configured
+///
+/// With shared keys, a ticket issued by one replica resumes on any other replica holding the
+/// same secret, so clients spread by an L4 balancer keep skipping the full handshake. Does
+/// nothing when session resumption is disabled or `ticket_keys` is unset.
+pub fn configure_ticket_keys(
+    server: &mut ServerConfig,
+    config: &SessionResumptionConfig,
+) -> Result<()> {
+    let Some(keys) = config.ticket_keys.as_ref().filter(|_| config.enabled) else {
+        return Ok(());
+    };
+    server.ticketer = Arc::new(SharedTicketer::from_config(keys)?);
+    Ok(())
+}
+
 // Implementations to disable session resumption
 
 /// No-op session storage that disables TLS 1.2 session ID resumption
@@ -0,0 +1,127 @@
+//! TLS 1.3 session tickets sealed with keys shared across replicas
+//! (`[tls.session_resumption.ticket_keys]`).
+//!
+//! The key of each rotation period is `HMAC-SHA256(secret, label || period)`, used with
+//! AES-256-GCM. A ticket is `period (8) || nonce (12) || ciphertext || tag`; the period is also
+//! the AEAD associated data, so it cannot be altered to select another key.
+
+use std::fmt;
+
+use aws_lc_rs::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use aws_lc_rs::{hmac, rand};
+use tokio_rustls::rustls::server::ProducesTickets;
+
+use crate::config::TicketKeysConfig;
+use crate::error::Result;
+use crate::utils::time::unix_now;
+
+/// Domain separation for the derived keys.
+const KEY_LABEL: &[u8] = b"huginn-proxy tls13 ticket key";
+
+/// Length of the big-endian period number that prefixes every ticket.
+const PERIOD_LEN: usize = 8;
+
+/// Issues and accepts TLS 1.3 session tickets under keys derived from a shared secret.
+pub struct SharedTicketer {
+    secret: hmac::Key,
+    rotation_secs: u64,
+}
+
+impl fmt::Debug for SharedTicketer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedTicketer")
+            .field("rotation_secs", &self.rotation_secs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SharedTicketer {
+    pub fn new(secret: &[u8], rotation_secs: u64) -> Self {
+        Self { secret: hmac::Key::new(hmac::HMAC_SHA256, secret), rotation_secs }
+    }
+
+    /// Build from config, reading `secret_file` when set.
+    pub fn from_config(config: &TicketKeysConfig) -> Result<Self> {
+        Ok(Self::new(&config.load_secret()?, config.rotation_secs))
+    }
+
+    /// Seal `plain` under the key of the period containing `unix_secs`.
+    pub fn encrypt_at(&self, plain: &[u8], unix_secs: u64) -> Option<Vec<u8>> {
+        let period = self.period_at(unix_secs).to_be_bytes();
+        let key = self.key(&period)?;
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::fill(&mut nonce).ok()?;
+        let mut sealed = plain.to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(period),
+            &mut sealed,
+        )
+        .ok()?;
+
+        let mut ticket = Vec::with_capacity(
+            PERIOD_LEN
+                .saturating_add(NONCE_LEN)
+                .saturating_add(sealed.len()),
+        );
+        ticket.extend_from_slice(&period);
+        ticket.extend_from_slice(&nonce);
+        ticket.extend_from_slice(&sealed);
+        Some(ticket)
+    }
+
+    /// Open a ticket issued in the period containing `unix_secs`, the one before it (still
+    /// within its lifetime) or the one after it (a replica whose clock runs slightly ahead).
+    pub fn decrypt_at(&self, ticket: &[u8], unix_secs: u64) -> Option<Vec<u8>> {
+        let (period_bytes, rest) = ticket.split_first_chunk::<PERIOD_LEN>()?;
+        let (nonce, sealed) = rest.split_first_chunk::<NONCE_LEN>()?;
+        let period = u64::from_be_bytes(*period_bytes);
+        let current = self.period_at(unix_secs);
+        if period < current.saturating_sub(1) || period > current.saturating_add(1) {
+            return None;
+        }
+        let key = self.key(period_bytes)?;
+        let mut plain = sealed.to_vec();
+        let len = key
+            .open_in_place(
+                Nonce::assume_unique_for_key(*nonce),
+                Aad::from(*period_bytes),
+                &mut plain,
+            )
+            .ok()?
+            .len();
+        plain.truncate(len);
+        Some(plain)
+    }
+
+    fn period_at(&self, unix_secs: u64) -> u64 {
+        unix_secs.checked_div(self.rotation_secs).unwrap_or(0)
+    }
+
+    fn key(&self, period: &[u8; PERIOD_LEN]) -> Option<LessSafeKey> {
+        let mut input = KEY_LABEL.to_vec();
+        input.extend_from_slice(period);
+        let tag = hmac::sign(&self.secret, &input);
+        UnboundKey::new(&AES_256_GCM, tag.as_ref())
+            .ok()
+            .map(LessSafeKey::new)
+    }
+}
+
+impl ProducesTickets for SharedTicketer {
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    fn lifetime(&self) -> u32 {
+        u32::try_from(self.rotation_secs).unwrap_or(u32::MAX)
+    }
+
+    fn encrypt(&self, plain: &[u8]) -> Option<Vec<u8>> {
+        self.encrypt_at(plain, unix_now())
+    }
+
+    fn decrypt(&self, ticket: &[u8]) -> Option<Vec<u8>> {
+        self.decrypt_at(ticket, unix_now())
+    }
+}
@@ -0,0 +1,68 @@
+//! Client-side TLS for re-encrypting traffic to `https://` backends.
+//!
+//! Builds one rustls [`ClientConfig`] per backend from its [`BackendTlsConfig`]: the trust anchors
+//! (a PEM CA bundle or the system store) and an optional client certificate for mutual TLS. The
+//! SNI override is applied by the connector (see [`crate::proxy::ClientPool`]), not here.
+
+use std::sync::Arc;
+
+use hyper_rustls::ConfigBuilderExt;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::crypto::aws_lc_rs as aws_lc_provider;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+
+use crate::config::BackendTlsConfig;
+use crate::error::{ProxyError, Result};
+
+fn read_pem_certs(path: &str, what: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| ProxyError::Tls(format!("Failed to read {what} [{path}]: {e}")))?;
+    let certs = CertificateDer::pem_slice_iter(&bytes)
+        .collect::<std::result::Result<Vec<_>, rustls_pki_types::pem::Error>>()
+        .map_err(|e| ProxyError::Tls(format!("Failed to parse {what} [{path}]: {e}")))?;
+    if certs.is_empty() {
+        return Err(ProxyError::Tls(format!("No certificates found in {what} [{path}]")));
+    }
+    Ok(certs)
+}
+
+fn read_pem_key(path: &str) -> Result<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_file(path)
+        .map_err(|e| ProxyError::Tls(format!("Failed to load upstream client key [{path}]: {e}")))
+}
+
+/// Build the rustls client config used to connect to one TLS backend.
+pub fn build_upstream_client_config(tls: &BackendTlsConfig) -> Result<Arc<ClientConfig>> {
+    let builder =
+        ClientConfig::builder_with_provider(Arc::new(aws_lc_provider::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| ProxyError::Tls(format!("Failed to set TLS protocol versions: {e}")))?;
+
+    let builder = match &tls.ca_cert_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_pem_certs(path, "upstream CA bundle")? {
+                roots
+                    .add(cert)
+                    .map_err(|e| ProxyError::Tls(format!("Failed to add upstream CA: {e}")))?;
+            }
+            builder.with_root_certificates(roots)
+        }
+        None => builder
+            .with_native_roots()
+            .map_err(|e| ProxyError::Tls(format!("Failed to load system trust store: {e}")))?,
+    };
+
+    let config = match (&tls.client_cert_path, &tls.client_key_path) {
+        (Some(cert_path), Some(key_path)) => builder
+            .with_client_auth_cert(
+                read_pem_certs(cert_path, "upstream client certificate")?,
+                read_pem_key(key_path)?,
+            )
+            .map_err(|e| ProxyError::Tls(format!("Invalid upstream client certificate: {e}")))?,
+        _ => builder.with_no_client_auth(),
+    };
+
+    Ok(Arc::new(config))
+}
@@ -1,10 +1,16 @@
+use std::borrow::Cow;
+
 use http_body_util::{combinators::BoxBody, BodyExt, Full};
 use hyper::body::Bytes;
 use hyper::header::{HeaderValue, CONTENT_TYPE};
 use hyper::{Response, StatusCode};
 use serde::Serialize;
 
-pub(crate) type RespBody = BoxBody<Bytes, hyper::Error>;
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Response body type served to clients. The error is boxed so proxied bodies can fail with
+/// errors of their own (e.g. a crossed `max_response_body_bytes`), not only `hyper::Error`.
+pub(crate) type RespBody = BoxBody<Bytes, BoxError>;
 
 pub(crate) fn full_body(bytes: impl Into<Bytes>) -> RespBody {
     Full::new(bytes.into())
@@ -33,3 +39,44 @@ struct ErrorBody<'a> {
 pub(crate) fn json_error(status: StatusCode, error: &str) -> Response<RespBody> {
     json_response(status, ErrorBody { error })
 }
+
+/// Decode `%XX` escapes (and `+` as a space for query strings and form bodies). Malformed
+/// escapes are kept as they are.
+pub(crate) fn percent_decode(input: &[u8], plus_as_space: bool) -> Cow<'_, [u8]> {
+    if !(input.contains(&b'%') || plus_as_space && input.contains(&b'+')) {
+        return Cow::Borrowed(input);
+    }
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0usize;
+    while let Some(&b) = input.get(i) {
+        let escaped = (b == b'%')
+            .then(|| {
+                let hi = hex_value(*input.get(i.saturating_add(1))?)?;
+                let lo = hex_value(*input.get(i.saturating_add(2))?)?;
+                Some(hi.wrapping_shl(4) | lo)
+            })
+            .flatten();
+        match escaped {
+            Some(decoded) => {
+                out.push(decoded);
+                i = i.saturating_add(3);
+            }
+            None => {
+                out.push(if plus_as_space && b == b'+' { b' ' } else { b });
+                i = i.saturating_add(1);
+            }
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// [`percent_decode`] of a path segment that must decode to UTF-8; `None` when it does not.
+pub(crate) fn percent_decode_utf8(input: &str) -> Option<String> {
+    String::from_utf8(percent_decode(input.as_bytes(), false).into_owned()).ok()
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    char::from(b)
+        .to_digit(16)
+        .and_then(|d| u8::try_from(d).ok())
+}
@@ -1,4 +1,7 @@
+pub(crate) mod hex;
 pub(crate) mod http;
+pub(crate) mod redis;
+pub(crate) mod time;
 
 use tokio::time::{Duration, Instant};
 
@@ -0,0 +1,112 @@
+//! Redis connections of the stores that fall back to process memory while Redis is down:
+//! `store = "redis"` rate limits and `[client_tracking.redis]`.
+//!
+//! After a failure or a call slower than `timeout_ms`, Redis is skipped for [`RETRY_AFTER`] and
+//! the caller uses its in-memory state; the outage is logged once, when it starts and ends.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::{ErrorKind, RedisError, RedisResult};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use crate::config::RedisStoreConfig;
+use crate::utils::time::unix_millis;
+
+/// How long Redis is skipped after a failure.
+pub(crate) const RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Connections to one Redis server with the back-off shared by its callers.
+pub(crate) struct RedisConnections {
+    client: redis::Client,
+    manager_config: ConnectionManagerConfig,
+    /// Multiplexed connections, opened on first use and picked round-robin.
+    connections: Vec<OnceCell<ConnectionManager>>,
+    next: AtomicUsize,
+    timeout: Duration,
+    key_prefix: String,
+    /// Unix time (ms) before which Redis is skipped after a failure.
+    retry_at: AtomicU64,
+    degraded: AtomicBool,
+    /// Names the store in the outage logs, e.g. `Rate limit`.
+    name: &'static str,
+}
+
+impl RedisConnections {
+    /// Parse the URL; no connection is opened until the first call.
+    pub(crate) fn new(config: &RedisStoreConfig, name: &'static str) -> RedisResult<Self> {
+        let timeout = Duration::from_millis(config.timeout_ms);
+        Ok(Self {
+            client: redis::Client::open(config.url.expose().as_str())?,
+            manager_config: ConnectionManagerConfig::new()
+                .set_connection_timeout(timeout)
+                .set_response_timeout(timeout),
+            connections: (0..config.pool_size).map(|_| OnceCell::new()).collect(),
+            next: AtomicUsize::new(0),
+            timeout,
+            key_prefix: config.key_prefix.clone(),
+            retry_at: AtomicU64::new(0),
+            degraded: AtomicBool::new(false),
+            name,
+        })
+    }
+
+    /// Prefix of every key the caller writes.
+    pub(crate) fn key_prefix(&self) -> &str {
+        &self.key_prefix
+    }
+
+    /// A connection of the pool, opened on its first use.
+    pub(crate) async fn connection(&self) -> RedisResult<ConnectionManager> {
+        let slot = self
+            .next
+            .fetch_add(1, Ordering::Relaxed)
+            .checked_rem(self.connections.len())
+            .unwrap_or_default();
+        let Some(cell) = self.connections.get(slot) else {
+            return Err(RedisError::from((ErrorKind::ClientError, "empty connection pool")));
+        };
+        let connection = cell
+            .get_or_try_init(|| {
+                ConnectionManager::new_with_config(self.client.clone(), self.manager_config.clone())
+            })
+            .await?;
+        Ok(connection.clone())
+    }
+
+    /// Run `call` within `timeout_ms`. `None`, for the caller to use its in-memory state, when
+    /// Redis is being skipped after a failure or `call` fails or times out.
+    pub(crate) async fn run<T>(&self, call: impl Future<Output = RedisResult<T>>) -> Option<T> {
+        if unix_millis() < self.retry_at.load(Ordering::Relaxed) {
+            return None;
+        }
+        match tokio::time::timeout(self.timeout, call).await {
+            Ok(Ok(value)) => {
+                if self.degraded.swap(false, Ordering::Relaxed) {
+                    info!("{} store reachable again", self.name);
+                }
+                Some(value)
+            }
+            Ok(Err(e)) => {
+                self.record_failure(&e);
+                None
+            }
+            Err(_) => {
+                self.record_failure(&"timed out");
+                None
+            }
+        }
+    }
+
+    fn record_failure(&self, error: &dyn std::fmt::Display) {
+        let retry_ms = u64::try_from(RETRY_AFTER.as_millis()).unwrap_or(u64::MAX);
+        self.retry_at
+            .store(unix_millis().saturating_add(retry_ms), Ordering::Relaxed);
+        if !self.degraded.swap(true, Ordering::Relaxed) {
+            warn!(error = %error, "{} store unreachable, using in-memory state", self.name);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Wall-clock time as Unix timestamps, for values that are signed, stored or sent to other hosts.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Unix time in seconds; `0` if the clock is set before 1970.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Unix time in milliseconds; `0` if the clock is set before 1970.
+pub(crate) fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use huginn_proxy_lib::backend::{Acquire, CircuitBreaker, CircuitBreakerRegistry, CircuitState};
+use huginn_proxy_lib::config::CircuitBreakerConfig;
+
+fn breaker(
+    failure_threshold: u32,
+    open_duration_secs: u64,
+    half_open_requests: u32,
+) -> CircuitBreaker {
+    CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold,
+        open_duration_secs,
+        half_open_requests,
+    })
+}
+
+fn after(t: Instant, secs: u64) -> Instant {
+    t.checked_add(Duration::from_secs(secs)).unwrap_or(t)
+}
+
+#[test]
+fn opens_after_consecutive_failures() {
+    let cb = breaker(3, 30, 1);
+    let t0 = Instant::now();
+    assert_eq!(cb.record_at(false, t0), None);
+    assert_eq!(cb.record_at(false, t0), None);
+    assert_eq!(cb.record_at(false, t0), Some(CircuitState::Open));
+    assert_eq!(cb.state(), CircuitState::Open);
+    assert!(!cb.is_available_at(t0));
+    assert_eq!(cb.try_acquire_at(t0), Acquire::Denied);
+}
+
+#[test]
+fn success_resets_failure_streak() {
+    let cb = breaker(2, 30, 1);
+    let t0 = Instant::now();
+    assert_eq!(cb.record_at(false, t0), None);
+    assert_eq!(cb.record_at(true, t0), None);
+    assert_eq!(cb.record_at(false, t0), None);
+    assert_eq!(cb.state(), CircuitState::Closed);
+}
+
+#[test]
+fn half_open_success_closes_circuit() {
+    let cb = breaker(1, 10, 1);
+    let t0 = Instant::now();
+    assert_eq!(cb.record_at(false, t0), Some(CircuitState::Open));
+
+    let later = after(t0, 10);
+    assert!(cb.is_available_at(later));
+    assert_eq!(cb.try_acquire_at(later), Acquire::GrantedHalfOpen);
+    assert_eq!(cb.state(), CircuitState::HalfOpen);
+    // Only one trial request allowed.
+    assert_eq!(cb.try_acquire_at(later), Acquire::Denied);
+
+    assert_eq!(cb.record_at(true, later), Some(CircuitState::Closed));
+    assert_eq!(cb.try_acquire_at(later), Acquire::Granted);
+}
+
+#[test]
+fn half_open_failure_reopens_circuit() {
+    let cb = breaker(1, 10, 2);
+    let t0 = Instant::now();
+    cb.record_at(false, t0);
+
+    let later = after(t0, 10);
+    assert_eq!(cb.try_acquire_at(later), Acquire::GrantedHalfOpen);
+    assert_eq!(cb.try_acquire_at(later), Acquire::Granted);
+    assert_eq!(cb.record_at(true, later), None);
+    assert_eq!(cb.record_at(false, later), Some(CircuitState::Open));
+    assert!(!cb.is_available_at(after(later, 9)));
+}
+
+#[test]
+fn unreported_trial_slot_is_recycled() {
+    let cb = breaker(1, 10, 1);
+    let t0 = Instant::now();
+    cb.record_at(false, t0);
+
+    let half_open_at = after(t0, 10);
+    assert_eq!(cb.try_acquire_at(half_open_at), Acquire::GrantedHalfOpen);
+    assert_eq!(cb.try_acquire_at(half_open_at), Acquire::Denied);
+    assert_eq!(cb.try_acquire_at(after(half_open_at, 10)), Acquire::Granted);
+}
+
+#[test]
+fn registry_reuses_breaker_until_config_changes() {
+    let registry = CircuitBreakerRegistry::new();
+    let config = CircuitBreakerConfig::default();
+    let a = registry.get("backend:9000", &config);
+    let b = registry.get("backend:9000", &config);
+    assert!(Arc::ptr_eq(&a, &b));
+
+    let changed = CircuitBreakerConfig { failure_threshold: 1, ..config };
+    let c = registry.get("backend:9000", &changed);
+    assert!(!Arc::ptr_eq(&a, &c));
+    assert_eq!(c.config(), &changed);
+}
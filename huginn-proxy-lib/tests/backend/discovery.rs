@@ -0,0 +1,215 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty, Full};
+use huginn_proxy_lib::config::{Backend, BackendPoolConfig, DiscoveryConfig, KeepAliveConfig};
+use huginn_proxy_lib::proxy::pool_connector::TrackedConnector;
+use huginn_proxy_lib::proxy::ClientPool;
+use huginn_proxy_lib::{BackendDiscovery, HealthRegistry, Metrics, SharedClientPool};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::Response;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::net::TcpListener;
+use tokio::runtime::Handle;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+fn discovered_backend(address: &str) -> Backend {
+    Backend {
+        address: address.to_string(),
+        http_version: None,
+        health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: Some(DiscoveryConfig::Dns { refresh_secs: 1 }),
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
+    }
+}
+
+/// Plain HTTP/1.1 server on 127.0.0.1 only, answering `200 ok`.
+async fn spawn_backend() -> Result<u16, Box<dyn std::error::Error + Send + Sync>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let service = service_fn(|_req| async {
+                    Ok::<_, hyper::Error>(Response::new(Full::new(Bytes::from_static(b"ok"))))
+                });
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    Ok(port)
+}
+
+/// Fake Consul agent answering every request with the current `catalog` JSON. The last request
+/// path and query are recorded in `seen`.
+async fn spawn_consul(
+    catalog: Arc<Mutex<String>>,
+    seen: Arc<Mutex<String>>,
+) -> Result<u16, Box<dyn std::error::Error + Send + Sync>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let catalog = Arc::clone(&catalog);
+            let seen = Arc::clone(&seen);
+            tokio::spawn(async move {
+                let service = service_fn(move |req: hyper::Request<hyper::body::Incoming>| {
+                    let body = catalog.lock().map(|c| c.clone()).unwrap_or_default();
+                    if let (Ok(mut seen), Some(pq)) = (seen.lock(), req.uri().path_and_query()) {
+                        *seen = pq.to_string();
+                    }
+                    async move { Ok::<_, hyper::Error>(Response::new(Full::new(Bytes::from(body)))) }
+                });
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    Ok(port)
+}
+
+async fn wait_until(what: &str, done: impl Fn() -> bool) -> TestResult {
+    for _ in 0..50 {
+        if done() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Err(format!("timed out waiting for {what}").into())
+}
+
+async fn wait_for_addrs(discovery: &BackendDiscovery, backend: &str) -> TestResult {
+    wait_until(&format!("{backend} to resolve"), || {
+        !discovery.addrs().addrs(backend).is_empty()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn resolves_backends_and_connects_through_discovered_addresses() -> TestResult {
+    let port = spawn_backend().await?;
+    let address = format!("localhost:{port}");
+    let backends = [discovered_backend(&address)];
+    let discovery = BackendDiscovery::new();
+    let keep_alive = KeepAliveConfig { enabled: true, upstream_idle_timeout: 90 };
+    let client_pool: SharedClientPool = Arc::new(ArcSwap::from_pointee(
+        ClientPool::new(&keep_alive, BackendPoolConfig::default(), Some(1000))
+            .with_discovery(Arc::clone(discovery.addrs()))
+            .with_backends(&backends)?,
+    ));
+    assert!(client_pool.load().has_dedicated_pool(&address));
+
+    discovery.reconcile(&backends, &client_pool, &Metrics::new_noop(), &Handle::current());
+    wait_for_addrs(&discovery, &address).await?;
+    let addrs = discovery.addrs().addrs(&address);
+    assert!(
+        addrs
+            .iter()
+            .any(|a| a.ip().is_loopback() && a.port() == port),
+        "got: {addrs:?}"
+    );
+
+    // `localhost` may also resolve to `::1`, where nothing listens: the connector falls through.
+    // No idle connections are kept, so every request dials the next discovered address.
+    let connector =
+        TrackedConnector::new(HttpConnector::new()).with_discovery(Arc::clone(discovery.addrs()));
+    let client: Client<_, Empty<Bytes>> = Client::builder(TokioExecutor::new())
+        .pool_max_idle_per_host(0)
+        .build(connector);
+    for _ in 0..4 {
+        let response = client.get(format!("http://{address}/").parse()?).await?;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.into_body().collect().await?.to_bytes(), "ok");
+    }
+
+    discovery.reconcile(&[], &client_pool, &Metrics::new_noop(), &Handle::current());
+    assert!(discovery.addrs().addrs(&address).is_empty());
+    discovery.shutdown();
+    Ok(())
+}
+
+#[tokio::test]
+async fn consul_service_members_drive_addresses_and_health() -> TestResult {
+    let port = spawn_backend().await?;
+    // The instance registered without its own address: the node address applies.
+    let members = format!(
+        r#"[{{"Node":{{"Address":"127.0.0.1"}},"Service":{{"Address":"","Port":{port}}}}}]"#
+    );
+    let catalog = Arc::new(Mutex::new(members));
+    let seen = Arc::new(Mutex::new(String::new()));
+    let consul_port = spawn_consul(Arc::clone(&catalog), Arc::clone(&seen)).await?;
+
+    let backends = [Backend {
+        discovery: Some(DiscoveryConfig::Consul {
+            url: format!("http://127.0.0.1:{consul_port}"),
+            service: "billing".to_string(),
+            tag: Some("v2".to_string()),
+            datacenter: None,
+            token: None,
+            refresh_secs: 1,
+        }),
+        ..discovered_backend("billing")
+    }];
+    backends[0].validate()?;
+    let registry = Arc::new(HealthRegistry::new());
+    let discovery = BackendDiscovery::new().with_health(Arc::clone(&registry));
+    let keep_alive = KeepAliveConfig { enabled: true, upstream_idle_timeout: 90 };
+    let client_pool: SharedClientPool = Arc::new(ArcSwap::from_pointee(
+        ClientPool::new(&keep_alive, BackendPoolConfig::default(), Some(1000))
+            .with_discovery(Arc::clone(discovery.addrs()))
+            .with_backends(&backends)?,
+    ));
+
+    discovery.reconcile(&backends, &client_pool, &Metrics::new_noop(), &Handle::current());
+    wait_for_addrs(&discovery, "billing").await?;
+    let member: SocketAddr = format!("127.0.0.1:{port}").parse()?;
+    assert_eq!(discovery.addrs().addrs("billing"), [member]);
+    assert_eq!(registry.probe_status("billing"), Some(true));
+    assert_eq!(
+        seen.lock().map(|s| s.clone()).unwrap_or_default(),
+        "/v1/health/service/billing?passing=true&tag=v2"
+    );
+
+    // The logical name is never resolved: requests reach the discovered member.
+    let connector =
+        TrackedConnector::new(HttpConnector::new()).with_discovery(Arc::clone(discovery.addrs()));
+    let client: Client<_, Empty<Bytes>> = Client::builder(TokioExecutor::new()).build(connector);
+    let response = client.get("http://billing/".parse()?).await?;
+    assert_eq!(response.status(), 200);
+
+    // No passing instance: the backend turns unhealthy and keeps its last members.
+    if let Ok(mut catalog) = catalog.lock() {
+        *catalog = "[]".to_string();
+    }
+    wait_until("billing to turn unhealthy", || registry.probe_status("billing") == Some(false))
+        .await?;
+    assert_eq!(discovery.addrs().addrs("billing").len(), 1);
+
+    discovery.reconcile(&[], &client_pool, &Metrics::new_noop(), &Handle::current());
+    assert!(discovery.addrs().connect_order("billing").is_none());
+    assert_eq!(registry.probe_status("billing"), None);
+    discovery.shutdown();
+    Ok(())
+}
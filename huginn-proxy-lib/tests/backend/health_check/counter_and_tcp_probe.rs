@@ -102,6 +102,18 @@ async fn tcp_returns_true_for_listening_port() -> Result<(), TestError> {
     Ok(())
 }
 
+#[tokio::test]
+async fn tcp_probe_connects_to_unix_socket() -> Result<(), TestError> {
+    let path = crate::helpers::tmp_path("probe.sock");
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    let addr = format!("unix://{}", path.display());
+    assert!(check_tcp(&addr, Duration::from_secs(2)).await);
+    drop(listener);
+    std::fs::remove_file(&path)?;
+    assert!(!check_tcp(&addr, Duration::from_millis(500)).await);
+    Ok(())
+}
+
 #[tokio::test]
 async fn tcp_returns_false_for_unreachable_port() {
     assert!(!check_tcp("127.0.0.1:1", Duration::from_millis(500)).await);
@@ -1,5 +1,7 @@
 use std::sync::Arc;
+use std::time::Duration;
 
+use huginn_proxy_lib::config::Backend;
 use huginn_proxy_lib::HealthRegistry;
 
 #[test]
@@ -85,3 +87,98 @@ fn clone_shares_state() {
     assert!(!r2.is_healthy("shared:1"));
     assert_eq!(r2.len(), 1);
 }
+
+#[test]
+fn snapshot_is_sorted_and_reflects_state() {
+    let r = HealthRegistry::new();
+    r.get_or_create("b:9000").set(false);
+    r.get_or_create("a:9000");
+    assert_eq!(r.snapshot(), vec![("a:9000".to_string(), true), ("b:9000".to_string(), false)]);
+}
+
+#[test]
+fn drain_gates_backend_until_undrained() {
+    let r = HealthRegistry::new();
+    assert!(r.drain("a:9000"));
+    assert!(!r.drain("a:9000"));
+    assert!(!r.is_healthy("a:9000"));
+    assert_eq!(r.probe_status("a:9000"), None);
+
+    r.get_or_create("a:9000");
+    assert_eq!(r.probe_status("a:9000"), Some(true));
+    assert!(!r.is_healthy("a:9000"));
+
+    assert!(r.undrain("a:9000"));
+    assert!(!r.undrain("a:9000"));
+    assert!(r.is_healthy("a:9000"));
+}
+
+#[tokio::test]
+async fn config_drain_cuts_requests_in_flight_after_its_timeout(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let r = HealthRegistry::new();
+    let mut backend: Backend = toml::from_str(
+        r#"
+        address = "a:9000"
+        drain = true
+        drain_timeout_secs = 1
+        "#,
+    )?;
+    let in_flight = r.drain_cutoff("a:9000");
+    r.configure_drains(std::slice::from_ref(&backend));
+    assert!(r.is_drained("a:9000"));
+    assert!(r.is_drained_by_config("a:9000"));
+    assert!(!r.is_healthy("a:9000"));
+    // Only the admin API's own drain can be lifted through it.
+    assert!(!r.undrain("a:9000"));
+    assert!(r.is_drained("a:9000"));
+
+    tokio::time::timeout(Duration::from_secs(3), in_flight.cancelled()).await?;
+    assert!(!r.drain_cutoff("a:9000").is_cancelled(), "later requests get a fresh cutoff");
+
+    backend.drain = false;
+    r.configure_drains(std::slice::from_ref(&backend));
+    assert!(!r.is_drained("a:9000"));
+
+    // Undrained before the timeout: requests in flight are left alone.
+    let in_flight = r.drain_cutoff("a:9000");
+    assert!(r.drain("a:9000"));
+    assert!(r.undrain("a:9000"));
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert!(!in_flight.is_cancelled());
+    Ok(())
+}
+
+#[test]
+fn slow_start_ramps_recovered_and_added_backends(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let r = HealthRegistry::new();
+    let warming: Backend = toml::from_str(
+        r#"
+        address = "a:9000"
+        slow_start_secs = 60
+        "#,
+    )?;
+    let plain = Backend::new("b:9000");
+    // Backends of the first configuration start at full weight.
+    r.configure_slow_start(&[warming.clone(), plain.clone()]);
+    assert_eq!(r.slow_start_weight("a:9000"), 1.0);
+
+    r.begin_slow_start("a:9000");
+    let weight = r.slow_start_weight("a:9000");
+    assert!((0.1..0.2).contains(&weight), "{weight}");
+    // Without slow_start_secs, recovery gives the full share right away.
+    r.begin_slow_start("b:9000");
+    assert_eq!(r.slow_start_weight("b:9000"), 1.0);
+
+    // A backend added by a reload warms up; one that stays keeps its state.
+    let added = Backend { address: "c:9000".to_string(), ..warming.clone() };
+    r.configure_slow_start(&[warming, plain, added]);
+    assert!(r.slow_start_weight("c:9000") < 0.2);
+    assert!(r.slow_start_weight("a:9000") < 0.2);
+
+    // Dropping slow_start_secs ends the warm-up.
+    r.configure_slow_start(&[Backend::new("a:9000")]);
+    assert_eq!(r.slow_start_weight("a:9000"), 1.0);
+    Ok(())
+}
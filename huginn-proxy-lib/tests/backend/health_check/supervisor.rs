@@ -16,6 +16,20 @@ fn tcp_backend(addr: &str, interval: u64, threshold: u32) -> Backend {
             unhealthy_threshold: threshold,
             healthy_threshold: 1,
         }),
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     }
 }
 
@@ -0,0 +1,67 @@
+use huginn_proxy_lib::backend::{backend_token, Affinity};
+use huginn_proxy_lib::{BackendSelector, HealthRegistry};
+
+const CANDIDATES: [&str; 3] = ["backend-a:9000", "backend-b:9000", "backend-c:9000"];
+
+#[test]
+fn token_keeps_the_client_on_its_backend() {
+    let selector = BackendSelector::new();
+    let token = backend_token("backend-c:9000");
+    for _ in 0..5 {
+        let selected =
+            selector.select_affine("/app", &CANDIDATES, Some(Affinity::Token(&token)), |_| true);
+        assert_eq!(selected.as_deref(), Some("backend-c:9000"));
+    }
+}
+
+#[test]
+fn token_of_an_unhealthy_backend_fails_over() {
+    let selector = BackendSelector::new();
+    let registry = HealthRegistry::new();
+    registry.get_or_create("backend-c:9000").set(false);
+    let token = backend_token("backend-c:9000");
+
+    let selected = selector
+        .select_affine("/app", &CANDIDATES, Some(Affinity::Token(&token)), |addr| {
+            registry.is_healthy(addr)
+        })
+        .unwrap_or_else(|| panic!("expected a failover candidate"));
+    assert_ne!(selected, "backend-c:9000");
+
+    let unknown = selector.select_affine("/app", &CANDIDATES, Some(Affinity::Token("x")), |_| true);
+    assert!(unknown.is_some());
+}
+
+#[test]
+fn hash_key_is_stable_and_only_moves_off_a_removed_backend() {
+    let selector = BackendSelector::new();
+    let pick = |key: &str, eligible: &dyn Fn(&str) -> bool| {
+        selector
+            .select_affine("/app", &CANDIDATES, Some(Affinity::Key(key)), eligible)
+            .unwrap_or_else(|| panic!("expected a candidate"))
+    };
+
+    let keys: Vec<String> = (0..32).map(|i| format!("203.0.113.{i}")).collect();
+    let before: Vec<String> = keys.iter().map(|k| pick(k, &|_| true)).collect();
+    let again: Vec<String> = keys.iter().map(|k| pick(k, &|_| true)).collect();
+    assert_eq!(before, again);
+    assert!(CANDIDATES.iter().all(|c| before.iter().any(|b| b == c)));
+
+    let without_b = |addr: &str| addr != "backend-b:9000";
+    for (key, previous) in keys.iter().zip(&before) {
+        let now = pick(key, &without_b);
+        if previous != "backend-b:9000" {
+            assert_eq!(&now, previous, "{key} moved although its backend stayed");
+        }
+        assert_ne!(now, "backend-b:9000");
+    }
+}
+
+#[test]
+fn backend_token_is_opaque_and_deterministic() {
+    let token = backend_token("backend-a:9000");
+    assert_eq!(token, backend_token("backend-a:9000"));
+    assert_ne!(token, backend_token("backend-b:9000"));
+    assert_eq!(token.len(), 16);
+    assert!(!token.contains("backend"));
+}
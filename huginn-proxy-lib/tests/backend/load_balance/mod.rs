@@ -1,2 +1,3 @@
+mod affinity;
 mod round_robin;
 mod selector;
@@ -1,3 +1,4 @@
+use huginn_proxy_lib::backend::Affinity;
 use huginn_proxy_lib::{BackendSelector, HealthRegistry};
 
 #[test]
@@ -48,3 +49,28 @@ fn select_returns_none_when_all_candidates_unhealthy() {
 
     assert!(selector.select("/api", &candidates, &registry).is_none());
 }
+
+#[test]
+fn weighted_candidates_get_their_fraction() {
+    let selector = BackendSelector::new();
+    let candidates = ["backend-a:9000", "backend-b:9000"];
+    let warming = |addr: &str| if addr == "backend-a:9000" { 0.0 } else { 1.0 };
+
+    for _ in 0..10 {
+        let selected = selector.select_weighted("/api", &candidates, None, |_| true, warming);
+        assert_eq!(selected.as_deref(), Some("backend-b:9000"));
+    }
+    // A weighted candidate left alone still gets the request.
+    let alone = selector.select_weighted("/api", &candidates[..1], None, |_| true, warming);
+    assert_eq!(alone.as_deref(), Some("backend-a:9000"));
+
+    // Hashed keys leave a zero-weight candidate too, and come back at full weight.
+    let keys: Vec<String> = (0..32).map(|i| format!("203.0.113.{i}")).collect();
+    for key in &keys {
+        let affinity = Some(Affinity::Key(key));
+        let warm = selector.select_weighted("/api", &candidates, affinity, |_| true, warming);
+        assert_eq!(warm.as_deref(), Some("backend-b:9000"));
+        let full = selector.select_weighted("/api", &candidates, affinity, |_| true, |_| 1.0);
+        assert_eq!(full, selector.select_affine("/api", &candidates, affinity, |_| true));
+    }
+}
@@ -1,2 +1,4 @@
+pub mod circuit_breaker;
+pub mod discovery;
 pub mod health_check;
 pub mod load_balance;
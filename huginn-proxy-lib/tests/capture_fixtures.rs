@@ -6,7 +6,7 @@
 //! Run once whenever reqwest or rustls is updated to refresh the fixtures:
 //!
 //! ```bash
-//! cargo test -p huginn-proxy-lib --test capture_fixtures -- --nocapture
+//! cargo test -p huginn-proxy-lib --test capture_fixtures -- --ignored --nocapture
 //! ```
 
 //!
@@ -43,6 +43,7 @@ fn fixtures_dir() -> PathBuf {
 // bytes - those ARE the TLS ClientHello record (before any handshake).
 // reqwest will get a connection error but we already have the bytes.
 // ---------------------------------------------------------------------------
+#[ignore]
 #[tokio::test]
 async fn capture_tls_client_hello() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let listener = TcpListener::bind("127.0.0.1:0").await?;
@@ -193,6 +194,20 @@ async fn capture_fingerprint_values() -> Result<(), Box<dyn std::error::Error +
             address: backend_addr.to_string(),
             http_version: None,
             health_check: None,
+            circuit_breaker: None,
+            tls: None,
+            pool: None,
+            max_in_flight: None,
+            queue: None,
+            discovery: None,
+            drain: false,
+            drain_timeout_secs: None,
+            slow_start_secs: None,
+            echo: false,
+            tcp: None,
+            proxy: None,
+            bandwidth: None,
+            tenant: None,
         }],
         domains: vec![Domain {
             host: None,
@@ -201,43 +216,69 @@ async fn capture_fingerprint_values() -> Result<(), Box<dyn std::error::Error +
             headers: None,
             security: None,
             fingerprinting: None,
+            ja4_variants: None,
+            fingerprint_format: None,
+            redirect: None,
             routes: vec![Route {
                 prefix: "/".to_string(),
                 backend: backend_addr.to_string(),
                 fingerprinting: Some(true),
-                force_new_connection: false,
-                replace_path: None,
-                security: None,
-                headers: None,
+                ..Default::default()
             }],
+            tenant: None,
         }],
         tls: Some(TlsConfig {
             alpn: vec!["h2".to_string(), "http/1.1".to_string()],
             options: Default::default(),
             client_auth: Default::default(),
+            client_cert: Default::default(),
             session_resumption: Default::default(),
+            ocsp: Default::default(),
         }),
         fingerprint: FingerprintConfig {
             tls_enabled: true,
             http_enabled: true,
             tcp_enabled: false,
+            tcp_capture: Default::default(),
             max_capture: 64 * 1024,
+            tls_info_headers: false,
+            headers: Default::default(),
+            whoami: Default::default(),
+            signing: Default::default(),
         },
         logging: LoggingConfig { level: "warn".to_string(), show_target: false },
         timeout: TimeoutConfig {
             upstream_connect_ms: Some(5000),
             proxy_idle_ms: 30_000,
             shutdown_secs: 3,
+            drain_delay_secs: 0,
             tls_handshake_secs: 10,
             connection_handling_secs: 60,
             keep_alive: KeepAliveConfig::default(),
         },
         security: SecurityConfig::default(),
-        telemetry: TelemetryConfig { metrics_port: None, otel_log_level: "warn".to_string() },
+        telemetry: TelemetryConfig {
+            metrics_port: None,
+            otel_log_level: "warn".to_string(),
+            ..Default::default()
+        },
         reload: huginn_proxy_lib::config::ReloadConfig::default(),
         headers: None,
         preserve_host: false,
+        default_host: None,
         backend_pool: Default::default(),
+        compression: None,
+        redirect: None,
+        cache: Default::default(),
+        access_log: Default::default(),
+        load_shedding: Default::default(),
+        http2: Default::default(),
+        request_id: Default::default(),
+        event_stream: Default::default(),
+        client_tracking: Default::default(),
+        handshake_capture: Default::default(),
+        tenants: vec![],
+        include: vec![],
     };
 
     let huginn_proxy_lib::config::ConfigParts { static_cfg, dynamic_cfg } = config.into_parts();
@@ -251,9 +292,13 @@ async fn capture_fingerprint_values() -> Result<(), Box<dyn std::error::Error +
             dynamic_cfg,
             huginn_proxy_lib::Metrics::new_noop(),
             None,
+            None,
+            None,
             huginn_proxy_lib::WatchOptions::default(),
             shutdown_tx,
             huginn_proxy_lib::Readiness::new(),
+            Arc::new(huginn_proxy_lib::HealthRegistry::new()),
+            huginn_proxy_lib::RuntimeHandles::default(),
         )
         .await;
     });
@@ -40,6 +40,38 @@ add = [{ name = "X-Bar", value = "1" }]
     Ok(())
 }
 
+#[test]
+fn header_audit_warns_on_duplicate_set_and_set_remove_conflict(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = tmp_path("hdr-set-dup");
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "backend:9000" }]
+
+[headers.response]
+# Server set twice (last wins) and also removed (the set never applies).
+set = [
+  { name = "Server", value = "a" },
+  { name = "server", value = "b" },
+]
+remove = ["Server"]
+"#;
+    fs::write(&path, toml)?;
+    let cfg = load_from_path(&path)?;
+
+    let warnings = header_config_warnings(&cfg);
+    assert_eq!(warnings.len(), 2, "expected dup + conflict, got: {warnings:?}");
+    assert!(warnings
+        .iter()
+        .any(|w| w.message.contains("set more than once")));
+    assert!(warnings
+        .iter()
+        .any(|w| w.message.contains("both set and removed")));
+
+    let _ = fs::remove_file(&path);
+    Ok(())
+}
+
 #[test]
 fn header_audit_warns_on_duplicate_custom_security_header(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
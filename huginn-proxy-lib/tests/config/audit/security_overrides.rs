@@ -95,3 +95,35 @@ requests_per_second = 5
     let _ = fs::remove_file(&path);
     Ok(())
 }
+
+#[test]
+fn audit_warns_when_route_headers_override_drops_domain_preset(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = tmp_path("audit-route-preset");
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "backend:9000" }]
+
+[[domains]]
+host = "api.example.com"
+security = { headers = { preset = "strict" } }
+
+[[domains.routes]]
+prefix = "/embed"
+backend = "backend:9000"
+
+# Relaxing one header re-declares the whole block, so the strict preset is gone.
+[domains.routes.security.headers]
+custom = [{ name = "X-Frame-Options", value = "SAMEORIGIN" }]
+"#;
+    fs::write(&path, toml)?;
+    let cfg = load_from_path(&path)?;
+
+    let warnings = security_override_warnings(&cfg);
+    assert_eq!(warnings.len(), 1, "expected one finding, got: {warnings:?}");
+    assert!(warnings[0].scope.starts_with("route '/embed'"));
+    assert!(warnings[0].message.contains("[preset, HSTS, CSP]"));
+
+    let _ = fs::remove_file(&path);
+    Ok(())
+}
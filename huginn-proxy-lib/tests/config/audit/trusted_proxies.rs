@@ -91,3 +91,27 @@ cidrs = ["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "fc00::/7", "::1/128"]
     let _ = fs::remove_file(&path);
     Ok(())
 }
+
+#[test]
+fn forwarded_for_ip_filter_without_trusted_proxies_warns(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = tmp_path("tp-ipfilter-xff");
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "backend:9000" }]
+
+[security.ip_filter]
+mode = "denylist"
+denylist = ["203.0.113.0/24"]
+use_forwarded_for = true
+"#;
+    fs::write(&path, toml)?;
+    let cfg = load_from_path(&path)?;
+
+    let warnings = trusted_proxies_warnings(&cfg);
+    assert_eq!(warnings.len(), 1, "expected 1 finding, got: {warnings:?}");
+    assert!(warnings[0].message.contains("use_forwarded_for"));
+
+    let _ = fs::remove_file(&path);
+    Ok(())
+}
@@ -22,7 +22,11 @@ headers = { request = { add = [
 routes = [{
   prefix = "/api",
   backend = "backend:9000",
-  security = { rate_limit = { enabled = true } },
+  security = { rate_limit = {
+    enabled = true,
+    store = "redis",
+    redis = { url = "redis://:redis-secret@cache:6379" }
+  } },
   headers = { response = { add = [
     { name = "X-Route-Token", value = "route-secret" }
   ] } }
@@ -58,6 +62,7 @@ fn effective_config_redacts_sensitive_values() -> TestResult {
         "/run/secrets/private-key.pem",
         "/run/secrets/client-ca.pem",
         "https://secret.internal",
+        "redis-secret",
     ] {
         assert!(!output.contains(secret), "effective config leaked sensitive value: {secret}");
     }
@@ -1,4 +1,7 @@
-use huginn_proxy_lib::config::{load_from_path, HeaderManipulation, HeaderManipulationGroup};
+use huginn_proxy_lib::config::{
+    load_from_path, render_header_template, CustomHeader, HeaderManipulation,
+    HeaderManipulationGroup, TemplateVar,
+};
 use std::io::Write;
 
 #[test]
@@ -241,3 +244,77 @@ fn test_empty_header_manipulation_group_serialization() {
         panic!("Failed to serialize HeaderManipulationGroup");
     }
 }
+
+#[tokio::test]
+async fn test_header_manipulation_route_set_with_template(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut file = tempfile::Builder::new().suffix(".toml").tempfile()?;
+    writeln!(
+        file,
+        r#"
+listen = {{ addrs = ["127.0.0.1:0"] }}
+backends = [{{ address = "localhost:9000" }}]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/api"
+  backend = "localhost:9000"
+
+  [domains.routes.headers.request]
+  set = [{{ name = "X-Real-IP", value = "${{client_ip}}" }}]
+"#
+    )?;
+
+    let config = load_from_path(file.path())?;
+    let Some(headers) = config.domains[0].routes[0].headers.as_ref() else {
+        return Err("route headers should be present".into());
+    };
+    assert_eq!(headers.request.set.len(), 1);
+    assert_eq!(headers.request.set[0].value.expose(), "${client_ip}");
+    assert!(headers.request.add.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_render_header_template() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let rendered = render_header_template("${route_prefix}:$x:${ja4}", |var, out| {
+        out.push_str(match var {
+            TemplateVar::RoutePrefix => "/api",
+            TemplateVar::Ja4 => "t13d",
+            TemplateVar::ClientIp | TemplateVar::Host => "other",
+        });
+    })?;
+    assert_eq!(rendered, "/api:$x:t13d");
+
+    assert!(render_header_template("${client_ip", |_, _| {}).is_err());
+    assert!(render_header_template("${nope}", |_, _| {}).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_header_manipulation_validate() {
+    let with_add = |name: &str, value: &str| HeaderManipulation {
+        request: HeaderManipulationGroup {
+            add: vec![CustomHeader { name: name.to_string(), value: value.to_string().into() }],
+            ..Default::default()
+        },
+        response: HeaderManipulationGroup::default(),
+    };
+
+    assert!(with_add("X-Client", "${client_ip} via ${host}")
+        .validate()
+        .is_ok());
+    assert!(with_add("X-Client", "${clientip}").validate().is_err());
+    assert!(with_add("X-Client", "${client_ip").validate().is_err());
+    assert!(with_add("bad header", "v").validate().is_err());
+    assert!(with_add("X-Client", "line\nbreak").validate().is_err());
+
+    let bad_remove = HeaderManipulation {
+        request: HeaderManipulationGroup::default(),
+        response: HeaderManipulationGroup {
+            remove: vec!["bad:name".to_string()],
+            ..Default::default()
+        },
+    };
+    assert!(bad_remove.validate().is_err());
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::Path;
+
+use huginn_proxy_lib::config::load_from_path;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+fn main_config(dir: &Path, include: &str) -> String {
+    format!(
+        r#"
+listen = {{ addrs = ["127.0.0.1:0"] }}
+backends = [{{ address = "app:9000" }}, {{ address = "billing:9000" }}, {{ address = "search:9000" }}]
+include = ["{}/{include}"]
+
+[[domains]]
+host = "api.example.com"
+routes = [{{ prefix = "/", backend = "app:9000" }}]
+"#,
+        dir.display()
+    )
+}
+
+#[test]
+fn merges_route_fragments_in_any_format() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("routes.d"))?;
+    fs::write(
+        dir.path().join("routes.d/billing.toml"),
+        r#"
+[[domains]]
+host = "API.example.com"
+  [[domains.routes]]
+  prefix = "/billing"
+  backend = "billing:9000"
+"#,
+    )?;
+    fs::write(
+        dir.path().join("routes.d/search.yaml"),
+        "domains:\n  - host: api.example.com\n    routes:\n      - prefix: /search\n        backend: search:9000\n",
+    )?;
+    // Editor leftovers and non-matching files are ignored.
+    fs::write(dir.path().join("routes.d/.draft.toml"), "not = [valid")?;
+    fs::write(dir.path().join("routes.d/README.md"), "# routes")?;
+
+    let path = dir.path().join("config.toml");
+    fs::write(&path, main_config(dir.path(), "routes.d/*.*ml"))?;
+    let cfg = load_from_path(&path)?;
+    let prefixes: Vec<&str> = cfg.domains[0]
+        .routes
+        .iter()
+        .map(|route| route.prefix.as_str())
+        .collect();
+    assert_eq!(prefixes, ["/", "/billing", "/search"]);
+    Ok(())
+}
+
+#[test]
+fn json_main_config_with_empty_include_directory() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("routes.d"))?;
+    let path = dir.path().join("config.json");
+    fs::write(
+        &path,
+        format!(
+            r#"{{
+  "listen": {{ "addrs": ["127.0.0.1:0"] }},
+  "backends": [{{ "address": "app:9000" }}],
+  "include": ["{}/routes.d/*.json"],
+  "domains": [{{ "routes": [{{ "prefix": "/", "backend": "app:9000" }}] }}]
+}}"#,
+            dir.path().display()
+        ),
+    )?;
+    let cfg = load_from_path(&path)?;
+    assert_eq!(cfg.domains[0].routes.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn fragment_errors_name_the_fragment() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let fragment = dir.path().join("routes.toml");
+    let path = dir.path().join("config.toml");
+    fs::write(&path, main_config(dir.path(), "routes.toml"))?;
+
+    // Merged routes are validated with the rest of the config, so that error names the domain.
+    for (content, expected, names_fragment) in [
+        (
+            "[[domains]]\nhost = \"www.example.com\"\nroutes = []\n",
+            "domain 'www.example.com' is not declared in the main config",
+            true,
+        ),
+        (
+            "[[domains]]\nhost = \"api.example.com\"\nroutes = [{ prefix = \"/x\", backend = \"nope:9000\" }]\n",
+            "references unknown backend 'nope:9000'",
+            false,
+        ),
+        ("include = [\"more.toml\"]\n", "unknown field `include`", true),
+    ] {
+        fs::write(&fragment, content)?;
+        let Err(err) = load_from_path(&path) else {
+            return Err(format!("expected Err for fragment {content:?} but got Ok").into());
+        };
+        let err = err.to_string();
+        assert!(err.contains(expected), "got: {err}");
+        assert_eq!(err.contains(&fragment.display().to_string()), names_fragment, "got: {err}");
+    }
+
+    fs::remove_file(&fragment)?;
+    let Err(err) = load_from_path(&path) else {
+        return Err("expected Err for a missing include file but got Ok".into());
+    };
+    assert!(err.to_string().contains("file not found"), "got: {err}");
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use std::fs;
+use std::path::PathBuf;
+
+use huginn_proxy_lib::config::{load_from_path, Config};
+
+use super::tmp_path;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// A value that would break out of any string it is pasted into unescaped.
+const TRICKY: &str = r#"pa"ss\wo'rd #1"#;
+
+fn write_config(name: &str, extension: &str, content: &str) -> std::io::Result<PathBuf> {
+    let path = tmp_path(name).with_extension(extension);
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+fn load(name: &str, extension: &str, content: &str) -> huginn_proxy_lib::Result<Config> {
+    let path = write_config(name, extension, content)?;
+    let loaded = load_from_path(&path);
+    let _ = fs::remove_file(&path);
+    loaded
+}
+
+fn load_err(name: &str, extension: &str, content: &str) -> Result<String, String> {
+    match load(name, extension, content) {
+        Ok(_) => Err(format!("expected Err for {name} but got Ok")),
+        Err(err) => Ok(err.to_string()),
+    }
+}
+
+fn header_value(cfg: &Config) -> Result<&str, &'static str> {
+    let headers = cfg.headers.as_ref().ok_or("headers missing")?;
+    Ok(headers.request.add[0].value.expose())
+}
+
+#[test]
+fn fallback_escape_and_plain_dollar() -> TestResult {
+    let toml = r#"
+# A comment may mention ${HUGINN_TEST_UNSET_IN_COMMENT} without defining it.
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "${HUGINN_TEST_UNSET_BACKEND:-backend:9000}" }]
+
+[[domains]]
+host = "example.com"
+  [[domains.routes]]
+  prefix = "/" # so can an inline one: ${HUGINN_TEST_UNSET_INLINE}
+  backend = "backend:9000"
+  regex = '^/[a-z]+$'
+
+[headers]
+request = { add = [{ name = "X-Template", value = "$${ja4}" }] }
+"#;
+    let cfg = load("interpolate-fallback", "toml", toml)?;
+    assert_eq!(cfg.backends[0].address, "backend:9000");
+    let regex = cfg.domains[0].routes[0]
+        .regex
+        .as_ref()
+        .ok_or("regex missing")?;
+    assert_eq!(regex.as_str(), "^/[a-z]+$");
+    assert_eq!(header_value(&cfg)?, "${ja4}");
+    Ok(())
+}
+
+#[test]
+fn header_template_variables_are_not_interpolated() -> TestResult {
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "backend:9000" }]
+
+[headers]
+request = { add = [{ name = "X-Real-IP", value = "${client_ip}" }] }
+"#;
+    let cfg = load("interpolate-template", "toml", toml)?;
+    assert_eq!(header_value(&cfg)?, "${client_ip}");
+    Ok(())
+}
+
+#[test]
+fn toml_values_are_escaped_in_double_quoted_strings() -> TestResult {
+    std::env::set_var("HUGINN_TEST_TRICKY_TOML", TRICKY);
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "backend:9000" }]
+
+[headers]
+request = { add = [{ name = "X-Secret", value = "${HUGINN_TEST_TRICKY_TOML}" }] }
+"#;
+    let cfg = load("interpolate-toml-escape", "toml", toml)?;
+    assert_eq!(header_value(&cfg)?, TRICKY);
+    Ok(())
+}
+
+#[test]
+fn yaml_values_are_escaped_in_quoted_scalars() -> TestResult {
+    std::env::set_var("HUGINN_TEST_TRICKY_YAML", TRICKY);
+    for (name, quoted) in [
+        ("interpolate-yaml-double", "\"${HUGINN_TEST_TRICKY_YAML}\""),
+        ("interpolate-yaml-single", "'${HUGINN_TEST_TRICKY_YAML}'"),
+    ] {
+        let yaml = format!(
+            "listen:\n  addrs: [\"127.0.0.1:0\"]\nbackends:\n  - address: backend:9000\n\
+             headers:\n  request:\n    add:\n      - name: X-Secret\n        value: {quoted}\n"
+        );
+        let cfg = load(name, "yaml", &yaml)?;
+        assert_eq!(header_value(&cfg)?, TRICKY, "{name}");
+    }
+    Ok(())
+}
+
+#[test]
+fn json_values_are_escaped_in_strings() -> TestResult {
+    std::env::set_var("HUGINN_TEST_TRICKY_JSON", TRICKY);
+    let json = r#"{
+  "listen": { "addrs": ["127.0.0.1:0"] },
+  "backends": [{ "address": "backend:9000" }],
+  "headers": { "request": { "add": [{ "name": "X-Secret", "value": "${HUGINN_TEST_TRICKY_JSON}" }] } }
+}"#;
+    let cfg = load("interpolate-json-escape", "json", json)?;
+    assert_eq!(header_value(&cfg)?, TRICKY);
+    Ok(())
+}
+
+#[test]
+fn bare_values_must_be_plain_words() -> TestResult {
+    std::env::set_var("HUGINN_TEST_BARE_TRUE", "true");
+    std::env::set_var("HUGINN_TEST_BARE_TRICKY", TRICKY);
+    let config = |variable: &str| {
+        format!(
+            "listen = {{ addrs = [\"127.0.0.1:0\"] }}\npreserve_host = ${{{variable}}}\n\
+             backends = [{{ address = \"backend:9000\" }}]\n"
+        )
+    };
+    let cfg = load("interpolate-bare", "toml", &config("HUGINN_TEST_BARE_TRUE"))?;
+    assert!(cfg.preserve_host);
+
+    let err = load_err("interpolate-bare-tricky", "toml", &config("HUGINN_TEST_BARE_TRICKY"))?;
+    assert!(
+        err.contains(
+            "line 2: the value of `HUGINN_TEST_BARE_TRICKY` is not a plain word or number"
+        ),
+        "got: {err}"
+    );
+    Ok(())
+}
+
+#[test]
+fn toml_literal_strings_reject_quotes() -> TestResult {
+    std::env::set_var("HUGINN_TEST_LITERAL_TRICKY", TRICKY);
+    let toml = "listen = { addrs = ['${HUGINN_TEST_LITERAL_TRICKY}'] }\n";
+    let err = load_err("interpolate-literal", "toml", toml)?;
+    assert!(
+        err.contains(
+            "line 1: the value of `HUGINN_TEST_LITERAL_TRICKY` cannot be written in a \
+             single-quoted string"
+        ),
+        "got: {err}"
+    );
+    Ok(())
+}
+
+#[test]
+fn undefined_variables_are_listed_with_lines() -> TestResult {
+    let path = write_config(
+        "interpolate-undefined",
+        "toml",
+        r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [
+  { address = "${HUGINN_TEST_UNSET_A}" },
+  { address = "${HUGINN_TEST_UNSET_B}:9000" },
+]
+"#,
+    )?;
+
+    let Err(err) = load_from_path(&path) else {
+        return Err("expected Err for undefined variables but got Ok".into());
+    };
+    let _ = fs::remove_file(&path);
+    let err = err.to_string();
+    assert!(err.contains(&path.display().to_string()), "got: {err}");
+    assert!(
+        err.contains("`HUGINN_TEST_UNSET_A` (line 4), `HUGINN_TEST_UNSET_B` (line 5)"),
+        "got: {err}"
+    );
+    Ok(())
+}
+
+#[test]
+fn unterminated_reference_is_rejected() -> TestResult {
+    let toml = "listen = { addrs = [\"127.0.0.1:0\"] }\n\n[[backends]]\naddress = \"${HUGINN_TEST_UNSET\"\n";
+    let err = load_err("interpolate-unterminated", "toml", toml)?;
+    assert!(err.contains("line 4: unterminated `${`"), "got: {err}");
+    Ok(())
+}
@@ -304,3 +304,49 @@ backends = [
     let _ = fs::remove_file(&path);
     Ok(())
 }
+
+#[test]
+fn rejects_missing_client_ca_file() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = tmp_path("missing-client-ca");
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "b:9000" }]
+
+[tls]
+client_auth = { required = { ca_cert_path = "/nonexistent/client-ca.pem" } }
+"#;
+    fs::write(&path, toml)?;
+    let Err(err) = load_from_path(&path) else {
+        return Err("expected Err for a missing client CA file but got Ok".into());
+    };
+    let err = err.to_string();
+    assert!(
+        err.contains("client CA file not found: /nonexistent/client-ca.pem"),
+        "got: {err}"
+    );
+    let _ = fs::remove_file(&path);
+    Ok(())
+}
+
+#[test]
+fn parse_errors_name_the_file_and_suggest_the_key(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = tmp_path("typo");
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "b:9000" }]
+
+[access_log]
+enabeld = true
+"#;
+    fs::write(&path, toml)?;
+    let Err(err) = load_from_path(&path) else {
+        return Err("expected Err for an unknown key but got Ok".into());
+    };
+    let err = err.to_string();
+    assert!(err.contains(&path.display().to_string()), "got: {err}");
+    assert!(err.contains("line 6"), "got: {err}");
+    assert!(err.ends_with("help: did you mean `enabled`?"), "got: {err}");
+    let _ = fs::remove_file(&path);
+    Ok(())
+}
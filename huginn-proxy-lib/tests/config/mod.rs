@@ -1,10 +1,13 @@
 mod audit;
 mod effective;
 mod header_manipulation;
+mod include;
+mod interpolate;
 mod loader;
 mod parser;
 mod reload;
 mod secret;
+mod tenants;
 mod types;
 
 use std::path::PathBuf;
@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use huginn_proxy_lib::config::parser::{ConfigFormat, TomlParser, YamlParser};
+use huginn_proxy_lib::config::parser::{ConfigFormat, JsonParser, TomlParser, YamlParser};
 use huginn_proxy_lib::config::ConfigParser;
 
 type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
@@ -23,13 +23,19 @@ fn detects_yml_extension() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn detects_json_extension() -> TestResult {
+    assert_eq!(ConfigFormat::from_path(Path::new("config.json"))?, ConfigFormat::Json);
+    Ok(())
+}
+
 #[test]
 fn rejects_unknown_extension() -> TestResult {
-    let Err(err) = ConfigFormat::from_path(Path::new("config.json")) else {
-        return Err("expected Err for .json extension but got Ok".into());
+    let Err(err) = ConfigFormat::from_path(Path::new("config.ini")) else {
+        return Err("expected Err for .ini extension but got Ok".into());
     };
     let msg = err.to_string();
-    assert!(msg.contains(".json"), "error should mention the bad extension: {msg}");
+    assert!(msg.contains(".ini"), "error should mention the bad extension: {msg}");
     assert!(msg.contains(".toml"), "error should hint at valid extensions: {msg}");
     Ok(())
 }
@@ -56,6 +62,37 @@ fn toml_parser_parses_minimal_config() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn json_parser_parses_minimal_config() -> TestResult {
+    let input = r#"{
+        "listen": { "addrs": ["127.0.0.1:0"] },
+        "backends": [{ "address": "localhost:3000" }]
+    }"#;
+    let cfg = JsonParser.parse(input)?;
+    assert_eq!(cfg.backends.len(), 1);
+    assert_eq!(cfg.backends[0].address, "localhost:3000");
+    Ok(())
+}
+
+#[test]
+fn json_parser_rejects_unknown_field_with_suggestion() -> TestResult {
+    let input = r#"{
+        "listen": { "addrs": ["127.0.0.1:0"] },
+        "backends": [{ "adress": "localhost:3000" }]
+    }"#;
+    let Err(err) = JsonParser.parse(input) else {
+        return Err("expected unknown backend field to be rejected".into());
+    };
+    let message = err.to_string();
+    assert!(message.contains("JSON parse error"), "unexpected error: {message}");
+    assert!(message.contains("line 3"), "error should point at the line: {message}");
+    assert!(
+        message.ends_with("help: did you mean `address`?"),
+        "unexpected error: {message}"
+    );
+    Ok(())
+}
+
 #[test]
 fn toml_parser_returns_error_on_invalid_syntax() -> TestResult {
     let Err(err) = TomlParser.parse("this is not toml :::") else {
@@ -108,6 +145,50 @@ fn toml_parser_rejects_unknown_nested_field() -> TestResult {
     let message = err.to_string();
     assert!(message.contains("TOML parse error"), "unexpected error: {message}");
     assert!(message.contains("header_timeoutms"), "error should identify typo: {message}");
+    assert!(
+        message.ends_with("help: did you mean `header_timeout_ms`?"),
+        "error should suggest the closest field: {message}"
+    );
+    Ok(())
+}
+
+#[test]
+fn yaml_parser_suggests_close_variant() -> TestResult {
+    let input = r#"
+listen:
+  addrs:
+    - "127.0.0.1:0"
+  proxy_protocol:
+    mode: optinal
+backends:
+  - address: "localhost:3000"
+"#;
+
+    let Err(err) = YamlParser.parse(input) else {
+        return Err("expected unknown proxy_protocol mode to be rejected".into());
+    };
+    let message = err.to_string();
+    assert!(
+        message.ends_with("help: did you mean `optional`?"),
+        "error should suggest the closest variant: {message}"
+    );
+    Ok(())
+}
+
+#[test]
+fn parser_does_not_suggest_unrelated_fields() -> TestResult {
+    let input = r#"
+        backends = [{ address = "localhost:3000" }]
+        listen = { addrs = ["127.0.0.1:0"] }
+        upstream_pool = 4
+    "#;
+
+    let Err(err) = TomlParser.parse(input) else {
+        return Err("expected unknown top-level field to be rejected".into());
+    };
+    let message = err.to_string();
+    assert!(message.contains("upstream_pool"), "error should identify field: {message}");
+    assert!(!message.contains("did you mean"), "no close field to suggest: {message}");
     Ok(())
 }
 
@@ -0,0 +1,197 @@
+//! `[[tenants]]`: tenant domains and backends merged into the top-level lists, tenant policy
+//! defaults, listeners bound to a tenant, and isolation between tenants.
+
+use std::fs;
+
+use huginn_proxy_lib::config::{load_from_path, Config, NO_TENANT_LABEL};
+
+use super::tmp_path;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const SHOP: &str = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "shared:9000" }]
+
+[[domains]]
+host = "www.example.com"
+routes = [{ prefix = "/", backend = "shared:9000" }]
+
+[[tenants]]
+name = "shop"
+backends = [{ address = "shop-api:9000" }, { address = "shop-web:9000" }]
+
+[tenants.headers.request]
+add = [{ name = "x-team", value = "shop" }]
+
+[tenants.security.rate_limit]
+enabled = true
+requests_per_second = 50
+burst = 100
+
+[[tenants.domains]]
+host = "shop.example.com"
+routes = [{ prefix = "/", backend = "shop-web:9000" }]
+
+[[tenants.domains]]
+host = "api.shop.example.com"
+routes = [{ prefix = "/", backend = "shop-api:9000" }]
+
+[tenants.domains.security.rate_limit]
+enabled = true
+requests_per_second = 5
+burst = 5
+"#;
+
+fn load(name: &str, toml: &str) -> Result<Config, Box<dyn std::error::Error + Send + Sync>> {
+    let path = tmp_path(name);
+    fs::write(&path, toml)?;
+    let cfg = load_from_path(&path);
+    let _ = fs::remove_file(&path);
+    Ok(cfg?)
+}
+
+#[test]
+fn tenant_domains_and_backends_are_merged_and_tagged() -> TestResult {
+    let cfg = load("tenants", SHOP)?;
+    assert!(cfg
+        .tenants
+        .iter()
+        .all(|t| t.domains.is_empty() && t.backends.is_empty()));
+
+    let owners: Vec<(&str, Option<&str>)> = cfg
+        .backends
+        .iter()
+        .map(|b| (b.address.as_str(), b.tenant.as_deref()))
+        .collect();
+    assert_eq!(
+        owners,
+        [
+            ("shared:9000", None),
+            ("shop-api:9000", Some("shop")),
+            ("shop-web:9000", Some("shop"))
+        ]
+    );
+    let labels: Vec<&str> = cfg.domains.iter().map(|d| d.tenant_label()).collect();
+    assert_eq!(labels, [NO_TENANT_LABEL, "shop", "shop"]);
+    Ok(())
+}
+
+#[test]
+fn tenant_policies_are_domain_defaults() -> TestResult {
+    let cfg = load("tenant-defaults", SHOP)?;
+    let rate = |host: &str| {
+        cfg.domains
+            .iter()
+            .find(|d| d.host.as_deref() == Some(host))
+            .and_then(|d| d.security.as_ref())
+            .and_then(|s| s.rate_limit.as_ref())
+            .map(|r| r.requests_per_second)
+    };
+    assert_eq!(rate("www.example.com"), None);
+    assert_eq!(rate("shop.example.com"), Some(50));
+    // A domain's own block wins over the tenant's.
+    assert_eq!(rate("api.shop.example.com"), Some(5));
+
+    // Only the inherited block is shared tenant-wide.
+    let shared = |host: &str| {
+        cfg.domains
+            .iter()
+            .find(|d| d.host.as_deref() == Some(host))
+            .and_then(|d| d.security.as_ref())
+            .is_some_and(|s| s.tenant_rate_limit)
+    };
+    assert!(shared("shop.example.com"));
+    assert!(!shared("api.shop.example.com"));
+
+    let shop = cfg
+        .domains
+        .iter()
+        .find(|d| d.host.as_deref() == Some("shop.example.com"))
+        .ok_or("shop domain missing")?;
+    let headers = shop.headers.as_ref().ok_or("tenant headers not adopted")?;
+    assert_eq!(headers.request.add.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn routes_cannot_cross_tenants() {
+    // A top-level domain using a tenant's backend.
+    let into_tenant = SHOP.replacen(
+        r#"routes = [{ prefix = "/", backend = "shared:9000" }]"#,
+        r#"routes = [{ prefix = "/", backend = "shop-api:9000" }]"#,
+        1,
+    );
+    assert!(load("tenant-into", &into_tenant).is_err());
+
+    // A tenant domain using a backend outside the tenant.
+    let out_of_tenant = SHOP.replacen(
+        r#"routes = [{ prefix = "/", backend = "shop-web:9000" }]"#,
+        r#"routes = [{ prefix = "/", backend = "shared:9000" }]"#,
+        1,
+    );
+    assert!(load("tenant-out", &out_of_tenant).is_err());
+}
+
+#[test]
+fn rejects_invalid_tenants() {
+    let duplicate_name = format!(
+        r#"{SHOP}
+[[tenants]]
+name = "shop"
+backends = [{{ address = "blog:9000" }}]
+
+[[tenants.domains]]
+host = "blog.example.com"
+routes = [{{ prefix = "/", backend = "blog:9000" }}]
+"#
+    );
+    assert!(load("tenant-dup", &duplicate_name).is_err());
+
+    let no_domains = format!("{SHOP}\n[[tenants]]\nname = \"blog\"\n");
+    assert!(load("tenant-empty", &no_domains).is_err());
+
+    let shared_backend = SHOP.replacen(
+        r#"{ address = "shop-api:9000" }, "#,
+        r#"{ address = "shared:9000" }, { address = "shop-api:9000" }, "#,
+        1,
+    );
+    assert!(load("tenant-shared", &shared_backend).is_err());
+
+    let long_name = "a".repeat(65);
+    for name in ["", "shop team", NO_TENANT_LABEL, long_name.as_str()] {
+        let toml = SHOP.replacen("name = \"shop\"", &format!("name = \"{name}\""), 1);
+        assert!(load("tenant-name", &toml).is_err(), "{name:?}");
+    }
+}
+
+#[test]
+fn listeners_can_serve_one_declared_tenant() -> TestResult {
+    let listener = |fields: &str| {
+        SHOP.replacen(
+            r#"listen = { addrs = ["127.0.0.1:0"] }"#,
+            &format!(
+                r#"listen = {{ addrs = ["127.0.0.1:0"], listeners = [{{ addr = "127.0.0.1:1", {fields} }}] }}"#
+            ),
+            1,
+        )
+    };
+    let cfg = load("tenant-listener", &listener(r#"tenant = "shop""#))?;
+    assert_eq!(cfg.listen.listeners[0].tenant.as_deref(), Some("shop"));
+
+    let err = load("tenant-listener-unknown", &listener(r#"tenant = "blog""#))
+        .err()
+        .map(|e| e.to_string())
+        .unwrap_or_default();
+    assert!(err.contains("tenant 'blog' is not declared in [[tenants]]"), "got: {err}");
+
+    let passthrough = listener(
+        r#"tenant = "shop", passthrough = { routes = [{ sni = "*.example.com", backend = "10.0.0.1:443" }] }"#,
+    );
+    let err = load("tenant-listener-passthrough", &passthrough)
+        .err()
+        .map(|e| e.to_string())
+        .unwrap_or_default();
+    assert!(err.contains("passthrough cannot be combined with tenant"), "got: {err}");
+    Ok(())
+}
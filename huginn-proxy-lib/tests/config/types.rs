@@ -1,6 +1,62 @@
 use huginn_proxy_lib::config::{
-    Backend, BackendHttpVersion, ClientAuth, Config, HealthCheckConfig, HealthCheckType, TlsConfig,
+    unix_socket_path, AccessLogField, AccessLogOutput, Backend, BackendHttp2Config,
+    BackendHttpVersion, BackendPoolConfig, BackendPoolLimits, BackendTlsConfig, BandwidthKey,
+    CacheConfig, CircuitBreakerConfig, ClientAuth, ClientCertConfig, CompressionAlgorithm,
+    CompressionConfig, Config, DiscoveryConfig, FingerprintFormat, FingerprintHeadersConfig,
+    HealthCheckConfig, HealthCheckType, Http2Config, Ja4Variant, LimitBy, ListenAddr,
+    LoadSheddingConfig, MetricsConfig, MissingClientCert, RateLimitConfig, RateLimitStore, Route,
+    RouteAccessLogConfig, RouteCacheConfig, RoutePriority, RouteProtocol, RouteTimeoutConfig,
+    StickyHashKey, StickyMode, StrictHttpMode, TcpConfig, TlsConfig, WebSocketConfig,
 };
+use huginn_proxy_lib::fingerprinting::names;
+
+#[test]
+fn test_limit_by_fingerprint_strategies() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for (value, expected) in [
+        ("ja4", LimitBy::Ja4),
+        ("akamai", LimitBy::Akamai),
+        ("ip+ja4", LimitBy::IpJa4),
+        ("sni", LimitBy::Sni),
+        ("alpn", LimitBy::Alpn),
+    ] {
+        let config: RateLimitConfig = toml::from_str(&format!("limit_by = \"{value}\""))?;
+        assert_eq!(config.limit_by, expected);
+        assert_eq!(expected.as_str(), value);
+    }
+    assert!(toml::from_str::<RateLimitConfig>(r#"limit_by = "ipja4""#).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_rate_limit_redis_store_validation() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let config: RateLimitConfig = toml::from_str("enabled = true")?;
+    assert_eq!(config.store, RateLimitStore::Memory);
+    assert!(config.validate("security.rate_limit").is_ok());
+
+    let config: RateLimitConfig = toml::from_str(r#"store = "redis""#)?;
+    assert!(config.validate("security.rate_limit").is_err());
+
+    let config: RateLimitConfig = toml::from_str(
+        r#"store = "redis"
+redis = { url = "redis://:secret@redis:6379/0" }"#,
+    )?;
+    assert!(config.validate("security.rate_limit").is_ok());
+    let redis = config.redis.as_ref().ok_or("redis block")?;
+    assert_eq!(redis.pool_size, 4);
+    assert_eq!(redis.timeout_ms, 50);
+    assert_eq!(redis.key_prefix, "huginn:rl:");
+
+    for bad in [
+        r#"redis = { url = "http://redis:6379" }"#,
+        r#"redis = { url = "redis://redis:6379", pool_size = 0 }"#,
+        r#"redis = { url = "redis://redis:6379", timeout_ms = 0 }"#,
+    ] {
+        let config: RateLimitConfig = toml::from_str(&format!("store = \"redis\"\n{bad}"))?;
+        assert!(config.validate("security.rate_limit").is_err(), "{bad}");
+    }
+    Ok(())
+}
 
 #[test]
 fn test_backend_http_version_deserialization(
@@ -20,6 +76,11 @@ http_version = "preserve""#;
     let backend: Backend = toml::from_str(toml)?;
     assert_eq!(backend.http_version, Some(BackendHttpVersion::Preserve));
 
+    let toml = r#"address = "backend:9000"
+http_version = "auto""#;
+    let backend: Backend = toml::from_str(toml)?;
+    assert_eq!(backend.http_version, Some(BackendHttpVersion::Auto));
+
     let toml = r#"address = "backend:9000""#;
     let backend: Backend = toml::from_str(toml)?;
     assert_eq!(backend.http_version, None);
@@ -68,11 +129,52 @@ required = { ca_cert_path = "/config/certs/client-ca.crt" }
         ClientAuth::Required { ca_cert_path } => {
             assert_eq!(ca_cert_path, "/config/certs/client-ca.crt");
         }
-        ClientAuth::Disabled => panic!("Expected ClientAuth::Required"),
+        ClientAuth::Disabled | ClientAuth::Optional { .. } => {
+            panic!("Expected ClientAuth::Required")
+        }
     }
     Ok(())
 }
 
+#[test]
+fn test_mtls_config_optional_with_client_cert_policy(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+[client_auth]
+optional = { ca_cert_path = "/config/certs/client-ca.crt" }
+
+[client_cert]
+forward_headers = true
+on_missing = "reject"
+reject_status = 401
+"#;
+
+    let config: TlsConfig = toml::from_str(toml)?;
+    assert_eq!(
+        config.client_auth,
+        ClientAuth::Optional { ca_cert_path: "/config/certs/client-ca.crt".to_string() }
+    );
+    assert_eq!(
+        config.client_cert,
+        ClientCertConfig {
+            forward_headers: true,
+            on_missing: MissingClientCert::Reject,
+            reject_status: 401
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mtls_client_cert_policy_defaults() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: TlsConfig = toml::from_str("alpn = []")?;
+    assert_eq!(config.client_cert, ClientCertConfig::default());
+    assert!(!config.client_cert.forward_headers);
+    assert_eq!(config.client_cert.on_missing, MissingClientCert::Allow);
+    assert_eq!(config.client_cert.reject_status, 403);
+    Ok(())
+}
+
 #[test]
 fn test_mtls_config_default_is_disabled() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let toml = r#"
@@ -118,7 +220,9 @@ required = { ca_cert_path = "/config/certs/client-ca.crt" }
         ClientAuth::Required { ca_cert_path } => {
             assert_eq!(ca_cert_path, "/config/certs/client-ca.crt");
         }
-        ClientAuth::Disabled => panic!("Expected ClientAuth::Required"),
+        ClientAuth::Disabled | ClientAuth::Optional { .. } => {
+            panic!("Expected ClientAuth::Required")
+        }
     }
     Ok(())
 }
@@ -162,6 +266,65 @@ preserve_host = false
     Ok(())
 }
 
+#[test]
+fn test_default_host_validation() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let base = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+"#;
+    let config: Config = toml::from_str(base)?;
+    assert_eq!(config.default_host, None);
+
+    for host in ["example.com", "example.com:8080", "127.0.0.1", "[::1]:443"] {
+        let config: Config = toml::from_str(&format!("{base}default_host = \"{host}\"\n"))?;
+        assert_eq!(config.default_host.as_deref(), Some(host));
+        config.validate_cross_refs()?;
+    }
+    for host in ["user@example.com", "example.com/path", "exa mple.com", ""] {
+        let config: Config = toml::from_str(&format!("{base}default_host = \"{host}\"\n"))?;
+        assert!(config.validate_cross_refs().is_err(), "{host} should be rejected");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_route_host_overrides() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let route = |extra: &str| {
+        format!(
+            r#"
+listen = {{ addrs = ["0.0.0.0:7000"] }}
+backends = [{{ address = "backend:9000" }}]
+preserve_host = true
+
+[[domains]]
+routes = [{{ prefix = "/", backend = "backend:9000"{extra} }}]
+"#
+        )
+    };
+    let config: Config = toml::from_str(&route(""))?;
+    assert_eq!(config.domains[0].routes[0].preserve_host, None);
+    assert_eq!(config.domains[0].routes[0].host_rewrite, None);
+
+    for extra in [
+        r#", preserve_host = false"#,
+        r#", host_rewrite = "api.vendor.example""#,
+        r#", host_rewrite = "api.vendor.example:8443", preserve_host = false"#,
+    ] {
+        let config: Config = toml::from_str(&route(extra))?;
+        config.validate_cross_refs()?;
+    }
+    for extra in [
+        r#", host_rewrite = "api.vendor.example", preserve_host = true"#,
+        r#", host_rewrite = "user@api.vendor.example""#,
+        r#", host_rewrite = "api.vendor.example/v1""#,
+        r#", host_rewrite = """#,
+    ] {
+        let config: Config = toml::from_str(&route(extra))?;
+        assert!(config.validate_cross_refs().is_err(), "{extra} should be rejected");
+    }
+    Ok(())
+}
+
 #[test]
 fn test_timeout_defaults() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let toml = r#"
@@ -316,3 +479,1647 @@ backends = [
     config.validate_cross_refs()?;
     Ok(())
 }
+
+#[test]
+fn test_backend_circuit_breaker_defaults_and_explicit(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [
+  { address = "a:1", circuit_breaker = {} },
+  { address = "b:1", circuit_breaker = { failure_threshold = 3, open_duration_secs = 10, half_open_requests = 2 } },
+  { address = "c:1" }
+]
+"#;
+    let config: Config = toml::from_str(toml)?;
+    assert_eq!(config.backends[0].circuit_breaker, Some(CircuitBreakerConfig::default()));
+    assert_eq!(
+        config.backends[1].circuit_breaker,
+        Some(CircuitBreakerConfig {
+            failure_threshold: 3,
+            open_duration_secs: 10,
+            half_open_requests: 2
+        })
+    );
+    assert!(config.backends[2].circuit_breaker.is_none());
+    config.validate_cross_refs()?;
+    Ok(())
+}
+
+#[test]
+fn test_circuit_breaker_config_validate_rejects_zero_values() {
+    let zero_threshold = CircuitBreakerConfig { failure_threshold: 0, ..Default::default() };
+    let zero_open = CircuitBreakerConfig { open_duration_secs: 0, ..Default::default() };
+    let zero_trials = CircuitBreakerConfig { half_open_requests: 0, ..Default::default() };
+    assert!(zero_threshold.validate().is_err());
+    assert!(zero_open.validate().is_err());
+    assert!(zero_trials.validate().is_err());
+    assert!(CircuitBreakerConfig::default().validate().is_ok());
+}
+
+#[test]
+fn test_backend_tls_deserialization() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [
+  { address = "api:443", tls = { ca_cert_path = "/etc/ca.pem", server_name = "api.internal" } },
+  { address = "plain:80" }
+]
+"#;
+    let config: Config = toml::from_str(toml)?;
+    assert_eq!(
+        config.backends[0].tls,
+        Some(BackendTlsConfig {
+            ca_cert_path: Some("/etc/ca.pem".to_string()),
+            server_name: Some("api.internal".to_string()),
+            client_cert_path: None,
+            client_key_path: None,
+        })
+    );
+    assert!(config.backends[1].tls.is_none());
+    config.validate_cross_refs()?;
+    Ok(())
+}
+
+#[test]
+fn test_backend_tls_validate() {
+    let cert_without_key = BackendTlsConfig {
+        client_cert_path: Some("/etc/client.pem".to_string()),
+        ..Default::default()
+    };
+    assert!(cert_without_key.validate().is_err());
+    let bad_name =
+        BackendTlsConfig { server_name: Some("not a hostname".to_string()), ..Default::default() };
+    assert!(bad_name.validate().is_err());
+    assert!(BackendTlsConfig::default().validate().is_ok());
+}
+
+#[test]
+fn test_backend_pool_limits() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backend_pool = { idle_timeout = 30, pool_max_idle_per_host = 8 }
+backends = [
+  { address = "api:80", pool = { max_idle = 2, max_connections = 64, wait_timeout_ms = 250 } },
+  { address = "plain:80" }
+]
+"#;
+    let config: Config = toml::from_str(toml)?;
+    let limits = config.backends[0].pool.clone().unwrap_or_default();
+    assert_eq!(limits.max_connections, Some(64));
+    assert_eq!(limits.max_concurrent_streams, None);
+    assert_eq!(limits.wait_timeout_ms, 250);
+    assert!(config.backends[1].pool.is_none());
+    config.validate_cross_refs()?;
+
+    // Unset idle settings inherit `[backend_pool]`.
+    let resolved = limits.resolve(&config.backend_pool);
+    assert_eq!(
+        resolved,
+        BackendPoolConfig {
+            enabled: true,
+            idle_timeout: 30,
+            pool_max_idle_per_host: 2,
+            ..BackendPoolConfig::default()
+        }
+    );
+    assert_eq!(BackendPoolLimits::default().wait_timeout_ms, 1000);
+
+    let zero = BackendPoolLimits { max_concurrent_streams: Some(0), ..Default::default() };
+    assert!(zero.validate().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_route_ja4_variants_deserialization() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let toml = r#"prefix = "/api"
+backend = "backend:9000"
+ja4_variants = ["ja4", "ja4_r", "ja4_o", "ja4_or", "ja4_s1", "ja4_s1r"]"#;
+    let route: Route = toml::from_str(toml)?;
+    assert_eq!(route.ja4_variants.as_deref(), Some(Ja4Variant::ALL));
+
+    let toml = r#"prefix = "/api"
+backend = "backend:9000""#;
+    let route: Route = toml::from_str(toml)?;
+    assert_eq!(route.ja4_variants, None);
+
+    let toml = r#"prefix = "/api"
+backend = "backend:9000"
+ja4_variants = []"#;
+    let route: Route = toml::from_str(toml)?;
+    assert_eq!(route.ja4_variants, Some(vec![]));
+    Ok(())
+}
+
+#[test]
+fn test_route_ja4_variants_rejects_unknown_variant() {
+    let toml = r#"prefix = "/api"
+backend = "backend:9000"
+ja4_variants = ["ja4_x"]"#;
+    assert!(toml::from_str::<Route>(toml).is_err());
+}
+
+#[test]
+fn test_route_websocket_deserialization() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"prefix = "/ws"
+backend = "backend:9000"
+websocket = { enabled = true, idle_timeout_secs = 60 }"#;
+    let route: Route = toml::from_str(toml)?;
+    assert_eq!(route.websocket, WebSocketConfig { enabled: true, idle_timeout_secs: 60 });
+
+    let toml = r#"prefix = "/api"
+backend = "backend:9000""#;
+    let route: Route = toml::from_str(toml)?;
+    assert!(!route.websocket.enabled);
+    assert_eq!(route.websocket.idle_timeout_secs, 300);
+    Ok(())
+}
+
+#[test]
+fn test_route_websocket_idle_timeout_must_be_positive() {
+    assert!(WebSocketConfig::default().validate().is_ok());
+    let config = WebSocketConfig { enabled: true, idle_timeout_secs: 0 };
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_route_protocol_deserialization() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"prefix = "/grpc"
+backend = "backend:9000"
+protocol = "grpc""#;
+    let route: Route = toml::from_str(toml)?;
+    assert_eq!(route.protocol, RouteProtocol::Grpc);
+
+    let toml = r#"prefix = "/api"
+backend = "backend:9000""#;
+    let route: Route = toml::from_str(toml)?;
+    assert_eq!(route.protocol, RouteProtocol::Http);
+
+    let toml = r#"prefix = "/api"
+backend = "backend:9000"
+protocol = "thrift""#;
+    assert!(toml::from_str::<Route>(toml).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_grpc_route_rejects_http11_backend_and_websocket(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config_with = |backend: &str, route_extra: &str| {
+        format!(
+            r#"
+listen = {{ addrs = ["0.0.0.0:7000"] }}
+backends = [{{ address = "backend:9000"{backend} }}]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/grpc"
+  backend = "backend:9000"
+  protocol = "grpc"{route_extra}
+"#
+        )
+    };
+
+    let config: Config = toml::from_str(&config_with(r#", http_version = "http2""#, ""))?;
+    config.validate_cross_refs()?;
+
+    let config: Config = toml::from_str(&config_with(r#", http_version = "http11""#, ""))?;
+    assert!(config.validate_cross_refs().is_err());
+
+    let config: Config = toml::from_str(&config_with("", "\n  websocket = { enabled = true }"))?;
+    assert!(config.validate_cross_refs().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_route_body_limits_must_be_positive() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let config_with = |limits: &str| {
+        format!(
+            r#"
+listen = {{ addrs = ["0.0.0.0:7000"] }}
+backends = [{{ address = "backend:9000" }}]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/upload"
+  backend = "backend:9000"
+  {limits}
+"#
+        )
+    };
+
+    let config: Config = toml::from_str(&config_with(
+        "max_request_body_bytes = 1048576\n  max_response_body_bytes = 1",
+    ))?;
+    config.validate_cross_refs()?;
+    let route = config
+        .domains
+        .first()
+        .and_then(|d| d.routes.first())
+        .ok_or("route missing")?;
+    assert_eq!(route.max_request_body_bytes, Some(1_048_576));
+    assert_eq!(route.max_response_body_bytes, Some(1));
+
+    for limits in ["max_request_body_bytes = 0", "max_response_body_bytes = 0"] {
+        let config: Config = toml::from_str(&config_with(limits))?;
+        assert!(config.validate_cross_refs().is_err(), "{limits} should be rejected");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_max_in_flight_must_be_positive() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config_with = |backend: &str, route: &str| {
+        format!(
+            r#"
+listen = {{ addrs = ["0.0.0.0:7000"] }}
+backends = [{{ address = "backend:9000"{backend} }}]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/api"
+  backend = "backend:9000"
+  {route}
+"#
+        )
+    };
+
+    let config: Config =
+        toml::from_str(&config_with(", max_in_flight = 100", "max_in_flight = 10"))?;
+    config.validate_cross_refs()?;
+    assert_eq!(config.backends.first().and_then(|b| b.max_in_flight), Some(100));
+    let route = config
+        .domains
+        .first()
+        .and_then(|d| d.routes.first())
+        .ok_or("route missing")?;
+    assert_eq!(route.max_in_flight, Some(10));
+
+    for (backend, route) in [(", max_in_flight = 0", ""), ("", "max_in_flight = 0")] {
+        let config: Config = toml::from_str(&config_with(backend, route))?;
+        assert!(config.validate_cross_refs().is_err(), "{backend}{route} should be rejected");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_serve_static_route_needs_no_backend() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let root = std::env::temp_dir();
+    let config_with = |route: &str| {
+        format!(
+            r#"
+listen = {{ addrs = ["0.0.0.0:7000"] }}
+backends = [{{ address = "backend:9000" }}]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/assets"
+  {route}
+"#
+        )
+    };
+
+    let config: Config = toml::from_str(&config_with(&format!(
+        "serve_static = {{ root = {:?} }}",
+        root.display().to_string()
+    )))?;
+    config.validate_cross_refs()?;
+
+    let config: Config = toml::from_str(&config_with(""))?;
+    let err = config
+        .validate_cross_refs()
+        .err()
+        .ok_or("a route without backend or serve_static should be rejected")?;
+    assert!(
+        err.to_string()
+            .contains("has no backend; only serve_static routes can leave it out"),
+        "got: {err}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_backend_discovery() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config_with = |backend: &str| {
+        format!(
+            r#"
+listen = {{ addrs = ["0.0.0.0:7000"] }}
+backends = [{backend}]
+"#
+        )
+    };
+
+    let config: Config = toml::from_str(&config_with(
+        r#"{ address = "app:9000", discovery = { type = "dns" } },
+  { address = "api:9000", discovery = { type = "dns", refresh_secs = 5 } }"#,
+    ))?;
+    config.validate_cross_refs()?;
+    let discovery: Vec<_> = config
+        .backends
+        .iter()
+        .map(|b| b.discovery.clone())
+        .collect();
+    assert_eq!(
+        discovery,
+        [
+            Some(DiscoveryConfig::Dns { refresh_secs: 30 }),
+            Some(DiscoveryConfig::Dns { refresh_secs: 5 })
+        ]
+    );
+
+    for backend in [
+        r#"{ address = "app:9000", discovery = { type = "dns", refresh_secs = 0 } }"#,
+        r#"{ address = "10.0.0.1:9000", discovery = { type = "dns" } }"#,
+        r#"{ address = "[::1]:9000", discovery = { type = "dns" } }"#,
+        r#"{ address = "unix:///var/run/app.sock", discovery = { type = "dns" } }"#,
+    ] {
+        let config: Config = toml::from_str(&config_with(backend))?;
+        assert!(config.validate_cross_refs().is_err(), "{backend} should be rejected");
+    }
+    assert!(toml::from_str::<Config>(&config_with(
+        r#"{ address = "app:9000", discovery = { type = "dns", ttl = 5 } }"#
+    ))
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_backend_discovery_providers() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config_with = |backend: &str| {
+        format!(
+            r#"
+listen = {{ addrs = ["0.0.0.0:7000"] }}
+backends = [{backend}]
+"#
+        )
+    };
+
+    let config: Config = toml::from_str(&config_with(
+        r#"{ address = "billing", discovery = { type = "static", members = ["10.0.0.1:9000", "app-2:9000"] } },
+  { address = "search", discovery = { type = "srv", name = "_http._tcp.search.example", refresh_secs = 10 } },
+  { address = "orders:8080", discovery = { type = "consul", service = "orders", tag = "v2", token = "t0k" } }"#,
+    ))?;
+    config.validate_cross_refs()?;
+    let discovery: Vec<_> = config
+        .backends
+        .iter()
+        .map(|b| b.discovery.clone())
+        .collect();
+    assert_eq!(
+        discovery,
+        [
+            Some(DiscoveryConfig::Static {
+                members: vec!["10.0.0.1:9000".to_string(), "app-2:9000".to_string()],
+                refresh_secs: 30,
+            }),
+            Some(DiscoveryConfig::Srv {
+                name: "_http._tcp.search.example".to_string(),
+                refresh_secs: 10,
+            }),
+            Some(DiscoveryConfig::Consul {
+                url: "http://127.0.0.1:8500".to_string(),
+                service: "orders".to_string(),
+                tag: Some("v2".to_string()),
+                datacenter: None,
+                token: Some("t0k".to_string().into()),
+                refresh_secs: 30,
+            }),
+        ]
+    );
+    assert!(discovery.iter().flatten().all(DiscoveryConfig::is_service));
+
+    for backend in [
+        r#"{ address = "billing", discovery = { type = "static", members = [] } }"#,
+        r#"{ address = "billing", discovery = { type = "static", members = ["app-1"] } }"#,
+        r#"{ address = "billing/v1", discovery = { type = "static", members = ["app-1:80"] } }"#,
+        r#"{ address = "search", discovery = { type = "srv", name = "" } }"#,
+        r#"{ address = "orders", discovery = { type = "consul", service = "orders", url = "https://consul:8501" } }"#,
+        r#"{ address = "orders", discovery = { type = "consul", service = "orders&dc=x" } }"#,
+        r#"{ address = "orders", discovery = { type = "consul", service = "orders" }, health_check = {} }"#,
+    ] {
+        let config: Config = toml::from_str(&config_with(backend))?;
+        assert!(config.validate_cross_refs().is_err(), "{backend} should be rejected");
+    }
+    assert!(toml::from_str::<Config>(&config_with(
+        r#"{ address = "orders", discovery = { type = "consul" } }"#
+    ))
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_backend_drain() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [
+  { address = "old:9000", drain = true, drain_timeout_secs = 30 },
+  { address = "new:9000" },
+]
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    assert!(config.backends[0].drain);
+    assert_eq!(config.backends[0].drain_timeout_secs, Some(30));
+    assert!(!config.backends[1].drain);
+    assert_eq!(config.backends[1].drain_timeout_secs, None);
+
+    let zero: Config = toml::from_str(
+        r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "old:9000", drain = true, drain_timeout_secs = 0 }]
+"#,
+    )?;
+    assert!(zero.validate_cross_refs().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_backend_slow_start() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "new:9000", slow_start_secs = 30 }, { address = "old:9000" }]
+"#,
+    )?;
+    config.validate_cross_refs()?;
+    assert_eq!(config.backends[0].slow_start_secs, Some(30));
+    assert_eq!(config.backends[1].slow_start_secs, None);
+
+    let zero: Config = toml::from_str(
+        r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "new:9000", slow_start_secs = 0 }]
+"#,
+    )?;
+    assert!(zero.validate_cross_refs().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_fingerprint_signing() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"] }
+
+[fingerprint.signing]
+enabled = true
+keys = [
+  { id = "2026-04", secret = "0123456789abcdef0123456789abcdef" },
+  { id = "2026-10", secret_file = "/run/secrets/huginn-2026-10", not_before = 4102444800 },
+]
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_ok());
+    assert_eq!(config.fingerprint.signing.keys[1].not_before, Some(4_102_444_800));
+
+    for invalid in [
+        "enabled = true",
+        r#"enabled = true
+keys = [{ id = "k1", secret = "too short" }]"#,
+        r#"enabled = true
+keys = [{ id = "k 1", secret = "0123456789abcdef0123456789abcdef" }]"#,
+        r#"enabled = true
+keys = [{ id = "k1", secret = "0123456789abcdef0123456789abcdef", secret_file = "/k" }]"#,
+        r#"enabled = true
+keys = [
+  { id = "k1", secret = "0123456789abcdef0123456789abcdef" },
+  { id = "k1", secret = "0123456789abcdef0123456789abcdef" },
+]"#,
+        r#"enabled = true
+keys = [{ id = "k1", secret = "0123456789abcdef0123456789abcdef", not_before = 4102444800 }]"#,
+    ] {
+        let config: Config = toml::from_str(&format!(
+            "listen = {{ addrs = [\"127.0.0.1:0\"] }}\n[fingerprint.signing]\n{invalid}\n"
+        ))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_route_tcp_os() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/"
+  backend = "backend:9000"
+
+  [[domains.routes]]
+  prefix = "/login"
+  backend = "backend:9000"
+  tcp_os = true
+"#,
+    )?;
+    assert!(!config.domains[0].routes[0].tcp_os);
+    assert!(config.domains[0].routes[1].tcp_os);
+    config.validate_cross_refs()?;
+    Ok(())
+}
+
+#[test]
+fn test_fingerprint_format() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let routes = r#"
+backends = [{ address = "backend:9000" }]
+
+[[domains]]
+fingerprint_format = "structured"
+  [[domains.routes]]
+  prefix = "/"
+  backend = "backend:9000"
+
+  [[domains.routes]]
+  prefix = "/api"
+  backend = "backend:9000"
+  fingerprint_format = "jwt"
+"#;
+    let config: Config =
+        toml::from_str(&format!("listen = {{ addrs = [\"0.0.0.0:7000\"] }}\n{routes}"))?;
+    assert_eq!(config.domains[0].fingerprint_format, Some(FingerprintFormat::Structured));
+    assert_eq!(config.domains[0].routes[1].fingerprint_format, Some(FingerprintFormat::Jwt));
+    // JWTs are signed with the `[fingerprint.signing]` key.
+    assert!(config.validate_cross_refs().is_err());
+
+    let config: Config = toml::from_str(&format!(
+        "listen = {{ addrs = [\"0.0.0.0:7000\"] }}\n\
+         fingerprint = {{ signing = {{ enabled = true, keys = [{{ id = \"k1\", secret = \
+         \"0123456789abcdef0123456789abcdef\" }}] }} }}\n{routes}"
+    ))?;
+    config.validate_cross_refs()?;
+    Ok(())
+}
+
+#[test]
+fn test_backend_auto_http_version_on_plain_backend(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = |backend: &str| {
+        toml::from_str::<Config>(&format!(
+            "listen = {{ addrs = [\"0.0.0.0:7000\"] }}\nbackends = [{backend}]\n"
+        ))
+    };
+    config(r#"{ address = "api:443", http_version = "auto", tls = {} }"#)?.validate_cross_refs()?;
+    // Without TLS there is no ALPN: the backend is spoken to over HTTP/1.1.
+    config(r#"{ address = "api:80", http_version = "auto" }"#)?.validate_cross_refs()?;
+    Ok(())
+}
+
+#[test]
+fn test_backend_echo() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "echo", echo = true }]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/"
+  backend = "echo"
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    assert!(config.backends[0].echo);
+
+    for invalid in [
+        r#"{ address = "echo", echo = true, tls = {} }"#,
+        r#"{ address = "echo", echo = true, health_check = {} }"#,
+        r#"{ address = "echo", echo = true, discovery = { type = "dns" } }"#,
+        r#"{ address = "unix:/tmp/echo.sock", echo = true }"#,
+    ] {
+        let config: Config = toml::from_str(&format!(
+            "listen = {{ addrs = [\"0.0.0.0:7000\"] }}\nbackends = [{invalid}]\n"
+        ))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_route_timeout_overrides() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/reports"
+  backend = "backend:9000"
+  timeout = { first_byte_ms = 90000, total_ms = 120000 }
+
+  [[domains.routes]]
+  prefix = "/api"
+  backend = "backend:9000"
+  timeout = { connect_ms = 200, first_byte_ms = 1000 }
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    let routes = &config.domains.first().ok_or("domain missing")?.routes;
+    let timeouts: Vec<_> = routes.iter().map(|r| r.timeout).collect();
+    assert_eq!(
+        timeouts,
+        [
+            Some(RouteTimeoutConfig {
+                connect_ms: None,
+                first_byte_ms: Some(90_000),
+                total_ms: Some(120_000)
+            }),
+            Some(RouteTimeoutConfig {
+                connect_ms: Some(200),
+                first_byte_ms: Some(1000),
+                total_ms: None
+            }),
+        ]
+    );
+
+    let config: Config = toml::from_str(&toml.replace("connect_ms = 200", "connect_ms = 0"))?;
+    assert!(config.validate_cross_refs().is_err());
+    assert!(toml::from_str::<Config>(&toml.replace("total_ms", "body_ms")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_route_sticky_sessions() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "app-1:9000" }, { address = "app-2:9000" }]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/app"
+  backend = "app-1:9000"
+  sticky = { mode = "cookie", cookie = "app_backend", ttl_secs = 900 }
+
+  [[domains.routes]]
+  prefix = "/app"
+  backend = "app-2:9000"
+
+  [[domains.routes]]
+  prefix = "/ws"
+  backend = "app-1:9000"
+  sticky = { mode = "hash", hash_by = "ja4" }
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    let routes = &config.domains.first().ok_or("domain missing")?.routes;
+    let cookie = routes[0].sticky.as_ref().ok_or("sticky missing")?;
+    assert_eq!(cookie.mode, StickyMode::Cookie);
+    assert_eq!((cookie.cookie.as_str(), cookie.ttl_secs), ("app_backend", 900));
+    let hash = routes[2].sticky.as_ref().ok_or("sticky missing")?;
+    assert_eq!((hash.mode, hash.hash_by), (StickyMode::Hash, StickyHashKey::Ja4));
+    assert_eq!(hash.cookie, "huginn_affinity");
+
+    for (good, bad) in [("app_backend", "app backend"), ("ttl_secs = 900", "ttl_secs = 0")] {
+        let config: Config = toml::from_str(&toml.replace(good, bad))?;
+        assert!(config.validate_cross_refs().is_err(), "{bad} should be rejected");
+    }
+    assert!(toml::from_str::<Config>(&toml.replace("\"hash\"", "\"random\"")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_route_exact_and_regex_matchers() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "app:9000" }]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/healthz"
+  backend = "app:9000"
+  exact = true
+
+  [[domains.routes]]
+  prefix = "/"
+  backend = "app:9000"
+  regex = '^/users/(?P<id>[0-9]+)$'
+  match_priority = 5
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    let routes = &config.domains.first().ok_or("domain missing")?.routes;
+    assert!(routes[0].exact && routes[0].regex.is_none());
+    let regex = routes[1].regex.as_ref().ok_or("regex missing")?;
+    assert_eq!((regex.as_str(), routes[1].match_priority), ("^/users/(?P<id>[0-9]+)$", 5));
+
+    // An invalid pattern fails the load itself, not the first request.
+    let error = toml::from_str::<Config>(&toml.replace("[0-9]+)$", "[0-9+)$"))
+        .err()
+        .ok_or("invalid regex accepted")?;
+    assert!(error.to_string().contains("invalid regex"));
+
+    let both: Config =
+        toml::from_str(&toml.replace("exact = true", "exact = true\n  regex = '^/'"))?;
+    assert!(both.validate_cross_refs().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_route_method_and_header_conditions() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "app:9000" }, { address = "upload:9000" }]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/api/upload"
+  backend = "upload:9000"
+  methods = ["POST", "PUT"]
+  match_headers = [{ name = "content-type", regex = '^multipart/' }]
+
+  [[domains.routes]]
+  prefix = "/api"
+  backend = "app:9000"
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    let upload = &config.domains.first().ok_or("domain missing")?.routes[0];
+    assert_eq!(upload.methods, ["POST", "PUT"]);
+    assert_eq!(upload.match_headers[0].name, "content-type");
+
+    for (good, bad) in [
+        ("\"POST\", \"PUT\"", "\"post\""),
+        ("name = \"content-type\"", "name = \"content type\""),
+        ("regex = '^multipart/'", "regex = '^multipart/', equals = \"x\""),
+    ] {
+        let config: Config = toml::from_str(&toml.replace(good, bad))?;
+        assert!(config.validate_cross_refs().is_err(), "{bad} should be rejected");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_load_shedding_and_route_priority() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+
+[load_shedding]
+enabled = true
+latency_threshold_ms = 250
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/reports"
+  backend = "backend:9000"
+  priority = "low"
+
+  [[domains.routes]]
+  prefix = "/"
+  backend = "backend:9000"
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    assert_eq!(config.load_shedding.latency_threshold_ms, 250);
+    assert_eq!(config.load_shedding.shed_priority, RoutePriority::Low);
+    let priorities: Vec<RoutePriority> = config
+        .domains
+        .iter()
+        .flat_map(|d| d.routes.iter().map(|r| r.priority))
+        .collect();
+    assert_eq!(priorities, [RoutePriority::Low, RoutePriority::Normal]);
+    assert!(
+        RoutePriority::Low < RoutePriority::Normal && RoutePriority::Normal < RoutePriority::High
+    );
+
+    for bad in ["window_ms = 0", "max_shed_ratio = 0.0", "shed_step = 1.5"] {
+        let config: LoadSheddingConfig = toml::from_str(&format!("enabled = true\n{bad}"))?;
+        assert!(config.validate().is_err(), "{bad} should be rejected");
+    }
+    let disabled: LoadSheddingConfig = toml::from_str("window_ms = 0")?;
+    assert!(disabled.validate().is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_compression_global_and_route_override(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+
+[compression]
+algorithms = ["gzip"]
+min_size_bytes = 256
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/assets"
+  backend = "backend:9000"
+  compression = { enabled = false }
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    let global = config
+        .compression
+        .as_ref()
+        .ok_or("global compression missing")?;
+    assert!(global.enabled);
+    assert_eq!(global.algorithms, vec![CompressionAlgorithm::Gzip]);
+    assert_eq!(global.min_size_bytes, 256);
+    assert!(global.allows_content_type("application/json; charset=utf-8"));
+    let route = config
+        .domains
+        .first()
+        .and_then(|d| d.routes.first())
+        .ok_or("route missing")?;
+    assert_eq!(route.compression.as_ref().map(|c| c.enabled), Some(false));
+
+    assert!(CompressionConfig { algorithms: vec![], ..Default::default() }
+        .validate()
+        .is_err());
+    for bad in ["json", "*/*", "/json"] {
+        let config =
+            CompressionConfig { content_types: vec![bad.to_string()], ..Default::default() };
+        assert!(config.validate().is_err(), "{bad} should be rejected");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_route_cache_config_and_store_size() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+
+[cache]
+max_size_bytes = 1048576
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/static"
+  backend = "backend:9000"
+  cache = { default_ttl_secs = 300, stale_if_error_secs = 60 }
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    assert_eq!(config.cache.max_size_bytes, 1_048_576);
+    let route = config
+        .domains
+        .first()
+        .and_then(|d| d.routes.first())
+        .ok_or("route missing")?;
+    let cache = route.cache.as_ref().ok_or("route cache missing")?;
+    assert!(cache.enabled);
+    assert_eq!(cache.max_object_bytes, 1_048_576);
+    assert_eq!(cache.default_ttl_secs, 300);
+    assert_eq!(cache.stale_if_error_secs, 60);
+
+    let too_large = toml.replace("default_ttl_secs = 300", "max_object_bytes = 2097152");
+    let config: Config = toml::from_str(&too_large)?;
+    assert!(config.validate_cross_refs().is_err());
+    assert!(RouteCacheConfig { max_object_bytes: 0, ..Default::default() }
+        .validate()
+        .is_err());
+    assert!(CacheConfig { max_size_bytes: 0 }.validate().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_route_access_log_sampling() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/hot"
+  backend = "backend:9000"
+  access_log = { sample_rate = 0.01 }
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    let sampling = config
+        .domains
+        .first()
+        .and_then(|d| d.routes.first())
+        .and_then(|r| r.access_log)
+        .ok_or("route access_log missing")?;
+    assert_eq!(sampling.rate_for(200), 0.01);
+    assert_eq!(sampling.rate_for(302), 0.01);
+    assert_eq!(sampling.rate_for(404), 1.0);
+    assert_eq!(sampling.rate_for(503), 1.0);
+
+    let config: Config = toml::from_str(&toml.replace("0.01", "1.5"))?;
+    assert!(config.validate_cross_refs().is_err());
+    assert!(RouteAccessLogConfig { error_sample_rate: -0.1, ..Default::default() }
+        .validate()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_mtls_client_cert_reject_status_must_be_4xx() {
+    assert!(ClientCertConfig::default().validate().is_ok());
+    for reject_status in [200, 302, 500] {
+        let config = ClientCertConfig { reject_status, ..Default::default() };
+        assert!(config.validate().is_err(), "{reject_status} should be rejected");
+    }
+}
+
+#[test]
+fn test_fingerprint_headers_prefix_and_rename(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+
+[fingerprint.headers]
+prefix = "x-acme-"
+ja4 = "X-Client-JA4"
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    let headers = &config.fingerprint.headers;
+    assert_eq!(headers.name_for(names::TLS_JA4), "X-Client-JA4");
+    assert_eq!(headers.name_for(names::TLS_JA4_R), "x-acme-tls-ja4-r");
+    assert_eq!(headers.name_for(names::HTTP2_AKAMAI), "x-acme-http2-akamai");
+    assert_eq!(
+        headers.name_for(names::SPOOFING_DETECTED),
+        "x-acme-fingerprint-spoofing-detected"
+    );
+
+    let defaults = FingerprintHeadersConfig::default();
+    assert_eq!(defaults.name_for(names::TCP_SYN), names::TCP_SYN);
+    assert_eq!(defaults.name_for(names::TCP_OS), names::TCP_OS);
+    Ok(())
+}
+
+#[test]
+fn test_fingerprint_headers_rejects_invalid_and_colliding_names() {
+    let invalid = FingerprintHeadersConfig { ja4: Some("x tls".to_string()), ..Default::default() };
+    assert!(invalid.validate().is_err());
+
+    let duplicate = FingerprintHeadersConfig {
+        ja4: Some("x-fp".to_string()),
+        ja4_r: Some("X-FP".to_string()),
+        ..Default::default()
+    };
+    assert!(duplicate.validate().is_err());
+
+    let reserved = FingerprintHeadersConfig {
+        tcp_p0f: Some("x-forwarded-for".to_string()),
+        ..Default::default()
+    };
+    assert!(reserved.validate().is_err());
+
+    assert!(FingerprintHeadersConfig::default().validate().is_ok());
+}
+
+#[test]
+fn test_fingerprint_filter_deserialization() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let toml = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+
+[security.fingerprint_filter]
+status = 429
+deny = { ja4 = ["t13d1516h2_*"], tcp = ["4:64+0:0:1460:mss*20,10:mss,sok,ts,nop,ws:df,id+:0"] }
+allow = { akamai = ["1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"] }
+"#;
+    let config: Config = toml::from_str(toml)?;
+    config.validate_cross_refs()?;
+    let filter = &config.security.fingerprint_filter;
+    assert_eq!(filter.status, 429);
+    assert_eq!(filter.deny.ja4, vec!["t13d1516h2_*".to_string()]);
+    assert_eq!(filter.allow.akamai.len(), 1);
+    assert!(filter.deny.akamai.is_empty());
+
+    let config: Config = toml::from_str(&toml.replace("status = 429", "status = 502"))?;
+    assert!(config.validate_cross_refs().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_listen_acceptors() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "backend:9000" }]
+"#,
+    )?;
+    assert_eq!(config.listen.acceptors, 1);
+
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"], acceptors = 4 }
+backends = [{ address = "backend:9000" }]
+"#,
+    )?;
+    assert_eq!(config.listen.acceptors, 4);
+    assert!(config.validate_cross_refs().is_ok());
+
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"], acceptors = 0 }
+backends = [{ address = "backend:9000" }]
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_listen_listeners_overrides() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str(
+        r#"
+backends = [{ address = "backend:9000" }]
+
+[listen]
+addrs = ["0.0.0.0:443"]
+
+[[listen.listeners]]
+addr = "127.0.0.1:8080"
+tls = false
+fingerprint = { http_enabled = false, max_capture = 1024 }
+
+[tls]
+alpn = ["h2"]
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_ok());
+    assert_eq!(config.listen.address_count(), 2);
+    let listener = &config.listen.listeners[0];
+    assert_eq!(listener.tls, Some(false));
+    let fingerprint = listener.fingerprint.resolve(&config.fingerprint);
+    assert!(fingerprint.tls_enabled);
+    assert!(!fingerprint.http_enabled);
+    assert_eq!(fingerprint.max_capture, 1024);
+
+    // Listeners alone are enough; `addrs` is optional.
+    let config: Config = toml::from_str(
+        r#"
+backends = [{ address = "backend:9000" }]
+listen = { listeners = [{ addr = "127.0.0.1:8080" }] }
+"#,
+    )?;
+    assert!(config.listen.addrs.is_empty());
+    assert!(config.validate_cross_refs().is_ok());
+
+    // A UDP forwarder may share its port number with a TCP listener.
+    let config: Config = toml::from_str(
+        r#"
+backends = [{ address = "backend:9000" }]
+
+[listen]
+addrs = ["127.0.0.1:5353"]
+
+[[listen.udp]]
+addr = "127.0.0.1:5353"
+backend = "10.0.0.53:53"
+idle_timeout_secs = 30
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_ok());
+    assert_eq!(config.listen.udp[0].idle_timeout_secs, 30);
+    assert_eq!(config.listen.udp[0].max_sessions, 4096);
+
+    // TLS passthrough needs no [tls] section, but cannot also terminate TLS.
+    let passthrough = r#"
+[[listen.listeners]]
+addr = "0.0.0.0:443"
+passthrough = { routes = [{ sni = "*.example.com", backend = "10.0.0.1:443" }] }
+"#;
+    let config: Config =
+        toml::from_str(&format!("backends = [{{ address = \"backend:9000\" }}]\n{passthrough}"))?;
+    assert!(config.validate_cross_refs().is_ok());
+    let routes = config.listen.listeners[0]
+        .passthrough
+        .as_ref()
+        .map(|p| p.routes.len());
+    assert_eq!(routes, Some(1));
+    let config: Config = toml::from_str(&format!(
+        "backends = [{{ address = \"backend:9000\" }}]\n{passthrough}tls = true\n"
+    ))?;
+    assert!(config.validate_cross_refs().is_err());
+
+    for invalid in [
+        // tls = true without a [tls] section
+        r#"listen = { listeners = [{ addr = "127.0.0.1:8080", tls = true }] }"#,
+        // TCP SYN lookup can only be turned off per listener
+        r#"listen = { listeners = [{ addr = "127.0.0.1:8080", fingerprint = { tcp_enabled = true } }] }"#,
+        // same address in `addrs` and `listeners`
+        r#"listen = { addrs = ["127.0.0.1:8080"], listeners = [{ addr = "127.0.0.1:8080" }] }"#,
+        // UDP forwarder without a port, with no sessions, or listed twice
+        r#"listen = { udp = [{ addr = "127.0.0.1:5353", backend = "dns" }] }"#,
+        r#"listen = { udp = [{ addr = "127.0.0.1:5353", backend = "dns:53", max_sessions = 0 }] }"#,
+        r#"listen = { udp = [{ addr = "127.0.0.1:5353", backend = "a:53" }, { addr = "127.0.0.1:5353", backend = "b:53" }] }"#,
+        // passthrough without a route or default backend
+        r#"listen = { listeners = [{ addr = "127.0.0.1:8443", passthrough = {} }] }"#,
+    ] {
+        let config: Config =
+            toml::from_str(&format!("backends = [{{ address = \"backend:9000\" }}]\n{invalid}"))?;
+        assert!(config.validate_cross_refs().is_err(), "accepted: {invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_unix_socket_backends_and_listeners() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    assert_eq!(
+        unix_socket_path("unix:///var/run/app.sock"),
+        Some(std::path::Path::new("/var/run/app.sock"))
+    );
+    assert_eq!(
+        unix_socket_path("unix:/var/run/app.sock"),
+        unix_socket_path("unix:///var/run/app.sock")
+    );
+    assert_eq!(unix_socket_path("backend:9000"), None);
+
+    let config: Config = toml::from_str(
+        r#"
+backends = [{ address = "unix:///var/run/app.sock", health_check = { type = "tcp" } }]
+
+[listen]
+listeners = [{ addr = "unix:/run/huginn.sock", mode = 0o660 }]
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_ok());
+    assert_eq!(config.listen.listeners[0].addr, ListenAddr::Unix("/run/huginn.sock".into()));
+    assert_eq!(config.listen.listeners[0].mode, Some(0o660));
+
+    for invalid in [
+        r#"listen = {}
+backends = [{ address = "unix:///var/run/app.sock", tls = {} }]"#,
+        r#"listen = {}
+backends = [{ address = "unix:///var/run/app.sock", health_check = { type = "http", path = "/health" } }]"#,
+        r#"listen = {}
+backends = [{ address = "unix:" }]"#,
+        r#"backends = [{ address = "backend:9000" }]
+listen = { listeners = [{ addr = "127.0.0.1:8080", mode = 0o660 }] }"#,
+    ] {
+        let config: Config = toml::from_str(invalid)?;
+        assert!(config.validate_cross_refs().is_err(), "accepted: {invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_telemetry_admin_requires_port_and_token(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"] }
+telemetry = { metrics_port = 9090, admin = { enabled = true, token = "s3cret" } }
+"#,
+    )?;
+    assert!(config.telemetry.admin.enabled);
+    assert_eq!(config.telemetry.admin.token.expose(), "s3cret");
+    assert!(config.validate_cross_refs().is_ok());
+
+    for invalid in [
+        r#"telemetry = { admin = { enabled = true, token = "s3cret" } }"#,
+        r#"telemetry = { metrics_port = 9090, admin = { enabled = true } }"#,
+        r#"telemetry = { metrics_port = 9090, admin = { enabled = true, token = "  " } }"#,
+    ] {
+        let config: Config =
+            toml::from_str(&format!("listen = {{ addrs = [\"127.0.0.1:0\"] }}\n{invalid}"))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+
+    let config: Config = toml::from_str("listen = { addrs = [\"127.0.0.1:0\"] }\n")?;
+    assert!(!config.telemetry.admin.enabled);
+    assert!(config.validate_cross_refs().is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_telemetry_tracing_defaults_and_validation(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str("listen = { addrs = [\"127.0.0.1:0\"] }\n")?;
+    let tracing = &config.telemetry.tracing;
+    assert!(!tracing.enabled);
+    assert_eq!(tracing.otlp_endpoint, "http://localhost:4318/v1/traces");
+    assert_eq!(tracing.service_name, "huginn-proxy");
+    assert_eq!(tracing.sample_ratio, 1.0);
+
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"] }
+telemetry = { tracing = { enabled = true, otlp_endpoint = "https://otel.internal/v1/traces", sample_ratio = 0.1 } }
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_ok());
+
+    for invalid in [
+        r#"{ enabled = true, otlp_endpoint = "otel.internal:4318" }"#,
+        r#"{ enabled = true, sample_ratio = 2.0 }"#,
+        r#"{ enabled = true, service_name = "" }"#,
+        r#"{ enabled = true, export_timeout_ms = 0 }"#,
+    ] {
+        let config: Config = toml::from_str(&format!(
+            "listen = {{ addrs = [\"127.0.0.1:0\"] }}\ntelemetry = {{ tracing = {invalid} }}"
+        ))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_telemetry_metrics_defaults_and_validation(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str("listen = { addrs = [\"127.0.0.1:0\"] }\n")?;
+    assert_eq!(config.telemetry.metrics, MetricsConfig::default());
+    assert_eq!(config.telemetry.metrics.duration_buckets.first(), Some(&0.005));
+    assert_eq!(config.telemetry.metrics.max_label_values, 1000);
+
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"] }
+telemetry = { metrics = { duration_buckets = [0.01, 0.1, 1.0], max_label_values = 50 } }
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_ok());
+    assert_eq!(config.telemetry.metrics.duration_buckets, vec![0.01, 0.1, 1.0]);
+
+    for invalid in [
+        "{ duration_buckets = [] }",
+        "{ duration_buckets = [0.1, 0.1] }",
+        "{ duration_buckets = [1.0, 0.5] }",
+        "{ duration_buckets = [0.0, 1.0] }",
+        "{ max_label_values = 0 }",
+    ] {
+        let config: Config = toml::from_str(&format!(
+            "listen = {{ addrs = [\"127.0.0.1:0\"] }}\ntelemetry = {{ metrics = {invalid} }}"
+        ))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_telemetry_fingerprint_stats_validation(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str("listen = { addrs = [\"127.0.0.1:0\"] }\n")?;
+    let stats = &config.telemetry.fingerprint_stats;
+    assert!(!stats.enabled);
+    assert_eq!((stats.top_n, stats.window_secs), (20, 3600));
+
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"] }
+telemetry = { metrics_port = 9090, fingerprint_stats = { enabled = true, top_n = 50 } }
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_ok());
+
+    for invalid in [
+        "{ fingerprint_stats = { enabled = true } }",
+        "{ metrics_port = 9090, fingerprint_stats = { enabled = true, top_n = 0 } }",
+        "{ metrics_port = 9090, fingerprint_stats = { enabled = true, window_secs = 0 } }",
+    ] {
+        let config: Config = toml::from_str(&format!(
+            "listen = {{ addrs = [\"127.0.0.1:0\"] }}\ntelemetry = {invalid}"
+        ))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_access_log_defaults_and_validation() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let config: Config = toml::from_str("listen = { addrs = [\"127.0.0.1:0\"] }\n")?;
+    assert!(!config.access_log.enabled);
+    assert_eq!(config.access_log.output, AccessLogOutput::Stdout);
+    assert_eq!(config.access_log.fields, AccessLogField::ALL.to_vec());
+    assert_eq!(config.access_log.max_size_bytes, 100 * 1024 * 1024);
+    assert_eq!(config.access_log.max_files, 5);
+
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"] }
+access_log = { enabled = true, output = "file", path = "/var/log/huginn/access.log", fields = ["timestamp", "client_ip", "status", "ja4"] }
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_ok());
+    assert_eq!(
+        config.access_log.fields,
+        vec![
+            AccessLogField::Timestamp,
+            AccessLogField::ClientIp,
+            AccessLogField::Status,
+            AccessLogField::Ja4
+        ]
+    );
+
+    for invalid in [
+        r#"access_log = { enabled = true, output = "file" }"#,
+        r#"access_log = { enabled = true, output = "file", path = "a.log", max_files = 0 }"#,
+        r#"access_log = { enabled = true, fields = [] }"#,
+    ] {
+        let config: Config =
+            toml::from_str(&format!("listen = {{ addrs = [\"127.0.0.1:0\"] }}\n{invalid}"))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+    assert!(toml::from_str::<Config>(
+        "listen = { addrs = [\"127.0.0.1:0\"] }\naccess_log = { fields = [\"cookie\"] }\n"
+    )
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_shadowed_route_is_rejected() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config_with = |routes: &str| {
+        format!(
+            r#"
+listen = {{ addrs = ["0.0.0.0:7000"] }}
+backends = [{{ address = "a:9000" }}, {{ address = "b:9000" }}]
+
+[[domains]]
+host = "example.com"
+{routes}
+"#
+        )
+    };
+
+    // A higher-priority `/api` takes every request of `/api/v1`.
+    let config: Config = toml::from_str(&config_with(
+        r#"routes = [
+  { prefix = "/api", backend = "a:9000", match_priority = 1 },
+  { prefix = "/api/v1", backend = "b:9000" },
+]"#,
+    ))?;
+    let err = config
+        .validate_cross_refs()
+        .err()
+        .ok_or("shadowed route accepted")?
+        .to_string();
+    assert!(err.contains("route '/api/v1' is never matched"), "{err}");
+    assert!(err.contains("route '/api' (match_priority 1)"), "{err}");
+
+    for valid in [
+        // Longest prefix first: `/api/v1` is tried before `/api`.
+        r#"routes = [{ prefix = "/api", backend = "a:9000" }, { prefix = "/api/v1", backend = "b:9000" }]"#,
+        // Same matcher: a load-balance group.
+        r#"routes = [{ prefix = "/api", backend = "a:9000" }, { prefix = "/api", backend = "b:9000" }]"#,
+        // `/apiv2` is not under `/api`.
+        r#"routes = [{ prefix = "/api", backend = "a:9000", match_priority = 1 }, { prefix = "/apiv2", backend = "b:9000" }]"#,
+        // Conditional routes only take part of the requests.
+        r#"routes = [{ prefix = "/", backend = "a:9000", match_priority = 1, methods = ["POST"] }, { prefix = "/api", backend = "b:9000" }]"#,
+        // An exact route leaves the sub-paths to the prefix route.
+        r#"routes = [{ prefix = "/api", backend = "a:9000", exact = true, match_priority = 1 }, { prefix = "/api", backend = "b:9000" }]"#,
+    ] {
+        let config: Config = toml::from_str(&config_with(valid))?;
+        assert!(config.validate_cross_refs().is_ok(), "{valid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_fingerprint_whoami() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str("listen = { addrs = [\"127.0.0.1:0\"] }\n")?;
+    assert!(!config.fingerprint.whoami.enabled);
+    assert_eq!(config.fingerprint.whoami.path, "/._huginn/whoami");
+    assert_eq!(config.fingerprint.whoami.enabled_path(), None);
+
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["127.0.0.1:0"] }
+fingerprint = { whoami = { enabled = true, path = "/debug/me" } }
+"#,
+    )?;
+    assert!(config.validate_cross_refs().is_ok());
+    assert_eq!(config.fingerprint.whoami.enabled_path(), Some("/debug/me"));
+
+    for invalid in ["debug", "/debug?x=1"] {
+        let config: Config = toml::from_str(&format!(
+            "listen = {{ addrs = [\"127.0.0.1:0\"] }}\n\
+             fingerprint = {{ whoami = {{ enabled = true, path = \"{invalid}\" }} }}\n"
+        ))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_strict_http() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let base = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+"#;
+    let config: Config = toml::from_str(base)?;
+    assert!(!config.security.strict_http.enabled);
+    assert_eq!(config.security.strict_http.mode, StrictHttpMode::Enforce);
+
+    let config: Config = toml::from_str(&format!(
+        "{base}[security.strict_http]\nenabled = true\nmode = \"detect\"\n"
+    ))?;
+    assert!(config.security.strict_http.enabled);
+    assert_eq!(config.security.strict_http.mode, StrictHttpMode::Detect);
+
+    assert!(
+        toml::from_str::<Config>(&format!("{base}[security.strict_http]\nmode = \"log\"\n"))
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_tarpit() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let base = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+"#;
+    let config: Config = toml::from_str(base)?;
+    assert!(!config.security.tarpit.is_active());
+    assert_eq!(config.security.tarpit.delay_ms, 10_000);
+    assert_eq!(config.security.tarpit.max_concurrent, 256);
+
+    let config: Config = toml::from_str(&format!(
+        "{base}[security.tarpit]\nips = [\"203.0.113.0/24\"]\nja4 = [\"t13d*\"]\ndelay_ms = 5000\n"
+    ))?;
+    assert_eq!(config.security.tarpit.ips.len(), 1);
+    assert_eq!(config.security.tarpit.delay_ms, 5000);
+    assert!(config.validate_cross_refs().is_ok());
+
+    let config: Config = toml::from_str(&format!("{base}[security.tarpit]\nja4 = [\"*\"]\n"))?;
+    assert!(config.validate_cross_refs().is_err());
+    assert!(
+        toml::from_str::<Config>(&format!("{base}[security.tarpit]\nips = [\"not-an-ip\"]\n"))
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_http2_settings() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let base = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "backend:9000" }]
+"#;
+    let config: Config = toml::from_str(base)?;
+    assert_eq!(config.http2, Http2Config::default());
+    assert_eq!(config.backend_pool.http2, BackendHttp2Config::default());
+
+    let config: Config = toml::from_str(&format!(
+        "{base}[http2]\ninitial_stream_window_size = 4194304\n\
+         initial_connection_window_size = 8388608\nmax_concurrent_streams = 500\n\
+         max_frame_size = 65536\nkeep_alive_interval_ms = 0\n\
+         [backend_pool.http2]\nadaptive_window = true\nkeep_alive_interval_ms = 30000\n\
+         keep_alive_while_idle = true\n"
+    ))?;
+    config.validate_cross_refs()?;
+    assert_eq!(config.http2.initial_stream_window_size, Some(4_194_304));
+    assert_eq!(config.http2.max_concurrent_streams, Some(500));
+    assert_eq!(config.http2.keep_alive_interval_ms, Some(0));
+    assert!(config.backend_pool.http2.adaptive_window);
+    assert_eq!(config.backend_pool.http2.keep_alive_interval_ms, Some(30_000));
+
+    // Backends with their own `pool` table keep the shared HTTP/2 settings.
+    let resolved = BackendPoolLimits::default().resolve(&config.backend_pool);
+    assert_eq!(resolved.http2, config.backend_pool.http2);
+
+    for invalid in [
+        "[http2]\nmax_frame_size = 1024\n",
+        "[http2]\ninitial_stream_window_size = 2147483648\n",
+        "[http2]\nmax_concurrent_streams = 0\n",
+        "[http2]\nadaptive_window = true\ninitial_connection_window_size = 1048576\n",
+        "[backend_pool.http2]\nkeep_alive_interval_ms = 0\n",
+        "[backend_pool.http2]\nmax_frame_size = 16777216\n",
+    ] {
+        let config: Config = toml::from_str(&format!("{base}{invalid}"))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_tcp_socket_options() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let base = r#"
+[listen]
+addrs = ["0.0.0.0:7000"]
+
+[[backends]]
+address = "api:80"
+tcp = { nodelay = false, recv_buffer_size = 1048576 }
+
+[[backends]]
+address = "web:80"
+"#;
+    let config: Config = toml::from_str(base)?;
+    assert_eq!(config.listen.tcp, TcpConfig::default());
+    assert_eq!(config.backend_pool.tcp, TcpConfig::default());
+
+    let config: Config = toml::from_str(&format!(
+        "{base}[listen.tcp]\nnodelay = true\nkeepalive_time_secs = 60\n\
+         keepalive_interval_secs = 10\nkeepalive_retries = 4\nsend_buffer_size = 262144\n\
+         [backend_pool.tcp]\nnodelay = true\nkeepalive_time_secs = 30\n"
+    ))?;
+    config.validate_cross_refs()?;
+    assert_eq!(config.listen.tcp.nodelay, Some(true));
+    assert_eq!(config.listen.tcp.keepalive_retries, Some(4));
+    assert!(config.listen.tcp.keepalive_configured());
+    assert_eq!(config.backend_pool.tcp.keepalive_time_secs, Some(30));
+
+    // A backend's own options win; unset ones come from `[backend_pool.tcp]`.
+    let own = config.backends[0].tcp.ok_or("api has a tcp table")?;
+    let resolved = own.or(&config.backend_pool.tcp);
+    assert_eq!(resolved.nodelay, Some(false));
+    assert_eq!(resolved.recv_buffer_size, Some(1_048_576));
+    assert_eq!(resolved.keepalive_time_secs, Some(30));
+    assert!(config.backends[1].tcp.is_none());
+
+    for invalid in [
+        "[listen.tcp]\nkeepalive_time_secs = 0\n",
+        "[listen.tcp]\nsend_buffer_size = 0\n",
+        "[backend_pool.tcp]\nkeepalive_retries = 0\n",
+        "[[backends]]\naddress = \"unix:/run/app.sock\"\ntcp = { nodelay = true }\n",
+        "[[backends]]\naddress = \"db:80\"\ntcp = { keepalive_interval_secs = 0 }\n",
+    ] {
+        let config: Config = toml::from_str(&format!("{base}{invalid}"))?;
+        assert!(config.validate_cross_refs().is_err(), "{invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_bandwidth_on_routes_and_backends() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let base = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+
+[[backends]]
+address = "files:80"
+bandwidth = { bytes_per_sec = 10485760, burst_bytes = 20971520 }
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/downloads"
+  backend = "files:80"
+  bandwidth = { bytes_per_sec = 1048576, limit_by = "ip" }
+"#;
+    let config: Config = toml::from_str(base)?;
+    config.validate_cross_refs()?;
+    let backend = config.backends[0]
+        .bandwidth
+        .ok_or("backend bandwidth missing")?;
+    assert_eq!((backend.bytes_per_sec, backend.burst()), (10_485_760, 20_971_520));
+    assert_eq!(backend.limit_by, BandwidthKey::Shared);
+    let route = config.domains.first().ok_or("domain missing")?.routes[0]
+        .bandwidth
+        .ok_or("route bandwidth missing")?;
+    // The burst defaults to one second worth of bytes.
+    assert_eq!((route.bytes_per_sec, route.burst()), (1_048_576, 1_048_576));
+    assert_eq!(route.limit_by, BandwidthKey::Ip);
+
+    for (from, to) in [
+        ("bytes_per_sec = 1048576,", "bytes_per_sec = 0,"),
+        ("burst_bytes = 20971520", "burst_bytes = 0"),
+    ] {
+        let config: Config = toml::from_str(&base.replace(from, to))?;
+        assert!(config.validate_cross_refs().is_err(), "{to}");
+    }
+    assert!(toml::from_str::<Config>(&base.replace("\"ip\"", "\"route\"")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_backend_queue_and_priority_headers() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let base = r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+
+[[backends]]
+address = "api:80"
+max_in_flight = 10
+queue = { max_queued = 100 }
+
+[[domains]]
+  [[domains.routes]]
+  prefix = "/api"
+  backend = "api:80"
+  priority = "low"
+  priority_headers = [{ header = { name = "x-plan", equals = "paid" }, priority = "high" }]
+"#;
+    let config: Config = toml::from_str(base)?;
+    config.validate_cross_refs()?;
+    let queue = config.backends[0].queue.ok_or("queue missing")?;
+    assert_eq!((queue.max_queued, queue.timeout_ms), (100, 5000));
+
+    let route = &config.domains.first().ok_or("domain missing")?.routes[0];
+    let mut headers = http::HeaderMap::new();
+    assert_eq!(route.request_priority(&headers), RoutePriority::Low);
+    headers.insert("x-plan", http::HeaderValue::from_static("free"));
+    assert_eq!(route.request_priority(&headers), RoutePriority::Low);
+    headers.insert("x-plan", http::HeaderValue::from_static("paid"));
+    assert_eq!(route.request_priority(&headers), RoutePriority::High);
+
+    for (from, to) in [
+        ("max_queued = 100", "max_queued = 0"),
+        ("max_queued = 100", "max_queued = 100, timeout_ms = 0"),
+        ("max_in_flight = 10\n", ""),
+        ("name = \"x-plan\"", "name = \"x plan\""),
+    ] {
+        let config: Config = toml::from_str(&base.replace(from, to))?;
+        assert!(config.validate_cross_refs().is_err(), "{to}");
+    }
+    Ok(())
+}
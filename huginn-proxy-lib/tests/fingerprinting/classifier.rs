@@ -0,0 +1,77 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use huginn_proxy_lib::config::FingerprintHeadersConfig;
+use huginn_proxy_lib::fingerprinting::{
+    names, FingerprintClassifier, FingerprintHeaderNames, FingerprintSet, SharedClassifier, Verdict,
+};
+
+/// Denies requests without a TLS fingerprint and tags curl user agents.
+struct UserAgentClassifier;
+
+impl FingerprintClassifier for UserAgentClassifier {
+    fn classify(&self, set: &FingerprintSet<'_>) -> Verdict {
+        if set.ja4.is_none() && set.route == "/api" {
+            return Verdict::Deny(StatusCode::FORBIDDEN);
+        }
+        let user_agent = set
+            .headers
+            .get(http::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok());
+        match user_agent {
+            Some(ua) if ua.starts_with("curl/") => Verdict::Tag("cli".to_string()),
+            _ => Verdict::Allow,
+        }
+    }
+}
+
+fn set<'a>(method: &'a Method, route: &'a str, headers: &'a HeaderMap) -> FingerprintSet<'a> {
+    FingerprintSet {
+        peer: SocketAddr::from(([127, 0, 0, 1], 40000)),
+        method,
+        host: "example.com",
+        path: route,
+        route,
+        headers,
+        ja4: None,
+        akamai: None,
+        tcp: None,
+        connection: None,
+    }
+}
+
+#[test]
+fn test_classifier_verdicts_through_shared_handle() {
+    let classifier: SharedClassifier = Arc::new(UserAgentClassifier);
+    let method = Method::GET;
+    let mut headers = HeaderMap::new();
+
+    assert_eq!(classifier.classify(&set(&method, "/", &headers)), Verdict::Allow);
+    assert_eq!(
+        classifier.classify(&set(&method, "/api", &headers)),
+        Verdict::Deny(StatusCode::FORBIDDEN)
+    );
+
+    headers.insert(http::header::USER_AGENT, HeaderValue::from_static("curl/8.5.0"));
+    assert_eq!(
+        classifier.classify(&set(&method, "/", &headers)),
+        Verdict::Tag("cli".to_string())
+    );
+}
+
+#[test]
+fn test_verdict_labels() {
+    assert_eq!(Verdict::Allow.label(), "allow");
+    assert_eq!(Verdict::Deny(StatusCode::TOO_MANY_REQUESTS).label(), "deny");
+    assert_eq!(Verdict::Tag(String::new()).label(), "tag");
+}
+
+#[test]
+fn test_classification_header_is_reserved() {
+    let config = FingerprintHeadersConfig {
+        ja4: Some(names::CLASSIFICATION.to_string()),
+        ..Default::default()
+    };
+    assert!(FingerprintHeaderNames::from_config(&config).is_err());
+}
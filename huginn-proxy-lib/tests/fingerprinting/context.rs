@@ -0,0 +1,95 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use http::{HeaderMap, HeaderValue};
+use huginn_proxy_lib::config::FingerprintFormat;
+use huginn_proxy_lib::fingerprinting::{
+    names, pack_context, tls_info, FingerprintHeaderNames, HeaderSigner,
+};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+
+fn injected() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers
+        .insert(names::TLS_JA4, HeaderValue::from_static("t13d1516h2_8daaf6152771_02713d6af862"));
+    headers.insert(names::HTTP2_AKAMAI, HeaderValue::from_static("1:65536;2:0|15663105|0|m,a,s,p"));
+    headers.insert(names::CLASSIFICATION, HeaderValue::from_static("say \"hi\""));
+    headers.insert(tls_info::VERSION, HeaderValue::from_static("TLSv1_3"));
+    headers.insert("user-agent", HeaderValue::from_static("curl/8.0"));
+    headers
+}
+
+#[test]
+fn headers_format_leaves_the_headers_alone() {
+    let mut headers = injected();
+    pack_context(
+        &mut headers,
+        FingerprintFormat::Headers,
+        &FingerprintHeaderNames::default(),
+        CLIENT,
+        None,
+    );
+    assert_eq!(headers, injected());
+}
+
+#[test]
+fn structured_format_packs_every_signal_into_one_dictionary() -> TestResult {
+    let mut headers = injected();
+    pack_context(
+        &mut headers,
+        FingerprintFormat::Structured,
+        &FingerprintHeaderNames::default(),
+        CLIENT,
+        None,
+    );
+    assert_eq!(
+        headers.get(names::CONTEXT).ok_or("missing context")?,
+        "client_ip=\"203.0.113.7\", ja4=\"t13d1516h2_8daaf6152771_02713d6af862\", \
+         akamai=\"1:65536;2:0|15663105|0|m,a,s,p\", class=\"say \\\"hi\\\"\", \
+         tls_version=\"TLSv1_3\""
+    );
+    assert!(headers.get(names::TLS_JA4).is_none());
+    assert!(headers.get(tls_info::VERSION).is_none());
+    assert_eq!(headers.get("user-agent").ok_or("user-agent dropped")?, "curl/8.0");
+    Ok(())
+}
+
+#[test]
+fn jwt_format_signs_the_claims_with_the_active_key() -> TestResult {
+    let signer =
+        HeaderSigner::new([("k1".to_string(), b"0123456789abcdef0123456789abcdef".to_vec(), 0)]);
+    let mut headers = injected();
+    pack_context(
+        &mut headers,
+        FingerprintFormat::Jwt,
+        &FingerprintHeaderNames::default(),
+        CLIENT,
+        Some(&signer),
+    );
+    let token = headers
+        .get(names::CONTEXT)
+        .ok_or("missing context")?
+        .to_str()?;
+    let parts: Vec<&str> = token.split('.').collect();
+    assert_eq!(parts.len(), 3, "{token}");
+    // base64url of `{"alg":"HS256","typ":"JWT","kid":"k1"}`.
+    assert_eq!(parts[0], "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCIsImtpZCI6ImsxIn0");
+    // HMAC-SHA256 tag: 32 bytes, 43 unpadded characters.
+    assert_eq!(parts[2].len(), 43);
+    assert!(headers.get(names::TLS_JA4).is_none());
+
+    // Without a signer the fingerprints are dropped rather than sent unsigned.
+    let mut headers = injected();
+    pack_context(
+        &mut headers,
+        FingerprintFormat::Jwt,
+        &FingerprintHeaderNames::default(),
+        CLIENT,
+        None,
+    );
+    assert!(headers.get(names::CONTEXT).is_none());
+    assert!(headers.get(names::TLS_JA4).is_none());
+    Ok(())
+}
@@ -112,9 +112,6 @@ async fn test_capturing_stream_max_capture() -> Result<(), Box<dyn std::error::E
     Ok(())
 }
 
-// Note: process_captured_bytes has been removed - fingerprint extraction now happens inline in CapturingStream
-// These tests are no longer needed as the functionality is tested through CapturingStream tests
-
 #[tokio::test]
 async fn test_capturing_stream_write_passthrough(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
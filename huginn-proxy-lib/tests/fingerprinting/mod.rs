@@ -1,3 +1,8 @@
+mod classifier;
+mod context;
 mod edge_cases;
 mod http2_extractor;
+mod os_match;
+mod signing;
+mod syn_capture;
 mod tls_extractor;
@@ -0,0 +1,53 @@
+use huginn_proxy_lib::fingerprinting::syn_capture::parse_ipv4_syn;
+use huginn_proxy_lib::fingerprinting::{match_os, OsGuess};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[rustfmt::skip]
+const LINUX_OPTIONS: [u8; 20] = [
+    2, 4, 0x05, 0xb4,               // MSS = 1460
+    4, 2,                           // SACK permitted
+    8, 10, 0, 0, 0, 1, 0, 0, 0, 0, // Timestamps
+    1,                              // NOP
+    3, 3, 7,                        // WS = 7
+];
+
+/// IPv4 SYN from 203.0.113.5:51000 with DF set, a non-zero IP id, TTL 64 and a
+/// `mss*20` window: what a current Linux kernel sends.
+fn linux_syn() -> Vec<u8> {
+    let mut packet =
+        vec![0x45, 0, 0, 0, 0x12, 0x34, 0x40, 0, 64, 6, 0, 0, 203, 0, 113, 5, 10, 0, 0, 1];
+    packet.extend_from_slice(&51000u16.to_be_bytes());
+    packet.extend_from_slice(&443u16.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0]);
+    packet.extend_from_slice(&[10 * 16, 0x02]);
+    packet.extend_from_slice(&(1460u16 * 20).to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0, 0]);
+    packet.extend_from_slice(&LINUX_OPTIONS);
+    packet
+}
+
+#[test]
+fn linux_syn_is_matched_with_confidence() -> TestResult {
+    let syn = parse_ipv4_syn(&linux_syn()).ok_or("SYN not parsed")?;
+    let observation = syn.result.observation().ok_or("expected a hit")?;
+    let guess = match_os(observation).ok_or("no OS matched")?;
+    assert_eq!(guess.name, "Linux", "{guess:?}");
+    assert!(guess.confidence > 0.0 && guess.confidence <= 1.0, "{guess:?}");
+    assert!(guess.to_string().starts_with("Linux"), "{guess}");
+    Ok(())
+}
+
+#[test]
+fn header_value_carries_variant_confidence_and_generic() {
+    let guess = OsGuess {
+        name: "Linux".to_string(),
+        variant: Some("3.11 and newer".to_string()),
+        generic: false,
+        confidence: 0.875,
+    };
+    assert_eq!(guess.to_string(), "Linux 3.11 and newer; confidence=0.88");
+
+    let generic = OsGuess { variant: None, generic: true, confidence: 1.0, ..guess };
+    assert_eq!(generic.to_string(), "Linux; confidence=1.00; generic");
+}
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+
+use aws_lc_rs::hmac;
+use http::{HeaderMap, HeaderValue};
+use huginn_proxy_lib::fingerprinting::{
+    names, FingerprintHeaderNames, HeaderSigner, SignedRequest,
+};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const OLD_SECRET: &[u8] = b"0123456789abcdef0123456789abcdef-old";
+const NEW_SECRET: &[u8] = b"0123456789abcdef0123456789abcdef-new";
+const REQUEST: SignedRequest<'static> =
+    SignedRequest { method: "GET", authority: "api.example.com", path: "/v1/users?page=2" };
+
+fn signer() -> HeaderSigner {
+    HeaderSigner::new([
+        ("2026-04".to_string(), OLD_SECRET.to_vec(), 0),
+        ("2026-10".to_string(), NEW_SECRET.to_vec(), 1_790_000_000),
+    ])
+}
+
+/// `name=value` fields of the signature header.
+fn field<'a>(signature: &'a str, name: &str) -> Option<&'a str> {
+    signature
+        .split(';')
+        .find_map(|part| part.strip_prefix(name)?.strip_prefix('='))
+}
+
+fn hex_hmac(secret: &[u8], message: &[u8]) -> String {
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, secret), message);
+    tag.as_ref().iter().fold(String::new(), |mut out, b| {
+        let _ = write!(out, "{b:02x}");
+        out
+    })
+}
+
+#[test]
+fn signs_the_fingerprint_headers_present() -> TestResult {
+    let mut headers = HeaderMap::new();
+    headers
+        .insert(names::TLS_JA4, HeaderValue::from_static("t13d1516h2_8daaf6152771_02713d6af862"));
+    headers.insert(names::HTTP2_AKAMAI, HeaderValue::from_static("1:65536;2:0|15663105|0|m,a,s,p"));
+    headers.insert("x-tls-version", HeaderValue::from_static("TLSv1_3"));
+    headers.insert("user-agent", HeaderValue::from_static("curl/8.0"));
+    headers.insert(names::SIGNATURE, HeaderValue::from_static("forged"));
+
+    signer().sign_at(REQUEST, &mut headers, &FingerprintHeaderNames::default(), 1_760_000_000);
+    let signature = headers
+        .get(names::SIGNATURE)
+        .ok_or("missing signature")?
+        .to_str()?;
+
+    assert!(signature.starts_with("v1;"), "{signature}");
+    assert_eq!(field(signature, "kid"), Some("2026-04"));
+    assert_eq!(field(signature, "ts"), Some("1760000000"));
+    assert_eq!(field(signature, "h"), Some("x-tls-ja4,x-http2-akamai,x-tls-version"));
+    let message = "v1\n2026-04\n1760000000\nGET\napi.example.com\n/v1/users?page=2\n\
+                   x-tls-ja4:t13d1516h2_8daaf6152771_02713d6af862\n\
+                   x-http2-akamai:1:65536;2:0|15663105|0|m,a,s,p\n\
+                   x-tls-version:TLSv1_3\n";
+    assert_eq!(field(signature, "sig"), Some(hex_hmac(OLD_SECRET, message.as_bytes()).as_str()));
+    Ok(())
+}
+
+#[test]
+fn the_latest_key_past_its_not_before_signs() {
+    let signer = signer();
+    assert_eq!(signer.active_key_id(1_789_999_999), Some("2026-04"));
+    assert_eq!(signer.active_key_id(1_790_000_000), Some("2026-10"));
+
+    let future_only = HeaderSigner::new([("next".to_string(), NEW_SECRET.to_vec(), 100)]);
+    let mut headers = HeaderMap::new();
+    headers.insert(names::SIGNATURE, HeaderValue::from_static("forged"));
+    future_only.sign_at(REQUEST, &mut headers, &FingerprintHeaderNames::default(), 99);
+    assert!(headers.get(names::SIGNATURE).is_none());
+}
+
+/// Signature header of a request with one fingerprint header.
+fn signature_for(request: SignedRequest<'_>) -> Option<String> {
+    let mut headers = HeaderMap::new();
+    headers
+        .insert(names::TLS_JA4, HeaderValue::from_static("t13d1516h2_8daaf6152771_02713d6af862"));
+    signer().sign_at(request, &mut headers, &FingerprintHeaderNames::default(), 1_760_000_000);
+    headers
+        .get(names::SIGNATURE)?
+        .to_str()
+        .ok()
+        .map(String::from)
+}
+
+#[test]
+fn the_signature_is_bound_to_the_request_line() -> TestResult {
+    let original = signature_for(REQUEST).ok_or("missing signature")?;
+    for replayed in [
+        SignedRequest { method: "POST", ..REQUEST },
+        SignedRequest { authority: "admin.example.com", ..REQUEST },
+        SignedRequest { path: "/v1/users?page=3", ..REQUEST },
+    ] {
+        let signature = signature_for(replayed).ok_or("missing signature")?;
+        assert_ne!(signature, original, "{replayed:?}");
+    }
+    Ok(())
+}
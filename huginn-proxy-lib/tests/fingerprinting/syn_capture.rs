@@ -0,0 +1,108 @@
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use huginn_proxy_lib::config::{FingerprintConfig, TcpCapture};
+use huginn_proxy_lib::fingerprinting::syn_capture::{parse_ipv4_syn, SynCapture};
+use huginn_proxy_lib::SynResult;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[rustfmt::skip]
+const LINUX_OPTIONS: [u8; 20] = [
+    2, 4, 0x05, 0xb4,               // MSS = 1460
+    4, 2,                           // SACK permitted
+    8, 10, 0, 0, 0, 1, 0, 0, 0, 0, // Timestamps
+    1,                              // NOP
+    3, 3, 7,                        // WS = 7
+];
+
+/// IPv4 SYN from 203.0.113.5:51000 to 10.0.0.1:443 with DF set, a non-zero IP id and TTL 64.
+fn syn_packet(tcp_flags: u8, options: &[u8]) -> Vec<u8> {
+    let doff = u8::try_from(options.len().saturating_add(20).saturating_div(4)).unwrap_or(5);
+    let mut packet =
+        vec![0x45, 0, 0, 0, 0x12, 0x34, 0x40, 0, 64, 6, 0, 0, 203, 0, 113, 5, 10, 0, 0, 1];
+    packet.extend_from_slice(&51000u16.to_be_bytes());
+    packet.extend_from_slice(&443u16.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0]);
+    packet.extend_from_slice(&[doff.saturating_mul(16), tcp_flags]);
+    packet.extend_from_slice(&64240u16.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0, 0]);
+    packet.extend_from_slice(options);
+    packet
+}
+
+#[test]
+fn ipv4_syn_yields_signature() -> TestResult {
+    let syn = parse_ipv4_syn(&syn_packet(0x02, &LINUX_OPTIONS)).ok_or("SYN not parsed")?;
+    assert_eq!(syn.source, SocketAddrV4::new(Ipv4Addr::new(203, 0, 113, 5), 51000));
+    assert_eq!(syn.destination, SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 443));
+
+    let signature = syn
+        .result
+        .observation()
+        .ok_or("expected a hit")?
+        .to_string();
+    let parts: Vec<&str> = signature.split(':').collect();
+    assert_eq!(parts.len(), 8, "{signature}");
+    assert_eq!(parts[0], "4", "{signature}");
+    assert_eq!(parts[2], "0", "no IP options: {signature}");
+    assert_eq!(parts[3], "1460", "{signature}");
+    assert!(parts[5].starts_with("mss,sok,ts,nop,ws"), "{signature}");
+    assert!(parts[6].contains("df"), "{signature}");
+    Ok(())
+}
+
+#[test]
+fn non_syn_and_truncated_packets_are_skipped() {
+    // SYN-ACK, plain ACK, truncated TCP header, UDP.
+    assert!(parse_ipv4_syn(&syn_packet(0x12, &LINUX_OPTIONS)).is_none());
+    assert!(parse_ipv4_syn(&syn_packet(0x10, &[])).is_none());
+    let packet = syn_packet(0x02, &[]);
+    assert!(parse_ipv4_syn(packet.get(..30).unwrap_or_default()).is_none());
+    let mut udp = syn_packet(0x02, &[]);
+    if let Some(protocol) = udp.get_mut(9) {
+        *protocol = 17;
+    }
+    assert!(parse_ipv4_syn(&udp).is_none());
+}
+
+#[test]
+fn malformed_options_are_reported() -> TestResult {
+    // MSS option claiming 8 bytes in a 4-byte option area.
+    let syn = parse_ipv4_syn(&syn_packet(0x02, &[2, 8, 0x05, 0xb4])).ok_or("SYN not parsed")?;
+    assert!(matches!(syn.result, SynResult::Malformed));
+    Ok(())
+}
+
+#[test]
+fn capture_hands_each_syn_out_once() -> TestResult {
+    let capture = SynCapture::new(1);
+    let syn = parse_ipv4_syn(&syn_packet(0x02, &LINUX_OPTIONS)).ok_or("SYN not parsed")?;
+    capture.record(syn.clone());
+    assert_eq!(capture.len(), 1);
+
+    // A dual-stack listener reports the client as an IPv4-mapped address.
+    let mapped: SocketAddr = "[::ffff:203.0.113.5]:51000".parse()?;
+    assert!(matches!(capture.take(mapped), SynResult::Hit(_)));
+    assert!(matches!(capture.take(mapped), SynResult::Miss));
+    assert!(capture.is_empty());
+
+    // Full: a SYN from another client is dropped while the first one is fresh.
+    capture.record(syn);
+    let mut other = parse_ipv4_syn(&syn_packet(0x02, &[])).ok_or("SYN not parsed")?;
+    other.source.set_port(51001);
+    capture.record(other);
+    assert_eq!(capture.len(), 1);
+    assert!(matches!(capture.take("203.0.113.5:51001".parse()?), SynResult::Miss));
+    assert!(matches!(capture.take("[2001:db8::1]:51000".parse()?), SynResult::Miss));
+    Ok(())
+}
+
+#[test]
+fn tcp_capture_defaults_to_ebpf() -> TestResult {
+    assert_eq!(FingerprintConfig::default().tcp_capture, TcpCapture::Ebpf);
+    let config: FingerprintConfig =
+        toml::from_str("tcp_enabled = true\ntcp_capture = \"raw_socket\"")?;
+    assert_eq!(config.tcp_capture, TcpCapture::RawSocket);
+    assert!(toml::from_str::<FingerprintConfig>("tcp_capture = \"pcap\"").is_err());
+    Ok(())
+}
@@ -16,3 +16,168 @@ async fn test_read_client_hello() -> Result<(), Box<dyn std::error::Error + Send
     // The actual function call would require a valid TLS handshake
     Ok(())
 }
+
+#[tokio::test]
+async fn test_read_client_hello_stops_at_record_end_and_replays(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use huginn_proxy_lib::proxy::connection::PrefixedStream;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Handshake record header announcing a 4-byte body (not a valid ClientHello), then data
+    // that belongs to the TLS layer after the record.
+    let record = [0x16, 0x03, 0x01, 0x00, 0x04, 0x01, 0x00, 0x00, 0x00];
+    let (mut client, mut server) = tokio::io::duplex(64);
+    client.write_all(&record).await?;
+
+    let (prefix, fingerprints) =
+        huginn_proxy_lib::read_client_hello(&mut server, huginn_proxy_lib::Metrics::new_noop())
+            .await?;
+    assert_eq!(&prefix[..], &record[..]);
+    assert!(fingerprints.is_none());
+
+    client.write_all(b"after").await?;
+    drop(client);
+    let mut replayed = Vec::new();
+    PrefixedStream::new(prefix, server)
+        .read_to_end(&mut replayed)
+        .await?;
+    assert_eq!(&replayed[..record.len()], &record[..]);
+    assert_eq!(&replayed[record.len()..], b"after");
+    Ok(())
+}
+
+/// Handshake record holding a minimal ClientHello with the given extension types (empty bodies).
+fn client_hello(extensions: &[u16]) -> Vec<u8> {
+    let mut ext = Vec::new();
+    for &ext_type in extensions {
+        ext.extend_from_slice(&ext_type.to_be_bytes());
+        ext.extend_from_slice(&[0, 0]);
+    }
+    let mut hello = vec![0x03, 0x03];
+    hello.extend_from_slice(&[0u8; 32]);
+    hello.push(0); // session id
+    hello.extend_from_slice(&[0, 2, 0x13, 0x01]); // TLS_AES_128_GCM_SHA256
+    hello.extend_from_slice(&[1, 0]); // null compression
+    hello.extend_from_slice(&u16::try_from(ext.len()).unwrap_or_default().to_be_bytes());
+    hello.extend_from_slice(&ext);
+
+    let hello_len = u32::try_from(hello.len()).unwrap_or_default().to_be_bytes();
+    let mut handshake = vec![0x01];
+    handshake.extend_from_slice(hello_len.get(1..).unwrap_or_default());
+    handshake.extend_from_slice(&hello);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend_from_slice(
+        &u16::try_from(handshake.len())
+            .unwrap_or_default()
+            .to_be_bytes(),
+    );
+    record.extend_from_slice(&handshake);
+    record
+}
+
+#[test]
+fn test_client_hello_early_data_detection() {
+    use huginn_proxy_lib::fingerprinting::client_hello_offers_early_data;
+
+    // server_name, supported_versions, early_data, pre_shared_key.
+    let resumed = client_hello(&[0x0000, 0x002b, 0x002a, 0x0029]);
+    assert!(client_hello_offers_early_data(&resumed));
+    assert!(!client_hello_offers_early_data(&client_hello(&[0x0000, 0x002b])));
+    assert!(!client_hello_offers_early_data(&client_hello(&[])));
+
+    // Truncated inside the extensions block, or not a handshake record at all.
+    let truncated = resumed
+        .get(..resumed.len().saturating_sub(6))
+        .unwrap_or_default();
+    assert!(!client_hello_offers_early_data(truncated));
+    assert!(!client_hello_offers_early_data(b"GET / HTTP/1.1\r\n\r\n"));
+    assert!(!client_hello_offers_early_data(&[]));
+}
+
+/// ClientHello captured from reqwest/rustls (see `capture_fixtures`), connecting by IP: no SNI.
+const REQWEST_CLIENT_HELLO: &[u8] =
+    include_bytes!("../../../benches/fixtures/clienthello_reqwest.bin");
+
+/// Offset of the extensions length in [`REQWEST_CLIENT_HELLO`].
+const REQWEST_EXTENSIONS_LEN_AT: usize = 100;
+
+/// `encrypted_client_hello` (outer): cipher suite, config id, empty enc and payload.
+const ECH_EXTENSION: [u8; 14] = [0xfe, 0x0d, 0, 10, 0, 0, 1, 0, 1, 0x2a, 0, 0, 0, 0];
+
+/// GREASE extension (RFC 8701) with an empty body.
+const GREASE_EXTENSION: [u8; 4] = [0x0a, 0x0a, 0, 0];
+
+fn put_u16(buf: &mut [u8], at: usize, value: usize) {
+    let bytes = u16::try_from(value).unwrap_or_default().to_be_bytes();
+    if let Some(slot) = buf.get_mut(at..at.saturating_add(2)) {
+        slot.copy_from_slice(&bytes);
+    }
+}
+
+/// The reqwest ClientHello with `extra` appended to its extensions, lengths fixed up.
+fn reqwest_hello_with(extra: &[u8]) -> Vec<u8> {
+    let mut hello = REQWEST_CLIENT_HELLO.to_vec();
+    hello.extend_from_slice(extra);
+    let len = hello.len();
+    put_u16(&mut hello, 3, len.saturating_sub(5));
+    // Handshake length is 24 bits; the high byte stays 0.
+    put_u16(&mut hello, 7, len.saturating_sub(9));
+    put_u16(
+        &mut hello,
+        REQWEST_EXTENSIONS_LEN_AT,
+        len.saturating_sub(REQWEST_EXTENSIONS_LEN_AT.saturating_add(2)),
+    );
+    hello
+}
+
+async fn ja4_of(hello: &[u8]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    use tokio::io::AsyncWriteExt;
+
+    let (mut client, mut server) = tokio::io::duplex(4096);
+    client.write_all(hello).await?;
+    let (_, fingerprints) =
+        huginn_proxy_lib::read_client_hello(&mut server, huginn_proxy_lib::Metrics::new_noop())
+            .await?;
+    Ok(fingerprints
+        .ok_or("ClientHello not parsed")?
+        .ja4
+        .full
+        .to_string())
+}
+
+#[tokio::test]
+async fn test_ech_and_grease_extensions_keep_ja4_consistent(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use huginn_proxy_lib::fingerprinting::{client_hello_has_sni, client_hello_offers_ech};
+
+    let plain = ja4_of(REQWEST_CLIENT_HELLO).await?;
+    assert!(!client_hello_offers_ech(REQWEST_CLIENT_HELLO));
+    assert!(!client_hello_has_sni(REQWEST_CLIENT_HELLO));
+
+    // GREASE values are ignored by JA4.
+    assert_eq!(ja4_of(&reqwest_hello_with(&GREASE_EXTENSION)).await?, plain);
+
+    // ECH is an ordinary extension for JA4: one more in the count and the extension hash.
+    let mut extra = ECH_EXTENSION.to_vec();
+    extra.extend_from_slice(&GREASE_EXTENSION);
+    let ech_hello = reqwest_hello_with(&extra);
+    assert!(client_hello_offers_ech(&ech_hello));
+    assert!(!client_hello_has_sni(&ech_hello));
+    let ech = ja4_of(&ech_hello).await?;
+    let (plain_a, plain_rest) = plain.split_once('_').ok_or("malformed JA4")?;
+    let (ech_a, ech_rest) = ech.split_once('_').ok_or("malformed JA4")?;
+    assert_eq!(plain_a, "t13i1010h2");
+    assert_eq!(ech_a, "t13i1011h2");
+    assert_eq!(
+        plain_rest.split_once('_').map(|(ciphers, _)| ciphers),
+        ech_rest.split_once('_').map(|(ciphers, _)| ciphers),
+        "cipher hash must not change"
+    );
+    assert_ne!(plain_rest, ech_rest);
+
+    // With an outer SNI.
+    assert!(client_hello_has_sni(&client_hello(&[0x0000, 0xfe0d])));
+    assert!(client_hello_offers_ech(&client_hello(&[0x0000, 0xfe0d])));
+    Ok(())
+}
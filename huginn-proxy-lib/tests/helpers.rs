@@ -14,6 +14,11 @@ pub fn tmp_path(name: &str) -> PathBuf {
     std::env::temp_dir().join(format!("huginn-test-{nanos}-{name}"))
 }
 
+/// Message of the error in `result`, empty if it is `Ok`
+pub fn error_message<T, E: std::fmt::Display>(result: Result<T, E>) -> String {
+    result.err().map(|e| e.to_string()).unwrap_or_default()
+}
+
 /// Generate valid test certificates using rcgen
 /// Returns paths to PEM files containing valid self-signed certificates
 /// Use for tests that need cryptographically valid certificates
@@ -0,0 +1,100 @@
+//! Zero-downtime binary upgrade: a second proxy binds the same port through `listen.reuse_port`,
+//! then the first one drains and stops.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use huginn_proxy_lib::config::{Config, ConfigParts};
+use huginn_proxy_lib::{
+    run, shutdown_channel, DrainTrigger, HealthRegistry, Metrics, Readiness, RuntimeHandles,
+    WatchOptions,
+};
+use tokio::task::JoinHandle;
+
+use super::helpers::{free_port, http_get, spawn_mock_backend};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+struct Proxy {
+    readiness: Readiness,
+    drain: DrainTrigger,
+    task: JoinHandle<huginn_proxy_lib::Result<()>>,
+}
+
+fn start(
+    listen_port: u16,
+    backend: SocketAddr,
+) -> Result<Proxy, Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str(&format!(
+        r#"listen = {{ addrs = ["127.0.0.1:{listen_port}"], reuse_port = true }}
+timeout = {{ drain_delay_secs = 1, shutdown_secs = 2 }}
+backends = [{{ address = "{backend}" }}]
+
+[[domains]]
+host = "127.0.0.1"
+routes = [{{ prefix = "/", backend = "{backend}" }}]
+"#
+    ))?;
+    config.validate_cross_refs()?;
+    let ConfigParts { static_cfg, dynamic_cfg } = config.into_parts();
+    let readiness = Readiness::new();
+    let drain = DrainTrigger::new();
+    let runtime = RuntimeHandles { drain: drain.clone(), ..RuntimeHandles::default() };
+    let (shutdown_tx, _) = shutdown_channel();
+    let task = tokio::spawn(run(
+        Arc::new(static_cfg),
+        Arc::new(ArcSwap::from_pointee(dynamic_cfg)),
+        Metrics::new_noop(),
+        None,
+        None,
+        None,
+        WatchOptions { config_path: None, watch: false, debounce_secs: 1 },
+        shutdown_tx,
+        readiness.clone(),
+        Arc::new(HealthRegistry::new()),
+        runtime,
+    ));
+    Ok(Proxy { readiness, drain, task })
+}
+
+async fn wait_until(what: &str, done: impl Fn() -> bool) -> TestResult {
+    for _ in 0..100 {
+        if done() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Err(format!("timed out waiting for {what}").into())
+}
+
+#[tokio::test]
+async fn new_process_takes_over_the_port_while_the_old_one_drains() -> TestResult {
+    let (old_backend, _old_bh) = spawn_mock_backend("old").await?;
+    let (new_backend, _new_bh) = spawn_mock_backend("new").await?;
+    let port = free_port()?;
+    let addr: SocketAddr = format!("127.0.0.1:{port}").parse()?;
+
+    let old = start(port, old_backend)?;
+    wait_until("old proxy ready", || old.readiness.is_ready()).await?;
+    assert_eq!(http_get(addr, "/").await?, (200, Some("old".to_string())));
+
+    // Binding the same address succeeds while the old proxy still listens.
+    let new = start(port, new_backend)?;
+    wait_until("new proxy ready", || new.readiness.is_ready()).await?;
+
+    assert!(old.drain.request());
+    assert!(!old.drain.request(), "a second request is a no-op");
+    wait_until("old proxy draining", || old.readiness.is_draining()).await?;
+    tokio::time::timeout(Duration::from_secs(10), old.task).await???;
+
+    for _ in 0..10 {
+        assert_eq!(http_get(addr, "/").await?, (200, Some("new".to_string())));
+    }
+    assert!(new.readiness.is_ready());
+
+    new.drain.request();
+    tokio::time::timeout(Duration::from_secs(10), new.task).await???;
+    Ok(())
+}
@@ -135,9 +135,13 @@ pub async fn spawn_proxy(
             dynamic_cfg,
             Metrics::new_noop(),
             None,
+            None,
+            None,
             WatchOptions { config_path: Some(config_path_buf), watch, debounce_secs },
             shutdown_tx,
             huginn_proxy_lib::Readiness::new(),
+            Arc::new(huginn_proxy_lib::HealthRegistry::new()),
+            huginn_proxy_lib::RuntimeHandles::default(),
         )
         .await;
     });
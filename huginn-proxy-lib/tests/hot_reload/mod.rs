@@ -1,3 +1,4 @@
-mod helpers;
+mod handover;
+pub(crate) mod helpers;
 mod integration;
 mod pipeline;
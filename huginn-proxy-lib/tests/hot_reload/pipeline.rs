@@ -3,9 +3,9 @@ use std::io::Write as _;
 use std::sync::Arc;
 
 use huginn_proxy_lib::{
-    initial_client_pool, initial_rate_limiter, try_reload, Config, DynamicConfig,
-    HealthCheckSupervisor, HealthRegistry, Metrics, SharedClientPool, SharedRateLimiter,
-    StaticConfig,
+    initial_client_pool, initial_rate_limiter, try_reload, BackendDiscovery, Config,
+    ConfigGeneration, DynamicConfig, HealthCheckSupervisor, HealthRegistry, Metrics,
+    SharedClientPool, SharedRateLimiter, StaticConfig,
 };
 
 fn test_health_supervisor() -> HealthCheckSupervisor {
@@ -36,6 +36,20 @@ fn minimal_config(backend_addr: std::net::SocketAddr, listen_port: u16) -> Confi
             address: backend_addr.to_string(),
             http_version: None,
             health_check: None,
+            circuit_breaker: None,
+            tls: None,
+            pool: None,
+            max_in_flight: None,
+            queue: None,
+            discovery: None,
+            drain: false,
+            drain_timeout_secs: None,
+            slow_start_secs: None,
+            echo: false,
+            tcp: None,
+            proxy: None,
+            bandwidth: None,
+            tenant: None,
         }],
         domains: vec![Domain {
             host: Some("127.0.0.1".to_string()),
@@ -44,55 +58,88 @@ fn minimal_config(backend_addr: std::net::SocketAddr, listen_port: u16) -> Confi
             headers: None,
             security: None,
             fingerprinting: None,
+            ja4_variants: None,
+            fingerprint_format: None,
+            redirect: None,
             routes: vec![Route {
                 prefix: "/".to_string(),
                 backend: backend_addr.to_string(),
                 fingerprinting: Some(false),
-                force_new_connection: false,
-                replace_path: None,
-                security: None,
-                headers: None,
+                ..Default::default()
             }],
+            tenant: None,
         }],
         tls: None,
         fingerprint: FingerprintConfig {
             tls_enabled: false,
             http_enabled: false,
             tcp_enabled: false,
+            tcp_capture: Default::default(),
             max_capture: 0,
+            tls_info_headers: false,
+            headers: Default::default(),
+            whoami: Default::default(),
+            signing: Default::default(),
         },
         logging: LoggingConfig { level: "warn".to_string(), show_target: false },
         timeout: TimeoutConfig {
             upstream_connect_ms: Some(1000),
             proxy_idle_ms: 5000,
             shutdown_secs: 1,
+            drain_delay_secs: 0,
             tls_handshake_secs: 5,
             connection_handling_secs: 10,
             keep_alive: KeepAliveConfig::default(),
         },
         security: SecurityConfig::default(),
-        telemetry: TelemetryConfig { metrics_port: None, otel_log_level: "warn".to_string() },
+        telemetry: TelemetryConfig {
+            metrics_port: None,
+            otel_log_level: "warn".to_string(),
+            ..Default::default()
+        },
         reload: ReloadConfig::default(),
         headers: None,
         preserve_host: false,
+        default_host: None,
         backend_pool: Default::default(),
+        compression: None,
+        redirect: None,
+        cache: Default::default(),
+        access_log: Default::default(),
+        load_shedding: Default::default(),
+        http2: Default::default(),
+        request_id: Default::default(),
+        event_stream: Default::default(),
+        client_tracking: Default::default(),
+        handshake_capture: Default::default(),
+        tenants: vec![],
+        include: vec![],
     }
 }
 
-fn into_shared(
-    config: Config,
-) -> (
+type Shared = (
     Arc<StaticConfig>,
     Arc<ArcSwap<DynamicConfig>>,
     SharedRateLimiter,
     SharedClientPool,
-) {
+);
+
+fn into_shared(config: Config) -> Result<Shared, Box<dyn std::error::Error + Send + Sync>> {
     let huginn_proxy_lib::config::ConfigParts { static_cfg, dynamic_cfg } = config.into_parts();
     let static_cfg = Arc::new(static_cfg);
     let shared_dyn = Arc::new(ArcSwap::from_pointee(dynamic_cfg));
     let rate_limiter = initial_rate_limiter(&shared_dyn.load());
-    let client_pool = initial_client_pool(&static_cfg, &shared_dyn.load().backend_pool);
-    (static_cfg, shared_dyn, rate_limiter, client_pool)
+    let client_pool = {
+        let dynamic = shared_dyn.load();
+        initial_client_pool(
+            &static_cfg,
+            &dynamic.backend_pool,
+            &dynamic.backends,
+            &Metrics::new_noop(),
+            &BackendDiscovery::new(),
+        )?
+    };
+    Ok((static_cfg, shared_dyn, rate_limiter, client_pool))
 }
 
 #[tokio::test]
@@ -100,7 +147,7 @@ async fn reload_invalid_toml_keeps_current_config() -> TestResult {
     let (backend_addr, _bh) = spawn_mock_backend("a").await?;
     let listen_port = free_port()?;
     let config = minimal_config(backend_addr, listen_port);
-    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config);
+    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config)?;
 
     let before = (*shared_dyn.load_full()).clone();
 
@@ -120,7 +167,9 @@ async fn reload_invalid_toml_keeps_current_config() -> TestResult {
         &reload_mutex,
         &metrics,
         &health_supervisor,
+        &BackendDiscovery::new(),
         None,
+        &ConfigGeneration::new(),
     )
     .await;
 
@@ -147,7 +196,7 @@ async fn drain_removed_backend_replaces_pool() -> TestResult {
     )?;
 
     let config = huginn_proxy_lib::config::load_from_path(initial_toml_file.path())?;
-    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config);
+    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config)?;
 
     let ptr_before = Arc::as_ptr(&client_pool.load_full());
 
@@ -167,7 +216,9 @@ async fn drain_removed_backend_replaces_pool() -> TestResult {
         &reload_mutex,
         &metrics,
         &health_supervisor,
+        &BackendDiscovery::new(),
         None,
+        &ConfigGeneration::new(),
     )
     .await;
 
@@ -192,7 +243,7 @@ async fn reload_static_change_proceeds_without_crash() -> TestResult {
     write_toml(tmp.path(), &toml_single_backend(listen_port, backend_a))?;
 
     let config = huginn_proxy_lib::config::load_from_path(tmp.path())?;
-    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config);
+    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config)?;
 
     let dynamic_before = (*shared_dyn.load_full()).clone();
 
@@ -213,7 +264,9 @@ async fn reload_static_change_proceeds_without_crash() -> TestResult {
         &reload_mutex,
         &metrics,
         &health_supervisor,
+        &BackendDiscovery::new(),
         None,
+        &ConfigGeneration::new(),
     )
     .await;
 
@@ -234,7 +287,7 @@ async fn concurrent_reloads_are_serialized() -> TestResult {
     write_toml(tmp.path(), &toml_single_backend(listen_port, backend_addr))?;
 
     let config = huginn_proxy_lib::config::load_from_path(tmp.path())?;
-    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config);
+    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config)?;
 
     let reload_mutex = Arc::new(tokio::sync::Mutex::new(()));
     let metrics = Metrics::new_noop();
@@ -263,7 +316,9 @@ async fn concurrent_reloads_are_serialized() -> TestResult {
                 &reload_mutex,
                 &metrics,
                 health_supervisor.as_ref(),
+                &BackendDiscovery::new(),
                 None,
+                &ConfigGeneration::new(),
             )
             .await;
         }));
@@ -289,7 +344,7 @@ async fn reload_toggles_rate_limiter() -> TestResult {
     write_toml(tmp.path(), &toml_single_backend(listen_port, backend_addr))?;
 
     let config = huginn_proxy_lib::config::load_from_path(tmp.path())?;
-    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config);
+    let (static_cfg, shared_dyn, rate_limiter, client_pool) = into_shared(config)?;
 
     {
         let mgr = (**rate_limiter.load()).clone();
@@ -311,7 +366,9 @@ async fn reload_toggles_rate_limiter() -> TestResult {
         &reload_mutex,
         &metrics,
         &health_supervisor,
+        &BackendDiscovery::new(),
         None,
+        &ConfigGeneration::new(),
     )
     .await;
 
@@ -331,7 +388,9 @@ async fn reload_toggles_rate_limiter() -> TestResult {
         &reload_mutex,
         &metrics,
         &health_supervisor,
+        &BackendDiscovery::new(),
         None,
+        &ConfigGeneration::new(),
     )
     .await;
 
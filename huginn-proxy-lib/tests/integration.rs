@@ -15,25 +15,48 @@ fn create_test_config(listen: &str, backends: Vec<Backend>) -> Config {
         backends,
         domains: vec![],
         preserve_host: false,
+        default_host: None,
         backend_pool: Default::default(),
+        compression: None,
+        redirect: None,
+        cache: Default::default(),
+        access_log: Default::default(),
+        load_shedding: Default::default(),
+        http2: Default::default(),
+        request_id: Default::default(),
+        event_stream: Default::default(),
+        client_tracking: Default::default(),
+        handshake_capture: Default::default(),
+        tenants: vec![],
+        include: vec![],
         tls: None,
         fingerprint: FingerprintConfig {
             tls_enabled: true,
             http_enabled: true,
             tcp_enabled: false,
+            tcp_capture: Default::default(),
             max_capture: 64 * 1024,
+            tls_info_headers: false,
+            headers: Default::default(),
+            whoami: Default::default(),
+            signing: Default::default(),
         },
         logging: LoggingConfig { level: "info".to_string(), show_target: false },
         timeout: TimeoutConfig {
             upstream_connect_ms: Some(5000),
             proxy_idle_ms: 60000,
             shutdown_secs: 30,
+            drain_delay_secs: 0,
             tls_handshake_secs: 15,
             connection_handling_secs: 300,
             keep_alive: KeepAliveConfig::default(),
         },
         security: SecurityConfig::default(),
-        telemetry: TelemetryConfig { metrics_port: None, otel_log_level: "warn".to_string() },
+        telemetry: TelemetryConfig {
+            metrics_port: None,
+            otel_log_level: "warn".to_string(),
+            ..Default::default()
+        },
         reload: ReloadConfig::default(),
         headers: None,
     }
@@ -0,0 +1,92 @@
+//! `bandwidth` shaping: token bucket accounting, bucket sharing and the `ShapedBody` wrapper.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::config::{BandwidthConfig, BandwidthKey};
+use huginn_proxy_lib::proxy::bandwidth::{BandwidthLimits, Direction, ShapedBody, TokenBucket};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// 10 KB/s with a 1 KB burst: each extra kilobyte costs 100 ms.
+fn config() -> BandwidthConfig {
+    BandwidthConfig {
+        bytes_per_sec: 10_000,
+        burst_bytes: Some(1_000),
+        limit_by: BandwidthKey::Shared,
+    }
+}
+
+#[test]
+fn burst_passes_then_bytes_wait_for_the_refill() {
+    let bucket = TokenBucket::new(&config());
+    assert_eq!(bucket.reserve(1_000), Duration::ZERO);
+    let wait = bucket.reserve(1_000);
+    assert!(
+        wait > Duration::from_millis(90) && wait <= Duration::from_millis(100),
+        "{wait:?}"
+    );
+    // Debt accumulates: the next kilobyte waits behind the previous one.
+    assert!(bucket.reserve(1_000) > Duration::from_millis(190));
+}
+
+#[test]
+fn buckets_are_shared_per_scope_client_and_direction() {
+    let limits = BandwidthLimits::new();
+    let config = config();
+    let shared = limits.route_bucket("example.com", "/files", None, Direction::Download, &config);
+    let again = limits.route_bucket("example.com", "/files", None, Direction::Download, &config);
+    assert!(Arc::ptr_eq(&shared, &again));
+
+    for other in [
+        limits.route_bucket("example.com", "/files", None, Direction::Upload, &config),
+        limits.route_bucket(
+            "example.com",
+            "/files",
+            Some("203.0.113.7"),
+            Direction::Download,
+            &config,
+        ),
+        limits.route_bucket("example.com", "/api", None, Direction::Download, &config),
+        limits.backend_bucket("example.com/files", None, Direction::Download, &config),
+    ] {
+        assert!(!Arc::ptr_eq(&shared, &other));
+    }
+
+    // A reloaded rate gets a fresh bucket.
+    let faster = BandwidthConfig { bytes_per_sec: 20_000, ..config };
+    let reloaded = limits.route_bucket("example.com", "/files", None, Direction::Download, &faster);
+    assert!(!Arc::ptr_eq(&shared, &reloaded));
+}
+
+#[tokio::test]
+async fn frames_beyond_the_burst_are_held_back() -> TestResult {
+    let bucket = Arc::new(TokenBucket::new(&config()));
+    let throttled = Arc::new(AtomicUsize::new(0));
+
+    // The first body fits in the burst.
+    let body = ShapedBody::new(Full::new(Bytes::from(vec![1u8; 1_000])), vec![Arc::clone(&bucket)]);
+    assert_eq!(body.collect().await?.to_bytes().len(), 1_000);
+
+    // The second one shares the now empty bucket and waits for its refill.
+    let counter = Arc::clone(&throttled);
+    let body = ShapedBody::new(Full::new(Bytes::from(vec![2u8; 2_000])), vec![bucket])
+        .on_throttled(move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+    let started = Instant::now();
+    assert_eq!(body.collect().await?.to_bytes(), Bytes::from(vec![2u8; 2_000]));
+    assert!(started.elapsed() >= Duration::from_millis(150), "{:?}", started.elapsed());
+    assert_eq!(throttled.load(Ordering::Relaxed), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unshaped_body_passes_through() -> TestResult {
+    let body = ShapedBody::new(Full::new(Bytes::from_static(b"hello")), Vec::new());
+    assert_eq!(body.collect().await?.to_bytes(), Bytes::from_static(b"hello"));
+    Ok(())
+}
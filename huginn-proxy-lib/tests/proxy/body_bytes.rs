@@ -0,0 +1,72 @@
+//! Body byte accounting: `CountedBody` reports what was actually pulled through it.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::proxy::body_bytes::CountedBody;
+use hyper::body::{Body, Frame};
+
+/// Body streaming its chunks one frame at a time, like a chunked backend response.
+struct Chunks(VecDeque<&'static [u8]>);
+
+impl Body for Chunks {
+    type Data = Bytes;
+    type Error = std::convert::Infallible;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let chunk = self.get_mut().0.pop_front();
+        Poll::Ready(chunk.map(|c| Ok(Frame::data(Bytes::from_static(c)))))
+    }
+}
+
+/// `CountedBody` over `inner` whose report lands in the returned counters (bytes, reports).
+fn counted<B>(inner: B) -> (CountedBody<B>, Arc<AtomicU64>, Arc<AtomicUsize>) {
+    let bytes = Arc::new(AtomicU64::new(0));
+    let reports = Arc::new(AtomicUsize::new(0));
+    let (b, r) = (Arc::clone(&bytes), Arc::clone(&reports));
+    let body = CountedBody::new(inner, move |n| {
+        b.store(n, Ordering::Relaxed);
+        r.fetch_add(1, Ordering::Relaxed);
+    });
+    (body, bytes, reports)
+}
+
+#[tokio::test]
+async fn counts_streamed_frames_once_the_body_is_dropped(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let chunks: &[&'static [u8]] = &[b"chunk-1", b"chunk-22", b"chunk-333"];
+    let (body, bytes, reports) = counted(Chunks(chunks.iter().copied().collect()));
+
+    let collected = body.collect().await?.to_bytes();
+    assert_eq!(collected.len(), 24);
+    assert_eq!(bytes.load(Ordering::Relaxed), 24);
+    assert_eq!(reports.load(Ordering::Relaxed), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn aborted_body_reports_the_bytes_that_moved(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (mut body, bytes, reports) = counted(Full::new(Bytes::from_static(b"partial")));
+    assert_eq!(reports.load(Ordering::Relaxed), 0);
+
+    let frame = body.frame().await.ok_or("frame expected")??;
+    assert_eq!(frame.data_ref().map(Bytes::len), Some(7));
+    drop(body);
+    assert_eq!(bytes.load(Ordering::Relaxed), 7);
+    assert_eq!(reports.load(Ordering::Relaxed), 1);
+
+    let (untouched, bytes, reports) = counted(Full::new(Bytes::from_static(b"never read")));
+    drop(untouched);
+    assert_eq!(bytes.load(Ordering::Relaxed), 0);
+    assert_eq!(reports.load(Ordering::Relaxed), 1);
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! Body size caps: declared `Content-Length` checks and the streaming `LimitedBody` wrapper.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http::header::CONTENT_LENGTH;
+use http::{HeaderMap, HeaderValue};
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::proxy::body_limit::{declared_length_exceeds, BodyTooLarge, LimitedBody};
+
+fn with_content_length(length: &'static str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_LENGTH, HeaderValue::from_static(length));
+    headers
+}
+
+#[test]
+fn declared_length_over_limit_is_detected() {
+    assert!(declared_length_exceeds(&with_content_length("1025"), Some(1024)));
+    assert!(!declared_length_exceeds(&with_content_length("1024"), Some(1024)));
+    assert!(!declared_length_exceeds(&with_content_length("1025"), None));
+    // Without a declared length the streaming cap applies instead.
+    assert!(!declared_length_exceeds(&HeaderMap::new(), Some(1)));
+    assert!(!declared_length_exceeds(&with_content_length("bogus"), Some(1)));
+}
+
+#[tokio::test]
+async fn body_within_limit_passes_through() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let body = LimitedBody::new(Full::new(Bytes::from_static(b"hello")), Some(5));
+    assert_eq!(body.collect().await?.to_bytes(), Bytes::from_static(b"hello"));
+
+    let unlimited = LimitedBody::new(Full::new(Bytes::from(vec![0u8; 4096])), None);
+    assert_eq!(unlimited.collect().await?.to_bytes().len(), 4096);
+    Ok(())
+}
+
+#[tokio::test]
+async fn body_over_limit_fails_and_reports_once() {
+    let exceeded = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&exceeded);
+    let body = LimitedBody::new(Full::new(Bytes::from_static(b"hello!")), Some(5)).on_exceeded(
+        move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        },
+    );
+
+    let err = match body.collect().await {
+        Ok(_) => panic!("body over the limit must fail"),
+        Err(e) => e,
+    };
+    assert_eq!(err.downcast_ref::<BodyTooLarge>().map(|e| e.limit), Some(5));
+    assert_eq!(exceeded.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,217 @@
+//! Response body rewriting: config validation, eligibility rules, find/replace across frame
+//! boundaries and the `Accept-Encoding` kept from the backend.
+
+use std::net::SocketAddr;
+
+use bytes::Bytes;
+use http::header::{
+    ACCEPT_ENCODING, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
+    ETAG,
+};
+use http::{HeaderValue, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::config::{BodyRewriteConfig, BodyRewriteRule};
+use huginn_proxy_lib::proxy::body_rewrite::{is_rewritable, rewrite_response, Rewriter};
+use huginn_proxy_lib::{Backend, ProxyBuilder, Route};
+
+use crate::helpers::error_message;
+use crate::hot_reload::helpers::free_port;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+fn config(rules: &[(&str, &str)]) -> BodyRewriteConfig {
+    BodyRewriteConfig {
+        rules: rules
+            .iter()
+            .map(|(find, replace)| BodyRewriteRule {
+                find: find.to_string(),
+                replace: replace.to_string(),
+            })
+            .collect(),
+        content_types: vec!["text/html".to_string(), "application/json".to_string()],
+        max_buffer_bytes: 64 * 1024,
+    }
+}
+
+fn response(content_type: &'static str, body: &str) -> Response<Full<Bytes>> {
+    let mut resp = Response::new(Full::new(Bytes::copy_from_slice(body.as_bytes())));
+    resp.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    resp.headers_mut()
+        .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+    resp
+}
+
+#[test]
+fn valid_config_validates() {
+    assert!(config(&[("http://app.internal:8080", "https://www.example.com")])
+        .validate("body_rewrite")
+        .is_ok());
+}
+
+#[test]
+fn empty_rules_are_rejected() {
+    let err = error_message(config(&[]).validate("body_rewrite"));
+    assert!(err.contains("body_rewrite.rules must not be empty"), "got: {err}");
+}
+
+#[test]
+fn empty_find_is_rejected() {
+    let err = error_message(config(&[("", "x")]).validate("body_rewrite"));
+    assert!(err.contains("body_rewrite.rules[0].find is empty"), "got: {err}");
+}
+
+#[test]
+fn duplicate_find_is_rejected() {
+    let err = error_message(config(&[("a", "b"), ("a", "c")]).validate("body_rewrite"));
+    assert!(
+        err.contains("body_rewrite.rules[1]: 'a' is already replaced by an earlier rule"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn find_longer_than_the_buffer_is_rejected() {
+    let err = error_message(
+        BodyRewriteConfig { max_buffer_bytes: 4, ..config(&[("internal", "")]) }
+            .validate("body_rewrite"),
+    );
+    assert!(
+        err.contains("body_rewrite.rules[0].find is longer than max_buffer_bytes (4)"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn invalid_content_type_is_rejected() {
+    let err = error_message(
+        BodyRewriteConfig { content_types: vec!["html".to_string()], ..config(&[("a", "b")]) }
+            .validate("body_rewrite"),
+    );
+    assert!(
+        err.contains("body_rewrite.content_types: invalid media type 'html'"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn empty_content_types_are_rejected() {
+    let err = error_message(
+        BodyRewriteConfig { content_types: Vec::new(), ..config(&[("a", "b")]) }
+            .validate("body_rewrite"),
+    );
+    assert!(
+        err.contains("body_rewrite.content_types must list at least one media type"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn zero_max_buffer_bytes_is_rejected() {
+    let err = error_message(
+        BodyRewriteConfig { max_buffer_bytes: 0, ..config(&[("a", "b")]) }.validate("body_rewrite"),
+    );
+    assert!(err.contains("body_rewrite.max_buffer_bytes must be > 0"), "got: {err}");
+}
+
+#[test]
+fn eligibility_checks_type_encoding_and_status() {
+    let config = config(&[("a", "b")]);
+    assert!(is_rewritable(&config, &response("text/html; charset=utf-8", "a")));
+    assert!(is_rewritable(&config, &response("application/json", "a")));
+    assert!(!is_rewritable(&config, &response("image/png", "a")));
+
+    let mut encoded = response("text/html", "a");
+    encoded
+        .headers_mut()
+        .insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    assert!(!is_rewritable(&config, &encoded));
+
+    let mut no_transform = response("text/html", "a");
+    no_transform
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("public, no-transform"));
+    assert!(!is_rewritable(&config, &no_transform));
+
+    let mut partial = response("text/html", "a");
+    *partial.status_mut() = StatusCode::PARTIAL_CONTENT;
+    assert!(!is_rewritable(&config, &partial));
+}
+
+#[test]
+fn rewriter_finds_matches_split_anywhere() {
+    let rules = config(&[("http://app.internal", "https://example.com"), ("app", "APP")]);
+    let body = r#"{"self":"http://app.internal/a","next":"http://app.internal/b","app":1}"#;
+    let expected = r#"{"self":"https://example.com/a","next":"https://example.com/b","APP":1}"#;
+
+    for split in 0..=body.len() {
+        let (head, tail) = body.as_bytes().split_at(split);
+        let mut rewriter = Rewriter::new(&rules);
+        let mut out = Vec::new();
+        out.extend_from_slice(&rewriter.feed(head));
+        out.extend_from_slice(&rewriter.feed(tail));
+        out.extend_from_slice(&rewriter.finish());
+        assert_eq!(String::from_utf8_lossy(&out), expected, "split at {split}");
+    }
+
+    // Replaced text is not searched again; the first listed rule wins at the same position.
+    let mut rewriter = Rewriter::new(&config(&[("ab", "abab"), ("abc", "x")]));
+    let out = [rewriter.feed(b"abcab"), rewriter.finish()].concat();
+    assert_eq!(out, b"ababcabab");
+}
+
+#[tokio::test]
+async fn responses_are_rewritten_in_bounded_pieces() -> Result<(), BoxError> {
+    let body = "<a href=\"http://app.internal:8080/x\">x</a>".repeat(50);
+    let mut resp = response("text/html", &body);
+    resp.headers_mut()
+        .insert(ETAG, HeaderValue::from_static("\"v1\""));
+    resp.headers_mut()
+        .insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    let resp = resp.map(|b| b.map_err(|never| match never {}).boxed());
+
+    // Pieces smaller than a whole link: matches straddle piece boundaries.
+    let config = BodyRewriteConfig {
+        max_buffer_bytes: 32,
+        ..config(&[("http://app.internal:8080", "https://www.example.com")])
+    };
+    let (parts, body) = rewrite_response(resp, &config).into_parts();
+    assert!(parts.headers.get(CONTENT_LENGTH).is_none());
+    assert!(parts.headers.get(ACCEPT_RANGES).is_none());
+    assert_eq!(parts.headers.get(ETAG), Some(&HeaderValue::from_static("W/\"v1\"")));
+    let rewritten = body.collect().await?.to_bytes();
+    assert_eq!(rewritten, "<a href=\"https://www.example.com/x\">x</a>".repeat(50));
+    Ok(())
+}
+
+#[tokio::test]
+async fn backends_of_rewritten_routes_are_not_offered_an_encoding() -> Result<(), BoxError> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], free_port()?));
+    let rewritten = Route {
+        body_rewrite: Some(config(&[("http://app.internal", "https://www.example.com")])),
+        ..Route::new("/rewritten", "echo")
+    };
+    let proxy = ProxyBuilder::new()
+        .listen(addr)
+        .backend(Backend { echo: true, ..Backend::new("echo") })
+        .route(rewritten)
+        .route(Route::new("/", "echo"))
+        .start()
+        .await?;
+
+    let client = reqwest::Client::new();
+    for (path, forwarded) in [("/rewritten", None), ("/plain", Some("gzip, br"))] {
+        let echoed: serde_json::Value = client
+            .get(format!("http://{addr}{path}"))
+            .header(ACCEPT_ENCODING, "gzip, br")
+            .send()
+            .await?
+            .json()
+            .await?;
+        assert_eq!(echoed["headers"]["accept-encoding"].as_str(), forwarded, "{path}");
+    }
+
+    drop(client);
+    proxy.stop().await?;
+    Ok(())
+}
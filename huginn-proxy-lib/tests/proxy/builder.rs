@@ -0,0 +1,63 @@
+//! `ProxyBuilder`: an embedded proxy routes, classifies and takes route changes while it runs.
+
+use std::net::SocketAddr;
+
+use http::StatusCode;
+use huginn_proxy_lib::{Backend, ProxyBuilder, Route, Verdict};
+
+use crate::hot_reload::helpers::{free_port, http_get, spawn_mock_backend};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[tokio::test]
+async fn embedded_proxy_routes_classifies_and_takes_route_changes() -> TestResult {
+    let (api, api_handle) = spawn_mock_backend("api").await?;
+    let (web, web_handle) = spawn_mock_backend("web").await?;
+    let addr = SocketAddr::from(([127, 0, 0, 1], free_port()?));
+
+    let proxy = ProxyBuilder::new()
+        .listen(addr)
+        .backend(Backend::new(api.to_string()))
+        .backend(Backend::new(web.to_string()))
+        .route(Route::new("/api", api.to_string()))
+        .on_fingerprint(|set| {
+            if set.path.starts_with("/api/admin") {
+                Verdict::Deny(StatusCode::FORBIDDEN)
+            } else {
+                Verdict::Allow
+            }
+        })
+        .start()
+        .await?;
+    assert!(proxy.is_ready());
+
+    assert_eq!(http_get(addr, "/api/users").await?, (200, Some("api".to_string())));
+    assert_eq!(http_get(addr, "/api/admin").await?.0, 403);
+    assert_eq!(http_get(addr, "/web").await?.0, 404);
+
+    proxy
+        .add_route(None, Route::new("/web", web.to_string()))
+        .await?;
+    assert_eq!(http_get(addr, "/web").await?, (200, Some("web".to_string())));
+    proxy.remove_route(None, "/api").await?;
+    assert_eq!(http_get(addr, "/api/users").await?.0, 404);
+    assert_eq!(proxy.config().domains.first().map(|d| d.routes.len()), Some(1));
+
+    proxy.stop().await?;
+    assert!(http_get(addr, "/web").await.is_err());
+    api_handle.abort();
+    web_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_config_fails_to_start() -> TestResult {
+    let addr = SocketAddr::from(([127, 0, 0, 1], free_port()?));
+    let started = ProxyBuilder::new()
+        .listen(addr)
+        .route(Route::new("/", "127.0.0.1:1"))
+        .start()
+        .await;
+    assert!(started.is_err());
+    Ok(())
+}
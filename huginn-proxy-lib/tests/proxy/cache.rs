@@ -0,0 +1,286 @@
+//! Response cache: lookup outcomes, storage rules, `Vary` handling, invalidation and eviction.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http::header::{ACCEPT_LANGUAGE, AGE, AUTHORIZATION, CACHE_CONTROL, SET_COOKIE, VARY};
+use http::{HeaderMap, HeaderValue, Method, Response, StatusCode, Uri};
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use huginn_proxy_lib::config::{CacheConfig, RouteCacheConfig};
+use huginn_proxy_lib::proxy::cache::{store_response, CacheStep, ResponseCache};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const HOST: &str = "example.com";
+
+fn store(max_size_bytes: u64) -> Arc<ResponseCache> {
+    Arc::new(ResponseCache::new(&CacheConfig { max_size_bytes }))
+}
+
+fn uri(path: &str) -> Uri {
+    path.parse().unwrap_or_default()
+}
+
+fn response(
+    cache_control: Option<&'static str>,
+    body: &'static str,
+) -> Response<BoxBody<Bytes, BoxError>> {
+    let mut resp = Response::new(
+        Full::new(Bytes::from_static(body.as_bytes()))
+            .map_err(|never| match never {})
+            .boxed(),
+    );
+    if let Some(value) = cache_control {
+        resp.headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static(value));
+    }
+    resp
+}
+
+/// Run a `GET` for `path` through the cache: serve a hit, or store `resp` and drain its body.
+async fn get(
+    cache: &Arc<ResponseCache>,
+    config: &RouteCacheConfig,
+    path: &str,
+    headers: &HeaderMap,
+    resp: Response<BoxBody<Bytes, BoxError>>,
+) -> (&'static str, Bytes) {
+    let (outcome, resp) = match cache.prepare(&Method::GET, HOST, &uri(path), headers) {
+        CacheStep::Hit(entry) => ("hit", entry.to_response()),
+        CacheStep::Fetch { request, stale: Some(_) } => {
+            ("stale", store_response(cache, config, request, resp))
+        }
+        CacheStep::Fetch { request, stale: None } => {
+            ("miss", store_response(cache, config, request, resp))
+        }
+        CacheStep::Invalidate(_) | CacheStep::Bypass => ("bypass", resp),
+    };
+    let body = resp
+        .into_body()
+        .collect()
+        .await
+        .map(|c| c.to_bytes())
+        .unwrap_or_default();
+    (outcome, body)
+}
+
+#[tokio::test]
+async fn fresh_response_is_served_from_cache() {
+    let cache = store(1024 * 1024);
+    let config = RouteCacheConfig::default();
+    let none = HeaderMap::new();
+
+    let first = get(&cache, &config, "/a?x=1", &none, response(Some("max-age=60"), "one")).await;
+    assert_eq!(first, ("miss", Bytes::from_static(b"one")));
+    assert_eq!(cache.len(), 1);
+
+    let second = get(&cache, &config, "/a?x=1", &none, response(None, "unused")).await;
+    assert_eq!(second, ("hit", Bytes::from_static(b"one")));
+
+    // The query is part of the key.
+    let other = get(&cache, &config, "/a?x=2", &none, response(None, "two")).await;
+    assert_eq!(other.0, "miss");
+
+    match cache.prepare(&Method::GET, HOST, &uri("/a?x=1"), &none) {
+        CacheStep::Hit(entry) => {
+            let resp = entry.to_response();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(resp.headers().contains_key(AGE));
+        }
+        _ => panic!("expected a cache hit"),
+    }
+}
+
+#[tokio::test]
+async fn uncacheable_responses_are_not_stored() {
+    let cache = store(1024 * 1024);
+    let config = RouteCacheConfig::default();
+    let none = HeaderMap::new();
+
+    for cache_control in [
+        None,
+        Some("no-store"),
+        Some("no-cache, max-age=60"),
+        Some("private, max-age=60"),
+    ] {
+        get(&cache, &config, "/r", &none, response(cache_control, "body")).await;
+        assert!(cache.is_empty(), "stored with Cache-Control {cache_control:?}");
+    }
+
+    let mut cookie = response(Some("max-age=60"), "body");
+    cookie
+        .headers_mut()
+        .insert(SET_COOKIE, HeaderValue::from_static("session=1"));
+    get(&cache, &config, "/r", &none, cookie).await;
+
+    let mut vary_all = response(Some("max-age=60"), "body");
+    vary_all
+        .headers_mut()
+        .insert(VARY, HeaderValue::from_static("*"));
+    get(&cache, &config, "/r", &none, vary_all).await;
+
+    let mut error = response(Some("max-age=60"), "body");
+    *error.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    get(&cache, &config, "/r", &none, error).await;
+
+    let mut authorized = HeaderMap::new();
+    authorized.insert(AUTHORIZATION, HeaderValue::from_static("Bearer t"));
+    get(&cache, &config, "/r", &authorized, response(Some("max-age=60"), "body")).await;
+    assert!(cache.is_empty());
+
+    get(&cache, &config, "/r", &authorized, response(Some("public, max-age=60"), "body")).await;
+    assert_eq!(cache.len(), 1);
+}
+
+#[tokio::test]
+async fn default_ttl_applies_without_explicit_lifetime() {
+    let cache = store(1024 * 1024);
+    let config = RouteCacheConfig { default_ttl_secs: 30, ..RouteCacheConfig::default() };
+    let none = HeaderMap::new();
+
+    get(&cache, &config, "/d", &none, response(None, "body")).await;
+    assert_eq!(
+        get(&cache, &config, "/d", &none, response(None, "x"))
+            .await
+            .0,
+        "hit"
+    );
+}
+
+#[tokio::test]
+async fn request_directives_bypass_or_reload() {
+    let cache = store(1024 * 1024);
+    let config = RouteCacheConfig::default();
+    let none = HeaderMap::new();
+    get(&cache, &config, "/c", &none, response(Some("max-age=60"), "old")).await;
+
+    let mut no_store = HeaderMap::new();
+    no_store.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    assert!(matches!(
+        cache.prepare(&Method::GET, HOST, &uri("/c"), &no_store),
+        CacheStep::Bypass
+    ));
+    assert!(matches!(
+        cache.prepare(&Method::HEAD, HOST, &uri("/c"), &none),
+        CacheStep::Bypass
+    ));
+
+    // `no-cache` skips the stored entry and replaces it with the new response.
+    let mut reload = HeaderMap::new();
+    reload.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    let fetched = get(&cache, &config, "/c", &reload, response(Some("max-age=60"), "new")).await;
+    assert_eq!(fetched.0, "miss");
+    let hit = get(&cache, &config, "/c", &none, response(None, "x")).await;
+    assert_eq!(hit, ("hit", Bytes::from_static(b"new")));
+}
+
+#[tokio::test]
+async fn vary_headers_select_the_variant() {
+    let cache = store(1024 * 1024);
+    let config = RouteCacheConfig::default();
+    let lang = |value: &'static str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static(value));
+        headers
+    };
+    let varying = |body: &'static str| {
+        let mut resp = response(Some("max-age=60"), body);
+        resp.headers_mut()
+            .insert(VARY, HeaderValue::from_static("Accept-Language"));
+        resp
+    };
+
+    get(&cache, &config, "/v", &lang("en"), varying("hello")).await;
+    get(&cache, &config, "/v", &lang("fr"), varying("bonjour")).await;
+    assert_eq!(cache.len(), 2);
+
+    let en = get(&cache, &config, "/v", &lang("en"), varying("x")).await;
+    assert_eq!(en, ("hit", Bytes::from_static(b"hello")));
+    let fr = get(&cache, &config, "/v", &lang("fr"), varying("x")).await;
+    assert_eq!(fr, ("hit", Bytes::from_static(b"bonjour")));
+    assert_eq!(
+        get(&cache, &config, "/v", &lang("de"), varying("hallo"))
+            .await
+            .0,
+        "miss"
+    );
+}
+
+#[tokio::test]
+async fn unsafe_method_invalidates_the_url() {
+    let cache = store(1024 * 1024);
+    let config = RouteCacheConfig::default();
+    let none = HeaderMap::new();
+    get(&cache, &config, "/items", &none, response(Some("max-age=60"), "list")).await;
+
+    match cache.prepare(&Method::POST, HOST, &uri("/items"), &none) {
+        CacheStep::Invalidate(url) => cache.invalidate(&url),
+        _ => panic!("expected an invalidation step"),
+    }
+    assert!(cache.is_empty());
+    assert_eq!(cache.size_bytes(), 0);
+}
+
+#[tokio::test]
+async fn oversized_and_evicted_entries() {
+    let config = RouteCacheConfig { max_object_bytes: 8, ..RouteCacheConfig::default() };
+    let none = HeaderMap::new();
+
+    // A body without Content-Length that outgrows `max_object_bytes` is passed through whole.
+    let cache = store(1024 * 1024);
+    let undeclared = response(Some("max-age=60"), "0123456789");
+    let (_, body) = get(&cache, &config, "/big", &none, undeclared).await;
+    assert_eq!(body, Bytes::from_static(b"0123456789"));
+    assert!(cache.is_empty());
+
+    // The least recently used entry goes first when the store is full.
+    let config = RouteCacheConfig::default();
+    let small = store(150);
+    get(&small, &config, "/1", &none, response(Some("max-age=60"), "first")).await;
+    get(&small, &config, "/2", &none, response(Some("max-age=60"), "second")).await;
+    assert_eq!(
+        get(&small, &config, "/1", &none, response(None, "x"))
+            .await
+            .0,
+        "hit"
+    );
+    get(&small, &config, "/3", &none, response(Some("max-age=60"), "third")).await;
+    assert!(small.size_bytes() <= 150);
+    assert_eq!(
+        get(&small, &config, "/1", &none, response(None, "x"))
+            .await
+            .0,
+        "hit"
+    );
+    assert_eq!(
+        get(&small, &config, "/2", &none, response(None, "x"))
+            .await
+            .0,
+        "miss"
+    );
+}
+
+#[tokio::test]
+async fn stale_entry_is_offered_within_stale_if_error() {
+    let config = RouteCacheConfig { stale_if_error_secs: 600, ..RouteCacheConfig::default() };
+    let cache = store(1024 * 1024);
+    let none = HeaderMap::new();
+
+    // Already older than its lifetime when received: only usable as an error fallback.
+    let mut aged = response(Some("max-age=30"), "fallback");
+    aged.headers_mut()
+        .insert(AGE, HeaderValue::from_static("40"));
+    get(&cache, &config, "/s", &none, aged).await;
+    assert!(matches!(
+        cache.prepare(&Method::GET, HOST, &uri("/s"), &none),
+        CacheStep::Fetch { stale: Some(_), .. }
+    ));
+
+    // Without a stale window the same response is not worth storing.
+    let cache = store(1024 * 1024);
+    let mut aged = response(Some("max-age=30"), "fallback");
+    aged.headers_mut()
+        .insert(AGE, HeaderValue::from_static("40"));
+    get(&cache, &RouteCacheConfig::default(), "/s", &none, aged).await;
+    assert!(cache.is_empty());
+}
@@ -1,7 +1,11 @@
 use http::Version;
-use huginn_proxy_lib::config::{BackendPoolConfig, KeepAliveConfig, TimeoutConfig};
+use huginn_proxy_lib::config::{
+    Backend, BackendPoolConfig, BackendPoolLimits, BackendTlsConfig, KeepAliveConfig, TimeoutConfig,
+};
 use huginn_proxy_lib::proxy::ClientPool;
 
+use crate::helpers::{create_valid_test_cert, tmp_path};
+
 fn default_keep_alive_config() -> KeepAliveConfig {
     KeepAliveConfig { enabled: true, upstream_idle_timeout: 90 }
 }
@@ -103,3 +107,140 @@ fn test_keep_alive_disabled() {
     // Pool should still be created, but without keep-alive
     assert!(pool.get_client(Version::HTTP_11, false).is_some());
 }
+
+fn tls_backend(address: &str, tls: Option<BackendTlsConfig>) -> Backend {
+    Backend {
+        address: address.to_string(),
+        http_version: None,
+        health_check: None,
+        circuit_breaker: None,
+        tls,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
+    }
+}
+
+#[test]
+fn test_with_backend_tls_builds_clients_for_tls_backends_only(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (cert_path, key_path) = create_valid_test_cert()?;
+    let ca = cert_path.to_string_lossy().into_owned();
+    let backends = vec![
+        tls_backend(
+            "secure:443",
+            Some(BackendTlsConfig {
+                ca_cert_path: Some(ca.clone()),
+                server_name: Some("localhost".to_string()),
+                client_cert_path: Some(ca),
+                client_key_path: Some(key_path.to_string_lossy().into_owned()),
+            }),
+        ),
+        tls_backend("plain:80", None),
+    ];
+    let pool = ClientPool::new(
+        &default_keep_alive_config(),
+        BackendPoolConfig::default(),
+        default_upstream_connect_ms(),
+    )
+    .with_backends(&backends)?;
+
+    assert!(pool.is_tls_backend("secure:443"));
+    assert!(!pool.is_tls_backend("plain:80"));
+    assert!(pool
+        .get_tls_client("secure:443", Some(Version::HTTP_11), false)
+        .is_some());
+    assert!(pool
+        .get_tls_client("secure:443", Some(Version::HTTP_2), true)
+        .is_some());
+    // `http_version = "auto"`: ALPN picks the protocol.
+    assert!(pool.get_tls_client("secure:443", None, false).is_some());
+    assert!(pool.get_tls_client("plain:80", None, false).is_none());
+    assert!(pool
+        .get_tls_client("plain:80", Some(Version::HTTP_11), false)
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn test_with_backend_tls_rejects_unreadable_ca() {
+    let missing = tmp_path("missing-ca.pem").to_string_lossy().into_owned();
+    let backends = vec![tls_backend(
+        "secure:443",
+        Some(BackendTlsConfig { ca_cert_path: Some(missing), ..Default::default() }),
+    )];
+    let pool = ClientPool::new(
+        &default_keep_alive_config(),
+        BackendPoolConfig::default(),
+        default_upstream_connect_ms(),
+    );
+    assert!(pool.with_backends(&backends).is_err());
+}
+
+#[tokio::test]
+async fn test_backend_pool_limits_bound_in_flight_requests(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut limited = tls_backend("limited:80", None);
+    limited.pool = Some(BackendPoolLimits {
+        max_connections: Some(1),
+        wait_timeout_ms: 20,
+        ..Default::default()
+    });
+    let backends = vec![limited, tls_backend("shared:80", None)];
+    let pool = ClientPool::new(
+        &default_keep_alive_config(),
+        BackendPoolConfig::default(),
+        default_upstream_connect_ms(),
+    )
+    .with_backends(&backends)?;
+
+    assert!(pool.has_dedicated_pool("limited:80"));
+    assert!(!pool.has_dedicated_pool("shared:80"));
+    assert!(pool
+        .get_backend_client("limited:80", Version::HTTP_11, false)
+        .is_some());
+    assert!(pool
+        .get_backend_client("limited:80", Version::HTTP_11, true)
+        .is_none());
+
+    let held = pool.acquire("limited:80", Version::HTTP_11).await;
+    assert!(matches!(held, Ok(Some(_))));
+    assert!(
+        pool.acquire("limited:80", Version::HTTP_11).await.is_err(),
+        "second request should time out waiting for the only connection"
+    );
+    drop(held);
+    assert!(matches!(pool.acquire("limited:80", Version::HTTP_11).await, Ok(Some(_))));
+
+    // No stream limit configured, and no limits at all for other backends.
+    assert!(matches!(pool.acquire("limited:80", Version::HTTP_2).await, Ok(None)));
+    assert!(matches!(pool.acquire("shared:80", Version::HTTP_11).await, Ok(None)));
+    Ok(())
+}
+
+#[test]
+fn test_unix_backend_gets_dedicated_clients() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let backends = vec![tls_backend("unix:///run/app.sock", None), tls_backend("plain:80", None)];
+    let pool = ClientPool::new(
+        &default_keep_alive_config(),
+        BackendPoolConfig::default(),
+        default_upstream_connect_ms(),
+    )
+    .with_backends(&backends)?;
+
+    assert!(pool.has_dedicated_pool("unix:///run/app.sock"));
+    assert!(!pool.has_dedicated_pool("plain:80"));
+    assert!(!pool.is_tls_backend("unix:///run/app.sock"));
+    let _oneoff = pool.create_oneoff_backend_client("unix:///run/app.sock", Version::HTTP_11);
+    Ok(())
+}
@@ -0,0 +1,279 @@
+//! `[client_tracking]`: signed client cookies, per-client records and the headers forwarded to
+//! the backend.
+
+use http::header::{COOKIE, SET_COOKIE};
+use http::{HeaderMap, HeaderValue, Response};
+use huginn_proxy_lib::config::{ClientTrackingConfig, Secret};
+use huginn_proxy_lib::proxy::client_tracking::{ClientRecord, ClientTracker, TrackedClient};
+use huginn_proxy_lib::telemetry::Metrics;
+
+use crate::helpers::error_message;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type TestResult = Result<(), BoxError>;
+
+const JA4: &str = "t13d1516h2_8daaf6152771_b186095e22b6";
+const OTHER_JA4: &str = "t13d1516h2_8daaf6152771_e5627efa2ab1";
+const FIRST_SEEN: &str = "x-huginn-client-first-seen";
+const LAST_SEEN: &str = "x-huginn-client-last-seen";
+const REQUEST_COUNT: &str = "x-huginn-client-request-count";
+
+fn config() -> ClientTrackingConfig {
+    ClientTrackingConfig {
+        enabled: true,
+        secret: Secret::new("0123456789abcdef0123456789abcdef".to_string()),
+        ttl_secs: 100,
+        ..ClientTrackingConfig::default()
+    }
+}
+
+fn new_tracker(config: &ClientTrackingConfig) -> Result<ClientTracker, BoxError> {
+    Ok(ClientTracker::from_config(config, Metrics::new_noop())?.ok_or("tracker expected")?)
+}
+
+/// `Cookie` header sending back the cookie set on the response to `client`.
+fn cookie_header(tracker: &ClientTracker, client: &TrackedClient) -> Result<HeaderMap, BoxError> {
+    let mut response = Response::new(());
+    tracker.set_cookie(&mut response, client, true);
+    let set_cookie = response
+        .headers()
+        .get(SET_COOKIE)
+        .ok_or("no Set-Cookie")?
+        .to_str()?;
+    let cookie = set_cookie.split(';').next().ok_or("empty Set-Cookie")?;
+    let mut headers = HeaderMap::new();
+    headers.insert(COOKIE, HeaderValue::from_str(&format!("theme=dark; {cookie}"))?);
+    Ok(headers)
+}
+
+fn header<'h>(headers: &'h HeaderMap, name: &str) -> Option<&'h str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+#[test]
+fn default_and_minimal_configs_validate() {
+    assert!(ClientTrackingConfig::default().validate().is_ok());
+    assert!(config().validate().is_ok());
+}
+
+#[test]
+fn short_secret_is_rejected() {
+    let err = error_message(
+        ClientTrackingConfig { secret: Secret::new("short".to_string()), ..config() }.validate(),
+    );
+    assert!(err.contains("client_tracking.secret must be at least 16 bytes"), "got: {err}");
+}
+
+#[test]
+fn invalid_cookie_name_is_rejected() {
+    let err = error_message(
+        ClientTrackingConfig { cookie_name: "bad name".to_string(), ..config() }.validate(),
+    );
+    assert!(err.contains("cookie_name 'bad name' is not a valid cookie name"), "got: {err}");
+}
+
+#[test]
+fn invalid_header_name_is_rejected() {
+    let err = error_message(
+        ClientTrackingConfig { request_count_header: "bad header".to_string(), ..config() }
+            .validate(),
+    );
+    assert!(
+        err.contains("request_count_header 'bad header' is not a valid HTTP header name"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn zero_ttl_is_rejected() {
+    let err = error_message(ClientTrackingConfig { ttl_secs: 0, ..config() }.validate());
+    assert!(err.contains("ttl_secs and max_entries must be greater than 0"), "got: {err}");
+}
+
+#[test]
+fn zero_max_entries_is_rejected() {
+    let err = error_message(ClientTrackingConfig { max_entries: 0, ..config() }.validate());
+    assert!(err.contains("ttl_secs and max_entries must be greater than 0"), "got: {err}");
+}
+
+#[test]
+fn disabled_creates_no_tracker() -> TestResult {
+    assert!(
+        ClientTracker::from_config(&ClientTrackingConfig::default(), Metrics::new_noop())?
+            .is_none()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn returning_clients_get_their_history() -> TestResult {
+    let tracker = new_tracker(&config())?;
+
+    // The client's own values of the headers never reach the backend.
+    let mut headers = HeaderMap::new();
+    headers.insert(FIRST_SEEN, HeaderValue::from_static("1"));
+    headers.insert(LAST_SEEN, HeaderValue::from_static("1"));
+    let first = tracker
+        .track_at(&mut headers, Some(JA4), 1_000)
+        .await
+        .ok_or("not tracked")?;
+    assert_eq!(
+        first.record,
+        ClientRecord { first_seen: 1_000, last_seen: None, request_count: 1 }
+    );
+    assert!(first.new_cookie.is_some());
+    assert_eq!(header(&headers, FIRST_SEEN), Some("1000"));
+    assert_eq!(header(&headers, LAST_SEEN), None);
+    assert_eq!(header(&headers, REQUEST_COUNT), Some("1"));
+
+    let mut response = Response::new(());
+    tracker.set_cookie(&mut response, &first, true);
+    let set_cookie = response
+        .headers()
+        .get(SET_COOKIE)
+        .ok_or("no Set-Cookie")?
+        .to_str()?;
+    assert!(set_cookie.starts_with("huginn_client="), "{set_cookie}");
+    assert!(
+        set_cookie.ends_with("; Max-Age=100; HttpOnly; SameSite=Lax; Secure"),
+        "{set_cookie}"
+    );
+
+    let mut headers = cookie_header(&tracker, &first)?;
+    let second = tracker
+        .track_at(&mut headers, Some(JA4), 1_010)
+        .await
+        .ok_or("not tracked")?;
+    assert_eq!(
+        second.record,
+        ClientRecord { first_seen: 1_000, last_seen: Some(1_000), request_count: 2 }
+    );
+    assert!(second.new_cookie.is_none());
+    assert_eq!(header(&headers, LAST_SEEN), Some("1000"));
+    assert_eq!(header(&headers, REQUEST_COUNT), Some("2"));
+
+    // A client that already has the cookie is not sent another one.
+    let mut response = Response::new(());
+    tracker.set_cookie(&mut response, &second, true);
+    assert!(response.headers().get(SET_COOKIE).is_none());
+
+    // The cookie is not accepted from another TLS stack; a record past its TTL starts over.
+    let mut headers = cookie_header(&tracker, &first)?;
+    let other = tracker
+        .track_at(&mut headers, Some(OTHER_JA4), 1_020)
+        .await
+        .ok_or("not tracked")?;
+    assert!(other.new_cookie.is_some());
+    assert_eq!(other.record.request_count, 1);
+    let mut headers = cookie_header(&tracker, &first)?;
+    let expired = tracker
+        .track_at(&mut headers, Some(JA4), 1_110)
+        .await
+        .ok_or("not tracked")?;
+    assert_eq!(
+        expired.record,
+        ClientRecord { first_seen: 1_110, last_seen: None, request_count: 1 }
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn forged_cookies_get_a_new_id() -> TestResult {
+    let tracker = new_tracker(&config())?;
+    let mut headers = HeaderMap::new();
+    let first = tracker
+        .track_at(&mut headers, None, 1_000)
+        .await
+        .ok_or("not tracked")?;
+    let cookie = first.new_cookie.ok_or("no cookie issued")?;
+
+    let (id, rest) = cookie.split_once('.').ok_or("unsigned cookie")?;
+    let (issued, mac) = rest.split_once('.').ok_or("unsigned cookie")?;
+    for forged in [
+        format!("{id}.{issued}.00"),
+        format!("{id}.{issued}."),
+        format!("{id}.{mac}"),
+        format!("{id}.999.{mac}"),
+        id.to_string(),
+        format!("{cookie}00"),
+    ] {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_str(&format!("huginn_client={forged}"))?);
+        let tracked = tracker
+            .track_at(&mut headers, None, 1_010)
+            .await
+            .ok_or("not tracked")?;
+        assert!(tracked.new_cookie.is_some(), "{forged}");
+        assert_eq!(tracked.record.request_count, 1, "{forged}");
+    }
+
+    // A cookie signed with another secret is not accepted either.
+    let other_secret = ClientTrackingConfig {
+        secret: Secret::new("fedcba9876543210fedcba9876543210".to_string()),
+        ..config()
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(COOKIE, HeaderValue::from_str(&format!("huginn_client={cookie}"))?);
+    let tracked = new_tracker(&other_secret)?
+        .track_at(&mut headers, None, 1_010)
+        .await
+        .ok_or("not tracked")?;
+    assert!(tracked.new_cookie.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn clients_without_the_cookie_are_not_recorded() -> TestResult {
+    let tracker = new_tracker(&config())?;
+    for now in 1_000..1_100 {
+        let mut headers = HeaderMap::new();
+        let tracked = tracker
+            .track_at(&mut headers, Some(JA4), now)
+            .await
+            .ok_or("not tracked")?;
+        assert!(tracked.new_cookie.is_some());
+    }
+    assert_eq!(tracker.memory_len(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn least_recently_seen_clients_are_evicted() -> TestResult {
+    let tracker = new_tracker(&ClientTrackingConfig { max_entries: 2, ..config() })?;
+    let mut clients = Vec::new();
+    for now in [1_000, 1_001, 1_002] {
+        let mut headers = HeaderMap::new();
+        let client = tracker
+            .track_at(&mut headers, None, now)
+            .await
+            .ok_or("not tracked")?;
+        // The record is created when the cookie comes back.
+        let mut headers = cookie_header(&tracker, &client)?;
+        tracker
+            .track_at(&mut headers, None, now)
+            .await
+            .ok_or("not tracked")?;
+        clients.push(client);
+    }
+    assert_eq!(tracker.memory_len(), 2);
+
+    // The first client was dropped and starts over from its cookie; the third is still known.
+    let first = clients.first().ok_or("no first client")?;
+    let mut headers = cookie_header(&tracker, first)?;
+    let tracked = tracker
+        .track_at(&mut headers, None, 1_003)
+        .await
+        .ok_or("not tracked")?;
+    assert_eq!(
+        tracked.record,
+        ClientRecord { first_seen: 1_000, last_seen: Some(1_000), request_count: 2 }
+    );
+    let third = clients.get(2).ok_or("no third client")?;
+    let mut headers = cookie_header(&tracker, third)?;
+    let tracked = tracker
+        .track_at(&mut headers, None, 1_004)
+        .await
+        .ok_or("not tracked")?;
+    assert_eq!(tracked.record.request_count, 3);
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Response compression: `Accept-Encoding` negotiation, eligibility rules and round trips through
+//! each encoder.
+
+use std::io::Read;
+
+use bytes::Bytes;
+use http::header::{
+    ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, VARY,
+};
+use http::{HeaderMap, HeaderValue, Method, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::config::{CompressionAlgorithm, CompressionConfig};
+use huginn_proxy_lib::proxy::compression::{compress_response, is_compressible, negotiate};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const ALL: &[CompressionAlgorithm] =
+    &[CompressionAlgorithm::Br, CompressionAlgorithm::Zstd, CompressionAlgorithm::Gzip];
+
+fn accepting(value: &'static str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(value));
+    headers
+}
+
+fn response(content_type: &'static str, body: &[u8]) -> Response<Full<Bytes>> {
+    let mut resp = Response::new(Full::new(Bytes::copy_from_slice(body)));
+    resp.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    resp.headers_mut()
+        .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+    resp
+}
+
+#[test]
+fn negotiation_follows_weights_then_config_order() {
+    let get = Method::GET;
+    assert_eq!(negotiate(&get, &accepting("gzip, br"), ALL), Some(CompressionAlgorithm::Br));
+    assert_eq!(
+        negotiate(&get, &accepting("br;q=0.5, gzip;q=0.9"), ALL),
+        Some(CompressionAlgorithm::Gzip)
+    );
+    assert_eq!(
+        negotiate(
+            &get,
+            &accepting("gzip, br"),
+            &[CompressionAlgorithm::Gzip, CompressionAlgorithm::Br]
+        ),
+        Some(CompressionAlgorithm::Gzip)
+    );
+    assert_eq!(negotiate(&get, &accepting("*"), ALL), Some(CompressionAlgorithm::Br));
+    assert_eq!(negotiate(&get, &accepting("*, br;q=0"), ALL), Some(CompressionAlgorithm::Zstd));
+    assert_eq!(negotiate(&get, &accepting("identity"), ALL), None);
+    assert_eq!(negotiate(&get, &accepting("gzip;q=0"), ALL), None);
+    assert_eq!(negotiate(&get, &HeaderMap::new(), ALL), None);
+    assert_eq!(negotiate(&Method::HEAD, &accepting("gzip"), ALL), None);
+}
+
+#[test]
+fn eligibility_checks_type_size_and_existing_encoding() {
+    let config = CompressionConfig::default();
+    let body = vec![b'a'; 2048];
+
+    assert!(is_compressible(&config, &response("text/html; charset=utf-8", &body)));
+    assert!(!is_compressible(&config, &response("image/png", &body)));
+    assert!(!is_compressible(&config, &response("text/html", b"tiny")));
+
+    let mut encoded = response("application/json", &body);
+    encoded
+        .headers_mut()
+        .insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    assert!(!is_compressible(&config, &encoded));
+
+    let mut no_transform = response("application/json", &body);
+    no_transform
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("public, no-transform"));
+    assert!(!is_compressible(&config, &no_transform));
+
+    let mut not_modified = response("text/html", &body);
+    *not_modified.status_mut() = StatusCode::NOT_MODIFIED;
+    assert!(!is_compressible(&config, &not_modified));
+
+    let wildcard = CompressionConfig { content_types: vec!["text/*".to_string()], ..config };
+    assert!(is_compressible(&wildcard, &response("text/markdown", &body)));
+}
+
+async fn compressed(
+    algorithm: CompressionAlgorithm,
+    body: &[u8],
+) -> Result<(HeaderMap, Bytes), BoxError> {
+    let mut resp = response("text/plain", body);
+    resp.headers_mut()
+        .insert(ETAG, HeaderValue::from_static("\"v1\""));
+    let resp = resp.map(|b| b.map_err(|never| match never {}).boxed());
+    let (parts, body) = compress_response(resp, algorithm).into_parts();
+    Ok((parts.headers, body.collect().await?.to_bytes()))
+}
+
+#[tokio::test]
+async fn compressed_bodies_round_trip() -> Result<(), BoxError> {
+    let original = "huginn proxy compression ".repeat(200).into_bytes();
+
+    for algorithm in
+        [CompressionAlgorithm::Br, CompressionAlgorithm::Zstd, CompressionAlgorithm::Gzip]
+    {
+        let (headers, encoded) = compressed(algorithm, &original).await?;
+        assert_eq!(
+            headers.get(CONTENT_ENCODING).map(HeaderValue::as_bytes),
+            Some(algorithm.as_str().as_bytes())
+        );
+        assert!(headers.get(CONTENT_LENGTH).is_none());
+        assert_eq!(headers.get(VARY), Some(&HeaderValue::from_static("accept-encoding")));
+        assert_eq!(headers.get(ETAG), Some(&HeaderValue::from_static("W/\"v1\"")));
+        assert!(encoded.len() < original.len());
+
+        let mut decoded = Vec::new();
+        match algorithm {
+            CompressionAlgorithm::Br => {
+                brotli::Decompressor::new(encoded.as_ref(), 4096).read_to_end(&mut decoded)?;
+            }
+            CompressionAlgorithm::Zstd => decoded = zstd::decode_all(encoded.as_ref())?,
+            CompressionAlgorithm::Gzip => {
+                flate2::read::GzDecoder::new(encoded.as_ref()).read_to_end(&mut decoded)?;
+            }
+        }
+        assert_eq!(decoded, original);
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::config::{BackendQueueConfig, RoutePriority};
+use huginn_proxy_lib::proxy::concurrency::{
+    BackendQueue, ConcurrencyLimits, InFlight, InFlightBody, QueuePermit, QueueRejection,
+};
+use huginn_proxy_lib::telemetry::Metrics;
+use tokio::task::JoinHandle;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[test]
+fn route_slots_are_limited_per_domain_and_prefix() {
+    let limits = ConcurrencyLimits::new();
+    let first = limits.try_acquire_route("example.com", "/api", 1);
+    assert!(first.is_some());
+    assert!(limits.try_acquire_route("example.com", "/api", 1).is_none());
+    assert!(limits
+        .try_acquire_route("example.com", "/static", 1)
+        .is_some());
+    assert!(limits.try_acquire_route("other.com", "/api", 1).is_some());
+
+    drop(first);
+    assert!(limits.try_acquire_route("example.com", "/api", 1).is_some());
+}
+
+#[test]
+fn changed_max_replaces_the_limit() {
+    let limits = ConcurrencyLimits::new();
+    let _held = limits.try_acquire_backend("backend:9000", 1);
+    assert!(limits.try_acquire_backend("backend:9000", 1).is_none());
+
+    let raised = [
+        limits.try_acquire_backend("backend:9000", 2),
+        limits.try_acquire_backend("backend:9000", 2),
+    ];
+    assert!(raised.iter().all(Option::is_some));
+    assert!(limits.try_acquire_backend("backend:9000", 2).is_none());
+}
+
+#[tokio::test]
+async fn body_releases_the_slot_once_read() -> TestResult {
+    let limits = ConcurrencyLimits::new();
+    let permit = limits
+        .try_acquire_backend("backend:9000", 1)
+        .ok_or("slot expected")?;
+    let in_flight = InFlight::new(Metrics::new_noop()).with_backend(permit, "backend:9000");
+    let mut body = InFlightBody::new(Full::new(Bytes::from_static(b"ok")), in_flight);
+    assert!(limits.try_acquire_backend("backend:9000", 1).is_none());
+
+    while let Some(frame) = body.frame().await {
+        frame?;
+    }
+    // Released at the end of the stream, before the body is dropped.
+    assert!(limits.try_acquire_backend("backend:9000", 1).is_some());
+    drop(body);
+    Ok(())
+}
+
+const ADDRESS: &str = "backend:9000";
+
+/// One slot, with `max_queued` requests allowed to wait for it.
+fn queue(max_queued: usize, timeout_ms: u64) -> Arc<BackendQueue> {
+    Arc::new(BackendQueue::new(1, BackendQueueConfig { max_queued, timeout_ms }))
+}
+
+async fn acquire(
+    queue: &Arc<BackendQueue>,
+    priority: RoutePriority,
+) -> Result<QueuePermit, QueueRejection> {
+    queue.acquire(priority, ADDRESS, &Metrics::new_noop()).await
+}
+
+fn wait(
+    queue: &Arc<BackendQueue>,
+    priority: RoutePriority,
+) -> JoinHandle<Result<QueuePermit, QueueRejection>> {
+    let queue = Arc::clone(queue);
+    tokio::spawn(async move { acquire(&queue, priority).await })
+}
+
+/// Let spawned waiters run until they are parked in the queue.
+async fn settle() {
+    for _ in 0..8 {
+        tokio::task::yield_now().await;
+    }
+}
+
+#[tokio::test]
+async fn freed_slots_go_to_the_highest_priority_first() -> TestResult {
+    let queue = queue(8, 5_000);
+    let held = acquire(&queue, RoutePriority::Normal).await.ok();
+    let low = wait(&queue, RoutePriority::Low);
+    settle().await;
+    let high = wait(&queue, RoutePriority::High);
+    settle().await;
+
+    drop(held);
+    let high = high
+        .await?
+        .ok()
+        .ok_or("high priority should get the slot")?;
+    settle().await;
+    assert!(!low.is_finished());
+
+    drop(high);
+    assert!(low.await?.is_ok());
+    Ok(())
+}
+
+#[tokio::test]
+async fn full_queue_displaces_lower_priorities_only() -> TestResult {
+    let queue = queue(1, 5_000);
+    let _held = acquire(&queue, RoutePriority::Normal).await.ok();
+    let low = wait(&queue, RoutePriority::Low);
+    settle().await;
+    let normal = wait(&queue, RoutePriority::Normal);
+    settle().await;
+
+    assert_eq!(low.await?.err(), Some(QueueRejection::Full));
+    assert_eq!(acquire(&queue, RoutePriority::Normal).await.err(), Some(QueueRejection::Full));
+    assert!(!normal.is_finished());
+    normal.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn abandoned_waits_do_not_keep_the_slot() -> TestResult {
+    let queue = queue(4, 50);
+    let held = acquire(&queue, RoutePriority::Normal).await.ok();
+    assert_eq!(acquire(&queue, RoutePriority::High).await.err(), Some(QueueRejection::Timeout));
+
+    let gone = wait(&queue, RoutePriority::Normal);
+    settle().await;
+    // The slot is handed to `gone`, whose client goes away before it runs again.
+    drop(held);
+    gone.abort();
+    assert!(gone.await.is_err());
+
+    assert!(acquire(&queue, RoutePriority::Normal).await.is_ok());
+    Ok(())
+}
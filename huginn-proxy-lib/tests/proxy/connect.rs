@@ -0,0 +1,193 @@
+//! `connect` on a route: config validation, which targets a tunnel may reach, routing of
+//! `CONNECT` requests and opening tunnels.
+
+use http::{HeaderMap, Method, Request, StatusCode};
+use huginn_proxy_lib::config::{sort_routes, Backend, BackendTlsConfig, ConnectConfig, Route};
+use huginn_proxy_lib::proxy::connect::{
+    connect_target, open_tunnel, target_allowed, target_backend, TunnelContext,
+};
+use huginn_proxy_lib::proxy::router::pick_request_route;
+use huginn_proxy_lib::proxy::HttpError;
+use huginn_proxy_lib::telemetry::Metrics;
+use tokio::net::TcpListener;
+
+use crate::helpers::error_message;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+fn connect(targets: &[&str]) -> ConnectConfig {
+    ConnectConfig {
+        targets: targets.iter().map(ToString::to_string).collect(),
+        ..ConnectConfig::default()
+    }
+}
+
+fn routes(connect_route: Option<ConnectConfig>) -> Vec<Route> {
+    let mut routes = vec![
+        Route {
+            prefix: "/api".to_string(),
+            backend: "api.internal:8080".to_string(),
+            ..Route::default()
+        },
+        Route {
+            prefix: "/".to_string(),
+            backend: "app.internal:8443".to_string(),
+            connect: connect_route,
+            ..Route::default()
+        },
+    ];
+    sort_routes(&mut routes);
+    routes
+}
+
+fn connect_request(target: &str) -> Result<Request<()>, http::Error> {
+    Request::builder()
+        .method(Method::CONNECT)
+        .uri(target)
+        .body(())
+}
+
+#[test]
+fn default_and_listed_targets_validate() {
+    assert!(ConnectConfig::default().validate().is_ok());
+    assert!(connect(&["mirror.internal:443", "*.corp.example:*", "[::1]:8443"])
+        .validate()
+        .is_ok());
+}
+
+#[test]
+fn zero_idle_timeout_is_rejected() {
+    let err = error_message(
+        ConnectConfig { idle_timeout_secs: 0, ..ConnectConfig::default() }.validate(),
+    );
+    assert!(err.contains("connect.idle_timeout_secs must be greater than 0"), "got: {err}");
+}
+
+#[test]
+fn targets_without_a_port_are_rejected() {
+    let err = error_message(connect(&["mirror.internal"]).validate());
+    assert!(
+        err.contains("connect.targets: 'mirror.internal' is not a host:port target"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn targets_without_a_host_are_rejected() {
+    for target in ["*:443", ":443"] {
+        let err = error_message(connect(&[target]).validate());
+        assert!(
+            err.contains(&format!("connect.targets: '{target}' is not a host:port target")),
+            "got: {err}"
+        );
+    }
+}
+
+#[test]
+fn targets_with_an_invalid_port_are_rejected() {
+    for target in ["mirror.internal:0", "mirror.internal:https"] {
+        let err = error_message(connect(&[target]).validate());
+        assert!(
+            err.contains(&format!("connect.targets: '{target}' is not a host:port target")),
+            "got: {err}"
+        );
+    }
+}
+
+#[test]
+fn wildcards_inside_the_host_are_rejected() {
+    let err = error_message(connect(&["a.*.example:443"]).validate());
+    assert!(
+        err.contains("connect.targets: 'a.*.example:443' is not a host:port target"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn targets_match_host_and_port() {
+    let config = connect(&["Mirror.Internal:443", "*.corp.example:*", "[::1]:8443"]);
+    assert!(config.allows("mirror.internal", 443));
+    assert!(!config.allows("mirror.internal", 80));
+    assert!(config.allows("git.corp.example", 22));
+    assert!(config.allows("a.b.corp.example", 443));
+    assert!(!config.allows("corp.example", 443));
+    assert!(!config.allows("evilcorp.example", 443));
+    assert!(config.allows("::1", 8443));
+}
+
+#[test]
+fn connect_requests_only_match_connect_routes() {
+    let headers = HeaderMap::new();
+    let without = routes(None);
+    assert!(pick_request_route(&Method::CONNECT, "", &headers, &without).is_none());
+
+    let with = routes(Some(ConnectConfig::default()));
+    let tunnel = pick_request_route(&Method::CONNECT, "", &headers, &with);
+    assert_eq!(tunnel.map(|m| (m.matched_prefix, m.connect.is_some())), Some(("/", true)));
+
+    // Other requests keep matching by path.
+    let api = pick_request_route(&Method::GET, "/api/users", &headers, &with);
+    assert_eq!(api.map(|m| m.matched_prefix), Some("/api"));
+}
+
+#[test]
+fn tunnels_reach_the_route_backend_and_listed_targets() -> TestResult {
+    let req = connect_request("Mirror.Internal:443")?;
+    assert_eq!(connect_target(&req), Some(("mirror.internal".to_string(), 443)));
+    let req = connect_request("[::1]:8443")?;
+    assert_eq!(connect_target(&req), Some(("::1".to_string(), 8443)));
+
+    let routes = routes(Some(connect(&["mirror.internal:443"])));
+    let route_match = pick_request_route(&Method::CONNECT, "", &HeaderMap::new(), &routes)
+        .ok_or("no CONNECT route")?;
+    assert!(target_allowed(&route_match, "app.internal", 8443));
+    assert!(target_allowed(&route_match, "mirror.internal", 443));
+    assert!(!target_allowed(&route_match, "app.internal", 22));
+    assert!(!target_allowed(&route_match, "example.com", 443));
+
+    let backends = [
+        Backend { address: "api.internal:8080".to_string(), ..Backend::default() },
+        Backend {
+            address: "app.internal:8443".to_string(),
+            tls: Some(BackendTlsConfig::default()),
+            ..Backend::default()
+        },
+    ];
+    let backend = target_backend(&backends, "APP.internal", 8443).ok_or("backend not found")?;
+    assert!(backend.tls.is_some());
+    assert!(target_backend(&backends, "mirror.internal", 443).is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn open_tunnel_dials_allowed_targets_only() -> TestResult {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let closed = TcpListener::bind("127.0.0.1:0").await?;
+    let closed_port = closed.local_addr()?.port();
+    drop(closed);
+
+    let routes = routes(Some(connect(&["127.0.0.1:*"])));
+    let route_match = pick_request_route(&Method::CONNECT, "", &HeaderMap::new(), &routes)
+        .ok_or("no CONNECT route")?;
+    let config = route_match.connect.ok_or("no connect block")?;
+    let tunnels = TunnelContext::default();
+    let metrics = Metrics::new_noop();
+    let peer = "192.0.2.10:50000".parse()?;
+
+    let mut req = connect_request(&format!("127.0.0.1:{port}"))?;
+    let (response, target) =
+        open_tunnel(&mut req, config, &route_match, &[], &tunnels, &metrics, peer).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(target, format!("127.0.0.1:{port}"));
+    listener.accept().await?;
+
+    let mut req = connect_request("192.0.2.1:25")?;
+    let refused = open_tunnel(&mut req, config, &route_match, &[], &tunnels, &metrics, peer).await;
+    assert!(matches!(refused, Err(HttpError::ConnectTargetRefused(_))));
+
+    let mut req = connect_request(&format!("127.0.0.1:{closed_port}"))?;
+    let failed = open_tunnel(&mut req, config, &route_match, &[], &tunnels, &metrics, peer).await;
+    assert!(matches!(failed, Err(HttpError::FailedToGetResponseFromBackend(_))));
+    Ok(())
+}
@@ -1 +1,3 @@
 mod connection_limit;
+mod per_ip;
+mod registry;
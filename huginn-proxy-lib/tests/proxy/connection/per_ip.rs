@@ -0,0 +1,86 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+
+use huginn_proxy_lib::proxy::connection::{ConnectionError, ConnectionManager};
+use huginn_proxy_lib::telemetry::Metrics;
+use tokio::sync::watch;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+const OTHER: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+
+fn manager(max_connections: usize, per_ip: Option<usize>) -> ConnectionManager {
+    let (closed_tx, _) = watch::channel(());
+    ConnectionManager::new(max_connections, Arc::new(AtomicUsize::new(0)), closed_tx)
+        .with_max_connections_per_ip(per_ip, &Metrics::new_noop())
+}
+
+#[test]
+fn per_ip_limit_rejects_only_the_busy_client() -> TestResult {
+    let manager = manager(16, Some(2));
+    let metrics = Metrics::new_noop();
+
+    let first = manager.try_accept_ip(CLIENT, &metrics)?;
+    let second = manager.try_accept_ip(CLIENT, &metrics)?;
+    assert!(first.is_some() && second.is_some());
+    assert!(matches!(
+        manager.try_accept_ip(CLIENT, &metrics),
+        Err(ConnectionError::PerIpLimitExceeded { ip, limit: 2 }) if ip == CLIENT
+    ));
+    assert!(manager.try_accept_ip(OTHER, &metrics)?.is_some());
+    Ok(())
+}
+
+#[test]
+fn per_ip_slot_is_released_on_drop() -> TestResult {
+    let manager = manager(16, Some(1));
+    let metrics = Metrics::new_noop();
+
+    let guard = manager.try_accept_ip(CLIENT, &metrics)?;
+    assert!(manager.try_accept_ip(CLIENT, &metrics).is_err());
+    drop(guard);
+    assert!(manager.try_accept_ip(CLIENT, &metrics)?.is_some());
+    Ok(())
+}
+
+#[test]
+fn no_per_ip_limit_tracks_nothing() -> TestResult {
+    let manager = manager(16, None);
+    let metrics = Metrics::new_noop();
+
+    for _ in 0..32 {
+        assert!(manager.try_accept_ip(CLIENT, &metrics)?.is_none());
+    }
+    Ok(())
+}
+
+#[test]
+fn global_limit_holds_under_concurrent_accepts() -> TestResult {
+    let manager = Arc::new(manager(8, None));
+    let metrics = Metrics::new_noop();
+    let peer = SocketAddr::new(CLIENT, 40000);
+
+    let handles: Vec<_> = (0..32)
+        .map(|_| {
+            let manager = Arc::clone(&manager);
+            let metrics = Arc::clone(&metrics);
+            std::thread::spawn(move || manager.try_accept(peer, &metrics).ok())
+        })
+        .collect();
+    let mut guards = Vec::new();
+    for handle in handles {
+        if let Some(guard) = handle.join().map_err(|_| "accept thread panicked")? {
+            guards.push(guard);
+        }
+    }
+    assert_eq!(guards.len(), 8);
+    assert_eq!(
+        manager
+            .active_connections()
+            .load(std::sync::atomic::Ordering::Relaxed),
+        8
+    );
+    Ok(())
+}
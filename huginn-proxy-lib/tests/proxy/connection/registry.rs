@@ -0,0 +1,127 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use huginn_proxy_lib::proxy::connection::{
+    ConnectionContext, ConnectionRegistry, ConnectionTls, Ja4Set,
+};
+use huginn_proxy_lib::security::TlsMetadata;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const JA4: &str = "t13d1516h2_8daaf6152771_02713d6af862";
+
+fn context(peer: &str) -> Result<Arc<ConnectionContext>, std::net::AddrParseError> {
+    Ok(Arc::new(ConnectionContext::new(peer.parse()?)))
+}
+
+fn tls(sni: &str, alpn: &str) -> ConnectionTls {
+    ConnectionTls {
+        sni: Some(Arc::from(sni)),
+        alpn: Some(Arc::from(alpn)),
+        ja4: Some(Ja4Set {
+            ja4: Arc::from(JA4),
+            ja4_o: Arc::from("t13d1516h2_acb858a92679_e5627efa2ab1"),
+            ja4_s1: Arc::from("t13d1516h2_8daaf6152771_d8a2da3f94cd"),
+        }),
+    }
+}
+
+#[test]
+fn context_details_are_set_once() -> TestResult {
+    let first = context("203.0.113.7:40000")?;
+    let second = context("203.0.113.7:40001")?;
+    assert_ne!(first.id(), second.id());
+    assert_eq!(first.tls_metadata(), TlsMetadata::default());
+
+    first.set_tls(tls("Example.com", "h2"));
+    first.set_tls(tls("other.example", "http/1.1"));
+    assert_eq!(first.sni(), Some("Example.com"));
+    assert_eq!(first.alpn(), Some("h2"));
+    assert_eq!(
+        first.tls_metadata(),
+        TlsMetadata { sni: Some("Example.com"), alpn: Some("h2"), ja4: Some(JA4) }
+    );
+    assert_eq!(first.akamai(), None);
+    assert_eq!(second.tls(), None);
+
+    let syn = ConnectionContext::new("203.0.113.7:40002".parse()?).with_tcp_syn("4:64".to_string());
+    assert_eq!(syn.tcp_syn(), Some("4:64"));
+    Ok(())
+}
+
+#[test]
+fn registry_lists_connections_until_dropped() -> TestResult {
+    let registry = ConnectionRegistry::new();
+    let peer: SocketAddr = "203.0.113.7:40000".parse()?;
+
+    let tls_context = Arc::new(ConnectionContext::new(peer));
+    let first = registry
+        .register(&tls_context, "0.0.0.0:443", true)
+        .ok_or("enabled registry must track")?;
+    // Set after registration: the registry reads the shared context when listed.
+    tls_context.set_tls(tls("example.com", "h2"));
+    let plain_context = Arc::new(
+        ConnectionContext::new(peer)
+            .with_tcp_syn("4:64+0:0:1460:mss*44,10:mss,sok,ts,nop,ws:df,id+:0".to_string()),
+    );
+    let second = registry
+        .register(&plain_context, "0.0.0.0:80", false)
+        .ok_or("enabled registry must track")?;
+
+    let snapshot = registry.snapshot();
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(snapshot[0].id, tls_context.id());
+    assert_eq!(snapshot[0].listener, "0.0.0.0:443");
+    assert!(snapshot[0].tls);
+    assert_eq!(snapshot[0].sni.as_deref(), Some("example.com"));
+    assert_eq!(snapshot[0].alpn.as_deref(), Some("h2"));
+    assert_eq!(snapshot[0].ja4.as_deref(), Some(JA4));
+    assert_eq!(snapshot[0].akamai, None);
+    assert_eq!(snapshot[1].id, plain_context.id());
+    assert_eq!(snapshot[1].sni, None);
+    assert!(snapshot[1].tcp_syn.is_some());
+
+    drop(first);
+    let snapshot = registry.snapshot();
+    assert_eq!(snapshot.len(), 1);
+    assert_eq!(snapshot[0].listener, "0.0.0.0:80");
+    drop(second);
+    assert!(registry.is_empty());
+    Ok(())
+}
+
+#[test]
+fn disabled_registry_tracks_nothing() -> TestResult {
+    let registry = ConnectionRegistry::disabled();
+    assert!(registry
+        .register(&context("127.0.0.1:1")?, "127.0.0.1:8080", false)
+        .is_none());
+    assert!(registry.snapshot().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn counted_stream_reports_connection_traffic() -> TestResult {
+    use huginn_proxy_lib::proxy::connection::CountedStream;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let registry = ConnectionRegistry::new();
+    let tracked = registry
+        .register(&context("203.0.113.7:40000")?, "0.0.0.0:80", false)
+        .ok_or("enabled registry must track")?;
+    let (mut client, server) = tokio::io::duplex(64);
+    let mut server = CountedStream::new(server, Some(tracked.traffic()));
+
+    client.write_all(b"GET / HTTP/1.1\r\n\r\n").await?;
+    let mut request = [0u8; 18];
+    server.read_exact(&mut request).await?;
+    server.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await?;
+
+    let info = registry.snapshot().pop().ok_or("connection listed")?;
+    assert_eq!(info.bytes_received, 18);
+    assert_eq!(info.bytes_sent, 27);
+    // Younger than a second: the rate is the byte count so far.
+    assert_eq!(info.received_bytes_per_sec, 18);
+    assert_eq!(info.sent_bytes_per_sec, 27);
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use bytes::Bytes;
+use http::{Request, Version};
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::proxy::echo::echo_response;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[tokio::test]
+async fn reflects_the_upstream_request() -> TestResult {
+    let req = Request::post("http://echo/v1/users?page=2")
+        .version(Version::HTTP_11)
+        .header("x-tls-ja4", "t13d1516h2_8daaf6152771_02713d6af862")
+        .header("accept", "text/html")
+        .header("accept", "application/json")
+        .body(Full::new(Bytes::from_static(b"hello")))?;
+
+    let response = echo_response(req, Version::HTTP_2).await?;
+    assert_eq!(response.status(), 200);
+    let body = response.into_body().collect().await?.to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body)?;
+
+    assert_eq!(json["method"], "POST");
+    assert_eq!(json["uri"], "/v1/users?page=2");
+    assert_eq!(json["protocol"], "HTTP/1.1");
+    assert_eq!(json["client_protocol"], "HTTP/2.0");
+    assert_eq!(json["headers"]["x-tls-ja4"], "t13d1516h2_8daaf6152771_02713d6af862");
+    assert_eq!(json["headers"]["accept"], "text/html, application/json");
+    assert_eq!(json["body_bytes"], 5);
+    Ok(())
+}
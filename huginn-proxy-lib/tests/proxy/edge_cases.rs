@@ -10,19 +10,13 @@ fn test_pick_route_no_match() {
             prefix: "/api".to_string(),
             backend: "backend-a:9000".to_string(),
             fingerprinting: Some(true),
-            replace_path: None,
-            security: None,
-            headers: None,
-            force_new_connection: false,
+            ..Default::default()
         },
         Route {
             prefix: "/static".to_string(),
             backend: "backend-b:9000".to_string(),
             fingerprinting: Some(true),
-            replace_path: None,
-            security: None,
-            headers: None,
-            force_new_connection: false,
+            ..Default::default()
         },
     ];
 
@@ -36,19 +30,13 @@ fn test_pick_route_exact_match() {
             prefix: "/api".to_string(),
             backend: "backend-a:9000".to_string(),
             fingerprinting: Some(true),
-            replace_path: None,
-            security: None,
-            headers: None,
-            force_new_connection: false,
+            ..Default::default()
         },
         Route {
             prefix: "/".to_string(),
             backend: "backend-b:9000".to_string(),
             fingerprinting: Some(true),
-            replace_path: None,
-            security: None,
-            headers: None,
-            force_new_connection: false,
+            ..Default::default()
         },
     ];
 
@@ -63,28 +51,19 @@ fn test_pick_route_longest_prefix() {
             prefix: "/api/v1".to_string(),
             backend: "backend-v1:9000".to_string(),
             fingerprinting: Some(true),
-            replace_path: None,
-            security: None,
-            headers: None,
-            force_new_connection: false,
+            ..Default::default()
         },
         Route {
             prefix: "/api".to_string(),
             backend: "backend-api:9000".to_string(),
             fingerprinting: Some(true),
-            replace_path: None,
-            security: None,
-            headers: None,
-            force_new_connection: false,
+            ..Default::default()
         },
         Route {
             prefix: "/".to_string(),
             backend: "backend-default:9000".to_string(),
             fingerprinting: Some(true),
-            replace_path: None,
-            security: None,
-            headers: None,
-            force_new_connection: false,
+            ..Default::default()
         },
     ];
 
@@ -97,6 +76,20 @@ fn test_find_backend_config_case_sensitive() {
         address: "Backend-A:9000".to_string(),
         http_version: None,
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     }];
 
     assert_eq!(
@@ -115,6 +108,20 @@ fn test_determine_http_version_http3_conversion() {
         address: "backend:9000".to_string(),
         http_version: Some(BackendHttpVersion::Preserve),
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     };
 
     assert_eq!(
@@ -133,22 +140,44 @@ fn test_determine_http_version_unknown_version() {
         address: "backend:9000".to_string(),
         http_version: Some(BackendHttpVersion::Preserve),
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     };
 
     let result = determine_http_version(Some(&backend_preserve), Version::HTTP_11, false);
     assert!(matches!(result, Version::HTTP_11 | Version::HTTP_2));
 }
 
+#[test]
+fn test_determine_http_version_auto_writes_http11() {
+    let backend_auto =
+        Backend { http_version: Some(BackendHttpVersion::Auto), ..Backend::new("backend:443") };
+    // The request is written as HTTP/1.1; ALPN may still carry it over h2.
+    assert_eq!(
+        determine_http_version(Some(&backend_auto), Version::HTTP_2, false),
+        Version::HTTP_11
+    );
+}
+
 #[test]
 fn test_pick_route_with_empty_prefix() {
     let routes = vec![Route {
         prefix: "".to_string(),
         backend: "backend-default:9000".to_string(),
         fingerprinting: Some(true),
-        replace_path: None,
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     assert_eq!(pick_route("/any/path", &routes), Some("backend-default:9000"));
@@ -158,8 +187,44 @@ fn test_pick_route_with_empty_prefix() {
 #[test]
 fn test_find_backend_config_with_port_variations() {
     let backends = vec![
-        Backend { address: "localhost:9000".to_string(), http_version: None, health_check: None },
-        Backend { address: "127.0.0.1:9000".to_string(), http_version: None, health_check: None },
+        Backend {
+            address: "localhost:9000".to_string(),
+            http_version: None,
+            health_check: None,
+            circuit_breaker: None,
+            tls: None,
+            pool: None,
+            max_in_flight: None,
+            queue: None,
+            discovery: None,
+            drain: false,
+            drain_timeout_secs: None,
+            slow_start_secs: None,
+            echo: false,
+            tcp: None,
+            proxy: None,
+            bandwidth: None,
+            tenant: None,
+        },
+        Backend {
+            address: "127.0.0.1:9000".to_string(),
+            http_version: None,
+            health_check: None,
+            circuit_breaker: None,
+            tls: None,
+            pool: None,
+            max_in_flight: None,
+            queue: None,
+            discovery: None,
+            drain: false,
+            drain_timeout_secs: None,
+            slow_start_secs: None,
+            echo: false,
+            tcp: None,
+            proxy: None,
+            bandwidth: None,
+            tenant: None,
+        },
     ];
 
     assert_eq!(
@@ -0,0 +1,181 @@
+//! External authorization: check request shape, upstream header hand-off, and decisions
+//! against an in-process hyper authorization service.
+
+use bytes::Bytes;
+use http::header::{AUTHORIZATION, CONNECTION, HOST, LOCATION};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode, Uri};
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::config::{
+    BackendPoolConfig, ExtAuthzConfig, ExtAuthzFailureMode, KeepAliveConfig,
+};
+use huginn_proxy_lib::proxy::ext_authz::{
+    apply_upstream_headers, check, check_request, AuthzDecision, FORWARDED_METHOD, FORWARDED_URI,
+};
+use huginn_proxy_lib::proxy::ClientPool;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use tokio::net::TcpListener;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+fn config(url: String) -> ExtAuthzConfig {
+    ExtAuthzConfig {
+        url,
+        timeout_ms: 1000,
+        failure_mode: ExtAuthzFailureMode::Deny,
+        status_on_error: 403,
+        upstream_headers: vec!["x-user-id".to_string()],
+    }
+}
+
+fn pool() -> ClientPool {
+    let keep_alive = KeepAliveConfig { enabled: true, upstream_idle_timeout: 90 };
+    ClientPool::new(&keep_alive, BackendPoolConfig::default(), Some(1000))
+}
+
+/// Allows requests carrying `authorization: Bearer ok` (granting `x-user-id: 42`) and
+/// redirects everything else to a login page.
+async fn spawn_authz_server() -> Result<std::net::SocketAddr, BoxError> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let svc = service_fn(|req: Request<Incoming>| async move {
+                let allowed = req
+                    .headers()
+                    .get(AUTHORIZATION)
+                    .is_some_and(|v| v == "Bearer ok");
+                let response = if allowed {
+                    Response::builder()
+                        .header("x-user-id", "42")
+                        .body(Full::new(Bytes::new()))
+                } else {
+                    Response::builder()
+                        .status(StatusCode::FOUND)
+                        .header(LOCATION, "/login")
+                        .body(Full::new(Bytes::from_static(b"login required")))
+                };
+                Ok::<_, Infallible>(response.unwrap_or_default())
+            });
+            tokio::spawn(
+                hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), svc),
+            );
+        }
+    });
+    Ok(addr)
+}
+
+#[test]
+fn check_request_forwards_metadata_without_host_or_hop_by_hop() -> Result<(), BoxError> {
+    let mut headers = HeaderMap::new();
+    headers.insert(HOST, HeaderValue::from_static("app.example.com"));
+    headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+    headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer ok"));
+    headers.insert("x-tls-ja4", HeaderValue::from_static("t13d1516h2_8daaf6152771_b186095e22b6"));
+    let uri: Uri = "/api/orders?page=2".parse()?;
+
+    let request = check_request(
+        &config("http://authz:9000/check".to_string()),
+        &Method::POST,
+        &uri,
+        &headers,
+    )?;
+    assert_eq!(request.method(), Method::GET);
+    assert_eq!(request.uri(), "http://authz:9000/check");
+    let sent = request.headers();
+    assert_eq!(sent.get(FORWARDED_METHOD).map(HeaderValue::as_bytes), Some(&b"POST"[..]));
+    assert_eq!(
+        sent.get(FORWARDED_URI).map(HeaderValue::as_bytes),
+        Some(&b"/api/orders?page=2"[..])
+    );
+    assert!(sent.contains_key(AUTHORIZATION));
+    assert!(sent.contains_key("x-tls-ja4"));
+    assert!(!sent.contains_key(HOST));
+    assert!(!sent.contains_key(CONNECTION));
+    Ok(())
+}
+
+#[test]
+fn upstream_headers_replace_client_copies() {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-user-id", HeaderValue::from_static("forged"));
+    let authz = config("http://authz:9000/".to_string());
+
+    apply_upstream_headers(
+        &mut headers,
+        &authz,
+        vec![(HeaderName::from_static("x-user-id"), HeaderValue::from_static("42"))],
+    );
+    assert_eq!(headers.get_all("x-user-id").iter().count(), 1);
+    assert_eq!(headers.get("x-user-id").map(HeaderValue::as_bytes), Some(&b"42"[..]));
+
+    apply_upstream_headers(&mut headers, &authz, Vec::new());
+    assert!(!headers.contains_key("x-user-id"));
+}
+
+#[tokio::test]
+async fn allow_and_deny_decisions_from_service() -> Result<(), BoxError> {
+    let addr = spawn_authz_server().await?;
+    let authz = config(format!("http://{addr}/check"));
+    let pool = pool();
+    let uri: Uri = "/api".parse()?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer ok"));
+    let request = check_request(&authz, &Method::GET, &uri, &headers)?;
+    match check(pool.authz_client(), &authz, request).await {
+        AuthzDecision::Allow(granted) => {
+            assert_eq!(granted.len(), 1);
+            assert_eq!(granted.first().map(|(_, v)| v.as_bytes()), Some(&b"42"[..]));
+        }
+        other => return Err(format!("expected allow, got {}", other.label()).into()),
+    }
+
+    let request = check_request(&authz, &Method::GET, &uri, &HeaderMap::new())?;
+    match check(pool.authz_client(), &authz, request).await {
+        AuthzDecision::Deny(response) => {
+            assert_eq!(response.status(), StatusCode::FOUND);
+            assert_eq!(
+                response.headers().get(LOCATION).map(HeaderValue::as_bytes),
+                Some(&b"/login"[..])
+            );
+            let body = response.into_body().collect().await?.to_bytes();
+            assert_eq!(body, Bytes::from_static(b"login required"));
+        }
+        other => return Err(format!("expected deny, got {}", other.label()).into()),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn unreachable_service_fails() -> Result<(), BoxError> {
+    // Bind then drop to get a port with nothing listening.
+    let addr = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+    let authz = config(format!("http://{addr}/check"));
+    let request = check_request(&authz, &Method::GET, &"/".parse()?, &HeaderMap::new())?;
+    let decision = check(pool().authz_client(), &authz, request).await;
+    assert_eq!(decision.label(), "error");
+    Ok(())
+}
+
+#[test]
+fn validate_rejects_bad_settings() {
+    assert!(config("http://authz:9000/check".to_string())
+        .validate()
+        .is_ok());
+    for url in ["https://authz/check", "/check", "not a url"] {
+        assert!(config(url.to_string()).validate().is_err(), "{url} should be rejected");
+    }
+    let zero_timeout = ExtAuthzConfig { timeout_ms: 0, ..config("http://authz/".to_string()) };
+    assert!(zero_timeout.validate().is_err());
+    let bad_status = ExtAuthzConfig { status_on_error: 200, ..config("http://authz/".to_string()) };
+    assert!(bad_status.validate().is_err());
+    let bad_header = ExtAuthzConfig {
+        upstream_headers: vec!["x user".to_string()],
+        ..config("http://authz/".to_string())
+    };
+    assert!(bad_header.validate().is_err());
+}
@@ -0,0 +1,264 @@
+//! `failover` on a route: config validation, which requests can be replayed (with and without
+//! `replay_body_bytes`), which answers fail over, and that a buffered `POST` that timed out is
+//! not sent to a second backend.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::header::{CONNECTION, UPGRADE};
+use http::{HeaderName, HeaderValue, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::config::{FailoverConfig, RouteProtocol, RouteTimeoutConfig};
+use huginn_proxy_lib::proxy::failover::{buffer_body, failover_reason, never_sent, replay};
+use huginn_proxy_lib::proxy::prefetch::PrefetchedBody;
+use huginn_proxy_lib::proxy::route_timeout::TimeoutKind;
+use huginn_proxy_lib::proxy::HttpError;
+use huginn_proxy_lib::{Backend, ProxyBuilder, Route};
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder as ConnBuilder;
+use tokio::net::TcpListener;
+
+use crate::helpers::error_message;
+use crate::hot_reload::helpers::free_port;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+fn request(method: Method, body: &'static [u8]) -> Request<PrefetchedBody<Full<Bytes>>> {
+    let mut req = Request::new(PrefetchedBody::new(Full::new(Bytes::from_static(body))));
+    *req.method_mut() = method;
+    *req.uri_mut() = http::Uri::from_static("/items?page=2");
+    req
+}
+
+fn response(status: StatusCode, header: Option<&'static str>) -> Response<()> {
+    let mut response = Response::new(());
+    *response.status_mut() = status;
+    if let Some(name) = header {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(name), HeaderValue::from_static("1"));
+    }
+    response
+}
+
+#[test]
+fn default_and_header_only_configs_validate() {
+    assert!(FailoverConfig::default().validate().is_ok());
+    let header_only = FailoverConfig {
+        statuses: Vec::new(),
+        header: Some("x-backend-warming".to_string()),
+        ..FailoverConfig::default()
+    };
+    assert!(header_only.validate().is_ok());
+}
+
+#[test]
+fn statuses_out_of_range_are_rejected() {
+    for (statuses, bad) in [(vec![503, 600], 600), (vec![99], 99)] {
+        let err =
+            error_message(FailoverConfig { statuses, ..FailoverConfig::default() }.validate());
+        assert!(
+            err.contains(&format!("failover.statuses: {bad} is not an HTTP status")),
+            "got: {err}"
+        );
+    }
+}
+
+#[test]
+fn nothing_to_fail_over_on_is_rejected() {
+    let err = error_message(
+        FailoverConfig { statuses: Vec::new(), ..FailoverConfig::default() }.validate(),
+    );
+    assert!(
+        err.contains("failover needs statuses or a header to fail over on"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn invalid_header_name_is_rejected() {
+    let err = error_message(
+        FailoverConfig { header: Some("bad header".to_string()), ..FailoverConfig::default() }
+            .validate(),
+    );
+    assert!(
+        err.contains("failover.header 'bad header' is not a valid HTTP header name"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn max_attempts_out_of_range_are_rejected() {
+    for max_attempts in [1, 11] {
+        let err =
+            error_message(FailoverConfig { max_attempts, ..FailoverConfig::default() }.validate());
+        assert!(
+            err.contains(&format!(
+                "failover.max_attempts must be between 2 and 10, got {max_attempts}"
+            )),
+            "got: {err}"
+        );
+    }
+}
+
+#[test]
+fn oversized_replay_body_bytes_is_rejected() {
+    let err = error_message(
+        FailoverConfig { replay_body_bytes: 2 * 1024 * 1024, ..FailoverConfig::default() }
+            .validate(),
+    );
+    assert!(err.contains("failover.replay_body_bytes must be at most"), "got: {err}");
+}
+
+#[test]
+fn only_bodyless_idempotent_requests_are_replayed() -> TestResult {
+    let mut req = request(Method::GET, b"");
+    req.headers_mut().insert("x-request-id", "abc".parse()?);
+    let copy = replay(&req, &FailoverConfig::default()).ok_or("GET not replayed")?;
+    assert_eq!(copy.method(), Method::GET);
+    assert_eq!(copy.uri(), "/items?page=2");
+    assert_eq!(copy.headers(), req.headers());
+
+    assert!(replay(&request(Method::DELETE, b""), &FailoverConfig::default()).is_some());
+    assert!(replay(&request(Method::POST, b""), &FailoverConfig::default()).is_none());
+    assert!(replay(&request(Method::PUT, b"{}"), &FailoverConfig::default()).is_none());
+
+    let mut upgrade = request(Method::GET, b"");
+    upgrade.headers_mut().insert(CONNECTION, "Upgrade".parse()?);
+    upgrade.headers_mut().insert(UPGRADE, "websocket".parse()?);
+    assert!(replay(&upgrade, &FailoverConfig::default()).is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn buffered_bodies_are_replayed_up_to_the_limit() -> TestResult {
+    let config = FailoverConfig { replay_body_bytes: 16, ..FailoverConfig::default() };
+
+    let req =
+        buffer_body(request(Method::POST, b"{\"id\":1}"), &config, RouteProtocol::Http).await?;
+    let copy = replay(&req, &config).ok_or("buffered POST not replayed")?;
+    assert_eq!(copy.method(), Method::POST);
+    assert_eq!(&copy.into_body().collect().await?.to_bytes()[..], b"{\"id\":1}");
+    assert_eq!(&req.into_body().collect().await?.to_bytes()[..], b"{\"id\":1}");
+
+    // Declared larger than the limit: left streaming, so not replayable.
+    let req = buffer_body(request(Method::POST, &[b'x'; 32]), &config, RouteProtocol::Http).await?;
+    assert!(replay(&req, &config).is_none());
+    assert_eq!(req.into_body().collect().await?.to_bytes().len(), 32);
+
+    // gRPC bodies are streams and are never read ahead.
+    let req = buffer_body(request(Method::POST, b"msg"), &config, RouteProtocol::Grpc).await?;
+    assert!(replay(&req, &config).is_none());
+    Ok(())
+}
+
+#[test]
+fn answers_that_fail_over() {
+    let config = FailoverConfig {
+        header: Some("X-Backend-Warming".to_string()),
+        ..FailoverConfig::default()
+    };
+    let reason = |status, header| failover_reason(&config, &Ok(response(status, header)));
+    assert_eq!(reason(StatusCode::SERVICE_UNAVAILABLE, None), Some("status"));
+    assert_eq!(reason(StatusCode::OK, Some("x-backend-warming")), Some("header"));
+    assert_eq!(reason(StatusCode::OK, None), None);
+    assert_eq!(reason(StatusCode::INTERNAL_SERVER_ERROR, None), None);
+
+    let error = |e: HttpError| failover_reason::<()>(&config, &Err(e));
+    assert_eq!(
+        error(HttpError::FailedToGetResponseFromBackend("refused".into())),
+        Some("status")
+    );
+    assert_eq!(error(HttpError::BackendTimeout(TimeoutKind::FirstByte)), Some("status"));
+    // Errors of the request itself are not the backend's fault.
+    assert_eq!(error(HttpError::RequestBodyTooLarge), None);
+}
+
+#[test]
+fn only_errors_before_sending_allow_non_idempotent_replays() {
+    let sent = |e: HttpError| never_sent::<()>(&Err(e));
+    assert!(sent(HttpError::BackendConnectFailed("refused".into())));
+    assert!(sent(HttpError::BackendTimeout(TimeoutKind::Connect)));
+    assert!(sent(HttpError::BackendPoolExhausted));
+
+    assert!(!sent(HttpError::FailedToGetResponseFromBackend("reset".into())));
+    assert!(!sent(HttpError::BackendTimeout(TimeoutKind::FirstByte)));
+    assert!(!sent(HttpError::BackendDrainCutoff));
+    assert!(!never_sent(&Ok(response(StatusCode::SERVICE_UNAVAILABLE, None))));
+}
+
+/// Backend that counts the requests it receives and answers each one after `delay`.
+async fn slow_backend(
+    delay: Duration,
+    received: Arc<AtomicUsize>,
+) -> Result<(SocketAddr, tokio::task::AbortHandle), Box<dyn std::error::Error + Send + Sync>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let handle = tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let received = Arc::clone(&received);
+            tokio::spawn(async move {
+                let svc = service_fn(move |_req: hyper::Request<hyper::body::Incoming>| {
+                    received.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        tokio::time::sleep(delay).await;
+                        Ok::<_, Infallible>(Response::new(Full::new(Bytes::from_static(b"ok"))))
+                    }
+                });
+                let _ = ConnBuilder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(stream), svc)
+                    .await;
+            });
+        }
+    });
+    Ok((addr, handle.abort_handle()))
+}
+
+#[tokio::test]
+async fn post_is_not_sent_again_after_a_backend_timeout() -> TestResult {
+    let received = Arc::new(AtomicUsize::new(0));
+    let delay = Duration::from_secs(2);
+    let (first, first_handle) = slow_backend(delay, Arc::clone(&received)).await?;
+    let (second, second_handle) = slow_backend(delay, Arc::clone(&received)).await?;
+    let addr = SocketAddr::from(([127, 0, 0, 1], free_port()?));
+
+    let route = |backend: SocketAddr| Route {
+        failover: Some(FailoverConfig { replay_body_bytes: 1024, ..FailoverConfig::default() }),
+        timeout: Some(RouteTimeoutConfig { first_byte_ms: Some(200), ..Default::default() }),
+        ..Route::new("/", backend.to_string())
+    };
+    let proxy = ProxyBuilder::new()
+        .listen(addr)
+        .backend(Backend::new(first.to_string()))
+        .backend(Backend::new(second.to_string()))
+        .route(route(first))
+        .route(route(second))
+        .start()
+        .await?;
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    let url = format!("http://{addr}/orders");
+
+    let post = client.post(&url).body("{\"id\":1}").send().await?;
+    assert_eq!(post.status(), StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(received.load(Ordering::SeqCst), 1, "the POST reached a second backend");
+
+    // The same timeout fails a GET over, so the POST was held back by its method alone.
+    received.store(0, Ordering::SeqCst);
+    let get = client.get(&url).send().await?;
+    assert_eq!(get.status(), StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(received.load(Ordering::SeqCst), 2);
+
+    drop(client);
+    proxy.stop().await?;
+    first_handle.abort();
+    second_handle.abort();
+    Ok(())
+}
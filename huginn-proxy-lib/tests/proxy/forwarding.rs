@@ -1,6 +1,9 @@
-use http::Version;
+use http::header::HOST;
+use http::{HeaderValue, Request, Version};
 use huginn_proxy_lib::config::{Backend, BackendHttpVersion};
-use huginn_proxy_lib::proxy::forwarding::{determine_http_version, find_backend_config};
+use huginn_proxy_lib::proxy::forwarding::{
+    determine_http_version, find_backend_config, upstream_host,
+};
 
 #[test]
 fn test_find_backend_config() {
@@ -9,11 +12,39 @@ fn test_find_backend_config() {
             address: "backend-a:9000".to_string(),
             http_version: Some(BackendHttpVersion::Http2),
             health_check: None,
+            circuit_breaker: None,
+            tls: None,
+            pool: None,
+            max_in_flight: None,
+            queue: None,
+            discovery: None,
+            drain: false,
+            drain_timeout_secs: None,
+            slow_start_secs: None,
+            echo: false,
+            tcp: None,
+            proxy: None,
+            bandwidth: None,
+            tenant: None,
         },
         Backend {
             address: "backend-b:9000".to_string(),
             http_version: Some(BackendHttpVersion::Http11),
             health_check: None,
+            circuit_breaker: None,
+            tls: None,
+            pool: None,
+            max_in_flight: None,
+            queue: None,
+            discovery: None,
+            drain: false,
+            drain_timeout_secs: None,
+            slow_start_secs: None,
+            echo: false,
+            tcp: None,
+            proxy: None,
+            bandwidth: None,
+            tenant: None,
         },
     ];
 
@@ -34,16 +65,58 @@ fn test_determine_http_version_with_config() {
         address: "backend:9000".to_string(),
         http_version: Some(BackendHttpVersion::Http2),
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     };
     let backend_http11 = Backend {
         address: "backend:9000".to_string(),
         http_version: Some(BackendHttpVersion::Http11),
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     };
     let backend_preserve = Backend {
         address: "backend:9000".to_string(),
         http_version: Some(BackendHttpVersion::Preserve),
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     };
 
     assert_eq!(
@@ -79,8 +152,25 @@ fn test_determine_http_version_with_config() {
 
 #[test]
 fn test_determine_http_version_defaults() {
-    let backend_no_config =
-        Backend { address: "backend:9000".to_string(), http_version: None, health_check: None };
+    let backend_no_config = Backend {
+        address: "backend:9000".to_string(),
+        http_version: None,
+        health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
+    };
 
     // Default for HTTP (non-HTTPS): HTTP/1.1
     assert_eq!(
@@ -108,3 +198,33 @@ fn test_determine_http_version_no_backend() {
     assert_eq!(determine_http_version(None, Version::HTTP_2, false), Version::HTTP_11);
     assert_eq!(determine_http_version(None, Version::HTTP_2, true), Version::HTTP_2);
 }
+
+#[test]
+fn test_upstream_host() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (http1, _) = Request::get("/users")
+        .header(HOST, "www.example.com")
+        .body(())?
+        .into_parts();
+    // HTTP/2 clients carry the host in the request authority instead of a `Host` header.
+    let (http2, _) = Request::get("https://www.example.com/users")
+        .body(())?
+        .into_parts();
+
+    for parts in [&http1, &http2] {
+        assert_eq!(
+            upstream_host(parts, None, true, "backend:9000"),
+            Some(HeaderValue::from_static("www.example.com"))
+        );
+        assert_eq!(
+            upstream_host(parts, None, false, "backend:9000"),
+            Some(HeaderValue::from_static("backend:9000"))
+        );
+        for preserve_host in [true, false] {
+            assert_eq!(
+                upstream_host(parts, Some("api.vendor.example"), preserve_host, "backend:9000"),
+                Some(HeaderValue::from_static("api.vendor.example"))
+            );
+        }
+    }
+    Ok(())
+}
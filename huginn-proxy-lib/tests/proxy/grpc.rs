@@ -0,0 +1,63 @@
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, HeaderValue};
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::proxy::grpc::{
+    grpc_status, is_grpc_content_type, observe_grpc_status, GRPC_STATUS,
+};
+
+fn with_content_type(value: &'static str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(value));
+    headers
+}
+
+#[test]
+fn detects_grpc_content_types() {
+    assert!(is_grpc_content_type(&with_content_type("application/grpc")));
+    assert!(is_grpc_content_type(&with_content_type("application/grpc+proto")));
+    assert!(is_grpc_content_type(&with_content_type("Application/GRPC;charset=utf-8")));
+}
+
+#[test]
+fn ignores_grpc_web_and_other_content_types() {
+    assert!(!is_grpc_content_type(&with_content_type("application/grpc-web")));
+    assert!(!is_grpc_content_type(&with_content_type("application/grpc-web+proto")));
+    assert!(!is_grpc_content_type(&with_content_type("application/json")));
+    assert!(!is_grpc_content_type(&HeaderMap::new()));
+}
+
+#[test]
+fn reads_trailers_only_status() {
+    let mut headers = with_content_type("application/grpc");
+    assert_eq!(grpc_status(&headers), None);
+    headers.insert(GRPC_STATUS, HeaderValue::from_static("14"));
+    assert_eq!(grpc_status(&headers), Some("14"));
+}
+
+#[tokio::test]
+async fn observes_status_trailer_and_keeps_frames(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut trailers = HeaderMap::new();
+    trailers.insert(GRPC_STATUS, HeaderValue::from_static("5"));
+    let body = Full::new(Bytes::from_static(b"\0\0\0\0\0"))
+        .with_trailers(std::future::ready(Some(Ok(trailers))));
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+    let observed = observe_grpc_status(body, move |status| {
+        if let Ok(mut seen) = sink.lock() {
+            seen.push(status.to_string());
+        }
+    });
+
+    let collected = observed.collect().await?;
+    assert_eq!(collected.trailers().and_then(grpc_status), Some("5"));
+    assert_eq!(collected.to_bytes(), Bytes::from_static(b"\0\0\0\0\0"));
+
+    let seen = seen.lock().map_err(|e| e.to_string())?;
+    assert_eq!(seen.as_slice(), ["5".to_string()]);
+    Ok(())
+}
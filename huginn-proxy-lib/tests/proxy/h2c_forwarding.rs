@@ -124,6 +124,20 @@ fn test_preserve_forwards_http2_as_h2c() {
         address: "backend:9000".to_string(),
         http_version: Some(BackendHttpVersion::Preserve),
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     };
 
     assert_eq!(
@@ -139,6 +153,20 @@ fn test_preserve_forwards_http11_as_http11() {
         address: "backend:9000".to_string(),
         http_version: Some(BackendHttpVersion::Preserve),
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     };
 
     assert_eq!(
@@ -154,6 +182,20 @@ fn test_forced_http2_config_always_uses_h2c() {
         address: "backend:9000".to_string(),
         http_version: Some(BackendHttpVersion::Http2),
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     };
 
     assert_eq!(
@@ -174,6 +216,20 @@ fn test_forced_http11_config_always_uses_http11() {
         address: "backend:9000".to_string(),
         http_version: Some(BackendHttpVersion::Http11),
         health_check: None,
+        circuit_breaker: None,
+        tls: None,
+        pool: None,
+        max_in_flight: None,
+        queue: None,
+        discovery: None,
+        drain: false,
+        drain_timeout_secs: None,
+        slow_start_secs: None,
+        echo: false,
+        tcp: None,
+        proxy: None,
+        bandwidth: None,
+        tenant: None,
     };
 
     assert_eq!(
@@ -0,0 +1,122 @@
+//! `Upgrade: h2c` on plaintext listeners: detecting the upgrade request and replaying it as
+//! stream 1 of the HTTP/2 connection that follows.
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::header::{CONNECTION, CONTENT_LENGTH, HOST, UPGRADE};
+use http::{Request, Response, Version};
+use http_body_util::Empty;
+use huginn_proxy_lib::proxy::transport::h2c::{is_h2c_upgrade, stream_one_frame, H2cUpgradeStream};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+/// Empty `SETTINGS` frame on stream 0.
+const EMPTY_SETTINGS: &[u8] = &[0, 0, 0, 0x4, 0, 0, 0, 0, 0];
+
+fn upgrade_request(path: &str) -> http::request::Builder {
+    Request::get(path)
+        .header(HOST, "example.com")
+        .header(UPGRADE, "h2c")
+        .header(CONNECTION, "Upgrade, HTTP2-Settings")
+        .header("http2-settings", "AAMAAABkAARAAAAAAAIAAAAA")
+}
+
+#[test]
+fn upgrade_requests_are_detected() -> TestResult {
+    assert!(is_h2c_upgrade(&upgrade_request("/").body(())?));
+    assert!(is_h2c_upgrade(&upgrade_request("/").header(CONTENT_LENGTH, "0").body(())?));
+    Ok(())
+}
+
+#[test]
+fn upgrade_requires_the_full_handshake() -> TestResult {
+    let with_body = upgrade_request("/").header(CONTENT_LENGTH, "5").body(())?;
+    assert!(!is_h2c_upgrade(&with_body));
+
+    let http10 = upgrade_request("/").version(Version::HTTP_10).body(())?;
+    assert!(!is_h2c_upgrade(&http10));
+
+    let websocket = Request::get("/")
+        .header(HOST, "example.com")
+        .header(UPGRADE, "websocket")
+        .header(CONNECTION, "Upgrade, HTTP2-Settings")
+        .header("http2-settings", "")
+        .body(())?;
+    assert!(!is_h2c_upgrade(&websocket));
+
+    let no_settings = Request::get("/")
+        .header(HOST, "example.com")
+        .header(UPGRADE, "h2c")
+        .header(CONNECTION, "Upgrade")
+        .body(())?;
+    assert!(!is_h2c_upgrade(&no_settings));
+    Ok(())
+}
+
+#[test]
+fn stream_one_needs_a_host() -> TestResult {
+    let req = Request::get("/")
+        .header(UPGRADE, "h2c")
+        .header(CONNECTION, "Upgrade, HTTP2-Settings")
+        .body(())?;
+    assert!(stream_one_frame(&req).is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn upgrade_request_is_served_as_stream_one() -> TestResult {
+    let long = "a".repeat(300);
+    let req = upgrade_request("/a?b=1")
+        .header("x-test", "1")
+        .header("x-long", long.as_str())
+        .body(())?;
+    let frame = stream_one_frame(&req).ok_or("no stream 1 frame")?;
+
+    let (mut client, server) = tokio::io::duplex(64 * 1024);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let svc = service_fn(move |req: Request<Incoming>| {
+            let _ = tx.send(req.map(|_| ()));
+            async { Ok::<_, Infallible>(Response::new(Empty::<Bytes>::new())) }
+        });
+        let io = TokioIo::new(H2cUpgradeStream::new(server, frame));
+        let _ = hyper::server::conn::http2::Builder::new(TokioExecutor::new())
+            .serve_connection(io, svc)
+            .await;
+    });
+    client.write_all(PREFACE).await?;
+    client.write_all(EMPTY_SETTINGS).await?;
+
+    let received = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await?
+        .ok_or("no request")?;
+    assert_eq!(received.version(), Version::HTTP_2);
+    assert_eq!(received.method(), http::Method::GET);
+    assert_eq!(received.uri().authority().map(|a| a.as_str()), Some("example.com"));
+    assert_eq!(received.uri().path_and_query().map(|pq| pq.as_str()), Some("/a?b=1"));
+    assert_eq!(received.headers().get("x-test").map(|v| v.as_bytes()), Some(&b"1"[..]));
+    assert_eq!(received.headers().get("x-long").map(|v| v.len()), Some(300));
+    assert!(received.headers().get(UPGRADE).is_none());
+    assert!(received.headers().get(CONNECTION).is_none());
+    assert!(received.headers().get("http2-settings").is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn non_http2_bytes_pass_through() -> TestResult {
+    let data: &[u8] = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    let frame = stream_one_frame(&upgrade_request("/").body(())?).ok_or("no stream 1 frame")?;
+    let mut stream = H2cUpgradeStream::new(data, frame);
+    let mut read = Vec::new();
+    stream.read_to_end(&mut read).await?;
+    assert_eq!(read, data);
+    Ok(())
+}
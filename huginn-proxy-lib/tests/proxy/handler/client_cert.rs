@@ -0,0 +1,81 @@
+use http::{HeaderMap, StatusCode};
+use huginn_proxy_lib::config::{ClientCertConfig, MissingClientCert};
+use huginn_proxy_lib::fingerprinting::client_cert;
+use huginn_proxy_lib::proxy::handler::{apply_client_cert_headers, ClientCertContext};
+use huginn_proxy_lib::proxy::HttpError;
+use huginn_proxy_lib::tls::ClientCertInfo;
+use hyper::header::{HeaderName, HeaderValue};
+
+fn forged_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for &name in client_cert::ALL {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from_static("forged"));
+    }
+    headers
+}
+
+fn context(cert: Option<ClientCertInfo>, policy: ClientCertConfig) -> ClientCertContext {
+    ClientCertContext { cert, policy }
+}
+
+fn sample_cert() -> ClientCertInfo {
+    ClientCertInfo { subject: "CN=client,O=Acme".to_string(), sha256: "ab".repeat(32) }
+}
+
+#[test]
+fn client_supplied_headers_are_always_stripped() {
+    let mut headers = forged_headers();
+    apply_client_cert_headers(&mut headers, None);
+    for &name in client_cert::ALL {
+        assert!(!headers.contains_key(name), "{name} should be stripped");
+    }
+
+    let mut headers = forged_headers();
+    let ctx = context(Some(sample_cert()), ClientCertConfig::default());
+    apply_client_cert_headers(&mut headers, Some(&ctx));
+    for &name in client_cert::ALL {
+        assert!(!headers.contains_key(name), "{name} must not be forwarded by default");
+    }
+}
+
+#[test]
+fn certificate_identity_is_forwarded_when_enabled() {
+    let mut headers = forged_headers();
+    let policy = ClientCertConfig { forward_headers: true, ..Default::default() };
+    apply_client_cert_headers(&mut headers, Some(&context(Some(sample_cert()), policy)));
+    assert_eq!(
+        headers.get(client_cert::SUBJECT),
+        Some(&HeaderValue::from_static("CN=client,O=Acme"))
+    );
+    assert_eq!(
+        headers.get(client_cert::SHA256).map(HeaderValue::as_bytes),
+        Some("ab".repeat(32).as_bytes())
+    );
+}
+
+#[test]
+fn missing_certificate_follows_on_missing_policy() {
+    let allow = context(None, ClientCertConfig::default());
+    assert!(allow.check().is_ok());
+
+    let reject = context(
+        None,
+        ClientCertConfig {
+            on_missing: MissingClientCert::Reject,
+            reject_status: 401,
+            ..Default::default()
+        },
+    );
+    match reject.check() {
+        Err(HttpError::ClientCertificateRequired(status)) => {
+            assert_eq!(status, StatusCode::UNAUTHORIZED)
+        }
+        other => panic!("expected ClientCertificateRequired, got {other:?}"),
+    }
+
+    let presented = context(
+        Some(sample_cert()),
+        ClientCertConfig { on_missing: MissingClientCert::Reject, ..Default::default() },
+    );
+    assert!(presented.check().is_ok());
+}
@@ -1,6 +1,9 @@
 use http::HeaderMap;
-use huginn_proxy_lib::fingerprinting::names;
-use huginn_proxy_lib::proxy::handler::request::strip_client_fingerprints;
+use huginn_proxy_lib::config::FingerprintHeadersConfig;
+use huginn_proxy_lib::fingerprinting::{names, FingerprintHeaderNames};
+use huginn_proxy_lib::proxy::handler::request::{
+    strip_client_fingerprints, strip_client_fingerprints_named,
+};
 use hyper::header::{HeaderName, HeaderValue};
 
 #[test]
@@ -105,12 +108,53 @@ fn fingerprints_list_covers_all_authoritative_headers() {
         names::TLS_JA4_S1R,
         names::HTTP2_AKAMAI,
         names::TCP_SYN,
+        names::TCP_OS,
     ]
     .into_iter()
     .collect();
     let actual: HashSet<&str> = names::FINGERPRINTS.iter().copied().collect();
     assert_eq!(
         actual, expected,
-        "names::FINGERPRINTS must contain exactly the 8 proxy-authoritative fingerprint headers"
+        "names::FINGERPRINTS must contain exactly the 9 proxy-authoritative fingerprint headers"
     );
 }
+
+#[test]
+fn strip_renamed_fingerprint_headers() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = FingerprintHeadersConfig {
+        prefix: Some("x-acme-".to_string()),
+        ja4: Some("x-client-ja4".to_string()),
+        ..Default::default()
+    };
+    let header_names = FingerprintHeaderNames::from_config(&config)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(HeaderName::from_static("x-client-ja4"), HeaderValue::from_static("FORGED"));
+    headers.insert(HeaderName::from_static("x-acme-tcp-p0f"), HeaderValue::from_static("FORGED"));
+    headers.insert(
+        HeaderName::from_static("x-acme-fingerprint-spoofing-detected"),
+        HeaderValue::from_static("false"),
+    );
+    // The built-in name is no longer proxy-authoritative once renamed.
+    headers.insert(HeaderName::from_static(names::TLS_JA4), HeaderValue::from_static("kept"));
+
+    let spoofed = strip_client_fingerprints_named(&mut headers, &header_names);
+    assert_eq!(spoofed, vec![names::TLS_JA4, names::TCP_SYN], "labels use built-in names");
+    assert!(!headers.contains_key("x-client-ja4"));
+    assert!(!headers.contains_key("x-acme-tcp-p0f"));
+    assert!(!headers.contains_key("x-acme-fingerprint-spoofing-detected"));
+    assert!(headers.contains_key(names::TLS_JA4));
+    Ok(())
+}
+
+#[test]
+fn strip_classification_header_without_reporting_spoofing() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        HeaderName::from_static(names::CLASSIFICATION),
+        HeaderValue::from_static("trusted-human"),
+    );
+    let spoofed = strip_client_fingerprints(&mut headers);
+    assert!(spoofed.is_empty());
+    assert!(!headers.contains_key(names::CLASSIFICATION));
+}
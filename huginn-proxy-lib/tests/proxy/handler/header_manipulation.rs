@@ -2,14 +2,16 @@ use http::{HeaderMap, HeaderValue};
 use huginn_proxy_lib::config::{CustomHeader, HeaderManipulation, HeaderManipulationGroup};
 use huginn_proxy_lib::proxy::handler::header_manipulation::{
     add_headers, apply_request_header_manipulation, apply_response_header_manipulation,
-    remove_headers,
+    remove_headers, set_headers,
 };
+use huginn_proxy_lib::proxy::handler::HeaderTemplateContext;
 use huginn_proxy_lib::telemetry::Metrics;
 
 /// Build a `HeaderManipulation` whose request+response both add `name: value`.
 fn add_both(name: &str, value: &str) -> HeaderManipulation {
     let group = HeaderManipulationGroup {
         add: vec![CustomHeader { name: name.to_string(), value: value.to_string().into() }],
+        set: vec![],
         remove: vec![],
     };
     HeaderManipulation { request: group.clone(), response: group }
@@ -106,6 +108,7 @@ fn request_domain_level_is_applied() {
         None,
         Some(&domain),
         None,
+        &HeaderTemplateContext::default(),
         &Metrics::new_noop(),
     );
 
@@ -122,6 +125,7 @@ fn response_domain_level_is_applied() {
         None,
         Some(&domain),
         None,
+        &HeaderTemplateContext::default(),
         &Metrics::new_noop(),
     );
 
@@ -141,6 +145,7 @@ fn most_specific_scope_wins_for_same_header() {
         Some(&global),
         Some(&domain),
         Some(&route),
+        &HeaderTemplateContext::default(),
         &Metrics::new_noop(),
     );
     assert_eq!(headers.get("x-scope").map(|v| v.as_bytes()), Some(b"route".as_ref()));
@@ -152,6 +157,7 @@ fn most_specific_scope_wins_for_same_header() {
         Some(&global),
         Some(&domain),
         None,
+        &HeaderTemplateContext::default(),
         &Metrics::new_noop(),
     );
     assert_eq!(headers.get("x-scope").map(|v| v.as_bytes()), Some(b"domain".as_ref()));
@@ -169,6 +175,7 @@ fn each_scope_contributes_distinct_headers() {
         Some(&global),
         Some(&domain),
         Some(&route),
+        &HeaderTemplateContext::default(),
         &Metrics::new_noop(),
     );
 
@@ -176,3 +183,85 @@ fn each_scope_contributes_distinct_headers() {
     assert_eq!(headers.get("x-domain").map(|v| v.as_bytes()), Some(b"d".as_ref()));
     assert_eq!(headers.get("x-route").map(|v| v.as_bytes()), Some(b"r".as_ref()));
 }
+
+fn header(name: &str, value: &str) -> CustomHeader {
+    CustomHeader { name: name.to_string(), value: value.to_string().into() }
+}
+
+#[test]
+fn test_set_headers_only_rewrites_present() {
+    let mut headers = HeaderMap::new();
+    headers.insert("server", HeaderValue::from_static("nginx"));
+
+    let to_set = vec![
+        ("Server".to_string(), "huginn".to_string()),
+        ("x-absent".to_string(), "value".to_string()),
+    ];
+    assert_eq!(set_headers(&mut headers, &to_set), 1);
+
+    assert_eq!(headers.get("server").map(|v| v.as_bytes()), Some(b"huginn".as_ref()));
+    assert!(headers.get("x-absent").is_none());
+}
+
+#[test]
+fn remove_runs_before_set_and_add() {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-old", HeaderValue::from_static("1"));
+    headers.insert("x-kept", HeaderValue::from_static("1"));
+    let group = HeaderManipulationGroup {
+        add: vec![header("x-old", "re-added")],
+        set: vec![header("x-old", "rewritten"), header("x-kept", "rewritten")],
+        remove: vec!["x-old".to_string()],
+    };
+    let manipulation = HeaderManipulation { request: group, response: Default::default() };
+
+    apply_request_header_manipulation(
+        &mut headers,
+        None,
+        None,
+        Some(&manipulation),
+        &HeaderTemplateContext::default(),
+        &Metrics::new_noop(),
+    );
+
+    // `x-old` is gone when `set` runs, so only `add` brings it back.
+    assert_eq!(headers.get("x-old").map(|v| v.as_bytes()), Some(b"re-added".as_ref()));
+    assert_eq!(headers.get("x-kept").map(|v| v.as_bytes()), Some(b"rewritten".as_ref()));
+}
+
+#[test]
+fn template_variables_are_expanded() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut headers = HeaderMap::new();
+    let group = HeaderManipulationGroup {
+        add: vec![
+            header("x-client", "ip=${client_ip}"),
+            header("x-route", "${route_prefix}@${host}"),
+            header("x-ja4", "${ja4}"),
+            header("x-price", "$5"),
+        ],
+        ..Default::default()
+    };
+    let manipulation = HeaderManipulation { request: Default::default(), response: group };
+    let template = HeaderTemplateContext {
+        client_ip: Some("203.0.113.7".parse()?),
+        ja4: None,
+        route_prefix: "/api",
+        host: "example.com",
+    };
+
+    apply_response_header_manipulation(
+        &mut headers,
+        Some(&manipulation),
+        None,
+        None,
+        &template,
+        &Metrics::new_noop(),
+    );
+
+    assert_eq!(headers.get("x-client").map(|v| v.as_bytes()), Some(b"ip=203.0.113.7".as_ref()));
+    assert_eq!(headers.get("x-route").map(|v| v.as_bytes()), Some(b"/api@example.com".as_ref()));
+    // No TLS fingerprint on this connection: the variable expands to nothing.
+    assert_eq!(headers.get("x-ja4").map(|v| v.as_bytes()), Some(b"".as_ref()));
+    assert_eq!(headers.get("x-price").map(|v| v.as_bytes()), Some(b"$5".as_ref()));
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use http::header::{CONTENT_LENGTH, HOST, TRANSFER_ENCODING};
+use http::{Request, Version};
+use huginn_proxy_lib::proxy::handler::check_http1_request;
+use huginn_proxy_lib::proxy::http_result::HttpError;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+fn request(version: Version, headers: &[(&str, &str)]) -> Result<Request<()>, http::Error> {
+    let mut builder = Request::builder().uri("/").version(version);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    builder.body(())
+}
+
+#[test]
+fn transfer_encoding_with_content_length_is_rejected() -> TestResult {
+    let mut req = request(
+        Version::HTTP_11,
+        &[
+            ("host", "example.com"),
+            ("transfer-encoding", "chunked"),
+            ("content-length", "5"),
+        ],
+    )?;
+    let err = check_http1_request(&mut req, None).err();
+    assert!(matches!(err, Some(HttpError::AmbiguousFraming(_))), "got: {err:?}");
+    Ok(())
+}
+
+#[test]
+fn transfer_encoding_alone_passes() -> TestResult {
+    let mut req =
+        request(Version::HTTP_11, &[("host", "example.com"), ("transfer-encoding", "chunked")])?;
+    check_http1_request(&mut req, None)?;
+    assert!(req.headers().contains_key(TRANSFER_ENCODING));
+    assert!(!req.headers().contains_key(CONTENT_LENGTH));
+    Ok(())
+}
+
+#[test]
+fn http11_without_host_is_rejected() -> TestResult {
+    let mut req = request(Version::HTTP_11, &[])?;
+    let err = check_http1_request(&mut req, Some("example.com")).err();
+    assert!(matches!(err, Some(HttpError::InvalidHostInRequestHeader)), "got: {err:?}");
+    Ok(())
+}
+
+#[test]
+fn duplicate_host_is_rejected() -> TestResult {
+    let mut req = request(Version::HTTP_10, &[("host", "a.example"), ("host", "b.example")])?;
+    let err = check_http1_request(&mut req, None).err();
+    assert!(matches!(err, Some(HttpError::InvalidHostInRequestHeader)), "got: {err:?}");
+    Ok(())
+}
+
+#[test]
+fn http10_without_host_gets_default_host() -> TestResult {
+    let mut req = request(Version::HTTP_10, &[])?;
+    check_http1_request(&mut req, Some("example.com:8080"))?;
+    assert_eq!(req.headers().get(HOST).map(|v| v.as_bytes()), Some(&b"example.com:8080"[..]));
+    Ok(())
+}
+
+#[test]
+fn http10_without_host_or_default_passes_unchanged() -> TestResult {
+    let mut req = request(Version::HTTP_10, &[])?;
+    check_http1_request(&mut req, None)?;
+    assert!(!req.headers().contains_key(HOST));
+    Ok(())
+}
+
+#[test]
+fn http10_absolute_form_keeps_the_uri_authority() -> TestResult {
+    let mut req = Request::builder()
+        .uri("http://origin.example/")
+        .version(Version::HTTP_10)
+        .body(())?;
+    check_http1_request(&mut req, Some("example.com"))?;
+    assert!(!req.headers().contains_key(HOST));
+    Ok(())
+}
+
+#[test]
+fn http2_requests_are_not_checked() -> TestResult {
+    let mut req =
+        request(Version::HTTP_2, &[("transfer-encoding", "chunked"), ("content-length", "5")])?;
+    check_http1_request(&mut req, None)?;
+    Ok(())
+}
@@ -1,3 +1,10 @@
+mod client_cert;
 mod fingerprint_spoofing;
 mod header_manipulation;
 mod host;
+mod http1;
+mod privacy;
+mod sticky;
+mod strict_http;
+mod tls_info;
+mod whoami;
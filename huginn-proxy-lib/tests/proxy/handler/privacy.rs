@@ -0,0 +1,203 @@
+use http::{HeaderMap, HeaderValue};
+use huginn_proxy_lib::config::{PrivacyConfig, PrivacyHeaderGroup, Secret};
+use huginn_proxy_lib::proxy::handler::privacy::{scrub_headers, ScrubCounts};
+
+use crate::helpers::error_message;
+
+const KEY: &str = "0123456789abcdef0123456789abcdef";
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(ToString::to_string).collect()
+}
+
+fn privacy(request: PrivacyHeaderGroup, key: Option<&str>) -> PrivacyConfig {
+    PrivacyConfig {
+        request,
+        response: PrivacyHeaderGroup::default(),
+        hash_key: key.map(|key| Secret::new(key.to_string())),
+    }
+}
+
+fn request_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("host", HeaderValue::from_static("vendor.example"));
+    headers.insert("content-length", HeaderValue::from_static("2"));
+    headers.insert("accept", HeaderValue::from_static("application/json"));
+    headers.insert("cookie", HeaderValue::from_static("session=abc"));
+    headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+    headers.append("x-user-id", HeaderValue::from_static("42"));
+    headers.append("x-user-id", HeaderValue::from_static("43"));
+    headers
+}
+
+#[test]
+fn allow_strip_and_hash_lists_with_a_key_validate() {
+    let valid = privacy(
+        PrivacyHeaderGroup {
+            allow: Some(names(&["accept", "x-user-id"])),
+            strip: names(&["cookie", "Authorization"]),
+            hash: names(&["x-user-id"]),
+        },
+        Some(KEY),
+    );
+    assert!(valid.validate("privacy").is_ok());
+    assert!(PrivacyConfig::default().validate("privacy").is_ok());
+}
+
+#[test]
+fn invalid_header_names_are_rejected() {
+    let err = error_message(
+        privacy(PrivacyHeaderGroup { strip: names(&["bad header"]), ..Default::default() }, None)
+            .validate("privacy"),
+    );
+    assert!(
+        err.contains("privacy.request.strip: invalid header name 'bad header'"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn framing_headers_cannot_be_stripped() {
+    let err = error_message(
+        privacy(
+            PrivacyHeaderGroup { strip: names(&["Content-Length"]), ..Default::default() },
+            None,
+        )
+        .validate("privacy"),
+    );
+    assert!(
+        err.contains("privacy.request.strip: 'Content-Length' carries message framing"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn upgrade_headers_cannot_be_hashed() {
+    let err = error_message(
+        privacy(
+            PrivacyHeaderGroup { hash: names(&["sec-websocket-key"]), ..Default::default() },
+            Some(KEY),
+        )
+        .validate("privacy"),
+    );
+    assert!(
+        err.contains("privacy.request.hash: 'sec-websocket-key' carries message framing"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn hash_lists_without_a_key_are_rejected() {
+    let err = error_message(
+        privacy(PrivacyHeaderGroup { hash: names(&["x-user-id"]), ..Default::default() }, None)
+            .validate("privacy"),
+    );
+    assert!(err.contains("privacy: hash lists need a hash_key"), "got: {err}");
+}
+
+#[test]
+fn short_hash_keys_are_rejected() {
+    let err = error_message(
+        privacy(
+            PrivacyHeaderGroup { hash: names(&["x-user-id"]), ..Default::default() },
+            Some("short"),
+        )
+        .validate("privacy"),
+    );
+    assert!(err.contains("privacy.hash_key must be at least"), "got: {err}");
+}
+
+#[test]
+fn headers_both_stripped_and_hashed_are_rejected() {
+    let err = error_message(
+        privacy(
+            PrivacyHeaderGroup {
+                strip: names(&["cookie"]),
+                hash: names(&["Cookie"]),
+                ..Default::default()
+            },
+            Some(KEY),
+        )
+        .validate("privacy"),
+    );
+    assert!(
+        err.contains("privacy.request: 'Cookie' is in both strip and hash"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn lets_through_what_the_group_does_not_scrub() {
+    let group = PrivacyHeaderGroup {
+        allow: Some(names(&["accept", "X-Huginn-Net-Signature", "x-user-id"])),
+        strip: names(&["cookie"]),
+        hash: names(&["x-user-id"]),
+    };
+    assert!(group.lets_through("x-huginn-net-signature"));
+    assert!(group.lets_through("content-length"));
+    assert!(!group.lets_through("x-user-id"));
+    assert!(!group.lets_through("authorization"));
+    let strip =
+        PrivacyHeaderGroup { strip: names(&["x-huginn-net-signature"]), ..Default::default() };
+    assert!(!strip.lets_through("x-huginn-net-signature"));
+    assert!(strip.lets_through("authorization"));
+}
+
+#[test]
+fn strips_and_hashes_configured_headers() {
+    let config = privacy(
+        PrivacyHeaderGroup {
+            strip: names(&["Cookie", "authorization", "x-absent"]),
+            hash: names(&["x-user-id"]),
+            ..Default::default()
+        },
+        Some(KEY),
+    );
+    let mut headers = request_headers();
+    let counts = scrub_headers(&mut headers, &config.request, &config);
+    assert_eq!(counts, ScrubCounts { not_allowed: 0, stripped: 2, hashed: 1 });
+    assert!(headers.get("cookie").is_none());
+    assert!(headers.get("authorization").is_none());
+    assert_eq!(headers.get("accept"), Some(&HeaderValue::from_static("application/json")));
+
+    let hashed: Vec<&str> = headers
+        .get_all("x-user-id")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect();
+    assert_eq!(hashed.len(), 2);
+    assert!(hashed
+        .iter()
+        .all(|v| v.len() == 64 && v.bytes().all(|b| b.is_ascii_hexdigit())));
+    assert_ne!(hashed[0], hashed[1]);
+
+    // Hashes are stable under one key and differ under another.
+    let mut again = request_headers();
+    scrub_headers(&mut again, &config.request, &config);
+    assert_eq!(again.get("x-user-id"), headers.get("x-user-id"));
+    let other_key = privacy(config.request.clone(), Some(&KEY.replace('0', "9")));
+    let mut other = request_headers();
+    scrub_headers(&mut other, &other_key.request, &other_key);
+    assert_ne!(other.get("x-user-id"), headers.get("x-user-id"));
+}
+
+#[test]
+fn allowlist_keeps_listed_and_framing_headers() {
+    let config = privacy(
+        PrivacyHeaderGroup { allow: Some(names(&["Accept"])), ..Default::default() },
+        None,
+    );
+    let mut headers = request_headers();
+    headers.insert("sec-websocket-key", HeaderValue::from_static("dGhlIHNhbXBsZQ=="));
+    let counts = scrub_headers(&mut headers, &config.request, &config);
+    assert_eq!(counts, ScrubCounts { not_allowed: 3, stripped: 0, hashed: 0 });
+    let mut kept: Vec<&str> = headers.keys().map(|name| name.as_str()).collect();
+    kept.sort_unstable();
+    assert_eq!(kept, ["accept", "content-length", "host", "sec-websocket-key"]);
+
+    // Without any list the headers are left alone.
+    let mut headers = request_headers();
+    let counts = scrub_headers(&mut headers, &PrivacyHeaderGroup::default(), &config);
+    assert_eq!(counts, ScrubCounts::default());
+    assert_eq!(headers, request_headers());
+}
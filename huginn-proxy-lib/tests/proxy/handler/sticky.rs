@@ -0,0 +1,97 @@
+use std::net::SocketAddr;
+
+use http::header::{COOKIE, SET_COOKIE};
+use http::{HeaderMap, HeaderValue, Response};
+use huginn_proxy_lib::backend::{backend_token, Affinity};
+use huginn_proxy_lib::config::{StickyConfig, StickyHashKey, StickyMode, TrustedProxiesConfig};
+use huginn_proxy_lib::proxy::handler::StickySession;
+
+fn config(mode: StickyMode) -> StickyConfig {
+    StickyConfig {
+        mode,
+        hash_by: StickyHashKey::ClientIp,
+        cookie: "huginn_affinity".to_string(),
+        ttl_secs: 600,
+    }
+}
+
+fn peer() -> SocketAddr {
+    SocketAddr::from(([198, 51, 100, 7], 40000))
+}
+
+fn set_cookies(response: &Response<()>) -> Vec<&str> {
+    response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect()
+}
+
+#[test]
+fn new_client_gets_a_cookie_for_its_backend() {
+    let config = config(StickyMode::Cookie);
+    let session = StickySession::from_request(
+        &config,
+        &HeaderMap::new(),
+        peer(),
+        &TrustedProxiesConfig::default(),
+        None,
+    );
+    assert_eq!(session.affinity(), None);
+
+    let mut response = Response::new(());
+    session.pin(&mut response, "backend-a:9000", "/app", true);
+    let expected = format!(
+        "huginn_affinity={}; Path=/app; Max-Age=600; HttpOnly; SameSite=Lax; Secure",
+        backend_token("backend-a:9000")
+    );
+    assert_eq!(set_cookies(&response), [expected.as_str()]);
+}
+
+#[test]
+fn returning_client_is_pinned_and_only_repinned_after_failover(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = config(StickyMode::Cookie);
+    let token = backend_token("backend-a:9000");
+    let mut headers = HeaderMap::new();
+    let cookie = format!("theme=dark; huginn_affinity={token}");
+    headers.insert(COOKIE, HeaderValue::from_str(&cookie)?);
+    let session = StickySession::from_request(
+        &config,
+        &headers,
+        peer(),
+        &TrustedProxiesConfig::default(),
+        None,
+    );
+    assert_eq!(session.affinity(), Some(Affinity::Token(&token)));
+
+    let mut same = Response::new(());
+    session.pin(&mut same, "backend-a:9000", "/app", false);
+    assert!(set_cookies(&same).is_empty());
+
+    let mut moved = Response::new(());
+    session.pin(&mut moved, "backend-b:9000", "/app", false);
+    let issued = set_cookies(&moved);
+    assert_eq!(issued.len(), 1);
+    assert!(issued[0].starts_with(&format!("huginn_affinity={}", backend_token("backend-b:9000"))));
+    assert!(!issued[0].contains("Secure"));
+    Ok(())
+}
+
+#[test]
+fn hash_mode_keys_on_the_client_ip_without_cookies() {
+    let config = config(StickyMode::Hash);
+    let session = StickySession::from_request(
+        &config,
+        &HeaderMap::new(),
+        peer(),
+        &TrustedProxiesConfig::default(),
+        None,
+    );
+    assert_eq!(session.affinity(), Some(Affinity::Key("198.51.100.7")));
+
+    let mut response = Response::new(());
+    session.pin(&mut response, "backend-a:9000", "/app", true);
+    assert!(set_cookies(&response).is_empty());
+}
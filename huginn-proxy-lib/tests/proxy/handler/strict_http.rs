@@ -0,0 +1,133 @@
+use std::net::SocketAddr;
+
+use http::header::{CONTENT_LENGTH, HOST};
+use http::{HeaderValue, Request, Version};
+use huginn_proxy_lib::config::{StrictHttpConfig, StrictHttpMode};
+use huginn_proxy_lib::proxy::handler::{check_strict_http, ProtocolViolation};
+use huginn_proxy_lib::proxy::http_result::HttpError;
+use huginn_proxy_lib::Metrics;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+fn config(mode: StrictHttpMode) -> StrictHttpConfig {
+    StrictHttpConfig { enabled: true, mode }
+}
+
+fn check(req: &mut Request<()>, config: &StrictHttpConfig) -> Result<(), HttpError> {
+    let peer: SocketAddr = ([203, 0, 113, 7], 50000).into();
+    check_strict_http(req, config, &Metrics::new_noop(), peer)
+}
+
+fn violation(result: Result<(), HttpError>) -> Option<ProtocolViolation> {
+    match result {
+        Err(HttpError::ProtocolViolation(violation)) => Some(violation),
+        _ => None,
+    }
+}
+
+#[test]
+fn disabled_leaves_requests_alone() -> TestResult {
+    let mut req = Request::get("http://other.example/")
+        .header(HOST, "example.com")
+        .header(CONTENT_LENGTH, "5")
+        .header(CONTENT_LENGTH, "6")
+        .body(())?;
+    check(&mut req, &StrictHttpConfig::default())?;
+    assert_eq!(req.headers().get_all(CONTENT_LENGTH).iter().count(), 2);
+    Ok(())
+}
+
+#[test]
+fn identical_content_lengths_are_collapsed() -> TestResult {
+    let mut req = Request::post("/")
+        .header(HOST, "example.com")
+        .header(CONTENT_LENGTH, "5")
+        .header(CONTENT_LENGTH, "5, 5")
+        .body(())?;
+    check(&mut req, &config(StrictHttpMode::Enforce))?;
+    let lengths: Vec<_> = req.headers().get_all(CONTENT_LENGTH).iter().collect();
+    assert_eq!(lengths, [&HeaderValue::from_static("5")]);
+    Ok(())
+}
+
+#[test]
+fn conflicting_content_lengths_are_rejected() -> TestResult {
+    let mut req = Request::post("/")
+        .header(HOST, "example.com")
+        .header(CONTENT_LENGTH, "5")
+        .header(CONTENT_LENGTH, "50")
+        .body(())?;
+    let result = check(&mut req, &config(StrictHttpMode::Enforce));
+    assert_eq!(violation(result), Some(ProtocolViolation::DuplicateContentLength));
+    assert!(
+        HttpError::ProtocolViolation(ProtocolViolation::DuplicateContentLength).closes_connection()
+    );
+    Ok(())
+}
+
+#[test]
+fn non_ascii_header_values_are_rejected() -> TestResult {
+    let mut req = Request::get("/")
+        .header(HOST, "example.com")
+        .header("x-note", HeaderValue::from_bytes(b"caf\xe9")?)
+        .body(())?;
+    let result = check(&mut req, &config(StrictHttpMode::Enforce));
+    assert_eq!(violation(result), Some(ProtocolViolation::InvalidHeaderValue));
+    Ok(())
+}
+
+#[test]
+fn absolute_form_naming_the_host_becomes_origin_form() -> TestResult {
+    let mut req = Request::get("http://Example.com:8080/a?b=1")
+        .header(HOST, "example.com:8080")
+        .body(())?;
+    check(&mut req, &config(StrictHttpMode::Enforce))?;
+    assert_eq!(req.uri(), "/a?b=1");
+    assert_eq!(req.headers().get(HOST), Some(&HeaderValue::from_static("example.com:8080")));
+    Ok(())
+}
+
+#[test]
+fn absolute_form_without_host_gets_the_authority_as_host() -> TestResult {
+    let mut req = Request::get("http://example.com/")
+        .version(Version::HTTP_10)
+        .body(())?;
+    check(&mut req, &config(StrictHttpMode::Enforce))?;
+    assert_eq!(req.uri(), "/");
+    assert_eq!(req.headers().get(HOST), Some(&HeaderValue::from_static("example.com")));
+    Ok(())
+}
+
+#[test]
+fn absolute_form_naming_another_host_is_rejected() -> TestResult {
+    let mut req = Request::get("http://internal.example/admin")
+        .header(HOST, "example.com")
+        .body(())?;
+    let result = check(&mut req, &config(StrictHttpMode::Enforce));
+    assert_eq!(violation(result), Some(ProtocolViolation::AbsoluteFormTarget));
+    Ok(())
+}
+
+#[test]
+fn http2_authority_is_not_absolute_form() -> TestResult {
+    let mut req = Request::get("https://example.com/")
+        .version(Version::HTTP_2)
+        .body(())?;
+    check(&mut req, &config(StrictHttpMode::Enforce))?;
+    assert_eq!(req.uri(), "https://example.com/");
+    Ok(())
+}
+
+#[test]
+fn detect_mode_forwards_unchanged() -> TestResult {
+    let mut req = Request::get("http://internal.example/admin")
+        .header(HOST, "example.com")
+        .header(CONTENT_LENGTH, "5")
+        .header(CONTENT_LENGTH, "50")
+        .header("x-note", HeaderValue::from_bytes(b"caf\xe9")?)
+        .body(())?;
+    check(&mut req, &config(StrictHttpMode::Detect))?;
+    assert_eq!(req.uri(), "http://internal.example/admin");
+    assert_eq!(req.headers().get_all(CONTENT_LENGTH).iter().count(), 2);
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use http::HeaderMap;
+use huginn_proxy_lib::fingerprinting::tls_info;
+use huginn_proxy_lib::proxy::handler::apply_tls_info_headers;
+use huginn_proxy_lib::tls::TlsHandshakeInfo;
+use hyper::header::{HeaderName, HeaderValue};
+
+fn forged_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for &name in tls_info::ALL {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from_static("forged"));
+    }
+    headers
+}
+
+fn sample_info() -> TlsHandshakeInfo {
+    TlsHandshakeInfo {
+        alpn: Some("h2".to_string()),
+        sni: Some("example.com".to_string()),
+        version: "TLSv1_3".to_string(),
+        cipher: "TLS13_AES_128_GCM_SHA256".to_string(),
+        early_data: true,
+        ech: true,
+    }
+}
+
+#[test]
+fn client_supplied_headers_are_always_stripped() {
+    let mut headers = forged_headers();
+    apply_tls_info_headers(&mut headers, None);
+    for &name in tls_info::ALL {
+        assert!(!headers.contains_key(name), "{name} should be stripped");
+    }
+}
+
+#[test]
+fn negotiated_parameters_are_forwarded() {
+    let mut headers = forged_headers();
+    apply_tls_info_headers(&mut headers, Some(&sample_info()));
+    assert_eq!(headers.get(tls_info::ALPN), Some(&HeaderValue::from_static("h2")));
+    assert_eq!(headers.get(tls_info::SNI), Some(&HeaderValue::from_static("example.com")));
+    assert_eq!(headers.get(tls_info::VERSION), Some(&HeaderValue::from_static("TLSv1_3")));
+    assert_eq!(
+        headers.get(tls_info::CIPHER),
+        Some(&HeaderValue::from_static("TLS13_AES_128_GCM_SHA256"))
+    );
+    assert_eq!(headers.get(tls_info::EARLY_DATA), Some(&HeaderValue::from_static("true")));
+    assert_eq!(headers.get(tls_info::ECH), Some(&HeaderValue::from_static("true")));
+
+    // No ALPN or SNI offered: those headers are absent rather than forged or empty.
+    let mut headers = forged_headers();
+    let info =
+        TlsHandshakeInfo { alpn: None, sni: None, early_data: false, ech: false, ..sample_info() };
+    apply_tls_info_headers(&mut headers, Some(&info));
+    assert!(!headers.contains_key(tls_info::ALPN));
+    assert!(!headers.contains_key(tls_info::SNI));
+    assert_eq!(headers.get(tls_info::EARLY_DATA), Some(&HeaderValue::from_static("false")));
+}
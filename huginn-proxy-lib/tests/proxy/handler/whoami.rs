@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use http::{Request, Version};
+use http_body_util::BodyExt;
+use huginn_proxy_lib::proxy::handler::{is_whoami_request, whoami_response};
+use huginn_proxy_lib::tls::TlsHandshakeInfo;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[test]
+fn only_the_configured_path_matches() -> TestResult {
+    let req = Request::get("https://example.com/._huginn/whoami?x=1").body(())?;
+    assert!(is_whoami_request(&req, Some("/._huginn/whoami")));
+    assert!(!is_whoami_request(&req, None));
+    let req = Request::get("https://example.com/._huginn/whoami/more").body(())?;
+    assert!(!is_whoami_request(&req, Some("/._huginn/whoami")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reports_tls_parameters_and_forwarded_headers() -> TestResult {
+    let mut req = Request::get("https://example.com/._huginn/whoami")
+        .version(Version::HTTP_11)
+        .header("x-forwarded-for", "203.0.113.9")
+        .header("x-forwarded-host", "forged.example")
+        .body(())?;
+    req.extensions_mut().insert(Arc::new(TlsHandshakeInfo {
+        alpn: Some("http/1.1".to_string()),
+        sni: Some("example.com".to_string()),
+        version: "TLSv1_3".to_string(),
+        cipher: "TLS13_AES_128_GCM_SHA256".to_string(),
+        early_data: false,
+        ech: false,
+    }));
+
+    let peer = "192.0.2.1:40000".parse()?;
+    let response = whoami_response(&req, peer, true, "example.com", None, None, None);
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get("content-type").map(|v| v.as_bytes()),
+        Some(&b"application/json"[..])
+    );
+    let body = response.into_body().collect().await?.to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body)?;
+
+    assert_eq!(json["client_ip"], "192.0.2.1");
+    assert_eq!(json["client_port"], 40000);
+    assert_eq!(json["http_version"], "HTTP/1.1");
+    assert_eq!(json["host"], "example.com");
+    assert!(json["ja4"].is_null());
+    assert!(json["akamai"].is_null());
+    assert!(json["tcp_syn"].is_null());
+    assert_eq!(json["tls"]["version"], "TLSv1_3");
+    assert_eq!(json["tls"]["alpn"], "http/1.1");
+    assert_eq!(json["tls"]["sni"], "example.com");
+    assert_eq!(json["forwarded"]["x-forwarded-for"], "203.0.113.9, 192.0.2.1");
+    assert_eq!(json["forwarded"]["x-forwarded-host"], "example.com");
+    assert_eq!(json["forwarded"]["x-forwarded-port"], "40000");
+    assert_eq!(json["forwarded"]["x-forwarded-proto"], "https");
+    Ok(())
+}
@@ -1,4 +1,6 @@
+use huginn_proxy_lib::proxy::handler::ProtocolViolation;
 use huginn_proxy_lib::proxy::http_result::HttpError;
+use huginn_proxy_lib::proxy::route_timeout::TimeoutKind;
 
 #[test]
 fn test_error_type_mapping() {
@@ -13,12 +15,36 @@ fn test_error_type_mapping() {
         HttpError::FailedToGetResponseFromBackend("test".to_string()).error_type(),
         "backend_error"
     );
+    assert_eq!(
+        HttpError::BackendConnectFailed("test".to_string()).error_type(),
+        "connect_failed"
+    );
     assert_eq!(
         HttpError::FailedToGenerateDownstreamResponse("test".to_string()).error_type(),
         "downstream_response_failed"
     );
     assert_eq!(HttpError::InvalidUri("test".to_string()).error_type(), "invalid_uri");
     assert_eq!(HttpError::UpstreamUnhealthy.error_type(), "upstream_unhealthy");
+    assert_eq!(
+        HttpError::ClientCertificateRequired(http::StatusCode::FORBIDDEN).error_type(),
+        "client_cert_required"
+    );
+    assert_eq!(
+        HttpError::WafBlocked(http::StatusCode::FORBIDDEN, "sqli:union".to_string()).error_type(),
+        "waf_blocked"
+    );
+    assert_eq!(
+        HttpError::BotSpoofed(http::StatusCode::FORBIDDEN, "googlebot".to_string()).error_type(),
+        "bot_spoofed"
+    );
+    assert_eq!(HttpError::RequestBodyTooLarge.error_type(), "request_body_too_large");
+    assert_eq!(HttpError::ResponseBodyTooLarge.error_type(), "response_body_too_large");
+    assert_eq!(HttpError::BackendTimeout(TimeoutKind::Total).error_type(), "backend_timeout");
+    assert_eq!(HttpError::AmbiguousFraming("te+cl").error_type(), "ambiguous_framing");
+    assert_eq!(
+        HttpError::ProtocolViolation(ProtocolViolation::InvalidHeaderValue).error_type(),
+        "protocol_violation"
+    );
 }
 
 #[test]
@@ -34,4 +60,33 @@ fn test_status_code_conversion() {
         StatusCode::BAD_GATEWAY
     );
     assert_eq!(StatusCode::from(HttpError::UpstreamUnhealthy), StatusCode::BAD_GATEWAY);
+    assert_eq!(
+        StatusCode::from(HttpError::ClientCertificateRequired(StatusCode::UNAUTHORIZED)),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        StatusCode::from(HttpError::WafBlocked(StatusCode::NOT_ACCEPTABLE, "r1".to_string())),
+        StatusCode::NOT_ACCEPTABLE
+    );
+    assert_eq!(
+        StatusCode::from(HttpError::BotSpoofed(StatusCode::UNAUTHORIZED, "bingbot".to_string())),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(StatusCode::from(HttpError::RequestBodyTooLarge), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(StatusCode::from(HttpError::ResponseBodyTooLarge), StatusCode::BAD_GATEWAY);
+    assert_eq!(
+        StatusCode::from(HttpError::BackendTimeout(TimeoutKind::Connect)),
+        StatusCode::GATEWAY_TIMEOUT
+    );
+    assert_eq!(StatusCode::from(HttpError::AmbiguousFraming("te+cl")), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_only_framing_errors_close_the_connection() {
+    assert!(HttpError::AmbiguousFraming("te+cl").closes_connection());
+    assert!(!HttpError::InvalidHostInRequestHeader.closes_connection());
+    assert!(
+        !HttpError::ProtocolViolation(ProtocolViolation::AbsoluteFormTarget).closes_connection()
+    );
+    assert!(!HttpError::RequestBodyTooLarge.closes_connection());
 }
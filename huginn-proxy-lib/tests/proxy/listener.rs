@@ -0,0 +1,68 @@
+use std::os::unix::fs::PermissionsExt;
+use std::time::Duration;
+
+use huginn_proxy_lib::config::TcpConfig;
+use huginn_proxy_lib::proxy::listener::{bind_listener, bind_unix_listener};
+use socket2::SockRef;
+
+use crate::helpers::tmp_path;
+
+#[tokio::test]
+async fn test_reuse_port_listeners_share_address(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let first = bind_listener("127.0.0.1:0".parse()?, 128, true, &TcpConfig::default())?;
+    let addr = first.local_addr()?;
+    let second = bind_listener(addr, 128, true, &TcpConfig::default())?;
+    assert_eq!(second.local_addr()?, addr);
+
+    // Without SO_REUSEPORT the address stays exclusive.
+    assert!(bind_listener(addr, 128, false, &TcpConfig::default()).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_accepted_connections_inherit_tcp_options(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let tcp = TcpConfig {
+        nodelay: Some(true),
+        keepalive_time_secs: Some(30),
+        keepalive_interval_secs: Some(5),
+        keepalive_retries: Some(3),
+        ..TcpConfig::default()
+    };
+    let listener = bind_listener("127.0.0.1:0".parse()?, 128, false, &tcp)?;
+    let _client = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (accepted, _) = listener.accept().await?;
+
+    let socket = SockRef::from(&accepted);
+    assert!(socket.tcp_nodelay()?);
+    assert!(socket.keepalive()?);
+    assert_eq!(socket.tcp_keepalive_time()?, Duration::from_secs(30));
+    assert_eq!(socket.tcp_keepalive_interval()?, Duration::from_secs(5));
+    assert_eq!(socket.tcp_keepalive_retries()?, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unix_listener_replaces_stale_socket(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = tmp_path("listener.sock");
+    let stale = bind_unix_listener(&path, None)?;
+    drop(stale); // the socket file stays behind, as after a crash
+
+    let listener = bind_unix_listener(&path, Some(0o600))?;
+    let mode = std::fs::metadata(&path)?.permissions().mode() & 0o777;
+    assert_eq!(mode, 0o600);
+    let client = tokio::net::UnixStream::connect(&path).await?;
+    let (_accepted, _) = listener.accept().await?;
+    drop(client);
+    std::fs::remove_file(&path)?;
+
+    // A regular file at the path is never removed.
+    let file = tmp_path("not-a-socket");
+    std::fs::write(&file, b"data")?;
+    assert!(bind_unix_listener(&file, None).is_err());
+    assert!(file.exists());
+    std::fs::remove_file(&file)?;
+    Ok(())
+}
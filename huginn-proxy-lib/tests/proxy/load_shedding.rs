@@ -0,0 +1,65 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use huginn_proxy_lib::config::{LoadSheddingConfig, RoutePriority};
+use huginn_proxy_lib::proxy::load_shedding::LoadShedder;
+use huginn_proxy_lib::telemetry::Metrics;
+
+const WINDOW: Duration = Duration::from_millis(20);
+
+fn shedder() -> Result<LoadShedder, &'static str> {
+    let config = LoadSheddingConfig {
+        enabled: true,
+        latency_threshold_ms: 5,
+        window_ms: 20,
+        max_shed_ratio: 1.0,
+        shed_step: 0.5,
+        ..LoadSheddingConfig::default()
+    };
+    LoadShedder::from_config(&config, Metrics::new_noop()).ok_or("shedder expected")
+}
+
+#[test]
+fn disabled_config_builds_no_shedder() {
+    assert!(LoadShedder::from_config(&LoadSheddingConfig::default(), Metrics::new_noop()).is_none());
+}
+
+#[test]
+fn slow_windows_raise_the_ratio_step_by_step() -> Result<(), &'static str> {
+    let shedder = shedder()?;
+    assert_eq!(shedder.ratio(), 0.0);
+    assert!(!shedder.should_shed(RoutePriority::Low));
+
+    for expected in [0.5, 1.0] {
+        shedder.record(Duration::from_millis(50));
+        sleep(WINDOW);
+        assert_eq!(shedder.ratio(), expected);
+    }
+    assert!(shedder.should_shed(RoutePriority::Low));
+    assert!(!shedder.should_shed(RoutePriority::Normal));
+    assert!(!shedder.should_shed(RoutePriority::High));
+    Ok(())
+}
+
+#[test]
+fn fast_or_idle_windows_lower_the_ratio() -> Result<(), &'static str> {
+    let shedder = shedder()?;
+    shedder.record(Duration::from_millis(50));
+    sleep(WINDOW);
+    assert_eq!(shedder.ratio(), 0.5);
+
+    // Only the slowest 1% may exceed the threshold.
+    for _ in 0..100 {
+        shedder.record(Duration::from_millis(1));
+    }
+    shedder.record(Duration::from_millis(50));
+    sleep(WINDOW);
+    assert_eq!(shedder.ratio(), 0.0);
+
+    shedder.record(Duration::from_millis(50));
+    sleep(WINDOW);
+    assert_eq!(shedder.ratio(), 0.5);
+    sleep(WINDOW);
+    assert_eq!(shedder.ratio(), 0.0);
+    Ok(())
+}
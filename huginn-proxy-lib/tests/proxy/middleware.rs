@@ -0,0 +1,73 @@
+//! `Middleware`: a tower layer stack between fingerprint injection and forwarding.
+
+use std::net::SocketAddr;
+
+use bytes::Bytes;
+use http::{HeaderValue, Response, StatusCode};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::proxy::middleware::{BoxError, Next, ProxyRequest};
+use huginn_proxy_lib::{Backend, ProxyBuilder, Route};
+use tower::layer::layer_fn;
+use tower::util::MapRequestLayer;
+use tower::{service_fn, ServiceBuilder, ServiceExt};
+
+use crate::hot_reload::helpers::free_port;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[tokio::test]
+async fn layers_see_the_prepared_request_and_may_answer_it() -> TestResult {
+    let addr = SocketAddr::from(([127, 0, 0, 1], free_port()?));
+    // Outermost first: tag every request, then answer `/teapot` without forwarding it.
+    let stack = ServiceBuilder::new()
+        .layer(MapRequestLayer::new(|mut req: ProxyRequest| {
+            req.headers_mut()
+                .insert("x-layer", HeaderValue::from_static("seen"));
+            req
+        }))
+        .layer(layer_fn(|next: Next| {
+            service_fn(move |req: ProxyRequest| {
+                let next = next.clone();
+                async move {
+                    if req.uri().path() == "/teapot" {
+                        let body: BoxBody<Bytes, BoxError> =
+                            Full::new(Bytes::from_static(b"short and stout"))
+                                .map_err(|never| match never {})
+                                .boxed();
+                        let mut response = Response::new(body);
+                        *response.status_mut() = StatusCode::IM_A_TEAPOT;
+                        return Ok(response);
+                    }
+                    next.oneshot(req).await
+                }
+            })
+        }));
+
+    let proxy = ProxyBuilder::new()
+        .listen(addr)
+        .backend(Backend { echo: true, ..Backend::new("echo") })
+        .route(Route::new("/", "echo"))
+        .layer(stack)
+        .start()
+        .await?;
+
+    let client = reqwest::Client::new();
+    let echoed: serde_json::Value = client
+        .get(format!("http://{addr}/users"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let headers = &echoed["headers"];
+    assert_eq!(headers["x-layer"], "seen");
+    // Forwarding headers were added before the stack saw the request.
+    assert_eq!(headers["x-forwarded-for"], "127.0.0.1");
+
+    let teapot = client.get(format!("http://{addr}/teapot")).send().await?;
+    assert_eq!(teapot.status().as_u16(), 418);
+    assert_eq!(teapot.text().await?, "short and stout");
+
+    proxy.stop().await?;
+    Ok(())
+}
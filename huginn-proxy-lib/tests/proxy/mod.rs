@@ -1,13 +1,41 @@
+mod bandwidth;
+mod body_bytes;
+mod body_limit;
+mod body_rewrite;
+mod builder;
+mod cache;
 mod client_pool;
+mod client_tracking;
+mod compression;
+mod concurrency;
+mod connect;
 mod connection;
+mod echo;
 mod edge_cases;
+mod ext_authz;
+mod failover;
 mod forwarding;
+mod grpc;
 mod h2c_forwarding;
+mod h2c_upgrade;
 mod handler;
 mod http_result;
+mod listener;
+mod load_shedding;
+mod middleware;
+mod passthrough;
 mod path_manipulation;
 mod peer_resolution;
+mod prefetch;
 mod protocol;
+mod redirect;
 mod reload;
+mod request_id;
 mod resolve;
+mod route_timeout;
 mod router;
+mod socks5;
+mod static_files;
+mod synthetic_response;
+mod udp;
+mod websocket;
@@ -0,0 +1,179 @@
+//! TLS passthrough listeners: SNI route selection and relaying the untouched ClientHello.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use huginn_proxy_lib::config::{PassthroughConfig, PassthroughRoute};
+use huginn_proxy_lib::proxy::connection::ConnectionContext;
+use huginn_proxy_lib::proxy::transport::{
+    handle_passthrough_connection, relay, PassthroughConnectionConfig,
+};
+use huginn_proxy_lib::telemetry::HandshakeCapture;
+use huginn_proxy_lib::Metrics;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// ClientHello without SNI (reqwest connecting to an IP).
+const CLIENT_HELLO: &[u8] = include_bytes!("../../../benches/fixtures/clienthello_reqwest.bin");
+
+fn route(sni: &str, backend: &str) -> PassthroughRoute {
+    PassthroughRoute { sni: sni.to_string(), backend: backend.to_string() }
+}
+
+fn connection_config(passthrough: PassthroughConfig) -> PassthroughConnectionConfig {
+    PassthroughConnectionConfig {
+        passthrough: Arc::new(passthrough),
+        tls_fingerprint: true,
+        metrics: Metrics::new_noop(),
+        tls_handshake_timeout: Duration::from_secs(5),
+        connect_timeout: Some(Duration::from_secs(5)),
+        connection_handling_timeout: Duration::from_secs(5),
+        syn_fingerprint: None,
+        connection: Arc::new(ConnectionContext::new(([127, 0, 0, 1], 40000).into())),
+        tracked: None,
+        handshake_capture: HandshakeCapture::disabled(),
+    }
+}
+
+#[test]
+fn exact_sni_wins_over_wildcards() {
+    let config = PassthroughConfig {
+        routes: vec![
+            route("*.example.com", "wildcard:443"),
+            route("*.eu.example.com", "eu:443"),
+            route("api.eu.example.com", "api:443"),
+        ],
+        default_backend: None,
+    };
+    assert_eq!(config.backend_for(Some("api.eu.example.com")), Some("api:443"));
+    assert_eq!(config.backend_for(Some("API.EU.Example.com.")), Some("api:443"));
+    assert_eq!(config.backend_for(Some("www.eu.example.com")), Some("eu:443"));
+    assert_eq!(config.backend_for(Some("www.example.com")), Some("wildcard:443"));
+    // A wildcard covers subdomains only, not the name itself.
+    assert_eq!(config.backend_for(Some("example.com")), None);
+    assert_eq!(config.backend_for(None), None);
+}
+
+#[test]
+fn unmatched_sni_uses_the_default_backend() {
+    let config = PassthroughConfig {
+        routes: vec![route("api.example.com", "api:443")],
+        default_backend: Some("fallback:443".to_string()),
+    };
+    assert_eq!(config.backend_for(Some("other.example.com")), Some("fallback:443"));
+    assert_eq!(config.backend_for(None), Some("fallback:443"));
+}
+
+#[test]
+fn invalid_passthrough_tables_are_rejected() {
+    let section = "listener 127.0.0.1:443: passthrough";
+    assert!(PassthroughConfig::default().validate(section).is_err());
+    for invalid in [
+        route("", "api:443"),
+        route("*.", "api:443"),
+        route("api.*.example.com", "api:443"),
+        route("api.example.com", "api"),
+        route("api.example.com", "api:0"),
+    ] {
+        let config = PassthroughConfig { routes: vec![invalid.clone()], default_backend: None };
+        assert!(config.validate(section).is_err(), "accepted: {invalid:?}");
+    }
+    let valid = PassthroughConfig {
+        routes: vec![route("*.example.com", "10.0.0.1:443")],
+        default_backend: Some("[::1]:8443".to_string()),
+    };
+    assert!(valid.validate(section).is_ok());
+}
+
+#[tokio::test]
+async fn client_hello_and_traffic_are_relayed_untouched() -> TestResult {
+    let backend = TcpListener::bind("127.0.0.1:0").await?;
+    let backend_addr = backend.local_addr()?;
+    let backend_task = tokio::spawn(async move {
+        let (mut conn, _) = backend.accept().await?;
+        let mut received = vec![0u8; CLIENT_HELLO.len().saturating_add(4)];
+        conn.read_exact(&mut received).await?;
+        conn.write_all(b"pong").await?;
+        Ok::<_, std::io::Error>(received)
+    });
+
+    let (mut client, server) = tokio::io::duplex(64 * 1024);
+    let config = connection_config(PassthroughConfig {
+        routes: vec![route("api.example.com", "127.0.0.1:1")],
+        default_backend: Some(backend_addr.to_string()),
+    });
+    let relay =
+        tokio::spawn(handle_passthrough_connection(server, "203.0.113.7:51000".parse()?, config));
+
+    client.write_all(CLIENT_HELLO).await?;
+    client.write_all(b"ping").await?;
+    let mut reply = [0u8; 4];
+    client.read_exact(&mut reply).await?;
+    assert_eq!(&reply, b"pong");
+
+    let received = backend_task.await??;
+    assert_eq!(received.get(..CLIENT_HELLO.len()), Some(CLIENT_HELLO));
+    assert_eq!(received.get(CLIENT_HELLO.len()..), Some(&b"ping"[..]));
+
+    drop(client);
+    tokio::time::timeout(Duration::from_secs(5), relay).await??;
+    Ok(())
+}
+
+/// A connected socket pair: (accepted side, connecting side).
+async fn tcp_pair() -> Result<(TcpStream, TcpStream), std::io::Error> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let connecting = TcpStream::connect(listener.local_addr()?).await?;
+    let (accepted, _) = listener.accept().await?;
+    Ok((accepted, connecting))
+}
+
+#[tokio::test]
+async fn tcp_clients_are_relayed_both_ways_until_closed() -> TestResult {
+    let (mut client_side, mut client) = tcp_pair().await?;
+    let (mut backend_side, mut backend) = tcp_pair().await?;
+    let relayed = tokio::spawn(async move { relay(&mut client_side, &mut backend_side).await });
+
+    // Larger than a pipe, so the splice path loops.
+    let upload: Vec<u8> = (0..200_000u32).map(|i| i.to_le_bytes()[0]).collect();
+    let expected = upload.clone();
+    let uploading = tokio::spawn(async move {
+        client.write_all(&upload).await?;
+        client.shutdown().await?;
+        let mut reply = Vec::new();
+        client.read_to_end(&mut reply).await?;
+        Ok::<_, std::io::Error>(reply)
+    });
+    let mut received = Vec::new();
+    backend.read_to_end(&mut received).await?;
+    assert_eq!(received, expected);
+    backend.write_all(b"done").await?;
+    backend.shutdown().await?;
+
+    assert_eq!(uploading.await??, b"done");
+    let relayed = tokio::time::timeout(Duration::from_secs(5), relayed).await???;
+    assert_eq!(relayed.from_client, 200_000);
+    assert_eq!(relayed.from_backend, 4);
+    assert_eq!(relayed.spliced, cfg!(all(target_os = "linux", feature = "splice")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn unrouted_client_hello_is_dropped() -> TestResult {
+    let (mut client, server) = tokio::io::duplex(64 * 1024);
+    let config = connection_config(PassthroughConfig {
+        routes: vec![route("api.example.com", "127.0.0.1:1")],
+        default_backend: None,
+    });
+    let relay =
+        tokio::spawn(handle_passthrough_connection(server, "203.0.113.7:51000".parse()?, config));
+
+    client.write_all(CLIENT_HELLO).await?;
+    tokio::time::timeout(Duration::from_secs(5), relay).await??;
+    let mut rest = Vec::new();
+    client.read_to_end(&mut rest).await?;
+    assert!(rest.is_empty());
+    Ok(())
+}
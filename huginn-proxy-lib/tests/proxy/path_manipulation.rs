@@ -1,7 +1,9 @@
 // Tests for path stripping and rewriting functionality
-use huginn_proxy_lib::config::Route;
+use huginn_proxy_lib::config::{PathRewriteConfig, RegexPattern, Route};
 use huginn_proxy_lib::proxy::router::pick_route_with_fingerprinting;
 
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 #[test]
 fn test_path_stripping_basic() {
     // Request: /api/users → Backend: /users (strip /api prefix)
@@ -9,10 +11,8 @@ fn test_path_stripping_basic() {
         prefix: "/api".to_string(),
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
-        replace_path: Some("".to_string()), // Empty string means strip prefix
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        replace_path: Some("".to_string()),
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api/users", &routes);
@@ -33,9 +33,7 @@ fn test_path_stripping_with_query_params() {
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
         replace_path: Some("".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api/users?id=123&name=test", &routes);
@@ -57,9 +55,7 @@ fn test_path_rewriting_basic() {
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
         replace_path: Some("/replacing/path1".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/maps/org/any.ext", &routes);
@@ -80,9 +76,7 @@ fn test_path_rewriting_with_versioned_api() {
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
         replace_path: Some("/v1/api".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api/users", &routes);
@@ -102,10 +96,7 @@ fn test_no_path_manipulation() {
         prefix: "/api".to_string(),
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
-        replace_path: None,
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api/users", &routes);
@@ -125,9 +116,7 @@ fn test_path_manipulation_with_nested_paths() {
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
         replace_path: Some("/backend/v1".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api/v1/users/123", &routes);
@@ -147,9 +136,7 @@ fn test_path_manipulation_root_path() {
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
         replace_path: Some("/api".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/users", &routes);
@@ -170,18 +157,14 @@ fn test_multiple_routes_matching_priority() {
             backend: "backend-v1:9000".to_string(),
             fingerprinting: Some(true),
             replace_path: Some("/v1".to_string()),
-            security: None,
-            headers: None,
-            force_new_connection: false,
+            ..Default::default()
         },
         Route {
             prefix: "/api".to_string(),
             backend: "backend-api:9000".to_string(),
             fingerprinting: Some(true),
             replace_path: Some("/".to_string()),
-            security: None,
-            headers: None,
-            force_new_connection: false,
+            ..Default::default()
         },
     ];
 
@@ -205,9 +188,7 @@ fn test_path_manipulation_exact_prefix_match() {
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
         replace_path: Some("/v1".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api", &routes);
@@ -228,9 +209,7 @@ fn test_path_stripping_to_root() {
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
         replace_path: Some("".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api/health", &routes);
@@ -251,9 +230,7 @@ fn test_path_manipulation_with_special_characters() {
         backend: "backend:9000".to_string(),
         fingerprinting: Some(true),
         replace_path: Some("/v1".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api/users%20info", &routes);
@@ -264,3 +241,44 @@ fn test_path_manipulation_with_special_characters() {
         assert_eq!(route.replace_path, Some("/v1"));
     }
 }
+
+fn rewrite(path: &str, to: &str) -> Result<PathRewriteConfig, BoxError> {
+    Ok(PathRewriteConfig { path: RegexPattern::new(path)?, to: to.to_string(), keep_query: true })
+}
+
+#[test]
+fn test_regex_rewrite_with_capture_groups() -> Result<(), BoxError> {
+    // Request: /v2/users/7?fields=name → Backend: /api/users/7?version=2&fields=name
+    let versioned = rewrite(r"^/v(\d+)/(.*)$", "/api/$2?version=$1")?;
+    assert_eq!(
+        versioned
+            .apply("/v2/users/7", Some("fields=name"))
+            .as_deref(),
+        Some("/api/users/7?version=2&fields=name")
+    );
+    assert_eq!(versioned.apply("/v2/users/7", None).as_deref(), Some("/api/users/7?version=2"));
+    // A path that does not match keeps the request's own path and query.
+    assert_eq!(versioned.apply("/users/7", Some("fields=name")), None);
+
+    let named = rewrite(r"^/u/(?P<id>[0-9]+)$", "/users/${id}/profile")?;
+    assert_eq!(named.apply("/u/42", Some("a=1")).as_deref(), Some("/users/42/profile?a=1"));
+    let dropped = PathRewriteConfig { keep_query: false, ..named };
+    assert_eq!(dropped.apply("/u/42", Some("a=1")).as_deref(), Some("/users/42/profile"));
+    Ok(())
+}
+
+#[test]
+fn test_regex_rewrite_validation() -> Result<(), BoxError> {
+    assert!(rewrite(r"^/v(\d+)/(.*)$", "/api/$2?version=$1")?
+        .validate()
+        .is_ok());
+    assert!(rewrite(r"^/v(\d+)/(.*)$", "/api/$3")?.validate().is_err());
+    assert!(rewrite(r"^/v(\d+)/(.*)$", "/api/${name}")?
+        .validate()
+        .is_err());
+    assert!(rewrite(r"^/v(\d+)$", "/api/${1")?.validate().is_err());
+    assert!(rewrite(r"^/v(\d+)$", "/api/$")?.validate().is_err());
+    assert!(rewrite(r"^/v(\d+)$", "api/$1")?.validate().is_err());
+    assert!(rewrite(r"^/v(\d+)$", "/api/$1#top")?.validate().is_err());
+    Ok(())
+}
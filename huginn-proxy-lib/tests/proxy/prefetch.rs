@@ -0,0 +1,95 @@
+//! Read-ahead bodies: `PrefetchedBody` replays what was read for inspection, then the rest.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::HeaderMap;
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::proxy::prefetch::PrefetchedBody;
+use hyper::body::{Body, Frame};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// Body streaming its frames one at a time.
+struct Frames(VecDeque<Frame<Bytes>>);
+
+impl Body for Frames {
+    type Data = Bytes;
+    type Error = std::convert::Infallible;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Poll::Ready(self.get_mut().0.pop_front().map(Ok))
+    }
+}
+
+fn chunks(chunks: &[&'static [u8]]) -> Frames {
+    Frames(
+        chunks
+            .iter()
+            .map(|c| Frame::data(Bytes::from_static(c)))
+            .collect(),
+    )
+}
+
+#[tokio::test]
+async fn prefetch_stops_at_the_limit_and_replays_the_whole_body() -> TestResult {
+    let (prefix, body) =
+        PrefetchedBody::prefetch(chunks(&[b"aaaa", b"bbbb", b"cccc", b"dddd"]), 6).await?;
+    assert_eq!(&prefix[..], b"aaaabbbb");
+    assert!(!body.is_end_stream());
+    assert_eq!(&body.collect().await?.to_bytes()[..], b"aaaabbbbccccdddd");
+    Ok(())
+}
+
+#[tokio::test]
+async fn short_bodies_and_trailers_are_replayed() -> TestResult {
+    let (prefix, body) =
+        PrefetchedBody::prefetch(Full::new(Bytes::from_static(b"small")), 1024).await?;
+    assert_eq!(&prefix[..], b"small");
+    assert_eq!(body.size_hint().exact(), Some(5));
+    assert_eq!(&body.collect().await?.to_bytes()[..], b"small");
+
+    let mut trailers = HeaderMap::new();
+    trailers.insert("grpc-status", http::HeaderValue::from_static("0"));
+    let mut frames = chunks(&[b"payload"]);
+    frames.0.push_back(Frame::trailers(trailers.clone()));
+    let (prefix, body) = PrefetchedBody::prefetch(frames, 1024).await?;
+    assert_eq!(&prefix[..], b"payload");
+    let collected = body.collect().await?;
+    assert_eq!(collected.trailers(), Some(&trailers));
+    assert_eq!(&collected.to_bytes()[..], b"payload");
+
+    let (prefix, body) = PrefetchedBody::prefetch(chunks(&[]), 1024).await?;
+    assert!(prefix.is_empty());
+    assert!(body.is_end_stream());
+    Ok(())
+}
+
+#[tokio::test]
+async fn filled_bodies_can_be_copied_until_they_stream() -> TestResult {
+    let body = PrefetchedBody::new(chunks(&[b"aaaa", b"bbbb"]))
+        .fill(16)
+        .await?;
+    let copy = body.try_clone().ok_or("whole body not copied")?;
+    assert_eq!(&copy.collect().await?.to_bytes()[..], b"aaaabbbb");
+    assert_eq!(&body.collect().await?.to_bytes()[..], b"aaaabbbb");
+
+    let body = PrefetchedBody::new(chunks(&[b"aaaa", b"bbbb", b"cccc"]))
+        .fill(6)
+        .await?;
+    assert!(body.try_clone().is_none());
+    assert_eq!(&body.collect().await?.to_bytes()[..], b"aaaabbbbcccc");
+    Ok(())
+}
+
+#[tokio::test]
+async fn pass_through_body_is_unchanged() -> TestResult {
+    let body = PrefetchedBody::new(chunks(&[b"one", b"two"]));
+    assert_eq!(&body.collect().await?.to_bytes()[..], b"onetwo");
+    Ok(())
+}
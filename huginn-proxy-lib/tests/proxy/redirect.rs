@@ -0,0 +1,114 @@
+use std::net::IpAddr;
+
+use http::{HeaderMap, HeaderValue, StatusCode, Uri};
+use huginn_proxy_lib::config::{Config, RedirectConfig, TrustedProxiesConfig};
+use huginn_proxy_lib::proxy::redirect::{find_redirect, is_secure_request, KIND_HTTPS, KIND_RULE};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const RULES: &str = r#"
+https = true
+[[rules]]
+path = "^/old/(.*)$"
+to = "https://new.example.com/$1"
+[[rules]]
+host = "docs.example.com"
+path = "^/v1/(?P<page>[^/]+)$"
+to = "/v2/${page}?from=v1"
+status = 308
+"#;
+
+#[test]
+fn rules_expand_capture_groups_and_keep_the_query() -> TestResult {
+    let config: RedirectConfig = toml::from_str(RULES)?;
+    config.validate()?;
+
+    let uri: Uri = "/old/a/b.html?lang=en".parse()?;
+    let redirect = find_redirect(&config, "example.com", &uri, true).ok_or("no redirect")?;
+    assert_eq!(redirect.status, StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(redirect.location, "https://new.example.com/a/b.html?lang=en");
+    assert_eq!(redirect.kind, KIND_RULE);
+
+    let uri: Uri = "/v1/intro?x=1".parse()?;
+    let redirect = find_redirect(&config, "docs.example.com", &uri, true).ok_or("no redirect")?;
+    assert_eq!(redirect.status, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(redirect.location, "/v2/intro?from=v1&x=1");
+    assert_eq!(find_redirect(&config, "example.com", &uri, true), None);
+
+    let response = redirect.into_response();
+    assert_eq!(response.headers()["location"], "/v2/intro?from=v1&x=1");
+    Ok(())
+}
+
+#[test]
+fn plaintext_requests_are_sent_to_https() -> TestResult {
+    let mut config: RedirectConfig = toml::from_str(RULES)?;
+    let uri: Uri = "/shop/cart?id=7".parse()?;
+
+    let redirect = find_redirect(&config, "example.com", &uri, false).ok_or("no redirect")?;
+    assert_eq!(redirect.status, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(redirect.location, "https://example.com/shop/cart?id=7");
+    assert_eq!(redirect.kind, KIND_HTTPS);
+    assert_eq!(find_redirect(&config, "example.com", &uri, true), None);
+
+    config.https_port = 8443;
+    let redirect = find_redirect(&config, "::1", &uri, false).ok_or("no redirect")?;
+    assert_eq!(redirect.location, "https://[::1]:8443/shop/cart?id=7");
+
+    // A matching rule wins over the HTTPS redirect.
+    let uri: Uri = "/old/page".parse()?;
+    let redirect = find_redirect(&config, "example.com", &uri, false).ok_or("no redirect")?;
+    assert_eq!(redirect.location, "https://new.example.com/page");
+    Ok(())
+}
+
+#[test]
+fn forwarded_proto_counts_only_from_trusted_proxies() -> TestResult {
+    let trusted: TrustedProxiesConfig = toml::from_str(r#"cidrs = ["10.0.0.0/8"]"#)?;
+    let mut headers = HeaderMap::new();
+    headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+    let proxy: IpAddr = "10.1.2.3".parse()?;
+    let client: IpAddr = "203.0.113.9".parse()?;
+
+    assert!(is_secure_request(false, proxy, &headers, &trusted));
+    assert!(!is_secure_request(false, client, &headers, &trusted));
+    assert!(!is_secure_request(false, proxy, &HeaderMap::new(), &trusted));
+    assert!(is_secure_request(true, client, &HeaderMap::new(), &trusted));
+    Ok(())
+}
+
+#[test]
+fn invalid_redirects_are_rejected_at_load() -> TestResult {
+    for (good, bad) in [
+        ("to = \"https://new.example.com/$1\"", "to = \"https://new.example.com/$2\""),
+        ("to = \"https://new.example.com/$1\"", "to = \"https://new.example.com/$1x\""),
+        ("to = \"https://new.example.com/$1\"", "to = \"new.example.com/$1\""),
+        ("status = 308", "status = 200"),
+        ("https = true", "https = true\nhttps_port = 0"),
+    ] {
+        let config: RedirectConfig = toml::from_str(&RULES.replace(good, bad))?;
+        assert!(config.validate().is_err(), "{bad} should be rejected");
+    }
+    assert!(toml::from_str::<RedirectConfig>(&RULES.replace("^/old/(.*)$", "^/old/(.*$")).is_err());
+
+    let config: Config = toml::from_str(
+        r#"
+listen = { addrs = ["0.0.0.0:7000"] }
+backends = [{ address = "app:9000" }]
+redirect = { https = true }
+
+[[domains]]
+host = "old.example.com"
+redirect = { rules = [{ path = "^/(.*)$", to = "https://example.com/$1", status = 307 }] }
+"#,
+    )?;
+    config.validate_cross_refs()?;
+    let domain = config.domains.first().ok_or("domain missing")?;
+    let rule = domain
+        .redirect
+        .as_ref()
+        .and_then(|r| r.rules.first())
+        .ok_or("rule missing")?;
+    assert_eq!((rule.path.as_str(), rule.status), ("^/(.*)$", 307));
+    Ok(())
+}
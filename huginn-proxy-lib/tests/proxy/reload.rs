@@ -3,8 +3,9 @@ use std::sync::Arc;
 use arc_swap::ArcSwap;
 use huginn_proxy_lib::config::{load_from_path, ConfigParts, DynamicConfig};
 use huginn_proxy_lib::{
-    initial_client_pool, initial_rate_limiter, try_reload, HealthCheckSupervisor, HealthRegistry,
-    Metrics, SharedClientPool, SharedRateLimiter, StaticConfig,
+    initial_client_pool, initial_rate_limiter, try_reload, BackendDiscovery, ConfigGeneration,
+    HealthCheckSupervisor, HealthRegistry, Metrics, SharedClientPool, SharedRateLimiter,
+    StaticConfig,
 };
 
 type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
@@ -125,6 +126,7 @@ struct Harness {
     dynamic: Arc<ArcSwap<DynamicConfig>>,
     rate_limiter: SharedRateLimiter,
     client_pool: SharedClientPool,
+    generation: ConfigGeneration,
 }
 
 impl Harness {
@@ -136,8 +138,24 @@ impl Harness {
         let static_cfg = Arc::new(static_cfg);
         let dynamic = Arc::new(ArcSwap::from_pointee(dynamic_cfg));
         let rate_limiter = initial_rate_limiter(&dynamic.load());
-        let client_pool = initial_client_pool(&static_cfg, &dynamic.load().backend_pool);
-        Ok(Self { tmp, static_cfg, dynamic, rate_limiter, client_pool })
+        let client_pool = {
+            let current = dynamic.load();
+            initial_client_pool(
+                &static_cfg,
+                &current.backend_pool,
+                &current.backends,
+                &Metrics::new_noop(),
+                &BackendDiscovery::new(),
+            )?
+        };
+        Ok(Self {
+            tmp,
+            static_cfg,
+            dynamic,
+            rate_limiter,
+            client_pool,
+            generation: ConfigGeneration::new(),
+        })
     }
 
     async fn reload(&self, content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -154,7 +172,9 @@ impl Harness {
             &reload_mutex,
             &metrics,
             &health,
+            &BackendDiscovery::new(),
             None,
+            &self.generation,
         )
         .await;
         Ok(())
@@ -376,6 +396,30 @@ async fn reload_applies_new_dynamic_config_when_route_changes() -> TestResult {
     Ok(())
 }
 
+#[tokio::test]
+async fn reload_signals_open_connections_when_dynamic_config_changes() -> TestResult {
+    let before = base();
+    let mut after = before.clone();
+    after.domains[0].routes[0].prefix = "/api";
+    let h = Harness::start(&before)?;
+    let mut config_changed = h.generation.subscribe();
+    h.reload_spec(&after).await?;
+    assert!(config_changed.has_changed()?);
+    assert_eq!(*config_changed.borrow_and_update(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reload_leaves_open_connections_alone_when_config_unchanged_or_invalid() -> TestResult {
+    let h = Harness::start(&base())?;
+    let config_changed = h.generation.subscribe();
+    h.reload_spec(&base()).await?;
+    h.reload("this is not valid toml !!!! @@@").await?;
+    assert!(!config_changed.has_changed()?);
+    assert_eq!(h.generation.current(), 0);
+    Ok(())
+}
+
 #[tokio::test]
 async fn reload_rebuilds_rate_limiter_when_second_domain_override_value_changes() -> TestResult {
     let mut before = base();
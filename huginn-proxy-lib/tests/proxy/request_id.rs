@@ -0,0 +1,95 @@
+use http::{HeaderValue, Request, Response};
+use huginn_proxy_lib::config::{RequestIdConfig, RequestIdFormat, TrustedProxiesConfig};
+use huginn_proxy_lib::proxy::request_id::{ulid, uuid_v4, RequestId, RequestIdGenerator};
+use ipnet::IpNet;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+fn generator(format: RequestIdFormat, trust_incoming: bool) -> Option<RequestIdGenerator> {
+    RequestIdGenerator::from_config(&RequestIdConfig {
+        enabled: true,
+        format,
+        trust_incoming,
+        ..RequestIdConfig::default()
+    })
+}
+
+fn trusted_proxies() -> Result<TrustedProxiesConfig, ipnet::AddrParseError> {
+    Ok(TrustedProxiesConfig { cidrs: vec![IpNet::from_str("10.0.0.0/8")?], insecure: false })
+}
+
+#[test]
+fn id_formats() {
+    assert_eq!(uuid_v4(0), "00000000-0000-4000-8000-000000000000");
+    assert_eq!(uuid_v4(u128::MAX), "ffffffff-ffff-4fff-bfff-ffffffffffff");
+    // Timestamp of the ULID spec example.
+    assert_eq!(ulid(1_469_918_176_385, 0), "01ARYZ6S410000000000000000");
+    assert_eq!(ulid(0, u128::MAX), "0000000000ZZZZZZZZZZZZZZZZ");
+}
+
+#[test]
+fn generated_id_is_set_on_request_and_echoed() -> TestResult {
+    let ids = generator(RequestIdFormat::Ulid, false).ok_or("generator expected")?;
+    let mut req = Request::new(());
+    let id = ids.assign(&mut req, IpAddr::from([192, 0, 2, 1]), &trusted_proxies()?);
+
+    assert_eq!(id.as_str().len(), 26);
+    assert_eq!(
+        req.headers().get("x-request-id").map(HeaderValue::as_bytes),
+        Some(id.as_str().as_bytes())
+    );
+    assert_eq!(req.extensions().get::<RequestId>().map(RequestId::as_str), Some(id.as_str()));
+
+    let mut response = Response::new(());
+    id.echo(&mut response);
+    assert_eq!(response.headers().get("x-request-id"), req.headers().get("x-request-id"));
+
+    // A backend's own ID is kept.
+    let mut response = Response::new(());
+    response
+        .headers_mut()
+        .insert("x-request-id", HeaderValue::from_static("backend-id"));
+    id.echo(&mut response);
+    assert_eq!(
+        response.headers().get("x-request-id"),
+        Some(&HeaderValue::from_static("backend-id"))
+    );
+    Ok(())
+}
+
+#[test]
+fn incoming_id_is_kept_only_from_trusted_proxies() -> TestResult {
+    let incoming = |ids: &RequestIdGenerator, peer: [u8; 4], value: &'static str| {
+        let mut req = Request::new(());
+        req.headers_mut()
+            .insert("x-request-id", HeaderValue::from_static(value));
+        let trusted_proxies = trusted_proxies()?;
+        let id = ids.assign(&mut req, IpAddr::from(peer), &trusted_proxies);
+        Ok::<_, ipnet::AddrParseError>(id.as_str().to_string())
+    };
+
+    let trusting = generator(RequestIdFormat::Uuid, true).ok_or("generator expected")?;
+    assert_eq!(incoming(&trusting, [10, 0, 0, 1], "lb-123")?, "lb-123");
+    assert_ne!(incoming(&trusting, [192, 0, 2, 1], "spoofed")?, "spoofed");
+    assert_ne!(incoming(&trusting, [10, 0, 0, 1], "has space")?, "has space");
+
+    let untrusting = generator(RequestIdFormat::Uuid, false).ok_or("generator expected")?;
+    assert_ne!(incoming(&untrusting, [10, 0, 0, 1], "lb-123")?, "lb-123");
+    Ok(())
+}
+
+#[test]
+fn disabled_or_invalid_header_is_rejected() {
+    assert!(RequestIdGenerator::from_config(&RequestIdConfig::default()).is_none());
+    let invalid = RequestIdConfig {
+        enabled: true,
+        header: "bad header".to_string(),
+        ..RequestIdConfig::default()
+    };
+    assert!(invalid.validate().is_err());
+    assert!(RequestIdConfig { enabled: true, ..RequestIdConfig::default() }
+        .validate()
+        .is_ok());
+}
@@ -1,6 +1,7 @@
 use huginn_proxy_lib::config::{
-    Domain, DomainSecurityConfig, HstsConfig, IpFilterConfig, IpFilterMode, RateLimitConfig, Route,
-    RouteSecurityConfig, SecurityHeaders, TrustedProxiesConfig,
+    Domain, DomainSecurityConfig, FingerprintFilterConfig, FingerprintFormat, HstsConfig,
+    IpFilterConfig, IpFilterMode, Ja4Variant, RateLimitConfig, Route, RouteSecurityConfig,
+    SecurityHeaders, TrustedProxiesConfig,
 };
 use huginn_proxy_lib::proxy::handler::resolve::domain_defers_ip_filter;
 use huginn_proxy_lib::proxy::handler::resolve_security;
@@ -30,6 +31,7 @@ fn ctx(ip_filter: IpFilterConfig, headers: SecurityHeaders) -> SecurityContext {
         None,
         None,
         TrustedProxiesConfig::default(),
+        FingerprintFilterConfig::default(),
     )
 }
 
@@ -38,10 +40,8 @@ fn route(security: Option<RouteSecurityConfig>, fingerprinting: Option<bool>) ->
         prefix: "/".to_string(),
         backend: "backend:80".to_string(),
         fingerprinting,
-        force_new_connection: false,
-        replace_path: None,
         security,
-        headers: None,
+        ..Default::default()
     }
 }
 
@@ -57,7 +57,11 @@ fn domain(
         headers: None,
         security,
         fingerprinting,
+        ja4_variants: None,
+        fingerprint_format: None,
+        redirect: None,
         routes,
+        tenant: None,
     }
 }
 
@@ -179,6 +183,63 @@ fn fingerprinting_resolves_route_over_domain_over_default() -> R {
     Ok(())
 }
 
+#[test]
+fn ja4_variants_resolve_route_over_domain_over_default() -> R {
+    let global = ctx(IpFilterConfig::default(), SecurityHeaders::default());
+
+    // Route selection beats domain selection, including an explicit empty list.
+    let mut d = domain(None, None, vec![route(None, None)]);
+    d.ja4_variants = Some(vec![Ja4Variant::Ja4, Ja4Variant::Ja4R]);
+    d.routes[0].ja4_variants = Some(vec![]);
+    let rm = pick_route_with_fingerprinting("/", &d.routes).ok_or("route should match")?;
+    assert!(resolve_security(&global, Some(&d), &rm)
+        .ja4_variants
+        .is_empty());
+
+    // Route unset → domain decides.
+    d.routes[0].ja4_variants = None;
+    let rm = pick_route_with_fingerprinting("/", &d.routes).ok_or("route should match")?;
+    assert_eq!(
+        resolve_security(&global, Some(&d), &rm).ja4_variants,
+        &[Ja4Variant::Ja4, Ja4Variant::Ja4R]
+    );
+
+    // Neither set → every variant.
+    let d = domain(None, None, vec![route(None, None)]);
+    let rm = pick_route_with_fingerprinting("/", &d.routes).ok_or("route should match")?;
+    assert_eq!(resolve_security(&global, Some(&d), &rm).ja4_variants, Ja4Variant::ALL);
+    Ok(())
+}
+
+#[test]
+fn fingerprint_format_resolves_route_over_domain_over_default() -> R {
+    let global = ctx(IpFilterConfig::default(), SecurityHeaders::default());
+
+    let mut d = domain(None, None, vec![route(None, None)]);
+    d.fingerprint_format = Some(FingerprintFormat::Jwt);
+    d.routes[0].fingerprint_format = Some(FingerprintFormat::Structured);
+    let rm = pick_route_with_fingerprinting("/", &d.routes).ok_or("route should match")?;
+    assert_eq!(
+        resolve_security(&global, Some(&d), &rm).fingerprint_format,
+        FingerprintFormat::Structured
+    );
+
+    d.routes[0].fingerprint_format = None;
+    let rm = pick_route_with_fingerprinting("/", &d.routes).ok_or("route should match")?;
+    assert_eq!(
+        resolve_security(&global, Some(&d), &rm).fingerprint_format,
+        FingerprintFormat::Jwt
+    );
+
+    let d = domain(None, None, vec![route(None, None)]);
+    let rm = pick_route_with_fingerprinting("/", &d.routes).ok_or("route should match")?;
+    assert_eq!(
+        resolve_security(&global, Some(&d), &rm).fingerprint_format,
+        FingerprintFormat::Headers
+    );
+    Ok(())
+}
+
 #[test]
 fn domain_defers_ip_filter_only_with_route_override() {
     // No route override → check stays pre-routing.
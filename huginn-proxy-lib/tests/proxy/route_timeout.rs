@@ -0,0 +1,84 @@
+//! Per-route backend timeouts: connect override scoping and the total-deadline body wrapper.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::proxy::route_timeout::{
+    connect_timeout_override, is_connect_timeout, with_connect_timeout, ConnectTimeout,
+    DeadlineBody, TimeoutKind, TotalTimeout,
+};
+use hyper::body::{Body, Frame};
+use tokio::time::Instant;
+
+/// Body whose first frame never arrives, like a backend that stalls mid-response.
+struct Stalled;
+
+impl Body for Stalled {
+    type Data = Bytes;
+    type Error = std::convert::Infallible;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Poll::Pending
+    }
+}
+
+#[tokio::test]
+async fn connect_timeout_override_is_scoped_to_the_request() {
+    assert_eq!(connect_timeout_override(), None);
+    let inside = with_connect_timeout(Some(Duration::from_millis(250)), async {
+        connect_timeout_override()
+    })
+    .await;
+    assert_eq!(inside, Some(Duration::from_millis(250)));
+    assert_eq!(with_connect_timeout(None, async { connect_timeout_override() }).await, None);
+    assert_eq!(connect_timeout_override(), None);
+}
+
+#[test]
+fn connect_timeout_is_found_in_the_error_chain() {
+    #[derive(Debug, thiserror::Error)]
+    #[error("client error")]
+    struct Wrapper(#[source] ConnectTimeout);
+
+    let wrapped = Wrapper(ConnectTimeout(Duration::from_millis(100)));
+    assert!(is_connect_timeout(&wrapped));
+    assert!(!is_connect_timeout(&TotalTimeout));
+    assert_eq!(TimeoutKind::FirstByte.as_str(), "first_byte");
+}
+
+#[tokio::test]
+async fn body_finishing_before_the_deadline_passes_through(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let body = DeadlineBody::new(Full::new(Bytes::from_static(b"report")), Some(deadline));
+    assert_eq!(body.collect().await?.to_bytes(), Bytes::from_static(b"report"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn stalled_body_fails_at_the_deadline_and_reports_once() {
+    let expired = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&expired);
+    let started = Instant::now();
+    let body = DeadlineBody::new(Stalled, Some(started + Duration::from_millis(50))).on_expired(
+        move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        },
+    );
+
+    let err = match body.collect().await {
+        Ok(_) => panic!("stalled body must fail at the deadline"),
+        Err(e) => e,
+    };
+    assert!(err.downcast_ref::<TotalTimeout>().is_some());
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert_eq!(expired.load(Ordering::Relaxed), 1);
+}
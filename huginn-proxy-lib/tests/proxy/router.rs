@@ -1,6 +1,8 @@
-use huginn_proxy_lib::config::{sort_domain_routes, sort_routes, Domain, Route};
+use http::{HeaderMap, HeaderValue, Method};
+use huginn_proxy_lib::config::{sort_domain_routes, sort_routes, Domain, RegexPattern, Route};
 use huginn_proxy_lib::proxy::router::{
-    authority_matches_sni, pick_domain, pick_route, pick_route_with_fingerprinting, prefix_matches,
+    authority_matches_sni, pick_domain, pick_request_route, pick_route,
+    pick_route_with_fingerprinting, pick_tenant_domain, prefix_matches,
 };
 
 fn route(prefix: &str, backend: &str) -> Route {
@@ -8,10 +10,7 @@ fn route(prefix: &str, backend: &str) -> Route {
         prefix: prefix.to_string(),
         backend: backend.to_string(),
         fingerprinting: Some(true),
-        force_new_connection: false,
-        replace_path: None,
-        security: None,
-        headers: None,
+        ..Default::default()
     }
 }
 
@@ -28,7 +27,11 @@ fn domain(host: &str, routes: Vec<Route>) -> Domain {
         headers: None,
         security: None,
         fingerprinting: None,
+        ja4_variants: None,
+        fingerprint_format: None,
+        redirect: None,
         routes,
+        tenant: None,
     }
 }
 
@@ -40,7 +43,11 @@ fn domain_with_cert(host: &str, cert_path: &str) -> Domain {
         headers: None,
         security: None,
         fingerprinting: None,
+        ja4_variants: None,
+        fingerprint_format: None,
+        redirect: None,
         routes: vec![],
+        tenant: None,
     }
 }
 
@@ -53,7 +60,11 @@ fn catch_all(routes: Vec<Route>) -> Domain {
         headers: None,
         security: None,
         fingerprinting: None,
+        ja4_variants: None,
+        fingerprint_format: None,
+        redirect: None,
         routes,
+        tenant: None,
     }
 }
 
@@ -105,9 +116,7 @@ fn test_pick_route_with_fingerprinting_with_replace_path() {
         backend: "backend-a:9000".to_string(),
         fingerprinting: Some(true),
         replace_path: Some("/v1".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api/users", &routes);
@@ -126,9 +135,7 @@ fn test_pick_route_with_fingerprinting_path_stripping() {
         backend: "backend-a:9000".to_string(),
         fingerprinting: Some(false),
         replace_path: Some("".to_string()),
-        security: None,
-        headers: None,
-        force_new_connection: false,
+        ..Default::default()
     }];
 
     let result = pick_route_with_fingerprinting("/api/users", &routes);
@@ -156,6 +163,118 @@ fn test_pick_route_with_fingerprinting_collects_same_prefix_candidates() {
     }
 }
 
+fn exact_route(prefix: &str, backend: &str) -> Route {
+    Route { exact: true, ..route(prefix, backend) }
+}
+
+fn regex_route(prefix: &str, regex: &str, backend: &str) -> Route {
+    match RegexPattern::new(regex) {
+        Ok(regex) => Route { regex: Some(regex), ..route(prefix, backend) },
+        Err(e) => panic!("invalid test regex: {e}"),
+    }
+}
+
+#[test]
+fn exact_route_does_not_match_sub_paths() {
+    let routes =
+        sorted_routes(vec![route("/health", "prefix:9000"), exact_route("/health", "exact:9000")]);
+    assert_eq!(pick_route("/health", &routes), Some("exact:9000"));
+    assert_eq!(pick_route("/health/deep", &routes), Some("prefix:9000"));
+    assert_eq!(pick_route("/healthz", &routes), None);
+}
+
+#[test]
+fn same_prefix_tries_exact_then_regex_then_prefix() {
+    let routes = sorted_routes(vec![
+        route("/users", "list:9000"),
+        regex_route("/users", r"^/users/[0-9]+$", "by-id:9000"),
+        exact_route("/users", "index:9000"),
+    ]);
+    assert_eq!(pick_route("/users", &routes), Some("index:9000"));
+    assert_eq!(pick_route("/users/42", &routes), Some("by-id:9000"));
+    assert_eq!(pick_route("/users/me", &routes), Some("list:9000"));
+}
+
+#[test]
+fn match_priority_overrides_longest_prefix() {
+    let assets = regex_route("/", r"\.(css|js|png)$", "cdn:9000");
+    let routes = sorted_routes(vec![route("/app", "app:9000"), assets.clone()]);
+    assert_eq!(pick_route("/app/main.css", &routes), Some("app:9000"));
+    assert_eq!(pick_route("/logo.png", &routes), Some("cdn:9000"));
+
+    let routes =
+        sorted_routes(vec![route("/app", "app:9000"), Route { match_priority: 10, ..assets }]);
+    assert_eq!(pick_route("/app/main.css", &routes), Some("cdn:9000"));
+    assert_eq!(pick_route("/app/index.html", &routes), Some("app:9000"));
+}
+
+#[test]
+fn candidates_share_the_whole_matcher() {
+    let routes = sorted_routes(vec![
+        regex_route("/api", "^/api/v1/", "v1-a:9000"),
+        regex_route("/api", "^/api/v2/", "v2:9000"),
+        regex_route("/api", "^/api/v1/", "v1-b:9000"),
+        route("/api", "api:9000"),
+    ]);
+    let Some(r) = pick_route_with_fingerprinting("/api/v1/users", &routes) else {
+        panic!("Expected a route match for /api/v1/users");
+    };
+    assert_eq!(r.backend_candidates, vec!["v1-a:9000", "v1-b:9000"]);
+}
+
+fn picked<'a>(
+    method: Method,
+    path: &str,
+    headers: &HeaderMap,
+    routes: &'a [Route],
+) -> Option<&'a str> {
+    pick_request_route(&method, path, headers, routes).map(|r| r.backend)
+}
+
+#[test]
+fn methods_send_uploads_to_a_dedicated_backend() {
+    let upload = Route {
+        methods: vec!["POST".to_string(), "PUT".to_string()],
+        ..route("/api/upload", "upload:9000")
+    };
+    let routes =
+        sorted_routes(vec![route("/api", "main:9000"), route("/api/upload", "main:9000"), upload]);
+    let headers = HeaderMap::new();
+    assert_eq!(picked(Method::POST, "/api/upload", &headers, &routes), Some("upload:9000"));
+    assert_eq!(picked(Method::PUT, "/api/upload/big", &headers, &routes), Some("upload:9000"));
+    assert_eq!(picked(Method::GET, "/api/upload", &headers, &routes), Some("main:9000"));
+    assert_eq!(picked(Method::POST, "/api/users", &headers, &routes), Some("main:9000"));
+}
+
+#[test]
+fn header_conditions_must_all_hold() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let canary: Route = toml::from_str(
+        r#"
+prefix = "/"
+backend = "canary:9000"
+match_headers = [
+  { name = "X-Canary" },
+  { name = "x-tenant", equals = "acme" },
+  { name = "user-agent", regex = "(?i)mobile" },
+]
+"#,
+    )?;
+    let routes = sorted_routes(vec![route("/", "stable:9000"), canary]);
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-canary", HeaderValue::from_static(""));
+    headers.insert("x-tenant", HeaderValue::from_static("acme"));
+    headers.insert("user-agent", HeaderValue::from_static("App/2.0 (Mobile)"));
+    assert_eq!(picked(Method::GET, "/", &headers, &routes), Some("canary:9000"));
+
+    headers.insert("x-tenant", HeaderValue::from_static("ACME"));
+    assert_eq!(picked(Method::GET, "/", &headers, &routes), Some("stable:9000"));
+    headers.insert("x-tenant", HeaderValue::from_static("acme"));
+    headers.remove("x-canary");
+    assert_eq!(picked(Method::GET, "/", &headers, &routes), Some("stable:9000"));
+    Ok(())
+}
+
 #[test]
 fn longest_prefix_wins_over_declaration_order() {
     let routes = sorted_routes(vec![
@@ -356,6 +475,28 @@ fn no_match_returns_none() {
     assert!(pick_domain(&domains, "other.example.com").is_none());
 }
 
+#[test]
+fn tenant_domains_only_are_picked_for_a_tenant() {
+    let owned = |host: &str, tenant: &str| Domain {
+        tenant: Some(tenant.to_string()),
+        ..domain(host, vec![])
+    };
+    let domains = vec![
+        owned("api.example.com", "api"),
+        owned("*.example.com", "web"),
+        Domain { tenant: Some("web".to_string()), ..catch_all(vec![]) },
+    ];
+    let picked = |host: &str, tenant: Option<&str>| {
+        pick_tenant_domain(&domains, host, tenant).and_then(|d| d.host.clone())
+    };
+    assert_eq!(picked("api.example.com", None).as_deref(), Some("api.example.com"));
+    assert_eq!(picked("api.example.com", Some("api")).as_deref(), Some("api.example.com"));
+    // Another tenant's host falls through to this tenant's wildcard.
+    assert_eq!(picked("api.example.com", Some("web")).as_deref(), Some("*.example.com"));
+    assert!(pick_tenant_domain(&domains, "other.test", Some("api")).is_none());
+    assert!(pick_tenant_domain(&domains, "other.test", Some("web")).is_some());
+}
+
 #[test]
 fn empty_domains_returns_none() {
     let domains: Vec<Domain> = vec![];
@@ -526,7 +667,11 @@ fn authority_matches_sni_certless_host_uses_default_cert() {
         headers: None,
         security: None,
         fingerprinting: None,
+        ja4_variants: None,
+        fingerprint_format: None,
+        redirect: None,
         routes: vec![],
+        tenant: None,
     };
     let domains = vec![domain("api.example.com", vec![]), catch_all_with_cert];
     // SNI=api.example.com -> certless -> default cert; authority=other -> catch-all -> default cert.
@@ -0,0 +1,251 @@
+//! `serve_static` routes: path resolution and traversal protection, content types, conditional
+//! and range requests.
+
+use std::path::{Path, PathBuf};
+
+use http::header::{
+    ACCEPT_RANGES, ALLOW, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+    IF_NONE_MATCH, IF_RANGE, RANGE,
+};
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use http_body_util::BodyExt;
+use huginn_proxy_lib::config::ServeStaticConfig;
+use huginn_proxy_lib::proxy::static_files::{
+    content_type, parse_range, relative_path, serve_static, ByteRange,
+};
+use huginn_proxy_lib::{ProxyBuilder, Route, Verdict};
+
+use crate::helpers::error_message;
+use crate::hot_reload::helpers::{free_port, http_get};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+fn config(root: &Path) -> ServeStaticConfig {
+    ServeStaticConfig {
+        root: root.display().to_string(),
+        index: "index.html".to_string(),
+        cache_control: Some("public, max-age=60".to_string()),
+        dotfiles: false,
+    }
+}
+
+/// `root` with `index.html`, `app.css`, `.env`, and a `secret.txt` next to it (outside `root`).
+fn site() -> Result<(tempfile::TempDir, PathBuf), BoxError> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path().join("public");
+    std::fs::create_dir_all(root.join("css"))?;
+    std::fs::write(root.join("index.html"), "<h1>home</h1>")?;
+    std::fs::write(root.join("css").join("app.css"), "body{color:#333}")?;
+    std::fs::write(root.join(".env"), "TOKEN=1")?;
+    std::fs::write(dir.path().join("secret.txt"), "secret")?;
+    Ok((dir, root))
+}
+
+fn request_headers(pairs: &[(http::HeaderName, &'static str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.insert(name.clone(), HeaderValue::from_static(value));
+    }
+    headers
+}
+
+async fn get(
+    config: &ServeStaticConfig,
+    path: &str,
+    headers: &HeaderMap,
+) -> Result<(StatusCode, HeaderMap, bytes::Bytes), BoxError> {
+    let response = serve_static(config, &Method::GET, path, "/static", headers).await;
+    let (parts, body) = response.into_parts();
+    Ok((parts.status, parts.headers, body.collect().await?.to_bytes()))
+}
+
+#[test]
+fn a_directory_root_validates() -> Result<(), BoxError> {
+    let (_dir, root) = site()?;
+    assert!(config(&root).validate("serve_static").is_ok());
+    Ok(())
+}
+
+#[test]
+fn missing_root_is_rejected() -> Result<(), BoxError> {
+    let (dir, _root) = site()?;
+    let err = error_message(config(&dir.path().join("missing")).validate("serve_static"));
+    assert!(err.contains("serve_static.root '"), "got: {err}");
+    Ok(())
+}
+
+#[test]
+fn file_root_is_rejected() -> Result<(), BoxError> {
+    let (_dir, root) = site()?;
+    let err = error_message(config(&root.join("index.html")).validate("serve_static"));
+    assert!(err.contains("is not a directory"), "got: {err}");
+    Ok(())
+}
+
+#[test]
+fn index_with_a_path_is_rejected() -> Result<(), BoxError> {
+    let (_dir, root) = site()?;
+    let err = error_message(
+        ServeStaticConfig { index: "../index.html".to_string(), ..config(&root) }
+            .validate("serve_static"),
+    );
+    assert!(
+        err.contains("serve_static.index '../index.html' must be a file name"),
+        "got: {err}"
+    );
+    Ok(())
+}
+
+#[test]
+fn invalid_cache_control_is_rejected() -> Result<(), BoxError> {
+    let (_dir, root) = site()?;
+    let err = error_message(
+        ServeStaticConfig { cache_control: Some("a\nb".to_string()), ..config(&root) }
+            .validate("serve_static"),
+    );
+    assert!(err.contains("serve_static.cache_control"), "got: {err}");
+    assert!(err.contains("is not a valid header value"), "got: {err}");
+    Ok(())
+}
+
+#[test]
+fn paths_cannot_leave_the_root() {
+    assert_eq!(relative_path("/css/./app.css", false), Some(PathBuf::from("css/app.css")));
+    assert_eq!(relative_path("/", false), Some(PathBuf::new()));
+    assert_eq!(relative_path("/css/../../secret.txt", false), None);
+    assert_eq!(relative_path("/..\\secret.txt", false), None);
+    assert_eq!(relative_path("/a\0b", false), None);
+    assert_eq!(relative_path("/.env", false), None);
+    assert_eq!(relative_path("/.well-known/x", true), Some(PathBuf::from(".well-known/x")));
+
+    assert_eq!(content_type(Path::new("css/app.CSS")), "text/css; charset=utf-8");
+    assert_eq!(content_type(Path::new("logo.svg")), "image/svg+xml");
+    assert_eq!(content_type(Path::new("archive")), "application/octet-stream");
+}
+
+#[test]
+fn ranges() {
+    assert_eq!(parse_range("bytes=0-3", 10), ByteRange::Partial(0, 3));
+    assert_eq!(parse_range("bytes=4-", 10), ByteRange::Partial(4, 9));
+    assert_eq!(parse_range("bytes=-3", 10), ByteRange::Partial(7, 9));
+    assert_eq!(parse_range("bytes=-30", 10), ByteRange::Partial(0, 9));
+    assert_eq!(parse_range("bytes=5-100", 10), ByteRange::Partial(5, 9));
+    assert_eq!(parse_range("bytes=10-", 10), ByteRange::Unsatisfiable);
+    assert_eq!(parse_range("bytes=-0", 10), ByteRange::Unsatisfiable);
+    assert_eq!(parse_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+    assert_eq!(parse_range("bytes=0-1,4-5", 10), ByteRange::Full);
+    assert_eq!(parse_range("bytes=5-2", 10), ByteRange::Full);
+    assert_eq!(parse_range("items=0-1", 10), ByteRange::Full);
+}
+
+#[tokio::test]
+async fn serves_files_and_index() -> Result<(), BoxError> {
+    let (_dir, root) = site()?;
+    let config = config(&root);
+    let none = HeaderMap::new();
+
+    let (status, headers, body) = get(&config, "/static/css/app.css", &none).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "body{color:#333}");
+    assert_eq!(
+        headers.get(CONTENT_TYPE),
+        Some(&HeaderValue::from_static("text/css; charset=utf-8"))
+    );
+    assert_eq!(headers.get(CONTENT_LENGTH), Some(&HeaderValue::from(16u64)));
+    assert_eq!(headers.get(ACCEPT_RANGES), Some(&HeaderValue::from_static("bytes")));
+    assert_eq!(
+        headers.get(CACHE_CONTROL),
+        Some(&HeaderValue::from_static("public, max-age=60"))
+    );
+    assert!(headers.contains_key(ETAG));
+
+    let (status, headers, body) = get(&config, "/static/", &none).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "<h1>home</h1>");
+    assert_eq!(
+        headers.get(CONTENT_TYPE),
+        Some(&HeaderValue::from_static("text/html; charset=utf-8"))
+    );
+
+    for missing in ["/static/nope.css", "/static/.env", "/static/%2e%2e/secret.txt", "/static/%zz"]
+    {
+        let (status, _, _) = get(&config, missing, &none).await?;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{missing}");
+    }
+    // Escapes that do not decode to UTF-8.
+    let (status, _, _) = get(&config, "/static/%ff", &none).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let post = serve_static(&config, &Method::POST, "/static/", "/static", &none).await;
+    assert_eq!(post.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(post.headers().get(ALLOW), Some(&HeaderValue::from_static("GET, HEAD")));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinks_out_of_the_root_are_not_followed() -> Result<(), BoxError> {
+    let (dir, root) = site()?;
+    std::os::unix::fs::symlink(dir.path().join("secret.txt"), root.join("leak.txt"))?;
+    let (status, _, _) = get(&config(&root), "/static/leak.txt", &HeaderMap::new()).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn conditional_and_range_requests() -> Result<(), BoxError> {
+    let (_dir, root) = site()?;
+    let config = config(&root);
+    let (_, headers, _) = get(&config, "/static/css/app.css", &HeaderMap::new()).await?;
+    let etag = headers.get(ETAG).cloned().ok_or("no etag")?;
+
+    let mut revalidate = HeaderMap::new();
+    revalidate.insert(IF_NONE_MATCH, etag.clone());
+    let (status, headers, body) = get(&config, "/static/css/app.css", &revalidate).await?;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert!(body.is_empty());
+    assert_eq!(headers.get(ETAG), Some(&etag));
+
+    let ranged = request_headers(&[(RANGE, "bytes=5-9")]);
+    let (status, headers, body) = get(&config, "/static/css/app.css", &ranged).await?;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body, "color");
+    assert_eq!(headers.get(CONTENT_RANGE), Some(&HeaderValue::from_static("bytes 5-9/16")));
+
+    // A stale If-Range gets the whole file.
+    let stale = request_headers(&[(RANGE, "bytes=5-9"), (IF_RANGE, "\"0-0\"")]);
+    let (status, _, body) = get(&config, "/static/css/app.css", &stale).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "body{color:#333}");
+
+    let past_end = request_headers(&[(RANGE, "bytes=100-")]);
+    let (status, headers, _) = get(&config, "/static/css/app.css", &past_end).await?;
+    assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(headers.get(CONTENT_RANGE), Some(&HeaderValue::from_static("bytes */16")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn static_routes_need_no_backend_and_pass_the_classifier() -> Result<(), BoxError> {
+    let (_dir, root) = site()?;
+    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], free_port()?));
+    let route = Route { serve_static: Some(config(&root)), ..Route::new("/static", "") };
+    let proxy = ProxyBuilder::new()
+        .listen(addr)
+        .route(route)
+        .on_fingerprint(|set| {
+            if set.path.ends_with(".css") {
+                Verdict::Deny(StatusCode::FORBIDDEN)
+            } else {
+                Verdict::Allow
+            }
+        })
+        .start()
+        .await?;
+
+    assert_eq!(http_get(addr, "/static/").await?.0, 200);
+    assert_eq!(http_get(addr, "/static/css/app.css").await?.0, 403);
+
+    proxy.stop().await?;
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::io::Write;
+
+use http::StatusCode;
+use http_body_util::BodyExt;
+use huginn_proxy_lib::config::SyntheticResponseConfig;
+use huginn_proxy_lib::proxy::synthetic_response::{synthetic_route_response, SyntheticSwitches};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[tokio::test]
+async fn inline_body_is_served_with_status_and_headers() -> TestResult {
+    let config: SyntheticResponseConfig = toml::from_str(
+        r#"
+body = "down for maintenance"
+headers = [{ name = "Retry-After", value = "120" }, { name = "Cache-Control", value = "no-store" }]
+"#,
+    )?;
+    config.validate()?;
+    let response = synthetic_route_response(&config).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()["content-type"], "text/plain; charset=utf-8");
+    assert_eq!(response.headers()["retry-after"], "120");
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let body = response.into_body().collect().await?.to_bytes();
+    assert_eq!(body.as_ref(), b"down for maintenance");
+    Ok(())
+}
+
+#[tokio::test]
+async fn body_file_is_read_for_each_response() -> TestResult {
+    let mut page = tempfile::Builder::new().suffix(".html").tempfile()?;
+    page.write_all(b"<h1>Back soon</h1>")?;
+    let path = page.path().to_string_lossy().into_owned();
+    let config: SyntheticResponseConfig =
+        toml::from_str(&format!("status = 200\nbody_file = {path:?}"))?;
+    config.validate()?;
+
+    let response = synthetic_route_response(&config).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/html; charset=utf-8");
+    let body = response.into_body().collect().await?.to_bytes();
+    assert_eq!(body.as_ref(), b"<h1>Back soon</h1>");
+    Ok(())
+}
+
+#[test]
+fn invalid_synthetic_configs_are_rejected() -> TestResult {
+    for bad in [
+        "status = 99",
+        "status = 101",
+        "body = \"a\"\nbody_file = \"/etc/hostname\"",
+        "body_file = \"/nonexistent/huginn/maintenance.html\"",
+        "headers = [{ name = \"Bad Header\", value = \"x\" }]",
+        "content_type = \"text/html\\n\"",
+    ] {
+        let config: SyntheticResponseConfig = toml::from_str(bad)?;
+        assert!(config.validate().is_err(), "{bad} should be rejected");
+    }
+    assert!(toml::from_str::<SyntheticResponseConfig>("body_path = \"x\"").is_err());
+    Ok(())
+}
+
+#[test]
+fn runtime_switch_overrides_the_configured_state() -> TestResult {
+    let config: SyntheticResponseConfig = toml::from_str("enabled = false")?;
+    let switches = SyntheticSwitches::new();
+    assert!(!switches.is_active("example.com", "/", &config));
+
+    switches.set("example.com", "/", Some(true));
+    assert!(switches.is_active("example.com", "/", &config));
+    assert!(!switches.is_active("other.com", "/", &config));
+
+    switches.set("example.com", "/", None);
+    assert_eq!(switches.get("example.com", "/"), None);
+    assert!(!switches.is_active("example.com", "/", &config));
+    Ok(())
+}
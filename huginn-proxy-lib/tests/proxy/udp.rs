@@ -0,0 +1,87 @@
+//! `[[listen.udp]]` forwarding: replies reach the right client, and sessions are capped.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use huginn_proxy_lib::config::UdpListenerConfig;
+use huginn_proxy_lib::proxy::udp::UdpForwarder;
+use huginn_proxy_lib::Metrics;
+use tokio::net::UdpSocket;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// A backend answering every datagram with `<source port>:<payload>`.
+async fn echo_backend() -> Result<SocketAddr, std::io::Error> {
+    let socket = UdpSocket::bind("127.0.0.1:0").await?;
+    let addr = socket.local_addr()?;
+    tokio::spawn(async move {
+        let mut buf = [0u8; 1500];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let mut reply = format!("{}:", from.port()).into_bytes();
+            reply.extend_from_slice(buf.get(..len).unwrap_or_default());
+            let _ = socket.send_to(&reply, from).await;
+        }
+    });
+    Ok(addr)
+}
+
+async fn forwarder(backend: SocketAddr, max_sessions: usize) -> Result<SocketAddr, std::io::Error> {
+    let config = UdpListenerConfig {
+        addr: "127.0.0.1:0".parse().map_err(std::io::Error::other)?,
+        backend: backend.to_string(),
+        idle_timeout_secs: 60,
+        max_sessions,
+    };
+    let forwarder = UdpForwarder::bind(&config, Metrics::new_noop()).await?;
+    let addr = forwarder.local_addr()?;
+    tokio::spawn(forwarder.run());
+    Ok(addr)
+}
+
+async fn exchange(
+    client: &UdpSocket,
+    payload: &[u8],
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    client.send(payload).await?;
+    let mut buf = [0u8; 1500];
+    let len = tokio::time::timeout(Duration::from_secs(5), client.recv(&mut buf)).await??;
+    Ok(String::from_utf8(buf.get(..len).unwrap_or_default().to_vec())?)
+}
+
+#[tokio::test]
+async fn each_client_gets_its_own_session() -> TestResult {
+    let proxy = forwarder(echo_backend().await?, 16).await?;
+    let a = UdpSocket::bind("127.0.0.1:0").await?;
+    a.connect(proxy).await?;
+    let b = UdpSocket::bind("127.0.0.1:0").await?;
+    b.connect(proxy).await?;
+
+    let first = exchange(&a, b"one").await?;
+    let (port_a, payload) = first.split_once(':').ok_or("malformed reply")?;
+    assert_eq!(payload, "one");
+    // Later datagrams of a client reuse its upstream socket.
+    assert_eq!(exchange(&a, b"two").await?, format!("{port_a}:two"));
+
+    let other = exchange(&b, b"three").await?;
+    let (port_b, payload) = other.split_once(':').ok_or("malformed reply")?;
+    assert_eq!(payload, "three");
+    assert_ne!(port_a, port_b);
+    Ok(())
+}
+
+#[tokio::test]
+async fn new_clients_are_dropped_at_the_session_limit() -> TestResult {
+    let proxy = forwarder(echo_backend().await?, 1).await?;
+    let a = UdpSocket::bind("127.0.0.1:0").await?;
+    a.connect(proxy).await?;
+    let b = UdpSocket::bind("127.0.0.1:0").await?;
+    b.connect(proxy).await?;
+
+    assert!(exchange(&a, b"kept").await?.ends_with(":kept"));
+    b.send(b"dropped").await?;
+    let mut buf = [0u8; 1500];
+    let reply = tokio::time::timeout(Duration::from_millis(300), b.recv(&mut buf)).await;
+    assert!(reply.is_err(), "a second session was opened");
+    assert!(exchange(&a, b"still").await?.ends_with(":still"));
+    Ok(())
+}
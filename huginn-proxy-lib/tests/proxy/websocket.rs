@@ -0,0 +1,58 @@
+use http::header::{CONNECTION, UPGRADE};
+use http::{HeaderMap, HeaderValue};
+use huginn_proxy_lib::proxy::websocket::{is_websocket_upgrade, splice, TunnelEnd};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::Duration;
+
+fn headers(connection: &'static str, upgrade: &'static str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONNECTION, HeaderValue::from_static(connection));
+    headers.insert(UPGRADE, HeaderValue::from_static(upgrade));
+    headers
+}
+
+#[test]
+fn detects_websocket_handshake() {
+    assert!(is_websocket_upgrade(&headers("Upgrade", "websocket")));
+    assert!(is_websocket_upgrade(&headers("keep-alive, upgrade", "WebSocket")));
+}
+
+#[test]
+fn ignores_other_upgrades_and_plain_requests() {
+    assert!(!is_websocket_upgrade(&headers("upgrade", "h2c")));
+    assert!(!is_websocket_upgrade(&headers("keep-alive", "websocket")));
+    assert!(!is_websocket_upgrade(&HeaderMap::new()));
+}
+
+#[tokio::test]
+async fn splice_relays_both_directions_until_close(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (mut client, proxy_client_side) = tokio::io::duplex(64);
+    let (proxy_upstream_side, mut upstream) = tokio::io::duplex(64);
+    let tunnel =
+        tokio::spawn(splice(proxy_client_side, proxy_upstream_side, Duration::from_secs(5)));
+
+    client.write_all(b"ping").await?;
+    let mut buf = [0u8; 4];
+    upstream.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"ping");
+
+    upstream.write_all(b"pong").await?;
+    client.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"pong");
+
+    drop(client);
+    assert_eq!(tunnel.await??, TunnelEnd::Closed);
+    // The upstream side sees EOF once the tunnel shuts its write half down.
+    assert_eq!(upstream.read(&mut buf).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn splice_closes_idle_tunnel() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (_client, proxy_client_side) = tokio::io::duplex(64);
+    let (proxy_upstream_side, _upstream) = tokio::io::duplex(64);
+    let end = splice(proxy_client_side, proxy_upstream_side, Duration::from_millis(50)).await?;
+    assert_eq!(end, TunnelEnd::IdleTimeout);
+    Ok(())
+}
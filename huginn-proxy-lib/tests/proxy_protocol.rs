@@ -135,9 +135,13 @@ async fn spawn_proxy(
             dynamic_cfg,
             Metrics::new_noop(),
             None,
+            None,
+            None,
             WatchOptions::default(),
             shutdown_tx,
             huginn_proxy_lib::Readiness::new(),
+            Arc::new(huginn_proxy_lib::HealthRegistry::new()),
+            huginn_proxy_lib::RuntimeHandles::default(),
         )
         .await;
     });
@@ -349,6 +353,20 @@ async fn spawn_proxy_tls(
             address: backend.to_string(),
             http_version: None,
             health_check: None,
+            circuit_breaker: None,
+            tls: None,
+            pool: None,
+            max_in_flight: None,
+            queue: None,
+            discovery: None,
+            drain: false,
+            drain_timeout_secs: None,
+            slow_start_secs: None,
+            echo: false,
+            tcp: None,
+            proxy: None,
+            bandwidth: None,
+            tenant: None,
         }],
         domains: vec![Domain {
             host: Some("localhost".to_string()),
@@ -357,43 +375,68 @@ async fn spawn_proxy_tls(
             headers: None,
             security: None,
             fingerprinting: None,
+            ja4_variants: None,
+            fingerprint_format: None,
+            redirect: None,
             routes: vec![Route {
                 prefix: "/".to_string(),
                 backend: backend.to_string(),
-                fingerprinting: None,
-                force_new_connection: false,
-                replace_path: None,
-                security: None,
-                headers: None,
+                ..Default::default()
             }],
+            tenant: None,
         }],
         tls: Some(TlsConfig {
             alpn: vec!["http/1.1".to_string()],
             options: Default::default(),
             client_auth: Default::default(),
+            client_cert: Default::default(),
             session_resumption: Default::default(),
+            ocsp: Default::default(),
         }),
         fingerprint: FingerprintConfig {
             tls_enabled: false,
             http_enabled: false,
             tcp_enabled: false,
+            tcp_capture: Default::default(),
             max_capture: 0,
+            tls_info_headers: false,
+            headers: Default::default(),
+            whoami: Default::default(),
+            signing: Default::default(),
         },
         logging: LoggingConfig { level: "error".to_string(), show_target: false },
         timeout: TimeoutConfig {
             upstream_connect_ms: Some(5000),
             proxy_idle_ms: 30_000,
             shutdown_secs: 3,
+            drain_delay_secs: 0,
             tls_handshake_secs: 10,
             connection_handling_secs: 60,
             keep_alive: KeepAliveConfig::default(),
         },
         security: SecurityConfig { trusted_proxies, ..Default::default() },
-        telemetry: TelemetryConfig { metrics_port: None, otel_log_level: "error".to_string() },
+        telemetry: TelemetryConfig {
+            metrics_port: None,
+            otel_log_level: "error".to_string(),
+            ..Default::default()
+        },
         reload: huginn_proxy_lib::config::ReloadConfig::default(),
         headers: None,
         preserve_host: false,
+        default_host: None,
         backend_pool: Default::default(),
+        compression: None,
+        redirect: None,
+        cache: Default::default(),
+        access_log: Default::default(),
+        load_shedding: Default::default(),
+        http2: Default::default(),
+        request_id: Default::default(),
+        event_stream: Default::default(),
+        client_tracking: Default::default(),
+        handshake_capture: Default::default(),
+        tenants: vec![],
+        include: vec![],
     };
 
     let huginn_proxy_lib::config::ConfigParts { static_cfg, dynamic_cfg } = config.into_parts();
@@ -407,9 +450,13 @@ async fn spawn_proxy_tls(
             dynamic_cfg,
             Metrics::new_noop(),
             None,
+            None,
+            None,
             WatchOptions::default(),
             shutdown_tx,
             huginn_proxy_lib::Readiness::new(),
+            Arc::new(huginn_proxy_lib::HealthRegistry::new()),
+            huginn_proxy_lib::RuntimeHandles::default(),
         )
         .await;
     });
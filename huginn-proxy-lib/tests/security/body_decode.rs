@@ -0,0 +1,91 @@
+use std::io::Write;
+
+use http::header::CONTENT_ENCODING;
+use http::{HeaderMap, HeaderValue};
+use huginn_proxy_lib::security::{decode_for_inspection, DecodeError, DecodeLimits};
+
+fn headers(encoding: &'static str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    headers
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap_or_default();
+    encoder.finish().unwrap_or_default()
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap_or_default();
+    encoder.finish().unwrap_or_default()
+}
+
+fn raw_deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap_or_default();
+    encoder.finish().unwrap_or_default()
+}
+
+#[test]
+fn test_uncompressed_body_is_borrowed() {
+    let body = b"user=admin' OR 1=1";
+    let decoded = decode_for_inspection(&HeaderMap::new(), body, &DecodeLimits::default());
+    assert!(matches!(decoded, Ok(std::borrow::Cow::Borrowed(b)) if b == body));
+
+    let decoded = decode_for_inspection(&headers("identity"), body, &DecodeLimits::default());
+    assert_eq!(decoded.as_deref().ok(), Some(&body[..]));
+}
+
+#[test]
+fn test_gzip_and_deflate_are_decoded() {
+    let plain = b"{\"query\":\"<script>alert(1)</script>\"}";
+    let limits = DecodeLimits::default();
+
+    let body = gzip(plain);
+    let decoded = decode_for_inspection(&headers("gzip"), &body, &limits);
+    assert_eq!(decoded.as_deref().ok(), Some(&plain[..]));
+
+    let body = zlib(plain);
+    let decoded = decode_for_inspection(&headers("deflate"), &body, &limits);
+    assert_eq!(decoded.as_deref().ok(), Some(&plain[..]));
+
+    let body = raw_deflate(plain);
+    let decoded = decode_for_inspection(&headers("deflate"), &body, &limits);
+    assert_eq!(decoded.as_deref().ok(), Some(&plain[..]));
+}
+
+#[test]
+fn test_stacked_codings_are_undone_in_reverse() {
+    let plain = b"stacked payload";
+    let body = gzip(&zlib(plain));
+    let decoded = decode_for_inspection(&headers("deflate, gzip"), &body, &DecodeLimits::default());
+    assert_eq!(decoded.as_deref().ok(), Some(&plain[..]));
+}
+
+#[test]
+fn test_decompression_bomb_is_rejected() {
+    let plain = vec![b'a'; 4 * 1024 * 1024];
+    let body = gzip(&plain);
+
+    let limits = DecodeLimits { max_decoded_bytes: 1024 * 1024, max_ratio: u64::MAX };
+    let decoded = decode_for_inspection(&headers("gzip"), &body, &limits);
+    assert_eq!(decoded.err(), Some(DecodeError::TooLarge(1024 * 1024)));
+
+    let limits = DecodeLimits { max_decoded_bytes: u64::MAX, max_ratio: 100 };
+    let decoded = decode_for_inspection(&headers("gzip"), &body, &limits);
+    assert_eq!(decoded.err(), Some(DecodeError::RatioExceeded(100)));
+}
+
+#[test]
+fn test_unsupported_and_malformed_bodies() {
+    let limits = DecodeLimits::default();
+
+    let decoded = decode_for_inspection(&headers("br"), b"\x0b\x02\x80", &limits);
+    assert_eq!(decoded.err(), Some(DecodeError::Unsupported("br".to_string())));
+
+    let decoded = decode_for_inspection(&headers("gzip"), b"not gzip at all", &limits);
+    assert!(matches!(decoded, Err(DecodeError::Malformed { encoding: "gzip", .. })));
+}
@@ -0,0 +1,240 @@
+//! Crawler verification: config validation, the DNS wire format, and `BotVerifier` against a
+//! fake resolver on 127.0.0.1.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use huginn_proxy_lib::config::BotVerificationConfig;
+use huginn_proxy_lib::security::bot_verification::dns::{
+    encode_query, parse_response, reverse_name, Record, SrvRecord, TYPE_A, TYPE_PTR, TYPE_SRV,
+};
+use huginn_proxy_lib::security::{BotVerdict, BotVerifier};
+use tokio::net::UdpSocket;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const GOOGLEBOT: &str = r#"
+enabled = true
+[[crawlers]]
+name = "googlebot"
+user_agent = "(?i)googlebot"
+domains = ["googlebot.com", "google.com"]
+"#;
+
+/// Answers of the fake resolver, keyed by question name and type.
+type Zone = HashMap<(String, u16), Vec<Vec<u8>>>;
+
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    for label in name.split('.') {
+        out.push(u8::try_from(label.len()).unwrap_or_default());
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    out
+}
+
+/// Answer `query` from `zone`: copies the question and points every answer's owner at it.
+fn answer(zone: &Zone, query: &[u8]) -> Option<Vec<u8>> {
+    let mut labels = Vec::new();
+    let mut rest = query.get(12..)?;
+    loop {
+        let (&len, tail) = rest.split_first()?;
+        rest = tail;
+        if len == 0 {
+            break;
+        }
+        let (label, tail) = rest.split_at_checked(usize::from(len))?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        rest = tail;
+    }
+    let name = labels.join(".");
+    let type_and_class = rest.get(..4)?;
+    let qtype = u16::from_be_bytes([*type_and_class.first()?, *type_and_class.get(1)?]);
+    let answers = zone
+        .get(&(name.clone(), qtype))
+        .cloned()
+        .unwrap_or_default();
+
+    let mut msg = query.get(..2)?.to_vec();
+    msg.extend_from_slice(&[0x81, 0x80, 0, 1]);
+    msg.extend_from_slice(&u16::try_from(answers.len()).ok()?.to_be_bytes());
+    msg.extend_from_slice(&[0, 0, 0, 0]);
+    msg.extend_from_slice(&encode_name(&name));
+    msg.extend_from_slice(type_and_class);
+    for rdata in answers {
+        msg.extend_from_slice(&[0xc0, 0x0c]);
+        msg.extend_from_slice(&qtype.to_be_bytes());
+        msg.extend_from_slice(&[0, 1, 0, 0, 0x0e, 0x10]);
+        msg.extend_from_slice(&u16::try_from(rdata.len()).ok()?.to_be_bytes());
+        msg.extend_from_slice(&rdata);
+    }
+    Some(msg)
+}
+
+/// Serve `zone` over UDP; returns the resolver address and a count of queries received.
+async fn fake_resolver(zone: Zone) -> Result<(SocketAddr, Arc<AtomicUsize>), std::io::Error> {
+    let socket = UdpSocket::bind("127.0.0.1:0").await?;
+    let addr = socket.local_addr()?;
+    let queries = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&queries);
+    tokio::spawn(async move {
+        let mut buf = [0u8; 512];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if let Some(reply) = buf.get(..len).and_then(|q| answer(&zone, q)) {
+                let _ = socket.send_to(&reply, from).await;
+            }
+        }
+    });
+    Ok((addr, queries))
+}
+
+fn ptr(zone: &mut Zone, ip: &str, host: &str) -> Result<(), std::net::AddrParseError> {
+    let ip: IpAddr = ip.parse()?;
+    zone.entry((reverse_name(ip), TYPE_PTR))
+        .or_default()
+        .push(encode_name(host));
+    Ok(())
+}
+
+fn a(zone: &mut Zone, host: &str, ip: [u8; 4]) {
+    zone.entry((host.to_string(), TYPE_A))
+        .or_default()
+        .push(ip.to_vec());
+}
+
+#[tokio::test]
+async fn crawlers_are_verified_by_reverse_and_forward_dns() -> TestResult {
+    let mut zone = Zone::new();
+    ptr(&mut zone, "66.249.66.1", "crawl-66-249-66-1.googlebot.com")?;
+    a(&mut zone, "crawl-66-249-66-1.googlebot.com", [66, 249, 66, 1]);
+    // A PTR name merely containing the crawler's domain.
+    ptr(&mut zone, "203.0.113.7", "crawl.googlebot.com.evil.example")?;
+    a(&mut zone, "crawl.googlebot.com.evil.example", [203, 0, 113, 7]);
+    // A PTR record the attacker controls, whose name resolves elsewhere.
+    ptr(&mut zone, "203.0.113.8", "rate-limited-proxy.google.com")?;
+    a(&mut zone, "rate-limited-proxy.google.com", [198, 51, 100, 1]);
+    let (resolver, queries) = fake_resolver(zone).await?;
+
+    let mut config: BotVerificationConfig = toml::from_str(GOOGLEBOT)?;
+    config.resolver = Some(resolver.to_string());
+    config.validate()?;
+    let crawler = config
+        .crawler_for("Mozilla/5.0 (compatible; Googlebot/2.1)")
+        .ok_or("googlebot not matched")?;
+    let verifier = BotVerifier::new();
+
+    let verified: IpAddr = "66.249.66.1".parse()?;
+    assert_eq!(verifier.verify(&config, crawler, verified).await, BotVerdict::Verified);
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+    // Cached: no further lookups, also for the IPv4-mapped form of the address.
+    let mapped: IpAddr = "::ffff:66.249.66.1".parse()?;
+    assert_eq!(verifier.verify(&config, crawler, mapped).await, BotVerdict::Verified);
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+    for ip in ["203.0.113.7", "203.0.113.8", "203.0.113.9"] {
+        let ip: IpAddr = ip.parse()?;
+        assert_eq!(verifier.verify(&config, crawler, ip).await, BotVerdict::Spoofed, "{ip}");
+    }
+    assert_eq!(verifier.cached_len(), 4);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unanswered_lookups_are_unknown_and_not_cached() -> TestResult {
+    let silent = UdpSocket::bind("127.0.0.1:0").await?;
+    let mut config: BotVerificationConfig = toml::from_str(GOOGLEBOT)?;
+    config.resolver = Some(silent.local_addr()?.to_string());
+    config.timeout_ms = 50;
+    let crawler = config
+        .crawler_for("Googlebot")
+        .ok_or("googlebot not matched")?;
+
+    let verifier = BotVerifier::new();
+    let ip: IpAddr = "66.249.66.1".parse()?;
+    assert_eq!(verifier.verify(&config, crawler, ip).await, BotVerdict::Unknown);
+    assert_eq!(verifier.cached_len(), 0);
+    Ok(())
+}
+
+#[test]
+fn dns_messages_round_trip() -> TestResult {
+    let v6: IpAddr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x1));
+    assert_eq!(
+        reverse_name(v6),
+        "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+    );
+    assert_eq!(reverse_name("192.0.2.10".parse()?), "10.2.0.192.in-addr.arpa");
+
+    let mut zone = Zone::new();
+    ptr(&mut zone, "192.0.2.10", "Crawl.Example.COM")?;
+    let query = encode_query(0x1234, "10.2.0.192.in-addr.arpa", TYPE_PTR)?;
+    let reply = answer(&zone, &query).ok_or("no answer")?;
+    assert_eq!(
+        parse_response(0x1234, TYPE_PTR, &reply)?,
+        vec![Record::Name("crawl.example.com".to_string())]
+    );
+    // A reply to another query, or a truncated one, is rejected.
+    assert!(parse_response(0x4321, TYPE_PTR, &reply).is_err());
+    let truncated = reply
+        .get(..reply.len().saturating_sub(3))
+        .ok_or("short reply")?;
+    assert!(parse_response(0x1234, TYPE_PTR, truncated).is_err());
+
+    let mut srv = vec![0, 10, 0, 5, 0x1f, 0x90];
+    srv.extend_from_slice(&encode_name("App-1.Example"));
+    zone.insert(("_http._tcp.billing".to_string(), TYPE_SRV), vec![srv]);
+    let query = encode_query(0x2222, "_http._tcp.billing", TYPE_SRV)?;
+    let reply = answer(&zone, &query).ok_or("no answer")?;
+    assert_eq!(
+        parse_response(0x2222, TYPE_SRV, &reply)?,
+        vec![Record::Srv(SrvRecord {
+            priority: 10,
+            weight: 5,
+            port: 8080,
+            target: "app-1.example".to_string(),
+        })]
+    );
+
+    assert!(encode_query(1, "", TYPE_A).is_err());
+    assert!(encode_query(1, "a..b", TYPE_A).is_err());
+    assert!(encode_query(1, &"x".repeat(64), TYPE_A).is_err());
+    Ok(())
+}
+
+#[test]
+fn crawler_domains_and_invalid_configs() -> TestResult {
+    let config: BotVerificationConfig = toml::from_str(GOOGLEBOT)?;
+    let crawler = config.crawlers.first().ok_or("crawler missing")?;
+    assert!(crawler.owns_host("crawl-1.googlebot.com"));
+    assert!(crawler.owns_host("GOOGLE.com"));
+    assert!(!crawler.owns_host("notgooglebot.com"));
+    assert!(!crawler.owns_host("googlebot.com.evil.example"));
+    assert!(config.crawler_for("curl/8.0").is_none());
+
+    let disabled = BotVerificationConfig::default();
+    disabled.validate()?;
+    assert_eq!(disabled.header, "x-huginn-bot-verified");
+
+    for (good, bad) in [
+        ("enabled = true", "enabled = true\nstatus = 503"),
+        ("enabled = true", "enabled = true\nresolver = \"dns.example\""),
+        ("enabled = true", "enabled = true\ntimeout_ms = 0"),
+        ("enabled = true", "enabled = true\nheader = \"bad header\""),
+        ("\"google.com\"", "\".google.com\""),
+        ("domains = [\"googlebot.com\", \"google.com\"]", "domains = []"),
+    ] {
+        let config: BotVerificationConfig = toml::from_str(&GOOGLEBOT.replace(good, bad))?;
+        assert!(config.validate().is_err(), "{bad} should be rejected");
+    }
+    let mut duplicated: BotVerificationConfig = toml::from_str(GOOGLEBOT)?;
+    duplicated.crawlers.push(crawler.clone());
+    assert!(duplicated.validate().is_err());
+    let mut empty: BotVerificationConfig = toml::from_str(GOOGLEBOT)?;
+    empty.crawlers.clear();
+    assert!(empty.validate().is_err());
+    Ok(())
+}
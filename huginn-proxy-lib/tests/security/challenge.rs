@@ -0,0 +1,197 @@
+//! `[security.challenge]`: cookie tokens, config validation, and the challenge page handing out a
+//! challenge whose solved token lets the next request through.
+
+use std::net::IpAddr;
+
+use http::header::{CACHE_CONTROL, COOKIE};
+use http::{HeaderMap, HeaderValue, StatusCode};
+use http_body_util::BodyExt;
+use huginn_proxy_lib::config::{ChallengeConfig, FingerprintList, Secret};
+use huginn_proxy_lib::proxy::handler::check_challenge;
+use huginn_proxy_lib::security::challenge::solve;
+use huginn_proxy_lib::security::{ChallengeTokens, ObservedFingerprints};
+use huginn_proxy_lib::telemetry::Metrics;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const JA4: &str = "t13d1516h2_8daaf6152771_b186095e22b6";
+const TCP: &str = "4:64+0:0:1460:mss*44,7:mss,sok,ts,nop,ws:df,id+:0";
+const SECRET: &str = "0123456789abcdef0123456789abcdef";
+const DIFFICULTY: u8 = 8;
+
+fn config() -> ChallengeConfig {
+    ChallengeConfig {
+        suspect: FingerprintList { tcp: vec!["4:64+0:*".to_string()], ..Default::default() },
+        secret: Secret::new(SECRET.to_string()),
+        difficulty: DIFFICULTY,
+        ..ChallengeConfig::default()
+    }
+}
+
+#[test]
+fn tokens_are_bound_to_the_ja4_and_ip_and_expire() -> TestResult {
+    let ip: IpAddr = "203.0.113.7".parse()?;
+    let tokens = ChallengeTokens::new(SECRET.as_bytes(), 60, DIFFICULTY);
+    let challenge = tokens.issue(JA4, ip, 1_000);
+    assert!(challenge.starts_with("1060."), "{challenge}");
+    let token = solve(&challenge, DIFFICULTY);
+
+    assert!(tokens.verify(&token, JA4, ip, 1_000));
+    assert!(tokens.verify(&token, JA4, ip, 1_059));
+    assert!(!tokens.verify(&token, JA4, ip, 1_060));
+    assert!(!tokens.verify(&token, "t13d1516h2_8daaf6152771_e5627efa2ab1", ip, 1_000));
+    assert!(!tokens.verify(&token, JA4, "203.0.113.8".parse()?, 1_000));
+    let other_secret = ChallengeTokens::new(b"another secret of 16+ bytes", 60, DIFFICULTY);
+    assert!(!other_secret.verify(&token, JA4, ip, 1_000));
+
+    // A forged expiry breaks the MAC; an expiry past the TTL is refused outright.
+    let forged = solve(&challenge.replacen("1060", "1061", 1), DIFFICULTY);
+    assert!(!tokens.verify(&forged, JA4, ip, 1_000));
+    assert!(!ChallengeTokens::new(SECRET.as_bytes(), 30, DIFFICULTY).verify(&token, JA4, ip, 1_000));
+    for garbage in ["", "1060", "1060.", "1060.zz", "x.00", "1060.00.1"] {
+        assert!(!tokens.verify(garbage, JA4, ip, 1_000), "{garbage}");
+    }
+    Ok(())
+}
+
+#[test]
+fn tokens_need_the_proof_of_work() -> TestResult {
+    let ip: IpAddr = "203.0.113.7".parse()?;
+    let tokens = ChallengeTokens::new(SECRET.as_bytes(), 60, 16);
+    let challenge = tokens.issue(JA4, ip, 1_000);
+    assert!(tokens.verify(&solve(&challenge, 16), JA4, ip, 1_000));
+
+    // The unsolved challenge, a nonce that is not a counter, and a cheaper proof are refused.
+    assert!(!tokens.verify(&challenge, JA4, ip, 1_000));
+    assert!(!tokens.verify(&format!("{challenge}.x"), JA4, ip, 1_000));
+    // The first nonce with 8 zero bits comes before the first with 16.
+    let weak = solve(&challenge, 8);
+    assert_ne!(weak, solve(&challenge, 16));
+    assert!(!tokens.verify(&weak, JA4, ip, 1_000));
+    Ok(())
+}
+
+#[test]
+fn solve_finds_the_nonce_of_the_built_in_page() {
+    // The nonce the built-in page's script finds for this challenge at 16 bits.
+    assert_eq!(solve("1060.abcdef0123", 16), "1060.abcdef0123.43792");
+}
+
+#[test]
+fn validation_requires_a_secret_for_suspects() {
+    assert!(ChallengeConfig::default().validate().is_ok());
+    assert!(!ChallengeConfig::default().is_active());
+    assert!(config().validate().is_ok());
+
+    let short_secret = ChallengeConfig { secret: Secret::new("short".to_string()), ..config() };
+    assert!(short_secret.validate().is_err());
+    let bare_wildcard = ChallengeConfig {
+        suspect: FingerprintList { ja4: vec!["*".to_string()], ..Default::default() },
+        ..config()
+    };
+    assert!(bare_wildcard.validate().is_err());
+    let cookie = ChallengeConfig { cookie_name: "bad name".to_string(), ..config() };
+    assert!(cookie.validate().is_err());
+    let both = ChallengeConfig {
+        body: Some("<p>{{token}}</p>".to_string()),
+        body_file: Some("/etc/hostname".to_string()),
+        ..config()
+    };
+    assert!(both.validate().is_err());
+    assert!(ChallengeConfig { status: 103, ..config() }
+        .validate()
+        .is_err());
+    assert!(ChallengeConfig { ttl_secs: 0, ..config() }
+        .validate()
+        .is_err());
+    assert!(ChallengeConfig { difficulty: 25, ..config() }
+        .validate()
+        .is_err());
+}
+
+#[tokio::test]
+async fn suspects_get_a_page_whose_cookie_lets_them_through() -> TestResult {
+    let mut config = ChallengeConfig {
+        body: Some("{{cookie_name}}={{challenge}};{{difficulty}}".to_string()),
+        ..config()
+    };
+    config.prepare()?;
+    let metrics = Metrics::new_noop();
+    let peer: IpAddr = "203.0.113.7".parse()?;
+    let suspect = ObservedFingerprints { tcp: Some(TCP.to_string()), ..Default::default() };
+
+    let response = check_challenge(&HeaderMap::new(), &config, &suspect, None, &metrics, peer)
+        .await
+        .ok_or("suspect not challenged")?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response
+            .headers()
+            .get(CACHE_CONTROL)
+            .ok_or("no Cache-Control")?,
+        "no-store"
+    );
+    let page = String::from_utf8(response.into_body().collect().await?.to_bytes().to_vec())?;
+    let (name, rest) = page.split_once('=').ok_or("no cookie name")?;
+    assert_eq!(name, "huginn_challenge");
+    let (challenge, difficulty) = rest.split_once(';').ok_or("no difficulty")?;
+    assert_eq!(difficulty, "8");
+    let cookie = format!("{name}={}", solve(challenge, DIFFICULTY));
+
+    // The same client with the solved cookie is forwarded; the unsolved challenge, a tampered
+    // cookie and another client IP are challenged again.
+    let mut headers = HeaderMap::new();
+    headers.insert(COOKIE, HeaderValue::from_str(&format!("{name}={challenge}"))?);
+    assert!(check_challenge(&headers, &config, &suspect, None, &metrics, peer)
+        .await
+        .is_some());
+    let mut headers = HeaderMap::new();
+    headers.insert(COOKIE, HeaderValue::from_str(&format!("theme=dark; {cookie}"))?);
+    assert!(check_challenge(&headers, &config, &suspect, None, &metrics, peer)
+        .await
+        .is_none());
+    let other_ip: IpAddr = "203.0.113.8".parse()?;
+    assert!(check_challenge(&headers, &config, &suspect, None, &metrics, other_ip)
+        .await
+        .is_some());
+    headers.insert(COOKIE, HeaderValue::from_str(&format!("{cookie}00"))?);
+    assert!(check_challenge(&headers, &config, &suspect, None, &metrics, peer)
+        .await
+        .is_some());
+
+    // Other fingerprints are never challenged.
+    let other = ObservedFingerprints { ja4: vec![JA4.to_string()], ..Default::default() };
+    assert!(check_challenge(&HeaderMap::new(), &config, &other, None, &metrics, peer)
+        .await
+        .is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn body_file_is_read_when_the_config_is_prepared() -> TestResult {
+    let path = std::env::temp_dir().join(format!("huginn-challenge-{}.html", std::process::id()));
+    std::fs::write(&path, "<p>{{challenge}}</p>")?;
+    let mut config =
+        ChallengeConfig { body_file: Some(path.to_string_lossy().into_owned()), ..config() };
+    config.prepare()?;
+    // The page was read once: removing the file does not change what is served.
+    std::fs::remove_file(&path)?;
+
+    let suspect = ObservedFingerprints { tcp: Some(TCP.to_string()), ..Default::default() };
+    let response = check_challenge(
+        &HeaderMap::new(),
+        &config,
+        &suspect,
+        None,
+        &Metrics::new_noop(),
+        "203.0.113.7".parse()?,
+    )
+    .await
+    .ok_or("suspect not challenged")?;
+    let page = String::from_utf8(response.into_body().collect().await?.to_bytes().to_vec())?;
+    assert!(page.starts_with("<p>") && page.ends_with("</p>"), "{page}");
+
+    config.body_file = Some(path.to_string_lossy().into_owned());
+    assert!(config.prepare().is_err());
+    Ok(())
+}
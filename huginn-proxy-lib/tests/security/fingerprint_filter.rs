@@ -0,0 +1,85 @@
+use huginn_proxy_lib::config::{FingerprintFilterConfig, FingerprintList};
+use huginn_proxy_lib::security::{check_fingerprint_filter, ObservedFingerprints};
+
+const JA4: &str = "t13d1516h2_8daaf6152771_b186095e22b6";
+const AKAMAI: &str = "1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p";
+
+fn strings(entries: &[&str]) -> Vec<String> {
+    entries.iter().map(|s| s.to_string()).collect()
+}
+
+fn observed() -> ObservedFingerprints {
+    ObservedFingerprints { ja4: strings(&[JA4]), akamai: Some(AKAMAI.to_string()), tcp: None }
+}
+
+fn filter(allow: FingerprintList, deny: FingerprintList) -> FingerprintFilterConfig {
+    FingerprintFilterConfig { allow, deny, ..FingerprintFilterConfig::default() }
+}
+
+#[test]
+fn test_empty_filter_is_inactive() {
+    let config = FingerprintFilterConfig::default();
+    assert!(!config.is_active());
+    assert_eq!(config.status, 403);
+    assert_eq!(check_fingerprint_filter(&config, &observed()), None);
+}
+
+#[test]
+fn test_deny_exact_and_prefix_match() {
+    let exact = filter(
+        FingerprintList::default(),
+        FingerprintList { ja4: strings(&[JA4]), ..Default::default() },
+    );
+    let block = check_fingerprint_filter(&exact, &observed());
+    assert_eq!(block.map(|b| (b.kind, b.entry)), Some(("ja4", JA4)));
+
+    let prefix = filter(
+        FingerprintList::default(),
+        FingerprintList { akamai: strings(&["1:65536;*"]), ..Default::default() },
+    );
+    let block = check_fingerprint_filter(&prefix, &observed());
+    assert_eq!(block.map(|b| (b.kind, b.entry)), Some(("akamai", "1:65536;*")));
+}
+
+#[test]
+fn test_non_matching_and_unobserved_fingerprints_pass() {
+    let config = filter(
+        FingerprintList::default(),
+        FingerprintList {
+            ja4: strings(&["t12d*"]),
+            akamai: strings(&["2:0"]),
+            tcp: strings(&["*:64:0:*"]),
+        },
+    );
+    assert!(config.is_active());
+    assert_eq!(check_fingerprint_filter(&config, &observed()), None);
+    assert_eq!(check_fingerprint_filter(&config, &ObservedFingerprints::default()), None);
+}
+
+#[test]
+fn test_allow_entry_overrides_deny() {
+    let config = filter(
+        FingerprintList { akamai: strings(&[AKAMAI]), ..Default::default() },
+        FingerprintList { ja4: strings(&["t13d*"]), ..Default::default() },
+    );
+    assert_eq!(check_fingerprint_filter(&config, &observed()), None);
+
+    let other_client = ObservedFingerprints { akamai: None, ..observed() };
+    assert!(check_fingerprint_filter(&config, &other_client).is_some());
+}
+
+#[test]
+fn test_validate_status_and_entries() {
+    assert!(FingerprintFilterConfig::default().validate().is_ok());
+
+    let config = FingerprintFilterConfig { status: 500, ..Default::default() };
+    assert!(config.validate().is_err());
+
+    for entry in ["", "*"] {
+        let config = filter(
+            FingerprintList::default(),
+            FingerprintList { tcp: strings(&[entry]), ..Default::default() },
+        );
+        assert!(config.validate().is_err(), "{entry:?} should be rejected");
+    }
+}
@@ -1,10 +1,15 @@
-use http::Response;
-use huginn_proxy_lib::config::{CspConfig, CustomHeader, HstsConfig, SecurityHeaders};
+use std::collections::BTreeMap;
+
+use http::{HeaderValue, Response};
+use huginn_proxy_lib::config::{
+    CspConfig, CustomHeader, HstsConfig, SecurityHeaders, SecurityHeadersPreset,
+};
 use huginn_proxy_lib::security::apply_security_headers;
 
 #[test]
 fn test_apply_custom_headers() {
     let config = SecurityHeaders {
+        preset: SecurityHeadersPreset::Off,
         custom: vec![
             CustomHeader { name: "X-Frame-Options".to_string(), value: "DENY".to_string().into() },
             CustomHeader {
@@ -14,6 +19,7 @@ fn test_apply_custom_headers() {
         ],
         hsts: HstsConfig::default(),
         csp: CspConfig::default(),
+        remove: vec![],
     };
 
     let mut response = Response::new("body");
@@ -43,6 +49,7 @@ fn test_apply_custom_headers() {
 #[test]
 fn test_hsts_header_https_only() {
     let config = SecurityHeaders {
+        preset: SecurityHeadersPreset::Off,
         custom: vec![],
         hsts: HstsConfig {
             enabled: true,
@@ -51,6 +58,7 @@ fn test_hsts_header_https_only() {
             preload: false,
         },
         csp: CspConfig::default(),
+        remove: vec![],
     };
 
     // HSTS should be added for HTTPS
@@ -73,6 +81,7 @@ fn test_hsts_header_https_only() {
 #[test]
 fn test_hsts_header_format() {
     let config = SecurityHeaders {
+        preset: SecurityHeadersPreset::Off,
         custom: vec![],
         hsts: HstsConfig {
             enabled: true,
@@ -81,6 +90,7 @@ fn test_hsts_header_format() {
             preload: true,
         },
         csp: CspConfig::default(),
+        remove: vec![],
     };
 
     let mut response = Response::new("body");
@@ -102,6 +112,7 @@ fn test_hsts_header_format() {
 #[test]
 fn test_csp_header() {
     let config = SecurityHeaders {
+        preset: SecurityHeadersPreset::Off,
         custom: vec![],
         hsts: HstsConfig::default(),
         csp: CspConfig {
@@ -109,7 +120,10 @@ fn test_csp_header() {
             policy: "default-src 'self'; script-src 'self' 'unsafe-inline'"
                 .to_string()
                 .into(),
+            directives: BTreeMap::new(),
+            report_only: false,
         },
+        remove: vec![],
     };
 
     let mut response = Response::new("body");
@@ -138,6 +152,7 @@ fn test_no_config() {
 #[test]
 fn test_disabled_features() {
     let config = SecurityHeaders {
+        preset: SecurityHeadersPreset::Off,
         custom: vec![],
         hsts: HstsConfig {
             enabled: false,
@@ -145,7 +160,12 @@ fn test_disabled_features() {
             include_subdomains: true,
             preload: false,
         },
-        csp: CspConfig { enabled: false, policy: "default-src 'self'".to_string().into() },
+        csp: CspConfig {
+            enabled: false,
+            policy: "default-src 'self'".to_string().into(),
+            ..CspConfig::default()
+        },
+        remove: vec![],
     };
 
     let mut response = Response::new("body");
@@ -154,3 +174,116 @@ fn test_disabled_features() {
     // No headers should be added when features are disabled
     assert!(response.headers().is_empty());
 }
+
+fn backend_response() -> Response<&'static str> {
+    let mut response = Response::new("body");
+    response
+        .headers_mut()
+        .insert("server", HeaderValue::from_static("Apache/2.4.1"));
+    response
+        .headers_mut()
+        .insert("x-powered-by", HeaderValue::from_static("PHP/8.1"));
+    response
+        .headers_mut()
+        .insert("x-backend-node", HeaderValue::from_static("app-3"));
+    response
+}
+
+#[test]
+fn test_presets_strip_backend_identity_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let config: SecurityHeaders = toml::from_str(r#"preset = "balanced""#)?;
+
+    let mut response = backend_response();
+    apply_security_headers(&mut response, Some(&config), true);
+    let headers = response.headers();
+    assert!(headers.get("server").is_none());
+    assert!(headers.get("x-powered-by").is_none());
+    assert_eq!(headers["x-backend-node"], "app-3");
+    assert_eq!(headers["x-frame-options"], "SAMEORIGIN");
+    assert_eq!(headers["referrer-policy"], "strict-origin-when-cross-origin");
+    assert_eq!(headers["strict-transport-security"], "max-age=31536000");
+    assert!(headers.get("content-security-policy").is_none());
+
+    let mut response = backend_response();
+    apply_security_headers(&mut response, Some(&config), false);
+    assert!(response
+        .headers()
+        .get("strict-transport-security")
+        .is_none());
+
+    // Without a preset only the listed headers are removed.
+    let config: SecurityHeaders = toml::from_str(r#"remove = ["x-backend-node"]"#)?;
+    let mut response = backend_response();
+    apply_security_headers(&mut response, Some(&config), true);
+    assert_eq!(response.headers()["server"], "Apache/2.4.1");
+    assert!(response.headers().get("x-backend-node").is_none());
+    Ok(())
+}
+
+#[test]
+fn test_strict_preset_yields_to_explicit_settings() -> Result<(), Box<dyn std::error::Error>> {
+    let config: SecurityHeaders = toml::from_str(r#"preset = "strict""#)?;
+    let mut response = Response::new("body");
+    apply_security_headers(&mut response, Some(&config), true);
+    let headers = response.headers();
+    assert_eq!(headers["x-frame-options"], "DENY");
+    assert_eq!(headers["cross-origin-opener-policy"], "same-origin");
+    assert_eq!(headers["strict-transport-security"], "max-age=63072000; includeSubDomains");
+    assert!(headers["content-security-policy"]
+        .to_str()?
+        .contains("frame-ancestors 'none'"));
+
+    let config: SecurityHeaders = toml::from_str(
+        r#"
+preset = "strict"
+custom = [{ name = "X-Frame-Options", value = "SAMEORIGIN" }]
+hsts = { enabled = true, max_age = 63072000, include_subdomains = true, preload = true }
+csp = { enabled = true, policy = "default-src 'self' cdn.example.com" }
+"#,
+    )?;
+    let mut response = Response::new("body");
+    apply_security_headers(&mut response, Some(&config), true);
+    let headers = response.headers();
+    assert_eq!(headers["x-frame-options"], "SAMEORIGIN");
+    assert_eq!(
+        headers["strict-transport-security"],
+        "max-age=63072000; includeSubDomains; preload"
+    );
+    assert_eq!(headers["content-security-policy"], "default-src 'self' cdn.example.com");
+    Ok(())
+}
+
+#[test]
+fn test_csp_directives_and_report_only() -> Result<(), Box<dyn std::error::Error>> {
+    let config: SecurityHeaders = toml::from_str(
+        r#"
+[csp]
+enabled = true
+report_only = true
+[csp.directives]
+default-src = ["'self'"]
+script-src = ["'self'", "https://cdn.example.com"]
+upgrade-insecure-requests = []
+"#,
+    )?;
+    config.validate("security.headers")?;
+
+    let mut response = Response::new("body");
+    apply_security_headers(&mut response, Some(&config), false);
+    assert!(response.headers().get("content-security-policy").is_none());
+    assert_eq!(
+        response.headers()["content-security-policy-report-only"],
+        "default-src 'self'; script-src 'self' https://cdn.example.com; upgrade-insecure-requests"
+    );
+
+    for bad in [
+        r#"csp = { directives = { "script src" = ["'self'"] } }"#,
+        r#"csp = { directives = { script-src = ["'self'; object-src *"] } }"#,
+        r#"csp = { directives = { script-src = [""] } }"#,
+        r#"remove = ["bad header"]"#,
+    ] {
+        let config: SecurityHeaders = toml::from_str(bad)?;
+        assert!(config.validate("security.headers").is_err(), "{bad} should be rejected");
+    }
+    Ok(())
+}
@@ -1,5 +1,8 @@
-use huginn_proxy_lib::config::{IpFilterConfig, IpFilterMode};
-use huginn_proxy_lib::security::is_ip_allowed;
+use http::{HeaderMap, HeaderValue};
+use huginn_proxy_lib::config::{
+    IpFilterConfig, IpFilterMode, IpFilterPrecedence, TlsMatch, TrustedProxiesConfig,
+};
+use huginn_proxy_lib::security::{filtered_ip, is_ip_allowed, TlsMetadata};
 use ipnet::IpNet;
 use std::net::IpAddr;
 use std::str::FromStr;
@@ -13,8 +16,7 @@ fn parse_networks(addrs: &[&str]) -> Vec<IpNet> {
 
 #[test]
 fn test_disabled_mode() {
-    let config =
-        IpFilterConfig { mode: IpFilterMode::Disabled, allowlist: vec![], denylist: vec![] };
+    let config = IpFilterConfig { mode: IpFilterMode::Disabled, ..IpFilterConfig::default() };
 
     let ip = IpAddr::from_str("192.168.1.1").unwrap_or(IpAddr::from([0, 0, 0, 0]));
     assert!(is_ip_allowed(ip, &config));
@@ -26,6 +28,7 @@ fn test_allowlist_single_ip() {
         mode: IpFilterMode::Allowlist,
         allowlist: parse_networks(&["127.0.0.1/32"]),
         denylist: vec![],
+        ..IpFilterConfig::default()
     };
 
     let allowed_ip = IpAddr::from_str("127.0.0.1").unwrap_or(IpAddr::from([0, 0, 0, 0]));
@@ -41,6 +44,7 @@ fn test_allowlist_cidr() {
         mode: IpFilterMode::Allowlist,
         allowlist: parse_networks(&["192.168.1.0/24"]),
         denylist: vec![],
+        ..IpFilterConfig::default()
     };
 
     let allowed_ip1 = IpAddr::from_str("192.168.1.1").unwrap_or(IpAddr::from([0, 0, 0, 0]));
@@ -59,6 +63,7 @@ fn test_allowlist_multiple_networks() {
         mode: IpFilterMode::Allowlist,
         allowlist: parse_networks(&["127.0.0.1/32", "192.168.1.0/24", "10.0.0.0/8"]),
         denylist: vec![],
+        ..IpFilterConfig::default()
     };
 
     let localhost = IpAddr::from_str("127.0.0.1").unwrap_or(IpAddr::from([0, 0, 0, 0]));
@@ -80,6 +85,7 @@ fn test_denylist_single_ip() {
         mode: IpFilterMode::Denylist,
         allowlist: vec![],
         denylist: parse_networks(&["192.168.1.100/32"]),
+        ..IpFilterConfig::default()
     };
 
     let blocked_ip = IpAddr::from_str("192.168.1.100").unwrap_or(IpAddr::from([0, 0, 0, 0]));
@@ -95,6 +101,7 @@ fn test_denylist_cidr() {
         mode: IpFilterMode::Denylist,
         allowlist: vec![],
         denylist: parse_networks(&["192.168.1.0/24"]),
+        ..IpFilterConfig::default()
     };
 
     let blocked_ip1 = IpAddr::from_str("192.168.1.1").unwrap_or(IpAddr::from([0, 0, 0, 0]));
@@ -113,6 +120,7 @@ fn test_ipv6_allowlist() {
         mode: IpFilterMode::Allowlist,
         allowlist: parse_networks(&["::1/128", "2001:db8::/32"]),
         denylist: vec![],
+        ..IpFilterConfig::default()
     };
 
     let localhost_v6 = IpAddr::from_str("::1").unwrap_or(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 0]));
@@ -129,8 +137,7 @@ fn test_ipv6_allowlist() {
 
 #[test]
 fn test_empty_allowlist_denies_all() {
-    let config =
-        IpFilterConfig { mode: IpFilterMode::Allowlist, allowlist: vec![], denylist: vec![] };
+    let config = IpFilterConfig { mode: IpFilterMode::Allowlist, ..IpFilterConfig::default() };
 
     let ip = IpAddr::from_str("192.168.1.1").unwrap_or(IpAddr::from([0, 0, 0, 0]));
     assert!(!is_ip_allowed(ip, &config));
@@ -138,9 +145,91 @@ fn test_empty_allowlist_denies_all() {
 
 #[test]
 fn test_empty_denylist_allows_all() {
-    let config =
-        IpFilterConfig { mode: IpFilterMode::Denylist, allowlist: vec![], denylist: vec![] };
+    let config = IpFilterConfig { mode: IpFilterMode::Denylist, ..IpFilterConfig::default() };
 
     let ip = IpAddr::from_str("192.168.1.1").unwrap_or(IpAddr::from([0, 0, 0, 0]));
     assert!(is_ip_allowed(ip, &config));
 }
+
+#[test]
+fn test_both_mode_precedence() {
+    let mut config = IpFilterConfig {
+        mode: IpFilterMode::Both,
+        allowlist: parse_networks(&["10.0.0.0/8"]),
+        denylist: parse_networks(&["10.1.0.0/16"]),
+        ..IpFilterConfig::default()
+    };
+
+    let in_both = IpAddr::from_str("10.1.2.3").unwrap_or(IpAddr::from([0, 0, 0, 0]));
+    let allowlisted = IpAddr::from_str("10.2.0.1").unwrap_or(IpAddr::from([0, 0, 0, 0]));
+    let unlisted = IpAddr::from_str("192.168.1.1").unwrap_or(IpAddr::from([0, 0, 0, 0]));
+
+    assert_eq!(config.precedence, IpFilterPrecedence::Deny);
+    assert!(!is_ip_allowed(in_both, &config));
+    assert!(is_ip_allowed(allowlisted, &config));
+    assert!(!is_ip_allowed(unlisted, &config));
+
+    config.precedence = IpFilterPrecedence::Allow;
+    assert!(is_ip_allowed(in_both, &config));
+
+    // Without an allowlist, only denylisted IPs are blocked.
+    config.allowlist.clear();
+    assert!(!is_ip_allowed(in_both, &config));
+    assert!(is_ip_allowed(unlisted, &config));
+}
+
+#[test]
+fn test_forwarded_for_filters_rightmost_untrusted_hop() {
+    let trusted_proxies = TrustedProxiesConfig {
+        cidrs: parse_networks(&["10.0.0.0/8"]),
+        ..TrustedProxiesConfig::default()
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "x-forwarded-for",
+        HeaderValue::from_static("198.51.100.7, 203.0.113.9, 10.0.0.2"),
+    );
+    let mut config = IpFilterConfig {
+        mode: IpFilterMode::Denylist,
+        denylist: parse_networks(&["203.0.113.0/24"]),
+        ..IpFilterConfig::default()
+    };
+
+    let proxy = IpAddr::from([10, 0, 0, 1]);
+    assert_eq!(filtered_ip(proxy, &headers, &config, &trusted_proxies), proxy);
+
+    config.use_forwarded_for = true;
+    let client = filtered_ip(proxy, &headers, &config, &trusted_proxies);
+    assert_eq!(client, IpAddr::from([203, 0, 113, 9]));
+    assert!(!is_ip_allowed(client, &config));
+
+    // An untrusted peer cannot pick the filtered address.
+    let direct = IpAddr::from([192, 0, 2, 1]);
+    assert_eq!(filtered_ip(direct, &headers, &config, &trusted_proxies), direct);
+}
+
+#[test]
+fn test_match_tls_condition() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config: IpFilterConfig = toml::from_str(
+        r#"
+mode = "allowlist"
+allowlist = ["10.0.0.0/8"]
+match_tls = { sni = ["Admin.example.com", ""], alpn = ["h2"] }
+"#,
+    )?;
+    config.validate("security.ip_filter")?;
+    let condition = config.match_tls.as_ref().ok_or("match_tls not parsed")?;
+
+    let tls = TlsMetadata { sni: Some("admin.example.com"), alpn: Some("h2"), ja4: None };
+    assert!(condition.matches(&tls));
+    // `""` stands for a connection without SNI.
+    assert!(condition.matches(&TlsMetadata { sni: None, ..tls }));
+    assert!(!condition.matches(&TlsMetadata { sni: Some("www.example.com"), ..tls }));
+    // Every list given has to match.
+    assert!(!condition.matches(&TlsMetadata { alpn: Some("http/1.1"), ..tls }));
+
+    let empty =
+        IpFilterConfig { match_tls: Some(TlsMatch::default()), ..IpFilterConfig::default() };
+    assert!(empty.validate("security.ip_filter").is_err());
+    Ok(())
+}
@@ -1,3 +1,7 @@
+pub mod body_decode;
+pub mod bot_verification;
+pub mod challenge;
+pub mod fingerprint_filter;
 pub mod headers;
 pub mod ip_filter;
 pub mod rate_limit;
@@ -7,3 +11,5 @@ pub mod rate_limit_key;
 pub mod rate_limit_limiter;
 pub mod rate_limit_manager;
 pub mod rate_limit_rate;
+pub mod tarpit;
+pub mod waf;
@@ -1,5 +1,5 @@
 use huginn_proxy_lib::config::{LimitBy, TrustedProxiesConfig};
-use huginn_proxy_lib::security::extract_rate_limit_key;
+use huginn_proxy_lib::security::{extract_rate_limit_key, RateLimitFingerprints, TlsMetadata};
 
 fn peer(s: &str) -> std::net::SocketAddr {
     s.parse()
@@ -30,7 +30,15 @@ fn key_ip(
     headers: &http::HeaderMap,
     proxies: &TrustedProxiesConfig,
 ) -> String {
-    extract_rate_limit_key(LimitBy::Ip, peer_addr, "/", None, headers, proxies)
+    extract_rate_limit_key(
+        LimitBy::Ip,
+        peer_addr,
+        "/",
+        None,
+        headers,
+        proxies,
+        RateLimitFingerprints::default(),
+    )
 }
 
 #[test]
@@ -138,6 +146,7 @@ fn combined_no_trusted_uses_peer() {
         None,
         &headers_with_xff("9.9.9.9"),
         &none(),
+        RateLimitFingerprints::default(),
     );
     assert_eq!(key, "1.2.3.4:/api");
 }
@@ -152,6 +161,7 @@ fn combined_trusted_returns_real_client() {
         None,
         &headers_with_xff("203.0.113.5"),
         &proxies,
+        RateLimitFingerprints::default(),
     );
     assert_eq!(key, "203.0.113.5:/api");
 }
@@ -170,6 +180,7 @@ fn header_strategy_unchanged() {
         Some("x-api-key"),
         &h,
         &none(),
+        RateLimitFingerprints::default(),
     );
     assert_eq!(key, "secret-token");
 }
@@ -183,6 +194,77 @@ fn route_strategy_unchanged() {
         None,
         &headers_with_xff("9.9.9.9"),
         &none(),
+        RateLimitFingerprints::default(),
     );
     assert_eq!(key, "/api");
 }
+
+const JA4: &str = "t13d1516h2_8daaf6152771_e5627efa2ab1";
+const AKAMAI: &str = "1:65536;4:6291456|15663105|0|m,a,s,p";
+
+fn fingerprints() -> RateLimitFingerprints<'static> {
+    RateLimitFingerprints {
+        tls: TlsMetadata { sni: Some("API.example.com"), alpn: Some("h2"), ja4: Some(JA4) },
+        akamai: Some(AKAMAI),
+    }
+}
+
+fn key_with(limit_by: LimitBy, peer_addr: &str, fingerprints: RateLimitFingerprints<'_>) -> String {
+    extract_rate_limit_key(
+        limit_by,
+        peer(peer_addr),
+        "/",
+        None,
+        &http::HeaderMap::new(),
+        &none(),
+        fingerprints,
+    )
+}
+
+#[test]
+fn ja4_strategy_ignores_ip_rotation() {
+    // Same TLS stack from two IPs → same key.
+    assert_eq!(key_with(LimitBy::Ja4, "1.2.3.4:1234", fingerprints()), JA4);
+    assert_eq!(key_with(LimitBy::Ja4, "5.6.7.8:1234", fingerprints()), JA4);
+    assert_eq!(key_with(LimitBy::Akamai, "1.2.3.4:1234", fingerprints()), AKAMAI);
+}
+
+#[test]
+fn ip_ja4_strategy_combines_both() {
+    assert_eq!(
+        key_with(LimitBy::IpJa4, "1.2.3.4:1234", fingerprints()),
+        format!("1.2.3.4|{JA4}")
+    );
+}
+
+#[test]
+fn fingerprint_strategies_fall_back_to_client_ip() {
+    // Plain HTTP has no JA4, HTTP/1.x no Akamai fingerprint.
+    for limit_by in [LimitBy::Ja4, LimitBy::Akamai, LimitBy::IpJa4, LimitBy::Sni, LimitBy::Alpn] {
+        assert_eq!(key_with(limit_by, "1.2.3.4:1234", RateLimitFingerprints::default()), "1.2.3.4");
+    }
+    let proxies = nets(&["10.0.0.0/8"]);
+    let key = extract_rate_limit_key(
+        LimitBy::Ja4,
+        peer("10.0.0.1:443"),
+        "/",
+        None,
+        &headers_with_xff("203.0.113.5"),
+        &proxies,
+        RateLimitFingerprints::default(),
+    );
+    assert_eq!(key, "203.0.113.5");
+}
+
+#[test]
+fn sni_and_alpn_strategies() {
+    // SNI keys ignore case, so `API.example.com` and `api.example.com` share a limit.
+    assert_eq!(key_with(LimitBy::Sni, "1.2.3.4:1234", fingerprints()), "api.example.com");
+    assert_eq!(key_with(LimitBy::Alpn, "5.6.7.8:1234", fingerprints()), "h2");
+
+    let no_sni = RateLimitFingerprints {
+        tls: TlsMetadata { sni: Some(""), ..TlsMetadata::default() },
+        ..RateLimitFingerprints::default()
+    };
+    assert_eq!(key_with(LimitBy::Sni, "1.2.3.4:1234", no_sni), "1.2.3.4");
+}
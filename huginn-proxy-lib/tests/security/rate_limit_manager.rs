@@ -1,5 +1,6 @@
 use huginn_proxy_lib::config::{
-    Domain, DomainSecurityConfig, RateLimitConfig, Route, RouteSecurityConfig,
+    Domain, DomainSecurityConfig, RateLimitConfig, RateLimitStore, RedisStoreConfig, Route,
+    RouteSecurityConfig, Secret,
 };
 use huginn_proxy_lib::security::RateLimitManager;
 
@@ -13,14 +14,11 @@ fn route(prefix: &str, rate_limit: Option<RateLimitConfig>) -> Route {
     Route {
         prefix: prefix.to_string(),
         backend: "backend:80".to_string(),
-        fingerprinting: None,
-        force_new_connection: false,
-        replace_path: None,
         security: rate_limit.map(|rl| RouteSecurityConfig {
             rate_limit: Some(rl),
             ..RouteSecurityConfig::default()
         }),
-        headers: None,
+        ..Default::default()
     }
 }
 
@@ -36,7 +34,11 @@ fn domain(
         headers: None,
         security,
         fingerprinting: None,
+        ja4_variants: None,
+        fingerprint_format: None,
+        redirect: None,
         routes,
+        tenant: None,
     }
 }
 
@@ -46,8 +48,8 @@ fn sec(rate_limit: RateLimitConfig) -> DomainSecurityConfig {
 
 /// A domain `rate_limit` override replaces the global policy for that domain, while a domain
 /// without an override still uses the global limiter.
-#[test]
-fn domain_override_replaces_global() {
+#[tokio::test]
+async fn domain_override_replaces_global() {
     let global = rl(true, 100, 100);
     let domains = vec![
         // a.com: tighter override (burst 1).
@@ -58,19 +60,19 @@ fn domain_override_replaces_global() {
     let mgr = RateLimitManager::new(&global, &domains);
 
     // a.com is exhausted after a single request (burst 1).
-    assert!(mgr.check("ip", "a.com", None).is_allowed());
-    assert!(mgr.check("ip", "a.com", None).is_limited());
+    assert!(mgr.check("ip", "a.com", None).await.is_allowed());
+    assert!(mgr.check("ip", "a.com", None).await.is_limited());
 
     // b.com still has the generous global burst.
     for _ in 0..100 {
-        assert!(mgr.check("ip", "b.com", None).is_allowed());
+        assert!(mgr.check("ip", "b.com", None).await.is_allowed());
     }
 }
 
 /// A domain that explicitly disables rate limiting (`enabled = false`) must NOT fall through to
 /// an enabled global limiter.
-#[test]
-fn domain_explicit_disable_does_not_fall_through_to_global() {
+#[tokio::test]
+async fn domain_explicit_disable_does_not_fall_through_to_global() {
     let global = rl(true, 1, 1);
     let domains = vec![
         domain(Some("free.com"), Some(sec(rl(false, 1, 1))), vec![]),
@@ -80,16 +82,42 @@ fn domain_explicit_disable_does_not_fall_through_to_global() {
 
     // free.com disabled rate limiting: every request is allowed.
     for _ in 0..50 {
-        assert!(mgr.check("ip", "free.com", None).is_allowed());
+        assert!(mgr.check("ip", "free.com", None).await.is_allowed());
     }
 
     // paid.com inherits the global burst of 1.
-    assert!(mgr.check("ip", "paid.com", None).is_allowed());
-    assert!(mgr.check("ip", "paid.com", None).is_limited());
+    assert!(mgr.check("ip", "paid.com", None).await.is_allowed());
+    assert!(mgr.check("ip", "paid.com", None).await.is_limited());
 }
 
-#[test]
-fn route_override_beats_domain() {
+/// The domains that inherit their tenant's `rate_limit` share one limiter, while a tenant domain
+/// with its own block keeps its own.
+#[tokio::test]
+async fn tenant_rate_limit_is_shared_by_its_domains() {
+    let inherited = DomainSecurityConfig { tenant_rate_limit: true, ..sec(rl(true, 1, 2)) };
+    let tenant_domain = |host: &str, security: DomainSecurityConfig| Domain {
+        tenant: Some("shop".to_string()),
+        ..domain(Some(host), Some(security), vec![])
+    };
+    let domains = vec![
+        tenant_domain("shop.com", inherited.clone()),
+        tenant_domain("cdn.shop.com", inherited),
+        tenant_domain("api.shop.com", sec(rl(true, 1, 2))),
+    ];
+    let mgr = RateLimitManager::new(&rl(false, 1, 1), &domains);
+
+    assert!(mgr.check("ip", "shop.com", None).await.is_allowed());
+    assert!(mgr.check("ip", "cdn.shop.com", None).await.is_allowed());
+    assert!(mgr.check("ip", "shop.com", None).await.is_limited());
+    assert!(mgr.check("ip", "cdn.shop.com", None).await.is_limited());
+
+    assert!(mgr.check("ip", "api.shop.com", None).await.is_allowed());
+    assert!(mgr.check("ip", "api.shop.com", None).await.is_allowed());
+    assert!(mgr.check("ip", "api.shop.com", None).await.is_limited());
+}
+
+#[tokio::test]
+async fn route_override_beats_domain() {
     let global = rl(false, 1000, 1000);
     let domains = vec![domain(
         Some("a.com"),
@@ -99,20 +127,20 @@ fn route_override_beats_domain() {
     let mgr = RateLimitManager::new(&global, &domains);
 
     // The route limiter (burst 1) applies on /tight.
-    assert!(mgr.check("ip", "a.com", Some("/tight")).is_allowed());
-    assert!(mgr.check("ip", "a.com", Some("/tight")).is_limited());
+    assert!(mgr.check("ip", "a.com", Some("/tight")).await.is_allowed());
+    assert!(mgr.check("ip", "a.com", Some("/tight")).await.is_limited());
 
     // A different (unmatched) prefix on the same domain falls back to the domain limiter (burst 100).
     for _ in 0..100 {
-        assert!(mgr.check("ip", "a.com", Some("/other")).is_allowed());
+        assert!(mgr.check("ip", "a.com", Some("/other")).await.is_allowed());
     }
 }
 
 /// Whole-block replace: a route block fully replaces the domain config, it does not merge fields.
 /// A route block left at the `RateLimitConfig` default (`enabled = false`) therefore DISABLES the
 /// limit for that route even when the domain limit is enabled — it does not inherit `burst`/`rps`.
-#[test]
-fn route_block_default_disabled_replaces_enabled_domain() {
+#[tokio::test]
+async fn route_block_default_disabled_replaces_enabled_domain() {
     let global = rl(false, 1000, 1000);
     let domains = vec![domain(
         Some("a.com"),
@@ -124,18 +152,18 @@ fn route_block_default_disabled_replaces_enabled_domain() {
 
     // /open is unlimited despite the enabled (burst 1) domain limit.
     for _ in 0..50 {
-        assert!(mgr.check("ip", "a.com", Some("/open")).is_allowed());
+        assert!(mgr.check("ip", "a.com", Some("/open")).await.is_allowed());
     }
 
     // A prefix without its own block still uses the enabled domain limiter (burst 1).
-    assert!(mgr.check("ip", "a.com", Some("/other")).is_allowed());
-    assert!(mgr.check("ip", "a.com", Some("/other")).is_limited());
+    assert!(mgr.check("ip", "a.com", Some("/other")).await.is_allowed());
+    assert!(mgr.check("ip", "a.com", Some("/other")).await.is_limited());
 }
 
 /// A route that explicitly disables rate limiting must NOT fall through to an enabled domain limiter
 /// (whole-block: the route slot is authoritative, mirroring the domain-vs-global rule).
-#[test]
-fn route_explicit_disable_does_not_fall_through_to_domain() {
+#[tokio::test]
+async fn route_explicit_disable_does_not_fall_through_to_domain() {
     let global = rl(true, 1, 1);
     let domains = vec![domain(
         Some("a.com"),
@@ -145,24 +173,24 @@ fn route_explicit_disable_does_not_fall_through_to_domain() {
     let mgr = RateLimitManager::new(&global, &domains);
 
     for _ in 0..50 {
-        assert!(mgr.check("ip", "a.com", Some("/free")).is_allowed());
+        assert!(mgr.check("ip", "a.com", Some("/free")).await.is_allowed());
     }
 }
 
 /// A route override applies even when neither the domain nor the global policy is enabled.
-#[test]
-fn route_override_with_no_domain_or_global() {
+#[tokio::test]
+async fn route_override_with_no_domain_or_global() {
     let global = rl(false, 1000, 1000);
     let domains = vec![domain(Some("a.com"), None, vec![route("/tight", Some(rl(true, 1, 1)))])];
     let mgr = RateLimitManager::new(&global, &domains);
 
-    assert!(mgr.check("ip", "a.com", Some("/tight")).is_allowed());
-    assert!(mgr.check("ip", "a.com", Some("/tight")).is_limited());
+    assert!(mgr.check("ip", "a.com", Some("/tight")).await.is_allowed());
+    assert!(mgr.check("ip", "a.com", Some("/tight")).await.is_limited());
 }
 
 /// The same route prefix under two domains uses independent limiters (no cross-domain collision).
-#[test]
-fn same_prefix_isolated_across_domains() {
+#[tokio::test]
+async fn same_prefix_isolated_across_domains() {
     let global = rl(false, 1000, 1000);
     let mk_route = || route("/", Some(rl(true, 1, 1)));
     let domains = vec![
@@ -172,23 +200,23 @@ fn same_prefix_isolated_across_domains() {
     let mgr = RateLimitManager::new(&global, &domains);
 
     // Exhaust a.com's "/" limiter.
-    assert!(mgr.check("ip", "a.com", Some("/")).is_allowed());
-    assert!(mgr.check("ip", "a.com", Some("/")).is_limited());
+    assert!(mgr.check("ip", "a.com", Some("/")).await.is_allowed());
+    assert!(mgr.check("ip", "a.com", Some("/")).await.is_limited());
 
     // b.com's "/" limiter is independent and still fresh.
-    assert!(mgr.check("ip", "b.com", Some("/")).is_allowed());
-    assert!(mgr.check("ip", "b.com", Some("/")).is_limited());
+    assert!(mgr.check("ip", "b.com", Some("/")).await.is_allowed());
+    assert!(mgr.check("ip", "b.com", Some("/")).await.is_limited());
 }
 
 /// The catch-all (host-less) domain is addressed by the `_default_` label.
-#[test]
-fn catch_all_domain_uses_default_label() {
+#[tokio::test]
+async fn catch_all_domain_uses_default_label() {
     let global = rl(false, 1000, 1000);
     let domains = vec![domain(None, Some(sec(rl(true, 1, 1))), vec![])];
     let mgr = RateLimitManager::new(&global, &domains);
 
-    assert!(mgr.check("ip", DEFAULT_LABEL, None).is_allowed());
-    assert!(mgr.check("ip", DEFAULT_LABEL, None).is_limited());
+    assert!(mgr.check("ip", DEFAULT_LABEL, None).await.is_allowed());
+    assert!(mgr.check("ip", DEFAULT_LABEL, None).await.is_limited());
 }
 
 /// `is_enabled` reflects global, per-domain, and per-route limiters.
@@ -226,3 +254,23 @@ fn is_enabled_tracks_all_limiter_sources() {
     );
     assert!(!mgr.is_enabled());
 }
+
+/// A `store = "redis"` limiter whose server is unreachable enforces the limit with its in-memory
+/// counters instead of failing open.
+#[tokio::test]
+async fn unreachable_redis_store_falls_back_to_memory() {
+    let global = RateLimitConfig {
+        store: RateLimitStore::Redis,
+        redis: Some(RedisStoreConfig {
+            url: Secret::new("redis://127.0.0.1:1".to_string()),
+            pool_size: 1,
+            timeout_ms: 200,
+            key_prefix: "test:".to_string(),
+        }),
+        ..rl(true, 1, 1)
+    };
+    let mgr = RateLimitManager::new(&global, &[]);
+
+    assert!(mgr.check("ip", "a.com", None).await.is_allowed());
+    assert!(mgr.check("ip", "a.com", None).await.is_limited());
+}
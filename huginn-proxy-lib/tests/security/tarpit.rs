@@ -0,0 +1,93 @@
+use std::net::IpAddr;
+
+use huginn_proxy_lib::config::TarpitConfig;
+use huginn_proxy_lib::security::{graylisted, ObservedFingerprints, Tarpit};
+
+use crate::helpers::error_message;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const JA4: &str = "t13d1516h2_8daaf6152771_b186095e22b6";
+
+fn observed(ja4: &[&str]) -> ObservedFingerprints {
+    ObservedFingerprints {
+        ja4: ja4.iter().map(|s| s.to_string()).collect(),
+        ..ObservedFingerprints::default()
+    }
+}
+
+#[test]
+fn graylist_matches_ips_and_ja4() -> TestResult {
+    let config = TarpitConfig {
+        ja4: vec!["t13d1516h2_*".to_string()],
+        ips: vec!["203.0.113.0/24".parse()?],
+        ..TarpitConfig::default()
+    };
+    assert!(config.is_active());
+    let listed: IpAddr = "203.0.113.9".parse()?;
+    let other: IpAddr = "198.51.100.1".parse()?;
+
+    assert_eq!(graylisted(&config, listed, &observed(&[])), Some("ip"));
+    assert_eq!(graylisted(&config, other, &observed(&[JA4])), Some("ja4"));
+    assert_eq!(graylisted(&config, other, &observed(&["t12d1516h2_aa_bb"])), None);
+    // No ClientHello fingerprint: only the IP list applies.
+    assert_eq!(graylisted(&config, other, &observed(&[])), None);
+    assert!(!TarpitConfig::default().is_active());
+    Ok(())
+}
+
+#[test]
+fn slots_are_capped_and_given_back() {
+    let tarpit = Tarpit::new();
+    let first = tarpit.try_enter(2);
+    let second = tarpit.try_enter(2);
+    assert!(first.is_some() && second.is_some());
+    assert!(tarpit.try_enter(2).is_none());
+    assert_eq!(tarpit.waiting(), 2);
+
+    drop(first);
+    assert_eq!(tarpit.waiting(), 1);
+    assert!(tarpit.try_enter(2).is_some());
+    assert_eq!(tarpit.waiting(), 1);
+}
+
+#[test]
+fn default_config_validates() {
+    assert!(TarpitConfig::default().validate().is_ok());
+}
+
+#[test]
+fn delay_out_of_range_is_rejected() {
+    for delay_ms in [0, 60_001] {
+        let err = error_message(TarpitConfig { delay_ms, ..TarpitConfig::default() }.validate());
+        assert!(
+            err.contains(&format!(
+                "security.tarpit.delay_ms must be between 1 and 60000, got {delay_ms}"
+            )),
+            "got: {err}"
+        );
+    }
+}
+
+#[test]
+fn zero_max_concurrent_is_rejected() {
+    let err =
+        error_message(TarpitConfig { max_concurrent: 0, ..TarpitConfig::default() }.validate());
+    assert!(
+        err.contains("security.tarpit.max_concurrent must be greater than 0"),
+        "got: {err}"
+    );
+}
+
+#[test]
+fn empty_or_bare_wildcard_ja4_is_rejected() {
+    for entry in ["*", ""] {
+        let err = error_message(
+            TarpitConfig { ja4: vec![entry.to_string()], ..TarpitConfig::default() }.validate(),
+        );
+        assert!(
+            err.contains("security.tarpit.ja4 entries must not be empty or a bare '*'"),
+            "got: {err}"
+        );
+    }
+}
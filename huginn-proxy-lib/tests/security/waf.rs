@@ -0,0 +1,260 @@
+use std::io::Write;
+
+use http::{HeaderMap, HeaderValue, Method, Uri};
+use huginn_proxy_lib::config::{load_from_path, RouteWafConfig, WafConfig, WafMode, WafRuleSet};
+use huginn_proxy_lib::security::waf::{
+    builtin_rule_ids, inspect, RULE_HEADER_COUNT, RULE_HEADER_SIZE, RULE_METHOD,
+};
+use huginn_proxy_lib::security::TlsMetadata;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const ALL_SETS: &str = r#"rule_sets = ["sqli", "xss", "path_traversal"]"#;
+
+fn hit<'a>(
+    config: &'a WafConfig,
+    uri: &str,
+    headers: &HeaderMap,
+    body: Option<&[u8]>,
+) -> Result<Option<&'a str>, http::uri::InvalidUri> {
+    let uri: Uri = uri.parse()?;
+    Ok(inspect(config, &Method::POST, &uri, headers, body, TlsMetadata::default()).map(|h| h.rule))
+}
+
+#[test]
+fn builtin_rules_catch_common_attacks() -> TestResult {
+    let config: WafConfig = toml::from_str(ALL_SETS)?;
+    let cases: &[(&str, &str)] = &[
+        ("/items?id=1%20UNION%20ALL%20SELECT%20password", "sqli:union"),
+        ("/items?id=1/**/union/**/select/**/1", "sqli:union"),
+        ("/login?user=admin%27%20or%20%271%27%3D%271", "sqli:tautology"),
+        ("/login?user=admin%27--%20", "sqli:comment"),
+        ("/items?id=1;%20DROP%20TABLE%20users", "sqli:stacked"),
+        ("/items?id=1%20and%20sleep(5)", "sqli:functions"),
+        ("/search?q=%3Cscript%3Ealert(1)%3C/script%3E", "xss:script_tag"),
+        ("/search?q=%3Cimg+src%3Dx+onerror%3Dalert(1)%3E", "xss:event_handler"),
+        ("/go?next=javascript:alert(1)", "xss:js_uri"),
+        ("/search?q=%3Ciframe%20src%3D//evil%3E", "xss:embed_tags"),
+        ("/static/%2e%2e/%2e%2e/app.conf", "traversal:dot_dot"),
+        ("/download?file=../../secret", "traversal:dot_dot"),
+        ("/files/etc/passwd", "traversal:sensitive_files"),
+        ("/.env", "traversal:sensitive_files"),
+    ];
+    for &(uri, rule) in cases {
+        assert_eq!(hit(&config, uri, &HeaderMap::new(), None)?, Some(rule), "{uri}");
+    }
+
+    // Every built-in rule has a case above, so none failed to compile.
+    for set in [WafRuleSet::Sqli, WafRuleSet::Xss, WafRuleSet::PathTraversal] {
+        for id in builtin_rule_ids(set) {
+            assert!(cases.iter().any(|&(_, rule)| rule == id), "{id} is not covered");
+        }
+    }
+
+    for uri in [
+        "/",
+        "/search?q=select+a+union+representative",
+        "/blog/o%27reilly-and-friends?page=2",
+        "/docs/javascript-basics",
+        "/assets/app..min.js",
+        "/api/users?sort=-created_at&color=%23fff",
+    ] {
+        assert_eq!(hit(&config, uri, &HeaderMap::new(), None)?, None, "{uri}");
+    }
+
+    // Only the enabled sets apply.
+    let config: WafConfig = toml::from_str(r#"rule_sets = ["xss"]"#)?;
+    assert_eq!(
+        hit(&config, "/items?id=1;%20DROP%20TABLE%20users", &HeaderMap::new(), None)?,
+        None
+    );
+    Ok(())
+}
+
+#[test]
+fn bodies_and_headers_are_inspected() -> TestResult {
+    let config: WafConfig = toml::from_str(
+        r#"
+rule_sets = ["sqli"]
+
+[[rules]]
+id = "scanner-ua"
+targets = ["headers"]
+pattern = "(?i)sqlmap|nikto"
+
+[[rules]]
+id = "internal-field"
+targets = ["body"]
+pattern = "\"is_admin\"\\s*:"
+"#,
+    )?;
+    config.validate()?;
+    assert!(config.inspects_body());
+
+    let mut form = HeaderMap::new();
+    form.insert("content-type", HeaderValue::from_static("application/x-www-form-urlencoded"));
+    let body = b"user=admin&pass=x%27+or+%271%27%3D%271";
+    assert_eq!(hit(&config, "/login", &form, Some(body))?, Some("sqli:tautology"));
+    // Bodies that were not read never match body rules.
+    assert_eq!(hit(&config, "/login", &form, None)?, None);
+
+    let json = br#"{"name": "bob", "is_admin": true}"#;
+    assert_eq!(hit(&config, "/users", &HeaderMap::new(), Some(json))?, Some("internal-field"));
+
+    let mut scanner = HeaderMap::new();
+    scanner.insert("user-agent", HeaderValue::from_static("sqlmap/1.7"));
+    assert_eq!(hit(&config, "/", &scanner, None)?, Some("scanner-ua"));
+    Ok(())
+}
+
+#[test]
+fn tls_details_are_inspected() -> TestResult {
+    let config: WafConfig = toml::from_str(
+        r#"
+[[rules]]
+id = "no-sni"
+targets = ["sni"]
+pattern = "^$"
+
+[[rules]]
+id = "legacy-alpn"
+targets = ["alpn"]
+pattern = "^http/1\\.0$"
+"#,
+    )?;
+    config.validate()?;
+    let uri: Uri = "/".parse()?;
+    let rule = |tls: TlsMetadata<'_>| {
+        inspect(&config, &Method::GET, &uri, &HeaderMap::new(), None, tls).map(|h| h.rule)
+    };
+
+    let browser = TlsMetadata { sni: Some("example.com"), alpn: Some("h2"), ja4: None };
+    assert_eq!(rule(browser), None);
+    assert_eq!(rule(TlsMetadata { sni: None, ..browser }), Some("no-sni"));
+    assert_eq!(rule(TlsMetadata { alpn: Some("http/1.0"), ..browser }), Some("legacy-alpn"));
+    // Plain HTTP has no SNI either.
+    assert_eq!(rule(TlsMetadata::default()), Some("no-sni"));
+    Ok(())
+}
+
+#[test]
+fn limits_are_checked_before_rules() -> TestResult {
+    let config: WafConfig = toml::from_str(
+        r#"
+rule_sets = ["xss"]
+allowed_methods = ["GET", "POST"]
+max_headers = 2
+max_header_bytes = 32
+"#,
+    )?;
+    config.validate()?;
+    let uri: Uri = "/search?q=%3Cscript%3E".parse()?;
+    let headers = HeaderMap::new();
+    let rule = |method: &Method, headers: &HeaderMap| {
+        inspect(&config, method, &uri, headers, None, TlsMetadata::default()).map(|h| h.rule)
+    };
+
+    assert_eq!(rule(&Method::DELETE, &headers), Some(RULE_METHOD));
+    assert_eq!(rule(&Method::GET, &headers), Some("xss:script_tag"));
+
+    let mut many = HeaderMap::new();
+    for name in ["a", "b", "c"] {
+        many.insert(name, HeaderValue::from_static("1"));
+    }
+    assert_eq!(rule(&Method::GET, &many), Some(RULE_HEADER_COUNT));
+
+    let mut large = HeaderMap::new();
+    large.insert("cookie", HeaderValue::from_static("session=0123456789abcdef0123456789"));
+    assert_eq!(rule(&Method::GET, &large), Some(RULE_HEADER_SIZE));
+    Ok(())
+}
+
+#[test]
+fn routes_switch_inspection_and_mode() -> TestResult {
+    let mut config = WafConfig::default();
+    let detect = RouteWafConfig { enabled: true, mode: Some(WafMode::Detect) };
+    let off = RouteWafConfig { enabled: false, mode: None };
+    let on: RouteWafConfig = toml::from_str("")?;
+
+    assert_eq!(config.mode_for(None), None);
+    assert_eq!(config.mode_for(Some(&on)), Some(WafMode::Block));
+    assert_eq!(config.mode_for(Some(&detect)), Some(WafMode::Detect));
+
+    config.enabled = true;
+    assert_eq!(config.mode_for(None), Some(WafMode::Block));
+    assert_eq!(config.mode_for(Some(&off)), None);
+    Ok(())
+}
+
+#[test]
+fn invalid_waf_configs_are_rejected() -> TestResult {
+    let valid = r#"
+status = 403
+allowed_methods = ["GET"]
+max_headers = 50
+[[rules]]
+id = "r1"
+targets = ["path"]
+pattern = "^/admin"
+"#;
+    let config: WafConfig = toml::from_str(valid)?;
+    config.validate()?;
+    for (good, bad) in [
+        ("status = 403", "status = 503"),
+        ("[\"GET\"]", "[\"get\"]"),
+        ("max_headers = 50", "max_headers = 0"),
+        ("id = \"r1\"", "id = \"sqli:union\""),
+        ("targets = [\"path\"]", "targets = []"),
+        (
+            "pattern = \"^/admin\"",
+            "pattern = \"^/admin\"\n[[rules]]\nid = \"r1\"\ntargets = [\"query\"]\npattern = \"x\"",
+        ),
+    ] {
+        let config: WafConfig = toml::from_str(&valid.replace(good, bad))?;
+        assert!(config.validate().is_err(), "{bad} should be rejected");
+    }
+    assert!(toml::from_str::<WafConfig>(&valid.replace("^/admin", "^/(admin")).is_err());
+    assert!(toml::from_str::<WafConfig>(&valid.replace("\"path\"", "\"cookie\"")).is_err());
+    Ok(())
+}
+
+#[test]
+fn rules_files_are_loaded_with_the_config() -> TestResult {
+    let mut rules = tempfile::Builder::new().suffix(".yaml").tempfile()?;
+    writeln!(
+        rules,
+        "rules:\n  - id: wp-probe\n    targets: [path]\n    pattern: \"^/wp-(admin|login)\""
+    )?;
+    let mut config_file = tempfile::Builder::new().suffix(".toml").tempfile()?;
+    writeln!(
+        config_file,
+        r#"
+listen = {{ addrs = ["0.0.0.0:7000"] }}
+backends = [{{ address = "app:9000" }}]
+
+[security.waf]
+enabled = true
+rules_files = ["{}"]
+
+[[domains]]
+host = "example.com"
+routes = [{{ prefix = "/", backend = "app:9000", waf = {{ mode = "detect" }} }}]
+"#,
+        rules.path().display()
+    )?;
+
+    let config = load_from_path(config_file.path())?;
+    let waf = &config.security.waf;
+    assert_eq!(waf.file_rules.len(), 1);
+    assert_eq!(hit(waf, "/wp-login.php", &HeaderMap::new(), None)?, Some("wp-probe"));
+    let route = config
+        .domains
+        .first()
+        .and_then(|d| d.routes.first())
+        .ok_or("route missing")?;
+    assert_eq!(waf.mode_for(route.waf.as_ref()), Some(WafMode::Detect));
+
+    writeln!(rules, "  - id: wp-probe\n    targets: [query]\n    pattern: x")?;
+    assert!(load_from_path(config_file.path()).is_err(), "duplicate rule id accepted");
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use http::Version;
+use huginn_proxy_lib::config::{AccessLogConfig, AccessLogField, AccessLogOutput};
+use huginn_proxy_lib::telemetry::{AccessLogger, AccessRecord};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+fn record(path: &str) -> AccessRecord {
+    AccessRecord {
+        timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+        request_id: Some("01HGW2N7EHJVJ6Q9XK4TQZ3M5B".to_string()),
+        connection_id: 7,
+        client_ip: IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+        sni: Some(Arc::from("example.com")),
+        host: "example.com".to_string(),
+        method: "GET".to_string(),
+        path: path.to_string(),
+        protocol: Version::HTTP_2,
+        status: 200,
+        duration: Duration::from_micros(1500),
+        bytes_in: None,
+        bytes_out: Some(42),
+        backend: Some("127.0.0.1:9001".to_string()),
+        tenant: None,
+        ja4: Some(Arc::from("t13d1516h2_8daaf6152771_e5627efa2ab1")),
+        akamai: None,
+        tcp_syn: None,
+    }
+}
+
+/// Wait until the writer thread has flushed a line containing `needle` into `path`.
+fn wait_for(path: &Path, needle: &str) -> TestResult {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if std::fs::read_to_string(path).is_ok_and(|content| content.contains(needle)) {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Err(format!("{needle} never written to {}", path.display()).into())
+}
+
+#[test]
+fn record_follows_field_selection_and_order() {
+    let line = record("/a").to_json(&[
+        AccessLogField::Status,
+        AccessLogField::Timestamp,
+        AccessLogField::Protocol,
+        AccessLogField::BytesIn,
+        AccessLogField::Backend,
+    ]);
+    assert_eq!(
+        line,
+        r#"{"status":200,"timestamp":"2023-11-14T22:13:20.123Z","protocol":"HTTP/2.0","bytes_in":null,"backend":"127.0.0.1:9001"}"#
+    );
+}
+
+#[test]
+fn record_with_all_fields_is_one_json_object() -> TestResult {
+    let value: serde_json::Value =
+        serde_json::from_str(&record("/a").to_json(&AccessLogField::ALL))?;
+    assert_eq!(value["request_id"], "01HGW2N7EHJVJ6Q9XK4TQZ3M5B");
+    assert_eq!(value["connection_id"], 7);
+    assert_eq!(value["client_ip"], "203.0.113.7");
+    assert_eq!(value["sni"], "example.com");
+    assert_eq!(value["path"], "/a");
+    assert_eq!(value["duration_ms"], 1.5);
+    assert_eq!(value["bytes_out"], 42);
+    assert_eq!(value["ja4"], "t13d1516h2_8daaf6152771_e5627efa2ab1");
+    assert!(value["akamai"].is_null());
+    assert!(value["tcp_syn"].is_null());
+    assert_eq!(value.as_object().map(|o| o.len()), Some(AccessLogField::ALL.len()));
+    Ok(())
+}
+
+#[test]
+fn disabled_logger_is_a_no_op() -> TestResult {
+    let logger = AccessLogger::from_config(&AccessLogConfig::default())?;
+    assert!(!logger.is_enabled());
+    logger.log(record("/a"));
+    Ok(())
+}
+
+#[test]
+fn file_output_rotates_past_max_size() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("access.log");
+    let logger = AccessLogger::from_config(&AccessLogConfig {
+        enabled: true,
+        output: AccessLogOutput::File,
+        path: Some(path.clone()),
+        max_size_bytes: 1,
+        max_files: 2,
+        fields: vec![AccessLogField::Path],
+    })?;
+
+    for n in 1..=4 {
+        let request = format!("/{n}");
+        logger.log(record(&request));
+        wait_for(&path, &request)?;
+    }
+
+    let rotated = |index: usize| dir.path().join(format!("access.log.{index}"));
+    assert_eq!(std::fs::read_to_string(&path)?, "{\"path\":\"/4\"}\n");
+    assert_eq!(std::fs::read_to_string(rotated(1))?, "{\"path\":\"/3\"}\n");
+    assert_eq!(std::fs::read_to_string(rotated(2))?, "{\"path\":\"/2\"}\n");
+    assert!(!rotated(3).exists());
+    Ok(())
+}
+
+#[test]
+fn file_output_appends_to_existing_file() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("access.log");
+    std::fs::write(&path, "previous\n")?;
+    let logger = AccessLogger::from_config(&AccessLogConfig {
+        enabled: true,
+        output: AccessLogOutput::File,
+        path: Some(path.clone()),
+        fields: vec![AccessLogField::Path],
+        ..AccessLogConfig::default()
+    })?;
+    logger.log(record("/new"));
+    wait_for(&path, "/new")?;
+    assert_eq!(std::fs::read_to_string(&path)?, "previous\n{\"path\":\"/new\"}\n");
+    Ok(())
+}
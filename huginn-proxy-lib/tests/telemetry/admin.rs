@@ -0,0 +1,363 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use bytes::Bytes;
+use http::{Method, Request, StatusCode};
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::config::{Config, ConfigParts, StaticConfig};
+use huginn_proxy_lib::proxy::connection::{ConnectionContext, ConnectionRegistry};
+use huginn_proxy_lib::telemetry::admin::{
+    apply_route_change, handle_admin, RouteChange, RouteChangeError,
+};
+use huginn_proxy_lib::telemetry::{AdminState, LogLevels};
+use huginn_proxy_lib::{DynamicConfig, HealthRegistry, RuntimeHandles};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const CONFIG: &str = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "127.0.0.1:9001" }, { address = "127.0.0.1:9002" }]
+telemetry = { metrics_port = 9090, admin = { enabled = true, token = "s3cret" } }
+
+[[domains]]
+host = "example.com"
+
+[[domains.routes]]
+prefix = "/"
+backend = "127.0.0.1:9001"
+"#;
+
+fn parts() -> Result<(StaticConfig, DynamicConfig), Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str(CONFIG)?;
+    config.validate_cross_refs()?;
+    let ConfigParts { static_cfg, dynamic_cfg } = config.into_parts();
+    Ok((static_cfg, dynamic_cfg))
+}
+
+fn change(json: &str) -> Result<RouteChange, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+#[test]
+fn route_change_adds_and_removes_routes() -> TestResult {
+    let (static_cfg, dynamic) = parts()?;
+    let added = apply_route_change(
+        &static_cfg,
+        &dynamic,
+        change(
+            r#"{"action":"add","host":"example.com",
+                "route":{"prefix":"/api","backend":"127.0.0.1:9002"}}"#,
+        )?,
+    )?;
+    let prefixes: Vec<&str> = added.domains[0]
+        .routes
+        .iter()
+        .map(|r| r.prefix.as_str())
+        .collect();
+    assert_eq!(prefixes, ["/api", "/"]);
+    assert_eq!(dynamic.domains[0].routes.len(), 1, "input config is left untouched");
+
+    let removed = apply_route_change(
+        &static_cfg,
+        &added,
+        change(r#"{"action":"remove","host":"example.com","prefix":"/"}"#)?,
+    )?;
+    assert_eq!(removed.domains[0].routes.len(), 1);
+    assert_eq!(removed.domains[0].routes[0].prefix, "/api");
+    Ok(())
+}
+
+#[test]
+fn route_change_rejects_invalid_changes() -> TestResult {
+    let (static_cfg, dynamic) = parts()?;
+    let cases = [
+        (
+            r#"{"action":"add","host":"other.com","route":{"prefix":"/x","backend":"127.0.0.1:9001"}}"#,
+            StatusKind::UnknownDomain,
+        ),
+        (
+            r#"{"action":"add","host":"example.com","route":{"prefix":"/","backend":"127.0.0.1:9001"}}"#,
+            StatusKind::Duplicate,
+        ),
+        (
+            r#"{"action":"add","host":"example.com","route":{"prefix":"/x","backend":"10.0.0.1:1"}}"#,
+            StatusKind::Invalid,
+        ),
+        (
+            r#"{"action":"add","host":"example.com","route":{"prefix":"/x","backend":"127.0.0.1:9001",
+                "security":{"rate_limit":{"enabled":true,"requests_per_second":1,"burst":1}}}}"#,
+            StatusKind::RateLimit,
+        ),
+        (
+            r#"{"action":"remove","host":"example.com","prefix":"/missing"}"#,
+            StatusKind::UnknownRoute,
+        ),
+    ];
+    for (json, expected) in cases {
+        let err = match apply_route_change(&static_cfg, &dynamic, change(json)?) {
+            Ok(_) => return Err(format!("expected rejection for {json}").into()),
+            Err(e) => e,
+        };
+        let kind = match err {
+            RouteChangeError::UnknownDomain(_) => StatusKind::UnknownDomain,
+            RouteChangeError::UnknownRoute { .. } => StatusKind::UnknownRoute,
+            RouteChangeError::DuplicateRoute { .. } => StatusKind::Duplicate,
+            RouteChangeError::NoSyntheticResponse { .. } => StatusKind::NoSynthetic,
+            RouteChangeError::RateLimitOverride(_) => StatusKind::RateLimit,
+            RouteChangeError::Invalid(_) => StatusKind::Invalid,
+        };
+        assert_eq!(kind, expected, "{json}");
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum StatusKind {
+    UnknownDomain,
+    UnknownRoute,
+    Duplicate,
+    NoSynthetic,
+    RateLimit,
+    Invalid,
+}
+
+struct Admin {
+    state: AdminState,
+    dynamic: Arc<ArcSwap<DynamicConfig>>,
+    health: Arc<HealthRegistry>,
+    runtime: RuntimeHandles,
+    /// Keeps the filter controlled by `runtime.log_levels` alive.
+    _log_filter: reload::Layer<EnvFilter, Registry>,
+}
+
+fn admin() -> Result<Admin, Box<dyn std::error::Error + Send + Sync>> {
+    let (static_cfg, dynamic_cfg) = parts()?;
+    let dynamic = Arc::new(ArcSwap::from_pointee(dynamic_cfg));
+    let health = Arc::new(HealthRegistry::new());
+    let (log_filter, log_levels) = LogLevels::reloadable("info".to_string(), None);
+    let runtime = RuntimeHandles {
+        connections: ConnectionRegistry::new(),
+        log_levels,
+        ..RuntimeHandles::default()
+    };
+    let state = AdminState::new(
+        Arc::new(static_cfg),
+        Arc::clone(&dynamic),
+        Arc::clone(&health),
+        runtime.clone(),
+    );
+    Ok(Admin { state, dynamic, health, runtime, _log_filter: log_filter })
+}
+
+async fn call(
+    admin: &Admin,
+    method: Method,
+    path: &str,
+    token: Option<&str>,
+    body: &str,
+) -> Result<(StatusCode, serde_json::Value), Box<dyn std::error::Error + Send + Sync>> {
+    let mut builder = Request::builder().method(method).uri(path);
+    if let Some(token) = token {
+        builder = builder.header("authorization", format!("Bearer {token}"));
+    }
+    let req = builder.body(Full::new(Bytes::from(body.to_string())))?;
+    let resp = handle_admin(req, &admin.state).await;
+    let status = resp.status();
+    let bytes = resp.into_body().collect().await?.to_bytes();
+    Ok((status, serde_json::from_slice(&bytes)?))
+}
+
+#[tokio::test]
+async fn admin_api_requires_bearer_token() -> TestResult {
+    let admin = admin()?;
+    for token in [None, Some("wrong"), Some("s3cre")] {
+        let (status, _) = call(&admin, Method::GET, "/admin/config", token, "").await?;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{token:?}");
+    }
+    let (status, body) = call(&admin, Method::GET, "/admin/config", Some("s3cret"), "").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["static"]["telemetry"]["admin"]["token"], "<redacted>");
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_api_drains_backends() -> TestResult {
+    let admin = admin()?;
+    let token = Some("s3cret");
+    let (status, _) =
+        call(&admin, Method::POST, "/admin/backends/127.0.0.1:9001/drain", token, "").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert!(admin.health.is_drained("127.0.0.1:9001"));
+
+    let (status, body) = call(&admin, Method::GET, "/admin/backends", token, "").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["backends"][0]["address"], "127.0.0.1:9001");
+    assert_eq!(body["backends"][0]["drained"], true);
+    assert_eq!(body["backends"][1]["drained"], false);
+
+    let (status, _) =
+        call(&admin, Method::POST, "/admin/backends/127.0.0.1%3A9001/undrain", token, "").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!admin.health.is_drained("127.0.0.1:9001"));
+
+    // A backend drained by its config cannot be undrained through the API.
+    let mut backends = admin.dynamic.load().backends.to_vec();
+    backends[1].drain = true;
+    admin.health.configure_drains(&backends);
+    let (status, body) = call(&admin, Method::GET, "/admin/backends", token, "").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["backends"][1]["drained"], true);
+    assert_eq!(body["backends"][1]["drained_by_config"], true);
+    let (status, _) =
+        call(&admin, Method::POST, "/admin/backends/127.0.0.1:9002/undrain", token, "").await?;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(admin.health.is_drained("127.0.0.1:9002"));
+
+    let (status, _) =
+        call(&admin, Method::POST, "/admin/backends/10.0.0.1:1/drain", token, "").await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_api_drains_the_proxy() -> TestResult {
+    let admin = admin()?;
+    let token = Some("s3cret");
+    let (status, _) = call(&admin, Method::GET, "/admin/drain", token, "").await?;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    assert!(!admin.runtime.drain.is_requested());
+
+    let (status, body) = call(&admin, Method::POST, "/admin/drain", token, "").await?;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(body["status"], "draining");
+    assert!(admin.runtime.drain.is_requested());
+
+    let (_, body) = call(&admin, Method::POST, "/admin/drain", token, "").await?;
+    assert_eq!(body["status"], "already_draining");
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_api_mutates_routes_and_drains_connections() -> TestResult {
+    let admin = admin()?;
+    let token = Some("s3cret");
+    let generation = admin.runtime.config_generation.subscribe();
+
+    let body = r#"{"action":"add","host":"example.com","route":{"prefix":"/api","backend":"127.0.0.1:9002"}}"#;
+    let (status, _) = call(&admin, Method::POST, "/admin/routes", token, body).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(admin.dynamic.load().domains[0].routes[0].prefix, "/api");
+    assert!(generation.has_changed()?);
+
+    let (status, _) = call(&admin, Method::POST, "/admin/routes", token, body).await?;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = call(&admin, Method::POST, "/admin/routes", token, "{").await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = call(&admin, Method::GET, "/admin/routes", token, "").await?;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_api_lists_connections() -> TestResult {
+    let admin = admin()?;
+    let connection = Arc::new(ConnectionContext::new("198.51.100.4:5000".parse()?));
+    let _conn = admin
+        .runtime
+        .connections
+        .register(&connection, "127.0.0.1:0", false);
+    let (status, body) =
+        call(&admin, Method::GET, "/admin/connections", Some("s3cret"), "").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["count"], 1);
+    assert_eq!(body["connections"][0]["id"], connection.id());
+    assert_eq!(body["connections"][0]["peer"], "198.51.100.4:5000");
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_api_changes_log_levels() -> TestResult {
+    let admin = admin()?;
+    let token = Some("s3cret");
+    let (status, body) = call(&admin, Method::GET, "/admin/log_level", token, "").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, serde_json::json!({"filter": "info", "routes": []}));
+
+    let (status, body) =
+        call(&admin, Method::PUT, "/admin/log_level", token, r#"{"level":"warn"}"#).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["filter"], "warn");
+
+    let route = r#"{"host":"example.com","prefix":"/","level":"debug"}"#;
+    let (status, body) = call(&admin, Method::PUT, "/admin/log_level", token, route).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["routes"],
+        serde_json::json!([{"domain": "example.com", "prefix": "/", "level": "debug"}])
+    );
+
+    let clear = r#"{"host":"example.com","prefix":"/","level":null}"#;
+    let (_, body) = call(&admin, Method::PUT, "/admin/log_level", token, clear).await?;
+    assert_eq!(body["routes"], serde_json::json!([]));
+
+    for (body, expected) in [
+        (
+            r#"{"host":"example.com","prefix":"/missing","level":"debug"}"#,
+            StatusCode::NOT_FOUND,
+        ),
+        (r#"{"host":"other.com","prefix":"/","level":"debug"}"#, StatusCode::NOT_FOUND),
+        (r#"{"host":"example.com","prefix":"/","level":"loud"}"#, StatusCode::BAD_REQUEST),
+        (r#"{"level":"info,=="}"#, StatusCode::BAD_REQUEST),
+        ("{}", StatusCode::BAD_REQUEST),
+    ] {
+        let (status, _) = call(&admin, Method::PUT, "/admin/log_level", token, body).await?;
+        assert_eq!(status, expected, "{body}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_api_switches_synthetic_responses() -> TestResult {
+    let admin = admin()?;
+    let token = Some("s3cret");
+    let add = r#"{"action":"add","host":"example.com","route":{"prefix":"/status","backend":"127.0.0.1:9002","synthetic":{"enabled":false,"status":200,"body":"ok"}}}"#;
+    let (status, _) = call(&admin, Method::POST, "/admin/routes", token, add).await?;
+    assert_eq!(status, StatusCode::OK);
+
+    let entry = |override_enabled: serde_json::Value, active: bool| {
+        serde_json::json!([{
+            "host": "example.com",
+            "prefix": "/status",
+            "status": 200,
+            "configured": false,
+            "override": override_enabled,
+            "active": active,
+        }])
+    };
+    let (status, body) = call(&admin, Method::GET, "/admin/synthetic", token, "").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["routes"], entry(serde_json::Value::Null, false));
+
+    let on = r#"{"host":"example.com","prefix":"/status","enabled":true}"#;
+    let (status, body) = call(&admin, Method::PUT, "/admin/synthetic", token, on).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["routes"], entry(true.into(), true));
+    assert_eq!(admin.runtime.synthetic.get("example.com", "/status"), Some(true));
+
+    let reset = r#"{"host":"example.com","prefix":"/status","enabled":null}"#;
+    let (_, body) = call(&admin, Method::PUT, "/admin/synthetic", token, reset).await?;
+    assert_eq!(body["routes"], entry(serde_json::Value::Null, false));
+
+    for body in [
+        r#"{"host":"example.com","prefix":"/","enabled":true}"#,
+        r#"{"host":"example.com","prefix":"/missing","enabled":true}"#,
+        r#"{"host":"other.com","prefix":"/status","enabled":true}"#,
+    ] {
+        let (status, _) = call(&admin, Method::PUT, "/admin/synthetic", token, body).await?;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    }
+    let (status, _) = call(&admin, Method::POST, "/admin/synthetic", token, on).await?;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! `[telemetry.anomaly_detection]`: reporting a fingerprint whose share of traffic grows.
+
+use std::time::Duration;
+
+use huginn_proxy_lib::config::AnomalyDetectionConfig;
+use huginn_proxy_lib::telemetry::{AnomalyDetector, Metrics};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const BROWSER: &str = "t13d1516h2_8daaf6152771_e5627efa2ab1";
+const BOT: &str = "t13d190900_9dc949149365_97f8aa674fd9";
+
+fn config() -> AnomalyDetectionConfig {
+    AnomalyDetectionConfig {
+        enabled: true,
+        short_window_secs: 1,
+        long_window_secs: 2,
+        min_share: 0.3,
+        min_growth: 3.0,
+        min_requests: 20,
+        ..AnomalyDetectionConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn growing_fingerprint_is_reported_once() -> TestResult {
+    let detector = AnomalyDetector::from_config(&config(), Metrics::new_noop())?;
+
+    // Without a complete long window there is no baseline, so a dominant fingerprint is normal.
+    for _ in 0..50 {
+        assert!(detector.observe(BROWSER).is_none());
+    }
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+
+    // The long window ended with only BROWSER traffic; now half the requests come from BOT.
+    let mut reports = Vec::new();
+    for _ in 0..50 {
+        reports.extend(detector.observe(BROWSER));
+        reports.extend(detector.observe(BOT));
+    }
+    let [report] = reports.as_slice() else {
+        return Err(format!("expected one report, got {reports:?}").into());
+    };
+    assert_eq!(report.fingerprint, BOT);
+    assert_eq!(report.kind, "ja4");
+    assert_eq!(report.requests, 20);
+    assert!(report.share > 0.4 && report.share <= 0.5, "{}", report.share);
+    assert_eq!(report.baseline_share, 0.0);
+    Ok(())
+}
+
+#[test]
+fn disabled_detector_reports_nothing() {
+    let detector = AnomalyDetector::disabled();
+    assert!(!detector.is_enabled());
+    assert!(detector.observe(BOT).is_none());
+}
+
+#[test]
+fn windows_and_thresholds_are_validated() {
+    assert!(config().validate().is_ok());
+    assert!(AnomalyDetectionConfig { long_window_secs: 1, ..config() }
+        .validate()
+        .is_err());
+    assert!(AnomalyDetectionConfig { min_share: 0.0, ..config() }
+        .validate()
+        .is_err());
+    assert!(AnomalyDetectionConfig { min_growth: 0.5, ..config() }
+        .validate()
+        .is_err());
+    assert!(AnomalyDetectionConfig {
+        webhook_url: Some("ftp://alerts.example.com".to_string()),
+        ..config()
+    }
+    .validate()
+    .is_err());
+    // Thresholds are not checked while disabled.
+    assert!(AnomalyDetectionConfig { enabled: false, min_share: 2.0, ..config() }
+        .validate()
+        .is_ok());
+}
@@ -0,0 +1,222 @@
+//! `[event_stream]`: batching against an in-process webhook, ClickHouse inserts against an
+//! in-process HTTP interface, and sink validation.
+
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::header::AUTHORIZATION;
+use http::request::Parts;
+use http::{Request, Response};
+use http_body_util::{BodyExt, Full};
+use huginn_proxy_lib::config::{EventSinkKind, EventStreamConfig, Secret};
+use huginn_proxy_lib::telemetry::{EventStream, FingerprintEvent, Metrics};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type TestResult = Result<(), BoxError>;
+
+fn event(status: u16) -> FingerprintEvent {
+    FingerprintEvent {
+        timestamp: "2026-01-02T03:04:05.678Z".to_string(),
+        request_id: None,
+        client_ip: IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+        sni: Some("example.com".to_string()),
+        host: "example.com".to_string(),
+        ja4: Some("t13d1516h2_8daaf6152771_e5627efa2ab1".to_string()),
+        ja4_o: Some("t13d1516h2_acb858a92679_e5627efa2ab1".to_string()),
+        ja4_s1: Some("t13d1516h2_8daaf6152771_d8a2da3f94cd".to_string()),
+        akamai: None,
+        tcp_syn: None,
+        backend: Some("127.0.0.1:9001".to_string()),
+        status,
+    }
+}
+
+/// HTTP server that forwards each request, with its body, to the returned channel.
+async fn spawn_webhook() -> Result<(String, mpsc::UnboundedReceiver<(Parts, Bytes)>), BoxError> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/events", listener.local_addr()?);
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let tx = tx.clone();
+            let svc = service_fn(move |req: Request<Incoming>| {
+                let tx = tx.clone();
+                async move {
+                    let (parts, body) = req.into_parts();
+                    let body = body.collect().await.map(|b| b.to_bytes());
+                    tx.send((parts, body.unwrap_or_default())).ok();
+                    Ok::<_, Infallible>(Response::new(Full::new(Bytes::new())))
+                }
+            });
+            tokio::spawn(
+                hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), svc),
+            );
+        }
+    });
+    Ok((url, rx))
+}
+
+fn webhook_config(url: String) -> EventStreamConfig {
+    EventStreamConfig {
+        enabled: true,
+        url: Some(url),
+        token: Some(Secret::new("siem-token".to_string())),
+        batch_size: 2,
+        flush_interval_ms: 50,
+        ..EventStreamConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn webhook_receives_full_and_timed_out_batches() -> TestResult {
+    let (url, mut bodies) = spawn_webhook().await?;
+    let stream = EventStream::from_config(&webhook_config(url), Metrics::new_noop())?;
+    for status in [200, 403, 502] {
+        stream.publish(event(status));
+    }
+
+    let receive = async {
+        let first = bodies.recv().await.ok_or("webhook closed")?;
+        let second = bodies.recv().await.ok_or("webhook closed")?;
+        Ok::<_, &str>((first, second))
+    };
+    let ((parts, first), (_, second)) =
+        tokio::time::timeout(Duration::from_secs(5), receive).await??;
+    assert_eq!(parts.headers.get(AUTHORIZATION).ok_or("no Authorization")?, "Bearer siem-token");
+
+    // The first two events fill a batch; the third goes out once the flush interval passed.
+    let first: Vec<Value> = serde_json::from_slice(&first)?;
+    let second: Vec<Value> = serde_json::from_slice(&second)?;
+    let statuses: Vec<_> = first
+        .iter()
+        .chain(&second)
+        .map(|e| e["status"].clone())
+        .collect();
+    assert_eq!(first.len(), 2);
+    assert_eq!(statuses, [200, 403, 502]);
+
+    let event = &first[0];
+    assert_eq!(event["client_ip"], "203.0.113.7");
+    assert_eq!(event["ja4_s1"], "t13d1516h2_8daaf6152771_d8a2da3f94cd");
+    assert_eq!(event["backend"], "127.0.0.1:9001");
+    // Fields without a value are left out.
+    assert!(event.get("akamai").is_none() && event.get("request_id").is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn clickhouse_creates_the_table_then_inserts_rows() -> TestResult {
+    let (url, mut requests) = spawn_webhook().await?;
+    let config = EventStreamConfig {
+        enabled: true,
+        sink: EventSinkKind::Clickhouse,
+        url: Some(url),
+        table: "analytics.fingerprints".to_string(),
+        user: Some("huginn".to_string()),
+        password: Some(Secret::new("ch-password".to_string())),
+        batch_size: 2,
+        ..EventStreamConfig::default()
+    };
+    let stream = EventStream::from_config(&config, Metrics::new_noop())?;
+    stream.publish(event(200));
+    stream.publish(event(429));
+
+    let receive = async {
+        let create = requests.recv().await.ok_or("server closed")?;
+        let insert = requests.recv().await.ok_or("server closed")?;
+        Ok::<_, &str>((create, insert))
+    };
+    let ((create, ddl), (insert, rows)) =
+        tokio::time::timeout(Duration::from_secs(5), receive).await??;
+
+    assert_eq!(create.headers.get("x-clickhouse-user").ok_or("no user")?, "huginn");
+    assert_eq!(create.headers.get("x-clickhouse-key").ok_or("no key")?, "ch-password");
+    let ddl = String::from_utf8(ddl.to_vec())?;
+    assert!(ddl.starts_with("CREATE TABLE IF NOT EXISTS analytics.fingerprints ("), "{ddl}");
+
+    let query = insert.uri.query().unwrap_or_default();
+    assert!(
+        query.starts_with("query=INSERT%20INTO%20analytics.fingerprints%20FORMAT%20JSONEachRow"),
+        "{query}"
+    );
+    // One JSON object per line.
+    let rows: Vec<Value> = std::str::from_utf8(&rows)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    let statuses: Vec<_> = rows.iter().map(|row| row["status"].clone()).collect();
+    assert_eq!(statuses, [200, 429]);
+    Ok(())
+}
+
+#[test]
+fn sinks_require_their_destination() {
+    let enabled = |sink| EventStreamConfig { enabled: true, sink, ..EventStreamConfig::default() };
+
+    assert!(EventStreamConfig::default().validate().is_ok());
+    assert!(enabled(EventSinkKind::Webhook).validate().is_err());
+    let webhook = EventStreamConfig {
+        url: Some("nats://127.0.0.1:4222".to_string()),
+        ..enabled(EventSinkKind::Webhook)
+    };
+    assert!(webhook.validate().is_err());
+    assert!(webhook_config("https://siem.example.com/ingest".to_string())
+        .validate()
+        .is_ok());
+    let unbatched = EventStreamConfig {
+        batch_size: 0,
+        ..webhook_config("https://siem.example.com/ingest".to_string())
+    };
+    assert!(unbatched.validate().is_err());
+
+    let nats = EventStreamConfig {
+        url: Some("nats://127.0.0.1:4222".to_string()),
+        topic: Some("huginn.fingerprints".to_string()),
+        ..enabled(EventSinkKind::Nats)
+    };
+    assert_eq!(nats.validate().is_ok(), cfg!(feature = "nats"));
+    let kafka = EventStreamConfig {
+        brokers: vec!["127.0.0.1:9092".to_string()],
+        ..enabled(EventSinkKind::Kafka)
+    };
+    // A topic is required either way.
+    assert!(kafka.validate().is_err());
+
+    let clickhouse = |url: &str, table: &str| EventStreamConfig {
+        url: Some(url.to_string()),
+        table: table.to_string(),
+        ..enabled(EventSinkKind::Clickhouse)
+    };
+    assert!(clickhouse("http://127.0.0.1:8123", "huginn_fingerprints")
+        .validate()
+        .is_ok());
+    assert!(clickhouse("https://ch.example.com", "analytics.fingerprints")
+        .validate()
+        .is_ok());
+    assert!(clickhouse("http://127.0.0.1:8123/?database=x", "fingerprints")
+        .validate()
+        .is_err());
+    // The table name is spliced into SQL.
+    assert!(clickhouse("http://127.0.0.1:8123", "fingerprints; DROP TABLE x")
+        .validate()
+        .is_err());
+
+    let sqlite = |table: &str| EventStreamConfig {
+        path: Some("/var/lib/huginn/fingerprints.db".into()),
+        table: table.to_string(),
+        ..enabled(EventSinkKind::Sqlite)
+    };
+    assert_eq!(sqlite("fingerprints").validate().is_ok(), cfg!(feature = "sqlite"));
+    // SQLite has no databases to qualify the table with.
+    assert!(sqlite("main.fingerprints").validate().is_err());
+    assert!(enabled(EventSinkKind::Sqlite).validate().is_err());
+}
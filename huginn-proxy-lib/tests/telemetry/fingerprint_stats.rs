@@ -0,0 +1,59 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use huginn_proxy_lib::config::FingerprintStatsConfig;
+use huginn_proxy_lib::telemetry::{FingerprintCount, FingerprintStats};
+
+fn client(last: u8) -> IpAddr {
+    IpAddr::V4(Ipv4Addr::new(198, 51, 100, last))
+}
+
+fn stats(top_n: usize) -> FingerprintStats {
+    FingerprintStats::from_config(&FingerprintStatsConfig {
+        enabled: true,
+        top_n,
+        ..FingerprintStatsConfig::default()
+    })
+}
+
+fn count(fingerprint: &str, requests: u64, clients: u64) -> FingerprintCount {
+    FingerprintCount { fingerprint: fingerprint.to_string(), requests, clients }
+}
+
+#[test]
+fn counts_requests_and_distinct_clients_per_fingerprint() {
+    let stats = stats(5);
+    stats.observe(client(1), Some("ja4-a"), Some("akamai-a"));
+    stats.observe(client(1), Some("ja4-a"), None);
+    stats.observe(client(2), Some("ja4-a"), None);
+    stats.observe(client(3), Some("ja4-b"), None);
+
+    let snapshot = stats
+        .snapshot()
+        .unwrap_or_else(|| panic!("stats are enabled"));
+    assert_eq!(snapshot.ja4, vec![count("ja4-a", 3, 2), count("ja4-b", 1, 1)]);
+    assert_eq!(snapshot.akamai, vec![count("akamai-a", 1, 1)]);
+    assert_eq!(snapshot.window_secs, 3600);
+}
+
+#[test]
+fn top_list_keeps_the_most_requested_fingerprints() {
+    let stats = stats(2);
+    for (fingerprint, requests) in [("one", 1), ("two", 2), ("three", 3)] {
+        for _ in 0..requests {
+            stats.observe(client(1), Some(fingerprint), None);
+        }
+    }
+    let ja4: Vec<String> = stats
+        .snapshot()
+        .map(|s| s.ja4.into_iter().map(|c| c.fingerprint).collect())
+        .unwrap_or_default();
+    assert_eq!(ja4, vec!["three".to_string(), "two".to_string()]);
+}
+
+#[test]
+fn disabled_stats_record_nothing() {
+    let stats = FingerprintStats::disabled();
+    stats.observe(client(1), Some("ja4-a"), None);
+    assert!(!stats.is_enabled());
+    assert!(stats.snapshot().is_none());
+}
@@ -0,0 +1,272 @@
+use std::path::Path;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use bytes::Bytes;
+use huginn_proxy_lib::config::{HandshakeCaptureConfig, HandshakeCaptureFormat};
+use huginn_proxy_lib::fingerprinting::Ja4Fingerprints;
+use huginn_proxy_lib::telemetry::{HandshakeCapture, HandshakeRecord};
+use tokio::io::AsyncWriteExt;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const REQWEST_CLIENT_HELLO: &[u8] =
+    include_bytes!("../../../benches/fixtures/clienthello_reqwest.bin");
+
+async fn fingerprints(
+    hello: &[u8],
+) -> Result<Ja4Fingerprints, Box<dyn std::error::Error + Send + Sync>> {
+    let (mut client, mut server) = tokio::io::duplex(4096);
+    client.write_all(hello).await?;
+    let (_, fingerprints) =
+        huginn_proxy_lib::read_client_hello(&mut server, huginn_proxy_lib::Metrics::new_noop())
+            .await?;
+    Ok(fingerprints.ok_or("ClientHello not parsed")?)
+}
+
+fn record(ja4: Option<Ja4Fingerprints>) -> Result<HandshakeRecord, std::net::AddrParseError> {
+    Ok(HandshakeRecord {
+        timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+        client: "203.0.113.7:51000".parse()?,
+        server: None,
+        client_hello: Bytes::from_static(REQWEST_CLIENT_HELLO),
+        ja4,
+        tcp_syn: None,
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Wait until the writer thread has flushed at least `len` bytes into `path`.
+fn wait_for_len(
+    path: &Path,
+    len: usize,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if let Ok(content) = std::fs::read(path) {
+            if content.len() >= len {
+                return Ok(content);
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Err(format!("{len} bytes never written to {}", path.display()).into())
+}
+
+fn u16_le(bytes: &[u8], at: usize) -> usize {
+    bytes
+        .get(at..at.saturating_add(2))
+        .and_then(|b| b.try_into().ok())
+        .map_or(0, |b| usize::from(u16::from_le_bytes(b)))
+}
+
+fn u32_le(bytes: &[u8], at: usize) -> usize {
+    bytes
+        .get(at..at.saturating_add(4))
+        .and_then(|b| b.try_into().ok())
+        .map_or(0, |b| usize::try_from(u32::from_le_bytes(b)).unwrap_or_default())
+}
+
+/// `(block type, body)` of every PCAPNG block in `file`.
+fn blocks(file: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut blocks = Vec::new();
+    let mut at = 0;
+    while at < file.len() {
+        let total = u32_le(file, at.saturating_add(4));
+        if total < 12 {
+            break;
+        }
+        let body = file
+            .get(at.saturating_add(8)..at.saturating_add(total).saturating_sub(4))
+            .unwrap_or_default();
+        blocks.push((u32_le(file, at), body));
+        at = at.saturating_add(total);
+    }
+    blocks
+}
+
+/// Internet checksum over `bytes`; zero when the embedded checksum is correct.
+fn ones_complement(bytes: &[u8]) -> u16 {
+    let mut sum = bytes.chunks(2).fold(0u32, |sum, word| {
+        let value = match word {
+            [high, low] => u16::from_be_bytes([*high, *low]),
+            [high] => u16::from_be_bytes([*high, 0]),
+            _ => 0,
+        };
+        sum.saturating_add(u32::from(value))
+    });
+    while sum > 0xFFFF {
+        sum = (sum % 0x1_0000).saturating_add(sum / 0x1_0000);
+    }
+    !u16::try_from(sum).unwrap_or(u16::MAX)
+}
+
+#[tokio::test]
+async fn json_record_holds_hello_and_fingerprints() -> TestResult {
+    let ja4 = fingerprints(REQWEST_CLIENT_HELLO).await?;
+    let expected_ja4 = ja4.ja4.full.to_string();
+    let value: serde_json::Value = serde_json::from_str(&record(Some(ja4))?.to_json())?;
+    assert_eq!(value["timestamp"], "2023-11-14T22:13:20.123Z");
+    assert_eq!(value["client"], "203.0.113.7:51000");
+    assert!(value["server"].is_null());
+    assert_eq!(value["ja4"], expected_ja4.as_str());
+    assert!(value["ja4_r"].is_string());
+    assert!(value["ja4_s1r"].is_string());
+    assert!(value["tcp_syn"].is_null());
+    assert_eq!(value["client_hello"], hex(REQWEST_CLIENT_HELLO).as_str());
+    Ok(())
+}
+
+#[test]
+fn record_keeps_only_the_first_tls_record() -> TestResult {
+    let mut prefix = REQWEST_CLIENT_HELLO.to_vec();
+    prefix.extend_from_slice(&[0x17, 0x03, 0x03, 0x00, 0x01, 0xff]);
+    let record =
+        HandshakeRecord::new("203.0.113.7:51000".parse()?, &Bytes::from(prefix), None, None);
+    assert_eq!(&record.client_hello[..], REQWEST_CLIENT_HELLO);
+    Ok(())
+}
+
+#[test]
+fn disabled_or_unsampled_capture_builds_nothing() -> TestResult {
+    let disabled = HandshakeCapture::from_config(&HandshakeCaptureConfig::default())?;
+    assert!(!disabled.is_enabled());
+    disabled.capture(|| panic!("disabled capture built a record"));
+
+    let dir = tempfile::tempdir()?;
+    let unsampled = HandshakeCapture::from_config(&HandshakeCaptureConfig {
+        enabled: true,
+        path: Some(dir.path().join("hello.jsonl")),
+        sample_rate: 0.0,
+        ..HandshakeCaptureConfig::default()
+    })?;
+    assert!(unsampled.is_enabled());
+    unsampled.capture(|| panic!("unsampled connection built a record"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn jsonl_file_gets_one_line_per_hello() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("hello.jsonl");
+    let capture = HandshakeCapture::from_config(&HandshakeCaptureConfig {
+        enabled: true,
+        path: Some(path.clone()),
+        ..HandshakeCaptureConfig::default()
+    })?
+    .with_server(Some("10.0.0.1:443".parse()?));
+    let record = record(Some(fingerprints(REQWEST_CLIENT_HELLO).await?))?;
+    capture.capture(|| record);
+
+    let content = wait_for_len(&path, REQWEST_CLIENT_HELLO.len().saturating_mul(2))?;
+    let content = String::from_utf8(content)?;
+    let line = content.strip_suffix('\n').ok_or("line not terminated")?;
+    let value: serde_json::Value = serde_json::from_str(line)?;
+    assert_eq!(value["server"], "10.0.0.1:443");
+    assert_eq!(value["client_hello"], hex(REQWEST_CLIENT_HELLO).as_str());
+    Ok(())
+}
+
+#[tokio::test]
+async fn pcapng_file_holds_a_tcp_segment_per_hello() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("hello.pcapng");
+    let capture = HandshakeCapture::from_config(&HandshakeCaptureConfig {
+        enabled: true,
+        format: HandshakeCaptureFormat::Pcapng,
+        path: Some(path.clone()),
+        // Every packet after the first rotates the file.
+        max_size_bytes: 1,
+        max_files: 1,
+        ..HandshakeCaptureConfig::default()
+    })?
+    .with_server(Some("10.0.0.1:8443".parse()?));
+    let ja4 = fingerprints(REQWEST_CLIENT_HELLO).await?;
+    let expected_ja4 = ja4.ja4.full.to_string();
+    let first = record(Some(ja4))?;
+    capture.capture(|| first);
+
+    let content = wait_for_len(&path, REQWEST_CLIENT_HELLO.len().saturating_add(40))?;
+    let parsed = blocks(&content);
+    let kinds: Vec<usize> = parsed.iter().map(|(kind, _)| *kind).collect();
+    assert_eq!(kinds, [0x0A0D_0D0A, 1, 6]);
+    let interface = parsed.get(1).map(|(_, body)| *body).unwrap_or_default();
+    assert_eq!(u16_le(interface, 0), 101, "LINKTYPE_RAW");
+
+    let packet_block = parsed.get(2).map(|(_, body)| *body).unwrap_or_default();
+    let captured = u32_le(packet_block, 12);
+    assert_eq!(captured, REQWEST_CLIENT_HELLO.len().saturating_add(40));
+    let packet = packet_block
+        .get(20..20usize.saturating_add(captured))
+        .ok_or("short block")?;
+    let ip = packet.get(..20).ok_or("short packet")?;
+    assert_eq!(ip.first(), Some(&0x45));
+    assert_eq!(ip.get(9), Some(&6), "TCP");
+    assert_eq!(ip.get(12..16), Some(&[203, 0, 113, 7][..]));
+    assert_eq!(ip.get(16..20), Some(&[10, 0, 0, 1][..]));
+    assert_eq!(ones_complement(ip), 0, "IPv4 header checksum");
+
+    let tcp = packet.get(20..).ok_or("short packet")?;
+    assert_eq!(tcp.get(..4), Some(&[0xc7, 0x38, 0x20, 0xfb][..]), "51000 -> 8443");
+    assert_eq!(tcp.get(20..), Some(REQWEST_CLIENT_HELLO));
+    let mut pseudo = vec![203, 0, 113, 7, 10, 0, 0, 1, 0, 6];
+    pseudo.extend_from_slice(&u16::try_from(tcp.len())?.to_be_bytes());
+    pseudo.extend_from_slice(tcp);
+    assert_eq!(ones_complement(&pseudo), 0, "TCP checksum");
+
+    let options = packet_block
+        .get(20usize.saturating_add(captured.next_multiple_of(4))..)
+        .ok_or("no options")?;
+    assert_eq!(u16_le(options, 0), 1, "opt_comment");
+    let comment = options
+        .get(4..4usize.saturating_add(u16_le(options, 2)))
+        .ok_or("short comment")?;
+    let value: serde_json::Value = serde_json::from_slice(comment)?;
+    assert_eq!(value["ja4"], expected_ja4.as_str());
+    assert!(value.get("client_hello").is_none());
+
+    // The rotated-in file starts with its own section header.
+    let size = content.len();
+    let second = record(None)?;
+    capture.capture(|| second);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !dir.path().join("hello.pcapng.1").exists() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(std::fs::read(dir.path().join("hello.pcapng.1"))?.len(), size);
+    let rotated = wait_for_len(&path, REQWEST_CLIENT_HELLO.len().saturating_add(40))?;
+    let kinds: Vec<usize> = blocks(&rotated).iter().map(|(kind, _)| *kind).collect();
+    assert_eq!(kinds, [0x0A0D_0D0A, 1, 6]);
+    Ok(())
+}
+
+#[test]
+fn config_validation() -> TestResult {
+    let valid = HandshakeCaptureConfig {
+        enabled: true,
+        path: Some("/var/log/huginn/hello.pcapng".into()),
+        ..HandshakeCaptureConfig::default()
+    };
+    assert!(valid.validate().is_ok());
+    assert!(HandshakeCaptureConfig { path: None, ..valid.clone() }
+        .validate()
+        .is_err());
+    assert!(HandshakeCaptureConfig { sample_rate: 1.5, ..valid.clone() }
+        .validate()
+        .is_err());
+    assert!(HandshakeCaptureConfig { max_files: 0, ..valid.clone() }
+        .validate()
+        .is_err());
+    assert!(HandshakeCaptureConfig { max_files: 0, max_size_bytes: 0, ..valid }
+        .validate()
+        .is_ok());
+
+    let parsed: HandshakeCaptureConfig = toml::from_str(
+        "enabled = true\nformat = \"pcapng\"\npath = \"hello.pcapng\"\nsample_rate = 0.01",
+    )?;
+    assert_eq!(parsed.format, HandshakeCaptureFormat::Pcapng);
+    assert!(toml::from_str::<HandshakeCaptureConfig>("format = \"pcap\"").is_err());
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use http::Request;
+use huginn_proxy_lib::config::{Config, ConfigParts, Domain};
+use huginn_proxy_lib::telemetry::{LogLevelError, LogLevels};
+use tracing::Level;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::Registry;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const CONFIG: &str = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "127.0.0.1:9001" }]
+
+[[domains]]
+host = "example.com"
+
+[[domains.routes]]
+prefix = "/"
+backend = "127.0.0.1:9001"
+
+[[domains.routes]]
+prefix = "/api"
+backend = "127.0.0.1:9001"
+"#;
+
+fn domains() -> Result<Vec<Domain>, Box<dyn std::error::Error + Send + Sync>> {
+    let config: Config = toml::from_str(CONFIG)?;
+    let ConfigParts { dynamic_cfg, .. } = config.into_parts();
+    Ok(dynamic_cfg.domains.as_ref().clone())
+}
+
+fn request(path: &str) -> Result<Request<()>, http::Error> {
+    Request::builder()
+        .uri(path)
+        .header("host", "example.com")
+        .body(())
+}
+
+#[test]
+fn set_filter_changes_the_global_level() -> TestResult {
+    let (layer, levels) = LogLevels::reloadable("warn".to_string(), None);
+    tracing::subscriber::with_default(Registry::default().with(layer), || -> TestResult {
+        assert!(!tracing::enabled!(Level::DEBUG));
+        levels.set_filter("debug")?;
+        assert!(tracing::enabled!(Level::DEBUG));
+        assert!(matches!(
+            levels.set_filter("debug,=="),
+            Err(LogLevelError::InvalidFilter { .. })
+        ));
+        assert_eq!(levels.snapshot().map(|s| s.filter), Some("debug".to_string()));
+        Ok(())
+    })
+}
+
+#[test]
+fn route_level_applies_only_inside_the_route_span() -> TestResult {
+    let domains = domains()?;
+    let (layer, levels) =
+        LogLevels::reloadable("warn".to_string(), Some("opentelemetry=warn".to_string()));
+    tracing::subscriber::with_default(Registry::default().with(layer), || -> TestResult {
+        assert!(levels
+            .request_span(&domains, None, &request("/api/users")?)
+            .is_none());
+
+        levels.set_route_level("example.com", "/api", Some("DEBUG"))?;
+        let span = levels.request_span(&domains, None, &request("/api/users")?);
+        assert!(!span.is_none());
+        assert!(span.in_scope(|| tracing::enabled!(Level::DEBUG)));
+        assert!(!span.in_scope(|| tracing::enabled!(Level::TRACE)));
+        assert!(!tracing::enabled!(Level::DEBUG));
+        assert!(levels
+            .request_span(&domains, None, &request("/other")?)
+            .is_none());
+
+        let routes = levels.snapshot().map(|s| s.routes).unwrap_or_default();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].level, "debug");
+
+        levels.set_route_level("example.com", "/api", None)?;
+        assert!(levels
+            .request_span(&domains, None, &request("/api/users")?)
+            .is_none());
+        Ok(())
+    })
+}
+
+#[test]
+fn route_level_rejects_bad_input() {
+    let (_layer, levels) = LogLevels::reloadable("info".to_string(), None);
+    assert!(matches!(
+        levels.set_route_level("example.com", "/api", Some("loud")),
+        Err(LogLevelError::InvalidLevel(_))
+    ));
+    assert!(matches!(
+        levels.set_route_level("example.com", "/a,b", Some("debug")),
+        Err(LogLevelError::InvalidRoute(_))
+    ));
+    assert!(matches!(
+        LogLevels::disabled().set_filter("debug"),
+        Err(LogLevelError::Unavailable)
+    ));
+}
@@ -0,0 +1,48 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use huginn_proxy_lib::config::{FingerprintStatsConfig, MetricsConfig};
+use huginn_proxy_lib::telemetry::{init_metrics, FingerprintStats};
+use prometheus::TextEncoder;
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[test]
+fn configured_buckets_and_label_cap_are_exported() -> TestResult {
+    let fingerprints = FingerprintStats::from_config(&FingerprintStatsConfig {
+        enabled: true,
+        ..FingerprintStatsConfig::default()
+    });
+    let (metrics, registry) = init_metrics(
+        &MetricsConfig { duration_buckets: vec![0.05, 0.5], max_label_values: 2 },
+        &fingerprints,
+    )?;
+    for route in ["/cap-a", "/cap-b", "/cap-c"] {
+        metrics.record_request_duration(
+            0.01,
+            "GET",
+            200,
+            "HTTP/1.1",
+            route,
+            "example.com",
+            "_none_",
+            None,
+        );
+    }
+    metrics.record_backend_ttfb(0.2, "127.0.0.1:9001", "/cap-a", "example.com");
+    metrics.record_backend_connect_duration(0.001, "127.0.0.1:9001");
+    fingerprints.observe(IpAddr::V4(Ipv4Addr::LOCALHOST), Some("t13d1516h2_aaa_bbb"), None);
+
+    let text = TextEncoder::new().encode_to_string(&registry.gather())?;
+    assert!(text.contains(r#"le="0.05""#));
+    assert!(text.contains(r#"le="0.5""#));
+    assert!(!text.contains(r#"le="75""#), "OTel default buckets still exported");
+    assert!(text.contains(r#"route="/cap-b""#));
+    assert!(!text.contains(r#"route="/cap-c""#));
+    assert!(text.contains(r#"route="other""#));
+    assert!(text.contains(r#"backend_address="none""#));
+    assert!(text.contains("huginn_backend_ttfb_seconds_bucket"));
+    assert!(text.contains("huginn_backend_connect_duration_seconds_bucket"));
+    assert!(text.contains("huginn_fingerprint_top_requests"));
+    assert!(text.contains(r#"fingerprint="t13d1516h2_aaa_bbb""#));
+    Ok(())
+}
@@ -0,0 +1,9 @@
+mod access_log;
+mod admin;
+mod anomaly;
+mod event_stream;
+mod fingerprint_stats;
+mod handshake_capture;
+mod log_levels;
+mod metrics;
+mod spans;
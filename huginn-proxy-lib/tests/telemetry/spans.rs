@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use http::{Request, Response, StatusCode};
+use huginn_proxy_lib::proxy::connection::ConnectionContext;
+use huginn_proxy_lib::telemetry::{AccessLogContext, AccessLogger, RequestLog, RequestTracer};
+use opentelemetry::trace::Status;
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+fn tracer() -> (RequestTracer, InMemorySpanExporter) {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    (RequestTracer::from_provider(provider), exporter)
+}
+
+fn traceparent<B>(req: &Request<B>) -> Option<&str> {
+    req.headers()
+        .get("traceparent")
+        .and_then(|v| v.to_str().ok())
+}
+
+#[test]
+fn start_continues_the_incoming_trace() -> TestResult {
+    let (tracer, _) = tracer();
+    let mut req = Request::builder()
+        .uri("/api")
+        .header("traceparent", PARENT)
+        .body(())?;
+    let _span = tracer.start(&mut req).ok_or("span not started")?;
+
+    let sent = traceparent(&req).ok_or("traceparent missing")?;
+    let parts: Vec<&str> = sent.split('-').collect();
+    assert_eq!(parts.len(), 4, "{sent}");
+    assert_eq!(parts[1], TRACE_ID);
+    assert_ne!(parts[2], "00f067aa0ba902b7", "backend sees the proxy span as parent");
+    assert_eq!(parts[3], "01");
+    Ok(())
+}
+
+#[test]
+fn start_without_trace_context_opens_a_new_trace() -> TestResult {
+    let (tracer, _) = tracer();
+    let mut req = Request::builder().uri("/").body(())?;
+    let _span = tracer.start(&mut req).ok_or("span not started")?;
+    let sent = traceparent(&req).ok_or("traceparent missing")?;
+    assert_eq!(sent.len(), 55, "{sent}");
+    assert!(!sent.contains(TRACE_ID));
+    Ok(())
+}
+
+#[test]
+fn disabled_tracer_leaves_headers_alone() -> TestResult {
+    let mut req = Request::builder()
+        .uri("/")
+        .header("traceparent", PARENT)
+        .body(())?;
+    assert!(RequestTracer::disabled().start(&mut req).is_none());
+    assert_eq!(traceparent(&req), Some(PARENT));
+    Ok(())
+}
+
+#[test]
+fn finished_request_exports_span_with_route_and_status() -> TestResult {
+    let (tracer, exporter) = tracer();
+    let connection = Arc::new(ConnectionContext::new("203.0.113.7:4000".parse()?));
+    let context = AccessLogContext::new(AccessLogger::disabled(), connection).with_tracer(tracer);
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/api/orders")
+        .header("host", "example.com")
+        .body(())?;
+    let pending = context.start(&mut req).ok_or("request not tracked")?;
+    let response = Response::builder()
+        .status(StatusCode::BAD_GATEWAY)
+        .body(())?;
+    pending.finish(
+        &response,
+        RequestLog {
+            tenant: None,
+            route: Some("/api".to_string()),
+            backend: Some("127.0.0.1:9001".to_string()),
+            sampling: None,
+        },
+    );
+
+    let spans = exporter.get_finished_spans()?;
+    assert_eq!(spans.len(), 1);
+    let span = &spans[0];
+    assert_eq!(span.name, "POST /api");
+    let attribute = |key: &str| {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.as_str().into_owned())
+    };
+    assert_eq!(attribute("http.route").as_deref(), Some("/api"));
+    assert_eq!(attribute("http.response.status_code").as_deref(), Some("502"));
+    assert_eq!(attribute("huginn.backend").as_deref(), Some("127.0.0.1:9001"));
+    assert_eq!(attribute("server.address").as_deref(), Some("example.com"));
+    assert_eq!(attribute("client.address").as_deref(), Some("203.0.113.7"));
+    assert!(matches!(span.status, Status::Error { .. }));
+    Ok(())
+}
@@ -101,3 +101,21 @@ fn test_client_auth_enum_default() {
     let default_auth = ClientAuth::default();
     assert!(matches!(default_auth, ClientAuth::Disabled));
 }
+
+#[test]
+fn test_mtls_optional_client_ca() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let ca_path = tmp_path("optional_ca.pem");
+    let ca_cert = rcgen::generate_simple_self_signed(vec!["ca.example.com".to_string()])?;
+    fs::write(&ca_path, ca_cert.cert.pem())?;
+    let result =
+        build_with(vec![], ClientAuth::Optional { ca_cert_path: ca_path.display().to_string() });
+    let _ = fs::remove_file(&ca_path);
+    assert!(result.is_ok(), "should succeed with optional client auth");
+
+    let missing = build_with(
+        vec![],
+        ClientAuth::Optional { ca_cert_path: "/nonexistent/ca.pem".to_string() },
+    );
+    assert!(missing.is_err(), "optional mode still requires a readable CA");
+    Ok(())
+}
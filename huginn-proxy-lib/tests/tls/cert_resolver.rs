@@ -11,7 +11,11 @@ fn domain(host: Option<&str>, cert: &std::path::Path, key: &std::path::Path) ->
         headers: None,
         security: None,
         fingerprinting: None,
+        ja4_variants: None,
+        fingerprint_format: None,
+        redirect: None,
         routes: vec![],
+        tenant: None,
     }
 }
 
@@ -16,7 +16,9 @@ async fn build_tls_acceptor_produces_usable_acceptor(
         alpn: vec![],
         options: TlsOptions::default(),
         client_auth: ClientAuth::Disabled,
+        client_cert: Default::default(),
         session_resumption: Default::default(),
+        ocsp: Default::default(),
     };
 
     let acceptor = build_tls_acceptor(&config, Arc::new(DynamicCertResolver::new(false))).await?;
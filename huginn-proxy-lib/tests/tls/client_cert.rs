@@ -0,0 +1,54 @@
+use huginn_proxy_lib::tls::ClientCertInfo;
+use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair};
+
+fn cert_der(dn: DistinguishedName) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut params = CertificateParams::new(vec!["client.example.com".to_string()])?;
+    params.distinguished_name = dn;
+    let key = KeyPair::generate()?;
+    Ok(params.self_signed(&key)?.der().to_vec())
+}
+
+#[test]
+fn subject_is_rendered_most_specific_first() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let mut dn = DistinguishedName::new();
+    dn.push(DnType::CountryName, "AR");
+    dn.push(DnType::OrganizationName, "Acme");
+    dn.push(DnType::CommonName, "client");
+    let info = ClientCertInfo::from_der(&cert_der(dn)?).ok_or("certificate should parse")?;
+    assert_eq!(info.subject, "CN=client,O=Acme,C=AR");
+    Ok(())
+}
+
+#[test]
+fn subject_special_characters_are_escaped() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let mut dn = DistinguishedName::new();
+    dn.push(DnType::OrganizationName, "Acme, Inc.");
+    dn.push(DnType::CommonName, "#svc+1 ");
+    let info = ClientCertInfo::from_der(&cert_der(dn)?).ok_or("certificate should parse")?;
+    assert_eq!(info.subject, "CN=\\#svc\\+1\\ ,O=Acme\\, Inc.");
+    Ok(())
+}
+
+#[test]
+fn sha256_is_lowercase_hex_of_der() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut dn = DistinguishedName::new();
+    dn.push(DnType::CommonName, "client");
+    let der = cert_der(dn)?;
+    let info = ClientCertInfo::from_der(&der).ok_or("certificate should parse")?;
+    assert_eq!(info.sha256.len(), 64);
+    assert!(info
+        .sha256
+        .chars()
+        .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+    assert_eq!(ClientCertInfo::from_der(&der), Some(info));
+    Ok(())
+}
+
+#[test]
+fn malformed_der_is_rejected() {
+    assert_eq!(ClientCertInfo::from_der(&[]), None);
+    assert_eq!(ClientCertInfo::from_der(b"not a certificate"), None);
+    assert_eq!(ClientCertInfo::from_der(&[0x30, 0x82, 0xff, 0xff]), None);
+}
@@ -2,6 +2,8 @@ mod acceptor;
 mod cert_resolver;
 mod cert_source;
 mod cipher_curve_signature;
+mod client_cert;
+mod ocsp;
 mod options;
 mod session_resumption;
 
@@ -0,0 +1,366 @@
+//! OCSP stapling: request encoding from a certificate chain, response checks, and
+//! `OcspStapler` against a fake responder on 127.0.0.1.
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use huginn_proxy_lib::config::{Domain, MustStapleFailure, OcspConfig};
+use huginn_proxy_lib::telemetry::Metrics;
+use huginn_proxy_lib::tls::ocsp::message::OcspTarget;
+use huginn_proxy_lib::tls::ocsp::{is_must_staple, OcspError};
+use huginn_proxy_lib::tls::{DynamicCertResolver, OcspStapler};
+use rcgen::{BasicConstraints, CertificateParams, CustomExtension, IsCa, KeyPair, SerialNumber};
+use rustls_pki_types::CertificateDer;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::helpers::{ensure_crypto_provider, tmp_path};
+
+type TestResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+const SERIAL: [u8; 4] = [0x12, 0x34, 0x56, 0x78];
+const OID_AD_OCSP: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01];
+const OID_OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+const OID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+const GOOD: &[u8] = &[0x80, 0x00];
+const REVOKED: &[u8] = &[
+    0xa1, 0x11, 0x18, 0x0f, 0x32, 0x30, 0x32, 0x35, 0x30, 0x31, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30,
+    0x30, 0x30, 0x5a,
+];
+const UNKNOWN: &[u8] = &[0x82, 0x00];
+
+/// 2026-01-01T00:00:00Z and 2026-01-08T00:00:00Z.
+const THIS_UPDATE: &str = "20260101000000Z";
+const NEXT_UPDATE: &str = "20260108000000Z";
+
+fn at(secs: u64) -> SystemTime {
+    UNIX_EPOCH
+        .checked_add(Duration::from_secs(secs))
+        .unwrap_or(UNIX_EPOCH)
+}
+
+fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match u8::try_from(contents.len()) {
+        Ok(len) if len < 0x80 => out.push(len),
+        _ => {
+            let len = u16::try_from(contents.len()).unwrap_or(u16::MAX);
+            out.push(0x82);
+            out.extend_from_slice(&len.to_be_bytes());
+        }
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+fn seq(parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x30, &parts.concat())
+}
+
+/// A basic `OCSPResponse` with one `SingleResponse`; the signature is not meaningful.
+fn ocsp_response(
+    serial: &[u8],
+    status: &[u8],
+    this_update: &str,
+    next_update: Option<&str>,
+) -> Vec<u8> {
+    let cert_id = seq(&[
+        seq(&[tlv(0x06, OID_SHA1), tlv(0x05, &[])]),
+        tlv(0x04, &[0; 20]),
+        tlv(0x04, &[0; 20]),
+        tlv(0x02, serial),
+    ]);
+    let mut single = vec![cert_id, status.to_vec(), tlv(0x18, this_update.as_bytes())];
+    if let Some(next_update) = next_update {
+        single.push(tlv(0xa0, &tlv(0x18, next_update.as_bytes())));
+    }
+    let data = seq(&[
+        tlv(0xa2, &tlv(0x04, &[0; 20])),
+        tlv(0x18, this_update.as_bytes()),
+        seq(&[seq(&single)]),
+    ]);
+    let basic = seq(&[data, seq(&[tlv(0x06, OID_SHA1)]), tlv(0x03, &[0, 1, 2, 3])]);
+    seq(&[
+        tlv(0x0a, &[0]),
+        tlv(0xa0, &seq(&[tlv(0x06, OID_OCSP_BASIC), tlv(0x04, &basic)])),
+    ])
+}
+
+/// Leaf for `localhost` naming `responder`, followed by an (unrelated, self-signed) issuer.
+/// Returns the chain as DER and as PEM, and the leaf's key as PEM.
+fn chain(
+    responder: &str,
+    must_staple: bool,
+) -> Result<(Vec<CertificateDer<'static>>, String, String), Box<dyn std::error::Error + Send + Sync>>
+{
+    let aia = seq(&[seq(&[tlv(0x06, OID_AD_OCSP), tlv(0x86, responder.as_bytes())])]);
+    let mut leaf = CertificateParams::new(vec!["localhost".to_string()])?;
+    leaf.serial_number = Some(SerialNumber::from_slice(&SERIAL));
+    leaf.custom_extensions
+        .push(CustomExtension::from_oid_content(&[1, 3, 6, 1, 5, 5, 7, 1, 1], aia));
+    if must_staple {
+        leaf.custom_extensions
+            .push(CustomExtension::from_oid_content(
+                &[1, 3, 6, 1, 5, 5, 7, 1, 24],
+                seq(&[tlv(0x02, &[5])]),
+            ));
+    }
+    let leaf_key = KeyPair::generate()?;
+    let leaf = leaf.self_signed(&leaf_key)?;
+
+    let mut issuer = CertificateParams::new(Vec::<String>::new())?;
+    issuer.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let issuer = issuer.self_signed(&KeyPair::generate()?)?;
+
+    let pem = format!("{}{}", leaf.pem(), issuer.pem());
+    let chain = vec![
+        CertificateDer::from(leaf.der().to_vec()),
+        CertificateDer::from(issuer.der().to_vec()),
+    ];
+    Ok((chain, pem, leaf_key.serialize_pem()))
+}
+
+fn domain(cert: &std::path::Path, key: &std::path::Path) -> Domain {
+    Domain {
+        host: Some("localhost".to_string()),
+        cert_path: Some(cert.display().to_string()),
+        key_path: Some(key.display().to_string()),
+        headers: None,
+        security: None,
+        fingerprinting: None,
+        ja4_variants: None,
+        fingerprint_format: None,
+        redirect: None,
+        routes: vec![],
+        tenant: None,
+    }
+}
+
+/// Answer every POST with `body`; returns the address and the request bodies received.
+async fn fake_responder(
+    body: Vec<u8>,
+) -> Result<(SocketAddr, Arc<Mutex<Vec<Vec<u8>>>>), std::io::Error> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&requests);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 4096];
+            // Read the head, then as much body as Content-Length announces.
+            let request_body = loop {
+                let Ok(n) = stream.read(&mut chunk).await else {
+                    break None;
+                };
+                if n == 0 {
+                    break None;
+                }
+                buf.extend_from_slice(chunk.get(..n).unwrap_or_default());
+                let text = String::from_utf8_lossy(&buf).to_string();
+                let Some(head_end) = text.find("\r\n\r\n") else {
+                    continue;
+                };
+                let length = text
+                    .lines()
+                    .find_map(|l| {
+                        l.to_ascii_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().to_string())
+                    })
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or_default();
+                let start = head_end.saturating_add(4);
+                if buf.len() >= start.saturating_add(length) {
+                    break buf
+                        .get(start..start.saturating_add(length))
+                        .map(<[u8]>::to_vec);
+                }
+            };
+            if let Some(request_body) = request_body {
+                seen.lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(request_body);
+            }
+            let head = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/ocsp-response\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes()).await;
+            let _ = stream.write_all(&body).await;
+        }
+    });
+    Ok((addr, requests))
+}
+
+#[test]
+fn requests_are_built_from_the_leaf_and_its_issuer() -> TestResult {
+    ensure_crypto_provider();
+    let (certs, _, _) = chain("http://ocsp.example.test/", true)?;
+    let target = OcspTarget::from_chain(&certs)?;
+    assert_eq!(target.responder, "http://ocsp.example.test/");
+    assert!(target.must_staple);
+    assert_eq!(target.request.first(), Some(&0x30));
+    assert!(target.request.windows(SERIAL.len()).any(|w| w == SERIAL));
+    assert!(target
+        .request
+        .windows(OID_SHA1.len())
+        .any(|w| w == OID_SHA1));
+    assert!(certs.first().is_some_and(|leaf| is_must_staple(leaf)));
+
+    let (plain, _, _) = chain("http://ocsp.example.test/", false)?;
+    assert!(!OcspTarget::from_chain(&plain)?.must_staple);
+    assert!(plain.first().is_some_and(|leaf| !is_must_staple(leaf)));
+
+    let leaf_only = certs.get(..1).unwrap_or_default();
+    assert!(matches!(OcspTarget::from_chain(leaf_only), Err(OcspError::NoIssuer)));
+    let (https, _, _) = chain("https://ocsp.example.test/", false)?;
+    assert!(matches!(
+        OcspTarget::from_chain(&https),
+        Err(OcspError::UnsupportedResponder(_))
+    ));
+    let (self_signed, _) = crate::helpers::generate_valid_test_cert_der()?;
+    assert!(matches!(
+        OcspTarget::from_chain(&[self_signed.clone(), self_signed]),
+        Err(OcspError::NoResponder)
+    ));
+    Ok(())
+}
+
+#[test]
+fn responses_are_checked_for_status_and_freshness() -> TestResult {
+    ensure_crypto_provider();
+    let (certs, _, _) = chain("http://ocsp.example.test/", false)?;
+    let target = OcspTarget::from_chain(&certs)?;
+    let during = at(1_767_528_000);
+
+    let good = ocsp_response(&SERIAL, GOOD, THIS_UPDATE, Some(NEXT_UPDATE));
+    let response = target.parse_response(&good, during)?;
+    assert_eq!(response.der, good);
+    assert_eq!(response.this_update, at(1_767_225_600));
+    assert_eq!(response.next_update, at(1_767_830_400));
+    assert_eq!(response.refresh_at(), during);
+    assert_eq!(response.age(during), Duration::from_secs(302_400));
+    assert!(!response.is_expired(during));
+    assert!(response.is_expired(at(1_767_830_400)));
+
+    let cases = [
+        (
+            ocsp_response(&SERIAL, REVOKED, THIS_UPDATE, Some(NEXT_UPDATE)),
+            during,
+            "revoked",
+        ),
+        (
+            ocsp_response(&SERIAL, UNKNOWN, THIS_UPDATE, Some(NEXT_UPDATE)),
+            during,
+            "unknown",
+        ),
+        (
+            ocsp_response(&[0x01], GOOD, THIS_UPDATE, Some(NEXT_UPDATE)),
+            during,
+            "other serial",
+        ),
+        (ocsp_response(&SERIAL, GOOD, THIS_UPDATE, None), during, "no nextUpdate"),
+        (good.clone(), at(1_767_830_400), "expired"),
+        (good.clone(), at(1_767_225_000), "not yet valid"),
+        (seq(&[tlv(0x0a, &[6])]), during, "unauthorized"),
+        (
+            good.get(..good.len().saturating_sub(1))
+                .unwrap_or_default()
+                .to_vec(),
+            during,
+            "truncated",
+        ),
+    ];
+    for (der, now, case) in cases {
+        assert!(target.parse_response(&der, now).is_err(), "{case} must be refused");
+    }
+    // Within the allowed clock skew.
+    assert!(target.parse_response(&good, at(1_767_225_500)).is_ok());
+    Ok(())
+}
+
+#[tokio::test]
+async fn stapler_staples_responses_and_keeps_them_across_reloads() -> TestResult {
+    ensure_crypto_provider();
+    let good = ocsp_response(&SERIAL, GOOD, "20200101000000Z", Some("20991231000000Z"));
+    let (responder, requests) = fake_responder(good.clone()).await?;
+    let (certs, pem, key) = chain(&format!("http://{responder}/"), false)?;
+    let (cert_path, key_path) = (tmp_path("ocsp.crt"), tmp_path("ocsp.key"));
+    std::fs::write(&cert_path, pem)?;
+    std::fs::write(&key_path, key)?;
+    let domains = vec![domain(&cert_path, &key_path)];
+
+    let metrics = Metrics::new_noop();
+    let resolver = Arc::new(DynamicCertResolver::new(false));
+    resolver.update(&domains, &metrics).await;
+    assert_eq!(resolver.staple_for(Some("localhost")), None);
+
+    let config = OcspConfig { enabled: true, ..OcspConfig::default() };
+    let mut stapler = OcspStapler::new(Arc::clone(&resolver), config, Arc::clone(&metrics));
+    let now = SystemTime::now();
+    let next = stapler.refresh(now).await;
+    assert_eq!(resolver.staple_for(Some("localhost")), Some(good.clone()));
+    let expected = OcspTarget::from_chain(&certs)?.request;
+    assert_eq!(*requests.lock().unwrap_or_else(|e| e.into_inner()), vec![expected]);
+    // Nothing is due before the next pass.
+    assert!(next > now);
+    stapler.refresh(now).await;
+    assert_eq!(requests.lock().unwrap_or_else(|e| e.into_inner()).len(), 1);
+
+    // A reload that keeps the chain keeps the staple.
+    resolver.update(&domains, &metrics).await;
+    assert_eq!(resolver.staple_for(Some("localhost")), Some(good));
+
+    let _ = std::fs::remove_file(&cert_path);
+    let _ = std::fs::remove_file(&key_path);
+    Ok(())
+}
+
+#[tokio::test]
+async fn must_staple_certificates_can_be_withheld_until_stapled() -> TestResult {
+    ensure_crypto_provider();
+    let (certs, pem, key) = chain("http://127.0.0.1:9/", true)?;
+    let (cert_path, key_path) = (tmp_path("must-staple.crt"), tmp_path("must-staple.key"));
+    std::fs::write(&cert_path, pem)?;
+    std::fs::write(&key_path, key)?;
+    let domains = vec![domain(&cert_path, &key_path)];
+    let metrics = Metrics::new_noop();
+
+    let lenient = DynamicCertResolver::new(false);
+    lenient.update(&domains, &metrics).await;
+    assert!(lenient.resolves_for(Some("localhost")));
+
+    let strict =
+        DynamicCertResolver::new(false).with_must_staple_failure(MustStapleFailure::Reject);
+    strict.update(&domains, &metrics).await;
+    assert!(!strict.resolves_for(Some("localhost")));
+
+    let hash = huginn_proxy_lib::tls::cert_chain_hash(&certs);
+    strict.set_staple(hash, Some(vec![0x30, 0x00]));
+    assert!(strict.resolves_for(Some("localhost")));
+    strict.set_staple(hash, None);
+    assert!(!strict.resolves_for(Some("localhost")));
+
+    let _ = std::fs::remove_file(&cert_path);
+    let _ = std::fs::remove_file(&key_path);
+    Ok(())
+}
+
+#[test]
+fn ocsp_config_defaults_and_validation() -> TestResult {
+    let config = OcspConfig::default();
+    assert!(!config.enabled);
+    assert_eq!(config.must_staple_failure, MustStapleFailure::Serve);
+    config.validate()?;
+
+    let parsed: OcspConfig = toml::from_str("enabled = true\nmust_staple_failure = \"reject\"")?;
+    assert_eq!(parsed.must_staple_failure, MustStapleFailure::Reject);
+    for bad in ["timeout_ms = 0", "retry_secs = 0", "retry_secs = 86401"] {
+        let config: OcspConfig = toml::from_str(bad)?;
+        assert!(config.validate().is_err(), "{bad} should be rejected");
+    }
+    assert!(toml::from_str::<OcspConfig>("must_staple_failure = \"warn\"").is_err());
+    Ok(())
+}
@@ -170,3 +170,64 @@ fn test_tls_options_default_values() {
         "Default curve_preferences should contain all supported curves"
     );
 }
+
+#[test]
+fn test_reject_ech_without_sni_is_opt_in() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    assert!(!TlsOptions::default().reject_ech_without_sni);
+    let options: TlsOptions = toml::from_str("reject_ech_without_sni = true")?;
+    assert!(options.reject_ech_without_sni);
+    assert!(validate_tls_options(&options).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_effective_versions() {
+    use TlsVersion::{V1_2, V1_3};
+
+    assert_eq!(TlsOptions::default().effective_versions(), vec![V1_2, V1_3]);
+    let only_13 = TlsOptions { versions: vec![V1_3], ..Default::default() };
+    assert_eq!(only_13.effective_versions(), vec![V1_3]);
+    // A range narrows the default list and is not a conflict.
+    let min_13 = TlsOptions { min_version: Some(V1_3), ..Default::default() };
+    assert_eq!(min_13.effective_versions(), vec![V1_3]);
+    assert!(validate_tls_options(&min_13).is_ok());
+    let max_12 = TlsOptions { versions: vec![], max_version: Some(V1_2), ..Default::default() };
+    assert_eq!(max_12.effective_versions(), vec![V1_2]);
+}
+
+#[test]
+fn test_cipher_suites_must_fit_enabled_versions() {
+    let tls12_only: Vec<String> = supported_cipher_suites()
+        .iter()
+        .filter(|s| !s.starts_with("TLS13_"))
+        .map(|s| s.to_string())
+        .collect();
+    let options = TlsOptions {
+        versions: vec![TlsVersion::V1_3],
+        cipher_suites: tls12_only.clone(),
+        ..Default::default()
+    };
+    assert!(validate_tls_options(&options).is_err());
+    let options = TlsOptions {
+        versions: vec![TlsVersion::V1_2],
+        cipher_suites: tls12_only,
+        ..Default::default()
+    };
+    assert!(validate_tls_options(&options).is_ok());
+}
+
+#[test]
+fn test_versions_and_groups_are_applied() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let options = TlsOptions {
+        versions: vec![TlsVersion::V1_3],
+        curve_preferences: vec!["X25519".to_string(), "X25519MLKEM768".to_string()],
+        ..Default::default()
+    };
+    super::build_acceptor(&[], &options, &huginn_proxy_lib::config::ClientAuth::Disabled)?;
+
+    // Offered by no provider this proxy builds with.
+    let options =
+        TlsOptions { curve_preferences: vec!["secp521r1".to_string()], ..Default::default() };
+    assert!(validate_tls_options(&options).is_err());
+    Ok(())
+}
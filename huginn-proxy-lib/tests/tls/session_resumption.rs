@@ -7,7 +7,9 @@ fn test_session_resumption_enabled_default() {
         alpn: vec![],
         options: Default::default(),
         client_auth: ClientAuth::Disabled,
+        client_cert: Default::default(),
         session_resumption: Default::default(),
+        ocsp: Default::default(),
     };
     assert!(config.session_resumption.enabled);
 }
@@ -18,7 +20,13 @@ fn test_session_resumption_disabled() {
         alpn: vec![],
         options: Default::default(),
         client_auth: ClientAuth::Disabled,
-        session_resumption: SessionResumptionConfig { enabled: false, max_sessions: 256 },
+        client_cert: Default::default(),
+        session_resumption: SessionResumptionConfig {
+            enabled: false,
+            max_sessions: 256,
+            ticket_keys: None,
+        },
+        ocsp: Default::default(),
     };
     assert!(!config.session_resumption.enabled);
 }
@@ -29,7 +37,13 @@ fn test_session_resumption_custom_cache_size() {
         alpn: vec![],
         options: Default::default(),
         client_auth: ClientAuth::Disabled,
-        session_resumption: SessionResumptionConfig { enabled: true, max_sessions: 512 },
+        client_cert: Default::default(),
+        session_resumption: SessionResumptionConfig {
+            enabled: true,
+            max_sessions: 512,
+            ticket_keys: None,
+        },
+        ocsp: Default::default(),
     };
     assert_eq!(config.session_resumption.max_sessions, 512);
 }
@@ -71,7 +85,8 @@ fn test_session_storage_configuration() -> Result<(), Box<dyn std::error::Error
         .with_no_client_auth()
         .with_single_cert(vec![cert.clone()], key.clone_key())?;
 
-    let config_enabled = SessionResumptionConfig { enabled: true, max_sessions: 512 };
+    let config_enabled =
+        SessionResumptionConfig { enabled: true, max_sessions: 512, ticket_keys: None };
 
     configure_session_resumption(&mut server, &config_enabled);
 
@@ -79,7 +94,8 @@ fn test_session_storage_configuration() -> Result<(), Box<dyn std::error::Error
         .with_no_client_auth()
         .with_single_cert(vec![cert], key)?;
 
-    let config_disabled = SessionResumptionConfig { enabled: false, max_sessions: 256 };
+    let config_disabled =
+        SessionResumptionConfig { enabled: false, max_sessions: 256, ticket_keys: None };
 
     configure_session_resumption(&mut server_disabled, &config_disabled);
 
@@ -104,7 +120,8 @@ fn test_ticket_producer_configuration() -> Result<(), Box<dyn std::error::Error
     // Check default ticketer state before configuration
     let default_ticketer_enabled = server.ticketer.enabled();
 
-    let config_enabled = SessionResumptionConfig { enabled: true, max_sessions: 256 };
+    let config_enabled =
+        SessionResumptionConfig { enabled: true, max_sessions: 256, ticket_keys: None };
 
     configure_session_resumption(&mut server, &config_enabled);
 
@@ -116,7 +133,8 @@ fn test_ticket_producer_configuration() -> Result<(), Box<dyn std::error::Error
         .with_no_client_auth()
         .with_single_cert(vec![cert], key)?;
 
-    let config_disabled = SessionResumptionConfig { enabled: false, max_sessions: 256 };
+    let config_disabled =
+        SessionResumptionConfig { enabled: false, max_sessions: 256, ticket_keys: None };
 
     configure_session_resumption(&mut server_disabled, &config_disabled);
 
@@ -125,3 +143,76 @@ fn test_ticket_producer_configuration() -> Result<(), Box<dyn std::error::Error
     assert_eq!(server_disabled.ticketer.lifetime(), 0);
     Ok(())
 }
+
+#[test]
+fn test_shared_ticket_keys_resume_across_instances(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use huginn_proxy_lib::tls::SharedTicketer;
+
+    let secret = b"0123456789abcdef0123456789abcdef";
+    let issuer = SharedTicketer::new(secret, 3600);
+    let replica = SharedTicketer::new(secret, 3600);
+    let now = 1_800_000_000;
+
+    let ticket = issuer
+        .encrypt_at(b"session state", now)
+        .ok_or("encrypt failed")?;
+    assert_eq!(replica.decrypt_at(&ticket, now).as_deref(), Some(&b"session state"[..]));
+    // The previous period's key is still accepted after a rotation, older ones are not.
+    assert!(replica
+        .decrypt_at(&ticket, now.saturating_add(3600))
+        .is_some());
+    assert!(replica
+        .decrypt_at(&ticket, now.saturating_add(7200))
+        .is_none());
+
+    // Another secret, or a ticket whose period was rewritten, does not decrypt.
+    let other = SharedTicketer::new(b"fedcba9876543210fedcba9876543210", 3600);
+    assert!(other.decrypt_at(&ticket, now).is_none());
+    let mut tampered = ticket.clone();
+    if let Some(byte) = tampered.get_mut(7) {
+        *byte ^= 1;
+    }
+    assert!(replica.decrypt_at(&tampered, now).is_none());
+    assert!(replica
+        .decrypt_at(ticket.get(..10).unwrap_or_default(), now)
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn test_ticket_keys_config() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use huginn_proxy_lib::tls::session_resumption::configure_ticket_keys;
+    use std::io::Write;
+    use tokio_rustls::rustls::ServerConfig;
+
+    let mut secret_file = tempfile::NamedTempFile::new()?;
+    writeln!(secret_file, "0123456789abcdef0123456789abcdef")?;
+    let toml_str = format!(
+        "[ticket_keys]\nsecret_file = \"{}\"\nrotation_secs = 600\n",
+        secret_file.path().display()
+    );
+    let config: SessionResumptionConfig = toml::from_str(&toml_str)?;
+    config.validate()?;
+    let keys = config.ticket_keys.as_ref().ok_or("ticket_keys missing")?;
+    assert_eq!(keys.load_secret()?.len(), 32);
+
+    let (cert, key) = generate_valid_test_cert_der()?;
+    let mut server = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert], key)?;
+    configure_ticket_keys(&mut server, &config)?;
+    assert!(server.ticketer.enabled());
+    assert_eq!(server.ticketer.lifetime(), 600);
+
+    for bad in [
+        "[ticket_keys]\nrotation_secs = 600",
+        "[ticket_keys]\nsecret = \"too short\"",
+        "[ticket_keys]\nsecret = \"0123456789abcdef0123456789abcdef\"\nsecret_file = \"/k\"",
+        "[ticket_keys]\nsecret = \"0123456789abcdef0123456789abcdef\"\nrotation_secs = 10",
+    ] {
+        let config: SessionResumptionConfig = toml::from_str(bad)?;
+        assert!(config.validate().is_err(), "{bad} should be rejected");
+    }
+    Ok(())
+}
@@ -9,13 +9,16 @@ use std::sync::Arc;
 use arc_swap::ArcSwap;
 use clap::Parser;
 use huginn_proxy::ebpf;
-use huginn_proxy_lib::config::load_from_path;
+use huginn_proxy_lib::config::{load_from_path, TcpCapture};
+use huginn_proxy_lib::fingerprinting::syn_capture::raw_socket_probe;
+use huginn_proxy_lib::proxy::connection::ConnectionRegistry;
 use huginn_proxy_lib::proxy::shutdown::{shutdown_channel, ServiceHandle, ServiceName};
-use huginn_proxy_lib::run;
 use huginn_proxy_lib::telemetry::{
-    init_metrics, init_tracing_with_otel, shutdown_tracing, start_observability_server, Readiness,
+    init_metrics, init_tracing_with_otel, shutdown_tracing, start_observability_server, AdminState,
+    FingerprintStats, Readiness,
 };
 use huginn_proxy_lib::WatchOptions;
+use huginn_proxy_lib::{run, HealthRegistry, RuntimeHandles};
 use tokio::time::Duration;
 use tracing::info;
 
@@ -31,10 +34,12 @@ HTTP/2 Akamai, TCP SYN), TLS termination, hot-reload config, and Prometheus metr
     after_help = "EXAMPLES:\n  \
 huginn-proxy config.toml                              Start the proxy\n  \
 huginn-proxy --validate config.toml                  Validate the config, then exit\n  \
+huginn-proxy --check config.toml                     Validate and print the effective config, then exit\n  \
 huginn-proxy --validate --strict config.toml         Validate and fail on any warning\n  \
 huginn-proxy --print-effective-config config.toml    Print the effective, secret-redacted config as JSON\n\n\
 ENVIRONMENT:\n  \
 HUGINN_CONFIG_PATH   Config file path (alternative to the CONFIG argument)\n  \
+HUGINN_SECRETS_FILE  NAME=value file for ${NAME} references in the config (after the environment)\n  \
 RUST_LOG             Override the log level at runtime (e.g. RUST_LOG=debug)\n\n\
 Filesystem hot reload is configured in the [reload] section of the config file (watch on by default).\n\
 Run --help to see all options."
@@ -52,7 +57,13 @@ struct Cli {
     #[arg(long)]
     print_effective_config: bool,
 
-    /// With --validate/--print-effective-config, exit non-zero if any config warnings are found
+    /// Validate and print the effective config without starting the listener; a summary and any
+    /// warnings go to stderr, the JSON to stdout
+    #[arg(long)]
+    check: bool,
+
+    /// With --validate/--print-effective-config/--check, exit non-zero if any config warnings are
+    /// found
     #[arg(long)]
     strict: bool,
 }
@@ -60,10 +71,23 @@ struct Cli {
 #[tokio::main]
 async fn main() -> Result<(), BoxError> {
     let cli = Cli::parse();
-    let validation_mode = cli.validate || cli.print_effective_config;
+    let output = if cli.check {
+        Some(validation::Output::Check)
+    } else if cli.print_effective_config {
+        Some(validation::Output::EffectiveConfig)
+    } else if cli.validate {
+        Some(validation::Output::Summary)
+    } else {
+        None
+    };
 
-    if validation_mode {
-        return validation::run(&cli.config_path, cli.print_effective_config, cli.strict);
+    if let Some(output) = output {
+        // Printed with `Display`: config errors span several lines (source snippet, hints).
+        if let Err(e) = validation::run(&cli.config_path, output, cli.strict) {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
     }
 
     let config = load_from_path(&cli.config_path)?;
@@ -72,7 +96,7 @@ async fn main() -> Result<(), BoxError> {
     // RUST_LOG environment variable can override at runtime (e.g., docker run -e RUST_LOG=debug)
     let log_level = env::var("RUST_LOG").unwrap_or_else(|_| config.logging.level.clone());
 
-    init_tracing_with_otel(
+    let log_levels = init_tracing_with_otel(
         log_level,
         config.logging.show_target,
         config.telemetry.otel_log_level.clone(),
@@ -85,18 +109,49 @@ async fn main() -> Result<(), BoxError> {
     // Single shutdown channel shared by all background tasks
     let (shutdown_tx, shutdown_rx) = shutdown_channel();
 
+    // Request counts per JA4 / Akamai fingerprint, exported through the metrics registry.
+    let fingerprint_stats = FingerprintStats::from_config(&static_cfg.telemetry.fingerprint_stats);
+
     // metrics (Arc<Metrics>) is kept alive for run(); only registry is moved into the spawn.
-    let (metrics, registry) =
-        init_metrics().map_err(|e| format!("Failed to initialize metrics: {e}"))?;
+    let (metrics, registry) = init_metrics(&static_cfg.telemetry.metrics, &fingerprint_stats)
+        .map_err(|e| format!("Failed to initialize metrics: {e}"))?;
 
     // Readiness shared between the proxy and the observability server's `/ready` endpoint.
     // Not-ready until the proxy listeners are accepting; not-ready again on shutdown.
     let readiness = Readiness::new();
 
+    // Backend health and drain state shared between the proxy's health-check supervisor, the
+    // observability server's `/health/backends` endpoint and the admin API.
+    let health_registry = Arc::new(HealthRegistry::new().with_metrics(Arc::clone(&metrics)));
+
+    // Live proxy state the admin API reads and mutates. Connections are only tracked when the
+    // admin API is served.
+    let admin_enabled = static_cfg.telemetry.admin.enabled;
+    let runtime = RuntimeHandles {
+        connections: if admin_enabled {
+            ConnectionRegistry::new()
+        } else {
+            ConnectionRegistry::disabled()
+        },
+        log_levels,
+        fingerprint_stats,
+        ..RuntimeHandles::default()
+    };
+
     let metrics_service: Option<ServiceHandle> =
         if let Some(metrics_port) = static_cfg.telemetry.metrics_port {
             info!(port = metrics_port, "Metrics initialized, starting observability server");
             let readiness_for_observability = readiness.clone();
+            let health_for_observability = Arc::clone(&health_registry);
+            let fingerprints_for_observability = runtime.fingerprint_stats.clone();
+            let admin = admin_enabled.then(|| {
+                AdminState::new(
+                    Arc::clone(&static_cfg),
+                    Arc::clone(&dynamic_cfg),
+                    Arc::clone(&health_registry),
+                    runtime.clone(),
+                )
+            });
             let mut metrics_shutdown = shutdown_rx.clone();
             let handle = tokio::spawn(async move {
                 tokio::select! {
@@ -108,6 +163,9 @@ async fn main() -> Result<(), BoxError> {
                         metrics_port,
                         registry,
                         readiness_for_observability,
+                        health_for_observability,
+                        fingerprints_for_observability,
+                        admin,
                     ) => {
                         if let Err(e) = result {
                             tracing::error!(error = %e, "Observability server error");
@@ -122,8 +180,12 @@ async fn main() -> Result<(), BoxError> {
             None
         };
 
-    let (syn_probe, ebpf_reconnect_service) =
-        ebpf::connect_syn_probe(&static_cfg, Arc::clone(&metrics), shutdown_rx.clone()).await;
+    let (syn_probe, ebpf_reconnect_service) = match static_cfg.fingerprint.tcp_capture {
+        TcpCapture::Ebpf => {
+            ebpf::connect_syn_probe(&static_cfg, Arc::clone(&metrics), shutdown_rx.clone()).await
+        }
+        TcpCapture::RawSocket => (raw_socket_probe(&static_cfg), None),
+    };
 
     info!("huginn-proxy starting");
 
@@ -133,16 +195,20 @@ async fn main() -> Result<(), BoxError> {
         debounce_secs: static_cfg.reload.debounce_secs,
     };
 
-    // run() broadcasts shutdown_tx on SIGTERM/SIGINT and awaits
+    // run() broadcasts shutdown_tx on SIGTERM/SIGINT or POST /admin/drain and awaits
     // cert-reload + config-watcher handles before returning.
     let result = run(
         Arc::clone(&static_cfg),
         Arc::clone(&dynamic_cfg),
         metrics,
         syn_probe,
+        None,
+        None,
         watch_opts,
         shutdown_tx,
         readiness,
+        health_registry,
+        runtime,
     )
     .await;
 
@@ -8,26 +8,30 @@ use huginn_proxy_lib::telemetry::{init_validation_tracing, shutdown_tracing};
 
 use crate::BoxError;
 
-pub(crate) fn run(
-    config_path: &Path,
-    print_effective_config: bool,
-    strict: bool,
-) -> Result<(), BoxError> {
+/// What a validation run prints on success.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Output {
+    /// `--validate`: `Config OK` and the warning count on stdout.
+    Summary,
+    /// `--print-effective-config`: the effective config as JSON on stdout.
+    EffectiveConfig,
+    /// `--check`: the effective config as JSON on stdout, `Config OK` and the warning count on
+    /// stderr.
+    Check,
+}
+
+pub(crate) fn run(config_path: &Path, output: Output, strict: bool) -> Result<(), BoxError> {
     init_validation_tracing()?;
-    let result = validate_and_report(config_path, print_effective_config, strict);
+    let result = validate_and_report(config_path, output, strict);
     shutdown_tracing();
     result
 }
 
-/// Validate the config and, with `print_effective_config`, print the effective (secret-redacted)
-/// view. `load_from_path` already logged the config-audit findings; the `proxy_protocol` trust-gap
-/// check has its own runtime logger that never fires under `--validate`, so it is logged here and
-/// folded into the warning count. With `strict`, a non-zero count makes this return an error.
-fn validate_and_report(
-    config_path: &Path,
-    print_effective_config: bool,
-    strict: bool,
-) -> Result<(), BoxError> {
+/// Validate the config and print what `output` asks for; the effective config is secret-redacted.
+/// `load_from_path` already logged the config-audit findings; the `proxy_protocol` trust-gap check
+/// has its own runtime logger that never fires under `--validate`, so it is logged here and folded
+/// into the warning count. With `strict`, a non-zero count makes this return an error.
+fn validate_and_report(config_path: &Path, output: Output, strict: bool) -> Result<(), BoxError> {
     let config = load_from_path(config_path)?;
     config.validate_cross_refs()?;
 
@@ -39,17 +43,17 @@ fn validate_and_report(
 
     let stdout = io::stdout();
     let mut stdout = stdout.lock();
-    if print_effective_config {
+    if output == Output::Summary {
+        write_summary(&mut stdout, config_path, warning_count)?;
+    } else {
         let huginn_proxy_lib::config::ConfigParts { static_cfg, dynamic_cfg } = config.into_parts();
         writeln!(
             stdout,
             "{}",
             EffectiveConfigView::new(&static_cfg, &dynamic_cfg).to_pretty_json()?
         )?;
-    } else {
-        writeln!(stdout, "Config OK: {}", config_path.display())?;
-        if warning_count > 0 {
-            writeln!(stdout, "{warning_count} warning(s) found (see log output above)")?;
+        if output == Output::Check {
+            write_summary(&mut io::stderr().lock(), config_path, warning_count)?;
         }
     }
 
@@ -61,3 +65,11 @@ fn validate_and_report(
 
     Ok(())
 }
+
+fn write_summary(out: &mut impl Write, config_path: &Path, warning_count: usize) -> io::Result<()> {
+    writeln!(out, "Config OK: {}", config_path.display())?;
+    if warning_count > 0 {
+        writeln!(out, "{warning_count} warning(s) found (see log output above)")?;
+    }
+    Ok(())
+}
@@ -125,3 +125,105 @@ fn print_effective_config_implies_validation_and_outputs_redacted_json() -> Test
     assert!(!stdout.contains("Config OK"));
     Ok(())
 }
+
+#[test]
+fn check_prints_effective_config_and_summary() -> TestResult {
+    let path = temp_config("check", CONFIG_WITH_WARNING)?;
+    let path_arg = path.to_string_lossy().into_owned();
+    let output = run(&["--check", &path_arg])?;
+    let _ = fs::remove_file(path);
+
+    assert!(output.status.success(), "warnings must not fail without --strict");
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(value["static"]["listen"]["addrs"][0], "127.0.0.1:0");
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains(&format!("Config OK: {path_arg}")), "stderr: {stderr}");
+    assert!(stderr.contains("1 warning(s) found"), "stderr: {stderr}");
+    Ok(())
+}
+
+#[test]
+fn check_reports_shadowed_route_and_fails() -> TestResult {
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "backend:9000" }]
+
+[[domains]]
+host = "example.com"
+routes = [
+  { prefix = "/api", backend = "backend:9000" },
+  { prefix = "/api/v1", backend = "backend:9000", match_priority = -1 },
+]
+"#;
+    let path = temp_config("check-shadowed", toml)?;
+    let path_arg = path.to_string_lossy().into_owned();
+    let output = run(&["--check", &path_arg])?;
+    let _ = fs::remove_file(path);
+
+    assert!(!output.status.success(), "a shadowed route must fail --check");
+    assert!(output.stdout.is_empty(), "nothing printed on failure");
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("route '/api/v1' is never matched"), "stderr: {stderr}");
+    Ok(())
+}
+
+#[test]
+fn check_reports_unknown_key_with_suggestion() -> TestResult {
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "backend:9000" }]
+preserve_hots = true
+"#;
+    let path = temp_config("check-typo", toml)?;
+    let path_arg = path.to_string_lossy().into_owned();
+    let output = run(&["--check", &path_arg])?;
+    let _ = fs::remove_file(path);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains(&path_arg), "stderr: {stderr}");
+    assert!(stderr.contains("help: did you mean `preserve_host`?"), "stderr: {stderr}");
+    Ok(())
+}
+
+#[test]
+fn config_variables_come_from_environment_and_secrets_file() -> TestResult {
+    let toml = r#"
+listen = { addrs = ["127.0.0.1:0"] }
+backends = [{ address = "${BACKEND_HOST}:${BACKEND_PORT}" }]
+headers = { request = { add = [
+  { name = "Authorization", value = "Bearer ${API_TOKEN}" }
+] } }
+"#;
+    let path = temp_config("interpolate", toml)?;
+    let secrets = temp_config("secrets", "# mounted secret\nAPI_TOKEN='s3cr3t'\nBACKEND_PORT=1\n")?;
+    let path_arg = path.to_string_lossy().into_owned();
+    let output = Command::new(env!("CARGO_BIN_EXE_huginn-proxy"))
+        .args(["--print-effective-config", &path_arg])
+        .env("BACKEND_HOST", "backend")
+        .env("BACKEND_PORT", "9000")
+        .env("HUGINN_SECRETS_FILE", &secrets)
+        .output()?;
+    let missing = Command::new(env!("CARGO_BIN_EXE_huginn-proxy"))
+        .args(["--validate", &path_arg])
+        .env_remove("BACKEND_HOST")
+        .env("BACKEND_PORT", "9000")
+        .env("HUGINN_SECRETS_FILE", &secrets)
+        .output()?;
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(secrets);
+
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    // The environment wins over the secrets file.
+    assert_eq!(value["dynamic"]["backends"][0]["address"], "backend:9000");
+    assert!(!String::from_utf8(output.stdout)?.contains("s3cr3t"));
+
+    assert!(!missing.status.success());
+    let stderr = String::from_utf8(missing.stderr)?;
+    assert!(
+        stderr.contains("undefined variable(s): `BACKEND_HOST` (line 3)"),
+        "stderr: {stderr}"
+    );
+    Ok(())
+}